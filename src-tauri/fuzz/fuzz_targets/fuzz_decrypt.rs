@@ -1,8 +1,9 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
-use qre_gui::crypto::{decrypt_file_with_master_key, EncryptedFileContainer};
-use qre_gui::keychain::MasterKey;
+use qre_core::fuzzing::{
+    decrypt_file_with_master_key, EncryptedFileContainer, KeyPurpose, MasterKey,
+};
 
 fuzz_target!(|data: &[u8]| {
     // GOAL: Feed arbitrary bytes into the deserialization and decryption
@@ -13,12 +14,13 @@ fuzz_target!(|data: &[u8]| {
     //   2. Invalid ciphertext — AES-GCM tag verification with random data
     //   3. Decompression bomb — oversized zstd payload that could exhaust memory
 
-    if let Ok(container) = bincode::deserialize::<EncryptedFileContainer>(data) {
+    // `from_bytes` is the same bounded parser `EncryptedFileContainer::load` uses.
+    if let Ok(container) = EncryptedFileContainer::from_bytes(data) {
         // Use a fixed all-zero master key — we're testing robustness, not correctness.
         // A real attacker can't control the master key, but they can control the file bytes.
         let mk = MasterKey([0u8; 32]);
 
         // Must never panic regardless of what garbage is inside the container.
-        let _ = decrypt_file_with_master_key(&mk, None, &container);
+        let _ = decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container);
     }
 });
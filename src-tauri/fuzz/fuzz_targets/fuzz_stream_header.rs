@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qre_core::fuzzing::parse_stream_header_bytes;
+
+fuzz_target!(|data: &[u8]| {
+    // GOAL: The V5 / V6 / V7 streaming header parser must never panic or allocate
+    // unbounded memory, whatever the version word and length prefixes claim.
+    //
+    // Inputs start with the 4-byte little-endian version, exactly like a .qre file.
+    // Seed the corpus with real headers (e.g. the first 8 KB of encrypted files)
+    // so the fuzzer starts from valid structures.
+    if let Ok((_version, header)) = parse_stream_header_bytes(data) {
+        // Anything the parser accepts must already satisfy the field bounds the
+        // decryptor relies on (nonce lengths, safe filename).
+        assert!(header.validate().is_ok());
+    }
+});
@@ -0,0 +1,317 @@
+// --- START OF FILE account_deletion.rs ---
+
+use crate::passwords::{base_domain, url_host, PasswordVault};
+use serde::{Deserialize, Serialize};
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+/// How painful it is to get an account deleted.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionDifficulty {
+    /// A self-service button that deletes the account immediately.
+    Easy,
+    /// Self-service, but with a waiting period or several confirmation steps.
+    Medium,
+    /// Requires contacting support or filing a privacy request.
+    Hard,
+}
+
+/// A curated record describing where and how to delete an account on a service.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ServiceDeletionInfo {
+    pub name: &'static str,
+    /// Registrable domains that identify the service (matched against vault entry URLs).
+    pub domains: &'static [&'static str],
+    pub deletion_url: &'static str,
+    pub difficulty: DeletionDifficulty,
+    pub notes: &'static str,
+}
+
+/// Where the user is in the deletion process for one vault entry. Stored in the vault itself.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum DeletionStatus {
+    /// Deletion was requested; many services keep the account for a grace period.
+    Requested { at: i64 },
+    /// The service confirmed the account is gone.
+    Confirmed { at: i64 },
+}
+
+/// One row of the personal deletion checklist sent to the frontend.
+#[derive(Serialize, Debug, Clone)]
+pub struct DeletionChecklistItem {
+    pub entry_id: String,
+    pub entry_service: String,
+    pub username: String,
+    /// `None` when the service is not in the curated dataset.
+    pub info: Option<ServiceDeletionInfo>,
+    pub status: Option<DeletionStatus>,
+}
+
+// ==========================================
+// --- CURATED DATASET ---
+// ==========================================
+// Deletion pages move around; this list is intentionally small and limited to services
+// whose deletion flow is well documented. Unknown services still appear in the checklist
+// (with `info: None`) so the user can track them manually.
+
+pub const DELETION_DATASET: &[ServiceDeletionInfo] = &[
+    ServiceDeletionInfo {
+        name: "Google",
+        domains: &["google.com", "gmail.com", "youtube.com"],
+        deletion_url: "https://myaccount.google.com/delete-services-or-account",
+        difficulty: DeletionDifficulty::Easy,
+        notes: "Deletes Gmail, Drive and YouTube data. Export with Google Takeout first.",
+    },
+    ServiceDeletionInfo {
+        name: "Facebook",
+        domains: &["facebook.com"],
+        deletion_url: "https://www.facebook.com/help/delete_account",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "30-day grace period; logging in during it cancels the deletion.",
+    },
+    ServiceDeletionInfo {
+        name: "Instagram",
+        domains: &["instagram.com"],
+        deletion_url: "https://www.instagram.com/accounts/remove/request/permanent/",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "30-day grace period.",
+    },
+    ServiceDeletionInfo {
+        name: "X (Twitter)",
+        domains: &["twitter.com", "x.com"],
+        deletion_url: "https://x.com/settings/deactivate",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "Account is deleted after 30 days of deactivation.",
+    },
+    ServiceDeletionInfo {
+        name: "Microsoft",
+        domains: &["microsoft.com", "live.com", "outlook.com", "hotmail.com", "xbox.com"],
+        deletion_url: "https://account.live.com/closeaccount.aspx",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "60-day waiting period before final closure.",
+    },
+    ServiceDeletionInfo {
+        name: "Apple",
+        domains: &["apple.com", "icloud.com"],
+        deletion_url: "https://privacy.apple.com/",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "Verification can take up to seven days.",
+    },
+    ServiceDeletionInfo {
+        name: "Amazon",
+        domains: &["amazon.com", "amazon.co.uk", "amazon.de"],
+        deletion_url: "https://www.amazon.com/privacy/data-deletion",
+        difficulty: DeletionDifficulty::Hard,
+        notes: "Closes all regional Amazon accounts, Kindle and Audible content included.",
+    },
+    ServiceDeletionInfo {
+        name: "LinkedIn",
+        domains: &["linkedin.com"],
+        deletion_url: "https://www.linkedin.com/mypreferences/d/close-account",
+        difficulty: DeletionDifficulty::Easy,
+        notes: "",
+    },
+    ServiceDeletionInfo {
+        name: "Reddit",
+        domains: &["reddit.com"],
+        deletion_url: "https://www.reddit.com/settings/account",
+        difficulty: DeletionDifficulty::Easy,
+        notes: "Posts and comments stay visible unless deleted first.",
+    },
+    ServiceDeletionInfo {
+        name: "GitHub",
+        domains: &["github.com"],
+        deletion_url: "https://github.com/settings/admin",
+        difficulty: DeletionDifficulty::Easy,
+        notes: "",
+    },
+    ServiceDeletionInfo {
+        name: "Dropbox",
+        domains: &["dropbox.com"],
+        deletion_url: "https://www.dropbox.com/account/delete",
+        difficulty: DeletionDifficulty::Easy,
+        notes: "",
+    },
+    ServiceDeletionInfo {
+        name: "Spotify",
+        domains: &["spotify.com"],
+        deletion_url: "https://support.spotify.com/article/close-account/",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "Cancel any Premium subscription first.",
+    },
+    ServiceDeletionInfo {
+        name: "Netflix",
+        domains: &["netflix.com"],
+        deletion_url: "https://www.netflix.com/cancelplan",
+        difficulty: DeletionDifficulty::Hard,
+        notes: "Cancelling keeps data for 10 months; full erasure requires emailing privacy@netflix.com.",
+    },
+    ServiceDeletionInfo {
+        name: "Yahoo",
+        domains: &["yahoo.com"],
+        deletion_url: "https://edit.yahoo.com/config/delete_user",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "Deletion completes after roughly 30 days.",
+    },
+    ServiceDeletionInfo {
+        name: "Snapchat",
+        domains: &["snapchat.com"],
+        deletion_url: "https://accounts.snapchat.com/accounts/delete_account",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "30-day deactivation period.",
+    },
+    ServiceDeletionInfo {
+        name: "TikTok",
+        domains: &["tiktok.com"],
+        deletion_url: "https://support.tiktok.com/en/account-and-privacy/deleting-an-account",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "Deletion is done in-app; 30-day deactivation period.",
+    },
+    ServiceDeletionInfo {
+        name: "PayPal",
+        domains: &["paypal.com"],
+        deletion_url: "https://www.paypal.com/myaccount/settings/",
+        difficulty: DeletionDifficulty::Medium,
+        notes: "Balance must be withdrawn first. Transaction history is retained for legal reasons.",
+    },
+    ServiceDeletionInfo {
+        name: "Steam",
+        domains: &["steampowered.com", "steamcommunity.com"],
+        deletion_url: "https://help.steampowered.com/en/wizard/HelpDeleteAccount",
+        difficulty: DeletionDifficulty::Hard,
+        notes: "Handled by Steam Support; purchased games are lost.",
+    },
+];
+
+// ==========================================
+// --- LOOKUP & CHECKLIST ---
+// ==========================================
+
+/// Finds the curated record for a URL by comparing registrable domains.
+pub fn lookup_service(url: &str) -> Option<&'static ServiceDeletionInfo> {
+    let domain = base_domain(&url_host(url)?);
+    DELETION_DATASET
+        .iter()
+        .find(|info| info.domains.contains(&domain.as_str()))
+}
+
+/// Builds the personal checklist: one item per vault entry, services with a known
+/// deletion flow first, then unknown ones (both alphabetical).
+pub fn build_checklist(vault: &PasswordVault) -> Vec<DeletionChecklistItem> {
+    let mut items: Vec<DeletionChecklistItem> = vault
+        .entries
+        .iter()
+        .map(|e| DeletionChecklistItem {
+            entry_id: e.id.clone(),
+            entry_service: e.service.clone(),
+            username: e.username.clone(),
+            info: lookup_service(&e.url).copied(),
+            status: e.deletion_status.clone(),
+        })
+        .collect();
+    items.sort_by_key(|i| (i.info.is_none(), i.entry_service.to_lowercase()));
+    items
+}
+
+/// Parses the status string sent by the UI ("requested", "confirmed" or "none").
+pub fn parse_status(status: &str, now: i64) -> Result<Option<DeletionStatus>, String> {
+    match status {
+        "requested" => Ok(Some(DeletionStatus::Requested { at: now })),
+        "confirmed" => Ok(Some(DeletionStatus::Confirmed { at: now })),
+        "none" | "" => Ok(None),
+        other => Err(format!("Unknown deletion status: '{}'", other)),
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passwords::VaultEntry;
+
+    fn entry(id: &str, service: &str, url: &str) -> VaultEntry {
+        VaultEntry {
+            id: id.to_string(),
+            service: service.to_string(),
+            username: "me@example.com".to_string(),
+            password: "pw".to_string(),
+            notes: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            url: url.to_string(),
+            color: String::new(),
+            is_pinned: false,
+            totp_secret: None,
+            url_match: Default::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_lookup_matches_subdomains_and_aliases() {
+        assert_eq!(
+            lookup_service("https://mail.google.com/mail/u/0").map(|i| i.name),
+            Some("Google")
+        );
+        assert_eq!(
+            lookup_service("https://x.com/home").map(|i| i.name),
+            Some("X (Twitter)")
+        );
+        assert_eq!(
+            lookup_service("https://www.amazon.co.uk/").map(|i| i.name),
+            Some("Amazon")
+        );
+        assert!(lookup_service("https://google.com.evil.io").is_none());
+        assert!(lookup_service("").is_none());
+    }
+
+    #[test]
+    fn test_dataset_is_well_formed() {
+        for info in DELETION_DATASET {
+            assert!(info.deletion_url.starts_with("https://"), "{}", info.name);
+            assert!(!info.domains.is_empty(), "{}", info.name);
+        }
+    }
+
+    #[test]
+    fn test_checklist_orders_known_services_first() {
+        let mut vault = PasswordVault::new();
+        vault.entries = vec![
+            entry("1", "Zeta Forum", "https://forum.zeta.example"),
+            entry("2", "Reddit", "https://www.reddit.com"),
+            entry("3", "Dropbox", "https://dropbox.com"),
+        ];
+        vault.entries[1].deletion_status = Some(DeletionStatus::Requested { at: 5 });
+
+        let list = build_checklist(&vault);
+        let ids: Vec<&str> = list.iter().map(|i| i.entry_id.as_str()).collect();
+        assert_eq!(ids, vec!["3", "2", "1"]);
+        assert_eq!(list[1].status, Some(DeletionStatus::Requested { at: 5 }));
+        assert!(list[2].info.is_none());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(
+            parse_status("confirmed", 7).unwrap(),
+            Some(DeletionStatus::Confirmed { at: 7 })
+        );
+        assert_eq!(parse_status("none", 7).unwrap(), None);
+        assert!(parse_status("deleted?", 7).is_err());
+    }
+}
+
+// --- END OF FILE account_deletion.rs ---
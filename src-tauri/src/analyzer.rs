@@ -1,10 +1,17 @@
 // --- START OF FILE analyzer.rs ---
 
-use anyhow::Result;
+use crate::keychain::MasterKey;
+use crate::progress::ProgressEmitter;
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
 use rayon::prelude::*; // Provides parallel iterators for multi-threaded performance
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use tauri::{AppHandle, Emitter};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+use tauri::AppHandle;
 use walkdir::{DirEntry, WalkDir};
 
 // Use the directories crate to resolve standard OS user folders on Desktop platforms.
@@ -44,12 +51,226 @@ fn is_ignored_dir(entry: &DirEntry) -> bool {
         || name == "__pycache__"
 }
 
+// ==========================================
+// --- SCAN OPTIONS & INCREMENTAL CACHE ---
+// ==========================================
+// Re-scanning Downloads used to re-read the magic bytes of every file each time.
+// The cache remembers the verdict for each file keyed by (path, size, mtime); if none of
+// those changed the stored verdict is reused without opening the file.
+// The cache is also tied to a fingerprint of the active rules, so editing
+// `analyzer_rules.toml` automatically invalidates every cached verdict.
+//
+// The cache file names no file or folder: paths are replaced by an HMAC under a vault
+// subkey (`KeyPurpose::ScanCache`) and verdicts are stored without the file name. With
+// the vault locked there is no key, and scans run uncached.
+
+/// Name of the scan cache inside the app data directory.
+pub const CACHE_FILE_NAME: &str = "analyzer_cache.json";
+
+fn default_true() -> bool {
+    true
+}
+
+/// Optional filters sent by the UI with a scan request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScanOptions {
+    /// Skip files smaller than this many bytes.
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    /// Skip files larger than this many bytes (e.g. multi-GB videos).
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    /// When false, every file is re-analyzed (the cache is still refreshed afterwards).
+    #[serde(default = "default_true")]
+    pub use_cache: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            min_size: None,
+            max_size: None,
+            use_cache: true,
+        }
+    }
+}
+
+impl ScanOptions {
+    fn size_allowed(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedFile {
+    /// Keyed hash of the scanned directory the file was found in, so a rescan of that
+    /// directory can forget files that are gone.
+    root: String,
+    size: u64,
+    mtime_ms: u64,
+    /// `None` means the file was analyzed and found SAFE.
+    finding: Option<CachedVerdict>,
+}
+
+/// A finding minus the fields that name the file; those are rebuilt from the path on a hit.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedVerdict {
+    real_type: String,
+    risk_level: String,
+    description: String,
+}
+
+impl CachedVerdict {
+    fn from_result(result: &AnalysisResult) -> Self {
+        Self {
+            real_type: result.real_type.clone(),
+            risk_level: result.risk_level.clone(),
+            description: result.description.clone(),
+        }
+    }
+
+    fn to_result(&self, path: &Path) -> AnalysisResult {
+        let (filename, extension) = name_and_extension(path);
+        AnalysisResult {
+            path: path.to_string_lossy().to_string(),
+            filename,
+            extension,
+            real_type: self.real_type.clone(),
+            risk_level: self.risk_level.clone(),
+            description: self.description.clone(),
+        }
+    }
+}
+
+/// Persistent per-file verdict cache, keyed by `entry_key`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ScanCache {
+    rules_fingerprint: String,
+    entries: HashMap<String, CachedFile>,
+    /// Never written to disk. A cache without a key is never consulted or saved.
+    #[serde(skip)]
+    key: Option<MasterKey>,
+}
+
+impl ScanCache {
+    /// An empty cache whose entries are keyed with `key`.
+    pub fn new(key: MasterKey) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::default()
+        }
+    }
+
+    /// Loads the cache from disk. A missing or unreadable cache is not an error:
+    /// it simply means the next scan is a full one. One that does not parse (damaged,
+    /// or written before paths were hashed) is deleted so the paths it holds go too.
+    pub fn load(path: &Path, key: MasterKey) -> Self {
+        let stored: Option<Self> = std::fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        let mut cache = match stored {
+            Some(cache) => cache,
+            None => {
+                let _ = remove_cache(path);
+                Self::default()
+            }
+        };
+        cache.key = Some(key);
+        cache
+    }
+
+    /// Writes the cache atomically (temp file + rename) so a crash mid-write
+    /// cannot leave a truncated JSON document behind. Does nothing without a key.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.key.is_none() {
+            return Ok(());
+        }
+        let tmp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp_path)?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Drops every entry if the rules changed since the cache was written.
+    fn sync_rules(&mut self, rules: &AnalyzerRules) {
+        let fingerprint = rules_fingerprint(rules);
+        if self.rules_fingerprint != fingerprint {
+            self.entries.clear();
+            self.rules_fingerprint = fingerprint;
+        }
+    }
+
+    /// HMAC-SHA256 of `path` under the cache key, hex-encoded; `None` without a key.
+    fn entry_key(&self, path: &str) -> Option<String> {
+        let key = self.key.as_ref()?;
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(&key.0).expect("HMAC accepts any key length");
+        mac.update(path.as_bytes());
+        Some(format!("{:x}", mac.finalize().into_bytes()))
+    }
+}
+
+/// Deletes the cache file, if there is one.
+pub fn remove_cache(path: &Path) -> Result<()> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn rules_fingerprint(rules: &AnalyzerRules) -> String {
+    let json = serde_json::to_vec(rules).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+fn mtime_ms(meta: &std::fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Resolves `<app_data_dir>/analyzer_cache.json`.
+pub fn cache_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(rules_path(app)?.with_file_name(CACHE_FILE_NAME))
+}
+
 // ==========================================
 // --- CORE: Directory Scanner ---
 // ==========================================
 
 /// Recursively scans a target directory and analyzes all files within it.
-pub fn scan_directory(app: &AppHandle, dir: &str) -> Vec<AnalysisResult> {
+pub fn scan_directory(
+    app: &AppHandle,
+    dir: &str,
+    options: &ScanOptions,
+    cache: &mut ScanCache,
+) -> Vec<AnalysisResult> {
+    // Emit a progress event to the Tauri UI.
+    // Note: Since this is highly multi-threaded, paths arrive rapidly and out of order, so
+    // they are coalesced; the emitter is shared by the rayon workers and flushed on drop.
+    let events = ProgressEmitter::new(app, "qre:analyzer-progress");
+    scan_directory_with(dir, &current_rules(), options, cache, |path_str| {
+        events.emit(path_str.to_string());
+    })
+}
+
+/// UI-independent scanner core (the progress sink is injected so tests can run without a Tauri app).
+fn scan_directory_with<F>(
+    dir: &str,
+    rules: &AnalyzerRules,
+    options: &ScanOptions,
+    cache: &mut ScanCache,
+    on_progress: F,
+) -> Vec<AnalysisResult>
+where
+    F: Fn(&str) + Sync,
+{
+    cache.sync_rules(rules);
+    let root_key = cache.entry_key(dir);
+
     // 1. Collect all valid file entries synchronously using WalkDir.
     // We cap the depth at 10 to prevent infinite symlink loops or excessively deep structures.
     let entries: Vec<_> = WalkDir::new(dir)
@@ -63,52 +284,402 @@ pub fn scan_directory(app: &AppHandle, dir: &str) -> Vec<AnalysisResult> {
 
     // 2. Process the collected files in PARALLEL using Rayon (`par_iter`).
     // This vastly speeds up I/O and CPU-bound heuristic checks across thousands of files.
-    let results: Vec<AnalysisResult> = entries
+    // Each worker returns the fresh cache record alongside the finding; the cache itself is
+    // only read here and updated sequentially afterwards.
+    let cache_ref: &ScanCache = cache;
+    let scanned: Vec<(Option<String>, CachedFile, Option<AnalysisResult>)> = entries
         .par_iter()
         .filter_map(|entry| {
             let path = entry.path();
             let path_str = path.to_string_lossy().to_string();
+            let entry_key = cache_ref.entry_key(&path_str);
+
+            // Size filter: uses the metadata WalkDir already fetched (no extra syscall for the check).
+            let meta = entry.metadata().ok()?;
+            let size = meta.len();
+            if !options.size_allowed(size) {
+                return None;
+            }
+            let mtime_ms = mtime_ms(&meta);
 
-            // Emit a progress event to the Tauri UI.
-            // Note: Since this is highly multi-threaded, events will arrive rapidly and out of order.
-            let _ = app.emit("qre:analyzer-progress", &path_str);
+            on_progress(&path_str);
 
-            // 3. Analyze the individual file.
-            match analyze_file(path) {
-                Ok(res) => {
-                    // Only return files that triggered a security flag.
-                    if res.risk_level != "SAFE" {
-                        Some(res)
-                    } else {
-                        None // Discard safe files to save memory
+            // Unchanged since the last scan: reuse the stored verdict without opening the file.
+            if options.use_cache {
+                if let Some(hit) = entry_key.as_ref().and_then(|k| cache_ref.entries.get(k)) {
+                    if hit.size == size && hit.mtime_ms == mtime_ms {
+                        let finding = hit.finding.as_ref().map(|v| v.to_result(path));
+                        return Some((entry_key, hit.clone(), finding));
                     }
                 }
-                Err(_) => None, // Ignore files that couldn't be read/analyzed
             }
+
+            // 3. Analyze the individual file.
+            // Files that couldn't be read/analyzed are ignored and not cached (retry next time).
+            let res = analyze_file_with_rules(path, rules).ok()?;
+            // Only keep files that triggered a security flag; discard safe files to save memory.
+            let finding = (res.risk_level != "SAFE").then_some(res);
+            let record = CachedFile {
+                root: root_key.clone().unwrap_or_default(),
+                size,
+                mtime_ms,
+                finding: finding.as_ref().map(CachedVerdict::from_result),
+            };
+            Some((entry_key, record, finding))
         })
         .collect();
 
+    // 4. Fold the fresh records back into the cache and forget files found under `dir`
+    // that no longer exist (or were filtered out), so the cache cannot grow without bound.
+    if let Some(root) = &root_key {
+        let seen: HashSet<&str> = scanned
+            .iter()
+            .filter_map(|(k, _, _)| k.as_deref())
+            .collect();
+        cache
+            .entries
+            .retain(|k, record| record.root != *root || seen.contains(k.as_str()));
+    }
+
+    let mut results = Vec::new();
+    for (entry_key, record, finding) in scanned {
+        results.extend(finding);
+        if let Some(k) = entry_key {
+            cache.entries.insert(k, record);
+        }
+    }
+
     results
 }
 
+// ==========================================
+// --- RULES ENGINE ---
+// ==========================================
+// The extension whitelists used by the heuristics below used to be hard-coded arrays.
+// They now live in a user-editable TOML file (`analyzer_rules.toml` in the app data dir)
+// so power users can silence known-good mismatches or watch additional formats without
+// a rebuild. Every list defaults to the previous built-in values, so a missing file or a
+// file that only sets one key behaves exactly like before.
+
+/// Name of the rules file inside the app data directory.
+pub const RULES_FILE_NAME: &str = "analyzer_rules.toml";
+
+/// Valid values for `risk_level` and for folder severity overrides.
+const SEVERITIES: [&str; 3] = ["DANGER", "WARNING", "SAFE"];
+
+/// Per-extension policy. Takes precedence over the list-based defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionPolicy {
+    /// Never flag files with this extension.
+    Ignore,
+    /// Executable content is expected (treated like an entry in `allowed_binary_exts`).
+    Executable,
+    /// Users implicitly trust this format: hidden executables are DANGER, mismatches WARNING.
+    Monitor,
+}
+
+/// Forces the severity of any finding located under `path`.
+/// Useful for e.g. downgrading a build-output folder to "SAFE" or escalating Downloads.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FolderOverride {
+    pub path: String,
+    pub severity: String, // "DANGER", "WARNING" or "SAFE"
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyzerRules {
+    /// Expected extensions for legitimate executable formats.
+    pub allowed_binary_exts: Vec<String>,
+    /// Formats users implicitly trust; an executable hiding behind one of these is DANGER.
+    pub user_safe_formats: Vec<String>,
+    /// OS and dev files that often have custom extensions but standard headers.
+    pub system_extensions: Vec<String>,
+    /// Extensions that are really ZIP/JAR archives under the hood (Office, APK, CRX, ...).
+    pub zip_container_exts: Vec<String>,
+    /// Extensions that raise a WARNING when the content does not match the name.
+    pub monitored_exts: Vec<String>,
+    /// Extra extensions to monitor on top of `monitored_exts` (the usual place for user additions).
+    pub custom_monitored_exts: Vec<String>,
+    pub extension_policies: BTreeMap<String, ExtensionPolicy>,
+    pub folder_overrides: Vec<FolderOverride>,
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+impl Default for AnalyzerRules {
+    fn default() -> Self {
+        Self {
+            allowed_binary_exts: to_strings(&[
+                "exe", "dll", "sys", "ocx", "cpl", "scr", "msi", "node", "pyd", "efi", "acm", "ax",
+                "tsp", "drv", "bin", "elf", "so", "o", "deb", "rpm", "appimage", "dylib", "kext",
+                "app", "sh", "bat", "cmd", "ps1", "vbs",
+            ]),
+            user_safe_formats: to_strings(&[
+                "txt", "pdf", "jpg", "jpeg", "png", "gif", "mp3", "mp4", "docx", "xlsx", "zip",
+                "rar", "csv",
+            ]),
+            system_extensions: to_strings(&[
+                "mui", "cat", "tlb", "cip", "nls", "icm", "inf", "pnf", "xml", "json", "lib",
+                "rlib", "pdb", "exp", "obj", "iobj", "ipdb", "dat", "bin", "cache", "tmp", "db",
+                "db-shm", "db-wal", "plugin", "bpl",
+            ]),
+            zip_container_exts: to_strings(&[
+                "docx", "xlsx", "pptx", "odt", "apk", "nupkg", "whl", "vsix", "crx",
+            ]),
+            monitored_exts: to_strings(&[
+                "jpg", "jpeg", "png", "gif", "pdf", "mp4", "mp3", "zip", "rar", "7z", "avi", "mov",
+                "wav",
+            ]),
+            custom_monitored_exts: Vec::new(),
+            extension_policies: BTreeMap::new(),
+            folder_overrides: Vec::new(),
+        }
+    }
+}
+
+impl AnalyzerRules {
+    /// Parses a TOML rules document and validates it.
+    /// Returns every problem found (not just the first) so the UI can list them all.
+    pub fn from_toml(text: &str) -> std::result::Result<Self, Vec<String>> {
+        let mut rules: AnalyzerRules =
+            toml::from_str(text).map_err(|e| vec![format!("Parse error: {}", e.message())])?;
+        rules.validate()?;
+        rules.normalize();
+        Ok(rules)
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let lists = [
+            ("allowed_binary_exts", &self.allowed_binary_exts),
+            ("user_safe_formats", &self.user_safe_formats),
+            ("system_extensions", &self.system_extensions),
+            ("zip_container_exts", &self.zip_container_exts),
+            ("monitored_exts", &self.monitored_exts),
+            ("custom_monitored_exts", &self.custom_monitored_exts),
+        ];
+        for (key, list) in lists {
+            for ext in list {
+                if let Err(e) = validate_extension(ext) {
+                    errors.push(format!("{}: {}", key, e));
+                }
+            }
+        }
+
+        for ext in self.extension_policies.keys() {
+            if let Err(e) = validate_extension(ext) {
+                errors.push(format!("extension_policies: {}", e));
+            }
+        }
+
+        for (i, rule) in self.folder_overrides.iter().enumerate() {
+            if rule.path.trim().is_empty() {
+                errors.push(format!("folder_overrides[{}]: path is empty", i));
+            }
+            if !SEVERITIES.contains(&rule.severity.to_uppercase().as_str()) {
+                errors.push(format!(
+                    "folder_overrides[{}]: unknown severity '{}' (expected DANGER, WARNING or SAFE)",
+                    i, rule.severity
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Lowercases extensions, strips a leading dot and uppercases severities so that
+    /// lookups during the scan are plain string comparisons.
+    fn normalize(&mut self) {
+        let norm = |ext: &str| ext.trim().trim_start_matches('.').to_lowercase();
+        for list in [
+            &mut self.allowed_binary_exts,
+            &mut self.user_safe_formats,
+            &mut self.system_extensions,
+            &mut self.zip_container_exts,
+            &mut self.monitored_exts,
+            &mut self.custom_monitored_exts,
+        ] {
+            for ext in list.iter_mut() {
+                *ext = norm(ext);
+            }
+        }
+        self.extension_policies = std::mem::take(&mut self.extension_policies)
+            .into_iter()
+            .map(|(k, v)| (norm(&k), v))
+            .collect();
+        for rule in &mut self.folder_overrides {
+            rule.severity = rule.severity.to_uppercase();
+        }
+    }
+
+    fn policy(&self, ext: &str) -> Option<ExtensionPolicy> {
+        self.extension_policies.get(ext).copied()
+    }
+
+    fn is_allowed_binary(&self, ext: &str) -> bool {
+        match self.policy(ext) {
+            Some(p) => p == ExtensionPolicy::Executable,
+            None => self.allowed_binary_exts.iter().any(|e| e == ext),
+        }
+    }
+
+    fn is_user_safe(&self, ext: &str) -> bool {
+        match self.policy(ext) {
+            Some(p) => p == ExtensionPolicy::Monitor,
+            None => self.user_safe_formats.iter().any(|e| e == ext),
+        }
+    }
+
+    fn is_monitored(&self, ext: &str) -> bool {
+        match self.policy(ext) {
+            Some(p) => p == ExtensionPolicy::Monitor,
+            None => {
+                self.monitored_exts.iter().any(|e| e == ext)
+                    || self.custom_monitored_exts.iter().any(|e| e == ext)
+            }
+        }
+    }
+
+    /// Returns the severity forced by the most specific matching folder override, if any.
+    fn folder_severity(&self, path: &Path) -> Option<&str> {
+        self.folder_overrides
+            .iter()
+            .filter(|rule| path.starts_with(&rule.path))
+            .max_by_key(|rule| Path::new(&rule.path).components().count())
+            .map(|rule| rule.severity.as_str())
+    }
+}
+
+fn validate_extension(ext: &str) -> std::result::Result<(), String> {
+    let trimmed = ext.trim().trim_start_matches('.');
+    if trimmed.is_empty() {
+        return Err("empty extension".to_string());
+    }
+    if trimmed
+        .chars()
+        .any(|c| c.is_whitespace() || c == '/' || c == '\\' || c == '.')
+    {
+        return Err(format!("invalid extension '{}'", ext));
+    }
+    Ok(())
+}
+
+/// Active rule set. Starts with the built-in defaults; replaced atomically on reload.
+fn rules_cell() -> &'static RwLock<AnalyzerRules> {
+    static RULES: OnceLock<RwLock<AnalyzerRules>> = OnceLock::new();
+    RULES.get_or_init(|| RwLock::new(AnalyzerRules::default()))
+}
+
+/// Set once the on-disk rules file has been read at least once.
+static RULES_LOADED: AtomicBool = AtomicBool::new(false);
+
+/// Returns a snapshot of the active rules (cheap enough to clone once per scan).
+pub fn current_rules() -> AnalyzerRules {
+    rules_cell().read().map(|r| r.clone()).unwrap_or_default()
+}
+
+/// Outcome of a rules reload, returned to the frontend.
+#[derive(Serialize, Debug, Clone)]
+pub struct RulesReloadResult {
+    pub path: String,
+    pub file_found: bool,
+    pub applied: bool,
+    pub errors: Vec<String>, // Empty when the file is valid
+}
+
+/// Reads and validates the rules file at `path`, installing it if valid.
+/// On validation failure the previously active rules stay in place.
+/// A missing file is not an error: the defaults are (re)installed.
+pub fn reload_rules(path: &Path) -> RulesReloadResult {
+    let path_str = path.to_string_lossy().to_string();
+    RULES_LOADED.store(true, Ordering::Release);
+
+    let parsed = if path.exists() {
+        match std::fs::read_to_string(path) {
+            Ok(text) => AnalyzerRules::from_toml(&text),
+            Err(e) => Err(vec![format!("Cannot read rules file: {}", e)]),
+        }
+    } else {
+        Ok(AnalyzerRules::default())
+    };
+
+    match parsed {
+        Ok(rules) => {
+            if let Ok(mut guard) = rules_cell().write() {
+                *guard = rules;
+            }
+            RulesReloadResult {
+                path: path_str,
+                file_found: path.exists(),
+                applied: true,
+                errors: Vec::new(),
+            }
+        }
+        Err(errors) => RulesReloadResult {
+            path: path_str,
+            file_found: true,
+            applied: false,
+            errors,
+        },
+    }
+}
+
+/// Loads the rules file the first time a scan runs. Errors are ignored here (defaults stay
+/// active); the UI sees them through `reload_analyzer_rules`.
+pub fn ensure_rules_loaded(path: &Path) {
+    if !RULES_LOADED.load(Ordering::Acquire) {
+        let _ = reload_rules(path);
+    }
+}
+
+/// Resolves `<app_data_dir>/analyzer_rules.toml`.
+pub fn rules_path(app: &AppHandle) -> Result<PathBuf> {
+    use tauri::Manager;
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| anyhow!("Cannot resolve app data dir: {}", e))?;
+    Ok(dir.join(RULES_FILE_NAME))
+}
+
 // ==========================================
 // --- CORE: Heuristic File Analysis ---
 // ==========================================
 
-/// Analyzes a single file by comparing its declared extension against its "Magic Bytes" (file header).
-pub fn analyze_file(path: &Path) -> Result<AnalysisResult> {
+/// The file name and the "declared" extension, lowercased (what the user sees and what
+/// the OS uses to open it).
+fn name_and_extension(path: &Path) -> (String, String) {
     let filename = path
         .file_name()
         .unwrap_or_default()
         .to_string_lossy()
         .to_string();
-
-    // The "Declared" extension (what the user sees and what the OS uses to open it)
     let ext = path
         .extension()
         .and_then(|s| s.to_str())
         .unwrap_or("")
         .to_lowercase();
+    (filename, ext)
+}
+
+/// Analyzes a single file using the currently active rule set.
+#[allow(dead_code)] // Single-file entry point; directory scans snapshot the rules instead.
+pub fn analyze_file(path: &Path) -> Result<AnalysisResult> {
+    analyze_file_with_rules(path, &current_rules())
+}
+
+/// Analyzes a single file by comparing its declared extension against its "Magic Bytes" (file header).
+pub fn analyze_file_with_rules(path: &Path, rules: &AnalyzerRules) -> Result<AnalysisResult> {
+    let (filename, ext) = name_and_extension(path);
 
     // Use the `infer` crate to read the first few bytes of the file and match them against known signatures.
     let kind_opt = infer::get_from_path(path).unwrap_or(None);
@@ -122,33 +693,22 @@ pub fn analyze_file(path: &Path) -> Result<AnalysisResult> {
     let mut description = "Match".to_string();
 
     // If we successfully identified the actual file type...
-    if real_ext != "unknown" {
+    // Extensions with an explicit "ignore" policy are never flagged.
+    if real_ext != "unknown" && rules.policy(&ext) != Some(ExtensionPolicy::Ignore) {
         // Check if the actual file contents represent an executable program.
         let is_executable_mime = mime.contains("dosexec") // Windows PE (.exe, .dll)
             || mime.contains("executable")
             || mime.contains("mach-binary") // macOS binaries
             || mime.contains("elf"); // Linux binaries
 
-        // Expected extensions for legitimate executable formats.
-        let allowed_binary_exts = [
-            "exe", "dll", "sys", "ocx", "cpl", "scr", "msi", "node", "pyd", "efi", "acm", "ax",
-            "tsp", "drv", "bin", "elf", "so", "o", "deb", "rpm", "appimage", "dylib", "kext",
-            "app", "sh", "bat", "cmd", "ps1", "vbs",
-        ];
-
         // ------------------------------------------------------------
         // SECURITY CHECK 1: DANGER - Executables masquerading as data
         // ------------------------------------------------------------
         if is_executable_mime {
             // If the actual file is an executable, but its extension is NOT an executable extension...
-            if !allowed_binary_exts.contains(&ext.as_str()) {
+            if !rules.is_allowed_binary(&ext) {
                 // If it's masquerading as a format users implicitly trust (like a document or image)...
-                let user_safe_formats = [
-                    "txt", "pdf", "jpg", "jpeg", "png", "gif", "mp3", "mp4", "docx", "xlsx", "zip",
-                    "rar", "csv",
-                ];
-
-                if user_safe_formats.contains(&ext.as_str()) {
+                if rules.is_user_safe(&ext) {
                     risk_level = "DANGER".to_string();
                     description = format!("EXECUTABLE hidden as .{}", ext.to_uppercase());
                 }
@@ -161,25 +721,16 @@ pub fn analyze_file(path: &Path) -> Result<AnalysisResult> {
             // The file isn't an executable, but its extension is lying about what it is.
             // We need to filter out common legitimate reasons for mismatches (whitelisting).
 
-            let system_extensions = [
-                "mui", "cat", "tlb", "cip", "nls", "icm", "inf", "pnf", "xml", "json", "lib",
-                "rlib", "pdb", "exp", "obj", "iobj", "ipdb", "dat", "bin", "cache", "tmp", "db",
-                "db-shm", "db-wal", "plugin", "bpl",
-            ];
-
             let image_formats = [
                 "jpg", "jpeg", "png", "gif", "webp", "bmp", "ico", "tiff", "tif",
             ];
 
-            if system_extensions.contains(&ext.as_str()) {
+            if rules.system_extensions.contains(&ext) && !rules.is_monitored(&ext) {
                 // Ignore: Common OS and dev files often have custom extensions but standard headers.
             } else if real_ext == "der" && (ext == "cat" || ext == "cip" || ext == "crl") {
                 // Safe: Windows security catalog files use DER certificate encoding.
             } else if (real_ext == "zip" || real_ext == "jar")
-                && matches!(
-                    ext.as_str(),
-                    "docx" | "xlsx" | "pptx" | "odt" | "apk" | "nupkg" | "whl" | "vsix" | "crx"
-                )
+                && rules.zip_container_exts.contains(&ext)
             {
                 // Safe: Modern documents (Office), Android apps (.apk), and browser extensions (.crx)
                 // are actually just ZIP archives under the hood. The infer crate sees "ZIP", but the
@@ -189,25 +740,25 @@ pub fn analyze_file(path: &Path) -> Result<AnalysisResult> {
             } else if image_formats.contains(&real_ext) && image_formats.contains(&ext.as_str()) {
                 // Safe: Image format mismatches are incredibly common on the web
                 // (e.g., a .webp file downloaded and saved as .png). Usually harmless.
-            } else {
+            } else if rules.is_monitored(&ext) {
                 // If it doesn't match our whitelists, flag it if the user thinks it's a standard media/document.
-                let monitored_exts = [
-                    "jpg", "jpeg", "png", "gif", "pdf", "mp4", "mp3", "zip", "rar", "7z", "avi",
-                    "mov", "wav",
-                ];
-
-                if monitored_exts.contains(&ext.as_str()) {
-                    risk_level = "WARNING".to_string();
-                    description = format!(
-                        "File is actually .{} but named .{}",
-                        real_ext.to_uppercase(),
-                        ext
-                    );
-                }
+                risk_level = "WARNING".to_string();
+                description = format!(
+                    "File is actually .{} but named .{}",
+                    real_ext.to_uppercase(),
+                    ext
+                );
             }
         }
     }
 
+    // Folder overrides only re-grade findings; they never turn a clean file into a finding.
+    if risk_level != "SAFE" {
+        if let Some(severity) = rules.folder_severity(path) {
+            risk_level = severity.to_string();
+        }
+    }
+
     Ok(AnalysisResult {
         path: path.to_string_lossy().to_string(),
         filename,
@@ -348,5 +899,231 @@ mod tests {
 
         let _ = fs::remove_file(path);
     }
+
+    #[test]
+    fn test_scan_cache_skips_unchanged_files() {
+        let dir = std::env::temp_dir()
+            .join("qre_analyzer_tests")
+            .join("cache_scan");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("holiday.jpg");
+        fs::write(&path, b"PK\x03\x04\x14\x00\x08\x00\x08\x00").unwrap();
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let rules = AnalyzerRules::default();
+        let options = ScanOptions::default();
+        let mut cache = ScanCache::new(MasterKey([7u8; 32]));
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let first = scan_directory_with(&dir_str, &rules, &options, &mut cache, |_| {});
+        assert_eq!(first.len(), 1);
+        assert_eq!(cache.entries.len(), 1);
+
+        // Neither the folder nor the file name reaches the cache file.
+        let cache_path = dir.join(CACHE_FILE_NAME);
+        cache.save(&cache_path).unwrap();
+        let stored = fs::read_to_string(&cache_path).unwrap();
+        assert!(!stored.contains("cache_scan") && !stored.contains("holiday"));
+        let mut cache = ScanCache::load(&cache_path, MasterKey([7u8; 32]));
+        fs::remove_file(&cache_path).unwrap();
+
+        // Same size and mtime but harmless content: the cached verdict must be reused,
+        // proving the file was not re-read.
+        fs::write(&path, b"plain text").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        let second = scan_directory_with(&dir_str, &rules, &options, &mut cache, |_| {});
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].path, path.to_string_lossy());
+        assert_eq!(second[0].filename, "holiday.jpg");
+
+        // Under another key (another vault) the entry is not found.
+        let mut other = ScanCache::new(MasterKey([8u8; 32]));
+        other.rules_fingerprint = cache.rules_fingerprint.clone();
+        other.entries = cache.entries.clone();
+        assert!(scan_directory_with(&dir_str, &rules, &options, &mut other, |_| {}).is_empty());
+
+        // A full rescan ignores the cache and sees the new content.
+        let full = ScanOptions {
+            use_cache: false,
+            ..ScanOptions::default()
+        };
+        let third = scan_directory_with(&dir_str, &rules, &full, &mut cache, |_| {});
+        assert!(third.is_empty());
+
+        // Deleted files are pruned from the cache.
+        fs::remove_file(&path).unwrap();
+        scan_directory_with(&dir_str, &rules, &options, &mut cache, |_| {});
+        assert_eq!(cache.entries.len(), 0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_scan_size_filters() {
+        let dir = std::env::temp_dir()
+            .join("qre_analyzer_tests")
+            .join("size_filter");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("small.jpg"), b"PK\x03\x04\x14\x00\x08\x00\x08\x00").unwrap();
+        let mut big = b"PK\x03\x04\x14\x00\x08\x00\x08\x00".to_vec();
+        big.resize(4096, 0);
+        fs::write(dir.join("big.jpg"), &big).unwrap();
+
+        let rules = AnalyzerRules::default();
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let only_small = ScanOptions {
+            max_size: Some(1024),
+            ..ScanOptions::default()
+        };
+        let results = scan_directory_with(
+            &dir_str,
+            &rules,
+            &only_small,
+            &mut ScanCache::default(),
+            |_| {},
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].filename, "small.jpg");
+
+        let only_big = ScanOptions {
+            min_size: Some(1024),
+            ..ScanOptions::default()
+        };
+        let results = scan_directory_with(
+            &dir_str,
+            &rules,
+            &only_big,
+            &mut ScanCache::default(),
+            |_| {},
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].filename, "big.jpg");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_scan_without_key_caches_nothing() {
+        let dir = std::env::temp_dir()
+            .join("qre_analyzer_tests")
+            .join("cache_keyless");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.jpg"), b"PK\x03\x04\x14\x00\x08\x00\x08\x00").unwrap();
+
+        let mut cache = ScanCache::default();
+        let dir_str = dir.to_string_lossy().to_string();
+        let results = scan_directory_with(
+            &dir_str,
+            &AnalyzerRules::default(),
+            &ScanOptions::default(),
+            &mut cache,
+            |_| {},
+        );
+        assert_eq!(results.len(), 1);
+        assert!(cache.entries.is_empty());
+
+        let cache_path = dir.join(CACHE_FILE_NAME);
+        cache.save(&cache_path).unwrap();
+        assert!(!cache_path.exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_scan_cache_invalidated_by_rule_change() {
+        let mut cache = ScanCache::default();
+        cache.sync_rules(&AnalyzerRules::default());
+        cache.entries.insert(
+            "x".into(),
+            CachedFile {
+                root: String::new(),
+                size: 1,
+                mtime_ms: 1,
+                finding: None,
+            },
+        );
+        cache.sync_rules(&AnalyzerRules::default());
+        assert_eq!(cache.entries.len(), 1);
+
+        let changed = AnalyzerRules::from_toml("custom_monitored_exts = [\"psd\"]\n").unwrap();
+        cache.sync_rules(&changed);
+        assert_eq!(cache.entries.len(), 0);
+    }
+
+    #[test]
+    fn test_rules_defaults_parse_from_empty_file() {
+        let rules = AnalyzerRules::from_toml("").unwrap();
+        assert_eq!(rules, AnalyzerRules::default());
+    }
+
+    #[test]
+    fn test_rules_extension_policy_ignore() {
+        let zip_magic_bytes: &[u8] = b"PK\x03\x04\x14\x00\x08\x00\x08\x00";
+        let path = create_temp_file("rules_ignored.jpg", zip_magic_bytes);
+
+        let rules = AnalyzerRules::from_toml("[extension_policies]\njpg = \"ignore\"\n").unwrap();
+        let result = analyze_file_with_rules(&path, &rules).unwrap();
+        assert_eq!(result.risk_level, "SAFE");
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_rules_custom_monitored_extension() {
+        // .psd is not monitored by default, so a ZIP named .psd is ignored...
+        let zip_magic_bytes: &[u8] = b"PK\x03\x04\x14\x00\x08\x00\x08\x00";
+        let path = create_temp_file("mockup.psd", zip_magic_bytes);
+        let default = analyze_file_with_rules(&path, &AnalyzerRules::default()).unwrap();
+        assert_eq!(default.risk_level, "SAFE");
+
+        // ...until the user adds it (leading dots and case are normalized).
+        let rules = AnalyzerRules::from_toml("custom_monitored_exts = [\".PSD\"]\n").unwrap();
+        let result = analyze_file_with_rules(&path, &rules).unwrap();
+        assert_eq!(result.risk_level, "WARNING");
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_rules_folder_override_regrades_findings() {
+        let zip_magic_bytes: &[u8] = b"PK\x03\x04\x14\x00\x08\x00\x08\x00";
+        let dir = std::env::temp_dir()
+            .join("qre_analyzer_tests")
+            .join("override");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("photo.png");
+        fs::write(&path, zip_magic_bytes).unwrap();
+
+        let toml = format!(
+            "[[folder_overrides]]\npath = {:?}\nseverity = \"danger\"\n",
+            dir.to_string_lossy()
+        );
+        let rules = AnalyzerRules::from_toml(&toml).unwrap();
+        let result = analyze_file_with_rules(&path, &rules).unwrap();
+        assert_eq!(result.risk_level, "DANGER");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_rules_validation_reports_all_errors() {
+        let toml = "monitored_exts = [\"\", \"a b\"]\n\n[[folder_overrides]]\npath = \"\"\nseverity = \"loud\"\n";
+        let errors = AnalyzerRules::from_toml(toml).unwrap_err();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors.iter().any(|e| e.contains("unknown severity 'loud'")));
+
+        // Unknown keys are rejected so typos do not silently do nothing.
+        let errors = AnalyzerRules::from_toml("monitored = [\"jpg\"]\n").unwrap_err();
+        assert!(errors[0].starts_with("Parse error"));
+    }
 }
 // --- END OF FILE analyzer.rs ---
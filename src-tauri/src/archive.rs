@@ -0,0 +1,660 @@
+// --- START OF FILE archive.rs ---
+
+// ==========================================
+// --- MULTI-FILE ARCHIVES ---
+// ==========================================
+// Bundle mode of `lock_file` packs several files (and folders) into one .qre instead of
+// one container per input. Layout:
+//
+//   [version u32 = 9][4 KB header region (the V8 StreamHeader)]
+//   [entry 0 chunks][entry 1 chunks]...
+//   [encrypted file table][table length u64 LE]
+//
+// Each entry is chunked like a V8 stream under its own random base nonce, so a single
+// entry can be decrypted by seeking to its offset without touching the others. The file
+// table (paths, sizes, offsets, nonces, SHA-256s) is sealed with the file key, so entry
+// names are not visible without the password. The table fixes every entry's chunk count
+// and length, and the chunk AAD binds each chunk to its entry and position, so chunks
+// cannot be dropped, moved between entries or reordered.
+
+use crate::av_guard::with_retry;
+use crate::crypto_stream::{
+    self, StreamHeader, AES_NONCE_LEN, CHUNK_SIZE, FILE_KEY_LEN, GCM_TAG_LEN,
+    HEADER_RESERVED_BYTES, SHA256_LEN, VALIDATION_MAGIC, VERSION_ARCHIVE,
+};
+use crate::keychain::MasterKey;
+use crate::subkeys::{self, KeyPurpose};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Context, Result};
+use bincode::Options;
+use rand::{rngs::OsRng, RngCore, SeedableRng, TryRngCore};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Bounds checked when reading the (attacker-controlled until decrypted) table.
+const MAX_ARCHIVE_ENTRIES: usize = 100_000;
+const MAX_TABLE_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_ENTRY_PATH_LEN: usize = 4096;
+const TABLE_AAD: &[u8] = b"QRE_ARCHIVE_TABLE";
+/// Entry data starts right after the version and the header region.
+const DATA_START: u64 = 4 + HEADER_RESERVED_BYTES as u64;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+/// One file in the encrypted table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ArchiveEntry {
+    /// Relative, '/'-separated path inside the archive (e.g. "Photos/2024/a.jpg").
+    path: String,
+    size: u64,
+    offset: u64,
+    /// Bytes from `offset` to the end of the entry's last chunk, length prefixes included.
+    stored_len: u64,
+    chunks: u64,
+    base_nonce: Vec<u8>,
+    sha256: Vec<u8>,
+}
+
+/// What `unlock_archive` shows for each entry.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveListing {
+    pub path: String,
+    pub size: u64,
+}
+
+/// The path must be relative and made of plain components only: it is joined onto the
+/// extraction directory.
+fn validate_entry_path(path: &str) -> Result<()> {
+    let valid = !path.is_empty()
+        && path.len() <= MAX_ENTRY_PATH_LEN
+        && path.split('/').all(|part| {
+            !part.contains('\\')
+                && matches!(
+                    Path::new(part).components().collect::<Vec<_>>().as_slice(),
+                    [Component::Normal(p)] if *p == std::ffi::OsStr::new(part)
+                )
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Malformed archive: invalid entry path"))
+    }
+}
+
+impl ArchiveEntry {
+    fn validate(&self, table_start: u64) -> Result<()> {
+        validate_entry_path(&self.path)?;
+        if self.base_nonce.len() != AES_NONCE_LEN || self.sha256.len() != SHA256_LEN {
+            return Err(anyhow!("Malformed archive: invalid entry fields"));
+        }
+        let in_bounds = self.offset >= DATA_START
+            && self
+                .offset
+                .checked_add(self.stored_len)
+                .is_some_and(|end| end <= table_start);
+        if !in_bounds {
+            return Err(anyhow!("Malformed archive: entry outside the data area"));
+        }
+        Ok(())
+    }
+}
+
+/// Chunk AAD: the entry index and the chunk index within the entry.
+fn chunk_aad(entry_index: usize, chunk_index: u64) -> String {
+    format!("archive:{}:{}", entry_index, chunk_index)
+}
+
+// ==========================================
+// --- INPUT COLLECTION ---
+// ==========================================
+
+/// Expands the inputs into (archive path, source file) pairs. A folder is stored under its
+/// own name with its structure intact; symlinks inside folders are skipped. Two inputs
+/// that would land on the same archive path are rejected rather than silently renamed.
+pub fn collect_inputs(inputs: &[PathBuf]) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let name = input
+            .file_name()
+            .ok_or_else(|| anyhow!("'{}' has no file name", input.display()))?
+            .to_string_lossy()
+            .to_string();
+        if input.is_dir() {
+            for entry in walkdir::WalkDir::new(input)
+                .follow_links(false)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let rel = entry.path().strip_prefix(input).unwrap_or(entry.path());
+                let mut parts = vec![name.clone()];
+                parts.extend(
+                    rel.components()
+                        .map(|c| c.as_os_str().to_string_lossy().to_string()),
+                );
+                files.push((parts.join("/"), entry.path().to_path_buf()));
+            }
+        } else {
+            files.push((name, input.clone()));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for (path, _) in &files {
+        validate_entry_path(path)?;
+        if !seen.insert(path.to_lowercase()) {
+            return Err(anyhow!(
+                "Two inputs would both be stored as '{}'. Rename one first.",
+                path
+            ));
+        }
+    }
+    if files.is_empty() {
+        return Err(anyhow!(
+            "Nothing to archive: the selection contains no files."
+        ));
+    }
+    if files.len() > MAX_ARCHIVE_ENTRIES {
+        return Err(anyhow!(
+            "An archive holds at most {} files.",
+            MAX_ARCHIVE_ENTRIES
+        ));
+    }
+    Ok(files)
+}
+
+// ==========================================
+// --- ARCHIVE WRITER ---
+// ==========================================
+
+/// Encrypts `files` (see `collect_inputs`) into a single archive at `output_path`.
+/// `level_for` picks the zstd level from an entry's path. `callback` reports plaintext
+/// bytes done out of the total. The caller deletes the output on error.
+#[allow(clippy::too_many_arguments)]
+pub fn create_archive(
+    files: &[(String, PathBuf)],
+    output_path: &str,
+    master_key: &MasterKey,
+    vault_id: &str,
+    keyfile_bytes: Option<&[u8]>,
+    entropy_seed: Option<[u8; 32]>,
+    level_for: impl Fn(&str) -> i32,
+    callback: impl Fn(u64, u64),
+) -> Result<()> {
+    let total_size: u64 = files
+        .iter()
+        .map(|(_, src)| fs::metadata(src).map(|m| m.len()).unwrap_or(0))
+        .sum();
+
+    // Entropy mixing (Paranoid Mode), as in the single-file writer
+    let mut combined_seed = [0u8; 32];
+    OsRng
+        .try_fill_bytes(&mut combined_seed)
+        .expect("OS RNG failed");
+    if let Some(user_seed) = entropy_seed {
+        for i in 0..32 {
+            combined_seed[i] ^= user_seed[i];
+        }
+    }
+    let mut rng = ChaCha20Rng::from_seed(combined_seed);
+    combined_seed.zeroize();
+
+    let mut file_key = Zeroizing::new([0u8; FILE_KEY_LEN]);
+    rng.fill_bytes(&mut *file_key);
+    let cipher_file = Aes256Gcm::new_from_slice(&*file_key).map_err(|e| anyhow!(e))?;
+
+    let wrapping_root = subkeys::derive(master_key, KeyPurpose::FileWrapping);
+    let wrapping_key = crypto_stream::derive_wrapping_key(&wrapping_root, keyfile_bytes);
+    let cipher_wrap = Aes256Gcm::new_from_slice(&*wrapping_key).map_err(|e| anyhow!(e))?;
+
+    let mut val_nonce = [0u8; AES_NONCE_LEN];
+    rng.fill_bytes(&mut val_nonce);
+    let encrypted_validation = cipher_wrap
+        .encrypt(Nonce::from_slice(&val_nonce), VALIDATION_MAGIC)
+        .map_err(|e| anyhow!("Validation encrypt: {}", e))?;
+
+    let mut key_wrap_nonce = [0u8; AES_NONCE_LEN];
+    rng.fill_bytes(&mut key_wrap_nonce);
+    let encrypted_file_key = cipher_wrap
+        .encrypt(Nonce::from_slice(&key_wrap_nonce), file_key.as_ref())
+        .map_err(|e| anyhow!("File key wrap: {}", e))?;
+
+    // The header's base nonce seals the file table; entries get their own.
+    let mut table_nonce = [0u8; AES_NONCE_LEN];
+    rng.fill_bytes(&mut table_nonce);
+
+    let header = StreamHeader {
+        vault_id: Some(vault_id.to_string()),
+        validation_nonce: val_nonce.to_vec(),
+        encrypted_validation_tag: encrypted_validation,
+        key_wrapping_nonce: key_wrap_nonce.to_vec(),
+        encrypted_file_key,
+        base_nonce: table_nonce.to_vec(),
+        original_filename: Path::new(output_path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        original_hash: None,
+        timelock: None,
+        metadata: None,
+        padding: None,
+        expiry: None,
+        parity: None,
+        puzzle: None,
+        chunk_size: None,
+        kem_wrap: None,
+    };
+    header.validate()?;
+    let serialized = bincode::serialize(&header).context("Failed to serialize header")?;
+    if serialized.len() > HEADER_RESERVED_BYTES {
+        return Err(anyhow!("Archive header exceeds the header region."));
+    }
+
+    let mut output = BufWriter::new(with_retry("create", Path::new(output_path), || {
+        File::create(output_path)
+    })?);
+    output.write_all(&VERSION_ARCHIVE.to_le_bytes())?;
+    let mut region = vec![0u8; HEADER_RESERVED_BYTES];
+    region[..serialized.len()].copy_from_slice(&serialized);
+    output.write_all(&region)?;
+
+    // ── ENTRIES ───────────────────────────────────────────────────────────────
+    let mut entries = Vec::with_capacity(files.len());
+    let mut offset = DATA_START;
+    let mut done: u64 = 0;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+
+    for (entry_index, (path, source)) in files.iter().enumerate() {
+        let mut input = BufReader::new(with_retry("open", source, || File::open(source))?);
+        let level = level_for(path);
+        let mut base_nonce = [0u8; AES_NONCE_LEN];
+        rng.fill_bytes(&mut base_nonce);
+
+        let mut hasher = Sha256::new();
+        let mut size: u64 = 0;
+        let mut stored_len: u64 = 0;
+        let mut chunks: u64 = 0;
+        loop {
+            let n = input.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            let compressed = crypto_stream::compress_chunk(&buffer[..n], level)?;
+            let aad = chunk_aad(entry_index, chunks);
+            let ciphertext = cipher_file
+                .encrypt(
+                    Nonce::from_slice(&crypto_stream::chunk_nonce(&base_nonce, chunks)),
+                    Payload {
+                        msg: &compressed,
+                        aad: aad.as_bytes(),
+                    },
+                )
+                .map_err(|_| anyhow!("Encryption of '{}' failed", path))?;
+            output.write_all(&(ciphertext.len() as u32).to_le_bytes())?;
+            output.write_all(&ciphertext)?;
+
+            stored_len += 4 + ciphertext.len() as u64;
+            size += n as u64;
+            chunks += 1;
+            done += n as u64;
+            callback(done, total_size);
+        }
+
+        entries.push(ArchiveEntry {
+            path: path.clone(),
+            size,
+            offset,
+            stored_len,
+            chunks,
+            base_nonce: base_nonce.to_vec(),
+            sha256: hasher.finalize().to_vec(),
+        });
+        offset += stored_len;
+    }
+
+    // ── FILE TABLE ────────────────────────────────────────────────────────────
+    let table_plain = Zeroizing::new(bincode::serialize(&entries)?);
+    let table = cipher_file
+        .encrypt(
+            Nonce::from_slice(&table_nonce),
+            Payload {
+                msg: &table_plain,
+                aad: TABLE_AAD,
+            },
+        )
+        .map_err(|_| anyhow!("File table encryption failed"))?;
+    output.write_all(&table)?;
+    output.write_all(&(table.len() as u64).to_le_bytes())?;
+    output.flush()?;
+    Ok(())
+}
+
+// ==========================================
+// --- ARCHIVE READER ---
+// ==========================================
+
+/// An archive whose header and file table have been decrypted. Entries are decrypted
+/// one at a time by `extract`.
+pub struct Archive {
+    file: BufReader<File>,
+    cipher: Aes256Gcm,
+    entries: Vec<ArchiveEntry>,
+}
+
+impl Archive {
+    /// Checks the password/keyfile and decrypts the file table. Nothing else is read.
+    pub fn open(path: &str, master_key: &MasterKey, keyfile_bytes: Option<&[u8]>) -> Result<Self> {
+        let file_len = fs::metadata(path)?.len();
+        let mut file = BufReader::new(with_retry("open", Path::new(path), || File::open(path))?);
+
+        let mut ver_buf = [0u8; 4];
+        file.read_exact(&mut ver_buf)
+            .context("Failed to read version")?;
+        let version = u32::from_le_bytes(ver_buf);
+        if version != VERSION_ARCHIVE {
+            return Err(anyhow!("Not a multi-file archive (version {}).", version));
+        }
+        let header = crypto_stream::parse_stream_header(version, &mut file)?;
+        let cipher = crypto_stream::unwrap_file_cipher(&header, master_key, keyfile_bytes, None)?;
+
+        // ── FILE TABLE ────────────────────────────────────────────────────────
+        if file_len < DATA_START + 8 {
+            return Err(anyhow!("INTEGRITY ERROR: The archive is truncated."));
+        }
+        file.seek(SeekFrom::Start(file_len - 8))?;
+        let mut len_buf = [0u8; 8];
+        file.read_exact(&mut len_buf)?;
+        let table_len = u64::from_le_bytes(len_buf);
+        let available = file_len - 8 - DATA_START;
+        if table_len > available || table_len > MAX_TABLE_BYTES || table_len < GCM_TAG_LEN as u64 {
+            return Err(anyhow!(
+                "INTEGRITY ERROR: The archive's file table is missing or truncated."
+            ));
+        }
+        let table_start = file_len - 8 - table_len;
+        file.seek(SeekFrom::Start(table_start))?;
+        let mut table = vec![0u8; table_len as usize];
+        file.read_exact(&mut table)?;
+
+        let plain = Zeroizing::new(
+            cipher
+                .decrypt(
+                    Nonce::from_slice(&header.base_nonce),
+                    Payload {
+                        msg: &table,
+                        aad: TABLE_AAD,
+                    },
+                )
+                .map_err(|_| anyhow!("INTEGRITY ERROR: The archive's file table is corrupt."))?,
+        );
+        let entries: Vec<ArchiveEntry> = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .with_limit(table_len)
+            .deserialize(&plain)
+            .context("Malformed archive: unreadable file table")?;
+        if entries.len() > MAX_ARCHIVE_ENTRIES {
+            return Err(anyhow!("Malformed archive: too many entries"));
+        }
+        for entry in &entries {
+            entry.validate(table_start)?;
+        }
+
+        Ok(Self {
+            file,
+            cipher,
+            entries,
+        })
+    }
+
+    pub fn list(&self) -> Vec<ArchiveListing> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveListing {
+                path: e.path.clone(),
+                size: e.size,
+            })
+            .collect()
+    }
+
+    /// Decrypts one entry into `output_dir`, recreating its folders. An existing file is
+    /// never overwritten (a numbered name is used instead). Returns the written path;
+    /// on any failure the partial output is deleted.
+    pub fn extract(
+        &mut self,
+        entry_path: &str,
+        output_dir: &Path,
+        callback: impl Fn(u64, u64),
+    ) -> Result<PathBuf> {
+        let index = self
+            .entries
+            .iter()
+            .position(|e| e.path == entry_path)
+            .ok_or_else(|| anyhow!("The archive has no entry '{}'.", entry_path))?;
+        let entry = self.entries[index].clone();
+
+        let raw_out = entry
+            .path
+            .split('/')
+            .fold(output_dir.to_path_buf(), |acc, part| acc.join(part));
+        if let Some(parent) = raw_out.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let final_out = crate::utils::get_unique_path(&raw_out);
+        let mut output = BufWriter::new(with_retry("create", &final_out, || {
+            File::create(&final_out)
+        })?);
+
+        let result = self.decrypt_entry(index, &entry, &mut output, callback);
+        drop(output);
+        if let Err(e) = result {
+            let _ = fs::remove_file(&final_out);
+            return Err(e);
+        }
+        Ok(final_out)
+    }
+
+    fn decrypt_entry<W: Write>(
+        &mut self,
+        index: usize,
+        entry: &ArchiveEntry,
+        output: &mut W,
+        callback: impl Fn(u64, u64),
+    ) -> Result<()> {
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        let mut hasher = Sha256::new();
+        let mut consumed: u64 = 0;
+        let mut written: u64 = 0;
+        let mut size_buf = [0u8; 4];
+
+        for chunk_index in 0..entry.chunks {
+            self.file.read_exact(&mut size_buf)?;
+            let chunk_len = u32::from_le_bytes(size_buf) as usize;
+            consumed += 4 + chunk_len as u64;
+            if !(GCM_TAG_LEN..=CHUNK_SIZE + 4096).contains(&chunk_len)
+                || consumed > entry.stored_len
+            {
+                return Err(anyhow!(
+                    "INTEGRITY ERROR: '{}' chunk {} has an invalid size.",
+                    entry.path,
+                    chunk_index
+                ));
+            }
+            let mut ciphertext = vec![0u8; chunk_len];
+            self.file.read_exact(&mut ciphertext)?;
+
+            let aad = chunk_aad(index, chunk_index);
+            let compressed = self
+                .cipher
+                .decrypt(
+                    Nonce::from_slice(&crypto_stream::chunk_nonce(&entry.base_nonce, chunk_index)),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: aad.as_bytes(),
+                    },
+                )
+                .map_err(|_| {
+                    anyhow!(
+                        "'{}' chunk {} integrity check failed",
+                        entry.path,
+                        chunk_index
+                    )
+                })?;
+            let plaintext = crypto_stream::decompress_chunk(&compressed)?;
+            hasher.update(&plaintext);
+            output.write_all(&plaintext)?;
+            written += plaintext.len() as u64;
+            callback(written, entry.size);
+        }
+        output.flush()?;
+
+        if consumed != entry.stored_len || written != entry.size {
+            return Err(anyhow!(
+                "INTEGRITY ERROR: '{}' does not match the file table.",
+                entry.path
+            ));
+        }
+        if !crypto_stream::constant_time_eq(&hasher.finalize(), &entry.sha256) {
+            return Err(anyhow!(
+                "INTEGRITY ERROR: '{}' hash mismatch. Output removed.",
+                entry.path
+            ));
+        }
+        Ok(())
+    }
+}
+
+// ==========================================
+// --- UNIT TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qre_archive_tests_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn build(dir: &Path, key: &MasterKey) -> String {
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("Photos/2024")).unwrap();
+        fs::write(src.join("notes.txt"), b"hello archive").unwrap();
+        fs::write(src.join("Photos/2024/a.bin"), vec![7u8; CHUNK_SIZE + 10]).unwrap();
+        fs::write(src.join("Photos/empty.txt"), b"").unwrap();
+
+        let files = collect_inputs(&[src.join("notes.txt"), src.join("Photos")]).unwrap();
+        let out = dir.join("bundle.qre").to_string_lossy().to_string();
+        create_archive(&files, &out, key, "local", None, None, |_| 3, |_, _| {}).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_archive_list_and_selective_extract() {
+        let dir = test_dir("roundtrip");
+        let key = MasterKey([1; 32]);
+        let out = build(&dir, &key);
+
+        let mut archive = Archive::open(&out, &key, None).unwrap();
+        let mut paths: Vec<_> = archive.list().into_iter().map(|l| l.path).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            ["Photos/2024/a.bin", "Photos/empty.txt", "notes.txt"]
+        );
+
+        let dest = dir.join("out");
+        let written = archive
+            .extract("Photos/2024/a.bin", &dest, |_, _| {})
+            .unwrap();
+        assert_eq!(written, dest.join("Photos").join("2024").join("a.bin"));
+        assert_eq!(fs::read(&written).unwrap(), vec![7u8; CHUNK_SIZE + 10]);
+        assert!(!dest.join("notes.txt").exists());
+
+        let empty = archive
+            .extract("Photos/empty.txt", &dest, |_, _| {})
+            .unwrap();
+        assert_eq!(fs::read(empty).unwrap(), b"");
+        assert!(archive.extract("missing.txt", &dest, |_, _| {}).is_err());
+
+        // A second extraction never overwrites the first.
+        let again = archive.extract("notes.txt", &dest, |_, _| {}).unwrap();
+        let third = archive.extract("notes.txt", &dest, |_, _| {}).unwrap();
+        assert_ne!(again, third);
+        assert_eq!(fs::read(third).unwrap(), b"hello archive");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_archive_rejects_wrong_key_and_tampering() {
+        let dir = test_dir("tamper");
+        let key = MasterKey([2; 32]);
+        let out = build(&dir, &key);
+        assert!(Archive::open(&out, &MasterKey([3; 32]), None).is_err());
+        assert!(crypto_stream::decrypt_file_stream(
+            &out,
+            &dir.to_string_lossy(),
+            &key,
+            None,
+            |_, _| {}
+        )
+        .is_err());
+
+        // Flip a byte inside the first entry's ciphertext: the table still opens and
+        // the other entries extract, but this one fails and leaves nothing behind.
+        let mut bytes = fs::read(&out).unwrap();
+        bytes[DATA_START as usize + 20] ^= 0xFF;
+        fs::write(&out, &bytes).unwrap();
+        let mut archive = Archive::open(&out, &key, None).unwrap();
+        let first = archive.list()[0].path.clone();
+        let dest = dir.join("out");
+        assert!(archive.extract(&first, &dest, |_, _| {}).is_err());
+        assert!(!dest.join(&first).exists());
+        assert!(archive
+            .extract("Photos/empty.txt", &dest, |_, _| {})
+            .is_ok());
+
+        // A truncated table is rejected.
+        fs::write(&out, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(Archive::open(&out, &key, None).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_archive_entry_paths() {
+        for ok in ["a.txt", "Photos/2024/a.jpg"] {
+            assert!(validate_entry_path(ok).is_ok(), "{}", ok);
+        }
+        for bad in ["", "../x", "a/../b", "/etc/passwd", "a//b", "a\\b", "a/./b"] {
+            assert!(validate_entry_path(bad).is_err(), "{}", bad);
+        }
+
+        let dir = test_dir("inputs");
+        fs::create_dir_all(dir.join("one")).unwrap();
+        fs::create_dir_all(dir.join("two")).unwrap();
+        fs::write(dir.join("one/same.txt"), b"1").unwrap();
+        fs::write(dir.join("two/same.txt"), b"2").unwrap();
+        assert!(collect_inputs(&[dir.join("one/same.txt"), dir.join("two/same.txt")]).is_err());
+        assert!(collect_inputs(&[dir.join("one"), dir.join("two")]).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE archive.rs ---
@@ -0,0 +1,457 @@
+// --- START OF FILE audit.rs ---
+
+// ==========================================
+// --- VAULT AUDIT LOG ---
+// ==========================================
+// An append-only record of security-relevant vault events (logins, failed logins,
+// user slot changes, password changes), attributed to the key slot that performed
+// them — "owner", "guest" or a named team member.
+//
+// The log lives next to the keychain as JSON lines (`audit.log`). It is deliberately
+// NOT encrypted: failed logins happen before any key is available, and the log must
+// stay writable then. It therefore never contains secrets — only slot names, action
+// names and timestamps. When it exceeds `MAX_LOG_BYTES` it is rotated to `audit.log.1`
+// (one generation kept).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+pub const AUDIT_FILE_NAME: &str = "audit.log";
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+/// Slot names and details are user-influenced; cap them so one event stays one short line.
+const MAX_FIELD_LEN: usize = 128;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    pub at: i64,
+    pub vault_id: String,
+    /// Key slot the action is attributed to ("owner", "guest" or a user slot name).
+    pub user: String,
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(vault_id: &str, user: &str, action: &str, detail: Option<String>) -> Self {
+        Self {
+            at: chrono::Utc::now().timestamp(),
+            vault_id: clip(vault_id),
+            user: clip(user),
+            action: clip(action),
+            detail: detail.map(|d| clip(&d)),
+        }
+    }
+}
+
+fn clip(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FIELD_LEN)
+        .collect()
+}
+
+/// Appends one event to `<dir>/audit.log`, rotating first if the file is too large.
+pub fn append(dir: &Path, event: &AuditEvent) -> Result<()> {
+    let path = dir.join(AUDIT_FILE_NAME);
+    if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) > MAX_LOG_BYTES {
+        fs::rename(&path, dir.join(format!("{}.1", AUDIT_FILE_NAME)))?;
+    }
+
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Returns up to `limit` most recent events, newest first. Unparseable lines are skipped.
+pub fn read_recent(dir: &Path, limit: usize) -> Result<Vec<AuditEvent>> {
+    let path = dir.join(AUDIT_FILE_NAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let reader = BufReader::new(fs::File::open(&path)?);
+    let mut events: Vec<AuditEvent> = reader
+        .lines()
+        .map_while(|l| l.ok())
+        .filter_map(|l| serde_json::from_str(&l).ok())
+        .collect();
+    events.reverse();
+    events.truncate(limit);
+    Ok(events)
+}
+
+/// All events of the current log generation, oldest first.
+fn read_all(dir: &Path) -> Result<Vec<AuditEvent>> {
+    let mut events = read_recent(dir, usize::MAX)?;
+    events.reverse();
+    Ok(events)
+}
+
+// ==========================================
+// --- ANOMALY ALERTS ---
+// ==========================================
+// Every appended event is checked against the log for three patterns:
+//   - a login at an hour the same slot has (almost) never logged in at before,
+//   - a burst of exports (keychain or password export, secret reveals) in a short window,
+//   - repeated failed unlocks (wrong password, keyfile or panel PIN) in a short window.
+// Hits become persistent alerts in `security_alerts.json` that stay until the user
+// acknowledges them. Like the log they hold no secrets.
+
+pub const ALERTS_FILE_NAME: &str = "security_alerts.json";
+
+/// Actions that take data out of the vault.
+const EXPORT_ACTIONS: &[&str] = &["export_keychain", "export_passwords", "secret_read"];
+const EXPORT_BURST_COUNT: usize = 5;
+const EXPORT_BURST_WINDOW_SECS: i64 = 10 * 60;
+
+const FAILURE_ACTIONS: &[&str] = &["login_failed", "keyfile_failed", "panel_pin_failed"];
+const FAILURE_BURST_COUNT: usize = 3;
+const FAILURE_WINDOW_SECS: i64 = 15 * 60;
+
+/// Logins needed before "unusual hour" means anything.
+const MIN_LOGIN_HISTORY: usize = 20;
+/// A login is unusual if fewer than this share of earlier logins were within an hour of it.
+const UNUSUAL_HOUR_SHARE: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    UnusualHour,
+    ExportBurst,
+    RepeatedFailures,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SecurityAlert {
+    /// Derived from the triggering event, so a rescan never duplicates an alert.
+    pub id: String,
+    pub kind: AnomalyKind,
+    pub vault_id: String,
+    pub user: String,
+    /// Time of the event that completed the pattern.
+    pub at: i64,
+    pub message: String,
+    #[serde(default)]
+    pub acknowledged_at: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AlertStore {
+    pub alerts: Vec<SecurityAlert>,
+    /// Events at or before this time have been checked.
+    pub scanned_until: i64,
+}
+
+fn alert(kind: AnomalyKind, event: &AuditEvent, message: String) -> SecurityAlert {
+    SecurityAlert {
+        id: format!("{:?}-{}-{}", kind, event.at, event.user).to_lowercase(),
+        kind,
+        vault_id: event.vault_id.clone(),
+        user: event.user.clone(),
+        at: event.at,
+        message,
+        acknowledged_at: None,
+    }
+}
+
+/// Events in `(at - window, at]` of the same vault whose action is in `actions`.
+fn count_in_window(
+    events: &[AuditEvent],
+    at: i64,
+    vault_id: &str,
+    window: i64,
+    actions: &[&str],
+) -> usize {
+    events
+        .iter()
+        .filter(|e| e.at > at - window && e.at <= at)
+        .filter(|e| e.vault_id == vault_id && actions.contains(&e.action.as_str()))
+        .count()
+}
+
+/// Circular distance between two hours of the day.
+fn hour_distance(a: u32, b: u32) -> u32 {
+    let d = a.abs_diff(b);
+    d.min(24 - d)
+}
+
+fn local_hour(at: i64, offset: chrono::FixedOffset) -> Option<u32> {
+    use chrono::Timelike;
+    Some(
+        chrono::DateTime::from_timestamp(at, 0)?
+            .with_timezone(&offset)
+            .hour(),
+    )
+}
+
+/// Checks the events newer than `since` against everything before them.
+/// `events` must be oldest first; hours are judged in the `offset` time zone.
+pub fn detect(
+    events: &[AuditEvent],
+    since: i64,
+    offset: chrono::FixedOffset,
+) -> Vec<SecurityAlert> {
+    let mut alerts = Vec::new();
+    for (i, event) in events.iter().enumerate().filter(|(_, e)| e.at > since) {
+        let history = &events[..=i];
+        let action = event.action.as_str();
+
+        // Each burst is reported once: when it reaches the threshold.
+        if EXPORT_ACTIONS.contains(&action)
+            && count_in_window(
+                history,
+                event.at,
+                &event.vault_id,
+                EXPORT_BURST_WINDOW_SECS,
+                EXPORT_ACTIONS,
+            ) == EXPORT_BURST_COUNT
+        {
+            alerts.push(alert(
+                AnomalyKind::ExportBurst,
+                event,
+                format!(
+                    "{} exports from the vault within {} minutes.",
+                    EXPORT_BURST_COUNT,
+                    EXPORT_BURST_WINDOW_SECS / 60
+                ),
+            ));
+        }
+        if FAILURE_ACTIONS.contains(&action)
+            && count_in_window(
+                history,
+                event.at,
+                &event.vault_id,
+                FAILURE_WINDOW_SECS,
+                FAILURE_ACTIONS,
+            ) == FAILURE_BURST_COUNT
+        {
+            alerts.push(alert(
+                AnomalyKind::RepeatedFailures,
+                event,
+                format!(
+                    "{} failed password or keyfile attempts within {} minutes.",
+                    FAILURE_BURST_COUNT,
+                    FAILURE_WINDOW_SECS / 60
+                ),
+            ));
+        }
+        if action == "login" {
+            let Some(hour) = local_hour(event.at, offset) else {
+                continue;
+            };
+            let earlier: Vec<u32> = events[..i]
+                .iter()
+                .filter(|e| {
+                    e.action == "login" && e.user == event.user && e.vault_id == event.vault_id
+                })
+                .filter_map(|e| local_hour(e.at, offset))
+                .collect();
+            let nearby = earlier
+                .iter()
+                .filter(|h| hour_distance(**h, hour) <= 1)
+                .count();
+            if earlier.len() >= MIN_LOGIN_HISTORY
+                && (nearby as f64) < UNUSUAL_HOUR_SHARE * earlier.len() as f64
+            {
+                alerts.push(alert(
+                    AnomalyKind::UnusualHour,
+                    event,
+                    format!(
+                        "Vault unlocked at {:02}:00, an hour it is rarely used.",
+                        hour
+                    ),
+                ));
+            }
+        }
+    }
+    alerts
+}
+
+pub fn load_alerts(dir: &Path) -> AlertStore {
+    fs::read_to_string(dir.join(ALERTS_FILE_NAME))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_alerts(dir: &Path, store: &AlertStore) -> Result<()> {
+    fs::write(
+        dir.join(ALERTS_FILE_NAME),
+        serde_json::to_vec_pretty(store)?,
+    )?;
+    Ok(())
+}
+
+/// Checks the events logged since the last scan and stores any new alerts.
+/// Returns only the new ones.
+pub fn scan_for_alerts(dir: &Path, offset: chrono::FixedOffset) -> Result<Vec<SecurityAlert>> {
+    let events = read_all(dir)?;
+    let mut store = load_alerts(dir);
+    let Some(latest) = events.iter().map(|e| e.at).max() else {
+        return Ok(Vec::new());
+    };
+    // Events logged within the same second as the last scan are checked again;
+    // the alert ids keep that from producing duplicates.
+    let mut new_alerts = detect(&events, store.scanned_until - 1, offset);
+    new_alerts.retain(|a| !store.alerts.iter().any(|old| old.id == a.id));
+    store.scanned_until = store.scanned_until.max(latest);
+    store.alerts.extend(new_alerts.iter().cloned());
+    save_alerts(dir, &store)?;
+    Ok(new_alerts)
+}
+
+/// Marks an alert as seen. Acknowledged alerts are kept for reference.
+pub fn acknowledge_alert(dir: &Path, id: &str, now: i64) -> Result<()> {
+    let mut store = load_alerts(dir);
+    let alert = store
+        .alerts
+        .iter_mut()
+        .find(|a| a.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Alert not found."))?;
+    alert.acknowledged_at.get_or_insert(now);
+    save_alerts(dir, &store)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join("qre_audit_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_append_and_read_newest_first() {
+        let dir = temp_dir("order");
+        for action in ["login", "add_user", "logout"] {
+            append(&dir, &AuditEvent::new("local", "alice", action, None)).unwrap();
+        }
+        let events = read_recent(&dir, 2).unwrap();
+        let actions: Vec<&str> = events.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec!["logout", "add_user"]);
+        assert_eq!(events[0].user, "alice");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fields_are_sanitized() {
+        let event = AuditEvent::new("local", "evil\nname", &"x".repeat(500), None);
+        assert_eq!(event.user, "evilname");
+        assert_eq!(event.action.len(), MAX_FIELD_LEN);
+    }
+
+    fn event(at: i64, user: &str, action: &str) -> AuditEvent {
+        AuditEvent {
+            at,
+            vault_id: "local".into(),
+            user: user.into(),
+            action: action.into(),
+            detail: None,
+        }
+    }
+
+    fn utc() -> chrono::FixedOffset {
+        chrono::FixedOffset::east_opt(0).unwrap()
+    }
+
+    #[test]
+    fn test_detects_bursts_once() {
+        let t = 1_700_000_000;
+        let mut events: Vec<AuditEvent> = (0..6)
+            .map(|i| event(t + i * 60, "owner", "secret_read"))
+            .collect();
+        events.extend((0..4).map(|i| event(t + 3600 + i * 10, "owner", "login_failed")));
+        // Spread out: never three within 15 minutes.
+        events.extend((0..3).map(|i| event(t + 7200 + i * 1000, "owner", "keyfile_failed")));
+
+        let alerts = detect(&events, 0, utc());
+        let kinds: Vec<AnomalyKind> = alerts.iter().map(|a| a.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![AnomalyKind::ExportBurst, AnomalyKind::RepeatedFailures]
+        );
+        assert_eq!(alerts[0].at, t + 4 * 60);
+        assert!(detect(&events, t + 3600 + 20, utc()).is_empty());
+    }
+
+    #[test]
+    fn test_detects_unusual_login_hour() {
+        let day = 86_400;
+        let base = 1_700_000_000 - 1_700_000_000 % day;
+        // Twenty logins around 09:00 UTC (08:30 - 09:30), then one at 03:00.
+        let mut events: Vec<AuditEvent> = (0..20)
+            .map(|d| {
+                event(
+                    base + d * day + 9 * 3600 + (d % 3 - 1) * 1800,
+                    "owner",
+                    "login",
+                )
+            })
+            .collect();
+        events.push(event(base + 20 * day + 3 * 3600, "owner", "login"));
+        events.push(event(base + 21 * day + 9 * 3600, "owner", "login"));
+        // Another slot has no history of its own yet.
+        events.push(event(base + 21 * day + 3 * 3600 + 60, "guest", "login"));
+
+        let alerts = detect(&events, 0, utc());
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, AnomalyKind::UnusualHour);
+        assert_eq!(alerts[0].at, base + 20 * day + 3 * 3600);
+
+        // Hours are reported in the user's time zone.
+        let plus6 = chrono::FixedOffset::east_opt(6 * 3600).unwrap();
+        assert!(detect(&events, 0, plus6)[0].message.contains("09:00"));
+    }
+
+    #[test]
+    fn test_alerts_persist_until_acknowledged() {
+        let dir = temp_dir("alerts");
+        for _ in 0..3 {
+            append(
+                &dir,
+                &AuditEvent::new("local", "owner", "login_failed", None),
+            )
+            .unwrap();
+        }
+        let new = scan_for_alerts(&dir, utc()).unwrap();
+        assert_eq!(new.len(), 1);
+        // Rescanning the same events finds nothing new.
+        assert!(scan_for_alerts(&dir, utc()).unwrap().is_empty());
+
+        acknowledge_alert(&dir, &new[0].id, 42).unwrap();
+        let store = load_alerts(&dir);
+        assert_eq!(store.alerts.len(), 1);
+        assert_eq!(store.alerts[0].acknowledged_at, Some(42));
+        assert!(acknowledge_alert(&dir, "missing", 1).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotation_keeps_one_generation() {
+        let dir = temp_dir("rotate");
+        fs::write(
+            dir.join(AUDIT_FILE_NAME),
+            vec![b'#'; MAX_LOG_BYTES as usize + 1],
+        )
+        .unwrap();
+        append(&dir, &AuditEvent::new("local", "owner", "login", None)).unwrap();
+
+        assert!(dir.join("audit.log.1").exists());
+        assert_eq!(read_recent(&dir, 10).unwrap().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE audit.rs ---
@@ -0,0 +1,297 @@
+// --- START OF FILE author_audit.rs ---
+
+// ==========================================
+// --- DOCUMENT AUTHOR AUDIT ---
+// ==========================================
+// Office and PDF files carry the name of whoever created and last saved them. Checking
+// files one by one in the metadata cleaner does not scale, so this sweep reads the
+// author fields of every document under Documents and Desktop and lists the ones that
+// name the *current user* — the ones that identify them when the file is shared.
+//
+// The user's identities are the account name, the full name from the OS account and,
+// on Windows, the Office user name. The caller can add more (maiden name, nickname).
+// Only the author fields are compared: the PDF `Creator` entry is the producing
+// application, not a person, and is ignored.
+
+use crate::cleaner::{self, MetadataEntry};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Documents read per scan.
+pub const MAX_DOCUMENTS: usize = 10_000;
+const DOCUMENT_EXTENSIONS: &[&str] = &["docx", "xlsx", "pptx", "pdf"];
+const MAX_DEPTH: usize = 8;
+/// Shorter identities ("jo", "me") would match half the dictionary.
+const MIN_IDENTITY_LEN: usize = 3;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuthorFinding {
+    pub path: String,
+    pub file_type: String,
+    /// Author fields that name the user, e.g. ("Last Modified By", "Jane Doe").
+    pub fields: Vec<MetadataEntry>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorAuditReport {
+    /// The names that were searched for.
+    pub identities: Vec<String>,
+    pub roots: Vec<String>,
+    pub documents_scanned: usize,
+    /// Stopped at `MAX_DOCUMENTS`.
+    pub truncated: bool,
+    pub findings: Vec<AuthorFinding>,
+}
+
+impl AuthorAuditReport {
+    /// The subset of `paths` that were reported by this scan, or every finding when
+    /// `paths` is `None`.
+    pub fn finding_paths(&self, paths: Option<&[String]>) -> Vec<String> {
+        self.findings
+            .iter()
+            .map(|f| &f.path)
+            .filter(|p| paths.is_none_or(|wanted| wanted.contains(p)))
+            .cloned()
+            .collect()
+    }
+}
+
+// ==========================================
+// --- IDENTITIES ---
+// ==========================================
+
+/// Account and full name of the logged-in user, as far as the OS reveals them.
+pub fn local_identities() -> Vec<String> {
+    let mut names: Vec<String> = ["USER", "USERNAME"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .collect();
+
+    #[cfg(target_os = "linux")]
+    if let (Some(user), Ok(passwd)) = (names.first(), std::fs::read_to_string("/etc/passwd")) {
+        // name:x:uid:gid:GECOS:home:shell; the full name is the first GECOS field.
+        if let Some(gecos) = passwd
+            .lines()
+            .map(|l| l.split(':').collect::<Vec<_>>())
+            .find(|f| f.len() > 4 && f[0] == user)
+            .map(|f| f[4].split(',').next().unwrap_or_default().to_string())
+        {
+            names.push(gecos);
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    if let Ok(output) = std::process::Command::new("id").arg("-F").output() {
+        names.push(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+        // The name Office writes into new documents.
+        if let Ok(name) = RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(r"Software\Microsoft\Office\Common\UserInfo")
+            .and_then(|k| k.get_value::<String, _>("UserName"))
+        {
+            names.push(name);
+        }
+    }
+
+    normalize_identities(names)
+}
+
+/// Trims, drops too-short entries and duplicates (case-insensitively).
+pub fn normalize_identities(names: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for name in names {
+        let name = name.trim().to_string();
+        if name.chars().count() >= MIN_IDENTITY_LEN
+            && !result.iter().any(|n| n.eq_ignore_ascii_case(&name))
+        {
+            result.push(name);
+        }
+    }
+    result
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// True if an author field names one of `identities`: every word of the identity
+/// appears as a whole word, in any order ("Doe, Jane" matches "Jane Doe"; "Janet"
+/// does not match "Jane").
+pub fn names_identity(value: &str, identities: &[String]) -> bool {
+    let value_words = words(value);
+    identities.iter().any(|identity| {
+        let wanted = words(identity);
+        !wanted.is_empty() && wanted.iter().all(|w| value_words.contains(w))
+    })
+}
+
+// ==========================================
+// --- SCANNING ---
+// ==========================================
+
+/// Documents and Desktop (the folders users share files from).
+pub fn document_dirs() -> Vec<PathBuf> {
+    #[cfg(not(target_os = "android"))]
+    let dirs: Vec<PathBuf> = directories::UserDirs::new()
+        .map(|d| {
+            [d.document_dir(), d.desktop_dir()]
+                .into_iter()
+                .flatten()
+                .map(Path::to_path_buf)
+                .collect()
+        })
+        .unwrap_or_default();
+    #[cfg(target_os = "android")]
+    let dirs: Vec<PathBuf> = vec![PathBuf::from("/storage/emulated/0/Documents")];
+    dirs.into_iter().filter(|d| d.is_dir()).collect()
+}
+
+fn is_document(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Author fields as reported by the cleaner: PDF `Author`, Office `Creator` and
+/// `Last Modified By`.
+fn author_fields(report: &cleaner::MetadataReport) -> impl Iterator<Item = &MetadataEntry> {
+    let is_pdf = report.file_type.starts_with("PDF");
+    report
+        .raw_tags
+        .iter()
+        .filter(move |t| match t.key.as_str() {
+            "Author" | "Last Modified By" => true,
+            "Creator" => !is_pdf,
+            _ => false,
+        })
+}
+
+/// Reads every document under `roots` and lists those whose author fields name one of
+/// `identities`.
+pub fn scan(roots: &[PathBuf], identities: Vec<String>) -> AuthorAuditReport {
+    let mut documents: Vec<PathBuf> = roots
+        .iter()
+        .flat_map(|root| {
+            WalkDir::new(root)
+                .follow_links(false)
+                .max_depth(MAX_DEPTH)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file() && is_document(e.path()))
+                .map(|e| e.into_path())
+                .take(MAX_DOCUMENTS + 1)
+        })
+        .collect();
+    let truncated = documents.len() > MAX_DOCUMENTS;
+    documents.truncate(MAX_DOCUMENTS);
+
+    let mut findings: Vec<AuthorFinding> = documents
+        .par_iter()
+        .filter_map(|path| {
+            let path = path.to_string_lossy().to_string();
+            let report = cleaner::analyze_file(&path).ok()?;
+            let fields: Vec<MetadataEntry> = author_fields(&report)
+                .filter(|t| names_identity(&t.value, &identities))
+                .cloned()
+                .collect();
+            (!fields.is_empty()).then(|| AuthorFinding {
+                path,
+                file_type: report.file_type.clone(),
+                fields,
+            })
+        })
+        .collect();
+    findings.sort_by(|a, b| a.path.cmp(&b.path));
+
+    AuthorAuditReport {
+        identities,
+        roots: roots
+            .iter()
+            .map(|r| r.to_string_lossy().to_string())
+            .collect(),
+        documents_scanned: documents.len(),
+        truncated,
+        findings,
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_docx(path: &Path, creator: &str, modified_by: &str) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("[Content_Types].xml", options).unwrap();
+        zip.write_all(b"<Types/>").unwrap();
+        zip.start_file("docProps/core.xml", options).unwrap();
+        write!(
+            zip,
+            "<cp:coreProperties><dc:creator>{}</dc:creator>\
+             <cp:lastModifiedBy>{}</cp:lastModifiedBy></cp:coreProperties>",
+            creator, modified_by
+        )
+        .unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_identity_matching() {
+        let ids = normalize_identities(vec![
+            "jdoe".into(),
+            " Jane Doe ".into(),
+            "JDOE".into(),
+            "me".into(),
+        ]);
+        assert_eq!(ids, vec!["jdoe", "Jane Doe"]);
+
+        assert!(names_identity("Jane Doe", &ids));
+        assert!(names_identity("Doe, Jane (Finance)", &ids));
+        assert!(names_identity("CORP\\jdoe", &ids));
+        assert!(!names_identity("Janet Doe", &ids));
+        assert!(!names_identity("John Doe", &ids));
+        assert!(!names_identity("", &ids));
+    }
+
+    #[test]
+    fn test_scan_finds_own_documents() {
+        let dir = std::env::temp_dir().join("qre_author_audit_tests");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("work")).unwrap();
+        write_docx(&dir.join("mine.docx"), "Jane Doe", "Someone Else");
+        write_docx(&dir.join("work/edited.docx"), "Someone Else", "Doe, Jane");
+        write_docx(&dir.join("theirs.docx"), "Someone Else", "Someone Else");
+        std::fs::write(dir.join("notes.txt"), b"Jane Doe").unwrap();
+
+        let report = scan(std::slice::from_ref(&dir), vec!["Jane Doe".to_string()]);
+        assert_eq!(report.documents_scanned, 3);
+        assert_eq!(report.findings.len(), 2);
+        assert_eq!(report.findings[0].fields[0].key, "Creator");
+        assert!(report.findings[1].path.ends_with("edited.docx"));
+        assert_eq!(report.findings[1].fields[0].key, "Last Modified By");
+
+        let mine = dir.join("mine.docx").to_string_lossy().to_string();
+        assert_eq!(report.finding_paths(None).len(), 2);
+        assert_eq!(
+            report.finding_paths(Some(&[mine.clone(), "/elsewhere.docx".to_string()])),
+            vec![mine]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE author_audit.rs ---
@@ -0,0 +1,306 @@
+// --- START OF FILE auto_lock.rs ---
+
+// ==========================================
+// --- AUTO-LOCK ---
+// ==========================================
+// Locks every unlocked vault (the master keys are dropped from `SessionState`, which
+// zeroizes them) when:
+//   - nothing has happened for `idle_minutes`. The UI reports user input through the
+//     `touch_session` command; the idle clock starts when a vault is unlocked;
+//   - the machine went to sleep. The background tick notices that the wall clock moved
+//     much further than one tick interval, which is what a suspend looks like (a large
+//     clock correction does too, and also locks);
+//   - the OS session was locked: Windows session flags (WTS), the console's
+//     `CGSSessionScreenIsLocked` on macOS, logind's `LockedHint` on Linux.
+// The command layer runs the tick (`spawn_auto_lock` in commands/vault.rs) and emits
+// `session-locked` with the reason so the UI can return to the login screen.
+//
+// The settings are app-wide and kept in plaintext next to the keychain
+// (`auto_lock.json`). A missing or unreadable file means the defaults.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+pub const SETTINGS_FILE_NAME: &str = "auto_lock.json";
+/// How often the background thread checks.
+pub const TICK: Duration = Duration::from_secs(5);
+/// A wall-clock step this much longer than a tick means the machine was suspended.
+const SLEEP_GAP: Duration = Duration::from_secs(30);
+const MAX_IDLE_MINUTES: u32 = 24 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct AutoLockSettings {
+    /// Minutes without activity before locking; 0 turns the idle timer off.
+    pub idle_minutes: u32,
+    pub lock_on_sleep: bool,
+    pub lock_on_screen_lock: bool,
+}
+
+impl Default for AutoLockSettings {
+    /// The idle timer is off until the user picks a period: a UI that does not report
+    /// activity yet would otherwise be locked in the middle of use.
+    fn default() -> Self {
+        Self {
+            idle_minutes: 0,
+            lock_on_sleep: true,
+            lock_on_screen_lock: true,
+        }
+    }
+}
+
+impl AutoLockSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.idle_minutes > MAX_IDLE_MINUTES {
+            return Err(anyhow!(
+                "Idle time must be at most {} minutes",
+                MAX_IDLE_MINUTES
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn load_settings(dir: &Path) -> Result<AutoLockSettings> {
+    let path = dir.join(SETTINGS_FILE_NAME);
+    if !path.exists() {
+        return Ok(AutoLockSettings::default());
+    }
+    let data = fs::read(&path)?;
+    serde_json::from_slice(&data).map_err(|_| anyhow!("Auto-lock settings are corrupted"))
+}
+
+pub fn save_settings(dir: &Path, settings: &AutoLockSettings) -> Result<()> {
+    let path = dir.join(SETTINGS_FILE_NAME);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(settings)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Why the vaults were locked; the payload of the `session-locked` event.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReason {
+    Idle,
+    Sleep,
+    ScreenLocked,
+}
+
+impl LockReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LockReason::Idle => "idle",
+            LockReason::Sleep => "sleep",
+            LockReason::ScreenLocked => "screen_locked",
+        }
+    }
+}
+
+// ==========================================
+// --- ACTIVITY CLOCK ---
+// ==========================================
+
+/// The idle clock and sleep detector kept in `SessionState`.
+pub struct AutoLock {
+    pub settings: AutoLockSettings,
+    last_activity: Instant,
+    last_tick: SystemTime,
+}
+
+impl AutoLock {
+    pub fn new(settings: AutoLockSettings) -> Self {
+        Self {
+            settings,
+            last_activity: Instant::now(),
+            last_tick: SystemTime::now(),
+        }
+    }
+
+    pub fn touch(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Called once per `TICK`, whether or not a vault is unlocked, so the sleep check
+    /// always compares against the previous tick. With nothing unlocked the idle clock
+    /// is held at `now`, so it starts counting at the unlock. The screen lock is
+    /// checked by the caller: it is a system query, only worth making while unlocked.
+    pub fn tick(
+        &mut self,
+        unlocked: bool,
+        now: Instant,
+        wall_now: SystemTime,
+    ) -> Option<LockReason> {
+        // A clock stepped backwards reads as no gap.
+        let slept = wall_now
+            .duration_since(self.last_tick)
+            .is_ok_and(|gap| gap > TICK + SLEEP_GAP);
+        self.last_tick = wall_now;
+
+        if !unlocked {
+            self.last_activity = now;
+            return None;
+        }
+        if slept && self.settings.lock_on_sleep {
+            return Some(LockReason::Sleep);
+        }
+        let idle = Duration::from_secs(u64::from(self.settings.idle_minutes) * 60);
+        if self.settings.idle_minutes > 0
+            && now.saturating_duration_since(self.last_activity) >= idle
+        {
+            return Some(LockReason::Idle);
+        }
+        None
+    }
+}
+
+// ==========================================
+// --- SCREEN LOCK ---
+// ==========================================
+
+/// Whether the OS session is locked right now. `false` whenever it cannot be told.
+pub fn screen_locked() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::RemoteDesktop::{
+            WTSFreeMemory, WTSQuerySessionInformationW, WTSSessionInfoEx, WTSINFOEXW,
+            WTS_CURRENT_SERVER_HANDLE, WTS_CURRENT_SESSION, WTS_SESSIONSTATE_LOCK,
+        };
+        let mut buffer: *mut u16 = std::ptr::null_mut();
+        let mut bytes = 0u32;
+        // SAFETY: on success the API allocates `buffer` (a WTSINFOEXW for this info
+        // class), which is read once and then released with WTSFreeMemory.
+        unsafe {
+            if WTSQuerySessionInformationW(
+                WTS_CURRENT_SERVER_HANDLE,
+                WTS_CURRENT_SESSION,
+                WTSSessionInfoEx,
+                &mut buffer,
+                &mut bytes,
+            ) == 0
+                || buffer.is_null()
+            {
+                return false;
+            }
+            let info = &*(buffer as *const WTSINFOEXW);
+            let locked = info.Level == 1
+                && info.Data.WTSInfoExLevel1.SessionFlags == WTS_SESSIONSTATE_LOCK as i32;
+            WTSFreeMemory(buffer.cast());
+            locked
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // The console user's entry in the I/O registry root carries the flag.
+        std::process::Command::new("ioreg")
+            .args(["-n", "Root", "-d1"])
+            .output()
+            .map(|out| {
+                String::from_utf8_lossy(&out.stdout).contains("\"CGSSessionScreenIsLocked\"=Yes")
+            })
+            .unwrap_or(false)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        std::process::Command::new("loginctl")
+            .args(["show-session", &session, "-p", "LockedHint", "--value"])
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim() == "yes")
+            .unwrap_or(false)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_with(settings: AutoLockSettings, start: Instant, wall: SystemTime) -> AutoLock {
+        AutoLock {
+            settings,
+            last_activity: start,
+            last_tick: wall,
+        }
+    }
+
+    #[test]
+    fn test_idle_timer_counts_from_unlock_and_touch() {
+        let start = Instant::now();
+        let wall = SystemTime::now();
+        let settings = AutoLockSettings {
+            idle_minutes: 1,
+            ..Default::default()
+        };
+        let mut lock = lock_with(settings, start, wall);
+
+        // Locked for ten minutes: the idle clock does not run.
+        let at = |secs: u64| {
+            (
+                start + Duration::from_secs(secs),
+                wall + Duration::from_secs(secs),
+            )
+        };
+        let (now, wall_now) = at(600);
+        assert_eq!(lock.tick(false, now, wall_now), None);
+        let (now, wall_now) = at(605);
+        assert_eq!(lock.tick(true, now, wall_now), None);
+
+        // Ticks stay closer together than the sleep gap throughout.
+        let (now, wall_now) = at(630);
+        assert_eq!(lock.tick(true, now, wall_now), None);
+        lock.touch(start + Duration::from_secs(640));
+        for secs in [655, 680] {
+            let (now, wall_now) = at(secs);
+            assert_eq!(lock.tick(true, now, wall_now), None);
+        }
+        let (now, wall_now) = at(700);
+        assert_eq!(lock.tick(true, now, wall_now), Some(LockReason::Idle));
+
+        lock.settings.idle_minutes = 0;
+        let (now, wall_now) = at(705);
+        assert_eq!(lock.tick(true, now, wall_now), None);
+        assert!(AutoLockSettings {
+            idle_minutes: MAX_IDLE_MINUTES + 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_wall_clock_jump_means_sleep() {
+        let start = Instant::now();
+        let wall = SystemTime::now();
+        let mut lock = lock_with(AutoLockSettings::default(), start, wall);
+
+        // The monotonic clock barely moved while the wall clock skipped an hour.
+        let now = start + TICK;
+        assert_eq!(
+            lock.tick(true, now, wall + Duration::from_secs(3600)),
+            Some(LockReason::Sleep)
+        );
+        // An ordinary tick, and a clock set backwards, are not sleep.
+        let wall = wall + Duration::from_secs(3600);
+        assert_eq!(lock.tick(true, now + TICK, wall + TICK), None);
+        assert_eq!(
+            lock.tick(true, now + TICK * 2, wall - Duration::from_secs(600)),
+            None
+        );
+
+        lock.settings.lock_on_sleep = false;
+        let wall = wall - Duration::from_secs(600);
+        assert_eq!(
+            lock.tick(true, now + TICK * 3, wall + Duration::from_secs(3600)),
+            None
+        );
+    }
+}
+
+// --- END OF FILE auto_lock.rs ---
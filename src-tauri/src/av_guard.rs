@@ -0,0 +1,200 @@
+// --- START OF FILE av_guard.rs ---
+
+// ==========================================
+// --- ANTIVIRUS INTERFERENCE ---
+// ==========================================
+// On-access scanners (Windows Defender above all) open every file we create or touch.
+// While the scan runs, our own open/rename/delete fails with a sharing or lock
+// violation, or simply stalls. Shredding and batch encryption hit this constantly.
+//
+// `with_retry` wraps a single file-system call and retries it with a short backoff
+// when the error looks like "someone else holds this file". It also records retried
+// and unusually slow calls so the UI can explain the slowdown and suggest an
+// exclusion for the working folder (`interference_report`).
+//
+// SAFETY OF RETRYING: only calls that either fully happen or not at all are wrapped —
+// opening/creating a file, rename, delete. Stream reads and writes are never retried:
+// a partially written chunk cannot be replayed safely. Retries are bounded (under
+// two seconds in total), so a file that is genuinely locked or forbidden still fails,
+// just slightly later, with a message that names the likely cause.
+
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Delays between attempts; the total stays below two seconds.
+const BACKOFF_MS: [u64; 5] = [50, 100, 200, 400, 800];
+/// A metadata-level call (open, rename, delete) taking longer than this is "slow".
+const SLOW_OP_THRESHOLD: Duration = Duration::from_secs(2);
+/// Retried operations after which the report suggests interference.
+const SUSPECT_RETRIES: u64 = 3;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct InterferenceReport {
+    /// Operations that succeeded only after at least one retry.
+    pub retried_ops: u64,
+    /// Operations that still failed after all retries.
+    pub failed_ops: u64,
+    pub slow_ops: u64,
+    pub last_path: Option<String>,
+    pub last_operation: Option<String>,
+    /// Unix seconds of the last recorded event.
+    pub last_seen: Option<u64>,
+    pub suspected: bool,
+    pub warning: Option<String>,
+}
+
+static STATS: Mutex<InterferenceReport> = Mutex::new(InterferenceReport {
+    retried_ops: 0,
+    failed_ops: 0,
+    slow_ops: 0,
+    last_path: None,
+    last_operation: None,
+    last_seen: None,
+    suspected: false,
+    warning: None,
+});
+
+/// Errors an on-access scanner produces while it holds a file open.
+pub fn is_transient_lock_error(e: &io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        // ERROR_ACCESS_DENIED (pending delete), ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        if matches!(e.raw_os_error(), Some(5) | Some(32) | Some(33)) {
+            return true;
+        }
+    }
+    e.kind() == io::ErrorKind::ResourceBusy
+}
+
+fn record(operation: &str, path: &Path, update: impl FnOnce(&mut InterferenceReport)) {
+    if let Ok(mut stats) = STATS.lock() {
+        update(&mut stats);
+        stats.last_path = Some(path.to_string_lossy().to_string());
+        stats.last_operation = Some(operation.to_string());
+        stats.last_seen = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+    }
+}
+
+/// Runs `f`, retrying with backoff while it fails with a transient lock error.
+/// `operation` names the call for the report ("open", "rename", ...).
+pub fn with_retry<T>(
+    operation: &str,
+    path: &Path,
+    mut f: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let started = Instant::now();
+    let mut attempt = 0;
+    let result = loop {
+        match f() {
+            Err(e) if is_transient_lock_error(&e) && attempt < BACKOFF_MS.len() => {
+                std::thread::sleep(Duration::from_millis(BACKOFF_MS[attempt]));
+                attempt += 1;
+            }
+            other => break other,
+        }
+    };
+
+    match &result {
+        Err(e) if is_transient_lock_error(e) => {
+            record(operation, path, |s| s.failed_ops += 1);
+            return Err(io::Error::new(
+                e.kind(),
+                format!(
+                    "{} (still locked after {} retries; an antivirus scanner may be holding the file)",
+                    e, attempt
+                ),
+            ));
+        }
+        Ok(_) if attempt > 0 => record(operation, path, |s| s.retried_ops += 1),
+        Ok(_) if started.elapsed() > SLOW_OP_THRESHOLD => {
+            record(operation, path, |s| s.slow_ops += 1)
+        }
+        _ => {}
+    }
+    result
+}
+
+/// Counters since start-up (or the last `reset_interference`), with a warning when
+/// the pattern points to an on-access scanner.
+pub fn interference_report() -> InterferenceReport {
+    let mut report = STATS.lock().map(|s| s.clone()).unwrap_or_default();
+    report.suspected =
+        report.failed_ops > 0 || report.retried_ops + report.slow_ops >= SUSPECT_RETRIES;
+    if report.suspected {
+        report.warning = Some(if cfg!(windows) {
+            "File operations are being blocked or slowed, most likely by Windows Defender or \
+             another antivirus scanning each file. Consider adding the working folder as an \
+             exclusion, or allowing QRE under Controlled Folder Access."
+                .to_string()
+        } else {
+            "File operations are being blocked or slowed, most likely by an antivirus or \
+             backup tool scanning each file. Consider excluding the working folder."
+                .to_string()
+        });
+    }
+    report
+}
+
+pub fn reset_interference() {
+    if let Ok(mut stats) = STATS.lock() {
+        *stats = InterferenceReport::default();
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_errors_are_retried() {
+        let before = interference_report().retried_ops;
+        let mut calls = 0;
+        let result = with_retry("open", Path::new("/tmp/held.bin"), || {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from(io::ErrorKind::ResourceBusy))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert!(interference_report().retried_ops > before);
+    }
+
+    #[test]
+    fn test_other_errors_fail_immediately() {
+        let mut calls = 0;
+        let result: io::Result<()> = with_retry("open", Path::new("/missing"), || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_persistent_lock_fails_with_hint() {
+        let mut calls = 0;
+        let result: io::Result<()> = with_retry("delete", Path::new("/tmp/locked.bin"), || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::ResourceBusy))
+        });
+        let err = result.unwrap_err();
+        assert_eq!(calls, BACKOFF_MS.len() + 1);
+        assert!(err.to_string().contains("antivirus"));
+        let report = interference_report();
+        assert!(report.suspected && report.warning.is_some());
+    }
+}
+
+// --- END OF FILE av_guard.rs ---
@@ -0,0 +1,433 @@
+// --- START OF FILE batch_journal.rs ---
+
+// ==========================================
+// --- BATCH JOB JOURNAL ---
+// ==========================================
+// If the app crashed halfway through a 200-file batch, nothing recorded which files had
+// been done, and the file being worked on could be left half-written. `lock_file` and
+// `unlock_file` now keep a journal for each run in `<app data>/batch_jobs/<id>.json`,
+// rewritten atomically as every item starts and ends:
+//
+//   items         every input of the run, in order
+//   finished      the inputs done so far and their outcome
+//   in_progress   the input being worked on and the partial outputs it has created
+//   options       the run's settings, so it can be repeated for the rest
+//
+// A run that ends normally deletes its journal, so a journal that is not owned by a
+// running batch belongs to an interrupted one. The UI lists them after a restart and
+// either resumes the remaining items or discards the run; both clean up the partial
+// outputs of the interrupted item, except that a resume keeps a partial container that
+// has a checkpoint (see crypto_stream.rs) and carries on from it.
+//
+// Journals hold paths and options only, never keys, keyfiles or key shares.
+
+use crate::file_lock;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const JOURNAL_DIR_NAME: &str = "batch_jobs";
+
+/// IDs of the journals owned by a batch running in this process.
+static ACTIVE: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchKind {
+    Lock,
+    Unlock,
+}
+
+/// What a partial output is, which decides how it is cleaned up.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialKind {
+    /// A container that may be continued from its checkpoint on resume. Deleted otherwise.
+    Resumable,
+    /// A container or temporary file that cannot be continued: deleted.
+    Incomplete,
+    /// A folder of decrypted output: shredded.
+    Plaintext,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartialOutput {
+    pub path: String,
+    pub kind: PartialKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FinishedItem {
+    pub input: String,
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InProgress {
+    pub input: String,
+    #[serde(default)]
+    pub partial_outputs: Vec<PartialOutput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchJournal {
+    pub id: String,
+    pub kind: BatchKind,
+    pub started_at: i64,
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub finished: Vec<FinishedItem>,
+    #[serde(default)]
+    pub in_progress: Option<InProgress>,
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+/// An interrupted run, as shown to the user after a restart.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct InterruptedBatch {
+    pub id: String,
+    pub kind: BatchKind,
+    pub started_at: i64,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Inputs not finished, the interrupted one first among them.
+    pub remaining: Vec<String>,
+    pub interrupted_item: Option<String>,
+    pub partial_outputs: Vec<String>,
+}
+
+impl BatchJournal {
+    /// Inputs not finished yet, in their original order. An input listed twice needs
+    /// two finished records.
+    pub fn remaining(&self) -> Vec<String> {
+        let mut done: Vec<&str> = self.finished.iter().map(|f| f.input.as_str()).collect();
+        self.items
+            .iter()
+            .filter(|item| match done.iter().position(|d| d == item) {
+                Some(i) => {
+                    done.swap_remove(i);
+                    false
+                }
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn summary(&self) -> InterruptedBatch {
+        let succeeded = self.finished.iter().filter(|f| f.success).count();
+        InterruptedBatch {
+            id: self.id.clone(),
+            kind: self.kind,
+            started_at: self.started_at,
+            total: self.items.len(),
+            succeeded,
+            failed: self.finished.len() - succeeded,
+            remaining: self.remaining(),
+            interrupted_item: self.in_progress.as_ref().map(|p| p.input.clone()),
+            partial_outputs: self
+                .in_progress
+                .iter()
+                .flat_map(|p| &p.partial_outputs)
+                .map(|o| o.path.clone())
+                .collect(),
+        }
+    }
+
+    fn path(dir: &Path, id: &str) -> Result<PathBuf> {
+        uuid::Uuid::parse_str(id).map_err(|_| anyhow!("Invalid batch ID '{}'.", id))?;
+        Ok(dir.join(format!("{}.json", id)))
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        // Only the batch owning the journal writes it, so no file lock is needed.
+        file_lock::write_atomic_locked(&Self::path(dir, &self.id)?, |file| {
+            file.write_all(&json)
+                .context("Failed to write batch journal")
+        })
+    }
+
+    pub fn load(dir: &Path, id: &str) -> Result<Self> {
+        let bytes = std::fs::read(Self::path(dir, id)?)
+            .map_err(|_| anyhow!("That batch is no longer recorded."))?;
+        serde_json::from_slice(&bytes).context("The batch journal is damaged")
+    }
+
+    /// Journals of interrupted runs, oldest first. Unreadable journals are skipped.
+    pub fn list_interrupted(dir: &Path) -> Vec<Self> {
+        let active = active_ids();
+        let mut journals: Vec<Self> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let id = name.strip_suffix(".json")?;
+                if active.contains(id) {
+                    return None;
+                }
+                Self::load(dir, id).ok()
+            })
+            .collect();
+        journals.sort_by_key(|j| j.started_at);
+        journals
+    }
+
+    /// Removes the journal of an interrupted run. Its partial outputs are the caller's.
+    pub fn delete(dir: &Path, id: &str) -> Result<()> {
+        if active_ids().contains(id) {
+            return Err(anyhow!("That batch is still running."));
+        }
+        std::fs::remove_file(Self::path(dir, id)?).context("Failed to delete batch journal")
+    }
+}
+
+fn active_ids() -> HashSet<String> {
+    ACTIVE
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// The journal of a running batch. Dropping it without `complete` leaves the journal on
+/// disk (the run counts as interrupted) and frees it to be listed and resumed.
+pub struct ActiveBatch {
+    journal: BatchJournal,
+    dir: PathBuf,
+}
+
+impl ActiveBatch {
+    pub fn start(
+        dir: &Path,
+        kind: BatchKind,
+        items: Vec<String>,
+        options: serde_json::Value,
+        now: i64,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(dir).context("Failed to create batch journal folder")?;
+        let journal = BatchJournal {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            started_at: now,
+            items,
+            finished: Vec::new(),
+            in_progress: None,
+            options,
+        };
+        Self::claim(journal, dir)
+    }
+
+    /// Takes over an interrupted run. Returns the batch and the partial outputs of the
+    /// interrupted item to clean up: all of them except those `can_continue` accepts.
+    pub fn resume(
+        dir: &Path,
+        id: &str,
+        can_continue: impl Fn(&PartialOutput) -> bool,
+    ) -> Result<(Self, Vec<PartialOutput>)> {
+        let mut journal = BatchJournal::load(dir, id)?;
+        let mut discard = Vec::new();
+        if let Some(progress) = journal.in_progress.as_mut() {
+            let (keep, drop): (Vec<_>, Vec<_>) =
+                progress.partial_outputs.drain(..).partition(&can_continue);
+            progress.partial_outputs = keep;
+            discard = drop;
+        }
+        Ok((Self::claim(journal, dir)?, discard))
+    }
+
+    fn claim(journal: BatchJournal, dir: &Path) -> Result<Self> {
+        let mut active = ACTIVE.lock().unwrap_or_else(|p| p.into_inner());
+        if !active
+            .get_or_insert_with(HashSet::new)
+            .insert(journal.id.clone())
+        {
+            return Err(anyhow!("That batch is already running."));
+        }
+        drop(active);
+        let batch = Self {
+            journal,
+            dir: dir.to_path_buf(),
+        };
+        batch.journal.save(&batch.dir)?;
+        Ok(batch)
+    }
+
+    pub fn journal(&self) -> &BatchJournal {
+        &self.journal
+    }
+
+    /// Marks `input` as the item being worked on. An item still in progress (one that
+    /// was resumed) keeps its partial outputs.
+    pub fn begin_item(&mut self, input: &str) -> Result<()> {
+        match &self.journal.in_progress {
+            Some(progress) if progress.input == input => return Ok(()),
+            _ => {}
+        }
+        self.journal.in_progress = Some(InProgress {
+            input: input.to_string(),
+            partial_outputs: Vec::new(),
+        });
+        self.journal.save(&self.dir)
+    }
+
+    /// Records an output of the current item before it is written.
+    pub fn add_partial(&mut self, path: &Path, kind: PartialKind) -> Result<()> {
+        let Some(progress) = self.journal.in_progress.as_mut() else {
+            return Ok(());
+        };
+        let path = path.to_string_lossy().to_string();
+        progress.partial_outputs.retain(|o| o.path != path);
+        progress.partial_outputs.push(PartialOutput { path, kind });
+        self.journal.save(&self.dir)
+    }
+
+    /// Records the outcome of the current item.
+    pub fn finish_item(&mut self, success: bool, message: &str) -> Result<()> {
+        let Some(progress) = self.journal.in_progress.take() else {
+            return Ok(());
+        };
+        self.journal.finished.push(FinishedItem {
+            input: progress.input,
+            success,
+            message: message.to_string(),
+        });
+        self.journal.save(&self.dir)
+    }
+
+    /// The run finished: its journal is deleted.
+    pub fn complete(self) -> Result<()> {
+        std::fs::remove_file(BatchJournal::path(&self.dir, &self.journal.id)?)
+            .context("Failed to delete batch journal")
+    }
+}
+
+impl Drop for ActiveBatch {
+    fn drop(&mut self) {
+        let mut active = ACTIVE.lock().unwrap_or_else(|p| p.into_inner());
+        if let Some(ids) = active.as_mut() {
+            ids.remove(&self.journal.id);
+        }
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("qre_batch_journal_tests")
+            .join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_interrupted_run_is_listed_and_resumed() {
+        let dir = temp_dir("interrupted");
+        let items = vec![
+            "a.txt".to_string(),
+            "b.txt".to_string(),
+            "c.txt".to_string(),
+        ];
+        let mut batch = ActiveBatch::start(
+            &dir,
+            BatchKind::Lock,
+            items,
+            serde_json::json!({ "compressionMode": "auto" }),
+            100,
+        )
+        .unwrap();
+        let id = batch.journal().id.clone();
+
+        batch.begin_item("a.txt").unwrap();
+        batch.finish_item(true, "Locked").unwrap();
+        batch.begin_item("b.txt").unwrap();
+        batch
+            .add_partial(Path::new("b.txt.qre"), PartialKind::Resumable)
+            .unwrap();
+        batch
+            .add_partial(Path::new("b.zip"), PartialKind::Incomplete)
+            .unwrap();
+
+        // Still running: not offered for resume.
+        assert!(BatchJournal::list_interrupted(&dir).is_empty());
+        assert!(BatchJournal::delete(&dir, &id).is_err());
+        drop(batch); // the crash
+
+        let listed = BatchJournal::list_interrupted(&dir);
+        assert_eq!(listed.len(), 1);
+        let summary = listed[0].summary();
+        assert_eq!(
+            (summary.total, summary.succeeded, summary.failed),
+            (3, 1, 0)
+        );
+        assert_eq!(summary.remaining, ["b.txt", "c.txt"]);
+        assert_eq!(summary.interrupted_item.as_deref(), Some("b.txt"));
+        assert_eq!(summary.partial_outputs, ["b.txt.qre", "b.zip"]);
+        assert_eq!(listed[0].options["compressionMode"], "auto");
+
+        // A resume cleans up all but the resumable container, which it carries on with.
+        let resumable = |o: &PartialOutput| o.kind == PartialKind::Resumable;
+        let (mut batch, discard) = ActiveBatch::resume(&dir, &id, resumable).unwrap();
+        assert_eq!(discard.len(), 1);
+        assert_eq!(discard[0].path, "b.zip");
+        assert!(ActiveBatch::resume(&dir, &id, resumable).is_err());
+        batch.begin_item("b.txt").unwrap();
+        assert_eq!(
+            batch
+                .journal()
+                .in_progress
+                .as_ref()
+                .unwrap()
+                .partial_outputs
+                .len(),
+            1
+        );
+        batch.finish_item(false, "Wrong keyfile").unwrap();
+        batch.begin_item("c.txt").unwrap();
+        batch.finish_item(true, "Locked").unwrap();
+        assert!(batch.journal().remaining().is_empty());
+
+        batch.complete().unwrap();
+        assert!(BatchJournal::list_interrupted(&dir).is_empty());
+        assert!(BatchJournal::load(&dir, &id).is_err());
+    }
+
+    #[test]
+    fn test_repeated_inputs_and_bad_ids() {
+        let journal = BatchJournal {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: BatchKind::Unlock,
+            started_at: 0,
+            items: vec!["x".into(), "y".into(), "x".into()],
+            finished: vec![FinishedItem {
+                input: "x".into(),
+                success: false,
+                message: "Invalid file".into(),
+            }],
+            in_progress: None,
+            options: serde_json::Value::Null,
+        };
+        assert_eq!(journal.remaining(), ["y", "x"]);
+        assert_eq!(journal.summary().failed, 1);
+
+        let dir = temp_dir("bad_ids");
+        assert!(BatchJournal::load(&dir, "../keychain").is_err());
+        assert!(BatchJournal::delete(&dir, "../keychain").is_err());
+    }
+}
+
+// --- END OF FILE batch_journal.rs ---
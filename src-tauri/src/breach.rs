@@ -3,6 +3,7 @@
 use anyhow::{anyhow, Result};
 use reqwest::Client; // Asynchronous HTTP client for external API calls
 use serde::Serialize;
+use std::collections::HashMap;
 use std::time::Duration;
 
 /// The structure returned to the frontend after a password breach check.
@@ -13,7 +14,7 @@ pub struct BreachResult {
 }
 
 /// The structure returned to the frontend after an IP address check.
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct IpResult {
     pub ip: String,           // The public IPv4 or IPv6 address
     pub is_warp: bool, // True if the user is currently routing traffic through Cloudflare WARP (VPN)
@@ -49,11 +50,32 @@ pub async fn check_pwned_by_prefix(prefix: &str, suffix: &str) -> Result<BreachR
         return Err(anyhow!("Invalid suffix: must be 35 hex characters"));
     }
 
+    // 2. Fetch every breached suffix under the prefix.
+    let range = fetch_pwned_range(prefix).await?;
+
+    // 3. Local Suffix Matching
+    // If the suffix from the API matches our local secret suffix, the password is breached.
+    // If it is not in the list, the password is safe.
+    let count = range.get(&suffix.to_uppercase()).copied().unwrap_or(0);
+    Ok(BreachResult {
+        found: count > 0,
+        count,
+    })
+}
+
+/// Downloads the k-anonymity range for a 5-character SHA-1 `prefix`: every breached
+/// suffix (uppercase hex) with its count. Batch checks (see password_audit.rs) use it
+/// to answer all passwords sharing a prefix with one request.
+pub async fn fetch_pwned_range(prefix: &str) -> Result<HashMap<String, u64>> {
+    if prefix.len() != 5 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid prefix: must be 5 hex characters"));
+    }
+
     // Append the 5-character prefix to the k-Anonymity API endpoint
     let url = format!("https://api.pwnedpasswords.com/range/{}", prefix);
     let client = Client::new();
 
-    // 2. Execute the HTTP Request
+    // Execute the HTTP Request
     let response = client
         .get(&url)
         // HIBP strictly requires a User-Agent header identifying the app consuming the API
@@ -63,7 +85,7 @@ pub async fn check_pwned_by_prefix(prefix: &str, suffix: &str) -> Result<BreachR
         .send()
         .await?;
 
-    // 3. Handle specific API Errors
+    // Handle specific API Errors
     // HTTP 429 means we are querying the API too quickly
     if response.status().as_u16() == 429 {
         return Err(anyhow!(
@@ -76,36 +98,98 @@ pub async fn check_pwned_by_prefix(prefix: &str, suffix: &str) -> Result<BreachR
         return Err(anyhow!("HIBP API error: {}", response.status()));
     }
 
-    // 4. Parse the Response
+    // Parse the Response
     let text = response.text().await?;
+    Ok(parse_pwned_range(&text))
+}
 
-    // The response is a newline-separated list formatted as `SUFFIX:COUNT`
-    // Example: `0018A45C4D1DEF81644B54AB7F969B88D65:1`
+/// The response is a newline-separated list formatted as `SUFFIX:COUNT`
+/// Example: `0018A45C4D1DEF81644B54AB7F969B88D65:1`
+fn parse_pwned_range(text: &str) -> HashMap<String, u64> {
+    let mut range = HashMap::new();
     for line in text.lines() {
-        let parts: Vec<&str> = line.split(':').collect();
+        let parts: Vec<&str> = line.trim().split(':').collect();
 
         if parts.len() != 2 {
-            // Malformed line - log warning to standard error and continue to the next line safely
-            eprintln!("Warning: Malformed HIBP response line: {}", line);
+            // Malformed line - log a warning and continue to the next line safely
+            tracing::warn!("Malformed HIBP response line: {}", line);
             continue;
         }
 
-        // 5. Local Suffix Matching
-        // If the suffix from the API matches our local secret suffix, the password is breached.
-        if parts[0].to_uppercase() == suffix.to_uppercase() {
-            let count = parts[1]
-                .parse::<u64>()
-                .map_err(|_| anyhow!("Invalid count in HIBP response"))?;
+        let Ok(count) = parts[1].parse::<u64>() else {
+            tracing::warn!("Invalid count in HIBP response line: {}", line);
+            continue;
+        };
+        range.insert(parts[0].to_uppercase(), count);
+    }
+    range
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// EMAIL BREACH CHECK (HIBP v3 "breachedaccount")
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Returns the names of the breaches an email address appears in.
+///
+/// Unlike the password range API, this endpoint is keyed and rate limited per key,
+/// so callers are expected to space their requests (see `breach_monitor.rs`).
+/// A 404 from HIBP is the normal "not pwned" answer, not an error.
+pub async fn check_email_breaches(email: &str, api_key: &str) -> Result<Vec<String>> {
+    let email = email.trim();
+    if email.is_empty() || !email.contains('@') || email.len() > 254 {
+        return Err(anyhow!("Invalid email address"));
+    }
+    if api_key.trim().is_empty() {
+        return Err(anyhow!("An HIBP API key is required for email checks"));
+    }
+
+    #[derive(serde::Deserialize)]
+    struct BreachName {
+        #[serde(rename = "Name")]
+        name: String,
+    }
 
-            return Ok(BreachResult { found: true, count });
+    let url = format!(
+        "https://haveibeenpwned.com/api/v3/breachedaccount/{}?truncateResponse=true",
+        url_encode_path(email)
+    );
+    let response = Client::new()
+        .get(&url)
+        .header("User-Agent", "QRE-Privacy-Toolkit/1.0")
+        .header("hibp-api-key", api_key.trim())
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?;
+
+    match response.status().as_u16() {
+        404 => return Ok(Vec::new()),
+        401 => return Err(anyhow!("HIBP rejected the API key.")),
+        429 => {
+            return Err(anyhow!(
+                "HIBP rate limit exceeded. Please wait a moment and try again."
+            ))
         }
+        _ => {}
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("HIBP API error: {}", response.status()));
     }
 
-    // If we loop through the entire list and find no match, the password is safe.
-    Ok(BreachResult {
-        found: false,
-        count: 0,
-    })
+    let breaches: Vec<BreachName> = response.json().await?;
+    Ok(breaches.into_iter().map(|b| b.name).collect())
+}
+
+/// Percent-encodes everything except unreserved characters (RFC 3986), for use in a path segment.
+fn url_encode_path(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -122,7 +206,7 @@ pub async fn get_public_ip() -> Result<IpResult> {
     match get_ip_cloudflare().await {
         Ok(res) => return Ok(res),
         Err(e) => {
-            eprintln!("Cloudflare IP check failed: {}", e);
+            tracing::warn!("Cloudflare IP check failed: {}", e);
         }
     }
 
@@ -130,7 +214,7 @@ pub async fn get_public_ip() -> Result<IpResult> {
     match get_ip_ipify().await {
         Ok(res) => return Ok(res),
         Err(e) => {
-            eprintln!("ipify IP check failed: {}", e);
+            tracing::warn!("ipify IP check failed: {}", e);
         }
     }
 
@@ -239,6 +323,32 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_parse_pwned_range() {
+        let range = parse_pwned_range(
+            "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+             00d4f6e8fa6eecad2a3aa415eec418d38ec:2\r\n\
+             garbage\r\n\
+             011053FD0102E94D6AE2F8B83D76FAF94F6:x\r\n",
+        );
+        assert_eq!(range.len(), 2);
+        assert_eq!(range["0018A45C4D1DEF81644B54AB7F969B88D65"], 1);
+        assert_eq!(range["00D4F6E8FA6EECAD2A3AA415EEC418D38EC"], 2);
+    }
+
+    #[test]
+    fn test_check_email_requires_key_and_address() {
+        tauri::async_runtime::block_on(async {
+            assert!(check_email_breaches("not-an-email", "key").await.is_err());
+            assert!(check_email_breaches("a@b.com", "  ").await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_url_encode_path() {
+        assert_eq!(url_encode_path("a+b@x.com"), "a%2Bb%40x.com");
+    }
+
     // --- Integration Tests (Requires Internet) ---
 
     #[test]
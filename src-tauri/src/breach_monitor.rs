@@ -0,0 +1,499 @@
+// --- START OF FILE breach_monitor.rs ---
+
+// ==========================================
+// --- BACKGROUND BREACH MONITORING ---
+// ==========================================
+// Periodically re-checks the password vault against HaveIBeenPwned:
+//   * Passwords via the k-anonymity range API (`breach::check_pwned_by_prefix`).
+//     Each distinct password is queried once per cycle, however many entries share it.
+//   * Email addresses (explicitly monitored ones + usernames that look like emails)
+//     via the keyed "breachedaccount" API — only when the user supplied an API key.
+//
+// Results live in an encrypted alert history (`breach_alerts.qre`, same V4 container
+// as the other vaults). A `seen` set of fingerprints makes alerts fire only once per
+// (entry, password) or (email, breach) pair, so re-running a check never spams the user.
+//
+// Network behaviour is deliberately conservative: nothing is sent while offline mode
+// is on, requests are spaced out, and the first rate-limit response ends the cycle.
+
+use crate::breach;
+use crate::passwords::PasswordVault;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+pub const ALERTS_FILE_NAME: &str = "breach_alerts.qre";
+
+/// Never check more often than this, whatever the settings say.
+pub const MIN_INTERVAL_HOURS: u32 = 6;
+/// Oldest acknowledged alerts are dropped beyond this many entries.
+const MAX_ALERTS: usize = 500;
+/// Courtesy delay between password range requests (the API itself is not rate limited).
+const PASSWORD_REQUEST_GAP: Duration = Duration::from_millis(1500);
+/// The lowest HIBP subscription allows 10 requests per minute.
+const EMAIL_REQUEST_GAP: Duration = Duration::from_millis(6500);
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MonitorSettings {
+    pub enabled: bool,
+    pub interval_hours: u32,
+    /// While set, no network request is made — neither scheduled nor manual.
+    pub offline_mode: bool,
+    pub check_passwords: bool,
+    pub monitored_emails: Vec<String>,
+    /// Required for email checks; stored only inside the encrypted history.
+    pub hibp_api_key: Option<String>,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: 24,
+            offline_mode: false,
+            check_passwords: true,
+            monitored_emails: Vec::new(),
+            hibp_api_key: None,
+        }
+    }
+}
+
+impl MonitorSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_hours < MIN_INTERVAL_HOURS {
+            return Err(format!(
+                "Check interval must be at least {} hours.",
+                MIN_INTERVAL_HOURS
+            ));
+        }
+        if let Some(bad) = self.monitored_emails.iter().find(|e| !looks_like_email(e)) {
+            return Err(format!("'{}' is not a valid email address.", bad));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    Password,
+    Email,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BreachAlert {
+    pub id: String,
+    pub kind: AlertKind,
+    /// Service name for password alerts, the email address for email alerts.
+    pub subject: String,
+    pub entry_id: Option<String>,
+    pub breach_name: Option<String>,
+    /// How often the password was seen in breaches (0 for email alerts).
+    pub count: u64,
+    pub detected_at: i64,
+    #[serde(default)]
+    pub acknowledged: bool,
+}
+
+/// Root of `breach_alerts.qre`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BreachMonitorStore {
+    pub settings: MonitorSettings,
+    pub last_check_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub alerts: Vec<BreachAlert>,
+    /// Fingerprints of findings that already produced an alert.
+    seen: BTreeSet<String>,
+}
+
+/// What the frontend gets: everything except the internal `seen` set.
+#[derive(Serialize, Debug)]
+pub struct MonitorStatus {
+    pub settings: MonitorSettings,
+    pub last_check_at: Option<i64>,
+    pub next_check_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub alerts: Vec<BreachAlert>,
+}
+
+// ==========================================
+// --- STORE LOGIC ---
+// ==========================================
+
+impl BreachMonitorStore {
+    /// True when the scheduler should run a cycle now.
+    pub fn is_due(&self, now: i64) -> bool {
+        if !self.settings.enabled || self.settings.offline_mode {
+            return false;
+        }
+        self.next_check_at().is_none_or(|next| now >= next)
+    }
+
+    pub fn next_check_at(&self) -> Option<i64> {
+        let interval = self.settings.interval_hours.max(MIN_INTERVAL_HOURS) as i64 * 3600;
+        self.last_check_at.map(|t| t + interval)
+    }
+
+    pub fn status(&self) -> MonitorStatus {
+        MonitorStatus {
+            settings: self.settings.clone(),
+            last_check_at: self.last_check_at,
+            next_check_at: self.next_check_at(),
+            last_error: self.last_error.clone(),
+            alerts: self.alerts.clone(),
+        }
+    }
+
+    /// Records a breached password for an entry. Returns the alert only the first time
+    /// this (entry, password) pair is seen; changing the password re-arms the alert.
+    pub fn record_password_finding(
+        &mut self,
+        entry_id: &str,
+        service: &str,
+        password: &str,
+        count: u64,
+        now: i64,
+    ) -> Option<BreachAlert> {
+        let mut hasher = Sha256::new();
+        hasher.update(entry_id.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(password.as_bytes());
+        let fingerprint = format!("pw:{:x}", hasher.finalize());
+
+        self.push_alert(
+            fingerprint,
+            BreachAlert {
+                id: uuid::Uuid::new_v4().to_string(),
+                kind: AlertKind::Password,
+                subject: service.to_string(),
+                entry_id: Some(entry_id.to_string()),
+                breach_name: None,
+                count,
+                detected_at: now,
+                acknowledged: false,
+            },
+        )
+    }
+
+    /// Records that `email` appears in `breach_name`. Returns the alert only once per pair.
+    pub fn record_email_finding(
+        &mut self,
+        email: &str,
+        breach_name: &str,
+        now: i64,
+    ) -> Option<BreachAlert> {
+        let fingerprint = format!("email:{}:{}", email.to_lowercase(), breach_name);
+        self.push_alert(
+            fingerprint,
+            BreachAlert {
+                id: uuid::Uuid::new_v4().to_string(),
+                kind: AlertKind::Email,
+                subject: email.to_string(),
+                entry_id: None,
+                breach_name: Some(breach_name.to_string()),
+                count: 0,
+                detected_at: now,
+                acknowledged: false,
+            },
+        )
+    }
+
+    fn push_alert(&mut self, fingerprint: String, alert: BreachAlert) -> Option<BreachAlert> {
+        if !self.seen.insert(fingerprint) {
+            return None;
+        }
+        self.alerts.push(alert.clone());
+
+        // Cap the history: drop the oldest acknowledged alerts first, never unread ones.
+        while self.alerts.len() > MAX_ALERTS {
+            match self.alerts.iter().position(|a| a.acknowledged) {
+                Some(idx) => {
+                    self.alerts.remove(idx);
+                }
+                None => break,
+            }
+        }
+        Some(alert)
+    }
+
+    /// Marks alerts as read. Returns how many were changed.
+    pub fn acknowledge(&mut self, ids: &[String]) -> usize {
+        let mut changed = 0;
+        for alert in self.alerts.iter_mut() {
+            if !alert.acknowledged && ids.contains(&alert.id) {
+                alert.acknowledged = true;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// A check cycle can take minutes. Before saving its result, carry over whatever the
+    /// user changed meanwhile (settings, read markers) from the freshly re-read store.
+    pub fn merge_user_changes(&mut self, latest: &BreachMonitorStore) {
+        self.settings = latest.settings.clone();
+        for alert in self.alerts.iter_mut() {
+            if latest
+                .alerts
+                .iter()
+                .any(|a| a.id == alert.id && a.acknowledged)
+            {
+                alert.acknowledged = true;
+            }
+        }
+    }
+}
+
+fn looks_like_email(s: &str) -> bool {
+    let s = s.trim();
+    match s.split_once('@') {
+        Some((local, domain)) => !local.is_empty() && domain.contains('.') && s.len() <= 254,
+        None => false,
+    }
+}
+
+// ==========================================
+// --- CHECK CYCLE ---
+// ==========================================
+
+/// Runs one full check and returns the new alerts. Blocking (it sleeps between requests),
+/// so call it from a worker thread or `spawn_blocking`.
+///
+/// `last_check_at` is updated even when the cycle ends early on a network error, so a
+/// persistent failure backs off to the normal interval instead of hammering the API.
+pub fn run_check(
+    vault: &PasswordVault,
+    store: &mut BreachMonitorStore,
+    now: i64,
+) -> Vec<BreachAlert> {
+    let mut new_alerts = Vec::new();
+    store.last_check_at = Some(now);
+    store.last_error = None;
+
+    if store.settings.offline_mode {
+        store.last_error = Some("Offline mode is enabled; no check was made.".to_string());
+        return new_alerts;
+    }
+
+    if let Err(e) = check_passwords(vault, store, now, &mut new_alerts) {
+        store.last_error = Some(e);
+        return new_alerts;
+    }
+    if let Err(e) = check_emails(vault, store, now, &mut new_alerts) {
+        store.last_error = Some(e);
+    }
+    new_alerts
+}
+
+fn check_passwords(
+    vault: &PasswordVault,
+    store: &mut BreachMonitorStore,
+    now: i64,
+    new_alerts: &mut Vec<BreachAlert>,
+) -> Result<(), String> {
+    if !store.settings.check_passwords {
+        return Ok(());
+    }
+
+    // Group entries by SHA-1 so a password reused across entries costs one request.
+    let mut by_hash: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, entry) in vault.entries.iter().enumerate() {
+        if entry.password.is_empty() {
+            continue;
+        }
+        let hash = data_encoding::HEXUPPER.encode(&Sha1::digest(entry.password.as_bytes()));
+        by_hash.entry(hash).or_default().push(idx);
+    }
+
+    for (i, (hash, indices)) in by_hash.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(PASSWORD_REQUEST_GAP);
+        }
+        let (prefix, suffix) = hash.split_at(5);
+        let result = tauri::async_runtime::block_on(breach::check_pwned_by_prefix(prefix, suffix))
+            .map_err(|e| e.to_string())?;
+        if !result.found {
+            continue;
+        }
+        for &idx in indices {
+            let entry = &vault.entries[idx];
+            if let Some(alert) = store.record_password_finding(
+                &entry.id,
+                &entry.service,
+                &entry.password,
+                result.count,
+                now,
+            ) {
+                new_alerts.push(alert);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_emails(
+    vault: &PasswordVault,
+    store: &mut BreachMonitorStore,
+    now: i64,
+    new_alerts: &mut Vec<BreachAlert>,
+) -> Result<(), String> {
+    let api_key = match store.settings.hibp_api_key.clone() {
+        Some(k) if !k.trim().is_empty() => k,
+        _ => return Ok(()),
+    };
+
+    let mut emails: BTreeSet<String> = store
+        .settings
+        .monitored_emails
+        .iter()
+        .map(|e| e.trim().to_lowercase())
+        .collect();
+    emails.extend(
+        vault
+            .entries
+            .iter()
+            .filter(|e| looks_like_email(&e.username))
+            .map(|e| e.username.trim().to_lowercase()),
+    );
+
+    for (i, email) in emails.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(EMAIL_REQUEST_GAP);
+        }
+        let breaches =
+            tauri::async_runtime::block_on(breach::check_email_breaches(email, &api_key))
+                .map_err(|e| e.to_string())?;
+        for name in breaches {
+            if let Some(alert) = store.record_email_finding(email, &name, now) {
+                new_alerts.push(alert);
+            }
+        }
+    }
+    Ok(())
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled_store() -> BreachMonitorStore {
+        let mut store = BreachMonitorStore::default();
+        store.settings.enabled = true;
+        store
+    }
+
+    #[test]
+    fn test_is_due_respects_interval_and_offline_mode() {
+        let mut store = enabled_store();
+        assert!(store.is_due(1_000), "never checked -> due");
+
+        store.last_check_at = Some(1_000);
+        assert!(!store.is_due(1_000 + 3600));
+        assert!(store.is_due(1_000 + 24 * 3600));
+
+        store.settings.offline_mode = true;
+        assert!(!store.is_due(1_000 + 48 * 3600));
+
+        store.settings.offline_mode = false;
+        store.settings.enabled = false;
+        assert!(!store.is_due(1_000 + 48 * 3600));
+    }
+
+    #[test]
+    fn test_password_alert_fires_once_per_password() {
+        let mut store = enabled_store();
+        assert!(store
+            .record_password_finding("e1", "Mail", "hunter2", 10, 1)
+            .is_some());
+        assert!(store
+            .record_password_finding("e1", "Mail", "hunter2", 12, 2)
+            .is_none());
+        // A new (still breached) password re-arms the alert.
+        assert!(store
+            .record_password_finding("e1", "Mail", "password1", 5, 3)
+            .is_some());
+        assert_eq!(store.alerts.len(), 2);
+    }
+
+    #[test]
+    fn test_email_alert_dedup_is_case_insensitive() {
+        let mut store = enabled_store();
+        assert!(store
+            .record_email_finding("Me@Example.com", "Adobe", 1)
+            .is_some());
+        assert!(store
+            .record_email_finding("me@example.com", "Adobe", 2)
+            .is_none());
+        assert!(store
+            .record_email_finding("me@example.com", "LinkedIn", 3)
+            .is_some());
+    }
+
+    #[test]
+    fn test_history_cap_keeps_unread_alerts() {
+        let mut store = enabled_store();
+        for i in 0..(MAX_ALERTS + 10) {
+            store.record_email_finding("a@b.com", &format!("B{}", i), i as i64);
+        }
+        assert_eq!(
+            store.alerts.len(),
+            MAX_ALERTS + 10,
+            "unread alerts are never dropped"
+        );
+
+        let ids: Vec<String> = store.alerts.iter().take(20).map(|a| a.id.clone()).collect();
+        assert_eq!(store.acknowledge(&ids), 20);
+        store.record_email_finding("a@b.com", "Final", 0);
+        assert_eq!(store.alerts.len(), MAX_ALERTS);
+        assert!(store
+            .alerts
+            .iter()
+            .any(|a| a.breach_name.as_deref() == Some("Final")));
+    }
+
+    #[test]
+    fn test_offline_mode_makes_no_requests() {
+        let mut store = enabled_store();
+        store.settings.offline_mode = true;
+        let alerts = run_check(&PasswordVault::new(), &mut store, 42);
+        assert!(alerts.is_empty());
+        assert_eq!(store.last_check_at, Some(42));
+        assert!(store.last_error.is_some());
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        let mut settings = MonitorSettings::default();
+        assert!(settings.validate().is_ok());
+        settings.interval_hours = 1;
+        assert!(settings.validate().is_err());
+        settings.interval_hours = 24;
+        settings.monitored_emails = vec!["nope".to_string()];
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_store_roundtrip_keeps_seen_set() {
+        let mut store = enabled_store();
+        store.record_email_finding("a@b.com", "Adobe", 1);
+        let json = serde_json::to_vec(&store).unwrap();
+        let mut restored: BreachMonitorStore = serde_json::from_slice(&json).unwrap();
+        assert!(restored
+            .record_email_finding("a@b.com", "Adobe", 2)
+            .is_none());
+    }
+}
+
+// --- END OF FILE breach_monitor.rs ---
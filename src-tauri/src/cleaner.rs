@@ -1,12 +1,12 @@
 // --- START OF FILE cleaner.rs ---
 
+use crate::progress::ProgressEmitter;
 use anyhow::{anyhow, Result};
 use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::Emitter;
 // `zip` crate is used because modern Office documents (.docx, .xlsx) are actually just ZIP files containing XML.
 use zip::write::SimpleFileOptions;
 
@@ -30,7 +30,7 @@ static CANCEL_FLAG: AtomicBool = AtomicBool::new(false);
 // ═══════════════════════════════════════════════════════════════════════════
 
 /// Represents a single piece of raw metadata found in a file (e.g., "Software: Adobe Photoshop 2024").
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct MetadataEntry {
     pub key: String,
     pub value: String,
@@ -53,13 +53,23 @@ pub struct MetadataReport {
 }
 
 /// Preferences selected by the user in the UI regarding what specific data to strip.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
 pub struct CleaningOptions {
     pub gps: bool,
     pub author: bool,
     pub date: bool,
 }
 
+impl Default for CleaningOptions {
+    fn default() -> Self {
+        Self {
+            gps: true,
+            author: true,
+            date: true,
+        }
+    }
+}
+
 /// Progress event emitted to the frontend during batch operations.
 #[derive(Clone, serde::Serialize)]
 pub struct CleanProgress {
@@ -85,6 +95,51 @@ pub struct FailedFile {
     pub error: String,
 }
 
+/// Privacy-relevant category of a metadata tag, used to group diff results.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum TagGroup {
+    Location,
+    Identity,
+    Device,
+    Software,
+    Timestamps,
+    Other,
+}
+
+/// One tag that differs between two files. `before` is the value in file A, `after`
+/// the value in file B; repeated keys (e.g. EXIF thumbnail IFDs) are joined with " | ".
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct TagChange {
+    pub key: String,
+    pub group: TagGroup,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Per-group counts, so the UI can say "2 location tags removed" at a glance.
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct TagGroupSummary {
+    pub group: TagGroup,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+/// Result of `diff_metadata`: how the metadata of file B differs from file A.
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct MetadataDiff {
+    pub file_type_a: String,
+    pub file_type_b: String,
+    pub size_a: u64,
+    pub size_b: u64,
+    pub added: Vec<TagChange>,
+    pub removed: Vec<TagChange>,
+    pub changed: Vec<TagChange>,
+    pub unchanged: usize,
+    pub groups: Vec<TagGroupSummary>,
+}
+
 /// Result of comparing an original file against a cleaned file to verify tag removal.
 #[derive(serde::Serialize)]
 pub struct ComparisonResult {
@@ -308,6 +363,7 @@ pub fn batch_clean<R: tauri::Runtime>(
         .collect();
 
     let total = paths.len();
+    let events = ProgressEmitter::new(app_handle, "clean-metadata-progress");
     let mut success = Vec::new();
     let mut failed = Vec::new();
     let mut size_before = 0u64;
@@ -329,7 +385,7 @@ pub fn batch_clean<R: tauri::Runtime>(
             .unwrap_or("Unknown")
             .to_string();
 
-        emit_progress(app_handle, idx, total, filename);
+        emit_progress(&events, idx, total, filename);
 
         // Try to clean file
         match remove_metadata(path_str, output_dir.as_deref(), options.clone()) {
@@ -354,7 +410,7 @@ pub fn batch_clean<R: tauri::Runtime>(
 
     // FIX: Pass an empty string rather than the misleading "Complete" filename literal,
     // so the UI filename display blanks out cleanly at 100%.
-    emit_progress(app_handle, total, total, String::new());
+    emit_progress(&events, total, total, String::new());
 
     Ok(CleanResult {
         success,
@@ -365,9 +421,9 @@ pub fn batch_clean<R: tauri::Runtime>(
     })
 }
 
-/// Helper to format and emit progress events to Tauri.
+/// Helper to format and emit progress events to Tauri (coalesced; the last file always gets through).
 fn emit_progress<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+    events: &ProgressEmitter<R, CleanProgress>,
     current: usize,
     total: usize,
     current_file: String,
@@ -385,7 +441,11 @@ fn emit_progress<R: tauri::Runtime>(
         percentage,
     };
 
-    let _ = app_handle.emit("clean-metadata-progress", progress);
+    if current >= total {
+        events.finish(progress);
+    } else {
+        events.emit(progress);
+    }
 }
 
 /// Cancels ongoing batch operation by flipping the atomic flag.
@@ -395,35 +455,138 @@ pub fn cancel_cleaning() {
 
 /// Compares a file before and after cleaning, mapping exactly which tags were deleted.
 pub fn compare_files(original: &str, cleaned: &str) -> Result<ComparisonResult> {
-    let original_path = Path::new(original);
-    let cleaned_path = Path::new(cleaned);
-
-    // FIX: Previously only the original was validated. Now both paths are checked,
-    // preventing an attacker from passing an arbitrary path as `cleaned` to extract
-    // metadata reports on files outside the normal workflow.
-    let _validated_original = validate_file_path(original_path)?;
-    let _validated_cleaned = validate_file_path(cleaned_path)?;
-
-    let original_size = fs::metadata(original_path)?.len();
-    let cleaned_size = fs::metadata(cleaned_path)?.len();
-
-    let original_report = analyze_file(original)?;
-    let cleaned_report = analyze_file(cleaned)?;
-
-    let mut removed_tags = Vec::new();
-    // Cross-reference original tags against the cleaned tags
-    for tag in &original_report.raw_tags {
-        if !cleaned_report.raw_tags.iter().any(|t| t.key == tag.key) {
-            removed_tags.push(format!("{}: {}", tag.key, tag.value));
+    let diff = diff_metadata(original, cleaned)?;
+    Ok(ComparisonResult {
+        original_size: diff.size_a,
+        cleaned_size: diff.size_b,
+        removed_tags: diff
+            .removed
+            .iter()
+            .map(|t| format!("{}: {}", t.key, t.before.as_deref().unwrap_or_default()))
+            .collect(),
+        size_reduction: diff.size_a.saturating_sub(diff.size_b),
+    })
+}
+
+/// Diffs the metadata of any two supported files (not only original vs. cleaned), e.g.
+/// to check what a third-party sanitizer actually removed.
+pub fn diff_metadata(path_a: &str, path_b: &str) -> Result<MetadataDiff> {
+    // FIX: Both paths are validated, preventing an attacker from passing an arbitrary
+    // path as the second file to extract metadata reports outside the normal workflow.
+    validate_file_path(Path::new(path_a))?;
+    validate_file_path(Path::new(path_b))?;
+
+    let report_a = analyze_file(path_a)?;
+    let report_b = analyze_file(path_b)?;
+    Ok(diff_reports(&report_a, &report_b))
+}
+
+/// Pure comparison of two reports, keyed by tag name.
+fn diff_reports(a: &MetadataReport, b: &MetadataReport) -> MetadataDiff {
+    // BTreeMap keeps the output sorted by key; repeated keys are joined in file order.
+    fn by_key(report: &MetadataReport) -> std::collections::BTreeMap<&str, String> {
+        let mut map: std::collections::BTreeMap<&str, String> = Default::default();
+        for tag in &report.raw_tags {
+            map.entry(tag.key.as_str())
+                .and_modify(|v| {
+                    v.push_str(" | ");
+                    v.push_str(&tag.value);
+                })
+                .or_insert_with(|| tag.value.clone());
         }
+        map
     }
+    let (tags_a, tags_b) = (by_key(a), by_key(b));
 
-    Ok(ComparisonResult {
-        original_size,
-        cleaned_size,
-        removed_tags,
-        size_reduction: original_size.saturating_sub(cleaned_size),
-    })
+    let change = |key: &str, before: Option<&String>, after: Option<&String>| TagChange {
+        key: key.to_string(),
+        group: tag_group(key),
+        before: before.cloned(),
+        after: after.cloned(),
+    };
+    let (mut added, mut removed, mut changed, mut unchanged) = (vec![], vec![], vec![], 0);
+    for (key, value_a) in &tags_a {
+        match tags_b.get(key) {
+            None => removed.push(change(key, Some(value_a), None)),
+            Some(value_b) if value_b != value_a => {
+                changed.push(change(key, Some(value_a), Some(value_b)))
+            }
+            Some(_) => unchanged += 1,
+        }
+    }
+    for (key, value_b) in tags_b.iter().filter(|(k, _)| !tags_a.contains_key(*k)) {
+        added.push(change(key, None, Some(value_b)));
+    }
+
+    let mut groups: Vec<TagGroupSummary> = Vec::new();
+    for (list, slot) in [(&added, 0), (&removed, 1), (&changed, 2)] {
+        for tag in list {
+            let summary = match groups.iter_mut().find(|g| g.group == tag.group) {
+                Some(g) => g,
+                None => {
+                    groups.push(TagGroupSummary {
+                        group: tag.group,
+                        added: 0,
+                        removed: 0,
+                        changed: 0,
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+            match slot {
+                0 => summary.added += 1,
+                1 => summary.removed += 1,
+                _ => summary.changed += 1,
+            }
+        }
+    }
+    groups.sort_by_key(|g| g.group);
+
+    MetadataDiff {
+        file_type_a: a.file_type.clone(),
+        file_type_b: b.file_type.clone(),
+        size_a: a.file_size,
+        size_b: b.file_size,
+        added,
+        removed,
+        changed,
+        unchanged,
+        groups,
+    }
+}
+
+/// Classifies a tag key from any handler (EXIF names, PDF Info keys, Office properties).
+fn tag_group(key: &str) -> TagGroup {
+    let k = key.to_ascii_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| k.contains(w));
+    if has(&["gps", "latitude", "longitude", "altitude", "location"]) {
+        TagGroup::Location
+    } else if has(&[
+        "author",
+        "artist",
+        "creator",
+        "copyright",
+        "owner",
+        "modified by",
+        "company",
+        "manager",
+    ]) {
+        TagGroup::Identity
+    } else if has(&["make", "model", "serial", "lens", "camera", "body"]) {
+        TagGroup::Device
+    } else if has(&[
+        "software",
+        "producer",
+        "application",
+        "template",
+        "processing",
+    ]) {
+        TagGroup::Software
+    } else if has(&["date", "time", "created", "modified"]) {
+        TagGroup::Timestamps
+    } else {
+        TagGroup::Other
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -522,6 +685,44 @@ fn analyze_image(path: &Path) -> Result<MetadataReport> {
     Ok(report)
 }
 
+/// Decimal-degree (latitude, longitude) from an image's EXIF GPS tags, or `None` if it has
+/// no usable position. A 0,0 position is treated as a placeholder written by some apps.
+pub fn gps_coordinates(path: &Path) -> Option<(f64, f64)> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::BufReader::new(&file))
+        .ok()?;
+
+    let degrees = |tag: exif::Tag, ref_tag: exif::Tag, negative: u8| -> Option<f64> {
+        let exif::Value::Rational(ref parts) = exif.get_field(tag, exif::In::PRIMARY)?.value else {
+            return None;
+        };
+        // Degrees, minutes, seconds.
+        let value: f64 = parts
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(r, div)| r.to_f64() / div)
+            .sum();
+        let hemisphere = match exif.get_field(ref_tag, exif::In::PRIMARY).map(|f| &f.value) {
+            Some(exif::Value::Ascii(v)) => v.first().and_then(|s| s.first()).copied(),
+            _ => None,
+        };
+        Some(if hemisphere == Some(negative) {
+            -value
+        } else {
+            value
+        })
+    };
+
+    let lat = degrees(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b'S')?;
+    let lon = degrees(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b'W')?;
+    let valid = lat.is_finite() && lon.is_finite() && lat.abs() <= 90.0 && lon.abs() <= 180.0;
+    (valid && (lat, lon) != (0.0, 0.0)).then_some((lat, lon))
+}
+
 /// Rebuilds a JPEG file, omitting EXIF Application segments.
 ///
 /// NOTE: JPEG EXIF is stored as a single APP1 segment containing a binary IFD structure.
@@ -958,6 +1159,15 @@ fn extract_xml_element_content(xml: &str, element_name: &str) -> Option<String>
     Some(xml[content_start..content_start + end_offset].to_string())
 }
 
+/// Extracts the value of `name="..."` from the inside of a single XML tag.
+/// Matches on a preceding space so `Id` does not match inside `r:Id` or `TargetId`.
+fn extract_xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let needle = format!(" {}=\"", name);
+    let start = format!(" {}", tag).find(&needle)? + needle.len() - 1;
+    let end = tag[start..].find('"')?;
+    Some(tag[start..start + end].to_string())
+}
+
 /// Returns a copy of `xml` with the text content of `element_name` replaced with an empty string.
 /// Handles elements with or without attributes. Leaves the element tag structure intact so
 /// that Office applications can still open the document without validation errors.
@@ -1242,6 +1452,270 @@ fn clean_zip_metadata(input: &Path, output: &Path) -> Result<()> {
     Ok(())
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// REMOTE CONTENT DETECTION (Tracking pixels, remote templates, auto-open URLs)
+// ═══════════════════════════════════════════════════════════════════════════
+// Documents can "phone home" the moment they are opened: Office files may pull an
+// image or a whole template from a URL (a classic way to learn the reader's IP and
+// open time), and PDFs can carry an /OpenAction that fetches a URI. Ordinary hyperlinks
+// are reported too, but only content that loads automatically marks the file as tracking.
+
+/// 1 pixel expressed in EMU (English Metric Units), the unit of `<wp:extent>` in DrawingML.
+const EMU_PER_PIXEL: u64 = 9525;
+/// Remote images this small (in pixels, both dimensions) are treated as tracking pixels.
+const TRACKING_PIXEL_MAX: u64 = 2;
+/// SECURITY: Cap the amount of XML read from any single part of an Office document.
+const MAX_PART_READ: u64 = 10 * 1024 * 1024;
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct RemoteReference {
+    pub url: String,
+    /// "tracking_pixel", "remote_image", "remote_template", "remote_object",
+    /// "auto_open_uri", "remote_file", "form_submit" or "hyperlink".
+    pub kind: String,
+    /// Where the reference was found (ZIP part name or PDF object id).
+    pub location: String,
+    /// True when the viewer fetches the URL without any user interaction.
+    pub loads_on_open: bool,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct RemoteContentReport {
+    pub path: String,
+    pub file_type: String,
+    pub references: Vec<RemoteReference>,
+    pub phones_home: bool, // At least one reference loads on open
+}
+
+/// Scans a PDF or Office document for embedded references to remote content.
+pub fn detect_remote_content(path_str: &str) -> Result<RemoteContentReport> {
+    let canonical = validate_file_path(Path::new(path_str))?;
+    let ext = canonical
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let (file_type, references) = match ext.as_str() {
+        "pdf" => ("PDF Document", scan_pdf_remote_content(&canonical)?),
+        "docx" | "xlsx" | "pptx" => ("Office Document", scan_office_remote_content(&canonical)?),
+        _ => {
+            return Err(anyhow!(
+                "Remote content detection supports PDF and Office documents only"
+            ))
+        }
+    };
+
+    Ok(RemoteContentReport {
+        path: canonical.display().to_string(),
+        file_type: file_type.to_string(),
+        phones_home: references.iter().any(|r| r.loads_on_open),
+        references,
+    })
+}
+
+fn is_remote_url(url: &str) -> bool {
+    let lower = url.trim().to_lowercase();
+    lower.starts_with("http://")
+        || lower.starts_with("https://")
+        || lower.starts_with("ftp://")
+        || lower.starts_with("\\\\") // UNC path: Windows leaks NTLM hashes when fetching these
+        || lower.starts_with("file://")
+}
+
+/// Walks every `.rels` part. External relationships are the only way an OpenXML document
+/// can point outside the package, so this catches images, templates, OLE links and hyperlinks.
+fn scan_office_remote_content(path: &Path) -> Result<Vec<RemoteReference>> {
+    let file = File::open(path)?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| anyhow!("Invalid Office document: {}", e))?;
+    validate_zip_archive(&mut archive)?;
+
+    let rels_names: Vec<String> = archive
+        .file_names()
+        .filter(|n| n.ends_with(".rels"))
+        .map(|n| n.to_string())
+        .collect();
+
+    let mut references = Vec::new();
+    for rels_name in rels_names {
+        let rels_xml = read_zip_part(&mut archive, &rels_name)?;
+        let external = parse_external_relationships(&rels_xml);
+        if external.is_empty() {
+            continue;
+        }
+
+        // The part the relationships belong to: `word/_rels/document.xml.rels` → `word/document.xml`.
+        let source_part = rels_name
+            .replace("_rels/", "")
+            .trim_end_matches(".rels")
+            .to_string();
+        let source_xml = read_zip_part(&mut archive, &source_part).unwrap_or_default();
+
+        for (id, rel_type, target) in external {
+            if !is_remote_url(&target) {
+                continue;
+            }
+            let kind = if rel_type.ends_with("/image") {
+                if is_tiny_drawing(&source_xml, &id) {
+                    "tracking_pixel"
+                } else {
+                    "remote_image"
+                }
+            } else if rel_type.ends_with("/attachedTemplate") {
+                "remote_template"
+            } else if rel_type.ends_with("/hyperlink") {
+                "hyperlink"
+            } else {
+                // oleObject, frame, subDocument, externalLinkPath, ...
+                "remote_object"
+            };
+            references.push(RemoteReference {
+                url: target,
+                kind: kind.to_string(),
+                location: rels_name.clone(),
+                loads_on_open: kind != "hyperlink",
+            });
+        }
+    }
+
+    Ok(references)
+}
+
+fn read_zip_part<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<String> {
+    let entry = archive.by_name(name)?;
+    let mut xml = String::new();
+    entry.take(MAX_PART_READ).read_to_string(&mut xml)?;
+    Ok(xml)
+}
+
+/// Returns `(Id, Type, Target)` for every `<Relationship TargetMode="External">`.
+fn parse_external_relationships(xml: &str) -> Vec<(String, String, String)> {
+    xml.split("<Relationship ")
+        .skip(1)
+        .filter_map(|chunk| {
+            let tag = &chunk[..chunk.find('>')?];
+            if extract_xml_attribute(tag, "TargetMode").as_deref() != Some("External") {
+                return None;
+            }
+            Some((
+                extract_xml_attribute(tag, "Id")?,
+                extract_xml_attribute(tag, "Type").unwrap_or_default(),
+                extract_xml_attribute(tag, "Target")?,
+            ))
+        })
+        .collect()
+}
+
+/// Checks whether the drawing that links relationship `rel_id` is at most a couple of
+/// pixels in size. Word stores the size in `<wp:extent cx=".." cy=".."/>` before the
+/// `<a:blip r:link="rIdN"/>` that references the remote image.
+fn is_tiny_drawing(part_xml: &str, rel_id: &str) -> bool {
+    let needle = format!("r:link=\"{}\"", rel_id);
+    let Some(link_pos) = part_xml.find(&needle) else {
+        return false;
+    };
+    let Some(extent_pos) = part_xml[..link_pos].rfind("<wp:extent ") else {
+        return false;
+    };
+    let tag_end = part_xml[extent_pos..]
+        .find('>')
+        .map(|i| extent_pos + i)
+        .unwrap_or(link_pos);
+    let tag = &part_xml[extent_pos..tag_end];
+
+    let dim = |attr: &str| {
+        extract_xml_attribute(tag, attr)
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|emu| emu / EMU_PER_PIXEL)
+    };
+    matches!((dim("cx"), dim("cy")), (Some(w), Some(h)) if w <= TRACKING_PIXEL_MAX && h <= TRACKING_PIXEL_MAX)
+}
+
+/// Walks every PDF object looking for URI actions, remote file specifications and form
+/// submission targets. A URI action reachable from the catalog's /OpenAction (or a page's
+/// /AA "additional actions") fires when the document opens.
+fn scan_pdf_remote_content(path: &Path) -> Result<Vec<RemoteReference>> {
+    let doc = lopdf::Document::load(path).map_err(|e| anyhow!("PDF Load Error: {}", e))?;
+
+    // Object ids of actions that run automatically.
+    let mut auto_ids: HashSet<lopdf::ObjectId> = HashSet::new();
+    for object in doc.objects.values() {
+        let Ok(dict) = object.as_dict() else { continue };
+        if let Ok(open_action) = dict.get(b"OpenAction").and_then(|o| o.as_reference()) {
+            auto_ids.insert(open_action);
+        }
+        if let Ok(aa) = dict.get(b"AA").and_then(|o| o.as_dict()) {
+            for (_, action) in aa.iter() {
+                if let Ok(id) = action.as_reference() {
+                    auto_ids.insert(id);
+                }
+            }
+        }
+    }
+
+    let obj_str = |o: &lopdf::Object| -> Option<String> {
+        o.as_str()
+            .ok()
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+    };
+
+    let mut references = Vec::new();
+    let mut seen = HashSet::new();
+    for (id, object) in &doc.objects {
+        let dict = match object {
+            lopdf::Object::Dictionary(d) => d,
+            lopdf::Object::Stream(s) => &s.dict,
+            _ => continue,
+        };
+        let loads_on_open = auto_ids.contains(id);
+
+        let found: Option<(String, &str)> = if let Some(uri) =
+            dict.get(b"URI").ok().and_then(obj_str)
+        {
+            Some((
+                uri,
+                if loads_on_open {
+                    "auto_open_uri"
+                } else {
+                    "hyperlink"
+                },
+            ))
+        } else if dict.get(b"FS").and_then(|o| o.as_name()).ok() == Some(b"URL".as_slice()) {
+            // File specification resolved over the network (e.g. remote /GoToR or embedded XObjects).
+            dict.get(b"F")
+                .ok()
+                .and_then(obj_str)
+                .map(|u| (u, "remote_file"))
+        } else if dict.get(b"S").and_then(|o| o.as_name()).ok() == Some(b"SubmitForm".as_slice()) {
+            dict.get(b"F")
+                .ok()
+                .and_then(|f| {
+                    obj_str(f).or_else(|| f.as_dict().ok()?.get(b"F").ok().and_then(obj_str))
+                })
+                .map(|u| (u, "form_submit"))
+        } else {
+            None
+        };
+
+        if let Some((url, kind)) = found {
+            if is_remote_url(&url) && seen.insert((url.clone(), kind)) {
+                references.push(RemoteReference {
+                    url,
+                    kind: kind.to_string(),
+                    location: format!("object {} {}", id.0, id.1),
+                    loads_on_open: loads_on_open || kind == "remote_file",
+                });
+            }
+        }
+    }
+
+    Ok(references)
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // STEGANOGRAPHY DETECTION (LSB Entropy Analysis)
 // ═══════════════════════════════════════════════════════════════════════════
@@ -1268,6 +1742,7 @@ pub async fn detect_steganography(
     tauri::async_runtime::spawn_blocking(move || {
         let mut results = Vec::new();
         let total = paths.len();
+        let events = ProgressEmitter::new(&app_handle, "stego-progress");
 
         for (idx, path_str) in paths.into_iter().enumerate() {
             let path = Path::new(&path_str);
@@ -1278,19 +1753,16 @@ pub async fn detect_steganography(
                 .to_string();
 
             // Emit progress
-            let _ = app_handle.emit(
-                "stego-progress",
-                CleanProgress {
-                    current: idx,
-                    total,
-                    current_file: filename.clone(),
-                    percentage: if total > 0 {
-                        ((idx as f64 / total as f64) * 100.0) as u8
-                    } else {
-                        0
-                    },
+            events.emit(CleanProgress {
+                current: idx,
+                total,
+                current_file: filename.clone(),
+                percentage: if total > 0 {
+                    ((idx as f64 / total as f64) * 100.0) as u8
+                } else {
+                    0
                 },
-            );
+            });
 
             // Only analyze PNG, BMP, or uncompressed formats where LSB stego is viable.
             // (JPEG stego usually alters DCT coefficients, but LSB on raw bytes can still indicate tampering).
@@ -1362,15 +1834,12 @@ pub async fn detect_steganography(
             }
         }
 
-        let _ = app_handle.emit(
-            "stego-progress",
-            CleanProgress {
-                current: total,
-                total,
-                current_file: String::new(),
-                percentage: 100,
-            },
-        );
+        events.finish(CleanProgress {
+            current: total,
+            total,
+            current_file: String::new(),
+            percentage: 100,
+        });
 
         Ok(results)
     })
@@ -1692,6 +2161,196 @@ mod tests {
         );
     }
 
+    // ─── Remote content detection ─────────────────────────────────────────
+
+    #[test]
+    fn test_extract_xml_attribute() {
+        let tag = r#"Id="rId5" Type="http://x/image" Target="https://t.example/p.gif" TargetMode="External"/"#;
+        assert_eq!(extract_xml_attribute(tag, "Id"), Some("rId5".into()));
+        assert_eq!(
+            extract_xml_attribute(tag, "Target"),
+            Some("https://t.example/p.gif".into())
+        );
+        assert_eq!(extract_xml_attribute(tag, "Missing"), None);
+    }
+
+    #[test]
+    fn test_detect_remote_content_office() {
+        let dir = temp_dir("remote_office");
+        let path = dir.join("invite.docx");
+
+        {
+            let file = fs::File::create(&path).unwrap();
+            let mut writer = zip::ZipWriter::new(file);
+            let opts = zip::write::SimpleFileOptions::default();
+            writer.start_file("word/document.xml", opts).unwrap();
+            writer
+                .write_all(br#"<w:document><wp:extent cx="9525" cy="9525"/><a:blip r:link="rId1"/></w:document>"#)
+                .unwrap();
+            writer
+                .start_file("word/_rels/document.xml.rels", opts)
+                .unwrap();
+            writer
+                .write_all(br#"<Relationships>
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="https://tracker.example/px.gif" TargetMode="External"/>
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/" TargetMode="External"/>
+<Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+</Relationships>"#)
+                .unwrap();
+            writer
+                .start_file("word/_rels/settings.xml.rels", opts)
+                .unwrap();
+            writer
+                .write_all(br#"<Relationships><Relationship Id="rId9" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/attachedTemplate" Target="http://evil.example/t.dotm" TargetMode="External"/></Relationships>"#)
+                .unwrap();
+            writer.finish().unwrap();
+        }
+
+        let report = detect_remote_content(path.to_str().unwrap()).unwrap();
+        assert!(report.phones_home);
+        assert_eq!(
+            report.references.len(),
+            3,
+            "Internal parts must not be reported"
+        );
+
+        let kind_of = |url: &str| {
+            report
+                .references
+                .iter()
+                .find(|r| r.url == url)
+                .map(|r| r.kind.clone())
+        };
+        assert_eq!(
+            kind_of("https://tracker.example/px.gif").as_deref(),
+            Some("tracking_pixel")
+        );
+        assert_eq!(
+            kind_of("https://example.com/").as_deref(),
+            Some("hyperlink")
+        );
+        assert_eq!(
+            kind_of("http://evil.example/t.dotm").as_deref(),
+            Some("remote_template")
+        );
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_detect_remote_content_pdf_open_action() {
+        use lopdf::{dictionary, Object};
+
+        let dir = temp_dir("remote_pdf");
+        let path = dir.join("beacon.pdf");
+
+        let mut doc = lopdf::Document::with_version("1.5");
+        let beacon = doc.add_object(dictionary! {
+            "S" => "URI",
+            "URI" => Object::string_literal("https://beacon.example/open"),
+        });
+        // A plain link annotation target: reported, but not fetched on open.
+        doc.add_object(dictionary! {
+            "S" => "URI",
+            "URI" => Object::string_literal("https://example.com/docs"),
+        });
+        let pages =
+            doc.add_object(dictionary! { "Type" => "Pages", "Kids" => vec![], "Count" => 0 });
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages,
+            "OpenAction" => beacon,
+        });
+        doc.trailer.set("Root", catalog);
+        doc.save(&path).unwrap();
+
+        let report = detect_remote_content(path.to_str().unwrap()).unwrap();
+        assert!(report.phones_home);
+        let open = report
+            .references
+            .iter()
+            .find(|r| r.url == "https://beacon.example/open")
+            .unwrap();
+        assert_eq!(open.kind, "auto_open_uri");
+        let plain = report
+            .references
+            .iter()
+            .find(|r| r.url == "https://example.com/docs")
+            .unwrap();
+        assert!(!plain.loads_on_open);
+
+        let _ = fs::remove_file(path);
+    }
+
+    // ─── Metadata diff ────────────────────────────────────────────────────
+
+    fn report_with(tags: &[(&str, &str)]) -> MetadataReport {
+        MetadataReport {
+            has_gps: false,
+            has_author: false,
+            camera_info: None,
+            software_info: None,
+            creation_date: None,
+            gps_info: None,
+            file_type: "Image".into(),
+            file_size: 100,
+            raw_tags: tags
+                .iter()
+                .map(|(k, v)| MetadataEntry {
+                    key: k.to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+            app_info: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_changed() {
+        let a = report_with(&[
+            ("GPSLatitude", "37.9"),
+            ("Model", "Pixel 8"),
+            ("Software", "v1"),
+            ("Orientation", "1"),
+        ]);
+        let b = report_with(&[("Software", "v2"), ("Orientation", "1"), ("Artist", "Bob")]);
+        let diff = diff_reports(&a, &b);
+
+        let keys = |list: &[TagChange]| list.iter().map(|t| t.key.clone()).collect::<Vec<_>>();
+        assert_eq!(keys(&diff.removed), vec!["GPSLatitude", "Model"]);
+        assert_eq!(keys(&diff.added), vec!["Artist"]);
+        assert_eq!(diff.changed[0].before.as_deref(), Some("v1"));
+        assert_eq!(diff.changed[0].after.as_deref(), Some("v2"));
+        assert_eq!(diff.unchanged, 1);
+
+        let location = diff
+            .groups
+            .iter()
+            .find(|g| g.group == TagGroup::Location)
+            .unwrap();
+        assert_eq!(location.removed, 1);
+        assert_eq!(diff.groups[0].group, TagGroup::Location, "Sorted by group");
+    }
+
+    #[test]
+    fn test_diff_reports_joins_repeated_keys() {
+        let a = report_with(&[("XResolution", "72"), ("XResolution", "72")]);
+        let b = report_with(&[("XResolution", "72")]);
+        let diff = diff_reports(&a, &b);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].before.as_deref(), Some("72 | 72"));
+    }
+
+    #[test]
+    fn test_tag_group_classification() {
+        assert_eq!(tag_group("GPSLongitude"), TagGroup::Location);
+        assert_eq!(tag_group("Last Modified By"), TagGroup::Identity);
+        assert_eq!(tag_group("Make"), TagGroup::Device);
+        assert_eq!(tag_group("Producer"), TagGroup::Software);
+        assert_eq!(tag_group("ModDate"), TagGroup::Timestamps);
+        assert_eq!(tag_group("Pages"), TagGroup::Other);
+    }
+
     // ─── ZIP analysis & protection ────────────────────────────────────────
 
     #[test]
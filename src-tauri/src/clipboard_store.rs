@@ -1,8 +1,21 @@
 // --- START OF FILE clipboard_store.rs ---
 
+use crate::keychain::MasterKey;
+use crate::pattern_packs;
+use crate::regexes::{self, Pattern};
+use crate::url_cleaner;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Result};
 use chrono::Utc;
-use regex::Regex;
+use rand::{rngs::OsRng, TryRngCore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
 use uuid::Uuid;
 // Zeroize ensures that sensitive copied data (like passwords) is aggressively wiped
 // from RAM when the struct is dropped, preventing memory forensics.
@@ -78,6 +91,185 @@ impl ClipboardVault {
         self.entries.push(entry);
         Ok(())
     }
+
+    /// Applies one journal operation. Replay must be idempotent: after a crash between
+    /// writing a compacted snapshot and deleting the journal, the same ops are replayed
+    /// onto a snapshot that already contains them.
+    pub fn apply(&mut self, op: &JournalOp) {
+        match op {
+            JournalOp::Add { entry } => {
+                // An already-present ID means this add was replayed; skipping it is correct.
+                let _ = self.add_entry(entry.clone());
+            }
+            JournalOp::Remove { id } => self.entries.retain(|e| &e.id != id),
+            JournalOp::SetPinned { id, pinned } => {
+                if let Some(e) = self.entries.iter_mut().find(|e| &e.id == id) {
+                    e.is_pinned = *pinned;
+                }
+            }
+        }
+    }
+}
+
+// ==========================================
+// --- APPEND-ONLY SYNC JOURNAL ---
+// ==========================================
+// Clipboard history changes every few seconds while monitoring is on. Re-encrypting and
+// rewriting the whole `clipboard.qre` snapshot on every copy made sync tools (Syncthing,
+// Dropbox) see a brand-new file constantly and produce endless conflict copies.
+//
+// Changes are instead appended to `clipboard.journal` as small, individually encrypted
+// records. The snapshot is only rewritten on compaction (journal too long, entries
+// expired, or an explicit full save from the UI), after which the journal is deleted.
+//
+// File layout:   JOURNAL_MAGIC | record*
+// Record layout: len (u32 LE, nonce + ciphertext) | nonce (12) | AES-256-GCM ciphertext
+//
+// Each record's AAD is the magic plus its sequence number, so records cannot be reordered,
+// duplicated or dropped from the middle. A torn record at the very end (crash mid-append)
+// is ignored on read and truncated away on the next append.
+
+pub const JOURNAL_FILE_NAME: &str = "clipboard.journal";
+/// Compact once this many records have accumulated.
+pub const COMPACT_AFTER_RECORDS: usize = 200;
+const JOURNAL_MAGIC: &[u8; 8] = b"QREJRNL1";
+const JOURNAL_NONCE_LEN: usize = 12;
+/// SECURITY: Upper bound on a single record, so a corrupt length prefix cannot trigger a huge allocation.
+const MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+/// One change to the clipboard vault.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalOp {
+    Add { entry: ClipboardEntry },
+    Remove { id: String },
+    SetPinned { id: String, pinned: bool },
+}
+
+/// The journal uses its own key, domain-separated from the master key (same construction
+/// as the file wrapping key in crypto.rs).
+fn journal_key(master_key: &MasterKey) -> zeroize::Zeroizing<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(master_key.0);
+    hasher.update(b"CLIPBOARD_JOURNAL");
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&hasher.finalize());
+    zeroize::Zeroizing::new(key)
+}
+
+fn record_aad(seq: u64) -> Vec<u8> {
+    let mut aad = JOURNAL_MAGIC.to_vec();
+    aad.extend_from_slice(&seq.to_le_bytes());
+    aad
+}
+
+/// Splits the journal into raw records. Returns the records and the byte length of the
+/// well-formed prefix (anything after it is a torn tail).
+fn split_records(data: &[u8]) -> Result<(Vec<&[u8]>, usize)> {
+    if data.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+    if data.len() < JOURNAL_MAGIC.len() || &data[..JOURNAL_MAGIC.len()] != JOURNAL_MAGIC {
+        return Err(anyhow!("Clipboard journal has an invalid header"));
+    }
+
+    let mut records = Vec::new();
+    let mut pos = JOURNAL_MAGIC.len();
+    while data.len() - pos >= 4 {
+        let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        if !(JOURNAL_NONCE_LEN..=MAX_RECORD_LEN).contains(&len) {
+            return Err(anyhow!("Clipboard journal record has an invalid length"));
+        }
+        if data.len() - pos - 4 < len {
+            break; // Torn tail
+        }
+        records.push(&data[pos + 4..pos + 4 + len]);
+        pos += 4 + len;
+    }
+    Ok((records, pos))
+}
+
+/// Decrypts every complete record in the journal. A missing journal is empty.
+pub fn read_journal(path: &Path, master_key: &MasterKey) -> Result<Vec<JournalOp>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read(path)?;
+    let (records, _) = split_records(&data)?;
+
+    let key = journal_key(master_key);
+    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+
+    let mut ops = Vec::with_capacity(records.len());
+    for (seq, record) in records.iter().enumerate() {
+        let (nonce, ciphertext) = record.split_at(JOURNAL_NONCE_LEN);
+        let aad = record_aad(seq as u64);
+        let plaintext = zeroize::Zeroizing::new(
+            cipher
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| anyhow!("Clipboard journal record {} failed authentication", seq))?,
+        );
+        ops.push(serde_json::from_slice(&plaintext)?);
+    }
+    Ok(ops)
+}
+
+/// Appends operations to the journal. Returns the total number of records afterwards,
+/// so the caller can decide whether to compact.
+pub fn append_journal(path: &Path, master_key: &MasterKey, ops: &[JournalOp]) -> Result<usize> {
+    let existing = if path.exists() { fs::read(path)? } else { Vec::new() };
+    let (records, valid_len) = split_records(&existing)?;
+    let mut seq = records.len() as u64;
+    let torn = valid_len < existing.len();
+
+    let key = journal_key(master_key);
+    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+
+    let mut out = Vec::new();
+    if existing.is_empty() {
+        out.extend_from_slice(JOURNAL_MAGIC);
+    }
+    for op in ops {
+        let plaintext = zeroize::Zeroizing::new(serde_json::to_vec(op)?);
+        let mut nonce = [0u8; JOURNAL_NONCE_LEN];
+        OsRng
+            .try_fill_bytes(&mut nonce)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        let aad = record_aad(seq);
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("Failed to encrypt journal record"))?;
+
+        let len = JOURNAL_NONCE_LEN + ciphertext.len();
+        if len > MAX_RECORD_LEN {
+            return Err(anyhow!("Clipboard entry is too large"));
+        }
+        out.extend_from_slice(&(len as u32).to_le_bytes());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        seq += 1;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if torn {
+        // Drop the half-written record left by a crash before appending after it.
+        file.set_len(valid_len as u64)?;
+    }
+    file.write_all(&out)?;
+    file.sync_all()?;
+    Ok(seq as usize)
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -86,17 +278,18 @@ impl ClipboardVault {
 // This function uses lightweight rules and strict bounds to analyze text.
 // SECURITY: Avoiding overly complex regex prevents "Regular Expression Denial of Service"
 // (ReDoS) attacks where a massive clipboard payload could freeze the application.
+// The patterns are compiled once, in the registry (regexes.rs), which also sets the
+// length limits.
 
 /// Analyzes raw text to guess what type of sensitive data it might be.
 pub fn analyze_content(text: &str) -> Option<String> {
     // SECURITY: Fast-path exit for massive text blobs to prevent CPU exhaustion.
-    if text.len() > 100_000 {
+    if text.len() > regexes::MAX_CLASSIFY_LEN {
         return Some("Text".to_string());
     }
 
     // 1. Credit Cards (Bounded)
-    let card_regex = Regex::new(r"\b\d{4}[ -]?\d{4}[ -]?\d{4}[ -]?\d{1,7}\b").unwrap();
-    if card_regex.is_match(text) {
+    if regexes::get(Pattern::CardNumber).is_match(text) {
         let nums = text.chars().filter(|c| c.is_numeric()).count();
         if (13..=19).contains(&nums) {
             return Some("Credit Card".to_string());
@@ -104,8 +297,7 @@ pub fn analyze_content(text: &str) -> Option<String> {
     }
 
     // 2. IBAN (International Bank Account Number)
-    let iban_regex = Regex::new(r"(?i)\b[A-Z]{2}\d{2}(?:[A-Z0-9]{4}[ ]?){3,7}\b").unwrap();
-    if iban_regex.is_match(text) {
+    if contains_iban(text) {
         return Some("Bank Info".to_string());
     }
 
@@ -136,8 +328,13 @@ pub fn analyze_content(text: &str) -> Option<String> {
         return Some("API Key".to_string());
     }
 
+    // 5. National ID numbers, from the enabled country packs (pattern_packs.rs)
+    if let Some(category) = pattern_packs::detect(text) {
+        return Some(category);
+    }
+
     // -------------------------------------------------------------
-    // 5. Passwords
+    // 6. Passwords
     // -------------------------------------------------------------
     // If it looks like a high-entropy string, we classify it as a password FIRST.
     // This prevents "p@.ssw0rd123!" from being caught by the weaker Email rule below.
@@ -153,13 +350,13 @@ pub fn analyze_content(text: &str) -> Option<String> {
         return Some("Password".to_string());
     }
 
-    // 6. Web Links
+    // 7. Web Links
     if text.starts_with("http") || (text.starts_with("www.") && text.contains('.')) {
         return Some("Link".to_string());
     }
 
     // -------------------------------------------------------------
-    // 7. Emails
+    // 8. Emails
     // -------------------------------------------------------------
     // Only fires if the string failed the strong Password check above.
     if !has_space
@@ -173,10 +370,148 @@ pub fn analyze_content(text: &str) -> Option<String> {
         }
     }
 
+    // 9. Phone numbers and street addresses, in any script
+    if looks_like_phone(text) {
+        return Some("Phone Number".to_string());
+    }
+    if looks_like_address(text) {
+        return Some("Address".to_string());
+    }
+
     // Fallback
     Some("Text".to_string())
 }
 
+/// IBANs printed in groups, run together or typed in lowercase. A candidate must pass the
+/// mod-97 check, which rules out reference numbers of the same shape.
+fn contains_iban(text: &str) -> bool {
+    let candidate = regexes::get(Pattern::IbanCandidate);
+    candidate.find_iter(text).take(16).any(|m| {
+        // The match can run on into the words after the IBAN; drop a group at a time.
+        let groups: Vec<&str> = m.as_str().split(' ').collect();
+        (1..=groups.len())
+            .rev()
+            .any(|end| pattern_packs::iban_valid(&groups[..end].concat()))
+    })
+}
+
+/// Phone numbers (E.164 allows 7 to 15 digits): an international number, written with
+/// `+` or `00`, anywhere in the text; or a copied national number on its own, written
+/// with the usual separators.
+fn looks_like_phone(text: &str) -> bool {
+    let international = regexes::get(Pattern::PhoneInternational);
+    let national = regexes::get(Pattern::PhoneNational);
+    let not_phone = regexes::get(Pattern::NotPhone);
+    let digit_count = |s: &str| s.chars().filter(|c| c.is_ascii_digit()).count();
+
+    let found = international.captures_iter(text).take(16).any(|caps| {
+        let prefix = if &caps[1] == "00" { 2 } else { 0 };
+        (7..=15).contains(&(digit_count(&caps[0]) - prefix))
+    });
+    if found {
+        return true;
+    }
+    let text = text.trim();
+    national.is_match(text) && !not_phone.is_match(text) && (7..=15).contains(&digit_count(text))
+}
+
+/// Street words, lowercased and space-separated, in the scripts the app is used with.
+const STREET_WORDS: &[&str] = &[
+    // English
+    "street st st. avenue ave ave. road rd rd. boulevard blvd lane drive court place square",
+    // French, Spanish, Portuguese, Italian
+    "rue chemin impasse allée calle avenida plaza paseo camino carrer rua travessa praça",
+    "via viale piazza corso",
+    // German, Dutch, Nordic
+    "straße strasse str. weg platz allee gasse straat laan plein gatan vägen gade vej veien",
+    // Polish, Czech, Turkish
+    "ulica ul. aleja ulice náměstí sokak sok. cadde cad. caddesi sokağı",
+    // Greek
+    "οδός οδος οδ. λεωφόρος λεωφορος λεωφ. πλατεία πλατεια",
+    // Cyrillic
+    "улица ул. проспект пр. пр-т переулок пер. площадь пл. бульвар шоссе вулиця вул.",
+];
+
+/// Endings of compound street names ("Hauptstraße", "Kalverstraat", "Storgatan").
+const STREET_SUFFIXES: &[&str] = &[
+    "straße", "strasse", "str.", "gasse", "weg", "allee", "platz", "straat", "laan", "gracht",
+    "gatan", "vägen", "gade", "vej", "veien", "caddesi", "sokağı",
+];
+
+/// Street addresses: a street word close to a house number, with a capitalised name
+/// among them ("221B Baker Street", "Via Roma 5", "Οδός Ερμού 12", "ул. Тверская, д. 7").
+/// Chinese, Japanese and Korean addresses are matched by their block and number markers.
+fn looks_like_address(text: &str) -> bool {
+    const WINDOW: usize = 3;
+    if text.chars().count() > 300 || text.lines().count() > 6 {
+        return false;
+    }
+    if regexes::get(Pattern::CjkAddress).is_match(text) {
+        return true;
+    }
+
+    let tokens: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .collect();
+    let is_street = |token: &str| {
+        let lower = token.to_lowercase();
+        STREET_WORDS
+            .iter()
+            .any(|words| words.split(' ').any(|w| w == lower))
+            || STREET_SUFFIXES
+                .iter()
+                .any(|s| lower.len() > s.len() && lower.ends_with(s))
+    };
+    let is_number = |token: &str| {
+        token.len() <= 6
+            && token.starts_with(|c: char| c.is_ascii_digit())
+            && token
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '/')
+    };
+    let is_name = |token: &str| token.starts_with(char::is_uppercase);
+
+    tokens.iter().enumerate().any(|(i, token)| {
+        if !is_street(token) {
+            return false;
+        }
+        let window = &tokens[i.saturating_sub(WINDOW)..(i + WINDOW + 1).min(tokens.len())];
+        window.iter().any(|t| is_number(t)) && window.iter().any(|t| is_name(t))
+    })
+}
+
+/// Replaces the characters `hide` selects with '•', except the last `keep` of them.
+fn mask_chars(text: &str, keep: usize, hide: impl Fn(char) -> bool) -> String {
+    let total = text.chars().filter(|&c| hide(c)).count();
+    let mut seen = 0;
+    text.chars()
+        .map(|c| {
+            if !hide(c) {
+                return c;
+            }
+            seen += 1;
+            if seen + keep > total {
+                c
+            } else {
+                '•'
+            }
+        })
+        .collect()
+}
+
+/// Truncates at 60 characters and removes newlines so the preview fits on one line in
+/// the UI. Counts characters, not bytes: text in Greek or Cyrillic is two bytes a letter.
+fn one_line_preview(text: &str) -> String {
+    const MAX_CHARS: usize = 60;
+    if text.chars().count() > MAX_CHARS {
+        let truncated: String = text.chars().take(MAX_CHARS).collect();
+        format!("{}...", truncated.replace("\n", " ").trim())
+    } else {
+        text.replace("\n", " ").trim().to_string()
+    }
+}
+
 /// Creates a new clipboard entry, automatically categorized and redacted for UI safety.
 pub fn create_entry(text: &str) -> ClipboardEntry {
     // 1. Guess the category
@@ -198,8 +533,8 @@ pub fn create_entry(text: &str) -> ClipboardEntry {
         }
         "API Key" | "Secret" | "Password" => {
             // Show only the first 6 characters, mask the rest
-            if text.len() > 6 {
-                format!("{}...", &text[0..6])
+            if text.chars().count() > 6 {
+                format!("{}...", text.chars().take(6).collect::<String>())
             } else {
                 "***".to_string()
             }
@@ -212,17 +547,15 @@ pub fn create_entry(text: &str) -> ClipboardEntry {
                 "****".to_string()
             }
         }
-        _ => {
-            // Standard Text / Links: No masking needed, just truncate at 60 chars
-            // and remove newlines so it fits nicely on one line in the UI.
-            let max_len = 60;
-            if text.len() > max_len {
-                let truncated = &text[0..max_len];
-                format!("{}...", truncated.replace("\n", " ").trim())
-            } else {
-                text.replace("\n", " ").trim().to_string()
-            }
+        // Phone numbers and addresses keep their shape; the digits are hidden.
+        "Phone Number" => one_line_preview(&mask_chars(text, 2, |c| c.is_numeric())),
+        "Address" => one_line_preview(&mask_chars(text, 0, |c| c.is_numeric())),
+        // ID numbers from the pattern packs can mix letters in, so those go too.
+        c if pattern_packs::is_pack_category(c) => {
+            one_line_preview(&mask_chars(text, 2, |c| c.is_alphanumeric()))
         }
+        // Standard Text / Links: No masking needed
+        _ => one_line_preview(text),
     };
 
     // 3. Construct and return the safe entry
@@ -236,6 +569,197 @@ pub fn create_entry(text: &str) -> ClipboardEntry {
     }
 }
 
+// ==========================================
+// --- ENTRY TRANSFORMS ---
+// ==========================================
+// Clean-ups applied to an entry before it is put back on the clipboard. They run here
+// rather than in the webview so sensitive content is only handled by the backend.
+
+/// A transformation offered in the clipboard history's context menu.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardTransform {
+    /// Trailing whitespace on every line, and leading/trailing blank space overall.
+    Trim,
+    /// Tracking parameters and redirector wrappers in every http(s) link in the text
+    /// (see url_cleaner.rs).
+    StripTracking,
+    /// HTML or RTF markup to plain text.
+    PlainText,
+    Base64Encode,
+    /// Accepts standard and URL-safe alphabets, with or without padding.
+    Base64Decode,
+}
+
+fn html_to_text(html: &str) -> String {
+    let invisible = regexes::get(Pattern::HtmlInvisible);
+    let breaks = regexes::get(Pattern::HtmlBreaks);
+    let tags = regexes::get(Pattern::HtmlTag);
+    let blank_lines = regexes::get(Pattern::BlankLines);
+
+    let text = invisible.replace_all(html, "");
+    let text = breaks.replace_all(&text, "\n");
+    let text = tags.replace_all(&text, "");
+    let text = decode_html_entities(&text);
+    blank_lines.replace_all(&text, "\n\n").trim().to_string()
+}
+
+fn decode_html_entities(text: &str) -> String {
+    let entity = regexes::get(Pattern::HtmlEntity);
+    entity
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ if name.starts_with("#x") || name.starts_with("#X") => {
+                    u32::from_str_radix(&name[2..], 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                }
+                _ if name.starts_with('#') => name[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            };
+            decoded
+                .map(String::from)
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Extracts the text of an RTF document: control words and groups are dropped,
+/// `\par` / `\line` become newlines and `\'hh` escapes are decoded as Windows-1252.
+fn rtf_to_text(rtf: &str) -> String {
+    // Destinations whose content is not document text.
+    const SKIPPED: [&str; 6] = ["fonttbl", "colortbl", "stylesheet", "info", "pict", "*"];
+    let mut out = String::new();
+    let mut chars = rtf.chars().peekable();
+    // Per group depth: is its content skipped?
+    let mut skip_stack: Vec<bool> = vec![false];
+
+    while let Some(c) = chars.next() {
+        let skipping = *skip_stack.last().unwrap_or(&false);
+        match c {
+            '{' => skip_stack.push(skipping),
+            // The outermost level is never popped, even for an unbalanced '}'.
+            '}' => skip_stack.truncate((skip_stack.len() - 1).max(1)),
+            '\\' => match chars.peek().copied() {
+                Some(esc @ ('\\' | '{' | '}')) => {
+                    chars.next();
+                    if !skipping {
+                        out.push(esc);
+                    }
+                }
+                Some('\'') => {
+                    chars.next();
+                    let hex: String = chars.by_ref().take(2).collect();
+                    if let (Ok(byte), false) = (u8::from_str_radix(&hex, 16), skipping) {
+                        // Latin-1 matches Windows-1252 outside 0x80..0x9F.
+                        out.push(byte as char);
+                    }
+                }
+                Some('*') => {
+                    chars.next();
+                    if let Some(top) = skip_stack.last_mut() {
+                        *top = true;
+                    }
+                }
+                Some(_) => {
+                    let mut word = String::new();
+                    while let Some(&ch) = chars.peek() {
+                        if ch.is_ascii_alphabetic() {
+                            word.push(ch);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    // Optional numeric parameter, then one optional space delimiter.
+                    while chars
+                        .peek()
+                        .is_some_and(|ch| ch.is_ascii_digit() || *ch == '-')
+                    {
+                        chars.next();
+                    }
+                    if chars.peek() == Some(&' ') {
+                        chars.next();
+                    }
+                    if SKIPPED.contains(&word.as_str()) {
+                        if let Some(top) = skip_stack.last_mut() {
+                            *top = true;
+                        }
+                    } else if !skipping && matches!(word.as_str(), "par" | "line") {
+                        out.push('\n');
+                    } else if !skipping && word == "tab" {
+                        out.push('\t');
+                    }
+                }
+                None => {}
+            },
+            '\r' | '\n' => {}
+            _ if !skipping => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+fn base64_decode(text: &str) -> Result<String, String> {
+    use data_encoding::{BASE64, BASE64URL, BASE64URL_NOPAD, BASE64_NOPAD};
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = [BASE64, BASE64_NOPAD, BASE64URL, BASE64URL_NOPAD]
+        .iter()
+        .find_map(|encoding| encoding.decode(compact.as_bytes()).ok())
+        .ok_or("The entry is not valid Base64.")?;
+    String::from_utf8(bytes).map_err(|_| "The decoded data is binary, not text.".to_string())
+}
+
+/// Applies `op` to `text`.
+pub fn transform_text(text: &str, op: ClipboardTransform) -> Result<String, String> {
+    let rewrites = matches!(
+        op,
+        ClipboardTransform::StripTracking | ClipboardTransform::PlainText
+    );
+    if rewrites && text.len() > regexes::MAX_REWRITE_LEN {
+        return Err("The entry is too large to transform.".to_string());
+    }
+    Ok(match op {
+        ClipboardTransform::Trim => text
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string(),
+        ClipboardTransform::StripTracking => url_cleaner::clean_links_in_text(text),
+        ClipboardTransform::PlainText => {
+            if text.trim_start().starts_with("{\\rtf") {
+                rtf_to_text(text)
+            } else {
+                html_to_text(text)
+            }
+        }
+        ClipboardTransform::Base64Encode => data_encoding::BASE64.encode(text.as_bytes()),
+        ClipboardTransform::Base64Decode => base64_decode(text)?,
+    })
+}
+
+impl ClipboardEntry {
+    /// The entry with new content: category and preview are recomputed, identity,
+    /// timestamp and pin state are kept.
+    pub fn with_content(&self, text: &str) -> ClipboardEntry {
+        let mut entry = create_entry(text);
+        entry.id = self.id.clone();
+        entry.created_at = self.created_at;
+        entry.is_pinned = self.is_pinned;
+        entry
+    }
+}
+
 // ==========================================
 // --- TESTS ---
 // ==========================================
@@ -275,6 +799,90 @@ mod tests {
         assert!(result.unwrap_err().contains("already exists"));
     }
 
+    // --- Journal Tests ---
+
+    fn journal_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join("qre_clipboard_journal_tests");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_journal_roundtrip_and_replay() {
+        let path = journal_path("roundtrip.journal");
+        let mk = MasterKey([7u8; 32]);
+        let a = create_entry("first");
+        let b = create_entry("second");
+
+        append_journal(&path, &mk, &[JournalOp::Add { entry: a.clone() }]).unwrap();
+        let count = append_journal(
+            &path,
+            &mk,
+            &[
+                JournalOp::Add { entry: b.clone() },
+                JournalOp::SetPinned { id: b.id.clone(), pinned: true },
+                JournalOp::Remove { id: a.id.clone() },
+            ],
+        )
+        .unwrap();
+        assert_eq!(count, 4);
+
+        let ops = read_journal(&path, &mk).unwrap();
+        let mut vault = ClipboardVault::new();
+        for op in &ops {
+            vault.apply(op);
+        }
+        // Replaying twice (crash during compaction) gives the same result.
+        for op in &ops {
+            vault.apply(op);
+        }
+        assert_eq!(vault.entries.len(), 1);
+        assert_eq!(vault.entries[0].id, b.id);
+        assert!(vault.entries[0].is_pinned);
+
+        assert!(read_journal(&path, &MasterKey([8u8; 32])).is_err());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_journal_torn_tail_is_ignored_and_repaired() {
+        let path = journal_path("torn.journal");
+        let mk = MasterKey([1u8; 32]);
+        append_journal(&path, &mk, &[JournalOp::Remove { id: "x".into() }]).unwrap();
+
+        // Simulate a crash halfway through the next record.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2, 3]).unwrap();
+        drop(file);
+        assert_eq!(read_journal(&path, &mk).unwrap().len(), 1);
+
+        let count = append_journal(&path, &mk, &[JournalOp::Remove { id: "y".into() }]).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(read_journal(&path, &mk).unwrap().len(), 2);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_journal_rejects_reordered_records() {
+        let path = journal_path("reorder.journal");
+        let mk = MasterKey([2u8; 32]);
+        append_journal(&path, &mk, &[JournalOp::Remove { id: "a".into() }]).unwrap();
+        append_journal(&path, &mk, &[JournalOp::Remove { id: "b".into() }]).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        let (records, _) = split_records(&data).unwrap();
+        let mut swapped = JOURNAL_MAGIC.to_vec();
+        for r in [records[1], records[0]] {
+            swapped.extend_from_slice(&(r.len() as u32).to_le_bytes());
+            swapped.extend_from_slice(r);
+        }
+        fs::write(&path, swapped).unwrap();
+        assert!(read_journal(&path, &mk).is_err());
+        let _ = fs::remove_file(&path);
+    }
+
     // --- Analyzer / Heuristic Tests ---
 
     #[test]
@@ -329,6 +937,44 @@ mod tests {
         assert_eq!(analyze_content("user@example.com").unwrap(), "Email");
     }
 
+    #[test]
+    fn test_analyze_iban_phone_and_address() {
+        // Spaced, run together or lowercase, but the check digits must be right.
+        assert_eq!(
+            analyze_content("IBAN: gr16 0110 1250 0000 0001 2300 695 (Alpha)").unwrap(),
+            "Bank Info"
+        );
+        assert_eq!(
+            analyze_content("DE89370400440532013000").unwrap(),
+            "Bank Info"
+        );
+        assert_ne!(
+            analyze_content("DE89 3704 0044 0532 0130 01").unwrap(),
+            "Bank Info"
+        );
+
+        assert_eq!(
+            analyze_content("Call me on +30 210 123 4567").unwrap(),
+            "Phone Number"
+        );
+        assert_eq!(analyze_content("(212) 555-1234").unwrap(), "Phone Number");
+        assert_eq!(analyze_content("06.12.34.56.78").unwrap(), "Phone Number");
+        assert_eq!(analyze_content("2024-10-16").unwrap(), "Text");
+        assert_eq!(analyze_content("192.168.10.1").unwrap(), "Text");
+
+        for address in [
+            "221B Baker Street, London",
+            "Hauptstraße 5, 10115 Berlin",
+            "Οδός Ερμού 12, Αθήνα",
+            "ул. Тверская, 7, Москва",
+            "東京都港区六本木6丁目10番1号",
+            "北京市朝阳区建国路88号",
+        ] {
+            assert_eq!(analyze_content(address).unwrap(), "Address", "{}", address);
+        }
+        assert_eq!(analyze_content("send it via email 5 times").unwrap(), "Text");
+    }
+
     // --- Redaction / Preview Tests ---
 
     #[test]
@@ -358,6 +1004,88 @@ mod tests {
         assert!(entry.preview.ends_with("..."));
         assert!(entry.preview.len() <= 65); // 60 chars + "..."
     }
+
+    #[test]
+    fn test_redaction_phone_address_and_non_latin_text() {
+        let entry = create_entry("+30 210 123 4567");
+        assert_eq!(entry.preview, "+•• ••• ••• ••67");
+        let entry = create_entry("Οδός Ερμού 12, Αθήνα");
+        assert_eq!(entry.preview, "Οδός Ερμού ••, Αθήνα");
+        let entry = create_entry("ΑΦΜ 094014201");
+        assert_eq!(entry.category, "Tax ID");
+        assert_eq!(entry.preview, "••• •••••••01");
+
+        // Truncation counts characters, so multi-byte text is cut cleanly.
+        let entry = create_entry(&"Καλημέρα κόσμε ".repeat(8));
+        assert_eq!(entry.category, "Text");
+        assert!(entry.preview.ends_with("..."));
+    }
+
+    // --- Transform Tests ---
+
+    #[test]
+    fn test_strip_tracking_keeps_other_params() {
+        let text = "see https://shop.example/item?id=7&utm_source=news&fbclid=abc#reviews now";
+        assert_eq!(
+            transform_text(text, ClipboardTransform::StripTracking).unwrap(),
+            "see https://shop.example/item?id=7#reviews now"
+        );
+        assert_eq!(
+            transform_text(
+                "https://a.example/?UTM_medium=x&gclid=y",
+                ClipboardTransform::StripTracking
+            )
+            .unwrap(),
+            "https://a.example/"
+        );
+    }
+
+    #[test]
+    fn test_plain_text_from_html_and_rtf() {
+        let html =
+            "<html><head><title>t</title></head><body><p>Hello&nbsp;<b>world</b> &amp; co</p>\
+                    <script>alert(1)</script><div>line&#33;</div></body></html>";
+        assert_eq!(
+            transform_text(html, ClipboardTransform::PlainText).unwrap(),
+            "Hello world & co\nline!"
+        );
+        let rtf = r"{\rtf1\ansi{\fonttbl\f0 Arial;}\f0 Caf\'e9 \b bold\b0\par next \{x\}}";
+        assert_eq!(
+            transform_text(rtf, ClipboardTransform::PlainText).unwrap(),
+            "Café bold\nnext {x}"
+        );
+    }
+
+    #[test]
+    fn test_trim_and_base64_round_trip() {
+        assert_eq!(
+            transform_text("  \n  a  \nb\t\n\n", ClipboardTransform::Trim).unwrap(),
+            "a\nb"
+        );
+        let encoded = transform_text("secret ✓", ClipboardTransform::Base64Encode).unwrap();
+        assert_eq!(
+            transform_text(&encoded, ClipboardTransform::Base64Decode).unwrap(),
+            "secret ✓"
+        );
+        // URL-safe alphabet without padding.
+        assert_eq!(
+            transform_text("Pz8-Pz8", ClipboardTransform::Base64Decode).unwrap(),
+            "??>??"
+        );
+        assert!(transform_text("not base64!", ClipboardTransform::Base64Decode).is_err());
+        assert!(transform_text("//8=", ClipboardTransform::Base64Decode).is_err());
+    }
+
+    #[test]
+    fn test_with_content_keeps_identity() {
+        let mut original = create_entry("https://example.com/?utm_source=x");
+        original.is_pinned = true;
+        let updated = original.with_content("SuperSecretPassword123!");
+        assert_eq!(updated.id, original.id);
+        assert_eq!(updated.created_at, original.created_at);
+        assert!(updated.is_pinned);
+        assert_eq!(updated.category, "Password");
+    }
 }
 
 // --- END OF FILE clipboard_store.rs ---
\ No newline at end of file
@@ -1,15 +1,36 @@
 // --- START OF FILE files.rs ---
 
+use crate::archive;
+use crate::batch_journal::{self, ActiveBatch, BatchJournal, BatchKind, InterruptedBatch, PartialKind, PartialOutput};
+use crate::container_meta::{self, ContainerMetadata, SearchIndex};
 use crate::crypto;
 use crate::crypto_stream;
+use crate::diagnosis;
+use crate::drive_health;
+use crate::entropy::{self, EntropyOptions, EntropyReport};
+use crate::formats;
+use crate::i18n;
+use crate::paper_backup;
+use crate::parity::ParityMeta;
+use crate::power;
+use crate::recipient;
+use crate::recovery_risk;
+use crate::renamer;
+use crate::salvage;
+use crate::shamir::{self, SplitKeyOptions};
+use super::guard::{rate_limit, Job, JobGuard, AUTH_RATE, DESTRUCTIVE_RATE};
+use super::safe_path::{PathPolicy, SafePath, SymlinkPolicy, MAX_IN_MEMORY_FILE_BYTES};
 use crate::shredder;
 use crate::state::SessionState;
+use crate::subkeys::KeyPurpose;
 use crate::utils;
 use sha2::{Digest, Sha256};
 use std::fs;
+use rand::{rngs::OsRng, TryRngCore};
 use std::io::Read;
 use std::path::{Component, Path, PathBuf};
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter};
+use zeroize::Zeroizing;
 
 #[cfg(not(target_os = "android"))]
 use std::process::Command;
@@ -26,6 +47,15 @@ pub struct BatchItemResult {
     pub message: String,
 }
 
+/// Records the outcome of a lock or unlock run in the diagnostic log (see logging.rs).
+fn log_batch(operation: &str, results: &[BatchItemResult]) {
+    let failed: Vec<&BatchItemResult> = results.iter().filter(|r| !r.success).collect();
+    tracing::info!(operation, files = results.len(), failed = failed.len(), "Batch finished");
+    for item in failed {
+        tracing::warn!(operation, file = %item.name, "{}", item.message);
+    }
+}
+
 #[cfg_attr(test, allow(dead_code))]
 pub(crate) fn is_already_compressed(filename: &str) -> bool {
     let ext = Path::new(filename)
@@ -60,6 +90,25 @@ pub(crate) fn is_already_compressed(filename: &str) -> bool {
     )
 }
 
+/// Compression presets offered in the UI, cheapest first.
+pub const COMPRESSION_PRESETS: [&str; 3] = ["store", "auto", "extreme"];
+
+/// Maps a UI compression preset to a zstd level. "auto" spends little effort on
+/// formats that are already compressed (media, archives, PDFs).
+pub(crate) fn compression_level(mode: &str, filename: &str) -> i32 {
+    match mode {
+        "store" => 0,
+        "extreme" => 19,
+        _ => {
+            if is_already_compressed(filename) {
+                1
+            } else {
+                3
+            }
+        }
+    }
+}
+
 pub(crate) fn is_system_critical(path: &Path) -> bool {
     let path_str = path.to_string_lossy().to_lowercase();
 
@@ -84,26 +133,37 @@ pub(crate) fn is_system_critical(path: &Path) -> bool {
 
 pub(crate) fn reject_critical_path(path: &Path) -> Result<(), String> {
     if path.components().any(|c| c == Component::ParentDir) {
-        return Err("Path traversal not allowed: path must not contain '..'".to_string());
+        return Err(i18n::AppError::new(i18n::ErrorCode::PathTraversal).into());
     }
     if is_system_critical(path) {
-        return Err(format!(
-            "Access Denied: '{}' is a protected system path.",
-            path.display()
-        ));
+        return Err(i18n::AppError::new(i18n::ErrorCode::PathProtected).with("path", path.display()).into());
     }
     Ok(())
 }
 
 pub(crate) fn reject_path_traversal(path: &Path) -> Result<(), String> {
     if path.components().any(|c| c == Component::ParentDir) {
-        return Err("Path traversal not allowed: path must not contain '..'".to_string());
+        return Err(i18n::AppError::new(i18n::ErrorCode::PathTraversal).into());
     }
     Ok(())
 }
 
 // --- CRYPTO LOGIC ---
 
+/// Shows the paranoid-mode panel how many bits each opted-in source would contribute.
+/// The seed itself is discarded; `lock_file` collects fresh samples when it runs.
+#[tauri::command]
+pub fn preview_entropy_sources(
+    extra_entropy: Option<Vec<u8>>,
+    entropy_sources: Option<EntropyOptions>,
+) -> CommandResult<EntropyReport> {
+    let (_, report) =
+        entropy::mix_sources(extra_entropy.as_deref(), &entropy_sources.unwrap_or_default())?;
+    Ok(report)
+}
+
+// Tauri commands take their arguments flat from the frontend's invoke() payload.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 pub async fn lock_file(
     app: AppHandle,
@@ -112,8 +172,42 @@ pub async fn lock_file(
     keyfile_path: Option<String>,
     keyfile_bytes: Option<Vec<u8>>,
     extra_entropy: Option<Vec<u8>>,
+    entropy_sources: Option<EntropyOptions>,
     compression_mode: Option<String>,
+    search_index: Option<bool>,
+    labels: Option<Vec<String>>,
+    padding: Option<crypto_stream::Padding>,
+    bundle: Option<bool>,
+    expiry: Option<crypto_stream::Expiry>,
+    split_key: Option<SplitKeyOptions>,
+    parity_percent: Option<u8>,
+    post_quantum: Option<bool>,
+    batch_id: Option<String>,
 ) -> CommandResult<Vec<BatchItemResult>> {
+    state.ensure_writable("local")?;
+    let labels = container_meta::normalize_labels(&labels.unwrap_or_default())?;
+    if let Some(p) = &padding {
+        p.validate().map_err(|e| e.to_string())?;
+    }
+    if let Some(e) = &expiry {
+        e.validate().map_err(|e| e.to_string())?;
+        if e.expires_at.is_some_and(|at| at <= crate::timelock_clock::system_time_secs()) {
+            return Err(i18n::AppError::new(i18n::ErrorCode::ExpiryInPast).into());
+        }
+    }
+    if let Some(percent) = parity_percent {
+        ParityMeta::new(percent).map_err(|e| e.to_string())?;
+    }
+    if let Some(split) = &split_key {
+        if split.threshold < 2 || split.threshold > split.shares {
+            return Err(i18n::AppError::new(i18n::ErrorCode::SplitKeyThresholdInvalid).into());
+        }
+    }
+    let share_dir = split_key
+        .as_ref()
+        .and_then(|s| s.output_dir.as_ref())
+        .map(|d| SafePath::new(d, PathPolicy::directory()))
+        .transpose()?;
     let keyfile_hash = if let Some(bytes) = keyfile_bytes {
         let mut hasher = Sha256::new();
         hasher.update(&bytes);
@@ -122,22 +216,86 @@ pub async fn lock_file(
         utils::process_keyfile(keyfile_path)?
     };
 
-    let raw_entropy: Option<Vec<u8>> = extra_entropy;
+    // Vault policy (keyfile / paranoid entropy) is enforced here, not trusted to the UI.
+    super::vault::load_vault_policy(&app, "local")?
+        .check_encryption(keyfile_hash.is_some(), extra_entropy.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    // Paranoid mode: mix the user's input with any opted-in extra sources into one pool.
+    let entropy_sources = entropy_sources.unwrap_or_default();
+    let (entropy_pool, entropy_report) =
+        entropy::mix_sources(extra_entropy.as_deref(), &entropy_sources)?;
+    if !entropy_report.sources.is_empty() {
+        let _ = app.emit("entropy-report", &entropy_report);
+    }
     let mode_str = compression_mode.unwrap_or("auto".to_string());
+    let with_search_index = search_index.unwrap_or(false);
 
     let vaults_arc = state.vaults.clone();
     let portable_mounts_arc = state.portable_mounts.clone();
 
-    tauri::async_runtime::spawn_blocking(move || {
-        let mut results = Vec::new();
+    if bundle.unwrap_or(false) {
+        if expiry.is_some() {
+            return Err(i18n::AppError::new(i18n::ErrorCode::ExpiryNeedsSingleFile).into());
+        }
+        if split_key.is_some() {
+            return Err(i18n::AppError::new(i18n::ErrorCode::SplitKeyNeedsSingleFile).into());
+        }
+        if parity_percent.is_some() {
+            return Err(i18n::AppError::new(i18n::ErrorCode::ParityNeedsSingleFile).into());
+        }
+        if post_quantum.unwrap_or(false) {
+            return Err(i18n::AppError::new(i18n::ErrorCode::PostQuantumNeedsSingleFile).into());
+        }
+        return lock_bundle(app, vaults_arc, portable_mounts_arc, file_paths, keyfile_hash, entropy_pool, mode_str).await;
+    }
+
+    // Post-quantum (V12) files also wrap their key for the vault's identity keypair,
+    // which is created on first use. Split-key files have no vault to bind to.
+    let identity = if post_quantum.unwrap_or(false) {
+        if split_key.is_some() {
+            return Err(i18n::AppError::new(i18n::ErrorCode::SplitKeyWithIdentity).into());
+        }
+        Some(super::vault::ensure_vault_identity(&app, "local", &state)?)
+    } else {
+        None
+    };
 
-        for (file_index, file_path) in file_paths.into_iter().enumerate() {
-            let path = Path::new(&file_path);
+    let options = serde_json::json!({
+        "entropySources": entropy_sources,
+        "compressionMode": mode_str,
+        "searchIndex": with_search_index,
+        "labels": labels,
+        "padding": padding,
+        "expiry": expiry,
+        "splitKey": split_key,
+        "parityPercent": parity_percent,
+        "postQuantum": identity.is_some(),
+    });
+    let mut batch = open_batch(&app, BatchKind::Lock, batch_id, &file_paths, options)?;
 
-            if let Err(e) = reject_critical_path(path) {
-                results.push(BatchItemResult { name: path.to_string_lossy().to_string(), success: false, message: e });
+    tauri::async_runtime::spawn_blocking(move || {
+        let _power = power::PowerHold::acquire("Encrypting files");
+        let mut results = Vec::new();
+        let volumes = mounted_volumes();
+        let mut removed: Option<MountedVolume> = None;
+
+        for (file_index, raw_path) in file_paths.into_iter().enumerate() {
+            journal_item(&mut batch, &results, &raw_path);
+            power::wait_for_power(None);
+            if let Some(drive) = removed.as_ref().filter(|d| Path::new(&raw_path).starts_with(&d.mount_point)) {
+                results.push(BatchItemResult { name: raw_path, success: false, message: format!("Skipped: drive '{}' was removed.", drive.name) });
                 continue;
             }
+            let safe = match SafePath::new(&raw_path, PathPolicy::existing_entry().symlinks(SymlinkPolicy::Follow)) {
+                Ok(p) => p,
+                Err(e) => {
+                    results.push(BatchItemResult { name: raw_path, success: false, message: e });
+                    continue;
+                }
+            };
+            let path: &Path = &safe;
+            let file_path = path.to_string_lossy().to_string();
 
             {
                 let mounts = portable_mounts_arc.lock().unwrap_or_else(|e| e.into_inner());
@@ -152,15 +310,25 @@ pub async fn lock_file(
                 }
             }
 
-            let vault_id = "local".to_string();
+            // Split-key files get a key of their own, which only exists as shares afterwards.
+            let file_key = match split_key.as_ref().map(|_| shamir::new_file_key()).transpose() {
+                Ok(k) => k,
+                Err(e) => {
+                    results.push(BatchItemResult { name: path.to_string_lossy().to_string(), success: false, message: e.to_string() });
+                    continue;
+                }
+            };
+            let vault_id = file_key.as_ref().map(shamir::vault_id).unwrap_or_else(|| "local".to_string());
 
-            let master_key = {
+            let master_key = if let Some(key) = file_key {
+                key
+            } else {
                 let guard = match vaults_arc.lock() {
                     Ok(g) => g,
                     Err(poisoned) => {
                         let mut p = poisoned.into_inner();
                         p.clear();
-                        return Err("Session state corrupted.".to_string());
+                        return Err(i18n::AppError::new(i18n::ErrorCode::SessionCorrupted).into());
                     }
                 };
                 match guard.get(&vault_id) {
@@ -175,11 +343,7 @@ pub async fn lock_file(
             let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
             utils::emit_progress(&app, &format!("Preparing: {}", filename), 5);
 
-            let level = match mode_str.as_str() {
-                "store" => 0,
-                "extreme" => 19,
-                _ => { if is_already_compressed(&filename) { 1 } else { 3 } }
-            };
+            let level = compression_level(&mode_str, &filename);
 
             let (input_path_str, is_temp) = if path.is_dir() {
                 let parent = path.parent().unwrap_or(Path::new("."));
@@ -187,6 +351,7 @@ pub async fn lock_file(
                 let temp_zip_path = utils::get_unique_path(&parent.join(&temp_zip_name));
 
                 utils::emit_progress(&app, &format!("Zipping Folder: {}", filename), 10);
+                if let Some(batch) = batch.as_mut() { let _ = batch.add_partial(&temp_zip_path, PartialKind::Incomplete); }
                 if let Err(e) = utils::zip_directory_to_file(path, &temp_zip_path) {
                     results.push(BatchItemResult { name: filename.to_string(), success: false, message: format!("Zip failed: {}", e) });
                     continue;
@@ -197,12 +362,22 @@ pub async fn lock_file(
             };
 
             let raw_output = format!("{}.qre", file_path);
-            let final_path = utils::get_unique_path(Path::new(&raw_output));
+            // An interrupted encryption of this same file carries on where it stopped;
+            // a stale partial (input changed since) is thrown away. A split-key partial
+            // cannot be resumed: its key was never saved.
+            let resume = split_key.is_none() && !is_temp && crypto_stream::can_resume(&input_path_str, &raw_output)
+                && crypto_stream::read_stream_header(&raw_output).is_ok_and(|(_, h)| h.kem_wrap.is_some() == identity.is_some());
+            if !resume { crypto_stream::discard_checkpoint(&raw_output); }
+            let final_path = if resume { std::path::PathBuf::from(&raw_output) } else { utils::get_unique_path(Path::new(&raw_output)) };
             let final_path_str = final_path.to_string_lossy().to_string();
+            if let Some(batch) = batch.as_mut() {
+                let kind = if split_key.is_none() && !is_temp { PartialKind::Resumable } else { PartialKind::Incomplete };
+                let _ = batch.add_partial(&final_path, kind);
+            }
 
-            let entropy_seed: Option<[u8; 32]> = raw_entropy.as_ref().map(|bytes| {
+            let entropy_seed: Option<[u8; 32]> = entropy_pool.as_ref().map(|pool| {
                 let mut hasher = Sha256::new();
-                hasher.update(bytes);
+                hasher.update(&pool[..]);
                 hasher.update((file_index as u64).to_le_bytes());
                 hasher.finalize().into()
             });
@@ -218,148 +393,1752 @@ pub async fn lock_file(
                 }
             };
 
-let encryption_result = crypto_stream::encrypt_file_stream(
-    &input_path_str, &final_path_str, &master_key, &vault_id, keyfile_hash.as_deref(), None, entropy_seed, level, progress_cb,
-);
+            // Optional labels and encrypted search index (built from the plaintext before it is locked).
+            let metadata = container_meta::build_for_file(path, with_search_index && !is_temp, &labels)
+                .and_then(|m| m.to_bytes().ok());
+
+let encryption_result = if resume {
+    crypto_stream::resume_file_stream(&input_path_str, &final_path_str, &master_key, keyfile_hash.as_deref(), identity.as_ref(), progress_cb)
+} else {
+    crypto_stream::encrypt_file_stream_with_metadata(
+        &input_path_str, &final_path_str, &master_key, &vault_id, keyfile_hash.as_deref(), None, entropy_seed, level, metadata.as_deref(), padding, expiry, parity_percent, None, identity.as_ref().map(|i| i.public()), progress_cb,
+    )
+};
+
+            if is_temp { let _ = fs::remove_file(&input_path_str); }
+
+            match (encryption_result, &split_key) {
+                (Ok(_), Some(split)) => {
+                    let dir = share_dir.as_ref().map(|d| d.to_path_buf()).unwrap_or_else(|| final_path.parent().unwrap_or(Path::new(".")).to_path_buf());
+                    match write_key_shares(&final_path, &master_key, split, &dir) {
+                        Ok(()) => results.push(BatchItemResult {
+                            name: filename.to_string(),
+                            success: true,
+                            message: format!("Locked. {} key shares saved to '{}'; any {} of them open the file.", split.shares, dir.display(), split.threshold),
+                        }),
+                        Err(e) => {
+                            // Without its shares the container could never be opened again.
+                            let _ = fs::remove_file(&final_path);
+                            results.push(BatchItemResult { name: filename.to_string(), success: false, message: format!("Could not save key shares: {}", e) });
+                        }
+                    }
+                }
+                (Ok(_), None) => results.push(BatchItemResult { name: filename.to_string(), success: true, message: if resume { "Locked (resumed)".into() } else { "Locked".into() } }),
+                (Err(e), _) => {
+                    // A pulled drive keeps its partial output and checkpoint: plugging it
+                    // back in and locking again resumes from the last checkpoint.
+                    if let Some(drive) = removed_drive(&[path], &volumes, &mounted_volumes()) {
+                        let resumable = split_key.is_none() && crypto_stream::checkpoint_path(&final_path_str).exists();
+                        let message = if resumable {
+                            format!("Drive '{}' was removed while encrypting. Reconnect it and lock the file again to resume.", drive.name)
+                        } else {
+                            format!("Drive '{}' was removed while encrypting. Reconnect it, delete the incomplete '{}' and lock the file again.", drive.name, final_path.file_name().unwrap_or_default().to_string_lossy())
+                        };
+                        let _ = app.emit("drive-removed", drive.mount_point.to_string_lossy().to_string());
+                        results.push(BatchItemResult { name: filename.to_string(), success: false, message });
+                        removed = Some(drive);
+                        continue;
+                    }
+                    // A failed resume keeps the partial output: retrying with the right
+                    // keyfile can still finish it.
+                    if !resume {
+                        crypto_stream::discard_checkpoint(&final_path_str);
+                        let _ = fs::remove_file(&final_path);
+                    }
+                    results.push(BatchItemResult { name: filename.to_string(), success: false, message: e.to_string() });
+                }
+            }
+        }
+        if let Some(batch) = batch {
+            let _ = batch.complete();
+        }
+        log_batch("lock", &results);
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Saves each share of a split-key container's key as `<container>.share-<i>-of-<n>.txt`
+/// and a QR code of the same text (`.svg`) in `dir`. Nothing is left behind on failure.
+fn write_key_shares(container: &Path, key: &crate::keychain::MasterKey, split: &SplitKeyOptions, dir: &Path) -> Result<(), String> {
+    let shares = shamir::split(&key.0, split.threshold, split.shares).map_err(|e| e.to_string())?;
+    let container_name = container.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut written: Vec<PathBuf> = Vec::new();
+    let result = shares.iter().try_for_each(|share| {
+        let text = Zeroizing::new(share.encode());
+        let qr = crate::qr::generate_qr(crate::qr::QrOptions {
+            text: text.to_string(),
+            fg_color: "#000000".to_string(),
+            bg_color: "#ffffff".to_string(),
+            ecc: crate::qr::ErrorCorrectionLevel::Medium,
+            border: 4,
+        })
+        .map_err(|e| e.to_string())?;
+        let note = Zeroizing::new(format!(
+            "QRE key share {} of {} for \"{}\".\nAny {} shares together open the file. Keep this share apart from the others.\n\n{}\n",
+            share.index, share.count, container_name, share.threshold, text.as_str()
+        ));
+        let stem = format!("{}.share-{}-of-{}", container_name, share.index, share.count);
+        for (extension, contents) in [("txt", note.as_bytes()), ("svg", qr.svg.as_bytes())] {
+            let target = utils::get_unique_path(&dir.join(format!("{}.{}", stem, extension)));
+            fs::write(&target, contents).map_err(|e| e.to_string())?;
+            written.push(target);
+        }
+        Ok::<(), String>(())
+    });
+    if result.is_err() {
+        for path in &written {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result
+}
+
+/// Bundle mode of `lock_file`: all inputs go into one archive (see archive.rs) next to
+/// the first input, named after it, or "Archive.qre" for several. Returns a single row.
+async fn lock_bundle(
+    app: AppHandle,
+    vaults_arc: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, crate::keychain::MasterKey>>>,
+    portable_mounts_arc: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>,
+    file_paths: Vec<String>,
+    keyfile_hash: Option<Vec<u8>>,
+    entropy_pool: Option<entropy::EntropySeed>,
+    mode_str: String,
+) -> CommandResult<Vec<BatchItemResult>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _power = power::PowerHold::acquire("Encrypting archive");
+        power::wait_for_power(None);
+
+        let mut inputs = Vec::new();
+        for raw_path in &file_paths {
+            let safe = SafePath::new(raw_path, PathPolicy::existing_entry().symlinks(SymlinkPolicy::Follow))?;
+            let path_lower = safe.to_string_lossy().to_lowercase();
+            let mounts = portable_mounts_arc.lock().unwrap_or_else(|e| e.into_inner());
+            if mounts.keys().any(|m| path_lower.starts_with(&m.to_lowercase())) {
+                return Err(i18n::AppError::new(i18n::ErrorCode::GhostFileProtection).into());
+            }
+            inputs.push(safe.to_path_buf());
+        }
+        let first = inputs.first().ok_or_else(|| i18n::AppError::new(i18n::ErrorCode::NoFilesSelected))?.clone();
+
+        let master_key = vaults_arc
+            .lock()
+            .map_err(|_| i18n::AppError::new(i18n::ErrorCode::SessionCorrupted))?
+            .get("local")
+            .cloned()
+            .ok_or_else(|| i18n::AppError::new(i18n::ErrorCode::VaultLocked))?;
+
+        let files = archive::collect_inputs(&inputs).map_err(|e| e.to_string())?;
+        let archive_name = if inputs.len() == 1 {
+            format!("{}.qre", first.file_name().unwrap_or_default().to_string_lossy())
+        } else {
+            "Archive.qre".to_string()
+        };
+        let parent = first.parent().unwrap_or(Path::new("."));
+        let final_path = utils::get_unique_path(&parent.join(&archive_name));
+        let final_path_str = final_path.to_string_lossy().to_string();
+        let display_name = final_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let entropy_seed: Option<[u8; 32]> = entropy_pool.as_ref().map(|pool| {
+            let mut hasher = Sha256::new();
+            hasher.update(&pool[..]);
+            hasher.update(0u64.to_le_bytes());
+            hasher.finalize().into()
+        });
+
+        let app_handle = app.clone();
+        let progress_name = display_name.clone();
+        let progress_cb = move |processed: u64, total: u64| {
+            if total > 0 {
+                let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
+                utils::emit_progress(&app_handle, &format!("Encrypting: {}", progress_name), pct);
+            }
+        };
+
+        let result = archive::create_archive(
+            &files, &final_path_str, &master_key, "local", keyfile_hash.as_deref(), entropy_seed,
+            |entry_path| compression_level(&mode_str, entry_path), progress_cb,
+        );
+        match result {
+            Ok(()) => Ok(vec![BatchItemResult { name: display_name, success: true, message: format!("Locked {} files", files.len()) }]),
+            Err(e) => {
+                let _ = fs::remove_file(&final_path);
+                Ok(vec![BatchItemResult { name: display_name, success: false, message: e.to_string() }])
+            }
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Logs a wrong-keyfile unlock in the vault's audit log, where repeated failures raise
+/// an alert. Failures without a keyfile are the password's business (login_failed).
+fn record_keyfile_failure(app: &AppHandle, vault_id: &str, used_keyfile: bool, error: &str) {
+    use tauri::Manager;
+    if used_keyfile && error.starts_with("Decryption Denied") {
+        let user = app.state::<SessionState>().user_for(vault_id);
+        super::vault::record_audit(app, vault_id, &user, "keyfile_failed", None);
+    }
+}
+
+// --- BATCH UNLOCK DESTINATION ---
+
+/// What happens when an unlocked file would land on an existing one in the destination.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Keep both: the new file gets a " (1)" suffix, as when unlocking next to the source.
+    #[default]
+    Rename,
+    Overwrite,
+    Skip,
+}
+
+/// One line of the manifest written after a batch unlock into a destination.
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub source: String,
+    /// Where the plaintext ended up; `None` if it failed or was skipped.
+    pub output: Option<String>,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Deepest folder containing every source, so the outputs can mirror the layout below it.
+pub(crate) fn common_source_root<'a>(sources: impl IntoIterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut root: Option<PathBuf> = None;
+    for parent in sources.into_iter().filter_map(Path::parent) {
+        root = Some(match root {
+            None => parent.to_path_buf(),
+            Some(root) => root
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    root
+}
+
+/// Folder under `destination` for `source`: its parent's path below `root`. Sources with
+/// no relative path below `root` (e.g. on another drive) go to `destination` itself.
+pub(crate) fn structured_target_dir(destination: &Path, root: &Path, source: &Path) -> PathBuf {
+    source
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .filter(|rel| rel.components().all(|c| matches!(c, Component::Normal(_))))
+        .map(|rel| destination.join(rel))
+        .unwrap_or_else(|| destination.to_path_buf())
+}
+
+/// Moves a file decrypted into a staging folder to `target` under `policy`. Returns the
+/// final path, or `None` if it was skipped (the staged copy is left for the caller).
+pub(crate) fn place_unlocked_file(staged: &Path, target: &Path, policy: CollisionPolicy) -> std::io::Result<Option<PathBuf>> {
+    let final_path = match policy {
+        CollisionPolicy::Rename => utils::get_unique_path(target),
+        CollisionPolicy::Skip if target.exists() => return Ok(None),
+        CollisionPolicy::Skip => target.to_path_buf(),
+        CollisionPolicy::Overwrite if target.is_dir() => {
+            return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "A folder with that name already exists"));
+        }
+        // `rename` replaces an existing file on every platform.
+        CollisionPolicy::Overwrite => target.to_path_buf(),
+    };
+    fs::rename(staged, &final_path)?;
+    Ok(Some(final_path))
+}
+
+/// Moves the single file a container produced out of `staging` into `final_dir` and
+/// rewrites `item` to name where it went. Whatever is left in `staging` (a skipped file
+/// is plaintext) is shredded.
+fn finish_staged_unlock(
+    app: &AppHandle,
+    item: &mut BatchItemResult,
+    staging: &Path,
+    final_dir: &Path,
+    destination: &Path,
+    policy: CollisionPolicy,
+) -> Option<PathBuf> {
+    let mut placed = None;
+    if item.success {
+        let staged = fs::read_dir(staging).ok().and_then(|mut entries| entries.next()).and_then(Result::ok).map(|e| e.path());
+        match staged {
+            Some(staged) => {
+                let target = final_dir.join(staged.file_name().unwrap_or_default());
+                let shown = |p: &Path| p.strip_prefix(destination).unwrap_or(p).display().to_string();
+                match place_unlocked_file(&staged, &target, policy) {
+                    Ok(Some(final_path)) => {
+                        // Keep any note after the name, e.g. who sent a public-key container.
+                        let note = item.message.rfind(" (from ").map(|i| item.message[i..].to_string()).unwrap_or_default();
+                        item.message = format!("Unlocked: {}{}", shown(&final_path), note);
+                        placed = Some(final_path);
+                    }
+                    Ok(None) => item.message = format!("Skipped: {} already exists", shown(&target)),
+                    Err(e) => {
+                        item.success = false;
+                        item.message = format!("Could not move the output into place: {}", e);
+                    }
+                }
+            }
+            None => {
+                item.success = false;
+                item.message = "Decryption produced no output".to_string();
+            }
+        }
+    }
+    let leftover = fs::read_dir(staging).map(|mut entries| entries.next().is_some()).unwrap_or(false);
+    if leftover {
+        let _ = utils::shred_recursive(app, staging);
+    } else {
+        let _ = fs::remove_dir(staging);
+    }
+    placed
+}
+
+/// Decrypts containers. By default each output goes next to its source; with
+/// `output_dir` everything goes there instead, below the same relative folders as the
+/// sources when `preserve_structure` is set, with `on_collision` deciding about existing
+/// files (default: rename). `write_manifest` then also saves a JSON list of what went
+/// where into the destination and emits its path as `unlock-manifest`. `batch_id`
+/// continues an interrupted run (see `resume_interrupted_batch`).
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn unlock_file(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    file_paths: Vec<String>,
+    keyfile_path: Option<String>,
+    keyfile_bytes: Option<Vec<u8>>,
+    output_dir: Option<String>,
+    preserve_structure: Option<bool>,
+    on_collision: Option<CollisionPolicy>,
+    write_manifest: Option<bool>,
+    shares: Option<Vec<String>>,
+    batch_id: Option<String>,
+) -> CommandResult<Vec<BatchItemResult>> {
+    // Decrypting writes plaintext to disk — an export as far as guest sessions are concerned.
+    state.ensure_writable("local")?;
+    // A keyfile on removable media is only a second factor while the media is unplugged:
+    // once unlocking succeeds, the UI is prompted to suggest ejecting it.
+    let eject_hint = keyfile_path
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| locate_keyfile(Path::new(p), &mounted_volumes(), &system_root()))
+        .filter(|loc| loc.removable);
+    let keyfile_hash = if let Some(bytes) = keyfile_bytes {
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+        Some(hasher.finalize().to_vec())
+    } else {
+        utils::process_keyfile(keyfile_path)?
+    };
+    let options = serde_json::json!({
+        "outputDir": output_dir,
+        "preserveStructure": preserve_structure,
+        "onCollision": on_collision,
+        "writeManifest": write_manifest,
+    });
+    let output_dir = output_dir
+        .map(|d| SafePath::new(&d, PathPolicy::directory()))
+        .transpose()?;
+    let collision = on_collision.unwrap_or_default();
+    // Split-key containers: each file takes the shares that belong to it.
+    let shares: Vec<Zeroizing<String>> = shares.unwrap_or_default().into_iter().map(Zeroizing::new).collect();
+
+    let vaults_arc = state.vaults.clone();
+    let resumed = batch_id.is_some();
+    let mut batch = open_batch(&app, BatchKind::Unlock, batch_id, &file_paths, options)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        use tauri::Manager;
+        let _power = power::PowerHold::acquire("Decrypting files");
+        let state = app.state::<SessionState>();
+        let mut results = Vec::new();
+        let mut manifest = Vec::new();
+        let volumes = mounted_volumes();
+
+        let sources: Vec<(String, CommandResult<SafePath>)> = file_paths
+            .into_iter()
+            .map(|raw| {
+                let safe = SafePath::new(&raw, PathPolicy::read_file());
+                (raw, safe)
+            })
+            .collect();
+        let structure_root = if output_dir.is_some() && preserve_structure.unwrap_or(false) {
+            match batch.as_ref().filter(|_| resumed) {
+                // A resumed run keeps mirroring the layout of the whole original selection.
+                Some(batch) => {
+                    let all: Vec<SafePath> = batch.journal().items.iter().filter_map(|raw| SafePath::new(raw, PathPolicy::read_file()).ok()).collect();
+                    common_source_root(all.iter().map(|p| p.as_path()))
+                }
+                None => common_source_root(sources.iter().filter_map(|(_, s)| s.as_ref().ok().map(|p| p.as_path()))),
+            }
+        } else {
+            None
+        };
+
+        for (raw_path, safe) in sources {
+            journal_item(&mut batch, &results, &raw_path);
+            power::wait_for_power(None);
+            let safe = match safe {
+                Ok(p) => p,
+                Err(e) => {
+                    results.push(BatchItemResult { name: raw_path, success: false, message: e });
+                    continue;
+                }
+            };
+            let path: &Path = &safe;
+            let file_path = path.to_string_lossy().to_string();
+            let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+            utils::emit_progress(&app, &format!("Checking: {}", filename), 5);
+
+            // Nothing is written for a file whose vault is in a guest session.
+            if let Err(e) = ensure_container_writable(&state, path) {
+                results.push(BatchItemResult { name: filename, success: false, message: e });
+                continue;
+            }
+
+            let mut file = match fs::File::open(path) {
+                Ok(f) => f,
+                Err(e) => { results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }); continue; }
+            };
+
+            let mut ver_buf = [0u8; 4];
+            if file.read_exact(&mut ver_buf).is_err() {
+                results.push(BatchItemResult { name: filename, success: false, message: "Invalid file".into() });
+                continue;
+            }
+            let version = u32::from_le_bytes(ver_buf);
+
+            // Each container is decrypted into a private staging folder in its destination
+            // and then moved into place, so the collision policy applies whatever the
+            // container type (V4 and V10 only reveal the file name once decrypted), and a
+            // crash mid-file leaves only the staging folder, which the journal records.
+            let final_dir = match (&output_dir, &structure_root) {
+                (Some(dir), Some(root)) => structured_target_dir(dir, root, path),
+                (Some(dir), None) => dir.to_path_buf(),
+                (None, _) => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            };
+            let staging = final_dir.join(format!(".qre-unlock-{}", uuid::Uuid::new_v4()));
+            if let Some(batch) = batch.as_mut() { let _ = batch.add_partial(&staging, PartialKind::Plaintext); }
+            if let Err(e) = fs::create_dir_all(&staging) {
+                results.push(BatchItemResult { name: filename, success: false, message: format!("Cannot create output folder: {}", e) });
+                continue;
+            }
+            let target_dir_path = staging.clone();
+            let target_dir_str = target_dir_path.to_string_lossy().to_string();
+
+            'unlock: {
+                if version == 4 {
+                    let master_key = {
+                        let guard = vaults_arc.lock().unwrap();
+                        match guard.get("local") {
+                            Some(mk) => mk.clone(),
+                            None => {
+                                results.push(BatchItemResult { name: filename.clone(), success: false, message: "Local Vault is locked.".to_string() });
+                                break 'unlock;
+                            }
+                        }
+                    };
+
+                    match crypto::EncryptedFileContainer::load(&file_path) {
+                        Ok(container) => {
+                            utils::emit_progress(&app, &format!("Decrypting: {}", filename), 50);
+                            match crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::FileWrapping, keyfile_hash.as_deref(), &container) {
+                                Ok(payload) => {
+                                    utils::emit_progress(&app, &format!("Writing: {}", payload.filename), 80);
+                                    let final_path = utils::get_unique_path(&target_dir_path.join(&payload.filename));
+
+                                    if let Err(e) = fs::write(&final_path, &payload.content) {
+                                        let _ = fs::remove_file(&final_path);
+                                        results.push(BatchItemResult { name: filename, success: false, message: e.to_string() });
+                                    } else {
+                                        results.push(BatchItemResult { name: filename, success: true, message: "Unlocked".into() });
+                                    }
+                                }
+                                Err(e) => {
+                                    record_keyfile_failure(&app, "local", keyfile_hash.is_some(), &e.to_string());
+                                    results.push(BatchItemResult { name: filename, success: false, message: e.to_string() })
+                                }
+                            }
+                        }
+                        Err(e) => results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }),
+                    }
+                } else if (5..=8).contains(&version) || version == crypto_stream::VERSION_HYBRID {
+                    let header = crypto_stream::parse_stream_header(version, &mut file);
+                    let vault_id = match header {
+                        Ok(h) => h.vault_id.unwrap_or_else(|| "local".to_string()),
+                        Err(_) => "local".to_string(), 
+                    };
+
+                    let master_key = if shamir::is_split_key_vault(&vault_id) {
+                        match shamir::key_for_vault(&vault_id, &shares) {
+                            Ok(key) => key,
+                            Err(e) => {
+                                results.push(BatchItemResult { name: filename.clone(), success: false, message: e.to_string() });
+                                break 'unlock;
+                            }
+                        }
+                    } else {
+                        let guard = vaults_arc.lock().unwrap();
+                        match guard.get(&vault_id) {
+                            Some(mk) => mk.clone(),
+                            None => {
+                                results.push(BatchItemResult { 
+                                    name: filename.clone(), 
+                                    success: false, 
+                                    message: if vault_id == "local" { "Local Vault is locked.".to_string() } else { "This file belongs to a Portable USB Vault. Please unlock the USB drive first.".to_string() }
+                                });
+                                break 'unlock;
+                            }
+                        }
+                    };
+
+                    let app_handle = app.clone();
+                    let f_name = filename.clone();
+
+                    let progress_cb = move |processed: u64, total: u64| {
+                        if total > 0 {
+                            let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
+                            utils::emit_progress(&app_handle, &format!("Decrypting: {}", f_name), pct);
+                        }
+                    };
+
+                    let identity = match hybrid_identity(&app, version, &vault_id) {
+                        Ok(identity) => identity,
+                        Err(e) => {
+                            results.push(BatchItemResult { name: filename.clone(), success: false, message: e });
+                            break 'unlock;
+                        }
+                    };
+
+                    match crypto_stream::decrypt_file_stream_with_identity(&file_path, &target_dir_str, &master_key, keyfile_hash.as_deref(), identity.as_ref(), progress_cb) {
+                        Ok(out_name) => results.push(BatchItemResult { name: filename, success: true, message: format!("Unlocked: {}", out_name) }),
+                        Err(e) => {
+                            record_keyfile_failure(&app, &vault_id, keyfile_hash.is_some(), &e.to_string());
+                            results.push(BatchItemResult { name: filename, success: false, message: e.to_string() })
+                        }
+                    }
+                } else if version == crypto_stream::VERSION_ARCHIVE {
+                    results.push(BatchItemResult { name: filename, success: false, message: "This is a multi-file archive. Open it with the archive viewer.".into() });
+                } else if version == crypto_stream::VERSION_RECIPIENT {
+                    let app_handle = app.clone();
+                    let f_name = filename.clone();
+                    let progress_cb = move |processed: u64, total: u64| {
+                        if total > 0 {
+                            let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
+                            utils::emit_progress(&app_handle, &format!("Decrypting: {}", f_name), pct);
+                        }
+                    };
+                    match unlock_recipient_file(&app, &file_path, &target_dir_str, progress_cb) {
+                        Ok(message) => results.push(BatchItemResult { name: filename, success: true, message }),
+                        Err(message) => results.push(BatchItemResult { name: filename, success: false, message }),
+                    }
+                } else if version == crypto_stream::VERSION_DENIABLE {
+                    results.push(BatchItemResult { name: filename, success: false, message: "This container is opened with its own password, not a vault. Use Unlock with Password.".into() });
+                } else {
+                    results.push(BatchItemResult { name: filename, success: false, message: unsupported_format_error(path, version) });
+                }
+            }
+
+            if let Some(item) = results.last_mut().filter(|r| !r.success) {
+                if let Some(drive) = removed_drive(&[path, target_dir_path.as_path()], &volumes, &mounted_volumes()) {
+                    item.message = format!("Drive '{}' was removed while decrypting. Reconnect it and unlock the file again.", drive.name);
+                    let _ = app.emit("drive-removed", drive.mount_point.to_string_lossy().to_string());
+                }
+            }
+
+            if let Some(item) = results.last_mut() {
+                let destination = output_dir.as_deref().unwrap_or(&final_dir);
+                let placed = finish_staged_unlock(&app, item, &staging, &final_dir, destination, collision);
+                if output_dir.is_some() {
+                    manifest.push(ManifestEntry {
+                        source: file_path,
+                        output: placed.map(|p| p.to_string_lossy().to_string()),
+                        success: item.success,
+                        message: item.message.clone(),
+                    });
+                }
+            }
+        }
+        if let Some(batch) = batch {
+            let _ = batch.complete();
+        }
+        log_batch("unlock", &results);
+        if let (Some(destination), true) = (&output_dir, write_manifest.unwrap_or(false)) {
+            let manifest_path = utils::get_unique_path(&destination.join("qre-unlock-manifest.json"));
+            match serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string()).and_then(|json| fs::write(&manifest_path, json).map_err(|e| e.to_string())) {
+                Ok(()) => {
+                    let _ = app.emit("unlock-manifest", manifest_path.to_string_lossy().to_string());
+                }
+                Err(e) => tracing::error!("[Unlock] Failed to write manifest: {}", e),
+            }
+        }
+        if let Some(location) = eject_hint {
+            if results.iter().any(|r| r.success) {
+                let _ = app.emit("keyfile-eject-hint", location);
+            }
+        }
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// --- BATCH JOB RECOVERY ---
+
+/// `<app data>/batch_jobs`, where `lock_file` and `unlock_file` journal their runs.
+fn batch_journal_dir(app: &AppHandle) -> CommandResult<PathBuf> {
+    use tauri::Manager;
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(batch_journal::JOURNAL_DIR_NAME))
+}
+
+/// Starts the journal of a run, or takes over the interrupted run `batch_id` after
+/// cleaning up what its interrupted item left behind. A new run goes ahead without a
+/// journal if none can be written.
+fn open_batch(app: &AppHandle, kind: BatchKind, batch_id: Option<String>, items: &[String], options: serde_json::Value) -> CommandResult<Option<ActiveBatch>> {
+    let Some(id) = batch_id else {
+        let now = crate::timelock_clock::system_time_secs() as i64;
+        let started = batch_journal_dir(app).and_then(|dir| ActiveBatch::start(&dir, kind, items.to_vec(), options, now).map_err(|e| e.to_string()));
+        return Ok(started.inspect_err(|e| tracing::warn!("[Batch] Running without a journal: {}", e)).ok());
+    };
+    let dir = batch_journal_dir(app)?;
+    if BatchJournal::load(&dir, &id).map_err(|e| e.to_string())?.kind != kind {
+        return Err(i18n::AppError::new(i18n::ErrorCode::BatchMismatch).into());
+    }
+    // A partial container is only worth keeping if it reached a checkpoint.
+    let can_continue = |o: &PartialOutput| o.kind == PartialKind::Resumable && crypto_stream::checkpoint_path(&o.path).exists();
+    let (batch, discard) = ActiveBatch::resume(&dir, &id, can_continue).map_err(|e| e.to_string())?;
+    remove_partial_outputs(app, &discard);
+    Ok(Some(batch))
+}
+
+/// Journals the outcome of the previous item (the last of `results`) and starts `input`.
+fn journal_item(batch: &mut Option<ActiveBatch>, results: &[BatchItemResult], input: &str) {
+    let Some(batch) = batch.as_mut() else { return };
+    if let Some(last) = results.last() {
+        let _ = batch.finish_item(last.success, &last.message);
+    }
+    let _ = batch.begin_item(input);
+}
+
+/// Deletes the partial outputs of an interrupted item; decrypted ones are shredded.
+/// Only names the batch commands create are touched. Returns the paths removed.
+fn remove_partial_outputs(app: &AppHandle, partials: &[PartialOutput]) -> Vec<String> {
+    let mut removed = Vec::new();
+    for partial in partials {
+        let path = Path::new(&partial.path);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        let done = match partial.kind {
+            PartialKind::Plaintext if name.starts_with(".qre-unlock-") && path.is_dir() => utils::shred_recursive(app, path).is_ok(),
+            PartialKind::Resumable | PartialKind::Incomplete if (ext == "qre" || ext == "zip") && path.is_file() => {
+                let _ = fs::remove_file(crypto_stream::checkpoint_path(&partial.path));
+                fs::remove_file(path).is_ok()
+            }
+            _ => false,
+        };
+        if done {
+            removed.push(partial.path.clone());
+        }
+    }
+    removed
+}
+
+/// `lock_file` settings kept in a journal (see the `options` written there).
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct LockBatchOptions {
+    entropy_sources: Option<EntropyOptions>,
+    compression_mode: Option<String>,
+    search_index: Option<bool>,
+    labels: Option<Vec<String>>,
+    padding: Option<crypto_stream::Padding>,
+    expiry: Option<crypto_stream::Expiry>,
+    split_key: Option<SplitKeyOptions>,
+    parity_percent: Option<u8>,
+}
+
+/// `unlock_file` settings kept in a journal.
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct UnlockBatchOptions {
+    output_dir: Option<String>,
+    preserve_structure: Option<bool>,
+    on_collision: Option<CollisionPolicy>,
+    write_manifest: Option<bool>,
+}
+
+/// Batch runs that did not finish because the app crashed or was closed, oldest first.
+#[tauri::command]
+pub fn list_interrupted_batches(app: AppHandle) -> CommandResult<Vec<InterruptedBatch>> {
+    let dir = batch_journal_dir(&app)?;
+    Ok(BatchJournal::list_interrupted(&dir).iter().map(BatchJournal::summary).collect())
+}
+
+/// Runs the remaining items of an interrupted batch with the settings it was started
+/// with. Keyfiles, paranoid-mode input and key shares are never journaled, so they are
+/// passed again here.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn resume_interrupted_batch(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    batch_id: String,
+    keyfile_path: Option<String>,
+    keyfile_bytes: Option<Vec<u8>>,
+    extra_entropy: Option<Vec<u8>>,
+    shares: Option<Vec<String>>,
+) -> CommandResult<Vec<BatchItemResult>> {
+    let journal = BatchJournal::load(&batch_journal_dir(&app)?, &batch_id).map_err(|e| e.to_string())?;
+    let remaining = journal.remaining();
+    let damaged = |e: serde_json::Error| format!("The batch journal is damaged: {}", e);
+    match journal.kind {
+        BatchKind::Lock => {
+            let o: LockBatchOptions = serde_json::from_value(journal.options).map_err(damaged)?;
+            lock_file(
+                app, state, remaining, keyfile_path, keyfile_bytes, extra_entropy, o.entropy_sources, o.compression_mode, o.search_index,
+                o.labels, o.padding, None, o.expiry, o.split_key, o.parity_percent, Some(batch_id),
+            )
+            .await
+        }
+        BatchKind::Unlock => {
+            let o: UnlockBatchOptions = serde_json::from_value(journal.options).map_err(damaged)?;
+            unlock_file(
+                app, state, remaining, keyfile_path, keyfile_bytes, o.output_dir, o.preserve_structure, o.on_collision, o.write_manifest, shares, Some(batch_id),
+            )
+            .await
+        }
+    }
+}
+
+/// Forgets an interrupted batch and removes the partial outputs of its interrupted item.
+/// Returns the paths removed.
+#[tauri::command]
+pub async fn discard_interrupted_batch(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    batch_id: String,
+) -> CommandResult<Vec<String>> {
+    state.ensure_writable("local")?;
+    let dir = batch_journal_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let journal = BatchJournal::load(&dir, &batch_id).map_err(|e| e.to_string())?;
+        BatchJournal::delete(&dir, &batch_id).map_err(|e| e.to_string())?;
+        let partials = journal.in_progress.map(|p| p.partial_outputs).unwrap_or_default();
+        Ok(remove_partial_outputs(&app, &partials))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// --- MULTI-FILE ARCHIVES ---
+
+#[derive(serde::Serialize)]
+pub struct ArchiveUnlockResult {
+    pub entries: Vec<archive::ArchiveListing>,
+    /// One row per extracted entry; empty when only listing.
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Opens an archive made by `lock_file` in bundle mode. Only the file table is decrypted
+/// until entries are asked for: with `list_only` nothing is written, otherwise `entries`
+/// (all of them if omitted) are extracted into `output_dir` (default: next to the archive).
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn unlock_archive(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    path: String,
+    keyfile_path: Option<String>,
+    keyfile_bytes: Option<Vec<u8>>,
+    entries: Option<Vec<String>>,
+    output_dir: Option<String>,
+    list_only: Option<bool>,
+) -> CommandResult<ArchiveUnlockResult> {
+    let list_only = list_only.unwrap_or(false);
+    if !list_only {
+        state.ensure_writable("local")?;
+    }
+    let keyfile_hash = if let Some(bytes) = keyfile_bytes {
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+        Some(hasher.finalize().to_vec())
+    } else {
+        utils::process_keyfile(keyfile_path)?
+    };
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    if !list_only {
+        ensure_container_writable(&state, &path)?;
+    }
+    let output_dir = output_dir
+        .map(|d| SafePath::new(&d, PathPolicy::directory()))
+        .transpose()?;
+    let vaults_arc = state.vaults.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let path_str = path.to_string_lossy().to_string();
+        let (_, header) = crypto_stream::read_stream_header(&path_str).map_err(|e| e.to_string())?;
+        let vault_id = header.vault_id.unwrap_or_else(|| "local".to_string());
+        let master_key = vaults_arc
+            .lock()
+            .map_err(|_| i18n::AppError::new(i18n::ErrorCode::SessionCorrupted))?
+            .get(&vault_id)
+            .cloned()
+            .ok_or_else(|| if vault_id == "local" { i18n::AppError::new(i18n::ErrorCode::VaultLocked) } else { i18n::AppError::new(i18n::ErrorCode::PortableVaultLocked) })?;
+
+        let mut opened = match archive::Archive::open(&path_str, &master_key, keyfile_hash.as_deref()) {
+            Ok(a) => a,
+            Err(e) => {
+                record_keyfile_failure(&app, &vault_id, keyfile_hash.is_some(), &e.to_string());
+                return Err(e.to_string());
+            }
+        };
+        let listing = opened.list();
+        if list_only {
+            return Ok(ArchiveUnlockResult { entries: listing, results: Vec::new() });
+        }
+
+        let _power = power::PowerHold::acquire("Decrypting archive");
+        let target_dir = match &output_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        };
+        let wanted = entries.unwrap_or_else(|| listing.iter().map(|l| l.path.clone()).collect());
+        let mut results = Vec::new();
+        for entry_path in wanted {
+            power::wait_for_power(None);
+            let app_handle = app.clone();
+            let progress_name = entry_path.clone();
+            let progress_cb = move |processed: u64, total: u64| {
+                if total > 0 {
+                    let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
+                    utils::emit_progress(&app_handle, &format!("Decrypting: {}", progress_name), pct);
+                }
+            };
+            match opened.extract(&entry_path, &target_dir, progress_cb) {
+                Ok(out) => results.push(BatchItemResult { name: entry_path, success: true, message: format!("Unlocked: {}", out.display()) }),
+                Err(e) => results.push(BatchItemResult { name: entry_path, success: false, message: e.to_string() }),
+            }
+        }
+        Ok(ArchiveUnlockResult { entries: listing, results })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// --- SELF-DECRYPTING EXPORT ---
+
+/// Decrypts a V4–V8 container fully into memory. Streamed containers are written to a
+/// private scratch folder first, read back and shredded.
+fn decrypt_container_to_memory(
+    app: &AppHandle,
+    vaults_arc: &std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, crate::keychain::MasterKey>>>,
+    path: &Path,
+    keyfile_hash: Option<&[u8]>,
+) -> Result<(String, Zeroizing<Vec<u8>>), String> {
+    let path_str = path.to_string_lossy().to_string();
+    let mut ver_buf = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut ver_buf))
+        .map_err(|_| i18n::AppError::new(i18n::ErrorCode::NotAContainer))?;
+    let version = u32::from_le_bytes(ver_buf);
+
+    let vault_id = if version == 4 {
+        "local".to_string()
+    } else if (5..=8).contains(&version) || version == crypto_stream::VERSION_HYBRID {
+        crypto_stream::read_stream_header(&path_str)
+            .ok()
+            .and_then(|(_, h)| h.vault_id)
+            .unwrap_or_else(|| "local".to_string())
+    } else if version == crypto_stream::VERSION_ARCHIVE {
+        return Err(i18n::AppError::new(i18n::ErrorCode::ArchiveExportUnsupported).into());
+    } else {
+        return Err(unsupported_format_error(path, version));
+    };
+    if shamir::is_split_key_vault(&vault_id) {
+        return Err(i18n::AppError::new(i18n::ErrorCode::SplitKeyExportUnsupported).into());
+    }
+    let master_key = vaults_arc
+        .lock()
+        .map_err(|_| i18n::AppError::new(i18n::ErrorCode::SessionCorrupted))?
+        .get(&vault_id)
+        .cloned()
+        .ok_or_else(|| if vault_id == "local" { i18n::AppError::new(i18n::ErrorCode::VaultLocked) } else { i18n::AppError::new(i18n::ErrorCode::PortableVaultLocked) })?;
+
+    if version == 4 {
+        let container = crypto::EncryptedFileContainer::load(&path_str).map_err(|e| e.to_string())?;
+        let mut payload = crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::FileWrapping, keyfile_hash, &container).map_err(|e| {
+            record_keyfile_failure(app, &vault_id, keyfile_hash.is_some(), &e.to_string());
+            e.to_string()
+        })?;
+        return Ok((std::mem::take(&mut payload.filename), Zeroizing::new(std::mem::take(&mut payload.content))));
+    }
+
+    let scratch = std::env::temp_dir().join(format!("qre_sfx_{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&scratch).map_err(|e| e.to_string())?;
+    let identity = hybrid_identity(app, version, &vault_id)?;
+    let decrypted = crypto_stream::decrypt_file_stream_with_identity(&path_str, &scratch.to_string_lossy(), &master_key, keyfile_hash, identity.as_ref(), |_, _| {})
+        .map_err(|e| {
+            record_keyfile_failure(app, &vault_id, keyfile_hash.is_some(), &e.to_string());
+            e.to_string()
+        })
+        .and_then(|name| {
+            let out = scratch.join(&name);
+            let size = fs::metadata(&out).map_err(|e| e.to_string())?.len();
+            if size > crate::self_decrypt::MAX_SFX_BYTES {
+                return Err(i18n::AppError::new(i18n::ErrorCode::ExportTooLarge).with("limit", crate::self_decrypt::MAX_SFX_BYTES / (1024 * 1024)).into());
+            }
+            fs::read(&out).map(|bytes| (name, Zeroizing::new(bytes))).map_err(|e| e.to_string())
+        });
+    let _ = utils::shred_recursive(app, &scratch);
+    decrypted
+}
+
+/// Wraps the contents of a container into a single HTML page that decrypts itself in a
+/// browser with `passphrase` (and `recipient_keyfile_path`, if given), for recipients who
+/// do not have QRE. The vault key is not involved; see self_decrypt.rs. Returns the path
+/// of the page (default: next to the container).
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn export_self_decrypting(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    path: String,
+    keyfile_path: Option<String>,
+    keyfile_bytes: Option<Vec<u8>>,
+    passphrase: String,
+    recipient_keyfile_path: Option<String>,
+    output_path: Option<String>,
+) -> CommandResult<String> {
+    // The plaintext leaves the vault: same rule as decrypting to disk.
+    state.ensure_writable("local")?;
+    let passphrase = Zeroizing::new(passphrase);
+    if passphrase.chars().count() < crate::self_decrypt::MIN_PASSPHRASE_LEN {
+        return Err(i18n::AppError::new(i18n::ErrorCode::PassphraseTooShort).with("min", crate::self_decrypt::MIN_PASSPHRASE_LEN).into());
+    }
+    // The page is a new encryption of the plaintext, so the vault policy applies to it. The
+    // browser cannot hold the vault keyfile or extra entropy: a policy requiring either
+    // refuses the export outright (an optional recipient keyfile is no substitute).
+    let policy = super::vault::load_vault_policy(&app, "local")?;
+    policy
+        .check_encryption(false, None)
+        .map_err(|e| format!("{} Self-decrypting export is not available under this policy.", e))?;
+    policy.check_password(&passphrase).map_err(|e| e.to_string())?;
+    let keyfile_hash = if let Some(bytes) = keyfile_bytes {
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+        Some(hasher.finalize().to_vec())
+    } else {
+        utils::process_keyfile(keyfile_path)?
+    };
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    ensure_container_writable(&state, &path)?;
+    let recipient_keyfile = recipient_keyfile_path
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| SafePath::new(&p, PathPolicy::read_file().max_bytes(MAX_IN_MEMORY_FILE_BYTES)))
+        .transpose()?
+        .map(|p| fs::read(&p).map(Zeroizing::new).map_err(|e| format!("Failed to read keyfile: {}", e)))
+        .transpose()?;
+    let output_path = output_path
+        .map(|p| SafePath::new(&p, PathPolicy::write_file()))
+        .transpose()?;
+    let vaults_arc = state.vaults.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        utils::emit_progress(&app, "Decrypting container", 10);
+        let (filename, content) = decrypt_container_to_memory(&app, &vaults_arc, &path, keyfile_hash.as_deref())?;
+
+        utils::emit_progress(&app, "Sealing export", 50);
+        let page = crate::self_decrypt::build_page(&filename, &content, &passphrase, recipient_keyfile.as_ref().map(|k| k.as_slice()))
+            .map_err(|e| e.to_string())?;
+
+        let target = match output_path {
+            Some(p) => p.into_path_buf(),
+            None => {
+                let stem = Path::new(&filename).file_stem().unwrap_or_default().to_string_lossy().to_string();
+                let dir = path.parent().unwrap_or(Path::new("."));
+                utils::get_unique_path(&dir.join(format!("{}.html", stem)))
+            }
+        };
+        fs::write(&target, page).map_err(|e| format!("Failed to write export: {}", e))?;
+        utils::emit_progress(&app, "Export complete", 100);
+        Ok(target.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// --- PUBLIC-KEY SHARING ---
+
+/// Encrypts files for an imported contact (see recipient.rs): only the vault holding that
+/// identity can open them, and it sees which vault sent them. No password is shared.
+/// Outputs go next to each source unless `output_dir` is given.
+#[tauri::command]
+pub async fn lock_file_for_recipient(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    vault_id: String,
+    file_paths: Vec<String>,
+    recipient: String,
+    output_dir: Option<String>,
+) -> CommandResult<Vec<BatchItemResult>> {
+    state.ensure_writable(&vault_id)?;
+    // Vault policy is enforced here too. Public-key containers take no keyfile and no extra
+    // entropy, so a vault whose policy requires either cannot send them.
+    super::vault::load_vault_policy(&app, &vault_id)?
+        .check_encryption(false, None)
+        .map_err(|e| format!("{} Public-key sharing is not available under this policy.", e))?;
+    let sender = super::vault::vault_identity(&app, &vault_id, &state)?
+        .ok_or_else(|| i18n::AppError::new(i18n::ErrorCode::IdentityMissing))?;
+    let contact = super::vault::find_contact(&app, &vault_id, &state, &recipient)?;
+    let output_dir = output_dir
+        .map(|d| SafePath::new(&d, PathPolicy::directory()))
+        .transpose()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _power = power::PowerHold::acquire("Encrypting files");
+        let mut results = Vec::new();
+        for raw_path in file_paths {
+            power::wait_for_power(None);
+            let safe = match SafePath::new(&raw_path, PathPolicy::read_file()) {
+                Ok(p) => p,
+                Err(e) => {
+                    results.push(BatchItemResult { name: raw_path, success: false, message: e });
+                    continue;
+                }
+            };
+            let path: &Path = &safe;
+            let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let dir = match &output_dir {
+                Some(d) => d.to_path_buf(),
+                None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            };
+            let target = utils::get_unique_path(&dir.join(format!("{}.qre", filename)));
+
+            let app_handle = app.clone();
+            let f_name = filename.clone();
+            let progress_cb = move |processed: u64, total: u64| {
+                if total > 0 {
+                    let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
+                    utils::emit_progress(&app_handle, &format!("Encrypting: {}", f_name), pct);
+                }
+            };
+            match recipient::encrypt_for_recipient(&path.to_string_lossy(), &target.to_string_lossy(), &sender, &contact, progress_cb) {
+                Ok(()) => results.push(BatchItemResult { name: filename, success: true, message: format!("Locked for {}", recipient) }),
+                Err(e) => results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }),
+            }
+        }
+        let sent = results.iter().filter(|r| r.success).count();
+        if sent > 0 {
+            use tauri::Manager;
+            let user = app.state::<SessionState>().user_for(&vault_id);
+            super::vault::record_audit(&app, &vault_id, &user, "lock_for_recipient", Some(format!("{} file(s) for {}", sent, recipient)));
+        }
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Opens a V10 container with whichever unlocked vault it was encrypted for.
+/// Returns the written file name and a description of the sender.
+fn unlock_recipient_file(app: &AppHandle, file_path: &str, output_dir: &str, progress_cb: impl Fn(u64, u64)) -> CommandResult<String> {
+    use tauri::Manager;
+    let header = recipient::read_header(file_path).map_err(|e| e.to_string())?;
+    let state = app.state::<SessionState>();
+    let vault_ids: Vec<String> = state
+        .vaults
+        .lock()
+        .map_err(|_| i18n::AppError::new(i18n::ErrorCode::SessionCorrupted))?
+        .keys()
+        .cloned()
+        .collect();
+    for vault_id in vault_ids {
+        let Ok(Some(identity)) = super::vault::vault_identity(app, &vault_id, &state) else {
+            continue;
+        };
+        if identity.public().fingerprint() != header.recipient_fingerprint {
+            continue;
+        }
+        let (name, sender) = recipient::decrypt_from_sender(file_path, output_dir, &identity, progress_cb).map_err(|e| e.to_string())?;
+        let from = match super::vault::contact_name_for(app, &vault_id, &state, &sender) {
+            Some(contact) => format!("from {}", contact),
+            None => format!("from an unknown sender (fingerprint {})", sender.fingerprint_text()),
+        };
+        return Ok(format!("Unlocked: {} ({})", name, from));
+    }
+    Err(i18n::AppError::new(i18n::ErrorCode::WrongRecipientVault).into())
+}
+
+// --- DENIABLE CONTAINERS ---
+// Password-only V11 containers (see deniable.rs). Neither command touches a vault key or
+// the audit log: a log entry per password would say how many payloads the file holds.
+// Locking still honours the local vault policy, like every other way of encrypting.
+
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn lock_deniable(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    decoy_path: String,
+    decoy_password: String,
+    hidden_path: Option<String>,
+    hidden_password: Option<String>,
+    reserve_bytes: Option<u64>,
+    output_path: Option<String>,
+) -> CommandResult<String> {
+    state.ensure_writable("local")?;
+    let decoy_password = Zeroizing::new(decoy_password);
+    let hidden_password = hidden_password.map(Zeroizing::new);
+    // Deniable containers take no keyfile and no extra entropy, so a policy requiring
+    // either refuses them; their passwords must meet the policy's minimum length.
+    let policy = super::vault::load_vault_policy(&app, "local")?;
+    policy
+        .check_encryption(false, None)
+        .map_err(|e| format!("{} Deniable containers are not available under this policy.", e))?;
+    for password in std::iter::once(&decoy_password).chain(hidden_password.as_ref()) {
+        policy.check_password(password).map_err(|e| e.to_string())?;
+    }
+    let decoy_path = SafePath::new(&decoy_path, PathPolicy::read_file())?;
+    let hidden_path = hidden_path
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| SafePath::new(&p, PathPolicy::read_file()))
+        .transpose()?;
+    if hidden_path.is_some() != hidden_password.is_some() {
+        return Err(i18n::AppError::new(i18n::ErrorCode::HiddenNeedsPassword).into());
+    }
+    let output_path = output_path
+        .map(|p| SafePath::new(&p, PathPolicy::write_file()))
+        .transpose()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _power = power::PowerHold::acquire("Encrypting files");
+        let decoy_size = fs::metadata(&decoy_path).map_err(|e| e.to_string())?.len();
+        let reserve = reserve_bytes.unwrap_or_else(|| crate::deniable::default_reserve(decoy_size));
+        let target = match output_path {
+            Some(p) => p.into_path_buf(),
+            None => {
+                let filename = decoy_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let dir = decoy_path.parent().unwrap_or(Path::new("."));
+                utils::get_unique_path(&dir.join(format!("{}.qre", filename)))
+            }
+        };
+
+        utils::emit_progress(&app, "Sealing container", 10);
+        let decoy = crate::deniable::DeniablePayload { path: &decoy_path, password: &decoy_password };
+        let hidden = match (&hidden_path, &hidden_password) {
+            (Some(path), Some(password)) => Some(crate::deniable::DeniablePayload { path, password }),
+            _ => None,
+        };
+        crate::deniable::lock(&decoy, hidden.as_ref(), reserve, &target).map_err(|e| e.to_string())?;
+        utils::emit_progress(&app, "Container sealed", 100);
+        Ok(target.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn unlock_deniable(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    path: String,
+    password: String,
+    output_dir: Option<String>,
+) -> CommandResult<String> {
+    state.ensure_writable("local")?;
+    rate_limit("unlock_deniable", AUTH_RATE)?;
+    let password = Zeroizing::new(password);
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    let output_dir = match output_dir {
+        Some(d) => SafePath::new(&d, PathPolicy::directory())?.into_path_buf(),
+        None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _power = power::PowerHold::acquire("Decrypting files");
+        utils::emit_progress(&app, "Deriving keys", 10);
+        let written = crate::deniable::unlock(&path, &password, &output_dir).map_err(|e| e.to_string())?;
+        utils::emit_progress(&app, "Unlock complete", 100);
+        Ok(written.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// --- CONTAINER REQUIREMENTS ---
+
+/// What unlocking a container will need, read from its plaintext header only.
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ContainerRequirements {
+    pub version: u32,
+    /// "local", the UUID of the portable vault the file was encrypted with, or
+    /// "shamir:<set>" for split-key files (opened with key shares, see shamir.rs).
+    pub vault_id: String,
+    /// Stored in plaintext by V5+ headers; V4 keeps it inside the ciphertext.
+    pub original_filename: Option<String>,
+    /// On-disk size. The plaintext size is not stored (content is compressed).
+    pub size_bytes: u64,
+    /// `None` when the owning vault is locked: V5+ headers carry no keyfile flag, so
+    /// it is inferred from whether the vault key opens the file on its own.
+    pub uses_keyfile: Option<bool>,
+    pub time_locked_until: Option<u64>,
+    /// Expiring containers: when the file stops opening, and how many wrong attempts
+    /// it still tolerates before destroying itself.
+    pub expires_at: Option<u64>,
+    pub attempts_left: Option<u32>,
+    /// The owning vault is unlocked in this session.
+    pub vault_unlocked: bool,
+    /// Unlocking right now would succeed without asking for anything else.
+    pub can_open: bool,
+}
+
+/// Lets the UI ask for a keyfile (or unlock a USB vault) before a doomed unlock attempt.
+#[tauri::command]
+pub async fn get_container_requirements(
+    state: tauri::State<'_, SessionState>,
+    path: String,
+) -> CommandResult<ContainerRequirements> {
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    let vaults_arc = state.vaults.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        inspect_container(&path, |vault_id| {
+            vaults_arc.lock().ok().and_then(|v| v.get(vault_id).cloned())
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Tells a wrong password or keyfile apart from a damaged or cut-off file after a failed
+/// unlock, and lists the damaged chunks (see diagnosis.rs). Nothing is written.
+#[tauri::command]
+pub async fn diagnose_container(
+    state: tauri::State<'_, SessionState>,
+    path: String,
+    keyfile_path: Option<String>,
+) -> CommandResult<diagnosis::Diagnosis> {
+    // The key check answers "is this the keyfile?" like an unlock does.
+    rate_limit("diagnose_container", AUTH_RATE)?;
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    let keyfile_hash = utils::process_keyfile(keyfile_path)?;
+    let vaults_arc = state.vaults.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        // V5+ headers name their vault; V4 files always belong to the local one.
+        let vault_id = crypto_stream::read_stream_header(&path.to_string_lossy())
+            .ok()
+            .and_then(|(_, h)| h.vault_id)
+            .unwrap_or_else(|| "local".to_string());
+        let master_key = vaults_arc.lock().ok().and_then(|v| v.get(&vault_id).cloned());
+        diagnosis::diagnose(&path, master_key.as_ref(), keyfile_hash.as_deref()).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Best-effort unlock of a damaged V5–V8 file: writes every chunk that still opens,
+/// zero-fills or drops the rest and labels the output as partial (see salvage.rs).
+/// Unlocks like `unlock_file`, so time-locks and failed-attempt limits still apply.
+#[tauri::command]
+pub async fn recover_damaged_file(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    path: String,
+    output_dir: Option<String>,
+    keyfile_path: Option<String>,
+    shares: Option<Vec<String>>,
+) -> CommandResult<salvage::SalvageReport> {
+    // Writes plaintext to disk, like unlocking.
+    state.ensure_writable("local")?;
+    rate_limit("recover_damaged_file", AUTH_RATE)?;
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    ensure_container_writable(&state, &path)?;
+    let output_dir = output_dir
+        .map(|d| SafePath::new(&d, PathPolicy::directory()))
+        .transpose()?;
+    let keyfile_hash = utils::process_keyfile(keyfile_path)?;
+    let shares: Vec<Zeroizing<String>> = shares.unwrap_or_default().into_iter().map(Zeroizing::new).collect();
+    let vaults_arc = state.vaults.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _power = power::PowerHold::acquire("Recovering a damaged file");
+        let file_path = path.to_string_lossy().to_string();
+        let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let target_dir = match &output_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        };
+
+        let vault_id = crypto_stream::read_stream_header(&file_path)
+            .ok()
+            .and_then(|(_, h)| h.vault_id)
+            .unwrap_or_else(|| "local".to_string());
+        let master_key = if shamir::is_split_key_vault(&vault_id) {
+            shamir::key_for_vault(&vault_id, &shares).map_err(|e| e.to_string())?
+        } else {
+            match vaults_arc.lock().unwrap().get(&vault_id) {
+                Some(mk) => mk.clone(),
+                None if vault_id == "local" => return Err(i18n::AppError::new(i18n::ErrorCode::VaultLocked).into()),
+                None => return Err(i18n::AppError::new(i18n::ErrorCode::PortableVaultLocked).into()),
+            }
+        };
+
+        let app_handle = app.clone();
+        let progress_cb = move |processed: u64, total: u64| {
+            if total > 0 {
+                let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
+                utils::emit_progress(&app_handle, &format!("Recovering: {}", filename), pct);
+            }
+        };
+        salvage::salvage(&file_path, &target_dir.to_string_lossy(), &master_key, keyfile_hash.as_deref(), progress_cb).map_err(|e| {
+            record_keyfile_failure(&app, &vault_id, keyfile_hash.is_some(), &e.to_string());
+            e.to_string()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Format version and features of a container, read even when this build cannot open it.
+#[tauri::command]
+pub async fn get_container_format(path: String) -> CommandResult<formats::FormatCapability> {
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    tauri::async_runtime::spawn_blocking(move || formats::inspect(&path).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// The container formats this build reads, for update prompts and the about screen.
+#[tauri::command]
+pub fn get_supported_formats() -> formats::SupportedFormats {
+    formats::supported_formats()
+}
+
+/// The vault whose key opens the container at `path`: the header's `vault_id` for
+/// streamed containers and archives, else the local vault (V4, unreadable files).
+fn container_vault_id(path: &Path) -> String {
+    crypto_stream::read_stream_header(&path.to_string_lossy())
+        .ok()
+        .and_then(|(_, h)| h.vault_id)
+        .unwrap_or_else(|| "local".to_string())
+}
+
+/// Refuses to write the plaintext of the container at `path` while the vault that opens
+/// it is in a guest session. Checking "local" alone would let a read-only portable vault
+/// export its files whenever the desktop vault is unlocked by its owner.
+pub fn ensure_container_writable(state: &SessionState, path: &Path) -> CommandResult<String> {
+    let vault_id = container_vault_id(path);
+    state.ensure_writable(&vault_id)?;
+    Ok(vault_id)
+}
+
+/// The identity of the vault that locked a V12 (post-quantum) file; `None` for the other
+/// versions, which do not need one.
+fn hybrid_identity(app: &AppHandle, version: u32, vault_id: &str) -> Result<Option<recipient::Identity>, String> {
+    if version != crypto_stream::VERSION_HYBRID {
+        return Ok(None);
+    }
+    use tauri::Manager;
+    let state = app.state::<SessionState>();
+    super::vault::vault_identity(app, vault_id, &state)?
+        .map(Some)
+        .ok_or_else(|| i18n::AppError::new(i18n::ErrorCode::IdentityKeyMissing).into())
+}
+
+/// Error for a container that cannot be opened where it was given. Formats this build
+/// does not know fail with `UNSUPPORTED_FORMAT:<version>:<human msg>`, so the UI can ask
+/// `get_container_format` for details and offer an update instead of a dead end.
+fn unsupported_format_error(path: &Path, version: u32) -> String {
+    if let Some(format) = formats::info(version) {
+        return format!("{} files cannot be opened this way.", format.name);
+    }
+    let message = match formats::inspect(path) {
+        Ok(report) if report.needs_update => match report.required_app_version {
+            Some(required) => i18n::AppError::new(i18n::ErrorCode::FormatNeedsUpdate)
+                .with("required", required)
+                .with("current", formats::APP_VERSION),
+            None => i18n::AppError::new(i18n::ErrorCode::FormatTooNew).with("version", version),
+        },
+        _ => i18n::AppError::new(i18n::ErrorCode::FormatUnknown).with("version", version),
+    };
+    format!("UNSUPPORTED_FORMAT:{}:{}", version, message)
+}
+
+/// Header inspection behind `get_container_requirements`. `key_for` looks up the
+/// session key of a vault, so the session lock is only held for the lookup.
+pub(crate) fn inspect_container(
+    path: &Path,
+    key_for: impl Fn(&str) -> Option<crate::keychain::MasterKey>,
+) -> CommandResult<ContainerRequirements> {
+    let size_bytes = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let path_str = path.to_string_lossy().to_string();
+
+    let mut ver_buf = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut ver_buf))
+        .map_err(|_| i18n::AppError::new(i18n::ErrorCode::NotAContainer))?;
+    let version = u32::from_le_bytes(ver_buf);
+
+    if version == 4 {
+        let container = crypto::EncryptedFileContainer::load(&path_str).map_err(|e| e.to_string())?;
+        let key = key_for("local");
+        let uses_keyfile = container.header.uses_keyfile;
+        return Ok(ContainerRequirements {
+            version,
+            vault_id: "local".to_string(),
+            original_filename: None,
+            size_bytes,
+            uses_keyfile: Some(uses_keyfile),
+            time_locked_until: None,
+            expires_at: None,
+            attempts_left: None,
+            vault_unlocked: key.is_some(),
+            can_open: key.is_some_and(|k| crypto::master_key_opens(&container.header, &k, KeyPurpose::FileWrapping)),
+        });
+    }
+    if !(5..=8).contains(&version) && version != crypto_stream::VERSION_ARCHIVE && version != crypto_stream::VERSION_HYBRID {
+        return Err(unsupported_format_error(path, version));
+    }
+
+    let (_, header) = crypto_stream::read_stream_header(&path_str).map_err(|e| e.to_string())?;
+    let vault_id = header.vault_id.clone().unwrap_or_else(|| "local".to_string());
+    let key = key_for(&vault_id);
+    let key_opens = key.as_ref().is_some_and(|k| crypto_stream::master_key_opens(&header, k));
+
+    let (uses_keyfile, can_open) = match &header.timelock {
+        // Time-locked files never use a keyfile. The system clock is only an estimate
+        // here; the unlock itself consults NTP and the header's ratchet.
+        Some(tl) => {
+            let now = crate::timelock_clock::system_time_secs().max(tl.ratchet_max_seen);
+            (Some(false), key_opens && now >= tl.locked_until)
+        }
+        None => (key.as_ref().map(|_| !key_opens), key_opens),
+    };
+    let expired = header.expiry.as_ref().is_some_and(|ex| {
+        let now = crate::timelock_clock::system_time_secs().max(ex.ratchet_max_seen);
+        ex.policy.expires_at.is_some_and(|at| now >= at)
+    });
+
+    Ok(ContainerRequirements {
+        version,
+        vault_id,
+        original_filename: Some(header.original_filename.clone()),
+        size_bytes,
+        uses_keyfile,
+        time_locked_until: header.timelock.as_ref().map(|tl| tl.locked_until),
+        expires_at: header.expiry.as_ref().and_then(|ex| ex.policy.expires_at),
+        attempts_left: header
+            .expiry
+            .as_ref()
+            .and_then(|ex| ex.policy.max_failed_attempts.map(|max| max.saturating_sub(ex.failed_attempts))),
+        vault_unlocked: key.is_some(),
+        can_open: can_open && !expired,
+    })
+}
+
+// --- LOCKED FILE SEARCH ---
+
+/// Upper bound on containers examined per search.
+const MAX_SEARCH_FILES: usize = 10_000;
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct LockedSearchHit {
+    pub path: String,
+    pub original_filename: String,
+    pub size_bytes: u64,
+}
+
+#[derive(serde::Serialize, Debug, Clone, Default)]
+pub struct LockedSearchResult {
+    /// Containers whose index matches every query word. Bloom filters can give false
+    /// positives, so a hit means "may contain"; a miss is definitive.
+    pub matches: Vec<LockedSearchHit>,
+    pub scanned: usize,
+    /// Containers locked without an index (or before indexing existed).
+    pub not_indexed: usize,
+    /// Containers of a locked vault or with unreadable headers.
+    pub inaccessible: usize,
+}
 
-            if is_temp { let _ = fs::remove_file(&input_path_str); }
+/// Finds which .qre files in `dir` contain `query`, using the encrypted search index
+/// sealed into their headers at lock time. Only headers are read; nothing is decrypted
+/// to disk.
+#[tauri::command]
+pub async fn search_locked_files(
+    state: tauri::State<'_, SessionState>,
+    query: String,
+    dir: String,
+    recursive: Option<bool>,
+) -> CommandResult<LockedSearchResult> {
+    if container_meta::tokenize(&query).is_empty() {
+        return Err(i18n::AppError::new(i18n::ErrorCode::SearchTermTooShort).into());
+    }
+    let dir = SafePath::new(&dir, PathPolicy::directory())?;
+    let vaults_arc = state.vaults.clone();
 
-            match encryption_result {
-                Ok(_) => results.push(BatchItemResult { name: filename.to_string(), success: true, message: "Locked".into() }),
-                Err(e) => {
-                    let _ = fs::remove_file(&final_path);
-                    results.push(BatchItemResult { name: filename.to_string(), success: false, message: e.to_string() });
-                }
-            }
-        }
-        Ok(results)
+    tauri::async_runtime::spawn_blocking(move || {
+        search_containers(&dir, &query, recursive.unwrap_or(false), |vault_id| {
+            vaults_arc.lock().ok().and_then(|v| v.get(vault_id).cloned())
+        })
     })
     .await
     .map_err(|e| e.to_string())?
 }
 
+/// Search behind `search_locked_files`; `key_for` as in `inspect_container`.
+pub(crate) fn search_containers(
+    dir: &Path,
+    query: &str,
+    recursive: bool,
+    key_for: impl Fn(&str) -> Option<crate::keychain::MasterKey>,
+) -> CommandResult<LockedSearchResult> {
+    let mut result = LockedSearchResult::default();
+    for entry in qre_entries(dir, recursive) {
+        result.scanned += 1;
+        let path = entry.path();
+        let Ok((_, header)) = crypto_stream::read_stream_header(&path.to_string_lossy()) else {
+            result.inaccessible += 1;
+            continue;
+        };
+        if header.metadata.is_none() {
+            result.not_indexed += 1;
+            continue;
+        }
+        let Some(key) = key_for(header.vault_id.as_deref().unwrap_or("local")) else {
+            result.inaccessible += 1;
+            continue;
+        };
+        let Ok(Some(sealed)) = crypto_stream::open_metadata(&header, &key) else {
+            result.inaccessible += 1;
+            continue;
+        };
+        let index = ContainerMetadata::from_bytes(&sealed)
+            .ok()
+            .and_then(|meta| meta.search_index)
+            .and_then(|encoded| SearchIndex::decode(&encoded).ok());
+        match index {
+            Some(index) if index.matches_query(query) => result.matches.push(LockedSearchHit {
+                path: path.to_string_lossy().to_string(),
+                original_filename: header.original_filename.clone(),
+                size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            }),
+            Some(_) => {}
+            None => result.not_indexed += 1,
+        }
+    }
+    Ok(result)
+}
+
+/// .qre files under `dir` (one level unless `recursive`), at most `MAX_SEARCH_FILES`.
+fn qre_entries(dir: &Path, recursive: bool) -> impl Iterator<Item = walkdir::DirEntry> {
+    walkdir::WalkDir::new(dir)
+        .max_depth(if recursive { usize::MAX } else { 1 })
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file()
+                && e.path()
+                    .extension()
+                    .is_some_and(|x| x.eq_ignore_ascii_case("qre"))
+        })
+        .take(MAX_SEARCH_FILES)
+}
+
+// --- CONTAINER LISTING ---
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ContainerListing {
+    pub path: String,
+    pub file_name: String,
+    /// Name of the file before it was locked (stored in the clear header).
+    pub original_filename: String,
+    pub size_bytes: u64,
+    pub vault_id: String,
+    /// Decrypted labels; `None` when the owning vault is not unlocked in this session
+    /// (or the header is unreadable), empty when the container has none.
+    pub labels: Option<Vec<String>>,
+    pub has_search_index: bool,
+    /// Unix time the container unlocks, for time-locked containers.
+    pub locked_until: Option<u64>,
+}
+
+/// Lists the .qre files in `dir` with their labels, so a folder of UUID-named
+/// containers can be browsed without unlocking anything.
 #[tauri::command]
-pub async fn unlock_file(
-    app: AppHandle,
+pub async fn list_containers(
     state: tauri::State<'_, SessionState>,
-    file_paths: Vec<String>,
-    keyfile_path: Option<String>,
-    keyfile_bytes: Option<Vec<u8>>,
-    output_dir: Option<String>,
-) -> CommandResult<Vec<BatchItemResult>> {
-    let keyfile_hash = if let Some(bytes) = keyfile_bytes {
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        Some(hasher.finalize().to_vec())
-    } else {
-        utils::process_keyfile(keyfile_path)?
-    };
-
+    dir: String,
+    recursive: Option<bool>,
+) -> CommandResult<Vec<ContainerListing>> {
+    let dir = SafePath::new(&dir, PathPolicy::directory())?;
     let vaults_arc = state.vaults.clone();
 
     tauri::async_runtime::spawn_blocking(move || {
-        let mut results = Vec::new();
-
-        for file_path in file_paths {
-            let path = Path::new(&file_path);
-            let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
-
-            utils::emit_progress(&app, &format!("Checking: {}", filename), 5);
-
-            let mut file = match fs::File::open(path) {
-                Ok(f) => f,
-                Err(e) => { results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }); continue; }
-            };
+        Ok(list_container_entries(&dir, recursive.unwrap_or(false), |vault_id| {
+            vaults_arc.lock().ok().and_then(|v| v.get(vault_id).cloned())
+        }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-            let mut ver_buf = [0u8; 4];
-            if file.read_exact(&mut ver_buf).is_err() {
-                results.push(BatchItemResult { name: filename, success: false, message: "Invalid file".into() });
-                continue;
-            }
-            let version = u32::from_le_bytes(ver_buf);
+/// Listing behind `list_containers`; `key_for` as in `inspect_container`.
+/// Files whose header cannot be parsed are skipped.
+pub(crate) fn list_container_entries(
+    dir: &Path,
+    recursive: bool,
+    key_for: impl Fn(&str) -> Option<crate::keychain::MasterKey>,
+) -> Vec<ContainerListing> {
+    let mut listing = Vec::new();
+    for entry in qre_entries(dir, recursive) {
+        let path = entry.path();
+        let Ok((_, header)) = crypto_stream::read_stream_header(&path.to_string_lossy()) else {
+            continue;
+        };
+        let vault_id = header.vault_id.clone().unwrap_or_else(|| "local".to_string());
+        let meta = match &header.metadata {
+            None => Some(ContainerMetadata::default()),
+            Some(_) => key_for(&vault_id)
+                .and_then(|key| crypto_stream::open_metadata(&header, &key).ok().flatten())
+                .and_then(|sealed| ContainerMetadata::from_bytes(&sealed).ok()),
+        };
+        listing.push(ContainerListing {
+            path: path.to_string_lossy().to_string(),
+            file_name: entry.file_name().to_string_lossy().to_string(),
+            original_filename: header.original_filename.clone(),
+            size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            vault_id,
+            has_search_index: meta.as_ref().is_some_and(|m| m.search_index.is_some()),
+            labels: meta.map(|m| m.labels),
+            locked_until: header.timelock.as_ref().map(|t| t.locked_until),
+        });
+    }
+    listing.sort_by(|a, b| a.path.cmp(&b.path));
+    listing
+}
 
-            let target_dir_path = match &output_dir {
-                Some(dir) => std::path::PathBuf::from(dir),
-                None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
-            };
-            let target_dir_str = target_dir_path.to_string_lossy().to_string();
+// --- COMPRESSION BENCHMARK ---
+
+/// Only the first 32 MB of the sample is benchmarked: representative of the data,
+/// and "extreme" still finishes in seconds on slow hardware.
+const BENCHMARK_SAMPLE_BYTES: u64 = 32 * 1024 * 1024;
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct CompressionBenchmark {
+    pub mode: String,
+    pub level: i32,
+    pub duration_ms: u64,
+    pub output_bytes: u64,
+    /// Output size relative to the sample (1.0 = no saving; includes encryption overhead).
+    pub ratio: f64,
+    pub throughput_mb_s: f64,
+}
 
-            if version == 4 {
-                let master_key = {
-                    let guard = vaults_arc.lock().unwrap();
-                    match guard.get("local") {
-                        Some(mk) => mk.clone(),
-                        None => {
-                            results.push(BatchItemResult { name: filename.clone(), success: false, message: "Local Vault is locked.".to_string() });
-                            continue;
-                        }
-                    }
-                };
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct CompressionBenchmarkReport {
+    pub sample_bytes: u64,
+    /// True when the sample was larger than the benchmarked prefix.
+    pub truncated: bool,
+    pub results: Vec<CompressionBenchmark>,
+    pub recommended: String,
+}
 
-                match crypto::EncryptedFileContainer::load(&file_path) {
-                    Ok(container) => {
-                        utils::emit_progress(&app, &format!("Decrypting: {}", filename), 50);
-                        match crypto::decrypt_file_with_master_key(&master_key, keyfile_hash.as_deref(), &container) {
-                            Ok(payload) => {
-                                utils::emit_progress(&app, &format!("Writing: {}", payload.filename), 80);
-                                let final_path = utils::get_unique_path(&target_dir_path.join(&payload.filename));
-
-                                if let Err(e) = fs::write(&final_path, &payload.content) {
-                                    let _ = fs::remove_file(&final_path);
-                                    results.push(BatchItemResult { name: filename, success: false, message: e.to_string() });
-                                } else {
-                                    results.push(BatchItemResult { name: filename, success: true, message: "Unlocked".into() });
-                                }
-                            }
-                            Err(e) => results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }),
-                        }
-                    }
-                    Err(e) => results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }),
-                }
-            } else if version == 5 || version == 6 || version == 7 {
-                let header: Result<crypto_stream::StreamHeader, _> = bincode::deserialize_from(&mut file);
-                let vault_id = match header {
-                    Ok(h) => h.vault_id.unwrap_or_else(|| "local".to_string()),
-                    Err(_) => "local".to_string(), 
-                };
+/// Encrypts a sample file once per compression preset and reports time and size,
+/// so the UI can recommend a preset for this machine and this kind of data.
+/// Uses a throwaway key: nothing is written outside a private temp folder.
+#[tauri::command]
+pub async fn benchmark_compression(sample_path: String) -> CommandResult<CompressionBenchmarkReport> {
+    let sample = SafePath::new(&sample_path, PathPolicy::read_file())?;
+    let work_dir = std::env::temp_dir().join(format!("qre_benchmark_{}", uuid::Uuid::new_v4()));
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = run_compression_benchmark(&sample, &work_dir);
+        let _ = fs::remove_dir_all(&work_dir);
+        report
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-                let master_key = {
-                    let guard = vaults_arc.lock().unwrap();
-                    match guard.get(&vault_id) {
-                        Some(mk) => mk.clone(),
-                        None => {
-                            results.push(BatchItemResult { 
-                                name: filename.clone(), 
-                                success: false, 
-                                message: if vault_id == "local" { "Local Vault is locked.".to_string() } else { "This file belongs to a Portable USB Vault. Please unlock the USB drive first.".to_string() }
-                            });
-                            continue;
-                        }
-                    }
-                };
+/// Benchmark core, split out of the command so tests can run it without a Tauri app.
+pub(crate) fn run_compression_benchmark(sample: &Path, work_dir: &Path) -> CommandResult<CompressionBenchmarkReport> {
+    fs::create_dir_all(work_dir).map_err(|e| e.to_string())?;
+    let filename = sample.file_name().unwrap_or_default().to_string_lossy().to_string();
 
-                let app_handle = app.clone();
-                let f_name = filename.clone();
+    // Copy the benchmarked prefix under the original name, so "auto" sees the real extension.
+    let sample_size = fs::metadata(sample).map_err(|e| e.to_string())?.len();
+    let input = work_dir.join(&filename);
+    {
+        let mut reader = fs::File::open(sample).map_err(|e| e.to_string())?.take(BENCHMARK_SAMPLE_BYTES);
+        let mut writer = fs::File::create(&input).map_err(|e| e.to_string())?;
+        std::io::copy(&mut reader, &mut writer).map_err(|e| e.to_string())?;
+    }
+    let bench_bytes = sample_size.min(BENCHMARK_SAMPLE_BYTES);
+    let input_str = input.to_string_lossy().to_string();
+
+    let mut key = [0u8; 32];
+    OsRng.try_fill_bytes(&mut key).map_err(|e| e.to_string())?;
+    let master_key = crate::keychain::MasterKey(key);
+
+    let mut results = Vec::new();
+    for mode in COMPRESSION_PRESETS {
+        let level = compression_level(mode, &filename);
+        let output = work_dir.join(format!("{}.qre", mode));
+        let started = std::time::Instant::now();
+        crypto_stream::encrypt_file_stream(
+            &input_str, &output.to_string_lossy(), &master_key, "benchmark", None, None, None, level, |_, _| {},
+        )
+        .map_err(|e| format!("Benchmark failed for '{}': {}", mode, e))?;
+        let elapsed = started.elapsed();
+        let output_bytes = fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+        let _ = fs::remove_file(&output);
+
+        let secs = elapsed.as_secs_f64().max(1e-6);
+        results.push(CompressionBenchmark {
+            mode: mode.to_string(),
+            level,
+            duration_ms: elapsed.as_millis() as u64,
+            output_bytes,
+            ratio: if bench_bytes > 0 { output_bytes as f64 / bench_bytes as f64 } else { 1.0 },
+            throughput_mb_s: bench_bytes as f64 / (1024.0 * 1024.0) / secs,
+        });
+    }
 
-                let progress_cb = move |processed: u64, total: u64| {
-                    if total > 0 {
-                        let pct = ((processed as f64 / total as f64 * 100.0) as u8).min(100);
-                        utils::emit_progress(&app_handle, &format!("Decrypting: {}", f_name), pct);
-                    }
-                };
+    Ok(CompressionBenchmarkReport {
+        sample_bytes: bench_bytes,
+        truncated: sample_size > BENCHMARK_SAMPLE_BYTES,
+        recommended: recommend_compression(&results).to_string(),
+        results,
+    })
+}
 
-                match crypto_stream::decrypt_file_stream(&file_path, &target_dir_str, &master_key, keyfile_hash.as_deref(), progress_cb) {
-                    Ok(out_name) => results.push(BatchItemResult { name: filename, success: true, message: format!("Unlocked: {}", out_name) }),
-                    Err(e) => results.push(BatchItemResult { name: filename, success: false, message: e.to_string() }),
-                }
-            } else {
-                results.push(BatchItemResult { name: filename, success: false, message: format!("Unsupported Version: {}", version) });
-            }
+/// Picks a preset from benchmark results:
+///   - "store" when "auto" saves under 5% of the sample (incompressible data; compression
+///     only costs time),
+///   - "extreme" when it saves at least 10% more than "auto" and still runs at 10 MB/s or more,
+///   - otherwise "auto".
+///
+/// Savings are measured against the sample size, not the "store" output: zstd treats
+/// level 0 as its default level, so "store" containers are still compressed.
+pub(crate) fn recommend_compression(results: &[CompressionBenchmark]) -> &'static str {
+    let Some(auto) = results.iter().find(|r| r.mode == "auto") else {
+        return "auto";
+    };
+    if auto.ratio > 0.95 {
+        return "store";
+    }
+    if let Some(extreme) = results.iter().find(|r| r.mode == "extreme") {
+        if (extreme.output_bytes as f64) <= auto.output_bytes as f64 * 0.90 && extreme.throughput_mb_s >= 10.0 {
+            return "extreme";
         }
-        Ok(results)
-    })
-    .await
-    .map_err(|e| e.to_string())?
+    }
+    "auto"
 }
 
 // --- FILE OPERATIONS ---
@@ -367,18 +2146,25 @@ pub async fn unlock_file(
 #[tauri::command]
 pub async fn delete_items(
     app: AppHandle,
+    state: tauri::State<'_, SessionState>,
     paths: Vec<String>,
 ) -> CommandResult<Vec<BatchItemResult>> {
+    state.ensure_writable("local")?;
+    rate_limit("delete_items", DESTRUCTIVE_RATE)?;
+    // Desktop deletion is a multi-pass shred; held until the blocking task below finishes.
+    let _job = JobGuard::acquire(Job::Shred)?;
     tauri::async_runtime::spawn_blocking(move || {
         let mut results = Vec::new();
 
         for path in paths {
-            let p = Path::new(&path);
-
-            if let Err(e) = reject_critical_path(p) {
-                results.push(BatchItemResult { name: p.to_string_lossy().to_string(), success: false, message: e });
-                continue;
-            }
+            let safe = match SafePath::new(&path, PathPolicy::existing_entry()) {
+                Ok(p) => p,
+                Err(e) => {
+                    results.push(BatchItemResult { name: path, success: false, message: e });
+                    continue;
+                }
+            };
+            let p: &Path = &safe;
 
             let filename = p.file_name().unwrap_or_default().to_string_lossy().to_string();
 
@@ -410,18 +2196,23 @@ pub async fn delete_items(
 #[tauri::command]
 pub async fn trash_items(
     app: AppHandle,
+    state: tauri::State<'_, SessionState>,
     paths: Vec<String>,
 ) -> CommandResult<Vec<BatchItemResult>> {
+    state.ensure_writable("local")?;
+    rate_limit("trash_items", DESTRUCTIVE_RATE)?;
     tauri::async_runtime::spawn_blocking(move || {
         let mut results = Vec::new();
 
         for path in paths {
-            let p = Path::new(&path);
-
-            if let Err(e) = reject_critical_path(p) {
-                results.push(BatchItemResult { name: p.to_string_lossy().to_string(), success: false, message: e });
-                continue;
-            }
+            let safe = match SafePath::new(&path, PathPolicy::existing_entry()) {
+                Ok(p) => p,
+                Err(e) => {
+                    results.push(BatchItemResult { name: path, success: false, message: e });
+                    continue;
+                }
+            };
+            let p: &Path = &safe;
 
             let filename = p.file_name().unwrap_or_default().to_string_lossy().to_string();
 
@@ -470,24 +2261,25 @@ pub async fn paste_items(
     sources: Vec<String>,
     dest_dir: String,
     is_cut: bool,
+    state: tauri::State<'_, SessionState>,
 ) -> CommandResult<Vec<BatchItemResult>> {
+    state.ensure_writable("local")?;
     
-    // FIX: Clone dest_dir into a PathBuf so it can be moved into the thread
-    let dest_base = PathBuf::from(dest_dir);
-
-    // Initial sanity check before thread
-    reject_critical_path(&dest_base)?;
+    // Validated (and owned) before the thread so it can be moved into it.
+    let dest_base = SafePath::new(&dest_dir, PathPolicy::directory())?.into_path_buf();
 
     tauri::async_runtime::spawn_blocking(move || {
         let mut results = Vec::new();
         
         for src_str in sources {
-            let src = Path::new(&src_str);
-            
-            if let Err(e) = reject_critical_path(src) {
-                results.push(BatchItemResult { name: src.to_string_lossy().to_string(), success: false, message: e });
-                continue;
-            }
+            let safe = match SafePath::new(&src_str, PathPolicy::existing_entry()) {
+                Ok(p) => p,
+                Err(e) => {
+                    results.push(BatchItemResult { name: src_str, success: false, message: e });
+                    continue;
+                }
+            };
+            let src: &Path = &safe;
             
             let filename = src.file_name().unwrap_or_default();
             let dest = utils::get_unique_path(&dest_base.join(filename));
@@ -522,45 +2314,86 @@ pub async fn paste_items(
 }
 
 #[tauri::command]
-pub fn create_dir(path: String) -> CommandResult<()> {
+pub fn create_dir(path: String, state: tauri::State<SessionState>) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::new_directory())?;
     fs::create_dir_all(&path).map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
-pub fn rename_item(path: String, new_name: String) -> CommandResult<()> {
+pub fn rename_item(path: String, new_name: String, state: tauri::State<SessionState>) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    rename_path(path, new_name)
+}
+
+/// Validation + rename, split out of the command so tests can call it without a `tauri::State`.
+pub(crate) fn rename_path(path: String, new_name: String) -> CommandResult<()> {
     if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains('/') || new_name.contains('\\') {
-        return Err("Invalid name".to_string());
+        return Err(i18n::AppError::new(i18n::ErrorCode::NameInvalid).into());
     }
 
-    let old_path = Path::new(&path);
-    let parent = old_path.parent().ok_or("Invalid path")?;
+    let old_path = SafePath::new(&path, PathPolicy::existing_entry())?;
+    let parent = old_path.parent().ok_or_else(|| i18n::AppError::new(i18n::ErrorCode::PathInvalid))?;
     let new_path = parent.join(&new_name);
-    fs::rename(old_path, new_path).map_err(|e| e.to_string())?;
+    fs::rename(&old_path, new_path).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Renames many files with a privacy preset (`renamer::RenamePattern`). With `dry_run`
+/// the plan is returned without renaming anything, for the preview (random names are
+/// drawn again when the rename is applied).
+#[tauri::command]
+pub async fn batch_rename(
+    state: tauri::State<'_, SessionState>,
+    paths: Vec<String>,
+    pattern: renamer::RenamePattern,
+    dry_run: Option<bool>,
+    on_collision: Option<renamer::CollisionPolicy>,
+) -> CommandResult<Vec<renamer::RenameItem>> {
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        state.ensure_writable("local")?;
+    }
+    let paths: Vec<_> = SafePath::all(&paths, PathPolicy::existing_entry())?
+        .into_iter()
+        .map(SafePath::into_path_buf)
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut plan = renamer::plan_renames(&paths, &pattern, on_collision.unwrap_or_default());
+        if !dry_run {
+            renamer::apply_plan(&mut plan);
+        }
+        plan
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn show_in_folder(path: String) -> CommandResult<()> {
     #[cfg(target_os = "android")]
     {
         let _ = path;
-        Err("Reveal in Explorer is not supported on Android".to_string())
+        Err(i18n::AppError::new(i18n::ErrorCode::UnsupportedOnAndroid).into())
     }
     #[cfg(not(target_os = "android"))]
     {
+        let path = SafePath::new(&path, PathPolicy::existing_entry())?;
+
         #[cfg(target_os = "windows")]
-        Command::new("explorer").args(["/select,", &path]).spawn().map_err(|e| e.to_string())?;
+        Command::new("explorer").arg("/select,").arg(path.as_path()).spawn().map_err(|e| e.to_string())?;
 
         #[cfg(target_os = "linux")]
         {
-            let p = Path::new(&path);
+            let p = path.as_path();
             let parent = p.parent().unwrap_or(p);
             Command::new("xdg-open").arg(parent).spawn().map_err(|e| e.to_string())?;
         }
 
         #[cfg(target_os = "macos")]
-        Command::new("open").args(["-R", &path]).spawn().map_err(|e| e.to_string())?;
+        Command::new("open").arg("-R").arg(path.as_path()).spawn().map_err(|e| e.to_string())?;
 
         Ok(())
     }
@@ -570,13 +2403,14 @@ pub fn show_in_folder(path: String) -> CommandResult<()> {
 
 #[tauri::command]
 pub fn read_text_file_content(path: String) -> CommandResult<String> {
-    reject_path_traversal(Path::new(&path))?;
+    let path = SafePath::new(&path, PathPolicy::read_file().max_bytes(MAX_IN_MEMORY_FILE_BYTES))?;
     std::fs::read_to_string(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn write_text_file_content(path: String, content: String) -> CommandResult<()> {
-    reject_path_traversal(Path::new(&path))?;
+pub fn write_text_file_content(path: String, content: String, state: tauri::State<SessionState>) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::write_file())?;
     std::fs::write(&path, content).map_err(|e| e.to_string())
 }
 
@@ -584,6 +2418,8 @@ pub fn write_text_file_content(path: String, content: String) -> CommandResult<(
 
 #[tauri::command]
 pub async fn dry_run_shred(paths: Vec<String>) -> CommandResult<shredder::DryRunResult> {
+    // No SafePath gate here: the dry run exists to report every rejected path to the
+    // user (shredder::dry_run lists them as blocked) instead of failing on the first.
     shredder::dry_run(paths).map_err(|e| e.to_string())
 }
 
@@ -591,12 +2427,26 @@ pub async fn dry_run_shred(paths: Vec<String>) -> CommandResult<shredder::DryRun
 pub async fn batch_shred_files(
     paths: Vec<String>,
     method: shredder::ShredMethod,
+    ignore_health_warning: Option<bool>,
     app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SessionState>,
 ) -> CommandResult<shredder::ShredResult> {
-    for path in &paths {
-        reject_critical_path(Path::new(path))?;
+    state.ensure_writable("local")?;
+    rate_limit("batch_shred_files", DESTRUCTIVE_RATE)?;
+    let _job = JobGuard::acquire(Job::Shred)?;
+    // The shredder re-validates against its own blacklist; this is the common gate.
+    let safe = SafePath::all(&paths, PathPolicy::existing_entry())?;
+    let total: u64 = safe.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+    if total > drive_health::HEALTH_CHECK_THRESHOLD {
+        let targets: Vec<&Path> = safe.iter().map(|p| p.as_path()).collect();
+        ensure_drive_health(&targets, ignore_health_warning.unwrap_or(false))?;
+    }
+    let result = shredder::batch_shred(paths, method, &app_handle).map_err(|e| e.to_string())?;
+    tracing::info!(files = result.total_files, failed = result.failed.len(), bytes = result.total_bytes_shredded, "Shred finished");
+    for failed in &result.failed {
+        tracing::warn!(path = %failed.path, "{}", failed.error);
     }
-    shredder::batch_shred(paths, method, &app_handle).map_err(|e| e.to_string())
+    Ok(result)
 }
 
 #[tauri::command]
@@ -608,37 +2458,415 @@ pub async fn cancel_shred() -> CommandResult<()> {
 #[tauri::command]
 pub async fn wipe_free_space(
     drive_path: String,
+    ignore_health_warning: Option<bool>,
     app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SessionState>,
 ) -> CommandResult<shredder::WipeFreeSpaceResult> {
+    state.ensure_writable("local")?;
     #[cfg(target_os = "android")]
     {
-        let _ = drive_path;
+        let _ = (drive_path, ignore_health_warning);
         let _ = app_handle;
-        Err("Free space wiping is not supported on Android.".to_string())
+        Err(i18n::AppError::new(i18n::ErrorCode::UnsupportedOnAndroid).into())
     }
     #[cfg(not(target_os = "android"))]
     {
-        reject_critical_path(Path::new(&drive_path))?;
-        shredder::wipe_free_space(drive_path, &app_handle).map_err(|e| e.to_string())
+        let drive = SafePath::new(&drive_path, PathPolicy::directory())?;
+        let _job = JobGuard::acquire(Job::DiskWipe)?;
+        ensure_drive_health(&[drive.as_path()], ignore_health_warning.unwrap_or(false))?;
+        shredder::wipe_free_space(drive.to_string_lossy().to_string(), &app_handle).map_err(|e| e.to_string())
     }
 }
 
 #[tauri::command]
-pub async fn trim_drive(drive_path: String) -> CommandResult<shredder::TrimResult> {
+pub async fn trim_drive(drive_path: String, state: tauri::State<'_, SessionState>) -> CommandResult<shredder::TrimResult> {
+    state.ensure_writable("local")?;
     #[cfg(target_os = "android")]
     {
         let _ = drive_path;
-        Err("TRIM is managed automatically by the Android OS.".to_string())
+        Err(i18n::AppError::new(i18n::ErrorCode::UnsupportedOnAndroid).into())
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        let drive = SafePath::new(&drive_path, PathPolicy::directory())?;
+        let _job = JobGuard::acquire(Job::DiskWipe)?;
+        shredder::trim_drive(drive.to_string_lossy().to_string()).map_err(|e| e.to_string())
+    }
+}
+
+/// S.M.A.R.T. report for the drive holding `path`, for the shred and wipe dialogs.
+#[tauri::command]
+pub async fn check_drive_health(path: String) -> CommandResult<drive_health::DriveHealth> {
+    let path = SafePath::new(&path, PathPolicy::existing_entry())?;
+    tauri::async_runtime::spawn_blocking(move || drive_health::check(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// How recoverable a file deleted normally from `path` (its former location) still is,
+/// and whether a free-space wipe or TRIM would help. `deleted_at` is Unix seconds.
+#[tauri::command]
+pub async fn estimate_recovery_risk(path: String, deleted_at: Option<u64>) -> CommandResult<recovery_risk::RecoveryRisk> {
+    // The file is gone: check the path itself but not its existence.
+    let path = PathBuf::from(path.trim());
+    reject_path_traversal(&path)?;
+    if !path.is_absolute() {
+        return Err(i18n::AppError::new(i18n::ErrorCode::DeletedPathNotAbsolute).into());
+    }
+    tauri::async_runtime::spawn_blocking(move || recovery_risk::estimate(&path, deleted_at))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Fails with `DRIVE_HEALTH:<warning>` when a drive under `paths` reports trouble, so
+/// the UI can show the warning and retry with `ignore_health_warning` once the user
+/// confirms. Each volume is queried once, however many files sit on it.
+fn ensure_drive_health(paths: &[&Path], ignore_warning: bool) -> CommandResult<()> {
+    if ignore_warning {
+        return Ok(());
+    }
+    let volumes = mounted_volumes();
+    let mut checked = Vec::new();
+    for path in paths {
+        let volume = volume_of(path, &volumes).map(|v| v.mount_point.clone());
+        if checked.contains(&volume) {
+            continue;
+        }
+        checked.push(volume);
+        if let Some(warning) = drive_health::check(path).warning {
+            return Err(format!("DRIVE_HEALTH:{}", warning));
+        }
     }
+    Ok(())
+}
+
+// --- KEYFILE LOCATION ---
+
+/// Where a keyfile lives, for the "two-factor you can remove" workflow.
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyfileLocation {
+    pub mount_point: Option<String>,
+    pub drive_name: Option<String>,
+    pub removable: bool,
+    /// On the volume that holds the operating system, i.e. always next to the vault.
+    pub on_system_drive: bool,
+    pub warning: Option<String>,
+}
+
+/// A mounted volume as reported by sysinfo, reduced to what the keyfile and drive-removal checks need.
+#[derive(Debug, Clone)]
+pub(crate) struct MountedVolume {
+    pub mount_point: std::path::PathBuf,
+    pub name: String,
+    pub removable: bool,
+}
+
+pub(crate) fn mounted_volumes() -> Vec<MountedVolume> {
     #[cfg(not(target_os = "android"))]
     {
-        reject_critical_path(Path::new(&drive_path))?;
-        shredder::trim_drive(drive_path).map_err(|e| e.to_string())
+        Disks::new_with_refreshed_list()
+            .list()
+            .iter()
+            .map(|d| MountedVolume {
+                mount_point: d.mount_point().to_path_buf(),
+                name: d.name().to_string_lossy().to_string(),
+                removable: d.is_removable(),
+            })
+            .collect()
+    }
+    #[cfg(target_os = "android")]
+    {
+        Vec::new()
+    }
+}
+
+/// The volume holding `path`: the longest mount-point prefix wins, so `/media/usb` beats `/`.
+fn volume_of<'a>(path: &Path, volumes: &'a [MountedVolume]) -> Option<&'a MountedVolume> {
+    volumes
+        .iter()
+        .filter(|v| path.starts_with(&v.mount_point))
+        .max_by_key(|v| v.mount_point.components().count())
+}
+
+/// After a failed job: the removable drive under any of `paths` that was mounted when
+/// the job started (`before`) and is gone now. A failure on such a drive is reported as
+/// the removal it was rather than as whatever I/O error surfaced first.
+pub(crate) fn removed_drive(paths: &[&Path], before: &[MountedVolume], now: &[MountedVolume]) -> Option<MountedVolume> {
+    paths
+        .iter()
+        .filter_map(|p| volume_of(p, before))
+        .filter(|v| v.removable)
+        .find(|v| !v.mount_point.exists() || !now.iter().any(|n| n.mount_point == v.mount_point))
+        .cloned()
+}
+
+/// Root of the operating system volume (the `SystemDrive`, usually `C:\`, on Windows; `/` elsewhere).
+pub(crate) fn system_root() -> std::path::PathBuf {
+    #[cfg(windows)]
+    {
+        let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+        std::path::PathBuf::from(format!("{}\\", drive))
+    }
+    #[cfg(not(windows))]
+    {
+        std::path::PathBuf::from("/")
+    }
+}
+
+/// Finds the volume holding `path` and whether that volume also holds the operating system.
+pub(crate) fn locate_keyfile(path: &Path, volumes: &[MountedVolume], system_root: &Path) -> KeyfileLocation {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let volume = volume_of(&path, volumes);
+    let system_volume = volume_of(system_root, volumes);
+
+    let removable = volume.is_some_and(|v| v.removable);
+    let on_system_drive = match (volume, system_volume) {
+        (Some(v), Some(sys)) => v.mount_point == sys.mount_point,
+        // Unknown volume layout: only a path under the system root counts.
+        _ => volume.is_none() && path.starts_with(system_root),
+    };
+    let warning = if removable {
+        None
+    } else if on_system_drive {
+        Some("The keyfile is on the system drive, next to the vault. Anyone who copies this computer's disk gets both factors. Move it to a USB drive you keep separately.".to_string())
+    } else {
+        Some("The keyfile is on a fixed drive. Keep it on removable media to make it a second factor you can take away.".to_string())
+    };
+
+    KeyfileLocation {
+        mount_point: volume.map(|v| v.mount_point.to_string_lossy().to_string()),
+        drive_name: volume.map(|v| v.name.clone()),
+        removable,
+        on_system_drive,
+        warning,
+    }
+}
+
+/// Reports whether a keyfile sits on removable media, with a warning when it does not.
+#[tauri::command]
+pub fn check_keyfile_location(path: String) -> CommandResult<KeyfileLocation> {
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    Ok(locate_keyfile(&path, &mounted_volumes(), &system_root()))
+}
+
+// --- KEYFILE GENERATION ---
+
+/// Keyfiles are hashed to 32 bytes before use, so anything above the minimum adds no
+/// strength; the larger sizes are there for users who expect them.
+const KEYFILE_DEFAULT_BYTES: usize = 64;
+const KEYFILE_MIN_BYTES: usize = 32;
+const KEYFILE_MAX_BYTES: usize = 1024 * 1024;
+/// Largest keyfile whose backup text still fits a QR code that prints and scans reliably.
+const KEYFILE_MAX_QR_BYTES: usize = 1024;
+const KEYFILE_BACKUP_PREFIX: &str = "qre-keyfile:1:";
+
+#[derive(serde::Serialize)]
+pub struct GeneratedKeyfile {
+    pub path: String,
+    pub size_bytes: usize,
+    /// Printable QR code of the backup text, when asked for. `restore_keyfile` turns the
+    /// scanned text back into the keyfile.
+    pub qr_svg: Option<String>,
+    pub entropy: EntropyReport,
+    pub location: KeyfileLocation,
+}
+
+/// `size` random bytes from the OS RNG, mixed with the paranoid-mode seed if there is
+/// one (the same way `crypto_stream` mixes it into a file key).
+pub(crate) fn random_keyfile_bytes(size: usize, entropy_seed: Option<&[u8; 32]>) -> CommandResult<Zeroizing<Vec<u8>>> {
+    use rand::{RngCore, SeedableRng};
+    let mut seed = Zeroizing::new([0u8; 32]);
+    OsRng.try_fill_bytes(&mut *seed).map_err(|e| format!("OS RNG failed: {}", e))?;
+    if let Some(extra) = entropy_seed {
+        seed.iter_mut().zip(extra).for_each(|(s, e)| *s ^= e);
+    }
+    let mut rng = rand_chacha::ChaCha20Rng::from_seed(*seed);
+    let mut bytes = Zeroizing::new(vec![0u8; size]);
+    rng.fill_bytes(&mut bytes);
+    Ok(bytes)
+}
+
+/// One-line text form of a keyfile, for the QR backup.
+pub(crate) fn keyfile_backup_text(bytes: &[u8]) -> Zeroizing<String> {
+    Zeroizing::new(format!("{}{}", KEYFILE_BACKUP_PREFIX, data_encoding::BASE64.encode(bytes)))
+}
+
+pub(crate) fn parse_keyfile_backup(text: &str) -> CommandResult<Zeroizing<Vec<u8>>> {
+    let encoded = text
+        .trim()
+        .strip_prefix(KEYFILE_BACKUP_PREFIX)
+        .ok_or_else(|| i18n::AppError::new(i18n::ErrorCode::KeyfileBackupInvalid))?;
+    data_encoding::BASE64
+        .decode(encoded.as_bytes())
+        .map(Zeroizing::new)
+        .map_err(|_| i18n::AppError::new(i18n::ErrorCode::KeyfileBackupDamaged).into())
+}
+
+/// Writes a new keyfile through a temp file, fsync and rename, so a crash never leaves
+/// a truncated keyfile behind. An existing file is never replaced: overwriting a
+/// keyfile would lock its owner out of everything encrypted with it.
+fn write_new_keyfile(path: &Path, bytes: &[u8]) -> CommandResult<()> {
+    use std::io::Write;
+    if path.exists() {
+        return Err(i18n::AppError::new(i18n::ErrorCode::KeyfileExists).with("path", path.display()).into());
+    }
+    let tmp = path.with_file_name(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    let result = (|| {
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map_err(|e| format!("Failed to write keyfile: {}", e))
+}
+
+/// Creates a keyfile of `size_bytes` random bytes (default 64) at `output_path`,
+/// optionally mixed with the paranoid-mode entropy sources and with a QR backup.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn generate_keyfile(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    output_path: String,
+    size_bytes: Option<usize>,
+    extra_entropy: Option<Vec<u8>>,
+    entropy_sources: Option<EntropyOptions>,
+    qr_backup: Option<bool>,
+) -> CommandResult<GeneratedKeyfile> {
+    state.ensure_writable("local")?;
+    let size = size_bytes.unwrap_or(KEYFILE_DEFAULT_BYTES);
+    if !(KEYFILE_MIN_BYTES..=KEYFILE_MAX_BYTES).contains(&size) {
+        return Err(i18n::AppError::new(i18n::ErrorCode::KeyfileSizeInvalid).with("min", KEYFILE_MIN_BYTES).with("max_kb", KEYFILE_MAX_BYTES / 1024).into());
+    }
+    let qr_backup = qr_backup.unwrap_or(false);
+    if qr_backup && size > KEYFILE_MAX_QR_BYTES {
+        return Err(i18n::AppError::new(i18n::ErrorCode::KeyfileTooLargeForQr).with("max", KEYFILE_MAX_QR_BYTES).into());
+    }
+    let output = SafePath::new(&output_path, PathPolicy::write_file())?;
+    let (entropy_pool, entropy_report) =
+        entropy::mix_sources(extra_entropy.as_deref(), &entropy_sources.unwrap_or_default())?;
+    if !entropy_report.sources.is_empty() {
+        let _ = app.emit("entropy-report", &entropy_report);
     }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = random_keyfile_bytes(size, entropy_pool.as_deref())?;
+        write_new_keyfile(&output, &bytes)?;
+        let qr_svg = if qr_backup {
+            let text = keyfile_backup_text(&bytes);
+            let qr = crate::qr::generate_qr(crate::qr::QrOptions {
+                text: text.to_string(),
+                fg_color: "#000000".to_string(),
+                bg_color: "#ffffff".to_string(),
+                ecc: crate::qr::ErrorCorrectionLevel::Medium,
+                border: 4,
+            })
+            .map_err(|e| e.to_string())?;
+            Some(qr.svg)
+        } else {
+            None
+        };
+        Ok(GeneratedKeyfile {
+            path: output.to_string_lossy().to_string(),
+            size_bytes: size,
+            qr_svg,
+            entropy: entropy_report,
+            location: locate_keyfile(&output, &mounted_volumes(), &system_root()),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Recreates a keyfile at `output_path` from the text of its QR backup.
+#[tauri::command]
+pub fn restore_keyfile(
+    state: tauri::State<'_, SessionState>,
+    backup_text: String,
+    output_path: String,
+) -> CommandResult<KeyfileLocation> {
+    state.ensure_writable("local")?;
+    let backup_text = Zeroizing::new(backup_text);
+    let bytes = parse_keyfile_backup(&backup_text)?;
+    let output = SafePath::new(&output_path, PathPolicy::write_file())?;
+    write_new_keyfile(&output, &bytes)?;
+    Ok(locate_keyfile(&output, &mounted_volumes(), &system_root()))
+}
+
+// --- PAPER BACKUP ---
+
+/// Encrypts a small secret (a keyfile, recovery codes...) with `passphrase` and saves it
+/// as printable pages with QR codes and restore instructions (HTML) at `output_path`.
+/// Owner only: the pages carry a secret out of the vault under a passphrase alone, so the
+/// local vault policy has to allow passphrase-only encryption.
+#[tauri::command]
+pub async fn export_paper_backup(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    data: Vec<u8>,
+    passphrase: String,
+    label: Option<String>,
+    output_path: String,
+) -> CommandResult<paper_backup::PaperBackup> {
+    super::vault::ensure_owner(&state, "local")?;
+    let data = Zeroizing::new(data);
+    let passphrase = Zeroizing::new(passphrase);
+    let policy = super::vault::load_vault_policy(&app, "local")?;
+    policy
+        .check_encryption(false, None)
+        .map_err(|e| format!("{} Paper backups are not available under this policy.", e))?;
+    policy.check_password(&passphrase).map_err(|e| e.to_string())?;
+    let output = SafePath::new(&output_path, PathPolicy::write_file())?;
+    // Argon2id takes a moment; keep it off the async runtime.
+    tauri::async_runtime::spawn_blocking(move || {
+        let backup = paper_backup::export(&data, &passphrase, label.as_deref().unwrap_or_default()).map_err(|e| e.to_string())?;
+        fs::write(&output, &backup.html).map_err(|e| format!("Failed to write the backup: {}", e))?;
+        Ok(backup)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Rebuilds a paper backup from the text of its pages (scanned QR codes or typed lines,
+/// any order) and writes the secret to a new file at `output_path`. Returns its size.
+#[tauri::command]
+pub async fn import_paper_backup(
+    state: tauri::State<'_, SessionState>,
+    pages: Vec<String>,
+    passphrase: String,
+    output_path: String,
+) -> CommandResult<usize> {
+    state.ensure_writable("local")?;
+    let passphrase = Zeroizing::new(passphrase);
+    let output = SafePath::new(&output_path, PathPolicy::write_file())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let secret = paper_backup::import(&pages, &passphrase).map_err(|e| e.to_string())?;
+        write_new_keyfile(&output, &secret)?;
+        Ok(secret.len())
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 // --- SYSTEM UTILS ---
 
+/// Selects the language of backend error messages ("en", "el", "de"; region tags accepted).
+/// Returns the locale actually applied. Unknown tags fall back to English.
+#[tauri::command]
+pub fn set_locale(locale: String) -> String {
+    let applied = i18n::Locale::parse(&locale).unwrap_or(i18n::Locale::En);
+    i18n::set_locale(applied);
+    applied.tag().to_string()
+}
+
+#[tauri::command]
+pub fn get_locale() -> String {
+    i18n::current_locale().tag().to_string()
+}
+
 #[tauri::command]
 pub fn get_drives(_app: AppHandle) -> Vec<String> {
     let mut drives = Vec::new();
@@ -663,6 +2891,34 @@ pub fn get_drives(_app: AppHandle) -> Vec<String> {
     drives
 }
 
+/// Arguments of a later launch that name existing files (a double-clicked .qre),
+/// resolved against that launch's working directory.
+pub(crate) fn paths_from_args(argv: &[String], cwd: &Path) -> Vec<String> {
+    argv.iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(|arg| cwd.join(arg))
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Runs in the existing instance when the app is launched again: brings its window to
+/// the front and forwards the files the new launch was asked to open ("open-files").
+#[cfg(not(mobile))]
+pub fn handoff_from_second_instance(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    use tauri::Manager;
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let paths = paths_from_args(&argv, Path::new(&cwd));
+    if !paths.is_empty() {
+        let _ = app.emit("open-files", paths);
+    }
+}
+
 #[tauri::command]
 pub fn get_startup_file() -> Option<String> {
     let args: Vec<String> = std::env::args().collect();
@@ -0,0 +1,177 @@
+// --- START OF FILE guard.rs ---
+
+// ==========================================
+// --- COMMAND RATE LIMITS & JOB GUARDS ---
+// ==========================================
+// Tauri commands are reachable from any script running in the webview, so the
+// command layer cannot assume the UI's buttons are the only caller:
+//
+//   - Rate limits: a sliding window per command name. `login` in a tight loop is
+//     already slowed by the failure lockout, but only once attempts start failing;
+//     destructive commands like `delete_items` had no bound at all.
+//   - Job guards: long-running exclusive jobs (shredding, free-space wipes, system
+//     cleaning) hold a guard for their whole run, so a second start is refused
+//     instead of racing the first one over the same files or the same disk.
+//
+// Both fail with machine-readable errors, following the `TIME_LOCKED:` convention:
+//   "RATE_LIMITED:<retry_after_ms>:<message>"
+//   "BUSY:<job>:<message>"
+
+use super::files::CommandResult;
+use crate::i18n::{AppError, ErrorCode};
+use crate::power::PowerHold;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// ==========================================
+// --- RATE LIMITS ---
+// ==========================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_calls: usize,
+    pub window: Duration,
+}
+
+/// Password checks: generous for a human typing, useless for a guessing loop.
+pub const AUTH_RATE: RateLimit = RateLimit {
+    max_calls: 5,
+    window: Duration::from_secs(10),
+};
+
+/// Deleting, trashing and shredding: one call per user action is the norm.
+pub const DESTRUCTIVE_RATE: RateLimit = RateLimit {
+    max_calls: 10,
+    window: Duration::from_secs(10),
+};
+
+/// Sliding-window call log per command, kept free of globals so it can be unit tested.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    calls: HashMap<&'static str, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    /// Records a call at `now`, or returns how long to wait if the window is full.
+    /// Rejected calls are not recorded, so a caller hammering the limit is not
+    /// locked out longer than the window itself.
+    pub fn check_at(
+        &mut self,
+        command: &'static str,
+        limit: RateLimit,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let calls = self.calls.entry(command).or_default();
+        while calls
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) >= limit.window)
+        {
+            calls.pop_front();
+        }
+        if calls.len() >= limit.max_calls {
+            let oldest = *calls.front().unwrap_or(&now);
+            return Err(limit
+                .window
+                .saturating_sub(now.saturating_duration_since(oldest)));
+        }
+        calls.push_back(now);
+        Ok(())
+    }
+}
+
+fn limiter() -> &'static Mutex<RateLimiter> {
+    static LIMITER: OnceLock<Mutex<RateLimiter>> = OnceLock::new();
+    LIMITER.get_or_init(|| Mutex::new(RateLimiter::default()))
+}
+
+/// Call first thing in a command body. Fails with `RATE_LIMITED:` when over the limit.
+pub fn rate_limit(command: &'static str, limit: RateLimit) -> CommandResult<()> {
+    let mut guard = limiter().lock().unwrap_or_else(|p| p.into_inner());
+    guard
+        .check_at(command, limit, Instant::now())
+        .map_err(|wait| {
+            format!(
+                "RATE_LIMITED:{}:{}",
+                wait.as_millis(),
+                AppError::new(ErrorCode::RateLimited).with("seconds", wait.as_secs().max(1))
+            )
+        })
+}
+
+// ==========================================
+// --- EXCLUSIVE JOBS ---
+// ==========================================
+
+/// Job families that must never run twice at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Job {
+    /// File shredding and secure deletion.
+    Shred,
+    /// Free-space wipe and TRIM (whole-disk operations).
+    DiskWipe,
+    /// System junk cleaning.
+    SystemClean,
+    /// Solving a time-lock puzzle (one at a time: each takes a whole core).
+    TimePuzzle,
+}
+
+static SHRED_BUSY: AtomicBool = AtomicBool::new(false);
+static DISK_WIPE_BUSY: AtomicBool = AtomicBool::new(false);
+static SYSTEM_CLEAN_BUSY: AtomicBool = AtomicBool::new(false);
+static TIME_PUZZLE_BUSY: AtomicBool = AtomicBool::new(false);
+
+impl Job {
+    fn flag(self) -> &'static AtomicBool {
+        match self {
+            Job::Shred => &SHRED_BUSY,
+            Job::DiskWipe => &DISK_WIPE_BUSY,
+            Job::SystemClean => &SYSTEM_CLEAN_BUSY,
+            Job::TimePuzzle => &TIME_PUZZLE_BUSY,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Job::Shred => "shred",
+            Job::DiskWipe => "disk_wipe",
+            Job::SystemClean => "system_clean",
+            Job::TimePuzzle => "time_puzzle",
+        }
+    }
+}
+
+/// Held for the lifetime of a job; dropping it (including on error or panic) frees the slot.
+#[must_use = "the job slot is released as soon as the guard is dropped"]
+pub struct JobGuard {
+    job: Job,
+    /// Keeps the machine awake while the job runs (see power.rs).
+    _power: PowerHold,
+}
+
+impl JobGuard {
+    pub fn acquire(job: Job) -> CommandResult<Self> {
+        job.flag()
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| JobGuard {
+                job,
+                _power: PowerHold::acquire(&format!("QRE {} job", job.name().replace('_', " "))),
+            })
+            .map_err(|_| {
+                format!(
+                    "BUSY:{}:{}",
+                    job.name(),
+                    AppError::new(ErrorCode::JobBusy).with("job", job.name().replace('_', " "))
+                )
+            })
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.job.flag().store(false, Ordering::Release);
+    }
+}
+
+// --- END OF FILE guard.rs ---
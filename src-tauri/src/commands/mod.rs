@@ -1,5 +1,7 @@
 pub mod files;
+pub mod guard;
 pub mod portable;
+pub mod safe_path;
 pub mod timelock;
 pub mod tools;
 pub mod vault;
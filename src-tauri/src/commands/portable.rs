@@ -1,5 +1,8 @@
 // --- START OF FILE portable.rs ---
 
+use super::guard::{rate_limit, AUTH_RATE};
+use super::safe_path::{PathPolicy, SafePath};
+use crate::i18n::{AppError, ErrorCode};
 use crate::keychain::MasterKey;
 use crate::state::SessionState;
 use aes_gcm::{
@@ -152,19 +155,18 @@ pub fn init_portable_vault(
     #[cfg(target_os = "android")]
     {
         let _ = (drive_path, password, tier);
-        return Err("Portable initialization not supported directly on Android yet.".to_string());
+        return Err(AppError::new(ErrorCode::UnsupportedOnAndroid).into());
     }
 
     #[cfg(not(target_os = "android"))]
     {
-        let base_path = PathBuf::from(&drive_path);
-        if !base_path.exists() {
-            return Err("Drive not found.".to_string());
-        }
+        let base_path = SafePath::new(&drive_path, PathPolicy::directory())
+            .map_err(|_| AppError::new(ErrorCode::DriveNotFound))?
+            .into_path_buf();
 
         let qre_dir = base_path.join(".qre_portable");
         if qre_dir.exists() {
-            return Err("Drive is already formatted as a QRE vault.".to_string());
+            return Err(AppError::new(ErrorCode::DriveAlreadyVault).into());
         }
 
         fs::create_dir_all(&qre_dir).map_err(|e| e.to_string())?;
@@ -283,12 +285,12 @@ pub(crate) fn unlock_vault_from_drive(
         .join("keychain.qre");
 
     if !keychain_path.exists() {
-        return Err("Portable vault not found on this drive.".to_string());
+        return Err(AppError::new(ErrorCode::PortableVaultNotFound).into());
     }
 
     let file = fs::File::open(&keychain_path).map_err(|e| e.to_string())?;
     let store: PortableKeychainStore =
-        serde_json::from_reader(file).map_err(|_| "Corrupted keychain".to_string())?;
+        serde_json::from_reader(file).map_err(|_| AppError::new(ErrorCode::KeychainCorrupted))?;
 
     let kek = derive_kek(
         password,
@@ -305,11 +307,11 @@ pub(crate) fn unlock_vault_from_drive(
     let mk_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
         cipher
             .decrypt(nonce, store.encrypted_master_key_pass.as_ref())
-            .map_err(|_| "Incorrect Password".to_string())?,
+            .map_err(|_| AppError::new(ErrorCode::IncorrectPassword))?,
     );
 
     if mk_bytes.len() != 32 {
-        return Err("Invalid master key length".to_string());
+        return Err(AppError::new(ErrorCode::KeychainCorrupted).into());
     }
 
     let mut arr = [0u8; 32];
@@ -378,13 +380,20 @@ pub fn unlock_portable_vault(
     drive_path: String,
     password: String,
 ) -> CommandResult<String> {
-    unlock_vault_from_drive(
+    rate_limit("unlock_portable_vault", AUTH_RATE)?;
+    // Validated only: the caller's spelling of the mount point stays the key in
+    // `portable_mounts`, which the UI matches against its drive list.
+    SafePath::new(&drive_path, PathPolicy::directory())?;
+    let vault_id = unlock_vault_from_drive(
         Some(&app), // <--- FIX: Pass Some(&app) here
         &drive_path,
         &password,
         &state.vaults,
         &state.portable_mounts,
-    )
+    )?;
+    // A password unlock is never a guest session, whatever this vault was before.
+    state.set_read_only(&vault_id, false);
+    Ok(vault_id)
 }
 
 pub(crate) fn lock_vault_by_id(
@@ -398,7 +407,7 @@ pub(crate) fn lock_vault_by_id(
 ) -> CommandResult<()> {
     let mut guard = vaults
         .lock()
-        .map_err(|_| "Session state corrupted".to_string())?;
+        .map_err(|_| AppError::new(ErrorCode::SessionCorrupted))?;
     guard.remove(vault_id);
 
     if let Ok(mut mg) = mounts.lock() {
@@ -412,6 +421,8 @@ pub fn lock_portable_vault(
     state: tauri::State<SessionState>,
     vault_id: String,
 ) -> CommandResult<()> {
-    lock_vault_by_id(&vault_id, &state.vaults, &state.portable_mounts)
+    lock_vault_by_id(&vault_id, &state.vaults, &state.portable_mounts)?;
+    state.set_read_only(&vault_id, false);
+    Ok(())
 }
 // --- END OF FILE portable.rs ---
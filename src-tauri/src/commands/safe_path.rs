@@ -0,0 +1,273 @@
+// --- START OF FILE safe_path.rs ---
+
+// ==========================================
+// --- VALIDATED COMMAND PATHS ---
+// ==========================================
+// Every command that accepts a file or folder path from the frontend turns the raw
+// string into a `SafePath` before touching the disk. Construction is the only way to
+// get one, so a command cannot "forget" a check the way `save_text_to_file` and
+// `export_keychain` previously did.
+//
+// Checks, in order:
+//   1. Not empty, no NUL bytes, no `..` components (lexical traversal).
+//   2. Symlink policy for the final component (reject, follow, or act on the link itself).
+//   3. Existence and kind (file / directory) as the policy requires.
+//   4. Canonicalization — the stored path is absolute and free of `.`/`..`/links.
+//   5. System-critical locations (`/etc`, `C:\Windows`, ...) are refused, both as
+//      given and after resolution.
+//   6. Optional size cap for files that will be read into memory.
+
+use super::files::{is_system_critical, reject_critical_path, CommandResult};
+use crate::i18n::{AppError, ErrorCode};
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Files read whole into memory (text imports, share bundles) are capped at 50 MB.
+pub const MAX_IN_MEMORY_FILE_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// A symlink as the final component is an error.
+    Reject,
+    /// Resolve the link and operate on its target.
+    Follow,
+    /// Operate on the link itself (delete, rename, shred the link, never its target).
+    AsLink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Existence {
+    /// Must already exist (inputs).
+    Required,
+    /// May or may not exist, but its parent directory must (outputs).
+    ParentRequired,
+}
+
+/// What a command expects of a path. Built from one of the presets below.
+#[derive(Debug, Clone, Copy)]
+pub struct PathPolicy {
+    existence: Existence,
+    allow_file: bool,
+    allow_dir: bool,
+    symlinks: SymlinkPolicy,
+    max_bytes: Option<u64>,
+}
+
+impl PathPolicy {
+    /// An existing regular file that will be read.
+    pub const fn read_file() -> Self {
+        Self {
+            existence: Existence::Required,
+            allow_file: true,
+            allow_dir: false,
+            symlinks: SymlinkPolicy::Follow,
+            max_bytes: None,
+        }
+    }
+
+    /// A file that will be created or overwritten. Writing through a symlink is refused,
+    /// so an attacker-planted link cannot redirect an export onto another file.
+    pub const fn write_file() -> Self {
+        Self {
+            existence: Existence::ParentRequired,
+            allow_file: true,
+            allow_dir: false,
+            symlinks: SymlinkPolicy::Reject,
+            max_bytes: None,
+        }
+    }
+
+    /// An existing directory (output folders, drives, scan roots).
+    pub const fn directory() -> Self {
+        Self {
+            existence: Existence::Required,
+            allow_file: false,
+            allow_dir: true,
+            symlinks: SymlinkPolicy::Follow,
+            max_bytes: None,
+        }
+    }
+
+    /// A directory that will be created.
+    pub const fn new_directory() -> Self {
+        Self {
+            existence: Existence::ParentRequired,
+            allow_file: false,
+            allow_dir: true,
+            symlinks: SymlinkPolicy::Reject,
+            max_bytes: None,
+        }
+    }
+
+    /// An existing file or folder that will be modified, moved or removed in place.
+    /// Links are handled as links so the operation never escapes to their target.
+    pub const fn existing_entry() -> Self {
+        Self {
+            existence: Existence::Required,
+            allow_file: true,
+            allow_dir: true,
+            symlinks: SymlinkPolicy::AsLink,
+            max_bytes: None,
+        }
+    }
+
+    pub const fn max_bytes(mut self, limit: u64) -> Self {
+        self.max_bytes = Some(limit);
+        self
+    }
+
+    pub const fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+}
+
+/// A frontend-supplied path that passed a `PathPolicy`. Always absolute and canonical
+/// (up to the final component for outputs and `SymlinkPolicy::AsLink`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafePath(PathBuf);
+
+impl SafePath {
+    pub fn new(raw: &str, policy: PathPolicy) -> CommandResult<Self> {
+        if raw.trim().is_empty() {
+            return Err(AppError::new(ErrorCode::PathMissing).into());
+        }
+        if raw.contains('\0') {
+            return Err(AppError::new(ErrorCode::PathInvalid).into());
+        }
+        let path = Path::new(raw);
+        // Lexical pass on the path as given; the system check is repeated on the
+        // resolved path below, since a link can point into a protected location.
+        reject_critical_path(path)?;
+
+        let link_meta = fs::symlink_metadata(path).ok();
+        let is_link = link_meta
+            .as_ref()
+            .is_some_and(|m| m.file_type().is_symlink());
+        if is_link && policy.symlinks == SymlinkPolicy::Reject {
+            return Err(AppError::new(ErrorCode::PathIsSymlink)
+                .with("path", path.display())
+                .into());
+        }
+
+        let resolved = match (&link_meta, policy.existence) {
+            (None, Existence::Required) => {
+                return Err(AppError::new(ErrorCode::PathNotFound)
+                    .with("path", path.display())
+                    .into())
+            }
+            (None, Existence::ParentRequired) => resolve_in_parent(path)?,
+            (Some(_), _) if is_link && policy.symlinks == SymlinkPolicy::AsLink => {
+                resolve_in_parent(path)?
+            }
+            (Some(_), _) => canonicalize(path)?,
+        };
+
+        if is_system_critical(&resolved) {
+            return Err(AppError::new(ErrorCode::PathProtected)
+                .with("path", resolved.display())
+                .into());
+        }
+
+        // Kind and size are judged on what the operation will actually touch: the link
+        // itself for AsLink, otherwise the resolved target.
+        let meta = if is_link && policy.symlinks == SymlinkPolicy::AsLink {
+            link_meta
+        } else {
+            fs::metadata(&resolved).ok()
+        };
+        if let Some(meta) = meta {
+            let is_dir = meta.is_dir();
+            if is_dir && !policy.allow_dir {
+                return Err(AppError::new(ErrorCode::PathIsFolder)
+                    .with("path", resolved.display())
+                    .into());
+            }
+            if !is_dir && !policy.allow_file {
+                return Err(AppError::new(ErrorCode::PathNotFolder)
+                    .with("path", resolved.display())
+                    .into());
+            }
+            if let Some(limit) = policy.max_bytes {
+                if !is_dir && meta.len() > limit {
+                    return Err(AppError::new(ErrorCode::PathTooLarge)
+                        .with("path", resolved.display())
+                        .with("size", meta.len())
+                        .with("limit", limit)
+                        .into());
+                }
+            }
+        }
+
+        Ok(SafePath(resolved))
+    }
+
+    /// Validates every path in a batch, failing on the first rejected one.
+    pub fn all(raw: &[String], policy: PathPolicy) -> CommandResult<Vec<SafePath>> {
+        raw.iter().map(|p| SafePath::new(p, policy)).collect()
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn into_path_buf(self) -> PathBuf {
+        self.0
+    }
+}
+
+/// Canonicalizes the parent and re-attaches the final component, for paths that do not
+/// exist yet or that must not be dereferenced.
+fn resolve_in_parent(path: &Path) -> CommandResult<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| AppError::new(ErrorCode::PathNoFileName).with("path", path.display()))?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let parent = canonicalize(parent)
+        .map_err(|_| AppError::new(ErrorCode::FolderNotFound).with("path", parent.display()))?;
+    Ok(parent.join(name))
+}
+
+/// `fs::canonicalize`, minus the `\\?\` verbatim prefix Windows adds to drive paths.
+/// Those paths are shown to the user and joined with relative names later, and the
+/// prefix breaks both.
+fn canonicalize(path: &Path) -> CommandResult<PathBuf> {
+    let resolved = fs::canonicalize(path)
+        .map_err(|e| format!("Cannot resolve '{}': {}", path.display(), e))?;
+    #[cfg(windows)]
+    {
+        let s = resolved.to_string_lossy();
+        if let Some(rest) = s.strip_prefix(r"\\?\") {
+            if rest.as_bytes().get(1) == Some(&b':') {
+                return Ok(PathBuf::from(rest));
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+impl Deref for SafePath {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for SafePath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl fmt::Display for SafePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+// --- END OF FILE safe_path.rs ---
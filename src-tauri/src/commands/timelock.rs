@@ -5,17 +5,25 @@
 // With the V6 embedded design:
 //   - lock_file_with_timelock  → calls encrypt_file_stream with timelock_until
 //   - get_file_timelock_status → reads the plaintext header, no master key needed
+//   - solve_timelock_puzzle    → works through a file's optional time-lock puzzle
+//     (timelock_puzzle.rs), checkpointing its progress into the header
 //   - unlock_file_with_timelock is REMOVED — the regular unlock command in
 //     files.rs now handles time-locked files natively, since decrypt_file_stream
 //     checks the timestamp and returns a TIME_LOCKED: error when appropriate.
 
-use super::files::{is_already_compressed, reject_critical_path, BatchItemResult, CommandResult};
+use super::files::{compression_level, BatchItemResult, CommandResult};
+use super::guard::{Job, JobGuard};
+use super::safe_path::{PathPolicy, SafePath, SymlinkPolicy};
 use crate::crypto_stream;
+use crate::i18n::{AppError, ErrorCode};
 use crate::keychain::MasterKey;
 use crate::state::SessionState;
 use crate::timelock::{self, TimeLockStatus};
+use crate::timelock_puzzle::{self, PuzzleStatus};
 use crate::utils;
-use std::path::{Component, Path};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tauri::AppHandle;
 
 // ==========================================
@@ -30,6 +38,10 @@ use tauri::AppHandle;
 /// Passes `timelock_until: Some(unlock_at)` and `keyfile_bytes: None`
 /// to `encrypt_file_stream`, which generates the binding key internally
 /// and embeds the time-lock metadata in the V6 StreamHeader.
+///
+/// With `puzzle`, the file is also gated by a time-lock puzzle sized from this
+/// computer's measured speed, which has to be solved with `solve_timelock_puzzle`
+/// before the file opens, even after the date.
 #[tauri::command]
 pub async fn lock_file_with_timelock(
     app: AppHandle,
@@ -37,25 +49,31 @@ pub async fn lock_file_with_timelock(
     file_path: String,
     unlock_at: u64,
     compression_mode: Option<String>,
+    puzzle: Option<bool>,
 ) -> CommandResult<BatchItemResult> {
-    // ── PATH VALIDATION ───────────────────────────────────────────────────────
-    let path = Path::new(&file_path);
-
-    if path.components().any(|c| c == Component::ParentDir) {
-        return Err("Path traversal not allowed.".to_string());
-    }
-    reject_critical_path(path)?;
+    state.ensure_writable("local")?;
 
-    if !path.is_absolute() {
-        return Err("File path must be absolute.".to_string());
+    // ── PATH VALIDATION ───────────────────────────────────────────────────────
+    if !Path::new(&file_path).is_absolute() {
+        return Err(AppError::new(ErrorCode::PathNotAbsolute).into());
     }
+    let file_path = SafePath::new(&file_path, PathPolicy::read_file())?
+        .to_string_lossy()
+        .to_string();
     if file_path.ends_with(".qre") {
-        return Err("Cannot time-lock an already-encrypted .qre file.".to_string());
+        return Err(AppError::new(ErrorCode::TimeLockAlreadyEncrypted).into());
     }
 
     // ── TIMESTAMP VALIDATION (authoritative — Rust side) ─────────────────────
     timelock::validate_unlock_at(unlock_at).map_err(|e| e)?;
 
+    // ── VAULT POLICY ─────────────────────────────────────────────────────────
+    // Time-locked files never take a keyfile or extra entropy, so a vault whose
+    // policy demands either cannot create them.
+    super::vault::load_vault_policy(&app, "local")?
+        .check_encryption(false, None)
+        .map_err(|e| e.to_string())?;
+
     let vaults_arc = state.vaults.clone();
     let portable_mounts_arc = state.portable_mounts.clone();
 
@@ -94,7 +112,7 @@ pub async fn lock_file_with_timelock(
                 Err(poisoned) => {
                     let mut p = poisoned.into_inner();
                     p.clear();
-                    return Err("Session state corrupted. Please re-authenticate.".to_string());
+                    return Err(AppError::new(ErrorCode::SessionCorrupted).into());
                 }
             };
             match guard.get("local") {
@@ -116,17 +134,7 @@ pub async fn lock_file_with_timelock(
 
         // Compression level
         let mode_str = compression_mode.unwrap_or_else(|| "auto".to_string());
-        let level = match mode_str.as_str() {
-            "store" => 0,
-            "extreme" => 19,
-            _ => {
-                if is_already_compressed(&filename) {
-                    1
-                } else {
-                    3
-                }
-            }
-        };
+        let level = compression_level(&mode_str, &filename);
 
         utils::emit_progress(&app, &format!("Time-locking: {}", filename), 10);
 
@@ -139,8 +147,27 @@ pub async fn lock_file_with_timelock(
             }
         };
 
+        let puzzle_rate = if puzzle.unwrap_or(false) {
+            utils::emit_progress(&app, "Measuring this computer's speed", 5);
+            match timelock_puzzle::measure_squarings_per_sec() {
+                Ok(rate) => {
+                    LOCAL_SQUARINGS_PER_SEC.store(rate, Ordering::Relaxed);
+                    Some(rate)
+                }
+                Err(e) => {
+                    return Ok(BatchItemResult {
+                        name: filename,
+                        success: false,
+                        message: format!("Could not set up the time-lock puzzle: {}", e),
+                    })
+                }
+            }
+        } else {
+            None
+        };
+
         // Encrypt with embedded time-lock (no external keyfile, no sidecar)
-        match crypto_stream::encrypt_file_stream(
+        match crypto_stream::encrypt_file_stream_with_metadata(
             &file_path,
             &final_qre_str,
             &master_key,
@@ -149,17 +176,29 @@ pub async fn lock_file_with_timelock(
             Some(unlock_at), // timelock_until: embedded in V6 StreamHeader
             None,            // entropy_seed
             level,
+            None,
+            None,
+            None,
+            None,
+            puzzle_rate,
+            None,
             progress_cb,
         ) {
             Ok(()) => {
                 utils::emit_progress(&app, &format!("Locked: {}", filename), 100);
+                let duration =
+                    timelock::format_duration(unlock_at.saturating_sub(timelock::now_secs()));
                 Ok(BatchItemResult {
                     name: filename,
                     success: true,
-                    message: format!(
-                        "Time-locked for {}",
-                        timelock::format_duration(unlock_at.saturating_sub(timelock::now_secs()))
-                    ),
+                    message: match puzzle_rate {
+                        Some(_) => format!(
+                            "Time-locked for about {} with a puzzle. {}",
+                            duration,
+                            timelock_puzzle::APPROXIMATE_NOTE
+                        ),
+                        None => format!("Time-locked for {}", duration),
+                    },
                 })
             }
             Err(e) => {
@@ -189,13 +228,19 @@ pub async fn lock_file_with_timelock(
 /// and any file that fails to parse.
 #[tauri::command]
 pub fn get_file_timelock_status(qre_path: String) -> CommandResult<TimeLockStatus> {
-    // Basic path safety
-    let path = Path::new(&qre_path);
-    if path.components().any(|c| c == Component::ParentDir) {
-        return Err("Path traversal not allowed.".to_string());
-    }
+    let unlocked = TimeLockStatus {
+        is_locked: false,
+        locked_until: 0,
+        remaining_display: String::new(),
+    };
+    // Traversal is an error; a missing or unreadable file is simply "not locked".
+    super::files::reject_path_traversal(Path::new(&qre_path))?;
+    let qre_path = match SafePath::new(&qre_path, PathPolicy::read_file()) {
+        Ok(p) => p,
+        Err(_) => return Ok(unlocked),
+    };
 
-    match crypto_stream::read_timelock_header(&qre_path) {
+    match crypto_stream::read_timelock_header(&qre_path.to_string_lossy()) {
         Ok(Some(meta)) => {
             let now = timelock::now_secs();
             let is_locked = now < meta.locked_until;
@@ -211,11 +256,124 @@ pub fn get_file_timelock_status(qre_path: String) -> CommandResult<TimeLockStatu
             })
         }
         // Not time-locked, V5 file, or unreadable — treat as unlocked
-        Ok(None) | Err(_) => Ok(TimeLockStatus {
-            is_locked: false,
-            locked_until: 0,
-            remaining_display: String::new(),
-        }),
+        Ok(None) | Err(_) => Ok(unlocked),
+    }
+}
+
+// ==========================================
+// --- TIME-LOCK PUZZLE ---
+// ==========================================
+
+/// Set by `stop_timelock_puzzle`, checked between batches of squarings.
+static PUZZLE_STOP_FLAG: AtomicBool = AtomicBool::new(false);
+/// This computer's speed, once measured; until then estimates use the speed of the
+/// computer that made the puzzle.
+static LOCAL_SQUARINGS_PER_SEC: AtomicU64 = AtomicU64::new(0);
+
+/// How often progress is saved into the header and reported.
+const PUZZLE_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+fn puzzle_speed(meta: &timelock_puzzle::PuzzleMeta) -> u64 {
+    match LOCAL_SQUARINGS_PER_SEC.load(Ordering::Relaxed) {
+        0 => meta.squarings_per_sec,
+        rate => rate,
+    }
+}
+
+/// Saves puzzle progress into the header region. The header is re-read first so a
+/// ratchet written by an unlock attempt in the meantime is kept.
+fn save_puzzle_progress(qre_path: &str, meta: &timelock_puzzle::PuzzleMeta) -> anyhow::Result<()> {
+    let (_, mut header) = crypto_stream::read_stream_header(qre_path)?;
+    header.puzzle = Some(meta.clone());
+    crypto_stream::write_header_region(qre_path, &header)
+}
+
+/// Works through a file's time-lock puzzle for up to `max_seconds` (until solved or
+/// stopped if omitted), saving progress every few seconds, and returns where it got.
+/// Solving needs no master key: the puzzle only replaces the wait, not the password.
+#[tauri::command]
+pub async fn solve_timelock_puzzle(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+    qre_path: String,
+    max_seconds: Option<u64>,
+) -> CommandResult<PuzzleStatus> {
+    state.ensure_writable("local")?;
+    super::files::reject_path_traversal(Path::new(&qre_path))?;
+    let qre_path = SafePath::new(
+        &qre_path,
+        PathPolicy::read_file().symlinks(SymlinkPolicy::Reject),
+    )?
+    .to_string_lossy()
+    .to_string();
+    let job = JobGuard::acquire(Job::TimePuzzle)?;
+    PUZZLE_STOP_FLAG.store(false, Ordering::Relaxed);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _job = job;
+        let (_, header) =
+            crypto_stream::read_stream_header(&qre_path).map_err(|e| e.to_string())?;
+        let mut meta = header
+            .puzzle
+            .ok_or_else(|| AppError::new(ErrorCode::NoTimeLockPuzzle))?;
+        let deadline = max_seconds.map(|s| Instant::now() + Duration::from_secs(s));
+
+        let mut last_checkpoint = Instant::now();
+        while !meta.is_solved() {
+            // Batches of about a second keep stop requests responsive.
+            let before = meta.done;
+            let batch_start = Instant::now();
+            let solved = timelock_puzzle::advance(&mut meta, puzzle_speed(&meta))
+                .map_err(|e| e.to_string())?;
+            let elapsed = batch_start.elapsed().as_secs_f64();
+            if elapsed > 0.0 {
+                let rate = ((meta.done - before) as f64 / elapsed).max(1.0) as u64;
+                LOCAL_SQUARINGS_PER_SEC.store(rate, Ordering::Relaxed);
+            }
+
+            let finished = solved
+                || PUZZLE_STOP_FLAG.load(Ordering::Relaxed)
+                || deadline.is_some_and(|d| Instant::now() >= d);
+            if finished || last_checkpoint.elapsed() >= PUZZLE_CHECKPOINT_INTERVAL {
+                save_puzzle_progress(&qre_path, &meta).map_err(|e| e.to_string())?;
+                utils::emit_progress(
+                    &app,
+                    &format!("Solving time-lock puzzle: {}%", meta.percent()),
+                    meta.percent(),
+                );
+                last_checkpoint = Instant::now();
+            }
+            if finished {
+                break;
+            }
+        }
+        Ok(timelock_puzzle::status(&meta, puzzle_speed(&meta)))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Stops a running `solve_timelock_puzzle` after its current batch; progress so far is kept.
+#[tauri::command]
+pub fn stop_timelock_puzzle() {
+    PUZZLE_STOP_FLAG.store(true, Ordering::Relaxed);
+}
+
+/// Returns a file's puzzle progress and an estimate of the solving time left, or
+/// `None` if it has no puzzle. Like `get_file_timelock_status`, reads only the
+/// plaintext header.
+#[tauri::command]
+pub fn get_timelock_puzzle_status(qre_path: String) -> CommandResult<Option<PuzzleStatus>> {
+    super::files::reject_path_traversal(Path::new(&qre_path))?;
+    let qre_path = match SafePath::new(&qre_path, PathPolicy::read_file()) {
+        Ok(p) => p,
+        Err(_) => return Ok(None),
+    };
+    match crypto_stream::read_stream_header(&qre_path.to_string_lossy()) {
+        Ok((_, header)) => Ok(header
+            .puzzle
+            .map(|meta| timelock_puzzle::status(&meta, puzzle_speed(&meta)))),
+        Err(_) => Ok(None),
     }
 }
 
@@ -1,15 +1,45 @@
 // --- START OF FILE tools.rs ---
 
+use super::guard::{Job, JobGuard};
+use super::safe_path::{PathPolicy, SafePath};
 use crate::analyzer;
+use crate::author_audit;
+use crate::av_guard;
 use crate::breach;
 use crate::cleaner::{self};
 use crate::hasher;
+use crate::honeyfiles;
+use crate::i18n::{AppError, ErrorCode};
+use crate::logging::{self, LogEntry, LogSettings};
+use crate::network_monitor::{
+    NetworkMonitor, NetworkMonitorConfig, NetworkMonitorStatus, NetworkSnapshot,
+};
+use crate::network_privacy;
+use crate::photo_locations;
+use crate::power;
+use crate::privacy_report;
+use crate::progress::ProgressEmitter;
 use crate::qr;
+use crate::random_names;
+use crate::regexes::{self, Pattern};
 use crate::registry_cleaner;
+use crate::resources;
+use crate::secure_clipboard;
+use crate::secure_dns;
+use crate::settings_profile::{SettingsProfile, MAX_SETTINGS_BYTES};
+use crate::site_policies;
+use crate::state::SessionState;
+use crate::subkeys::{self, KeyPurpose};
 use crate::system_cleaner;
+use crate::timestamps;
+use crate::tor;
+use crate::url_cleaner;
 use crate::wordlist::WORDLIST;
 use rand::RngCore;
-use tauri::AppHandle;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use zeroize::Zeroizing;
 
 /// Standardized result type for Tauri commands in this module.
 /// Maps successful outcomes to `T` and errors to standard Strings for easy JSON serialization to the frontend.
@@ -20,23 +50,74 @@ pub type CommandResult<T> = Result<T, String>;
 // ==========================================
 // These commands handle the detection and removal of system junk (e.g., temp files, caches).
 
-/// Scans the system for safe-to-delete junk files.
+/// Scans the system for safe-to-delete junk files. Folders unchanged since the last scan
+/// are sized from the size cache. With `estimate`, the last scan's sizes are returned at
+/// once and a background scan sends fresh results in a `junk-scan-refreshed` event.
 #[tauri::command]
-pub async fn scan_system_junk() -> CommandResult<Vec<system_cleaner::JunkItem>> {
+pub async fn scan_system_junk(
+    app: AppHandle,
+    estimate: Option<bool>,
+) -> CommandResult<Vec<system_cleaner::JunkItem>> {
+    let cache_path = app_data_dir(&app)
+        .ok()
+        .map(|dir| dir.join(system_cleaner::SIZE_CACHE_FILE_NAME));
+
+    if estimate.unwrap_or(false) {
+        let cache = cache_path
+            .as_deref()
+            .map(system_cleaner::SizeCache::load)
+            .unwrap_or_default();
+        let items = system_cleaner::estimate_targets(&cache);
+        // One refresh at a time; a repeated estimate request just gets the cached numbers.
+        if system_cleaner::try_start_refresh() {
+            tauri::async_runtime::spawn_blocking(move || {
+                let fresh = cached_junk_scan(cache_path.as_deref(), cache);
+                system_cleaner::finish_refresh();
+                let _ = app.emit("junk-scan-refreshed", fresh);
+            });
+        }
+        return Ok(items);
+    }
+
     // Run the potentially slow disk scan on a dedicated blocking thread to keep the Tauri UI responsive.
-    tauri::async_runtime::spawn_blocking(move || Ok(system_cleaner::scan_targets()))
-        .await
-        .map_err(|e| e.to_string())?
+    tauri::async_runtime::spawn_blocking(move || {
+        let cache = cache_path
+            .as_deref()
+            .map(system_cleaner::SizeCache::load)
+            .unwrap_or_default();
+        Ok(cached_junk_scan(cache_path.as_deref(), cache))
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
-/// Permanently deletes the selected junk files/folders from the system.
+fn cached_junk_scan(
+    cache_path: Option<&std::path::Path>,
+    mut cache: system_cleaner::SizeCache,
+) -> Vec<system_cleaner::JunkItem> {
+    let items = system_cleaner::scan_targets_cached(&mut cache);
+    // A cache that fails to save only makes the next scan slower.
+    if let Some(path) = cache_path {
+        let _ = cache.save(path);
+    }
+    items
+}
+
+/// Permanently deletes the selected junk files/folders from the system, then reports
+/// each file that could not be removed. `retry_on_reboot` (Windows) queues files held
+/// open by other programs for deletion at the next restart.
 #[tauri::command]
 pub async fn clean_system_junk(
     paths: Vec<String>,
+    retry_on_reboot: Option<bool>,
     app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SessionState>,
 ) -> CommandResult<system_cleaner::CleanResult> {
+    state.ensure_writable("local")?;
+    let _job = JobGuard::acquire(Job::SystemClean)?;
     // Passes the AppHandle down so the actual cleaner function can emit live progress events.
-    system_cleaner::clean_paths(paths, &app_handle).map_err(|e| e.to_string())
+    system_cleaner::clean_paths(paths, retry_on_reboot.unwrap_or(false), &app_handle)
+        .map_err(|e| e.to_string())
 }
 
 /// Performs a simulation of the cleaning process to report how much space *would* be freed,
@@ -61,22 +142,55 @@ pub async fn cancel_system_clean() -> CommandResult<()> {
 #[tauri::command]
 pub async fn scan_directory_targets(
     app: AppHandle,
+    state: tauri::State<'_, SessionState>,
     path: Option<String>,
+    options: Option<analyzer::ScanOptions>,
 ) -> CommandResult<Vec<analyzer::AnalysisResult>> {
     let app_handle = app.clone(); // Clone handle so it can be moved into the thread
+    let path = path
+        .map(|p| SafePath::new(&p, PathPolicy::directory()))
+        .transpose()?;
+    // The cache is keyed with a subkey of the local vault; locked, the scan runs uncached.
+    let cache_key = state.vaults.lock().ok().and_then(|vaults| {
+        vaults
+            .get("local")
+            .map(|k| subkeys::derive(k, KeyPurpose::ScanCache))
+    });
 
     tauri::async_runtime::spawn_blocking(move || {
         // If a specific path is provided, use it. Otherwise, default to standard user directories.
         let targets = if let Some(p) = path {
-            vec![p]
+            vec![p.to_string_lossy().to_string()]
         } else {
             analyzer::get_user_dirs()
         };
 
+        // Pick up the user's rules file on the first scan of the session.
+        if let Ok(rules_path) = analyzer::rules_path(&app_handle) {
+            analyzer::ensure_rules_loaded(&rules_path);
+        }
+
+        let options = options.unwrap_or_default();
+        let cache_path = analyzer::cache_path(&app_handle).ok();
+        let mut cache = match (cache_path.as_deref(), cache_key) {
+            (Some(p), Some(key)) => analyzer::ScanCache::load(p, key),
+            _ => analyzer::ScanCache::default(),
+        };
+
         let mut results = Vec::new();
         for dir in targets {
             // Pass app_handle to emit live discovery events as files are found
-            results.extend(analyzer::scan_directory(&app_handle, &dir));
+            results.extend(analyzer::scan_directory(
+                &app_handle,
+                &dir,
+                &options,
+                &mut cache,
+            ));
+        }
+
+        // A failed cache write only costs speed on the next scan; never fail the scan for it.
+        if let Some(p) = cache_path {
+            let _ = cache.save(&p);
         }
         Ok(results)
     })
@@ -84,6 +198,24 @@ pub async fn scan_directory_targets(
     .map_err(|e| e.to_string())?
 }
 
+/// Deletes the incremental scan cache so the next scan re-reads every file.
+#[tauri::command]
+pub async fn clear_analyzer_cache(app: AppHandle) -> CommandResult<()> {
+    let path = analyzer::cache_path(&app).map_err(|e| e.to_string())?;
+    analyzer::remove_cache(&path).map_err(|e| e.to_string())
+}
+
+/// Re-reads `analyzer_rules.toml` from the app data directory.
+/// Validation errors are returned in the result (not as an Err) so the UI can list each one;
+/// in that case the previously active rules remain in effect.
+#[tauri::command]
+pub async fn reload_analyzer_rules(app: AppHandle) -> CommandResult<analyzer::RulesReloadResult> {
+    let path = analyzer::rules_path(&app).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || Ok(analyzer::reload_rules(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 // ==========================================
 // --- METADATA CLEANER COMMANDS ---
 // ==========================================
@@ -92,7 +224,8 @@ pub async fn scan_directory_targets(
 /// Reads and reports all metadata currently attached to a target file.
 #[tauri::command]
 pub async fn analyze_file_metadata(path: String) -> CommandResult<cleaner::MetadataReport> {
-    cleaner::analyze_file(&path).map_err(|e| e.to_string())
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    cleaner::analyze_file(&path.to_string_lossy()).map_err(|e| e.to_string())
 }
 
 /// Strips metadata from a single file, optionally saving it to a new output directory.
@@ -101,8 +234,13 @@ pub async fn clean_file_metadata(
     path: String,
     output_dir: Option<String>,
     options: cleaner::CleaningOptions,
+    state: tauri::State<'_, SessionState>,
 ) -> CommandResult<String> {
-    cleaner::remove_metadata(&path, output_dir.as_deref(), options).map_err(|e| e.to_string())
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    let output_dir = validate_output_dir(output_dir)?;
+    cleaner::remove_metadata(&path.to_string_lossy(), output_dir.as_deref(), options)
+        .map_err(|e| e.to_string())
 }
 
 /// Strips metadata from a batch of files asynchronously, emitting progress to the UI.
@@ -112,10 +250,24 @@ pub async fn batch_clean_metadata(
     output_dir: Option<String>,
     options: cleaner::CleaningOptions,
     app_handle: tauri::AppHandle, // Required for sending progress events back to the frontend
+    state: tauri::State<'_, SessionState>,
 ) -> CommandResult<cleaner::CleanResult> {
+    state.ensure_writable("local")?;
+    // Input files are validated per file by the cleaner, which reports each rejection
+    // in the batch result instead of aborting the whole batch.
+    let output_dir = validate_output_dir(output_dir)?;
     cleaner::batch_clean(paths, output_dir, options, &app_handle).map_err(|e| e.to_string())
 }
 
+/// Output folders must already exist; the canonical form is passed on to the cleaner.
+fn validate_output_dir(output_dir: Option<String>) -> CommandResult<Option<String>> {
+    output_dir
+        .map(|d| {
+            SafePath::new(&d, PathPolicy::directory()).map(|p| p.to_string_lossy().to_string())
+        })
+        .transpose()
+}
+
 /// Signals the active metadata cleaning thread to halt.
 #[tauri::command]
 pub async fn cancel_metadata_clean() -> CommandResult<()> {
@@ -129,7 +281,364 @@ pub async fn compare_metadata_files(
     original: String,
     cleaned: String,
 ) -> CommandResult<cleaner::ComparisonResult> {
-    cleaner::compare_files(&original, &cleaned).map_err(|e| e.to_string())
+    let original = SafePath::new(&original, PathPolicy::read_file())?;
+    let cleaned = SafePath::new(&cleaned, PathPolicy::read_file())?;
+    cleaner::compare_files(&original.to_string_lossy(), &cleaned.to_string_lossy())
+        .map_err(|e| e.to_string())
+}
+
+/// Diffs the metadata of any two supported files: added, removed and changed tags,
+/// grouped by privacy category. Useful for checking other sanitizers' output.
+#[tauri::command]
+pub async fn diff_metadata(path_a: String, path_b: String) -> CommandResult<cleaner::MetadataDiff> {
+    let path_a = SafePath::new(&path_a, PathPolicy::read_file())?;
+    let path_b = SafePath::new(&path_b, PathPolicy::read_file())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        cleaner::diff_metadata(&path_a.to_string_lossy(), &path_b.to_string_lossy())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Lists URLs a PDF/Office document would contact (tracking pixels, remote templates, auto-open URIs).
+#[tauri::command]
+pub async fn detect_remote_content(path: String) -> CommandResult<cleaner::RemoteContentReport> {
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        cleaner::detect_remote_content(&path.to_string_lossy())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Sets file and folder timestamps to a fixed, random or current time. With `dry_run`
+/// the report shows the current and new times without changing anything.
+#[tauri::command]
+pub async fn scrub_timestamps(
+    paths: Vec<String>,
+    mode: timestamps::TimestampMode,
+    recursive: Option<bool>,
+    dry_run: Option<bool>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<timestamps::ScrubReport> {
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        state.ensure_writable("local")?;
+    }
+    let paths: Vec<_> = SafePath::all(&paths, PathPolicy::existing_entry())?
+        .into_iter()
+        .map(SafePath::into_path_buf)
+        .collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        timestamps::scrub_timestamps(&paths, &mode, recursive.unwrap_or(true), dry_run)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Removes tracking parameters from a URL and unwraps redirector links (see
+/// url_cleaner.rs). Reports what was removed so the UI can show it.
+#[tauri::command]
+pub fn clean_url(url: String) -> url_cleaner::CleanedUrl {
+    url_cleaner::clean_url(&url)
+}
+
+// ==========================================
+// --- PHOTO LOCATIONS ---
+// ==========================================
+// GPS hotspots across the photo library; see photo_locations.rs. The last scan is kept
+// so a cluster can be cleaned by id without the frontend sending every path back.
+
+static PHOTO_LOCATIONS: Mutex<Option<photo_locations::PhotoLocationReport>> = Mutex::new(None);
+
+/// Reads the GPS position of every photo in `path` (default: the Pictures folder) and
+/// groups them into frequently photographed locations.
+#[tauri::command]
+pub async fn scan_photo_locations(
+    path: Option<String>,
+) -> CommandResult<photo_locations::PhotoLocationReport> {
+    let roots = match path {
+        Some(p) => vec![SafePath::new(&p, PathPolicy::directory())?.into_path_buf()],
+        None => photo_locations::photo_dirs(),
+    };
+    if roots.is_empty() {
+        return Err(AppError::new(ErrorCode::NoPicturesFolder).into());
+    }
+    let report = tauri::async_runtime::spawn_blocking(move || photo_locations::scan(&roots))
+        .await
+        .map_err(|e| e.to_string())?;
+    *PHOTO_LOCATIONS.lock().unwrap_or_else(|p| p.into_inner()) = Some(report.clone());
+    Ok(report)
+}
+
+/// Strips the location data from every photo in the selected clusters of the last scan.
+#[tauri::command]
+pub async fn clean_photo_locations(
+    cluster_ids: Vec<String>,
+    output_dir: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<cleaner::CleanResult> {
+    state.ensure_writable("local")?;
+    let paths = PHOTO_LOCATIONS
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .as_ref()
+        .map(|r| r.paths_for(&cluster_ids))
+        .ok_or_else(|| AppError::new(ErrorCode::PhotoScanMissing))?;
+    if paths.is_empty() {
+        return Err(AppError::new(ErrorCode::NoPhotosSelected).into());
+    }
+    let output_dir = validate_output_dir(output_dir)?;
+    let options = cleaner::CleaningOptions {
+        gps: true,
+        author: false,
+        date: false,
+    };
+    cleaner::batch_clean(paths, output_dir, options, &app_handle).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- DOCUMENT AUTHORS ---
+// ==========================================
+// Documents whose author fields name the user; see author_audit.rs. Like the photo
+// scan, the last result is kept and cleaning is limited to the files it reported.
+
+static AUTHOR_AUDIT: Mutex<Option<author_audit::AuthorAuditReport>> = Mutex::new(None);
+
+/// Lists Office/PDF files under Documents and Desktop (or `path`) whose author or
+/// last-modified-by field names the user. `names` adds to the names found on the system.
+#[tauri::command]
+pub async fn scan_document_authors(
+    path: Option<String>,
+    names: Option<Vec<String>>,
+) -> CommandResult<author_audit::AuthorAuditReport> {
+    let roots = match path {
+        Some(p) => vec![SafePath::new(&p, PathPolicy::directory())?.into_path_buf()],
+        None => author_audit::document_dirs(),
+    };
+    if roots.is_empty() {
+        return Err(AppError::new(ErrorCode::NoDocumentsFolder).into());
+    }
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let mut identities = author_audit::local_identities();
+        identities.extend(names.unwrap_or_default());
+        author_audit::scan(&roots, author_audit::normalize_identities(identities))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    *AUTHOR_AUDIT.lock().unwrap_or_else(|p| p.into_inner()) = Some(report.clone());
+    Ok(report)
+}
+
+/// Removes author metadata from the files of the last scan (all of them, or `paths`).
+#[tauri::command]
+pub async fn clean_document_authors(
+    paths: Option<Vec<String>>,
+    output_dir: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<cleaner::CleanResult> {
+    state.ensure_writable("local")?;
+    let paths = AUTHOR_AUDIT
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .as_ref()
+        .map(|r| r.finding_paths(paths.as_deref()))
+        .ok_or_else(|| AppError::new(ErrorCode::DocumentScanMissing))?;
+    if paths.is_empty() {
+        return Err(AppError::new(ErrorCode::NoDocumentsSelected).into());
+    }
+    let output_dir = validate_output_dir(output_dir)?;
+    let options = cleaner::CleaningOptions {
+        gps: false,
+        author: true,
+        date: false,
+    };
+    cleaner::batch_clean(paths, output_dir, options, &app_handle).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- BROWSER COOKIES ---
+// ==========================================
+// Per-site cookie listing and deletion; see cookie_inspector.rs. Desktop only.
+
+/// Lists the cookies of every installed Chromium/Firefox profile, grouped by site,
+/// with known trackers flagged. Cookie values are never read.
+#[tauri::command]
+pub async fn list_browser_cookies() -> CommandResult<crate::cookie_inspector::CookieReport> {
+    #[cfg(target_os = "android")]
+    {
+        Err(AppError::new(ErrorCode::UnsupportedOnAndroid).into())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        use crate::cookie_inspector;
+        tauri::async_runtime::spawn_blocking(|| {
+            cookie_inspector::inspect(&cookie_inspector::find_cookie_stores())
+        })
+        .await
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Deletes the cookies of `domains` (and their subdomains) from every cookie store, or
+/// only from `store_paths` (as listed by `list_browser_cookies`). The browsers must be closed.
+#[tauri::command]
+pub async fn delete_browser_cookies(
+    domains: Vec<String>,
+    store_paths: Option<Vec<String>>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<crate::cookie_inspector::CookieDeletion> {
+    state.ensure_writable("local")?;
+    #[cfg(target_os = "android")]
+    {
+        let _ = (domains, store_paths);
+        Err(AppError::new(ErrorCode::UnsupportedOnAndroid).into())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        use crate::cookie_inspector;
+        if domains.is_empty() {
+            return Err(AppError::new(ErrorCode::NoSitesSelected).into());
+        }
+        tauri::async_runtime::spawn_blocking(move || {
+            // Only discovered stores can be written to, never an arbitrary path.
+            let stores: Vec<_> = cookie_inspector::find_cookie_stores()
+                .into_iter()
+                .filter(|s| {
+                    store_paths
+                        .as_ref()
+                        .is_none_or(|paths| paths.contains(&s.path))
+                })
+                .collect();
+            if stores.is_empty() {
+                return Err(AppError::new(ErrorCode::NoCookieStore).into());
+            }
+            cookie_inspector::delete_cookies(&stores, &domains)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
+
+// ==========================================
+// --- ANTIVIRUS INTERFERENCE ---
+// ==========================================
+
+/// Retried and slow file operations seen by the shredder and encryption engine, with a
+/// warning when an on-access scanner is the likely cause.
+#[tauri::command]
+pub fn get_av_interference_report() -> av_guard::InterferenceReport {
+    av_guard::interference_report()
+}
+
+#[tauri::command]
+pub fn reset_av_interference_report() {
+    av_guard::reset_interference();
+}
+
+// ==========================================
+// --- POWER STATE ---
+// ==========================================
+
+/// Battery level, the active policy, and whether a job is keeping the system awake or
+/// waiting for power.
+#[tauri::command]
+pub fn get_power_status() -> power::PowerStatus {
+    power::status()
+}
+
+/// Applies the sleep-inhibition / low-battery policy. Relaxing it resumes paused jobs.
+#[tauri::command]
+pub fn set_power_policy(policy: power::PowerPolicy) -> CommandResult<()> {
+    power::set_policy(policy)
+}
+
+// ==========================================
+// --- RESOURCE LIMITS ---
+// ==========================================
+
+/// Available memory, the low resource mode setting, and the limits an operation would
+/// get right now.
+#[tauri::command]
+pub fn get_resource_status() -> resources::ResourceStatus {
+    resources::status()
+}
+
+/// Sets low resource mode (`auto`, `on` or `off`). Applies to operations started after it.
+#[tauri::command]
+pub fn set_resource_mode(mode: resources::ResourceMode) -> CommandResult<()> {
+    resources::set_mode(mode)
+}
+
+// ==========================================
+// --- DIAGNOSTIC LOG ---
+// ==========================================
+
+/// The newest log entries (100 by default), newest first, for a support request. File
+/// names are already redacted in the log (see logging.rs).
+#[tauri::command]
+pub fn get_recent_logs(limit: Option<usize>) -> CommandResult<Vec<LogEntry>> {
+    logging::recent(limit.unwrap_or(100)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_log_settings() -> LogSettings {
+    logging::settings()
+}
+
+/// Turns the log on or off and picks how file names are redacted. Turning it off
+/// deletes the existing logs.
+#[tauri::command]
+pub fn set_log_settings(settings: LogSettings) -> CommandResult<()> {
+    logging::set_settings(settings).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- SETTINGS PROFILE ---
+// ==========================================
+// Export/import of the app configuration; see settings_profile.rs. Never contains secrets.
+
+/// Writes `settings` (as kept by the frontend) to `path`. The power policy, resource mode
+/// and network monitor settings are taken from the running backend.
+#[tauri::command]
+pub fn export_settings(
+    path: String,
+    settings: SettingsProfile,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::write_file())?;
+    let profile = SettingsProfile {
+        exported_at: Some(chrono::Utc::now().timestamp()),
+        power: power::policy(),
+        resources: resources::mode(),
+        network_monitor: network_monitor().config.clone(),
+        ..settings
+    };
+    std::fs::write(&path, profile.to_json()?).map_err(|e| e.to_string())
+}
+
+/// Reads a settings file, applies the backend-held parts, and returns the whole profile
+/// for the frontend to store.
+#[tauri::command]
+pub fn import_settings(
+    path: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<SettingsProfile> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::read_file().max_bytes(MAX_SETTINGS_BYTES))?;
+    let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let profile = SettingsProfile::from_json(&json)?;
+    power::set_policy(profile.power)?;
+    resources::set_mode(profile.resources)?;
+    network_monitor().configure(profile.network_monitor.clone())?;
+    Ok(profile)
 }
 
 // ==========================================
@@ -143,13 +652,34 @@ pub async fn calculate_file_hashes(
     path: String,
     app_handle: tauri::AppHandle,
 ) -> CommandResult<hasher::HashResult> {
-    hasher::calculate_hashes(&path, &app_handle).map_err(|e| e.to_string())
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    hasher::calculate_hashes(&path.to_string_lossy(), &app_handle).map_err(|e| e.to_string())
 }
 
 /// Retrieves basic OS-level file properties (size, creation date, etc.) prior to hashing.
 #[tauri::command]
 pub async fn get_file_metadata(path: String) -> CommandResult<hasher::FileMetadata> {
-    hasher::get_file_metadata(&path).map_err(|e| e.to_string())
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    hasher::get_file_metadata(&path.to_string_lossy()).map_err(|e| e.to_string())
+}
+
+/// Checks a downloaded container against a published checksum before any unlock attempt.
+/// `algorithm` is "sha256", "sha1", "md5" or "auto" (inferred from the digest length).
+#[tauri::command]
+pub async fn verify_container_hash(
+    path: String,
+    expected_hash: String,
+    algorithm: String,
+    app_handle: tauri::AppHandle,
+) -> CommandResult<hasher::HashVerification> {
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    hasher::verify_hash(
+        &path.to_string_lossy(),
+        &expected_hash,
+        &algorithm,
+        &app_handle,
+    )
+    .map_err(|e| e.to_string())
 }
 
 /// Cancels an ongoing hashing operation (useful for very large files).
@@ -161,8 +691,14 @@ pub async fn cancel_hashing() -> CommandResult<()> {
 
 /// Utility to export calculated hashes or text output to a local file.
 #[tauri::command]
-pub async fn save_text_to_file(path: String, content: String) -> CommandResult<()> {
-    hasher::save_text_to_file(&path, &content).map_err(|e| e.to_string())
+pub async fn save_text_to_file(
+    path: String,
+    content: String,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::write_file())?;
+    hasher::save_text_to_file(&path.to_string_lossy(), &content).map_err(|e| e.to_string())
 }
 
 /// Quickly calculates cryptographic hashes for an arbitrary string of text from the UI.
@@ -204,7 +740,7 @@ pub async fn check_password_breach(sha1_hash: String) -> CommandResult<breach::B
     // 1. Validate that the frontend provided a properly formatted, fully calculated SHA-1 hash.
     // The raw password MUST NOT be sent to the backend to minimize memory exposure.
     if sha1_hash.len() != 40 || !sha1_hash.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err("Invalid hash format. Frontend must send a SHA-1 hash.".to_string());
+        return Err(AppError::new(ErrorCode::HashInvalid).into());
     }
 
     // 2. Split hash for k-Anonymity (e.g., HaveIBeenPwned API model).
@@ -225,6 +761,314 @@ pub async fn get_public_ip_address() -> CommandResult<breach::IpResult> {
     breach::get_public_ip().await.map_err(|e| e.to_string())
 }
 
+/// Detects a local Tor SOCKS proxy and checks, through it only, that traffic exits via Tor.
+/// `ports` overrides the default 9050 / 9150 probe list.
+#[tauri::command]
+pub async fn check_tor_status(ports: Option<Vec<u16>>) -> CommandResult<tor::TorStatus> {
+    let ports = ports
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| tor::DEFAULT_SOCKS_PORTS.to_vec());
+    Ok(tor::check_tor_status(ports).await)
+}
+
+// ==========================================
+// --- NETWORK MONITOR ---
+// ==========================================
+// Background re-run of the public IP check; see network_monitor.rs for the change logic.
+
+/// How often the background thread wakes up to see whether a check is due.
+const NETWORK_MONITOR_TICK: Duration = Duration::from_secs(5);
+
+static NETWORK_MONITOR: Mutex<NetworkMonitor> = Mutex::new(NetworkMonitor::new());
+
+fn network_monitor() -> std::sync::MutexGuard<'static, NetworkMonitor> {
+    NETWORK_MONITOR.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Starts the background thread (called once from `lib.rs` setup). It stays idle until
+/// the frontend enables monitoring with `set_network_monitor_config`.
+pub fn spawn_network_monitor(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(NETWORK_MONITOR_TICK);
+        if !network_monitor().is_due(chrono::Utc::now().timestamp()) {
+            continue;
+        }
+
+        // The lock is not held across the request: the UI may reconfigure meanwhile.
+        let result =
+            tauri::async_runtime::block_on(breach::get_public_ip()).map_err(|e| e.to_string());
+        let snapshot = NetworkSnapshot::from_check(result, chrono::Utc::now().timestamp());
+
+        let change = {
+            let mut monitor = network_monitor();
+            // Disabled (or switched to offline mode) while the request was in flight.
+            if !monitor.is_active() {
+                continue;
+            }
+            monitor.record(snapshot)
+        };
+        if let Some(change) = change {
+            let _ = app.emit("network-status-changed", change);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_network_monitor_status() -> NetworkMonitorStatus {
+    network_monitor().status()
+}
+
+/// Enables, disables or re-times the monitor. Offline mode stops all requests.
+#[tauri::command]
+pub fn set_network_monitor_config(
+    config: NetworkMonitorConfig,
+) -> CommandResult<NetworkMonitorStatus> {
+    let mut monitor = network_monitor();
+    monitor.configure(config)?;
+    Ok(monitor.status())
+}
+
+// ==========================================
+// --- SECURE DNS ---
+// ==========================================
+// DNS-over-HTTPS / DNS-over-TLS status and resolver switching; see secure_dns.rs.
+
+/// `<app_data_dir>`, created on first use. Holds the DNS settings kept for
+/// `revert_secure_dns` and the honeyfile registry.
+fn app_data_dir(app: &AppHandle) -> CommandResult<std::path::PathBuf> {
+    use tauri::Manager;
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+#[tauri::command]
+pub fn list_secure_dns_resolvers() -> Vec<secure_dns::Resolver> {
+    secure_dns::RESOLVERS.to_vec()
+}
+
+/// Whether DNS queries currently leave the machine encrypted, and through which servers.
+#[tauri::command]
+pub async fn get_secure_dns_status(app: AppHandle) -> CommandResult<secure_dns::SecureDnsStatus> {
+    let dir = app_data_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || secure_dns::status(&dir))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Switches the system to `resolver_id` (see `list_secure_dns_resolvers`) with encryption
+/// on. The settings found before the first switch are kept for `revert_secure_dns`.
+#[tauri::command]
+pub async fn set_secure_dns(
+    app: AppHandle,
+    resolver_id: String,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<secure_dns::SecureDnsChange> {
+    state.ensure_writable("local")?;
+    let dir = app_data_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || secure_dns::configure(&resolver_id, &dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Restores the DNS settings saved by the first `set_secure_dns`.
+#[tauri::command]
+pub async fn revert_secure_dns(
+    app: AppHandle,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<secure_dns::SecureDnsChange> {
+    state.ensure_writable("local")?;
+    let dir = app_data_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || secure_dns::revert(&dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// ==========================================
+// --- NETWORK PRIVACY REPORT ---
+// ==========================================
+// Read-only: Wi-Fi MAC randomization, VPN, public IP and DNS; see network_privacy.rs.
+
+/// Whether each Wi-Fi interface uses a random MAC address, with guidance where it does not.
+#[tauri::command]
+pub async fn get_mac_randomization_status() -> CommandResult<Vec<network_privacy::MacRandomization>>
+{
+    tauri::async_runtime::spawn_blocking(network_privacy::mac_randomization)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Runs the IP, VPN, DNS and MAC checks together. The public IP is not looked up while
+/// the network monitor is in offline mode.
+#[tauri::command]
+pub async fn get_network_privacy_report(
+    app: AppHandle,
+) -> CommandResult<network_privacy::NetworkPrivacyReport> {
+    collect_network_privacy(&app).await
+}
+
+async fn collect_network_privacy(
+    app: &AppHandle,
+) -> CommandResult<network_privacy::NetworkPrivacyReport> {
+    let dir = app_data_dir(app)?;
+    let offline = network_monitor().config.offline_mode;
+    let public_ip = if offline {
+        Err(AppError::new(ErrorCode::OfflineIpNotChecked).into())
+    } else {
+        breach::get_public_ip().await.map_err(|e| e.to_string())
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        network_privacy::build_report(
+            public_ip,
+            network_privacy::vpn_interfaces(),
+            secure_dns::status(&dir),
+            network_privacy::mac_randomization(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- PRIVACY REPORT ---
+// ==========================================
+// Scored summary across the cleaner, metadata, breach, password, telemetry and network
+// checks; see privacy_report.rs.
+
+/// Runs every check and scores the result. Breach alerts and password health are only
+/// included when `vault_id` names an unlocked vault.
+#[tauri::command]
+pub async fn generate_privacy_report(
+    app: AppHandle,
+    vault_id: Option<String>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<privacy_report::PrivacyReport> {
+    let (breach_alerts, passwords) = match vault_id {
+        Some(vault_id) => {
+            let (alerts, summary) =
+                super::vault::privacy_report_vault_inputs(&app, &vault_id, &state)?;
+            (Some(alerts), Some(summary))
+        }
+        None => (None, None),
+    };
+    let network = collect_network_privacy(&app).await.ok();
+    tauri::async_runtime::spawn_blocking(move || {
+        let inputs = privacy_report::ReportInputs {
+            junk: Some(system_cleaner::scan_targets()),
+            metadata: Some(privacy_report::scan_metadata(
+                &privacy_report::metadata_roots(),
+            )),
+            breach_alerts,
+            passwords,
+            telemetry: Some(privacy_report::telemetry_settings()),
+            network,
+        };
+        privacy_report::build_report(&inputs)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Writes a report from `generate_privacy_report` to `path` as JSON or PDF.
+#[tauri::command]
+pub fn export_privacy_report(
+    report: privacy_report::PrivacyReport,
+    path: String,
+    format: privacy_report::ReportFormat,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::write_file())?;
+    std::fs::write(&path, privacy_report::export(&report, format)?).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- HONEYFILES ---
+// ==========================================
+// Bait files and the watcher that reports when they are touched; see honeyfiles.rs.
+
+/// How often the watcher compares the honeyfiles with their baselines.
+const HONEYFILE_WATCH_TICK: Duration = Duration::from_secs(30);
+
+/// Serializes read-modify-write of the registry between the commands and the watcher.
+static HONEYFILES: Mutex<()> = Mutex::new(());
+
+fn honeyfiles_lock() -> std::sync::MutexGuard<'static, ()> {
+    HONEYFILES.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Checks every honeyfile, saves the new baselines and emits `honeyfile-alert`.
+fn run_honeyfile_check(app: &AppHandle) -> CommandResult<Vec<honeyfiles::HoneyfileAlert>> {
+    let dir = app_data_dir(app)?;
+    let _lock = honeyfiles_lock();
+    let mut store = honeyfiles::load_store(&dir);
+    let alerts = honeyfiles::check(&mut store, chrono::Utc::now().timestamp());
+    if !alerts.is_empty() {
+        honeyfiles::save_store(&dir, &store)?;
+        let _ = app.emit("honeyfile-alert", &alerts);
+    }
+    Ok(alerts)
+}
+
+/// Starts the watcher thread (called once from `lib.rs` setup). It does nothing until
+/// a honeyfile has been created.
+pub fn spawn_honeyfile_watcher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(HONEYFILE_WATCH_TICK);
+        let _ = run_honeyfile_check(&app);
+    });
+}
+
+/// Writes a bait file into `directory` and starts watching it. `canary_url` is an
+/// optional canary token link embedded in the file.
+#[tauri::command]
+pub async fn create_honeyfile(
+    app: AppHandle,
+    kind: honeyfiles::HoneyfileKind,
+    directory: String,
+    canary_url: Option<String>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<honeyfiles::Honeyfile> {
+    state.ensure_writable("local")?;
+    let directory = SafePath::new(&directory, PathPolicy::directory())?.into_path_buf();
+    let dir = app_data_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let _lock = honeyfiles_lock();
+        honeyfiles::create(&dir, kind, &directory, canary_url)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn list_honeyfiles(app: AppHandle) -> CommandResult<Vec<honeyfiles::Honeyfile>> {
+    let dir = app_data_dir(&app)?;
+    let _lock = honeyfiles_lock();
+    Ok(honeyfiles::load_store(&dir).honeyfiles)
+}
+
+/// Runs a check now instead of waiting for the watcher.
+#[tauri::command]
+pub async fn check_honeyfiles(app: AppHandle) -> CommandResult<Vec<honeyfiles::HoneyfileAlert>> {
+    tauri::async_runtime::spawn_blocking(move || run_honeyfile_check(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Stops watching a honeyfile; `delete_file` also removes it from disk.
+#[tauri::command]
+pub fn remove_honeyfile(
+    app: AppHandle,
+    id: String,
+    delete_file: Option<bool>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    let dir = app_data_dir(&app)?;
+    let _lock = honeyfiles_lock();
+    honeyfiles::remove(&dir, &id, delete_file.unwrap_or(false))
+}
+
 // ==========================================
 // --- PASSWORD GENERATOR ---
 // ==========================================
@@ -246,9 +1090,46 @@ pub fn generate_passphrase() -> String {
         .join("-") // Join the 6 randomly selected words with hyphens (e.g., "correct-horse-battery-staple-apple-tree").
 }
 
-use regex::Regex;
+/// A random name for a file or label: a UUID (the default), "quiet-harbor-4821" or
+/// "kilo-echo-tango-lima-07" (see random_names.rs).
+#[tauri::command]
+pub fn generate_random_name(kind: Option<random_names::NameKind>) -> CommandResult<String> {
+    random_names::generate(kind.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+/// Generates a random password for the site at `url`, following its known length and
+/// character rules (see site_policies.rs). Unknown sites get the default policy.
+#[tauri::command]
+pub fn generate_password_for(
+    url: String,
+    length: Option<usize>,
+) -> CommandResult<site_policies::GeneratedPassword> {
+    site_policies::generate_for_url(&url, length)
+}
+
+// ==========================================
+// --- SECURE COPY ---
+// ==========================================
+
+/// Copies a secret to the system clipboard and clears it again, either after
+/// `clear_after_secs` (default 30) or, with `paste_once`, right after the first paste.
+/// The result reports the mode actually used, since paste-once is not available everywhere.
+#[tauri::command]
+pub fn copy_secret_to_clipboard(
+    app: AppHandle,
+    text: String,
+    mode: Option<secure_clipboard::CopyMode>,
+    clear_after_secs: Option<u64>,
+) -> CommandResult<secure_clipboard::CopyOutcome> {
+    secure_clipboard::copy_secret(
+        &app,
+        Zeroizing::new(text),
+        mode.unwrap_or_default(),
+        clear_after_secs,
+    )
+}
+
 use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::Emitter;
 
 /// Global cancellation flag for the secret scanner.
 /// Reset to `false` at the start of every scan, set to `true` by `cancel_secret_scan`.
@@ -458,33 +1339,32 @@ pub async fn scan_local_secrets(
     // Reset cancel flag for this new scan
     SCAN_CANCEL_FLAG.store(false, Ordering::Relaxed);
 
-    // Canonicalize to resolve any ".." traversal before security checks
-    let canonical = std::fs::canonicalize(&dir_path)
-        .map_err(|_| "Could not resolve the selected directory.".to_string())?;
+    // Canonicalize (and reject ".." traversal) before the scan-specific checks
+    let canonical = SafePath::new(&dir_path, PathPolicy::directory())?.into_path_buf();
 
     if !is_safe_to_scan(&canonical) {
-        return Err("Protected system directories cannot be scanned.".to_string());
+        return Err(AppError::new(ErrorCode::ScanProtected).into());
     }
 
     tauri::async_runtime::spawn_blocking(move || {
         let mut findings = Vec::new();
 
         if !canonical.exists() || !canonical.is_dir() {
-            return Err("Invalid directory".to_string());
+            return Err(AppError::new(ErrorCode::FolderNotFound)
+                .with("path", canonical.display())
+                .into());
         }
 
-        // Compile regexes ONCE outside the loop.
-        // Whitespace quantifier capped at {0,5} to prevent ReDoS.
+        // Compiled once per process by the registry (see regexes.rs).
         // Capture group 2 captures the value so we can entropy-check it.
-        let regex_credentials = Regex::new(
-            r#"(?i)(password|secret|api_key|token|access_key)[\s]{0,5}[:=][\s]{0,5}['"]?([a-zA-Z0-9\-_]{16,})['"]?"#,
-        ).unwrap();
+        let regex_credentials = regexes::get(Pattern::SecretCredential);
         // High-confidence specific key formats — no entropy check needed, format is unique
-        let regex_api = Regex::new(
-            r"(sk_live_[0-9a-zA-Z]{24,}|sk_test_[0-9a-zA-Z]{24,}|ghp_[0-9a-zA-Z]{36}|gho_[0-9a-zA-Z]{36}|AKIA[0-9A-Z]{16}|eyJ[a-zA-Z0-9_-]{20,}[.][a-zA-Z0-9_-]{20,})",
-        ).unwrap();
+        let regex_api = regexes::get(Pattern::SecretApiKey);
         // Crypto seed phrases: exactly 12 lowercase BIP-39 words on a single line
-        let regex_seed = Regex::new(r"^(?:[a-z]{3,}\s){11}[a-z]{3,}$").unwrap();
+        let regex_seed = regexes::get(Pattern::SeedPhrase);
+
+        // One event per scanned file floods the UI on large trees; coalesce them.
+        let events = ProgressEmitter::new(&app_handle, "secret-scan-progress");
 
         for entry in walkdir::WalkDir::new(&canonical)
             .max_depth(5)
@@ -542,7 +1422,7 @@ pub async fn scan_local_secrets(
                 continue;
             }
 
-            let _ = app_handle.emit("secret-scan-progress", p.to_string_lossy().to_string());
+            events.emit(p.to_string_lossy().to_string());
 
             if let Ok(file_content) = std::fs::read_to_string(p) {
                 let mut found_category = None;
@@ -612,7 +1492,15 @@ pub fn backup_registry() -> registry_cleaner::RegistryBackupResult {
 #[tauri::command]
 pub fn clean_registry(
     entries: Vec<registry_cleaner::RegistryCleanEntry>,
+    state: tauri::State<SessionState>,
 ) -> registry_cleaner::RegistryCleanResult {
+    if let Err(e) = state.ensure_writable("local") {
+        return registry_cleaner::RegistryCleanResult {
+            items_cleaned: 0,
+            errors: vec![e],
+            backup_path: None,
+        };
+    }
     registry_cleaner::clean_registry_entries(entries)
 }
 
@@ -621,6 +1509,12 @@ pub async fn detect_steganography(
     paths: Vec<String>,
     app_handle: tauri::AppHandle,
 ) -> CommandResult<Vec<crate::cleaner::StegoReport>> {
+    // Rejected paths are skipped, the same way the scan already skips unreadable files.
+    let paths = paths
+        .iter()
+        .filter_map(|p| SafePath::new(p, PathPolicy::read_file()).ok())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
     crate::cleaner::detect_steganography(paths, app_handle)
         .await
         .map_err(|e| e.to_string())
@@ -1,19 +1,55 @@
 // --- START OF FILE vault.rs ---
 
+use super::guard::{rate_limit, Job, JobGuard, AUTH_RATE};
+use super::safe_path::{PathPolicy, SafePath, MAX_IN_MEMORY_FILE_BYTES};
+use crate::account_deletion;
+use crate::audit;
+use crate::auto_lock::{self, AutoLockSettings, LockReason};
 use crate::bookmarks::BookmarksVault;
-use crate::clipboard_store::ClipboardVault;
+use crate::breach_monitor::{
+    self, BreachAlert, BreachMonitorStore, MonitorSettings, MonitorStatus,
+};
+use crate::clipboard_store::{self, ClipboardVault, JournalOp};
+use crate::compaction::{self, CompactionReport};
 use crate::crypto;
-use crate::keychain;
-use crate::notes::NotesVault;
-use crate::passwords::PasswordVault;
+use crate::device_pairing;
+use crate::duress;
+use crate::entry_attachments::{self, EntryAttachment};
+use crate::formats;
+use crate::i18n::{AppError, ErrorCode};
+use crate::keychain::{self, DuressSlotInfo, KdfStatus, RecoveryCodeFormat, VaultPolicy};
+use crate::note_assets::{self, NoteAssetBundle, NoteAssetContent, NoteAssetInfo};
+use crate::note_images::{self, NoteImageInfo};
+use crate::note_query::{NoteMatch, NoteQuery};
+use crate::notes::{NoteDiff, NotesVault};
+use crate::os_keystore;
+use crate::panel_lock::{self, Panel, PanelLockStatus, PanelRule, PanelToken};
+use crate::password_audit::{self, PasswordAuditReport};
+use crate::passwords::{DuplicateGroup, EntryUsage, PasswordVault, VaultEntry};
+use crate::pattern_packs::{self, PackInfo};
+use crate::privacy_report;
+use crate::profiles;
+use crate::recipient;
+use crate::search::{
+    self, SearchDoc, SearchField, SearchKind, WEIGHT_BODY, WEIGHT_LABEL, WEIGHT_TITLE,
+};
+use crate::secrets::{self, SecretInfo, SecretsStore};
+use crate::sharing::{self, ConflictResolution, ImportPreviewItem, ImportSummary, ShareKdf};
+use crate::shredder;
 use crate::state::SessionState;
+use crate::subkeys::KeyPurpose;
+use crate::totp::{self, TotpInfo};
+use crate::url_cleaner;
+use crate::vault_export;
+use crate::vault_import::{self, ImportFormat, PasswordImportPreview};
+use crate::vault_meta::{self, VaultMeta, VaultStats, WriteStamp};
 use data_encoding::BASE32_NOPAD;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::{AppHandle, Manager};
-use totp_rs::{Algorithm, TOTP};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
 
 pub type CommandResult<T> = Result<T, String>;
 
@@ -32,6 +68,41 @@ fn lockout_duration_secs(fail_count: u32) -> u64 {
     30u64 * (1u64 << extra.min(4))
 }
 
+/// Shared by every login path (owner, guest, named user) so that switching slots
+/// cannot be used to brute-force around the lockout. The rate limit shares one
+/// bucket for the same reason, and applies even while attempts are succeeding.
+fn check_login_lockout() -> CommandResult<()> {
+    rate_limit("login", AUTH_RATE)?;
+    let fail_count = LOGIN_FAIL_COUNT.load(Ordering::SeqCst);
+    if fail_count >= MAX_ATTEMPTS_BEFORE_LOCKOUT {
+        let last_fail = LOGIN_LAST_FAIL_SECS.load(Ordering::SeqCst);
+        let wait = lockout_duration_secs(fail_count);
+        let elapsed = now_secs().saturating_sub(last_fail);
+
+        if elapsed < wait {
+            return Err(AppError::new(ErrorCode::LoginLockedOut)
+                .with("seconds", wait - elapsed)
+                .into());
+        }
+    }
+    Ok(())
+}
+
+/// Keychain errors are plain `anyhow` messages; the wrong-password case is the one a
+/// user sees constantly, so it is mapped onto the localized catalog entry.
+fn login_error(e: anyhow::Error) -> String {
+    if e.to_string() == "Incorrect Password" {
+        AppError::new(ErrorCode::IncorrectPassword).into()
+    } else {
+        e.to_string()
+    }
+}
+
+fn record_login_failure() {
+    LOGIN_FAIL_COUNT.fetch_add(1, Ordering::SeqCst);
+    LOGIN_LAST_FAIL_SECS.store(now_secs(), Ordering::SeqCst);
+}
+
 fn now_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -43,20 +114,36 @@ fn now_secs() -> u64 {
 // --- HELPER: Resolve Keychain Path ---
 // ==========================================
 
+/// The app data directory, created on first use. App-wide settings are kept here,
+/// whichever profile is active.
+fn app_data_root(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Could not resolve app data dir: {}", e))?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create data directory at {:?}: {}", data_dir, e))?;
+    }
+    Ok(data_dir)
+}
+
 /// Resolves the keychain path.
-/// Currently only supports "local" until Phase 2 (USB) is implemented.
+/// "local" is the vault of the active profile (see profiles.rs), or its decoy vault in a
+/// duress session (see duress.rs); the other vault files are resolved next to its keychain.
 fn resolve_keychain_path(app: &AppHandle, vault_id: &str) -> Result<PathBuf, String> {
     if vault_id == "local" {
-        let data_dir = app
-            .path()
-            .app_data_dir()
-            .map_err(|e| format!("Could not resolve app data dir: {}", e))?;
-
-        if !data_dir.exists() {
-            fs::create_dir_all(&data_dir)
-                .map_err(|e| format!("Failed to create data directory at {:?}: {}", data_dir, e))?;
+        let root = app_data_root(app)?;
+        let mut dir = profiles::profile_dir(&root, &profiles::active(&root));
+        if app.state::<SessionState>().is_decoy() {
+            dir = duress::decoy_dir(&dir);
+        }
+        if !dir.exists() {
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create profile directory at {:?}: {}", dir, e))?;
         }
-        Ok(data_dir.join("keychain.json"))
+        Ok(dir.join(profiles::KEYCHAIN_FILE_NAME))
     } else {
         // Phase 2 Preparation: If vault_id is a drive path, look for .qre_portable/keychain.qre
         let path = PathBuf::from(vault_id)
@@ -66,6 +153,60 @@ fn resolve_keychain_path(app: &AppHandle, vault_id: &str) -> Result<PathBuf, Str
     }
 }
 
+// ==========================================
+// --- HELPER: Audit Log ---
+// ==========================================
+
+/// Appends an event to the audit log next to the vault's keychain, then checks the log
+/// for anomalies and emits `security-alert` with any new ones.
+/// Audit failures are logged but never block the action itself.
+pub(super) fn record_audit(
+    app: &AppHandle,
+    vault_id: &str,
+    user: &str,
+    action: &str,
+    detail: Option<String>,
+) {
+    let dir = match resolve_keychain_path(app, vault_id) {
+        Ok(path) => match path.parent() {
+            Some(dir) if dir.exists() => dir.to_path_buf(),
+            _ => return,
+        },
+        Err(_) => return,
+    };
+    let event = audit::AuditEvent::new(vault_id, user, action, detail);
+    if let Err(e) = audit::append(&dir, &event) {
+        tracing::error!("[Audit] Failed to record '{}': {}", action, e);
+        return;
+    }
+    match audit::scan_for_alerts(&dir, *chrono::Local::now().offset()) {
+        Ok(alerts) if !alerts.is_empty() => {
+            let _ = app.emit("security-alert", alerts);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("[Audit] Anomaly check failed: {}", e),
+    }
+}
+
+/// Locks every vault and records `action` in each one's audit log. A duress session's
+/// entry belongs in the decoy vault's log, so the decoy flag is only cleared afterwards.
+fn lock_all_with_audit(
+    app: &AppHandle,
+    state: &SessionState,
+    action: &str,
+    detail: Option<String>,
+) {
+    for (vault_id, user) in state.lock_all() {
+        record_audit(app, &vault_id, &user, action, detail.clone());
+    }
+    state.set_decoy(false);
+    // Decrypted note attachments opened in other apps go with the keys.
+    let copies = std::env::temp_dir().join(note_assets::TEMP_DIR_NAME);
+    if copies.exists() {
+        let _ = crate::utils::shred_recursive(app, &copies);
+    }
+}
+
 // ==========================================
 // --- SAFE MUTEX ACCESSOR ---
 // ==========================================
@@ -78,7 +219,9 @@ macro_rules! lock_session {
             Err(poisoned) => {
                 let mut guard = poisoned.into_inner();
                 guard.clear(); // Zeroizes all MasterKeys in the map
-                Err("Session state is corrupted. All vaults locked.".to_string())
+                Err(String::from($crate::i18n::AppError::new(
+                    $crate::i18n::ErrorCode::SessionCorrupted,
+                )))
             }
         }
     };
@@ -89,21 +232,38 @@ macro_rules! lock_session {
 // ==========================================
 
 #[tauri::command]
-pub fn get_keychain_data(app: AppHandle) -> CommandResult<Vec<u8>> {
+pub fn get_keychain_data(
+    app: AppHandle,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<u8>> {
+    state.ensure_writable("local")?;
     let path = resolve_keychain_path(&app, "local")?;
     if !path.exists() {
-        return Err("Keychain not found on disk.".to_string());
+        return Err(AppError::new(ErrorCode::KeychainNotFound).into());
     }
     fs::read(path).map_err(|e| format!("Failed to read keychain: {}", e))
 }
 
 #[tauri::command]
-pub fn export_keychain(app: AppHandle, save_path: String) -> CommandResult<()> {
+pub fn export_keychain(
+    app: AppHandle,
+    save_path: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable("local")?;
     let src = resolve_keychain_path(&app, "local")?;
     if !src.exists() {
-        return Err("Keychain not found on disk.".to_string());
+        return Err(AppError::new(ErrorCode::KeychainNotFound).into());
     }
+    let save_path = SafePath::new(&save_path, PathPolicy::write_file())?;
     fs::copy(src, &save_path).map_err(|e| format!("Failed to export: {}", e))?;
+    record_audit(
+        &app,
+        "local",
+        &state.user_for("local"),
+        "export_keychain",
+        None,
+    );
     Ok(())
 }
 
@@ -120,7 +280,7 @@ pub fn get_backup_done(app: AppHandle) -> bool {
 pub fn set_backup_done(app: AppHandle) -> CommandResult<()> {
     let path = resolve_keychain_path(&app, "local")?
         .parent()
-        .ok_or("Keychain path has no parent directory".to_string())?
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?
         .join("backup_done");
     fs::write(&path, b"1").map_err(|e| format!("Failed to write backup flag: {}", e))?;
     Ok(())
@@ -160,14 +320,25 @@ pub fn init_vault(
     app: AppHandle,
     password: String,
     vault_id: String,
+    recovery_format: Option<RecoveryCodeFormat>,
     state: tauri::State<SessionState>,
 ) -> CommandResult<String> {
     let path = resolve_keychain_path(&app, &vault_id)?;
     let (recovery_code, master_key) =
-        keychain::init_keychain(&path, &password).map_err(|e| e.to_string())?;
+        keychain::init_keychain(&path, &password, recovery_format.unwrap_or_default())
+            .map_err(|e| e.to_string())?;
 
     let mut guard = lock_session!(state)?;
-    guard.insert(vault_id, master_key);
+    guard.insert(vault_id.clone(), master_key);
+    state.set_read_only(&vault_id, false);
+    state.set_user(&vault_id, keychain::OWNER_SLOT_NAME);
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "init_vault",
+        None,
+    );
 
     Ok(recovery_code)
 }
@@ -179,303 +350,3289 @@ pub fn login(
     vault_id: String,
     state: tauri::State<SessionState>,
 ) -> CommandResult<String> {
-    let fail_count = LOGIN_FAIL_COUNT.load(Ordering::SeqCst);
-    if fail_count >= MAX_ATTEMPTS_BEFORE_LOCKOUT {
-        let last_fail = LOGIN_LAST_FAIL_SECS.load(Ordering::SeqCst);
-        let wait = lockout_duration_secs(fail_count);
-        let elapsed = now_secs().saturating_sub(last_fail);
-
-        if elapsed < wait {
-            return Err(format!(
-                "Too many failed attempts. Please wait {} more second(s).",
-                wait - elapsed
-            ));
-        }
-    }
+    check_login_lockout()?;
 
     let path = resolve_keychain_path(&app, &vault_id)?;
     match keychain::unlock_keychain(&path, &password) {
         Ok(master_key) => {
             LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
+            let owner = keychain::OWNER_SLOT_NAME;
+            upgrade_slot_kdf(&app, &path, &vault_id, owner, &password, &master_key);
             let mut guard = lock_session!(state)?;
-            guard.insert(vault_id, master_key);
+            guard.insert(vault_id.clone(), master_key);
+            state.set_read_only(&vault_id, false);
+            state.set_user(&vault_id, keychain::OWNER_SLOT_NAME);
+            record_audit(&app, &vault_id, keychain::OWNER_SLOT_NAME, "login", None);
             Ok("Logged in".to_string())
         }
         Err(e) => {
-            LOGIN_FAIL_COUNT.fetch_add(1, Ordering::SeqCst);
-            LOGIN_LAST_FAIL_SECS.store(now_secs(), Ordering::SeqCst);
-            Err(e.to_string())
-        }
-    }
-}
-
-#[tauri::command]
-pub fn logout(state: tauri::State<SessionState>) {
-    match state.vaults.lock() {
-        Ok(mut guard) => {
-            guard.clear(); // Lock ALL vaults
-        }
-        Err(poisoned) => {
-            let mut guard = poisoned.into_inner();
-            guard.clear();
+            // The duress password goes through the same form and ends the same way.
+            if vault_id == "local" {
+                if let Ok((decoy_key, wipe)) = keychain::unlock_duress(&path, &password) {
+                    return open_decoy_session(&app, &state, &path, decoy_key, wipe);
+                }
+            }
+            record_login_failure();
+            record_audit(
+                &app,
+                &vault_id,
+                keychain::OWNER_SLOT_NAME,
+                "login_failed",
+                None,
+            );
+            Err(login_error(e))
         }
     }
 }
 
+/// Unlocks the vault with the guest credential. The session can list and view
+/// everything but every mutating, exporting or shredding command is refused.
+/// Shares the login lockout counter, so it cannot be used to brute-force around it.
 #[tauri::command]
-pub fn change_user_password(
+pub fn login_read_only(
     app: AppHandle,
-    current_password: String,
-    new_password: String,
+    password: String,
     vault_id: String,
     state: tauri::State<SessionState>,
 ) -> CommandResult<String> {
-    let path = resolve_keychain_path(&app, &vault_id)?;
-
-    keychain::unlock_keychain(&path, &current_password)
-        .map_err(|_| "Current password is incorrect.".to_string())?;
-
-    let guard = lock_session!(state)?;
-    let master_key = guard
-        .get(&vault_id)
-        .ok_or_else(|| "Vault is locked.".to_string())?;
+    check_login_lockout()?;
 
-    keychain::change_password(&path, master_key, &new_password).map_err(|e| e.to_string())?;
-    Ok("Password changed successfully.".to_string())
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    match keychain::unlock_guest(&path, &password) {
+        Ok(master_key) => {
+            LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
+            let guest = keychain::GUEST_SLOT_NAME;
+            upgrade_slot_kdf(&app, &path, &vault_id, guest, &password, &master_key);
+            let mut guard = lock_session!(state)?;
+            // Flag first: no window in which the key is present but the session is writable.
+            state.set_read_only(&vault_id, true);
+            guard.insert(vault_id.clone(), master_key);
+            state.set_user(&vault_id, keychain::GUEST_SLOT_NAME);
+            record_audit(&app, &vault_id, keychain::GUEST_SLOT_NAME, "login", None);
+            Ok("Logged in (read-only)".to_string())
+        }
+        Err(e) => {
+            record_login_failure();
+            record_audit(
+                &app,
+                &vault_id,
+                keychain::GUEST_SLOT_NAME,
+                "login_failed",
+                None,
+            );
+            Err(login_error(e))
+        }
+    }
 }
 
+/// Unlocks the vault with a team member's named slot. Full access, but actions are
+/// attributed to `user` in the audit log and owner-only commands are refused.
 #[tauri::command]
-pub fn recover_vault(
+pub fn login_as_user(
     app: AppHandle,
-    recovery_code: String,
-    new_password: String,
+    user: String,
+    password: String,
     vault_id: String,
     state: tauri::State<SessionState>,
 ) -> CommandResult<String> {
-    let fail_count = RECOVERY_FAIL_COUNT.load(Ordering::SeqCst);
-    if fail_count >= MAX_ATTEMPTS_BEFORE_LOCKOUT {
-        let last_fail = RECOVERY_LAST_FAIL_SECS.load(Ordering::SeqCst);
-        let wait = lockout_duration_secs(fail_count);
-        let elapsed = now_secs().saturating_sub(last_fail);
-
-        if elapsed < wait {
-            return Err(format!(
-                "Too many failed recovery attempts. Please wait {} more second(s).",
-                wait - elapsed
-            ));
-        }
-    }
+    check_login_lockout()?;
 
     let path = resolve_keychain_path(&app, &vault_id)?;
-
-    match keychain::recover_with_code(&path, &recovery_code, &new_password) {
-        Ok(master_key) => {
-            RECOVERY_FAIL_COUNT.store(0, Ordering::SeqCst);
+    match keychain::unlock_as_user(&path, &user, &password) {
+        Ok((name, master_key)) => {
             LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
-
+            upgrade_slot_kdf(&app, &path, &vault_id, &name, &password, &master_key);
             let mut guard = lock_session!(state)?;
-            guard.insert(vault_id, master_key);
-            Ok("Recovery successful. Password updated.".to_string())
+            guard.insert(vault_id.clone(), master_key);
+            state.set_read_only(&vault_id, false);
+            state.set_user(&vault_id, &name);
+            record_audit(&app, &vault_id, &name, "login", None);
+            Ok(format!("Logged in as {}", name))
         }
         Err(e) => {
-            RECOVERY_FAIL_COUNT.fetch_add(1, Ordering::SeqCst);
-            RECOVERY_LAST_FAIL_SECS.store(now_secs(), Ordering::SeqCst);
-            Err(e.to_string())
+            record_login_failure();
+            record_audit(&app, &vault_id, &user, "login_failed", None);
+            Err(login_error(e))
         }
     }
 }
 
+/// Slot name the vault is unlocked with ("owner", "guest" or a user name).
 #[tauri::command]
-pub fn regenerate_recovery_code(
-    app: AppHandle,
+pub fn get_session_user(
     vault_id: String,
     state: tauri::State<SessionState>,
 ) -> CommandResult<String> {
     let guard = lock_session!(state)?;
-    let master_key = guard
-        .get(&vault_id)
-        .ok_or_else(|| "Vault is locked. Cannot reset code.".to_string())?;
-
-    let path = resolve_keychain_path(&app, &vault_id)?;
-    let new_code = keychain::reset_recovery_code(&path, master_key).map_err(|e| e.to_string())?;
-    Ok(new_code)
+    if !guard.contains_key(&vault_id) {
+        return Err(AppError::new(ErrorCode::VaultLocked).into());
+    }
+    Ok(state.user_for(&vault_id))
 }
 
-// ==========================================
-// --- PASSWORD VAULT COMMANDS ---
-// ==========================================
-
+/// "full" or "read_only" for `vault_id`, so the UI can hide controls that would be
+/// refused anyway.
 #[tauri::command]
-pub fn load_password_vault(
-    app: AppHandle,
-    vault_id: String,
-    state: tauri::State<SessionState>,
-) -> CommandResult<PasswordVault> {
-    let master_key = {
-        let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
-
-    let path = resolve_keychain_path(&app, &vault_id)?
-        .parent()
-        .unwrap()
-        .join("passwords.qre");
-
-    if !path.exists() {
-        return Ok(PasswordVault::new());
+pub fn get_session_mode(vault_id: String, state: tauri::State<SessionState>) -> String {
+    if state.is_read_only(&vault_id) {
+        "read_only".to_string()
+    } else {
+        "full".to_string()
     }
-
-    let container =
-        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
-    let payload = crypto::decrypt_file_with_master_key(&master_key, None, &container)
-        .map_err(|e| e.to_string())?;
-
-    let vault: PasswordVault = serde_json::from_slice(&payload.content)
-        .map_err(|_| "Failed to parse vault".to_string())?;
-    Ok(vault)
 }
 
+/// Enables, changes or (with `guest_password: None`) disables guest access.
 #[tauri::command]
-pub fn save_password_vault(
+pub fn set_guest_password(
     app: AppHandle,
     vault_id: String,
+    current_password: String,
+    guest_password: Option<String>,
     state: tauri::State<SessionState>,
-    vault: PasswordVault,
 ) -> CommandResult<()> {
-    vault.validate().map_err(|e| e.to_string())?;
-
-    let master_key = {
-        let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
-
-    let path = resolve_keychain_path(&app, &vault_id)?
-        .parent()
-        .unwrap()
-        .join("passwords.qre");
-    let json_data = serde_json::to_vec(&vault).map_err(|e| e.to_string())?;
-
-    let container = crypto::encrypt_file_with_master_key(
-        &master_key,
-        None,
-        "passwords.json",
-        &json_data,
-        None,
-        3,
-    )
-    .map_err(|e| e.to_string())?;
-    container
-        .save(path.to_str().unwrap())
+    state.ensure_writable(&vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::set_guest_slot(&path, &current_password, guest_password.as_deref())
         .map_err(|e| e.to_string())?;
+    let action = if guest_password.is_some() {
+        "set_guest_password"
+    } else {
+        "disable_guest"
+    };
+    record_audit(&app, &vault_id, &state.user_for(&vault_id), action, None);
     Ok(())
 }
 
 // ==========================================
-// --- NOTES VAULT COMMANDS ---
+// --- DURESS PASSWORD (duress.rs) ---
 // ==========================================
+// A second password for the login form that opens a decoy vault, optionally wiping the
+// real one. Only for the local vault.
+
+/// Unlocks the decoy vault for a duress login. Looks like an ordinary owner login,
+/// including the audit entry, which goes to the decoy vault's log.
+fn open_decoy_session(
+    app: &AppHandle,
+    state: &SessionState,
+    path: &std::path::Path,
+    decoy_key: keychain::MasterKey,
+    wipe: bool,
+) -> CommandResult<String> {
+    let vault_dir = path
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?;
+    // After a wipe the decoy files are the vault; otherwise they stay in the decoy dir.
+    let mut decoy = true;
+    // stderr only: a line in the diagnostic log would give the decoy away.
+    if wipe {
+        match duress::wipe_real_vault(vault_dir) {
+            Ok(failed) => {
+                decoy = false;
+                forget_scan_cache(app);
+                for (file, error) in failed {
+                    eprintln!("[Duress] Could not clean up '{}': {}", file, error);
+                }
+            }
+            Err(e) => eprintln!("[Duress] Wipe failed: {}", e),
+        }
+    }
+
+    LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
+    let mut guard = lock_session!(state)?;
+    state.set_decoy(decoy);
+    guard.insert("local".to_string(), decoy_key);
+    state.set_read_only("local", false);
+    state.set_user("local", keychain::OWNER_SLOT_NAME);
+    drop(guard);
+    record_audit(app, "local", keychain::OWNER_SLOT_NAME, "login", None);
+    Ok("Logged in".to_string())
+}
 
 #[tauri::command]
-pub fn load_notes_vault(
+pub fn get_duress_status(
     app: AppHandle,
     vault_id: String,
     state: tauri::State<SessionState>,
-) -> CommandResult<NotesVault> {
-    let master_key = {
-        let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
-    let path = resolve_keychain_path(&app, &vault_id)?
-        .parent()
-        .unwrap()
-        .join("notes.qre");
-
-    if !path.exists() {
-        return Ok(NotesVault::new());
-    }
-
-    let container =
-        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
-    let payload = crypto::decrypt_file_with_master_key(&master_key, None, &container)
-        .map_err(|e| e.to_string())?;
-    let vault: NotesVault = serde_json::from_slice(&payload.content)
-        .map_err(|_| "Failed to parse notes".to_string())?;
-    Ok(vault)
+) -> CommandResult<Option<DuressSlotInfo>> {
+    ensure_owner(&state, &vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::duress_slot_info(&path).map_err(|e| e.to_string())
 }
 
+/// Sets or (with `duress_password: None`) removes the duress password. With `wipe`,
+/// using it also destroys the real vault. A new duress password starts an empty decoy
+/// vault; re-entering the current one keeps the decoy's content.
 #[tauri::command]
-pub fn save_notes_vault(
+pub fn set_duress_password(
     app: AppHandle,
     vault_id: String,
+    current_password: String,
+    duress_password: Option<String>,
+    wipe: Option<bool>,
     state: tauri::State<SessionState>,
-    vault: NotesVault,
 ) -> CommandResult<()> {
-    vault.validate().map_err(|e| e.to_string())?;
-
-    let master_key = {
-        let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
-
-    let path = resolve_keychain_path(&app, &vault_id)?
+    state.ensure_writable(&vault_id)?;
+    ensure_owner(&state, &vault_id)?;
+    rate_limit("set_duress_password", AUTH_RATE)?;
+    if vault_id != "local" {
+        return Err(AppError::new(ErrorCode::DuressLocalOnly).into());
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let vault_dir = path
         .parent()
-        .unwrap()
-        .join("notes.qre");
-    let json_data = serde_json::to_vec(&vault).map_err(|e| e.to_string())?;
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?;
 
-    let container =
-        crypto::encrypt_file_with_master_key(&master_key, None, "notes.json", &json_data, None, 3)
+    let duress = duress_password
+        .as_deref()
+        .map(|password| (password, wipe.unwrap_or(false)));
+    let new_decoy =
+        keychain::set_duress_slot(&path, &current_password, duress).map_err(|e| e.to_string())?;
+    match (&duress_password, new_decoy) {
+        (None, _) => duress::remove_decoy(vault_dir).map_err(|e| e.to_string())?,
+        (Some(password), Some(decoy_key)) => {
+            duress::remove_decoy(vault_dir).map_err(|e| e.to_string())?;
+            let decoy_dir = duress::decoy_dir(vault_dir);
+            fs::create_dir_all(&decoy_dir).map_err(|e| e.to_string())?;
+            keychain::init_decoy_keychain(
+                &decoy_dir.join(profiles::KEYCHAIN_FILE_NAME),
+                password,
+                &decoy_key,
+            )
             .map_err(|e| e.to_string())?;
-    container
-        .save(path.to_str().unwrap())
-        .map_err(|e| e.to_string())?;
+        }
+        (Some(_), None) => {}
+    }
+
+    let action = if duress_password.is_some() {
+        "set_duress_password"
+    } else {
+        "remove_duress_password"
+    };
+    record_audit(&app, &vault_id, &state.user_for(&vault_id), action, None);
     Ok(())
 }
 
 // ==========================================
-// --- BOOKMARKS COMMANDS ---
+// --- ORGANIZATION MODE (NAMED USER SLOTS) ---
 // ==========================================
 
+/// Only the owner may manage user slots or the recovery code; team members cannot add
+/// or revoke each other.
+pub(super) fn ensure_owner(state: &SessionState, vault_id: &str) -> CommandResult<()> {
+    // `user_for` reports the owner for a vault nobody has unlocked, so the session has
+    // to exist first.
+    if !lock_session!(state)?.contains_key(vault_id) {
+        return Err(AppError::new(ErrorCode::VaultLocked).into());
+    }
+    state.ensure_writable(vault_id)?;
+    if state.user_for(vault_id) != keychain::OWNER_SLOT_NAME {
+        return Err(AppError::new(ErrorCode::OwnerOnly).into());
+    }
+    Ok(())
+}
+
 #[tauri::command]
-pub fn load_bookmarks_vault(
+pub fn list_vault_users(
     app: AppHandle,
     vault_id: String,
-    state: tauri::State<SessionState>,
-) -> CommandResult<BookmarksVault> {
-    let master_key = {
-        let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
-
-    let path = resolve_keychain_path(&app, &vault_id)?
-        .parent()
-        .unwrap()
-        .join("bookmarks.qre");
+) -> CommandResult<Vec<keychain::UserSlotInfo>> {
+    let path = resolve_keychain_path(&app, &vault_id)?;
     if !path.exists() {
-        return Ok(BookmarksVault::new());
+        return Ok(Vec::new());
     }
-
-    let container =
-        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
-    let payload = crypto::decrypt_file_with_master_key(&master_key, None, &container)
-        .map_err(|e| e.to_string())?;
-    let vault: BookmarksVault = serde_json::from_slice(&payload.content)
-        .map_err(|_| "Failed to parse bookmarks data".to_string())?;
-    Ok(vault)
+    keychain::list_user_slots(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn save_bookmarks_vault(
+pub fn add_vault_user(
     app: AppHandle,
     vault_id: String,
+    name: String,
+    password: String,
     state: tauri::State<SessionState>,
-    vault: BookmarksVault,
 ) -> CommandResult<()> {
-    vault.validate().map_err(|e| e.to_string())?;
-
-    let master_key = {
+    ensure_owner(&state, &vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    {
         let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
+        let master_key = guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?;
+        keychain::add_user_slot(&path, master_key, &name, &password).map_err(|e| e.to_string())?;
+    }
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "add_user",
+        Some(name),
+    );
+    Ok(())
+}
+
+/// Revokes a team member's password. Their current session (if any, on another machine
+/// sharing the keychain) is unaffected until it locks.
+#[tauri::command]
+pub fn remove_vault_user(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    ensure_owner(&state, &vault_id)?;
+    {
+        let guard = lock_session!(state)?;
+        if !guard.contains_key(&vault_id) {
+            return Err(AppError::new(ErrorCode::VaultLocked).into());
+        }
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::remove_user_slot(&path, &name).map_err(|e| e.to_string())?;
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "remove_user",
+        Some(name),
+    );
+    Ok(())
+}
+
+/// Most recent audit events for an unlocked vault, newest first.
+#[tauri::command]
+pub fn get_audit_log(
+    app: AppHandle,
+    vault_id: String,
+    limit: Option<usize>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<audit::AuditEvent>> {
+    {
+        let guard = lock_session!(state)?;
+        if !guard.contains_key(&vault_id) {
+            return Err(AppError::new(ErrorCode::VaultLocked).into());
+        }
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?;
+    audit::read_recent(dir, limit.unwrap_or(200).min(1000)).map_err(|e| e.to_string())
+}
+
+/// Anomaly alerts raised from the audit log (see `audit::detect`), newest first.
+/// Acknowledged ones are only included when asked for.
+#[tauri::command]
+pub fn get_security_alerts(
+    app: AppHandle,
+    vault_id: String,
+    include_acknowledged: Option<bool>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<audit::SecurityAlert>> {
+    {
+        let guard = lock_session!(state)?;
+        if !guard.contains_key(&vault_id) {
+            return Err(AppError::new(ErrorCode::VaultLocked).into());
+        }
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?;
+    let mut alerts: Vec<audit::SecurityAlert> = audit::load_alerts(dir)
+        .alerts
+        .into_iter()
+        .filter(|a| include_acknowledged.unwrap_or(false) || a.acknowledged_at.is_none())
+        .collect();
+    alerts.sort_by_key(|a| std::cmp::Reverse(a.at));
+    Ok(alerts)
+}
+
+/// Dismisses an alert. Guests cannot, so they cannot hide what they did.
+#[tauri::command]
+pub fn acknowledge_security_alert(
+    app: AppHandle,
+    vault_id: String,
+    alert_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    {
+        let guard = lock_session!(state)?;
+        if !guard.contains_key(&vault_id) {
+            return Err(AppError::new(ErrorCode::VaultLocked).into());
+        }
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?;
+    audit::acknowledge_alert(dir, &alert_id, chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "acknowledge_alert",
+        None,
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn logout(app: AppHandle, state: tauri::State<SessionState>) {
+    // Lock ALL vaults
+    lock_all_with_audit(&app, &state, "logout", None);
+}
+
+// ==========================================
+// --- PROFILES (profiles.rs) ---
+// ==========================================
+// Separate local vaults for the people sharing a computer. The "local" vault id always
+// resolves to the active profile, so the auth commands work on whichever is active.
+
+/// The profile's name as spelled on disk (names are matched ignoring case).
+fn find_profile(root: &std::path::Path, name: &str) -> CommandResult<String> {
+    let name = name.trim();
+    profiles::find(root, name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| {
+            AppError::new(ErrorCode::ProfileNotFound)
+                .with("name", name)
+                .into()
+        })
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> CommandResult<Vec<profiles::ProfileInfo>> {
+    let root = app_data_root(&app)?;
+    profiles::list(&root).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_active_profile(app: AppHandle) -> CommandResult<String> {
+    Ok(profiles::active(&app_data_root(&app)?))
+}
+
+/// Creates an empty profile. It gets its own vault, set up the first time it is used.
+#[tauri::command]
+pub fn create_profile(
+    app: AppHandle,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    let root = app_data_root(&app)?;
+    create_profile_in(&state, &root, &name)
+}
+
+/// Only the owner of the unlocked local vault can add profiles to the install; guests,
+/// team members and a locked app cannot.
+pub fn create_profile_in(
+    state: &SessionState,
+    root: &std::path::Path,
+    name: &str,
+) -> CommandResult<()> {
+    ensure_owner(state, "local")?;
+    profiles::create(root, name.trim()).map_err(|e| e.to_string())
+}
+
+/// Locks the session, then makes `name` the active profile. Returns the new profile's
+/// auth status, as `check_auth_status` would.
+///
+/// Needs no owner session: it is how the login screen picks a profile. It opens nothing
+/// (every vault is locked first, and the new profile's vault asks for its own password)
+/// and changes nothing but which profile is active.
+#[tauri::command]
+pub fn switch_profile(
+    app: AppHandle,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    let root = app_data_root(&app)?;
+    let name = find_profile(&root, &name)?;
+    // Logged out under the old profile, so the audit entries land in its log.
+    lock_all_with_audit(&app, &state, "logout", None);
+    profiles::switch(&root, &name).map_err(|e| e.to_string())?;
+    Ok(check_auth_status(app, state))
+}
+
+/// Deletes a profile that is not active, with all of its files. Only the owner of the
+/// unlocked local vault can delete profiles, and a profile with a vault also needs that
+/// vault's owner password.
+#[tauri::command]
+pub fn delete_profile(
+    app: AppHandle,
+    name: String,
+    password: Option<String>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    ensure_owner(&state, "local")?;
+    rate_limit("delete_profile", AUTH_RATE)?;
+    let root = app_data_root(&app)?;
+    let name = find_profile(&root, &name)?;
+    let keychain_path = profiles::profile_dir(&root, &name).join(profiles::KEYCHAIN_FILE_NAME);
+    if keychain::keychain_exists(&keychain_path) {
+        let password = password.unwrap_or_default();
+        keychain::unlock_keychain(&keychain_path, &password)
+            .map_err(|_| AppError::new(ErrorCode::IncorrectPassword))?;
+    }
+    profiles::delete(&root, &name).map_err(|e| e.to_string())?;
+    forget_scan_cache(&app);
+    Ok(())
+}
+
+/// Deletes the file analyzer's cache when a vault is destroyed. Its entries name no file,
+/// but their number and verdicts still say something about what the user scanned.
+fn forget_scan_cache(app: &AppHandle) {
+    if let Ok(path) = crate::analyzer::cache_path(app) {
+        let _ = crate::analyzer::remove_cache(&path);
+    }
+}
+
+#[tauri::command]
+pub fn change_user_password(
+    app: AppHandle,
+    current_password: String,
+    new_password: String,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    state.ensure_writable(&vault_id)?;
+    rate_limit("change_user_password", AUTH_RATE)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+
+    // A team member changes their own slot, never the owner's password.
+    let user = state.user_for(&vault_id);
+    let is_owner = user == keychain::OWNER_SLOT_NAME;
+    if is_owner {
+        keychain::unlock_keychain(&path, &current_password)
+            .map_err(|_| AppError::new(ErrorCode::CurrentPasswordIncorrect))?;
+    } else {
+        keychain::unlock_as_user(&path, &user, &current_password)
+            .map_err(|_| AppError::new(ErrorCode::CurrentPasswordIncorrect))?;
+    }
+
+    let guard = lock_session!(state)?;
+    let master_key = guard
+        .get(&vault_id)
+        .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?;
+
+    if is_owner {
+        keychain::change_password(&path, master_key, &new_password)
+    } else {
+        keychain::change_user_slot_password(&path, &user, master_key, &new_password)
+    }
+    .map_err(|e| e.to_string())?;
+    // In a duress session, the duress slot must keep opening the decoy vault.
+    if is_owner && state.is_decoy() {
+        let real = duress::real_keychain_path(&path)
+            .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?;
+        keychain::rewrap_duress_slot(&real, master_key, &new_password)
+            .map_err(|e| e.to_string())?;
+    }
+    drop(guard);
+    record_audit(&app, &vault_id, &user, "change_password", None);
+    Ok("Password changed successfully.".to_string())
+}
+
+#[tauri::command]
+pub fn recover_vault(
+    app: AppHandle,
+    recovery_code: String,
+    new_password: String,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    rate_limit("recover_vault", AUTH_RATE)?;
+    let fail_count = RECOVERY_FAIL_COUNT.load(Ordering::SeqCst);
+    if fail_count >= MAX_ATTEMPTS_BEFORE_LOCKOUT {
+        let last_fail = RECOVERY_LAST_FAIL_SECS.load(Ordering::SeqCst);
+        let wait = lockout_duration_secs(fail_count);
+        let elapsed = now_secs().saturating_sub(last_fail);
+
+        if elapsed < wait {
+            return Err(AppError::new(ErrorCode::RecoveryLockedOut)
+                .with("seconds", wait - elapsed)
+                .into());
+        }
+    }
+
+    let path = resolve_keychain_path(&app, &vault_id)?;
+
+    // A policy violation is not a failed recovery attempt: check it before the lockout counter.
+    keychain::load_policy(&path)
+        .and_then(|policy| policy.check_password(&new_password))
+        .map_err(|e| e.to_string())?;
+
+    match keychain::recover_with_code(&path, &recovery_code, &new_password) {
+        Ok(master_key) => {
+            RECOVERY_FAIL_COUNT.store(0, Ordering::SeqCst);
+            LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
+
+            let mut guard = lock_session!(state)?;
+            guard.insert(vault_id.clone(), master_key);
+            state.set_read_only(&vault_id, false);
+            state.set_user(&vault_id, keychain::OWNER_SLOT_NAME);
+            record_audit(&app, &vault_id, keychain::OWNER_SLOT_NAME, "recover", None);
+            Ok("Recovery successful. Password updated.".to_string())
+        }
+        Err(e) => {
+            RECOVERY_FAIL_COUNT.fetch_add(1, Ordering::SeqCst);
+            RECOVERY_LAST_FAIL_SECS.store(now_secs(), Ordering::SeqCst);
+            Err(e.to_string())
+        }
+    }
+}
+
+#[tauri::command]
+pub fn regenerate_recovery_code(
+    app: AppHandle,
+    vault_id: String,
+    recovery_format: Option<RecoveryCodeFormat>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    // The recovery code bypasses every slot, so team members may not rotate it.
+    ensure_owner(&state, &vault_id)?;
+    let guard = lock_session!(state)?;
+    let master_key = guard
+        .get(&vault_id)
+        .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?;
+
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let new_code = keychain::reset_recovery_code(&path, master_key, recovery_format)
+        .map_err(|e| e.to_string())?;
+    Ok(new_code)
+}
+
+/// Lets the recovery screen offer a word grid or a hex field. Needs no unlocked session.
+#[tauri::command]
+pub fn get_recovery_code_format(
+    app: AppHandle,
+    vault_id: String,
+) -> CommandResult<RecoveryCodeFormat> {
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::load_recovery_format(&path).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- VAULT POLICY ---
+// ==========================================
+
+/// Policy lookup for the other command modules (files.rs, timelock.rs) that encrypt files.
+pub fn load_vault_policy(app: &AppHandle, vault_id: &str) -> CommandResult<VaultPolicy> {
+    let path = resolve_keychain_path(app, vault_id)?;
+    keychain::load_policy(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_vault_policy(app: AppHandle, vault_id: String) -> CommandResult<VaultPolicy> {
+    load_vault_policy(&app, &vault_id)
+}
+
+/// Changing the policy re-checks the password, so a briefly unattended unlocked session
+/// cannot be used to weaken it.
+#[tauri::command]
+pub fn set_vault_policy(
+    app: AppHandle,
+    vault_id: String,
+    current_password: String,
+    policy: VaultPolicy,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::set_policy(&path, &current_password, policy).map_err(|e| format!("{:#}", e))
+}
+
+// ==========================================
+// --- KDF TUNING (keychain.rs) ---
+// ==========================================
+
+/// Moves the slot just opened to the vault's tuned KDF parameters. Best effort: on
+/// failure the slot keeps its old parameters and the login goes ahead.
+fn upgrade_slot_kdf(
+    app: &AppHandle,
+    path: &std::path::Path,
+    vault_id: &str,
+    slot: &str,
+    password: &str,
+    master_key: &keychain::MasterKey,
+) {
+    match keychain::upgrade_slot_kdf(path, slot, password, master_key) {
+        Ok(true) => record_audit(app, vault_id, slot, "kdf_upgraded", None),
+        Ok(false) => {}
+        Err(e) => tracing::warn!("KDF upgrade of slot '{}' failed: {}", slot, e),
+    }
+}
+
+#[tauri::command]
+pub fn get_kdf_status(app: AppHandle, vault_id: String) -> CommandResult<KdfStatus> {
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::kdf_status(&path).map_err(|e| e.to_string())
+}
+
+/// Measures this device and stores Argon2id parameters that take about half a second as
+/// the vault's target. Slots move to them as they are next used. Parameters weaker than
+/// the current ones need `allow_weaker`. Owner only.
+#[tauri::command]
+pub async fn benchmark_kdf(
+    app: AppHandle,
+    vault_id: String,
+    allow_weaker: Option<bool>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<KdfStatus> {
+    ensure_owner(&state, &vault_id)?;
+    if !lock_session!(state)?.contains_key(&vault_id) {
+        return Err(AppError::new(ErrorCode::VaultLocked).into());
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+
+    let status = tauri::async_runtime::spawn_blocking(move || -> CommandResult<KdfStatus> {
+        let kdf = keychain::benchmark_kdf(keychain::KDF_TARGET).map_err(|e| e.to_string())?;
+        keychain::set_kdf_target(&path, kdf, allow_weaker.unwrap_or(false))
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if let Some(target) = status.target {
+        record_audit(
+            &app,
+            &vault_id,
+            keychain::OWNER_SLOT_NAME,
+            "kdf_tuned",
+            Some(format!(
+                "{} MiB, {} passes, {} lanes",
+                target.memory / 1024,
+                target.iterations,
+                target.parallelism
+            )),
+        );
+    }
+    Ok(status)
+}
+
+// ==========================================
+// --- PASSWORD VAULT COMMANDS ---
+// ==========================================
+
+/// Decrypts `passwords.qre` for an unlocked vault (an empty vault if the file does not exist yet).
+/// Shared by the load command and by backend-side queries such as URL matching.
+fn read_password_vault(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<PasswordVault> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join("passwords.qre");
+
+    if !path.exists() {
+        return Ok(PasswordVault::new());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload = crypto::decrypt_file_with_master_key(
+        &master_key,
+        KeyPurpose::PasswordsVault,
+        None,
+        &container,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let vault: PasswordVault = serde_json::from_slice(&payload.content)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted))?;
+    Ok(vault)
+}
+
+/// Validates and re-encrypts `passwords.qre`.
+fn write_password_vault(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    vault: &PasswordVault,
+) -> CommandResult<()> {
+    vault.validate().map_err(|e| e.to_string())?;
+
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join("passwords.qre");
+    let json_data = serde_json::to_vec(vault).map_err(|e| e.to_string())?;
+
+    let container = crypto::encrypt_file_with_master_key(
+        &master_key,
+        KeyPurpose::PasswordsVault,
+        None,
+        "passwords.json",
+        &json_data,
+        None,
+        3,
+    )
+    .map_err(|e| e.to_string())?;
+    container
+        .save(path.to_str().unwrap())
+        .map_err(|e| e.to_string())?;
+    stamp_vault_writes(app, vault_id, state, &["passwords.qre"]);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn load_password_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<PasswordVault> {
+    read_password_vault(&app, &vault_id, &state)
+}
+
+#[tauri::command]
+pub fn save_password_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    mut vault: PasswordVault,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    // Usage statistics are owned by the backend: carry them over from the stored vault.
+    let previous = read_password_vault(&app, &vault_id, &state)?;
+    vault.preserve_usage_from(&previous);
+    // So is the password history: changed passwords are recorded here as well.
+    vault.preserve_history_from(&previous, chrono::Utc::now().timestamp());
+    // And the attachment lists; files of deleted entries go once the save succeeds.
+    let orphaned = vault.preserve_attachments_from(&previous);
+    write_password_vault(&app, &vault_id, &state, &vault)?;
+    let vault_dir = resolve_keychain_path(&app, &vault_id)?;
+    for id in &orphaned {
+        let _ = entry_attachments::remove_attachment_file(vault_dir.parent().unwrap(), id);
+    }
+    Ok(())
+}
+
+/// Saves one edited entry. A changed password is moved into the entry's history
+/// (newest first, at most the vault's history limit).
+#[tauri::command]
+pub fn update_password_entry(
+    app: AppHandle,
+    vault_id: String,
+    entry: VaultEntry,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let mut vault = read_password_vault(&app, &vault_id, &state)?;
+    vault.update_entry(entry, chrono::Utc::now().timestamp())?;
+    write_password_vault(&app, &vault_id, &state, &vault)
+}
+
+/// Sets how many earlier passwords each entry keeps (0 turns the history off and
+/// clears it). Longer histories are trimmed right away.
+#[tauri::command]
+pub fn set_password_history_limit(
+    app: AppHandle,
+    vault_id: String,
+    limit: usize,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let mut vault = read_password_vault(&app, &vault_id, &state)?;
+    vault.set_history_limit(limit)?;
+    write_password_vault(&app, &vault_id, &state, &vault)
+}
+
+/// Returns an entry's password for copying/auto-fill and records the use.
+/// Routing retrieval through the backend is what keeps `last_used_at` trustworthy.
+#[tauri::command]
+pub fn use_password_entry(
+    app: AppHandle,
+    vault_id: String,
+    entry_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    let mut vault = read_password_vault(&app, &vault_id, &state)?;
+    // Guests may view passwords, but their views do not count as use (and cannot be saved).
+    if state.is_read_only(&vault_id) {
+        return vault
+            .entries
+            .iter()
+            .find(|e| e.id == entry_id)
+            .map(|e| e.password.clone())
+            .ok_or_else(|| {
+                AppError::new(ErrorCode::EntryNotFound)
+                    .with("id", &entry_id)
+                    .into()
+            });
+    }
+    let password = vault
+        .record_use(&entry_id, chrono::Utc::now().timestamp())?
+        .password
+        .clone();
+    write_password_vault(&app, &vault_id, &state, &vault)?;
+    Ok(password)
+}
+
+/// Lists entries by how long they have gone unused, optionally only those idle for at
+/// least `min_idle_days`. Feeds the "digital footprint reduction" report.
+#[tauri::command]
+pub fn list_entry_usage(
+    app: AppHandle,
+    vault_id: String,
+    min_idle_days: Option<u32>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<EntryUsage>> {
+    let vault = read_password_vault(&app, &vault_id, &state)?;
+    Ok(vault.usage_report(chrono::Utc::now().timestamp(), min_idle_days))
+}
+
+/// Returns the entries whose URL rule matches `url`, most specific first.
+/// The matching lives in the backend so every caller (UI, auto-fill, auto-type) agrees.
+#[tauri::command]
+pub fn find_entries_for_url(
+    app: AppHandle,
+    vault_id: String,
+    url: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<VaultEntry>> {
+    let vault = read_password_vault(&app, &vault_id, &state)?;
+    Ok(vault.find_for_url(&url).into_iter().cloned().collect())
+}
+
+/// Personal "right to be forgotten" checklist: every vault entry with its known
+/// deletion page and difficulty, plus the tracked request status.
+#[tauri::command]
+pub fn get_deletion_checklist(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<account_deletion::DeletionChecklistItem>> {
+    let vault = read_password_vault(&app, &vault_id, &state)?;
+    Ok(account_deletion::build_checklist(&vault))
+}
+
+/// Records that deletion was requested/confirmed for an entry (`status`: "requested",
+/// "confirmed" or "none" to clear).
+#[tauri::command]
+pub fn set_deletion_status(
+    app: AppHandle,
+    vault_id: String,
+    entry_id: String,
+    status: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let new_status = account_deletion::parse_status(&status, chrono::Utc::now().timestamp())?;
+    let mut vault = read_password_vault(&app, &vault_id, &state)?;
+    let entry = vault
+        .entries
+        .iter_mut()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| AppError::new(ErrorCode::EntryNotFound).with("id", &entry_id))?;
+    entry.deletion_status = new_status;
+    write_password_vault(&app, &vault_id, &state, &vault)
+}
+
+/// Flags exact duplicates, conflicting passwords for the same login and near-identical
+/// usernames, each with a suggested entry to keep. Meant for cleanup after imports.
+#[tauri::command]
+pub fn find_duplicate_entries(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<DuplicateGroup>> {
+    let vault = read_password_vault(&app, &vault_id, &state)?;
+    Ok(vault.find_duplicates())
+}
+
+/// Merges `merge_ids` into `keep_id` (see `PasswordVault::merge_entries`) and saves.
+/// Returns the number of entries removed.
+#[tauri::command]
+pub fn merge_password_entries(
+    app: AppHandle,
+    vault_id: String,
+    keep_id: String,
+    merge_ids: Vec<String>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<usize> {
+    state.ensure_writable(&vault_id)?;
+    let mut vault = read_password_vault(&app, &vault_id, &state)?;
+    let removed = vault.merge_entries(&keep_id, &merge_ids, chrono::Utc::now().timestamp())?;
+    write_password_vault(&app, &vault_id, &state, &vault)?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "merge_entries",
+        Some(format!("{} merged into {}", removed, keep_id)),
+    );
+    Ok(removed)
+}
+
+// ==========================================
+// --- ENTRY ATTACHMENTS (entry_attachments.rs) ---
+// ==========================================
+
+/// Encrypts a file into `entry_attachments/` and lists it on the entry.
+#[tauri::command]
+pub async fn add_entry_attachment(
+    app: AppHandle,
+    vault_id: String,
+    entry_id: String,
+    name: String,
+    data: Vec<u8>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<EntryAttachment> {
+    state.ensure_writable(&vault_id)?;
+    let attachment = entry_attachments::prepare(&name, &data, chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())?;
+    let data = zeroize::Zeroizing::new(data);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let mut vault = read_password_vault(&app, &vault_id, &state)?;
+        let entry = vault
+            .entries
+            .iter_mut()
+            .find(|e| e.id == entry_id)
+            .ok_or_else(|| AppError::new(ErrorCode::EntryNotFound).with("id", &entry_id))?;
+        if entry.attachments.len() >= entry_attachments::MAX_ATTACHMENTS_PER_ENTRY {
+            return Err(AppError::new(ErrorCode::TooManyAttachments)
+                .with("max", entry_attachments::MAX_ATTACHMENTS_PER_ENTRY)
+                .into());
+        }
+        entry.attachments.push(attachment.clone());
+
+        let master_key = {
+            let guard = lock_session!(state)?;
+            guard
+                .get(&vault_id)
+                .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+                .clone()
+        };
+        let vault_dir = resolve_keychain_path(&app, &vault_id)?
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        fs::create_dir_all(vault_dir.join(entry_attachments::ATTACHMENTS_DIR_NAME))
+            .map_err(|e| e.to_string())?;
+        let path = entry_attachments::attachment_path(&vault_dir, &attachment.id)
+            .map_err(|e| e.to_string())?;
+        let container = crypto::encrypt_file_with_master_key(
+            &master_key,
+            KeyPurpose::PasswordsVault,
+            None,
+            &attachment.name,
+            &data,
+            None,
+            3,
+        )
+        .map_err(|e| e.to_string())?;
+        container
+            .save(path.to_str().unwrap())
+            .map_err(|e| e.to_string())?;
+
+        // The file is only kept if the entry lists it.
+        if let Err(e) = write_password_vault(&app, &vault_id, &state, &vault) {
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+        Ok(attachment)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Decrypts an attachment of an entry.
+#[tauri::command]
+pub async fn get_entry_attachment(
+    app: AppHandle,
+    vault_id: String,
+    entry_id: String,
+    attachment_id: String,
+) -> CommandResult<Vec<u8>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let vault = read_password_vault(&app, &vault_id, &state)?;
+        let listed = vault
+            .entries
+            .iter()
+            .find(|e| e.id == entry_id)
+            .is_some_and(|e| e.attachments.iter().any(|a| a.id == attachment_id));
+        if !listed {
+            return Err(AppError::new(ErrorCode::AttachmentNotFound)
+                .with("id", &attachment_id)
+                .into());
+        }
+        let master_key = {
+            let guard = lock_session!(state)?;
+            guard
+                .get(&vault_id)
+                .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+                .clone()
+        };
+        let vault_dir = resolve_keychain_path(&app, &vault_id)?;
+        let path = entry_attachments::attachment_path(vault_dir.parent().unwrap(), &attachment_id)
+            .map_err(|e| e.to_string())?;
+        let container = crypto::EncryptedFileContainer::load(path.to_str().unwrap())
+            .map_err(|e| e.to_string())?;
+        let payload = crypto::decrypt_file_with_master_key(
+            &master_key,
+            KeyPurpose::PasswordsVault,
+            None,
+            &container,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(payload.content.clone())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Removes an attachment from its entry and deletes the file.
+#[tauri::command]
+pub fn delete_entry_attachment(
+    app: AppHandle,
+    vault_id: String,
+    entry_id: String,
+    attachment_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let mut vault = read_password_vault(&app, &vault_id, &state)?;
+    let entry = vault
+        .entries
+        .iter_mut()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| AppError::new(ErrorCode::EntryNotFound).with("id", &entry_id))?;
+    let before = entry.attachments.len();
+    entry.attachments.retain(|a| a.id != attachment_id);
+    if entry.attachments.len() == before {
+        return Err(AppError::new(ErrorCode::AttachmentNotFound)
+            .with("id", &attachment_id)
+            .into());
+    }
+    write_password_vault(&app, &vault_id, &state, &vault)?;
+    let vault_dir = resolve_keychain_path(&app, &vault_id)?;
+    entry_attachments::remove_attachment_file(vault_dir.parent().unwrap(), &attachment_id)
+        .map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- BREACH MONITOR ---
+// ==========================================
+
+/// How often the background thread wakes up to see whether a check is due.
+const BREACH_MONITOR_TICK: Duration = Duration::from_secs(15 * 60);
+
+/// Prevents the scheduler and a manual "check now" from running overlapping cycles.
+static BREACH_CHECK_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn read_breach_store(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<BreachMonitorStore> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join(breach_monitor::ALERTS_FILE_NAME);
+
+    if !path.exists() {
+        return Ok(BreachMonitorStore::default());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload =
+        crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::VaultData, None, &container)
+            .map_err(|e| e.to_string())?;
+    serde_json::from_slice(&payload.content)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted).into())
+}
+
+fn write_breach_store(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    store: &BreachMonitorStore,
+) -> CommandResult<()> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join(breach_monitor::ALERTS_FILE_NAME);
+    let json_data = serde_json::to_vec(store).map_err(|e| e.to_string())?;
+
+    let container = crypto::encrypt_file_with_master_key(
+        &master_key,
+        KeyPurpose::VaultData,
+        None,
+        "breach_alerts.json",
+        &json_data,
+        None,
+        3,
+    )
+    .map_err(|e| e.to_string())?;
+    container
+        .save(path.to_str().unwrap())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Runs one check cycle, persists the outcome and emits `breach-alert` when something new
+/// turned up. Blocking — callers must be on a worker thread.
+fn run_breach_cycle(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<Vec<BreachAlert>> {
+    // The cycle rewrites the alert history, which a guest session may not do.
+    state.ensure_writable(vault_id)?;
+    if BREACH_CHECK_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(ErrorCode::BreachCheckRunning).into());
+    }
+
+    let result = (|| {
+        let vault = read_password_vault(app, vault_id, state)?;
+        let mut store = read_breach_store(app, vault_id, state)?;
+        let alerts = breach_monitor::run_check(&vault, &mut store, chrono::Utc::now().timestamp());
+
+        let latest = read_breach_store(app, vault_id, state)?;
+        store.merge_user_changes(&latest);
+        write_breach_store(app, vault_id, state, &store)?;
+        Ok(alerts)
+    })();
+    BREACH_CHECK_RUNNING.store(false, Ordering::SeqCst);
+
+    if let Ok(alerts) = &result {
+        if !alerts.is_empty() {
+            let _ = app.emit("breach-alert", alerts);
+        }
+    }
+    result
+}
+
+/// Starts the background scheduler thread (called once from `lib.rs` setup).
+/// It only ever looks at the local vault, and only while it is unlocked: the alert
+/// history and the passwords it checks are both encrypted with the session key.
+pub fn spawn_breach_monitor(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(BREACH_MONITOR_TICK);
+
+        let state = app.state::<SessionState>();
+        let unlocked = lock_session!(state)
+            .map(|guard| guard.contains_key("local"))
+            .unwrap_or(false);
+        if !unlocked {
+            continue;
+        }
+
+        let due = read_breach_store(&app, "local", &state)
+            .map(|store| store.is_due(chrono::Utc::now().timestamp()))
+            .unwrap_or(false);
+        if due {
+            if let Err(e) = run_breach_cycle(&app, "local", &state) {
+                tracing::warn!("Scheduled breach check failed: {}", e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_breach_monitor_status(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<MonitorStatus> {
+    Ok(read_breach_store(&app, &vault_id, &state)?.status())
+}
+
+#[tauri::command]
+pub fn update_breach_monitor_settings(
+    app: AppHandle,
+    vault_id: String,
+    settings: MonitorSettings,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    settings.validate()?;
+    let mut store = read_breach_store(&app, &vault_id, &state)?;
+    store.settings = settings;
+    write_breach_store(&app, &vault_id, &state, &store)
+}
+
+/// Manual "check now". Ignores the interval but still honours offline mode.
+#[tauri::command]
+pub async fn run_breach_check(app: AppHandle, vault_id: String) -> CommandResult<Vec<BreachAlert>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        run_breach_cycle(&app, &vault_id, &state)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Marks alerts as read. Returns how many changed.
+#[tauri::command]
+pub fn acknowledge_breach_alerts(
+    app: AppHandle,
+    vault_id: String,
+    alert_ids: Vec<String>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<usize> {
+    state.ensure_writable(&vault_id)?;
+    let mut store = read_breach_store(&app, &vault_id, &state)?;
+    let changed = store.acknowledge(&alert_ids);
+    if changed > 0 {
+        write_breach_store(&app, &vault_id, &state, &store)?;
+    }
+    Ok(changed)
+}
+
+// ==========================================
+// --- PASSWORD AUDIT (password_audit.rs) ---
+// ==========================================
+
+/// The "security score" report. With `check_breaches`, every password is also looked up
+/// in HaveIBeenPwned (k-anonymity, one request per hash prefix) unless the breach
+/// monitor's offline mode is on; a failed lookup leaves the breach columns empty.
+#[tauri::command]
+pub async fn audit_password_vault(
+    app: AppHandle,
+    vault_id: String,
+    check_breaches: Option<bool>,
+) -> CommandResult<PasswordAuditReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let vault = read_password_vault(&app, &vault_id, &state)?;
+
+        let (breaches, breach_note) = if !check_breaches.unwrap_or(false) {
+            (None, Some("Not requested.".to_string()))
+        } else if read_breach_store(&app, &vault_id, &state)?
+            .settings
+            .offline_mode
+        {
+            (None, Some("Offline mode is on.".to_string()))
+        } else {
+            match password_audit::check_breaches(&vault) {
+                Ok(counts) => (Some(counts), None),
+                Err(e) => (None, Some(format!("Breach check failed: {}", e))),
+            }
+        };
+        Ok(password_audit::audit(
+            &vault,
+            breaches.as_ref(),
+            breach_note,
+            chrono::Utc::now().timestamp(),
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// ==========================================
+// --- ENCRYPTED SECRETS (secrets.rs) ---
+// ==========================================
+
+fn secrets_path(app: &AppHandle, vault_id: &str) -> CommandResult<PathBuf> {
+    Ok(resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join(secrets::SECRETS_FILE_NAME))
+}
+
+fn read_secrets_store(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<SecretsStore> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = secrets_path(app, vault_id)?;
+    if !path.exists() {
+        return Ok(SecretsStore::default());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload =
+        crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::VaultData, None, &container)
+            .map_err(|e| e.to_string())?;
+    serde_json::from_slice(&payload.content)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted).into())
+}
+
+fn write_secrets_store(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    store: &SecretsStore,
+) -> CommandResult<()> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = secrets_path(app, vault_id)?;
+    let json_data = zeroize::Zeroizing::new(serde_json::to_vec(store).map_err(|e| e.to_string())?);
+
+    let container = crypto::encrypt_file_with_master_key(
+        &master_key,
+        KeyPurpose::VaultData,
+        None,
+        "secrets.json",
+        &json_data,
+        None,
+        3,
+    )
+    .map_err(|e| e.to_string())?;
+    container
+        .save(path.to_str().unwrap())
+        .map_err(|e| e.to_string())?;
+    stamp_vault_writes(app, vault_id, state, &[secrets::SECRETS_FILE_NAME]);
+    Ok(())
+}
+
+/// Names and last-update times of the stored credentials. Values are never listed.
+#[tauri::command]
+pub fn list_secrets(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<SecretInfo>> {
+    Ok(read_secrets_store(&app, &vault_id, &state)?.list())
+}
+
+/// Reveals one credential. Counts as an export, so guest sessions are refused.
+#[tauri::command]
+pub fn get_secret(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Option<String>> {
+    state.ensure_writable(&vault_id)?;
+    secrets::validate_name(&name)?;
+    let store = read_secrets_store(&app, &vault_id, &state)?;
+    let value = store.get(&name).map(str::to_string);
+    if value.is_some() {
+        record_audit(
+            &app,
+            &vault_id,
+            &state.user_for(&vault_id),
+            "secret_read",
+            Some(name),
+        );
+    }
+    Ok(value)
+}
+
+#[tauri::command]
+pub fn set_secret(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    value: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let value = zeroize::Zeroizing::new(value);
+    let mut store = read_secrets_store(&app, &vault_id, &state)?;
+    let created = store.set(&name, &value, chrono::Utc::now().timestamp())?;
+    write_secrets_store(&app, &vault_id, &state, &store)?;
+    let action = if created {
+        "secret_create"
+    } else {
+        "secret_update"
+    };
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        action,
+        Some(name),
+    );
+    Ok(())
+}
+
+/// Returns false if no secret had that name.
+#[tauri::command]
+pub fn delete_secret(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<bool> {
+    state.ensure_writable(&vault_id)?;
+    secrets::validate_name(&name)?;
+    let mut store = read_secrets_store(&app, &vault_id, &state)?;
+    if !store.remove(&name) {
+        return Ok(false);
+    }
+    write_secrets_store(&app, &vault_id, &state, &store)?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "secret_delete",
+        Some(name),
+    );
+    Ok(true)
+}
+
+// ==========================================
+// --- PAIRED DEVICE UNLOCK (device_pairing.rs) ---
+// ==========================================
+
+/// The challenge the desktop is currently showing, with the vault it unlocks. Single
+/// use: taken (and cleared) by the first completion attempt.
+static PENDING_DEVICE_UNLOCK: std::sync::Mutex<Option<(String, device_pairing::UnlockChallenge)>> =
+    std::sync::Mutex::new(None);
+
+/// Pairs a phone with this vault. Owner only. The returned payload carries the pairing
+/// secret: the UI shows it once as a QR code for the phone and then forgets it.
+#[tauri::command]
+pub fn pair_mobile_device(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<device_pairing::PairingOffer> {
+    ensure_owner(&state, &vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let secret = device_pairing::new_pairing_secret()?;
+    let device = {
+        let guard = lock_session!(state)?;
+        let master_key = guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?;
+        keychain::add_device_slot(
+            &path,
+            master_key,
+            &name,
+            &device_pairing::slot_key(&secret),
+            &device_pairing::auth_key(&secret),
+        )
+        .map_err(|e| e.to_string())?
+    };
+    let label = tauri_plugin_os::hostname();
+    let payload = device_pairing::encode_pairing(&device_pairing::PairedDesktop {
+        vault_uuid: keychain::keychain_vault_uuid(&path).map_err(|e| e.to_string())?,
+        device_id: device.id.clone(),
+        secret: BASE32_NOPAD.encode(&*secret),
+        label: label.chars().take(64).collect(),
+    });
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "pair_device",
+        Some(name),
+    );
+    Ok(device_pairing::PairingOffer { device, payload })
+}
+
+#[tauri::command]
+pub fn list_paired_devices(
+    app: AppHandle,
+    vault_id: String,
+) -> CommandResult<Vec<keychain::DeviceSlotInfo>> {
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    keychain::list_device_slots(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_paired_device(
+    app: AppHandle,
+    vault_id: String,
+    device_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    ensure_owner(&state, &vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::remove_device_slot(&path, &device_id).map_err(|e| e.to_string())?;
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "unpair_device",
+        Some(device_id),
+    );
+    Ok(())
+}
+
+/// Desktop, locked: creates the challenge to show as a QR code. Replaces any earlier one.
+#[tauri::command]
+pub fn begin_device_unlock(app: AppHandle, vault_id: String) -> CommandResult<String> {
+    rate_limit("login", AUTH_RATE)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    if keychain::list_device_slots(&path)
+        .map_err(|e| e.to_string())?
+        .is_empty()
+    {
+        return Err(AppError::new(ErrorCode::NoPairedDevice).into());
+    }
+    let uuid = keychain::keychain_vault_uuid(&path).map_err(|e| e.to_string())?;
+    let challenge = device_pairing::new_challenge(&uuid, chrono::Utc::now().timestamp())?;
+    let payload = device_pairing::encode_challenge(&challenge);
+    *PENDING_DEVICE_UNLOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some((vault_id, challenge));
+    Ok(payload)
+}
+
+/// Desktop: unlocks with the phone's approval for the pending challenge. The session is
+/// the owner's (the owner paired the phone); the audit log names the device. Failures
+/// count towards the login lockout like a wrong password.
+#[tauri::command]
+pub fn complete_device_unlock(
+    app: AppHandle,
+    vault_id: String,
+    response: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    check_login_lockout()?;
+    let pending = PENDING_DEVICE_UNLOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    let Some((pending_vault, challenge)) = pending.filter(|(v, _)| *v == vault_id) else {
+        return Err(AppError::new(ErrorCode::NoPendingUnlock).into());
+    };
+
+    let path = resolve_keychain_path(&app, &pending_vault)?;
+    let result = device_pairing::response_device_id(&response).and_then(|device_id| {
+        let auth_key = keychain::device_auth_key(&path, &device_id).map_err(|e| e.to_string())?;
+        let slot_key = device_pairing::open_approval(
+            &response,
+            &challenge,
+            &auth_key,
+            chrono::Utc::now().timestamp(),
+        )?;
+        keychain::unlock_device_slot(&path, &device_id, &slot_key).map_err(|e| e.to_string())
+    });
+
+    match result {
+        Ok((device_name, master_key)) => {
+            LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
+            let mut guard = lock_session!(state)?;
+            guard.insert(vault_id.clone(), master_key);
+            state.set_read_only(&vault_id, false);
+            state.set_user(&vault_id, keychain::OWNER_SLOT_NAME);
+            record_audit(
+                &app,
+                &vault_id,
+                keychain::OWNER_SLOT_NAME,
+                "login_device",
+                Some(device_name),
+            );
+            Ok("Logged in".to_string())
+        }
+        Err(e) => {
+            record_login_failure();
+            record_audit(
+                &app,
+                &vault_id,
+                keychain::OWNER_SLOT_NAME,
+                "login_failed",
+                Some("device".to_string()),
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Phone: stores a desktop's pairing code in this vault's encrypted secrets store.
+#[tauri::command]
+pub fn accept_device_pairing(
+    app: AppHandle,
+    vault_id: String,
+    payload: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    state.ensure_writable(&vault_id)?;
+    let pairing = device_pairing::decode_pairing(&payload)?;
+    let value =
+        zeroize::Zeroizing::new(serde_json::to_string(&pairing).map_err(|e| e.to_string())?);
+    let name = format!(
+        "{}{}",
+        device_pairing::PAIRED_DESKTOP_PREFIX,
+        pairing.device_id.to_lowercase()
+    );
+    let mut store = read_secrets_store(&app, &vault_id, &state)?;
+    store.set(&name, &value, chrono::Utc::now().timestamp())?;
+    write_secrets_store(&app, &vault_id, &state, &store)?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "accept_pairing",
+        Some(pairing.label.clone()),
+    );
+    Ok(pairing.label)
+}
+
+/// Phone: answers a desktop's unlock challenge with one of the stored pairings. Needs
+/// this vault unlocked, so a lost phone cannot approve anything by itself.
+#[tauri::command]
+pub fn approve_device_unlock(
+    app: AppHandle,
+    vault_id: String,
+    challenge: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<String> {
+    state.ensure_writable(&vault_id)?;
+    let vault_uuid = device_pairing::decode_challenge(&challenge)?.vault_uuid;
+    let store = read_secrets_store(&app, &vault_id, &state)?;
+    let pairing = store
+        .list()
+        .into_iter()
+        .filter(|info| info.name.starts_with(device_pairing::PAIRED_DESKTOP_PREFIX))
+        .filter_map(|info| store.get(&info.name).map(str::to_string))
+        .filter_map(|json| serde_json::from_str::<device_pairing::PairedDesktop>(&json).ok())
+        .find(|p| p.vault_uuid == vault_uuid)
+        .ok_or_else(|| AppError::new(ErrorCode::DeviceNotPaired))?;
+    let response = device_pairing::approve(&pairing, &challenge)?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "approve_device_unlock",
+        Some(pairing.label),
+    );
+    Ok(response)
+}
+
+// ==========================================
+// --- OS KEYSTORE AUTO-UNLOCK (os_keystore.rs) ---
+// ==========================================
+
+/// Shown by the Windows Hello prompt.
+const AUTO_UNLOCK_REASON: &str = "Unlock your QRE Privacy Toolkit vault";
+
+#[tauri::command]
+pub fn get_auto_unlock_status(
+    app: AppHandle,
+    vault_id: String,
+) -> CommandResult<os_keystore::AutoUnlockStatus> {
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let info = if path.exists() {
+        keychain::keystore_slot_info(&path).map_err(|e| e.to_string())?
+    } else {
+        None
+    };
+    Ok(os_keystore::AutoUnlockStatus {
+        supported: os_keystore::is_supported(),
+        enabled: info.is_some(),
+        created_at: info.as_ref().map(|i| i.created_at),
+        last_used: info.and_then(|i| i.last_used),
+    })
+}
+
+/// Turns on (or renews) auto-unlock for this vault on this device. Owner only, and the
+/// current password is asked for again. A fresh secret goes into the platform keystore
+/// and the entry of any earlier slot is deleted.
+#[tauri::command]
+pub async fn enable_auto_unlock(
+    app: AppHandle,
+    vault_id: String,
+    current_password: String,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<()> {
+    ensure_owner(&state, &vault_id)?;
+    if !os_keystore::is_supported() {
+        return Err(AppError::new(ErrorCode::AutoUnlockUnavailable).into());
+    }
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let current_password = zeroize::Zeroizing::new(current_password);
+
+    tauri::async_runtime::spawn_blocking(move || -> CommandResult<()> {
+        let (account, secret) = os_keystore::new_entry().map_err(|e| e.to_string())?;
+        os_keystore::store(&account, &secret).map_err(|e| e.to_string())?;
+        match keychain::set_keystore_slot(&path, &current_password, &account, &secret) {
+            Ok(previous) => {
+                if let Some(previous) = previous {
+                    let _ = os_keystore::delete(&previous);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                let _ = os_keystore::delete(&account);
+                Err(login_error(e))
+            }
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "enable_auto_unlock",
+        None,
+    );
+    Ok(())
+}
+
+/// Turns auto-unlock off: removes the keystore slot and its keystore entry.
+#[tauri::command]
+pub fn disable_auto_unlock(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    ensure_owner(&state, &vault_id)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    if let Some(account) = keychain::remove_keystore_slot(&path).map_err(|e| e.to_string())? {
+        // The slot is gone, so a leftover entry can no longer open anything.
+        let _ = os_keystore::delete(&account);
+    }
+    record_audit(
+        &app,
+        &vault_id,
+        keychain::OWNER_SLOT_NAME,
+        "disable_auto_unlock",
+        None,
+    );
+    Ok(())
+}
+
+/// Unlocks the vault with the secret from the platform keystore, after its Windows
+/// Hello / Touch ID check. The session is the owner's. A cancelled or unavailable check
+/// is not counted towards the login lockout; a secret that does not open the slot is.
+#[tauri::command]
+pub async fn auto_unlock(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<String> {
+    check_login_lockout()?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    let account = keychain::keystore_slot_account(&path)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| AppError::new(ErrorCode::AutoUnlockDisabled))?;
+
+    // The error says whether it counts as a failed login.
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let secret =
+            os_keystore::load(&account, AUTO_UNLOCK_REASON).map_err(|e| (false, e.to_string()))?;
+        keychain::unlock_keystore_slot(&path, &secret).map_err(|e| (true, e.to_string()))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(master_key) => {
+            LOGIN_FAIL_COUNT.store(0, Ordering::SeqCst);
+            let mut guard = lock_session!(state)?;
+            guard.insert(vault_id.clone(), master_key);
+            state.set_read_only(&vault_id, false);
+            state.set_user(&vault_id, keychain::OWNER_SLOT_NAME);
+            record_audit(
+                &app,
+                &vault_id,
+                keychain::OWNER_SLOT_NAME,
+                "login_keystore",
+                None,
+            );
+            Ok("Logged in".to_string())
+        }
+        Err((counts, e)) => {
+            if counts {
+                record_login_failure();
+                record_audit(
+                    &app,
+                    &vault_id,
+                    keychain::OWNER_SLOT_NAME,
+                    "login_failed",
+                    Some("keystore".to_string()),
+                );
+            }
+            Err(e)
+        }
+    }
+}
+
+// ==========================================
+// --- ENTRY SHARING (sharing.rs) ---
+// ==========================================
+
+/// Writes the selected password/note entries to `path` as a passphrase-encrypted bundle.
+/// `kdf` picks scrypt instead of the default Argon2id when the recipient uses another tool.
+#[tauri::command]
+pub async fn share_entries(
+    app: AppHandle,
+    vault_id: String,
+    entry_ids: Vec<String>,
+    passphrase: String,
+    path: String,
+    kdf: Option<ShareKdf>,
+) -> CommandResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        state.ensure_writable(&vault_id)?;
+        let path = SafePath::new(&path, PathPolicy::write_file())?;
+
+        let passwords = read_password_vault(&app, &vault_id, &state)?;
+        let notes = read_notes_vault(&app, &vault_id, &state)?;
+        let bundle = sharing::build_bundle(
+            &passwords,
+            &notes,
+            &entry_ids,
+            chrono::Utc::now().timestamp(),
+        )?;
+        let bytes = sharing::seal_bundle(&bundle, &passphrase, kdf.unwrap_or_default())
+            .map_err(|e| e.to_string())?;
+        fs::write(&path, bytes).map_err(|e| format!("Failed to write share file: {}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Decrypts a share file and reports, per entry, whether importing it would collide
+/// with something already in this vault. Nothing is written.
+#[tauri::command]
+pub async fn preview_shared_entries(
+    app: AppHandle,
+    vault_id: String,
+    path: String,
+    passphrase: String,
+) -> CommandResult<Vec<ImportPreviewItem>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let path = SafePath::new(
+            &path,
+            PathPolicy::read_file().max_bytes(MAX_IN_MEMORY_FILE_BYTES),
+        )?;
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read share file: {}", e))?;
+        let bundle = sharing::open_bundle(&bytes, &passphrase).map_err(|e| e.to_string())?;
+
+        let passwords = read_password_vault(&app, &vault_id, &state)?;
+        let notes = read_notes_vault(&app, &vault_id, &state)?;
+        Ok(sharing::preview_import(&bundle, &passwords, &notes))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Imports a share file. `resolutions` maps incoming entry IDs to a per-entry choice;
+/// other conflicts fall back to `default_resolution` (skip when omitted).
+#[tauri::command]
+pub async fn import_shared_entries(
+    app: AppHandle,
+    vault_id: String,
+    path: String,
+    passphrase: String,
+    resolutions: Option<HashMap<String, ConflictResolution>>,
+    default_resolution: Option<ConflictResolution>,
+) -> CommandResult<ImportSummary> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        state.ensure_writable(&vault_id)?;
+        let path = SafePath::new(
+            &path,
+            PathPolicy::read_file().max_bytes(MAX_IN_MEMORY_FILE_BYTES),
+        )?;
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read share file: {}", e))?;
+        let bundle = sharing::open_bundle(&bytes, &passphrase).map_err(|e| e.to_string())?;
+
+        let mut passwords = read_password_vault(&app, &vault_id, &state)?;
+        let mut notes = read_notes_vault(&app, &vault_id, &state)?;
+        let summary = sharing::apply_import(
+            &bundle,
+            &mut passwords,
+            &mut notes,
+            &resolutions.unwrap_or_default(),
+            default_resolution.unwrap_or(ConflictResolution::Skip),
+            chrono::Utc::now().timestamp(),
+        );
+
+        if !bundle.passwords.is_empty() {
+            write_password_vault(&app, &vault_id, &state, &passwords)?;
+        }
+        if !bundle.notes.is_empty() {
+            save_notes_vault(app.clone(), vault_id.clone(), state.clone(), notes)?;
+        }
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Reads and parses another password manager's export (vault_import.rs). The key file
+/// and password are only used for KeePass databases.
+fn read_password_export(
+    path: &str,
+    format: Option<ImportFormat>,
+    password: Option<String>,
+    keyfile_path: Option<String>,
+) -> CommandResult<vault_import::ParsedImport> {
+    let policy = PathPolicy::read_file().max_bytes(MAX_IN_MEMORY_FILE_BYTES);
+    let path = SafePath::new(path, policy)?;
+    let bytes = zeroize::Zeroizing::new(
+        fs::read(&path).map_err(|e| format!("Failed to read export file: {}", e))?,
+    );
+    let keyfile = match keyfile_path.filter(|p| !p.trim().is_empty()) {
+        Some(p) => {
+            let p = SafePath::new(&p, policy)?;
+            Some(fs::read(&p).map_err(|e| format!("Failed to read key file: {}", e))?)
+        }
+        None => None,
+    };
+    let password = password.map(zeroize::Zeroizing::new);
+    vault_import::parse(
+        &bytes,
+        format,
+        password.as_deref().map(String::as_str),
+        keyfile.as_deref(),
+        chrono::Utc::now().timestamp(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Dry run of `import_passwords`: the logins found in the export, whether each collides
+/// with the vault, and the records that would be skipped. Nothing is written.
+/// `format` is detected from the content when omitted.
+#[tauri::command]
+pub async fn preview_password_import(
+    app: AppHandle,
+    vault_id: String,
+    path: String,
+    format: Option<ImportFormat>,
+    password: Option<String>,
+    keyfile_path: Option<String>,
+) -> CommandResult<PasswordImportPreview> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let parsed = read_password_export(&path, format, password, keyfile_path)?;
+        let vault = read_password_vault(&app, &vault_id, &state)?;
+        Ok(vault_import::preview(&parsed, &vault))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Imports the logins of a Chrome/Firefox CSV, Bitwarden JSON or KeePass export.
+/// `resolutions` is keyed by the IDs of the preview; other conflicts fall back to
+/// `default_resolution` (skip when omitted).
+// Tauri commands take their arguments flat from the frontend's invoke() payload.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn import_passwords(
+    app: AppHandle,
+    vault_id: String,
+    path: String,
+    format: Option<ImportFormat>,
+    password: Option<String>,
+    keyfile_path: Option<String>,
+    resolutions: Option<HashMap<String, ConflictResolution>>,
+    default_resolution: Option<ConflictResolution>,
+) -> CommandResult<ImportSummary> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        state.ensure_writable(&vault_id)?;
+        let parsed = read_password_export(&path, format, password, keyfile_path)?;
+        let mut vault = read_password_vault(&app, &vault_id, &state)?;
+        let summary = vault_import::apply(
+            &parsed,
+            &mut vault,
+            &resolutions.unwrap_or_default(),
+            default_resolution.unwrap_or(ConflictResolution::Skip),
+            chrono::Utc::now().timestamp(),
+        );
+        if summary.added + summary.overwritten > 0 {
+            write_password_vault(&app, &vault_id, &state, &vault)?;
+        }
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Writes the whole password vault to `path` as a KeePass (KDBX 4) database locked with
+/// `passphrase`, readable by KeePass and KeePassXC without QRE (see vault_export.rs).
+#[tauri::command]
+pub async fn export_password_vault(
+    app: AppHandle,
+    vault_id: String,
+    passphrase: String,
+    path: String,
+) -> CommandResult<usize> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        state.ensure_writable(&vault_id)?;
+        let path = SafePath::new(&path, PathPolicy::write_file())?;
+        let passphrase = zeroize::Zeroizing::new(passphrase);
+
+        let vault = read_password_vault(&app, &vault_id, &state)?;
+        let bytes = vault_export::export_kdbx(&vault, &passphrase).map_err(|e| e.to_string())?;
+        fs::write(&path, bytes).map_err(|e| format!("Failed to write export: {}", e))?;
+        record_audit(
+            &app,
+            &vault_id,
+            &state.user_for(&vault_id),
+            "export_passwords",
+            None,
+        );
+        Ok(vault.entries.len())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// ==========================================
+// --- PUBLIC-KEY IDENTITIES (recipient.rs) ---
+// ==========================================
+
+#[derive(serde::Serialize)]
+pub struct PublicIdentityInfo {
+    /// `qre-identity:1:...` text to hand to colleagues.
+    pub identity: String,
+    pub fingerprint: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ContactInfo {
+    pub name: String,
+    pub label: String,
+    pub fingerprint: String,
+    pub added_at: i64,
+}
+
+/// This vault's identity keypair, or `None` if it has not created one yet.
+pub(super) fn vault_identity(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<Option<recipient::Identity>> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = resolve_keychain_path(app, vault_id)?;
+    match keychain::load_identity(&path, &master_key).map_err(|e| e.to_string())? {
+        Some(secret) => recipient::Identity::from_secret_bytes(&secret)
+            .map(Some)
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// This vault's identity keypair, created on first use.
+pub(super) fn ensure_vault_identity(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<recipient::Identity> {
+    if let Some(identity) = vault_identity(app, vault_id, state)? {
+        return Ok(identity);
+    }
+
+    state.ensure_writable(vault_id)?;
+    let identity = recipient::Identity::generate().map_err(|e| e.to_string())?;
+    {
+        let guard = lock_session!(state)?;
+        let master_key = guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?;
+        keychain::set_identity(
+            &resolve_keychain_path(app, vault_id)?,
+            master_key,
+            &identity.public().to_bytes(),
+            &identity.secret_bytes().map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    record_audit(
+        app,
+        vault_id,
+        &state.user_for(vault_id),
+        "identity_create",
+        None,
+    );
+    Ok(identity)
+}
+
+/// An imported contact's public identity by contact name.
+pub(super) fn find_contact(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    name: &str,
+) -> CommandResult<recipient::PublicIdentity> {
+    let store = read_secrets_store(app, vault_id, state)?;
+    let encoded = store
+        .get(&format!("{}{}", recipient::CONTACT_PREFIX, name))
+        .ok_or_else(|| AppError::new(ErrorCode::ContactNotFound).with("name", &name))?;
+    recipient::PublicIdentity::decode(encoded)
+        .map(|(identity, _)| identity)
+        .map_err(|e| e.to_string())
+}
+
+/// The contact whose identity signed a received file, if it is one of ours.
+pub(super) fn contact_name_for(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    sender: &recipient::PublicIdentity,
+) -> Option<String> {
+    let store = read_secrets_store(app, vault_id, state).ok()?;
+    store.list().into_iter().find_map(|info| {
+        let name = info.name.strip_prefix(recipient::CONTACT_PREFIX)?;
+        let (identity, _) = recipient::PublicIdentity::decode(store.get(&info.name)?).ok()?;
+        (&identity == sender).then(|| name.to_string())
+    })
+}
+
+/// Returns the vault's public identity for sharing, creating the keypair on first use.
+/// `label` (default: this computer's name) only tells the recipient whose key it is.
+#[tauri::command]
+pub fn get_public_identity(
+    app: AppHandle,
+    vault_id: String,
+    label: Option<String>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<PublicIdentityInfo> {
+    let label = label.unwrap_or_else(tauri_plugin_os::hostname);
+    let identity = ensure_vault_identity(&app, &vault_id, &state)?;
+    Ok(PublicIdentityInfo {
+        identity: identity.public().encode(&label),
+        fingerprint: identity.public().fingerprint_text(),
+    })
+}
+
+/// Saves a colleague's public identity under `name` (lowercase, like secret names).
+/// Compare the returned fingerprint with theirs over a second channel before use.
+#[tauri::command]
+pub fn import_contact(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    identity: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<ContactInfo> {
+    state.ensure_writable(&vault_id)?;
+    let (public, label) =
+        recipient::PublicIdentity::decode(&identity).map_err(|e| e.to_string())?;
+    if vault_identity(&app, &vault_id, &state)?.is_some_and(|own| own.public() == &public) {
+        return Err(AppError::new(ErrorCode::OwnIdentity).into());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut store = read_secrets_store(&app, &vault_id, &state)?;
+    store.set(
+        &format!("{}{}", recipient::CONTACT_PREFIX, name),
+        &public.encode(&label),
+        now,
+    )?;
+    write_secrets_store(&app, &vault_id, &state, &store)?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "contact_import",
+        Some(name.clone()),
+    );
+    Ok(ContactInfo {
+        name,
+        label,
+        fingerprint: public.fingerprint_text(),
+        added_at: now,
+    })
+}
+
+#[tauri::command]
+pub fn list_contacts(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<ContactInfo>> {
+    let store = read_secrets_store(&app, &vault_id, &state)?;
+    Ok(store
+        .list()
+        .into_iter()
+        .filter_map(|info| {
+            let name = info.name.strip_prefix(recipient::CONTACT_PREFIX)?;
+            let (public, label) = recipient::PublicIdentity::decode(store.get(&info.name)?).ok()?;
+            Some(ContactInfo {
+                name: name.to_string(),
+                label,
+                fingerprint: public.fingerprint_text(),
+                added_at: info.updated_at,
+            })
+        })
+        .collect())
+}
+
+/// Returns false if no contact had that name.
+#[tauri::command]
+pub fn remove_contact(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<bool> {
+    state.ensure_writable(&vault_id)?;
+    let mut store = read_secrets_store(&app, &vault_id, &state)?;
+    if !store.remove(&format!("{}{}", recipient::CONTACT_PREFIX, name)) {
+        return Ok(false);
+    }
+    write_secrets_store(&app, &vault_id, &state, &store)?;
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "contact_remove",
+        Some(name),
+    );
+    Ok(true)
+}
+
+// ==========================================
+// --- AUTO-LOCK (auto_lock.rs) ---
+// ==========================================
+
+fn auto_lock_dir(app: &AppHandle) -> CommandResult<PathBuf> {
+    app_data_root(app)
+}
+
+/// Loads the auto-lock settings and starts the tick that locks every vault on idle,
+/// sleep or screen lock, emitting `session-locked` with the reason.
+pub fn spawn_auto_lock(app: AppHandle) {
+    let settings = auto_lock_dir(&app)
+        .ok()
+        .and_then(|dir| auto_lock::load_settings(&dir).ok())
+        .unwrap_or_default();
+    app.state::<SessionState>()
+        .auto_lock
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .settings = settings;
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(auto_lock::TICK);
+
+        let state = app.state::<SessionState>();
+        let unlocked = lock_session!(state)
+            .map(|guard| !guard.is_empty())
+            .unwrap_or(false);
+        let (reason, check_screen) = {
+            let mut clock = state.auto_lock.lock().unwrap_or_else(|p| p.into_inner());
+            let reason = clock.tick(unlocked, Instant::now(), SystemTime::now());
+            (reason, unlocked && clock.settings.lock_on_screen_lock)
+        };
+        let reason = reason.or_else(|| {
+            (check_screen && auto_lock::screen_locked()).then_some(LockReason::ScreenLocked)
+        });
+
+        if let Some(reason) = reason {
+            lock_all_with_audit(&app, &state, "auto_lock", Some(reason.as_str().to_string()));
+            let _ = app.emit("session-locked", reason);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_auto_lock_settings(state: tauri::State<SessionState>) -> AutoLockSettings {
+    state
+        .auto_lock
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .settings
+}
+
+/// Saves and applies new auto-lock settings. Refused in a guest session.
+#[tauri::command]
+pub fn set_auto_lock_settings(
+    app: AppHandle,
+    settings: AutoLockSettings,
+    state: tauri::State<SessionState>,
+) -> CommandResult<AutoLockSettings> {
+    state.ensure_writable("local")?;
+    settings.validate().map_err(|e| e.to_string())?;
+    auto_lock::save_settings(&auto_lock_dir(&app)?, &settings).map_err(|e| e.to_string())?;
+    let mut clock = state.auto_lock.lock().unwrap_or_else(|p| p.into_inner());
+    clock.settings = settings;
+    // A shorter period starts counting now rather than locking at once.
+    clock.touch(Instant::now());
+    Ok(settings)
+}
+
+/// Called by the UI on user input (throttled) to restart the idle timer.
+#[tauri::command]
+pub fn touch_session(state: tauri::State<SessionState>) {
+    state.touch();
+}
+
+// ==========================================
+// --- PANEL PASSCODE (panel_lock.rs) ---
+// ==========================================
+
+fn panel_vault_dir(app: &AppHandle, vault_id: &str) -> CommandResult<PathBuf> {
+    Ok(resolve_keychain_path(app, vault_id)?
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?
+        .to_path_buf())
+}
+
+/// Gate for commands returning panel content. Passes when the panel is not protected or
+/// `token` is fresh; otherwise fails with `PANEL_LOCKED:<panel>:` so the UI asks for the PIN.
+fn ensure_panel_access(
+    app: &AppHandle,
+    state: &SessionState,
+    vault_id: &str,
+    panel: Panel,
+    token: Option<&str>,
+) -> CommandResult<()> {
+    let config =
+        panel_lock::load_config(&panel_vault_dir(app, vault_id)?).map_err(|e| e.to_string())?;
+    let Some(rule) = config.rule_for(panel) else {
+        return Ok(());
+    };
+    let idle = Duration::from_secs(u64::from(rule.idle_minutes) * 60);
+    let fresh = token.is_some_and(|token| {
+        let mut sessions = state
+            .panel_sessions
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+        sessions.check(token, vault_id, panel, idle, std::time::Instant::now())
+    });
+    if fresh {
+        return Ok(());
+    }
+    Err(format!(
+        "PANEL_LOCKED:{}:{}",
+        panel.as_str(),
+        AppError::new(ErrorCode::PanelLocked)
+    ))
+}
+
+#[tauri::command]
+pub fn get_panel_lock_status(app: AppHandle, vault_id: String) -> CommandResult<PanelLockStatus> {
+    let config =
+        panel_lock::load_config(&panel_vault_dir(&app, &vault_id)?).map_err(|e| e.to_string())?;
+    Ok(config.status())
+}
+
+/// Sets the PIN (`pin`, optional when one is already set) and the protected panels.
+/// An empty `rules` list without a new PIN turns the feature off and forgets the PIN.
+/// Owner only, and re-checks the vault password like `set_vault_policy`.
+#[tauri::command]
+pub fn set_panel_lock(
+    app: AppHandle,
+    vault_id: String,
+    current_password: String,
+    pin: Option<String>,
+    rules: Vec<PanelRule>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<PanelLockStatus> {
+    ensure_owner(&state, &vault_id)?;
+    rate_limit("set_panel_lock", AUTH_RATE)?;
+    let path = resolve_keychain_path(&app, &vault_id)?;
+    keychain::unlock_keychain(&path, &current_password)
+        .map_err(|_| AppError::new(ErrorCode::CurrentPasswordIncorrect))?;
+
+    let dir = panel_vault_dir(&app, &vault_id)?;
+    let mut config = panel_lock::load_config(&dir).unwrap_or_default();
+    if pin.is_none() && rules.is_empty() {
+        config.clear();
+    } else {
+        config
+            .update(pin.as_deref(), rules)
+            .map_err(|e| e.to_string())?;
+    }
+    panel_lock::save_config(&dir, &config).map_err(|e| e.to_string())?;
+
+    // Tokens issued under the old settings do not carry over.
+    state
+        .panel_sessions
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .clear();
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "panel_lock_changed",
+        None,
+    );
+    Ok(config.status())
+}
+
+/// Checks the PIN and returns a token for `panel`. After `MAX_PIN_ATTEMPTS` wrong PINs
+/// the panels stay locked until the vault is locked and unlocked again.
+#[tauri::command]
+pub fn unlock_panel(
+    app: AppHandle,
+    vault_id: String,
+    panel: Panel,
+    pin: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<PanelToken> {
+    rate_limit("unlock_panel", AUTH_RATE)?;
+    if !lock_session!(state)?.contains_key(&vault_id) {
+        return Err(AppError::new(ErrorCode::VaultLocked).into());
+    }
+    if state
+        .panel_sessions
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .locked_out()
+    {
+        return Err(AppError::new(ErrorCode::PinLockedOut).into());
+    }
+    let config =
+        panel_lock::load_config(&panel_vault_dir(&app, &vault_id)?).map_err(|e| e.to_string())?;
+    let rule = config
+        .rule_for(panel)
+        .ok_or_else(|| AppError::new(ErrorCode::PanelNotProtected))?;
+
+    let verified = config.verify_pin(&pin);
+    let mut sessions = state
+        .panel_sessions
+        .lock()
+        .unwrap_or_else(|p| p.into_inner());
+    if !verified {
+        let remaining = sessions.record_failure();
+        drop(sessions);
+        record_audit(
+            &app,
+            &vault_id,
+            &state.user_for(&vault_id),
+            "panel_pin_failed",
+            Some(panel.as_str().to_string()),
+        );
+        return Err(if remaining == 0 {
+            AppError::new(ErrorCode::PinLockedOut)
+        } else {
+            AppError::new(ErrorCode::IncorrectPin).with("remaining", remaining)
+        }
+        .into());
+    }
+    let token = sessions
+        .issue(&vault_id, panel, std::time::Instant::now())
+        .map_err(|e| e.to_string())?;
+    Ok(PanelToken {
+        token,
+        panel,
+        idle_minutes: rule.idle_minutes,
+    })
+}
+
+/// Locks a panel right away (e.g. a "lock" button), without waiting for the idle timeout.
+#[tauri::command]
+pub fn lock_panel(vault_id: String, panel: Panel, state: tauri::State<SessionState>) {
+    state
+        .panel_sessions
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .revoke(&vault_id, panel);
+}
+
+// ==========================================
+// --- NOTES VAULT COMMANDS ---
+// ==========================================
+
+/// Decrypts `notes.qre` (an empty vault if it does not exist yet). Backend callers use
+/// this directly; the UI goes through `load_notes_vault` and its panel check.
+fn read_notes_vault(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<NotesVault> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join("notes.qre");
+
+    if !path.exists() {
+        return Ok(NotesVault::new());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload =
+        crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::NotesVault, None, &container)
+            .map_err(|e| e.to_string())?;
+    let vault: NotesVault = serde_json::from_slice(&payload.content)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted))?;
+    Ok(vault)
+}
+
+#[tauri::command]
+pub fn load_notes_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<NotesVault> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    read_notes_vault(&app, &vault_id, &state)
+}
+
+/// Runs a notes query over the decrypted vault: words, quoted phrases and the `tag:`,
+/// `title:`, `content:`, `before:`, `after:` and `is:pinned` filters (see note_query.rs).
+/// A blank query returns no matches.
+#[tauri::command]
+pub fn query_notes(
+    app: AppHandle,
+    vault_id: String,
+    query: String,
+    whole_words: Option<bool>,
+    state: tauri::State<SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<Vec<NoteMatch>> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    let query = zeroize::Zeroizing::new(query);
+    let query = NoteQuery::parse(&query, whole_words.unwrap_or(false))?;
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(read_notes_vault(&app, &vault_id, &state)?.query(&query))
+}
+
+/// Encrypts `vault` into `notes.qre` as is. `save_notes_vault` is the UI's entry point.
+fn write_notes_vault(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    vault: &NotesVault,
+) -> CommandResult<()> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join("notes.qre");
+    let json_data = serde_json::to_vec(vault).map_err(|e| e.to_string())?;
+
+    let container = crypto::encrypt_file_with_master_key(
+        &master_key,
+        KeyPurpose::NotesVault,
+        None,
+        "notes.json",
+        &json_data,
+        None,
+        3,
+    )
+    .map_err(|e| e.to_string())?;
+    container
+        .save(path.to_str().unwrap())
+        .map_err(|e| e.to_string())?;
+    stamp_vault_writes(app, vault_id, state, &["notes.qre"]);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn save_notes_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    mut vault: NotesVault,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    vault.validate().map_err(|e| e.to_string())?;
+    // Images and attachments no longer referenced by any note are deleted once the save
+    // succeeds.
+    let previous = read_notes_vault(&app, &vault_id, &state)?;
+    // The history of each note is owned by the backend: edited notes add a revision.
+    vault.preserve_history_from(&previous, chrono::Utc::now().timestamp());
+    write_notes_vault(&app, &vault_id, &state, &vault)?;
+
+    let kept: std::collections::HashSet<&String> =
+        vault.entries.iter().flat_map(|n| &n.image_ids).collect();
+    let keychain_path = resolve_keychain_path(&app, &vault_id)?;
+    let vault_dir = keychain_path.parent().unwrap();
+    for removed in previous
+        .entries
+        .iter()
+        .flat_map(|n| &n.image_ids)
+        .filter(|id| !kept.contains(id))
+    {
+        let _ = note_images::remove_image_files(vault_dir, removed);
+    }
+
+    let kept: HashSet<&str> = vault
+        .entries
+        .iter()
+        .flat_map(|n| &n.asset_ids)
+        .map(String::as_str)
+        .collect();
+    let dropped: Vec<&String> = previous
+        .entries
+        .iter()
+        .flat_map(|n| &n.asset_ids)
+        .filter(|id| !kept.contains(id.as_str()))
+        .collect();
+    if !dropped.is_empty() {
+        let _io = NOTE_ASSETS_IO.lock().unwrap_or_else(|p| p.into_inner());
+        if let Ok(mut assets) = read_note_assets(&app, &vault_id, &state) {
+            for id in dropped {
+                assets.remove(id);
+            }
+            let _ = write_note_assets(&app, &vault_id, &state, &assets);
+        }
+    }
+    Ok(())
+}
+
+/// Compares two versions of a note line by line. Versions are numbered from the current
+/// one: 0 is the note as it is now, 1 the version it replaced, and so on. Pass the older
+/// version as `rev_a` to read removed lines as text that was lost.
+#[tauri::command]
+pub fn diff_note_versions(
+    app: AppHandle,
+    vault_id: String,
+    id: String,
+    rev_a: usize,
+    rev_b: usize,
+    state: tauri::State<SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<NoteDiff> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    read_notes_vault(&app, &vault_id, &state)?.diff_versions(&id, rev_a, rev_b)
+}
+
+/// Sets how many earlier versions each note keeps (0 turns the history off and clears
+/// it). Longer histories are trimmed right away.
+#[tauri::command]
+pub fn set_note_history_limit(
+    app: AppHandle,
+    vault_id: String,
+    limit: usize,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let mut vault = read_notes_vault(&app, &vault_id, &state)?;
+    vault.set_history_limit(limit)?;
+    write_notes_vault(&app, &vault_id, &state, &vault)
+}
+
+// ==========================================
+// --- NOTE IMAGES (note_images.rs) ---
+// ==========================================
+
+/// Encrypts a pasted image and its thumbnail into `note_images/`. The caller adds the
+/// returned ID to the note's `image_ids` and saves the notes vault as usual.
+#[tauri::command]
+pub async fn add_note_image(
+    app: AppHandle,
+    vault_id: String,
+    data: Vec<u8>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<NoteImageInfo> {
+    state.ensure_writable(&vault_id)?;
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let vault_dir = resolve_keychain_path(&app, &vault_id)?
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let prepared = note_images::prepare_image(&data).map_err(|e| e.to_string())?;
+        let info = prepared.info;
+        fs::create_dir_all(vault_dir.join(note_images::IMAGES_DIR_NAME))
+            .map_err(|e| e.to_string())?;
+
+        // PNG and JPEG are already compressed: use the fastest zstd level.
+        for (thumbnail, name, bytes) in [
+            (false, "image", &data),
+            (true, "thumbnail.png", &prepared.thumbnail_png),
+        ] {
+            let path = note_images::image_path(&vault_dir, &info.id, thumbnail)
+                .map_err(|e| e.to_string())?;
+            let container = crypto::encrypt_file_with_master_key(
+                &master_key,
+                KeyPurpose::NotesVault,
+                None,
+                name,
+                bytes,
+                None,
+                1,
+            )
+            .map_err(|e| e.to_string())?;
+            container
+                .save(path.to_str().unwrap())
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(info)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Decrypts an embedded image, or its PNG thumbnail with `thumbnail = true`.
+#[tauri::command]
+pub async fn get_note_image(
+    app: AppHandle,
+    vault_id: String,
+    image_id: String,
+    thumbnail: bool,
+    state: tauri::State<'_, SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<Vec<u8>> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let vault_dir = resolve_keychain_path(&app, &vault_id)?
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path =
+        note_images::image_path(&vault_dir, &image_id, thumbnail).map_err(|e| e.to_string())?;
+    if !path.exists() {
+        return Err(AppError::new(ErrorCode::ImageNotFound)
+            .with("id", &image_id)
+            .into());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let container = crypto::EncryptedFileContainer::load(path.to_str().unwrap())
+            .map_err(|e| e.to_string())?;
+        let payload = crypto::decrypt_file_with_master_key(
+            &master_key,
+            KeyPurpose::NotesVault,
+            None,
+            &container,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(payload.content.clone())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Deletes an image that was added but never saved into a note (e.g. paste cancelled).
+/// Images removed from saved notes are cleaned up by `save_notes_vault`.
+#[tauri::command]
+pub fn delete_note_image(
+    app: AppHandle,
+    vault_id: String,
+    image_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let notes = read_notes_vault(&app, &vault_id, &state)?;
+    if notes
+        .entries
+        .iter()
+        .any(|n| n.image_ids.contains(&image_id))
+    {
+        return Err(AppError::new(ErrorCode::ImageInUse).into());
+    }
+    let vault_dir = resolve_keychain_path(&app, &vault_id)?;
+    note_images::remove_image_files(vault_dir.parent().unwrap(), &image_id)
+        .map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- NOTE ATTACHMENTS (note_assets.rs) ---
+// ==========================================
+
+/// Serializes changes to `notes_assets.qre`: each one rewrites the whole bundle, so two
+/// running at once would lose one of them.
+static NOTE_ASSETS_IO: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn note_assets_path(app: &AppHandle, vault_id: &str) -> CommandResult<PathBuf> {
+    Ok(resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join(note_assets::ASSETS_FILE_NAME))
+}
+
+/// Decrypts `notes_assets.qre` (an empty bundle if it does not exist yet).
+fn read_note_assets(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<NoteAssetBundle> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = note_assets_path(app, vault_id)?;
+    if !path.exists() {
+        return Ok(NoteAssetBundle::default());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload =
+        crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::NotesVault, None, &container)
+            .map_err(|e| e.to_string())?;
+    NoteAssetBundle::from_bytes(&payload.content).map_err(|e| e.to_string())
+}
+
+/// Encrypts the bundle over `notes_assets.qre`, or removes the file once it is empty.
+fn write_note_assets(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    assets: &NoteAssetBundle,
+) -> CommandResult<()> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = note_assets_path(app, vault_id)?;
+    if assets.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+            stamp_vault_writes(app, vault_id, state, &[note_assets::ASSETS_FILE_NAME]);
+        }
+        return Ok(());
+    }
+    let bytes = zeroize::Zeroizing::new(assets.to_bytes().map_err(|e| e.to_string())?);
+
+    // Mostly images and PDFs, which are already compressed: fastest zstd level.
+    let container = crypto::encrypt_file_with_master_key(
+        &master_key,
+        KeyPurpose::NotesVault,
+        None,
+        "notes_assets.bin",
+        &bytes,
+        None,
+        1,
+    )
+    .map_err(|e| e.to_string())?;
+    container
+        .save(path.to_str().unwrap())
+        .map_err(|e| e.to_string())?;
+    stamp_vault_writes(app, vault_id, state, &[note_assets::ASSETS_FILE_NAME]);
+    Ok(())
+}
+
+/// Encrypts a file into `notes_assets.qre`. The caller adds the returned ID to the note's
+/// `asset_ids`, references it from the markdown as `qre-asset:<id>` and saves the notes
+/// vault as usual.
+#[tauri::command]
+pub async fn add_note_asset(
+    app: AppHandle,
+    vault_id: String,
+    name: String,
+    data: Vec<u8>,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<NoteAssetInfo> {
+    state.ensure_writable(&vault_id)?;
+    let data = zeroize::Zeroizing::new(data);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let _io = NOTE_ASSETS_IO.lock().unwrap_or_else(|p| p.into_inner());
+        let mut assets = read_note_assets(&app, &vault_id, &state)?;
+        let info = assets
+            .add(&name, &data, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?;
+        write_note_assets(&app, &vault_id, &state, &assets)?;
+        Ok(info)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Every stored attachment, whether or not a note lists it yet.
+#[tauri::command]
+pub async fn list_note_assets(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<'_, SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<Vec<NoteAssetInfo>> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        Ok(read_note_assets(&app, &vault_id, &state)?.list())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Decrypts an attachment and returns it base64-encoded, for inline images and previews.
+#[tauri::command]
+pub async fn get_note_asset(
+    app: AppHandle,
+    vault_id: String,
+    asset_id: String,
+    state: tauri::State<'_, SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<NoteAssetContent> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let assets = read_note_assets(&app, &vault_id, &state)?;
+        let asset = assets
+            .get(&asset_id)
+            .ok_or_else(|| AppError::new(ErrorCode::AttachmentNotFound).with("id", &asset_id))?;
+        Ok(asset.content())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Decrypts an attachment to a temporary file, for opening PDFs and other documents in
+/// their own app. Returns the path. The copies are shredded when the vaults lock.
+#[tauri::command]
+pub async fn get_note_asset_file(
+    app: AppHandle,
+    vault_id: String,
+    asset_id: String,
+    state: tauri::State<'_, SessionState>,
+    panel_token: Option<String>,
+) -> CommandResult<String> {
+    // A plaintext copy on disk counts as an export.
+    state.ensure_writable(&vault_id)?;
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Notes,
+        panel_token.as_deref(),
+    )?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        let assets = read_note_assets(&app, &vault_id, &state)?;
+        let asset = assets
+            .get(&asset_id)
+            .ok_or_else(|| AppError::new(ErrorCode::AttachmentNotFound).with("id", &asset_id))?;
+        let path = note_assets::temp_copy_path(&asset.info);
+        fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        fs::write(&path, &asset.data).map_err(|e| e.to_string())?;
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Deletes an attachment that was added but never saved into a note (e.g. upload
+/// cancelled). Attachments removed from saved notes are cleaned up by `save_notes_vault`.
+#[tauri::command]
+pub fn delete_note_asset(
+    app: AppHandle,
+    vault_id: String,
+    asset_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let notes = read_notes_vault(&app, &vault_id, &state)?;
+    if notes
+        .entries
+        .iter()
+        .any(|n| n.asset_ids.contains(&asset_id))
+    {
+        return Err(AppError::new(ErrorCode::AttachmentInUse).into());
+    }
+    let _io = NOTE_ASSETS_IO.lock().unwrap_or_else(|p| p.into_inner());
+    let mut assets = read_note_assets(&app, &vault_id, &state)?;
+    if !assets.remove(&asset_id) {
+        return Err(AppError::new(ErrorCode::AttachmentNotFound)
+            .with("id", &asset_id)
+            .into());
+    }
+    write_note_assets(&app, &vault_id, &state, &assets)
+}
+
+/// Deletes every attachment no note lists, such as uploads left over from an edit
+/// that was never saved. Returns the IDs removed.
+#[tauri::command]
+pub async fn collect_note_assets(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<Vec<String>> {
+    state.ensure_writable(&vault_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SessionState>();
+        // Fails (and aborts) if the notes cannot be read: otherwise every attachment
+        // would look orphaned.
+        let notes = read_notes_vault(&app, &vault_id, &state)?;
+        let referenced: HashSet<&str> = notes
+            .entries
+            .iter()
+            .flat_map(|n| &n.asset_ids)
+            .map(String::as_str)
+            .collect();
+        let _io = NOTE_ASSETS_IO.lock().unwrap_or_else(|p| p.into_inner());
+        let mut assets = read_note_assets(&app, &vault_id, &state)?;
+        let removed = assets.retain_referenced(&referenced);
+        if !removed.is_empty() {
+            write_note_assets(&app, &vault_id, &state, &assets)?;
+        }
+        Ok(removed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// ==========================================
+// --- BOOKMARKS COMMANDS ---
+// ==========================================
+
+/// Decrypts `bookmarks.qre` (an empty vault if it does not exist yet).
+fn read_bookmarks_vault(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<BookmarksVault> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    let path = resolve_keychain_path(app, vault_id)?
+        .parent()
+        .unwrap()
+        .join("bookmarks.qre");
+    if !path.exists() {
+        return Ok(BookmarksVault::new());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload =
+        crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::VaultData, None, &container)
+            .map_err(|e| e.to_string())?;
+    let vault: BookmarksVault = serde_json::from_slice(&payload.content)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted))?;
+    Ok(vault)
+}
+
+#[tauri::command]
+pub fn load_bookmarks_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<BookmarksVault> {
+    read_bookmarks_vault(&app, &vault_id, &state)
+}
+
+#[tauri::command]
+pub fn save_bookmarks_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    vault: BookmarksVault,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    vault.validate().map_err(|e| e.to_string())?;
+
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
 
     let path = resolve_keychain_path(&app, &vault_id)?
         .parent()
@@ -485,6 +3642,7 @@ pub fn save_bookmarks_vault(
 
     let container = crypto::encrypt_file_with_master_key(
         &master_key,
+        KeyPurpose::VaultData,
         None,
         "bookmarks.json",
         &json_data,
@@ -495,18 +3653,28 @@ pub fn save_bookmarks_vault(
     container
         .save(path.to_str().unwrap())
         .map_err(|e| e.to_string())?;
+    stamp_vault_writes(&app, &vault_id, &state, &["bookmarks.qre"]);
     Ok(())
 }
 
+/// Imports Chromium bookmarks into the local vault. Tracking parameters and redirector
+/// wrappers are removed from the URLs unless `clean_links` is `false`.
 #[tauri::command]
 pub fn import_browser_bookmarks(
     app: AppHandle,
     state: tauri::State<SessionState>,
+    clean_links: Option<bool>,
 ) -> CommandResult<usize> {
-    let new_bookmarks = crate::bookmarks::import_chrome_bookmarks()?;
+    state.ensure_writable("local")?;
+    let mut new_bookmarks = crate::bookmarks::import_chrome_bookmarks()?;
+    if clean_links.unwrap_or(true) {
+        for bookmark in &mut new_bookmarks {
+            bookmark.url = url_cleaner::clean_url(&bookmark.url).cleaned;
+        }
+    }
     let count = new_bookmarks.len();
     if count == 0 {
-        return Err("No bookmarks found.".to_string());
+        return Err(AppError::new(ErrorCode::NoBookmarks).into());
     }
 
     let vault_id = "local".to_string(); // Import only makes sense locally
@@ -521,33 +3689,82 @@ pub fn import_browser_bookmarks(
 // --- CLIPBOARD COMMANDS ---
 // ==========================================
 
-#[tauri::command]
-pub fn load_clipboard_vault(
-    app: AppHandle,
-    vault_id: String,
-    state: tauri::State<SessionState>,
-    retention_hours: u64,
-) -> CommandResult<ClipboardVault> {
-    let master_key = {
-        let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
-    };
+// Adds go to the append-only journal (see clipboard_store.rs); the snapshot is only
+// rewritten on compaction. Every journal/snapshot access holds this lock so the
+// monitoring loop and manual adds cannot interleave a compaction with an append.
+static CLIPBOARD_IO: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
-    let path = resolve_keychain_path(&app, &vault_id)?
+fn clipboard_paths(app: &AppHandle, vault_id: &str) -> CommandResult<(PathBuf, PathBuf)> {
+    let dir = resolve_keychain_path(app, vault_id)?
         .parent()
-        .unwrap()
-        .join("clipboard.qre");
-    if !path.exists() {
-        return Ok(ClipboardVault::new());
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?
+        .to_path_buf();
+    Ok((
+        dir.join("clipboard.qre"),
+        dir.join(clipboard_store::JOURNAL_FILE_NAME),
+    ))
+}
+
+/// Snapshot plus replayed journal. Returns the vault and the number of journal records.
+fn read_clipboard_state(
+    master_key: &keychain::MasterKey,
+    snapshot: &std::path::Path,
+    journal: &std::path::Path,
+) -> CommandResult<(ClipboardVault, usize)> {
+    let mut vault = if snapshot.exists() {
+        let container = crypto::EncryptedFileContainer::load(snapshot.to_str().unwrap())
+            .map_err(|e| e.to_string())?;
+        let payload = crypto::decrypt_file_with_master_key(
+            master_key,
+            KeyPurpose::VaultData,
+            None,
+            &container,
+        )
+        .map_err(|e| e.to_string())?;
+        serde_json::from_slice(&payload.content)
+            .map_err(|_| AppError::new(ErrorCode::DataCorrupted))?
+    } else {
+        ClipboardVault::new()
+    };
+
+    let ops = clipboard_store::read_journal(journal, master_key).map_err(|e| e.to_string())?;
+    for op in &ops {
+        vault.apply(op);
     }
+    Ok((vault, ops.len()))
+}
 
-    let container =
-        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
-    let payload = crypto::decrypt_file_with_master_key(&master_key, None, &container)
+/// Writes the full snapshot, then drops the journal it now contains.
+/// The snapshot goes first: if we crash in between, replaying the journal is idempotent.
+fn compact_clipboard(
+    master_key: &keychain::MasterKey,
+    snapshot: &std::path::Path,
+    journal: &std::path::Path,
+    vault: &ClipboardVault,
+) -> CommandResult<()> {
+    let json_data = serde_json::to_vec(vault).map_err(|e| e.to_string())?;
+    let container = crypto::encrypt_file_with_master_key(
+        master_key,
+        KeyPurpose::VaultData,
+        None,
+        "clipboard.json",
+        &json_data,
+        None,
+        3,
+    )
+    .map_err(|e| e.to_string())?;
+    container
+        .save(snapshot.to_str().unwrap())
         .map_err(|e| e.to_string())?;
-    let mut vault: ClipboardVault = serde_json::from_slice(&payload.content)
-        .map_err(|_| "Failed to parse clipboard data".to_string())?;
+    if journal.exists() {
+        fs::remove_file(journal)
+            .map_err(|e| format!("Failed to reset clipboard journal: {}", e))?;
+    }
+    Ok(())
+}
 
+/// Drops entries older than the retention window. Returns true if anything was removed.
+fn prune_expired_clipboard(vault: &mut ClipboardVault, retention_hours: u64) -> bool {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -565,25 +3782,49 @@ pub fn load_clipboard_vault(
         (now_sec - entry_time_sec) < (ttl_seconds as i64)
     });
 
-    if vault.entries.len() != initial_count {
-        let json_data = serde_json::to_vec(&vault).map_err(|e| e.to_string())?;
-        let container = crypto::encrypt_file_with_master_key(
-            &master_key,
-            None,
-            "clipboard.json",
-            &json_data,
-            None,
-            3,
-        )
-        .map_err(|e| e.to_string())?;
-        container
-            .save(path.to_str().unwrap())
-            .map_err(|e| e.to_string())?;
+    vault.entries.len() != initial_count
+}
+
+#[tauri::command]
+pub fn load_clipboard_vault(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    retention_hours: u64,
+    panel_token: Option<String>,
+) -> CommandResult<ClipboardVault> {
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Clipboard,
+        panel_token.as_deref(),
+    )?;
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    let (snapshot, journal) = clipboard_paths(&app, &vault_id)?;
+    let _io = CLIPBOARD_IO.lock().unwrap_or_else(|p| p.into_inner());
+    let (mut vault, records) = read_clipboard_state(&master_key, &snapshot, &journal)?;
+
+    // Expired entries are purged from disk right away (not left in the journal until
+    // the next compaction), matching the retention promise. Guest sessions only filter.
+    let pruned = prune_expired_clipboard(&mut vault, retention_hours);
+    if (pruned || records >= clipboard_store::COMPACT_AFTER_RECORDS)
+        && !state.is_read_only(&vault_id)
+    {
+        compact_clipboard(&master_key, &snapshot, &journal, &vault)?;
     }
 
     Ok(vault)
 }
 
+/// Full save from the UI (delete, pin, clear). Doubles as a compaction.
 #[tauri::command]
 pub fn save_clipboard_vault(
     app: AppHandle,
@@ -591,23 +3832,435 @@ pub fn save_clipboard_vault(
     state: tauri::State<SessionState>,
     vault: ClipboardVault,
 ) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
     vault.validate().map_err(|e| e.to_string())?;
 
     let master_key = {
         let guard = lock_session!(state)?;
-        guard.get(&vault_id).ok_or("Vault is locked")?.clone()
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
     };
 
-    let path = resolve_keychain_path(&app, &vault_id)?
+    let (snapshot, journal) = clipboard_paths(&app, &vault_id)?;
+    let _io = CLIPBOARD_IO.lock().unwrap_or_else(|p| p.into_inner());
+    compact_clipboard(&master_key, &snapshot, &journal, &vault)
+}
+
+/// Used by both the clipboard monitor and manual adds. Appends a single journal record
+/// instead of rewriting the snapshot; compacts once the journal is long enough.
+/// With `clean_links`, a copied link is stored without tracking parameters/redirectors.
+#[tauri::command]
+pub fn add_clipboard_entry(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    text: String,
+    retention_hours: u64,
+    clean_links: Option<bool>,
+) -> CommandResult<()> {
+    state.ensure_writable(&vault_id)?;
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    // Only a copied link on its own is rewritten; links inside longer text are left to
+    // the explicit "strip tracking" transform.
+    let text = match clean_links {
+        Some(true) if url_cleaner::is_single_link(&text) => url_cleaner::clean_url(&text).cleaned,
+        _ => text,
+    };
+    let entry = clipboard_store::create_entry(&text);
+    let (snapshot, journal) = clipboard_paths(&app, &vault_id)?;
+    let _io = CLIPBOARD_IO.lock().unwrap_or_else(|p| p.into_inner());
+    let records =
+        clipboard_store::append_journal(&journal, &master_key, &[JournalOp::Add { entry }])
+            .map_err(|e| e.to_string())?;
+
+    if records >= clipboard_store::COMPACT_AFTER_RECORDS {
+        let (mut vault, _) = read_clipboard_state(&master_key, &snapshot, &journal)?;
+        prune_expired_clipboard(&mut vault, retention_hours);
+        compact_clipboard(&master_key, &snapshot, &journal, &vault)?;
+    }
+    Ok(())
+}
+
+/// Applies a clean-up (trim, strip tracking, plain text, Base64) to a history entry,
+/// stores the result in place of the original and returns it for restoring.
+#[tauri::command]
+pub fn transform_clipboard_entry(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<SessionState>,
+    id: String,
+    op: clipboard_store::ClipboardTransform,
+    panel_token: Option<String>,
+) -> CommandResult<clipboard_store::ClipboardEntry> {
+    state.ensure_writable(&vault_id)?;
+    ensure_panel_access(
+        &app,
+        &state,
+        &vault_id,
+        Panel::Clipboard,
+        panel_token.as_deref(),
+    )?;
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+
+    let (snapshot, journal) = clipboard_paths(&app, &vault_id)?;
+    let _io = CLIPBOARD_IO.lock().unwrap_or_else(|p| p.into_inner());
+    let (mut vault, _) = read_clipboard_state(&master_key, &snapshot, &journal)?;
+    let entry = vault
+        .entries
+        .iter_mut()
+        .find(|e| e.id == id)
+        .ok_or_else(|| AppError::new(ErrorCode::ClipboardEntryNotFound))?;
+
+    let text = clipboard_store::transform_text(&entry.content, op)?;
+    *entry = entry.with_content(&text);
+    let updated = entry.clone();
+    compact_clipboard(&master_key, &snapshot, &journal, &vault)?;
+    Ok(updated)
+}
+
+/// Generates the current 2FA code of a password entry, or of `secret` for an entry that is
+/// still being edited. The key may be Base32 or an `otpauth://` link (see totp.rs).
+/// Returns the code and the number of seconds remaining until it expires.
+#[tauri::command]
+pub fn generate_totp_code(
+    app: AppHandle,
+    vault_id: Option<String>,
+    entry_id: Option<String>,
+    secret: Option<String>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<(String, u64)> {
+    let secret = match (entry_id, secret) {
+        (Some(entry_id), _) => {
+            let vault_id = vault_id.unwrap_or_else(|| "local".to_string());
+            let vault = read_password_vault(&app, &vault_id, &state)?;
+            let entry = vault
+                .entries
+                .iter()
+                .find(|e| e.id == entry_id)
+                .ok_or_else(|| AppError::new(ErrorCode::EntryNotFound).with("id", &entry_id))?;
+            zeroize::Zeroizing::new(
+                entry
+                    .totp_secret
+                    .clone()
+                    .filter(|s| !s.trim().is_empty())
+                    .ok_or_else(|| AppError::new(ErrorCode::TotpKeyMissing))?,
+            )
+        }
+        (None, Some(secret)) => zeroize::Zeroizing::new(secret),
+        (None, None) => return Err(AppError::new(ErrorCode::TotpKeyNotProvided).into()),
+    };
+    let config = totp::parse(&secret).map_err(|e| e.to_string())?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    Ok(config.code_at(now))
+}
+
+/// Reads the issuer, account and code settings from a pasted `otpauth://` link (the
+/// content of a 2FA setup QR code), so the UI can fill in the entry.
+#[tauri::command]
+pub fn parse_otpauth_uri(uri: String) -> CommandResult<TotpInfo> {
+    let uri = zeroize::Zeroizing::new(uri);
+    if !uri
+        .trim()
+        .get(..10)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("otpauth://"))
+    {
+        return Err(AppError::new(ErrorCode::NotOtpauthLink).into());
+    }
+    Ok(totp::parse(&uri).map_err(|e| e.to_string())?.info())
+}
+
+// ==========================================
+// --- VAULT SEARCH (search.rs) ---
+// ==========================================
+
+/// Searches the notes, passwords, bookmarks and clipboard history of an unlocked vault.
+/// PIN-protected panels are searched only when one of `panel_tokens` is fresh for them,
+/// and are listed in `locked_panels` otherwise. Clipboard entries older than
+/// `clipboard_retention_hours` are left out, as `load_clipboard_vault` would drop them.
+#[tauri::command]
+pub fn search_vaults(
+    app: AppHandle,
+    vault_id: String,
+    query: String,
+    state: tauri::State<SessionState>,
+    panel_tokens: Option<Vec<String>>,
+    clipboard_retention_hours: Option<u64>,
+) -> CommandResult<search::SearchResults> {
+    let query = zeroize::Zeroizing::new(query);
+    if search::parse_query(&query).is_empty() {
+        return Ok(search::SearchResults::default());
+    }
+    let tokens = panel_tokens.unwrap_or_default();
+    let panel_open = |panel: Panel| -> CommandResult<bool> {
+        let locked = match ensure_panel_access(&app, &state, &vault_id, panel, None) {
+            Ok(()) => return Ok(true),
+            Err(e) => e,
+        };
+        if !locked.starts_with("PANEL_LOCKED:") {
+            return Err(locked);
+        }
+        Ok(tokens
+            .iter()
+            .any(|token| ensure_panel_access(&app, &state, &vault_id, panel, Some(token)).is_ok()))
+    };
+    let mut locked_panels = Vec::new();
+
+    let passwords = read_password_vault(&app, &vault_id, &state)?;
+    let bookmarks = read_bookmarks_vault(&app, &vault_id, &state)?;
+    let notes = if panel_open(Panel::Notes)? {
+        read_notes_vault(&app, &vault_id, &state)?
+    } else {
+        locked_panels.push(Panel::Notes.as_str());
+        NotesVault::new()
+    };
+    let clipboard = if panel_open(Panel::Clipboard)? {
+        let master_key = {
+            let guard = lock_session!(state)?;
+            guard
+                .get(&vault_id)
+                .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+                .clone()
+        };
+        let (snapshot, journal) = clipboard_paths(&app, &vault_id)?;
+        let _io = CLIPBOARD_IO.lock().unwrap_or_else(|p| p.into_inner());
+        let (mut vault, _) = read_clipboard_state(&master_key, &snapshot, &journal)?;
+        if let Some(hours) = clipboard_retention_hours {
+            prune_expired_clipboard(&mut vault, hours);
+        }
+        vault
+    } else {
+        locked_panels.push(Panel::Clipboard.as_str());
+        ClipboardVault::new()
+    };
+
+    let field = |name, text, weight| SearchField { name, text, weight };
+    let mut docs = Vec::new();
+
+    for note in &notes.entries {
+        let mut fields = vec![
+            field("title", note.title.as_str(), WEIGHT_TITLE),
+            field("content", note.content.as_str(), WEIGHT_BODY),
+        ];
+        fields.extend(
+            note.tags
+                .iter()
+                .map(|t| field("tags", t.as_str(), WEIGHT_LABEL)),
+        );
+        docs.push(SearchDoc {
+            kind: SearchKind::Note,
+            id: &note.id,
+            title: &note.title,
+            fields,
+            preview: None,
+        });
+    }
+    // The password, TOTP secret and hidden custom fields are never searched.
+    for entry in &passwords.entries {
+        let mut fields = vec![
+            field("service", entry.service.as_str(), WEIGHT_TITLE),
+            field("username", entry.username.as_str(), WEIGHT_LABEL),
+            field("url", entry.url.as_str(), WEIGHT_LABEL),
+            field("notes", entry.notes.as_str(), WEIGHT_BODY),
+        ];
+        for custom in entry.custom_fields.iter().filter(|f| !f.hidden) {
+            fields.push(field("custom_field", custom.name.as_str(), WEIGHT_BODY));
+            fields.push(field("custom_field", custom.value.as_str(), WEIGHT_BODY));
+        }
+        docs.push(SearchDoc {
+            kind: SearchKind::Password,
+            id: &entry.id,
+            title: &entry.service,
+            fields,
+            preview: None,
+        });
+    }
+    for bookmark in &bookmarks.entries {
+        docs.push(SearchDoc {
+            kind: SearchKind::Bookmark,
+            id: &bookmark.id,
+            title: &bookmark.title,
+            fields: vec![
+                field("title", bookmark.title.as_str(), WEIGHT_TITLE),
+                field("url", bookmark.url.as_str(), WEIGHT_LABEL),
+                field("category", bookmark.category.as_str(), WEIGHT_LABEL),
+            ],
+            preview: None,
+        });
+    }
+    // Clipboard entries have no title; hits carry the redacted preview, never the content.
+    for clip in &clipboard.entries {
+        docs.push(SearchDoc {
+            kind: SearchKind::Clipboard,
+            id: &clip.id,
+            title: &clip.category,
+            fields: vec![
+                field("content", clip.content.as_str(), WEIGHT_BODY),
+                field("category", clip.category.as_str(), WEIGHT_LABEL),
+            ],
+            preview: Some(clip.preview.as_str()),
+        });
+    }
+
+    let mut results = search::search(&query, &docs);
+    results.locked_panels = locked_panels;
+    Ok(results)
+}
+
+// ==========================================
+// --- CLIPBOARD PATTERN PACKS (pattern_packs.rs) ---
+// ==========================================
+
+// Packs and their on/off settings are app-wide, next to the keychain like the auto-lock
+// settings.
+fn pattern_packs_dir(app: &AppHandle) -> CommandResult<PathBuf> {
+    app_data_root(app)
+}
+
+/// Loads the user's pattern packs at startup. Packs that fail to load are skipped.
+pub fn load_pattern_packs(app: &AppHandle) {
+    if let Ok(dir) = pattern_packs_dir(app) {
+        for problem in pattern_packs::reload(&dir) {
+            tracing::warn!("Pattern pack skipped: {}", problem);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_pattern_packs() -> Vec<PackInfo> {
+    pattern_packs::list()
+}
+
+/// Re-reads the packs folder, for packs copied there by hand. Returns the files that
+/// could not be loaded, as "file name: reason".
+#[tauri::command]
+pub fn reload_pattern_packs(app: AppHandle) -> CommandResult<Vec<String>> {
+    Ok(pattern_packs::reload(&pattern_packs_dir(&app)?))
+}
+
+/// Turns packs on and off; every pack not listed in `enabled` is switched off.
+#[tauri::command]
+pub fn set_enabled_pattern_packs(
+    app: AppHandle,
+    enabled: Vec<String>,
+    state: tauri::State<SessionState>,
+) -> CommandResult<Vec<PackInfo>> {
+    state.ensure_writable("local")?;
+    pattern_packs::set_enabled(&pattern_packs_dir(&app)?, &enabled).map_err(|e| e.to_string())
+}
+
+/// Validates a pack file and installs it; a newer version of the same pack replaces it.
+#[tauri::command]
+pub fn import_pattern_pack(
+    app: AppHandle,
+    path: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<PackInfo> {
+    state.ensure_writable("local")?;
+    let path = SafePath::new(&path, PathPolicy::read_file())?;
+    pattern_packs::import(&pattern_packs_dir(&app)?, &path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_pattern_pack(
+    app: AppHandle,
+    id: String,
+    state: tauri::State<SessionState>,
+) -> CommandResult<()> {
+    state.ensure_writable("local")?;
+    pattern_packs::remove(&pattern_packs_dir(&app)?, &id).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- LAST WRITER & VAULT STATS (vault_meta.rs) ---
+// ==========================================
+
+/// Serializes the read-modify-write of `vault_meta.qre` between concurrent saves.
+static VAULT_META_IO: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Containers stamped by their save commands and listed by `get_vault_stats`. The
+/// clipboard is left out: the app rewrites it on its own (journal folding, retention),
+/// and it is a local history rather than something synced between machines.
+const STAMPED_CONTAINERS: [&str; 5] = [
+    "passwords.qre",
+    "notes.qre",
+    note_assets::ASSETS_FILE_NAME,
+    "bookmarks.qre",
+    secrets::SECRETS_FILE_NAME,
+];
+
+fn vault_meta_path(app: &AppHandle, vault_id: &str) -> CommandResult<PathBuf> {
+    Ok(resolve_keychain_path(app, vault_id)?
         .parent()
         .unwrap()
-        .join("clipboard.qre");
-    let json_data = serde_json::to_vec(&vault).map_err(|e| e.to_string())?;
+        .join(vault_meta::META_FILE_NAME))
+}
+
+fn read_vault_meta(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<VaultMeta> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = vault_meta_path(app, vault_id)?;
+    if !path.exists() {
+        return Ok(VaultMeta::default());
+    }
+
+    let container =
+        crypto::EncryptedFileContainer::load(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+    let payload =
+        crypto::decrypt_file_with_master_key(&master_key, KeyPurpose::VaultData, None, &container)
+            .map_err(|e| e.to_string())?;
+    serde_json::from_slice(&payload.content)
+        .map_err(|_| AppError::new(ErrorCode::DataCorrupted).into())
+}
+
+fn write_vault_meta(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+    meta: &VaultMeta,
+) -> CommandResult<()> {
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    let path = vault_meta_path(app, vault_id)?;
+    let json_data = serde_json::to_vec(meta).map_err(|e| e.to_string())?;
 
     let container = crypto::encrypt_file_with_master_key(
         &master_key,
+        KeyPurpose::VaultData,
         None,
-        "clipboard.json",
+        "vault_meta.json",
         &json_data,
         None,
         3,
@@ -615,71 +4268,260 @@ pub fn save_clipboard_vault(
     .map_err(|e| e.to_string())?;
     container
         .save(path.to_str().unwrap())
-        .map_err(|e| e.to_string())?;
-    Ok(())
+        .map_err(|e| e.to_string())
+}
+
+/// A stamp for a write made now by this machine.
+fn this_device(app: &AppHandle) -> CommandResult<WriteStamp> {
+    Ok(WriteStamp {
+        device_id: vault_meta::device_id(&app_data_root(app)?).map_err(|e| e.to_string())?,
+        device_name: tauri_plugin_os::hostname(),
+        app_version: formats::APP_VERSION.to_string(),
+        written_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Records this device as the last writer of `containers`, after they were saved. A
+/// failure is logged, not returned: the save itself went through.
+fn stamp_vault_writes(app: &AppHandle, vault_id: &str, state: &SessionState, containers: &[&str]) {
+    if containers.is_empty() {
+        return;
+    }
+    let stamp = || -> CommandResult<()> {
+        let device = this_device(app)?;
+        let _io = VAULT_META_IO.lock().unwrap_or_else(|p| p.into_inner());
+        let mut meta = read_vault_meta(app, vault_id, state)?;
+        for container in containers {
+            meta.record(container, device.clone());
+        }
+        write_vault_meta(app, vault_id, state, &meta)
+    };
+    if let Err(e) = stamp() {
+        tracing::warn!(
+            "Could not record the last writer of {:?}: {}",
+            containers,
+            e
+        );
+    }
 }
 
+/// Size, modification time and last writer (device, app version, time) of each vault
+/// container, and the vault's most recent write overall. Containers whose file changed
+/// after their last stamp are flagged, which is what a sync conflict looks like.
 #[tauri::command]
-pub fn add_clipboard_entry(
+pub fn get_vault_stats(
     app: AppHandle,
     vault_id: String,
     state: tauri::State<SessionState>,
-    text: String,
-    retention_hours: u64,
-) -> CommandResult<()> {
-    let entry = crate::clipboard_store::create_entry(&text);
-    let mut vault = load_clipboard_vault(
-        app.clone(),
-        vault_id.clone(),
-        state.clone(),
-        retention_hours,
-    )?;
-    vault.add_entry(entry).map_err(|e| e.to_string())?;
-    save_clipboard_vault(app, vault_id, state, vault)?;
-    Ok(())
+) -> CommandResult<VaultStats> {
+    let meta = read_vault_meta(&app, &vault_id, &state)?;
+    let vault_dir = resolve_keychain_path(&app, &vault_id)?
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let files: Vec<(&str, Option<(u64, i64)>)> = STAMPED_CONTAINERS
+        .iter()
+        .map(|&name| {
+            let file = fs::metadata(vault_dir.join(name)).ok().map(|m| {
+                let modified = m
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_secs() as i64);
+                (m.len(), modified)
+            });
+            (name, file)
+        })
+        .collect();
+    Ok(meta.stats(&files, &this_device(&app)?))
+}
+
+// ==========================================
+// --- DATA DIRECTORY MAINTENANCE (compaction.rs) ---
+// ==========================================
+
+/// Vault containers rewritten by `compact_data_dir` (if present).
+const COMPACTED_CONTAINERS: [&str; 7] = [
+    "passwords.qre",
+    "notes.qre",
+    note_assets::ASSETS_FILE_NAME,
+    "bookmarks.qre",
+    secrets::SECRETS_FILE_NAME,
+    breach_monitor::ALERTS_FILE_NAME,
+    vault_meta::META_FILE_NAME,
+];
+
+/// The subkey a vault container is encrypted with (see subkeys.rs), from its name
+/// relative to the vault directory.
+fn container_purpose(name: &str) -> KeyPurpose {
+    if name == "passwords.qre" || name.starts_with(entry_attachments::ATTACHMENTS_DIR_NAME) {
+        KeyPurpose::PasswordsVault
+    } else if name == "notes.qre"
+        || name == note_assets::ASSETS_FILE_NAME
+        || name.starts_with(note_images::IMAGES_DIR_NAME)
+    {
+        KeyPurpose::NotesVault
+    } else {
+        KeyPurpose::VaultData
+    }
 }
 
-/// Generates a Time-Based One-Time Password (TOTP) from a provided secret key.
-/// Returns the 6-digit code and the number of seconds remaining until it expires.
+/// Folds the clipboard journal, re-encrypts every vault file and note image, and shreds
+/// leftover temp files and orphaned images. Reports what was done and the space reclaimed.
+/// Containers still wrapped with the master key itself are moved to their subkey.
 #[tauri::command]
-pub fn generate_totp_code(secret: String) -> CommandResult<(String, u64)> {
-    // 1. Clean up the secret: Remove spaces, dashes, make uppercase
-    let clean_secret = secret
-        .split_whitespace()
-        .collect::<String>()
-        .replace("-", "")
-        .to_uppercase();
+pub async fn compact_data_dir(
+    app: AppHandle,
+    vault_id: String,
+    state: tauri::State<'_, SessionState>,
+) -> CommandResult<CompactionReport> {
+    state.ensure_writable(&vault_id)?;
+    let _job = JobGuard::acquire(Job::Shred)?;
+    let master_key = {
+        let guard = lock_session!(state)?;
+        guard
+            .get(&vault_id)
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked))?
+            .clone()
+    };
+    // Fails (and aborts) if the notes cannot be read: otherwise every image would
+    // look orphaned.
+    let referenced_images: HashSet<String> = read_notes_vault(&app, &vault_id, &state)?
+        .entries
+        .iter()
+        .flat_map(|n| n.image_ids.iter().cloned())
+        .collect();
+    let attachment_ids: Vec<String> = read_password_vault(&app, &vault_id, &state)?
+        .entries
+        .iter()
+        .flat_map(|e| e.attachments.iter().map(|a| a.id.clone()))
+        .collect();
+    let (snapshot, journal) = clipboard_paths(&app, &vault_id)?;
+    let vault_dir = snapshot
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorCode::KeychainPathInvalid))?
+        .to_path_buf();
 
-    // Some keys come with padding (=), some don't. We strip padding and use the NOPAD decoder.
-    let stripped_secret = clean_secret.trim_end_matches('=');
+    let app_handle = app.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let mut report = CompactionReport {
+            bytes_before: compaction::dir_size(&vault_dir),
+            ..Default::default()
+        };
 
-    // 2. Parse the Base32 secret robustly into raw bytes
-    let secret_bytes = match BASE32_NOPAD.decode(stripped_secret.as_bytes()) {
-        Ok(b) => b,
-        Err(e) => {
-            println!(
-                "Base32 Decode Error: {} for string '{}'",
-                e, stripped_secret
-            );
-            return Err("Invalid 2FA Secret Key (Must be valid Base32)".to_string());
+        // 1. Clipboard: snapshot + journal -> snapshot.
+        if snapshot.exists() || journal.exists() {
+            let _io = CLIPBOARD_IO.lock().unwrap_or_else(|p| p.into_inner());
+            match read_clipboard_state(&master_key, &snapshot, &journal)
+                .and_then(|(vault, _)| compact_clipboard(&master_key, &snapshot, &journal, &vault))
+            {
+                Ok(()) => report.rewritten.push("clipboard.qre".to_string()),
+                Err(e) => report.failed.push(("clipboard.qre".to_string(), e)),
+            }
+        }
+
+        // 2. Re-encrypt vault files and referenced images.
+        let mut containers: Vec<(String, PathBuf)> = COMPACTED_CONTAINERS
+            .iter()
+            .map(|name| (name.to_string(), vault_dir.join(name)))
+            .collect();
+        for id in &referenced_images {
+            for thumbnail in [false, true] {
+                if let Ok(path) = note_images::image_path(&vault_dir, id, thumbnail) {
+                    let name = format!(
+                        "{}/{}",
+                        note_images::IMAGES_DIR_NAME,
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                    containers.push((name, path));
+                }
+            }
+        }
+        for id in &attachment_ids {
+            if let Ok(path) = entry_attachments::attachment_path(&vault_dir, id) {
+                let name = format!("{}/{}.qre", entry_attachments::ATTACHMENTS_DIR_NAME, id);
+                containers.push((name, path));
+            }
+        }
+        for (name, path) in containers.into_iter().filter(|(_, p)| p.exists()) {
+            // Images are already compressed: fastest level, as in `add_note_image`.
+            let level = if name.starts_with(note_images::IMAGES_DIR_NAME)
+                || name == note_assets::ASSETS_FILE_NAME
+            {
+                1
+            } else {
+                3
+            };
+            let purpose = container_purpose(&name);
+            match compaction::rewrite_container(&path, &master_key, purpose, level) {
+                Ok(()) => report.rewritten.push(name),
+                Err(e) => report.failed.push((name, e.to_string())),
+            }
         }
-    };
 
-    // 3. Standard TOTP configuration: SHA-1, 6 digits, 30-second steps
-    let totp = TOTP::new_unchecked(Algorithm::SHA1, 6, 1, 30, secret_bytes);
+        // 3. Shred what is no longer needed.
+        let obsolete: Vec<String> = compaction::find_obsolete(&vault_dir, &referenced_images)
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        if !obsolete.is_empty() {
+            match shredder::batch_shred(obsolete, shredder::ShredMethod::DoD3Pass, &app_handle) {
+                Ok(result) => {
+                    report.shredded = result.success;
+                    report
+                        .failed
+                        .extend(result.failed.into_iter().map(|f| (f.path, f.error)));
+                }
+                Err(e) => report
+                    .failed
+                    .push(("obsolete files".to_string(), e.to_string())),
+            }
+        }
 
-    // 4. Generate the code based on the current Unix timestamp
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+        report.bytes_after = compaction::dir_size(&vault_dir);
+        report.reclaimed_bytes = report.bytes_before.saturating_sub(report.bytes_after);
+        report
+    })
+    .await
+    .map_err(|e| e.to_string())?;
 
-    let code = totp
-        .generate_current()
-        .map_err(|e| format!("Failed to generate code: {}", e))?;
+    // Rewritten containers have a new modification time; stamp them so they are not
+    // reported as changed outside the app.
+    let restamped: Vec<&str> = report
+        .rewritten
+        .iter()
+        .map(String::as_str)
+        .filter(|name| STAMPED_CONTAINERS.contains(name))
+        .collect();
+    stamp_vault_writes(&app, &vault_id, &state, &restamped);
 
-    // Calculate remaining seconds in the current 30-second window
-    let remaining_seconds = 30 - (now % 30);
+    record_audit(
+        &app,
+        &vault_id,
+        &state.user_for(&vault_id),
+        "compact_data_dir",
+        Some(format!(
+            "{} rewritten, {} shredded, {} bytes reclaimed",
+            report.rewritten.len(),
+            report.shredded.len(),
+            report.reclaimed_bytes
+        )),
+    );
+    Ok(report)
+}
+
+// ==========================================
+// --- PRIVACY REPORT (privacy_report.rs) ---
+// ==========================================
 
-    Ok((code, remaining_seconds))
+/// Breach alerts and password health counts for `generate_privacy_report`. The vault
+/// contents themselves never leave this function.
+pub(super) fn privacy_report_vault_inputs(
+    app: &AppHandle,
+    vault_id: &str,
+    state: &SessionState,
+) -> CommandResult<(Vec<BreachAlert>, privacy_report::PasswordSummary)> {
+    let alerts = read_breach_store(app, vault_id, state)?.alerts;
+    let vault = read_password_vault(app, vault_id, state)?;
+    Ok((alerts, privacy_report::summarize_passwords(&vault)))
 }
@@ -0,0 +1,214 @@
+// --- START OF FILE compaction.rs ---
+
+// ==========================================
+// --- DATA DIRECTORY COMPACTION ---
+// ==========================================
+// Over time the vault directory collects files that still say something even though
+// their content is encrypted: `.tmp` leftovers of interrupted saves, images of deleted
+// notes, a clipboard journal that grows with every copy. Their sizes and timestamps
+// outline what the user did and when.
+//
+// `compact_data_dir` (commands/vault.rs) fixes this in three steps:
+//   1. Fold the clipboard journal into its snapshot.
+//   2. Rewrite every vault container (note images and entry attachments included) with
+//      fresh keys and nonces (`rewrite_container`), which also resets file timestamps to
+//      the compaction time.
+//   3. Shred obsolete files (`find_obsolete`) with the regular shredder.
+//
+// Only files this app creates are considered; anything else in the directory is left
+// alone. Note: on SSDs and copy-on-write filesystems, old blocks may survive a rewrite.
+
+use crate::crypto::{self, EncryptedFileContainer};
+use crate::keychain::MasterKey;
+use crate::note_images;
+use crate::subkeys::KeyPurpose;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CompactionReport {
+    /// Containers re-encrypted in place (file names relative to the vault directory).
+    pub rewritten: Vec<String>,
+    pub shredded: Vec<String>,
+    /// (file, error) for every step that failed; the rest still ran.
+    pub failed: Vec<(String, String)>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub reclaimed_bytes: u64,
+}
+
+/// Total size of the regular files under `dir`.
+pub fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Files that can go: temp files from interrupted atomic saves, and images (or stray
+/// files) in `note_images/` that no note references any more.
+pub fn find_obsolete(vault_dir: &Path, referenced_images: &HashSet<String>) -> Vec<PathBuf> {
+    let mut obsolete = Vec::new();
+    let is_tmp = |p: &Path| p.extension().is_some_and(|e| e == "tmp");
+
+    if let Ok(entries) = fs::read_dir(vault_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_file()) && is_tmp(&path) {
+                obsolete.push(path);
+            }
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(vault_dir.join(note_images::IMAGES_DIR_NAME)) {
+        for entry in entries.flatten() {
+            if !entry.file_type().is_ok_and(|t| t.is_file()) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            // "<id>.qre" or "<id>.thumb.qre"
+            let id = name
+                .strip_suffix(".thumb.qre")
+                .or_else(|| name.strip_suffix(".qre"));
+            if id.is_none_or(|id| !referenced_images.contains(id)) {
+                obsolete.push(entry.path());
+            }
+        }
+    }
+    obsolete.sort();
+    obsolete
+}
+
+/// Re-encrypts a V4 container under the same master key with a fresh file key and
+/// nonces. The content and inner filename are unchanged. A container still wrapped with
+/// the master key itself comes out wrapped with its purpose subkey (see subkeys.rs).
+pub fn rewrite_container(
+    path: &Path,
+    master_key: &MasterKey,
+    purpose: KeyPurpose,
+    level: i32,
+) -> Result<()> {
+    let path_str = path.to_string_lossy();
+    let container = EncryptedFileContainer::load(&path_str)?;
+    let payload = crypto::decrypt_file_with_master_key(master_key, purpose, None, &container)?;
+    let rewritten = crypto::encrypt_file_with_master_key(
+        master_key,
+        purpose,
+        None,
+        &payload.filename,
+        &payload.content,
+        None,
+        level,
+    )?;
+    rewritten.save(&path_str)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("qre_compaction_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(note_images::IMAGES_DIR_NAME)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_find_obsolete_keeps_referenced_images() {
+        let dir = temp_dir("obsolete");
+        let kept = uuid::Uuid::new_v4().to_string();
+        let orphan = uuid::Uuid::new_v4().to_string();
+        for id in [&kept, &orphan] {
+            for thumbnail in [false, true] {
+                fs::write(note_images::image_path(&dir, id, thumbnail).unwrap(), b"x").unwrap();
+            }
+        }
+        fs::write(dir.join("passwords.qre"), b"x").unwrap();
+        fs::write(dir.join("passwords.tmp"), b"x").unwrap();
+
+        let referenced = HashSet::from([kept.clone()]);
+        let obsolete = find_obsolete(&dir, &referenced);
+        let names: Vec<String> = obsolete
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(obsolete.len(), 3, "{:?}", names);
+        assert!(names.contains(&"passwords.tmp".to_string()));
+        assert!(names.iter().all(|n| !n.starts_with(&kept)));
+        assert!(!names.contains(&"passwords.qre".to_string()));
+    }
+
+    #[test]
+    fn test_rewrite_container_keeps_content() {
+        let dir = temp_dir("rewrite");
+        let path = dir.join("notes.qre");
+        let mk = MasterKey([0x42; 32]);
+        let notes = KeyPurpose::NotesVault;
+        crypto::encrypt_file_with_master_key(&mk, notes, None, "notes.json", b"{\"a\":1}", None, 3)
+            .unwrap()
+            .save(&path.to_string_lossy())
+            .unwrap();
+        let before = fs::read(&path).unwrap();
+
+        rewrite_container(&path, &mk, notes, 3).unwrap();
+        let after = fs::read(&path).unwrap();
+        assert_ne!(before, after, "Fresh nonces and file key");
+        let payload = crypto::decrypt_file_with_master_key(
+            &mk,
+            notes,
+            None,
+            &EncryptedFileContainer::load(&path.to_string_lossy()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(payload.filename, "notes.json");
+        assert_eq!(payload.content, b"{\"a\":1}");
+
+        assert!(rewrite_container(&path, &MasterKey([0x43; 32]), notes, 3).is_err());
+        assert!(
+            rewrite_container(&path, &mk, KeyPurpose::PasswordsVault, 3).is_err(),
+            "Another purpose's subkey does not open it"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_migrates_legacy_container() {
+        let dir = temp_dir("legacy");
+        let path = dir.join("bookmarks.qre");
+        let mk = MasterKey([0x42; 32]);
+        let data = KeyPurpose::VaultData;
+        crypto::encrypt_file_with_legacy_key(&mk, "bookmarks.json", b"[]")
+            .unwrap()
+            .save(&path.to_string_lossy())
+            .unwrap();
+        let load = || EncryptedFileContainer::load(&path.to_string_lossy()).unwrap();
+        assert_eq!(
+            crypto::uses_legacy_key(&load().header, &mk, data),
+            Some(true)
+        );
+
+        rewrite_container(&path, &mk, data, 3).unwrap();
+        assert_eq!(
+            crypto::uses_legacy_key(&load().header, &mk, data),
+            Some(false)
+        );
+        let payload = crypto::decrypt_file_with_master_key(&mk, data, None, &load()).unwrap();
+        assert_eq!(payload.content, b"[]");
+        assert_eq!(
+            crypto::uses_legacy_key(&load().header, &MasterKey([0x43; 32]), data),
+            None
+        );
+    }
+}
+
+// --- END OF FILE compaction.rs ---
@@ -0,0 +1,329 @@
+// --- START OF FILE container_meta.rs ---
+
+// ==========================================
+// --- CONTAINER METADATA ---
+// ==========================================
+// Per-file metadata that travels inside the .qre header, sealed under the master key
+// (see `crypto_stream::SealedMetadata`). It lets the app answer questions about a
+// directory of containers without decrypting any content to disk.
+//
+// The sealed payload is JSON so fields can be added without a format bump; unknown
+// fields are ignored and missing ones default.
+//
+// LABELS
+// Free-form user labels ("tax 2024", "medical") so a directory of UUID-named
+// containers stays navigable. They are only readable while the vault is unlocked.
+//
+// SEARCH INDEX
+// At lock time (opt-in) plain text is extracted from txt/md/csv/pdf/docx files and
+// every word is added to a Bloom filter. The filter holds only hashed bit positions,
+// never words, and is itself encrypted. A Bloom filter can report false positives
+// ("may contain") but never false negatives, which is the right trade-off for
+// finding candidate files. Its fixed size keeps the header within its 4 KB region.
+
+use anyhow::{anyhow, Result};
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// 12,288 bits. With `BLOOM_HASHES` = 5 the false-positive rate stays around 5% up to
+/// roughly 2,000 distinct words.
+const BLOOM_BYTES: usize = 1536;
+const BLOOM_HASHES: usize = 5;
+const MIN_TOKEN_LEN: usize = 3;
+const MAX_TOKEN_LEN: usize = 32;
+/// Source files larger than this are locked without an index.
+const MAX_INDEX_SOURCE_BYTES: u64 = 50 * 1024 * 1024;
+/// Extracted text is truncated here; the Bloom filter saturates long before.
+const MAX_EXTRACTED_CHARS: usize = 4 * 1024 * 1024;
+/// Label limits keep labels plus the search index inside the 4 KB header region.
+const MAX_LABELS: usize = 10;
+const MAX_LABEL_LEN: usize = 40;
+
+// ==========================================
+// --- METADATA DOCUMENT ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ContainerMetadata {
+    /// Base64 Bloom filter of the file's words (`SearchIndex`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_index: Option<String>,
+    /// User labels, already normalized (`normalize_labels`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+impl ContainerMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.search_index.is_none() && self.labels.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).map_err(|_| anyhow!("Malformed container metadata"))
+    }
+}
+
+// ==========================================
+// --- LABELS ---
+// ==========================================
+
+/// Trims labels, drops duplicates (case-insensitive) and enforces the limits.
+pub fn normalize_labels(labels: &[String]) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::new();
+    for label in labels {
+        let label = label.trim();
+        if label.is_empty() {
+            continue;
+        }
+        if label.chars().count() > MAX_LABEL_LEN {
+            return Err(format!(
+                "Label '{}' is too long (max {} characters).",
+                label, MAX_LABEL_LEN
+            ));
+        }
+        if label.chars().any(char::is_control) {
+            return Err("Labels cannot contain control characters.".to_string());
+        }
+        if !out.iter().any(|l| l.eq_ignore_ascii_case(label)) {
+            out.push(label.to_string());
+        }
+    }
+    if out.len() > MAX_LABELS {
+        return Err(format!(
+            "A container can have at most {} labels.",
+            MAX_LABELS
+        ));
+    }
+    Ok(out)
+}
+
+// ==========================================
+// --- SEARCH INDEX ---
+// ==========================================
+
+pub struct SearchIndex {
+    bits: Vec<u8>,
+}
+
+impl SearchIndex {
+    pub fn build<'a>(tokens: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut index = Self {
+            bits: vec![0u8; BLOOM_BYTES],
+        };
+        for token in tokens {
+            for bit in bloom_positions(token) {
+                index.bits[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+        index
+    }
+
+    /// True if `token` may be in the file (false positives possible, no false negatives).
+    pub fn may_contain(&self, token: &str) -> bool {
+        bloom_positions(token).all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// True if every word of `query` may be in the file.
+    pub fn matches_query(&self, query: &str) -> bool {
+        let terms = tokenize(query);
+        !terms.is_empty() && terms.iter().all(|t| self.may_contain(t))
+    }
+
+    pub fn encode(&self) -> String {
+        BASE64.encode(&self.bits)
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let bits = BASE64
+            .decode(encoded.as_bytes())
+            .map_err(|_| anyhow!("Malformed search index"))?;
+        if bits.len() != BLOOM_BYTES {
+            return Err(anyhow!("Malformed search index"));
+        }
+        Ok(Self { bits })
+    }
+}
+
+/// `BLOOM_HASHES` bit positions from one SHA-256 of the token.
+fn bloom_positions(token: &str) -> impl Iterator<Item = usize> {
+    let digest = Sha256::new()
+        .chain_update(b"QRE_SEARCH_V1")
+        .chain_update(token.as_bytes())
+        .finalize();
+    (0..BLOOM_HASHES).map(move |i| {
+        let word = u32::from_le_bytes(digest[i * 4..i * 4 + 4].try_into().unwrap());
+        word as usize % (BLOOM_BYTES * 8)
+    })
+}
+
+/// Lowercased alphanumeric words of `MIN_TOKEN_LEN..=MAX_TOKEN_LEN` characters.
+pub fn tokenize(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| (MIN_TOKEN_LEN..=MAX_TOKEN_LEN).contains(&w.chars().count()))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// ==========================================
+// --- TEXT EXTRACTION ---
+// ==========================================
+
+/// Extracts searchable text from a supported document. `None` for unsupported types,
+/// oversized files, or documents that yield no text (e.g. scanned PDFs: no OCR).
+pub fn extract_text(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    if fs::metadata(path).ok()?.len() > MAX_INDEX_SOURCE_BYTES {
+        return None;
+    }
+    let mut text = match ext.as_str() {
+        "txt" | "md" | "csv" | "log" | "json" | "xml" | "html" | "htm" => {
+            String::from_utf8_lossy(&fs::read(path).ok()?).into_owned()
+        }
+        "pdf" => {
+            let doc = lopdf::Document::load(path).ok()?;
+            let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+            doc.extract_text(&pages).ok()?
+        }
+        "docx" => extract_docx_text(path).ok()?,
+        _ => return None,
+    };
+    if text.len() > MAX_EXTRACTED_CHARS {
+        let mut cut = MAX_EXTRACTED_CHARS;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        text.truncate(cut);
+    }
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Text content of `word/document.xml`, with markup replaced by spaces.
+fn extract_docx_text(path: &Path) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")?
+        .take(MAX_EXTRACTED_CHARS as u64 * 4)
+        .read_to_string(&mut xml)?;
+
+    let mut text = String::with_capacity(xml.len() / 4);
+    let mut in_tag = false;
+    for c in xml.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+/// Metadata to seal into a container at lock time, or `None` if there is nothing to add.
+/// `labels` must already be normalized (`normalize_labels`).
+pub fn build_for_file(
+    path: &Path,
+    with_search_index: bool,
+    labels: &[String],
+) -> Option<ContainerMetadata> {
+    let mut meta = ContainerMetadata {
+        labels: labels.to_vec(),
+        ..Default::default()
+    };
+    if with_search_index {
+        if let Some(text) = extract_text(path) {
+            meta.search_index = Some(SearchIndex::build(&tokenize(&text)).encode());
+        }
+    }
+    (!meta.is_empty()).then_some(meta)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("Invoice #42 — Acme GmbH, ACME invoice; total: 1999.00");
+        assert!(tokens.contains("invoice"));
+        assert!(tokens.contains("acme"));
+        assert!(tokens.contains("1999"));
+        assert!(!tokens.contains("42"), "Too short");
+        assert_eq!(tokens.iter().filter(|t| *t == "acme").count(), 1);
+    }
+
+    #[test]
+    fn test_search_index_round_trip() {
+        let index = SearchIndex::build(&tokenize("passport renewal appointment Athens"));
+        let restored = SearchIndex::decode(&index.encode()).unwrap();
+        assert!(restored.matches_query("Passport"));
+        assert!(restored.matches_query("athens passport"));
+        assert!(!restored.matches_query("bitcoin"));
+        assert!(
+            !restored.matches_query("a"),
+            "Queries without usable words match nothing"
+        );
+        assert!(SearchIndex::decode("AAAA").is_err());
+    }
+
+    #[test]
+    fn test_build_for_file_extracts_text() {
+        let dir = std::env::temp_dir().join("qre_container_meta_tests");
+        fs::create_dir_all(&dir).unwrap();
+        let txt = dir.join("notes.txt");
+        fs::write(&txt, "Quarterly revenue report").unwrap();
+        let bin = dir.join("photo.jpg");
+        fs::write(&bin, [0xFFu8, 0xD8, 0xFF]).unwrap();
+
+        let meta = build_for_file(&txt, true, &[]).unwrap();
+        let index = SearchIndex::decode(meta.search_index.as_deref().unwrap()).unwrap();
+        assert!(index.matches_query("revenue"));
+        assert!(build_for_file(&txt, false, &[]).is_none());
+        assert!(build_for_file(&bin, true, &[]).is_none());
+
+        let bytes = meta.to_bytes().unwrap();
+        assert_eq!(ContainerMetadata::from_bytes(&bytes).unwrap(), meta);
+
+        // Labels alone are enough to produce metadata, for any file type.
+        let labelled = build_for_file(&bin, false, &["holiday".to_string()]).unwrap();
+        assert_eq!(labelled.labels, vec!["holiday"]);
+        assert!(labelled.search_index.is_none());
+    }
+
+    #[test]
+    fn test_normalize_labels() {
+        let labels = vec![
+            " Tax 2024 ".to_string(),
+            "tax 2024".to_string(),
+            "".to_string(),
+            "Medical".to_string(),
+        ];
+        assert_eq!(
+            normalize_labels(&labels).unwrap(),
+            vec!["Tax 2024", "Medical"]
+        );
+        assert!(normalize_labels(&["x".repeat(MAX_LABEL_LEN + 1)]).is_err());
+        assert!(normalize_labels(&["a\nb".to_string()]).is_err());
+        let many: Vec<String> = (0..=MAX_LABELS).map(|i| format!("l{}", i)).collect();
+        assert!(normalize_labels(&many).is_err());
+    }
+}
+
+// --- END OF FILE container_meta.rs ---
@@ -0,0 +1,585 @@
+// --- START OF FILE cookie_inspector.rs ---
+
+// ==========================================
+// --- BROWSER COOKIE INSPECTOR ---
+// ==========================================
+// The system cleaner can only delete a browser's cookie store as a whole, which also
+// logs the user out of every site. This reads the cookie databases of Chromium-based
+// browsers (Chrome, Edge, Brave, Chromium) and Firefox, groups the cookies by site,
+// flags known trackers, and deletes the cookies of selected sites only.
+//
+// - Only metadata is read (host, name, last access). Cookie values are never
+//   selected: Chromium encrypts them anyway, and they are session secrets.
+// - Browsers keep the database open and rewrite it from memory, so deleting while the
+//   browser runs would be undone (or corrupt the store). Deletion refuses to run while
+//   the owning browser is open; listing works on a read-only connection either way.
+
+use crate::passwords::base_domain;
+use directories::BaseDirs;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreKind {
+    Chromium,
+    Firefox,
+}
+
+/// One cookie database of one browser profile.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CookieStore {
+    pub browser: String,
+    pub profile: String,
+    pub path: String,
+    pub kind: StoreKind,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackerCategory {
+    Advertising,
+    Analytics,
+    Social,
+}
+
+/// All cookies of one registrable domain, across the scanned stores.
+#[derive(Serialize, Debug, Clone)]
+pub struct DomainCookies {
+    pub domain: String,
+    pub cookie_count: usize,
+    /// Distinct cookie names (at most `MAX_NAMES_PER_DOMAIN`).
+    pub names: Vec<String>,
+    /// Browsers holding cookies for this domain.
+    pub browsers: Vec<String>,
+    pub tracker: Option<TrackerCategory>,
+    /// Unix seconds of the most recent access, when the browser records it.
+    pub last_access: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StoreSummary {
+    pub store: CookieStore,
+    pub cookie_count: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CookieReport {
+    pub stores: Vec<StoreSummary>,
+    /// Trackers first, then by cookie count.
+    pub domains: Vec<DomainCookies>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CookieDeletion {
+    pub deleted: usize,
+    /// (store path, error) for stores that could not be changed.
+    pub failed: Vec<(String, String)>,
+}
+
+/// One row as read from a store, before grouping.
+#[derive(Debug, Clone)]
+struct CookieRow {
+    host: String,
+    name: String,
+    last_access: Option<i64>,
+}
+
+const MAX_NAMES_PER_DOMAIN: usize = 50;
+/// Seconds between 1601-01-01 (Chromium's epoch) and 1970-01-01.
+const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+// ==========================================
+// --- TRACKER CLASSIFICATION ---
+// ==========================================
+// A short list of the most widespread third-party tracking domains, plus cookie names
+// that analytics and ad scripts set under the visited site's own domain.
+
+const TRACKER_DOMAINS: &[(&str, TrackerCategory)] = &[
+    ("doubleclick.net", TrackerCategory::Advertising),
+    ("googlesyndication.com", TrackerCategory::Advertising),
+    ("googleadservices.com", TrackerCategory::Advertising),
+    ("adnxs.com", TrackerCategory::Advertising),
+    ("criteo.com", TrackerCategory::Advertising),
+    ("criteo.net", TrackerCategory::Advertising),
+    ("taboola.com", TrackerCategory::Advertising),
+    ("outbrain.com", TrackerCategory::Advertising),
+    ("rubiconproject.com", TrackerCategory::Advertising),
+    ("pubmatic.com", TrackerCategory::Advertising),
+    ("openx.net", TrackerCategory::Advertising),
+    ("casalemedia.com", TrackerCategory::Advertising),
+    ("amazon-adsystem.com", TrackerCategory::Advertising),
+    ("adsrvr.org", TrackerCategory::Advertising),
+    ("bidswitch.net", TrackerCategory::Advertising),
+    ("demdex.net", TrackerCategory::Advertising),
+    ("google-analytics.com", TrackerCategory::Analytics),
+    ("scorecardresearch.com", TrackerCategory::Analytics),
+    ("quantserve.com", TrackerCategory::Analytics),
+    ("hotjar.com", TrackerCategory::Analytics),
+    ("mixpanel.com", TrackerCategory::Analytics),
+    ("clarity.ms", TrackerCategory::Analytics),
+    ("newrelic.com", TrackerCategory::Analytics),
+    ("omtrdc.net", TrackerCategory::Analytics),
+    ("facebook.com", TrackerCategory::Social),
+    ("facebook.net", TrackerCategory::Social),
+    ("twitter.com", TrackerCategory::Social),
+    ("linkedin.com", TrackerCategory::Social),
+    ("addthis.com", TrackerCategory::Social),
+    ("sharethis.com", TrackerCategory::Social),
+];
+
+/// First-party cookies set by common analytics/ad scripts.
+const TRACKER_COOKIE_PREFIXES: &[&str] = &[
+    "_ga", "_gid", "_gat", "__utm", "_gcl_", "_fbp", "_fbc", "_hj", "_clck", "_clsk", "_uet",
+    "mp_", "ajs_",
+];
+
+fn classify(domain: &str, names: &[String]) -> Option<TrackerCategory> {
+    TRACKER_DOMAINS
+        .iter()
+        .find(|(d, _)| *d == domain)
+        .map(|(_, category)| *category)
+        .or_else(|| {
+            names
+                .iter()
+                .any(|n| TRACKER_COOKIE_PREFIXES.iter().any(|p| n.starts_with(p)))
+                .then_some(TrackerCategory::Analytics)
+        })
+}
+
+/// Registrable domain of a cookie host (Chromium and Firefox prefix domain cookies with '.').
+fn cookie_domain(host: &str) -> String {
+    base_domain(&host.trim_start_matches('.').to_lowercase())
+}
+
+// ==========================================
+// --- STORE DISCOVERY ---
+// ==========================================
+
+/// Chromium-based browsers: (display name, user-data directory).
+fn chromium_roots(dirs: &BaseDirs) -> Vec<(&'static str, PathBuf)> {
+    #[cfg(target_os = "windows")]
+    let (base, layout): (&Path, &[(&str, &[&str])]) = (
+        dirs.data_local_dir(),
+        &[
+            ("Chrome", &["Google", "Chrome", "User Data"]),
+            ("Edge", &["Microsoft", "Edge", "User Data"]),
+            ("Brave", &["BraveSoftware", "Brave-Browser", "User Data"]),
+            ("Chromium", &["Chromium", "User Data"]),
+        ],
+    );
+    #[cfg(target_os = "macos")]
+    let (base, layout): (&Path, &[(&str, &[&str])]) = (
+        dirs.config_dir(),
+        &[
+            ("Chrome", &["Google", "Chrome"]),
+            ("Edge", &["Microsoft Edge"]),
+            ("Brave", &["BraveSoftware", "Brave-Browser"]),
+            ("Chromium", &["Chromium"]),
+        ],
+    );
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let (base, layout): (&Path, &[(&str, &[&str])]) = (
+        dirs.config_dir(),
+        &[
+            ("Chrome", &["google-chrome"]),
+            ("Edge", &["microsoft-edge"]),
+            ("Brave", &["BraveSoftware", "Brave-Browser"]),
+            ("Chromium", &["chromium"]),
+        ],
+    );
+    layout
+        .iter()
+        .map(|(name, parts)| {
+            (
+                *name,
+                parts.iter().fold(base.to_path_buf(), |p, s| p.join(s)),
+            )
+        })
+        .collect()
+}
+
+fn firefox_profiles_dir(dirs: &BaseDirs) -> PathBuf {
+    #[cfg(target_os = "windows")]
+    let dir = dirs
+        .config_dir()
+        .join("Mozilla")
+        .join("Firefox")
+        .join("Profiles");
+    #[cfg(target_os = "macos")]
+    let dir = dirs.config_dir().join("Firefox").join("Profiles");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let dir = dirs.home_dir().join(".mozilla").join("firefox");
+    dir
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+/// Every cookie database found for the current user.
+pub fn find_cookie_stores() -> Vec<CookieStore> {
+    let Some(dirs) = BaseDirs::new() else {
+        return Vec::new();
+    };
+    let mut stores = Vec::new();
+
+    for (browser, root) in chromium_roots(&dirs) {
+        for profile in subdirs(&root) {
+            // Newer versions keep the database under Network/.
+            let path = [
+                profile.join("Network").join("Cookies"),
+                profile.join("Cookies"),
+            ]
+            .into_iter()
+            .find(|p| p.is_file());
+            if let Some(path) = path {
+                stores.push(CookieStore {
+                    browser: browser.to_string(),
+                    profile: profile
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    kind: StoreKind::Chromium,
+                });
+            }
+        }
+    }
+
+    for profile in subdirs(&firefox_profiles_dir(&dirs)) {
+        let path = profile.join("cookies.sqlite");
+        if path.is_file() {
+            stores.push(CookieStore {
+                browser: "Firefox".to_string(),
+                profile: profile
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                path: path.to_string_lossy().to_string(),
+                kind: StoreKind::Firefox,
+            });
+        }
+    }
+    stores
+}
+
+// ==========================================
+// --- READING & DELETING ---
+// ==========================================
+
+fn read_rows(path: &Path, kind: StoreKind) -> Result<Vec<CookieRow>, String> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| e.to_string())?;
+    let sql = match kind {
+        StoreKind::Chromium => "SELECT host_key, name, last_access_utc FROM cookies",
+        StoreKind::Firefox => "SELECT host, name, lastAccessed FROM moz_cookies",
+    };
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let raw: Option<i64> = row.get(2)?;
+            let last_access = raw.filter(|v| *v > 0).map(|v| match kind {
+                StoreKind::Chromium => v / 1_000_000 - WINDOWS_EPOCH_OFFSET,
+                StoreKind::Firefox => v / 1_000_000,
+            });
+            Ok(CookieRow {
+                host: row.get(0)?,
+                name: row.get(1)?,
+                last_access,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Lists the cookies of `stores`, grouped by registrable domain.
+pub fn inspect(stores: &[CookieStore]) -> CookieReport {
+    let mut summaries = Vec::new();
+    let mut groups: BTreeMap<String, DomainCookies> = BTreeMap::new();
+
+    for store in stores {
+        let rows = read_rows(Path::new(&store.path), store.kind);
+        summaries.push(StoreSummary {
+            store: store.clone(),
+            cookie_count: rows.as_ref().map(Vec::len).unwrap_or(0),
+            error: rows.as_ref().err().cloned(),
+        });
+        for row in rows.unwrap_or_default() {
+            let domain = cookie_domain(&row.host);
+            let group = groups
+                .entry(domain.clone())
+                .or_insert_with(|| DomainCookies {
+                    domain,
+                    cookie_count: 0,
+                    names: Vec::new(),
+                    browsers: Vec::new(),
+                    tracker: None,
+                    last_access: None,
+                });
+            group.cookie_count += 1;
+            if group.names.len() < MAX_NAMES_PER_DOMAIN && !group.names.contains(&row.name) {
+                group.names.push(row.name);
+            }
+            if !group.browsers.contains(&store.browser) {
+                group.browsers.push(store.browser.clone());
+            }
+            group.last_access = group.last_access.max(row.last_access);
+        }
+    }
+
+    let mut domains: Vec<DomainCookies> = groups
+        .into_values()
+        .map(|mut g| {
+            g.tracker = classify(&g.domain, &g.names);
+            g
+        })
+        .collect();
+    domains.sort_by(|a, b| {
+        (
+            a.tracker.is_none(),
+            std::cmp::Reverse(a.cookie_count),
+            &a.domain,
+        )
+            .cmp(&(
+                b.tracker.is_none(),
+                std::cmp::Reverse(b.cookie_count),
+                &b.domain,
+            ))
+    });
+    CookieReport {
+        stores: summaries,
+        domains,
+    }
+}
+
+/// Deletes every cookie of `domains` (and their subdomains) from one store. Returns the
+/// number of cookies removed.
+pub fn delete_domains(path: &Path, kind: StoreKind, domains: &[String]) -> Result<usize, String> {
+    let mut conn = Connection::open(path).map_err(|e| e.to_string())?;
+    let (table, column) = match kind {
+        StoreKind::Chromium => ("cookies", "host_key"),
+        StoreKind::Firefox => ("moz_cookies", "host"),
+    };
+    // host == domain, host == .domain, or host ends with .domain
+    let sql = format!(
+        "DELETE FROM {table} WHERE lower({column}) = ?1 OR lower({column}) = '.' || ?1 \
+         OR (length({column}) > length(?1) + 1 AND lower(substr({column}, -length(?1) - 1)) = '.' || ?1)"
+    );
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut deleted = 0;
+    for domain in domains {
+        let domain = domain.trim().trim_start_matches('.').to_lowercase();
+        if domain.is_empty() {
+            continue;
+        }
+        deleted += tx
+            .execute(&sql, params![domain])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(deleted)
+}
+
+/// Process names of a browser on the supported platforms.
+fn process_names(browser: &str) -> &'static [&'static str] {
+    match browser {
+        "Chrome" => &["chrome", "chrome.exe", "Google Chrome"],
+        "Edge" => &["msedge", "msedge.exe", "Microsoft Edge"],
+        "Brave" => &["brave", "brave.exe", "Brave Browser"],
+        "Chromium" => &["chromium", "chromium-browser", "chromium.exe", "Chromium"],
+        "Firefox" => &["firefox", "firefox.exe", "firefox-bin", "firefox-esr"],
+        _ => &[],
+    }
+}
+
+/// Browsers among `stores` that currently have a running process.
+pub fn running_browsers(stores: &[CookieStore]) -> Vec<String> {
+    use sysinfo::{ProcessesToUpdate, System};
+    let mut sys = System::new();
+    sys.refresh_processes(ProcessesToUpdate::All, true);
+    let running: Vec<String> = sys
+        .processes()
+        .values()
+        .map(|p| p.name().to_string_lossy().to_string())
+        .collect();
+
+    let mut browsers: Vec<String> = stores
+        .iter()
+        .map(|s| s.browser.clone())
+        .filter(|b| {
+            process_names(b)
+                .iter()
+                .any(|n| running.iter().any(|r| r == n))
+        })
+        .collect();
+    browsers.dedup();
+    browsers
+}
+
+/// Deletes the cookies of `domains` from every store in `stores`. Fails up front if any
+/// of the browsers involved is still running.
+pub fn delete_cookies(
+    stores: &[CookieStore],
+    domains: &[String],
+) -> Result<CookieDeletion, String> {
+    let running = running_browsers(stores);
+    if !running.is_empty() {
+        return Err(format!(
+            "Close {} before deleting cookies; the browser would restore them.",
+            running.join(", ")
+        ));
+    }
+    let mut result = CookieDeletion::default();
+    for store in stores {
+        match delete_domains(Path::new(&store.path), store.kind, domains) {
+            Ok(n) => result.deleted += n,
+            Err(e) => result.failed.push((store.path.clone(), e)),
+        }
+    }
+    Ok(result)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str, kind: StoreKind, hosts: &[(&str, &str)]) -> CookieStore {
+        let dir = std::env::temp_dir().join("qre_cookie_inspector_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        let (create, insert) = match kind {
+            StoreKind::Chromium => (
+                "CREATE TABLE cookies (host_key TEXT, name TEXT, value TEXT, \
+                 encrypted_value BLOB, last_access_utc INTEGER)",
+                "INSERT INTO cookies VALUES (?1, ?2, '', x'00', 13300000000000000)",
+            ),
+            StoreKind::Firefox => (
+                "CREATE TABLE moz_cookies (host TEXT, name TEXT, value TEXT, lastAccessed INTEGER)",
+                "INSERT INTO moz_cookies VALUES (?1, ?2, 'secret', 1700000000000000)",
+            ),
+        };
+        conn.execute(create, []).unwrap();
+        for (host, cookie) in hosts {
+            conn.execute(insert, params![host, cookie]).unwrap();
+        }
+        CookieStore {
+            browser: match kind {
+                StoreKind::Chromium => "Chrome".to_string(),
+                StoreKind::Firefox => "Firefox".to_string(),
+            },
+            profile: "Default".to_string(),
+            path: path.to_string_lossy().to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_groups_by_domain_and_flags_trackers() {
+        let chrome = temp_store(
+            "group_chrome.sqlite",
+            StoreKind::Chromium,
+            &[
+                (".doubleclick.net", "IDE"),
+                ("mail.example.com", "session"),
+                (".example.com", "_ga"),
+            ],
+        );
+        let firefox = temp_store(
+            "group_firefox.sqlite",
+            StoreKind::Firefox,
+            &[("www.example.com", "pref"), ("shop.example.org", "cart")],
+        );
+        let report = inspect(&[chrome, firefox]);
+        assert!(report.stores.iter().all(|s| s.error.is_none()));
+
+        let names: Vec<&str> = report.domains.iter().map(|d| d.domain.as_str()).collect();
+        // Trackers first (example.com is flagged through its `_ga` cookie).
+        assert_eq!(names, vec!["example.com", "doubleclick.net", "example.org"]);
+        let example = &report.domains[0];
+        assert_eq!(example.cookie_count, 3);
+        assert_eq!(example.browsers, vec!["Chrome", "Firefox"]);
+        assert_eq!(example.tracker, Some(TrackerCategory::Analytics));
+        assert_eq!(
+            report.domains[1].tracker,
+            Some(TrackerCategory::Advertising)
+        );
+        assert_eq!(report.domains[2].tracker, None);
+        assert_eq!(report.domains[2].last_access, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_delete_only_selected_domains() {
+        let store = temp_store(
+            "delete_chrome.sqlite",
+            StoreKind::Chromium,
+            &[
+                (".tracker.net", "a"),
+                ("ads.tracker.net", "b"),
+                ("nottracker.net", "c"),
+                ("example.com", "d"),
+            ],
+        );
+        let deleted = delete_domains(
+            Path::new(&store.path),
+            store.kind,
+            &["Tracker.net".to_string()],
+        )
+        .unwrap();
+        assert_eq!(deleted, 2);
+        let left: Vec<String> = read_rows(Path::new(&store.path), store.kind)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.host)
+            .collect();
+        assert_eq!(left, vec!["nottracker.net", "example.com"]);
+    }
+
+    #[test]
+    fn test_unreadable_store_is_reported() {
+        let store = CookieStore {
+            browser: "Chrome".to_string(),
+            profile: "Default".to_string(),
+            path: std::env::temp_dir()
+                .join("qre_cookie_inspector_tests")
+                .join("missing")
+                .join("Cookies")
+                .to_string_lossy()
+                .to_string(),
+            kind: StoreKind::Chromium,
+        };
+        let report = inspect(&[store]);
+        assert!(report.stores[0].error.is_some());
+        assert!(report.domains.is_empty());
+    }
+}
+
+// --- END OF FILE cookie_inspector.rs ---
@@ -1,20 +1,26 @@
 // --- START OF FILE crypto.rs ---
 
+use crate::file_lock;
 use crate::keychain::MasterKey;
+use crate::subkeys::{self, KeyPurpose};
 use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
 };
 use anyhow::{anyhow, Context, Result};
+use bincode::Options;
 use rand::{rngs::OsRng, RngCore, SeedableRng, TryRngCore};
 use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::io::Cursor;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 const AES_NONCE_LEN: usize = 12;
 const VALIDATION_MAGIC: &[u8] = b"QRE_VALID";
+/// Field bounds for V4 headers (see `EncryptedFileHeader::validate`).
+const MAX_WRAPPED_SECRET_LEN: usize = 64;
+const SHA256_LEN: usize = 32;
 
 // ==========================================
 // --- DATA STRUCTURES ---
@@ -45,32 +51,72 @@ pub struct EncryptedFileContainer {
     pub ciphertext: Vec<u8>,
 }
 
+impl EncryptedFileHeader {
+    /// Rejects field lengths that would make `Nonce::from_slice` panic. The file is
+    /// attacker-controlled until the GCM tags have been checked.
+    pub fn validate(&self) -> Result<()> {
+        for nonce in [
+            &self.validation_nonce,
+            &self.key_wrapping_nonce,
+            &self.body_nonce,
+        ] {
+            if nonce.len() != AES_NONCE_LEN {
+                return Err(anyhow!("Malformed header: invalid nonce length"));
+            }
+        }
+        if self.encrypted_validation_tag.len() > MAX_WRAPPED_SECRET_LEN
+            || self.encrypted_file_key.len() > MAX_WRAPPED_SECRET_LEN
+        {
+            return Err(anyhow!("Malformed header: oversized key material"));
+        }
+        if self
+            .original_hash
+            .as_ref()
+            .is_some_and(|h| h.len() != SHA256_LEN)
+        {
+            return Err(anyhow!("Malformed header: invalid hash length"));
+        }
+        Ok(())
+    }
+}
+
 impl EncryptedFileContainer {
+    /// Writes the container atomically under the directory's write lock, so two
+    /// instances saving the same vault cannot interleave (see file_lock.rs).
     pub fn save(&self, path: &str) -> Result<()> {
-        let file = std::fs::File::create(path).context("Failed to create output file")?;
-        let writer = std::io::BufWriter::new(file);
-        bincode::serialize_into(writer, self).context("Failed to write encrypted file")?;
-        Ok(())
+        let path = std::path::Path::new(path);
+        let _lock = file_lock::FileLock::exclusive(path)?;
+        file_lock::write_atomic_locked(path, |file| {
+            let writer = std::io::BufWriter::new(file);
+            bincode::serialize_into(writer, self).context("Failed to write encrypted file")
+        })
     }
 
     pub fn load(path: &str) -> Result<Self> {
-        let mut file = std::fs::File::open(path).context("Failed to open encrypted file")?;
-
-        let mut ver_buf = [0u8; 4];
-        file.read_exact(&mut ver_buf)
-            .context("Failed to read version")?;
-        let version = u32::from_le_bytes(ver_buf);
-
-        file.seek(SeekFrom::Start(0))?;
-        let reader = std::io::BufReader::new(file);
-
-        if version == 4 {
-            let container: Self =
-                bincode::deserialize_from(reader).context("Failed to parse V4 file")?;
-            Ok(container)
-        } else {
-            Err(anyhow!("Unsupported or legacy file version: {}.", version))
+        let data = std::fs::read(path).context("Failed to open encrypted file")?;
+        Self::from_bytes(&data)
+    }
+
+    /// Parses and validates a V4 container.
+    /// SECURITY: The bincode limit is the input size, so no length prefix in a crafted
+    /// file can make us allocate more than the file itself.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 4 {
+            return Err(anyhow!("Failed to read version"));
+        }
+        let version = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        if version != 4 {
+            return Err(anyhow!("Unsupported or legacy file version: {}.", version));
         }
+
+        let container: Self = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(data.len() as u64)
+            .deserialize(data)
+            .context("Failed to parse V4 file")?;
+        container.header.validate()?;
+        Ok(container)
     }
 }
 
@@ -99,6 +145,37 @@ fn derive_wrapping_key(
     Zeroizing::new(key)
 }
 
+/// Finds the key that wraps the file key of `h`: the purpose subkey, or the master key
+/// for containers written before subkeys (see subkeys.rs). Returns the wrapping cipher
+/// and whether it is the legacy one.
+fn open_wrapping(
+    h: &EncryptedFileHeader,
+    master_key: &MasterKey,
+    purpose: KeyPurpose,
+    keyfile_bytes: Option<&[u8]>,
+) -> Result<(Aes256Gcm, bool)> {
+    let mut tag_mismatch = false;
+    for (root, legacy) in subkeys::wrapping_roots(master_key, purpose) {
+        let wrapping_key = derive_wrapping_key(&root, keyfile_bytes);
+        let cipher_wrap = Aes256Gcm::new_from_slice(&*wrapping_key)
+            .map_err(|e| anyhow!("Cipher error: {}", e))?;
+        let val_nonce = Nonce::from_slice(&h.validation_nonce);
+        match cipher_wrap.decrypt(val_nonce, h.encrypted_validation_tag.as_ref()) {
+            Ok(bytes) if constant_time_eq(&bytes, VALIDATION_MAGIC) => {
+                return Ok((cipher_wrap, legacy))
+            }
+            Ok(_) => tag_mismatch = true,
+            Err(_) => {}
+        }
+    }
+    if tag_mismatch {
+        return Err(anyhow!("Validation tag mismatch."));
+    }
+    Err(anyhow!(
+        "Decryption Denied. Password or Keyfile is incorrect."
+    ))
+}
+
 fn compress_data(data: &[u8], level: i32) -> Result<Vec<u8>> {
     zstd::stream::encode_all(Cursor::new(data), level)
         .map_err(|e| anyhow!("Compression failed: {}", e))
@@ -125,6 +202,39 @@ fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
 
 pub fn encrypt_file_with_master_key(
     master_key: &MasterKey,
+    purpose: KeyPurpose,
+    keyfile_bytes: Option<&[u8]>,
+    filename: &str,
+    file_bytes: &[u8],
+    entropy_seed: Option<[u8; 32]>,
+    compression_level: i32,
+) -> Result<EncryptedFileContainer> {
+    let subkey = subkeys::derive(master_key, purpose);
+    encrypt_with_root(
+        &subkey,
+        keyfile_bytes,
+        filename,
+        file_bytes,
+        entropy_seed,
+        compression_level,
+    )
+}
+
+/// A container as written before subkeys, wrapped with the master key itself. Lets
+/// the tests check that such containers still open and get migrated.
+#[cfg(test)]
+pub(crate) fn encrypt_file_with_legacy_key(
+    master_key: &MasterKey,
+    filename: &str,
+    file_bytes: &[u8],
+) -> Result<EncryptedFileContainer> {
+    encrypt_with_root(master_key, None, filename, file_bytes, None, 3)
+}
+
+/// `root` is the key the wrapping key derives from: a purpose subkey, or the master
+/// key for legacy containers.
+fn encrypt_with_root(
+    root: &MasterKey,
     keyfile_bytes: Option<&[u8]>,
     filename: &str,
     file_bytes: &[u8],
@@ -174,8 +284,8 @@ pub fn encrypt_file_with_master_key(
         .encrypt(Nonce::from_slice(&body_nonce), plaintext_blob.as_ref())
         .map_err(|_| anyhow!("Body encryption failed"))?;
 
-    // 6. Wrap (Encrypt) the File Key using the Master Wrapping Key
-    let wrapping_key = derive_wrapping_key(master_key, keyfile_bytes);
+    // 6. Wrap (Encrypt) the File Key using the Wrapping Key
+    let wrapping_key = derive_wrapping_key(root, keyfile_bytes);
     let cipher_wrap =
         Aes256Gcm::new_from_slice(&*wrapping_key).map_err(|e| anyhow!("Cipher error: {}", e))?;
 
@@ -214,34 +324,46 @@ pub fn encrypt_file_with_master_key(
 // --- DECRYPTION LOGIC ---
 // ==========================================
 
+/// True if `master_key` alone opens the header. Keyfile-protected files always return
+/// false: without the keyfile there is nothing to check.
+pub fn master_key_opens(
+    header: &EncryptedFileHeader,
+    master_key: &MasterKey,
+    purpose: KeyPurpose,
+) -> bool {
+    uses_legacy_key(header, master_key, purpose).is_some()
+}
+
+/// Whether the header's file key is still wrapped with the master key rather than the
+/// purpose subkey (`None` if neither opens it). Such containers are migrated by
+/// rewriting them.
+pub fn uses_legacy_key(
+    header: &EncryptedFileHeader,
+    master_key: &MasterKey,
+    purpose: KeyPurpose,
+) -> Option<bool> {
+    if header.uses_keyfile || header.validate().is_err() {
+        return None;
+    }
+    open_wrapping(header, master_key, purpose, None)
+        .ok()
+        .map(|(_, legacy)| legacy)
+}
+
 pub fn decrypt_file_with_master_key(
     master_key: &MasterKey,
+    purpose: KeyPurpose,
     keyfile_bytes: Option<&[u8]>,
     container: &EncryptedFileContainer,
 ) -> Result<InnerPayload> {
     let h = &container.header;
+    h.validate()?;
 
     if h.uses_keyfile && keyfile_bytes.is_none() {
         return Err(anyhow!("This file requires a Keyfile. Please select it."));
     }
 
-    let wrapping_key = derive_wrapping_key(master_key, keyfile_bytes);
-    let cipher_wrap =
-        Aes256Gcm::new_from_slice(&*wrapping_key).map_err(|e| anyhow!("Cipher error: {}", e))?;
-
-    let val_nonce = Nonce::from_slice(&h.validation_nonce);
-    match cipher_wrap.decrypt(val_nonce, h.encrypted_validation_tag.as_ref()) {
-        Ok(bytes) => {
-            if !constant_time_eq(&bytes, VALIDATION_MAGIC) {
-                return Err(anyhow!("Validation tag mismatch."));
-            }
-        }
-        Err(_) => {
-            return Err(anyhow!(
-                "Decryption Denied. Password or Keyfile is incorrect."
-            ));
-        }
-    }
+    let (cipher_wrap, _) = open_wrapping(h, master_key, purpose, keyfile_bytes)?;
 
     let file_key_vec = cipher_wrap
         .decrypt(
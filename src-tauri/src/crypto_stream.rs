@@ -1,36 +1,97 @@
 // --- START OF FILE src-tauri/src/crypto_stream.rs ---
 
+use crate::av_guard::with_retry;
+use crate::formats;
 use crate::keychain::MasterKey;
+use crate::parity::{self, BodyReader, ParityMeta};
+use crate::recipient::{self, Encapsulation, Identity, PublicIdentity};
+use crate::resources;
+use crate::subkeys::{self, KeyPurpose};
 use crate::timelock_clock;
+use crate::timelock_puzzle::{self, PuzzleMeta};
 use aes_gcm::{
     aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use anyhow::{anyhow, Context, Result};
+use bincode::Options;
 use rand::{rngs::OsRng, RngCore, SeedableRng, TryRngCore};
 use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use zeroize::{Zeroize, Zeroizing};
 
 // ==========================================
 // --- CONSTANTS ---
 // ==========================================
 
-const CHUNK_SIZE: usize = 1024 * 1024; // 1 MB
-const AES_NONCE_LEN: usize = 12;
-const FILE_KEY_LEN: usize = 32;
-const VALIDATION_MAGIC: &[u8] = b"QRE_VALID";
+pub(crate) const CHUNK_SIZE: usize = 1024 * 1024; // 1 MB
+/// Smallest chunk size a header may record (low resource mode, see resources.rs).
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+pub(crate) const AES_NONCE_LEN: usize = 12;
+pub(crate) const FILE_KEY_LEN: usize = 32;
+pub(crate) const VALIDATION_MAGIC: &[u8] = b"QRE_VALID";
 
 /// Fixed header region size for V7 files (bytes 4 – 4099, after the version u32).
 /// Allows in-place ratchet rewrites without touching ciphertext chunks.
-const HEADER_RESERVED_BYTES: usize = 4096;
+pub(crate) const HEADER_RESERVED_BYTES: usize = 4096;
+
+/// SECURITY: Upper bound for a V5/V6 (variable-length) header. bincode trusts the length
+/// prefixes in the file, so without a limit a crafted String length would allocate
+/// gigabytes before a single byte is read. Real headers are a few hundred bytes.
+const MAX_STREAM_HEADER_BYTES: u64 = 16 * 1024;
+/// Field bounds checked after parsing (see `StreamHeader::validate`).
+const MAX_WRAPPED_SECRET_LEN: usize = 64;
+const MAX_VAULT_ID_LEN: usize = 256;
+const MAX_FILENAME_LEN: usize = 1024;
+pub(crate) const SHA256_LEN: usize = 32;
+/// AES-GCM tag size: no valid chunk can be shorter.
+pub(crate) const GCM_TAG_LEN: usize = 16;
 
 const VERSION_V5: u32 = 5;
 const VERSION_V6: u32 = 6;
 const VERSION_V7: u32 = 7; // V7 adds ratchet + fixed header region
+pub(crate) const VERSION_V8: u32 = 8; // V8 = V7 header region for every file + authenticated trailer
+/// Multi-file archive: V8 header region, then per-entry chunks and an encrypted file
+/// table (see archive.rs). Not readable by `decrypt_file_stream`.
+pub const VERSION_ARCHIVE: u32 = 9;
+/// Public-key container addressed to another vault's identity (see recipient.rs).
+/// Has its own header; not readable by `decrypt_file_stream`.
+pub const VERSION_RECIPIENT: u32 = 10;
+/// Password-only container with an optional hidden payload (see deniable.rs).
+pub const VERSION_DENIABLE: u32 = 11;
+/// V8 whose file key is also wrapped under a hybrid X25519 + ML-KEM-1024 encapsulation to
+/// the locking vault's identity (see `StreamHeader::kem_wrap`). Its header region starts
+/// with the capability notice (see formats.rs).
+pub const VERSION_HYBRID: u32 = 12;
+
+/// V8: the chunk stream ends with `TRAILER_MARKER` followed by an AEAD record holding
+/// (total chunks u64 LE, total plaintext bytes u64 LE, SHA-256 of the plaintext). The
+/// marker can never be a real chunk length (chunks are bounded by CHUNK_SIZE + 4096).
+pub(crate) const TRAILER_MARKER: u32 = u32::MAX;
+const TRAILER_PLAINTEXT_LEN: usize = 16 + SHA256_LEN;
+pub(crate) const TRAILER_RECORD_LEN: usize = TRAILER_PLAINTEXT_LEN + GCM_TAG_LEN;
+
+/// Padded V8 files put a padding record before the trailer: `PADDING_MARKER`, the
+/// padding length (u64 LE) and that many random bytes. Like the trailer marker it can
+/// never be a chunk length.
+pub(crate) const PADDING_MARKER: u32 = u32::MAX - 1;
+const PADDING_RECORD_OVERHEAD: u64 = 4 + 8;
+/// Bounds for `Padding::Bucket`. Below 4 KB a bucket hides nothing the header region
+/// does not already; above 1 GB it mostly wastes disk.
+const MIN_PADDING_BUCKET: u64 = 4 * 1024;
+const MAX_PADDING_BUCKET: u64 = 1024 * 1024 * 1024;
+
+/// Upper bound for `Expiry::max_failed_attempts`.
+pub const MAX_FAILED_ATTEMPTS_LIMIT: u32 = 100;
+
+/// Chunks (MB) between resume checkpoints; smaller files never get a sidecar.
+const CHECKPOINT_INTERVAL: u64 = 64;
+/// Appended to the output path to name the checkpoint sidecar.
+pub const CHECKPOINT_SUFFIX: &str = ".resume";
 
 // ==========================================
 // --- DATA STRUCTURES ---
@@ -50,8 +111,115 @@ pub struct TimeLockMeta {
     pub ratchet_max_seen: u64,
 }
 
+/// Optional container metadata (search index, labels) sealed under a key derived from
+/// the master key alone, so it can be read for browsing without the file's keyfile.
+/// The AAD is the file's base nonce: a sealed blob cannot be moved to another file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SealedMetadata {
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Size obfuscation for a container. The ciphertext length otherwise gives away the
+/// plaintext size to within a chunk; a padded container only reveals its bucket.
+///
+/// The scheme is recorded in the header; the padding itself sits before the trailer,
+/// whose AAD commits to its length and digest, and is dropped on decrypt.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Padding {
+    /// Pad the whole file to the next power of two (at most doubles its size).
+    PowerOfTwo,
+    /// Pad the whole file to the next multiple of this many bytes.
+    Bucket(u64),
+}
+
+impl Padding {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Padding::PowerOfTwo => Ok(()),
+            Padding::Bucket(size) if (MIN_PADDING_BUCKET..=MAX_PADDING_BUCKET).contains(size) => {
+                Ok(())
+            }
+            Padding::Bucket(_) => Err(anyhow!(
+                "Padding bucket must be between {} KB and {} MB.",
+                MIN_PADDING_BUCKET / 1024,
+                MAX_PADDING_BUCKET / (1024 * 1024)
+            )),
+        }
+    }
+
+    /// Final file size for a container that would be `size` bytes with an empty
+    /// padding record.
+    pub fn padded_size(&self, size: u64) -> Result<u64> {
+        match self {
+            Padding::PowerOfTwo => size.checked_next_power_of_two(),
+            Padding::Bucket(bucket) => size.div_ceil(*bucket).checked_mul(*bucket),
+        }
+        .ok_or_else(|| anyhow!("File too large to pad"))
+    }
+}
+
+/// Expiry and self-destruct policy chosen when locking a file to share.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Expiry {
+    /// Unix seconds after which the file refuses to decrypt.
+    pub expires_at: Option<u64>,
+    /// Wrong-credential attempts in a row after which the file destroys itself.
+    pub max_failed_attempts: Option<u32>,
+}
+
+impl Expiry {
+    pub fn validate(&self) -> Result<()> {
+        if self.expires_at.is_none() && self.max_failed_attempts.is_none() {
+            return Err(anyhow!("Set an expiry date or a failed-attempt limit."));
+        }
+        if self
+            .max_failed_attempts
+            .is_some_and(|n| n == 0 || n > MAX_FAILED_ATTEMPTS_LIMIT)
+        {
+            return Err(anyhow!(
+                "The failed-attempt limit must be between 1 and {}.",
+                MAX_FAILED_ATTEMPTS_LIMIT
+            ));
+        }
+        Ok(())
+    }
+
+    /// AAD for the file key wrap. Stripping or editing the policy in the header makes
+    /// the key unwrap fail, so the limits cannot be removed without breaking the file.
+    fn aad(&self) -> Vec<u8> {
+        let mut aad = b"QRE_EXPIRY_V1".to_vec();
+        for value in [self.expires_at, self.max_failed_attempts.map(u64::from)] {
+            aad.push(value.is_some() as u8);
+            aad.extend_from_slice(&value.unwrap_or(0).to_le_bytes());
+        }
+        aad
+    }
+}
+
+/// Expiry state embedded in the StreamHeader (V8 only). The policy is bound to the
+/// file key; the counters are rewritten in place like the time-lock ratchet.
+///
+/// ENFORCEMENT IS LOCAL: it stops the app from opening the file, not someone who
+/// copies it first or edits the counters. A copy taken before the limit keeps its
+/// own counter, and the policy only protects what has not been decrypted yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExpiryMeta {
+    pub policy: Expiry,
+    /// Wrong-credential attempts since the last successful unlock.
+    pub failed_attempts: u32,
+    /// Highest Unix timestamp witnessed at an unlock attempt (see `TimeLockMeta`).
+    pub ratchet_max_seen: u64,
+}
+
 /// Stream header — written unencrypted at the start of every .qre file.
-/// Identical layout for V6 (variable-length) and V7 (fixed 4 KB region).
+/// V7/V8 keep it in a fixed 4 KB region.
+///
+/// `metadata`, `padding`, `expiry`, `parity`, `puzzle`, `chunk_size` and `kem_wrap` are the last fields on
+/// purpose: older V7/V8 headers are followed by zero padding, which bincode reads as `None`. V6 headers are
+/// variable-length (chunks follow immediately), so they are parsed through
+/// `StreamHeaderV6`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StreamHeader {
     pub vault_id: Option<String>,
@@ -63,6 +231,32 @@ pub struct StreamHeader {
     pub original_filename: String,
     pub original_hash: Option<Vec<u8>>,
     pub timelock: Option<TimeLockMeta>,
+    pub metadata: Option<SealedMetadata>,
+    pub padding: Option<Padding>,
+    pub expiry: Option<ExpiryMeta>,
+    pub parity: Option<ParityMeta>,
+    /// Time-lock puzzle gating a time-locked file (see timelock_puzzle.rs). Its progress
+    /// is rewritten in place while it is being solved.
+    pub puzzle: Option<PuzzleMeta>,
+    /// Plaintext bytes per chunk when smaller than `CHUNK_SIZE` (low resource mode).
+    pub chunk_size: Option<u32>,
+    /// V12 only: the encapsulation whose secret is mixed into the key wrapping the FEK,
+    /// so opening the file also takes the vault's identity keypair.
+    pub kem_wrap: Option<Encapsulation>,
+}
+
+/// V6 header — no metadata field. For reading legacy files only.
+#[derive(Serialize, Deserialize, Debug)]
+struct StreamHeaderV6 {
+    pub vault_id: Option<String>,
+    pub validation_nonce: Vec<u8>,
+    pub encrypted_validation_tag: Vec<u8>,
+    pub key_wrapping_nonce: Vec<u8>,
+    pub encrypted_file_key: Vec<u8>,
+    pub base_nonce: Vec<u8>,
+    pub original_filename: String,
+    pub original_hash: Option<Vec<u8>>,
+    pub timelock: Option<TimeLockMeta>,
 }
 
 /// V5 header — no timelock field. For reading legacy files only.
@@ -78,6 +272,112 @@ struct StreamHeaderV5 {
     pub original_hash: Option<Vec<u8>>,
 }
 
+impl StreamHeader {
+    /// Plaintext bytes per chunk; every chunk but the last is this long.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size.map_or(CHUNK_SIZE, |size| size as usize)
+    }
+
+    /// Rejects headers whose fields could panic the decryptor (`Nonce::from_slice` and
+    /// `copy_from_slice` panic on a wrong length) or escape the output directory.
+    pub fn validate(&self) -> Result<()> {
+        let nonces = [
+            ("validation", &self.validation_nonce),
+            ("key wrapping", &self.key_wrapping_nonce),
+            ("base", &self.base_nonce),
+        ];
+        for (name, nonce) in nonces {
+            if nonce.len() != AES_NONCE_LEN {
+                return Err(anyhow!("Malformed header: invalid {} nonce length", name));
+            }
+        }
+        if self.encrypted_validation_tag.len() > MAX_WRAPPED_SECRET_LEN
+            || self.encrypted_file_key.len() > MAX_WRAPPED_SECRET_LEN
+        {
+            return Err(anyhow!("Malformed header: oversized key material"));
+        }
+        if self
+            .vault_id
+            .as_ref()
+            .is_some_and(|v| v.len() > MAX_VAULT_ID_LEN)
+        {
+            return Err(anyhow!("Malformed header: vault id too long"));
+        }
+        if self
+            .original_hash
+            .as_ref()
+            .is_some_and(|h| h.len() != SHA256_LEN)
+        {
+            return Err(anyhow!("Malformed header: invalid hash length"));
+        }
+        if let Some(tl) = &self.timelock {
+            if tl.binding_key_nonce.len() != AES_NONCE_LEN
+                || tl.encrypted_binding_key.len() > MAX_WRAPPED_SECRET_LEN
+            {
+                return Err(anyhow!("Malformed header: invalid time-lock fields"));
+            }
+        }
+        if let Some(meta) = &self.metadata {
+            if meta.nonce.len() != AES_NONCE_LEN || meta.ciphertext.len() > HEADER_RESERVED_BYTES {
+                return Err(anyhow!("Malformed header: invalid metadata fields"));
+            }
+        }
+        if let Some(padding) = &self.padding {
+            padding
+                .validate()
+                .context("Malformed header: invalid padding")?;
+        }
+        if let Some(expiry) = &self.expiry {
+            expiry
+                .policy
+                .validate()
+                .context("Malformed header: invalid expiry")?;
+        }
+        if let Some(parity) = &self.parity {
+            parity
+                .validate()
+                .context("Malformed header: invalid parity")?;
+        }
+        if let Some(puzzle) = &self.puzzle {
+            if self.timelock.is_none() {
+                return Err(anyhow!("Malformed header: puzzle without a time-lock"));
+            }
+            puzzle
+                .validate()
+                .context("Malformed header: invalid time-lock puzzle")?;
+        }
+        if !(MIN_CHUNK_SIZE..=CHUNK_SIZE).contains(&self.chunk_size()) {
+            return Err(anyhow!("Malformed header: invalid chunk size"));
+        }
+        if let Some(kem_wrap) = &self.kem_wrap {
+            kem_wrap
+                .validate()
+                .context("Malformed header: invalid key encapsulation")?;
+        }
+        validate_original_filename(&self.original_filename)
+    }
+}
+
+/// The stored filename is joined onto the output directory, so it must be a single
+/// plain path component — never "..", an absolute path, or a drive prefix.
+pub(crate) fn validate_original_filename(name: &str) -> Result<()> {
+    let single_component = matches!(
+        std::path::Path::new(name)
+            .components()
+            .collect::<Vec<_>>()
+            .as_slice(),
+        [std::path::Component::Normal(_)]
+    );
+    if name.is_empty()
+        || name.len() > MAX_FILENAME_LEN
+        || name.contains(['/', '\\', '\0'])
+        || !single_component
+    {
+        return Err(anyhow!("Malformed header: unsafe original filename"));
+    }
+    Ok(())
+}
+
 impl From<StreamHeaderV5> for StreamHeader {
     fn from(v5: StreamHeaderV5) -> Self {
         Self {
@@ -90,6 +390,36 @@ impl From<StreamHeaderV5> for StreamHeader {
             original_filename: v5.original_filename,
             original_hash: v5.original_hash,
             timelock: None,
+            metadata: None,
+            padding: None,
+            expiry: None,
+            parity: None,
+            puzzle: None,
+            chunk_size: None,
+            kem_wrap: None,
+        }
+    }
+}
+
+impl From<StreamHeaderV6> for StreamHeader {
+    fn from(v6: StreamHeaderV6) -> Self {
+        Self {
+            vault_id: v6.vault_id,
+            validation_nonce: v6.validation_nonce,
+            encrypted_validation_tag: v6.encrypted_validation_tag,
+            key_wrapping_nonce: v6.key_wrapping_nonce,
+            encrypted_file_key: v6.encrypted_file_key,
+            base_nonce: v6.base_nonce,
+            original_filename: v6.original_filename,
+            original_hash: v6.original_hash,
+            timelock: v6.timelock,
+            metadata: None,
+            padding: None,
+            expiry: None,
+            parity: None,
+            puzzle: None,
+            chunk_size: None,
+            kem_wrap: None,
         }
     }
 }
@@ -98,7 +428,7 @@ impl From<StreamHeaderV5> for StreamHeader {
 // --- INTERNAL HELPERS ---
 // ==========================================
 
-fn derive_wrapping_key(
+pub(crate) fn derive_wrapping_key(
     master_key: &MasterKey,
     keyfile_bytes: Option<&[u8]>,
 ) -> Zeroizing<[u8; 32]> {
@@ -116,20 +446,31 @@ fn derive_wrapping_key(
     Zeroizing::new(key)
 }
 
-fn compress_chunk(data: &[u8], level: i32) -> Result<Vec<u8>> {
+/// V12: the FEK is wrapped under H(wrapping key | encapsulated secret), so it takes both
+/// the password-derived key and the vault's identity keypair. The validation tag stays
+/// under the plain wrapping key, so a wrong password is still reported as such.
+fn hybrid_wrapping_key(wrapping_key: &[u8; 32], kem_secret: &[u8; 32]) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_STREAM_HYBRID_V1");
+    hasher.update(wrapping_key);
+    hasher.update(kem_secret);
+    Zeroizing::new(hasher.finalize().into())
+}
+
+pub(crate) fn compress_chunk(data: &[u8], level: i32) -> Result<Vec<u8>> {
     let mut encoder = zstd::Encoder::new(Vec::new(), level)?;
     encoder.write_all(data)?;
     Ok(encoder.finish()?)
 }
 
-fn decompress_chunk(data: &[u8]) -> Result<Vec<u8>> {
+pub(crate) fn decompress_chunk(data: &[u8]) -> Result<Vec<u8>> {
     let mut decoder = zstd::Decoder::new(std::io::Cursor::new(data))?;
     let mut out = Vec::new();
     decoder.read_to_end(&mut out)?;
     Ok(out)
 }
 
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
     }
@@ -180,27 +521,266 @@ fn format_duration_secs(secs: u64) -> String {
 /// Errors are intentionally swallowed — a failed write degrades offline
 /// protection but does not corrupt the file or block future decryption.
 fn update_v7_header_in_place(qre_path: &str, updated_header: &StreamHeader) {
-    let serialized = match bincode::serialize(updated_header) {
-        Ok(b) => b,
-        Err(_) => return,
+    let _ = write_header_region(qre_path, updated_header);
+}
+
+/// The fixed header region of a V7+ file: the serialized header, zero padded. V12
+/// headers (those with `kem_wrap`) are preceded by the capability notice.
+fn encode_header_region(header: &StreamHeader) -> Result<Vec<u8>> {
+    let mut region = match header.kem_wrap {
+        Some(_) => formats::encode_notice(VERSION_HYBRID, header)?,
+        None => Vec::new(),
     };
-    if serialized.len() > HEADER_RESERVED_BYTES {
-        return;
+    bincode::serialize_into(&mut region, header).context("Failed to serialize header")?;
+    if region.len() > HEADER_RESERVED_BYTES {
+        return Err(anyhow!(
+            "Header ({} bytes) exceeds HEADER_RESERVED_BYTES ({}).",
+            region.len(),
+            HEADER_RESERVED_BYTES
+        ));
     }
+    region.resize(HEADER_RESERVED_BYTES, 0);
+    Ok(region)
+}
 
-    let mut file = match OpenOptions::new().write(true).open(qre_path) {
-        Ok(f) => f,
-        Err(_) => return,
-    };
+/// Rewrites the fixed header region (bytes 4–4099) of a V7+ file.
+pub(crate) fn write_header_region(qre_path: &str, updated_header: &StreamHeader) -> Result<()> {
+    let region = encode_header_region(updated_header)?;
 
-    if file.seek(SeekFrom::Start(4)).is_err() {
-        return;
-    } // skip 4-byte version
+    let mut file = OpenOptions::new().write(true).open(qre_path)?;
+    file.seek(SeekFrom::Start(4))?; // skip 4-byte version
+    file.write_all(&region)?;
+    file.flush()?;
+    Ok(())
+}
 
+/// Self-destruct for an expiring file: overwrites the header region (and with it the
+/// wrapped file key, without which the chunks are noise) with random bytes, syncs, and
+/// deletes the file.
+fn destroy_container(qre_path: &str) -> Result<()> {
+    let mut file = OpenOptions::new().write(true).open(qre_path)?;
+    file.seek(SeekFrom::Start(4))?;
     let mut region = vec![0u8; HEADER_RESERVED_BYTES];
-    region[..serialized.len()].copy_from_slice(&serialized);
-    let _ = file.write_all(&region);
-    let _ = file.flush();
+    OsRng
+        .try_fill_bytes(&mut region)
+        .map_err(|e| anyhow!("RNG failure: {}", e))?;
+    file.write_all(&region)?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(qre_path)?;
+    Ok(())
+}
+
+/// V7+ files keep the header in a fixed 4 KB region (ratchet can be rewritten in place).
+fn has_fixed_header(version: u32) -> bool {
+    version >= VERSION_V7
+}
+
+/// Per-chunk nonce: base nonce with the chunk index XORed into its last 8 bytes.
+/// The trailer uses index = total chunks, which no chunk ever uses.
+pub(crate) fn chunk_nonce(base_nonce: &[u8], index: u64) -> [u8; AES_NONCE_LEN] {
+    let mut nonce = [0u8; AES_NONCE_LEN];
+    nonce.copy_from_slice(base_nonce);
+    for (n, i) in nonce[4..].iter_mut().zip(index.to_le_bytes()) {
+        *n ^= i;
+    }
+    nonce
+}
+
+/// Length and SHA-256 of a padding record's random bytes.
+pub(crate) struct PaddingRecord {
+    len: u64,
+    digest: [u8; SHA256_LEN],
+}
+
+/// Padded files bind the padding into the trailer, so it cannot be resized or swapped
+/// without failing authentication.
+fn trailer_aad(original_filename: &str, padding: Option<&PaddingRecord>) -> Vec<u8> {
+    let mut aad = format!("{}:trailer", original_filename).into_bytes();
+    if let Some(p) = padding {
+        aad.extend_from_slice(b":padding:");
+        aad.extend_from_slice(&p.len.to_le_bytes());
+        aad.extend_from_slice(&p.digest);
+    }
+    aad
+}
+
+/// Writes a padding record of `len` random bytes.
+fn write_padding<W: Write>(
+    output: &mut W,
+    len: u64,
+    rng: &mut ChaCha20Rng,
+) -> Result<PaddingRecord> {
+    output.write_all(&PADDING_MARKER.to_le_bytes())?;
+    output.write_all(&len.to_le_bytes())?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(CHUNK_SIZE as u64) as usize;
+        rng.fill_bytes(&mut buf[..n]);
+        hasher.update(&buf[..n]);
+        output.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    Ok(PaddingRecord {
+        len,
+        digest: hasher.finalize().into(),
+    })
+}
+
+/// Reads (and hashes) the padding record that follows `PADDING_MARKER`.
+pub(crate) fn read_padding<R: Read>(input: &mut R) -> Result<PaddingRecord> {
+    let mut len_buf = [0u8; 8];
+    input
+        .read_exact(&mut len_buf)
+        .context("INTEGRITY ERROR: Padding record is truncated.")?;
+    let len = u64::from_le_bytes(len_buf);
+    let mut hasher = Sha256::new();
+    let copied = std::io::copy(&mut input.take(len), &mut hasher)?;
+    if copied != len {
+        return Err(anyhow!("INTEGRITY ERROR: Padding record is truncated."));
+    }
+    Ok(PaddingRecord {
+        len,
+        digest: hasher.finalize().into(),
+    })
+}
+
+fn metadata_cipher(master_key: &MasterKey) -> Result<Aes256Gcm> {
+    let mut hasher = Sha256::new();
+    hasher.update(master_key.0);
+    hasher.update(b"QRE_CONTAINER_METADATA");
+    let key = Zeroizing::new(<[u8; 32]>::from(hasher.finalize()));
+    Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!(e))
+}
+
+fn seal_metadata(
+    master_key: &MasterKey,
+    base_nonce: &[u8],
+    plaintext: &[u8],
+    rng: &mut ChaCha20Rng,
+) -> Result<SealedMetadata> {
+    let mut nonce = [0u8; AES_NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+    let ciphertext = metadata_cipher(master_key)?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: base_nonce,
+            },
+        )
+        .map_err(|_| anyhow!("Metadata encryption failed"))?;
+    Ok(SealedMetadata {
+        nonce: nonce.to_vec(),
+        ciphertext,
+    })
+}
+
+/// Decrypts the sealed metadata of a header. `Ok(None)` if the file has none;
+/// an error if it belongs to a different master key or was tampered with.
+pub fn open_metadata(header: &StreamHeader, master_key: &MasterKey) -> Result<Option<Vec<u8>>> {
+    let Some(meta) = &header.metadata else {
+        return Ok(None);
+    };
+    let plain = metadata_cipher(master_key)?
+        .decrypt(
+            Nonce::from_slice(&meta.nonce),
+            Payload {
+                msg: &meta.ciphertext,
+                aad: &header.base_nonce,
+            },
+        )
+        .map_err(|_| anyhow!("Container metadata could not be decrypted"))?;
+    Ok(Some(plain))
+}
+
+// ==========================================
+// --- HEADER PARSING ---
+// ==========================================
+
+/// Same wire format as `bincode::deserialize` (fixed-width ints, trailing bytes allowed),
+/// plus a hard byte limit.
+fn header_options() -> impl bincode::Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_STREAM_HEADER_BYTES)
+}
+
+/// Parses and validates the header that follows the 4-byte version.
+/// Leaves `reader` positioned at the first chunk.
+pub fn parse_stream_header<R: Read>(version: u32, reader: &mut R) -> Result<StreamHeader> {
+    let header: StreamHeader = match version {
+        VERSION_V5 => {
+            let v5: StreamHeaderV5 = header_options()
+                .deserialize_from(&mut *reader)
+                .context("Failed to parse V5 header")?;
+            v5.into()
+        }
+        VERSION_V6 => {
+            let v6: StreamHeaderV6 = header_options()
+                .deserialize_from(&mut *reader)
+                .context("Failed to parse V6 header")?;
+            v6.into()
+        }
+        VERSION_V7 | VERSION_V8 | VERSION_ARCHIVE | VERSION_HYBRID => {
+            // Read the full fixed region; trailing zero padding is ignored,
+            // leaving the reader positioned at HEADER_RESERVED_BYTES + 4.
+            let mut region = vec![0u8; HEADER_RESERVED_BYTES];
+            reader
+                .read_exact(&mut region)
+                .context("Failed to read V7 header region")?;
+            // V12 regions open with the capability notice, which older releases read.
+            let start = match version {
+                VERSION_HYBRID => formats::notice_len(&region)
+                    .ok_or_else(|| anyhow!("Malformed header: missing capability notice"))?,
+                _ => 0,
+            };
+            let header: StreamHeader = header_options()
+                .deserialize(&region[start..])
+                .context("Failed to parse fixed header region")?;
+            // The writer always zero-pads the region. Anything else in the padding is
+            // tampering (or a bit flip) that would otherwise go unnoticed.
+            let used = start
+                + header_options()
+                    .serialized_size(&header)
+                    .context("Failed to size header")? as usize;
+            if region[used.min(HEADER_RESERVED_BYTES)..]
+                .iter()
+                .any(|&b| b != 0)
+            {
+                return Err(anyhow!(
+                    "Malformed header: non-zero padding in header region"
+                ));
+            }
+            // The encapsulation belongs to V12 only: a V8 header with one, or a V12
+            // header without, is a relabelled file.
+            if header.kem_wrap.is_some() != (version == VERSION_HYBRID) {
+                return Err(anyhow!(
+                    "Malformed header: key encapsulation does not match the format version"
+                ));
+            }
+            header
+        }
+        other => return Err(anyhow!("Unsupported file version: {}", other)),
+    };
+    header.validate()?;
+    Ok(header)
+}
+
+/// Parses the version and header from an in-memory prefix of a .qre file.
+/// Entry point for the fuzz targets and the known-answer tests.
+#[cfg(any(test, feature = "fuzzing"))]
+pub fn parse_stream_header_bytes(data: &[u8]) -> Result<(u32, StreamHeader)> {
+    let mut cursor = std::io::Cursor::new(data);
+    let mut ver_buf = [0u8; 4];
+    cursor
+        .read_exact(&mut ver_buf)
+        .context("Failed to read version")?;
+    let version = u32::from_le_bytes(ver_buf);
+    Ok((version, parse_stream_header(version, &mut cursor)?))
 }
 
 // ==========================================
@@ -221,20 +801,183 @@ pub fn read_timelock_header(path: &str) -> Result<Option<TimeLockMeta>> {
 
     match version {
         VERSION_V5 => Ok(None),
-        VERSION_V6 => {
-            let header: StreamHeader =
-                bincode::deserialize_from(&mut file).context("Failed to read V6 header")?;
-            Ok(header.timelock)
+        _ => Ok(parse_stream_header(version, &mut file)?.timelock),
+    }
+}
+
+/// Reads the version and header of a V5+ container without touching the chunks.
+pub fn read_stream_header(path: &str) -> Result<(u32, StreamHeader)> {
+    let mut file = BufReader::new(File::open(path).context("Failed to open file")?);
+
+    let mut ver_buf = [0u8; 4];
+    file.read_exact(&mut ver_buf)
+        .context("Failed to read version")?;
+    let version = u32::from_le_bytes(ver_buf);
+    Ok((version, parse_stream_header(version, &mut file)?))
+}
+
+/// True if `master_key` alone (no keyfile) opens the header.
+///
+/// Time-locked files ignore keyfiles; for them this checks the binding key, which is
+/// wrapped with the master key only, so the answer does not depend on the lock state.
+pub fn master_key_opens(header: &StreamHeader, master_key: &MasterKey) -> bool {
+    subkeys::wrapping_roots(master_key, KeyPurpose::FileWrapping)
+        .iter()
+        .any(|(root, _)| {
+            let base_wrapping_key = derive_wrapping_key(root, None);
+            let Ok(cipher) = Aes256Gcm::new_from_slice(&*base_wrapping_key) else {
+                return false;
+            };
+            match &header.timelock {
+                Some(tl) => cipher
+                    .decrypt(
+                        Nonce::from_slice(&tl.binding_key_nonce),
+                        tl.encrypted_binding_key.as_ref(),
+                    )
+                    .is_ok(),
+                None => cipher
+                    .decrypt(
+                        Nonce::from_slice(&header.validation_nonce),
+                        header.encrypted_validation_tag.as_ref(),
+                    )
+                    .is_ok_and(|bytes| constant_time_eq(&bytes, VALIDATION_MAGIC)),
+            }
+        })
+}
+
+/// Checks the validation tag and unwraps the file key (FEK) of a header. Files written
+/// before subkeys were wrapped with the master key itself (see subkeys.rs). V12 headers
+/// also need the identity of the vault that locked them.
+pub(crate) fn unwrap_file_cipher(
+    header: &StreamHeader,
+    master_key: &MasterKey,
+    keyfile_bytes: Option<&[u8]>,
+    identity: Option<&Identity>,
+) -> Result<Aes256Gcm> {
+    let mut opened = None;
+    for (root, _) in subkeys::wrapping_roots(master_key, KeyPurpose::FileWrapping) {
+        let wrapping_key = derive_wrapping_key(&root, keyfile_bytes);
+        let cipher = Aes256Gcm::new_from_slice(&*wrapping_key).map_err(|e| anyhow!(e))?;
+        if cipher
+            .decrypt(
+                Nonce::from_slice(&header.validation_nonce),
+                header.encrypted_validation_tag.as_ref(),
+            )
+            .is_ok_and(|bytes| constant_time_eq(&bytes, VALIDATION_MAGIC))
+        {
+            opened = Some((cipher, wrapping_key));
+            break;
         }
-        VERSION_V7 => {
-            let mut region = vec![0u8; HEADER_RESERVED_BYTES];
-            file.read_exact(&mut region)
-                .context("Failed to read V7 header region")?;
-            let header: StreamHeader =
-                bincode::deserialize(&region).context("Failed to parse V7 header")?;
-            Ok(header.timelock)
+    }
+    let Some((cipher_wrap, wrapping_key)) = opened else {
+        return Err(anyhow!(
+            "Decryption Denied. Password or Keyfile is incorrect."
+        ));
+    };
+    let cipher_wrap = match &header.kem_wrap {
+        None => cipher_wrap,
+        Some(kem_wrap) => {
+            let identity = identity.ok_or_else(|| {
+                anyhow!(
+                    "This file is also locked to its vault's identity key, which is not available."
+                )
+            })?;
+            let kem_secret = recipient::decapsulate(identity, kem_wrap)?;
+            let hybrid_key = hybrid_wrapping_key(&wrapping_key, &kem_secret);
+            Aes256Gcm::new_from_slice(&*hybrid_key).map_err(|e| anyhow!(e))?
         }
-        other => Err(anyhow!("Unsupported file version: {}", other)),
+    };
+
+    let aad = header
+        .expiry
+        .as_ref()
+        .map(|e| e.policy.aad())
+        .unwrap_or_default();
+    let file_key_vec = cipher_wrap
+        .decrypt(
+            Nonce::from_slice(&header.key_wrapping_nonce),
+            Payload {
+                msg: &header.encrypted_file_key,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to unwrap file key"))?;
+
+    let file_key = Zeroizing::new(file_key_vec);
+    Aes256Gcm::new_from_slice(&file_key).map_err(|_| anyhow!("Invalid file key"))
+}
+
+// ==========================================
+// --- RESUME CHECKPOINTS ---
+// ==========================================
+// Encrypting a very large file can be interrupted by a crash or by the machine going to
+// sleep. Every `CHECKPOINT_INTERVAL` chunks the output is synced and a JSON sidecar
+// (`<output>.resume`) records how far it got. The sidecar holds no secrets: on resume the
+// file key is unwrapped again from the header already written to the partial output.
+// The sidecar is removed once the trailer is written.
+
+/// Progress of an encryption, as saved in the sidecar.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Checkpoint {
+    input_path: String,
+    input_len: u64,
+    /// Unix seconds; a changed input cannot be resumed.
+    input_modified: Option<u64>,
+    compression_level: i32,
+    chunks_done: u64,
+    plaintext_done: u64,
+    /// Output bytes covered by the checkpoint; anything after it is discarded.
+    output_len: u64,
+}
+
+pub fn checkpoint_path(output_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", output_path, CHECKPOINT_SUFFIX))
+}
+
+fn input_fingerprint(input_path: &str) -> Result<(u64, Option<u64>)> {
+    let meta = fs::metadata(input_path).context("Failed to read input metadata")?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Ok((meta.len(), modified))
+}
+
+fn load_checkpoint(output_path: &str) -> Option<Checkpoint> {
+    let json = fs::read_to_string(checkpoint_path(output_path)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Written to a temp file and renamed, so a crash never leaves half a sidecar.
+fn save_checkpoint(output_path: &str, checkpoint: &Checkpoint) -> Result<()> {
+    let path = checkpoint_path(output_path);
+    let tmp = PathBuf::from(format!("{}.tmp", path.to_string_lossy()));
+    fs::write(&tmp, serde_json::to_vec(checkpoint)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// True if `output_path` is a partial encryption of `input_path` that can be resumed:
+/// the sidecar names this input, the input is unchanged and the output is long enough.
+pub fn can_resume(input_path: &str, output_path: &str) -> bool {
+    let Some(checkpoint) = load_checkpoint(output_path) else {
+        return false;
+    };
+    let output_len = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    checkpoint.input_path == input_path
+        && input_fingerprint(input_path).ok()
+            == Some((checkpoint.input_len, checkpoint.input_modified))
+        && output_len >= checkpoint.output_len
+}
+
+/// Deletes an abandoned partial output together with its sidecar. Does nothing if
+/// `output_path` has no sidecar (it is then a finished container).
+pub fn discard_checkpoint(output_path: &str) {
+    let sidecar = checkpoint_path(output_path);
+    if sidecar.exists() {
+        let _ = fs::remove_file(output_path);
+        let _ = fs::remove_file(sidecar);
     }
 }
 
@@ -244,9 +987,14 @@ pub fn read_timelock_header(path: &str) -> Result<Option<TimeLockMeta>> {
 
 /// Encrypts a file of any size using AES-256-GCM in 1 MB streaming chunks.
 ///
-/// # Version selection
-///   `timelock_until: None`  → V6 file (variable-length header, no ratchet)
-///   `timelock_until: Some`  → V7 file (fixed 4 KB header, ratchet field)
+/// # Format
+///   Always writes V8: fixed 4 KB header region (ratchet-capable for time-locked
+///   files), 1 MB chunks bound to their index, then an authenticated trailer with
+///   the chunk count and plaintext length. V5–V7 remain readable.
+///
+/// # Resume
+///   Past `CHECKPOINT_INTERVAL` chunks a `.resume` sidecar tracks progress, so an
+///   interrupted run can be finished with `resume_file_stream`.
 ///
 /// # Time-lock internals
 ///   A random `binding_key` is generated internally.
@@ -268,9 +1016,67 @@ pub fn encrypt_file_stream(
     compression_level: i32,
     callback: impl Fn(u64, u64),
 ) -> Result<()> {
-    let total_size = fs::metadata(input_path)
-        .context("Failed to read input metadata")?
-        .len();
+    encrypt_file_stream_with_metadata(
+        input_path,
+        output_path,
+        master_key,
+        vault_id,
+        keyfile_bytes,
+        timelock_until,
+        entropy_seed,
+        compression_level,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        callback,
+    )
+}
+
+/// `encrypt_file_stream` plus optional container metadata (see `SealedMetadata`),
+/// sealed into the header, optional size padding (see `Padding`), an optional
+/// expiry policy (see `Expiry`), optional Reed–Solomon parity of `parity_percent` %
+/// (see parity.rs) and, for time-locked files, an optional time-lock puzzle sized for a
+/// computer doing `puzzle_squarings_per_sec` (see timelock_puzzle.rs). The metadata must
+/// fit in the 4 KB header region.
+///
+/// With `hybrid_recipient` (the locking vault's own identity) the file is written as V12:
+/// the FEK is additionally wrapped under a hybrid X25519 + ML-KEM-1024 encapsulation, and
+/// decrypting it takes that vault's identity keypair as well as its master key.
+#[allow(clippy::too_many_arguments)]
+pub fn encrypt_file_stream_with_metadata(
+    input_path: &str,
+    output_path: &str,
+    master_key: &MasterKey,
+    vault_id: &str,
+    keyfile_bytes: Option<&[u8]>,
+    timelock_until: Option<u64>,
+    entropy_seed: Option<[u8; 32]>,
+    compression_level: i32,
+    metadata: Option<&[u8]>,
+    padding: Option<Padding>,
+    expiry: Option<Expiry>,
+    parity_percent: Option<u8>,
+    puzzle_squarings_per_sec: Option<u64>,
+    hybrid_recipient: Option<&PublicIdentity>,
+    callback: impl Fn(u64, u64),
+) -> Result<()> {
+    if puzzle_squarings_per_sec.is_some() && timelock_until.is_none() {
+        return Err(anyhow!("A time-lock puzzle needs an unlock date."));
+    }
+    if let Some(p) = &padding {
+        p.validate()?;
+    }
+    let parity = parity_percent.map(ParityMeta::new).transpose()?;
+    if let Some(e) = &expiry {
+        e.validate()?;
+    }
+    // Low resource mode: smaller chunks and a capped compression level (resources.rs).
+    let limits = resources::limits();
+    let compression_level = limits.compression_level(compression_level);
+    let (total_size, input_modified) = input_fingerprint(input_path)?;
 
     let original_filename = std::path::Path::new(input_path)
         .file_name()
@@ -280,10 +1086,12 @@ pub fn encrypt_file_stream(
 
     // Pre-hash entire plaintext for truncation-attack defense
     let original_hash = {
-        let mut reader =
-            BufReader::new(File::open(input_path).context("Failed to open input for pre-hash")?);
+        let mut reader = BufReader::new(
+            with_retry("open", Path::new(input_path), || File::open(input_path))
+                .context("Failed to open input for pre-hash")?,
+        );
         let mut hasher = Sha256::new();
-        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut buf = vec![0u8; limits.chunk_size];
         loop {
             let n = reader.read(&mut buf)?;
             if n == 0 {
@@ -294,13 +1102,18 @@ pub fn encrypt_file_stream(
         hasher.finalize().to_vec()
     };
 
-    let mut input_file = BufReader::new(File::open(input_path)?);
-    let mut output_file = BufWriter::new(File::create(output_path)?);
-
-    let version: u32 = if timelock_until.is_some() {
-        VERSION_V7
-    } else {
-        VERSION_V6
+    // Opens are retried while an on-access scanner holds the file (see av_guard.rs);
+    // the stream writes below never are.
+    let mut input_file = BufReader::new(with_retry("open", Path::new(input_path), || {
+        File::open(input_path)
+    })?);
+    let mut output_file = BufWriter::new(with_retry("create", Path::new(output_path), || {
+        File::create(output_path)
+    })?);
+
+    let version: u32 = match hybrid_recipient {
+        Some(_) => VERSION_HYBRID,
+        None => VERSION_V8,
     };
     output_file.write_all(&version.to_le_bytes())?;
 
@@ -322,20 +1135,31 @@ pub fn encrypt_file_stream(
     let cipher_file = Aes256Gcm::new_from_slice(&*file_key).map_err(|e| anyhow!(e))?;
 
     // ── TIME-LOCK KEY SETUP ───────────────────────────────────────────────────
+    // Both keys are derived from the file-wrapping subkey, not the master key itself.
     // For time-locked files two wrapping keys are needed:
-    //   base_wrapping_key = H(master || "NO_KEYFILE")
+    //   base_wrapping_key = H(subkey || "NO_KEYFILE")
     //     → encrypts binding_key for storage in the header
-    //   file_wrapping_key = H(master || "KEYFILE_MIX" || SHA-256(binding_key))
+    //   file_wrapping_key = H(subkey || "KEYFILE_MIX" || SHA-256(binding_key))
     //     → encrypts the validation tag and wraps the FEK
     // For normal files only file_wrapping_key is used with caller's keyfile_bytes.
+    // With a puzzle, SHA-256(binding_key) is further mixed with the puzzle key, which
+    // the creator derives directly and everyone else has to compute.
+    let wrapping_root = subkeys::derive(master_key, KeyPurpose::FileWrapping);
+    let mut puzzle_meta = None;
     let (timelock_meta, effective_keyfile_owned): (Option<TimeLockMeta>, Option<Vec<u8>>) =
         if let Some(locked_until) = timelock_until {
             let mut binding_key = Zeroizing::new([0u8; 32]);
             rng.fill_bytes(&mut *binding_key);
 
-            let binding_key_hash: Vec<u8> = Sha256::digest(&*binding_key).to_vec();
+            let mut binding_key_hash: Vec<u8> = Sha256::digest(&*binding_key).to_vec();
+            if let Some(rate) = puzzle_squarings_per_sec {
+                let seconds = locked_until.saturating_sub(timelock_clock::system_time_secs());
+                let (meta, puzzle_key) = timelock_puzzle::create(seconds, rate)?;
+                binding_key_hash = timelock_puzzle::mix(&puzzle_key, &binding_key_hash);
+                puzzle_meta = Some(meta);
+            }
 
-            let base_wrapping_key = derive_wrapping_key(master_key, None);
+            let base_wrapping_key = derive_wrapping_key(&wrapping_root, None);
             let cipher_base =
                 Aes256Gcm::new_from_slice(&*base_wrapping_key).map_err(|e| anyhow!(e))?;
 
@@ -360,7 +1184,7 @@ pub fn encrypt_file_stream(
 
     let effective_keyfile: Option<&[u8]> = effective_keyfile_owned.as_deref().or(keyfile_bytes);
 
-    let wrapping_key = derive_wrapping_key(master_key, effective_keyfile);
+    let wrapping_key = derive_wrapping_key(&wrapping_root, effective_keyfile);
     let cipher_wrap = Aes256Gcm::new_from_slice(&*wrapping_key).map_err(|e| anyhow!(e))?;
 
     let mut val_nonce = [0u8; AES_NONCE_LEN];
@@ -369,15 +1193,37 @@ pub fn encrypt_file_stream(
         .encrypt(Nonce::from_slice(&val_nonce), VALIDATION_MAGIC)
         .map_err(|e| anyhow!("Validation encrypt: {}", e))?;
 
+    // V12: the FEK itself is wrapped under the hybrid key (see `hybrid_wrapping_key`).
+    let (kem_wrap, cipher_fek) = match hybrid_recipient {
+        Some(identity) => {
+            let (kem_wrap, kem_secret) = recipient::encapsulate(identity)?;
+            let hybrid_key = hybrid_wrapping_key(&wrapping_key, &kem_secret);
+            let cipher = Aes256Gcm::new_from_slice(&*hybrid_key).map_err(|e| anyhow!(e))?;
+            (Some(kem_wrap), cipher)
+        }
+        None => (None, cipher_wrap),
+    };
+
     let mut key_wrap_nonce = [0u8; AES_NONCE_LEN];
     rng.fill_bytes(&mut key_wrap_nonce);
-    let encrypted_file_key = cipher_wrap
-        .encrypt(Nonce::from_slice(&key_wrap_nonce), file_key.as_ref())
+    let expiry_aad = expiry.map(|e| e.aad()).unwrap_or_default();
+    let encrypted_file_key = cipher_fek
+        .encrypt(
+            Nonce::from_slice(&key_wrap_nonce),
+            Payload {
+                msg: file_key.as_ref(),
+                aad: &expiry_aad,
+            },
+        )
         .map_err(|e| anyhow!("File key wrap: {}", e))?;
 
     let mut base_nonce = [0u8; AES_NONCE_LEN];
     rng.fill_bytes(&mut base_nonce);
 
+    let sealed_metadata = metadata
+        .map(|plain| seal_metadata(master_key, &base_nonce, plain, &mut rng))
+        .transpose()?;
+
     let header = StreamHeader {
         vault_id: Some(vault_id.to_string()),
         validation_nonce: val_nonce.to_vec(),
@@ -388,46 +1234,136 @@ pub fn encrypt_file_stream(
         original_filename: original_filename.clone(),
         original_hash: Some(original_hash),
         timelock: timelock_meta,
+        metadata: sealed_metadata,
+        padding,
+        expiry: expiry.map(|policy| ExpiryMeta {
+            policy,
+            failed_attempts: 0,
+            ratchet_max_seen: 0,
+        }),
+        parity,
+        puzzle: puzzle_meta,
+        chunk_size: (limits.chunk_size < CHUNK_SIZE).then_some(limits.chunk_size as u32),
+        kem_wrap,
     };
 
-    // Write header — V7 uses fixed padded region; V6 uses variable length
-    if version == VERSION_V7 {
-        let serialized = bincode::serialize(&header).context("Failed to serialize V7 header")?;
-
-        if serialized.len() > HEADER_RESERVED_BYTES {
-            return Err(anyhow!(
-                "V7 header ({} bytes) exceeds HEADER_RESERVED_BYTES ({}).",
-                serialized.len(),
-                HEADER_RESERVED_BYTES
-            ));
-        }
-
-        let mut region = vec![0u8; HEADER_RESERVED_BYTES];
-        region[..serialized.len()].copy_from_slice(&serialized);
-        output_file.write_all(&region)?;
+    // Write header — V7+ uses fixed padded region; V6 used variable length
+    if has_fixed_header(version) {
+        output_file.write_all(&encode_header_region(&header)?)?;
     } else {
         bincode::serialize_into(&mut output_file, &header)
             .context("Failed to serialize V6 header")?;
     }
 
-    // ── STREAMING ENCRYPTION LOOP ─────────────────────────────────────────────
-    let mut buffer = vec![0u8; CHUNK_SIZE];
-    let mut chunk_index: u64 = 0;
-    let mut processed_bytes: u64 = 0;
+    let progress = Checkpoint {
+        input_path: input_path.to_string(),
+        input_len: total_size,
+        input_modified,
+        compression_level,
+        chunks_done: 0,
+        plaintext_done: 0,
+        output_len: 4 + HEADER_RESERVED_BYTES as u64,
+    };
+    let result = write_chunks(
+        &mut input_file,
+        &mut output_file,
+        output_path,
+        &cipher_file,
+        &header,
+        progress,
+        &mut rng,
+        callback,
+    );
+    combined_seed.zeroize();
+    result
+}
 
+/// Continues an encryption interrupted after a checkpoint (see `can_resume`).
+/// `keyfile_bytes` must be the keyfile the encryption was started with, and a V12
+/// partial needs the `identity` it is being locked to.
+/// Time-locked encryptions are not checkpointed and cannot be resumed.
+pub fn resume_file_stream(
+    input_path: &str,
+    output_path: &str,
+    master_key: &MasterKey,
+    keyfile_bytes: Option<&[u8]>,
+    identity: Option<&Identity>,
+    callback: impl Fn(u64, u64),
+) -> Result<()> {
+    if !can_resume(input_path, output_path) {
+        return Err(anyhow!(
+            "There is no interrupted encryption of this file to resume."
+        ));
+    }
+    let mut progress =
+        load_checkpoint(output_path).ok_or_else(|| anyhow!("Resume checkpoint is unreadable"))?;
+    progress.compression_level = resources::limits().compression_level(progress.compression_level);
+    let (version, header) = read_stream_header(output_path)?;
+    if !matches!(version, VERSION_V8 | VERSION_HYBRID) || header.timelock.is_some() {
+        return Err(anyhow!("This encryption cannot be resumed."));
+    }
+    let cipher_file = unwrap_file_cipher(&header, master_key, keyfile_bytes, identity)?;
+
+    let mut input_file = BufReader::new(with_retry("open", Path::new(input_path), || {
+        File::open(input_path)
+    })?);
+    input_file.seek(SeekFrom::Start(progress.plaintext_done))?;
+
+    // Drop whatever was written after the checkpoint, then append from there.
+    let output = with_retry("open", Path::new(output_path), || {
+        OpenOptions::new().write(true).open(output_path)
+    })?;
+    output.set_len(progress.output_len)?;
+    let mut output_file = BufWriter::new(output);
+    output_file.seek(SeekFrom::End(0))?;
+
+    let mut seed = [0u8; 32];
+    OsRng.try_fill_bytes(&mut seed).expect("OS RNG failed");
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    seed.zeroize();
+
+    write_chunks(
+        &mut input_file,
+        &mut output_file,
+        output_path,
+        &cipher_file,
+        &header,
+        progress,
+        &mut rng,
+        callback,
+    )
+}
+
+/// Encrypts the chunks from `progress` onward, then the padding and the trailer.
+/// Saves a checkpoint every `CHECKPOINT_INTERVAL` chunks and removes it when done.
+#[allow(clippy::too_many_arguments)]
+fn write_chunks(
+    input_file: &mut BufReader<File>,
+    output_file: &mut BufWriter<File>,
+    output_path: &str,
+    cipher_file: &Aes256Gcm,
+    header: &StreamHeader,
+    mut progress: Checkpoint,
+    rng: &mut ChaCha20Rng,
+    callback: impl Fn(u64, u64),
+) -> Result<()> {
+    let original_filename = &header.original_filename;
+    let base_nonce = &header.base_nonce;
+    // Time-locked files wrap their key with a binding key that is never stored, so
+    // they could not be resumed anyway.
+    let checkpoints = header.timelock.is_none();
+
+    // ── STREAMING ENCRYPTION LOOP ─────────────────────────────────────────────
+    let mut buffer = vec![0u8; header.chunk_size()];
     loop {
         let n = input_file.read(&mut buffer)?;
         if n == 0 {
             break;
         }
 
-        let compressed = compress_chunk(&buffer[..n], compression_level)?;
-
-        let mut chunk_nonce = base_nonce;
-        let idx_bytes = chunk_index.to_le_bytes();
-        for i in 0..8 {
-            chunk_nonce[4 + i] ^= idx_bytes[i];
-        }
+        let chunk_index = progress.chunks_done;
+        let compressed = compress_chunk(&buffer[..n], progress.compression_level)?;
+        let chunk_nonce = chunk_nonce(base_nonce, chunk_index);
 
         let aad = format!("{}:{}", original_filename, chunk_index);
         let payload = Payload {
@@ -441,14 +1377,123 @@ pub fn encrypt_file_stream(
 
         output_file.write_all(&(ciphertext.len() as u32).to_le_bytes())?;
         output_file.write_all(&ciphertext)?;
-
-        processed_bytes += n as u64;
-        chunk_index += 1;
-        callback(processed_bytes, total_size);
+        progress.output_len += 4 + ciphertext.len() as u64;
+        progress.plaintext_done += n as u64;
+        progress.chunks_done += 1;
+
+        if checkpoints && progress.chunks_done.is_multiple_of(CHECKPOINT_INTERVAL) {
+            output_file.flush()?;
+            output_file.get_ref().sync_data()?;
+            save_checkpoint(output_path, &progress)?;
+        }
+        callback(progress.plaintext_done, progress.input_len);
     }
 
+    // ── SIZE PADDING ──────────────────────────────────────────────────────────
+    let padding_record = match header.padding {
+        Some(p) => {
+            let unpadded =
+                progress.output_len + PADDING_RECORD_OVERHEAD + 4 + TRAILER_RECORD_LEN as u64;
+            let len = p.padded_size(unpadded)? - unpadded;
+            Some(write_padding(output_file, len, rng)?)
+        }
+        None => None,
+    };
+
+    // ── AUTHENTICATED TRAILER (V8) ────────────────────────────────────────────
+    // Commits to how many chunks and bytes there are, so dropping trailing chunks
+    // is detected, and to the whole-file hash, which the header only carries
+    // unauthenticated.
+    let original_hash = header
+        .original_hash
+        .as_deref()
+        .filter(|h| h.len() == SHA256_LEN)
+        .ok_or_else(|| anyhow!("The header is missing its whole-file hash"))?;
+    let mut trailer_plain = [0u8; TRAILER_PLAINTEXT_LEN];
+    trailer_plain[..8].copy_from_slice(&progress.chunks_done.to_le_bytes());
+    trailer_plain[8..16].copy_from_slice(&progress.plaintext_done.to_le_bytes());
+    trailer_plain[16..].copy_from_slice(original_hash);
+    let trailer_aad = trailer_aad(original_filename, padding_record.as_ref());
+    let trailer = cipher_file
+        .encrypt(
+            Nonce::from_slice(&chunk_nonce(base_nonce, progress.chunks_done)),
+            Payload {
+                msg: &trailer_plain,
+                aad: &trailer_aad,
+            },
+        )
+        .map_err(|_| anyhow!("Trailer encryption failed"))?;
+    output_file.write_all(&TRAILER_MARKER.to_le_bytes())?;
+    output_file.write_all(&trailer)?;
+
+    // On a USB drive a successful write may still be sitting in the OS cache; sync, then
+    // read the framing back so a yanked or failing drive is caught before reporting
+    // success. The sidecar stays until the check passes, so a failure can be resumed.
     output_file.flush()?;
-    combined_seed.zeroize();
+    output_file.get_ref().sync_all()?;
+    verify_written(
+        output_path,
+        cipher_file,
+        header,
+        progress.chunks_done,
+        progress.plaintext_done,
+    )?;
+
+    // ── PARITY (archival containers) ──────────────────────────────────────────
+    // Computed over the finished, verified stream and appended after the trailer.
+    parity::append(output_path, header, 4 + HEADER_RESERVED_BYTES as u64)?;
+    let _ = fs::remove_file(checkpoint_path(output_path));
+    Ok(())
+}
+
+/// Quick post-write check of a V8 file: walks the chunk length prefixes without
+/// decrypting them, then authenticates the trailer against the expected counts.
+/// Catches truncated or unreadable output in one pass over the frame headers.
+pub(crate) fn verify_written(
+    output_path: &str,
+    cipher_file: &Aes256Gcm,
+    header: &StreamHeader,
+    chunks: u64,
+    plaintext_len: u64,
+) -> Result<()> {
+    let fail = |e: anyhow::Error| anyhow!("Written file failed verification: {}", e);
+    let mut input = BufReader::new(File::open(output_path).map_err(|e| fail(e.into()))?);
+    input
+        .seek(SeekFrom::Start(4 + HEADER_RESERVED_BYTES as u64))
+        .map_err(|e| fail(e.into()))?;
+
+    let mut seen = 0u64;
+    let mut padding = None;
+    loop {
+        let mut marker_buf = [0u8; 4];
+        input
+            .read_exact(&mut marker_buf)
+            .map_err(|_| fail(anyhow!("the file ends before its trailer")))?;
+        match u32::from_le_bytes(marker_buf) {
+            TRAILER_MARKER => break,
+            PADDING_MARKER if header.padding.is_some() && padding.is_none() => {
+                padding = Some(read_padding(&mut input).map_err(fail)?);
+            }
+            len => {
+                input
+                    .seek_relative(len as i64)
+                    .map_err(|e| fail(e.into()))?;
+                seen += 1;
+            }
+        }
+    }
+    if seen != chunks {
+        return Err(fail(anyhow!("found {} of {} chunks", seen, chunks)));
+    }
+    verify_trailer(
+        &mut input,
+        cipher_file,
+        header,
+        chunks,
+        plaintext_len,
+        padding.as_ref(),
+    )
+    .map_err(fail)?;
     Ok(())
 }
 
@@ -456,63 +1501,104 @@ pub fn encrypt_file_stream(
 // --- STREAM DECRYPTOR ---
 // ==========================================
 
-/// Decrypts a V5, V6, or V7 `.qre` file back to disk.
-///
-/// # Time-lock enforcement
-/// Returns `Err("TIME_LOCKED:<unix_ts>:<human msg>")` when locked.
-///
-/// # Clock verification
-/// V7: NTP (online) + ratchet (offline) — full two-layer protection.
-/// V6: NTP (online) + system clock (offline) — no ratchet possible.
-/// V5: no time-lock.
-///
-/// # Ratchet update (V7 only)
-/// On every failed unlock attempt the highest witnessed timestamp is written
-/// back into the file header in-place. This prevents offline clock rewinds
-/// from bypassing a lock that was previously accessed while online.
-pub fn decrypt_file_stream(
-    input_path: &str,
-    output_dir: &str,
-    master_key: &MasterKey,
-    keyfile_bytes: Option<&[u8]>,
-    callback: impl Fn(u64, u64),
-) -> Result<String> {
-    let file_size = fs::metadata(input_path)?.len();
-    let mut input_file = BufReader::new(File::open(input_path)?);
+/// What an authenticated V8 trailer commits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Trailer {
+    pub chunks: u64,
+    pub plaintext_len: u64,
+    /// SHA-256 of the whole plaintext. Unlike `StreamHeader::original_hash` it cannot be
+    /// changed or removed without the file key.
+    pub hash: [u8; SHA256_LEN],
+}
 
-    let mut ver_buf = [0u8; 4];
-    input_file.read_exact(&mut ver_buf)?;
-    let version = u32::from_le_bytes(ver_buf);
+/// Authenticates a V8 trailer record and returns what it commits to.
+pub(crate) fn open_trailer(
+    record: &[u8; TRAILER_RECORD_LEN],
+    cipher_file: &Aes256Gcm,
+    header: &StreamHeader,
+    chunks_seen: u64,
+    padding: Option<&PaddingRecord>,
+) -> Result<Trailer> {
+    // The nonce index is the number of chunks we saw: if chunks were dropped or added,
+    // the trailer was sealed under a different nonce and fails authentication.
+    let aad = trailer_aad(&header.original_filename, padding);
+    let plain = cipher_file
+        .decrypt(
+            Nonce::from_slice(&chunk_nonce(&header.base_nonce, chunks_seen)),
+            Payload {
+                msg: record,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| {
+            anyhow!(
+                "INTEGRITY ERROR: Trailer check failed. Chunks or padding were removed or added."
+            )
+        })?;
+    Ok(Trailer {
+        chunks: u64::from_le_bytes(plain[..8].try_into().unwrap()),
+        plaintext_len: u64::from_le_bytes(plain[8..16].try_into().unwrap()),
+        hash: plain[16..].try_into().unwrap(),
+    })
+}
 
-    // ── HEADER DESERIALIZATION ────────────────────────────────────────────────
-    let header: StreamHeader = match version {
-        VERSION_V5 => {
-            let v5: StreamHeaderV5 =
-                bincode::deserialize_from(&mut input_file).context("Failed to parse V5 header")?;
-            v5.into()
-        }
-        VERSION_V6 => {
-            bincode::deserialize_from(&mut input_file).context("Failed to parse V6 header")?
-        }
-        VERSION_V7 => {
-            // Read the full fixed region; bincode::deserialize ignores zero padding,
-            // leaving input_file positioned at HEADER_RESERVED_BYTES + 4.
-            let mut region = vec![0u8; HEADER_RESERVED_BYTES];
-            input_file
-                .read_exact(&mut region)
-                .context("Failed to read V7 header region")?;
-            bincode::deserialize::<StreamHeader>(&region).context("Failed to parse V7 header")?
-        }
-        other => return Err(anyhow!("Unsupported file version: {}", other)),
-    };
+/// Reads and checks the V8 trailer that follows `TRAILER_MARKER`. The counts must match
+/// what was actually decrypted, and nothing may follow the trailer. Returns the trailer,
+/// whose hash the caller checks once the output is complete.
+fn verify_trailer<R: Read>(
+    input: &mut R,
+    cipher_file: &Aes256Gcm,
+    header: &StreamHeader,
+    chunks_seen: u64,
+    plaintext_seen: u64,
+    padding: Option<&PaddingRecord>,
+) -> Result<Trailer> {
+    let mut record = [0u8; TRAILER_RECORD_LEN];
+    input
+        .read_exact(&mut record)
+        .context("INTEGRITY ERROR: Trailer is truncated.")?;
+
+    let trailer = open_trailer(&record, cipher_file, header, chunks_seen, padding)?;
+    if trailer.chunks != chunks_seen || trailer.plaintext_len != plaintext_seen {
+        return Err(anyhow!(
+            "INTEGRITY ERROR: Expected {} chunks / {} bytes, found {} / {}.",
+            trailer.chunks,
+            trailer.plaintext_len,
+            chunks_seen,
+            plaintext_seen
+        ));
+    }
 
+    let mut probe = [0u8; 1];
+    if input.read(&mut probe)? != 0 {
+        return Err(anyhow!(
+            "INTEGRITY ERROR: Unexpected data after the trailer."
+        ));
+    }
+    Ok(trailer)
+}
+
+/// The unlock checks of a V5–V8 or V12 file, in order: time-lock (updating its ratchet), expiry,
+/// then the key, counting failed attempts where the file has a limit. Returns the file
+/// cipher and hands `input_file` back; it is closed first if the file has to be destroyed.
+/// Shared by `decrypt_file_stream` and the partial recovery (salvage.rs), so neither can
+/// be used to get around the other's checks.
+pub(crate) fn unlock_stream_cipher(
+    input_path: &str,
+    version: u32,
+    header: &StreamHeader,
+    master_key: &MasterKey,
+    keyfile_bytes: Option<&[u8]>,
+    identity: Option<&Identity>,
+    input_file: BufReader<File>,
+) -> Result<(Aes256Gcm, BufReader<File>)> {
     // ── TIME-LOCK CHECK ──────────────────────────────────────────────────────
     // Runs BEFORE key derivation — never reveals password correctness while locked.
     let effective_keyfile: Option<Vec<u8>> = if let Some(ref tl) = header.timelock {
         // Get the authoritative current time:
         //   V7 → NTP (online) or max(system_clock, ratchet) (offline)
         //   V6 → NTP (online) or system_clock (offline) — no ratchet available
-        let authoritative_time = if version == VERSION_V7 {
+        let authoritative_time = if has_fixed_header(version) {
             timelock_clock::get_authoritative_time(tl.ratchet_max_seen)
         } else {
             match timelock_clock::get_ntp_time() {
@@ -526,7 +1612,7 @@ pub fn decrypt_file_stream(
             // Persist the highest witnessed time back into the V7 header.
             // On the next attempt — even offline with a rewound clock — the
             // ratchet value will be read from the file and override the clock.
-            if version == VERSION_V7 {
+            if has_fixed_header(version) {
                 let new_ratchet = tl.ratchet_max_seen.max(authoritative_time);
                 if new_ratchet > tl.ratchet_max_seen {
                     let mut updated = header.clone();
@@ -546,49 +1632,200 @@ pub fn decrypt_file_stream(
         }
 
         // Lock expired — decrypt the binding key with the BASE wrapping key
-        let base_wrapping_key = derive_wrapping_key(master_key, None);
-        let cipher_base = Aes256Gcm::new_from_slice(&*base_wrapping_key).map_err(|e| anyhow!(e))?;
-
-        let binding_key_vec = cipher_base
-            .decrypt(
-                Nonce::from_slice(&tl.binding_key_nonce),
-                tl.encrypted_binding_key.as_ref(),
-            )
-            .map_err(|_| anyhow!("Failed to decrypt binding key. Wrong master password?"))?;
+        let binding_key_vec = subkeys::wrapping_roots(master_key, KeyPurpose::FileWrapping)
+            .iter()
+            .find_map(|(root, _)| {
+                let base_wrapping_key = derive_wrapping_key(root, None);
+                Aes256Gcm::new_from_slice(&*base_wrapping_key)
+                    .ok()?
+                    .decrypt(
+                        Nonce::from_slice(&tl.binding_key_nonce),
+                        tl.encrypted_binding_key.as_ref(),
+                    )
+                    .ok()
+            })
+            .ok_or_else(|| anyhow!("Failed to decrypt binding key. Wrong master password?"))?;
 
         let binding_key_hash = Sha256::digest(&binding_key_vec).to_vec();
-        Some(binding_key_hash)
+        match &header.puzzle {
+            // The date has passed on the clock, but the puzzle is the real lock.
+            Some(puzzle) if !puzzle.is_solved() => {
+                return Err(anyhow!(
+                    "TIME_PUZZLE:{}:The date has passed, but this file's time-lock puzzle is \
+                     only {}% solved. Keep solving it to open the file.",
+                    puzzle.percent(),
+                    puzzle.percent()
+                ));
+            }
+            Some(puzzle) => {
+                let puzzle_key = timelock_puzzle::solved_key(puzzle)?;
+                Some(timelock_puzzle::mix(&puzzle_key, &binding_key_hash))
+            }
+            None => Some(binding_key_hash),
+        }
     } else {
         keyfile_bytes.map(|b| b.to_vec())
     };
 
-    // ── VALIDATION AND KEY UNWRAP ─────────────────────────────────────────────
-    let effective_keyfile_ref: Option<&[u8]> = effective_keyfile.as_deref();
-    let wrapping_key = derive_wrapping_key(master_key, effective_keyfile_ref);
-    let cipher_wrap = Aes256Gcm::new_from_slice(&*wrapping_key).map_err(|e| anyhow!(e))?;
+    // ── EXPIRY CHECK ─────────────────────────────────────────────────────────
+    // Also before key derivation, with the same clock and ratchet as the time-lock.
+    // Only V8 headers carry an expiry, so the header can always be rewritten in place.
+    let mut expiry_header = None;
+    if let Some(ref ex) = header.expiry {
+        let now = timelock_clock::get_authoritative_time(ex.ratchet_max_seen);
+        let mut updated = header.clone();
+        if let Some(ref mut uex) = updated.expiry {
+            uex.ratchet_max_seen = ex.ratchet_max_seen.max(now);
+        }
+        if ex.policy.expires_at.is_some_and(|at| now >= at) {
+            update_v7_header_in_place(input_path, &updated);
+            return Err(anyhow!(
+                "EXPIRED:{}:This file expired {} ago and can no longer be opened.",
+                ex.policy.expires_at.unwrap_or(0),
+                format_duration_secs(now.saturating_sub(ex.policy.expires_at.unwrap_or(0)))
+            ));
+        }
+        expiry_header = Some(updated);
+    }
 
-    match cipher_wrap.decrypt(
-        Nonce::from_slice(&header.validation_nonce),
-        header.encrypted_validation_tag.as_ref(),
-    ) {
-        Ok(bytes) if constant_time_eq(&bytes, VALIDATION_MAGIC) => {}
-        _ => {
+    // ── VALIDATION AND KEY UNWRAP ─────────────────────────────────────────────
+    let unwrapped = unwrap_file_cipher(header, master_key, effective_keyfile.as_deref(), identity);
+    let cipher_file = match unwrapped {
+        Ok(cipher) => cipher,
+        Err(e) => {
+            let limit = header
+                .expiry
+                .as_ref()
+                .and_then(|ex| ex.policy.max_failed_attempts);
+            let (Some(limit), Some(mut updated)) = (limit, expiry_header) else {
+                return Err(e);
+            };
+            if !e.to_string().starts_with("Decryption Denied") {
+                return Err(e);
+            }
+            let failed = match updated.expiry {
+                Some(ref mut uex) => {
+                    uex.failed_attempts = uex.failed_attempts.saturating_add(1);
+                    uex.failed_attempts
+                }
+                None => return Err(e),
+            };
+            if failed >= limit {
+                drop(input_file);
+                destroy_container(input_path).context(
+                    "The failed-attempt limit was reached, but destroying the file failed",
+                )?;
+                return Err(anyhow!(
+                    "DESTROYED:The failed-attempt limit was reached. The file has been destroyed."
+                ));
+            }
+            update_v7_header_in_place(input_path, &updated);
+            let left = limit - failed;
             return Err(anyhow!(
-                "Decryption Denied. Password or Keyfile is incorrect."
-            ))
+                "{} {} attempt{} left before this file destroys itself.",
+                e,
+                left,
+                plural_s(left as u64)
+            ));
+        }
+    };
+    // A successful unlock resets the failure count and stores the witnessed time.
+    if let (Some(old), Some(mut updated)) = (&header.expiry, expiry_header) {
+        if let Some(ref mut uex) = updated.expiry {
+            if old.failed_attempts > 0 || uex.ratchet_max_seen > old.ratchet_max_seen {
+                uex.failed_attempts = 0;
+                update_v7_header_in_place(input_path, &updated);
+            }
         }
     }
+    Ok((cipher_file, input_file))
+}
 
-    let file_key_vec = cipher_wrap
-        .decrypt(
-            Nonce::from_slice(&header.key_wrapping_nonce),
-            header.encrypted_file_key.as_ref(),
-        )
-        .map_err(|_| anyhow!("Failed to unwrap file key"))?;
+/// Decrypts a V5, V6, V7 or V8 `.qre` file back to disk. V12 files need the vault
+/// identity too, see `decrypt_file_stream_with_identity`.
+///
+/// # Time-lock enforcement
+/// Returns `Err("TIME_LOCKED:<unix_ts>:<human msg>")` when locked.
+///
+/// # Chunk framing
+/// Every version binds each chunk to its index (nonce + AAD), which catches
+/// reordering and duplication. V8 additionally requires the authenticated trailer,
+/// so removed trailing chunks, a stripped trailer, or appended data are rejected.
+/// V8 checks the output against the whole-file SHA-256 in the trailer; the copy in the
+/// header is not authenticated and is only used for V5–V7.
+/// Padded V8 files must carry exactly one padding record, right before the trailer;
+/// it is checked against the trailer and discarded.
+/// On any failure the partial output file is deleted.
+///
+/// # Parity
+/// Containers locked with parity are read through it (see parity.rs): damaged blocks
+/// are rebuilt before the chunk checks above ever see them.
+///
+/// # Clock verification
+/// V7/V8: NTP (online) + ratchet (offline) — full two-layer protection.
+/// V6: NTP (online) + system clock (offline) — no ratchet possible.
+/// V5: no time-lock.
+///
+/// # Ratchet update (V7/V8 only)
+/// On every failed unlock attempt the highest witnessed timestamp is written
+/// back into the file header in-place. This prevents offline clock rewinds
+/// from bypassing a lock that was previously accessed while online.
+pub fn decrypt_file_stream(
+    input_path: &str,
+    output_dir: &str,
+    master_key: &MasterKey,
+    keyfile_bytes: Option<&[u8]>,
+    callback: impl Fn(u64, u64),
+) -> Result<String> {
+    decrypt_file_stream_with_identity(
+        input_path,
+        output_dir,
+        master_key,
+        keyfile_bytes,
+        None,
+        callback,
+    )
+}
 
-    let file_key = Zeroizing::new(file_key_vec);
-    let cipher_file =
-        Aes256Gcm::new_from_slice(&file_key).map_err(|_| anyhow!("Invalid file key"))?;
+/// `decrypt_file_stream` for any V5–V8 or V12 file. `identity` is the identity of the
+/// vault that locked a V12 file; the other versions ignore it.
+pub fn decrypt_file_stream_with_identity(
+    input_path: &str,
+    output_dir: &str,
+    master_key: &MasterKey,
+    keyfile_bytes: Option<&[u8]>,
+    identity: Option<&Identity>,
+    callback: impl Fn(u64, u64),
+) -> Result<String> {
+    let file_size = fs::metadata(input_path)?.len();
+    let mut input_file = BufReader::new(with_retry("open", Path::new(input_path), || {
+        File::open(input_path)
+    })?);
+
+    let mut ver_buf = [0u8; 4];
+    input_file.read_exact(&mut ver_buf)?;
+    let version = u32::from_le_bytes(ver_buf);
+
+    // ── HEADER DESERIALIZATION ────────────────────────────────────────────────
+    let header = parse_stream_header(version, &mut input_file)?;
+    if version == VERSION_ARCHIVE {
+        return Err(anyhow!(
+            "This is a multi-file archive. Open it with the archive viewer."
+        ));
+    }
+    let requires_trailer = version >= VERSION_V8;
+
+    let (cipher_file, input_file) = unlock_stream_cipher(
+        input_path,
+        version,
+        &header,
+        master_key,
+        keyfile_bytes,
+        identity,
+        input_file,
+    )?;
+    // Containers with parity are read through it, repairing damaged blocks on the way.
+    let mut input_file = BodyReader::new(input_file, &header, false)?;
 
     // ── OUTPUT FILE ───────────────────────────────────────────────────────────
     let raw_out = std::path::Path::new(output_dir).join(&header.original_filename);
@@ -599,65 +1836,127 @@ pub fn decrypt_file_stream(
         .to_string_lossy()
         .to_string();
 
-    let mut output_file = BufWriter::new(File::create(&final_out)?);
+    let mut output_file = BufWriter::new(with_retry("create", &final_out, || {
+        File::create(&final_out)
+    })?);
     let mut output_hasher = Sha256::new();
 
     // ── DECRYPTION LOOP ───────────────────────────────────────────────────────
-    let mut chunk_index: u64 = 0;
-    let mut size_buf = [0u8; 4];
-    let mut processed: u64 = 0;
+    let stream_result = (|| -> Result<Option<Trailer>> {
+        let mut chunk_index: u64 = 0;
+        let mut size_buf = [0u8; 4];
+        let mut processed: u64 = 0;
+        let mut plaintext_len: u64 = 0;
+        let mut trailer = None;
+        let mut padding_record: Option<PaddingRecord> = None;
 
-    loop {
-        match input_file.read_exact(&mut size_buf) {
-            Ok(_) => {}
-            Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(anyhow!("Read error at chunk {}: {}", chunk_index, e)),
-        }
+        loop {
+            match input_file.read_exact(&mut size_buf) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(anyhow!("Read error at chunk {}: {}", chunk_index, e)),
+            }
 
-        let chunk_len = u32::from_le_bytes(size_buf) as usize;
-        if chunk_len > CHUNK_SIZE + 4096 {
-            return Err(anyhow!(
-                "Chunk {} size anomaly ({} bytes) — file may be corrupt.",
-                chunk_index,
-                chunk_len
-            ));
-        }
+            let mut marker = u32::from_le_bytes(size_buf);
+            if requires_trailer && header.padding.is_some() && marker == PADDING_MARKER {
+                padding_record = Some(read_padding(&mut input_file)?);
+                input_file.read_exact(&mut size_buf).context(
+                    "INTEGRITY ERROR: The file ends after its padding without its trailer. \
+                     It has been truncated.",
+                )?;
+                marker = u32::from_le_bytes(size_buf);
+                if marker != TRAILER_MARKER {
+                    return Err(anyhow!(
+                        "INTEGRITY ERROR: Unexpected data after the size padding."
+                    ));
+                }
+            }
+            if requires_trailer && marker == TRAILER_MARKER {
+                if header.padding.is_some() && padding_record.is_none() {
+                    return Err(anyhow!("INTEGRITY ERROR: The size padding is missing."));
+                }
+                trailer = Some(verify_trailer(
+                    &mut input_file,
+                    &cipher_file,
+                    &header,
+                    chunk_index,
+                    plaintext_len,
+                    padding_record.as_ref(),
+                )?);
+                break;
+            }
 
-        let mut ciphertext = vec![0u8; chunk_len];
-        input_file.read_exact(&mut ciphertext)?;
+            let chunk_len = marker as usize;
+            if !(GCM_TAG_LEN..=CHUNK_SIZE + 4096).contains(&chunk_len) {
+                return Err(anyhow!(
+                    "Chunk {} size anomaly ({} bytes) — file may be corrupt.",
+                    chunk_index,
+                    chunk_len
+                ));
+            }
 
-        let mut chunk_nonce = [0u8; AES_NONCE_LEN];
-        chunk_nonce.copy_from_slice(&header.base_nonce);
-        let idx_bytes = chunk_index.to_le_bytes();
-        for i in 0..8 {
-            chunk_nonce[4 + i] ^= idx_bytes[i];
-        }
+            let mut ciphertext = vec![0u8; chunk_len];
+            input_file.read_exact(&mut ciphertext)?;
 
-        let aad = format!("{}:{}", header.original_filename, chunk_index);
-        let payload = Payload {
-            msg: &ciphertext,
-            aad: aad.as_bytes(),
-        };
+            let chunk_nonce = chunk_nonce(&header.base_nonce, chunk_index);
+            let aad = format!("{}:{}", header.original_filename, chunk_index);
+            let payload = Payload {
+                msg: &ciphertext,
+                aad: aad.as_bytes(),
+            };
 
-        let compressed = cipher_file
-            .decrypt(Nonce::from_slice(&chunk_nonce), payload)
-            .map_err(|_| anyhow!("Chunk {} integrity check failed", chunk_index))?;
+            let compressed = cipher_file
+                .decrypt(Nonce::from_slice(&chunk_nonce), payload)
+                .map_err(|_| anyhow!("Chunk {} integrity check failed", chunk_index))?;
 
-        let plaintext = decompress_chunk(&compressed)?;
-        output_hasher.update(&plaintext);
-        output_file.write_all(&plaintext)?;
+            let plaintext = decompress_chunk(&compressed)?;
+            output_hasher.update(&plaintext);
+            output_file.write_all(&plaintext)?;
 
-        processed += chunk_len as u64;
-        chunk_index += 1;
-        if chunk_index.is_multiple_of(5) {
-            callback(processed, file_size);
+            processed += chunk_len as u64;
+            plaintext_len += plaintext.len() as u64;
+            chunk_index += 1;
+            if chunk_index.is_multiple_of(5) {
+                callback(processed, file_size);
+            }
         }
-    }
 
-    output_file.flush()?;
+        if requires_trailer && trailer.is_none() {
+            return Err(anyhow!(
+                "INTEGRITY ERROR: The file ends after chunk {} without its trailer. \
+                 It has been truncated.",
+                chunk_index
+            ));
+        }
+        output_file.flush()?;
+        output_file.get_ref().sync_all()?;
+        Ok(trailer)
+    })();
+
+    let trailer = match stream_result {
+        Ok(trailer) => trailer,
+        Err(e) => {
+            drop(output_file);
+            let _ = fs::remove_file(&final_out);
+            return Err(e);
+        }
+    };
+    drop(output_file);
+    if let Some(stats) = input_file.stats().filter(|s| s.repaired_blocks > 0) {
+        tracing::info!(
+            path = input_path,
+            repaired = stats.repaired_blocks,
+            "Repaired damaged blocks from parity"
+        );
+    }
 
-    // Whole-file integrity check (truncation attack defense)
-    if let Some(expected) = &header.original_hash {
+    // Whole-file integrity check (truncation attack defense). V8 takes the hash from
+    // the authenticated trailer; the header's copy can be edited without the key.
+    let expected = match trailer {
+        Some(trailer) => Some(trailer.hash.to_vec()),
+        None => header.original_hash.clone(),
+    };
+    if let Some(expected) = &expected {
         let actual = output_hasher.finalize().to_vec();
         if !constant_time_eq(&actual, expected) {
             let _ = fs::remove_file(&final_out);
@@ -0,0 +1,558 @@
+// --- START OF FILE deniable.rs ---
+
+// ==========================================
+// --- DENIABLE CONTAINERS (V11) ---
+// ==========================================
+// A container that opens with its own password and can hide a second file behind a
+// second password, in the spirit of VeraCrypt hidden volumes. Under coercion the owner
+// gives up the decoy password; nothing in the file shows whether a hidden payload exists.
+//
+// Every V11 container has the same shape whether or not it hides anything:
+//
+//   [version u32 = 11][salt 0][salt 1][slot 0][slot 1][data area]
+//
+//   slot       = nonce | AES-GCM(Argon2id(password, salt_i); payload key | offset | length)
+//   data area  = decoy chunks, then random bytes, with the hidden chunks (if any) at the end
+//
+// A container without a hidden payload fills the unused slot, its salt and the reserve
+// with random bytes, which cannot be told apart from a slot and chunks under a key
+// nobody knows. Slot order is random, chunks have no plaintext framing (lengths come from
+// the slot), and unlocking always derives keys for both slots so timing does not reveal
+// which one opened. The reserve size is chosen when locking and is the same whether or
+// not it is used, so the hidden file must fit inside it.
+//
+// Unlike vault containers these are not tied to the vault key: a vault key opens for
+// anyone holding the vault, which is exactly what deniability must not depend on.
+//
+// LIMITS: deniability covers the file alone. Recent-file lists, thumbnails, the audit
+// log or a decrypted copy left on disk can still show the hidden file was opened.
+
+use crate::av_guard::with_retry;
+use crate::crypto_stream::{self, AES_NONCE_LEN, CHUNK_SIZE, GCM_TAG_LEN, VERSION_DENIABLE};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{rngs::OsRng, RngCore, SeedableRng, TryRngCore};
+use rand_chacha::ChaCha20Rng;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const SLOT_PLAINTEXT_LEN: usize = KEY_LEN + 8 + 8;
+const SLOT_LEN: usize = AES_NONCE_LEN + SLOT_PLAINTEXT_LEN + GCM_TAG_LEN;
+const SLOT_COUNT: usize = 2;
+const HEADER_LEN: usize = 4 + SLOT_COUNT * (SALT_LEN + SLOT_LEN);
+/// The data area is rounded up to this, so the reserve does not show its exact size.
+const AREA_ALIGN: u64 = 64 * 1024;
+/// Default reserve: as large as the decoy, and at least this much.
+const MIN_DEFAULT_RESERVE: u64 = 1024 * 1024;
+pub const MIN_PASSWORD_LEN: usize = 8;
+const MAX_FILENAME_LEN: usize = 255;
+
+/// Fixed for the format: the parameters cannot be stored without showing they exist.
+#[cfg(not(test))]
+const KDF: (u32, u32, u32) = (65_536, 3, 4);
+#[cfg(test)]
+const KDF: (u32, u32, u32) = (8_192, 1, 1);
+
+/// What one password unlocks: a random key and where its chunks sit in the data area.
+struct SlotContent {
+    key: Zeroizing<[u8; KEY_LEN]>,
+    offset: u64,
+    /// Plaintext length (name header + file).
+    length: u64,
+}
+
+impl SlotContent {
+    fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut out = Zeroizing::new(Vec::with_capacity(SLOT_PLAINTEXT_LEN));
+        out.extend_from_slice(self.key.as_ref());
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&self.length.to_le_bytes());
+        out
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != SLOT_PLAINTEXT_LEN {
+            return Err(anyhow!("Corrupt key slot"));
+        }
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        key.copy_from_slice(&bytes[..KEY_LEN]);
+        let word = |at: usize| {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&bytes[at..at + 8]);
+            u64::from_le_bytes(buf)
+        };
+        Ok(Self {
+            key,
+            offset: word(KEY_LEN),
+            length: word(KEY_LEN + 8),
+        })
+    }
+}
+
+/// A file to seal: its path and the password that will open it.
+pub struct DeniablePayload<'a> {
+    pub path: &'a Path,
+    pub password: &'a str,
+}
+
+// ==========================================
+// --- HELPERS ---
+// ==========================================
+
+fn fill_random(buf: &mut [u8]) -> Result<()> {
+    OsRng
+        .try_fill_bytes(buf)
+        .map_err(|e| anyhow!("RNG failure: {}", e))
+}
+
+fn derive_slot_key(password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; KEY_LEN]>> {
+    let (m, t, p) = KDF;
+    let params =
+        Params::new(m, t, p, Some(KEY_LEN)).map_err(|e| anyhow!("KDF param error: {}", e))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Both salts are bound into every slot, so slots cannot be swapped between containers.
+fn slot_aad(salts: &[[u8; SALT_LEN]; SLOT_COUNT]) -> Vec<u8> {
+    let mut aad = VERSION_DENIABLE.to_le_bytes().to_vec();
+    for salt in salts {
+        aad.extend_from_slice(salt);
+    }
+    aad
+}
+
+fn chunk_nonce(index: u64) -> [u8; AES_NONCE_LEN] {
+    crypto_stream::chunk_nonce(&[0u8; AES_NONCE_LEN], index)
+}
+
+fn chunk_aad(index: u64, is_final: bool) -> [u8; 9] {
+    let mut aad = [0u8; 9];
+    aad[..8].copy_from_slice(&index.to_le_bytes());
+    aad[8] = is_final as u8;
+    aad
+}
+
+/// Ciphertext size of `length` plaintext bytes: one tag per started chunk, and one
+/// (empty) chunk for an empty payload.
+fn sealed_len(length: u64) -> u64 {
+    let chunks = length.div_ceil(CHUNK_SIZE as u64).max(1);
+    length + chunks * GCM_TAG_LEN as u64
+}
+
+/// `name_len u32 LE | name`, prepended to the file content.
+fn name_header(path: &Path) -> Result<Vec<u8>> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("Invalid input file name"))?;
+    if name.len() > MAX_FILENAME_LEN {
+        return Err(anyhow!("File name is too long"));
+    }
+    let mut out = (name.len() as u32).to_le_bytes().to_vec();
+    out.extend_from_slice(name.as_bytes());
+    Ok(out)
+}
+
+fn check_password(password: &str) -> Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(anyhow!(
+            "Passwords must be at least {} characters",
+            MIN_PASSWORD_LEN
+        ));
+    }
+    Ok(())
+}
+
+/// Encrypts `length` bytes from `input` as chunks without framing.
+fn write_payload<R: Read, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    key: &[u8],
+    length: u64,
+) -> Result<()> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!(e))?;
+    let mut buf = Zeroizing::new(vec![0u8; CHUNK_SIZE]);
+    let mut remaining = length;
+    let mut index = 0u64;
+    loop {
+        let n = remaining.min(CHUNK_SIZE as u64) as usize;
+        input.read_exact(&mut buf[..n])?;
+        remaining -= n as u64;
+        let is_final = remaining == 0;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&chunk_nonce(index)),
+                Payload {
+                    msg: &buf[..n],
+                    aad: &chunk_aad(index, is_final),
+                },
+            )
+            .map_err(|_| anyhow!("Encryption failed"))?;
+        output.write_all(&ciphertext)?;
+        if is_final {
+            return Ok(());
+        }
+        index += 1;
+    }
+}
+
+fn write_random<W: Write>(output: &mut W, mut len: u64, rng: &mut ChaCha20Rng) -> Result<()> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    while len > 0 {
+        let n = len.min(CHUNK_SIZE as u64) as usize;
+        rng.fill_bytes(&mut buf[..n]);
+        output.write_all(&buf[..n])?;
+        len -= n as u64;
+    }
+    Ok(())
+}
+
+// ==========================================
+// --- LOCK / UNLOCK ---
+// ==========================================
+
+/// Default reserve for a decoy of `decoy_size` bytes.
+pub fn default_reserve(decoy_size: u64) -> u64 {
+    decoy_size.max(MIN_DEFAULT_RESERVE)
+}
+
+/// Writes a V11 container holding `decoy` and, optionally, `hidden` inside a random
+/// reserve of at least `reserve` bytes.
+pub fn lock(
+    decoy: &DeniablePayload,
+    hidden: Option<&DeniablePayload>,
+    reserve: u64,
+    output_path: &Path,
+) -> Result<()> {
+    check_password(decoy.password)?;
+    if let Some(hidden) = hidden {
+        check_password(hidden.password)?;
+        if hidden.password == decoy.password {
+            return Err(anyhow!(
+                "The hidden password must differ from the decoy password"
+            ));
+        }
+    }
+
+    let decoy_header = name_header(decoy.path)?;
+    let decoy_len = decoy_header.len() as u64 + fs::metadata(decoy.path)?.len();
+    let hidden_header = hidden.map(|h| name_header(h.path)).transpose()?;
+    let hidden_len = match (hidden, &hidden_header) {
+        (Some(h), Some(header)) => Some(header.len() as u64 + fs::metadata(h.path)?.len()),
+        _ => None,
+    };
+
+    let decoy_sealed = sealed_len(decoy_len);
+    if let Some(len) = hidden_len {
+        if sealed_len(len) > reserve {
+            return Err(anyhow!(
+                "The hidden file needs a reserve of at least {} bytes",
+                sealed_len(len)
+            ));
+        }
+    }
+    let area_len = (decoy_sealed + reserve).div_ceil(AREA_ALIGN) * AREA_ALIGN;
+
+    let mut salts = [[0u8; SALT_LEN]; SLOT_COUNT];
+    for salt in salts.iter_mut() {
+        fill_random(salt)?;
+    }
+    let mut seed = Zeroizing::new([0u8; 32]);
+    fill_random(seed.as_mut())?;
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+
+    // The decoy takes a random slot; the other one is the hidden payload or noise.
+    let decoy_slot = (rng.next_u32() & 1) as usize;
+    let mut slots = [[0u8; SLOT_LEN]; SLOT_COUNT];
+    let aad = slot_aad(&salts);
+    let mut seal_slot = |index: usize, password: &str, content: &SlotContent| -> Result<()> {
+        let key = derive_slot_key(password, &salts[index])?;
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!(e))?;
+        let mut nonce = [0u8; AES_NONCE_LEN];
+        fill_random(&mut nonce)?;
+        let sealed = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &content.to_bytes(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("Encryption failed"))?;
+        slots[index][..AES_NONCE_LEN].copy_from_slice(&nonce);
+        slots[index][AES_NONCE_LEN..].copy_from_slice(&sealed);
+        Ok(())
+    };
+
+    let mut decoy_key = Zeroizing::new([0u8; KEY_LEN]);
+    fill_random(decoy_key.as_mut())?;
+    seal_slot(
+        decoy_slot,
+        decoy.password,
+        &SlotContent {
+            key: decoy_key.clone(),
+            offset: 0,
+            length: decoy_len,
+        },
+    )?;
+    let hidden_slot = 1 - decoy_slot;
+    let mut hidden_key = Zeroizing::new([0u8; KEY_LEN]);
+    fill_random(hidden_key.as_mut())?;
+    let hidden_offset = hidden_len.map(|len| area_len - sealed_len(len));
+    match (hidden, hidden_len, hidden_offset) {
+        (Some(h), Some(length), Some(offset)) => seal_slot(
+            hidden_slot,
+            h.password,
+            &SlotContent {
+                key: hidden_key.clone(),
+                offset,
+                length,
+            },
+        )?,
+        _ => rng.fill_bytes(&mut slots[hidden_slot]),
+    }
+
+    let mut output = BufWriter::new(with_retry("create", output_path, || {
+        File::create(output_path)
+    })?);
+    let result = (|| -> Result<()> {
+        output.write_all(&VERSION_DENIABLE.to_le_bytes())?;
+        for salt in &salts {
+            output.write_all(salt)?;
+        }
+        for slot in &slots {
+            output.write_all(slot)?;
+        }
+
+        let mut decoy_input = Read::chain(
+            decoy_header.as_slice(),
+            BufReader::new(File::open(decoy.path)?),
+        );
+        write_payload(&mut decoy_input, &mut output, decoy_key.as_ref(), decoy_len)?;
+        let gap_end = hidden_offset.unwrap_or(area_len);
+        write_random(&mut output, gap_end - decoy_sealed, &mut rng)?;
+        if let (Some(h), Some(header), Some(length)) = (hidden, &hidden_header, hidden_len) {
+            let mut hidden_input =
+                Read::chain(header.as_slice(), BufReader::new(File::open(h.path)?));
+            write_payload(&mut hidden_input, &mut output, hidden_key.as_ref(), length)?;
+        }
+        output.flush()?;
+        Ok(())
+    })();
+    if result.is_err() {
+        drop(output);
+        let _ = fs::remove_file(output_path);
+    }
+    result
+}
+
+/// Opens whichever payload `password` unlocks and writes it into `output_dir`. Returns
+/// the written path. A wrong password and "no such payload" are the same error.
+pub fn unlock(path: &Path, password: &str, output_dir: &Path) -> Result<PathBuf> {
+    let mut input = BufReader::new(with_retry("open", path, || File::open(path))?);
+    let file_len = fs::metadata(path)?.len();
+    let mut header = [0u8; HEADER_LEN];
+    input
+        .read_exact(&mut header)
+        .map_err(|_| anyhow!("Not a deniable container"))?;
+    if header[..4] != VERSION_DENIABLE.to_le_bytes() {
+        return Err(anyhow!("Not a deniable container"));
+    }
+    let mut salts = [[0u8; SALT_LEN]; SLOT_COUNT];
+    for (i, salt) in salts.iter_mut().enumerate() {
+        let at = 4 + i * SALT_LEN;
+        salt.copy_from_slice(&header[at..at + SALT_LEN]);
+    }
+    let aad = slot_aad(&salts);
+    let slots_at = 4 + SLOT_COUNT * SALT_LEN;
+
+    // Both keys are always derived, so the time taken does not show which slot opened.
+    let mut opened = None;
+    for (i, salt) in salts.iter().enumerate() {
+        let key = derive_slot_key(password, salt)?;
+        let slot = &header[slots_at + i * SLOT_LEN..slots_at + (i + 1) * SLOT_LEN];
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!(e))?;
+        if let Ok(plain) = cipher.decrypt(
+            Nonce::from_slice(&slot[..AES_NONCE_LEN]),
+            Payload {
+                msg: &slot[AES_NONCE_LEN..],
+                aad: &aad,
+            },
+        ) {
+            opened = Some(SlotContent::from_bytes(&Zeroizing::new(plain))?);
+        }
+    }
+    let content = opened.ok_or_else(|| anyhow!("Wrong password"))?;
+
+    let area_len = file_len - HEADER_LEN as u64;
+    let sealed = sealed_len(content.length);
+    if content
+        .offset
+        .checked_add(sealed)
+        .is_none_or(|end| end > area_len)
+    {
+        return Err(anyhow!("Container is truncated or corrupt"));
+    }
+    input.seek(SeekFrom::Start(HEADER_LEN as u64 + content.offset))?;
+
+    let cipher = Aes256Gcm::new_from_slice(content.key.as_ref()).map_err(|e| anyhow!(e))?;
+    let mut remaining = content.length;
+    let mut index = 0u64;
+    // Yields each decrypted chunk in turn, then `None` once the payload is exhausted.
+    let mut next_chunk = |input: &mut BufReader<File>| -> Result<Option<Zeroizing<Vec<u8>>>> {
+        if remaining == 0 {
+            return Ok(None);
+        }
+        let n = remaining.min(CHUNK_SIZE as u64) as usize;
+        let mut ciphertext = vec![0u8; n + GCM_TAG_LEN];
+        input.read_exact(&mut ciphertext)?;
+        remaining -= n as u64;
+        let plain = cipher
+            .decrypt(
+                Nonce::from_slice(&chunk_nonce(index)),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &chunk_aad(index, remaining == 0),
+                },
+            )
+            .map_err(|_| anyhow!("Chunk {} integrity check failed", index))?;
+        index += 1;
+        Ok(Some(Zeroizing::new(plain)))
+    };
+
+    // The first chunk starts with the name header (names are far shorter than a chunk).
+    let first = next_chunk(&mut input)?.ok_or_else(|| anyhow!("Corrupt payload"))?;
+    let name_len = first
+        .get(..4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .filter(|&n| n <= MAX_FILENAME_LEN)
+        .ok_or_else(|| anyhow!("Corrupt payload"))?;
+    let name = first
+        .get(4..4 + name_len)
+        .ok_or_else(|| anyhow!("Corrupt payload"))?;
+    let name = String::from_utf8(name.to_vec()).map_err(|_| anyhow!("Invalid file name"))?;
+    crypto_stream::validate_original_filename(&name)?;
+
+    let final_out = crate::utils::get_unique_path(&output_dir.join(&name));
+    let mut output = BufWriter::new(with_retry("create", &final_out, || {
+        File::create(&final_out)
+    })?);
+    let result = (|| -> Result<()> {
+        output.write_all(&first[4 + name_len..])?;
+        while let Some(chunk) = next_chunk(&mut input)? {
+            output.write_all(&chunk)?;
+        }
+        output.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        drop(output);
+        let _ = fs::remove_file(&final_out);
+        return Err(e);
+    }
+    Ok(final_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("qre_deniable_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_decoy_and_hidden_open_with_their_passwords() {
+        let dir = temp_dir("both");
+        let decoy = dir.join("shopping.txt");
+        let hidden = dir.join("sources.txt");
+        fs::write(&decoy, b"milk, eggs").unwrap();
+        let secret: Vec<u8> = (0..CHUNK_SIZE + 99).map(|i| (i % 13) as u8).collect();
+        fs::write(&hidden, &secret).unwrap();
+        let container = dir.join("box.qre");
+
+        lock(
+            &DeniablePayload {
+                path: &decoy,
+                password: "decoy password",
+            },
+            Some(&DeniablePayload {
+                path: &hidden,
+                password: "hidden password",
+            }),
+            4 * 1024 * 1024,
+            &container,
+        )
+        .unwrap();
+
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let opened = unlock(&container, "decoy password", &out).unwrap();
+        assert_eq!(fs::read(&opened).unwrap(), b"milk, eggs");
+        let opened = unlock(&container, "hidden password", &out).unwrap();
+        assert_eq!(opened.file_name().unwrap(), "sources.txt");
+        assert_eq!(fs::read(&opened).unwrap(), secret);
+        assert!(unlock(&container, "wrong password", &out).is_err());
+    }
+
+    #[test]
+    fn test_size_does_not_reveal_hidden_payload() {
+        let dir = temp_dir("size");
+        let decoy = dir.join("decoy.txt");
+        let hidden = dir.join("hidden.txt");
+        fs::write(&decoy, vec![1u8; 5000]).unwrap();
+        fs::write(&hidden, vec![2u8; 70_000]).unwrap();
+        let decoy_payload = DeniablePayload {
+            path: &decoy,
+            password: "decoy password",
+        };
+        let reserve = default_reserve(5000);
+
+        let plain = dir.join("plain.qre");
+        let with_hidden = dir.join("hidden.qre");
+        lock(&decoy_payload, None, reserve, &plain).unwrap();
+        lock(
+            &decoy_payload,
+            Some(&DeniablePayload {
+                path: &hidden,
+                password: "hidden password",
+            }),
+            reserve,
+            &with_hidden,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::metadata(&plain).unwrap().len(),
+            fs::metadata(&with_hidden).unwrap().len()
+        );
+
+        // A hidden file larger than the reserve is refused rather than growing the file.
+        assert!(lock(
+            &decoy_payload,
+            Some(&DeniablePayload {
+                path: &hidden,
+                password: "hidden password",
+            }),
+            1000,
+            &dir.join("small.qre"),
+        )
+        .is_err());
+    }
+}
+
+// --- END OF FILE deniable.rs ---
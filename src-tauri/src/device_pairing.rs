@@ -0,0 +1,431 @@
+// --- START OF FILE device_pairing.rs ---
+
+// ==========================================
+// --- PAIRED DEVICE UNLOCK ---
+// ==========================================
+// Lets a paired phone (the Android build of this app) approve a desktop unlock, so the
+// master password does not have to be typed at a desk where others can see the screen.
+//
+// Pairing: the desktop owner creates a random 32-byte pairing secret and shows it once as
+// a `qre-pair:` QR code. The phone stores it in its own encrypted secrets store. The
+// desktop keeps only two keys derived from it: the slot key wraps a copy of the master
+// key in a device slot of the keychain, and the auth key checks approvals. The slot key
+// is not stored on the desktop, so the keychain file alone cannot open the device slot.
+//
+// Unlock: the desktop shows a fresh `qre-unlock:` challenge (single use, two minutes)
+// carrying a random nonce and the public half of an X25519 key made for that challenge
+// alone. The phone, once its own vault is unlocked, answers with a `qre-approve:`
+// response: its own ephemeral X25519 public key and the slot key sealed under
+//   H(auth key | challenge | X25519(phone eph, desktop eph) | both public keys)
+// with the device id and challenge as AAD. A response only opens against the challenge
+// it was made for, so a recorded one cannot be replayed. It can travel by QR code or over
+// the LAN; the format does not depend on the transport.
+//
+// NOTE: both ephemeral secrets are gone once the unlock ends (the desktop's lives in
+// memory with the pending challenge), so nothing at rest, neither keychain.json (the
+// auth key) nor a photo of the pairing QR code, opens a recorded challenge and response.
+// The auth key still decides who can answer: without it a response does not open.
+
+use crate::keychain::DeviceSlotInfo;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use data_encoding::BASE32_NOPAD;
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+const NONCE_LEN: usize = 12;
+const CHALLENGE_LEN: usize = 32;
+const X25519_LEN: usize = 32;
+/// How long a displayed challenge can be answered.
+pub const CHALLENGE_TTL_SECS: i64 = 120;
+/// Prefix of the secrets-store names under which a phone keeps its pairings.
+pub const PAIRED_DESKTOP_PREFIX: &str = "paired-desktop.";
+const MAX_LABEL_LEN: usize = 64;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+/// What the phone keeps (as JSON in its secrets store) for each paired desktop vault.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PairedDesktop {
+    pub vault_uuid: String,
+    pub device_id: String,
+    /// Base32 pairing secret.
+    pub secret: String,
+    /// The desktop's name, shown on the phone when it asks for approval.
+    pub label: String,
+}
+
+/// A challenge the desktop is waiting on. `ephemeral` answers this challenge only and
+/// is never written anywhere.
+#[derive(Clone)]
+pub struct UnlockChallenge {
+    pub vault_uuid: String,
+    pub nonce: [u8; CHALLENGE_LEN],
+    pub expires_at: i64,
+    ephemeral: StaticSecret,
+}
+
+/// A challenge as the phone reads it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengeRequest {
+    pub vault_uuid: String,
+    pub nonce: [u8; CHALLENGE_LEN],
+    pub ephemeral_public: [u8; X25519_LEN],
+}
+
+/// Result of pairing, for the desktop UI: the slot it created and the one-time QR payload.
+#[derive(Serialize, Debug, Clone)]
+pub struct PairingOffer {
+    pub device: DeviceSlotInfo,
+    pub payload: String,
+}
+
+// ==========================================
+// --- KEY DERIVATION ---
+// ==========================================
+
+fn derive(parts: &[&[u8]]) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    Zeroizing::new(hasher.finalize().into())
+}
+
+pub fn new_pairing_secret() -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut secret = Zeroizing::new([0u8; 32]);
+    OsRng
+        .try_fill_bytes(&mut *secret)
+        .map_err(|e| format!("OS RNG failed: {}", e))?;
+    Ok(secret)
+}
+
+/// Wraps the master key in the device slot. Never stored on the desktop.
+pub fn slot_key(secret: &[u8; 32]) -> Zeroizing<[u8; 32]> {
+    derive(&[secret, b"QRE_DEVICE_SLOT"])
+}
+
+/// Stored in the device slot to check approvals.
+pub fn auth_key(secret: &[u8; 32]) -> Zeroizing<[u8; 32]> {
+    derive(&[secret, b"QRE_DEVICE_AUTH"])
+}
+
+fn new_ephemeral() -> Result<StaticSecret, String> {
+    let mut bytes = Zeroizing::new([0u8; X25519_LEN]);
+    OsRng
+        .try_fill_bytes(bytes.as_mut())
+        .map_err(|e| format!("OS RNG failed: {}", e))?;
+    Ok(StaticSecret::from(*bytes))
+}
+
+/// Key sealing a response. `own` is this side's ephemeral secret and `peer` the other
+/// side's public key; `desktop_public` and `phone_public` bind both into the key.
+fn response_cipher(
+    auth_key: &[u8],
+    challenge: &[u8; CHALLENGE_LEN],
+    own: &StaticSecret,
+    peer: &[u8; X25519_LEN],
+    desktop_public: &[u8; X25519_LEN],
+    phone_public: &[u8; X25519_LEN],
+) -> Result<Aes256Gcm, String> {
+    let shared = own.diffie_hellman(&PublicKey::from(*peer));
+    // A low-order peer key would make the shared secret predictable.
+    if !shared.was_contributory() {
+        return Err("Malformed code.".to_string());
+    }
+    let key = derive(&[
+        auth_key,
+        b"QRE_DEVICE_RESPONSE",
+        challenge,
+        shared.as_bytes(),
+        desktop_public,
+        phone_public,
+    ]);
+    Aes256Gcm::new_from_slice(&*key).map_err(|e| e.to_string())
+}
+
+fn response_aad(device_id: &str, vault_uuid: &str, challenge: &[u8; CHALLENGE_LEN]) -> Vec<u8> {
+    let mut aad = format!("{}:{}:", device_id, vault_uuid).into_bytes();
+    aad.extend_from_slice(challenge);
+    aad
+}
+
+fn decode_fixed<const N: usize>(field: &str) -> Result<[u8; N], String> {
+    BASE32_NOPAD
+        .decode(field.as_bytes())
+        .ok()
+        .and_then(|bytes| <[u8; N]>::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| "Malformed code.".to_string())
+}
+
+// ==========================================
+// --- PAYLOADS ---
+// ==========================================
+
+/// `qre-pair:1:<vault uuid>:<device id>:<secret>:<label>`. The label comes last so it
+/// may contain ':'.
+pub fn encode_pairing(pairing: &PairedDesktop) -> String {
+    format!(
+        "qre-pair:1:{}:{}:{}:{}",
+        pairing.vault_uuid, pairing.device_id, pairing.secret, pairing.label
+    )
+}
+
+pub fn decode_pairing(payload: &str) -> Result<PairedDesktop, String> {
+    let parts: Vec<&str> = payload.trim().splitn(6, ':').collect();
+    let [scheme, version, vault_uuid, device_id, secret, label] = parts.as_slice() else {
+        return Err("This is not a QRE pairing code.".to_string());
+    };
+    if *scheme != "qre-pair" || *version != "1" {
+        return Err("This is not a QRE pairing code.".to_string());
+    }
+    decode_fixed::<32>(secret)?;
+    if vault_uuid.is_empty() || device_id.is_empty() || label.chars().count() > MAX_LABEL_LEN {
+        return Err("Malformed code.".to_string());
+    }
+    Ok(PairedDesktop {
+        vault_uuid: vault_uuid.to_string(),
+        device_id: device_id.to_string(),
+        secret: secret.to_string(),
+        label: label.to_string(),
+    })
+}
+
+pub fn new_challenge(vault_uuid: &str, now: i64) -> Result<UnlockChallenge, String> {
+    let mut nonce = [0u8; CHALLENGE_LEN];
+    OsRng
+        .try_fill_bytes(&mut nonce)
+        .map_err(|e| format!("OS RNG failed: {}", e))?;
+    Ok(UnlockChallenge {
+        vault_uuid: vault_uuid.to_string(),
+        nonce,
+        expires_at: now + CHALLENGE_TTL_SECS,
+        ephemeral: new_ephemeral()?,
+    })
+}
+
+/// `qre-unlock:1:<vault uuid>:<challenge>:<ephemeral public key>`
+pub fn encode_challenge(challenge: &UnlockChallenge) -> String {
+    format!(
+        "qre-unlock:1:{}:{}:{}",
+        challenge.vault_uuid,
+        BASE32_NOPAD.encode(&challenge.nonce),
+        BASE32_NOPAD.encode(PublicKey::from(&challenge.ephemeral).as_bytes())
+    )
+}
+
+/// Parses a challenge on the phone. Its expiry is only known to the desktop.
+pub fn decode_challenge(payload: &str) -> Result<ChallengeRequest, String> {
+    let parts: Vec<&str> = payload.trim().split(':').collect();
+    match parts.as_slice() {
+        ["qre-unlock", "1", vault_uuid, nonce, ephemeral] if !vault_uuid.is_empty() => {
+            Ok(ChallengeRequest {
+                vault_uuid: vault_uuid.to_string(),
+                nonce: decode_fixed(nonce)?,
+                ephemeral_public: decode_fixed(ephemeral)?,
+            })
+        }
+        _ => Err("This is not a QRE unlock request.".to_string()),
+    }
+}
+
+// ==========================================
+// --- APPROVAL ---
+// ==========================================
+
+/// Phone side: answers a challenge for a desktop it is paired with.
+/// Returns `qre-approve:1:<device id>:<ephemeral public key>:<nonce>:<sealed slot key>`.
+pub fn approve(pairing: &PairedDesktop, challenge: &str) -> Result<String, String> {
+    let ChallengeRequest {
+        vault_uuid,
+        nonce,
+        ephemeral_public: desktop_public,
+    } = decode_challenge(challenge)?;
+    if vault_uuid != pairing.vault_uuid {
+        return Err("This phone is not paired with the vault asking for approval.".to_string());
+    }
+    let secret = Zeroizing::new(decode_fixed::<32>(&pairing.secret)?);
+    let slot_key = slot_key(&secret);
+    let auth_key = auth_key(&secret);
+
+    let mut seal_nonce = [0u8; NONCE_LEN];
+    OsRng
+        .try_fill_bytes(&mut seal_nonce)
+        .map_err(|e| format!("OS RNG failed: {}", e))?;
+    let ephemeral = new_ephemeral()?;
+    let phone_public = PublicKey::from(&ephemeral).to_bytes();
+    let aad = response_aad(&pairing.device_id, &vault_uuid, &nonce);
+    let cipher = response_cipher(
+        &*auth_key,
+        &nonce,
+        &ephemeral,
+        &desktop_public,
+        &desktop_public,
+        &phone_public,
+    )?;
+    let sealed = cipher
+        .encrypt(
+            Nonce::from_slice(&seal_nonce),
+            Payload {
+                msg: &*slot_key,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "Failed to seal the approval.".to_string())?;
+
+    Ok(format!(
+        "qre-approve:1:{}:{}:{}:{}",
+        pairing.device_id,
+        BASE32_NOPAD.encode(&phone_public),
+        BASE32_NOPAD.encode(&seal_nonce),
+        BASE32_NOPAD.encode(&sealed)
+    ))
+}
+
+/// The device id an approval claims to come from, so the desktop can look up its auth key.
+pub fn response_device_id(response: &str) -> Result<String, String> {
+    match response.trim().split(':').collect::<Vec<_>>().as_slice() {
+        ["qre-approve", "1", device_id, _, _, _] if !device_id.is_empty() => {
+            Ok(device_id.to_string())
+        }
+        _ => Err("This is not a QRE approval code.".to_string()),
+    }
+}
+
+/// Desktop side: checks an approval against the pending challenge and returns the slot
+/// key it carries. Expired challenges and approvals for another challenge are refused.
+pub fn open_approval(
+    response: &str,
+    challenge: &UnlockChallenge,
+    auth_key: &[u8],
+    now: i64,
+) -> Result<Zeroizing<[u8; 32]>, String> {
+    if now > challenge.expires_at {
+        return Err("The unlock request expired. Start a new one.".to_string());
+    }
+    let parts: Vec<&str> = response.trim().split(':').collect();
+    let ["qre-approve", "1", device_id, phone_public, seal_nonce, sealed] = parts.as_slice() else {
+        return Err("This is not a QRE approval code.".to_string());
+    };
+    let phone_public = decode_fixed::<X25519_LEN>(phone_public)?;
+    let seal_nonce = decode_fixed::<NONCE_LEN>(seal_nonce)?;
+    let sealed = BASE32_NOPAD
+        .decode(sealed.as_bytes())
+        .map_err(|_| "Malformed code.".to_string())?;
+
+    let aad = response_aad(device_id, &challenge.vault_uuid, &challenge.nonce);
+    let cipher = response_cipher(
+        auth_key,
+        &challenge.nonce,
+        &challenge.ephemeral,
+        &phone_public,
+        PublicKey::from(&challenge.ephemeral).as_bytes(),
+        &phone_public,
+    )?;
+    let plain = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&seal_nonce),
+                Payload {
+                    msg: &sealed,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| "The approval was not accepted.".to_string())?,
+    );
+    let key = <[u8; 32]>::try_from(plain.as_slice())
+        .map_err(|_| "The approval was not accepted.".to_string())?;
+    Ok(Zeroizing::new(key))
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paired() -> (Zeroizing<[u8; 32]>, PairedDesktop) {
+        let secret = new_pairing_secret().unwrap();
+        let pairing = PairedDesktop {
+            vault_uuid: "vault-1".into(),
+            device_id: "dev-1".into(),
+            secret: BASE32_NOPAD.encode(&*secret),
+            label: "Office PC: desk 3".into(),
+        };
+        (secret, pairing)
+    }
+
+    #[test]
+    fn test_pairing_payload_roundtrip() {
+        let (_, pairing) = paired();
+        let payload = encode_pairing(&pairing);
+        assert_eq!(decode_pairing(&payload).unwrap(), pairing);
+        assert!(decode_pairing("qre-pair:2:a:b:c:d").is_err());
+        assert!(decode_pairing("qre-pair:1:a:b:NOTBASE32:d").is_err());
+        assert!(decode_pairing("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_approval_releases_slot_key_once_per_challenge() {
+        let (secret, pairing) = paired();
+        let auth = auth_key(&secret);
+        let challenge = new_challenge("vault-1", 1_000).unwrap();
+        let response = approve(&pairing, &encode_challenge(&challenge)).unwrap();
+
+        assert_eq!(response_device_id(&response).unwrap(), "dev-1");
+        let key = open_approval(&response, &challenge, &*auth, 1_050).unwrap();
+        assert_eq!(*key, *slot_key(&secret));
+
+        // Expired, replayed against a new challenge, or checked with another auth key.
+        assert!(open_approval(&response, &challenge, &*auth, 1_000 + CHALLENGE_TTL_SECS + 1).is_err());
+        let next = new_challenge("vault-1", 1_100).unwrap();
+        assert!(open_approval(&response, &next, &*auth, 1_100).is_err());
+        assert!(open_approval(&response, &challenge, &[0u8; 32], 1_050).is_err());
+
+        // A response claiming another device id fails the AAD check.
+        let forged = response.replacen("dev-1", "dev-2", 1);
+        assert!(open_approval(&forged, &challenge, &*auth, 1_050).is_err());
+    }
+
+    #[test]
+    fn test_transcript_needs_the_challenge_ephemeral_key() {
+        let (secret, pairing) = paired();
+        let auth = auth_key(&secret);
+        let challenge = new_challenge("vault-1", 1_000).unwrap();
+        let response = approve(&pairing, &encode_challenge(&challenge)).unwrap();
+
+        // The auth key (keychain.json), the nonce and the response are all someone with
+        // a recording has; without the desktop's ephemeral secret they open nothing.
+        let recorded = UnlockChallenge {
+            ephemeral: new_ephemeral().unwrap(),
+            ..challenge.clone()
+        };
+        assert!(open_approval(&response, &recorded, &*auth, 1_050).is_err());
+        assert!(open_approval(&response, &challenge, &*auth, 1_050).is_ok());
+
+        // A low-order ephemeral key in a tampered challenge is refused by the phone.
+        let nonce = BASE32_NOPAD.encode(&challenge.nonce);
+        let zero = BASE32_NOPAD.encode(&[0u8; 32]);
+        let tampered = format!("qre-unlock:1:vault-1:{}:{}", nonce, zero);
+        assert!(approve(&pairing, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_phone_refuses_other_vaults() {
+        let (_, pairing) = paired();
+        let other = new_challenge("vault-2", 0).unwrap();
+        assert!(approve(&pairing, &encode_challenge(&other)).is_err());
+        assert!(approve(&pairing, "qre-unlock:1:vault-1:short").is_err());
+    }
+}
+
+// --- END OF FILE device_pairing.rs ---
@@ -0,0 +1,716 @@
+// --- START OF FILE diagnosis.rs ---
+
+// ==========================================
+// --- CONTAINER DIAGNOSIS ---
+// ==========================================
+// A failed unlock says "Decryption Denied" or "integrity check failed", which leaves the
+// user guessing whether the password is wrong or the file is broken. `diagnose` checks a
+// container step by step without writing anything:
+//   1. the header parses (for V5+ this also runs the field bounds checks);
+//   2. the validation tag opens with the session key (and the keyfile, if given);
+//   3. the chunk length prefixes line up from the header to the end of the file;
+//   4. with the key, every chunk authenticates and decompresses, and the trailer (V8) or
+//      the whole-file hash (V5–V7) accounts for all of them.
+// Containers with Reed–Solomon parity are walked through it (see parity.rs), like an
+// unlock reads them, and the report says how many blocks needed repairing.
+// The verdict is the first thing that went wrong, and the chunk indexes that failed are
+// listed so the user knows how much of the file is affected.
+//
+// What is not decrypted: time-locked files (the lock decides when the key exists), and
+// the keyfile of files with a failed-attempt limit (a wrong guess here would not count).
+// Chunk plaintext is hashed and dropped, never written.
+
+use crate::crypto;
+use crate::crypto_stream::{
+    self, PaddingRecord, StreamHeader, CHUNK_SIZE, GCM_TAG_LEN, PADDING_MARKER, SHA256_LEN,
+    TRAILER_MARKER, TRAILER_RECORD_LEN, VERSION_HYBRID, VERSION_V8,
+};
+use crate::keychain::MasterKey;
+use crate::parity::{BodyReader, ParityStats};
+use crate::subkeys::KeyPurpose;
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Everything checked out.
+    Healthy,
+    /// The structure is intact; the contents could not be checked without the key.
+    Unverified,
+    /// The header is fine, the password or keyfile is not.
+    WrongKey,
+    /// The header cannot be read. Nothing after it can be located.
+    HeaderDamaged,
+    /// Some chunks fail authentication or the framing breaks part way.
+    Damaged,
+    /// Damaged, but the parity rebuilds every damaged block when the file is opened.
+    Repairable,
+    /// The file ends early: chunks, padding or the trailer are missing.
+    Truncated,
+    /// Not a single-file container this check understands.
+    Unsupported,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub version: u32,
+    pub verdict: Verdict,
+    /// One or two sentences for the user.
+    pub summary: String,
+    pub header_ok: bool,
+    /// `None` when no key could be tried (vault locked, keyfile missing...).
+    pub key_ok: Option<bool>,
+    /// The chunks were decrypted, not only located.
+    pub contents_checked: bool,
+    /// Chunks located by walking the length prefixes.
+    pub chunks: u64,
+    /// Indexes of chunks that failed authentication or decompression.
+    pub damaged_chunks: Vec<u64>,
+    /// Index of the chunk whose length prefix makes no sense; nothing after it can be found.
+    pub framing_broken_at: Option<u64>,
+    pub truncated: bool,
+    /// V8: the trailer authenticated and matches the chunks. `None` when not checked.
+    pub trailer_ok: Option<bool>,
+    /// The decrypted data matches the whole-file hash in the header. `None` when not checked.
+    pub hash_ok: Option<bool>,
+    /// What the parity found and repaired. `None` for containers without parity.
+    pub parity: Option<ParityStats>,
+    /// Details behind the verdict, in the order they were found.
+    pub findings: Vec<String>,
+}
+
+impl Diagnosis {
+    fn new(version: u32) -> Self {
+        Diagnosis {
+            version,
+            verdict: Verdict::Unverified,
+            summary: String::new(),
+            header_ok: false,
+            key_ok: None,
+            contents_checked: false,
+            chunks: 0,
+            damaged_chunks: Vec::new(),
+            framing_broken_at: None,
+            truncated: false,
+            trailer_ok: None,
+            hash_ok: None,
+            parity: None,
+            findings: Vec::new(),
+        }
+    }
+
+    /// Picks the verdict and summary from what was found.
+    fn conclude(mut self) -> Self {
+        let (verdict, summary) = if !self.header_ok {
+            (
+                Verdict::HeaderDamaged,
+                "The file header is damaged, so the file cannot be opened. Restore it from a \
+                 backup."
+                    .to_string(),
+            )
+        } else if self.key_ok == Some(false) {
+            let mut summary =
+                "The password or keyfile is wrong: the file itself looks intact.".to_string();
+            if self.framing_broken_at.is_some() || self.truncated {
+                summary =
+                    "The password or keyfile is wrong, and the file is also damaged.".to_string();
+            }
+            (Verdict::WrongKey, summary)
+        } else if self.framing_broken_at.is_some()
+            || !self.damaged_chunks.is_empty()
+            || (self.hash_ok == Some(false) && !self.truncated)
+        {
+            // Each chunk holds up to 1 MB of the original file.
+            let mut summary = match self.damaged_chunks.len() {
+                0 => "The file is damaged.".to_string(),
+                1 => {
+                    "The file is damaged: 1 chunk (up to 1 MB of data) fails its check.".to_string()
+                }
+                n => format!(
+                    "The file is damaged: {} chunks (up to {} MB of data) fail their check.",
+                    n, n
+                ),
+            };
+            if let Some(at) = self.framing_broken_at {
+                summary.push_str(&format!(" Nothing from chunk {} on can be located.", at));
+            }
+            if self
+                .parity
+                .as_ref()
+                .is_some_and(|p| p.damaged_blocks > p.repaired_blocks)
+            {
+                summary.push_str(" There is too much damage for its parity to repair.");
+            }
+            (Verdict::Damaged, summary)
+        } else if self.truncated {
+            (
+                Verdict::Truncated,
+                "The file is incomplete: it was cut off, probably by an interrupted copy or \
+                 download. Copy it again from the source."
+                    .to_string(),
+            )
+        } else if let Some(repaired) = self
+            .parity
+            .as_ref()
+            .map(|p| p.repaired_blocks)
+            .filter(|&n| n > 0)
+        {
+            (
+                Verdict::Repairable,
+                format!(
+                    "The file is damaged in {} place{}, and its parity repairs all of them \
+                     whenever it is opened. Lock it again to get fresh parity.",
+                    repaired,
+                    if repaired == 1 { "" } else { "s" }
+                ),
+            )
+        } else if self.key_ok == Some(true) && self.contents_checked {
+            (
+                Verdict::Healthy,
+                "The file is intact and opens with this key.".to_string(),
+            )
+        } else {
+            (
+                Verdict::Unverified,
+                "The file structure is intact. Its contents were not checked: unlock the vault \
+                 it belongs to (or give its keyfile) for a full check."
+                    .to_string(),
+            )
+        };
+        self.verdict = verdict;
+        self.summary = summary;
+        self
+    }
+}
+
+/// Diagnoses the container at `path`. `master_key` is the session key of the vault the
+/// file belongs to, if it is unlocked; `keyfile` is the hashed keyfile, as for unlocking.
+/// Fails only when the file cannot be read at all.
+pub fn diagnose(
+    path: &Path,
+    master_key: Option<&MasterKey>,
+    keyfile: Option<&[u8]>,
+) -> Result<Diagnosis> {
+    let mut input = BufReader::new(File::open(path).context("Failed to open file")?);
+    let mut ver_buf = [0u8; 4];
+    input
+        .read_exact(&mut ver_buf)
+        .context("The file is too short to be a QRE container")?;
+    let version = u32::from_le_bytes(ver_buf);
+
+    match version {
+        4 => Ok(diagnose_legacy(path, master_key, keyfile)),
+        5..=VERSION_V8 | VERSION_HYBRID => Ok(diagnose_stream(version, input, master_key, keyfile)),
+        _ => {
+            let mut d = Diagnosis::new(version);
+            d.verdict = Verdict::Unsupported;
+            d.summary = format!(
+                "Format {} is not a single-file container, or is newer than this version of \
+                 QRE. Archives are checked by the archive viewer.",
+                version
+            );
+            Ok(d)
+        }
+    }
+}
+
+/// V4 keeps the whole file in one AEAD body, so the only "chunk" is that body.
+fn diagnose_legacy(
+    path: &Path,
+    master_key: Option<&MasterKey>,
+    keyfile: Option<&[u8]>,
+) -> Diagnosis {
+    let mut d = Diagnosis::new(4);
+    let container = match crypto::EncryptedFileContainer::load(&path.to_string_lossy()) {
+        Ok(c) => c,
+        Err(e) => {
+            d.findings.push(format!("Header: {:#}", e));
+            return d.conclude();
+        }
+    };
+    d.header_ok = true;
+    d.chunks = 1;
+
+    let Some(master_key) = master_key else {
+        d.findings
+            .push("The vault is locked, so the body was not checked.".into());
+        return d.conclude();
+    };
+    if container.header.uses_keyfile && keyfile.is_none() {
+        d.findings
+            .push("The file needs its keyfile, so the body was not checked.".into());
+        return d.conclude();
+    }
+    // Vault containers can be diagnosed too: find the subkey this one is wrapped with.
+    let purpose = KeyPurpose::ALL
+        .into_iter()
+        .find(|&p| crypto::master_key_opens(&container.header, master_key, p))
+        .unwrap_or(KeyPurpose::FileWrapping);
+    match crypto::decrypt_file_with_master_key(master_key, purpose, keyfile, &container) {
+        Ok(_) => {
+            d.key_ok = Some(true);
+            d.contents_checked = true;
+        }
+        Err(e) => {
+            let msg = e.to_string();
+            if msg.starts_with("Decryption Denied") || msg.starts_with("Validation tag") {
+                d.key_ok = Some(false);
+            } else {
+                d.key_ok = Some(true);
+                d.contents_checked = true;
+                d.damaged_chunks.push(0);
+                d.findings.push(format!("Body: {}", msg));
+            }
+        }
+    }
+    d.conclude()
+}
+
+fn diagnose_stream(
+    version: u32,
+    mut input: BufReader<File>,
+    master_key: Option<&MasterKey>,
+    keyfile: Option<&[u8]>,
+) -> Diagnosis {
+    let mut d = Diagnosis::new(version);
+    let header = match crypto_stream::parse_stream_header(version, &mut input) {
+        Ok(h) => h,
+        Err(e) => {
+            d.findings.push(format!("Header: {:#}", e));
+            return d.conclude();
+        }
+    };
+    d.header_ok = true;
+    let cipher = check_key(&header, master_key, keyfile, &mut d);
+    let mut body = match BodyReader::new(input, &header, true) {
+        Ok(body) => body,
+        Err(e) => {
+            d.findings.push(format!("Read error: {}", e));
+            d.framing_broken_at = Some(0);
+            return d.conclude();
+        }
+    };
+    walk_chunks(version, &header, &mut body, cipher.as_ref(), &mut d);
+    if let Some(stats) = body.stats() {
+        report_parity(stats, &mut d);
+    }
+    d.conclude()
+}
+
+fn report_parity(stats: &ParityStats, d: &mut Diagnosis) {
+    if stats.repaired_blocks > 0 {
+        d.findings.push(format!(
+            "The parity repaired {} damaged block{} of 4 KB.",
+            stats.repaired_blocks,
+            if stats.repaired_blocks == 1 { "" } else { "s" }
+        ));
+    }
+    if stats.damaged_blocks > stats.repaired_blocks {
+        d.findings.push(format!(
+            "{} damaged block{} could not be repaired: a group had more damage than parity.",
+            stats.damaged_blocks - stats.repaired_blocks,
+            if stats.damaged_blocks - stats.repaired_blocks == 1 {
+                ""
+            } else {
+                "s"
+            }
+        ));
+    }
+    if stats.damaged_parity_blocks > 0 {
+        d.findings.push(format!(
+            "{} parity block{} damaged, which leaves less to repair with.",
+            stats.damaged_parity_blocks,
+            if stats.damaged_parity_blocks == 1 {
+                " is"
+            } else {
+                "s are"
+            }
+        ));
+    }
+    if stats.groups_without_parity > 0 {
+        d.findings.push(format!(
+            "The parity of {} of {} groups is missing.",
+            stats.groups_without_parity, stats.groups
+        ));
+    }
+    d.parity = Some(stats.clone());
+}
+
+/// Sets `key_ok` and returns the file cipher when the chunks can be checked.
+fn check_key(
+    header: &StreamHeader,
+    master_key: Option<&MasterKey>,
+    keyfile: Option<&[u8]>,
+    d: &mut Diagnosis,
+) -> Option<Aes256Gcm> {
+    let Some(master_key) = master_key else {
+        d.findings
+            .push("The vault is locked, so the chunks were not decrypted.".into());
+        return None;
+    };
+    if header.timelock.is_some() {
+        // Checks the binding key, which the master key alone opens.
+        d.key_ok = Some(crypto_stream::master_key_opens(header, master_key));
+        d.findings
+            .push("The file is time-locked, so the chunks were not decrypted.".into());
+        return None;
+    }
+    if header.kem_wrap.is_some() {
+        // The file key also needs the vault identity, which diagnosis does not load.
+        if crypto_stream::master_key_opens(header, master_key) {
+            d.key_ok = Some(true);
+        }
+        d.findings.push(
+            "The file key is also locked to the vault's identity key, so the chunks were not \
+             decrypted."
+                .into(),
+        );
+        return None;
+    }
+    if crypto_stream::master_key_opens(header, master_key) {
+        d.key_ok = Some(true);
+        return crypto_stream::unwrap_file_cipher(header, master_key, None, None).ok();
+    }
+    let attempt_limit = header
+        .expiry
+        .as_ref()
+        .is_some_and(|ex| ex.policy.max_failed_attempts.is_some());
+    match keyfile {
+        Some(_) if attempt_limit => {
+            d.findings
+                .push("The file counts failed attempts, so its keyfile was not tried here.".into());
+            None
+        }
+        Some(keyfile) => {
+            match crypto_stream::unwrap_file_cipher(header, master_key, Some(keyfile), None) {
+                Ok(cipher) => {
+                    d.key_ok = Some(true);
+                    Some(cipher)
+                }
+                Err(_) => {
+                    d.key_ok = Some(false);
+                    None
+                }
+            }
+        }
+        // Without the keyfile a keyfile-protected file looks exactly like a wrong key.
+        None => {
+            d.key_ok = Some(false);
+            None
+        }
+    }
+}
+
+/// Reads up to `buf.len()` bytes; fewer only at the end of the input.
+pub(crate) fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Walks the chunk framing (and, with `cipher`, authenticates each chunk) up to the end.
+fn walk_chunks<R: Read>(
+    version: u32,
+    header: &StreamHeader,
+    input: &mut R,
+    cipher: Option<&Aes256Gcm>,
+    d: &mut Diagnosis,
+) {
+    let requires_trailer = version >= VERSION_V8;
+    d.contents_checked = cipher.is_some();
+    let mut hasher = Sha256::new();
+    let mut plaintext_len = 0u64;
+    let mut padding: Option<PaddingRecord> = None;
+    let mut trailer_seen = false;
+    let mut trailer_hash = None;
+
+    loop {
+        let index = d.chunks;
+        let mut marker_buf = [0u8; 4];
+        match read_full(input, &mut marker_buf) {
+            Ok(0) => break,
+            Ok(4) => {}
+            Ok(_) => {
+                d.truncated = true;
+                d.findings.push(format!(
+                    "The file ends inside the length of chunk {}.",
+                    index
+                ));
+                break;
+            }
+            Err(e) => {
+                d.findings
+                    .push(format!("Read error at chunk {}: {}", index, e));
+                d.framing_broken_at = Some(index);
+                break;
+            }
+        }
+        let marker = u32::from_le_bytes(marker_buf);
+
+        if requires_trailer && marker == PADDING_MARKER && header.padding.is_some() {
+            match crypto_stream::read_padding(input) {
+                Ok(record) => padding = Some(record),
+                Err(_) => {
+                    d.truncated = true;
+                    d.findings
+                        .push("The file ends inside its size padding.".into());
+                    break;
+                }
+            }
+            continue;
+        }
+        if requires_trailer && marker == TRAILER_MARKER {
+            trailer_seen = true;
+            trailer_hash = check_trailer(header, input, cipher, plaintext_len, padding.as_ref(), d);
+            break;
+        }
+
+        let len = marker as usize;
+        if !(GCM_TAG_LEN..=CHUNK_SIZE + 4096).contains(&len) {
+            d.framing_broken_at = Some(index);
+            d.findings.push(format!(
+                "Chunk {} has an impossible length ({} bytes).",
+                index, len
+            ));
+            break;
+        }
+        let mut ciphertext = vec![0u8; len];
+        match read_full(input, &mut ciphertext) {
+            Ok(n) if n == len => {}
+            _ => {
+                d.truncated = true;
+                d.findings
+                    .push(format!("The file ends inside chunk {}.", index));
+                break;
+            }
+        }
+        d.chunks += 1;
+
+        let Some(cipher) = cipher else { continue };
+        let aad = format!("{}:{}", header.original_filename, index);
+        let opened = cipher
+            .decrypt(
+                Nonce::from_slice(&crypto_stream::chunk_nonce(&header.base_nonce, index)),
+                Payload {
+                    msg: &ciphertext,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .ok()
+            .and_then(|compressed| crypto_stream::decompress_chunk(&compressed).ok());
+        match opened {
+            Some(plaintext) => {
+                hasher.update(&plaintext);
+                plaintext_len += plaintext.len() as u64;
+            }
+            None => d.damaged_chunks.push(index),
+        }
+    }
+
+    if requires_trailer && !trailer_seen && d.framing_broken_at.is_none() {
+        d.truncated = true;
+        d.findings.push(format!(
+            "The file ends after chunk {} without its trailer.",
+            d.chunks
+        ));
+    }
+
+    // V5–V7 have no trailer: with every chunk intact, only the whole-file hash can tell
+    // that chunks are missing from the end. V8 compares with the hash in its
+    // authenticated trailer instead of the header's copy.
+    let complete = cipher.is_some()
+        && d.damaged_chunks.is_empty()
+        && d.framing_broken_at.is_none()
+        && !d.truncated;
+    let expected = if requires_trailer {
+        trailer_hash.map(|hash| hash.to_vec())
+    } else {
+        header.original_hash.clone()
+    };
+    if let (true, Some(expected)) = (complete, &expected) {
+        let hash_ok = crypto_stream::constant_time_eq(&hasher.finalize(), expected);
+        d.hash_ok = Some(hash_ok);
+        if !hash_ok && requires_trailer {
+            d.findings
+                .push("The decrypted data does not match the stored hash.".into());
+        } else if !hash_ok {
+            d.truncated = true;
+            d.findings.push(
+                "The decrypted data does not match the stored hash: chunks are missing from \
+                 the end."
+                    .into(),
+            );
+        }
+    }
+}
+
+/// Checks the V8 trailer and that nothing follows it. Returns the whole-file hash the
+/// trailer carries, when it authenticated.
+fn check_trailer<R: Read>(
+    header: &StreamHeader,
+    input: &mut R,
+    cipher: Option<&Aes256Gcm>,
+    plaintext_len: u64,
+    padding: Option<&PaddingRecord>,
+    d: &mut Diagnosis,
+) -> Option<[u8; SHA256_LEN]> {
+    let mut record = [0u8; TRAILER_RECORD_LEN];
+    if !matches!(read_full(input, &mut record), Ok(n) if n == TRAILER_RECORD_LEN) {
+        d.truncated = true;
+        d.findings.push("The file ends inside its trailer.".into());
+        return None;
+    }
+    if header.padding.is_some() && padding.is_none() {
+        d.findings.push("The size padding is missing.".into());
+        d.trailer_ok = Some(false);
+    }
+    let mut hash = None;
+    if let Some(cipher) = cipher {
+        match crypto_stream::open_trailer(&record, cipher, header, d.chunks, padding) {
+            Ok(trailer) => {
+                let (chunks, bytes) = (trailer.chunks, trailer.plaintext_len);
+                hash = Some(trailer.hash);
+                // Damaged chunks have no known size, so the byte count is only comparable
+                // when all of them opened.
+                let bytes_match = !d.damaged_chunks.is_empty() || bytes == plaintext_len;
+                if chunks != d.chunks || !bytes_match {
+                    d.trailer_ok = Some(false);
+                    d.findings.push(format!(
+                        "The trailer expects {} chunks / {} bytes.",
+                        chunks, bytes
+                    ));
+                } else if d.trailer_ok.is_none() {
+                    d.trailer_ok = Some(true);
+                }
+            }
+            Err(_) => {
+                d.trailer_ok = Some(false);
+                d.truncated = true;
+                d.findings.push(
+                    "The trailer does not match the chunks found: chunks were removed or added, \
+                     or the trailer itself is damaged."
+                        .into(),
+                );
+            }
+        }
+    }
+    let mut probe = [0u8; 1];
+    if matches!(input.read(&mut probe), Ok(n) if n > 0) {
+        d.framing_broken_at = Some(d.chunks);
+        d.findings
+            .push("There is unexpected data after the trailer.".into());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn key(byte: u8) -> MasterKey {
+        MasterKey([byte; 32])
+    }
+
+    /// Encrypts 2.5 chunks of data and returns (dir, container path).
+    fn sample_container() -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("qre_diag_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("data.bin");
+        let data: Vec<u8> = (0..CHUNK_SIZE * 5 / 2)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect();
+        fs::write(&input, data).unwrap();
+        let output = dir.join("data.bin.qre");
+        crypto_stream::encrypt_file_stream(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &key(1),
+            "local",
+            None,
+            None,
+            None,
+            1,
+            |_, _| {},
+        )
+        .unwrap();
+        (dir, output)
+    }
+
+    /// Offset of the first byte of chunk 1's ciphertext.
+    fn second_chunk_offset(bytes: &[u8]) -> usize {
+        let start = 4 + crypto_stream::HEADER_RESERVED_BYTES;
+        let first = u32::from_le_bytes(bytes[start..start + 4].try_into().unwrap()) as usize;
+        start + 4 + first + 4
+    }
+
+    #[test]
+    fn test_healthy_wrong_key_and_locked() {
+        let (dir, path) = sample_container();
+        let d = diagnose(&path, Some(&key(1)), None).unwrap();
+        assert_eq!(d.verdict, Verdict::Healthy, "{:?}", d);
+        assert_eq!((d.chunks, d.trailer_ok), (3, Some(true)));
+
+        let d = diagnose(&path, Some(&key(2)), None).unwrap();
+        assert_eq!(d.verdict, Verdict::WrongKey);
+        assert_eq!(d.chunks, 3, "the framing is still walked");
+
+        let d = diagnose(&path, None, None).unwrap();
+        assert_eq!(d.verdict, Verdict::Unverified);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_damaged_chunk_is_located() {
+        let (dir, path) = sample_container();
+        let mut bytes = fs::read(&path).unwrap();
+        let at = second_chunk_offset(&bytes) + 100;
+        bytes[at] ^= 0x01;
+        fs::write(&path, &bytes).unwrap();
+
+        let d = diagnose(&path, Some(&key(1)), None).unwrap();
+        assert_eq!(d.verdict, Verdict::Damaged, "{:?}", d);
+        assert_eq!(d.damaged_chunks, [1]);
+        assert_eq!(d.framing_broken_at, None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_truncation_and_broken_header() {
+        let (dir, path) = sample_container();
+        let bytes = fs::read(&path).unwrap();
+
+        fs::write(&path, &bytes[..second_chunk_offset(&bytes) - 4]).unwrap();
+        let d = diagnose(&path, Some(&key(1)), None).unwrap();
+        assert_eq!(d.verdict, Verdict::Truncated, "{:?}", d);
+        assert_eq!(d.chunks, 1);
+
+        // Without the key, the missing trailer still gives it away.
+        let d = diagnose(&path, None, None).unwrap();
+        assert_eq!(d.verdict, Verdict::Truncated);
+
+        let mut broken = bytes.clone();
+        broken[4..40].fill(0xFF);
+        fs::write(&path, &broken).unwrap();
+        let d = diagnose(&path, Some(&key(1)), None).unwrap();
+        assert_eq!(d.verdict, Verdict::HeaderDamaged);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE diagnosis.rs ---
@@ -0,0 +1,215 @@
+// --- START OF FILE drive_health.rs ---
+
+// ==========================================
+// --- DRIVE HEALTH (S.M.A.R.T.) ---
+// ==========================================
+// Overwriting a failing drive gives false confidence: sectors the firmware has already
+// remapped keep their old contents, and a drive that is about to die may not finish
+// the job. Before a free-space wipe or a large shred the command layer asks this
+// module for the target drive's health and warns when it looks bad.
+//
+// The data comes from smartmontools (`smartctl --json`), which covers SATA and NVMe on
+// Linux, macOS and Windows. Without smartctl, or without the privileges it needs, the
+// report says the check was unavailable; that is never treated as a failing drive.
+
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+/// Shreds above this total size get a health check first.
+pub const HEALTH_CHECK_THRESHOLD: u64 = 10 * 1024 * 1024 * 1024;
+
+/// ATA attribute ids that count sectors the drive could not (or can no longer) write.
+const ATTR_REALLOCATED: u64 = 5;
+const ATTR_PENDING: u64 = 197;
+const ATTR_UNCORRECTABLE: u64 = 198;
+
+#[derive(serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriveHealth {
+    /// Device queried, e.g. `/dev/sda1` or `C:`.
+    pub device: Option<String>,
+    /// smartctl ran and returned health data.
+    pub available: bool,
+    /// The drive's own overall verdict.
+    pub passed: Option<bool>,
+    pub reallocated_sectors: Option<u64>,
+    pub pending_sectors: Option<u64>,
+    pub uncorrectable_sectors: Option<u64>,
+    /// NVMe: media and data integrity errors.
+    pub media_errors: Option<u64>,
+    /// NVMe: percentage of rated endurance used (may exceed 100).
+    pub percentage_used: Option<u64>,
+    /// Why the check was unavailable, when it was.
+    pub note: Option<String>,
+    /// Set when the drive should not be trusted with an overwrite.
+    pub warning: Option<String>,
+}
+
+impl DriveHealth {
+    fn unavailable(device: Option<String>, note: impl Into<String>) -> Self {
+        Self {
+            device,
+            note: Some(note.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Reads the health of the drive holding `path`.
+pub fn check(path: &Path) -> DriveHealth {
+    let Some(device) = device_for_path(path) else {
+        return DriveHealth::unavailable(None, "Could not determine the drive for this path.");
+    };
+    let mut cmd = Command::new("smartctl");
+    cmd.args(["--json=c", "-H", "-A", &device]);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    match cmd.output() {
+        // smartctl's exit status is a bit mask that is non-zero for failing drives
+        // too, so the JSON is parsed whatever the status.
+        Ok(output) => parse_smartctl_json(&device, &String::from_utf8_lossy(&output.stdout)),
+        Err(_) => DriveHealth::unavailable(
+            Some(device),
+            "smartctl was not found. Install smartmontools to enable drive health checks.",
+        ),
+    }
+}
+
+/// Turns `smartctl --json -H -A` output into a report.
+pub(crate) fn parse_smartctl_json(device: &str, json: &str) -> DriveHealth {
+    let device = Some(device.to_string());
+    let Ok(root) = serde_json::from_str::<Value>(json) else {
+        return DriveHealth::unavailable(device, "smartctl returned no readable output.");
+    };
+    let passed = root["smart_status"]["passed"].as_bool();
+    if passed.is_none() {
+        let reason = root["smartctl"]["messages"]
+            .as_array()
+            .and_then(|m| m.first())
+            .and_then(|m| m["string"].as_str())
+            .unwrap_or("The drive did not report S.M.A.R.T. data.");
+        return DriveHealth::unavailable(device, reason);
+    }
+
+    let attribute = |id: u64| {
+        root["ata_smart_attributes"]["table"]
+            .as_array()?
+            .iter()
+            .find(|a| a["id"].as_u64() == Some(id))
+            .and_then(|a| a["raw"]["value"].as_u64())
+    };
+    let nvme = &root["nvme_smart_health_information_log"];
+    let mut health = DriveHealth {
+        device,
+        available: true,
+        passed,
+        reallocated_sectors: attribute(ATTR_REALLOCATED),
+        pending_sectors: attribute(ATTR_PENDING),
+        uncorrectable_sectors: attribute(ATTR_UNCORRECTABLE),
+        media_errors: nvme["media_errors"].as_u64(),
+        percentage_used: nvme["percentage_used"].as_u64(),
+        note: None,
+        warning: None,
+    };
+
+    let mut problems = Vec::new();
+    if passed == Some(false) {
+        problems.push("the drive reports that it is failing".to_string());
+    }
+    for (count, what) in [
+        (health.reallocated_sectors, "reallocated sectors"),
+        (health.pending_sectors, "sectors pending reallocation"),
+        (health.uncorrectable_sectors, "uncorrectable sectors"),
+        (health.media_errors, "media errors"),
+    ] {
+        if let Some(n) = count.filter(|&n| n > 0) {
+            problems.push(format!("{} {}", n, what));
+        }
+    }
+    if nvme["critical_warning"].as_u64().is_some_and(|w| w != 0) {
+        problems.push("an NVMe critical warning is set".to_string());
+    }
+    if health.percentage_used.is_some_and(|p| p >= 100) {
+        problems.push("its rated write endurance is used up".to_string());
+    }
+    if !problems.is_empty() {
+        health.warning = Some(format!(
+            "Drive health warning: {}. Overwrites on a failing drive may leave remapped sectors untouched or stop partway. Back up what you need and consider physical destruction instead.",
+            problems.join(", ")
+        ));
+    }
+    health
+}
+
+/// The device node (Unix) or drive letter (Windows) that holds `path`.
+fn device_for_path(path: &Path) -> Option<String> {
+    #[cfg(target_os = "windows")]
+    {
+        let text = path.to_string_lossy();
+        let text = text.trim_start_matches(r"\\?\");
+        let letter = text.chars().next().filter(|c| c.is_ascii_alphabetic())?;
+        (text.chars().nth(1) == Some(':')).then(|| format!("{}:", letter))
+    }
+    #[cfg(all(unix, not(target_os = "android")))]
+    {
+        // `df -P` prints one POSIX-format line per file system; the first column is
+        // the device on both Linux and macOS.
+        let output = Command::new("df").arg("-P").arg(path).output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let device = stdout.lines().nth(1)?.split_whitespace().next()?;
+        device.starts_with("/dev/").then(|| device.to_string())
+    }
+    #[cfg(not(any(target_os = "windows", all(unix, not(target_os = "android")))))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_healthy_and_failing_ata() {
+        let healthy = r#"{"smart_status":{"passed":true},"ata_smart_attributes":{"table":[
+            {"id":5,"raw":{"value":0}},{"id":197,"raw":{"value":0}},{"id":198,"raw":{"value":0}}]}}"#;
+        let report = parse_smartctl_json("/dev/sda", healthy);
+        assert!(report.available && report.warning.is_none());
+        assert_eq!(report.reallocated_sectors, Some(0));
+
+        let worn = r#"{"smart_status":{"passed":true},"ata_smart_attributes":{"table":[
+            {"id":5,"raw":{"value":24}},{"id":197,"raw":{"value":3}}]}}"#;
+        let warning = parse_smartctl_json("/dev/sda", worn).warning.unwrap();
+        assert!(warning.contains("24 reallocated sectors"));
+        assert!(warning.contains("3 sectors pending"));
+
+        let failing = r#"{"smart_status":{"passed":false}}"#;
+        assert!(parse_smartctl_json("/dev/sda", failing)
+            .warning
+            .unwrap()
+            .contains("failing"));
+    }
+
+    #[test]
+    fn test_parse_nvme_and_unavailable() {
+        let nvme = r#"{"smart_status":{"passed":true},"nvme_smart_health_information_log":
+            {"critical_warning":0,"media_errors":0,"percentage_used":104}}"#;
+        let report = parse_smartctl_json("/dev/nvme0n1", nvme);
+        assert_eq!(report.percentage_used, Some(104));
+        assert!(report.warning.unwrap().contains("endurance"));
+
+        // No root: smartctl explains itself, and that is not a failing drive.
+        let denied = r#"{"smartctl":{"messages":[{"string":"Smartctl open device: /dev/sda failed: Permission denied","severity":"error"}]}}"#;
+        let report = parse_smartctl_json("/dev/sda", denied);
+        assert!(!report.available && report.warning.is_none());
+        assert!(report.note.unwrap().contains("Permission denied"));
+        assert!(!parse_smartctl_json("/dev/sda", "not json").available);
+    }
+}
+
+// --- END OF FILE drive_health.rs ---
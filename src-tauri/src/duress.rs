@@ -0,0 +1,192 @@
+// --- START OF FILE duress.rs ---
+
+// ==========================================
+// --- DURESS PASSWORD: DECOY VAULT ---
+// ==========================================
+// The duress slot (keychain.rs) opens a decoy vault instead of the real one. The decoy
+// is a complete vault of its own in `decoy/` next to the real keychain: a keychain.json
+// whose password is the duress password, plus whatever the decoy session saves. It
+// starts out empty; the owner can log in with the duress password beforehand and fill
+// it with believable entries. While a duress session is open, the "local" vault id
+// resolves into that directory (`resolve_keychain_path` in commands/vault.rs), so every
+// command works as usual.
+//
+// With the wipe option, the duress login first destroys the real vault (`wipe_real_vault`):
+//   1. The decoy keychain replaces the real one in a single rename. The Master Key was
+//      only ever stored wrapped in that file, so from here on the real files cannot be
+//      decrypted, even if a later step fails.
+//   2. The real vault files are deleted. Plain deletes are enough: they are unreadable.
+//   3. The decoy files move into their place and the decoy directory goes away.
+// What is left is an ordinary vault whose password is the duress password.
+//
+// LIMITS: this protects against someone watching the unlock, not against forensics.
+// Until a wipe, the duress slot in keychain.json and the `decoy/` directory are visible
+// to anyone who reads the disk. Copies of keychain.json made before a wipe (backups,
+// sync folders) still open the real vault, and SSDs may keep the old blocks.
+
+use crate::audit;
+use crate::breach_monitor;
+use crate::clipboard_store;
+use crate::note_assets;
+use crate::note_images;
+use crate::panel_lock;
+use crate::profiles::KEYCHAIN_FILE_NAME;
+use crate::secrets;
+use crate::vault_meta;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DECOY_DIR_NAME: &str = "decoy";
+
+/// Files next to the keychain that belong to the vault. The default profile shares its
+/// directory with app-wide files (profiles, auto-lock, pattern packs...), so only these
+/// are ever deleted or moved.
+const VAULT_FILES: &[&str] = &[
+    "passwords.qre",
+    "notes.qre",
+    note_assets::ASSETS_FILE_NAME,
+    "bookmarks.qre",
+    "clipboard.qre",
+    clipboard_store::JOURNAL_FILE_NAME,
+    secrets::SECRETS_FILE_NAME,
+    vault_meta::META_FILE_NAME,
+    breach_monitor::ALERTS_FILE_NAME,
+    audit::AUDIT_FILE_NAME,
+    "audit.log.1",
+    audit::ALERTS_FILE_NAME,
+    panel_lock::CONFIG_FILE_NAME,
+    "backup_done",
+];
+
+/// Where the decoy vault of the vault in `vault_dir` lives.
+pub fn decoy_dir(vault_dir: &Path) -> PathBuf {
+    vault_dir.join(DECOY_DIR_NAME)
+}
+
+/// The real vault's keychain, given the decoy vault's keychain path.
+pub fn real_keychain_path(decoy_keychain: &Path) -> Option<PathBuf> {
+    let vault_dir = decoy_keychain.parent()?.parent()?;
+    Some(vault_dir.join(KEYCHAIN_FILE_NAME))
+}
+
+/// Deletes the decoy vault, before a new duress password starts a fresh one or after
+/// the duress password was removed.
+pub fn remove_decoy(vault_dir: &Path) -> Result<()> {
+    let dir = decoy_dir(vault_dir);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).context("Failed to remove the decoy vault")?;
+    }
+    Ok(())
+}
+
+/// Destroys the real vault in `vault_dir` and puts the decoy vault in its place (see the
+/// steps above). Fails only if the real keychain could not be replaced; files that could
+/// not be deleted or moved afterwards are returned as `(file, error)`.
+pub fn wipe_real_vault(vault_dir: &Path) -> Result<Vec<(String, String)>> {
+    let decoy = decoy_dir(vault_dir);
+    let decoy_keychain = decoy.join(KEYCHAIN_FILE_NAME);
+    if !decoy_keychain.exists() {
+        return Err(anyhow!("The decoy vault is missing"));
+    }
+
+    // 1. Replace the keychain.
+    fs::rename(&decoy_keychain, vault_dir.join(KEYCHAIN_FILE_NAME))
+        .context("Failed to replace the keychain")?;
+
+    // 2. Delete the real vault files.
+    let mut failed = Vec::new();
+    for name in VAULT_FILES {
+        let path = vault_dir.join(name);
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                failed.push((name.to_string(), e.to_string()));
+            }
+        }
+    }
+    let images = vault_dir.join(note_images::IMAGES_DIR_NAME);
+    if images.exists() {
+        if let Err(e) = fs::remove_dir_all(&images) {
+            failed.push((note_images::IMAGES_DIR_NAME.to_string(), e.to_string()));
+        }
+    }
+
+    // 3. Move the decoy vault up.
+    let decoy_entries = VAULT_FILES
+        .iter()
+        .copied()
+        .chain([note_images::IMAGES_DIR_NAME]);
+    for name in decoy_entries {
+        let from = decoy.join(name);
+        if from.exists() {
+            if let Err(e) = fs::rename(&from, vault_dir.join(name)) {
+                failed.push((name.to_string(), e.to_string()));
+            }
+        }
+    }
+    if let Err(e) = fs::remove_dir_all(&decoy) {
+        failed.push((DECOY_DIR_NAME.to_string(), e.to_string()));
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_vault_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qre_duress_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(decoy_dir(&dir).join(note_images::IMAGES_DIR_NAME)).unwrap();
+        fs::create_dir_all(dir.join(note_images::IMAGES_DIR_NAME)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_wipe_puts_the_decoy_in_place() {
+        let dir = temp_vault_dir();
+        let decoy = decoy_dir(&dir);
+        for (name, content) in [
+            (KEYCHAIN_FILE_NAME, "real keychain"),
+            ("passwords.qre", "real passwords"),
+            ("notes.qre", "real notes"),
+            (audit::AUDIT_FILE_NAME, "real log"),
+            ("profiles.json", "app-wide"),
+        ] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        fs::write(dir.join(note_images::IMAGES_DIR_NAME).join("a.qre"), "img").unwrap();
+        fs::write(decoy.join(KEYCHAIN_FILE_NAME), "decoy keychain").unwrap();
+        fs::write(decoy.join("passwords.qre"), "decoy passwords").unwrap();
+
+        let failed = wipe_real_vault(&dir).unwrap();
+        assert!(failed.is_empty(), "{:?}", failed);
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+        assert_eq!(read(KEYCHAIN_FILE_NAME).as_deref(), Some("decoy keychain"));
+        assert_eq!(read("passwords.qre").as_deref(), Some("decoy passwords"));
+        assert_eq!(read("notes.qre"), None);
+        assert_eq!(read(audit::AUDIT_FILE_NAME), None);
+        assert_eq!(read("profiles.json").as_deref(), Some("app-wide"));
+        let images = dir.join(note_images::IMAGES_DIR_NAME);
+        assert!(images.exists() && !images.join("a.qre").exists());
+        assert!(!decoy.exists());
+
+        // Without a decoy keychain nothing is touched.
+        assert!(wipe_real_vault(&dir).is_err());
+        assert!(dir.join(KEYCHAIN_FILE_NAME).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_decoy_paths() {
+        let dir = Path::new("data");
+        let decoy_keychain = decoy_dir(dir).join(KEYCHAIN_FILE_NAME);
+        assert_eq!(
+            real_keychain_path(&decoy_keychain).unwrap(),
+            dir.join(KEYCHAIN_FILE_NAME)
+        );
+    }
+}
+
+// --- END OF FILE duress.rs ---
@@ -0,0 +1,233 @@
+// --- START OF FILE entropy.rs ---
+
+// ==========================================
+// --- PARANOID MODE ENTROPY MIXING ---
+// ==========================================
+// The OS CSPRNG is always the base of every file key. Paranoid mode additionally XORs
+// in a 32-byte seed built from sources the user controls, so that a backdoored or
+// broken OS RNG alone is not enough to predict keys.
+//
+// Sources (all opt-in except the user's mouse/keyboard input, which the UI collects):
+//   - User input:    raw bytes from the EntropyModal (mouse movement timing).
+//   - CPU jitter:    timing noise of a small workload, measured here in the backend.
+//   - Audio noise:   coarse microphone samples, captured by the desktop frontend.
+//   - Motion sensor: accelerometer readings, captured by the Android frontend.
+//
+// Every source is fed, length-prefixed and tagged, into one SHA-256. Mixing can only
+// add unpredictability: a source that is constant or attacker-known contributes
+// nothing but also cannot cancel the others out.
+//
+// SECURITY: The "bits" in the report are a deliberately conservative min-entropy
+// estimate for the UI. They are never used to weaken any other check.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::hint::black_box;
+use std::time::Instant;
+use zeroize::Zeroizing;
+
+/// Frontend-captured samples larger than this are rejected (1 MB).
+pub const MAX_SAMPLE_BYTES: usize = 1024 * 1024;
+/// Number of timing measurements taken by the jitter collector.
+const JITTER_SAMPLES: usize = 2048;
+/// No single source is credited with more than the seed size.
+const MAX_BITS_PER_SOURCE: u32 = 256;
+
+/// The mixed 32-byte seed XORed into the OS RNG seed by `crypto_stream`.
+pub type EntropySeed = Zeroizing<[u8; 32]>;
+
+/// Which additional sources the user opted into for this operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EntropyOptions {
+    pub cpu_jitter: bool,
+    /// Raw microphone samples (e.g. 8-bit PCM) collected by the desktop UI.
+    pub audio_noise: Option<Vec<u8>>,
+    /// Raw accelerometer readings collected by the Android UI.
+    pub motion_sensor: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SourceContribution {
+    pub source: String,
+    pub bytes: usize,
+    pub estimated_bits: u32,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct EntropyReport {
+    pub sources: Vec<SourceContribution>,
+    /// Sum of the per-source estimates, capped at 256 (the seed size).
+    pub total_estimated_bits: u32,
+}
+
+// ==========================================
+// --- COLLECTORS ---
+// ==========================================
+
+/// Collects CPU timing jitter: the low byte of the duration of a short, fixed workload.
+/// Cache misses, interrupts and frequency scaling make these durations unpredictable.
+pub fn collect_cpu_jitter(samples: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(samples);
+    let mut acc: u64 = 0x9E37_79B9_7F4A_7C15;
+    for _ in 0..samples {
+        let start = Instant::now();
+        for i in 0..64u64 {
+            acc = black_box(acc.rotate_left(7) ^ i.wrapping_mul(0x2545_F491_4F6C_DD1D));
+        }
+        out.push((start.elapsed().as_nanos() & 0xFF) as u8);
+    }
+    black_box(acc);
+    out
+}
+
+/// Conservative min-entropy estimate of a byte sample: -log2(p_max) per byte,
+/// halved as a safety margin for correlated samples, capped at `MAX_BITS_PER_SOURCE`.
+pub fn estimate_bits(samples: &[u8]) -> u32 {
+    if samples.is_empty() {
+        return 0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in samples {
+        counts[b as usize] += 1;
+    }
+    let max = *counts.iter().max().unwrap_or(&0) as f64;
+    let p_max = max / samples.len() as f64;
+    let per_sample = -p_max.log2();
+    let bits = (per_sample * samples.len() as f64 / 2.0).floor();
+    (bits.max(0.0) as u32).min(MAX_BITS_PER_SOURCE)
+}
+
+// ==========================================
+// --- MIXING ---
+// ==========================================
+
+fn absorb(hasher: &mut Sha256, report: &mut EntropyReport, source: &str, bytes: &[u8]) {
+    hasher.update((source.len() as u32).to_le_bytes());
+    hasher.update(source.as_bytes());
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+    report.sources.push(SourceContribution {
+        source: source.to_string(),
+        bytes: bytes.len(),
+        estimated_bits: estimate_bits(bytes),
+    });
+}
+
+/// Mixes the user's input with the opted-in sources into a 32-byte paranoid-mode seed.
+/// Returns `None` for the seed when no source provided any bytes.
+pub fn mix_sources(
+    user_input: Option<&[u8]>,
+    options: &EntropyOptions,
+) -> Result<(Option<EntropySeed>, EntropyReport), String> {
+    for (name, sample) in [
+        ("audio noise", &options.audio_noise),
+        ("motion sensor", &options.motion_sensor),
+    ] {
+        if sample.as_ref().is_some_and(|s| s.len() > MAX_SAMPLE_BYTES) {
+            return Err(format!("The {} sample is too large.", name));
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_ENTROPY_MIX_V1");
+    let mut report = EntropyReport::default();
+
+    if let Some(bytes) = user_input.filter(|b| !b.is_empty()) {
+        absorb(&mut hasher, &mut report, "user_input", bytes);
+    }
+    if options.cpu_jitter {
+        let jitter = Zeroizing::new(collect_cpu_jitter(JITTER_SAMPLES));
+        absorb(&mut hasher, &mut report, "cpu_jitter", &jitter);
+    }
+    if let Some(bytes) = options.audio_noise.as_deref().filter(|b| !b.is_empty()) {
+        absorb(&mut hasher, &mut report, "audio_noise", bytes);
+    }
+    if let Some(bytes) = options.motion_sensor.as_deref().filter(|b| !b.is_empty()) {
+        absorb(&mut hasher, &mut report, "motion_sensor", bytes);
+    }
+
+    report.total_estimated_bits = report
+        .sources
+        .iter()
+        .map(|s| s.estimated_bits)
+        .sum::<u32>()
+        .min(MAX_BITS_PER_SOURCE);
+
+    if report.sources.is_empty() {
+        return Ok((None, report));
+    }
+    let mut seed = Zeroizing::new([0u8; 32]);
+    seed.copy_from_slice(&hasher.finalize());
+    Ok((Some(seed), report))
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_bits_is_conservative() {
+        assert_eq!(estimate_bits(&[]), 0);
+        assert_eq!(
+            estimate_bits(&[42u8; 4096]),
+            0,
+            "constant input is worthless"
+        );
+        let uniform: Vec<u8> = (0..=255u8).collect();
+        assert_eq!(
+            estimate_bits(&uniform),
+            MAX_BITS_PER_SOURCE,
+            "capped per source"
+        );
+        let sixteen: Vec<u8> = (0..16u8).collect();
+        assert_eq!(estimate_bits(&sixteen), 16 * 4 / 2);
+    }
+
+    #[test]
+    fn test_mix_reports_each_source_and_binds_all_inputs() {
+        let opts = EntropyOptions {
+            cpu_jitter: false,
+            audio_noise: Some((0..64u8).collect()),
+            motion_sensor: None,
+        };
+        let user: Vec<u8> = (100..132u8).collect();
+        let (seed, report) = mix_sources(Some(&user), &opts).unwrap();
+        let names: Vec<&str> = report.sources.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(names, vec!["user_input", "audio_noise"]);
+        assert!(report.total_estimated_bits > 0);
+
+        // Deterministic without jitter; any input change changes the seed.
+        let (again, _) = mix_sources(Some(&user), &opts).unwrap();
+        assert_eq!(*seed.clone().unwrap(), *again.unwrap());
+        let (other, _) = mix_sources(Some(&user[1..]), &opts).unwrap();
+        assert_ne!(*seed.unwrap(), *other.unwrap());
+    }
+
+    #[test]
+    fn test_mix_without_sources_and_oversized_samples() {
+        let (seed, report) = mix_sources(None, &EntropyOptions::default()).unwrap();
+        assert!(seed.is_none());
+        assert!(report.sources.is_empty());
+
+        let huge = EntropyOptions {
+            motion_sensor: Some(vec![0u8; MAX_SAMPLE_BYTES + 1]),
+            ..Default::default()
+        };
+        assert!(mix_sources(None, &huge).is_err());
+    }
+
+    #[test]
+    fn test_cpu_jitter_collects_requested_samples() {
+        let jitter = collect_cpu_jitter(256);
+        assert_eq!(jitter.len(), 256);
+        // Reported, but not asserted > 0: a VM with a coarse clock may legitimately yield nothing.
+        let _ = estimate_bits(&jitter);
+    }
+}
+
+// --- END OF FILE entropy.rs ---
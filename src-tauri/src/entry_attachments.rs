@@ -0,0 +1,141 @@
+// --- START OF FILE entry_attachments.rs ---
+
+// ==========================================
+// --- PASSWORD ENTRY ATTACHMENTS ---
+// ==========================================
+// Recovery-code PDFs, licence files and the like. Like note images (note_images.rs),
+// they are kept out of `passwords.qre`, which is re-encrypted on every save: the entry
+// only lists them, and each file lives next to the vault in its own encrypted container
+// `entry_attachments/<id>.qre`.
+//
+// The lists are owned by the backend. Attachments are added and removed through their
+// own commands, and a whole-vault save keeps the stored lists (see
+// `PasswordVault::preserve_attachments_from`), so a stale frontend copy cannot orphan a
+// file. The files of deleted entries are removed once the save succeeds.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub const ATTACHMENTS_DIR_NAME: &str = "entry_attachments";
+/// Attachments are for small documents; larger files belong in the file vault.
+pub const MAX_ATTACHMENT_BYTES: usize = 5 * 1024 * 1024;
+pub const MAX_ATTACHMENTS_PER_ENTRY: usize = 20;
+const MAX_NAME_CHARS: usize = 255;
+
+/// An attachment as listed on its entry. The name is as sensitive as the entry, so it
+/// is scrubbed from RAM on drop like the other fields.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct EntryAttachment {
+    pub id: String,
+    pub name: String,
+    pub size_bytes: u64,
+    pub added_at: i64,
+}
+
+/// IDs are UUIDs generated by the backend; anything else is refused before it can be
+/// joined onto a path.
+pub fn validate_attachment_id(id: &str) -> Result<()> {
+    uuid::Uuid::parse_str(id)
+        .map(|_| ())
+        .map_err(|_| anyhow!("Invalid attachment ID '{}'.", id))
+}
+
+pub fn attachment_path(vault_dir: &Path, id: &str) -> Result<PathBuf> {
+    validate_attachment_id(id)?;
+    Ok(vault_dir
+        .join(ATTACHMENTS_DIR_NAME)
+        .join(format!("{}.qre", id)))
+}
+
+/// Checks a new attachment and gives it an ID. Only the last path component of `name`
+/// is kept.
+pub fn prepare(name: &str, data: &[u8], now: i64) -> Result<EntryAttachment> {
+    if data.is_empty() {
+        return Err(anyhow!("The file is empty."));
+    }
+    if data.len() > MAX_ATTACHMENT_BYTES {
+        return Err(anyhow!(
+            "Attachments are limited to {} MB. Keep larger files in the file vault.",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(EntryAttachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: clean_file_name(name)?,
+        size_bytes: data.len() as u64,
+        added_at: now,
+    })
+}
+
+/// The last path component of `name`, trimmed and without control characters. Also
+/// used for note attachments (note_assets.rs).
+pub fn clean_file_name(name: &str) -> Result<String> {
+    let name = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(anyhow!("The attachment needs a file name."));
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(anyhow!(
+            "Attachment names are limited to {} characters.",
+            MAX_NAME_CHARS
+        ));
+    }
+    Ok(name)
+}
+
+/// Deletes an attachment's file. A missing file is not an error.
+pub fn remove_attachment_file(vault_dir: &Path, id: &str) -> Result<()> {
+    let path = attachment_path(vault_dir, id)?;
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_keeps_only_the_file_name() {
+        let attachment = prepare("C:\\Users\\me\\recovery codes.pdf", b"%PDF-1.7", 9).unwrap();
+        assert_eq!(attachment.name, "recovery codes.pdf");
+        assert_eq!((attachment.size_bytes, attachment.added_at), (8, 9));
+        validate_attachment_id(&attachment.id).unwrap();
+
+        assert_eq!(
+            prepare("../../licence\n.txt", b"key", 0).unwrap().name,
+            "licence.txt"
+        );
+        assert!(prepare("empty.txt", b"", 0).is_err());
+        assert!(prepare("dir/", b"x", 0).is_err());
+        assert!(prepare("..", b"x", 0).is_err());
+        assert!(prepare("big.bin", &vec![0u8; MAX_ATTACHMENT_BYTES + 1], 0).is_err());
+    }
+
+    #[test]
+    fn test_attachment_ids_cannot_escape_directory() {
+        let dir = Path::new("/vault");
+        assert!(attachment_path(dir, "../passwords").is_err());
+        let id = uuid::Uuid::new_v4().to_string();
+        assert_eq!(
+            attachment_path(dir, &id).unwrap(),
+            dir.join(ATTACHMENTS_DIR_NAME).join(format!("{}.qre", id))
+        );
+    }
+}
+
+// --- END OF FILE entry_attachments.rs ---
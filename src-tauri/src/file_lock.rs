@@ -0,0 +1,132 @@
+// --- START OF FILE file_lock.rs ---
+
+// ==========================================
+// --- ADVISORY FILE LOCKS ---
+// ==========================================
+// Two app instances (or two windows saving at once) could write the same vault file
+// concurrently and interleave the bytes. Every keychain and vault write now runs under
+// an exclusive OS lock on a `.qre.lock` file in the same directory:
+//   - The lock is on a separate file, not the vault itself, because the vault file is
+//     replaced by rename; a lock on the old inode would not cover the new one.
+//   - One lock per directory: the keychain, vaults and note images of a profile share
+//     a folder, and a single lock file avoids a sidecar next to every image.
+//   - The lock file is never deleted: removing lock files reintroduces the race.
+//   - Locks are advisory (flock / LockFileEx) and released by the OS when the process
+//     dies, so a crash can never leave a vault permanently locked.
+//
+// `write_atomic_locked` does the write-to-temp + fsync + rename under a held lock, so
+// readers see either the old or the new file, never a partial one.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long a writer waits for another instance to finish its save.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_POLL: Duration = Duration::from_millis(50);
+
+/// Held while writing `target`; the OS lock is released when this is dropped.
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct FileLock {
+    _file: File,
+}
+
+const LOCK_FILE_NAME: &str = ".qre.lock";
+
+pub fn lock_path(target: &Path) -> PathBuf {
+    target.with_file_name(LOCK_FILE_NAME)
+}
+
+impl FileLock {
+    /// Takes the exclusive lock for `target`, waiting up to `LOCK_TIMEOUT`.
+    pub fn exclusive(target: &Path) -> Result<Self> {
+        Self::exclusive_within(target, LOCK_TIMEOUT)
+    }
+
+    pub fn exclusive_within(target: &Path, timeout: Duration) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(target))
+            .context("Failed to open lock file")?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(fs::TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    std::thread::sleep(LOCK_POLL)
+                }
+                Err(fs::TryLockError::WouldBlock) => {
+                    return Err(anyhow!(
+                        "'{}' is being saved by another QRE window or instance. Try again.",
+                        target.file_name().unwrap_or_default().to_string_lossy()
+                    ))
+                }
+                Err(fs::TryLockError::Error(e)) => {
+                    return Err(anyhow!("Failed to lock '{}': {}", target.display(), e))
+                }
+            }
+        }
+    }
+}
+
+/// Replaces `path` via a temp file, fsync and rename. The caller holds the `FileLock`
+/// (the temp name is shared with any other writer of `path`). `write` fills the temp file.
+pub fn write_atomic_locked(path: &Path, write: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+
+    let result = (|| {
+        let mut file = File::create(&tmp_path).context("Failed to create temporary file")?;
+        write(&mut file)?;
+        file.sync_all().context("Failed to flush temporary file")?;
+        fs::rename(&tmp_path, path).context("Failed to replace file")
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("qre_file_lock_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_second_writer_waits_then_times_out() {
+        let target = temp_dir("contended").join("vault.qre");
+        let held = FileLock::exclusive(&target).unwrap();
+        let err = FileLock::exclusive_within(&target, Duration::from_millis(100))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("another QRE window"));
+        drop(held);
+        assert!(FileLock::exclusive_within(&target, Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_contents() {
+        let target = temp_dir("atomic").join("keychain.json");
+        fs::write(&target, b"old").unwrap();
+        let _lock = FileLock::exclusive(&target).unwrap();
+        write_atomic_locked(&target, |f| Ok(f.write_all(b"new contents")?)).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new contents");
+        assert!(!target.with_extension("tmp").exists());
+        assert!(lock_path(&target).exists());
+    }
+}
+
+// --- END OF FILE file_lock.rs ---
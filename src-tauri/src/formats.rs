@@ -0,0 +1,371 @@
+// --- START OF FILE formats.rs ---
+
+// ==========================================
+// --- CONTAINER FORMAT VERSIONS ---
+// ==========================================
+// Every .qre file starts with its format version, a little-endian u32. This module is
+// the one list of the versions this build reads and of the features each can carry, so
+// the UI can tell "made by a newer QRE, update to open it" apart from "not a QRE file".
+//
+// Feature flags (stable strings, shown by the UI):
+//   keyfile, streaming, time_lock, time_lock_ratchet, trailer, sealed_metadata, padding,
+//   expiry, archive, public_key_recipient, hidden_volume, hybrid_kem
+//
+// public_key_recipient is X25519 + ML-KEM-1024 (FIPS 203). The parameter set is named by a
+// KEM id in the V10 header, so it can change without a new version (see recipient.rs).
+//
+// FORWARD COMPATIBILITY: every format after 11 starts with a plaintext capability notice
+// right after the version, so that older releases can say what they are missing:
+//   u32 version | "QRECAPS\0" | u16 LE length | JSON
+//   JSON: { "min_app_version": "3.1.0", "features": ["time_lock", ...] }
+// The notice is advisory: nothing is decrypted or trusted because of it, and it holds
+// nothing a version number does not already give away. V12 (hybrid_kem) is the first
+// format written with a notice, at the start of its header region; for other versions
+// this build only looks for one after an unknown version.
+
+use crate::crypto_stream::{self, StreamHeader, VERSION_ARCHIVE, VERSION_HYBRID};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const NOTICE_MAGIC: &[u8; 8] = b"QRECAPS\0";
+const MAX_NOTICE_LEN: usize = 4096;
+const MAX_NOTICE_FEATURES: usize = 32;
+/// Enough for the version, a V5/V6 header (capped at 16 KB) or a V7+ header region.
+const MAX_PREFIX_LEN: u64 = 20 * 1024;
+/// Unknown versions up to this one, without a notice, are still taken for a newer QRE
+/// format. Larger numbers are almost always the first bytes of some other file.
+const LIKELY_FORMAT_MAX: u32 = 255;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FormatInfo {
+    pub version: u32,
+    pub name: &'static str,
+    /// Features files in this format can use.
+    pub features: &'static [&'static str],
+    /// New files are still written in this format; the others are only read.
+    pub writable: bool,
+}
+
+const FORMATS: &[FormatInfo] = &[
+    FormatInfo {
+        version: 4,
+        name: "Legacy container",
+        features: &["keyfile"],
+        writable: false,
+    },
+    FormatInfo {
+        version: 5,
+        name: "Streamed container",
+        features: &["keyfile", "streaming"],
+        writable: false,
+    },
+    FormatInfo {
+        version: 6,
+        name: "Streamed container",
+        features: &["keyfile", "streaming", "time_lock"],
+        writable: false,
+    },
+    FormatInfo {
+        version: 7,
+        name: "Streamed container",
+        features: &["keyfile", "streaming", "time_lock", "time_lock_ratchet"],
+        writable: false,
+    },
+    FormatInfo {
+        version: 8,
+        name: "Streamed container",
+        features: &[
+            "keyfile",
+            "streaming",
+            "time_lock",
+            "time_lock_ratchet",
+            "trailer",
+            "sealed_metadata",
+            "padding",
+            "expiry",
+        ],
+        writable: true,
+    },
+    FormatInfo {
+        version: VERSION_ARCHIVE,
+        name: "Multi-file archive",
+        features: &["keyfile", "streaming", "trailer", "archive"],
+        writable: true,
+    },
+    FormatInfo {
+        version: crypto_stream::VERSION_RECIPIENT,
+        name: "Public-key container",
+        features: &["streaming", "public_key_recipient"],
+        writable: true,
+    },
+    FormatInfo {
+        version: crypto_stream::VERSION_DENIABLE,
+        name: "Deniable container",
+        features: &["streaming", "hidden_volume"],
+        writable: true,
+    },
+    FormatInfo {
+        version: VERSION_HYBRID,
+        name: "Hybrid streamed container",
+        features: &[
+            "keyfile",
+            "streaming",
+            "time_lock",
+            "time_lock_ratchet",
+            "trailer",
+            "sealed_metadata",
+            "padding",
+            "expiry",
+            "hybrid_kem",
+        ],
+        writable: true,
+    },
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SupportedFormats {
+    pub app_version: &'static str,
+    pub newest_version: u32,
+    pub formats: &'static [FormatInfo],
+}
+
+/// What one file needs, read from its first bytes only.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FormatCapability {
+    pub version: u32,
+    /// This build can open the format.
+    pub supported: bool,
+    pub name: Option<String>,
+    /// Supported V5–V9 files: the features this file uses. Other supported formats: what
+    /// the format can carry. Newer formats: what their notice lists.
+    pub features: Vec<String>,
+    /// The oldest QRE release that opens the file, when the file says so.
+    pub required_app_version: Option<String>,
+    /// The file comes from a newer QRE; updating will open it.
+    pub needs_update: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct CapabilityNotice {
+    min_app_version: Option<String>,
+    features: Vec<String>,
+}
+
+pub fn supported_formats() -> SupportedFormats {
+    SupportedFormats {
+        app_version: APP_VERSION,
+        newest_version: newest_version(),
+        formats: FORMATS,
+    }
+}
+
+pub fn newest_version() -> u32 {
+    FORMATS.iter().map(|f| f.version).max().unwrap_or(0)
+}
+
+pub fn info(version: u32) -> Option<&'static FormatInfo> {
+    FORMATS.iter().find(|f| f.version == version)
+}
+
+/// Reads the start of the file at `path`, without any key.
+pub fn inspect(path: &Path) -> Result<FormatCapability> {
+    let mut prefix = Vec::new();
+    File::open(path)
+        .context("Failed to open file")?
+        .take(MAX_PREFIX_LEN)
+        .read_to_end(&mut prefix)
+        .context("Failed to read file")?;
+    inspect_bytes(&prefix)
+}
+
+/// Same as `inspect`, for the first bytes of a file.
+pub fn inspect_bytes(prefix: &[u8]) -> Result<FormatCapability> {
+    let version = prefix
+        .get(..4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .context("Not a QRE container")?;
+
+    if let Some(format) = info(version) {
+        let header = matches!(version, 5..=VERSION_ARCHIVE | VERSION_HYBRID)
+            .then(|| crypto_stream::parse_stream_header(version, &mut &prefix[4..]).ok())
+            .flatten();
+        let features = match header {
+            Some(header) => header_features(version, &header),
+            None => format.features.iter().map(|f| f.to_string()).collect(),
+        };
+        return Ok(FormatCapability {
+            version,
+            supported: true,
+            name: Some(format.name.to_string()),
+            features,
+            required_app_version: None,
+            needs_update: false,
+        });
+    }
+
+    let notice = (version > newest_version())
+        .then(|| read_notice(&prefix[4..]))
+        .flatten();
+    let needs_update =
+        version > newest_version() && (notice.is_some() || version <= LIKELY_FORMAT_MAX);
+    let notice = notice.unwrap_or_default();
+    Ok(FormatCapability {
+        version,
+        supported: false,
+        name: None,
+        features: notice.features,
+        required_app_version: notice.min_app_version,
+        needs_update,
+    })
+}
+
+/// The features a parsed V5–V9 or V12 header actually uses.
+fn header_features(version: u32, header: &StreamHeader) -> Vec<String> {
+    let mut features = vec!["streaming"];
+    if header.timelock.is_some() {
+        features.push("time_lock");
+        if version >= 7 {
+            features.push("time_lock_ratchet");
+        }
+    }
+    if version >= 8 {
+        features.push("trailer");
+    }
+    if header.metadata.is_some() {
+        features.push("sealed_metadata");
+    }
+    if header.padding.is_some() {
+        features.push("padding");
+    }
+    if header.expiry.is_some() {
+        features.push("expiry");
+    }
+    if version == VERSION_ARCHIVE {
+        features.push("archive");
+    }
+    if header.kem_wrap.is_some() {
+        features.push("hybrid_kem");
+    }
+    features.into_iter().map(str::to_string).collect()
+}
+
+/// The capability notice a V12 header region starts with: this release as the oldest
+/// one that opens the file, and the features the header uses.
+pub(crate) fn encode_notice(version: u32, header: &StreamHeader) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(&CapabilityNotice {
+        min_app_version: Some(APP_VERSION.to_string()),
+        features: header_features(version, header),
+    })?;
+    let mut notice = NOTICE_MAGIC.to_vec();
+    notice.extend_from_slice(&(json.len() as u16).to_le_bytes());
+    notice.extend_from_slice(&json);
+    Ok(notice)
+}
+
+/// Length of the well-formed notice at the start of `rest`, magic and length included.
+pub(crate) fn notice_len(rest: &[u8]) -> Option<usize> {
+    let body = rest.strip_prefix(NOTICE_MAGIC.as_slice())?;
+    let len = u16::from_le_bytes([*body.first()?, *body.get(1)?]) as usize;
+    (len <= MAX_NOTICE_LEN && body.len() >= 2 + len).then_some(NOTICE_MAGIC.len() + 2 + len)
+}
+
+/// Parses the capability notice at the start of `rest` (the bytes after the version).
+/// Anything malformed counts as no notice; unreadable entries are dropped.
+fn read_notice(rest: &[u8]) -> Option<CapabilityNotice> {
+    let json = &rest[NOTICE_MAGIC.len() + 2..notice_len(rest)?];
+    let mut notice: CapabilityNotice = serde_json::from_slice(json).ok()?;
+
+    notice.min_app_version = notice.min_app_version.filter(|v| {
+        !v.is_empty()
+            && v.len() <= 32
+            && v.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    });
+    notice.features.retain(|f| {
+        !f.is_empty()
+            && f.len() <= 64
+            && f.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    });
+    notice.features.truncate(MAX_NOTICE_FEATURES);
+    Some(notice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_notice(version: u32, json: &str) -> Vec<u8> {
+        let mut bytes = version.to_le_bytes().to_vec();
+        bytes.extend_from_slice(NOTICE_MAGIC);
+        bytes.extend_from_slice(&(json.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(json.as_bytes());
+        bytes.extend_from_slice(&[0xAB; 64]);
+        bytes
+    }
+
+    #[test]
+    fn test_format_table() {
+        let versions: Vec<u32> = FORMATS.iter().map(|f| f.version).collect();
+        assert_eq!(versions, (4..=12).collect::<Vec<u32>>());
+        assert_eq!(newest_version(), VERSION_HYBRID);
+        assert!(info(8).unwrap().writable && !info(7).unwrap().writable);
+        assert!(info(3).is_none() && info(13).is_none());
+        assert_eq!(supported_formats().app_version, APP_VERSION);
+    }
+
+    #[test]
+    fn test_newer_format_with_notice() {
+        let report = inspect_bytes(&with_notice(
+            13,
+            r#"{"min_app_version":"3.1.0","features":["time_lock","Bad Flag","quantum_kem"]}"#,
+        ))
+        .unwrap();
+        assert!(!report.supported && report.needs_update);
+        assert_eq!(report.required_app_version.as_deref(), Some("3.1.0"));
+        assert_eq!(report.features, ["time_lock", "quantum_kem"]);
+
+        // A future format far past the usual range is believed only with a notice.
+        let report = inspect_bytes(&with_notice(4000, r#"{"features":[]}"#)).unwrap();
+        assert!(report.needs_update && report.required_app_version.is_none());
+    }
+
+    #[test]
+    fn test_unknown_bytes_are_not_a_newer_format() {
+        // Plain text: "Hello world" reads as a huge version with no notice.
+        let report = inspect_bytes(b"Hello world").unwrap();
+        assert!(!report.supported && !report.needs_update);
+
+        // Unknown but small, without a notice: most likely a newer QRE.
+        let report = inspect_bytes(&20u32.to_le_bytes()).unwrap();
+        assert!(report.needs_update && report.features.is_empty());
+
+        // Older than every supported format.
+        let report = inspect_bytes(&[2, 0, 0, 0, 1, 2, 3]).unwrap();
+        assert!(!report.supported && !report.needs_update);
+
+        // A truncated or oversized notice is ignored rather than trusted.
+        let mut bytes = with_notice(13, r#"{"min_app_version":"3.1.0"}"#);
+        bytes[12..14].copy_from_slice(&u16::MAX.to_le_bytes());
+        let report = inspect_bytes(&bytes).unwrap();
+        assert!(report.needs_update && report.required_app_version.is_none());
+
+        assert!(inspect_bytes(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_known_version_reports_format_features() {
+        let report = inspect_bytes(&crypto_stream::VERSION_DENIABLE.to_le_bytes()).unwrap();
+        assert!(report.supported && !report.needs_update);
+        assert_eq!(report.name.as_deref(), Some("Deniable container"));
+        assert!(report.features.contains(&"hidden_volume".to_string()));
+    }
+}
+
+// --- END OF FILE formats.rs ---
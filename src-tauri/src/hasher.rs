@@ -1,5 +1,7 @@
 // --- START OF FILE hasher.rs ---
 
+use crate::progress::ProgressEmitter;
+use crate::resources;
 use anyhow::{anyhow, Result};
 use std::fs::File;
 use std::io::{BufReader, Read};
@@ -7,7 +9,6 @@ use std::path::Path;
 // AtomicBool is used for thread-safe communication, allowing the UI thread
 // to signal a background processing thread to stop what it's doing.
 use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::Emitter;
 
 // Import the Digest trait which provides the standard .update() and .finalize()
 // methods used by all the cryptographic hash algorithms below.
@@ -23,7 +24,6 @@ use sha2::Sha256;
 
 // SECURITY & PERFORMANCE LIMITS
 const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024 * 1024; // 10 GB limit to prevent exhausting system time/resources
-const BUFFER_SIZE: usize = 8192; // 8 KB buffer is standard for optimal disk I/O reads
 const PROGRESS_REPORT_INTERVAL: u64 = 10 * 1024 * 1024; // Only send a UI update every 10 MB to prevent flooding the React frontend with events
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -136,7 +136,8 @@ where
     let mut sha1 = Sha1::new();
     let mut md5_hasher = Md5::new();
 
-    let mut buffer = [0u8; BUFFER_SIZE];
+    // Large reads are faster; low resource mode keeps the buffer small (resources.rs).
+    let mut buffer = vec![0u8; resources::limits().read_buffer_bytes];
     let mut bytes_processed = 0u64;
     let mut last_progress_report = 0u64;
 
@@ -200,9 +201,138 @@ pub fn calculate_hashes<R: tauri::Runtime>(
     // Reset the global flag before starting
     CANCEL_FLAG.store(false, Ordering::Relaxed);
 
-    // Pass the global CANCEL_FLAG to the core function
+    // Pass the global CANCEL_FLAG to the core function.
+    // Chunk updates are coalesced; the core's closing 100% update is always delivered.
+    let events = ProgressEmitter::new(app_handle, "hash-progress");
     calculate_hashes_core(path_str, &CANCEL_FLAG, |progress| {
-        let _ = app_handle.emit("hash-progress", progress);
+        if progress.percentage >= 100 {
+            events.finish(progress);
+        } else {
+            events.emit(progress);
+        }
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PUBLISHED DIGEST VERIFICATION
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Digest algorithms a published checksum can be given in.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// Parses "sha256" / "sha-256" / "sha1" / "md5" (case-insensitive). "auto" or an empty
+    /// string infers the algorithm from the digest length.
+    pub fn resolve(name: &str, digest: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "md5" => Ok(HashAlgorithm::Md5),
+            "" | "auto" => match digest.len() {
+                64 => Ok(HashAlgorithm::Sha256),
+                40 => Ok(HashAlgorithm::Sha1),
+                32 => Ok(HashAlgorithm::Md5),
+                n => Err(anyhow!(
+                    "Cannot infer the algorithm from a {}-character digest.",
+                    n
+                )),
+            },
+            other => Err(anyhow!("Unsupported hash algorithm: {}", other)),
+        }
+    }
+
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Md5 => 32,
+        }
+    }
+
+    fn pick(self, hashes: &HashResult) -> &str {
+        match self {
+            HashAlgorithm::Sha256 => &hashes.sha256,
+            HashAlgorithm::Sha1 => &hashes.sha1,
+            HashAlgorithm::Md5 => &hashes.md5,
+        }
+    }
+}
+
+/// Outcome of checking a file against a published digest.
+#[derive(serde::Serialize, Debug)]
+pub struct HashVerification {
+    pub algorithm: HashAlgorithm,
+    pub expected: String,
+    pub actual: String,
+    pub matches: bool,
+}
+
+/// Normalizes a pasted digest. Accepts the bare hex digest, a `sha256sum`-style line
+/// ("<digest>  file.qre") or a prefixed form ("sha256:<digest>").
+pub fn normalize_digest(input: &str) -> String {
+    let token = input.split_whitespace().next().unwrap_or("");
+    let token = token.rsplit(':').next().unwrap_or(token);
+    token.to_ascii_lowercase()
+}
+
+/// Hashes the file with the shared engine and compares it with `expected`.
+/// Malformed digests are rejected up front so a typo is never reported as a mismatch.
+pub fn verify_hash_core<F>(
+    path_str: &str,
+    expected: &str,
+    algorithm: &str,
+    cancel_flag: &AtomicBool,
+    progress_callback: F,
+) -> Result<HashVerification>
+where
+    F: FnMut(ProgressPayload),
+{
+    let expected = normalize_digest(expected);
+    if expected.is_empty() || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("The expected hash must be a hexadecimal digest."));
+    }
+    let algorithm = HashAlgorithm::resolve(algorithm, &expected)?;
+    if expected.len() != algorithm.hex_len() {
+        return Err(anyhow!(
+            "A {:?} digest is {} characters long, but {} were given.",
+            algorithm,
+            algorithm.hex_len(),
+            expected.len()
+        ));
+    }
+
+    let hashes = calculate_hashes_core(path_str, cancel_flag, progress_callback)?;
+    let actual = algorithm.pick(&hashes).to_string();
+    Ok(HashVerification {
+        algorithm,
+        matches: actual == expected,
+        expected,
+        actual,
+    })
+}
+
+/// Tauri-facing wrapper: same progress channel and cancel flag as `calculate_hashes`.
+pub fn verify_hash<R: tauri::Runtime>(
+    path_str: &str,
+    expected: &str,
+    algorithm: &str,
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<HashVerification> {
+    CANCEL_FLAG.store(false, Ordering::Relaxed);
+
+    let events = ProgressEmitter::new(app_handle, "hash-progress");
+    verify_hash_core(path_str, expected, algorithm, &CANCEL_FLAG, |progress| {
+        if progress.percentage >= 100 {
+            events.finish(progress);
+        } else {
+            events.emit(progress);
+        }
     })
 }
 
@@ -320,6 +450,58 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn test_verify_hash_match_and_mismatch() {
+        let path = create_temp_file("verify_target.qre", "hello world");
+        let cancel_flag = AtomicBool::new(false);
+        let p = path.to_str().unwrap();
+
+        // sha256sum-style line, upper case, algorithm inferred from length.
+        let ok = verify_hash_core(
+            p,
+            "B94D27B9934D3E08A52E52D7DA7DABFAC484EFE37A5380EE9088F7ACE2EFCDE9  verify_target.qre",
+            "auto",
+            &cancel_flag,
+            |_| {},
+        )
+        .unwrap();
+        assert!(ok.matches);
+        assert_eq!(ok.algorithm, HashAlgorithm::Sha256);
+
+        let bad = verify_hash_core(
+            p,
+            "md5:00000000000000000000000000000000",
+            "MD5",
+            &cancel_flag,
+            |_| {},
+        )
+        .unwrap();
+        assert!(!bad.matches);
+        assert_eq!(bad.actual, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_verify_hash_rejects_malformed_digest() {
+        let path = create_temp_file("verify_malformed.qre", "hello world");
+        let cancel_flag = AtomicBool::new(false);
+        let p = path.to_str().unwrap();
+
+        assert!(verify_hash_core(p, "not-a-hash", "sha256", &cancel_flag, |_| {}).is_err());
+        // Length does not fit the named algorithm.
+        assert!(verify_hash_core(
+            p,
+            "5eb63bbbe01eeed093cb22bb8f5acdc3",
+            "sha256",
+            &cancel_flag,
+            |_| {}
+        )
+        .is_err());
+
+        let _ = fs::remove_file(path);
+    }
+
     #[test]
     fn test_cancel_hashing() {
         let data = vec![0u8; 10000];
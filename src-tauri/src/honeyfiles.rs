@@ -0,0 +1,488 @@
+// --- START OF FILE honeyfiles.rs ---
+
+// ==========================================
+// --- HONEYFILES ---
+// ==========================================
+// Bait files nobody has a reason to touch: a "passwords.xlsx" or a "seed phrase.txt"
+// full of plausible but fake secrets. If one is opened, changed or removed, someone (or
+// some malware) is browsing the user's files.
+//
+// Every honeyfile carries a unique marker ("QRE-…") that can be searched for in leaks,
+// and optionally a canary token URL (e.g. from canarytokens.org) that reports back when
+// the file is opened elsewhere. Locally, the watcher compares file metadata only: reading
+// the file to hash it would itself update the access time and trip the alarm.
+//
+// "Opened" relies on the OS recording access times. Linux (relatime) records the first
+// read after a write, macOS does by default, Windows only when last-access updates are
+// enabled. Modification and deletion are detected everywhere.
+
+use crate::random_names::{self, NameKind};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+pub const STORE_FILE_NAME: &str = "honeyfiles.json";
+const MAX_CANARY_URL_LEN: usize = 2048;
+const SEED_PHRASE_WORDS: usize = 24;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HoneyfileKind {
+    /// A spreadsheet of fake logins.
+    Passwords,
+    /// A fake 24-word wallet recovery phrase.
+    SeedPhrase,
+}
+
+impl HoneyfileKind {
+    pub fn default_file_name(self) -> &'static str {
+        match self {
+            HoneyfileKind::Passwords => "passwords.xlsx",
+            HoneyfileKind::SeedPhrase => "seed phrase.txt",
+        }
+    }
+}
+
+/// Metadata the watcher compares. Times are Unix seconds; `None` where the platform or
+/// filesystem does not record them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Fingerprint {
+    pub len: u64,
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Honeyfile {
+    pub id: String,
+    pub path: String,
+    pub kind: HoneyfileKind,
+    /// Unique string embedded in the file.
+    pub marker: String,
+    pub canary_url: Option<String>,
+    pub created_at: i64,
+    /// State at creation or at the last alert.
+    pub baseline: Fingerprint,
+    /// Set once a deletion has been reported, so it is not reported again.
+    #[serde(default)]
+    pub missing: bool,
+    #[serde(default)]
+    pub last_alert: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HoneyfileStore {
+    pub honeyfiles: Vec<Honeyfile>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HoneyfileEvent {
+    Opened,
+    Modified,
+    Deleted,
+}
+
+/// Payload of the `honeyfile-alert` event.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HoneyfileAlert {
+    pub id: String,
+    pub path: String,
+    pub event: HoneyfileEvent,
+    pub detected_at: i64,
+}
+
+// ==========================================
+// --- STORE ---
+// ==========================================
+
+fn store_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STORE_FILE_NAME)
+}
+
+pub fn load_store(data_dir: &Path) -> HoneyfileStore {
+    std::fs::read_to_string(store_path(data_dir))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_store(data_dir: &Path, store: &HoneyfileStore) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    std::fs::write(store_path(data_dir), json).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- BAIT CONTENT ---
+// ==========================================
+
+fn random_below(n: u32) -> Result<u32, String> {
+    random_names::random_below(n).map_err(|e| e.to_string())
+}
+
+fn random_word() -> Result<&'static str, String> {
+    random_names::pick(crate::wordlist::WORDLIST).map_err(|e| e.to_string())
+}
+
+fn new_marker() -> Result<String, String> {
+    let id = random_names::generate(NameKind::Uuid).map_err(|e| e.to_string())?;
+    Ok(format!("QRE-{}", &id.replace('-', "")[..16]))
+}
+
+/// Only plain http(s) URLs; anything else would end up as a live link in the bait.
+fn validate_canary_url(url: &str) -> Result<(), String> {
+    let valid = (url.starts_with("https://") || url.starts_with("http://"))
+        && url.len() <= MAX_CANARY_URL_LEN
+        && !url
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>\"'".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err("The canary URL must be a plain http(s) link.".to_string())
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn seed_phrase_text(marker: &str, canary_url: Option<&str>) -> Result<String, String> {
+    let words = (0..SEED_PHRASE_WORDS)
+        .map(|_| random_word())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut text = String::from("Wallet recovery phrase - DO NOT SHARE\n\n");
+    for (i, chunk) in words.chunks(6).enumerate() {
+        let numbered: Vec<String> = chunk
+            .iter()
+            .enumerate()
+            .map(|(j, w)| format!("{}. {}", i * 6 + j + 1, w))
+            .collect();
+        text.push_str(&numbered.join("  "));
+        text.push('\n');
+    }
+    text.push_str(&format!("\nBackup ID: {}\n", marker));
+    if let Some(url) = canary_url {
+        text.push_str(&format!("Restore wallet: {}\n", url));
+    }
+    Ok(text)
+}
+
+/// Rows of the fake password sheet: (service, username, password).
+fn fake_logins() -> Result<Vec<[String; 3]>, String> {
+    let services = [
+        "Online Banking",
+        "Email",
+        "Brokerage",
+        "Crypto Exchange",
+        "Work VPN",
+    ];
+    let user = random_word()?;
+    services
+        .iter()
+        .map(|service| {
+            let password = format!(
+                "{}-{}{}!",
+                random_word()?,
+                random_word()?,
+                random_below(100)?
+            );
+            Ok([
+                service.to_string(),
+                format!("{}.{}", user, random_below(1000)?),
+                password,
+            ])
+        })
+        .collect()
+}
+
+/// A minimal but valid .xlsx: one sheet with inline strings.
+fn passwords_xlsx(marker: &str, canary_url: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut rows: Vec<Vec<String>> =
+        vec![vec!["Service".into(), "Username".into(), "Password".into()]];
+    rows.extend(fake_logins()?.into_iter().map(Vec::from));
+    rows.push(vec![]);
+    rows.push(vec!["Ref".into(), marker.to_string()]);
+    if let Some(url) = canary_url {
+        rows.push(vec!["Reset link".into(), url.to_string()]);
+    }
+
+    let mut sheet = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    );
+    for (r, row) in rows.iter().enumerate() {
+        sheet.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, value) in row.iter().enumerate() {
+            sheet.push_str(&format!(
+                r#"<c r="{}{}" t="inlineStr"><is><t>{}</t></is></c>"#,
+                (b'A' + c as u8) as char,
+                r + 1,
+                xml_escape(value)
+            ));
+        }
+        sheet.push_str("</row>");
+    }
+    sheet.push_str("</sheetData></worksheet>");
+
+    let parts: [(&str, &str); 4] = [
+        (
+            "[Content_Types].xml",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#,
+        ),
+        (
+            "_rels/.rels",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+        ),
+        (
+            "xl/workbook.xml",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Logins" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        ),
+        (
+            "xl/_rels/workbook.xml.rels",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+        ),
+    ];
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    let mut write_part = |name: &str, body: &[u8]| -> zip::result::ZipResult<()> {
+        zip.start_file(name, options)?;
+        zip.write_all(body)?;
+        Ok(())
+    };
+    for (name, body) in parts {
+        write_part(name, body.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    write_part("xl/worksheets/sheet1.xml", sheet.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(zip.finish().map_err(|e| e.to_string())?.into_inner())
+}
+
+// ==========================================
+// --- CREATION ---
+// ==========================================
+
+fn unix_secs(time: std::io::Result<std::time::SystemTime>) -> Option<u64> {
+    time.ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Current metadata of `path`, or `None` if it is gone. Never reads the contents.
+pub fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some(Fingerprint {
+        len: meta.len(),
+        modified: unix_secs(meta.modified()),
+        accessed: unix_secs(meta.accessed()),
+    })
+}
+
+/// Writes a new honeyfile of `kind` into `directory` and registers it in the store.
+pub fn create(
+    data_dir: &Path,
+    kind: HoneyfileKind,
+    directory: &Path,
+    canary_url: Option<String>,
+) -> Result<Honeyfile, String> {
+    let canary_url = canary_url
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty());
+    if let Some(url) = &canary_url {
+        validate_canary_url(url)?;
+    }
+    let marker = new_marker()?;
+    let content = match kind {
+        HoneyfileKind::Passwords => passwords_xlsx(&marker, canary_url.as_deref())?,
+        HoneyfileKind::SeedPhrase => seed_phrase_text(&marker, canary_url.as_deref())?.into_bytes(),
+    };
+
+    let path = crate::utils::get_unique_path(&directory.join(kind.default_file_name()));
+    std::fs::write(&path, content).map_err(|e| e.to_string())?;
+    let baseline = fingerprint(&path).ok_or("The honeyfile could not be read back.")?;
+
+    let honeyfile = Honeyfile {
+        id: uuid::Uuid::new_v4().to_string(),
+        path: path.to_string_lossy().to_string(),
+        kind,
+        marker,
+        canary_url,
+        created_at: chrono::Utc::now().timestamp(),
+        baseline,
+        missing: false,
+        last_alert: None,
+    };
+    let mut store = load_store(data_dir);
+    store.honeyfiles.push(honeyfile.clone());
+    if let Err(e) = save_store(data_dir, &store) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(honeyfile)
+}
+
+/// Unregisters a honeyfile and, if asked, deletes it from disk.
+pub fn remove(data_dir: &Path, id: &str, delete_file: bool) -> Result<(), String> {
+    let mut store = load_store(data_dir);
+    let index = store
+        .honeyfiles
+        .iter()
+        .position(|h| h.id == id)
+        .ok_or("Honeyfile not found.")?;
+    let honeyfile = store.honeyfiles.remove(index);
+    save_store(data_dir, &store)?;
+    if delete_file {
+        match std::fs::remove_file(&honeyfile.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// ==========================================
+// --- WATCHER ---
+// ==========================================
+
+/// What changed between the baseline and the current state, if anything.
+pub fn classify(baseline: &Fingerprint, current: Option<&Fingerprint>) -> Option<HoneyfileEvent> {
+    let Some(current) = current else {
+        return Some(HoneyfileEvent::Deleted);
+    };
+    if current.len != baseline.len || current.modified != baseline.modified {
+        Some(HoneyfileEvent::Modified)
+    } else if current.accessed.is_some() && current.accessed != baseline.accessed {
+        Some(HoneyfileEvent::Opened)
+    } else {
+        None
+    }
+}
+
+/// Compares every honeyfile with its baseline. Each change is reported once: the baseline
+/// moves to the new state (a deletion is remembered until the file reappears).
+/// Returns the alerts; the caller saves the store when there are any.
+pub fn check(store: &mut HoneyfileStore, now: i64) -> Vec<HoneyfileAlert> {
+    let mut alerts = Vec::new();
+    for honeyfile in &mut store.honeyfiles {
+        let current = fingerprint(Path::new(&honeyfile.path));
+        match (honeyfile.missing, current) {
+            // Still gone: already reported.
+            (true, None) => continue,
+            // Put back: watch the new file from here on.
+            (true, Some(current)) => {
+                honeyfile.missing = false;
+                honeyfile.baseline = current;
+                continue;
+            }
+            (false, current) => {
+                let Some(event) = classify(&honeyfile.baseline, current.as_ref()) else {
+                    continue;
+                };
+                match current {
+                    Some(current) => honeyfile.baseline = current,
+                    None => honeyfile.missing = true,
+                }
+                honeyfile.last_alert = Some(now);
+                alerts.push(HoneyfileAlert {
+                    id: honeyfile.id.clone(),
+                    path: honeyfile.path.clone(),
+                    event,
+                    detected_at: now,
+                });
+            }
+        }
+    }
+    alerts
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qre_honeyfiles_tests_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_create_bait_files() {
+        let dir = test_dir("create");
+        let url = "https://canarytokens.com/abc/def".to_string();
+
+        let sheet = create(&dir, HoneyfileKind::Passwords, &dir, Some(url.clone())).unwrap();
+        assert!(sheet.path.ends_with("passwords.xlsx") && sheet.marker.starts_with("QRE-"));
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&sheet.path).unwrap()).unwrap();
+        let mut xml = String::new();
+        zip.by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert!(xml.contains(&sheet.marker) && xml.contains(&url));
+
+        let phrase = create(&dir, HoneyfileKind::SeedPhrase, &dir, None).unwrap();
+        let text = std::fs::read_to_string(&phrase.path).unwrap();
+        assert!(text.contains("24. ") && text.contains(&phrase.marker));
+        assert_ne!(sheet.marker, phrase.marker);
+
+        assert_eq!(load_store(&dir).honeyfiles.len(), 2);
+        assert!(create(
+            &dir,
+            HoneyfileKind::SeedPhrase,
+            &dir,
+            Some("javascript:x".into())
+        )
+        .is_err());
+
+        remove(&dir, &sheet.id, true).unwrap();
+        assert!(!Path::new(&sheet.path).exists());
+        assert_eq!(load_store(&dir).honeyfiles, vec![phrase]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_watcher_reports_each_change_once() {
+        let dir = test_dir("watch");
+        create(&dir, HoneyfileKind::SeedPhrase, &dir, None).unwrap();
+        let mut store = load_store(&dir);
+        let path = PathBuf::from(&store.honeyfiles[0].path);
+        assert!(check(&mut store, 1).is_empty());
+
+        // Opened: only the access time moves.
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(3600);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_times(std::fs::FileTimes::new().set_accessed(later))
+            .unwrap();
+        let alerts = check(&mut store, 2);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].event, HoneyfileEvent::Opened);
+        assert!(check(&mut store, 3).is_empty());
+
+        std::fs::write(&path, "edited").unwrap();
+        assert_eq!(check(&mut store, 4)[0].event, HoneyfileEvent::Modified);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(check(&mut store, 5)[0].event, HoneyfileEvent::Deleted);
+        assert!(check(&mut store, 6).is_empty());
+        assert_eq!(store.honeyfiles[0].last_alert, Some(5));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE honeyfiles.rs ---
@@ -0,0 +1,883 @@
+// --- START OF FILE i18n.rs ---
+
+// ==========================================
+// --- LOCALIZED ERROR MESSAGES ---
+// ==========================================
+// Command errors reach the user verbatim, so they must follow the UI language.
+// Instead of hard-coding English at each call site, the command layer raises an
+// `AppError` (a stable code plus named parameters) and the message is rendered from
+// the catalog below in the locale chosen with the `set_locale` command.
+//
+// Adding a message: add an `ErrorCode` variant, then one row to `template()` with
+// all three languages. Parameters are written `{name}` in every translation.
+//
+// Machine-readable prefixes (`TIME_LOCKED:`, `TIME_PUZZLE:`, `RATE_LIMITED:`, `BUSY:`,
+// `PANEL_LOCKED:`, `EXPIRED:`, `DESTROYED:`, `DRIVE_HEALTH:`, `UNSUPPORTED_FORMAT:`) are not
+// translated — the frontend parses them — only the human-readable tail is.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    El,
+    De,
+}
+
+impl Locale {
+    /// Accepts BCP 47 tags as sent by the browser ("de", "de-AT", "el-GR").
+    pub fn parse(tag: &str) -> Option<Self> {
+        let lang = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+        match lang.as_str() {
+            "en" => Some(Locale::En),
+            "el" => Some(Locale::El),
+            "de" => Some(Locale::De),
+            _ => None,
+        }
+    }
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::El => "el",
+            Locale::De => "de",
+        }
+    }
+}
+
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_locale(locale: Locale) {
+    CURRENT_LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn current_locale() -> Locale {
+    match CURRENT_LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::El,
+        2 => Locale::De,
+        _ => Locale::En,
+    }
+}
+
+// ==========================================
+// --- CATALOG ---
+// ==========================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    ReadOnlySession,
+    VaultLocked,
+    SessionCorrupted,
+    IncorrectPassword,
+    CurrentPasswordIncorrect,
+    OwnerOnly,
+    LoginLockedOut,
+    RecoveryLockedOut,
+    RateLimited,
+    JobBusy,
+    PathMissing,
+    PathInvalid,
+    PathProtected,
+    PathNotFound,
+    PathIsSymlink,
+    PathIsFolder,
+    PathNotFolder,
+    PathTooLarge,
+    FolderNotFound,
+    PanelLocked,
+    IncorrectPin,
+    PinLockedOut,
+    FormatNeedsUpdate,
+    FormatTooNew,
+    FormatUnknown,
+    PathTraversal,
+    PathNotAbsolute,
+    PathNoFileName,
+    NameInvalid,
+    ScanProtected,
+    UnsupportedOnAndroid,
+    KeychainNotFound,
+    KeychainPathInvalid,
+    KeychainCorrupted,
+    DataCorrupted,
+    DuressLocalOnly,
+    ProfileNotFound,
+    EntryNotFound,
+    TooManyAttachments,
+    AttachmentNotFound,
+    AttachmentInUse,
+    ImageNotFound,
+    ImageInUse,
+    ClipboardEntryNotFound,
+    NoBookmarks,
+    TotpKeyMissing,
+    TotpKeyNotProvided,
+    NotOtpauthLink,
+    BreachCheckRunning,
+    NoPairedDevice,
+    NoPendingUnlock,
+    DeviceNotPaired,
+    AutoUnlockUnavailable,
+    AutoUnlockDisabled,
+    ContactNotFound,
+    OwnIdentity,
+    IdentityMissing,
+    IdentityKeyMissing,
+    WrongRecipientVault,
+    PanelNotProtected,
+    PortableVaultLocked,
+    PortableVaultNotFound,
+    DriveNotFound,
+    DriveAlreadyVault,
+    GhostFileProtection,
+    NoFilesSelected,
+    NotAContainer,
+    BatchMismatch,
+    ExpiryInPast,
+    ExpiryNeedsSingleFile,
+    SplitKeyThresholdInvalid,
+    SplitKeyNeedsSingleFile,
+    SplitKeyWithIdentity,
+    SplitKeyExportUnsupported,
+    ParityNeedsSingleFile,
+    PostQuantumNeedsSingleFile,
+    ArchiveExportUnsupported,
+    ExportTooLarge,
+    PassphraseTooShort,
+    HiddenNeedsPassword,
+    SearchTermTooShort,
+    TimeLockAlreadyEncrypted,
+    NoTimeLockPuzzle,
+    DeletedPathNotAbsolute,
+    KeyfileExists,
+    KeyfileSizeInvalid,
+    KeyfileTooLargeForQr,
+    KeyfileBackupInvalid,
+    KeyfileBackupDamaged,
+    NoPicturesFolder,
+    PhotoScanMissing,
+    NoPhotosSelected,
+    NoDocumentsFolder,
+    DocumentScanMissing,
+    NoDocumentsSelected,
+    NoSitesSelected,
+    NoCookieStore,
+    HashInvalid,
+    OfflineIpNotChecked,
+}
+
+impl ErrorCode {
+    /// Stable identifier, for logs and for a frontend that wants to branch on the error.
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorCode::ReadOnlySession => "read_only_session",
+            ErrorCode::VaultLocked => "vault_locked",
+            ErrorCode::SessionCorrupted => "session_corrupted",
+            ErrorCode::IncorrectPassword => "incorrect_password",
+            ErrorCode::CurrentPasswordIncorrect => "current_password_incorrect",
+            ErrorCode::OwnerOnly => "owner_only",
+            ErrorCode::LoginLockedOut => "login_locked_out",
+            ErrorCode::RecoveryLockedOut => "recovery_locked_out",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::JobBusy => "job_busy",
+            ErrorCode::PathMissing => "path_missing",
+            ErrorCode::PathInvalid => "path_invalid",
+            ErrorCode::PathProtected => "path_protected",
+            ErrorCode::PathNotFound => "path_not_found",
+            ErrorCode::PathIsSymlink => "path_is_symlink",
+            ErrorCode::PathIsFolder => "path_is_folder",
+            ErrorCode::PathNotFolder => "path_not_folder",
+            ErrorCode::PathTooLarge => "path_too_large",
+            ErrorCode::FolderNotFound => "folder_not_found",
+            ErrorCode::PanelLocked => "panel_locked",
+            ErrorCode::IncorrectPin => "incorrect_pin",
+            ErrorCode::PinLockedOut => "pin_locked_out",
+            ErrorCode::FormatNeedsUpdate => "format_needs_update",
+            ErrorCode::FormatTooNew => "format_too_new",
+            ErrorCode::FormatUnknown => "format_unknown",
+            ErrorCode::PathTraversal => "path_traversal",
+            ErrorCode::PathNotAbsolute => "path_not_absolute",
+            ErrorCode::PathNoFileName => "path_no_file_name",
+            ErrorCode::NameInvalid => "name_invalid",
+            ErrorCode::ScanProtected => "scan_protected",
+            ErrorCode::UnsupportedOnAndroid => "unsupported_on_android",
+            ErrorCode::KeychainNotFound => "keychain_not_found",
+            ErrorCode::KeychainPathInvalid => "keychain_path_invalid",
+            ErrorCode::KeychainCorrupted => "keychain_corrupted",
+            ErrorCode::DataCorrupted => "data_corrupted",
+            ErrorCode::DuressLocalOnly => "duress_local_only",
+            ErrorCode::ProfileNotFound => "profile_not_found",
+            ErrorCode::EntryNotFound => "entry_not_found",
+            ErrorCode::TooManyAttachments => "too_many_attachments",
+            ErrorCode::AttachmentNotFound => "attachment_not_found",
+            ErrorCode::AttachmentInUse => "attachment_in_use",
+            ErrorCode::ImageNotFound => "image_not_found",
+            ErrorCode::ImageInUse => "image_in_use",
+            ErrorCode::ClipboardEntryNotFound => "clipboard_entry_not_found",
+            ErrorCode::NoBookmarks => "no_bookmarks",
+            ErrorCode::TotpKeyMissing => "totp_key_missing",
+            ErrorCode::TotpKeyNotProvided => "totp_key_not_provided",
+            ErrorCode::NotOtpauthLink => "not_otpauth_link",
+            ErrorCode::BreachCheckRunning => "breach_check_running",
+            ErrorCode::NoPairedDevice => "no_paired_device",
+            ErrorCode::NoPendingUnlock => "no_pending_unlock",
+            ErrorCode::DeviceNotPaired => "device_not_paired",
+            ErrorCode::AutoUnlockUnavailable => "auto_unlock_unavailable",
+            ErrorCode::AutoUnlockDisabled => "auto_unlock_disabled",
+            ErrorCode::ContactNotFound => "contact_not_found",
+            ErrorCode::OwnIdentity => "own_identity",
+            ErrorCode::IdentityMissing => "identity_missing",
+            ErrorCode::IdentityKeyMissing => "identity_key_missing",
+            ErrorCode::WrongRecipientVault => "wrong_recipient_vault",
+            ErrorCode::PanelNotProtected => "panel_not_protected",
+            ErrorCode::PortableVaultLocked => "portable_vault_locked",
+            ErrorCode::PortableVaultNotFound => "portable_vault_not_found",
+            ErrorCode::DriveNotFound => "drive_not_found",
+            ErrorCode::DriveAlreadyVault => "drive_already_vault",
+            ErrorCode::GhostFileProtection => "ghost_file_protection",
+            ErrorCode::NoFilesSelected => "no_files_selected",
+            ErrorCode::NotAContainer => "not_a_container",
+            ErrorCode::BatchMismatch => "batch_mismatch",
+            ErrorCode::ExpiryInPast => "expiry_in_past",
+            ErrorCode::ExpiryNeedsSingleFile => "expiry_needs_single_file",
+            ErrorCode::SplitKeyThresholdInvalid => "split_key_threshold_invalid",
+            ErrorCode::SplitKeyNeedsSingleFile => "split_key_needs_single_file",
+            ErrorCode::SplitKeyWithIdentity => "split_key_with_identity",
+            ErrorCode::SplitKeyExportUnsupported => "split_key_export_unsupported",
+            ErrorCode::ParityNeedsSingleFile => "parity_needs_single_file",
+            ErrorCode::PostQuantumNeedsSingleFile => "post_quantum_needs_single_file",
+            ErrorCode::ArchiveExportUnsupported => "archive_export_unsupported",
+            ErrorCode::ExportTooLarge => "export_too_large",
+            ErrorCode::PassphraseTooShort => "passphrase_too_short",
+            ErrorCode::HiddenNeedsPassword => "hidden_needs_password",
+            ErrorCode::SearchTermTooShort => "search_term_too_short",
+            ErrorCode::TimeLockAlreadyEncrypted => "time_lock_already_encrypted",
+            ErrorCode::NoTimeLockPuzzle => "no_time_lock_puzzle",
+            ErrorCode::DeletedPathNotAbsolute => "deleted_path_not_absolute",
+            ErrorCode::KeyfileExists => "keyfile_exists",
+            ErrorCode::KeyfileSizeInvalid => "keyfile_size_invalid",
+            ErrorCode::KeyfileTooLargeForQr => "keyfile_too_large_for_qr",
+            ErrorCode::KeyfileBackupInvalid => "keyfile_backup_invalid",
+            ErrorCode::KeyfileBackupDamaged => "keyfile_backup_damaged",
+            ErrorCode::NoPicturesFolder => "no_pictures_folder",
+            ErrorCode::PhotoScanMissing => "photo_scan_missing",
+            ErrorCode::NoPhotosSelected => "no_photos_selected",
+            ErrorCode::NoDocumentsFolder => "no_documents_folder",
+            ErrorCode::DocumentScanMissing => "document_scan_missing",
+            ErrorCode::NoDocumentsSelected => "no_documents_selected",
+            ErrorCode::NoSitesSelected => "no_sites_selected",
+            ErrorCode::NoCookieStore => "no_cookie_store",
+            ErrorCode::HashInvalid => "hash_invalid",
+            ErrorCode::OfflineIpNotChecked => "offline_ip_not_checked",
+        }
+    }
+
+    fn template(self, locale: Locale) -> &'static str {
+        use ErrorCode::*;
+        use Locale::*;
+        match (self, locale) {
+            (ReadOnlySession, En) => "This session is read-only (guest access).",
+            (ReadOnlySession, El) => "Αυτή η συνεδρία είναι μόνο για ανάγνωση (πρόσβαση επισκέπτη).",
+            (ReadOnlySession, De) => "Diese Sitzung ist schreibgeschützt (Gastzugang).",
+
+            (VaultLocked, En) => "Vault is locked.",
+            (VaultLocked, El) => "Το θησαυροφυλάκιο είναι κλειδωμένο.",
+            (VaultLocked, De) => "Der Tresor ist gesperrt.",
+
+            (SessionCorrupted, En) => "Session state is corrupted. All vaults locked.",
+            (SessionCorrupted, El) => "Η κατάσταση της συνεδρίας είναι κατεστραμμένη. Όλα τα θησαυροφυλάκια κλειδώθηκαν.",
+            (SessionCorrupted, De) => "Der Sitzungszustand ist beschädigt. Alle Tresore wurden gesperrt.",
+
+            (IncorrectPassword, En) => "Incorrect Password",
+            (IncorrectPassword, El) => "Λανθασμένος κωδικός πρόσβασης",
+            (IncorrectPassword, De) => "Falsches Passwort",
+
+            (CurrentPasswordIncorrect, En) => "Current password is incorrect.",
+            (CurrentPasswordIncorrect, El) => "Ο τρέχων κωδικός πρόσβασης είναι λανθασμένος.",
+            (CurrentPasswordIncorrect, De) => "Das aktuelle Passwort ist falsch.",
+
+            (OwnerOnly, En) => "Only the vault owner can manage users.",
+            (OwnerOnly, El) => "Μόνο ο κάτοχος του θησαυροφυλακίου μπορεί να διαχειρίζεται χρήστες.",
+            (OwnerOnly, De) => "Nur der Besitzer des Tresors kann Benutzer verwalten.",
+
+            (LoginLockedOut, En) => "Too many failed attempts. Please wait {seconds} more second(s).",
+            (LoginLockedOut, El) => "Πάρα πολλές αποτυχημένες προσπάθειες. Περιμένετε άλλα {seconds} δευτερόλεπτα.",
+            (LoginLockedOut, De) => "Zu viele fehlgeschlagene Versuche. Bitte warten Sie noch {seconds} Sekunde(n).",
+
+            (RecoveryLockedOut, En) => "Too many failed recovery attempts. Please wait {seconds} more second(s).",
+            (RecoveryLockedOut, El) => "Πάρα πολλές αποτυχημένες προσπάθειες ανάκτησης. Περιμένετε άλλα {seconds} δευτερόλεπτα.",
+            (RecoveryLockedOut, De) => "Zu viele fehlgeschlagene Wiederherstellungsversuche. Bitte warten Sie noch {seconds} Sekunde(n).",
+
+            (RateLimited, En) => "Too many requests. Please wait {seconds} second(s) and try again.",
+            (RateLimited, El) => "Πάρα πολλά αιτήματα. Περιμένετε {seconds} δευτερόλεπτα και δοκιμάστε ξανά.",
+            (RateLimited, De) => "Zu viele Anfragen. Bitte warten Sie {seconds} Sekunde(n) und versuchen Sie es erneut.",
+
+            (JobBusy, En) => "Another {job} operation is already running. Wait for it to finish or cancel it.",
+            (JobBusy, El) => "Εκτελείται ήδη άλλη λειτουργία ({job}). Περιμένετε να ολοκληρωθεί ή ακυρώστε την.",
+            (JobBusy, De) => "Ein anderer Vorgang ({job}) läuft bereits. Warten Sie, bis er abgeschlossen ist, oder brechen Sie ihn ab.",
+
+            (PathMissing, En) => "No path was provided.",
+            (PathMissing, El) => "Δεν δόθηκε διαδρομή.",
+            (PathMissing, De) => "Es wurde kein Pfad angegeben.",
+
+            (PathInvalid, En) => "Invalid path.",
+            (PathInvalid, El) => "Μη έγκυρη διαδρομή.",
+            (PathInvalid, De) => "Ungültiger Pfad.",
+
+            (PathProtected, En) => "Access Denied: '{path}' is a protected system path.",
+            (PathProtected, El) => "Δεν επιτρέπεται η πρόσβαση: το '{path}' είναι προστατευμένη διαδρομή συστήματος.",
+            (PathProtected, De) => "Zugriff verweigert: '{path}' ist ein geschützter Systempfad.",
+
+            (PathNotFound, En) => "'{path}' does not exist.",
+            (PathNotFound, El) => "Το '{path}' δεν υπάρχει.",
+            (PathNotFound, De) => "'{path}' existiert nicht.",
+
+            (PathIsSymlink, En) => "'{path}' is a symbolic link, which is not allowed here.",
+            (PathIsSymlink, El) => "Το '{path}' είναι συμβολικός σύνδεσμος, ο οποίος δεν επιτρέπεται εδώ.",
+            (PathIsSymlink, De) => "'{path}' ist eine symbolische Verknüpfung, die hier nicht erlaubt ist.",
+
+            (PathIsFolder, En) => "'{path}' is a folder, not a file.",
+            (PathIsFolder, El) => "Το '{path}' είναι φάκελος, όχι αρχείο.",
+            (PathIsFolder, De) => "'{path}' ist ein Ordner, keine Datei.",
+
+            (PathNotFolder, En) => "'{path}' is not a folder.",
+            (PathNotFolder, El) => "Το '{path}' δεν είναι φάκελος.",
+            (PathNotFolder, De) => "'{path}' ist kein Ordner.",
+
+            (PathTooLarge, En) => "'{path}' is too large ({size} bytes, limit {limit}).",
+            (PathTooLarge, El) => "Το '{path}' είναι πολύ μεγάλο ({size} bytes, όριο {limit}).",
+            (PathTooLarge, De) => "'{path}' ist zu groß ({size} Bytes, Grenze {limit}).",
+
+            (FolderNotFound, En) => "The folder '{path}' does not exist.",
+            (FolderNotFound, El) => "Ο φάκελος '{path}' δεν υπάρχει.",
+            (FolderNotFound, De) => "Der Ordner '{path}' existiert nicht.",
+
+            (PanelLocked, En) => "Enter your PIN to open this panel.",
+            (PanelLocked, El) => "Εισαγάγετε το PIN σας για να ανοίξετε αυτόν τον πίνακα.",
+            (PanelLocked, De) => "Geben Sie Ihre PIN ein, um diesen Bereich zu öffnen.",
+
+            (IncorrectPin, En) => "Incorrect PIN. {remaining} attempt(s) left.",
+            (IncorrectPin, El) => "Λανθασμένο PIN. Απομένουν {remaining} προσπάθειες.",
+            (IncorrectPin, De) => "Falsche PIN. Noch {remaining} Versuch(e).",
+
+            (PinLockedOut, En) => "Too many incorrect PINs. Lock and unlock the vault to try again.",
+            (PinLockedOut, El) => "Πάρα πολλά λανθασμένα PIN. Κλειδώστε και ξεκλειδώστε το θησαυροφυλάκιο για να δοκιμάσετε ξανά.",
+            (PinLockedOut, De) => "Zu viele falsche PINs. Sperren und entsperren Sie den Tresor, um es erneut zu versuchen.",
+
+            (FormatNeedsUpdate, En) => "This file needs QRE {required} or newer (this is {current}). Update QRE to open it.",
+            (FormatNeedsUpdate, El) => "Αυτό το αρχείο απαιτεί QRE {required} ή νεότερο (αυτή είναι η {current}). Ενημερώστε το QRE για να το ανοίξετε.",
+            (FormatNeedsUpdate, De) => "Diese Datei benötigt QRE {required} oder neuer (dies ist {current}). Aktualisieren Sie QRE, um sie zu öffnen.",
+
+            (FormatTooNew, En) => "This file was made by a newer version of QRE (format {version}). Update QRE to open it.",
+            (FormatTooNew, El) => "Αυτό το αρχείο δημιουργήθηκε από νεότερη έκδοση του QRE (μορφή {version}). Ενημερώστε το QRE για να το ανοίξετε.",
+            (FormatTooNew, De) => "Diese Datei wurde mit einer neueren QRE-Version erstellt (Format {version}). Aktualisieren Sie QRE, um sie zu öffnen.",
+
+            (FormatUnknown, En) => "This is not a QRE container this version can open (format {version}).",
+            (FormatUnknown, El) => "Αυτό δεν είναι κοντέινερ QRE που μπορεί να ανοίξει αυτή η έκδοση (μορφή {version}).",
+            (FormatUnknown, De) => "Dies ist kein QRE-Container, den diese Version öffnen kann (Format {version}).",
+
+            (PathTraversal, En) => "Path traversal is not allowed: the path must not contain '..'.",
+            (PathTraversal, El) => "Δεν επιτρέπεται η διάσχιση διαδρομής: η διαδρομή δεν πρέπει να περιέχει '..'.",
+            (PathTraversal, De) => "Pfad-Traversierung ist nicht erlaubt: Der Pfad darf kein '..' enthalten.",
+
+            (PathNotAbsolute, En) => "The file path must be absolute.",
+            (PathNotAbsolute, El) => "Η διαδρομή του αρχείου πρέπει να είναι απόλυτη.",
+            (PathNotAbsolute, De) => "Der Dateipfad muss absolut sein.",
+
+            (PathNoFileName, En) => "'{path}' has no file name.",
+            (PathNoFileName, El) => "Το '{path}' δεν έχει όνομα αρχείου.",
+            (PathNoFileName, De) => "'{path}' hat keinen Dateinamen.",
+
+            (NameInvalid, En) => "Invalid name.",
+            (NameInvalid, El) => "Μη έγκυρο όνομα.",
+            (NameInvalid, De) => "Ungültiger Name.",
+
+            (ScanProtected, En) => "Protected system directories cannot be scanned.",
+            (ScanProtected, El) => "Οι προστατευμένοι κατάλογοι συστήματος δεν μπορούν να σαρωθούν.",
+            (ScanProtected, De) => "Geschützte Systemverzeichnisse können nicht durchsucht werden.",
+
+            (UnsupportedOnAndroid, En) => "This is not supported on Android.",
+            (UnsupportedOnAndroid, El) => "Αυτό δεν υποστηρίζεται στο Android.",
+            (UnsupportedOnAndroid, De) => "Dies wird unter Android nicht unterstützt.",
+
+            (KeychainNotFound, En) => "Keychain not found on disk.",
+            (KeychainNotFound, El) => "Η κλειδοθήκη δεν βρέθηκε στον δίσκο.",
+            (KeychainNotFound, De) => "Der Schlüsselbund wurde auf dem Datenträger nicht gefunden.",
+
+            (KeychainPathInvalid, En) => "The keychain path has no parent folder.",
+            (KeychainPathInvalid, El) => "Η διαδρομή της κλειδοθήκης δεν έχει γονικό φάκελο.",
+            (KeychainPathInvalid, De) => "Der Pfad des Schlüsselbunds hat keinen übergeordneten Ordner.",
+
+            (KeychainCorrupted, En) => "The keychain is corrupted.",
+            (KeychainCorrupted, El) => "Η κλειδοθήκη είναι κατεστραμμένη.",
+            (KeychainCorrupted, De) => "Der Schlüsselbund ist beschädigt.",
+
+            (DataCorrupted, En) => "Stored vault data could not be read. It may be damaged.",
+            (DataCorrupted, El) => "Τα αποθηκευμένα δεδομένα του θησαυροφυλακίου δεν ήταν δυνατό να διαβαστούν. Μπορεί να είναι κατεστραμμένα.",
+            (DataCorrupted, De) => "Gespeicherte Tresordaten konnten nicht gelesen werden. Sie sind möglicherweise beschädigt.",
+
+            (DuressLocalOnly, En) => "A duress password can only be set for the local vault.",
+            (DuressLocalOnly, El) => "Κωδικός εξαναγκασμού μπορεί να οριστεί μόνο για το τοπικό θησαυροφυλάκιο.",
+            (DuressLocalOnly, De) => "Ein Zwangspasswort kann nur für den lokalen Tresor festgelegt werden.",
+
+            (ProfileNotFound, En) => "No profile named '{name}'.",
+            (ProfileNotFound, El) => "Δεν υπάρχει προφίλ με όνομα '{name}'.",
+            (ProfileNotFound, De) => "Kein Profil mit dem Namen '{name}'.",
+
+            (EntryNotFound, En) => "No entry found with ID '{id}'.",
+            (EntryNotFound, El) => "Δεν βρέθηκε καταχώριση με αναγνωριστικό '{id}'.",
+            (EntryNotFound, De) => "Kein Eintrag mit der ID '{id}' gefunden.",
+
+            (TooManyAttachments, En) => "An entry can hold up to {max} attachments.",
+            (TooManyAttachments, El) => "Μια καταχώριση μπορεί να έχει έως {max} συνημμένα.",
+            (TooManyAttachments, De) => "Ein Eintrag kann bis zu {max} Anhänge enthalten.",
+
+            (AttachmentNotFound, En) => "Attachment '{id}' not found.",
+            (AttachmentNotFound, El) => "Το συνημμένο '{id}' δεν βρέθηκε.",
+            (AttachmentNotFound, De) => "Anhang '{id}' nicht gefunden.",
+
+            (AttachmentInUse, En) => "This attachment is still used by a note.",
+            (AttachmentInUse, El) => "Αυτό το συνημμένο χρησιμοποιείται ακόμη από μια σημείωση.",
+            (AttachmentInUse, De) => "Dieser Anhang wird noch von einer Notiz verwendet.",
+
+            (ImageNotFound, En) => "Image '{id}' not found.",
+            (ImageNotFound, El) => "Η εικόνα '{id}' δεν βρέθηκε.",
+            (ImageNotFound, De) => "Bild '{id}' nicht gefunden.",
+
+            (ImageInUse, En) => "This image is still used by a note.",
+            (ImageInUse, El) => "Αυτή η εικόνα χρησιμοποιείται ακόμη από μια σημείωση.",
+            (ImageInUse, De) => "Dieses Bild wird noch von einer Notiz verwendet.",
+
+            (ClipboardEntryNotFound, En) => "Clipboard entry not found.",
+            (ClipboardEntryNotFound, El) => "Η καταχώριση του προχείρου δεν βρέθηκε.",
+            (ClipboardEntryNotFound, De) => "Zwischenablage-Eintrag nicht gefunden.",
+
+            (NoBookmarks, En) => "No bookmarks found.",
+            (NoBookmarks, El) => "Δεν βρέθηκαν σελιδοδείκτες.",
+            (NoBookmarks, De) => "Keine Lesezeichen gefunden.",
+
+            (TotpKeyMissing, En) => "This entry has no 2FA key.",
+            (TotpKeyMissing, El) => "Αυτή η καταχώριση δεν έχει κλειδί 2FA.",
+            (TotpKeyMissing, De) => "Dieser Eintrag hat keinen 2FA-Schlüssel.",
+
+            (TotpKeyNotProvided, En) => "No 2FA key was provided.",
+            (TotpKeyNotProvided, El) => "Δεν δόθηκε κλειδί 2FA.",
+            (TotpKeyNotProvided, De) => "Es wurde kein 2FA-Schlüssel angegeben.",
+
+            (NotOtpauthLink, En) => "Not an otpauth:// link.",
+            (NotOtpauthLink, El) => "Δεν είναι σύνδεσμος otpauth://.",
+            (NotOtpauthLink, De) => "Kein otpauth://-Link.",
+
+            (BreachCheckRunning, En) => "A breach check is already running.",
+            (BreachCheckRunning, El) => "Εκτελείται ήδη έλεγχος διαρροών.",
+            (BreachCheckRunning, De) => "Eine Datenleck-Prüfung läuft bereits.",
+
+            (NoPairedDevice, En) => "No phone is paired with this vault.",
+            (NoPairedDevice, El) => "Κανένα τηλέφωνο δεν έχει συζευχθεί με αυτό το θησαυροφυλάκιο.",
+            (NoPairedDevice, De) => "Mit diesem Tresor ist kein Telefon gekoppelt.",
+
+            (NoPendingUnlock, En) => "There is no unlock request waiting. Start a new one.",
+            (NoPendingUnlock, El) => "Δεν υπάρχει εκκρεμές αίτημα ξεκλειδώματος. Ξεκινήστε νέο.",
+            (NoPendingUnlock, De) => "Es wartet keine Entsperranfrage. Starten Sie eine neue.",
+
+            (DeviceNotPaired, En) => "This phone is not paired with the vault asking for approval.",
+            (DeviceNotPaired, El) => "Αυτό το τηλέφωνο δεν έχει συζευχθεί με το θησαυροφυλάκιο που ζητά έγκριση.",
+            (DeviceNotPaired, De) => "Dieses Telefon ist nicht mit dem Tresor gekoppelt, der die Freigabe anfordert.",
+
+            (AutoUnlockUnavailable, En) => "Auto-unlock is not available on this platform.",
+            (AutoUnlockUnavailable, El) => "Το αυτόματο ξεκλείδωμα δεν είναι διαθέσιμο σε αυτή την πλατφόρμα.",
+            (AutoUnlockUnavailable, De) => "Automatisches Entsperren ist auf dieser Plattform nicht verfügbar.",
+
+            (AutoUnlockDisabled, En) => "Auto-unlock is not enabled for this vault.",
+            (AutoUnlockDisabled, El) => "Το αυτόματο ξεκλείδωμα δεν είναι ενεργό για αυτό το θησαυροφυλάκιο.",
+            (AutoUnlockDisabled, De) => "Automatisches Entsperren ist für diesen Tresor nicht aktiviert.",
+
+            (ContactNotFound, En) => "No contact named '{name}'.",
+            (ContactNotFound, El) => "Δεν υπάρχει επαφή με όνομα '{name}'.",
+            (ContactNotFound, De) => "Kein Kontakt mit dem Namen '{name}'.",
+
+            (OwnIdentity, En) => "That is this vault's own identity.",
+            (OwnIdentity, El) => "Αυτή είναι η ταυτότητα του ίδιου του θησαυροφυλακίου.",
+            (OwnIdentity, De) => "Das ist die eigene Identität dieses Tresors.",
+
+            (IdentityMissing, En) => "Create this vault's public identity before sending files.",
+            (IdentityMissing, El) => "Δημιουργήστε τη δημόσια ταυτότητα αυτού του θησαυροφυλακίου πριν στείλετε αρχεία.",
+            (IdentityMissing, De) => "Erstellen Sie die öffentliche Identität dieses Tresors, bevor Sie Dateien senden.",
+
+            (IdentityKeyMissing, En) => "This file is bound to a vault identity key that this vault does not have.",
+            (IdentityKeyMissing, El) => "Αυτό το αρχείο είναι δεσμευμένο σε κλειδί ταυτότητας θησαυροφυλακίου που αυτό το θησαυροφυλάκιο δεν διαθέτει.",
+            (IdentityKeyMissing, De) => "Diese Datei ist an einen Tresor-Identitätsschlüssel gebunden, den dieser Tresor nicht besitzt.",
+
+            (WrongRecipientVault, En) => "This file was encrypted for another vault's identity. Unlock the vault it was sent to.",
+            (WrongRecipientVault, El) => "Αυτό το αρχείο κρυπτογραφήθηκε για την ταυτότητα άλλου θησαυροφυλακίου. Ξεκλειδώστε το θησαυροφυλάκιο στο οποίο στάλθηκε.",
+            (WrongRecipientVault, De) => "Diese Datei wurde für die Identität eines anderen Tresors verschlüsselt. Entsperren Sie den Tresor, an den sie gesendet wurde.",
+
+            (PanelNotProtected, En) => "This panel is not protected by a PIN.",
+            (PanelNotProtected, El) => "Αυτός ο πίνακας δεν προστατεύεται με PIN.",
+            (PanelNotProtected, De) => "Dieser Bereich ist nicht durch eine PIN geschützt.",
+
+            (PortableVaultLocked, En) => "This file belongs to a Portable USB Vault. Please unlock the USB drive first.",
+            (PortableVaultLocked, El) => "Αυτό το αρχείο ανήκει σε φορητό θησαυροφυλάκιο USB. Ξεκλειδώστε πρώτα τη μονάδα USB.",
+            (PortableVaultLocked, De) => "Diese Datei gehört zu einem tragbaren USB-Tresor. Bitte entsperren Sie zuerst das USB-Laufwerk.",
+
+            (PortableVaultNotFound, En) => "Portable vault not found on this drive.",
+            (PortableVaultNotFound, El) => "Δεν βρέθηκε φορητό θησαυροφυλάκιο σε αυτή τη μονάδα.",
+            (PortableVaultNotFound, De) => "Auf diesem Laufwerk wurde kein tragbarer Tresor gefunden.",
+
+            (DriveNotFound, En) => "Drive not found.",
+            (DriveNotFound, El) => "Η μονάδα δίσκου δεν βρέθηκε.",
+            (DriveNotFound, De) => "Laufwerk nicht gefunden.",
+
+            (DriveAlreadyVault, En) => "Drive is already formatted as a QRE vault.",
+            (DriveAlreadyVault, El) => "Η μονάδα έχει ήδη διαμορφωθεί ως θησαυροφυλάκιο QRE.",
+            (DriveAlreadyVault, De) => "Das Laufwerk ist bereits als QRE-Tresor eingerichtet.",
+
+            (GhostFileProtection, En) => "Ghost-file protection: files on a portable USB drive cannot be encrypted directly. Copy them to your PC first.",
+            (GhostFileProtection, El) => "Προστασία από αρχεία-φαντάσματα: τα αρχεία σε φορητή μονάδα USB δεν μπορούν να κρυπτογραφηθούν απευθείας. Αντιγράψτε τα πρώτα στον υπολογιστή σας.",
+            (GhostFileProtection, De) => "Schutz vor Geisterdateien: Dateien auf einem tragbaren USB-Laufwerk können nicht direkt verschlüsselt werden. Kopieren Sie sie zuerst auf Ihren PC.",
+
+            (NoFilesSelected, En) => "No files selected.",
+            (NoFilesSelected, El) => "Δεν επιλέχθηκαν αρχεία.",
+            (NoFilesSelected, De) => "Keine Dateien ausgewählt.",
+
+            (NotAContainer, En) => "Not a QRE container.",
+            (NotAContainer, El) => "Δεν είναι κοντέινερ QRE.",
+            (NotAContainer, De) => "Kein QRE-Container.",
+
+            (BatchMismatch, En) => "That batch was started by a different operation.",
+            (BatchMismatch, El) => "Αυτή η παρτίδα ξεκίνησε από διαφορετική λειτουργία.",
+            (BatchMismatch, De) => "Dieser Stapel wurde von einem anderen Vorgang gestartet.",
+
+            (ExpiryInPast, En) => "The expiry date must be in the future.",
+            (ExpiryInPast, El) => "Η ημερομηνία λήξης πρέπει να είναι στο μέλλον.",
+            (ExpiryInPast, De) => "Das Ablaufdatum muss in der Zukunft liegen.",
+
+            (ExpiryNeedsSingleFile, En) => "Expiring containers hold a single file. Lock the files separately or zip them first.",
+            (ExpiryNeedsSingleFile, El) => "Τα κοντέινερ με λήξη περιέχουν ένα μόνο αρχείο. Κλειδώστε τα αρχεία ξεχωριστά ή συμπιέστε τα πρώτα σε zip.",
+            (ExpiryNeedsSingleFile, De) => "Ablaufende Container enthalten eine einzelne Datei. Sperren Sie die Dateien einzeln oder packen Sie sie zuerst in ein ZIP.",
+
+            (SplitKeyThresholdInvalid, En) => "Choose at least 2 required shares and no more than the number of shares.",
+            (SplitKeyThresholdInvalid, El) => "Επιλέξτε τουλάχιστον 2 απαιτούμενα μερίδια και όχι περισσότερα από τον αριθμό των μεριδίων.",
+            (SplitKeyThresholdInvalid, De) => "Wählen Sie mindestens 2 erforderliche Anteile und nicht mehr als die Anzahl der Anteile.",
+
+            (SplitKeyNeedsSingleFile, En) => "Split-key locking works on single files. Lock the files separately or zip them first.",
+            (SplitKeyNeedsSingleFile, El) => "Το κλείδωμα με διαιρεμένο κλειδί λειτουργεί σε μεμονωμένα αρχεία. Κλειδώστε τα αρχεία ξεχωριστά ή συμπιέστε τα πρώτα σε zip.",
+            (SplitKeyNeedsSingleFile, De) => "Das Sperren mit geteiltem Schlüssel funktioniert nur mit einzelnen Dateien. Sperren Sie die Dateien einzeln oder packen Sie sie zuerst in ein ZIP.",
+
+            (SplitKeyWithIdentity, En) => "Split-key files cannot also be bound to the vault identity.",
+            (SplitKeyWithIdentity, El) => "Τα αρχεία με διαιρεμένο κλειδί δεν μπορούν να δεσμευτούν επιπλέον στην ταυτότητα του θησαυροφυλακίου.",
+            (SplitKeyWithIdentity, De) => "Dateien mit geteiltem Schlüssel können nicht zusätzlich an die Tresor-Identität gebunden werden.",
+
+            (SplitKeyExportUnsupported, En) => "Split-key files cannot be exported this way. Unlock the file with its key shares first.",
+            (SplitKeyExportUnsupported, El) => "Τα αρχεία με διαιρεμένο κλειδί δεν μπορούν να εξαχθούν με αυτόν τον τρόπο. Ξεκλειδώστε πρώτα το αρχείο με τα μερίδια κλειδιού του.",
+            (SplitKeyExportUnsupported, De) => "Dateien mit geteiltem Schlüssel können so nicht exportiert werden. Entsperren Sie die Datei zuerst mit ihren Schlüsselanteilen.",
+
+            (ParityNeedsSingleFile, En) => "Parity protects single-file containers. Lock the files separately or zip them first.",
+            (ParityNeedsSingleFile, El) => "Η ισοτιμία προστατεύει κοντέινερ ενός αρχείου. Κλειδώστε τα αρχεία ξεχωριστά ή συμπιέστε τα πρώτα σε zip.",
+            (ParityNeedsSingleFile, De) => "Parität schützt Container mit einer einzelnen Datei. Sperren Sie die Dateien einzeln oder packen Sie sie zuerst in ein ZIP.",
+
+            (PostQuantumNeedsSingleFile, En) => "Post-quantum wrapping protects single-file containers. Lock the files separately or zip them first.",
+            (PostQuantumNeedsSingleFile, El) => "Η μετακβαντική περιτύλιξη προστατεύει κοντέινερ ενός αρχείου. Κλειδώστε τα αρχεία ξεχωριστά ή συμπιέστε τα πρώτα σε zip.",
+            (PostQuantumNeedsSingleFile, De) => "Die Post-Quanten-Umhüllung schützt Container mit einer einzelnen Datei. Sperren Sie die Dateien einzeln oder packen Sie sie zuerst in ein ZIP.",
+
+            (ArchiveExportUnsupported, En) => "Multi-file archives cannot be exported this way. Extract the file first.",
+            (ArchiveExportUnsupported, El) => "Τα αρχειοθετημένα πολλαπλά αρχεία δεν μπορούν να εξαχθούν με αυτόν τον τρόπο. Εξαγάγετε πρώτα το αρχείο.",
+            (ArchiveExportUnsupported, De) => "Archive mit mehreren Dateien können so nicht exportiert werden. Entpacken Sie die Datei zuerst.",
+
+            (ExportTooLarge, En) => "File is too large for a self-decrypting export (limit {limit} MB).",
+            (ExportTooLarge, El) => "Το αρχείο είναι πολύ μεγάλο για αυτο-αποκρυπτογραφούμενη εξαγωγή (όριο {limit} MB).",
+            (ExportTooLarge, De) => "Die Datei ist zu groß für einen selbstentschlüsselnden Export (Grenze {limit} MB).",
+
+            (PassphraseTooShort, En) => "Passphrase must be at least {min} characters.",
+            (PassphraseTooShort, El) => "Η φράση πρόσβασης πρέπει να έχει τουλάχιστον {min} χαρακτήρες.",
+            (PassphraseTooShort, De) => "Die Passphrase muss mindestens {min} Zeichen lang sein.",
+
+            (HiddenNeedsPassword, En) => "A hidden file needs its own password.",
+            (HiddenNeedsPassword, El) => "Ένα κρυφό αρχείο χρειάζεται δικό του κωδικό πρόσβασης.",
+            (HiddenNeedsPassword, De) => "Eine versteckte Datei benötigt ein eigenes Passwort.",
+
+            (SearchTermTooShort, En) => "Enter at least one word of three or more characters.",
+            (SearchTermTooShort, El) => "Εισαγάγετε τουλάχιστον μία λέξη με τρεις ή περισσότερους χαρακτήρες.",
+            (SearchTermTooShort, De) => "Geben Sie mindestens ein Wort mit drei oder mehr Zeichen ein.",
+
+            (TimeLockAlreadyEncrypted, En) => "Cannot time-lock an already-encrypted .qre file.",
+            (TimeLockAlreadyEncrypted, El) => "Δεν είναι δυνατό να κλειδωθεί χρονικά ένα ήδη κρυπτογραφημένο αρχείο .qre.",
+            (TimeLockAlreadyEncrypted, De) => "Eine bereits verschlüsselte .qre-Datei kann nicht mit einer Zeitsperre versehen werden.",
+
+            (NoTimeLockPuzzle, En) => "This file has no time-lock puzzle.",
+            (NoTimeLockPuzzle, El) => "Αυτό το αρχείο δεν έχει γρίφο χρονικού κλειδώματος.",
+            (NoTimeLockPuzzle, De) => "Diese Datei hat kein Zeitschloss-Rätsel.",
+
+            (DeletedPathNotAbsolute, En) => "Give the full path the file was deleted from.",
+            (DeletedPathNotAbsolute, El) => "Δώστε την πλήρη διαδρομή από την οποία διαγράφηκε το αρχείο.",
+            (DeletedPathNotAbsolute, De) => "Geben Sie den vollständigen Pfad an, aus dem die Datei gelöscht wurde.",
+
+            (KeyfileExists, En) => "'{path}' already exists. Choose a new file name for the keyfile.",
+            (KeyfileExists, El) => "Το '{path}' υπάρχει ήδη. Επιλέξτε νέο όνομα αρχείου για το αρχείο-κλειδί.",
+            (KeyfileExists, De) => "'{path}' existiert bereits. Wählen Sie einen neuen Dateinamen für die Schlüsseldatei.",
+
+            (KeyfileSizeInvalid, En) => "Keyfile size must be between {min} bytes and {max_kb} KB.",
+            (KeyfileSizeInvalid, El) => "Το μέγεθος του αρχείου-κλειδιού πρέπει να είναι μεταξύ {min} bytes και {max_kb} KB.",
+            (KeyfileSizeInvalid, De) => "Die Größe der Schlüsseldatei muss zwischen {min} Bytes und {max_kb} KB liegen.",
+
+            (KeyfileTooLargeForQr, En) => "A QR backup holds keyfiles of up to {max} bytes. Choose a smaller size.",
+            (KeyfileTooLargeForQr, El) => "Ένα αντίγραφο ασφαλείας QR χωράει αρχεία-κλειδιά έως {max} bytes. Επιλέξτε μικρότερο μέγεθος.",
+            (KeyfileTooLargeForQr, De) => "Eine QR-Sicherung fasst Schlüsseldateien bis {max} Bytes. Wählen Sie eine kleinere Größe.",
+
+            (KeyfileBackupInvalid, En) => "This is not a QRE keyfile backup.",
+            (KeyfileBackupInvalid, El) => "Αυτό δεν είναι αντίγραφο ασφαλείας αρχείου-κλειδιού QRE.",
+            (KeyfileBackupInvalid, De) => "Dies ist keine QRE-Schlüsseldatei-Sicherung.",
+
+            (KeyfileBackupDamaged, En) => "The keyfile backup is damaged or incomplete.",
+            (KeyfileBackupDamaged, El) => "Το αντίγραφο ασφαλείας του αρχείου-κλειδιού είναι κατεστραμμένο ή ελλιπές.",
+            (KeyfileBackupDamaged, De) => "Die Schlüsseldatei-Sicherung ist beschädigt oder unvollständig.",
+
+            (NoPicturesFolder, En) => "No Pictures folder found.",
+            (NoPicturesFolder, El) => "Δεν βρέθηκε φάκελος Εικόνες.",
+            (NoPicturesFolder, De) => "Kein Bilder-Ordner gefunden.",
+
+            (PhotoScanMissing, En) => "Scan the photo library first.",
+            (PhotoScanMissing, El) => "Σαρώστε πρώτα τη βιβλιοθήκη φωτογραφιών.",
+            (PhotoScanMissing, De) => "Durchsuchen Sie zuerst die Fotobibliothek.",
+
+            (NoPhotosSelected, En) => "No photos in the selected locations.",
+            (NoPhotosSelected, El) => "Δεν υπάρχουν φωτογραφίες στις επιλεγμένες τοποθεσίες.",
+            (NoPhotosSelected, De) => "Keine Fotos an den ausgewählten Orten.",
+
+            (NoDocumentsFolder, En) => "No Documents or Desktop folder found.",
+            (NoDocumentsFolder, El) => "Δεν βρέθηκε φάκελος Έγγραφα ή Επιφάνεια εργασίας.",
+            (NoDocumentsFolder, De) => "Kein Dokumente- oder Desktop-Ordner gefunden.",
+
+            (DocumentScanMissing, En) => "Scan for documents first.",
+            (DocumentScanMissing, El) => "Σαρώστε πρώτα για έγγραφα.",
+            (DocumentScanMissing, De) => "Suchen Sie zuerst nach Dokumenten.",
+
+            (NoDocumentsSelected, En) => "No documents selected.",
+            (NoDocumentsSelected, El) => "Δεν επιλέχθηκαν έγγραφα.",
+            (NoDocumentsSelected, De) => "Keine Dokumente ausgewählt.",
+
+            (NoSitesSelected, En) => "No sites selected.",
+            (NoSitesSelected, El) => "Δεν επιλέχθηκαν ιστότοποι.",
+            (NoSitesSelected, De) => "Keine Websites ausgewählt.",
+
+            (NoCookieStore, En) => "No matching browser cookie store found.",
+            (NoCookieStore, El) => "Δεν βρέθηκε αντίστοιχη αποθήκη cookies προγράμματος περιήγησης.",
+            (NoCookieStore, De) => "Kein passender Browser-Cookie-Speicher gefunden.",
+
+            (HashInvalid, En) => "Invalid hash format. A SHA-1 hash is expected.",
+            (HashInvalid, El) => "Μη έγκυρη μορφή hash. Αναμένεται hash SHA-1.",
+            (HashInvalid, De) => "Ungültiges Hash-Format. Erwartet wird ein SHA-1-Hash.",
+
+            (OfflineIpNotChecked, En) => "Offline mode is on; the public IP was not checked.",
+            (OfflineIpNotChecked, El) => "Η λειτουργία εκτός σύνδεσης είναι ενεργή· η δημόσια IP δεν ελέγχθηκε.",
+            (OfflineIpNotChecked, De) => "Der Offline-Modus ist aktiv; die öffentliche IP wurde nicht geprüft.",
+        }
+    }
+}
+
+// ==========================================
+// --- ERROR VALUE ---
+// ==========================================
+
+/// A localizable command error. Converts into the `String` error of `CommandResult`,
+/// so `?` works unchanged in every command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub params: Vec<(&'static str, String)>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self {
+            code,
+            params: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    pub fn render(&self, locale: Locale) -> String {
+        self.params.iter().fold(
+            self.code.template(locale).to_string(),
+            |msg, (name, value)| msg.replace(&format!("{{{}}}", name), value),
+        )
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(current_locale()))
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<AppError> for String {
+    fn from(e: AppError) -> String {
+        e.to_string()
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_CODES: [ErrorCode; 99] = [
+        ErrorCode::ReadOnlySession,
+        ErrorCode::VaultLocked,
+        ErrorCode::SessionCorrupted,
+        ErrorCode::IncorrectPassword,
+        ErrorCode::CurrentPasswordIncorrect,
+        ErrorCode::OwnerOnly,
+        ErrorCode::LoginLockedOut,
+        ErrorCode::RecoveryLockedOut,
+        ErrorCode::RateLimited,
+        ErrorCode::JobBusy,
+        ErrorCode::PathMissing,
+        ErrorCode::PathInvalid,
+        ErrorCode::PathProtected,
+        ErrorCode::PathNotFound,
+        ErrorCode::PathIsSymlink,
+        ErrorCode::PathIsFolder,
+        ErrorCode::PathNotFolder,
+        ErrorCode::PathTooLarge,
+        ErrorCode::FolderNotFound,
+        ErrorCode::PanelLocked,
+        ErrorCode::IncorrectPin,
+        ErrorCode::PinLockedOut,
+        ErrorCode::FormatNeedsUpdate,
+        ErrorCode::FormatTooNew,
+        ErrorCode::FormatUnknown,
+        ErrorCode::PathTraversal,
+        ErrorCode::PathNotAbsolute,
+        ErrorCode::PathNoFileName,
+        ErrorCode::NameInvalid,
+        ErrorCode::ScanProtected,
+        ErrorCode::UnsupportedOnAndroid,
+        ErrorCode::KeychainNotFound,
+        ErrorCode::KeychainPathInvalid,
+        ErrorCode::KeychainCorrupted,
+        ErrorCode::DataCorrupted,
+        ErrorCode::DuressLocalOnly,
+        ErrorCode::ProfileNotFound,
+        ErrorCode::EntryNotFound,
+        ErrorCode::TooManyAttachments,
+        ErrorCode::AttachmentNotFound,
+        ErrorCode::AttachmentInUse,
+        ErrorCode::ImageNotFound,
+        ErrorCode::ImageInUse,
+        ErrorCode::ClipboardEntryNotFound,
+        ErrorCode::NoBookmarks,
+        ErrorCode::TotpKeyMissing,
+        ErrorCode::TotpKeyNotProvided,
+        ErrorCode::NotOtpauthLink,
+        ErrorCode::BreachCheckRunning,
+        ErrorCode::NoPairedDevice,
+        ErrorCode::NoPendingUnlock,
+        ErrorCode::DeviceNotPaired,
+        ErrorCode::AutoUnlockUnavailable,
+        ErrorCode::AutoUnlockDisabled,
+        ErrorCode::ContactNotFound,
+        ErrorCode::OwnIdentity,
+        ErrorCode::IdentityMissing,
+        ErrorCode::IdentityKeyMissing,
+        ErrorCode::WrongRecipientVault,
+        ErrorCode::PanelNotProtected,
+        ErrorCode::PortableVaultLocked,
+        ErrorCode::PortableVaultNotFound,
+        ErrorCode::DriveNotFound,
+        ErrorCode::DriveAlreadyVault,
+        ErrorCode::GhostFileProtection,
+        ErrorCode::NoFilesSelected,
+        ErrorCode::NotAContainer,
+        ErrorCode::BatchMismatch,
+        ErrorCode::ExpiryInPast,
+        ErrorCode::ExpiryNeedsSingleFile,
+        ErrorCode::SplitKeyThresholdInvalid,
+        ErrorCode::SplitKeyNeedsSingleFile,
+        ErrorCode::SplitKeyWithIdentity,
+        ErrorCode::SplitKeyExportUnsupported,
+        ErrorCode::ParityNeedsSingleFile,
+        ErrorCode::PostQuantumNeedsSingleFile,
+        ErrorCode::ArchiveExportUnsupported,
+        ErrorCode::ExportTooLarge,
+        ErrorCode::PassphraseTooShort,
+        ErrorCode::HiddenNeedsPassword,
+        ErrorCode::SearchTermTooShort,
+        ErrorCode::TimeLockAlreadyEncrypted,
+        ErrorCode::NoTimeLockPuzzle,
+        ErrorCode::DeletedPathNotAbsolute,
+        ErrorCode::KeyfileExists,
+        ErrorCode::KeyfileSizeInvalid,
+        ErrorCode::KeyfileTooLargeForQr,
+        ErrorCode::KeyfileBackupInvalid,
+        ErrorCode::KeyfileBackupDamaged,
+        ErrorCode::NoPicturesFolder,
+        ErrorCode::PhotoScanMissing,
+        ErrorCode::NoPhotosSelected,
+        ErrorCode::NoDocumentsFolder,
+        ErrorCode::DocumentScanMissing,
+        ErrorCode::NoDocumentsSelected,
+        ErrorCode::NoSitesSelected,
+        ErrorCode::NoCookieStore,
+        ErrorCode::HashInvalid,
+        ErrorCode::OfflineIpNotChecked,
+    ];
+
+    fn placeholders(s: &str) -> Vec<String> {
+        let mut names: Vec<String> = s
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(n, _)| n.to_string()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_every_translation_uses_the_same_parameters() {
+        for code in ALL_CODES {
+            let en = placeholders(code.template(Locale::En));
+            for locale in [Locale::El, Locale::De] {
+                assert_eq!(
+                    placeholders(code.template(locale)),
+                    en,
+                    "{} ({}) placeholders differ from English",
+                    code.code(),
+                    locale.tag()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_parameters() {
+        let err = AppError::new(ErrorCode::LoginLockedOut).with("seconds", 30);
+        assert_eq!(
+            err.render(Locale::En),
+            "Too many failed attempts. Please wait 30 more second(s)."
+        );
+        assert!(err.render(Locale::De).contains("noch 30 Sekunde(n)"));
+        assert!(err.render(Locale::El).contains("30"));
+    }
+
+    #[test]
+    fn test_locale_parse_accepts_region_tags() {
+        assert_eq!(Locale::parse("de-AT"), Some(Locale::De));
+        assert_eq!(Locale::parse("el_GR"), Some(Locale::El));
+        assert_eq!(Locale::parse("EN"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr"), None);
+        assert_eq!(Locale::parse(""), None);
+    }
+}
+
+// --- END OF FILE i18n.rs ---
@@ -0,0 +1,1079 @@
+// --- START OF FILE kdbx.rs ---
+
+// ==========================================
+// --- KEEPASS DATABASES (.kdbx) ---
+// ==========================================
+// Opens KDBX 3.1 and 4.x databases for the password import (vault_import.rs). Only what
+// an import needs is decoded: the XML document and the inner stream that hides the
+// protected values (passwords) inside it. Attachments are skipped.
+// `seal` writes KDBX 4 (AES-256, Argon2id, gzip, ChaCha20 inner stream) for the password
+// export (vault_export.rs).
+//
+// LAYOUT (integers are little-endian):
+//   u32 0x9AA2D903 | u32 0xB54BFB67 | u16 minor | u16 major
+//   header fields: u8 id | u16 (v3) or u32 (v4) length | data ... up to id 0
+//   v3: AES-CBC( stream start bytes (32) | blocks: u32 index | SHA-256 | u32 length | data )
+//   v4: SHA-256(header) | HMAC-SHA-256(header) | blocks: HMAC | u32 length | data
+//       The blocks join into the ciphertext of inner header | XML.
+//   Both compress the XML (v4: inner header and XML) with gzip when the header says so.
+//
+// KEYS: composite = SHA-256(SHA-256(password) | key file key), either part optional.
+// The KDF (AES-KDF or Argon2) turns it into `transformed`; the cipher key is
+// SHA-256(master seed | transformed), the v4 HMAC key SHA-512(master seed | transformed | 1).
+//
+// A database is untrusted input: KDF costs are checked before any work (a crafted file
+// could ask for hours of AES rounds or gigabytes of Argon2 memory) and the decompressed
+// document is capped.
+
+use aes::cipher::{
+    block_padding::Pkcs7, BlockDecryptMut, BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit,
+    StreamCipher,
+};
+use aes::Aes256;
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20::ChaCha20;
+use data_encoding::{BASE64, HEXLOWER_PERMISSIVE};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, TryRngCore};
+use salsa20::Salsa20;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use zeroize::Zeroizing;
+
+const SIGNATURE_1: u32 = 0x9AA2_D903;
+const SIGNATURE_2: u32 = 0xB54B_FB67;
+/// KeePass 1.x (.kdb) uses the same first signature.
+const SIGNATURE_2_KDB: u32 = 0xB54B_FB65;
+
+const CIPHER_AES256: [u8; 16] = [
+    0x31, 0xc1, 0xf2, 0xe6, 0xbf, 0x71, 0x43, 0x50, 0xbe, 0x58, 0x05, 0x21, 0x6a, 0xfc, 0x5a, 0xff,
+];
+const CIPHER_CHACHA20: [u8; 16] = [
+    0xd6, 0x03, 0x8a, 0x2b, 0x8b, 0x6f, 0x4c, 0xb5, 0xa5, 0x24, 0x33, 0x9a, 0x31, 0xdb, 0xb5, 0x9a,
+];
+/// AES-KDF has one UUID in KDBX 3.1 and another in KDBX 4.
+const KDF_AES_KDBX3: [u8; 16] = [
+    0xc9, 0xd9, 0xf3, 0x9a, 0x62, 0x8a, 0x44, 0x60, 0xbf, 0x74, 0x0d, 0x08, 0xc1, 0x8a, 0x4f, 0xea,
+];
+const KDF_AES_KDBX4: [u8; 16] = [
+    0x7c, 0x02, 0xbb, 0x82, 0x79, 0xa7, 0x4a, 0xc0, 0x92, 0x7d, 0x11, 0x4a, 0x00, 0x64, 0x82, 0x38,
+];
+const KDF_ARGON2D: [u8; 16] = [
+    0xef, 0x63, 0x6d, 0xdf, 0x8c, 0x29, 0x44, 0x4b, 0x91, 0xf7, 0xa9, 0xa4, 0x03, 0xe3, 0x0a, 0x0c,
+];
+const KDF_ARGON2ID: [u8; 16] = [
+    0x9e, 0x29, 0x8b, 0x19, 0x56, 0xdb, 0x47, 0x73, 0xb2, 0x3d, 0xfc, 0x3e, 0xc6, 0xf0, 0xa1, 0xe6,
+];
+
+const INNER_STREAM_NONE: u32 = 0;
+const INNER_STREAM_SALSA20: u32 = 2;
+const INNER_STREAM_CHACHA20: u32 = 3;
+const SALSA20_IV: [u8; 8] = [0xE8, 0x30, 0x09, 0x4B, 0x97, 0x20, 0x5D, 0x2A];
+
+/// KeePass' "1 second" benchmark lands in the low millions on current hardware.
+const MAX_AES_ROUNDS: u64 = 100_000_000;
+const MAX_ARGON2_MEMORY_KIB: u64 = 1_048_576;
+const MAX_ARGON2_ITERATIONS: u64 = 256;
+const MAX_ARGON2_LANES: u64 = 64;
+/// The XML of a database with tens of thousands of entries stays well below this.
+const MAX_XML_BYTES: u64 = 128 * 1024 * 1024;
+/// Argon2id for written databases: (memory KiB, iterations, lanes), around KeePassXC's
+/// defaults so opening the export takes about a second there too.
+#[cfg(not(test))]
+const SEAL_ARGON2: (u32, u32, u32) = (64 * 1024, 10, 2);
+#[cfg(test)]
+const SEAL_ARGON2: (u32, u32, u32) = (64, 1, 1);
+/// KeePass splits the v4 payload into HMAC blocks of this size.
+const SEAL_BLOCK_SIZE: usize = 1024 * 1024;
+
+const WRONG_KEY: &str = "Wrong password or key file for this KeePass database";
+const DAMAGED: &str = "The KeePass database is damaged";
+
+pub fn is_kdbx(bytes: &[u8]) -> bool {
+    bytes.len() >= 8 && read_u32(&bytes[..4]) == SIGNATURE_1
+}
+
+/// A decrypted database: the XML document and the stream for its protected values.
+pub struct Database {
+    pub xml: Zeroizing<String>,
+    pub protected: InnerStream,
+}
+
+/// The keystream protected `<Value Protected="True">` fields are XORed with. It runs
+/// through the values in document order, so every one of them must be passed to
+/// `unprotect`, including those of entries that are not imported.
+pub enum InnerStream {
+    None,
+    Salsa20(Box<Salsa20>),
+    ChaCha20(Box<ChaCha20>),
+}
+
+impl InnerStream {
+    fn new(id: u32, key: &[u8]) -> Result<Self> {
+        match id {
+            INNER_STREAM_NONE => Ok(InnerStream::None),
+            INNER_STREAM_SALSA20 => {
+                let key = Sha256::digest(key);
+                let cipher = Salsa20::new_from_slices(&key, &SALSA20_IV)
+                    .map_err(|_| anyhow!("Invalid inner stream key"))?;
+                Ok(InnerStream::Salsa20(Box::new(cipher)))
+            }
+            INNER_STREAM_CHACHA20 => {
+                let hash = Zeroizing::new(Sha512::digest(key).to_vec());
+                let cipher = ChaCha20::new_from_slices(&hash[..32], &hash[32..44])
+                    .map_err(|_| anyhow!("Invalid inner stream key"))?;
+                Ok(InnerStream::ChaCha20(Box::new(cipher)))
+            }
+            other => Err(anyhow!("Unsupported inner stream cipher: {}", other)),
+        }
+    }
+
+    /// Encodes `value` as the next protected value: the text of `<Value Protected="True">`.
+    pub fn protect(&mut self, value: &str) -> String {
+        let mut bytes = Zeroizing::new(value.as_bytes().to_vec());
+        match self {
+            InnerStream::None => {}
+            InnerStream::Salsa20(cipher) => cipher.apply_keystream(&mut bytes),
+            InnerStream::ChaCha20(cipher) => cipher.apply_keystream(&mut bytes),
+        }
+        BASE64.encode(&bytes)
+    }
+
+    /// Decodes the text of the next protected value.
+    pub fn unprotect(&mut self, value: &str) -> Result<Zeroizing<String>> {
+        let mut bytes = Zeroizing::new(
+            BASE64
+                .decode(value.trim().as_bytes())
+                .map_err(|_| anyhow!("{}: invalid protected value", DAMAGED))?,
+        );
+        match self {
+            InnerStream::None => {}
+            InnerStream::Salsa20(cipher) => cipher.apply_keystream(&mut bytes),
+            InnerStream::ChaCha20(cipher) => cipher.apply_keystream(&mut bytes),
+        }
+        let text = String::from_utf8(bytes.to_vec())
+            .map_err(|_| anyhow!("{}: invalid protected value", DAMAGED))?;
+        Ok(Zeroizing::new(text))
+    }
+}
+
+/// Decrypts a database. `password` and `keyfile` are the parts of its composite key;
+/// `Some("")` is an empty password, which KeePass treats differently from none.
+pub fn open(bytes: &[u8], password: Option<&str>, keyfile: Option<&[u8]>) -> Result<Database> {
+    let mut cursor = Cursor::new(bytes);
+    if cursor.u32()? != SIGNATURE_1 {
+        return Err(anyhow!("Not a KeePass database"));
+    }
+    match cursor.u32()? {
+        SIGNATURE_2 => {}
+        SIGNATURE_2_KDB => {
+            return Err(anyhow!(
+                "KeePass 1.x databases (.kdb) are not supported; save it as .kdbx first"
+            ))
+        }
+        _ => return Err(anyhow!("Not a KeePass database")),
+    }
+    let _minor = cursor.u16()?;
+    let major = cursor.u16()?;
+    let header = OuterHeader::read(&mut cursor, major)?;
+    let header_bytes = &bytes[..cursor.pos];
+
+    let composite = composite_key(password, keyfile)?;
+    let transformed = header.kdf.transform(&composite)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&header.master_seed);
+    hasher.update(*transformed);
+    let cipher_key = Zeroizing::new(hasher.finalize().to_vec());
+
+    match major {
+        3 => open_v3(&header, &cipher_key, cursor.rest()),
+        _ => {
+            let mut hasher = Sha512::new();
+            hasher.update(&header.master_seed);
+            hasher.update(*transformed);
+            hasher.update([1u8]);
+            let hmac_key = Zeroizing::new(hasher.finalize().to_vec());
+            open_v4(&header, header_bytes, &cipher_key, &hmac_key, cursor.rest())
+        }
+    }
+}
+
+fn open_v3(header: &OuterHeader, cipher_key: &[u8], payload: &[u8]) -> Result<Database> {
+    let plain = decrypt(&header.cipher, cipher_key, &header.iv, payload)?;
+    let start_bytes = header
+        .stream_start_bytes
+        .as_deref()
+        .context("Missing stream start bytes")?;
+    if plain.len() < start_bytes.len() || &plain[..start_bytes.len()] != start_bytes {
+        return Err(anyhow!(WRONG_KEY));
+    }
+
+    // Hashed blocks: u32 index | SHA-256 | u32 length | data, ending with an empty block.
+    let mut cursor = Cursor::new(&plain[start_bytes.len()..]);
+    let mut content = Zeroizing::new(Vec::new());
+    loop {
+        let _index = cursor.u32()?;
+        let hash = cursor.take(32)?;
+        let len = cursor.u32()? as usize;
+        if len == 0 {
+            break;
+        }
+        let data = cursor.take(len)?;
+        if Sha256::digest(data).as_slice() != hash {
+            return Err(anyhow!(DAMAGED));
+        }
+        content.extend_from_slice(data);
+    }
+    let content = if header.compressed {
+        decompress(&content)?
+    } else {
+        content
+    };
+
+    let protected = InnerStream::new(
+        header.inner_stream_id.unwrap_or(INNER_STREAM_NONE),
+        header
+            .protected_stream_key
+            .as_ref()
+            .map_or(&[][..], |k| k.as_slice()),
+    )?;
+    Ok(Database {
+        xml: into_xml(&content)?,
+        protected,
+    })
+}
+
+fn open_v4(
+    header: &OuterHeader,
+    header_bytes: &[u8],
+    cipher_key: &[u8],
+    hmac_key: &[u8],
+    rest: &[u8],
+) -> Result<Database> {
+    let mut cursor = Cursor::new(rest);
+    if cursor.take(32)? != Sha256::digest(header_bytes).as_slice() {
+        return Err(anyhow!(
+            "{}: the header does not match its checksum",
+            DAMAGED
+        ));
+    }
+    // With an intact header, a failing header HMAC can only mean a different key.
+    let header_mac = cursor.take(32)?;
+    block_mac(hmac_key, u64::MAX)
+        .chain_update(header_bytes)
+        .verify_slice(header_mac)
+        .map_err(|_| anyhow!(WRONG_KEY))?;
+
+    // HMAC blocks: HMAC-SHA-256 | u32 length | data, ending with an empty block.
+    let mut ciphertext = Vec::new();
+    for index in 0u64.. {
+        let mac = cursor.take(32)?;
+        let len_bytes = cursor.take(4)?;
+        let data = cursor.take(read_u32(len_bytes) as usize)?;
+        block_mac(hmac_key, index)
+            .chain_update(index.to_le_bytes())
+            .chain_update(len_bytes)
+            .chain_update(data)
+            .verify_slice(mac)
+            .map_err(|_| anyhow!("{}: block {} fails its check", DAMAGED, index))?;
+        if data.is_empty() {
+            break;
+        }
+        ciphertext.extend_from_slice(data);
+    }
+
+    let plain = decrypt(&header.cipher, cipher_key, &header.iv, &ciphertext)?;
+    let plain = if header.compressed {
+        decompress(&plain)?
+    } else {
+        plain
+    };
+
+    // Inner header: u8 id | u32 length | data ... up to id 0. Id 3 holds attachments.
+    let mut cursor = Cursor::new(&plain);
+    let mut stream_id = INNER_STREAM_NONE;
+    let mut stream_key = Zeroizing::new(Vec::new());
+    loop {
+        let id = cursor.u8()?;
+        let len = cursor.u32()? as usize;
+        let data = cursor.take(len)?;
+        match id {
+            0 => break,
+            1 => stream_id = read_u32(data),
+            2 => stream_key = Zeroizing::new(data.to_vec()),
+            _ => {}
+        }
+    }
+    Ok(Database {
+        xml: into_xml(cursor.rest())?,
+        protected: InnerStream::new(stream_id, &stream_key)?,
+    })
+}
+
+/// The HMAC for block `index` (`u64::MAX` is the header).
+fn block_mac(hmac_key: &[u8], index: u64) -> Hmac<Sha256> {
+    let mut hasher = Sha512::new();
+    hasher.update(index.to_le_bytes());
+    hasher.update(hmac_key);
+    let key = Zeroizing::new(hasher.finalize().to_vec());
+    <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("HMAC accepts any key length")
+}
+
+fn decrypt(cipher: &[u8], key: &[u8], iv: &[u8], data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if cipher == CIPHER_AES256 {
+        let decryptor = cbc::Decryptor::<Aes256>::new_from_slices(key, iv)
+            .map_err(|_| anyhow!("{}: invalid IV", DAMAGED))?;
+        // KDBX 3.1 has nothing but the padding to notice a wrong key by.
+        let plain = decryptor
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .map_err(|_| anyhow!(WRONG_KEY))?;
+        Ok(Zeroizing::new(plain))
+    } else if cipher == CIPHER_CHACHA20 {
+        let mut stream =
+            ChaCha20::new_from_slices(key, iv).map_err(|_| anyhow!("{}: invalid IV", DAMAGED))?;
+        let mut plain = Zeroizing::new(data.to_vec());
+        stream.apply_keystream(&mut plain);
+        Ok(plain)
+    } else {
+        Err(anyhow!(
+            "This KeePass database uses a cipher QRE does not support (only AES-256 and ChaCha20)"
+        ))
+    }
+}
+
+fn decompress(data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut out = Zeroizing::new(Vec::new());
+    GzDecoder::new(data)
+        .take(MAX_XML_BYTES + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| anyhow!("{}: decompression failed", DAMAGED))?;
+    if out.len() as u64 > MAX_XML_BYTES {
+        return Err(anyhow!("The KeePass database is too large to import"));
+    }
+    Ok(out)
+}
+
+fn into_xml(bytes: &[u8]) -> Result<Zeroizing<String>> {
+    let xml = std::str::from_utf8(bytes).map_err(|_| anyhow!("{}: invalid XML", DAMAGED))?;
+    Ok(Zeroizing::new(xml.to_string()))
+}
+
+// ==========================================
+// --- HEADER ---
+// ==========================================
+
+struct OuterHeader {
+    cipher: Vec<u8>,
+    compressed: bool,
+    master_seed: Vec<u8>,
+    iv: Vec<u8>,
+    kdf: Kdf,
+    // KDBX 3.1 only; KDBX 4 moved them to the inner header.
+    protected_stream_key: Option<Zeroizing<Vec<u8>>>,
+    stream_start_bytes: Option<Vec<u8>>,
+    inner_stream_id: Option<u32>,
+}
+
+impl OuterHeader {
+    fn read(cursor: &mut Cursor, major: u16) -> Result<Self> {
+        if major != 3 && major != 4 {
+            return Err(anyhow!(
+                "KeePass database version {} is not supported",
+                major
+            ));
+        }
+        let mut cipher = None;
+        let mut compressed = false;
+        let mut master_seed = None;
+        let mut iv = None;
+        let mut kdf = None;
+        let mut transform_seed = None;
+        let mut transform_rounds = None;
+        let mut protected_stream_key = None;
+        let mut stream_start_bytes = None;
+        let mut inner_stream_id = None;
+        loop {
+            let id = cursor.u8()?;
+            let len = if major == 3 {
+                cursor.u16()? as usize
+            } else {
+                cursor.u32()? as usize
+            };
+            let data = cursor.take(len)?;
+            match id {
+                0 => break,
+                2 => cipher = Some(data.to_vec()),
+                3 => compressed = read_u32(data) == 1,
+                4 => master_seed = Some(data.to_vec()),
+                5 => transform_seed = Some(data.to_vec()),
+                6 => transform_rounds = Some(read_u64(data)),
+                7 => iv = Some(data.to_vec()),
+                8 => protected_stream_key = Some(Zeroizing::new(data.to_vec())),
+                9 => stream_start_bytes = Some(data.to_vec()),
+                10 => inner_stream_id = Some(read_u32(data)),
+                11 => kdf = Some(Kdf::from_parameters(data)?),
+                _ => {}
+            }
+        }
+        let kdf = match (kdf, transform_seed, transform_rounds) {
+            (Some(kdf), _, _) => kdf,
+            (None, Some(seed), Some(rounds)) => Kdf::aes(seed, rounds)?,
+            _ => return Err(anyhow!("{}: no key derivation settings", DAMAGED)),
+        };
+        let master_seed = master_seed.context("Missing master seed")?;
+        if master_seed.len() != 32 {
+            return Err(anyhow!("{}: invalid master seed", DAMAGED));
+        }
+        Ok(OuterHeader {
+            cipher: cipher.context("Missing cipher")?,
+            compressed,
+            master_seed,
+            iv: iv.context("Missing encryption IV")?,
+            kdf,
+            protected_stream_key,
+            stream_start_bytes,
+            inner_stream_id,
+        })
+    }
+}
+
+enum Kdf {
+    Aes {
+        seed: [u8; 32],
+        rounds: u64,
+    },
+    Argon2 {
+        algorithm: Algorithm,
+        version: Version,
+        salt: Vec<u8>,
+        memory_kib: u32,
+        iterations: u32,
+        lanes: u32,
+    },
+}
+
+impl Kdf {
+    fn aes(seed: Vec<u8>, rounds: u64) -> Result<Self> {
+        if rounds > MAX_AES_ROUNDS {
+            return Err(anyhow!(
+                "The database asks for {} AES-KDF rounds; QRE stops at {}",
+                rounds,
+                MAX_AES_ROUNDS
+            ));
+        }
+        let seed = seed
+            .try_into()
+            .map_err(|_| anyhow!("{}: invalid AES-KDF seed", DAMAGED))?;
+        Ok(Kdf::Aes { seed, rounds })
+    }
+
+    /// KDBX 4 KDF parameters: a KeePass VariantDictionary.
+    fn from_parameters(data: &[u8]) -> Result<Self> {
+        let params = read_variant_dictionary(data)?;
+        let bytes = |key: &str| params.get(key).map(Vec::as_slice);
+        let number = |key: &str| match bytes(key) {
+            Some(b) if b.len() == 4 => Ok(read_u32(b) as u64),
+            Some(b) if b.len() == 8 => Ok(read_u64(b)),
+            _ => Err(anyhow!("{}: missing KDF parameter {}", DAMAGED, key)),
+        };
+
+        let uuid = bytes("$UUID").unwrap_or_default();
+        if uuid == KDF_AES_KDBX3 || uuid == KDF_AES_KDBX4 {
+            let seed = bytes("S").context("Missing AES-KDF seed")?.to_vec();
+            return Kdf::aes(seed, number("R")?);
+        }
+        let algorithm = if uuid == KDF_ARGON2D {
+            Algorithm::Argon2d
+        } else if uuid == KDF_ARGON2ID {
+            Algorithm::Argon2id
+        } else {
+            return Err(anyhow!(
+                "This KeePass database uses an unknown key derivation"
+            ));
+        };
+        let version = match number("V")? {
+            0x10 => Version::V0x10,
+            0x13 => Version::V0x13,
+            other => return Err(anyhow!("Unsupported Argon2 version: {:#x}", other)),
+        };
+        let memory_kib = number("M")? / 1024;
+        let iterations = number("I")?;
+        let lanes = number("P")?;
+        if memory_kib > MAX_ARGON2_MEMORY_KIB
+            || iterations > MAX_ARGON2_ITERATIONS
+            || lanes > MAX_ARGON2_LANES
+        {
+            return Err(anyhow!(
+                "The database's Argon2 settings ({} MB, {} iterations, {} lanes) exceed what QRE will run",
+                memory_kib / 1024,
+                iterations,
+                lanes
+            ));
+        }
+        Ok(Kdf::Argon2 {
+            algorithm,
+            version,
+            salt: bytes("S").context("Missing Argon2 salt")?.to_vec(),
+            memory_kib: memory_kib as u32,
+            iterations: iterations as u32,
+            lanes: lanes as u32,
+        })
+    }
+
+    /// The VariantDictionary `from_parameters` reads.
+    fn parameters(&self) -> Vec<u8> {
+        let mut out = 0x0100u16.to_le_bytes().to_vec();
+        let mut item = |kind: u8, key: &str, value: &[u8]| {
+            out.push(kind);
+            out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            out.extend_from_slice(key.as_bytes());
+            out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            out.extend_from_slice(value);
+        };
+        // Types: 0x04 u32, 0x05 u64, 0x42 bytes.
+        match self {
+            Kdf::Aes { seed, rounds } => {
+                item(0x42, "$UUID", &KDF_AES_KDBX4);
+                item(0x05, "R", &rounds.to_le_bytes());
+                item(0x42, "S", seed);
+            }
+            Kdf::Argon2 {
+                algorithm,
+                version,
+                salt,
+                memory_kib,
+                iterations,
+                lanes,
+            } => {
+                let uuid = match algorithm {
+                    Algorithm::Argon2d => KDF_ARGON2D,
+                    _ => KDF_ARGON2ID,
+                };
+                item(0x42, "$UUID", &uuid);
+                item(0x04, "V", &u32::from(*version).to_le_bytes());
+                item(0x42, "S", salt);
+                item(0x05, "M", &(*memory_kib as u64 * 1024).to_le_bytes());
+                item(0x05, "I", &(*iterations as u64).to_le_bytes());
+                item(0x04, "P", &lanes.to_le_bytes());
+            }
+        }
+        out.push(0);
+        out
+    }
+
+    fn transform(&self, composite: &[u8; 32]) -> Result<Zeroizing<[u8; 32]>> {
+        let mut out = Zeroizing::new([0u8; 32]);
+        match self {
+            Kdf::Aes { seed, rounds } => {
+                let cipher = Aes256::new(seed.into());
+                let mut blocks = [
+                    aes::Block::clone_from_slice(&composite[..16]),
+                    aes::Block::clone_from_slice(&composite[16..]),
+                ];
+                for _ in 0..*rounds {
+                    cipher.encrypt_blocks(&mut blocks);
+                }
+                let mut hasher = Sha256::new();
+                hasher.update(blocks[0]);
+                hasher.update(blocks[1]);
+                out.copy_from_slice(&hasher.finalize());
+                blocks[0].fill(0);
+                blocks[1].fill(0);
+            }
+            Kdf::Argon2 {
+                algorithm,
+                version,
+                salt,
+                memory_kib,
+                iterations,
+                lanes,
+            } => {
+                let params = Params::new(*memory_kib, *iterations, *lanes, Some(32))
+                    .map_err(|e| anyhow!("KDF param error: {}", e))?;
+                Argon2::new(*algorithm, *version, params)
+                    .hash_password_into(composite, salt, &mut *out)
+                    .map_err(|_| anyhow!("Hashing failed"))?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Values by key; numbers stay little-endian bytes and are read by length.
+fn read_variant_dictionary(data: &[u8]) -> Result<HashMap<String, Vec<u8>>> {
+    let mut cursor = Cursor::new(data);
+    if cursor.u16()? >> 8 != 1 {
+        return Err(anyhow!("Unsupported KDF parameter format"));
+    }
+    let mut entries = HashMap::new();
+    loop {
+        let kind = cursor.u8()?;
+        if kind == 0 {
+            return Ok(entries);
+        }
+        let key_len = cursor.u32()? as usize;
+        let key = String::from_utf8_lossy(cursor.take(key_len)?).into_owned();
+        let value_len = cursor.u32()? as usize;
+        entries.insert(key, cursor.take(value_len)?.to_vec());
+    }
+}
+
+// ==========================================
+// --- COMPOSITE KEY ---
+// ==========================================
+
+fn composite_key(password: Option<&str>, keyfile: Option<&[u8]>) -> Result<Zeroizing<[u8; 32]>> {
+    if password.is_none() && keyfile.is_none() {
+        return Err(anyhow!(
+            "A password or key file is needed to open the database"
+        ));
+    }
+    let mut hasher = Sha256::new();
+    if let Some(password) = password {
+        hasher.update(Sha256::digest(password.as_bytes()));
+    }
+    if let Some(keyfile) = keyfile {
+        hasher.update(*keyfile_key(keyfile)?);
+    }
+    Ok(Zeroizing::new(hasher.finalize().into()))
+}
+
+/// The 32-byte key a KeePass key file stands for: XML (v1 Base64, v2 hex with a
+/// checksum), exactly 32 raw bytes, 64 hex digits, or else the SHA-256 of any file.
+fn keyfile_key(data: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    if let Some(key) = xml_keyfile_key(data)? {
+        return Ok(key);
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    if data.len() == 32 {
+        key.copy_from_slice(data);
+    } else if let Some(decoded) = (data.len() == 64)
+        .then(|| HEXLOWER_PERMISSIVE.decode(data).ok())
+        .flatten()
+    {
+        key.copy_from_slice(&decoded);
+    } else {
+        key.copy_from_slice(&Sha256::digest(data));
+    }
+    Ok(key)
+}
+
+fn xml_keyfile_key(data: &[u8]) -> Result<Option<Zeroizing<[u8; 32]>>> {
+    use quick_xml::events::Event;
+
+    let Ok(text) = std::str::from_utf8(data) else {
+        return Ok(None);
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if !text.starts_with('<') {
+        return Ok(None);
+    }
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut version = String::new();
+    let mut data_hash = None;
+    let mut key_data = Zeroizing::new(String::new());
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                if e.name().as_ref() == b"Data" {
+                    data_hash = e
+                        .try_get_attribute("Hash")
+                        .ok()
+                        .flatten()
+                        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()));
+                }
+                path.push(e.name().as_ref().to_vec());
+            }
+            Ok(Event::End(_)) => {
+                path.pop();
+            }
+            Ok(Event::Text(e)) => {
+                let Ok(value) = e.unescape() else {
+                    return Ok(None);
+                };
+                match path.iter().map(Vec::as_slice).collect::<Vec<_>>()[..] {
+                    [b"KeyFile", b"Meta", b"Version"] => version.push_str(&value),
+                    [b"KeyFile", b"Key", b"Data"] => key_data.push_str(&value),
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            // Not XML after all: an ordinary key file that happens to start with '<'.
+            Err(_) => return Ok(None),
+            Ok(_) => {}
+        }
+    }
+    if key_data.is_empty() {
+        return Ok(None);
+    }
+
+    let compact: Zeroizing<String> =
+        Zeroizing::new(key_data.chars().filter(|c| !c.is_whitespace()).collect());
+    let decoded = if version.trim().starts_with("2.") {
+        let decoded = Zeroizing::new(
+            HEXLOWER_PERMISSIVE
+                .decode(compact.as_bytes())
+                .map_err(|_| anyhow!("The key file is damaged"))?,
+        );
+        if let Some(hash) = data_hash {
+            let expected = HEXLOWER_PERMISSIVE.encode(&Sha256::digest(&*decoded)[..4]);
+            if !expected.eq_ignore_ascii_case(hash.trim()) {
+                return Err(anyhow!("The key file is damaged (checksum mismatch)"));
+            }
+        }
+        decoded
+    } else {
+        Zeroizing::new(
+            BASE64
+                .decode(compact.as_bytes())
+                .map_err(|_| anyhow!("The key file is damaged"))?,
+        )
+    };
+    if decoded.len() != 32 {
+        return Err(anyhow!("The key file is damaged"));
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(&decoded);
+    Ok(Some(key))
+}
+
+// ==========================================
+// --- WRITER ---
+// ==========================================
+
+/// Writes a KDBX 4 database protected by `password`. `xml` builds the document and must
+/// encode its protected values with the stream it is given, in document order.
+pub fn seal(
+    password: &str,
+    xml: impl FnOnce(&mut InnerStream) -> Result<Zeroizing<String>>,
+) -> Result<Vec<u8>> {
+    let (memory_kib, iterations, lanes) = SEAL_ARGON2;
+    let kdf = Kdf::Argon2 {
+        algorithm: Algorithm::Argon2id,
+        version: Version::V0x13,
+        salt: random_bytes(32)?,
+        memory_kib,
+        iterations,
+        lanes,
+    };
+    let master_seed = random_bytes(32)?;
+    let iv = random_bytes(16)?;
+    let stream_key = Zeroizing::new(random_bytes(64)?);
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&SIGNATURE_1.to_le_bytes());
+    header.extend_from_slice(&SIGNATURE_2.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes()); // 4.0
+    header.extend_from_slice(&4u16.to_le_bytes());
+    write_field(&mut header, 2, &CIPHER_AES256);
+    write_field(&mut header, 3, &1u32.to_le_bytes());
+    write_field(&mut header, 4, &master_seed);
+    write_field(&mut header, 7, &iv);
+    write_field(&mut header, 11, &kdf.parameters());
+    write_field(&mut header, 0, b"\r\n\r\n");
+
+    let composite = composite_key(Some(password), None)?;
+    let transformed = kdf.transform(&composite)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&master_seed);
+    hasher.update(*transformed);
+    let cipher_key = Zeroizing::new(hasher.finalize().to_vec());
+    let mut hasher = Sha512::new();
+    hasher.update(&master_seed);
+    hasher.update(*transformed);
+    hasher.update([1u8]);
+    let hmac_key = Zeroizing::new(hasher.finalize().to_vec());
+
+    // Inner header (stream cipher and key, no attachments), then the document.
+    let mut inner = Zeroizing::new(Vec::new());
+    write_field(&mut inner, 1, &INNER_STREAM_CHACHA20.to_le_bytes());
+    write_field(&mut inner, 2, &stream_key);
+    write_field(&mut inner, 0, &[]);
+    let mut stream = InnerStream::new(INNER_STREAM_CHACHA20, &stream_key)?;
+    inner.extend_from_slice(xml(&mut stream)?.as_bytes());
+
+    let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&inner)?;
+    let compressed = Zeroizing::new(gzip.finish()?);
+    let ciphertext = cbc::Encryptor::<Aes256>::new_from_slices(&cipher_key, &iv)
+        .map_err(|_| anyhow!("Invalid cipher key"))?
+        .encrypt_padded_vec_mut::<Pkcs7>(&compressed);
+
+    let mut out = header.clone();
+    out.extend_from_slice(&Sha256::digest(&header));
+    out.extend_from_slice(
+        &block_mac(&hmac_key, u64::MAX)
+            .chain_update(&header)
+            .finalize()
+            .into_bytes(),
+    );
+    let blocks = ciphertext
+        .chunks(SEAL_BLOCK_SIZE)
+        .chain(std::iter::once(&[][..]));
+    for (index, data) in (0u64..).zip(blocks) {
+        let len = (data.len() as u32).to_le_bytes();
+        let mac = block_mac(&hmac_key, index)
+            .chain_update(index.to_le_bytes())
+            .chain_update(len)
+            .chain_update(data)
+            .finalize()
+            .into_bytes();
+        out.extend_from_slice(&mac);
+        out.extend_from_slice(&len);
+        out.extend_from_slice(data);
+    }
+    Ok(out)
+}
+
+/// A v4 header field: u8 id | u32 length | data.
+fn write_field(out: &mut Vec<u8>, id: u8, data: &[u8]) {
+    out.push(id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    Ok(bytes)
+}
+
+// ==========================================
+// --- BYTE READING ---
+// ==========================================
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Cursor { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow!("{}: the file is truncated", DAMAGED))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(read_u32(self.take(4)?))
+    }
+}
+
+/// Little-endian; short input reads as zero-padded.
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0u8; 4];
+    let len = bytes.len().min(4);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    let len = bytes.len().min(8);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    u64::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Built to the KeePass format description with an independent implementation
+    // (Python): AES-KDF (10 rounds), AES-256-CBC, gzip. The XML holds one entry with
+    // a protected password and a history entry with another.
+    // KDBX 4.1, ChaCha20 inner stream, password "correct horse".
+    const KDBX4: &[&str] = &[
+    "A9mimmf7S7UBAAQAAhAAAAAxwfLmv3FDUL5YBSFq/Fr/AwQAAAABAAAABCAAAAAAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcY",
+    "GRobHB0eHwcQAAAAEBESExQVFhcYGRobHB0eHwtdAAAAAAFCBQAAACRVVUlEEAAAAHwCu4J5p0rAkn0RSgBkgjgFAQAAAFII",
+    "AAAACgAAAAAAAABCAQAAAFMgAAAAZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1+f4CBgoMAAAQAAAANCg0KF3tjOZkyV3Ti",
+    "zrmQTp6XSIiC4LHrrEfhjUcjiR5eKvC1HxuIaADKwupAcZttCmULcwvs+MpoiNTqCBvdMC1vohWY8XBrqlGX7YWUKolFAxtp",
+    "WcWxMpaKAkXBe+NlVke5wAEAAIBV+LzKAYQDWbBdghGzpbmhEKhkrt6VRIS1qnK3wQVRBExN83neCNHx/cbUDbip4yGepESJ",
+    "7rOhTf/r/LVUF5b6ycNDBelXGEkwzT9UFwlZd31PLlcmo4KeMxDZdW/EtbmiLJqQAd7w2qzlzgYaZ0hDuTZhrObfuwE1iyor",
+    "KogvOeFQbtouYIzIKiaY2MX5Oyn0ndKVLjmVBxKQ/8kYX0YlUM/+Z0s7dAkSehpE4dDU0FlFAH0PrCkrbrktbC0lhlbzCMJm",
+    "5/84G5O9mh2OcjUAESESlXrHYRd0ATZAuhekhzUi1eNjVkLu08uWgJolvN2018d+sByuiY1pvVMtc/pxjUbKYT+gqeyILG6p",
+    "aS7irS3+ZdL7iqekEG9zgWQa5H62waUgHaQA71atHHTV1fCn4z+5lhDYEMMNQ579HwBBvmw5owc7TOte2sjpTicsx7j156yW",
+    "iEhBxzxfkdZcum1kwGXuNuy1Qxp/UuSBTO3pd74S7RaL6ESGOvPJiSDRHbBDzs5UvFhgFOkVyBKDDer9TEnzpVzGXAwheOEH",
+    "V2q6uPSCFEWLz/ROvz5NOF3HjG3OWh2UNJmrF9ru01Ye3krgWa6bvUG1IIO6or+9ozdcCq/LUbV/JR5mB3IlQwWYzgAAAAA=",
+    ];
+    // KDBX 3.1, Salsa20 inner stream, password "correct horse" plus KEYFILE_V2.
+    const KDBX3: &[&str] = &[
+    "A9mimmf7S7UBAAMAAhAAMcHy5r9xQ1C+WAUhavxa/wMEAAEAAAAEIAAAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0e",
+    "HwUgAGRlZmdoaWprbG1ub3BxcnN0dXZ3eHl6e3x9fn+AgYKDBggACgAAAAAAAAAHEAAQERITFBUWFxgZGhscHR4fCCAAMjM0",
+    "NTY3ODk6Ozw9Pj9AQUJDREVGR0hJSktMTU5PUFEJIACWl5iZmpucnZ6foKGio6SlpqeoqaqrrK2ur7CxsrO0tQoEAAIAAAAA",
+    "BAANCg0KBN2zWjt9HBEiWO2sWegDmztMc7xXjFAqhzVOoUDXgkr2m3KdmQFY9TfxHiFMxo9vGAdWxrWWSK1ixm7XCuwHoHg6",
+    "6sSqIONape8gcS+IFZJ+iq3zgqRNhUJIIrH/7k0PnWRaJ1aaVHxOiOoVct1qAJ1sEA3PKXQ43Z0uV+nTcgzIyhyQ+lnYad23",
+    "t/hRB0bji0coUVFGmKfieLXLR0+aseJHpJenvXMDtUMjskHQ1PAL8S37VwBL2QeivrCJXZ7jJ2vjMrMzYoDQlA0Cw00MPIYD",
+    "LyP6DdH8GuMODXbiCNq0OkNkSMv0sGB3UDElwxvCHH7mNMZKgxItnjJLbnysKXifU7VGuDQqFmSYfpp7iV+8QOYb1geQm1WY",
+    "9qEJOR+uogrstJl2YbhdiCG7Z9xrX+PbB9TIe6sPAI8vUXL5rscVM/wQxtuPPErUa2SIn7RCYmWEL5N9HMQmBzBczmkDG2+/",
+    "qdeXzjYSqc6ucHdl/WX4aJLbIkCmseUCXRYUUORZ5/BD9imcZkYw7smd0SWwsj2NqqiKpq7hqL2H2FJn6Gc/lYBJPQnfrgHI",
+    "BylxWK3k0ViAco9RHpj73/qx3fiXjg==",
+    ];
+    const KEYFILE_V2: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<KeyFile>
+    <Meta><Version>2.0</Version></Meta>
+    <Key>
+        <Data Hash="630DCD29">
+            00010203 04050607 08090A0B 0C0D0E0F
+            10111213 14151617 18191A1B 1C1D1E1F
+        </Data>
+    </Key>
+</KeyFile>"#;
+
+    fn fixture(parts: &[&str]) -> Vec<u8> {
+        BASE64.decode(parts.concat().as_bytes()).unwrap()
+    }
+
+    /// The protected values, in document order.
+    fn protected_values(database: &mut Database) -> Vec<String> {
+        let mut values = Vec::new();
+        for part in database.xml.split("<Value Protected=\"True\">").skip(1) {
+            let text = &part[..part.find('<').unwrap()];
+            values.push(database.protected.unprotect(text).unwrap().to_string());
+        }
+        values
+    }
+
+    #[test]
+    fn test_open_kdbx4() {
+        let bytes = fixture(KDBX4);
+        assert!(is_kdbx(&bytes));
+        let mut database = open(&bytes, Some("correct horse"), None).unwrap();
+        assert!(database.xml.contains("<Value>Example</Value>"));
+        assert_eq!(
+            protected_values(&mut database),
+            ["hunter2 & <co>", "old-password"]
+        );
+
+        let wrong = open(&bytes, Some("wrong horse"), None).err().unwrap();
+        assert_eq!(wrong.to_string(), WRONG_KEY);
+        assert!(open(&bytes, None, None).is_err());
+
+        // A flipped byte in the payload fails its block HMAC, not the key check.
+        let mut damaged = bytes.clone();
+        let last = damaged.len() - 50;
+        damaged[last] ^= 1;
+        let err = open(&damaged, Some("correct horse"), None).err().unwrap();
+        assert!(err.to_string().starts_with(DAMAGED), "{}", err);
+        assert!(open(&bytes[..bytes.len() - 40], Some("correct horse"), None).is_err());
+    }
+
+    #[test]
+    fn test_open_kdbx3_with_keyfile() {
+        let bytes = fixture(KDBX3);
+        let keyfile = KEYFILE_V2.as_bytes();
+        let mut database = open(&bytes, Some("correct horse"), Some(keyfile)).unwrap();
+        assert_eq!(
+            protected_values(&mut database),
+            ["hunter2 & <co>", "old-password"]
+        );
+
+        for (password, keyfile) in [
+            (Some("correct horse"), None),
+            (None, Some(keyfile)),
+            (Some(""), Some(keyfile)),
+        ] {
+            let err = open(&bytes, password, keyfile).err().unwrap();
+            assert_eq!(err.to_string(), WRONG_KEY);
+        }
+    }
+
+    #[test]
+    fn test_keyfile_forms() {
+        let key: Vec<u8> = (0u8..32).collect();
+        let xml = keyfile_key(KEYFILE_V2.as_bytes()).unwrap();
+        assert_eq!(xml.as_slice(), key.as_slice());
+        // The same key as raw bytes, as hex, and as a v1 XML file.
+        assert_eq!(*keyfile_key(&key).unwrap(), *xml);
+        let hex = HEXLOWER_PERMISSIVE.encode(&key);
+        assert_eq!(*keyfile_key(hex.as_bytes()).unwrap(), *xml);
+        let v1 = format!(
+            "<KeyFile><Meta><Version>1.00</Version></Meta><Key><Data>{}</Data></Key></KeyFile>",
+            BASE64.encode(&key)
+        );
+        assert_eq!(*keyfile_key(v1.as_bytes()).unwrap(), *xml);
+        // Any other file stands for its hash.
+        let other = keyfile_key(b"<not a key file").unwrap();
+        assert_eq!(
+            other.as_slice(),
+            Sha256::digest(b"<not a key file").as_slice()
+        );
+
+        let tampered = KEYFILE_V2.replace("630DCD29", "00000000");
+        assert!(keyfile_key(tampered.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_kdf_limits() {
+        let mut params = vec![0x00, 0x01];
+        let mut item = |kind: u8, key: &str, value: &[u8]| {
+            params.push(kind);
+            params.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            params.extend_from_slice(key.as_bytes());
+            params.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            params.extend_from_slice(value);
+        };
+        item(0x42, "$UUID", &KDF_ARGON2ID);
+        item(0x42, "S", &[7; 32]);
+        item(0x04, "V", &0x13u32.to_le_bytes());
+        item(0x05, "M", &(4u64 << 30).to_le_bytes());
+        item(0x05, "I", &2u64.to_le_bytes());
+        item(0x04, "P", &2u32.to_le_bytes());
+        params.push(0);
+        let err = Kdf::from_parameters(&params).err().unwrap();
+        assert!(err.to_string().contains("4096 MB"), "{}", err);
+
+        assert!(Kdf::aes(vec![0; 32], MAX_AES_ROUNDS + 1).is_err());
+        assert!(Kdf::aes(vec![0; 16], 1).is_err());
+    }
+    #[test]
+    fn test_seal_roundtrip() {
+        let sealed = seal("export pass", |stream| {
+            let first = stream.protect("s3cret & <co>");
+            let second = stream.protect("");
+            Ok(Zeroizing::new(format!(
+                "<KeePassFile><Value Protected=\"True\">{}</Value><Value Protected=\"True\">{}</Value></KeePassFile>",
+                first, second
+            )))
+        })
+        .unwrap();
+        assert!(is_kdbx(&sealed));
+
+        let mut database = open(&sealed, Some("export pass"), None).unwrap();
+        assert!(database.xml.starts_with("<KeePassFile>"));
+        assert_eq!(protected_values(&mut database), ["s3cret & <co>", ""]);
+
+        let err = open(&sealed, Some("export pas"), None).err().unwrap();
+        assert_eq!(err.to_string(), WRONG_KEY);
+    }
+}
+
+// --- END OF FILE kdbx.rs ---
@@ -1,7 +1,8 @@
 // --- START OF FILE keychain.rs ---
 
+use crate::file_lock;
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use anyhow::{anyhow, Context, Result};
@@ -10,8 +11,10 @@ use argon2::password_hash::{rand_core::OsRng as Argon2OsRng, SaltString};
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
 use rand::{rngs::OsRng, TryRngCore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, Instant};
 // Zeroize prevents memory scraping/forensics by actively overwriting cryptographic
 // keys with zeros before releasing the RAM back to the operating system.
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
@@ -41,6 +44,21 @@ fn default_kdf_parallelism() -> u32 {
     4 // Four CPU threads. Increases hardware cost of parallelised attacks.
 }
 
+/// One set of Argon2id parameters. `memory` is in KiB, as Argon2 counts it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Rough attacker cost per guess: memory × passes.
+    fn cost(&self) -> u64 {
+        u64::from(self.memory) * u64::from(self.iterations)
+    }
+}
+
 // ==========================================
 // --- Data Structures ---
 // ==========================================
@@ -67,6 +85,10 @@ pub struct KeychainStore {
     pub kdf_iterations: u32,
     #[serde(default = "default_kdf_parallelism")]
     pub kdf_parallelism: u32,
+    // Parameters measured for this device by `benchmark_kdf`. Each password-derived slot
+    // is re-wrapped with them the next time it is used (see `upgrade_slot_kdf`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf_target: Option<KdfParams>,
 
     // --- Slot 1: User Password ---
     // The random salt defends against pre-computed Rainbow Table attacks.
@@ -75,6 +97,9 @@ pub struct KeychainStore {
     pub password_nonce: Vec<u8>,
     // The Master Key, securely encrypted by the User's Password.
     pub encrypted_master_key_pass: Vec<u8>,
+    // The KDF parameters this slot was wrapped with. Absent: the store-level ones above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_kdf: Option<KdfParams>,
 
     // --- Slot 2: Recovery Code ---
     // A secondary salt specifically for the recovery code.
@@ -83,6 +108,256 @@ pub struct KeychainStore {
     pub recovery_nonce: Vec<u8>,
     // The SAME Master Key, encrypted by the randomly generated Recovery Code (QRE-XXXX...).
     pub encrypted_master_key_recovery: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_kdf: Option<KdfParams>,
+    // Which format the current recovery code was generated in, so the recovery screen can
+    // show the right input. Absent in older keychains, which all used hex codes.
+    #[serde(default)]
+    pub recovery_format: RecoveryCodeFormat,
+
+    // --- Vault Policy ---
+    // Absent in keychains created before policies existed; those get the permissive default.
+    #[serde(default)]
+    pub policy: VaultPolicy,
+
+    // --- Slot 3 (optional): Guest / Read-Only Password ---
+    // Unwraps the SAME Master Key (viewing needs it), but a session opened through this
+    // slot is flagged read-only in `SessionState` and every mutating command refuses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guest_slot: Option<GuestSlot>,
+
+    // --- Duress Slot (optional) ---
+    // A second password for coercion scenarios. It wraps a DIFFERENT key, the decoy
+    // vault's, so a session opened through it never holds the real Master Key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duress_slot: Option<DuressSlot>,
+
+    // --- Named User Slots (Organization Mode) ---
+    // Additional full-access passwords for team members, each wrapping the SAME Master Key.
+    // Slot 1 above is always the "owner"; only the owner can add or remove user slots.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_slots: Vec<UserSlot>,
+
+    // --- Paired Device Slots ---
+    // The SAME Master Key, wrapped with a key only a paired phone can supply (see
+    // device_pairing.rs). Unlocking through one of these acts as the owner.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device_slots: Vec<DeviceSlot>,
+
+    // --- OS Keystore Slot (optional, auto-unlock) ---
+    // The SAME Master Key, wrapped with a random secret that lives in the platform
+    // keystore (see os_keystore.rs). Unlocking through it acts as the owner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keystore_slot: Option<KeystoreSlot>,
+
+    // --- Public-Key Identity ---
+    // The vault's X25519 + ML-KEM keypair for files other vaults encrypt to it (see
+    // recipient.rs). Created on first use; the secret half is sealed under the Master Key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<IdentityKeys>,
+}
+
+impl KeychainStore {
+    /// The store-level parameters, used by slots that do not record their own.
+    fn base_kdf(&self) -> KdfParams {
+        KdfParams {
+            memory: self.kdf_memory,
+            iterations: self.kdf_iterations,
+            parallelism: self.kdf_parallelism,
+        }
+    }
+
+    /// Parameters for a slot being wrapped now: the tuned ones, once measured.
+    fn new_slot_kdf(&self) -> KdfParams {
+        self.kdf_target.unwrap_or_else(|| self.base_kdf())
+    }
+
+    fn password_kdf(&self) -> KdfParams {
+        self.password_kdf.unwrap_or_else(|| self.base_kdf())
+    }
+
+    fn recovery_kdf(&self) -> KdfParams {
+        self.recovery_kdf.unwrap_or_else(|| self.base_kdf())
+    }
+
+    /// Whether a slot wrapped with `used` should be re-wrapped with the tuned parameters.
+    fn is_stale(&self, used: KdfParams) -> bool {
+        self.kdf_target.is_some_and(|target| target != used)
+    }
+}
+
+/// A team member's key slot. Same construction as the password slot, plus a name used
+/// for login and audit-log attribution.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserSlot {
+    pub name: String,
+    pub salt: String,
+    pub nonce: Vec<u8>,
+    pub encrypted_master_key: Vec<u8>,
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<KdfParams>,
+}
+
+/// Public view of a user slot (no key material).
+#[derive(Serialize, Debug, Clone)]
+pub struct UserSlotInfo {
+    pub name: String,
+    pub created_at: i64,
+}
+
+/// A paired phone's key slot. The wrapping key is derived from the pairing secret, which
+/// only the phone keeps; `auth_key` lets the desktop check the phone's approvals.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceSlot {
+    pub id: String,
+    pub name: String,
+    pub auth_key: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub encrypted_master_key: Vec<u8>,
+    pub created_at: i64,
+    #[serde(default)]
+    pub last_used: Option<i64>,
+}
+
+/// Public view of a device slot (no key material).
+#[derive(Serialize, Debug, Clone)]
+pub struct DeviceSlotInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+    pub last_used: Option<i64>,
+}
+
+/// The auto-unlock slot. The wrapping secret is filed in the platform keystore under
+/// `keystore_account`; only the wrapped key is kept here.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeystoreSlot {
+    pub keystore_account: String,
+    pub nonce: Vec<u8>,
+    pub encrypted_master_key: Vec<u8>,
+    pub created_at: i64,
+    #[serde(default)]
+    pub last_used: Option<i64>,
+}
+
+/// Public view of the keystore slot (no key material).
+#[derive(Serialize, Debug, Clone)]
+pub struct KeystoreSlotInfo {
+    pub created_at: i64,
+    pub last_used: Option<i64>,
+}
+
+/// The vault's public-key identity. `public` is what contacts import; `encrypted_secret`
+/// is the private half sealed under a key derived from the Master Key, with `public` as
+/// AAD so the two cannot be mixed up between keychains.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdentityKeys {
+    pub public: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub encrypted_secret: Vec<u8>,
+    pub created_at: i64,
+}
+
+/// Names that identify the built-in slots in the session and the audit log.
+pub const OWNER_SLOT_NAME: &str = "owner";
+pub const GUEST_SLOT_NAME: &str = "guest";
+const MAX_USER_SLOTS: usize = 32;
+const MAX_DEVICE_SLOTS: usize = 8;
+
+/// The guest credential's key slot. Same construction as the password slot.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GuestSlot {
+    pub salt: String,
+    pub nonce: Vec<u8>,
+    pub encrypted_master_key: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<KdfParams>,
+}
+
+/// The duress credential's key slot. Same construction as the password slot, but what
+/// it wraps is the decoy vault's master key (see duress.rs).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuressSlot {
+    pub salt: String,
+    pub nonce: Vec<u8>,
+    pub encrypted_decoy_key: Vec<u8>,
+    /// Unlocking through this slot also destroys the real vault.
+    pub wipe: bool,
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<KdfParams>,
+}
+
+/// Public view of the duress slot (no key material).
+#[derive(Serialize, Debug, Clone)]
+pub struct DuressSlotInfo {
+    pub wipe: bool,
+    pub created_at: i64,
+}
+
+// ==========================================
+// --- Vault Policy ---
+// ==========================================
+// Per-vault rules that the backend enforces on every relevant operation, so a modified
+// or buggy frontend cannot skip them. The policy is stored in plaintext next to the key
+// slots: it protects against the UI/IPC layer, not against someone with write access to
+// keychain.json (who could simply delete the vault). Changing it requires the password.
+
+/// Minimum amount of user-gathered entropy (mouse/keyboard noise) for "paranoid" mode.
+pub const PARANOID_MIN_ENTROPY_BYTES: usize = 32;
+/// Upper bound for `min_password_length`, to keep a typo from locking the user out.
+const MAX_POLICY_PASSWORD_LENGTH: usize = 128;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct VaultPolicy {
+    /// Every file encryption must be bound to a keyfile.
+    pub require_keyfile: bool,
+    /// Minimum length (in characters) for new vault passwords. 0 = no extra rule.
+    pub min_password_length: usize,
+    /// Every file encryption must mix in user-gathered entropy.
+    pub require_paranoid_entropy: bool,
+}
+
+impl VaultPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if self.min_password_length > MAX_POLICY_PASSWORD_LENGTH {
+            return Err(anyhow!(
+                "Minimum password length cannot exceed {} characters.",
+                MAX_POLICY_PASSWORD_LENGTH
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks a password that is about to become the vault password.
+    pub fn check_password(&self, password: &str) -> Result<()> {
+        if password.chars().count() < self.min_password_length {
+            return Err(anyhow!(
+                "Vault policy requires a password of at least {} characters.",
+                self.min_password_length
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the parameters of a file encryption before any work is done.
+    pub fn check_encryption(&self, uses_keyfile: bool, extra_entropy: Option<&[u8]>) -> Result<()> {
+        if self.require_keyfile && !uses_keyfile {
+            return Err(anyhow!(
+                "Vault policy requires a keyfile for every encryption."
+            ));
+        }
+        if self.require_paranoid_entropy
+            && extra_entropy.is_none_or(|e| e.len() < PARANOID_MIN_ENTROPY_BYTES)
+        {
+            return Err(anyhow!(
+                "Vault policy requires paranoid mode (extra entropy) for every encryption."
+            ));
+        }
+        Ok(())
+    }
 }
 
 // ==========================================
@@ -93,15 +368,9 @@ pub struct KeychainStore {
 ///
 /// SECURITY: Returns a `Zeroizing` wrapper. This means the highly sensitive derived KEK
 /// is automatically wiped from RAM the moment the calling function finishes using it.
-fn derive_kek(
-    secret: &str,
-    salt_str: &str,
-    mem: u32,
-    iter: u32,
-    par: u32,
-) -> Result<Zeroizing<[u8; 32]>> {
+fn derive_kek(secret: &str, salt_str: &str, kdf: KdfParams) -> Result<Zeroizing<[u8; 32]>> {
     // Set up Argon2 parameters dynamically based on the stored vault settings
-    let params = Params::new(mem, iter, par, Some(32))
+    let params = Params::new(kdf.memory, kdf.iterations, kdf.parallelism, Some(32))
         .map_err(|e| anyhow!("Invalid KDF parameters: {}", e))?;
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
 
@@ -131,30 +400,92 @@ fn derive_kek(
 ///
 /// On all major OS filesystems, `rename` within the same directory is guaranteed to be
 /// atomic — the old file is never visible as empty or partial to any reader.
+///
+/// The whole write holds the directory's write lock (`file_lock.rs`), so a second app
+/// instance cannot write the same temp file or keychain at the same time.
 fn atomic_write_keychain(path: &Path, store: &KeychainStore) -> Result<()> {
-    let tmp_path = path.with_extension("tmp");
+    let _lock = file_lock::FileLock::exclusive(path)?;
+    write_keychain_locked(path, store)
+}
 
-    // Step 1: Write to a temp file. If this fails, the real keychain is untouched.
-    let tmp_file = fs::File::create(&tmp_path)
-        .context("Failed to create temporary keychain file for atomic write")?;
-    serde_json::to_writer_pretty(tmp_file, store)
-        .context("Failed to serialize keychain to temp file")?;
+/// `atomic_write_keychain` for a caller that already holds the lock.
+fn write_keychain_locked(path: &Path, store: &KeychainStore) -> Result<()> {
+    file_lock::write_atomic_locked(path, |file| {
+        serde_json::to_writer_pretty(file, store)
+            .context("Failed to serialize keychain to temp file")
+    })
+}
 
-    // Step 2: Atomically swap. If this fails, the temp file is left behind
-    // (it will be overwritten on the next write attempt, which is harmless).
-    fs::rename(&tmp_path, path).context("Failed to atomically replace keychain file")?;
+/// Read-modify-write of the keychain. The lock is taken before the read and held until
+/// the rename, so two mutators (or two app instances) cannot both start from the same
+/// copy and have the second write silently drop the first one's change. Nothing is
+/// written when `f` fails.
+///
+/// `FileLock` is not reentrant: `f` must not call another keychain mutator.
+fn modify_keychain<T>(path: &Path, f: impl FnOnce(&mut KeychainStore) -> Result<T>) -> Result<T> {
+    let _lock = file_lock::FileLock::exclusive(path)?;
+    let file = fs::File::open(path)?;
+    let mut store: KeychainStore =
+        serde_json::from_reader(file).context("Corrupted keychain file")?;
+    let result = f(&mut store)?;
+    write_keychain_locked(path, &store)?;
+    Ok(result)
+}
 
-    Ok(())
+/// Printable layouts for the recovery code. Every format carries at least 128 bits.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryCodeFormat {
+    /// QRE-XXXXXXXX-XXXXXXXX-XXXXXXXX-XXXXXXXX (128 bits). Also covers the original
+    /// 64-bit QRE-XXXX-XXXX-XXXX-XXXX codes, which are still accepted on recovery.
+    #[default]
+    Hex128,
+    /// Eight groups of eight hex characters (256 bits).
+    Hex256,
+    /// BIP39-style mnemonic: `RECOVERY_WORD_COUNT` words from the passphrase wordlist.
+    Words,
+}
+
+/// 12 words × log2(3618 distinct words) ≈ 141 bits.
+pub const RECOVERY_WORD_COUNT: usize = 12;
+
+/// Uniform index below `n` from the OS RNG (rejection sampling, no modulo bias).
+fn random_index(n: usize) -> Result<usize> {
+    let n = n as u32;
+    let zone = u32::MAX - (u32::MAX % n);
+    loop {
+        let mut buf = [0u8; 4];
+        OsRng
+            .try_fill_bytes(&mut buf)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        let v = u32::from_le_bytes(buf);
+        if v < zone {
+            return Ok((v % n) as usize);
+        }
+    }
 }
 
-/// FIX F-05: Generates a cryptographically random recovery code with 128 bits of entropy.
+/// FIX F-05: Generates a cryptographically random recovery code with at least 128 bits of entropy.
 ///
-/// Format: QRE-XXXXXXXX-XXXXXXXX-XXXXXXXX-XXXXXXXX
-/// Each group is a random 32-bit integer formatted as 8 uppercase hex characters.
+/// Hex formats: each group is a random 32-bit integer formatted as 8 uppercase hex characters.
 /// 4 groups × 32 bits = 128 bits total (meets NIST SP 800-63B guidance for long-lived secrets).
-fn generate_recovery_code() -> Result<String> {
-    let mut raw_parts = Vec::with_capacity(4);
-    for _ in 0..4 {
+/// Word format: lowercase words separated by single spaces.
+fn generate_recovery_code(format: RecoveryCodeFormat) -> Result<String> {
+    let groups = match format {
+        RecoveryCodeFormat::Hex128 => 4,
+        RecoveryCodeFormat::Hex256 => 8,
+        RecoveryCodeFormat::Words => {
+            let mut words = Vec::with_capacity(RECOVERY_WORD_COUNT);
+            for _ in 0..RECOVERY_WORD_COUNT {
+                words.push(
+                    crate::wordlist::WORDLIST[random_index(crate::wordlist::WORDLIST.len())?],
+                );
+            }
+            return Ok(words.join(" "));
+        }
+    };
+    let mut raw_parts = Vec::with_capacity(groups);
+    for _ in 0..groups {
         let mut buf = [0u8; 4];
         // FIX F-07: Propagate RNG errors as Results instead of panicking.
         OsRng
@@ -165,6 +496,24 @@ fn generate_recovery_code() -> Result<String> {
     Ok(format!("QRE-{}", raw_parts.join("-")))
 }
 
+/// Canonical KDF input for a typed recovery code, so case, spacing and line breaks from
+/// a paper copy do not matter. Hex codes (any generation, including the old 64-bit
+/// ones) become upper case with no whitespace; mnemonics become lower case words
+/// separated by single spaces.
+pub fn normalize_recovery_code(input: &str) -> String {
+    let trimmed = input.trim();
+    let compact: String = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.to_ascii_uppercase().starts_with("QRE-") {
+        compact.to_ascii_uppercase()
+    } else {
+        trimmed
+            .split_whitespace()
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
 // ==========================================
 // --- Public API ---
 // ==========================================
@@ -174,33 +523,59 @@ fn generate_recovery_code() -> Result<String> {
 /// The Step-by-Step Envelope Encryption Setup:
 /// 1. Generates a truly random Master Key.
 /// 2. Derives a KEK from the User's Password & Encrypts the Master Key (Slot 1).
-/// 3. Generates a random, printable Recovery Code (QRE-XXXXXXXX-...).
+/// 3. Generates a random, printable Recovery Code in `recovery_format` (QRE-XXXXXXXX-... by default).
 /// 4. Derives a KEK from the Recovery Code & Encrypts the Master Key again (Slot 2).
 /// 5. Saves the metadata and encrypted slots to `keychain.json` on disk atomically.
-pub fn init_keychain(path: &Path, password: &str) -> Result<(String, MasterKey)> {
+pub fn init_keychain(
+    path: &Path,
+    password: &str,
+    recovery_format: RecoveryCodeFormat,
+) -> Result<(String, MasterKey)> {
     // Prevent accidentally overwriting an existing user's vault
     if path.exists() {
         return Err(anyhow!("Keychain already exists."));
     }
 
     // 1. Define KDF Settings
-    let mem = default_kdf_memory();
-    let iter = default_kdf_iterations();
-    let par = default_kdf_parallelism();
+    let kdf = KdfParams {
+        memory: default_kdf_memory(),
+        iterations: default_kdf_iterations(),
+        parallelism: default_kdf_parallelism(),
+    };
 
     // 2. Generate Truly Random Master Key
-    // FIX F-07: Use ? instead of .expect() so RNG failure surfaces as a recoverable error.
+    let master_key = random_master_key()?;
+
+    let recovery_code = write_new_keychain(path, password, recovery_format, kdf, &master_key)?;
+
+    // Return both the string recovery code (to show the user) and the MasterKey (to load into active RAM)
+    Ok((recovery_code, master_key))
+}
+
+/// A truly random 32-byte key.
+/// FIX F-07: Use ? instead of .expect() so RNG failure surfaces as a recoverable error.
+fn random_master_key() -> Result<MasterKey> {
     let mut mk_bytes = [0u8; 32];
     OsRng
         .try_fill_bytes(&mut mk_bytes)
         .map_err(|e| anyhow!("OS RNG failed generating master key: {}", e))?;
-    let master_key = MasterKey(mk_bytes);
+    Ok(MasterKey(mk_bytes))
+}
 
+/// Steps 3-5 of `init_keychain`: wraps `master_key` in a password slot and a recovery
+/// slot and writes the new keychain. Returns the recovery code.
+fn write_new_keychain(
+    path: &Path,
+    password: &str,
+    recovery_format: RecoveryCodeFormat,
+    kdf: KdfParams,
+    master_key: &MasterKey,
+) -> Result<String> {
     // 3. Prepare Password Slot (Slot 1)
     let pass_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
 
     // pass_kek is auto-zeroized when this function ends
-    let pass_kek = derive_kek(password, &pass_salt, mem, iter, par)?;
+    let pass_kek = derive_kek(password, &pass_salt, kdf)?;
     let cipher_pass =
         Aes256Gcm::new_from_slice(&*pass_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
 
@@ -216,12 +591,12 @@ pub fn init_keychain(path: &Path, password: &str) -> Result<(String, MasterKey)>
 
     // 4. Prepare Recovery Slot (Slot 2)
     // FIX F-05 + F-07: generate_recovery_code() now produces 128-bit codes and propagates RNG errors.
-    let recovery_code = generate_recovery_code()?;
+    let recovery_code = generate_recovery_code(recovery_format)?;
 
     let rec_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
 
     // rec_kek is auto-zeroized when this function ends
-    let rec_kek = derive_kek(&recovery_code, &rec_salt, mem, iter, par)?;
+    let rec_kek = derive_kek(&recovery_code, &rec_salt, kdf)?;
     let cipher_rec =
         Aes256Gcm::new_from_slice(&*rec_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
 
@@ -239,21 +614,30 @@ pub fn init_keychain(path: &Path, password: &str) -> Result<(String, MasterKey)>
     // FIX F-02: Use atomic_write_keychain instead of fs::File::create() to prevent data loss.
     let store = KeychainStore {
         vault_id: uuid::Uuid::new_v4().to_string(),
-        kdf_memory: mem,
-        kdf_iterations: iter,
-        kdf_parallelism: par,
+        kdf_memory: kdf.memory,
+        kdf_iterations: kdf.iterations,
+        kdf_parallelism: kdf.parallelism,
+        kdf_target: None,
         password_salt: pass_salt,
         password_nonce: pass_nonce_bytes.to_vec(),
         encrypted_master_key_pass: enc_mk_pass,
+        password_kdf: None,
         recovery_salt: rec_salt,
         recovery_nonce: rec_nonce_bytes.to_vec(),
         encrypted_master_key_recovery: enc_mk_rec,
+        recovery_kdf: None,
+        recovery_format,
+        policy: VaultPolicy::default(),
+        guest_slot: None,
+        duress_slot: None,
+        user_slots: Vec::new(),
+        device_slots: Vec::new(),
+        keystore_slot: None,
+        identity: None,
     };
 
     atomic_write_keychain(path, &store)?;
-
-    // Return both the string recovery code (to show the user) and the MasterKey (to load into active RAM)
-    Ok((recovery_code, master_key))
+    Ok(recovery_code)
 }
 
 /// Attempts to unlock the keychain using the User's Password (Slot 1).
@@ -267,13 +651,7 @@ pub fn unlock_keychain(path: &Path, password: &str) -> Result<MasterKey> {
     let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
 
     // 1. Re-derive the KEK using the SAME parameters stored in the file.
-    let kek = derive_kek(
-        password,
-        &store.password_salt,
-        store.kdf_memory,
-        store.kdf_iterations,
-        store.kdf_parallelism,
-    )?;
+    let kek = derive_kek(password, &store.password_salt, store.password_kdf())?;
 
     let cipher = Aes256Gcm::new_from_slice(&*kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
     let nonce = Nonce::from_slice(&store.password_nonce);
@@ -307,26 +685,266 @@ pub fn recover_with_code(
     recovery_code: &str,
     new_password: &str,
 ) -> Result<MasterKey> {
+    modify_keychain(path, |store| {
+        store.policy.check_password(new_password)?;
+
+        // 1. Decrypt Master Key using Recovery Code (Slot 2).
+        // The normalized form is tried first; the code exactly as typed is the fallback, for
+        // slots written from a code that normalization would alter (at most two KDF runs).
+        let normalized = normalize_recovery_code(recovery_code);
+        let mut candidates = vec![normalized.as_str()];
+        if recovery_code != normalized {
+            candidates.push(recovery_code);
+        }
+        let nonce_rec = Nonce::from_slice(&store.recovery_nonce);
+        let mut decrypted = None;
+        for candidate in candidates {
+            let rec_kek = derive_kek(candidate, &store.recovery_salt, store.recovery_kdf())?;
+            let cipher_rec =
+                Aes256Gcm::new_from_slice(&*rec_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+            if let Ok(bytes) =
+                cipher_rec.decrypt(nonce_rec, store.encrypted_master_key_recovery.as_ref())
+            {
+                decrypted = Some((candidate, bytes));
+                break;
+            }
+        }
+
+        // Securely hold the decrypted master key
+        let (used_code, mk_bytes) = decrypted.ok_or_else(|| anyhow!("Invalid Recovery Code"))?;
+        let mk_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(mk_bytes);
+
+        if mk_bytes.len() != 32 {
+            return Err(anyhow!("Keychain is corrupt: invalid master key length"));
+        }
+
+        let mut arr = [0u8; 32];
+        arr.copy_from_slice(&mk_bytes);
+        let master_key = MasterKey(arr);
+        // `mk_bytes` drops and zeroizes here.
+
+        // The recovery slot moves to the tuned KDF parameters while the code is at hand.
+        if store.is_stale(store.recovery_kdf()) {
+            let kdf = store.new_slot_kdf();
+            let (salt, nonce, encrypted) = wrap_master_key(used_code, &master_key, kdf)?;
+            store.recovery_salt = salt;
+            store.recovery_nonce = nonce;
+            store.encrypted_master_key_recovery = encrypted;
+            store.recovery_kdf = Some(kdf);
+        }
+
+        // 2. Re-encrypt the extracted Master Key with the NEW Password (Slot 1).
+        let new_pass_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
+        let new_pass_kdf = store.new_slot_kdf();
+        let new_pass_kek = derive_kek(new_password, &new_pass_salt, new_pass_kdf)?;
+        let cipher_pass =
+            Aes256Gcm::new_from_slice(&*new_pass_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+
+        let mut new_pass_nonce_bytes = [0u8; NONCE_LEN];
+        // FIX F-07: Propagate RNG errors.
+        OsRng
+            .try_fill_bytes(&mut new_pass_nonce_bytes)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+
+        let new_enc_mk_pass = cipher_pass
+            .encrypt(
+                Nonce::from_slice(&new_pass_nonce_bytes),
+                master_key.0.as_ref(),
+            )
+            .map_err(|e| anyhow!("Failed to encrypt with new password: {}", e))?;
+
+        // 3. Update the JSON Store; modify_keychain saves it atomically.
+        // NOTE: Because we are only updating the keychain, the terabytes of files the user encrypted
+        // over the years do NOT need to be re-encrypted! The Master Key never changed.
+        store.password_salt = new_pass_salt;
+        store.password_nonce = new_pass_nonce_bytes.to_vec();
+        store.encrypted_master_key_pass = new_enc_mk_pass;
+        store.password_kdf = Some(new_pass_kdf);
+
+        Ok(master_key)
+    })
+}
+
+/// Generates a new Recovery Code and updates Slot 2.
+/// Useful if the user suspects someone found the piece of paper where they wrote down their recovery code.
+/// `format` switches the code to another layout; `None` keeps the vault's current one.
+pub fn reset_recovery_code(
+    path: &Path,
+    master_key: &MasterKey,
+    format: Option<RecoveryCodeFormat>,
+) -> Result<String> {
+    modify_keychain(path, |store| {
+        // 1. Generate NEW recovery code.
+        // FIX F-05 + F-07: generate_recovery_code() produces 128-bit codes and propagates RNG errors.
+        let format = format.unwrap_or(store.recovery_format);
+        let recovery_code = generate_recovery_code(format)?;
+
+        // 2. Derive new KEK and Encrypt the active Master Key with the new code.
+        let rec_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
+        let rec_kdf = store.new_slot_kdf();
+        let rec_kek = derive_kek(&recovery_code, &rec_salt, rec_kdf)?;
+        let cipher_rec =
+            Aes256Gcm::new_from_slice(&*rec_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+
+        let mut rec_nonce_bytes = [0u8; NONCE_LEN];
+        // FIX F-07: Propagate RNG errors.
+        OsRng
+            .try_fill_bytes(&mut rec_nonce_bytes)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        let rec_nonce = Nonce::from_slice(&rec_nonce_bytes);
+
+        let enc_mk_rec = cipher_rec
+            .encrypt(rec_nonce, master_key.0.as_ref())
+            .map_err(|_| anyhow!("Failed to encrypt recovery slot"))?;
+
+        // 3. Update Store; modify_keychain saves it atomically.
+        store.recovery_salt = rec_salt;
+        store.recovery_nonce = rec_nonce_bytes.to_vec();
+        store.encrypted_master_key_recovery = enc_mk_rec;
+        store.recovery_kdf = Some(rec_kdf);
+        store.recovery_format = format;
+
+        Ok(recovery_code)
+    })
+}
+
+/// Changes the main User Password (Slot 1) while the user is already logged in.
+/// This functions exactly like Step 2 of the `recover_with_code` function.
+pub fn change_password(path: &Path, master_key: &MasterKey, new_password: &str) -> Result<()> {
+    modify_keychain(path, |store| {
+        store.policy.check_password(new_password)?;
+
+        // 1. Generate new Salt
+        let new_pass_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
+
+        // 2. Derive new Key Encryption Key (KEK) using the new password.
+        let new_pass_kdf = store.new_slot_kdf();
+        let new_pass_kek = derive_kek(new_password, &new_pass_salt, new_pass_kdf)?;
+        let cipher_pass =
+            Aes256Gcm::new_from_slice(&*new_pass_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+
+        // 3. Encrypt the existing active Master Key with the new KEK
+        let mut new_pass_nonce_bytes = [0u8; NONCE_LEN];
+        // FIX F-07: Propagate RNG errors.
+        OsRng
+            .try_fill_bytes(&mut new_pass_nonce_bytes)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+
+        let new_enc_mk_pass = cipher_pass
+            .encrypt(
+                Nonce::from_slice(&new_pass_nonce_bytes),
+                master_key.0.as_ref(),
+            )
+            .map_err(|e| anyhow!("Failed to encrypt with new password: {}", e))?;
+
+        // 4. Update JSON Store
+        store.password_salt = new_pass_salt;
+        store.password_nonce = new_pass_nonce_bytes.to_vec();
+        store.encrypted_master_key_pass = new_enc_mk_pass;
+        store.password_kdf = Some(new_pass_kdf);
+
+        Ok(())
+    })
+}
+
+/// Reads the vault policy. A vault that does not exist yet has the default policy.
+pub fn load_policy(path: &Path) -> Result<VaultPolicy> {
+    if !path.exists() {
+        return Ok(VaultPolicy::default());
+    }
     let file = fs::File::open(path)?;
-    let mut store: KeychainStore = serde_json::from_reader(file)?;
-
-    // 1. Decrypt Master Key using Recovery Code (Slot 2).
-    let rec_kek = derive_kek(
-        recovery_code,
-        &store.recovery_salt,
-        store.kdf_memory,
-        store.kdf_iterations,
-        store.kdf_parallelism,
-    )?;
-    let cipher_rec =
-        Aes256Gcm::new_from_slice(&*rec_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
-    let nonce_rec = Nonce::from_slice(&store.recovery_nonce);
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store.policy)
+}
+
+/// Format of the vault's current recovery code. Readable while locked (it is shown on
+/// the recovery screen); a missing keychain reports the default.
+pub fn load_recovery_format(path: &Path) -> Result<RecoveryCodeFormat> {
+    if !path.exists() {
+        return Ok(RecoveryCodeFormat::default());
+    }
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store.recovery_format)
+}
 
-    // Securely hold the decrypted master key
+/// Replaces the vault policy. Requires the current password (an unlocked session alone
+/// is not enough), and refuses a minimum length the current password does not meet.
+pub fn set_policy(path: &Path, password: &str, policy: VaultPolicy) -> Result<()> {
+    policy.validate()?;
+    unlock_keychain(path, password)?;
+    policy
+        .check_password(password)
+        .context("Change your password before raising the minimum length")?;
+
+    modify_keychain(path, |store| {
+        store.policy = policy;
+        Ok(())
+    })
+}
+
+/// Adds, replaces or (with `guest_password: None`) removes the read-only guest slot.
+/// Requires the owner password; the guest password must differ from it.
+pub fn set_guest_slot(
+    path: &Path,
+    owner_password: &str,
+    guest_password: Option<&str>,
+) -> Result<()> {
+    let master_key = unlock_keychain(path, owner_password)?;
+
+    modify_keychain(path, |store| {
+        store.guest_slot = match guest_password {
+            None => None,
+            Some(guest) => {
+                if guest == owner_password {
+                    return Err(anyhow!(
+                        "The guest password must differ from the vault password."
+                    ));
+                }
+                store.policy.check_password(guest)?;
+
+                let kdf = store.new_slot_kdf();
+                let (salt, nonce, encrypted_master_key) = wrap_master_key(guest, &master_key, kdf)?;
+                Some(GuestSlot {
+                    salt,
+                    nonce,
+                    encrypted_master_key,
+                    kdf: Some(kdf),
+                })
+            }
+        };
+
+        Ok(())
+    })
+}
+
+/// Unlocks the vault through the guest slot (Slot 3).
+/// The caller is responsible for flagging the session read-only.
+pub fn unlock_guest(path: &Path, guest_password: &str) -> Result<MasterKey> {
+    if !path.exists() {
+        return Err(anyhow!("No keychain found. Please initialize first."));
+    }
+
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    let slot = store
+        .guest_slot
+        .as_ref()
+        .ok_or_else(|| anyhow!("Guest access is not enabled for this vault."))?;
+
+    let kek = derive_kek(
+        guest_password,
+        &slot.salt,
+        slot.kdf.unwrap_or_else(|| store.base_kdf()),
+    )?;
+    let cipher = Aes256Gcm::new_from_slice(&*kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
     let mk_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
-        cipher_rec
-            .decrypt(nonce_rec, store.encrypted_master_key_recovery.as_ref())
-            .map_err(|_| anyhow!("Invalid Recovery Code"))?,
+        cipher
+            .decrypt(
+                Nonce::from_slice(&slot.nonce),
+                slot.encrypted_master_key.as_ref(),
+            )
+            .map_err(|_| anyhow!("Incorrect Password"))?,
     );
 
     if mk_bytes.len() != 32 {
@@ -335,137 +953,847 @@ pub fn recover_with_code(
 
     let mut arr = [0u8; 32];
     arr.copy_from_slice(&mk_bytes);
-    let master_key = MasterKey(arr);
-    // `mk_bytes` drops and zeroizes here.
-
-    // 2. Re-encrypt the extracted Master Key with the NEW Password (Slot 1).
-    let new_pass_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
-    let new_pass_kek = derive_kek(
-        new_password,
-        &new_pass_salt,
-        store.kdf_memory,
-        store.kdf_iterations,
-        store.kdf_parallelism,
-    )?;
-    let cipher_pass =
-        Aes256Gcm::new_from_slice(&*new_pass_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    Ok(MasterKey(arr))
+}
 
-    let mut new_pass_nonce_bytes = [0u8; NONCE_LEN];
-    // FIX F-07: Propagate RNG errors.
+/// Wraps the master key under a fresh salt/nonce for a password-derived slot.
+/// Returns (salt, nonce, encrypted master key).
+fn wrap_master_key(
+    password: &str,
+    master_key: &MasterKey,
+    kdf: KdfParams,
+) -> Result<(String, Vec<u8>, Vec<u8>)> {
+    let salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
+    let kek = derive_kek(password, &salt, kdf)?;
+    let cipher = Aes256Gcm::new_from_slice(&*kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let mut nonce_bytes = [0u8; NONCE_LEN];
     OsRng
-        .try_fill_bytes(&mut new_pass_nonce_bytes)
+        .try_fill_bytes(&mut nonce_bytes)
         .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    let encrypted_master_key = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), master_key.0.as_ref())
+        .map_err(|_| anyhow!("Failed to encrypt key slot"))?;
+    Ok((salt, nonce_bytes.to_vec(), encrypted_master_key))
+}
 
-    let new_enc_mk_pass = cipher_pass
-        .encrypt(
-            Nonce::from_slice(&new_pass_nonce_bytes),
-            master_key.0.as_ref(),
-        )
-        .map_err(|e| anyhow!("Failed to encrypt with new password: {}", e))?;
-
-    // 3. Update the JSON Store and save it atomically.
-    // NOTE: Because we are only updating the keychain, the terabytes of files the user encrypted
-    // over the years do NOT need to be re-encrypted! The Master Key never changed.
-    store.password_salt = new_pass_salt;
-    store.password_nonce = new_pass_nonce_bytes.to_vec();
-    store.encrypted_master_key_pass = new_enc_mk_pass;
-
-    // FIX F-02: Use atomic_write_keychain to prevent data loss on crash during write.
-    atomic_write_keychain(path, &store)?;
+fn validate_user_name(name: &str) -> Result<()> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.');
+    if name.trim().is_empty() || name.chars().count() > 32 || !valid_chars || name.trim() != name {
+        return Err(anyhow!(
+            "User names must be 1-32 letters, digits, spaces, '.', '-' or '_'."
+        ));
+    }
+    if name.eq_ignore_ascii_case(OWNER_SLOT_NAME) || name.eq_ignore_ascii_case(GUEST_SLOT_NAME) {
+        return Err(anyhow!("'{}' is a reserved name.", name));
+    }
+    Ok(())
+}
 
-    Ok(master_key)
+/// Wraps the master key for a new team member. The caller must hold the unlocked
+/// master key, i.e. be logged in (the command layer additionally requires the owner).
+pub fn add_user_slot(
+    path: &Path,
+    master_key: &MasterKey,
+    name: &str,
+    password: &str,
+) -> Result<()> {
+    validate_user_name(name)?;
+
+    modify_keychain(path, |store| {
+        if store
+            .user_slots
+            .iter()
+            .any(|s| s.name.eq_ignore_ascii_case(name))
+        {
+            return Err(anyhow!("A user named '{}' already exists.", name));
+        }
+        if store.user_slots.len() >= MAX_USER_SLOTS {
+            return Err(anyhow!(
+                "A vault can have at most {} users.",
+                MAX_USER_SLOTS
+            ));
+        }
+        store.policy.check_password(password)?;
+
+        let kdf = store.new_slot_kdf();
+        let (salt, nonce, encrypted_master_key) = wrap_master_key(password, master_key, kdf)?;
+        store.user_slots.push(UserSlot {
+            name: name.to_string(),
+            salt,
+            nonce,
+            encrypted_master_key,
+            created_at: chrono::Utc::now().timestamp(),
+            kdf: Some(kdf),
+        });
+        Ok(())
+    })
 }
 
-/// Generates a new Recovery Code and updates Slot 2.
-/// Useful if the user suspects someone found the piece of paper where they wrote down their recovery code.
-pub fn reset_recovery_code(path: &Path, master_key: &MasterKey) -> Result<String> {
+/// Revokes a team member's password.
+///
+/// NOTE: This stops future logins with that password. It cannot un-share the master key
+/// itself: someone who kept an old copy of keychain.json (or the files) still holds it.
+pub fn remove_user_slot(path: &Path, name: &str) -> Result<()> {
+    modify_keychain(path, |store| {
+        let before = store.user_slots.len();
+        store
+            .user_slots
+            .retain(|s| !s.name.eq_ignore_ascii_case(name));
+        if store.user_slots.len() == before {
+            return Err(anyhow!("No user named '{}'.", name));
+        }
+        Ok(())
+    })
+}
+
+/// Lets a team member change their own slot password (the caller has verified the old one).
+pub fn change_user_slot_password(
+    path: &Path,
+    name: &str,
+    master_key: &MasterKey,
+    new_password: &str,
+) -> Result<()> {
+    modify_keychain(path, |store| {
+        store.policy.check_password(new_password)?;
+        let kdf = store.new_slot_kdf();
+        let (salt, nonce, encrypted_master_key) = wrap_master_key(new_password, master_key, kdf)?;
+
+        let slot = store
+            .user_slots
+            .iter_mut()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("No user named '{}'.", name))?;
+        slot.salt = salt;
+        slot.nonce = nonce;
+        slot.encrypted_master_key = encrypted_master_key;
+        slot.kdf = Some(kdf);
+        Ok(())
+    })
+}
+
+pub fn list_user_slots(path: &Path) -> Result<Vec<UserSlotInfo>> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store
+        .user_slots
+        .iter()
+        .map(|s| UserSlotInfo {
+            name: s.name.clone(),
+            created_at: s.created_at,
+        })
+        .collect())
+}
+
+/// Unlocks the vault through a named user slot. Returns the canonical slot name
+/// (as stored) together with the master key, for session and audit attribution.
+pub fn unlock_as_user(path: &Path, name: &str, password: &str) -> Result<(String, MasterKey)> {
+    if !path.exists() {
+        return Err(anyhow!("No keychain found. Please initialize first."));
+    }
     let file = fs::File::open(path)?;
-    let mut store: KeychainStore = serde_json::from_reader(file)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
 
-    // 1. Generate NEW recovery code.
-    // FIX F-05 + F-07: generate_recovery_code() produces 128-bit codes and propagates RNG errors.
-    let recovery_code = generate_recovery_code()?;
+    // Unknown user and wrong password produce the same error, so names cannot be probed.
+    let slot = store
+        .user_slots
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("Incorrect Password"))?;
 
-    // 2. Derive new KEK and Encrypt the active Master Key with the new code.
-    let rec_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
-    let rec_kek = derive_kek(
-        &recovery_code,
-        &rec_salt,
-        store.kdf_memory,
-        store.kdf_iterations,
-        store.kdf_parallelism,
+    let kek = derive_kek(
+        password,
+        &slot.salt,
+        slot.kdf.unwrap_or_else(|| store.base_kdf()),
     )?;
-    let cipher_rec =
-        Aes256Gcm::new_from_slice(&*rec_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let cipher = Aes256Gcm::new_from_slice(&*kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let mk_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&slot.nonce),
+                slot.encrypted_master_key.as_ref(),
+            )
+            .map_err(|_| anyhow!("Incorrect Password"))?,
+    );
+    if mk_bytes.len() != 32 {
+        return Err(anyhow!("Keychain is corrupt: invalid master key length"));
+    }
 
-    let mut rec_nonce_bytes = [0u8; NONCE_LEN];
-    // FIX F-07: Propagate RNG errors.
-    OsRng
-        .try_fill_bytes(&mut rec_nonce_bytes)
-        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
-    let rec_nonce = Nonce::from_slice(&rec_nonce_bytes);
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&mk_bytes);
+    Ok((slot.name.clone(), MasterKey(arr)))
+}
 
-    let enc_mk_rec = cipher_rec
-        .encrypt(rec_nonce, master_key.0.as_ref())
-        .map_err(|_| anyhow!("Failed to encrypt recovery slot"))?;
+/// The vault's UUID, which pairing offers and unlock challenges name so a phone paired
+/// with several desktops knows which one is asking.
+pub fn keychain_vault_uuid(path: &Path) -> Result<String> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store.vault_id)
+}
 
-    // 3. Update Store and save atomically.
-    // FIX F-02: Use atomic_write_keychain to prevent data loss on crash during write.
-    store.recovery_salt = rec_salt;
-    store.recovery_nonce = rec_nonce_bytes.to_vec();
-    store.encrypted_master_key_recovery = enc_mk_rec;
+/// Wraps the master key for a paired phone. `slot_key` and `auth_key` come from the
+/// pairing secret (see device_pairing.rs); the secret itself is never stored here.
+pub fn add_device_slot(
+    path: &Path,
+    master_key: &MasterKey,
+    name: &str,
+    slot_key: &[u8; 32],
+    auth_key: &[u8; 32],
+) -> Result<DeviceSlotInfo> {
+    validate_user_name(name)?;
+    modify_keychain(path, |store| {
+        if store.device_slots.len() >= MAX_DEVICE_SLOTS {
+            return Err(anyhow!(
+                "A vault can have at most {} paired devices.",
+                MAX_DEVICE_SLOTS
+            ));
+        }
+
+        let cipher =
+            Aes256Gcm::new_from_slice(slot_key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng
+            .try_fill_bytes(&mut nonce_bytes)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        let encrypted_master_key = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), master_key.0.as_ref())
+            .map_err(|_| anyhow!("Failed to encrypt device slot"))?;
+
+        let slot = DeviceSlot {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            auth_key: auth_key.to_vec(),
+            nonce: nonce_bytes.to_vec(),
+            encrypted_master_key,
+            created_at: chrono::Utc::now().timestamp(),
+            last_used: None,
+        };
+        let info = DeviceSlotInfo {
+            id: slot.id.clone(),
+            name: slot.name.clone(),
+            created_at: slot.created_at,
+            last_used: None,
+        };
+        store.device_slots.push(slot);
+        Ok(info)
+    })
+}
 
-    atomic_write_keychain(path, &store)?;
+/// Unpairs a phone. Like `remove_user_slot`, this cannot take back a master key the
+/// phone's approvals have already released.
+pub fn remove_device_slot(path: &Path, id: &str) -> Result<()> {
+    modify_keychain(path, |store| {
+        let before = store.device_slots.len();
+        store.device_slots.retain(|s| s.id != id);
+        if store.device_slots.len() == before {
+            return Err(anyhow!("No paired device with that id."));
+        }
+        Ok(())
+    })
+}
 
-    Ok(recovery_code)
+pub fn list_device_slots(path: &Path) -> Result<Vec<DeviceSlotInfo>> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store
+        .device_slots
+        .iter()
+        .map(|s| DeviceSlotInfo {
+            id: s.id.clone(),
+            name: s.name.clone(),
+            created_at: s.created_at,
+            last_used: s.last_used,
+        })
+        .collect())
 }
 
-/// Changes the main User Password (Slot 1) while the user is already logged in.
-/// This functions exactly like Step 2 of the `recover_with_code` function.
-pub fn change_password(path: &Path, master_key: &MasterKey, new_password: &str) -> Result<()> {
+/// The `auth_key` of a device slot, needed to check an approval before anything is
+/// unwrapped. Unknown ids get the same error as a bad approval.
+pub fn device_auth_key(path: &Path, id: &str) -> Result<Vec<u8>> {
     let file = fs::File::open(path)?;
-    let mut store: KeychainStore = serde_json::from_reader(file)?;
-
-    // 1. Generate new Salt
-    let new_pass_salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
-
-    // 2. Derive new Key Encryption Key (KEK) using the new password.
-    let new_pass_kek = derive_kek(
-        new_password,
-        &new_pass_salt,
-        store.kdf_memory,
-        store.kdf_iterations,
-        store.kdf_parallelism,
-    )?;
-    let cipher_pass =
-        Aes256Gcm::new_from_slice(&*new_pass_kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    store
+        .device_slots
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| s.auth_key.clone())
+        .ok_or_else(|| anyhow!("The approval was not accepted."))
+}
 
-    // 3. Encrypt the existing active Master Key with the new KEK
-    let mut new_pass_nonce_bytes = [0u8; NONCE_LEN];
-    // FIX F-07: Propagate RNG errors.
-    OsRng
-        .try_fill_bytes(&mut new_pass_nonce_bytes)
-        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+/// Unlocks the vault through a device slot with the slot key a phone's approval carried.
+/// Records the time of use. Returns the device name together with the master key.
+pub fn unlock_device_slot(
+    path: &Path,
+    id: &str,
+    slot_key: &[u8; 32],
+) -> Result<(String, MasterKey)> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    let slot = store
+        .device_slots
+        .iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| anyhow!("The approval was not accepted."))?;
 
-    let new_enc_mk_pass = cipher_pass
-        .encrypt(
-            Nonce::from_slice(&new_pass_nonce_bytes),
-            master_key.0.as_ref(),
-        )
-        .map_err(|e| anyhow!("Failed to encrypt with new password: {}", e))?;
+    let cipher = Aes256Gcm::new_from_slice(slot_key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let mk_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&slot.nonce),
+                slot.encrypted_master_key.as_ref(),
+            )
+            .map_err(|_| anyhow!("The approval was not accepted."))?,
+    );
+    if mk_bytes.len() != 32 {
+        return Err(anyhow!("Keychain is corrupt: invalid master key length"));
+    }
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&mk_bytes);
 
-    // 4. Update JSON Store
-    store.password_salt = new_pass_salt;
-    store.password_nonce = new_pass_nonce_bytes.to_vec();
-    store.encrypted_master_key_pass = new_enc_mk_pass;
+    // The timestamp is informational: failing to save it must not block the unlock.
+    let _ = modify_keychain(path, |store| {
+        if let Some(slot) = store.device_slots.iter_mut().find(|s| s.id == id) {
+            slot.last_used = Some(chrono::Utc::now().timestamp());
+        }
+        Ok(())
+    });
+    Ok((slot.name.clone(), MasterKey(arr)))
+}
 
-    // 5. Save to Disk atomically.
-    // FIX F-02: Use atomic_write_keychain to prevent data loss on crash during write.
-    atomic_write_keychain(path, &store)?;
+// ==========================================
+// --- OS Keystore Slot (Auto-Unlock) ---
+// ==========================================
+
+/// Adds or replaces the auto-unlock slot, wrapping the master key with `secret` (which
+/// the caller files in the platform keystore under `keystore_account`). Requires the
+/// owner password. Returns the account of the slot it replaced, so the caller can
+/// delete that keystore entry.
+pub fn set_keystore_slot(
+    path: &Path,
+    owner_password: &str,
+    keystore_account: &str,
+    secret: &[u8; 32],
+) -> Result<Option<String>> {
+    let master_key = unlock_keychain(path, owner_password)?;
+
+    modify_keychain(path, |store| {
+        let cipher =
+            Aes256Gcm::new_from_slice(secret).map_err(|e| anyhow!("Cipher init: {}", e))?;
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng
+            .try_fill_bytes(&mut nonce_bytes)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        let encrypted_master_key = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), master_key.0.as_ref())
+            .map_err(|_| anyhow!("Failed to encrypt keystore slot"))?;
+
+        let previous = store.keystore_slot.replace(KeystoreSlot {
+            keystore_account: keystore_account.to_string(),
+            nonce: nonce_bytes.to_vec(),
+            encrypted_master_key,
+            created_at: chrono::Utc::now().timestamp(),
+            last_used: None,
+        });
+        Ok(previous.map(|slot| slot.keystore_account))
+    })
+}
+
+/// Removes the auto-unlock slot. Returns its keystore account, or `None` if auto-unlock
+/// was not enabled.
+pub fn remove_keystore_slot(path: &Path) -> Result<Option<String>> {
+    modify_keychain(path, |store| {
+        let Some(slot) = store.keystore_slot.take() else {
+            return Ok(None);
+        };
+        Ok(Some(slot.keystore_account))
+    })
+}
 
+/// The keystore account of the auto-unlock slot, if there is one.
+pub fn keystore_slot_account(path: &Path) -> Result<Option<String>> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store.keystore_slot.map(|s| s.keystore_account))
+}
+
+pub fn keystore_slot_info(path: &Path) -> Result<Option<KeystoreSlotInfo>> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store.keystore_slot.map(|s| KeystoreSlotInfo {
+        created_at: s.created_at,
+        last_used: s.last_used,
+    }))
+}
+
+/// Unlocks the vault through the auto-unlock slot with the secret read back from the
+/// platform keystore. Records the time of use.
+pub fn unlock_keystore_slot(path: &Path, secret: &[u8; 32]) -> Result<MasterKey> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    let slot = store
+        .keystore_slot
+        .as_ref()
+        .ok_or_else(|| anyhow!("Auto-unlock is not enabled for this vault."))?;
+
+    let cipher = Aes256Gcm::new_from_slice(secret).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let mk_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&slot.nonce),
+                slot.encrypted_master_key.as_ref(),
+            )
+            .map_err(|_| anyhow!("The keystore entry does not match this vault."))?,
+    );
+    if mk_bytes.len() != 32 {
+        return Err(anyhow!("Keychain is corrupt: invalid master key length"));
+    }
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&mk_bytes);
+
+    // The timestamp is informational: failing to save it must not block the unlock.
+    let _ = modify_keychain(path, |store| {
+        let account = &slot.keystore_account;
+        if let Some(slot) = store
+            .keystore_slot
+            .as_mut()
+            .filter(|s| &s.keystore_account == account)
+        {
+            slot.last_used = Some(chrono::Utc::now().timestamp());
+        }
+        Ok(())
+    });
+    Ok(MasterKey(arr))
+}
+
+// ==========================================
+// --- Duress Slot ---
+// ==========================================
+// A second password for someone forced to open the vault. Typed into the normal login
+// form, it unwraps the decoy vault's key instead of the Master Key, so whoever is
+// watching gets a working vault that holds none of the real data. Where the decoy files
+// live, and the optional wipe of the real ones, is handled by duress.rs.
+
+fn unwrap_duress_key(
+    store: &KeychainStore,
+    slot: &DuressSlot,
+    password: &str,
+) -> Result<MasterKey> {
+    let kek = derive_kek(
+        password,
+        &slot.salt,
+        slot.kdf.unwrap_or_else(|| store.base_kdf()),
+    )?;
+    let cipher = Aes256Gcm::new_from_slice(&*kek).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let key_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&slot.nonce),
+                slot.encrypted_decoy_key.as_ref(),
+            )
+            .map_err(|_| anyhow!("Incorrect Password"))?,
+    );
+    if key_bytes.len() != 32 {
+        return Err(anyhow!("Keychain is corrupt: invalid master key length"));
+    }
+
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&key_bytes);
+    Ok(MasterKey(arr))
+}
+
+/// Adds, replaces or (with `duress: None`) removes the duress slot. `duress` is the
+/// duress password and whether using it wipes the real vault. Requires the owner
+/// password, which the duress password must differ from.
+///
+/// Re-entering the current duress password keeps its decoy vault (e.g. to toggle the
+/// wipe); a new password starts a new, empty one. Returns the new decoy vault's key in
+/// that case, for `init_decoy_keychain`.
+pub fn set_duress_slot(
+    path: &Path,
+    owner_password: &str,
+    duress: Option<(&str, bool)>,
+) -> Result<Option<MasterKey>> {
+    unlock_keychain(path, owner_password)?;
+
+    modify_keychain(path, |store| {
+        let Some((password, wipe)) = duress else {
+            store.duress_slot = None;
+            return Ok(None);
+        };
+        if password == owner_password {
+            return Err(anyhow!(
+                "The duress password must differ from the vault password."
+            ));
+        }
+        store.policy.check_password(password)?;
+
+        let current = store
+            .duress_slot
+            .as_ref()
+            .and_then(|slot| unwrap_duress_key(store, slot, password).ok());
+        let (decoy_key, is_new) = match current {
+            Some(key) => (key, false),
+            None => (random_master_key()?, true),
+        };
+
+        let kdf = store.new_slot_kdf();
+        let (salt, nonce, encrypted_decoy_key) = wrap_master_key(password, &decoy_key, kdf)?;
+        store.duress_slot = Some(DuressSlot {
+            salt,
+            nonce,
+            encrypted_decoy_key,
+            wipe,
+            created_at: chrono::Utc::now().timestamp(),
+            kdf: Some(kdf),
+        });
+        Ok(is_new.then_some(decoy_key))
+    })
+}
+
+/// Creates the decoy vault's own keychain: `password` unlocks `decoy_key` there, as it
+/// does through the duress slot. Its recovery code is discarded.
+pub fn init_decoy_keychain(path: &Path, password: &str, decoy_key: &MasterKey) -> Result<()> {
+    if path.exists() {
+        return Err(anyhow!("Keychain already exists."));
+    }
+    let kdf = KdfParams {
+        memory: default_kdf_memory(),
+        iterations: default_kdf_iterations(),
+        parallelism: default_kdf_parallelism(),
+    };
+    write_new_keychain(
+        path,
+        password,
+        RecoveryCodeFormat::default(),
+        kdf,
+        decoy_key,
+    )?;
     Ok(())
 }
 
+/// Unlocks the duress slot. Returns the decoy vault's key and whether the real vault
+/// must be wiped. Fails like a wrong password when no duress slot is set.
+pub fn unlock_duress(path: &Path, password: &str) -> Result<(MasterKey, bool)> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    let slot = store
+        .duress_slot
+        .as_ref()
+        .ok_or_else(|| anyhow!("Incorrect Password"))?;
+    let decoy_key = unwrap_duress_key(&store, slot, password)?;
+    Ok((decoy_key, slot.wipe))
+}
+
+/// Re-wraps the decoy key under a new duress password, after the password was changed
+/// from inside the decoy vault. The wipe setting is kept.
+pub fn rewrap_duress_slot(path: &Path, decoy_key: &MasterKey, new_password: &str) -> Result<()> {
+    modify_keychain(path, |store| {
+        let kdf = store.new_slot_kdf();
+        let (salt, nonce, encrypted_decoy_key) = wrap_master_key(new_password, decoy_key, kdf)?;
+        let slot = store
+            .duress_slot
+            .as_mut()
+            .ok_or_else(|| anyhow!("No duress password is set."))?;
+        slot.salt = salt;
+        slot.nonce = nonce;
+        slot.encrypted_decoy_key = encrypted_decoy_key;
+        slot.kdf = Some(kdf);
+        Ok(())
+    })
+}
+
+pub fn duress_slot_info(path: &Path) -> Result<Option<DuressSlotInfo>> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(store.duress_slot.map(|slot| DuressSlotInfo {
+        wipe: slot.wipe,
+        created_at: slot.created_at,
+    }))
+}
+
+// ==========================================
+// --- Public-Key Identity ---
+// ==========================================
+
+/// Key sealing the identity secret. Derived from, never equal to, the Master Key.
+fn identity_key(master_key: &MasterKey) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_IDENTITY_V1");
+    hasher.update(master_key.0);
+    Zeroizing::new(hasher.finalize().into())
+}
+
+/// The stored identity's secret bytes, or `None` if the vault has not created one yet.
+pub fn load_identity(
+    path: &Path,
+    master_key: &MasterKey,
+) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    let Some(identity) = store.identity else {
+        return Ok(None);
+    };
+    if identity.nonce.len() != NONCE_LEN {
+        return Err(anyhow!("Keychain is corrupt: invalid identity nonce"));
+    }
+    let key = identity_key(master_key);
+    let cipher =
+        Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let secret = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&identity.nonce),
+                Payload {
+                    msg: &identity.encrypted_secret,
+                    aad: &identity.public,
+                },
+            )
+            .map_err(|_| anyhow!("Keychain is corrupt: the identity key does not open"))?,
+    );
+    Ok(Some(secret))
+}
+
+/// Stores a freshly generated identity. Refuses to replace an existing one: files already
+/// encrypted to it could no longer be opened.
+pub fn set_identity(
+    path: &Path,
+    master_key: &MasterKey,
+    public: &[u8],
+    secret: &[u8],
+) -> Result<()> {
+    modify_keychain(path, |store| {
+        if store.identity.is_some() {
+            return Err(anyhow!("This vault already has an identity."));
+        }
+
+        let key = identity_key(master_key);
+        let cipher =
+            Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!("Cipher init: {}", e))?;
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng
+            .try_fill_bytes(&mut nonce_bytes)
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        let encrypted_secret = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: secret,
+                    aad: public,
+                },
+            )
+            .map_err(|_| anyhow!("Failed to encrypt identity"))?;
+
+        store.identity = Some(IdentityKeys {
+            public: public.to_vec(),
+            nonce: nonce_bytes.to_vec(),
+            encrypted_secret,
+            created_at: chrono::Utc::now().timestamp(),
+        });
+        Ok(())
+    })
+}
+
+// ==========================================
+// --- KDF Tuning ---
+// ==========================================
+// The defaults above are one guess for every device. `benchmark_kdf` measures this one
+// and picks Argon2id parameters that take about `KDF_TARGET` per unlock: the memory
+// first (doubled while one pass stays well inside the target, halved while it does
+// not), then as many passes as fit. The result is stored as the keychain's `kdf_target`.
+//
+// Slots cannot all be re-wrapped at once, since that needs each slot's secret. Every
+// password-derived slot records the parameters it was wrapped with and moves to the
+// target when it is next opened (`upgrade_slot_kdf` after a login, `recover_with_code`
+// for the recovery slot) or rewritten. Device and keystore slots use random keys, no KDF.
+
+/// How long one unlock should take on the measured device.
+pub const KDF_TARGET: Duration = Duration::from_millis(500);
+/// OWASP's Argon2id minimum (19 MiB, 2 passes). Tuning never goes below it.
+const MIN_KDF_MEMORY: u32 = 19 * 1024;
+const MIN_KDF_ITERATIONS: u32 = 2;
+/// A keychain may be synced to a phone, so the memory stays within what one can spare.
+const MAX_KDF_MEMORY: u32 = 256 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 16;
+const MAX_KDF_PARALLELISM: u32 = 4;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct KdfStatus {
+    /// The store-level parameters, used by slots written before tuning.
+    pub base: KdfParams,
+    pub target: Option<KdfParams>,
+    /// Slots still wrapped with other parameters than `target`: "owner", "recovery",
+    /// "guest" or a user name.
+    pub pending_slots: Vec<String>,
+}
+
+/// The parameter search. `measure` times one derivation; it is a parameter so the search
+/// can be tested against a model device.
+fn tune_kdf(
+    target: Duration,
+    parallelism: u32,
+    mut measure: impl FnMut(KdfParams) -> Result<Duration>,
+) -> Result<KdfParams> {
+    let mut kdf = KdfParams {
+        memory: default_kdf_memory(),
+        iterations: 1,
+        parallelism,
+    };
+    let mut pass = measure(kdf)?;
+    while pass * 4 <= target && kdf.memory < MAX_KDF_MEMORY {
+        kdf.memory = (kdf.memory * 2).min(MAX_KDF_MEMORY);
+        pass = measure(kdf)?;
+    }
+    // At least two passes must fit.
+    while pass > target / 2 && kdf.memory > MIN_KDF_MEMORY {
+        kdf.memory = (kdf.memory / 2).max(MIN_KDF_MEMORY);
+        pass = measure(kdf)?;
+    }
+    let passes = (target.as_secs_f64() / pass.as_secs_f64().max(0.001)) as u32;
+    kdf.iterations = passes.clamp(MIN_KDF_ITERATIONS, MAX_KDF_ITERATIONS);
+    Ok(kdf)
+}
+
+/// Measures this device and returns the parameters for `target`. Runs Argon2 several
+/// times (a few seconds in all), so call it off the UI thread.
+pub fn benchmark_kdf(target: Duration) -> Result<KdfParams> {
+    let parallelism = std::thread::available_parallelism()
+        .map_or(1, |n| n.get() as u32)
+        .min(MAX_KDF_PARALLELISM);
+    let salt = SaltString::generate(&mut Argon2OsRng);
+    tune_kdf(target, parallelism, |kdf| {
+        let start = Instant::now();
+        derive_kek("benchmark", salt.as_str(), kdf)?;
+        Ok(start.elapsed())
+    })
+}
+
+fn kdf_status_of(store: &KeychainStore) -> KdfStatus {
+    let mut pending = Vec::new();
+    if store.is_stale(store.password_kdf()) {
+        pending.push(OWNER_SLOT_NAME.to_string());
+    }
+    if store.is_stale(store.recovery_kdf()) {
+        pending.push("recovery".to_string());
+    }
+    if let Some(guest) = &store.guest_slot {
+        if store.is_stale(guest.kdf.unwrap_or_else(|| store.base_kdf())) {
+            pending.push(GUEST_SLOT_NAME.to_string());
+        }
+    }
+    for user in &store.user_slots {
+        if store.is_stale(user.kdf.unwrap_or_else(|| store.base_kdf())) {
+            pending.push(user.name.clone());
+        }
+    }
+    KdfStatus {
+        base: store.base_kdf(),
+        target: store.kdf_target,
+        pending_slots: pending,
+    }
+}
+
+pub fn kdf_status(path: &Path) -> Result<KdfStatus> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    Ok(kdf_status_of(&store))
+}
+
+/// Stores `kdf` as the parameters slots move to. Parameters cheaper than the owner slot's
+/// current ones are refused unless `allow_weaker` (an old phone may need that).
+pub fn set_kdf_target(path: &Path, kdf: KdfParams, allow_weaker: bool) -> Result<KdfStatus> {
+    if !(MIN_KDF_MEMORY..=MAX_KDF_MEMORY).contains(&kdf.memory)
+        || !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&kdf.iterations)
+        || !(1..=MAX_KDF_PARALLELISM).contains(&kdf.parallelism)
+    {
+        return Err(anyhow!("KDF parameters are out of range."));
+    }
+    modify_keychain(path, |store| {
+        let current = store.password_kdf();
+        if !allow_weaker && kdf.cost() < current.cost() {
+            return Err(anyhow!(
+            "The measured parameters are weaker than the vault's current ones ({} MiB, {} passes).",
+            current.memory / 1024,
+            current.iterations
+        ));
+        }
+        store.kdf_target = Some(kdf);
+        Ok(kdf_status_of(store))
+    })
+}
+
+/// The target parameters `slot` should move to, or `None` if it is already on them (or
+/// there is no target, or no such slot).
+fn pending_slot_kdf(store: &KeychainStore, slot: &str) -> Option<KdfParams> {
+    let base = store.base_kdf();
+    let used = if slot == OWNER_SLOT_NAME {
+        Some(store.password_kdf())
+    } else if slot == GUEST_SLOT_NAME {
+        store.guest_slot.as_ref().map(|g| g.kdf.unwrap_or(base))
+    } else {
+        store
+            .user_slots
+            .iter()
+            .find(|u| u.name.eq_ignore_ascii_case(slot))
+            .map(|u| u.kdf.unwrap_or(base))
+    };
+    store
+        .kdf_target
+        .filter(|_| used.is_some_and(|used| store.is_stale(used)))
+}
+
+/// Re-wraps a slot the caller has just opened with the tuned parameters, if it still uses
+/// others. `slot` is "owner", "guest" or a user name, and `password` the secret that
+/// opened it. Returns whether the slot was rewritten.
+///
+/// Runs after every login, so the common case (nothing to do) is decided without the
+/// lock; the check is repeated under it before anything is re-wrapped.
+pub fn upgrade_slot_kdf(
+    path: &Path,
+    slot: &str,
+    password: &str,
+    master_key: &MasterKey,
+) -> Result<bool> {
+    let file = fs::File::open(path)?;
+    let store: KeychainStore = serde_json::from_reader(file).context("Corrupted keychain file")?;
+    if pending_slot_kdf(&store, slot).is_none() {
+        return Ok(false);
+    }
+
+    modify_keychain(path, |store| {
+        let Some(kdf) = pending_slot_kdf(store, slot) else {
+            return Ok(false);
+        };
+        let (salt, nonce, encrypted) = wrap_master_key(password, master_key, kdf)?;
+        if slot == OWNER_SLOT_NAME {
+            store.password_salt = salt;
+            store.password_nonce = nonce;
+            store.encrypted_master_key_pass = encrypted;
+            store.password_kdf = Some(kdf);
+        } else if let Some(guest) = store
+            .guest_slot
+            .as_mut()
+            .filter(|_| slot == GUEST_SLOT_NAME)
+        {
+            guest.salt = salt;
+            guest.nonce = nonce;
+            guest.encrypted_master_key = encrypted;
+            guest.kdf = Some(kdf);
+        } else if let Some(user) = store
+            .user_slots
+            .iter_mut()
+            .find(|u| u.name.eq_ignore_ascii_case(slot))
+        {
+            user.salt = salt;
+            user.nonce = nonce;
+            user.encrypted_master_key = encrypted;
+            user.kdf = Some(kdf);
+        }
+        Ok(true)
+    })
+}
+
 /// Simple utility check to see if a vault file exists on disk yet.
 pub fn keychain_exists(path: &Path) -> bool {
     path.exists()
@@ -495,7 +1823,7 @@ mod tests {
         let password = "MySuperSecretPassword123!";
 
         // 1. Initialize
-        let init_result = init_keychain(&path, password);
+        let init_result = init_keychain(&path, password, RecoveryCodeFormat::default());
         assert!(init_result.is_ok(), "Failed to initialize keychain");
 
         let (recovery_code, original_master_key) = init_result.unwrap();
@@ -527,7 +1855,7 @@ mod tests {
         let path = get_temp_keychain_path("test_wrong_password");
         let _ = fs::remove_file(&path);
 
-        init_keychain(&path, "CorrectPassword").unwrap();
+        init_keychain(&path, "CorrectPassword", RecoveryCodeFormat::default()).unwrap();
 
         // Attempt unlock with wrong password
         let result = unlock_keychain(&path, "WrongPassword");
@@ -547,7 +1875,8 @@ mod tests {
         let _ = fs::remove_file(&path);
 
         // 1. Init with forgotten password
-        let (recovery_code, original_mk) = init_keychain(&path, "ForgottenPassword").unwrap();
+        let (recovery_code, original_mk) =
+            init_keychain(&path, "ForgottenPassword", RecoveryCodeFormat::default()).unwrap();
 
         // 2. Recover using code and set new password
         let new_password = "NewRememberedPassword!";
@@ -573,10 +1902,11 @@ mod tests {
         let path = get_temp_keychain_path("test_reset_recovery");
         let _ = fs::remove_file(&path);
 
-        let (old_code, mk) = init_keychain(&path, "Password").unwrap();
+        let (old_code, mk) =
+            init_keychain(&path, "Password", RecoveryCodeFormat::default()).unwrap();
 
         // Generate new code
-        let new_code = reset_recovery_code(&path, &mk).expect("Failed to reset code");
+        let new_code = reset_recovery_code(&path, &mk, None).expect("Failed to reset code");
 
         assert_ne!(
             old_code, new_code,
@@ -592,12 +1922,81 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn test_word_recovery_code_roundtrip() {
+        let path = get_temp_keychain_path("test_word_recovery");
+        let _ = fs::remove_file(&path);
+
+        let (code, mk) = init_keychain(&path, "Password", RecoveryCodeFormat::Words).unwrap();
+        assert_eq!(code.split(' ').count(), RECOVERY_WORD_COUNT);
+        assert_eq!(
+            load_recovery_format(&path).unwrap(),
+            RecoveryCodeFormat::Words
+        );
+
+        // As copied from paper: capitals, line break, extra spaces.
+        let typed = format!("  {}\n", code.to_uppercase().replacen(' ', "   ", 3));
+        let recovered = recover_with_code(&path, &typed, "NewPass").unwrap();
+        assert_eq!(recovered.0, mk.0);
+
+        // Switching format on reset is recorded; `None` keeps it.
+        let hex = reset_recovery_code(&path, &mk, Some(RecoveryCodeFormat::Hex256)).unwrap();
+        assert_eq!(hex.len(), 4 + 8 * 8 + 7);
+        assert_eq!(
+            load_recovery_format(&path).unwrap(),
+            RecoveryCodeFormat::Hex256
+        );
+        reset_recovery_code(&path, &mk, None).unwrap();
+        assert_eq!(
+            load_recovery_format(&path).unwrap(),
+            RecoveryCodeFormat::Hex256
+        );
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_legacy_64bit_recovery_code_still_accepted() {
+        let path = get_temp_keychain_path("test_legacy_recovery");
+        let _ = fs::remove_file(&path);
+        let (_, mk) = init_keychain(&path, "Password", RecoveryCodeFormat::default()).unwrap();
+
+        // Rewrite Slot 2 the way old versions did: a 64-bit code and no format field.
+        let legacy_code = "QRE-1A2B-3C4D-5E6F-7A8B";
+        let mut json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let salt = json["recovery_salt"].as_str().unwrap().to_string();
+        let kdf = KdfParams {
+            memory: json["kdf_memory"].as_u64().unwrap() as u32,
+            iterations: json["kdf_iterations"].as_u64().unwrap() as u32,
+            parallelism: json["kdf_parallelism"].as_u64().unwrap() as u32,
+        };
+        let kek = derive_kek(legacy_code, &salt, kdf).unwrap();
+        let nonce: Vec<u8> = serde_json::from_value(json["recovery_nonce"].clone()).unwrap();
+        let enc = Aes256Gcm::new_from_slice(&*kek)
+            .unwrap()
+            .encrypt(Nonce::from_slice(&nonce), mk.0.as_ref())
+            .unwrap();
+        json["encrypted_master_key_recovery"] = serde_json::json!(enc);
+        json.as_object_mut().unwrap().remove("recovery_format");
+        fs::write(&path, serde_json::to_string(&json).unwrap()).unwrap();
+
+        assert_eq!(
+            load_recovery_format(&path).unwrap(),
+            RecoveryCodeFormat::Hex128
+        );
+        let recovered = recover_with_code(&path, "qre-1a2b-3c4d-5e6f-7a8b ", "NewPass").unwrap();
+        assert_eq!(recovered.0, mk.0);
+
+        let _ = fs::remove_file(path);
+    }
+
     #[test]
     fn test_change_password() {
         let path = get_temp_keychain_path("test_change_pass");
         let _ = fs::remove_file(&path);
 
-        let (_, mk) = init_keychain(&path, "OldPass").unwrap();
+        let (_, mk) = init_keychain(&path, "OldPass", RecoveryCodeFormat::default()).unwrap();
 
         // Change password
         let change_result = change_password(&path, &mk, "NewPass");
@@ -610,12 +2009,378 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn test_policy_defaults_and_password_rule() {
+        let path = get_temp_keychain_path("test_policy_password");
+        let _ = fs::remove_file(&path);
+
+        let (code, mk) =
+            init_keychain(&path, "LongEnoughPass1", RecoveryCodeFormat::default()).unwrap();
+        assert_eq!(load_policy(&path).unwrap(), VaultPolicy::default());
+
+        // Wrong password cannot change the policy.
+        let policy = VaultPolicy {
+            min_password_length: 12,
+            ..Default::default()
+        };
+        assert!(set_policy(&path, "wrong", policy.clone()).is_err());
+        set_policy(&path, "LongEnoughPass1", policy).unwrap();
+        assert_eq!(load_policy(&path).unwrap().min_password_length, 12);
+
+        // Both password-setting paths enforce it.
+        assert!(change_password(&path, &mk, "short").is_err());
+        assert!(recover_with_code(&path, &code, "short").is_err());
+        assert!(unlock_keychain(&path, "LongEnoughPass1").is_ok());
+        assert!(change_password(&path, &mk, "AnotherLongPass").is_ok());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_policy_rejects_min_length_above_current_password() {
+        let path = get_temp_keychain_path("test_policy_min_len");
+        let _ = fs::remove_file(&path);
+
+        init_keychain(&path, "Short1", RecoveryCodeFormat::default()).unwrap();
+        let policy = VaultPolicy {
+            min_password_length: 12,
+            ..Default::default()
+        };
+        assert!(set_policy(&path, "Short1", policy).is_err());
+        assert_eq!(load_policy(&path).unwrap(), VaultPolicy::default());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_policy_encryption_checks() {
+        let policy = VaultPolicy {
+            require_keyfile: true,
+            require_paranoid_entropy: true,
+            ..Default::default()
+        };
+        let entropy = [7u8; PARANOID_MIN_ENTROPY_BYTES];
+        assert!(policy.check_encryption(false, Some(&entropy)).is_err());
+        assert!(policy.check_encryption(true, None).is_err());
+        assert!(policy.check_encryption(true, Some(&entropy[..8])).is_err());
+        assert!(policy.check_encryption(true, Some(&entropy)).is_ok());
+        assert!(VaultPolicy::default().check_encryption(false, None).is_ok());
+    }
+
+    #[test]
+    fn test_guest_slot_lifecycle() {
+        let path = get_temp_keychain_path("test_guest_slot");
+        let _ = fs::remove_file(&path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        assert!(
+            unlock_guest(&path, "GuestPassword").is_err(),
+            "not enabled yet"
+        );
+
+        // Owner password is required, and the guest password must differ from it.
+        assert!(set_guest_slot(&path, "wrong", Some("GuestPassword")).is_err());
+        assert!(set_guest_slot(&path, "OwnerPassword", Some("OwnerPassword")).is_err());
+        set_guest_slot(&path, "OwnerPassword", Some("GuestPassword")).unwrap();
+
+        // The guest slot yields the same master key; the owner slot is untouched.
+        assert_eq!(unlock_guest(&path, "GuestPassword").unwrap().0, mk.0);
+        assert!(unlock_guest(&path, "OwnerPassword").is_err());
+        assert!(unlock_keychain(&path, "OwnerPassword").is_ok());
+
+        // Changing the owner password keeps guest access working.
+        change_password(&path, &mk, "NewOwnerPassword").unwrap();
+        assert!(unlock_guest(&path, "GuestPassword").is_ok());
+
+        set_guest_slot(&path, "NewOwnerPassword", None).unwrap();
+        assert!(unlock_guest(&path, "GuestPassword").is_err());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_duress_slot_opens_a_separate_decoy_key() {
+        let path = get_temp_keychain_path("test_duress_slot");
+        let decoy_path = get_temp_keychain_path("test_duress_slot_decoy");
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&decoy_path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        assert!(
+            unlock_duress(&path, "DuressPassword").is_err(),
+            "not set yet"
+        );
+        assert!(duress_slot_info(&path).unwrap().is_none());
+
+        assert!(set_duress_slot(&path, "wrong", Some(("DuressPassword", false))).is_err());
+        assert!(set_duress_slot(&path, "OwnerPassword", Some(("OwnerPassword", false))).is_err());
+        let decoy = set_duress_slot(&path, "OwnerPassword", Some(("DuressPassword", false)))
+            .unwrap()
+            .expect("a new decoy vault");
+        assert_ne!(decoy.0, mk.0, "the duress slot never wraps the real key");
+
+        let (unlocked, wipe) = unlock_duress(&path, "DuressPassword").unwrap();
+        assert_eq!(unlocked.0, decoy.0);
+        assert!(!wipe);
+        assert!(unlock_duress(&path, "OwnerPassword").is_err());
+        assert!(unlock_keychain(&path, "DuressPassword").is_err());
+
+        // The decoy vault's own keychain opens with the same password.
+        init_decoy_keychain(&decoy_path, "DuressPassword", &decoy).unwrap();
+        assert_eq!(
+            unlock_keychain(&decoy_path, "DuressPassword").unwrap().0,
+            decoy.0
+        );
+
+        // Same password: same decoy, new setting. New password: a fresh decoy.
+        let kept = set_duress_slot(&path, "OwnerPassword", Some(("DuressPassword", true))).unwrap();
+        assert!(kept.is_none());
+        assert!(duress_slot_info(&path).unwrap().unwrap().wipe);
+        assert_eq!(
+            unlock_duress(&path, "DuressPassword").unwrap().0 .0,
+            decoy.0
+        );
+
+        // A password change from inside the decoy vault keeps the slot in step.
+        rewrap_duress_slot(&path, &decoy, "OtherDuress").unwrap();
+        let (unlocked, wipe) = unlock_duress(&path, "OtherDuress").unwrap();
+        assert_eq!(unlocked.0, decoy.0);
+        assert!(wipe);
+
+        let fresh = set_duress_slot(&path, "OwnerPassword", Some(("Another1", false))).unwrap();
+        assert!(fresh.is_some_and(|key| key.0 != decoy.0));
+
+        set_duress_slot(&path, "OwnerPassword", None).unwrap();
+        assert!(unlock_duress(&path, "Another1").is_err());
+        assert!(unlock_keychain(&path, "OwnerPassword").is_ok());
+
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(decoy_path);
+    }
+
+    #[test]
+    fn test_user_slots_share_master_key_and_revoke() {
+        let path = get_temp_keychain_path("test_user_slots");
+        let _ = fs::remove_file(&path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        add_user_slot(&path, &mk, "Alice", "AlicePassword").unwrap();
+        add_user_slot(&path, &mk, "bob", "BobPassword").unwrap();
+
+        let (name, alice_mk) = unlock_as_user(&path, "alice", "AlicePassword").unwrap();
+        assert_eq!(name, "Alice", "canonical name is returned");
+        assert_eq!(alice_mk.0, mk.0);
+        assert!(unlock_as_user(&path, "bob", "AlicePassword").is_err());
+        assert!(unlock_as_user(&path, "carol", "AlicePassword").is_err());
+
+        // Revoking Alice leaves Bob and the owner untouched.
+        change_user_slot_password(&path, "bob", &mk, "BobNewPassword").unwrap();
+        assert!(unlock_as_user(&path, "bob", "BobPassword").is_err());
+        remove_user_slot(&path, "ALICE").unwrap();
+        assert!(unlock_as_user(&path, "Alice", "AlicePassword").is_err());
+        assert!(unlock_as_user(&path, "bob", "BobNewPassword").is_ok());
+        assert!(unlock_keychain(&path, "OwnerPassword").is_ok());
+        assert_eq!(list_user_slots(&path).unwrap().len(), 1);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_user_slot_name_rules() {
+        let path = get_temp_keychain_path("test_user_slot_names");
+        let _ = fs::remove_file(&path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        for bad in ["", " padded", "owner", "Guest", "a/b", &"x".repeat(33)] {
+            assert!(
+                add_user_slot(&path, &mk, bad, "Whatever1").is_err(),
+                "{:?}",
+                bad
+            );
+        }
+        add_user_slot(&path, &mk, "Team Lead", "Whatever1").unwrap();
+        assert!(add_user_slot(&path, &mk, "team lead", "Whatever2").is_err());
+        assert!(remove_user_slot(&path, "nobody").is_err());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_device_slots_unlock_and_unpair() {
+        let path = get_temp_keychain_path("test_device_slots");
+        let _ = fs::remove_file(&path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        let slot_key = [4u8; 32];
+        let device = add_device_slot(&path, &mk, "Pixel 8", &slot_key, &[5u8; 32]).unwrap();
+        assert_eq!(device_auth_key(&path, &device.id).unwrap(), vec![5u8; 32]);
+
+        let (name, device_mk) = unlock_device_slot(&path, &device.id, &slot_key).unwrap();
+        assert_eq!(name, "Pixel 8");
+        assert_eq!(device_mk.0, mk.0);
+        assert!(list_device_slots(&path).unwrap()[0].last_used.is_some());
+        assert!(unlock_device_slot(&path, &device.id, &[6u8; 32]).is_err());
+        assert!(unlock_device_slot(&path, "unknown", &slot_key).is_err());
+
+        remove_device_slot(&path, &device.id).unwrap();
+        assert!(unlock_device_slot(&path, &device.id, &slot_key).is_err());
+        assert!(unlock_keychain(&path, "OwnerPassword").is_ok());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_keystore_slot_unlock_and_remove() {
+        let path = get_temp_keychain_path("test_keystore_slot");
+        let _ = fs::remove_file(&path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        let secret = [7u8; 32];
+        assert!(set_keystore_slot(&path, "WrongPassword", "acct-1", &secret).is_err());
+        assert_eq!(
+            set_keystore_slot(&path, "OwnerPassword", "acct-1", &secret).unwrap(),
+            None
+        );
+        assert_eq!(unlock_keystore_slot(&path, &secret).unwrap().0, mk.0);
+        let info = keystore_slot_info(&path).unwrap().unwrap();
+        assert!(info.last_used.is_some());
+        assert!(unlock_keystore_slot(&path, &[8u8; 32]).is_err());
+
+        // Re-enabling hands back the old account so its keystore entry can be deleted.
+        let replaced = set_keystore_slot(&path, "OwnerPassword", "acct-2", &[9u8; 32]).unwrap();
+        assert_eq!(replaced.as_deref(), Some("acct-1"));
+        assert_eq!(
+            keystore_slot_account(&path).unwrap().as_deref(),
+            Some("acct-2")
+        );
+
+        assert_eq!(
+            remove_keystore_slot(&path).unwrap().as_deref(),
+            Some("acct-2")
+        );
+        assert_eq!(remove_keystore_slot(&path).unwrap(), None);
+        assert!(unlock_keystore_slot(&path, &[9u8; 32]).is_err());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_identity_sealed_under_master_key() {
+        let path = get_temp_keychain_path("test_identity");
+        let _ = fs::remove_file(&path);
+
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        assert!(load_identity(&path, &mk).unwrap().is_none());
+
+        set_identity(&path, &mk, b"public", b"secret").unwrap();
+        let secret = load_identity(&path, &mk).unwrap().unwrap();
+        assert_eq!(secret.as_slice(), b"secret");
+
+        assert!(set_identity(&path, &mk, b"public", b"other").is_err());
+        assert!(load_identity(&path, &MasterKey([9u8; 32])).is_err());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_tune_kdf_fits_the_target() {
+        // Model devices: a fixed time per KiB per pass.
+        let model = |micros_per_kib: f64| {
+            move |kdf: KdfParams| -> Result<Duration> {
+                let micros = f64::from(kdf.memory) * f64::from(kdf.iterations) * micros_per_kib;
+                Ok(Duration::from_micros(micros as u64))
+            }
+        };
+
+        let desktop = tune_kdf(KDF_TARGET, 4, model(1.0)).unwrap();
+        assert_eq!((desktop.memory, desktop.iterations), (128 * 1024, 3));
+        assert_eq!(desktop.parallelism, 4);
+
+        // A slow phone ends at the floor rather than below it.
+        let phone = tune_kdf(KDF_TARGET, 1, model(20.0)).unwrap();
+        assert_eq!((phone.memory, phone.iterations), (MIN_KDF_MEMORY, 2));
+
+        let fast = tune_kdf(KDF_TARGET, 4, model(0.01)).unwrap();
+        assert_eq!(fast.memory, MAX_KDF_MEMORY);
+        assert_eq!(fast.iterations, MAX_KDF_ITERATIONS);
+    }
+
+    #[test]
+    fn test_slots_move_to_the_kdf_target_when_used() {
+        let path = get_temp_keychain_path("test_kdf_target");
+        let _ = fs::remove_file(&path);
+        let (code, mk) = init_keychain(&path, "Password", RecoveryCodeFormat::default()).unwrap();
+        assert!(kdf_status(&path).unwrap().target.is_none());
+
+        let target = KdfParams {
+            memory: MIN_KDF_MEMORY,
+            iterations: MIN_KDF_ITERATIONS,
+            parallelism: 1,
+        };
+        assert!(set_kdf_target(&path, target, false).is_err());
+        let status = set_kdf_target(&path, target, true).unwrap();
+        assert_eq!(status.pending_slots, vec!["owner", "recovery"]);
+
+        // Opening with the old parameters still works; the upgrade happens once.
+        let opened = unlock_keychain(&path, "Password").unwrap();
+        assert!(upgrade_slot_kdf(&path, OWNER_SLOT_NAME, "Password", &opened).unwrap());
+        assert!(!upgrade_slot_kdf(&path, OWNER_SLOT_NAME, "Password", &opened).unwrap());
+        assert_eq!(unlock_keychain(&path, "Password").unwrap().0, mk.0);
+
+        // New slots are written with the target; recovery moves when it is used.
+        add_user_slot(&path, &mk, "alice", "AlicePass").unwrap();
+        assert_eq!(kdf_status(&path).unwrap().pending_slots, vec!["recovery"]);
+        recover_with_code(&path, &code, "NewPassword").unwrap();
+        assert!(kdf_status(&path).unwrap().pending_slots.is_empty());
+        assert_eq!(recover_with_code(&path, &code, "Again").unwrap().0, mk.0);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_interleaved_mutators_keep_both_changes() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let path = get_temp_keychain_path("test_interleaved_mutators");
+        let _ = fs::remove_file(&path);
+        let (_, mk) = init_keychain(&path, "OwnerPassword", RecoveryCodeFormat::default()).unwrap();
+        add_user_slot(&path, &mk, "alice", "AlicePassword").unwrap();
+
+        // The first mutator has read the keychain and is still working on it when the
+        // second one starts. Before the lock covered the read, the second read the same
+        // copy and the first write then brought Alice back.
+        let (read_tx, read_rx) = mpsc::channel();
+        let (go_tx, go_rx) = mpsc::channel::<()>();
+        let first_path = path.clone();
+        let first = std::thread::spawn(move || {
+            modify_keychain(&first_path, |store| {
+                read_tx.send(()).unwrap();
+                go_rx.recv().unwrap();
+                store.kdf_target = Some(store.base_kdf());
+                Ok(())
+            })
+        });
+        read_rx.recv().unwrap();
+        let second_path = path.clone();
+        let second = std::thread::spawn(move || remove_user_slot(&second_path, "alice"));
+        std::thread::sleep(Duration::from_millis(200));
+        go_tx.send(()).unwrap();
+
+        first.join().unwrap().unwrap();
+        second.join().unwrap().unwrap();
+        assert!(list_user_slots(&path).unwrap().is_empty());
+        assert!(kdf_status(&path).unwrap().target.is_some());
+
+        let _ = fs::remove_file(path);
+    }
+
     #[test]
     fn test_atomic_write_no_tmp_file_left_on_success() {
         let path = get_temp_keychain_path("test_atomic_write");
         let _ = fs::remove_file(&path);
 
-        init_keychain(&path, "TestPassword").unwrap();
+        init_keychain(&path, "TestPassword", RecoveryCodeFormat::default()).unwrap();
 
         // After a successful write, the .tmp file should be gone
         let tmp_path = path.with_extension("tmp");
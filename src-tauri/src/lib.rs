@@ -5,30 +5,110 @@
 // ==========================================
 // In Rust, explicitly declaring `mod` tells the compiler to look for these files
 // (e.g., `analyzer.rs`, `bookmarks.rs`) and compile them into the binary tree.
+mod account_deletion;
 mod analyzer;
+mod archive;
+mod audit;
+mod author_audit;
+mod auto_lock;
+mod av_guard;
+mod batch_journal;
 mod bookmarks;
 mod breach;
+mod breach_monitor;
 mod cleaner;
 mod clipboard_store;
+mod compaction;
 mod commands; // Refers to src/commands/mod.rs (which encapsulates files.rs, tools.rs, vault.rs)
+mod container_meta;
+#[cfg(not(target_os = "android"))]
+mod cookie_inspector;
 mod crypto;
 mod crypto_stream;
+mod deniable;
+mod device_pairing;
+mod diagnosis;
+mod drive_health;
+mod duress;
+mod entropy;
+mod entry_attachments;
+mod file_lock;
+mod formats;
 mod hasher;
+mod honeyfiles;
+mod i18n;
+mod kdbx;
 mod keychain;
+mod line_diff;
+mod logging;
+mod network_monitor;
+mod network_privacy;
+mod note_assets;
+mod note_images;
+mod note_query;
 mod notes;
+mod os_keystore;
+mod panel_lock;
+mod paper_backup;
+mod parity;
+mod password_audit;
 mod passwords;
+mod pattern_packs;
+mod photo_locations;
+mod power;
+mod privacy_report;
+mod profiles;
+mod progress;
 mod qr;
+mod random_names;
+mod recipient;
+mod recovery_risk;
+mod regexes;
 mod registry_cleaner;
+mod renamer;
+mod resources;
+mod salvage;
+mod search;
+mod secrets;
+mod secure_clipboard;
+mod secure_dns;
+mod self_decrypt;
+mod settings_profile;
+mod shamir;
+mod sharing;
 mod shredder;
+mod site_policies;
 mod state;
+mod subkeys;
 mod system_cleaner;
 #[cfg(test)]
 mod tests; // Only compiled when running `cargo test`
 mod timelock;
 mod timelock_clock;
+mod timelock_puzzle;
+mod timestamps;
+mod tor;
+mod totp;
+mod url_cleaner;
 mod utils;
+mod vault_export;
+mod vault_import;
+mod vault_meta;
 mod wordlist;
 
+// ==========================================
+// --- FUZZING ENTRY POINTS ---
+// ==========================================
+// The modules above are private to the app. The cargo-fuzz targets in `fuzz/` need
+// the untrusted-input parsers, so they are re-exported here behind the `fuzzing` feature.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    pub use crate::crypto::{decrypt_file_with_master_key, EncryptedFileContainer};
+    pub use crate::crypto_stream::parse_stream_header_bytes;
+    pub use crate::keychain::MasterKey;
+    pub use crate::subkeys::KeyPurpose;
+}
+
 // Conditional compilation: Global OS-level keyboard shortcuts are not supported on iOS/Android.
 #[cfg(not(mobile))]
 use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut, ShortcutState};
@@ -41,7 +121,23 @@ use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut, ShortcutState};
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     #[allow(unused_mut)]
-    let mut builder = tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+
+    // ==========================================
+    // --- SINGLE INSTANCE (DESKTOP ONLY) ---
+    // ==========================================
+    // Two instances could save the same vault files concurrently. A second launch
+    // (e.g. double-clicking a .qre file while the app is open) exits immediately and
+    // hands its arguments to the running instance instead. Registered before any other
+    // plugin so the second process does no other initialization.
+    #[cfg(not(mobile))]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            commands::files::handoff_from_second_instance(app, argv, cwd);
+        }));
+    }
+
+    builder = builder
         // --- PLUGIN INITIALIZATION ---
         // Tauri plugins provide safe, sandboxed APIs to native OS features so the frontend
         // doesn't have to use raw Node.js/OS calls (which is a major security risk in Electron).
@@ -86,15 +182,34 @@ pub fn run() {
     }
 
     builder
-        .setup(|_app| {
+        .setup(|app| {
+            // Diagnostic log (see logging.rs), first so start-up problems are recorded
+            {
+                use tauri::Manager;
+                if let Ok(dir) = app.path().app_data_dir() {
+                    logging::init(&dir);
+                }
+            }
             // Register the panic button shortcut during app initialization
             #[cfg(not(mobile))]
             {
                 let ctrl_shift_q =
                     Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyQ);
                 use tauri_plugin_global_shortcut::GlobalShortcutExt;
-                _app.global_shortcut().register(ctrl_shift_q)?;
+                app.global_shortcut().register(ctrl_shift_q)?;
             }
+            // Background HIBP re-checks (idle until enabled in the vault's monitor settings)
+            commands::vault::spawn_breach_monitor(app.handle().clone());
+            // Public IP / VPN change events (idle until enabled from the UI)
+            commands::tools::spawn_network_monitor(app.handle().clone());
+            // Honeyfile open/modify/delete alerts (idle until a honeyfile exists)
+            commands::tools::spawn_honeyfile_watcher(app.handle().clone());
+            // Locks the vaults on idle, sleep or screen lock (see auto_lock.rs)
+            commands::vault::spawn_auto_lock(app.handle().clone());
+            // User-supplied clipboard pattern packs (see pattern_packs.rs)
+            commands::vault::load_pattern_packs(app.handle());
+            // Built-in regexes, compiled off the main thread before the first capture
+            std::thread::spawn(regexes::precompile);
             Ok(())
         })
         // ==========================================
@@ -105,12 +220,35 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             // --- FILE COMMANDS (commands/files.rs) ---
             commands::files::lock_file,
+            commands::files::preview_entropy_sources,
+            commands::files::benchmark_compression,
             commands::files::unlock_file,
+            commands::files::list_interrupted_batches,
+            commands::files::resume_interrupted_batch,
+            commands::files::discard_interrupted_batch,
+            commands::files::unlock_archive,
+            commands::files::export_self_decrypting,
+            commands::files::lock_file_for_recipient,
+            commands::files::lock_deniable,
+            commands::files::unlock_deniable,
+            commands::files::get_container_requirements,
+            commands::files::get_container_format,
+            commands::files::diagnose_container,
+            commands::files::recover_damaged_file,
+            commands::files::get_supported_formats,
+            commands::files::check_keyfile_location,
+            commands::files::generate_keyfile,
+            commands::files::restore_keyfile,
+            commands::files::export_paper_backup,
+            commands::files::import_paper_backup,
+            commands::files::search_locked_files,
+            commands::files::list_containers,
             commands::files::delete_items,
             commands::files::trash_items,
             commands::files::paste_items,
             commands::files::create_dir,
             commands::files::rename_item,
+            commands::files::batch_rename,
             commands::files::show_in_folder,
             commands::files::read_text_file_content,
             commands::files::write_text_file_content,
@@ -119,8 +257,12 @@ pub fn run() {
             commands::files::cancel_shred,
             commands::files::wipe_free_space,
             commands::files::trim_drive,
+            commands::files::check_drive_health,
+            commands::files::estimate_recovery_risk,
             commands::files::get_drives,
             commands::files::get_startup_file,
+            commands::files::set_locale,
+            commands::files::get_locale,
             commands::portable::enumerate_removable_drives,
             commands::portable::init_portable_vault,
             commands::portable::unlock_portable_vault,
@@ -130,29 +272,128 @@ pub fn run() {
             commands::vault::check_auth_status,
             commands::vault::init_vault,
             commands::vault::login,
+            commands::vault::login_read_only,
             commands::vault::logout,
+            commands::vault::list_profiles,
+            commands::vault::get_active_profile,
+            commands::vault::create_profile,
+            commands::vault::switch_profile,
+            commands::vault::delete_profile,
+            commands::vault::get_auto_lock_settings,
+            commands::vault::set_auto_lock_settings,
+            commands::vault::touch_session,
+            commands::vault::get_session_mode,
+            commands::vault::set_guest_password,
+            commands::vault::get_duress_status,
+            commands::vault::set_duress_password,
+            commands::vault::login_as_user,
+            commands::vault::get_session_user,
+            commands::vault::list_vault_users,
+            commands::vault::add_vault_user,
+            commands::vault::remove_vault_user,
+            commands::vault::get_audit_log,
+            commands::vault::get_security_alerts,
+            commands::vault::acknowledge_security_alert,
             commands::vault::change_user_password,
             commands::vault::recover_vault,
             commands::vault::regenerate_recovery_code,
+            commands::vault::get_recovery_code_format,
             commands::vault::get_keychain_data,
             commands::vault::export_keychain,
             commands::vault::get_backup_done,
             commands::vault::set_backup_done,
+            commands::vault::pair_mobile_device,
+            commands::vault::list_paired_devices,
+            commands::vault::remove_paired_device,
+            commands::vault::begin_device_unlock,
+            commands::vault::complete_device_unlock,
+            commands::vault::accept_device_pairing,
+            commands::vault::approve_device_unlock,
+            commands::vault::get_auto_unlock_status,
+            commands::vault::enable_auto_unlock,
+            commands::vault::disable_auto_unlock,
+            commands::vault::auto_unlock,
+            commands::vault::get_vault_policy,
+            commands::vault::set_vault_policy,
+            commands::vault::get_kdf_status,
+            commands::vault::benchmark_kdf,
             // Password Vault
             commands::vault::load_password_vault,
             commands::vault::save_password_vault,
+            commands::vault::update_password_entry,
+            commands::vault::set_password_history_limit,
+            commands::vault::add_entry_attachment,
+            commands::vault::get_entry_attachment,
+            commands::vault::delete_entry_attachment,
+            commands::vault::find_entries_for_url,
+            commands::vault::use_password_entry,
+            commands::vault::list_entry_usage,
+            commands::vault::get_deletion_checklist,
+            commands::vault::set_deletion_status,
+            commands::vault::find_duplicate_entries,
+            commands::vault::merge_password_entries,
             commands::vault::generate_totp_code,
+            commands::vault::parse_otpauth_uri,
+            // Breach Monitor
+            commands::vault::get_breach_monitor_status,
+            commands::vault::update_breach_monitor_settings,
+            commands::vault::run_breach_check,
+            commands::vault::acknowledge_breach_alerts,
+            commands::vault::audit_password_vault,
+            // Encrypted API credentials
+            commands::vault::list_secrets,
+            commands::vault::get_secret,
+            commands::vault::set_secret,
+            commands::vault::delete_secret,
+            // Entry Sharing
+            commands::vault::share_entries,
+            commands::vault::preview_shared_entries,
+            commands::vault::import_shared_entries,
+            commands::vault::preview_password_import,
+            commands::vault::import_passwords,
+            commands::vault::export_password_vault,
+            // Public-Key Identities
+            commands::vault::get_public_identity,
+            commands::vault::import_contact,
+            commands::vault::list_contacts,
+            commands::vault::remove_contact,
             // Notes Vault
+            commands::vault::get_panel_lock_status,
+            commands::vault::set_panel_lock,
+            commands::vault::unlock_panel,
+            commands::vault::lock_panel,
             commands::vault::load_notes_vault,
+            commands::vault::query_notes,
             commands::vault::save_notes_vault,
+            commands::vault::diff_note_versions,
+            commands::vault::set_note_history_limit,
+            commands::vault::add_note_image,
+            commands::vault::get_note_image,
+            commands::vault::delete_note_image,
+            commands::vault::add_note_asset,
+            commands::vault::list_note_assets,
+            commands::vault::get_note_asset,
+            commands::vault::get_note_asset_file,
+            commands::vault::delete_note_asset,
+            commands::vault::collect_note_assets,
             // Bookmarks Vault
             commands::vault::load_bookmarks_vault,
+            commands::vault::search_vaults,
             commands::vault::save_bookmarks_vault,
             commands::vault::import_browser_bookmarks,
             // Clipboard Vault
             commands::vault::load_clipboard_vault,
             commands::vault::save_clipboard_vault,
             commands::vault::add_clipboard_entry,
+            commands::vault::transform_clipboard_entry,
+            commands::vault::list_pattern_packs,
+            commands::vault::reload_pattern_packs,
+            commands::vault::set_enabled_pattern_packs,
+            commands::vault::import_pattern_pack,
+            commands::vault::remove_pattern_pack,
+            // Maintenance
+            commands::vault::get_vault_stats,
+            commands::vault::compact_data_dir,
             // --- TOOLS COMMANDS (commands/tools.rs) ---
             // System Cleaner
             commands::tools::scan_system_junk,
@@ -165,17 +406,41 @@ pub fn run() {
             commands::tools::clean_registry,
             // File Analyzer
             commands::tools::scan_directory_targets,
+            commands::tools::reload_analyzer_rules,
+            commands::tools::clear_analyzer_cache,
             // Metadata Cleaner
             commands::tools::analyze_file_metadata,
             commands::tools::clean_file_metadata,
             commands::tools::batch_clean_metadata,
             commands::tools::cancel_metadata_clean,
             commands::tools::compare_metadata_files,
+            commands::tools::diff_metadata,
             commands::tools::detect_steganography,
+            commands::tools::detect_remote_content,
+            commands::tools::scrub_timestamps,
+            commands::tools::clean_url,
+            commands::tools::scan_photo_locations,
+            commands::tools::clean_photo_locations,
+            commands::tools::scan_document_authors,
+            commands::tools::clean_document_authors,
+            commands::tools::list_browser_cookies,
+            commands::tools::delete_browser_cookies,
+            commands::tools::get_av_interference_report,
+            commands::tools::reset_av_interference_report,
+            commands::tools::get_power_status,
+            commands::tools::set_power_policy,
+            commands::tools::get_resource_status,
+            commands::tools::set_resource_mode,
+            commands::tools::get_recent_logs,
+            commands::tools::get_log_settings,
+            commands::tools::set_log_settings,
+            commands::tools::export_settings,
+            commands::tools::import_settings,
             // Hasher
             commands::tools::calculate_file_hashes,
             commands::tools::get_file_metadata,
             commands::tools::cancel_hashing,
+            commands::tools::verify_container_hash,
             commands::tools::save_text_to_file,
             commands::tools::calculate_text_hashes,
             // QR Generator
@@ -185,13 +450,34 @@ pub fn run() {
             // Privacy Check
             commands::tools::check_password_breach,
             commands::tools::get_public_ip_address,
+            commands::tools::check_tor_status,
+            commands::tools::get_network_monitor_status,
+            commands::tools::set_network_monitor_config,
+            commands::tools::list_secure_dns_resolvers,
+            commands::tools::get_secure_dns_status,
+            commands::tools::set_secure_dns,
+            commands::tools::revert_secure_dns,
+            commands::tools::get_mac_randomization_status,
+            commands::tools::get_network_privacy_report,
+            commands::tools::generate_privacy_report,
+            commands::tools::export_privacy_report,
+            commands::tools::create_honeyfile,
+            commands::tools::list_honeyfiles,
+            commands::tools::check_honeyfiles,
+            commands::tools::remove_honeyfile,
             commands::tools::scan_local_secrets,
             commands::tools::cancel_secret_scan,
             // Generator
             commands::tools::generate_passphrase,
+            commands::tools::generate_random_name,
+            commands::tools::generate_password_for,
+            commands::tools::copy_secret_to_clipboard,
             // Timelock
             commands::timelock::lock_file_with_timelock,
             commands::timelock::get_file_timelock_status,
+            commands::timelock::solve_timelock_puzzle,
+            commands::timelock::stop_timelock_puzzle,
+            commands::timelock::get_timelock_puzzle_status,
         ])
         // Boot the Tauri application loop. This will block the main thread and keep the app alive
         // until all windows are closed or `std::process::exit()` is called.
@@ -0,0 +1,212 @@
+// --- START OF FILE line_diff.rs ---
+
+// ==========================================
+// --- LINE-LEVEL TEXT DIFF ---
+// ==========================================
+// Compares two versions of a text line by line, as `diff_note_versions` shows them.
+// Lines shared by the start and the end of both texts are matched first; what is left in
+// between is aligned with a longest-common-subsequence table. When that middle part is
+// too large for the table (`MAX_TABLE_CELLS`), it is reported as removed and then added:
+// still a correct diff, only not the smallest one.
+
+use serde::Serialize;
+
+/// Caps the LCS table at 16 MB of `u32`.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineChange {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub change: LineChange,
+    pub text: String,
+    /// 1-based line number in the old text; `None` for added lines.
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new text; `None` for removed lines.
+    pub new_line: Option<usize>,
+}
+
+/// Every line of `old` and `new` in order, each marked as kept, added or removed.
+/// Removed lines come before the lines added in their place.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_lines[prefix..old_lines.len() - suffix];
+    let new_middle = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut changes = vec![LineChange::Same; prefix];
+    changes.extend(align(old_middle, new_middle));
+    changes.extend(std::iter::repeat_n(LineChange::Same, suffix));
+
+    let (mut old_index, mut new_index) = (0, 0);
+    changes
+        .into_iter()
+        .map(|change| {
+            let (text, old_line, new_line) = match change {
+                LineChange::Same => {
+                    old_index += 1;
+                    new_index += 1;
+                    (old_lines[old_index - 1], Some(old_index), Some(new_index))
+                }
+                LineChange::Removed => {
+                    old_index += 1;
+                    (old_lines[old_index - 1], Some(old_index), None)
+                }
+                LineChange::Added => {
+                    new_index += 1;
+                    (new_lines[new_index - 1], None, Some(new_index))
+                }
+            };
+            DiffLine {
+                change,
+                text: text.to_string(),
+                old_line,
+                new_line,
+            }
+        })
+        .collect()
+}
+
+/// The edit script turning `old` into `new`, from a table of LCS lengths of their
+/// suffixes. Falls back to "remove everything, add everything" above `MAX_TABLE_CELLS`.
+fn align(old: &[&str], new: &[&str]) -> Vec<LineChange> {
+    let width = new.len() + 1;
+    let cells = (old.len() + 1).checked_mul(width);
+    if old.is_empty() || new.is_empty() || cells.is_none_or(|c| c > MAX_TABLE_CELLS) {
+        let mut changes = vec![LineChange::Removed; old.len()];
+        changes.extend(std::iter::repeat_n(LineChange::Added, new.len()));
+        return changes;
+    }
+
+    // table[i * width + j] = length of the LCS of old[i..] and new[j..]
+    let mut table = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(LineChange::Same);
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            changes.push(LineChange::Removed);
+            i += 1;
+        } else {
+            changes.push(LineChange::Added);
+            j += 1;
+        }
+    }
+    changes.extend(std::iter::repeat_n(LineChange::Removed, old.len() - i));
+    changes.extend(std::iter::repeat_n(LineChange::Added, new.len() - j));
+    changes
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diff: &[DiffLine]) -> Vec<String> {
+        diff.iter()
+            .map(|line| {
+                let mark = match line.change {
+                    LineChange::Same => ' ',
+                    LineChange::Added => '+',
+                    LineChange::Removed => '-',
+                };
+                format!("{}{}", mark, line.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recovers_deleted_paragraph() {
+        let old = "# Trip\n\nFlights booked.\nHotel: Ritz, room 12.\nCode 4471.\n\nPack light.";
+        let new = "# Trip\n\nFlights booked.\n\nPack light.\nBring charger.";
+        let diff = diff_lines(old, new);
+        assert_eq!(
+            render(&diff),
+            vec![
+                " # Trip",
+                " ",
+                " Flights booked.",
+                "-Hotel: Ritz, room 12.",
+                "-Code 4471.",
+                " ",
+                " Pack light.",
+                "+Bring charger.",
+            ]
+        );
+        assert_eq!((diff[3].old_line, diff[3].new_line), (Some(4), None));
+        assert_eq!((diff[6].old_line, diff[6].new_line), (Some(7), Some(5)));
+        assert_eq!((diff[7].old_line, diff[7].new_line), (None, Some(6)));
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert!(diff_lines("", "").is_empty());
+        assert_eq!(render(&diff_lines("", "a\nb")), vec!["+a", "+b"]);
+        assert_eq!(render(&diff_lines("a\nb", "")), vec!["-a", "-b"]);
+        assert_eq!(render(&diff_lines("a\nb\n", "a\nb")), vec![" a", " b"]);
+        assert_eq!(
+            render(&diff_lines("a\nx\nb", "a\ny\nb")),
+            vec![" a", "-x", "+y", " b"]
+        );
+        // A moved line shows as removed and added again.
+        assert_eq!(
+            render(&diff_lines("x\na\nx", "a\nx\nx")),
+            vec!["-x", " a", "+x", " x"]
+        );
+    }
+
+    #[test]
+    fn test_large_middle_falls_back_to_replace() {
+        let old: Vec<String> = (0..2100).map(|i| format!("old {}", i)).collect();
+        let new: Vec<String> = (0..2100).map(|i| format!("new {}", i)).collect();
+        let diff = diff_lines(
+            &format!("head\n{}\ntail", old.join("\n")),
+            &format!("head\n{}\ntail", new.join("\n")),
+        );
+        assert_eq!(diff.len(), 4202);
+        assert_eq!(diff[0].change, LineChange::Same);
+        assert!(diff[1..2101]
+            .iter()
+            .all(|l| l.change == LineChange::Removed));
+        assert!(diff[2101..4201]
+            .iter()
+            .all(|l| l.change == LineChange::Added));
+        assert_eq!(diff[4201].old_line, Some(2102));
+    }
+}
+
+// --- END OF FILE line_diff.rs ---
@@ -0,0 +1,570 @@
+// --- START OF FILE logging.rs ---
+
+// ==========================================
+// --- DIAGNOSTIC LOG ---
+// ==========================================
+// A local record of operations and errors to help with support requests. Code reports
+// through the `tracing` macros (`tracing::info!`, `warn!`, `error!`); `LogWriter` is the
+// process-wide subscriber and appends each event to `<app data>/logs/qre.log` as a JSON
+// line. DEBUG and TRACE events are dropped. Nothing is sent anywhere: `get_recent_logs`
+// hands the lines to the UI so the user can read them and copy them into a report.
+//
+// Never log secrets, key material or anything about duress slots and decoy vaults (a
+// log line would give the decoy away).
+//
+// REDACTION
+// File names say a lot about a person, so every path is cut down to its folder and a
+// token for the name before it is written:
+//
+//   /home/ana/Taxes/2024 return.pdf  ->  /home/ana/Taxes/<name:3f9a1c2e>.pdf
+//
+// The token is the start of SHA-256(salt || name), with a random salt made per install
+// and never logged: the same file gets the same token (a log can show "the same file
+// failed twice"), but a name cannot be confirmed by hashing guesses. With
+// `NameRedaction::Remove` the token is left out as well.
+// Fields named `path`, `file`, `dir` or `*_path` are taken as one path, spaces and all.
+// In messages and other fields, paths are found by pattern (see `LogPath` in
+// regexes.rs); a name with spaces may take a few following words with it.
+//
+// ROTATION
+// When the log exceeds `MAX_LOG_BYTES` it becomes `qre.log.1` (and `.1` becomes `.2`),
+// keeping `ROTATED_GENERATIONS` old files.
+//
+// TURNING IT OFF
+// `LogSettings::enabled = false` stops all writing and deletes the existing logs. The
+// settings live in `<app data>/log_settings.json` and are read before the subscriber is
+// installed, so the choice holds from the first event after start-up.
+
+use crate::regexes::{self, Pattern};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span;
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+pub const LOG_DIR_NAME: &str = "logs";
+pub const LOG_FILE_NAME: &str = "qre.log";
+pub const SETTINGS_FILE_NAME: &str = "log_settings.json";
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const ROTATED_GENERATIONS: usize = 3;
+/// Longest message or field value kept; the rest is cut.
+const MAX_VALUE_CHARS: usize = 2000;
+/// Upper bound for `get_recent_logs`.
+pub const MAX_RECENT: usize = 1000;
+const SALT_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NameRedaction {
+    /// A salted hash of the name, so repeated names can be told apart.
+    #[default]
+    Hash,
+    /// Only the extension is kept.
+    Remove,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct LogSettings {
+    pub enabled: bool,
+    pub file_names: NameRedaction,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file_names: NameRedaction::Hash,
+        }
+    }
+}
+
+/// `log_settings.json`: the settings plus the install's hashing salt.
+#[derive(Serialize, Deserialize, Default)]
+struct StoredSettings {
+    #[serde(flatten)]
+    settings: LogSettings,
+    #[serde(default)]
+    salt: String,
+}
+
+/// One line of the log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub at: i64,
+    pub level: String,
+    /// The module the event came from.
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+// ==========================================
+// --- REDACTION ---
+// ==========================================
+
+struct Redactor {
+    salt: Vec<u8>,
+    mode: NameRedaction,
+}
+
+impl Redactor {
+    /// `path` with its last component replaced by a token. The extension is kept when
+    /// it looks like one (1-8 letters or digits).
+    fn path(&self, path: &str) -> String {
+        let trimmed = path.trim_end_matches(['/', '\\']);
+        let cut = trimmed.rfind(['/', '\\']).map_or(0, |i| i + 1);
+        let (dir, name) = trimmed.split_at(cut);
+        // Nothing to hide in `/` or `C:\`.
+        if name.is_empty() || (dir.is_empty() && name.ends_with(':')) {
+            return path.to_string();
+        }
+        let extension = name
+            .rsplit_once('.')
+            .filter(|(stem, ext)| {
+                !stem.is_empty()
+                    && (1..=8).contains(&ext.len())
+                    && ext.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+            .map_or(String::new(), |(_, ext)| format!(".{}", ext));
+        match self.mode {
+            NameRedaction::Hash => {
+                let digest = Sha256::new()
+                    .chain_update(&self.salt)
+                    .chain_update(name.as_bytes())
+                    .finalize();
+                let token = data_encoding::HEXLOWER.encode(&digest[..4]);
+                format!("{}<name:{}>{}", dir, token, extension)
+            }
+            NameRedaction::Remove => format!("{}<name>{}", dir, extension),
+        }
+    }
+
+    /// `text` with every path it contains redacted.
+    fn text(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(caps) = regexes::get(Pattern::LogPath).captures(rest) {
+            let (lead, candidate) = (caps.get(1).unwrap(), caps.get(2).unwrap());
+            out.push_str(&rest[..lead.end()]);
+            // The pattern runs to the end of the line; stop at the likeliest end instead.
+            let end = regexes::get(Pattern::LogPathEnd)
+                .find(candidate.as_str())
+                .map_or(candidate.len(), |m| m.start());
+            let path = candidate.as_str()[..end].trim_end_matches(['.', ',', ';', ')', ']']);
+            out.push_str(&self.path(path));
+            rest = &rest[candidate.start() + path.len()..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+fn is_path_field(name: &str) -> bool {
+    matches!(name, "path" | "file" | "dir") || name.ends_with("_path")
+}
+
+fn clip(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n')
+        .take(MAX_VALUE_CHARS)
+        .collect()
+}
+
+/// Collects an event's message and fields, redacted.
+struct EntryVisitor<'a> {
+    redactor: &'a Redactor,
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl EntryVisitor<'_> {
+    fn record_text(&mut self, name: &str, value: &str) {
+        let value = clip(value);
+        let redacted = if is_path_field(name) {
+            self.redactor.path(&value)
+        } else {
+            self.redactor.text(&value)
+        };
+        if name == "message" {
+            self.message = redacted;
+        } else {
+            self.fields.insert(name.to_string(), redacted.into());
+        }
+    }
+}
+
+impl Visit for EntryVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_text(field.name(), value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_text(field.name(), &format!("{:?}", value));
+    }
+}
+
+// ==========================================
+// --- WRITER ---
+// ==========================================
+
+/// The `tracing` subscriber that writes the log.
+pub struct LogWriter {
+    data_dir: PathBuf,
+    inner: Mutex<WriterState>,
+    next_span: AtomicU64,
+}
+
+struct WriterState {
+    settings: LogSettings,
+    redactor: Redactor,
+}
+
+impl LogWriter {
+    /// Reads the settings in `data_dir`, creating them (and the salt) the first time.
+    pub fn open(data_dir: &Path) -> Self {
+        let path = data_dir.join(SETTINGS_FILE_NAME);
+        let mut stored: StoredSettings = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        let mut salt = data_encoding::HEXLOWER
+            .decode(stored.salt.as_bytes())
+            .unwrap_or_default();
+        if salt.len() != SALT_LEN {
+            salt = rand::random::<[u8; SALT_LEN]>().to_vec();
+            stored.salt = data_encoding::HEXLOWER.encode(&salt);
+            let _ = fs::create_dir_all(data_dir);
+            let _ = serde_json::to_vec_pretty(&stored).map(|json| fs::write(&path, json));
+        }
+        Self {
+            data_dir: data_dir.to_path_buf(),
+            inner: Mutex::new(WriterState {
+                settings: stored.settings,
+                redactor: Redactor {
+                    salt,
+                    mode: stored.settings.file_names,
+                },
+            }),
+            next_span: AtomicU64::new(1),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, WriterState> {
+        self.inner.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn log_dir(&self) -> PathBuf {
+        self.data_dir.join(LOG_DIR_NAME)
+    }
+
+    pub fn settings(&self) -> LogSettings {
+        self.state().settings
+    }
+
+    /// Saves new settings. Turning the log off also deletes the existing files.
+    pub fn update(&self, settings: LogSettings) -> Result<()> {
+        let mut state = self.state();
+        let stored = StoredSettings {
+            settings,
+            salt: data_encoding::HEXLOWER.encode(&state.redactor.salt),
+        };
+        fs::create_dir_all(&self.data_dir)?;
+        fs::write(
+            self.data_dir.join(SETTINGS_FILE_NAME),
+            serde_json::to_vec_pretty(&stored)?,
+        )?;
+        state.settings = settings;
+        state.redactor.mode = settings.file_names;
+        if !settings.enabled {
+            let dir = self.log_dir();
+            if dir.exists() {
+                fs::remove_dir_all(&dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` entries, newest first, across the rotated files. Lines that do not
+    /// parse are skipped.
+    pub fn recent(&self, limit: usize) -> Result<Vec<LogEntry>> {
+        let _state = self.state();
+        let mut entries = Vec::new();
+        for generation in 0..=ROTATED_GENERATIONS {
+            let path = generation_path(&self.log_dir(), generation);
+            let Ok(file) = fs::File::open(&path) else {
+                continue;
+            };
+            let mut lines: Vec<LogEntry> = BufReader::new(file)
+                .lines()
+                .map_while(|l| l.ok())
+                .filter_map(|l| serde_json::from_str(&l).ok())
+                .collect();
+            lines.reverse();
+            entries.extend(lines);
+            if entries.len() >= limit {
+                break;
+            }
+        }
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    fn append(&self, entry: &LogEntry) -> Result<()> {
+        let dir = self.log_dir();
+        fs::create_dir_all(&dir)?;
+        let path = generation_path(&dir, 0);
+        if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) > MAX_LOG_BYTES {
+            rotate(&dir)?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        if cfg!(debug_assertions) {
+            eprint!("{}", line);
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn generation_path(dir: &Path, generation: usize) -> PathBuf {
+    match generation {
+        0 => dir.join(LOG_FILE_NAME),
+        n => dir.join(format!("{}.{}", LOG_FILE_NAME, n)),
+    }
+}
+
+/// Shifts every generation up by one; the oldest falls off.
+fn rotate(dir: &Path) -> Result<()> {
+    for generation in (0..ROTATED_GENERATIONS).rev() {
+        let from = generation_path(dir, generation);
+        if from.exists() {
+            fs::rename(&from, generation_path(dir, generation + 1))?;
+        }
+    }
+    Ok(())
+}
+
+impl Subscriber for LogWriter {
+    // Checked on every event rather than cached per call site, so turning the log off
+    // takes effect at once.
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= Level::INFO && self.state().settings.enabled
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::INFO)
+    }
+
+    // Spans are not recorded; they only need distinct IDs.
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let state = self.state();
+        if !state.settings.enabled {
+            return;
+        }
+        let metadata = event.metadata();
+        let mut visitor = EntryVisitor {
+            redactor: &state.redactor,
+            message: String::new(),
+            fields: serde_json::Map::new(),
+        };
+        event.record(&mut visitor);
+        let entry = LogEntry {
+            at: chrono::Utc::now().timestamp(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        };
+        // Nowhere to report a failure to write the log.
+        let _ = self.append(&entry);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+// ==========================================
+// --- PROCESS-WIDE LOG ---
+// ==========================================
+
+static WRITER: OnceLock<Arc<LogWriter>> = OnceLock::new();
+
+/// Installs the log for `data_dir` as the global `tracing` subscriber. Later calls do
+/// nothing.
+pub fn init(data_dir: &Path) {
+    if WRITER.get().is_some() {
+        return;
+    }
+    let writer = Arc::new(LogWriter::open(data_dir));
+    if tracing::subscriber::set_global_default(writer.clone()).is_ok() {
+        let _ = WRITER.set(writer);
+    }
+}
+
+fn writer() -> Result<&'static LogWriter> {
+    WRITER
+        .get()
+        .map(Arc::as_ref)
+        .ok_or_else(|| anyhow!("The log is not available."))
+}
+
+pub fn settings() -> LogSettings {
+    writer().map(LogWriter::settings).unwrap_or_default()
+}
+
+pub fn set_settings(settings: LogSettings) -> Result<()> {
+    writer()?.update(settings)
+}
+
+pub fn recent(limit: usize) -> Result<Vec<LogEntry>> {
+    writer()?.recent(limit.min(MAX_RECENT))
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qre_logging_tests_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn redactor(mode: NameRedaction) -> Redactor {
+        Redactor {
+            salt: vec![7; SALT_LEN],
+            mode,
+        }
+    }
+
+    #[test]
+    fn test_file_names_are_redacted() {
+        let r = redactor(NameRedaction::Hash);
+        let token = r.path("/home/ana/Taxes/2024 return.pdf");
+        assert!(token.starts_with("/home/ana/Taxes/<name:"));
+        assert!(token.ends_with(">.pdf"));
+        // Same name, same token; the folder does not matter.
+        assert_eq!(
+            r.path("C:\\Backup\\2024 return.pdf").rsplit('\\').next(),
+            token.rsplit('/').next()
+        );
+        assert_eq!(r.path("/"), "/");
+        assert_eq!(r.path("C:\\"), "C:\\");
+
+        let text = r.text("Failed to shred /home/ana/Taxes/2024 return.pdf: permission denied");
+        assert_eq!(
+            text,
+            format!("Failed to shred {}: permission denied", token)
+        );
+        let text =
+            r.text("Copied '/a/secret.txt' to D:\\out\\secret.txt, see https://example.com/x");
+        assert!(!text.contains("secret"));
+        assert!(text.starts_with("Copied '/a/<name:"));
+        assert!(text.contains("to D:\\out\\<name:"));
+        assert!(text.ends_with(", see https://example.com/x"));
+        assert_eq!(r.text("and/or 1/2"), "and/or 1/2");
+
+        let removed = redactor(NameRedaction::Remove);
+        assert_eq!(removed.path("/a/b/diary.md"), "/a/b/<name>.md");
+        assert_eq!(removed.path("/a/b/.bashrc"), "/a/b/<name>");
+    }
+
+    #[test]
+    fn test_events_are_written_rotated_and_turned_off() {
+        let dir = temp_dir("events");
+        let writer = Arc::new(LogWriter::open(&dir));
+        tracing::subscriber::with_default(writer.clone(), || {
+            tracing::debug!("dropped");
+            tracing::info!(
+                path = "/home/ana/My Passport.jpg",
+                size = 12u64,
+                "Locked file"
+            );
+            tracing::error!("Cannot open /home/ana/notes.txt");
+        });
+        let entries = writer.recent(10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].level, "ERROR");
+        assert!(entries[0]
+            .message
+            .starts_with("Cannot open /home/ana/<name:"));
+        assert_eq!(entries[1].message, "Locked file");
+        let path = entries[1].fields["path"].as_str().unwrap();
+        assert!(path.starts_with("/home/ana/<name:") && path.ends_with(".jpg"));
+        assert_eq!(entries[1].fields["size"], 12);
+
+        // The salt survives a restart, so tokens stay comparable.
+        let reopened = LogWriter::open(&dir);
+        assert_eq!(reopened.state().redactor.salt, writer.state().redactor.salt);
+
+        let log_dir = dir.join(LOG_DIR_NAME);
+        for _ in 0..ROTATED_GENERATIONS + 2 {
+            fs::write(
+                generation_path(&log_dir, 0),
+                vec![b'#'; MAX_LOG_BYTES as usize + 1],
+            )
+            .unwrap();
+            tracing::subscriber::with_default(writer.clone(), || tracing::warn!("rotated"));
+        }
+        assert!(generation_path(&log_dir, ROTATED_GENERATIONS).exists());
+        assert!(!generation_path(&log_dir, ROTATED_GENERATIONS + 1).exists());
+        assert_eq!(writer.recent(1).unwrap()[0].message, "rotated");
+
+        writer
+            .update(LogSettings {
+                enabled: false,
+                ..LogSettings::default()
+            })
+            .unwrap();
+        assert!(!log_dir.exists());
+        tracing::subscriber::with_default(writer.clone(), || tracing::error!("not written"));
+        assert!(writer.recent(10).unwrap().is_empty());
+        assert!(!LogWriter::open(&dir).settings().enabled);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// --- END OF FILE logging.rs ---
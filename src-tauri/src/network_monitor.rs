@@ -0,0 +1,291 @@
+// --- START OF FILE network_monitor.rs ---
+
+// ==========================================
+// --- PUBLIC IP / VPN MONITORING ---
+// ==========================================
+// `get_public_ip_address` is a one-shot check; a VPN that drops five minutes later goes
+// unnoticed. The monitor re-runs the same check (`breach::get_public_ip`) on a
+// configurable interval and reports *changes* only:
+//   * the public IP changed,
+//   * Cloudflare WARP went on or off,
+//   * the connection went away or came back.
+//
+// The decision logic here is pure (no network, no Tauri) so it can be unit tested; the
+// background thread that performs the requests and emits `network-status-changed`
+// lives in `commands/tools.rs`. Nothing is sent while disabled or in offline mode.
+
+use crate::breach::IpResult;
+use serde::{Deserialize, Serialize};
+
+/// Never poll more often than this, whatever the settings say.
+pub const MIN_INTERVAL_SECS: u64 = 30;
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct NetworkMonitorConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// While set, no request is made and the last known state is kept.
+    pub offline_mode: bool,
+}
+
+impl Default for NetworkMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: DEFAULT_INTERVAL_SECS,
+            offline_mode: false,
+        }
+    }
+}
+
+impl NetworkMonitorConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_secs < MIN_INTERVAL_SECS {
+            return Err(format!(
+                "Check interval must be at least {} seconds.",
+                MIN_INTERVAL_SECS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Result of one check. `ip` is `None` when every IP service failed (no connectivity).
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NetworkSnapshot {
+    pub ip: Option<String>,
+    pub is_warp: bool,
+    pub checked_at: i64,
+}
+
+impl NetworkSnapshot {
+    pub fn from_check(result: Result<IpResult, String>, checked_at: i64) -> Self {
+        match result {
+            Ok(r) => Self {
+                ip: Some(r.ip),
+                is_warp: r.is_warp,
+                checked_at,
+            },
+            Err(_) => Self {
+                ip: None,
+                is_warp: false,
+                checked_at,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkChangeKind {
+    IpChanged,
+    VpnConnected,
+    VpnDropped,
+    WentOffline,
+    BackOnline,
+}
+
+/// Payload of the `network-status-changed` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct NetworkChange {
+    pub changes: Vec<NetworkChangeKind>,
+    pub previous: NetworkSnapshot,
+    pub current: NetworkSnapshot,
+}
+
+/// What `get_network_monitor_status` returns.
+#[derive(Serialize, Debug, Clone)]
+pub struct NetworkMonitorStatus {
+    pub config: NetworkMonitorConfig,
+    pub last: Option<NetworkSnapshot>,
+    pub next_check_at: Option<i64>,
+}
+
+// ==========================================
+// --- MONITOR LOGIC ---
+// ==========================================
+
+#[derive(Debug, Default)]
+pub struct NetworkMonitor {
+    pub config: NetworkMonitorConfig,
+    last: Option<NetworkSnapshot>,
+}
+
+impl NetworkMonitor {
+    pub const fn new() -> Self {
+        Self {
+            config: NetworkMonitorConfig {
+                enabled: false,
+                interval_secs: DEFAULT_INTERVAL_SECS,
+                offline_mode: false,
+            },
+            last: None,
+        }
+    }
+
+    /// Applies new settings. Turning monitoring off (or going offline) forgets the last
+    /// state, so re-enabling later does not report a change against stale data.
+    pub fn configure(&mut self, config: NetworkMonitorConfig) -> Result<(), String> {
+        config.validate()?;
+        if !config.enabled || config.offline_mode {
+            self.last = None;
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.config.enabled && !self.config.offline_mode
+    }
+
+    pub fn next_check_at(&self) -> Option<i64> {
+        if !self.is_active() {
+            return None;
+        }
+        let interval = self.config.interval_secs.max(MIN_INTERVAL_SECS) as i64;
+        Some(self.last.as_ref().map_or(0, |s| s.checked_at + interval))
+    }
+
+    pub fn is_due(&self, now: i64) -> bool {
+        self.next_check_at().is_some_and(|at| now >= at)
+    }
+
+    /// Records a snapshot and returns the change against the previous one, if any.
+    /// The first snapshot after enabling is the baseline and never produces an event.
+    pub fn record(&mut self, current: NetworkSnapshot) -> Option<NetworkChange> {
+        let previous = self.last.replace(current.clone())?;
+        let changes = diff(&previous, &current);
+        if changes.is_empty() {
+            None
+        } else {
+            Some(NetworkChange {
+                changes,
+                previous,
+                current,
+            })
+        }
+    }
+
+    pub fn status(&self) -> NetworkMonitorStatus {
+        NetworkMonitorStatus {
+            config: self.config.clone(),
+            last: self.last.clone(),
+            next_check_at: self.next_check_at(),
+        }
+    }
+}
+
+/// Differences between two snapshots. Losing connectivity is reported as `WentOffline`
+/// only, not also as a dropped VPN: the VPN state is unknown while offline.
+pub fn diff(previous: &NetworkSnapshot, current: &NetworkSnapshot) -> Vec<NetworkChangeKind> {
+    let mut changes = Vec::new();
+    match (&previous.ip, &current.ip) {
+        (Some(_), None) => return vec![NetworkChangeKind::WentOffline],
+        (None, Some(_)) => changes.push(NetworkChangeKind::BackOnline),
+        (Some(a), Some(b)) if a != b => changes.push(NetworkChangeKind::IpChanged),
+        _ => {}
+    }
+    if current.ip.is_some() && previous.is_warp != current.is_warp {
+        changes.push(if current.is_warp {
+            NetworkChangeKind::VpnConnected
+        } else {
+            NetworkChangeKind::VpnDropped
+        });
+    }
+    changes
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(ip: Option<&str>, is_warp: bool, at: i64) -> NetworkSnapshot {
+        NetworkSnapshot {
+            ip: ip.map(str::to_string),
+            is_warp,
+            checked_at: at,
+        }
+    }
+
+    fn enabled() -> NetworkMonitor {
+        let mut m = NetworkMonitor::new();
+        m.configure(NetworkMonitorConfig {
+            enabled: true,
+            interval_secs: 60,
+            offline_mode: false,
+        })
+        .unwrap();
+        m
+    }
+
+    #[test]
+    fn test_first_snapshot_is_baseline() {
+        let mut m = enabled();
+        assert!(m.is_due(0));
+        assert!(m.record(snap(Some("1.1.1.1"), true, 100)).is_none());
+        assert!(!m.is_due(159));
+        assert!(m.is_due(160));
+    }
+
+    #[test]
+    fn test_vpn_drop_and_ip_change_reported() {
+        let mut m = enabled();
+        m.record(snap(Some("104.28.0.1"), true, 0));
+        let change = m.record(snap(Some("81.2.3.4"), false, 60)).unwrap();
+        assert_eq!(
+            change.changes,
+            vec![NetworkChangeKind::IpChanged, NetworkChangeKind::VpnDropped]
+        );
+        assert!(m.record(snap(Some("81.2.3.4"), false, 120)).is_none());
+    }
+
+    #[test]
+    fn test_offline_transitions() {
+        let mut m = enabled();
+        m.record(snap(Some("104.28.0.1"), true, 0));
+        let down = m.record(snap(None, false, 60)).unwrap();
+        assert_eq!(down.changes, vec![NetworkChangeKind::WentOffline]);
+        let up = m.record(snap(Some("104.28.0.1"), true, 120)).unwrap();
+        assert_eq!(
+            up.changes,
+            vec![
+                NetworkChangeKind::BackOnline,
+                NetworkChangeKind::VpnConnected
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offline_mode_and_validation() {
+        let mut m = enabled();
+        m.record(snap(Some("1.1.1.1"), false, 0));
+        m.configure(NetworkMonitorConfig {
+            enabled: true,
+            interval_secs: 60,
+            offline_mode: true,
+        })
+        .unwrap();
+        assert!(!m.is_due(i64::MAX));
+        assert!(m.status().last.is_none());
+
+        assert!(m
+            .configure(NetworkMonitorConfig {
+                enabled: true,
+                interval_secs: 5,
+                offline_mode: false,
+            })
+            .is_err());
+    }
+}
+
+// --- END OF FILE network_monitor.rs ---
@@ -0,0 +1,395 @@
+// --- START OF FILE network_privacy.rs ---
+
+// ==========================================
+// --- NETWORK PRIVACY REPORT ---
+// ==========================================
+// One read-only overview of what the local network can learn about the user:
+//   - Wi-Fi MAC randomization per interface. A fixed hardware address lets every hotspot
+//     (and anyone sniffing nearby) recognise the device across networks and visits.
+//   - VPN: tunnel interfaces on this machine, and whether the public IP check sees
+//     Cloudflare WARP.
+//   - Public IP (`breach::get_public_ip`) and secure DNS (`secure_dns::status`).
+//
+// Nothing is changed here. MAC randomization is detected from the addresses themselves:
+// an address that differs from the burned-in one, or has the "locally administered" bit
+// set, was not assigned by the manufacturer.
+
+use crate::breach::IpResult;
+use crate::secure_dns::SecureDnsStatus;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MacStatus {
+    /// The interface uses a random (private) address.
+    Randomized,
+    /// The interface uses the manufacturer's address.
+    Hardware,
+    Unknown,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MacRandomization {
+    pub interface: String,
+    pub current_mac: Option<String>,
+    pub status: MacStatus,
+    /// What to do about it; `None` when nothing needs doing.
+    pub guidance: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct VpnCheck {
+    /// Tunnel interfaces (WireGuard, OpenVPN, ...) that are up on this machine.
+    pub interfaces: Vec<String>,
+    /// The public IP check reports Cloudflare WARP.
+    pub warp: bool,
+    pub detected: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NetworkPrivacyReport {
+    /// Unix seconds.
+    pub generated_at: i64,
+    pub public_ip: Option<IpResult>,
+    /// Why `public_ip` is missing (offline mode, no connection).
+    pub ip_error: Option<String>,
+    pub vpn: VpnCheck,
+    pub dns: SecureDnsStatus,
+    pub mac: Vec<MacRandomization>,
+    pub recommendations: Vec<String>,
+}
+
+// ==========================================
+// --- MAC RANDOMIZATION ---
+// ==========================================
+
+#[cfg(target_os = "windows")]
+const MAC_GUIDANCE: &str =
+    "Turn on Settings › Network & internet › Wi-Fi › Random hardware addresses.";
+#[cfg(target_os = "macos")]
+const MAC_GUIDANCE: &str =
+    "Set System Settings › Wi-Fi › Details… › Private Wi-Fi address to Rotating or Fixed.";
+#[cfg(target_os = "linux")]
+const MAC_GUIDANCE: &str =
+    "Set `wifi.cloned-mac-address=stable` (or `random`) in a NetworkManager \
+     [connection] section, or per connection with nmcli.";
+
+/// Normalises `AA-BB-CC-DD-EE-FF` / `aa:bb:...` to lower-case colon form.
+fn normalize_mac(mac: &str) -> Option<String> {
+    let octets: Vec<&str> = mac.trim().split([':', '-']).collect();
+    if octets.len() != 6
+        || !octets
+            .iter()
+            .all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return None;
+    }
+    Some(octets.join(":").to_ascii_lowercase())
+}
+
+/// Bit 1 of the first octet: set for addresses not assigned by the manufacturer.
+fn is_locally_administered(mac: &str) -> bool {
+    u8::from_str_radix(&mac[..2], 16).is_ok_and(|first| first & 0b10 != 0)
+}
+
+/// Classifies one interface from its current and, where the platform reports it, its
+/// burned-in address.
+fn classify(current: Option<&str>, hardware: Option<&str>) -> MacStatus {
+    let current = current.and_then(normalize_mac);
+    let hardware = hardware.and_then(normalize_mac);
+    match (current, hardware) {
+        (None, _) => MacStatus::Unknown,
+        (Some(c), Some(h)) if c != h => MacStatus::Randomized,
+        (Some(c), _) if is_locally_administered(&c) => MacStatus::Randomized,
+        (Some(_), _) => MacStatus::Hardware,
+    }
+}
+
+/// (interface, current address, burned-in address) of every Wi-Fi interface.
+#[cfg(target_os = "linux")]
+fn wifi_interfaces() -> Vec<(String, Option<String>, Option<String>)> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.path().join("wireless").exists() || e.path().join("phy80211").exists())
+        .map(|e| {
+            let address = std::fs::read_to_string(e.path().join("address"))
+                .ok()
+                .map(|a| a.trim().to_string());
+            (e.file_name().to_string_lossy().to_string(), address, None)
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn wifi_interfaces() -> Vec<(String, Option<String>, Option<String>)> {
+    let Ok(output) = std::process::Command::new("networksetup")
+        .arg("-listallhardwareports")
+        .output()
+    else {
+        return Vec::new();
+    };
+    parse_hardware_ports(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .map(|(device, hardware)| {
+            let current = std::process::Command::new("ifconfig")
+                .arg(&device)
+                .output()
+                .ok()
+                .and_then(|o| {
+                    String::from_utf8_lossy(&o.stdout).lines().find_map(|l| {
+                        l.trim()
+                            .strip_prefix("ether ")
+                            .map(|m| m.trim().to_string())
+                    })
+                });
+            (device, current, Some(hardware))
+        })
+        .collect()
+}
+
+/// `Hardware Port: Wi-Fi` blocks of `networksetup -listallhardwareports`, as
+/// (device, burned-in address).
+#[cfg(target_os = "macos")]
+fn parse_hardware_ports(output: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    for block in output.split("\n\n") {
+        let field = |name: &str| {
+            block
+                .lines()
+                .find_map(|l| l.strip_prefix(name).map(|v| v.trim().to_string()))
+        };
+        if let (Some(port), Some(device), Some(mac)) = (
+            field("Hardware Port:"),
+            field("Device:"),
+            field("Ethernet Address:"),
+        ) {
+            if port == "Wi-Fi" || port == "AirPort" {
+                result.push((device, mac));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(target_os = "windows")]
+fn wifi_interfaces() -> Vec<(String, Option<String>, Option<String>)> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let script =
+        "Get-NetAdapter -Physical | Where-Object { $_.PhysicalMediaType -like '*802.11*' } | \
+         ForEach-Object { \"$($_.Name)|$($_.MacAddress)\" }";
+    let Ok(output) = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.trim().split_once('|'))
+        .map(|(name, mac)| (name.to_string(), Some(mac.to_string()), None))
+        .collect()
+}
+
+/// Read-only: reports whether each Wi-Fi interface uses a random address.
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub fn mac_randomization() -> Vec<MacRandomization> {
+    wifi_interfaces()
+        .into_iter()
+        .map(|(interface, current, hardware)| {
+            let status = classify(current.as_deref(), hardware.as_deref());
+            MacRandomization {
+                interface,
+                current_mac: current.as_deref().and_then(normalize_mac),
+                status,
+                guidance: (status == MacStatus::Hardware).then(|| MAC_GUIDANCE.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Android randomizes per network by default and does not let apps read the address.
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn mac_randomization() -> Vec<MacRandomization> {
+    Vec::new()
+}
+
+// ==========================================
+// --- VPN ---
+// ==========================================
+
+/// Interface names used by common VPN clients.
+const VPN_PREFIXES: &[&str] = &[
+    "tun", "tap", "wg", "ppp", "ipsec", "nordlynx", "proton", "mullvad",
+];
+const VPN_KEYWORDS: &[&str] = &["wireguard", "openvpn", "vpn", "tap-windows", "wintun"];
+
+/// True if an interface with this name and these addresses looks like a VPN tunnel.
+/// macOS keeps several `utun` interfaces up for its own services with link-local IPv6
+/// only, so a `utun` counts only once it carries an IPv4 address.
+fn is_vpn_interface(name: &str, has_ipv4: bool) -> bool {
+    let name = name.to_ascii_lowercase();
+    if name.starts_with("utun") {
+        return has_ipv4;
+    }
+    VPN_PREFIXES.iter().any(|p| name.starts_with(p))
+        || VPN_KEYWORDS.iter().any(|k| name.contains(k))
+}
+
+/// Names of the VPN tunnel interfaces on this machine.
+pub fn vpn_interfaces() -> Vec<String> {
+    let networks = sysinfo::Networks::new_with_refreshed_list();
+    let mut names: Vec<String> = networks
+        .iter()
+        .filter(|(name, data)| {
+            let has_ipv4 = data.ip_networks().iter().any(|n| n.addr.is_ipv4());
+            is_vpn_interface(name, has_ipv4)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+// ==========================================
+// --- REPORT ---
+// ==========================================
+
+/// Combines the individual checks and derives the recommendations.
+pub fn build_report(
+    public_ip: Result<IpResult, String>,
+    vpn_interfaces: Vec<String>,
+    dns: SecureDnsStatus,
+    mac: Vec<MacRandomization>,
+) -> NetworkPrivacyReport {
+    let (public_ip, ip_error) = match public_ip {
+        Ok(ip) => (Some(ip), None),
+        Err(e) => (None, Some(e)),
+    };
+    let warp = public_ip.as_ref().is_some_and(|ip| ip.is_warp);
+    let vpn = VpnCheck {
+        detected: warp || !vpn_interfaces.is_empty(),
+        interfaces: vpn_interfaces,
+        warp,
+    };
+
+    let mut recommendations = Vec::new();
+    if !vpn.detected {
+        recommendations
+            .push("No VPN detected: websites and your ISP see your real IP address.".to_string());
+    }
+    if dns.enabled == Some(false) {
+        recommendations
+            .push("DNS queries are sent unencrypted; switch to a secure DNS resolver.".to_string());
+    }
+    for m in mac.iter().filter(|m| m.status == MacStatus::Hardware) {
+        recommendations.push(format!(
+            "{} uses its hardware MAC address. {}",
+            m.interface,
+            m.guidance.as_deref().unwrap_or_default()
+        ));
+    }
+
+    NetworkPrivacyReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        public_ip,
+        ip_error,
+        vpn,
+        dns,
+        mac,
+        recommendations,
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mac_classification() {
+        assert_eq!(
+            normalize_mac("AA-BB-CC-00-11-22").unwrap(),
+            "aa:bb:cc:00:11:22"
+        );
+        assert!(normalize_mac("aa:bb:cc").is_none());
+
+        // 0x3c = 0b0011_1100: manufacturer-assigned.
+        assert_eq!(
+            classify(Some("3c:22:fb:01:02:03"), None),
+            MacStatus::Hardware
+        );
+        // 0x3e has the locally administered bit set.
+        assert_eq!(
+            classify(Some("3e:22:fb:01:02:03"), None),
+            MacStatus::Randomized
+        );
+        // Differs from the burned-in address (macOS reports both).
+        assert_eq!(
+            classify(Some("3c:22:fb:01:02:03"), Some("3C-22-FB-99-99-99")),
+            MacStatus::Randomized
+        );
+        assert_eq!(
+            classify(Some("3c:22:fb:01:02:03"), Some("3C-22-FB-01-02-03")),
+            MacStatus::Hardware
+        );
+        assert_eq!(classify(None, None), MacStatus::Unknown);
+    }
+
+    #[test]
+    fn test_vpn_interface_names() {
+        assert!(is_vpn_interface("wg0", false));
+        assert!(is_vpn_interface("tun0", false));
+        assert!(is_vpn_interface("ProtonVPN", false));
+        assert!(is_vpn_interface("Mullvad", false));
+        assert!(is_vpn_interface("utun4", true));
+        assert!(!is_vpn_interface("utun0", false));
+        assert!(!is_vpn_interface("wlan0", true));
+        assert!(!is_vpn_interface("Ethernet", true));
+    }
+
+    #[test]
+    fn test_report_recommendations() {
+        let ip = IpResult {
+            ip: "203.0.113.7".to_string(),
+            is_warp: false,
+            service_used: "test".to_string(),
+        };
+        let dns = SecureDnsStatus {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        let mac = vec![MacRandomization {
+            interface: "wlan0".to_string(),
+            current_mac: Some("3c:22:fb:01:02:03".to_string()),
+            status: MacStatus::Hardware,
+            guidance: Some("Enable it.".to_string()),
+        }];
+        let report = build_report(Ok(ip), Vec::new(), dns, mac);
+        assert!(!report.vpn.detected);
+        assert_eq!(report.recommendations.len(), 3);
+        assert!(report.recommendations[2].starts_with("wlan0"));
+
+        let secure = SecureDnsStatus {
+            enabled: Some(true),
+            ..Default::default()
+        };
+        let report = build_report(
+            Err("Offline mode".to_string()),
+            vec!["wg0".to_string()],
+            secure,
+            Vec::new(),
+        );
+        assert!(report.vpn.detected && report.public_ip.is_none());
+        assert!(report.recommendations.is_empty());
+    }
+}
+
+// --- END OF FILE network_privacy.rs ---
@@ -0,0 +1,283 @@
+// --- START OF FILE note_assets.rs ---
+
+// ==========================================
+// --- ENCRYPTED NOTE ATTACHMENTS ---
+// ==========================================
+// PDFs, images and other files attached to notes. The markdown of a note refers to them
+// as `qre-asset:<id>`: `![scan](qre-asset:<id>)` shows an image inline and
+// `[invoice.pdf](qre-asset:<id>)` links to the file. The note lists the IDs it uses in
+// `asset_ids`, the same way it lists pasted screenshots in `image_ids` (note_images.rs).
+//
+// All attachments of a vault share one encrypted bundle, `notes_assets.qre`, kept apart
+// from `notes.qre` so saving a note does not re-encrypt them. A single bundle, unlike a
+// folder of containers, does not reveal how many files are attached or how large each
+// one is. The price is that adding or removing one rewrites the bundle, hence the caps.
+//
+// An attachment is deleted once no saved note lists it (see `save_notes_vault`);
+// `collect_note_assets` sweeps those that were added but never saved into a note.
+
+use crate::entry_attachments;
+use anyhow::{anyhow, Context, Result};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub const ASSETS_FILE_NAME: &str = "notes_assets.qre";
+/// Under the system temp dir: decrypted copies handed to other apps by
+/// `get_note_asset_file`. Shredded when the vaults lock.
+pub const TEMP_DIR_NAME: &str = "qre_note_assets";
+pub const MAX_ASSET_BYTES: usize = 10 * 1024 * 1024;
+/// The whole bundle is decrypted and re-encrypted on every change.
+pub const MAX_BUNDLE_BYTES: usize = 64 * 1024 * 1024;
+/// Decoding limit on top of the asset bytes, for names and other metadata.
+const METADATA_ALLOWANCE: u64 = 1024 * 1024;
+const CURRENT_VERSION: u32 = 1;
+
+/// Types the note view shows inline; anything else is opened or saved.
+const INLINE_MIMES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+const UNKNOWN_MIME: &str = "application/octet-stream";
+
+/// An attachment without its bytes. The name is as sensitive as the note, so it is
+/// scrubbed from RAM on drop.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct NoteAssetInfo {
+    pub id: String,
+    pub name: String,
+    /// Sniffed from the content rather than trusted from the name.
+    pub mime: String,
+    pub inline: bool,
+    pub size_bytes: u64,
+    pub added_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct NoteAsset {
+    pub info: NoteAssetInfo,
+    pub data: Vec<u8>,
+}
+
+/// What `get_note_asset` returns: the bytes base64-encoded, ready for a `data:` URL.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NoteAssetContent {
+    pub info: NoteAssetInfo,
+    pub data_base64: String,
+}
+
+impl NoteAsset {
+    pub fn content(&self) -> NoteAssetContent {
+        NoteAssetContent {
+            info: self.info.clone(),
+            data_base64: data_encoding::BASE64.encode(&self.data),
+        }
+    }
+}
+
+/// The decrypted contents of `notes_assets.qre`.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct NoteAssetBundle {
+    version: u32,
+    assets: Vec<NoteAsset>,
+}
+
+impl Default for NoteAssetBundle {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            assets: Vec::new(),
+        }
+    }
+}
+
+fn codec() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(MAX_BUNDLE_BYTES as u64 + METADATA_ALLOWANCE)
+}
+
+/// IDs are UUIDs generated by the backend; anything else is refused.
+pub fn validate_asset_id(id: &str) -> Result<()> {
+    uuid::Uuid::parse_str(id)
+        .map(|_| ())
+        .map_err(|_| anyhow!("Invalid attachment ID '{}'.", id))
+}
+
+/// A fresh path for a decrypted copy of `info`, in its own folder under `TEMP_DIR_NAME`
+/// so the file keeps its name.
+pub fn temp_copy_path(info: &NoteAssetInfo) -> PathBuf {
+    std::env::temp_dir()
+        .join(TEMP_DIR_NAME)
+        .join(uuid::Uuid::new_v4().to_string())
+        .join(&info.name)
+}
+
+impl NoteAssetBundle {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bundle: Self = codec()
+            .deserialize(bytes)
+            .context("The note attachments are unreadable")?;
+        if bundle.version > CURRENT_VERSION {
+            return Err(anyhow!(
+                "The note attachments were saved by a newer version of the app."
+            ));
+        }
+        for asset in &bundle.assets {
+            validate_asset_id(&asset.info.id)?;
+        }
+        Ok(bundle)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        codec()
+            .serialize(self)
+            .context("Failed to serialize the note attachments")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn list(&self) -> Vec<NoteAssetInfo> {
+        self.assets.iter().map(|a| a.info.clone()).collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&NoteAsset> {
+        self.assets.iter().find(|a| a.info.id == id)
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.assets.iter().map(|a| a.data.len()).sum()
+    }
+
+    /// Checks a new attachment, gives it an ID and stores it. Only the last path
+    /// component of `name` is kept.
+    pub fn add(&mut self, name: &str, data: &[u8], now: i64) -> Result<NoteAssetInfo> {
+        if data.is_empty() {
+            return Err(anyhow!("The file is empty."));
+        }
+        if data.len() > MAX_ASSET_BYTES {
+            return Err(anyhow!(
+                "Note attachments are limited to {} MB. Keep larger files in the file vault.",
+                MAX_ASSET_BYTES / (1024 * 1024)
+            ));
+        }
+        if self.total_bytes() + data.len() > MAX_BUNDLE_BYTES {
+            return Err(anyhow!(
+                "Note attachments are limited to {} MB in total. Remove unused ones first.",
+                MAX_BUNDLE_BYTES / (1024 * 1024)
+            ));
+        }
+        let mime = infer::get(data).map_or(UNKNOWN_MIME, |kind| kind.mime_type());
+        let info = NoteAssetInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: entry_attachments::clean_file_name(name)?,
+            mime: mime.to_string(),
+            inline: INLINE_MIMES.contains(&mime),
+            size_bytes: data.len() as u64,
+            added_at: now,
+        };
+        self.assets.push(NoteAsset {
+            info: info.clone(),
+            data: data.to_vec(),
+        });
+        Ok(info)
+    }
+
+    /// Returns whether the attachment existed.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.assets.len();
+        self.assets.retain(|a| a.info.id != id);
+        self.assets.len() != before
+    }
+
+    /// Drops every attachment not in `referenced`. Returns the IDs removed.
+    pub fn retain_referenced(&mut self, referenced: &HashSet<&str>) -> Vec<String> {
+        let removed: Vec<String> = self
+            .assets
+            .iter()
+            .filter(|a| !referenced.contains(a.info.id.as_str()))
+            .map(|a| a.info.id.clone())
+            .collect();
+        self.assets
+            .retain(|a| referenced.contains(a.info.id.as_str()));
+        removed
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_bundle_round_trip_and_collection() {
+        let mut bundle = NoteAssetBundle::default();
+        let png = bundle
+            .add("C:\\scans\\receipt.png", PNG_SIGNATURE, 7)
+            .unwrap();
+        let pdf = bundle.add("warranty.pdf", b"%PDF-1.7 ...", 8).unwrap();
+        let blob = bundle.add("notes.bin", b"\x01\x02\x03", 9).unwrap();
+        assert_eq!(
+            (png.name.as_str(), png.mime.as_str(), png.inline),
+            ("receipt.png", "image/png", true)
+        );
+        assert_eq!((pdf.mime.as_str(), pdf.inline), ("application/pdf", false));
+        assert_eq!(blob.mime, UNKNOWN_MIME);
+
+        let mut restored = NoteAssetBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.list(), bundle.list());
+        assert_eq!(restored.get(&pdf.id).unwrap().data, b"%PDF-1.7 ...");
+        assert_eq!(
+            restored.get(&blob.id).unwrap().content().data_base64,
+            "AQID"
+        );
+
+        let referenced: HashSet<&str> = [png.id.as_str()].into();
+        let mut removed = restored.retain_referenced(&referenced);
+        removed.sort();
+        let mut expected = vec![pdf.id.clone(), blob.id.clone()];
+        expected.sort();
+        assert_eq!(removed, expected);
+        assert!(restored.remove(&png.id));
+        assert!(!restored.remove(&png.id));
+        assert!(restored.is_empty());
+    }
+
+    #[test]
+    fn test_limits_and_malformed_bundles() {
+        let mut bundle = NoteAssetBundle::default();
+        assert!(bundle.add("empty.txt", b"", 0).is_err());
+        assert!(bundle.add("..", b"x", 0).is_err());
+        assert!(bundle
+            .add("big.bin", &vec![0u8; MAX_ASSET_BYTES + 1], 0)
+            .is_err());
+        for i in 0..MAX_BUNDLE_BYTES / MAX_ASSET_BYTES {
+            bundle
+                .add(&format!("{}.bin", i), &vec![0u8; MAX_ASSET_BYTES], 0)
+                .unwrap();
+        }
+        let err = bundle
+            .add("last.bin", &vec![0u8; MAX_ASSET_BYTES], 0)
+            .unwrap_err();
+        assert!(err.to_string().contains("in total"));
+
+        assert!(NoteAssetBundle::from_bytes(b"garbage").is_err());
+        let mut bad = NoteAssetBundle::default();
+        bad.add("a.txt", b"a", 0).unwrap();
+        bad.assets[0].info.id = "../notes".to_string();
+        assert!(NoteAssetBundle::from_bytes(&bad.to_bytes().unwrap()).is_err());
+        let newer = NoteAssetBundle {
+            version: CURRENT_VERSION + 1,
+            assets: Vec::new(),
+        };
+        assert!(NoteAssetBundle::from_bytes(&newer.to_bytes().unwrap()).is_err());
+    }
+}
+
+// --- END OF FILE note_assets.rs ---
@@ -0,0 +1,171 @@
+// --- START OF FILE note_images.rs ---
+
+// ==========================================
+// --- ENCRYPTED NOTE IMAGES ---
+// ==========================================
+// Pasted screenshots would bloat `notes.qre` (every save re-encrypts the whole vault),
+// so notes only hold image IDs. Each image lives next to the vault in its own encrypted
+// container under `note_images/`:
+//   <id>.qre        the original bytes, as pasted
+//   <id>.thumb.qre  a PNG thumbnail generated here, so the note list never has to
+//                   decrypt and decode full-size images
+//
+// Only PNG and JPEG are accepted. Decoding runs with explicit limits so a crafted
+// "image" cannot exhaust memory (decompression bomb).
+
+use anyhow::{anyhow, Result};
+use image::{ImageFormat, ImageReader, Limits};
+use serde::Serialize;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+pub const IMAGES_DIR_NAME: &str = "note_images";
+/// Generous for screenshots; larger files belong in the file vault.
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+const MAX_DIMENSION: u32 = 16_384;
+/// Decoder allocation cap (a 16k x 16k RGBA bitmap would already be 1 GB).
+const MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// What `add_note_image` returns: enough for the frontend to lay out the image
+/// before fetching any pixels.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NoteImageInfo {
+    pub id: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: usize,
+}
+
+/// A validated image ready to be encrypted.
+pub struct PreparedImage {
+    pub info: NoteImageInfo,
+    pub thumbnail_png: Vec<u8>,
+}
+
+/// IDs are UUIDs generated by the backend; anything else is refused before it can be
+/// joined onto a path.
+pub fn validate_image_id(id: &str) -> Result<()> {
+    uuid::Uuid::parse_str(id)
+        .map(|_| ())
+        .map_err(|_| anyhow!("Invalid image ID '{}'.", id))
+}
+
+pub fn image_path(vault_dir: &Path, id: &str, thumbnail: bool) -> Result<PathBuf> {
+    validate_image_id(id)?;
+    let name = if thumbnail {
+        format!("{}.thumb.qre", id)
+    } else {
+        format!("{}.qre", id)
+    };
+    Ok(vault_dir.join(IMAGES_DIR_NAME).join(name))
+}
+
+/// Checks the bytes, decodes them under limits and renders the thumbnail.
+pub fn prepare_image(data: &[u8]) -> Result<PreparedImage> {
+    if data.is_empty() {
+        return Err(anyhow!("The image is empty."));
+    }
+    if data.len() > MAX_IMAGE_BYTES {
+        return Err(anyhow!(
+            "Images are limited to {} MB.",
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+    let format = image::guess_format(data).map_err(|_| anyhow!("Unrecognized image format."))?;
+    let mime = match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        _ => return Err(anyhow!("Only PNG and JPEG images can be embedded.")),
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let decoded = reader
+        .decode()
+        .map_err(|e| anyhow!("Failed to decode image: {}", e))?;
+
+    let mut thumbnail_png = Vec::new();
+    decoded
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut thumbnail_png), ImageFormat::Png)
+        .map_err(|e| anyhow!("Failed to create thumbnail: {}", e))?;
+
+    Ok(PreparedImage {
+        info: NoteImageInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            mime: mime.to_string(),
+            width: decoded.width(),
+            height: decoded.height(),
+            size_bytes: data.len(),
+        },
+        thumbnail_png,
+    })
+}
+
+/// Deletes an image and its thumbnail. Missing files are not an error.
+pub fn remove_image_files(vault_dir: &Path, id: &str) -> Result<()> {
+    for thumbnail in [false, true] {
+        let path = image_path(vault_dir, id, thumbnail)?;
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let img = ImageBuffer::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 128]));
+        let mut out = Vec::new();
+        img.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_prepare_image_makes_bounded_thumbnail() {
+        let data = png_bytes(1024, 512);
+        let prepared = prepare_image(&data).unwrap();
+        assert_eq!(prepared.info.mime, "image/png");
+        assert_eq!((prepared.info.width, prepared.info.height), (1024, 512));
+        assert_eq!(prepared.info.size_bytes, data.len());
+
+        let thumb = image::load_from_memory(&prepared.thumbnail_png).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (THUMBNAIL_SIZE, 128));
+    }
+
+    #[test]
+    fn test_prepare_image_rejects_non_images() {
+        assert!(prepare_image(b"").is_err());
+        assert!(prepare_image(b"definitely not an image").is_err());
+        // Valid PNG signature, truncated body.
+        assert!(prepare_image(&png_bytes(8, 8)[..20]).is_err());
+    }
+
+    #[test]
+    fn test_image_ids_cannot_escape_directory() {
+        let dir = Path::new("/vault");
+        assert!(image_path(dir, "../keychain", false).is_err());
+        let id = uuid::Uuid::new_v4().to_string();
+        assert_eq!(
+            image_path(dir, &id, true).unwrap(),
+            dir.join(IMAGES_DIR_NAME).join(format!("{}.thumb.qre", id))
+        );
+    }
+}
+
+// --- END OF FILE note_images.rs ---
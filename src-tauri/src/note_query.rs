@@ -0,0 +1,444 @@
+// --- START OF FILE note_query.rs ---
+
+// ==========================================
+// --- NOTES QUERY ENGINE ---
+// ==========================================
+// The Notes panel's search, run in Rust over the decrypted vault (`NotesVault::query`).
+//
+// SYNTAX (every part must match)
+//   word                  the title, the content or a tag contains it
+//   "two words"           the phrase, in that order
+//   tag:work              a tag equal to "work"; tag:"two words" for tags with spaces
+//   title:x  content:x    x only in that field (also with a quoted phrase)
+//   before:2024-01-01     last edited before that day (UTC)
+//   after:2024-01-01      last edited on or after that day (UTC)
+//   is:pinned
+//
+// Matching ignores case. Other `name:` prefixes are plain text, so a pasted
+// "https://..." searches as typed. With `whole_words`, words and phrases only match
+// between word boundaries ("cat" no longer finds "concatenate").
+//
+// RESULTS
+// Each match carries highlight ranges for the title and up to `MAX_SNIPPETS` excerpts
+// of the content. Offsets count UTF-16 code units, as JavaScript strings do, so the UI
+// can slice the text it was given directly.
+
+use crate::notes::NoteEntry;
+use serde::Serialize;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+const MAX_QUERY_CHARS: usize = 500;
+const MAX_SNIPPETS: usize = 3;
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_CHARS: usize = 160;
+
+/// `start..end` in UTF-16 code units.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Zeroize)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct NoteSnippet {
+    pub text: String,
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct NoteMatch {
+    pub id: String,
+    pub title_highlights: Vec<Highlight>,
+    /// Excerpts around the content matches, or the start of the content when only the
+    /// title, a tag or a filter matched.
+    pub snippets: Vec<NoteSnippet>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Any,
+    Title,
+    Content,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Clause {
+    /// Case-folded text; a phrase when it contains spaces.
+    Text {
+        field: Field,
+        text: Vec<char>,
+    },
+    Tag(Vec<char>),
+    Before(i64),
+    After(i64),
+    Pinned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteQuery {
+    clauses: Vec<Clause>,
+    whole_words: bool,
+}
+
+impl NoteQuery {
+    pub fn parse(query: &str, whole_words: bool) -> Result<Self, String> {
+        let chars: Vec<char> = query.chars().take(MAX_QUERY_CHARS).collect();
+        let mut clauses = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            // A known `name:` prefix; anything else is read as text.
+            let mut filter = None;
+            let name_end = chars[i..]
+                .iter()
+                .position(|c| !c.is_ascii_alphabetic())
+                .map_or(chars.len(), |n| i + n);
+            if chars.get(name_end) == Some(&':') {
+                let name: String = chars[i..name_end].iter().collect::<String>().to_lowercase();
+                if matches!(
+                    name.as_str(),
+                    "tag" | "title" | "content" | "before" | "after" | "is"
+                ) {
+                    filter = Some(name);
+                    i = name_end + 1;
+                }
+            }
+
+            let value: String = if chars.get(i) == Some(&'"') {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .map_or(chars.len(), |n| i + 1 + n);
+                let value = chars[i + 1..end].iter().collect();
+                i = end + 1;
+                value
+            } else {
+                let end = chars[i..]
+                    .iter()
+                    .position(|c| c.is_whitespace())
+                    .map_or(chars.len(), |n| i + n);
+                let value = chars[i..end].iter().collect();
+                i = end;
+                value
+            };
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            // `tag:` with nothing after it yet, or an empty pair of quotes.
+            if value.is_empty() {
+                continue;
+            }
+
+            clauses.push(match filter.as_deref() {
+                None => Clause::Text {
+                    field: Field::Any,
+                    text: fold(&value),
+                },
+                Some("title") => Clause::Text {
+                    field: Field::Title,
+                    text: fold(&value),
+                },
+                Some("content") => Clause::Text {
+                    field: Field::Content,
+                    text: fold(&value),
+                },
+                Some("tag") => Clause::Tag(fold(&value)),
+                Some("before") => Clause::Before(parse_day("before", &value)?),
+                Some("after") => Clause::After(parse_day("after", &value)?),
+                _ if value.eq_ignore_ascii_case("pinned") => Clause::Pinned,
+                _ => return Err(format!("Unknown filter 'is:{}'. Use is:pinned.", value)),
+            });
+        }
+
+        Ok(Self {
+            clauses,
+            whole_words,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// `None` when the note does not match every part of the query.
+    pub fn match_note(&self, note: &NoteEntry) -> Option<NoteMatch> {
+        let title = fold(&note.title);
+        let content = fold(&note.content);
+        let mut title_ranges = Vec::new();
+        let mut content_ranges = Vec::new();
+
+        for clause in &self.clauses {
+            let matched = match clause {
+                Clause::Text { field, text } => {
+                    let in_title =
+                        *field != Field::Content && self.find_all(&title, text, &mut title_ranges);
+                    let in_content = *field != Field::Title
+                        && self.find_all(&content, text, &mut content_ranges);
+                    let in_tags = *field == Field::Any
+                        && note.tags.iter().any(|tag| {
+                            let tag = fold(tag);
+                            self.find_all(&tag, text, &mut Vec::new())
+                        });
+                    in_title || in_content || in_tags
+                }
+                Clause::Tag(wanted) => note.tags.iter().any(|tag| fold(tag.trim()) == *wanted),
+                Clause::Before(day) => note.updated_at < *day,
+                Clause::After(day) => note.updated_at >= *day,
+                Clause::Pinned => note.is_pinned,
+            };
+            if !matched {
+                return None;
+            }
+        }
+
+        let title_chars: Vec<char> = note.title.chars().collect();
+        let content_chars: Vec<char> = note.content.chars().collect();
+        Some(NoteMatch {
+            id: note.id.clone(),
+            title_highlights: to_utf16(&title_chars, 0, &merge(title_ranges)),
+            snippets: snippets(&content_chars, &merge(content_ranges)),
+        })
+    }
+
+    /// Adds every occurrence of `needle` in `haystack` (character ranges) to `out`.
+    fn find_all(&self, haystack: &[char], needle: &[char], out: &mut Vec<(usize, usize)>) -> bool {
+        let found_before = out.len();
+        if needle.is_empty() || needle.len() > haystack.len() {
+            return false;
+        }
+        let is_word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+        let mut start = 0;
+        while start + needle.len() <= haystack.len() {
+            let end = start + needle.len();
+            let same = haystack[start..end]
+                .iter()
+                .zip(needle)
+                .all(|(h, n)| h == n || (h.is_whitespace() && n.is_whitespace()));
+            let bounded = !self.whole_words
+                || (!is_word(start.checked_sub(1).and_then(|p| haystack.get(p)))
+                    && !is_word(haystack.get(end)));
+            if same && bounded {
+                out.push((start, end));
+                start = end;
+            } else {
+                start += 1;
+            }
+        }
+        out.len() > found_before
+    }
+}
+
+/// Lower-cases one character at a time, so character positions in the folded text
+/// line up with the original.
+fn fold(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Midnight UTC of a `YYYY-MM-DD` day, as a Unix timestamp in seconds.
+fn parse_day(filter: &str, value: &str) -> Result<i64, String> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc().timestamp())
+        .ok_or_else(|| format!("Invalid date in '{}:{}'. Use YYYY-MM-DD.", filter, value))
+}
+
+/// Sorts ranges and joins overlapping ones.
+fn merge(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Character ranges (shifted by `offset`) converted to UTF-16 offsets into `chars`.
+fn to_utf16(chars: &[char], offset: usize, ranges: &[(usize, usize)]) -> Vec<Highlight> {
+    let mut units = Vec::with_capacity(chars.len() + 1);
+    units.push(0);
+    for c in chars {
+        units.push(units.last().unwrap() + c.len_utf16());
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| Highlight {
+            start: units[start - offset],
+            end: units[end - offset],
+        })
+        .collect()
+}
+
+/// Excerpts of the content around the match ranges, on one line, with "…" where text
+/// was cut.
+fn snippets(content: &[char], ranges: &[(usize, usize)]) -> Vec<NoteSnippet> {
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+    for &(start, end) in ranges {
+        if windows.last().is_some_and(|w| end <= w.1) {
+            continue;
+        }
+        if windows.len() == MAX_SNIPPETS {
+            break;
+        }
+        // Never repeat text the previous excerpt already shows.
+        let from = start
+            .saturating_sub(SNIPPET_BEFORE)
+            .max(windows.last().map_or(0, |w| w.1));
+        windows.push((from, (from + SNIPPET_CHARS).max(end).min(content.len())));
+    }
+    if windows.is_empty() {
+        windows.push((0, SNIPPET_CHARS.min(content.len())));
+    }
+
+    windows
+        .into_iter()
+        .map(|(from, to)| {
+            let mut chars: Vec<char> = content[from..to]
+                .iter()
+                .map(|&c| if c.is_whitespace() { ' ' } else { c })
+                .collect();
+            // Highlights are counted after the leading "…", if any.
+            let lead = usize::from(from > 0);
+            if from > 0 {
+                chars.insert(0, '…');
+            }
+            if to < content.len() {
+                chars.push('…');
+            }
+            let inside: Vec<(usize, usize)> = ranges
+                .iter()
+                .filter(|&&(start, end)| start < to && end > from)
+                .map(|&(start, end)| (start.max(from) + lead, end.min(to) + lead))
+                .collect();
+            NoteSnippet {
+                highlights: to_utf16(&chars, from, &inside),
+                text: chars.into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, title: &str, content: &str, tags: &[&str], updated_at: i64) -> NoteEntry {
+        NoteEntry {
+            id: id.to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+            created_at: updated_at,
+            updated_at,
+            is_pinned: false,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            image_ids: vec![],
+            asset_ids: vec![],
+            history: vec![],
+        }
+    }
+
+    fn ids(notes: &[NoteEntry], query: &str, whole_words: bool) -> Vec<String> {
+        let query = NoteQuery::parse(query, whole_words).unwrap();
+        notes
+            .iter()
+            .filter_map(|n| query.match_note(n))
+            .map(|m| m.id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_filters_phrases_and_whole_words() {
+        // 1704067200 is 2024-01-01T00:00:00Z.
+        let mut notes = vec![
+            note(
+                "a",
+                "Work plan",
+                "Concatenate the reports",
+                &["work"],
+                1704067199,
+            ),
+            note(
+                "b",
+                "Cat photos",
+                "my cat sleeps",
+                &["home", "pets"],
+                1704067200,
+            ),
+            note(
+                "c",
+                "Ideas",
+                "the cat SLEEPS all day",
+                &["Work Stuff"],
+                1710000000,
+            ),
+        ];
+        notes[2].is_pinned = true;
+
+        assert_eq!(ids(&notes, "cat", false), ["a", "b", "c"]);
+        assert_eq!(ids(&notes, "cat", true), ["b", "c"]);
+        assert_eq!(ids(&notes, "\"cat sleeps\"", false), ["b", "c"]);
+        assert_eq!(ids(&notes, "cat sleeps", false), ["b", "c"]);
+        assert_eq!(ids(&notes, "tag:work", false), ["a"]);
+        assert_eq!(ids(&notes, "tag:\"work stuff\"", false), ["c"]);
+        assert_eq!(ids(&notes, "title:cat", false), ["b"]);
+        assert_eq!(ids(&notes, "content:cat before:2024-01-01", false), ["a"]);
+        assert_eq!(ids(&notes, "after:2024-01-01", false), ["b", "c"]);
+        assert_eq!(ids(&notes, "is:pinned", false), ["c"]);
+        // Free text also finds tags; unknown prefixes are plain text.
+        assert_eq!(ids(&notes, "pets", false), ["b"]);
+        assert!(ids(&notes, "https://cat", false).is_empty());
+        assert!(NoteQuery::parse("tag: \"\"", false).unwrap().is_empty());
+
+        assert!(NoteQuery::parse("before:2024-13-01", false)
+            .unwrap_err()
+            .contains("YYYY-MM-DD"));
+        assert!(NoteQuery::parse("is:archived", false).is_err());
+    }
+
+    #[test]
+    fn test_highlights_use_utf16_offsets() {
+        let n = note(
+            "a",
+            "🔑 Keys",
+            &("x ".repeat(60) + "🔑 spare key\nin the drawer"),
+            &[],
+            0,
+        );
+        let m = NoteQuery::parse("key", false)
+            .unwrap()
+            .match_note(&n)
+            .unwrap();
+
+        // "🔑" is two UTF-16 units, then a space.
+        assert_eq!(m.title_highlights, [Highlight { start: 3, end: 6 }]);
+        assert_eq!(m.snippets.len(), 1);
+        let snippet = &m.snippets[0];
+        assert!(snippet.text.starts_with('…') && !snippet.text.contains('\n'));
+        let utf16: Vec<u16> = snippet.text.encode_utf16().collect();
+        for h in &snippet.highlights {
+            assert_eq!(String::from_utf16(&utf16[h.start..h.end]).unwrap(), "key");
+        }
+        assert_eq!(snippet.highlights.len(), 1);
+
+        // Only a filter matched: the start of the content, nothing highlighted.
+        let m = NoteQuery::parse("after:1970-01-01", false)
+            .unwrap()
+            .match_note(&n)
+            .unwrap();
+        assert!(m.snippets[0].text.starts_with("x x") && m.snippets[0].highlights.is_empty());
+    }
+}
+
+// --- END OF FILE note_query.rs ---
@@ -1,5 +1,9 @@
 // --- START OF FILE notes.rs---
 
+use crate::line_diff::{self, DiffLine, LineChange};
+use crate::note_assets;
+use crate::note_images;
+use crate::note_query::{NoteMatch, NoteQuery};
 use serde::{Deserialize, Serialize};
 // Zeroize prevents memory forensics by explicitly overwriting sensitive variables
 // in RAM with zeroes (`0x00`) the exact moment they drop out of scope.
@@ -30,33 +34,132 @@ pub struct NoteEntry {
     // Older vaults without this field will deserialize as an empty Vec via `default`.
     #[serde(default)]
     pub tags: Vec<String>,
+
+    // IDs of encrypted images embedded in the note (see note_images.rs). Only the IDs
+    // live here; the pixels are stored in separate files so this vault stays small.
+    #[serde(default)]
+    pub image_ids: Vec<String>,
+
+    // IDs of the files attached to the note (see note_assets.rs), referenced from the
+    // markdown as `qre-asset:<id>`. The files live in `notes_assets.qre`.
+    #[serde(default)]
+    pub asset_ids: Vec<String>,
+
+    // Earlier versions, newest first, at most `NotesVault::history_limit` of them.
+    // Maintained by the backend on save; values sent by the frontend are ignored
+    // (see `NoteEntry::carry_history_from`).
+    #[serde(default)]
+    pub history: Vec<NoteRevision>,
+}
+
+/// An earlier version of a note, kept so edits can be compared and lost text recovered.
+/// Scrubbed from RAM on drop like the note itself, including when the history is trimmed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Zeroize, ZeroizeOnDrop)]
+pub struct NoteRevision {
+    pub title: String,
+    pub content: String,
+    /// The `updated_at` of the note when this version was saved.
+    pub updated_at: i64,
+    /// When a save replaced it (UNIX timestamp in seconds).
+    pub replaced_at: i64,
+}
+
+/// What `diff_note_versions` returns: the titles of both versions and their content
+/// compared line by line.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NoteDiff {
+    pub old_title: String,
+    pub new_title: String,
+    pub old_updated_at: i64,
+    pub new_updated_at: i64,
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl NoteEntry {
+    /// Takes over the history of `previous` (the stored version of this note), adds
+    /// `previous` itself when the title or content changed, and keeps the newest `limit`
+    /// revisions. Whatever history `self` arrived with is discarded.
+    pub fn carry_history_from(&mut self, previous: &NoteEntry, now: i64, limit: usize) {
+        self.history = previous.history.clone();
+        if previous.title != self.title || previous.content != self.content {
+            self.history.insert(
+                0,
+                NoteRevision {
+                    title: previous.title.clone(),
+                    content: previous.content.clone(),
+                    updated_at: previous.updated_at,
+                    replaced_at: now,
+                },
+            );
+        }
+        self.history.truncate(limit);
+    }
+
+    /// Title, content and `updated_at` of a version: 0 is the current one, 1 the
+    /// revision it replaced, and so on.
+    fn version(&self, revision: usize) -> Option<(&str, &str, i64)> {
+        match revision {
+            0 => Some((&self.title, &self.content, self.updated_at)),
+            n => self
+                .history
+                .get(n - 1)
+                .map(|r| (r.title.as_str(), r.content.as_str(), r.updated_at)),
+        }
+    }
 }
 
+/// Upper bound on embedded images per note.
+const MAX_IMAGES_PER_NOTE: usize = 20;
+/// Upper bound on attachments per note.
+const MAX_ASSETS_PER_NOTE: usize = 20;
+
 /// The root container for all Secure Notes.
 /// This entire struct is serialized into JSON and encrypted as a single payload into `notes.qre`.
-#[derive(Serialize, Deserialize, Debug, Default, Zeroize, ZeroizeOnDrop)]
+#[derive(Serialize, Deserialize, Debug, Zeroize, ZeroizeOnDrop)]
 pub struct NotesVault {
     // Schema versioning allows for safe, backwards-compatible updates.
     // If we add new fields in V2, V1 vaults can still be loaded and migrated safely.
     #[serde(default = "NotesVault::default_schema_version")]
     pub schema_version: u32,
     pub entries: Vec<NoteEntry>,
+
+    /// How many earlier versions each note keeps. Set through `set_history_limit`;
+    /// the value sent by the frontend on save is ignored.
+    #[serde(default = "NotesVault::default_history_limit")]
+    pub history_limit: usize,
+}
+
+impl Default for NotesVault {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NotesVault {
     // Defines the current data structure version expected by the backend
     pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+    pub const DEFAULT_HISTORY_LIMIT: usize = 10;
+    /// Lower than for passwords: every revision is a full copy of the note, and the
+    /// whole vault is re-encrypted on each save.
+    pub const MAX_HISTORY_LIMIT: usize = 50;
 
     // Fallback for older JSON files that might lack the version field entirely
     fn default_schema_version() -> u32 {
         1
     }
 
+    fn default_history_limit() -> usize {
+        Self::DEFAULT_HISTORY_LIMIT
+    }
+
     /// Initializes a brand new, empty notes vault.
     pub fn new() -> Self {
         Self {
             schema_version: Self::CURRENT_SCHEMA_VERSION,
             entries: Vec::new(),
+            history_limit: Self::DEFAULT_HISTORY_LIMIT,
         }
     }
 
@@ -93,10 +196,116 @@ impl NotesVault {
                     return Err(format!("Note '{}' has an empty tag", note.id));
                 }
             }
+            // Validate images: well-formed IDs, no repeats, max 20 per note
+            if note.image_ids.len() > MAX_IMAGES_PER_NOTE {
+                return Err(format!(
+                    "Note '{}' has too many images (max {})",
+                    note.id, MAX_IMAGES_PER_NOTE
+                ));
+            }
+            let mut seen_images = std::collections::HashSet::new();
+            for image_id in &note.image_ids {
+                if note_images::validate_image_id(image_id).is_err() {
+                    return Err(format!("Note '{}' has an invalid image ID", note.id));
+                }
+                if !seen_images.insert(image_id) {
+                    return Err(format!("Note '{}' embeds an image twice", note.id));
+                }
+            }
+            // Same rules for attachments
+            if note.asset_ids.len() > MAX_ASSETS_PER_NOTE {
+                return Err(format!(
+                    "Note '{}' has too many attachments (max {})",
+                    note.id, MAX_ASSETS_PER_NOTE
+                ));
+            }
+            let mut seen_assets = std::collections::HashSet::new();
+            for asset_id in &note.asset_ids {
+                if note_assets::validate_asset_id(asset_id).is_err() {
+                    return Err(format!("Note '{}' has an invalid attachment ID", note.id));
+                }
+                if !seen_assets.insert(asset_id) {
+                    return Err(format!("Note '{}' lists an attachment twice", note.id));
+                }
+            }
         }
 
         Ok(()) // Validation passed successfully
     }
+
+    /// Runs a parsed query (see note_query.rs) over the notes, most recently edited first.
+    pub fn query(&self, query: &NoteQuery) -> Vec<NoteMatch> {
+        let mut notes: Vec<&NoteEntry> = self.entries.iter().collect();
+        notes.sort_by_key(|n| std::cmp::Reverse(n.updated_at));
+        notes
+            .into_iter()
+            .filter_map(|n| query.match_note(n))
+            .collect()
+    }
+
+    // ==========================================
+    // --- NOTE HISTORY ---
+    // ==========================================
+
+    /// Carries the history limit and every note's history over from the vault on disk,
+    /// recording the stored version of each note whose title or content changed (see
+    /// `NoteEntry::carry_history_from`). Notes new to the vault start without history.
+    pub fn preserve_history_from(&mut self, previous: &NotesVault, now: i64) {
+        self.history_limit = previous.history_limit;
+        for note in &mut self.entries {
+            match previous.entries.iter().find(|n| n.id == note.id) {
+                Some(stored) => note.carry_history_from(stored, now, self.history_limit),
+                None => note.history.clear(),
+            }
+        }
+    }
+
+    /// Changes how many earlier versions are kept, trimming longer histories now.
+    pub fn set_history_limit(&mut self, limit: usize) -> Result<(), String> {
+        if limit > Self::MAX_HISTORY_LIMIT {
+            return Err(format!(
+                "Keep at most {} earlier versions per note.",
+                Self::MAX_HISTORY_LIMIT
+            ));
+        }
+        self.history_limit = limit;
+        for note in &mut self.entries {
+            note.history.truncate(limit);
+        }
+        Ok(())
+    }
+
+    /// Compares two versions of a note (0 = current, n = the n-th earlier one), from
+    /// `rev_a` to `rev_b`.
+    pub fn diff_versions(&self, id: &str, rev_a: usize, rev_b: usize) -> Result<NoteDiff, String> {
+        let note = self
+            .entries
+            .iter()
+            .find(|n| n.id == id)
+            .ok_or("Note not found.")?;
+        let missing = |rev: usize| {
+            format!(
+                "Version {} of this note is not kept ({} earlier versions available).",
+                rev,
+                note.history.len()
+            )
+        };
+        let (old_title, old_content, old_updated_at) =
+            note.version(rev_a).ok_or_else(|| missing(rev_a))?;
+        let (new_title, new_content, new_updated_at) =
+            note.version(rev_b).ok_or_else(|| missing(rev_b))?;
+        let lines = line_diff::diff_lines(old_content, new_content);
+        let count = |kind: LineChange| lines.iter().filter(|l| l.change == kind).count();
+        Ok(NoteDiff {
+            old_title: old_title.to_string(),
+            new_title: new_title.to_string(),
+            old_updated_at,
+            new_updated_at,
+            added: count(LineChange::Added),
+            removed: count(LineChange::Removed),
+            lines,
+        })
+    }
 }
 
 // ==========================================
@@ -122,6 +331,9 @@ mod tests {
             updated_at: 1700000000,
             is_pinned: false,
             tags: vec!["personal".to_string(), "finance".to_string()],
+            image_ids: vec![],
+            asset_ids: vec![],
+            history: vec![],
         }
     }
 
@@ -194,6 +406,41 @@ mod tests {
         assert!(result.unwrap_err().contains("empty tag"));
     }
 
+    #[test]
+    fn test_image_ids_are_validated() {
+        let mut vault = NotesVault::new();
+        let mut note = create_valid_note("note-1");
+        let id = uuid::Uuid::new_v4().to_string();
+        note.image_ids = vec![id.clone()];
+        vault.entries.push(note);
+        assert!(vault.validate().is_ok());
+
+        vault.entries[0].image_ids.push(id);
+        assert!(vault.validate().unwrap_err().contains("image twice"));
+
+        vault.entries[0].image_ids = vec!["../../keychain.json".to_string()];
+        assert!(vault.validate().unwrap_err().contains("invalid image ID"));
+    }
+
+    #[test]
+    fn test_asset_ids_are_validated() {
+        let mut vault = NotesVault::new();
+        let mut note = create_valid_note("note-1");
+        let id = uuid::Uuid::new_v4().to_string();
+        note.asset_ids = vec![id.clone()];
+        vault.entries.push(note);
+        assert!(vault.validate().is_ok());
+
+        vault.entries[0].asset_ids.push(id);
+        assert!(vault.validate().unwrap_err().contains("attachment twice"));
+
+        vault.entries[0].asset_ids = vec!["../notes.qre".to_string()];
+        assert!(vault
+            .validate()
+            .unwrap_err()
+            .contains("invalid attachment ID"));
+    }
+
     // 1. Serialization round-trip
     // The most critical path: this is exactly what happens every time the
     // vault is saved and loaded. If any field is silently dropped during
@@ -402,4 +649,63 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Duplicate ID"));
     }
+
+    // 11. Saving keeps earlier versions and they can be diffed
+    // The frontend sends notes without history (or with a forged one); only edits to
+    // the title or content add a revision, and the stored limit wins.
+    #[test]
+    fn test_history_is_kept_on_save_and_diffed() {
+        let mut stored = NotesVault::new();
+        stored.history_limit = 2;
+        let mut note = create_valid_note("n1");
+        note.content = "Line one\nDoor code 4471\nLine three".to_string();
+        stored.entries.push(note);
+
+        let mut saved: NotesVault = serde_json::from_str(
+            &serde_json::to_string(&stored)
+                .unwrap()
+                .replace(",\"history_limit\":2", ""),
+        )
+        .unwrap();
+        assert_eq!(saved.history_limit, NotesVault::DEFAULT_HISTORY_LIMIT);
+        saved.entries[0].content = "Line one\nLine three".to_string();
+        saved.entries[0].updated_at = 1700000100;
+        saved.entries[0].history = vec![NoteRevision {
+            title: "forged".to_string(),
+            content: String::new(),
+            updated_at: 0,
+            replaced_at: 0,
+        }];
+        saved.entries.push(create_valid_note("n2"));
+        saved.preserve_history_from(&stored, 500);
+        assert_eq!(saved.history_limit, 2);
+        assert_eq!(saved.entries[0].history.len(), 1);
+        assert_eq!(saved.entries[0].history[0].updated_at, 1700000000);
+        assert_eq!(saved.entries[0].history[0].replaced_at, 500);
+        assert!(saved.entries[1].history.is_empty());
+
+        // Pinning or tagging alone does not add a revision.
+        let mut repinned = NotesVault::new();
+        repinned.entries.push(saved.entries[0].clone());
+        repinned.entries[0].is_pinned = true;
+        repinned.preserve_history_from(&saved, 600);
+        assert_eq!(repinned.entries[0].history.len(), 1);
+
+        let diff = saved.diff_versions("n1", 1, 0).unwrap();
+        assert_eq!((diff.added, diff.removed), (0, 1));
+        assert_eq!(diff.lines[1].text, "Door code 4471");
+        assert_eq!(diff.lines[1].change, LineChange::Removed);
+        assert_eq!(diff.old_updated_at, 1700000000);
+        assert!(saved
+            .diff_versions("n1", 0, 2)
+            .unwrap_err()
+            .contains("not kept"));
+        assert!(saved.diff_versions("missing", 0, 0).is_err());
+
+        assert!(saved
+            .set_history_limit(NotesVault::MAX_HISTORY_LIMIT + 1)
+            .is_err());
+        saved.set_history_limit(0).unwrap();
+        assert!(saved.entries[0].history.is_empty());
+    }
 }
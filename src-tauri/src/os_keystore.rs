@@ -0,0 +1,214 @@
+// --- START OF FILE os_keystore.rs ---
+
+// ==========================================
+// --- OS KEYSTORE (AUTO-UNLOCK) ---
+// ==========================================
+// Opt-in unlock without typing the master password. Enabling it creates a random 32-byte
+// secret, wraps a copy of the master key with it in the keychain's keystore slot, and
+// files the secret in the platform keystore:
+//   - Windows: Credential Manager. Read only after a Windows Hello check.
+//   - macOS / iOS: the data protection keychain, with a user-presence access control, so
+//     the system asks for Touch ID / Face ID (or the account password) on every read.
+//     This keychain needs a signed build with a keychain access group entitlement.
+//   - Linux: the Secret Service (GNOME Keyring, KWallet). There is no biometric step;
+//     the login keyring is unlocked together with the desktop session.
+// Android Keystore keys are only reachable from the Java side, so the Android build
+// reports the feature as unsupported here.
+//
+// Neither half opens the vault alone: keychain.json holds only the wrapped key, the
+// keystore only the wrapping secret. Anyone who can act as the logged-in OS user (and
+// pass the presence check) can unlock, which is the trade-off the user opts into.
+
+use anyhow::{anyhow, Result};
+use rand::{rngs::OsRng, TryRngCore};
+use zeroize::Zeroizing;
+
+/// Service name the secrets are filed under in the platform keystore.
+const SERVICE: &str = "com.qre.locker.auto-unlock";
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct AutoUnlockStatus {
+    /// This platform has a keystore backend.
+    pub supported: bool,
+    pub enabled: bool,
+    pub created_at: Option<i64>,
+    pub last_used: Option<i64>,
+}
+
+/// Whether this build has a keystore backend. On Linux the Secret Service can still be
+/// missing at runtime; `store` reports that.
+pub fn is_supported() -> bool {
+    cfg!(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "linux"
+    ))
+}
+
+/// A fresh keystore account name and secret for a new slot.
+pub fn new_entry() -> Result<(String, Zeroizing<[u8; 32]>)> {
+    let mut secret = Zeroizing::new([0u8; 32]);
+    OsRng
+        .try_fill_bytes(secret.as_mut())
+        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    Ok((uuid::Uuid::new_v4().to_string(), secret))
+}
+
+/// Files `secret` under `account`, replacing any earlier entry.
+pub fn store(account: &str, secret: &[u8; 32]) -> Result<()> {
+    platform::store(account, secret)
+}
+
+/// Reads the secret for `account` back, after the platform's presence check. `reason`
+/// is shown in the Windows Hello prompt.
+pub fn load(account: &str, reason: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let bytes = platform::load(account, reason)?;
+    let secret: [u8; 32] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("The keystore entry is damaged."))?;
+    Ok(Zeroizing::new(secret))
+}
+
+/// Removes the entry for `account`. An entry that is already gone is not an error.
+pub fn delete(account: &str) -> Result<()> {
+    platform::delete(account)
+}
+
+const NOT_FOUND: &str =
+    "The keystore has no auto-unlock entry for this vault. Unlock with your password and enable auto-unlock again.";
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+mod platform {
+    use super::{NOT_FOUND, SERVICE};
+    use anyhow::{anyhow, Result};
+    use zeroize::Zeroizing;
+
+    fn entry(account: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(SERVICE, account)
+            .map_err(|e| anyhow!("The system keystore is not available: {}", e))
+    }
+
+    pub fn store(account: &str, secret: &[u8; 32]) -> Result<()> {
+        entry(account)?
+            .set_secret(secret)
+            .map_err(|e| anyhow!("Could not save to the system keystore: {}", e))
+    }
+
+    pub fn load(account: &str, reason: &str) -> Result<Zeroizing<Vec<u8>>> {
+        #[cfg(target_os = "windows")]
+        verify_windows_hello(reason)?;
+        #[cfg(not(target_os = "windows"))]
+        let _ = reason;
+
+        match entry(account)?.get_secret() {
+            Ok(bytes) => Ok(Zeroizing::new(bytes)),
+            Err(keyring::Error::NoEntry) => Err(anyhow!(NOT_FOUND)),
+            Err(e) => Err(anyhow!("Could not read from the system keystore: {}", e)),
+        }
+    }
+
+    pub fn delete(account: &str) -> Result<()> {
+        match entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!("Could not remove the keystore entry: {}", e)),
+        }
+    }
+
+    /// Credential Manager itself does not ask for anything, so the Hello prompt comes
+    /// first. Blocks until the user answers; call it off the UI thread.
+    #[cfg(target_os = "windows")]
+    fn verify_windows_hello(reason: &str) -> Result<()> {
+        use windows::core::HSTRING;
+        use windows::Security::Credentials::UI::{
+            UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+        };
+
+        let availability = UserConsentVerifier::CheckAvailabilityAsync()?.get()?;
+        if availability != UserConsentVerifierAvailability::Available {
+            return Err(anyhow!(
+                "Windows Hello is not set up on this device. Set up a PIN, fingerprint or face sign-in first."
+            ));
+        }
+        let result =
+            UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))?.get()?;
+        if result != UserConsentVerificationResult::Verified {
+            return Err(anyhow!(
+                "Windows Hello verification was cancelled or failed."
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod platform {
+    use super::{NOT_FOUND, SERVICE};
+    use anyhow::{anyhow, Result};
+    use security_framework::passwords::{self, AccessControlOptions, PasswordOptions};
+    use zeroize::Zeroizing;
+
+    /// `errSecItemNotFound`.
+    const ERR_ITEM_NOT_FOUND: i32 = -25300;
+
+    fn options(account: &str) -> PasswordOptions {
+        let mut options = PasswordOptions::new_generic_password(SERVICE, account);
+        options.use_protected_keychain();
+        options
+    }
+
+    pub fn store(account: &str, secret: &[u8; 32]) -> Result<()> {
+        // An item's access control cannot be changed by an update, so any earlier item
+        // goes first.
+        let _ = passwords::delete_generic_password_options(options(account));
+        let mut options = options(account);
+        options.set_access_control_options(AccessControlOptions::USER_PRESENCE);
+        passwords::set_generic_password_options(secret, options)
+            .map_err(|e| anyhow!("Could not save to the keychain: {}", e))
+    }
+
+    pub fn load(account: &str, _reason: &str) -> Result<Zeroizing<Vec<u8>>> {
+        // The access control makes the system show its Touch ID / password sheet here.
+        match passwords::generic_password(options(account)) {
+            Ok(bytes) => Ok(Zeroizing::new(bytes)),
+            Err(e) if e.code() == ERR_ITEM_NOT_FOUND => Err(anyhow!(NOT_FOUND)),
+            Err(e) => Err(anyhow!("Could not read from the keychain: {}", e)),
+        }
+    }
+
+    pub fn delete(account: &str) -> Result<()> {
+        match passwords::delete_generic_password_options(options(account)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.code() == ERR_ITEM_NOT_FOUND => Ok(()),
+            Err(e) => Err(anyhow!("Could not remove the keychain entry: {}", e)),
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+mod platform {
+    use anyhow::{anyhow, Result};
+    use zeroize::Zeroizing;
+
+    const UNSUPPORTED: &str = "Auto-unlock is not available on this platform.";
+
+    pub fn store(_account: &str, _secret: &[u8; 32]) -> Result<()> {
+        Err(anyhow!(UNSUPPORTED))
+    }
+
+    pub fn load(_account: &str, _reason: &str) -> Result<Zeroizing<Vec<u8>>> {
+        Err(anyhow!(UNSUPPORTED))
+    }
+
+    pub fn delete(_account: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+// --- END OF FILE os_keystore.rs ---
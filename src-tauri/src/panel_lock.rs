@@ -0,0 +1,367 @@
+// --- START OF FILE panel_lock.rs ---
+
+// ==========================================
+// --- PANEL PASSCODE ---
+// ==========================================
+// An unlocked vault stays unlocked until auto-lock or logout, which can be a long time
+// for a machine left on a desk. Panels showing sensitive content (clipboard history,
+// notes) can additionally ask for a short PIN once they have been idle for N minutes.
+//
+// Entering the PIN issues a random panel token. The backend commands that return panel
+// content refuse to answer without a token that is fresh for that vault and panel, so
+// the check cannot be skipped by a modified frontend. Each successful use refreshes the
+// token; after `idle_minutes` without use it expires and the PIN is asked again.
+//
+// The settings live in plaintext next to the keychain (`panel_lock.json`) with the PIN
+// stored as an Argon2id hash. They only guard an unlocked session: the content itself
+// stays encrypted under the vault key, which the PIN has nothing to do with. Changing
+// the settings therefore needs the vault password, not the PIN.
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{rand_core::OsRng as Argon2OsRng, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const CONFIG_FILE_NAME: &str = "panel_lock.json";
+pub const MIN_PIN_LEN: usize = 4;
+pub const MAX_PIN_LEN: usize = 12;
+/// Wrong PINs allowed per session before the vault must be locked and unlocked again.
+pub const MAX_PIN_ATTEMPTS: u32 = 5;
+const MAX_IDLE_MINUTES: u32 = 24 * 60;
+
+/// Small on purpose: a PIN has little entropy to protect, and it is entered often.
+const PIN_KDF: (u32, u32, u32) = (19_456, 2, 1);
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Panel {
+    Clipboard,
+    Notes,
+}
+
+impl Panel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Panel::Clipboard => "clipboard",
+            Panel::Notes => "notes",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelRule {
+    pub panel: Panel,
+    /// Minutes without use before the PIN is asked again.
+    pub idle_minutes: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PanelLockConfig {
+    #[serde(default)]
+    pin_hash: Option<String>,
+    #[serde(default)]
+    rules: Vec<PanelRule>,
+}
+
+/// What the settings screen and the panels need to know.
+#[derive(Serialize, Debug, Clone)]
+pub struct PanelLockStatus {
+    pub pin_set: bool,
+    pub rules: Vec<PanelRule>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PanelToken {
+    pub token: String,
+    pub panel: Panel,
+    pub idle_minutes: u32,
+}
+
+impl PanelLockConfig {
+    pub fn status(&self) -> PanelLockStatus {
+        PanelLockStatus {
+            pin_set: self.pin_hash.is_some(),
+            rules: self.rules.clone(),
+        }
+    }
+
+    /// The rule gating `panel`, if a PIN is set and the panel is protected.
+    pub fn rule_for(&self, panel: Panel) -> Option<PanelRule> {
+        self.pin_hash.as_ref()?;
+        self.rules.iter().copied().find(|r| r.panel == panel)
+    }
+
+    /// Replaces the PIN (`Some`) and the protected panels. A config with rules but no
+    /// PIN is refused: it would look protected without being so.
+    pub fn update(&mut self, pin: Option<&str>, rules: Vec<PanelRule>) -> Result<()> {
+        if let Some(pin) = pin {
+            self.pin_hash = Some(hash_pin(pin)?);
+        }
+        let mut seen = Vec::new();
+        for rule in &rules {
+            if !(1..=MAX_IDLE_MINUTES).contains(&rule.idle_minutes) {
+                return Err(anyhow!(
+                    "Idle time must be between 1 and {} minutes",
+                    MAX_IDLE_MINUTES
+                ));
+            }
+            if seen.contains(&rule.panel) {
+                return Err(anyhow!("Panel '{}' listed twice", rule.panel.as_str()));
+            }
+            seen.push(rule.panel);
+        }
+        if !rules.is_empty() && self.pin_hash.is_none() {
+            return Err(anyhow!("Set a PIN before protecting panels"));
+        }
+        self.rules = rules;
+        Ok(())
+    }
+
+    /// Turns the feature off entirely.
+    pub fn clear(&mut self) {
+        self.pin_hash = None;
+        self.rules.clear();
+    }
+
+    pub fn verify_pin(&self, pin: &str) -> bool {
+        let Some(stored) = &self.pin_hash else {
+            return false;
+        };
+        PasswordHash::new(stored)
+            .map(|parsed| {
+                pin_hasher()
+                    .verify_password(pin.as_bytes(), &parsed)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    }
+}
+
+fn pin_hasher() -> Argon2<'static> {
+    let (m, t, p) = PIN_KDF;
+    let params = Params::new(m, t, p, None).expect("static Argon2 params are valid");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+fn validate_pin(pin: &str) -> Result<()> {
+    if !(MIN_PIN_LEN..=MAX_PIN_LEN).contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(anyhow!(
+            "PIN must be {} to {} digits",
+            MIN_PIN_LEN,
+            MAX_PIN_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn hash_pin(pin: &str) -> Result<String> {
+    validate_pin(pin)?;
+    let salt = SaltString::generate(&mut Argon2OsRng);
+    pin_hasher()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| anyhow!("Failed to hash PIN: {}", e))
+}
+
+// ==========================================
+// --- STORAGE ---
+// ==========================================
+
+/// A vault without the file has no protected panels. A corrupt file is an error rather
+/// than "unprotected", so damaging it does not silently open the panels.
+pub fn load_config(vault_dir: &Path) -> Result<PanelLockConfig> {
+    let path = vault_dir.join(CONFIG_FILE_NAME);
+    if !path.exists() {
+        return Ok(PanelLockConfig::default());
+    }
+    let data = fs::read(&path)?;
+    serde_json::from_slice(&data).map_err(|_| anyhow!("Panel lock settings are corrupted"))
+}
+
+pub fn save_config(vault_dir: &Path, config: &PanelLockConfig) -> Result<()> {
+    let path = vault_dir.join(CONFIG_FILE_NAME);
+    if config.pin_hash.is_none() && config.rules.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(config)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+// ==========================================
+// --- SESSION TOKENS ---
+// ==========================================
+
+struct TokenEntry {
+    vault_id: String,
+    panel: Panel,
+    last_used: Instant,
+}
+
+/// Issued panel tokens and the wrong-PIN counter for the current session. Lives in
+/// `SessionState` and is reset on logout.
+#[derive(Default)]
+pub struct PanelSessions {
+    tokens: HashMap<String, TokenEntry>,
+    failures: u32,
+}
+
+impl PanelSessions {
+    pub fn issue(&mut self, vault_id: &str, panel: Panel, now: Instant) -> Result<String> {
+        let mut raw = [0u8; 32];
+        OsRng
+            .try_fill_bytes(&mut raw)
+            .map_err(|e| anyhow!("RNG failure: {}", e))?;
+        let token = raw.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        // One live token per panel: unlocking again replaces the previous one.
+        self.revoke(vault_id, panel);
+        self.tokens.insert(
+            token.clone(),
+            TokenEntry {
+                vault_id: vault_id.to_string(),
+                panel,
+                last_used: now,
+            },
+        );
+        self.failures = 0;
+        Ok(token)
+    }
+
+    /// Accepts `token` for this vault and panel if it was used within `idle`, and
+    /// refreshes it. Expired tokens are dropped.
+    pub fn check(
+        &mut self,
+        token: &str,
+        vault_id: &str,
+        panel: Panel,
+        idle: Duration,
+        now: Instant,
+    ) -> bool {
+        let Some(entry) = self.tokens.get_mut(token) else {
+            return false;
+        };
+        if entry.vault_id != vault_id || entry.panel != panel {
+            return false;
+        }
+        if now.saturating_duration_since(entry.last_used) > idle {
+            self.tokens.remove(token);
+            return false;
+        }
+        entry.last_used = now;
+        true
+    }
+
+    pub fn revoke(&mut self, vault_id: &str, panel: Panel) {
+        self.tokens
+            .retain(|_, e| !(e.vault_id == vault_id && e.panel == panel));
+    }
+
+    /// Counts a wrong PIN. Returns the attempts left (0 = locked out for this session).
+    pub fn record_failure(&mut self) -> u32 {
+        self.failures = self.failures.saturating_add(1);
+        MAX_PIN_ATTEMPTS.saturating_sub(self.failures)
+    }
+
+    pub fn locked_out(&self) -> bool {
+        self.failures >= MAX_PIN_ATTEMPTS
+    }
+
+    pub fn clear(&mut self) {
+        self.tokens.clear();
+        self.failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_rules_and_verification() {
+        let mut config = PanelLockConfig::default();
+        let rules = vec![PanelRule {
+            panel: Panel::Notes,
+            idle_minutes: 5,
+        }];
+        assert!(config.update(None, rules.clone()).is_err());
+        assert!(config.update(Some("12a4"), rules.clone()).is_err());
+        assert!(config.update(Some("123"), rules.clone()).is_err());
+
+        config.update(Some("4821"), rules).unwrap();
+        assert!(config.verify_pin("4821"));
+        assert!(!config.verify_pin("4822"));
+        assert!(config.rule_for(Panel::Notes).is_some());
+        assert!(config.rule_for(Panel::Clipboard).is_none());
+
+        config.clear();
+        assert!(config.rule_for(Panel::Notes).is_none());
+    }
+
+    #[test]
+    fn test_tokens_expire_when_idle() {
+        let mut sessions = PanelSessions::default();
+        let start = Instant::now();
+        let idle = Duration::from_secs(60);
+        let token = sessions.issue("local", Panel::Notes, start).unwrap();
+
+        assert!(!sessions.check(&token, "local", Panel::Clipboard, idle, start));
+        assert!(!sessions.check(&token, "other", Panel::Notes, idle, start));
+        // Use keeps it alive past the original deadline...
+        assert!(sessions.check(
+            &token,
+            "local",
+            Panel::Notes,
+            idle,
+            start + Duration::from_secs(50)
+        ));
+        assert!(sessions.check(
+            &token,
+            "local",
+            Panel::Notes,
+            idle,
+            start + Duration::from_secs(100)
+        ));
+        // ...idling does not.
+        assert!(!sessions.check(
+            &token,
+            "local",
+            Panel::Notes,
+            idle,
+            start + Duration::from_secs(161)
+        ));
+        assert!(!sessions.check(
+            &token,
+            "local",
+            Panel::Notes,
+            idle,
+            start + Duration::from_secs(162)
+        ));
+    }
+
+    #[test]
+    fn test_failures_lock_out_until_cleared() {
+        let mut sessions = PanelSessions::default();
+        for left in (0..MAX_PIN_ATTEMPTS).rev() {
+            assert_eq!(sessions.record_failure(), left);
+        }
+        assert!(sessions.locked_out());
+        sessions.clear();
+        assert!(!sessions.locked_out());
+    }
+}
+
+// --- END OF FILE panel_lock.rs ---
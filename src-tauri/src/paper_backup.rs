@@ -0,0 +1,438 @@
+// --- START OF FILE paper_backup.rs ---
+
+// ==========================================
+// --- PAPER BACKUP (COLD STORAGE) ---
+// ==========================================
+// Small secrets — keyfiles, recovery codes, key shares — encrypted with a passphrase and
+// printed as pages of text with a QR code of the same text, for a drawer or a safe.
+//
+// Encrypted payload: version (1) | salt (16) | nonce (12) | AES-256-GCM ciphertext
+//   key = Argon2id(UTF-8 passphrase, salt, 64 MiB, 3 iterations, 4 lanes, 32 bytes)
+//   associated data = version | salt | nonce
+// The payload is cut into pages of up to PAGE_BYTES bytes, each printed as one line:
+//   `QRE-PAPER:1:<set>:<page>:<pages>:<data>:<check>`
+//   set   — 4 bytes of SHA-256 over the whole payload (hex): which backup the page
+//           belongs to, and a check on the reassembled payload
+//   data  — the page's bytes in Base32 (RFC 4648, no padding)
+//   check — 4 bytes of SHA-256 over everything before it (hex), to catch typos
+// Only upper-case letters, digits and ':' are used, so the QR codes use the compact
+// alphanumeric mode, and whitespace is ignored when pages are typed back in.
+// The printout repeats this description, so the secret can be rebuilt without QRE.
+// Restoring takes the page text: QR codes are read with any scanner (phone camera,
+// webcam app) and the result pasted, or the printed lines typed in.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use data_encoding::{BASE32_NOPAD, HEXUPPER};
+use quick_xml::escape::escape;
+use rand::{rngs::OsRng, TryRngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+const PAGE_PREFIX: &str = "QRE-PAPER:1";
+const PAYLOAD_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const SET_ID_LEN: usize = 4;
+const CHECK_LEN: usize = 4;
+/// Same cost as a freshly created keychain (64 MB / 3 iterations / 4 lanes).
+const KDF: (u32, u32, u32) = (65536, 3, 4);
+/// Payload bytes per page: about 360 characters, a QR code that prints and scans well.
+const PAGE_BYTES: usize = 224;
+/// Paper is for small secrets; 4 KB is already around 20 pages.
+pub const MAX_SECRET_BYTES: usize = 4096;
+/// The printout can be attacked offline for as long as it exists.
+pub const MIN_PASSPHRASE_LEN: usize = 12;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct PaperPage {
+    /// 1-based.
+    pub index: usize,
+    pub count: usize,
+    /// The line to scan or type back in.
+    pub text: String,
+    pub qr_svg: String,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct PaperBackup {
+    /// Printed on every page, so pages of different backups are not mixed up.
+    pub set_id: String,
+    pub pages: Vec<PaperPage>,
+    /// The whole printout with instructions, one page per sheet.
+    pub html: String,
+}
+
+// ==========================================
+// --- EXPORT ---
+// ==========================================
+
+/// Encrypts `secret` with `passphrase` and lays it out as printable pages.
+pub fn export(secret: &[u8], passphrase: &str, label: &str) -> Result<PaperBackup> {
+    if secret.is_empty() {
+        return Err(anyhow!("There is nothing to back up."));
+    }
+    if secret.len() > MAX_SECRET_BYTES {
+        return Err(anyhow!(
+            "Paper backups hold up to {} bytes. Back up larger files digitally.",
+            MAX_SECRET_BYTES
+        ));
+    }
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!(
+            "The backup passphrase must be at least {} characters.",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    let payload = seal(secret, passphrase)?;
+    let set_id = set_id(&payload);
+    let count = payload.len().div_ceil(PAGE_BYTES);
+    let pages = payload
+        .chunks(PAGE_BYTES)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let text = page_text(&set_id, i + 1, count, chunk);
+            let qr = crate::qr::generate_qr(crate::qr::QrOptions {
+                text: text.clone(),
+                fg_color: "#000000".to_string(),
+                bg_color: "#ffffff".to_string(),
+                ecc: crate::qr::ErrorCorrectionLevel::Medium,
+                border: 4,
+            })?;
+            Ok(PaperPage {
+                index: i + 1,
+                count,
+                text,
+                qr_svg: qr.svg,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let html = render_html(&set_id, label, &pages);
+    Ok(PaperBackup {
+        set_id,
+        pages,
+        html,
+    })
+}
+
+fn seal(secret: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng
+        .try_fill_bytes(&mut salt)
+        .and_then(|_| OsRng.try_fill_bytes(&mut nonce))
+        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    let mut payload = vec![PAYLOAD_VERSION];
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    let cipher = cipher(passphrase, &salt)?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: secret,
+                aad: &payload,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt the backup"))?;
+    payload.extend_from_slice(&ciphertext);
+    Ok(payload)
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm> {
+    let (memory, iterations, lanes) = KDF;
+    let params = Params::new(memory, iterations, lanes, Some(32))
+        .map_err(|e| anyhow!("KDF param error: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(|_| anyhow!("Hashing failed"))?;
+    Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))
+}
+
+fn set_id(payload: &[u8]) -> String {
+    HEXUPPER.encode(&Sha256::digest(payload)[..SET_ID_LEN])
+}
+
+fn checksum(body: &str) -> String {
+    HEXUPPER.encode(&Sha256::digest(body.as_bytes())[..CHECK_LEN])
+}
+
+fn page_text(set_id: &str, index: usize, count: usize, data: &[u8]) -> String {
+    let body = format!(
+        "{}:{}:{}:{}:{}",
+        PAGE_PREFIX,
+        set_id,
+        index,
+        count,
+        BASE32_NOPAD.encode(data)
+    );
+    format!("{}:{}", body, checksum(&body))
+}
+
+// ==========================================
+// --- PRINTOUT ---
+// ==========================================
+
+fn render_html(set_id: &str, label: &str, pages: &[PaperPage]) -> String {
+    let label = escape(label.trim());
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>QRE paper backup</title>\
+         <style>body{font-family:sans-serif;margin:0}\
+         section{padding:15mm;page-break-after:always}\
+         .qr{width:90mm;height:90mm}\
+         pre{font-size:11pt;line-height:1.5;white-space:pre-wrap}\
+         </style></head><body>\n",
+    );
+    for page in pages {
+        html.push_str(&format!(
+            "<section><h1>QRE paper backup {}</h1><p>{}Page {} of {}</p>\
+             <div class=\"qr\">{}</div><pre>{}</pre></section>\n",
+            set_id,
+            if label.is_empty() {
+                String::new()
+            } else {
+                format!("{} &middot; ", label)
+            },
+            page.index,
+            page.count,
+            page.qr_svg
+                .trim_start_matches("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"),
+            grouped(&page.text)
+        ));
+    }
+    html.push_str(&format!(
+        "<section><h1>Restoring backup {}</h1><pre>{}</pre></section>\n</body></html>\n",
+        set_id,
+        escape(instructions(pages.len()).as_str())
+    ));
+    html
+}
+
+/// Groups of 5 characters, 8 groups a line, for typing the page in by hand.
+fn grouped(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(40)
+        .map(|line| {
+            line.chunks(5)
+                .map(|group| group.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn instructions(count: usize) -> String {
+    let (memory, iterations, lanes) = KDF;
+    format!(
+        "You need all {count} page(s) of this backup and its passphrase.\n\n\
+         With QRE: open Paper backup > Restore, scan the QR code of every page (or type the \
+         text under it; spaces and line breaks do not matter), enter the passphrase and \
+         choose where to save the secret. The pages can be given in any order.\n\n\
+         Without QRE: every page is one line\n  \
+         {PAGE_PREFIX}:<set>:<page>:<pages>:<data>:<check>\n\
+         <check> is the first {CHECK_LEN} bytes, in hex, of SHA-256 over the line before the \
+         last ':'. Base32-decode (RFC 4648) the <data> of pages 1 to {count} and join them. \
+         The result is\n  \
+         version (1 byte, = {PAYLOAD_VERSION}) | salt ({SALT_LEN} bytes) | nonce ({NONCE_LEN} \
+         bytes) | ciphertext\n\
+         and the first {SET_ID_LEN} bytes of its SHA-256, in hex, are <set>. Derive the key \
+         with Argon2id (version 0x13) from the UTF-8 passphrase and the salt: {memory} KiB, \
+         {iterations} iterations, {lanes} lanes, 32 bytes. Decrypt the ciphertext with \
+         AES-256-GCM using the nonce; the associated data is the first {} bytes \
+         (version, salt and nonce). The ciphertext ends with the 16-byte tag.\n",
+        1 + SALT_LEN + NONCE_LEN
+    )
+}
+
+// ==========================================
+// --- IMPORT ---
+// ==========================================
+
+/// Rebuilds the secret from the scanned or typed pages, in any order. Each entry of
+/// `pages` may hold one page or several.
+pub fn import(pages: &[String], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let text: String = pages
+        .iter()
+        .flat_map(|p| p.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let starts: Vec<usize> = text.match_indices(PAGE_PREFIX).map(|(i, _)| i).collect();
+    if starts.is_empty() {
+        return Err(anyhow!("No QRE paper backup page was found."));
+    }
+
+    let mut set: Option<(String, usize)> = None;
+    let mut found: Vec<Option<Vec<u8>>> = Vec::new();
+    for (n, &start) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(text.len());
+        let page = decode_page(&text[start..end])?;
+        match &set {
+            None => {
+                set = Some((page.set_id.clone(), page.count));
+                found = vec![None; page.count];
+            }
+            Some((id, count)) if *id != page.set_id || *count != page.count => {
+                return Err(anyhow!(
+                    "Page {} belongs to backup {}, not {}. Use the pages of one backup.",
+                    page.index,
+                    page.set_id,
+                    id
+                ));
+            }
+            Some(_) => {}
+        }
+        found[page.index - 1] = Some(page.data);
+    }
+
+    let missing: Vec<String> = found
+        .iter()
+        .enumerate()
+        .filter(|(_, page)| page.is_none())
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Page(s) {} of {} are missing.",
+            missing.join(", "),
+            found.len()
+        ));
+    }
+    let payload: Vec<u8> = found.into_iter().flatten().flatten().collect();
+    if set.is_some_and(|(id, _)| id != set_id(&payload)) {
+        return Err(anyhow!(
+            "The pages do not fit together. Check that every page was scanned completely."
+        ));
+    }
+    open(&payload, passphrase)
+}
+
+struct DecodedPage {
+    set_id: String,
+    index: usize,
+    count: usize,
+    data: Vec<u8>,
+}
+
+fn decode_page(text: &str) -> Result<DecodedPage> {
+    let (body, check) = text
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Not a QRE paper backup page"))?;
+    let parts: Vec<&str> = body.split(':').collect();
+    if parts.len() != 6 || format!("{}:{}", parts[0], parts[1]) != PAGE_PREFIX {
+        return Err(anyhow!("Not a QRE paper backup page"));
+    }
+    if checksum(body) != check {
+        return Err(anyhow!(
+            "Page {} is damaged or mistyped (its check does not match).",
+            parts[3]
+        ));
+    }
+    let number = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|_| anyhow!("Invalid page header"))
+    };
+    let (index, count) = (number(parts[3])?, number(parts[4])?);
+    if index == 0 || index > count || count > MAX_SECRET_BYTES.div_ceil(PAGE_BYTES) + 1 {
+        return Err(anyhow!("Invalid page header"));
+    }
+    Ok(DecodedPage {
+        set_id: parts[2].to_string(),
+        index,
+        count,
+        data: BASE32_NOPAD
+            .decode(parts[5].as_bytes())
+            .map_err(|_| anyhow!("Invalid page data"))?,
+    })
+}
+
+fn open(payload: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let header_len = 1 + SALT_LEN + NONCE_LEN;
+    if payload.len() <= header_len || payload[0] != PAYLOAD_VERSION {
+        return Err(anyhow!(
+            "This backup was made by a newer QRE version or is damaged."
+        ));
+    }
+    let (header, ciphertext) = payload.split_at(header_len);
+    let cipher = cipher(passphrase, &header[1..1 + SALT_LEN])?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(&header[1 + SALT_LEN..]),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow!("Wrong passphrase for this backup."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    #[test]
+    fn test_pages_round_trip_in_any_order_and_typed() {
+        let secret: Vec<u8> = (0..600u32).map(|i| (i * 7 % 251) as u8).collect();
+        let backup = export(&secret, PASSPHRASE, "NAS <keyfile>").unwrap();
+        assert_eq!(backup.pages.len(), 3);
+        assert!(backup
+            .html
+            .contains("NAS &lt;keyfile&gt; &middot; Page 2 of 3"));
+        assert!(backup.pages.iter().all(|p| p
+            .text
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || ":-".contains(c))));
+
+        // Scanned out of order, the last one typed from the printout in lower case.
+        let typed = grouped(&backup.pages[0].text).to_lowercase();
+        let pages = vec![
+            backup.pages[2].text.clone(),
+            format!("{}\n{}", backup.pages[1].text, typed),
+        ];
+        assert_eq!(import(&pages, PASSPHRASE).unwrap().as_slice(), secret);
+        assert!(import(&pages, "wrong passphrase!").is_err());
+    }
+
+    #[test]
+    fn test_damaged_missing_and_foreign_pages_are_named() {
+        let first = export(b"recovery code 1234", PASSPHRASE, "").unwrap();
+        let second = export(&[7u8; 300], PASSPHRASE, "").unwrap();
+        assert_eq!(first.pages.len(), 1);
+
+        let missing = import(&[second.pages[1].text.clone()], PASSPHRASE).unwrap_err();
+        assert_eq!(missing.to_string(), "Page(s) 1 of 2 are missing.");
+
+        let mut typo = second.pages[0].text.clone();
+        typo.replace_range(30..31, if &typo[30..31] == "A" { "B" } else { "A" });
+        let damaged = import(&[typo, second.pages[1].text.clone()], PASSPHRASE).unwrap_err();
+        assert!(damaged.to_string().contains("Page 1 is damaged"));
+
+        let mixed = import(
+            &[first.pages[0].text.clone(), second.pages[1].text.clone()],
+            PASSPHRASE,
+        );
+        assert!(mixed
+            .unwrap_err()
+            .to_string()
+            .contains("Use the pages of one backup"));
+
+        assert!(export(b"x", "short", "").is_err());
+        assert!(export(&[0u8; MAX_SECRET_BYTES + 1], PASSPHRASE, "").is_err());
+    }
+}
+
+// --- END OF FILE paper_backup.rs ---
@@ -0,0 +1,820 @@
+// --- START OF FILE password_audit.rs ---
+
+// ==========================================
+// --- PASSWORD HEALTH AUDIT ---
+// ==========================================
+// Rates every password of a vault for the "security score" dashboard:
+//   - strength: an offline estimate of the guesses a cracker needs, in the spirit of
+//     zxcvbn. The password is split into the cheapest run of guessable pieces (common
+//     passwords, dictionary words with capitals and l33t, keyboard walks, sequences,
+//     repeats, years and dates); whatever no pattern covers is brute-forced at 10
+//     guesses per character. Scores 0-4 use zxcvbn's thresholds (10^3 ... 10^10);
+//   - reuse: the same password on several entries, and near-copies ("Summer2023!" and
+//     "summer2024") that fall together as soon as one of them leaks;
+//   - age: days since the entry was last changed;
+//   - breaches: how often HaveIBeenPwned has seen the password, through the k-anonymity
+//     range API (breach.rs). Only the first 5 characters of each SHA-1 leave the device,
+//     one request per distinct prefix, and only when the caller asks for it.
+//
+// The report names entries and patterns, never passwords, so the dashboard can show it
+// as-is. privacy_report.rs keeps its simpler weak/reused counts for the overview.
+
+use crate::breach;
+use crate::passwords::PasswordVault;
+use crate::wordlist::WORDLIST;
+use anyhow::Result;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::Digest;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::OnceLock;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// Passwords whose entry has not changed for this long are flagged as old.
+pub const OLD_AFTER_DAYS: i64 = 365;
+/// log10 of the guesses where scores 1, 2, 3 and 4 start (zxcvbn's thresholds).
+const SCORE_THRESHOLDS: [f64; 4] = [3.0, 6.0, 8.0, 10.0];
+/// Scores below this are reported as weak.
+const STRONG_SCORE: u8 = 3;
+/// Patterns are looked for in the first characters only; the rest is brute-forced.
+const MAX_ANALYZED_CHARS: usize = 64;
+/// Guesses per character no pattern explains (zxcvbn's brute-force cardinality).
+const BRUTEFORCE_LOG10: f64 = 1.0;
+/// Each extra piece of the password costs the attacker a little more ordering work.
+const PIECE_LOG10: f64 = 0.3;
+/// Near-copies are only flagged when what they share is at least this long.
+const MIN_SIMILAR_BASE: usize = 4;
+/// Pause between range requests, so a large vault does not trip HIBP's 429 throttling.
+const BREACH_REQUEST_GAP: Duration = Duration::from_millis(150);
+
+/// The most common passwords of public breach corpora, most common first. Together with
+/// WORDLIST it is the dictionary of the estimate; the rank is the guess count.
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "password", "12345678", "qwerty", "12345", "1234", "111111", "1234567", "dragon",
+    "123123", "baseball", "abc123", "football", "monkey", "letmein", "696969", "shadow", "master",
+    "666666", "123321", "mustang", "michael", "654321", "superman", "1qaz2wsx", "7777777",
+    "121212", "000000", "qazwsx", "123qwe", "killer", "trustno1", "jordan", "jennifer", "zxcvbnm",
+    "asdfgh", "hunter", "buster", "soccer", "harley", "batman", "andrew", "tigger", "sunshine",
+    "iloveyou", "2000", "charlie", "robert", "thomas", "hockey", "ranger", "daniel", "starwars",
+    "klaster", "112233", "george", "computer", "michelle", "jessica", "pepper", "1111", "zxcvbn",
+    "555555", "11111111", "131313", "freedom", "777777", "pass", "maggie", "159753", "aaaaaa",
+    "ginger", "princess", "joshua", "cheese", "amanda", "summer", "love", "ashley", "nicole",
+    "chelsea", "biteme", "matthew", "access", "yankees", "dallas", "austin", "thunder", "taylor",
+    "matrix", "welcome", "admin", "login", "passw0rd", "hello", "secret", "solo", "flower",
+    "lovely", "whatever", "winter", "spring", "autumn", "default", "changeme", "guest", "root",
+    "test",
+];
+
+/// Keyboard rows for walks like "asdfgh"; digits are covered by sequences.
+const KEYBOARD_ROWS: &[&str] = &[
+    "qwertyuiop",
+    "asdfghjkl",
+    "zxcvbnm",
+    "qwertzuiop",
+    "yxcvbnm",
+    "azertyuiop",
+    "qsdfghjklm",
+    "wxcvbn",
+];
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WeakPattern {
+    CommonPassword,
+    DictionaryWord,
+    KeyboardWalk,
+    Sequence,
+    Repeat,
+    Date,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Issue {
+    /// Strength score below 3.
+    Weak,
+    /// Another entry has the same password.
+    Reused,
+    /// Another entry has a near-copy of the password.
+    Similar,
+    /// Not changed for `OLD_AFTER_DAYS`.
+    Old,
+    /// Seen in a breach by HaveIBeenPwned.
+    Breached,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Strength {
+    /// 0 (guessed at once) to 4 (out of reach), on zxcvbn's scale.
+    pub score: u8,
+    /// log10 of the estimated number of guesses.
+    pub guesses_log10: f64,
+    /// The patterns the estimate is made of, in the order they appear.
+    pub patterns: Vec<WeakPattern>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EntryAudit {
+    pub id: String,
+    pub service: String,
+    pub username: String,
+    pub strength: Strength,
+    /// IDs of the entries with the same password.
+    pub reused_with: Vec<String>,
+    /// IDs of the entries with a near-copy of it.
+    pub similar_to: Vec<String>,
+    pub age_days: i64,
+    /// Times HaveIBeenPwned has seen the password; `None` when not checked.
+    pub breach_count: Option<u64>,
+    pub issues: Vec<Issue>,
+    /// 0-100: what the entry adds to the vault score.
+    pub health: u8,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PasswordAuditReport {
+    pub generated_at: i64,
+    /// 0-100: the average health of the entries with a password (100 when there are none).
+    pub score: u8,
+    pub total: usize,
+    pub without_password: usize,
+    pub weak: usize,
+    pub reused: usize,
+    pub similar: usize,
+    pub old: usize,
+    /// `None` when the breach check did not run.
+    pub breached: Option<usize>,
+    /// Why the breach check did not run, if it did not.
+    pub breach_note: Option<String>,
+    /// Entries with a password per strength score, 0 to 4.
+    pub strength_distribution: [usize; 5],
+    /// Entries with a password, least healthy first.
+    pub entries: Vec<EntryAudit>,
+}
+
+/// Breach counts by SHA-1 (uppercase hex) of the password; 0 for passwords not found.
+pub type BreachCounts = HashMap<String, u64>;
+
+// ==========================================
+// --- AUDIT ---
+// ==========================================
+
+/// Audits every entry of `vault`. `breaches` comes from `check_breaches`; without it the
+/// report leaves the breach columns empty and says why in `breach_note`.
+pub fn audit(
+    vault: &PasswordVault,
+    breaches: Option<&BreachCounts>,
+    breach_note: Option<String>,
+    now: i64,
+) -> PasswordAuditReport {
+    let with_password: Vec<_> = vault
+        .entries
+        .iter()
+        .filter(|e| !e.password.is_empty())
+        .collect();
+
+    // Bases of near-copies, kept in wiping buffers and borrowed by the maps below.
+    let bases: Vec<Zeroizing<String>> = with_password
+        .iter()
+        .map(|e| similarity_base(&e.password))
+        .collect();
+    let mut same: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut near: HashMap<&str, Vec<(&str, &str)>> = HashMap::new();
+    for (entry, base) in with_password.iter().zip(&bases) {
+        same.entry(entry.password.as_str())
+            .or_default()
+            .push(entry.id.as_str());
+        if base.chars().count() >= MIN_SIMILAR_BASE {
+            near.entry(base.as_str())
+                .or_default()
+                .push((entry.id.as_str(), entry.password.as_str()));
+        }
+    }
+
+    let mut entries: Vec<EntryAudit> = with_password
+        .iter()
+        .zip(&bases)
+        .map(|(entry, base)| {
+            let strength = estimate(&entry.password);
+            let others = |ids: &[&str]| -> Vec<String> {
+                ids.iter()
+                    .filter(|id| **id != entry.id)
+                    .map(|id| id.to_string())
+                    .collect()
+            };
+            let reused_with = others(&same[entry.password.as_str()]);
+            let similar_to = near
+                .get(base.as_str())
+                .map(|group| {
+                    let ids: Vec<&str> = group
+                        .iter()
+                        .filter(|(_, password)| *password != entry.password)
+                        .map(|(id, _)| *id)
+                        .collect();
+                    others(&ids)
+                })
+                .unwrap_or_default();
+            let changed_at = entry.updated_at.max(entry.created_at);
+            let age_days = (now - changed_at).max(0) / 86_400;
+            let breach_count =
+                breaches.map(|counts| counts.get(&sha1_hex(&entry.password)).copied().unwrap_or(0));
+
+            let mut issues = Vec::new();
+            if strength.score < STRONG_SCORE {
+                issues.push(Issue::Weak);
+            }
+            if !reused_with.is_empty() {
+                issues.push(Issue::Reused);
+            }
+            if !similar_to.is_empty() {
+                issues.push(Issue::Similar);
+            }
+            if age_days >= OLD_AFTER_DAYS {
+                issues.push(Issue::Old);
+            }
+            if breach_count.is_some_and(|c| c > 0) {
+                issues.push(Issue::Breached);
+            }
+            let health = health(strength.score, &issues);
+            EntryAudit {
+                id: entry.id.clone(),
+                service: entry.service.clone(),
+                username: entry.username.clone(),
+                strength,
+                reused_with,
+                similar_to,
+                age_days,
+                breach_count,
+                issues,
+                health,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        a.health
+            .cmp(&b.health)
+            .then_with(|| a.service.to_lowercase().cmp(&b.service.to_lowercase()))
+    });
+
+    let count = |issue: Issue| entries.iter().filter(|e| e.issues.contains(&issue)).count();
+    let mut strength_distribution = [0usize; 5];
+    for entry in &entries {
+        strength_distribution[entry.strength.score as usize] += 1;
+    }
+    let score = if entries.is_empty() {
+        100
+    } else {
+        let sum: usize = entries.iter().map(|e| e.health as usize).sum();
+        ((sum as f64 / entries.len() as f64).round()) as u8
+    };
+    PasswordAuditReport {
+        generated_at: now,
+        score,
+        total: vault.entries.len(),
+        without_password: vault.entries.len() - entries.len(),
+        weak: count(Issue::Weak),
+        reused: count(Issue::Reused),
+        similar: count(Issue::Similar),
+        old: count(Issue::Old),
+        breached: breaches.map(|_| count(Issue::Breached)),
+        breach_note: if breaches.is_some() {
+            None
+        } else {
+            breach_note
+        },
+        strength_distribution,
+        entries,
+    }
+}
+
+/// Strength sets the ceiling; a breach zeroes it, reuse and age pull it down.
+fn health(score: u8, issues: &[Issue]) -> u8 {
+    let mut health = score * 25;
+    if issues.contains(&Issue::Breached) {
+        return 0;
+    }
+    if issues.contains(&Issue::Reused) {
+        health = health.min(40);
+    }
+    if issues.contains(&Issue::Similar) {
+        health = health.min(60);
+    }
+    if issues.contains(&Issue::Old) {
+        health = health.saturating_sub(10);
+    }
+    health
+}
+
+/// What near-copies have in common: digits and symbols around the password dropped,
+/// lowercase, l33t undone ("Summer2023!" and "$ummer2024" both give "summer").
+fn similarity_base(password: &str) -> Zeroizing<String> {
+    let core = password
+        .trim_start_matches(|c: char| !c.is_alphabetic() && !"@$!(|+".contains(c))
+        .trim_end_matches(|c: char| !c.is_alphabetic());
+    Zeroizing::new(core.chars().map(|c| unleet(lower(c))).collect())
+}
+
+fn sha1_hex(password: &str) -> String {
+    data_encoding::HEXUPPER.encode(&Sha1::digest(password.as_bytes()))
+}
+
+// ==========================================
+// --- BREACH LOOKUP ---
+// ==========================================
+
+/// Looks every distinct password of the vault up in HaveIBeenPwned, one range request
+/// per SHA-1 prefix. Blocking (it sleeps between requests), so call it from a worker
+/// thread. Fails as a whole: a partial answer would show unchecked passwords as clean.
+pub fn check_breaches(vault: &PasswordVault) -> Result<BreachCounts> {
+    let hashes: BTreeSet<String> = vault
+        .entries
+        .iter()
+        .filter(|e| !e.password.is_empty())
+        .map(|e| sha1_hex(&e.password))
+        .collect();
+    let mut by_prefix: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for hash in &hashes {
+        let (prefix, suffix) = hash.split_at(5);
+        by_prefix.entry(prefix).or_default().push(suffix);
+    }
+
+    let mut counts = BreachCounts::new();
+    for (i, (prefix, suffixes)) in by_prefix.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(BREACH_REQUEST_GAP);
+        }
+        let range = tauri::async_runtime::block_on(breach::fetch_pwned_range(prefix))?;
+        for suffix in suffixes {
+            let count = range.get(*suffix).copied().unwrap_or(0);
+            counts.insert(format!("{}{}", prefix, suffix), count);
+        }
+    }
+    Ok(counts)
+}
+
+// ==========================================
+// --- STRENGTH ESTIMATE ---
+// ==========================================
+
+/// A guessable piece of the password: `chars[start..end]`.
+struct Piece {
+    start: usize,
+    end: usize,
+    guesses_log10: f64,
+    pattern: WeakPattern,
+}
+
+/// Estimates how many guesses `password` takes; see the top of the file.
+pub fn estimate(password: &str) -> Strength {
+    let all: Vec<char> = password.chars().collect();
+    if all.is_empty() {
+        return Strength {
+            score: 0,
+            guesses_log10: 0.0,
+            patterns: Vec::new(),
+        };
+    }
+    let chars = &all[..all.len().min(MAX_ANALYZED_CHARS)];
+    let mut pieces = Vec::new();
+    dictionary_pieces(chars, &mut pieces);
+    sequence_pieces(chars, &mut pieces);
+    repeat_pieces(chars, &mut pieces);
+    keyboard_pieces(chars, &mut pieces);
+    date_pieces(chars, &mut pieces);
+
+    // Cheapest cover of chars[..i]: best[i] = (log10 guesses, piece that ends at i).
+    let n = chars.len();
+    let mut best: Vec<(f64, Option<usize>)> = vec![(0.0, None); n + 1];
+    for i in 1..=n {
+        best[i] = (best[i - 1].0 + BRUTEFORCE_LOG10, None);
+        for (index, piece) in pieces.iter().enumerate().filter(|(_, p)| p.end == i) {
+            let cost = best[piece.start].0 + piece.guesses_log10 + PIECE_LOG10;
+            if cost < best[i].0 {
+                best[i] = (cost, Some(index));
+            }
+        }
+    }
+    let mut patterns = Vec::new();
+    let mut i = n;
+    while i > 0 {
+        match best[i].1 {
+            Some(index) => {
+                patterns.push(pieces[index].pattern);
+                i = pieces[index].start;
+            }
+            None => i -= 1,
+        }
+    }
+    patterns.reverse();
+    patterns.dedup();
+
+    let guesses_log10 = best[n].0 + (all.len() - n) as f64 * BRUTEFORCE_LOG10;
+    let score = SCORE_THRESHOLDS
+        .iter()
+        .filter(|&&threshold| guesses_log10 >= threshold)
+        .count() as u8;
+    Strength {
+        score,
+        guesses_log10: (guesses_log10 * 100.0).round() / 100.0,
+        patterns,
+    }
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '8' => 'b',
+        '(' => 'c',
+        '3' => 'e',
+        '6' | '9' => 'g',
+        '1' | '!' => 'i',
+        '|' => 'l',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' | '+' => 't',
+        '2' => 'z',
+        other => other,
+    }
+}
+
+fn common_ranks() -> &'static HashMap<&'static str, usize> {
+    static RANKS: OnceLock<HashMap<&'static str, usize>> = OnceLock::new();
+    RANKS.get_or_init(|| {
+        COMMON_PASSWORDS
+            .iter()
+            .enumerate()
+            .map(|(rank, word)| (*word, rank + 1))
+            .collect()
+    })
+}
+
+fn dictionary() -> &'static HashSet<&'static str> {
+    static WORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| WORDLIST.iter().copied().filter(|w| w.len() >= 4).collect())
+}
+
+/// Common passwords and dictionary words, also with capitals or l33t substitutions.
+fn dictionary_pieces(chars: &[char], pieces: &mut Vec<Piece>) {
+    let lowered: Vec<char> = chars.iter().map(|&c| lower(c)).collect();
+    let unleeted: Vec<char> = lowered.iter().map(|&c| unleet(c)).collect();
+    let words = dictionary();
+    for start in 0..chars.len() {
+        for end in start + 3..=chars.len().min(start + 16) {
+            for (candidate, leet) in [(&lowered, false), (&unleeted, true)] {
+                let word: Zeroizing<String> =
+                    Zeroizing::new(candidate[start..end].iter().collect());
+                let (rank, pattern) = if let Some(&rank) = common_ranks().get(word.as_str()) {
+                    (rank, WeakPattern::CommonPassword)
+                } else if words.contains(word.as_str()) {
+                    (WORDLIST.len(), WeakPattern::DictionaryWord)
+                } else {
+                    continue;
+                };
+                let original = &chars[start..end];
+                let substitutions = if leet {
+                    original
+                        .iter()
+                        .zip(&unleeted[start..end])
+                        .filter(|(o, u)| lower(**o) != **u)
+                        .count()
+                } else {
+                    0
+                };
+                if leet && substitutions == 0 {
+                    continue;
+                }
+                pieces.push(Piece {
+                    start,
+                    end,
+                    guesses_log10: (rank as f64).log10()
+                        + case_variations_log10(original)
+                        + substitutions as f64 * 2f64.log10(),
+                    pattern,
+                });
+            }
+        }
+    }
+}
+
+/// Capitalized or all caps: one extra guess; other mixes: a guess per capital position.
+fn case_variations_log10(word: &[char]) -> f64 {
+    let upper = word.iter().filter(|c| c.is_uppercase()).count();
+    let letters = word.iter().filter(|c| c.is_alphabetic()).count();
+    if upper == 0 {
+        0.0
+    } else if upper == letters || (upper == 1 && word[0].is_uppercase()) {
+        2f64.log10()
+    } else {
+        upper as f64 * 2f64.log10()
+    }
+}
+
+/// Runs of at least 3 characters stepping by one: "abcd", "9876".
+fn sequence_pieces(chars: &[char], pieces: &mut Vec<Piece>) {
+    let lowered: Vec<char> = chars.iter().map(|&c| lower(c)).collect();
+    let mut start = 0;
+    while start + 2 < lowered.len() {
+        let step = lowered[start + 1] as i64 - lowered[start] as i64;
+        let same_class = |a: char, b: char| {
+            a.is_ascii_digit() == b.is_ascii_digit() && a.is_alphabetic() == b.is_alphabetic()
+        };
+        let mut end = start + 1;
+        while end < lowered.len()
+            && (step == 1 || step == -1)
+            && lowered[end] as i64 - lowered[end - 1] as i64 == step
+            && same_class(lowered[end], lowered[start])
+        {
+            end += 1;
+        }
+        if end - start >= 3 {
+            let first = lowered[start];
+            let starts = if matches!(first, 'a' | 'z' | '0' | '1' | '9') {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let directions = if step < 0 { 2.0 } else { 1.0 };
+            pieces.push(Piece {
+                start,
+                end,
+                guesses_log10: (starts * directions * (end - start) as f64).log10(),
+                pattern: WeakPattern::Sequence,
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+}
+
+/// "aaaa", and blocks repeated back to back: "abcabc", "hello!hello!".
+fn repeat_pieces(chars: &[char], pieces: &mut Vec<Piece>) {
+    let n = chars.len();
+    for start in 0..n {
+        for block in 1..=(n - start) / 2 {
+            let mut count = 1;
+            while start + (count + 1) * block <= n
+                && chars[start..start + block]
+                    == chars[start + count * block..start + (count + 1) * block]
+            {
+                count += 1;
+            }
+            if count < 2 || (block == 1 && count < 3) {
+                continue;
+            }
+            // The block itself is guessed at brute-force cost, then its repeat count.
+            pieces.push(Piece {
+                start,
+                end: start + count * block,
+                guesses_log10: block as f64 * BRUTEFORCE_LOG10 + (count as f64).log10(),
+                pattern: WeakPattern::Repeat,
+            });
+        }
+    }
+}
+
+/// At least 4 neighbouring keys of one keyboard row, either way: "asdf", "poiuy".
+fn keyboard_pieces(chars: &[char], pieces: &mut Vec<Piece>) {
+    let lowered: Vec<char> = chars.iter().map(|&c| lower(c)).collect();
+    let adjacent = |a: char, b: char| {
+        KEYBOARD_ROWS.iter().any(|row| {
+            let keys: Vec<char> = row.chars().collect();
+            keys.windows(2)
+                .any(|w| (w[0] == a && w[1] == b) || (w[0] == b && w[1] == a))
+        })
+    };
+    let mut start = 0;
+    while start < lowered.len() {
+        let mut end = start + 1;
+        while end < lowered.len() && adjacent(lowered[end - 1], lowered[end]) {
+            end += 1;
+        }
+        if end - start >= 4 {
+            // About 40 starting keys, each walk also read backwards.
+            pieces.push(Piece {
+                start,
+                end,
+                guesses_log10: (80.0 * (end - start) as f64).log10()
+                    + case_variations_log10(&chars[start..end]),
+                pattern: WeakPattern::KeyboardWalk,
+            });
+        }
+        start = end;
+    }
+}
+
+/// Years from 1900 to 2039, and dates: "251290", "19901225", "12/25/1990", "1990-12-25".
+fn date_pieces(chars: &[char], pieces: &mut Vec<Piece>) {
+    let digit_run = |start: usize, max: usize| -> usize {
+        chars[start..]
+            .iter()
+            .take(max)
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let text = |start: usize, len: usize| -> String { chars[start..start + len].iter().collect() };
+    let mut push = |start: usize, end: usize, guesses: f64| {
+        pieces.push(Piece {
+            start,
+            end,
+            guesses_log10: guesses.log10(),
+            pattern: WeakPattern::Date,
+        })
+    };
+    for start in 0..chars.len() {
+        let run = digit_run(start, 8);
+        if run >= 4 && is_year(&text(start, 4)) {
+            push(start, start + 4, 140.0);
+        }
+        // Without separators.
+        for (len, split) in [(6, [2, 2, 2]), (8, [4, 2, 2]), (8, [2, 2, 4])] {
+            if run >= len {
+                let digits = text(start, len);
+                let (a, rest) = digits.split_at(split[0]);
+                let (b, c) = rest.split_at(split[1]);
+                if is_date(a, b, c) {
+                    push(start, start + len, 366.0 * 140.0);
+                }
+            }
+        }
+        // With one kind of separator: 1-4 digits, sep, 1-2 digits, sep, 2-4 digits.
+        let first = digit_run(start, 4);
+        let Some(&separator) = chars.get(start + first).filter(|c| "/-._ ".contains(**c)) else {
+            continue;
+        };
+        if first == 0 {
+            continue;
+        }
+        let second_at = start + first + 1;
+        let second = digit_run(second_at.min(chars.len()), 2);
+        let third_at = second_at + second + 1;
+        if second == 0 || chars.get(third_at - 1) != Some(&separator) || third_at >= chars.len() {
+            continue;
+        }
+        let third = digit_run(third_at, 4);
+        for len in (2..=third).rev() {
+            if is_date(
+                &text(start, first),
+                &text(second_at, second),
+                &text(third_at, len),
+            ) {
+                // The separator is one more guess.
+                push(start, third_at + len, 366.0 * 140.0 * 5.0);
+                break;
+            }
+        }
+    }
+}
+
+fn is_year(year: &str) -> bool {
+    year.len() == 4 && year.parse().is_ok_and(|y: u32| (1900..=2039).contains(&y))
+}
+
+/// Year first (then month and day), or day and month either way round, then the year.
+fn is_date(a: &str, b: &str, c: &str) -> bool {
+    let number = |s: &str| s.parse::<u32>().unwrap_or(0);
+    let day_month = |d: &str, m: &str| {
+        d.len() <= 2
+            && m.len() <= 2
+            && (1..=31).contains(&number(d))
+            && (1..=12).contains(&number(m))
+    };
+    if a.len() == 4 {
+        is_year(a) && day_month(c, b)
+    } else {
+        (c.len() == 2 || is_year(c)) && a.len() <= 2 && (day_month(a, b) || day_month(b, a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passwords::VaultEntry;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn entry(id: &str, password: &str, changed_at: i64) -> VaultEntry {
+        VaultEntry {
+            id: id.to_string(),
+            service: format!("Service {}", id),
+            username: "me@example.com".to_string(),
+            password: password.to_string(),
+            notes: String::new(),
+            created_at: changed_at,
+            updated_at: changed_at,
+            url: String::new(),
+            color: String::new(),
+            is_pinned: false,
+            totp_secret: None,
+            url_match: Default::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_estimate_finds_patterns() {
+        for (password, max_score, pattern) in [
+            ("password", 0, Some(WeakPattern::CommonPassword)),
+            ("P@ssw0rd", 0, Some(WeakPattern::CommonPassword)),
+            ("qwerty123", 0, Some(WeakPattern::CommonPassword)),
+            ("asdfghjkl", 1, Some(WeakPattern::KeyboardWalk)),
+            ("abcdefgh", 0, Some(WeakPattern::Sequence)),
+            ("zzzzzzzzzzzz", 1, Some(WeakPattern::Repeat)),
+            ("Summer2023!", 2, Some(WeakPattern::Date)),
+            ("12/25/1990", 2, Some(WeakPattern::Date)),
+        ] {
+            let strength = estimate(password);
+            assert!(strength.score <= max_score, "{}: {:?}", password, strength);
+            if let Some(pattern) = pattern {
+                assert!(
+                    strength.patterns.contains(&pattern),
+                    "{}: {:?}",
+                    password,
+                    strength
+                );
+            }
+        }
+
+        assert_eq!(estimate("").score, 0);
+        for strong in [
+            "Xk9#mP2$vL7!qR4&",
+            "tQ8zR3nW5yB1",
+            "bacon visor shrug yodel tulip",
+        ] {
+            assert_eq!(
+                estimate(strong).score,
+                4,
+                "{}: {:?}",
+                strong,
+                estimate(strong)
+            );
+        }
+        // Longer is never weaker.
+        assert!(
+            estimate("Xk9#mP2$vL7!qR4&zz").guesses_log10
+                > estimate("Xk9#mP2$vL7!qR4&").guesses_log10
+        );
+    }
+
+    #[test]
+    fn test_audit_flags_reuse_age_and_breaches() {
+        let mut vault = PasswordVault::new();
+        vault.entries.push(entry("a", "Xk9#mP2$vL7!qR4&", NOW));
+        vault.entries.push(entry("b", "Xk9#mP2$vL7!qR4&", NOW));
+        vault
+            .entries
+            .push(entry("c", "Wombat-Harbour2023!", NOW - 400 * 86_400));
+        vault.entries.push(entry("d", "w0mbat-harbour2024", NOW));
+        vault.entries.push(entry("e", "hunter2", NOW));
+        vault.entries.push(entry("f", "", NOW));
+
+        let report = audit(&vault, None, Some("Offline mode is on.".into()), NOW);
+        assert_eq!((report.total, report.without_password), (6, 1));
+        assert_eq!((report.reused, report.similar, report.old), (2, 2, 1));
+        assert_eq!(report.breached, None);
+        assert_eq!(report.breach_note.as_deref(), Some("Offline mode is on."));
+        let find = |id: &str| report.entries.iter().find(|e| e.id == id).unwrap().clone();
+        assert_eq!(find("a").reused_with, ["b"]);
+        assert_eq!(find("c").similar_to, ["d"]);
+        assert_eq!(find("c").age_days, 400);
+        assert!(find("c").issues.contains(&Issue::Old));
+        assert_eq!(find("e").issues, [Issue::Weak]);
+        assert!(report.score < 100);
+        assert_eq!(report.strength_distribution.iter().sum::<usize>(), 5);
+        // The report carries no password.
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("hunter2") && !json.contains("Xk9#"));
+
+        let mut counts = BreachCounts::new();
+        counts.insert(sha1_hex("hunter2"), 17);
+        counts.insert(sha1_hex("Xk9#mP2$vL7!qR4&"), 0);
+        let report = audit(&vault, Some(&counts), None, NOW);
+        assert_eq!(report.breached, Some(1));
+        assert_eq!(report.entries[0].id, "e", "least healthy first");
+        assert_eq!(report.entries[0].breach_count, Some(17));
+        assert_eq!(report.entries[0].health, 0);
+        assert_eq!(
+            sha1_hex("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+}
+
+// --- END OF FILE password_audit.rs ---
@@ -1,6 +1,10 @@
 // --- START OF FILE vault.rs ---
 
+use crate::account_deletion::DeletionStatus;
+use crate::entry_attachments::{self, EntryAttachment};
+use crate::regexes;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 // Zeroize prevents memory forensics by explicitly overwriting sensitive variables
 // in RAM with zeroes (`0x00`) the exact moment they drop out of scope.
 use zeroize::{Zeroize, ZeroizeOnDrop};
@@ -9,6 +13,25 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 // --- DATA STRUCTURES ---
 // ==========================================
 
+/// How an entry's `url` is compared against the page the user is on.
+/// Shared by every consumer that needs "which logins belong to this site?" so the
+/// answer is the same whether it comes from auto-fill, auto-type or the UI search.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlMatchMode {
+    /// `mail.example.com` matches `login.example.com` (same registrable domain).
+    /// Opt-in only: without a full Public Suffix List, tenants of shared hosts
+    /// (`alice.github.io`, `bob.herokuapp.com`) would share one "domain".
+    BaseDomain,
+    /// Only the exact host matches (`login.example.com` ≠ `mail.example.com`).
+    #[default]
+    ExactHost,
+    /// `url_match_pattern` is a regular expression tested against the full URL.
+    Regex,
+    /// Never suggest this entry automatically.
+    Never,
+}
+
 /// Represents a single saved password entry in the user's vault.
 ///
 /// SECURITY IMPLEMENTATION:
@@ -35,36 +58,285 @@ pub struct VaultEntry {
     pub is_pinned: bool,
 
     // --- NEW: OFFLINE 2FA (TOTP) ---
-    // The secret key (usually a base32 string) provided by the website to generate 2FA codes.
-    #[serde(default)]
+    // The secret key provided by the website to generate 2FA codes: a base32 string, or
+    // the otpauth:// link from the setup QR code when the site uses other settings (totp.rs).
+    #[serde(default, alias = "otp_secret")]
     pub totp_secret: Option<String>,
+
+    // --- URL MATCHING ---
+    #[serde(default)]
+    #[zeroize(skip)]
+    pub url_match: UrlMatchMode,
+    /// Only used with `UrlMatchMode::Regex`.
+    #[serde(default)]
+    pub url_match_pattern: Option<String>,
+
+    // --- USAGE STATISTICS ---
+    // Maintained by the backend when a credential is retrieved. Values sent by the
+    // frontend on save are ignored (see `PasswordVault::preserve_usage_from`).
+    #[serde(default)]
+    pub last_used_at: Option<i64>, // UNIX timestamp in seconds
+    #[serde(default)]
+    pub use_count: u64,
+
+    // --- ACCOUNT DELETION TRACKING (see account_deletion.rs) ---
+    #[serde(default)]
+    #[zeroize(skip)]
+    pub deletion_status: Option<DeletionStatus>,
+
+    // --- PASSWORD HISTORY ---
+    // Earlier passwords, newest first, at most `PasswordVault::history_limit` of them.
+    // Maintained by the backend when the password changes; values sent by the frontend
+    // are ignored (see `VaultEntry::carry_history_from`).
+    #[serde(default)]
+    pub history: Vec<PasswordRevision>,
+
+    // --- CUSTOM FIELDS & ATTACHMENTS ---
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    /// Files stored beside the vault (see entry_attachments.rs). Owned by the backend.
+    #[serde(default)]
+    pub attachments: Vec<EntryAttachment>,
+}
+
+/// A user-defined field: PIN, licence key, security question...
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Zeroize, ZeroizeOnDrop)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+    /// Masked in the UI and exported as a protected value, like the password.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// A password an entry used before, kept so a rotation can be looked up or undone.
+/// Scrubbed from RAM on drop like the entry itself, including when the history is trimmed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Zeroize, ZeroizeOnDrop)]
+pub struct PasswordRevision {
+    pub password: String,
+    /// When it stopped being the entry's password (UNIX timestamp in seconds).
+    pub replaced_at: i64,
+}
+
+/// Per-entry usage summary for the "abandoned accounts" / footprint reduction view.
+/// Deliberately excludes the secret fields.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EntryUsage {
+    pub id: String,
+    pub service: String,
+    pub username: String,
+    pub url: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub use_count: u64,
+    /// Days since last use (or since creation if never used).
+    pub idle_days: i64,
+}
+
+/// Why a set of entries was flagged by `PasswordVault::find_duplicates`.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// Same site, username (ignoring case) and password: import leftovers, safe to merge.
+    Exact,
+    /// Same site and username but different passwords: only one of them is current.
+    PasswordConflict,
+    /// Same site, usernames a single character apart (typos, old imports).
+    SimilarUsername,
+}
+
+/// A group of entries that probably describe the same account.
+/// Deliberately excludes the secret fields.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// Registrable domain of the entries (or the service name when they have no URL).
+    pub site: String,
+    pub entry_ids: Vec<String>,
+    /// Suggested survivor for `merge_password_entries`: the most recently updated entry.
+    pub keep_id: String,
+}
+
+/// Public suffixes made of two labels. Without a full Public Suffix List this keeps the
+/// common cases right (`shop.example.co.uk` → `example.co.uk`, not `co.uk`); private
+/// suffixes such as `github.io` are not covered, which is why `BaseDomain` is opt-in.
+const MULTI_LABEL_SUFFIXES: &[&str] = &[
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "com.au", "net.au", "org.au", "co.nz", "co.jp", "co.kr",
+    "co.in", "com.br", "com.cn", "com.gr", "gov.gr", "com.mx", "com.tr", "co.za",
+];
+
+/// Extracts the lowercase host from a URL (scheme optional), dropping user-info and port.
+/// A backslash ends the authority as browsers read it, so `https://evil.com\@github.com/`
+/// is evil.com, not github.com.
+pub fn url_host(url: &str) -> Option<String> {
+    let rest = url.trim();
+    let rest = rest.split_once("://").map(|(_, r)| r).unwrap_or(rest);
+    let authority = rest.split(['/', '\\', '?', '#']).next()?;
+    let host_port = authority.rsplit('@').next()?;
+    let host = if host_port.starts_with('[') {
+        // IPv6 literal: keep the brackets, drop the port.
+        &host_port[..=host_port.find(']')?]
+    } else {
+        host_port.split(':').next()?
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// Reduces a host to its registrable domain (`a.b.example.com` → `example.com`).
+/// IP addresses are returned unchanged.
+pub fn base_domain(host: &str) -> String {
+    if host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok() {
+        return host.to_string();
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let keep = if labels.len() >= 3
+        && MULTI_LABEL_SUFFIXES.contains(&labels[labels.len() - 2..].join(".").as_str())
+    {
+        3
+    } else {
+        2
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+impl VaultEntry {
+    /// Returns true if this entry should be offered for `page_url` according to its match rule.
+    pub fn matches_url(&self, page_url: &str) -> bool {
+        match self.url_match {
+            UrlMatchMode::Never => false,
+            UrlMatchMode::Regex => self
+                .url_match_pattern
+                .as_deref()
+                .and_then(regexes::cached_untrusted)
+                .is_some_and(|re| re.is_match(page_url)),
+            UrlMatchMode::ExactHost => match (url_host(&self.url), url_host(page_url)) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
+            UrlMatchMode::BaseDomain => match (url_host(&self.url), url_host(page_url)) {
+                (Some(a), Some(b)) => base_domain(&a) == base_domain(&b),
+                _ => false,
+            },
+        }
+    }
+
+    /// Key used to decide whether two entries belong to the same site: the registrable
+    /// domain of the URL, else the lowercased service name.
+    fn site_key(&self) -> Option<String> {
+        if let Some(host) = url_host(&self.url) {
+            return Some(base_domain(&host));
+        }
+        let service = self.service.trim().to_lowercase();
+        (!service.is_empty()).then_some(service)
+    }
+
+    /// Takes over the history of `previous` (the stored version of this entry), adds its
+    /// password when `self` has a different one, and keeps the newest `limit` revisions.
+    /// Whatever history `self` arrived with is discarded.
+    pub fn carry_history_from(&mut self, previous: &VaultEntry, now: i64, limit: usize) {
+        self.history = previous.history.clone();
+        if !previous.password.is_empty() && previous.password != self.password {
+            self.history.insert(
+                0,
+                PasswordRevision {
+                    password: previous.password.clone(),
+                    replaced_at: now,
+                },
+            );
+        }
+        self.history.truncate(limit);
+    }
+}
+
+impl DuplicateGroup {
+    fn new(kind: DuplicateKind, site: &str, entries: &[&VaultEntry]) -> Self {
+        let keep = entries
+            .iter()
+            .max_by_key(|e| (e.updated_at.max(e.created_at), e.use_count))
+            .expect("duplicate groups are never empty");
+        Self {
+            kind,
+            site: site.to_string(),
+            entry_ids: entries.iter().map(|e| e.id.clone()).collect(),
+            keep_id: keep.id.clone(),
+        }
+    }
+}
+
+/// Usernames (already trimmed and lowercased) that differ by a single edit, e.g.
+/// "john.doe@mail.com" / "johndoe@mail.com". Short names are excluded: "bob" and "rob"
+/// are different people.
+fn usernames_similar(a: &str, b: &str) -> bool {
+    const MIN_LEN: usize = 5;
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    if a.len().min(b.len()) < MIN_LEN || a.len().abs_diff(b.len()) > 1 {
+        return false;
+    }
+    // Single-row Levenshtein, stopping as soon as the distance exceeds 1.
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            row[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        if row.iter().min().is_some_and(|&m| m > 1) {
+            return false;
+        }
+        prev = row;
+    }
+    prev[b.len()] <= 1
 }
 
 /// The root container for the Password Vault.
 /// This entire struct is serialized into JSON and encrypted into the `passwords.qre` file.
-#[derive(Serialize, Deserialize, Debug, Default, Zeroize, ZeroizeOnDrop)]
+#[derive(Serialize, Deserialize, Debug, Zeroize, ZeroizeOnDrop)]
 pub struct PasswordVault {
     // Schema versioning allows for safe, backwards-compatible updates.
     #[serde(default = "PasswordVault::default_schema_version")]
     pub schema_version: u32,
 
     pub entries: Vec<VaultEntry>,
+
+    /// How many earlier passwords each entry keeps. Set through `set_history_limit`;
+    /// the value sent by the frontend on save is ignored.
+    #[serde(default = "PasswordVault::default_history_limit")]
+    pub history_limit: usize,
+}
+
+impl Default for PasswordVault {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PasswordVault {
     // Defines the current data structure version expected by this backend build
     pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+    pub const DEFAULT_HISTORY_LIMIT: usize = 10;
+    pub const MAX_HISTORY_LIMIT: usize = 100;
+    pub const MAX_CUSTOM_FIELDS: usize = 50;
 
     fn default_schema_version() -> u32 {
         // Old vaults created before the versioning system was implemented are treated as v1.
         1
     }
 
+    fn default_history_limit() -> usize {
+        Self::DEFAULT_HISTORY_LIMIT
+    }
+
     /// Creates a new, empty password vault.
     pub fn new() -> Self {
         Self {
             schema_version: Self::CURRENT_SCHEMA_VERSION,
             entries: Vec::new(),
+            history_limit: Self::DEFAULT_HISTORY_LIMIT,
         }
     }
 
@@ -100,16 +372,314 @@ impl PasswordVault {
                     entry.id
                 ));
             }
+            // A regex rule without a usable pattern would silently never match.
+            if entry.url_match == UrlMatchMode::Regex {
+                let pattern = entry.url_match_pattern.as_deref().unwrap_or("");
+                regexes::compile_untrusted(pattern).map_err(|e| {
+                    format!(
+                        "Entry '{}' has an invalid URL pattern: {}",
+                        entry.service, e
+                    )
+                })?;
+                if pattern.is_empty() {
+                    return Err(format!(
+                        "Entry '{}' uses regex URL matching but has no pattern.",
+                        entry.service
+                    ));
+                }
+            }
+            if entry.custom_fields.len() > Self::MAX_CUSTOM_FIELDS {
+                return Err(format!(
+                    "Entry '{}' has more than {} custom fields.",
+                    entry.service,
+                    Self::MAX_CUSTOM_FIELDS
+                ));
+            }
+            let mut field_names = std::collections::HashSet::new();
+            for field in &entry.custom_fields {
+                if field.name.trim().is_empty() {
+                    return Err(format!(
+                        "Entry '{}' has a custom field without a name.",
+                        entry.service
+                    ));
+                }
+                if !field_names.insert(field.name.trim()) {
+                    return Err(format!(
+                        "Entry '{}' has two custom fields named '{}'.",
+                        entry.service,
+                        field.name.trim()
+                    ));
+                }
+            }
+            // Attachment IDs end up in file paths.
+            for attachment in &entry.attachments {
+                entry_attachments::validate_attachment_id(&attachment.id)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Entries that should be offered for `page_url`, most specific first:
+    /// exact-host and regex rules before base-domain matches, then pinned entries.
+    pub fn find_for_url(&self, page_url: &str) -> Vec<&VaultEntry> {
+        let mut matches: Vec<&VaultEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.matches_url(page_url))
+            .collect();
+        matches.sort_by_key(|e| (e.url_match == UrlMatchMode::BaseDomain, !e.is_pinned));
+        matches
+    }
+
+    // ==========================================
+    // --- USAGE TRACKING ---
+    // ==========================================
+
+    /// Bumps the usage counters of an entry and returns it.
+    pub fn record_use(&mut self, id: &str, now: i64) -> Result<&VaultEntry, String> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or_else(|| format!("No entry found with ID '{}'.", id))?;
+        entry.last_used_at = Some(now);
+        entry.use_count = entry.use_count.saturating_add(1);
+        Ok(entry)
+    }
+
+    /// Copies the usage counters from the vault currently on disk into `self`.
+    /// The frontend round-trips the whole vault on every save; without this step it
+    /// could reset (or forge) statistics that only the backend is meant to write.
+    pub fn preserve_usage_from(&mut self, previous: &PasswordVault) {
+        let stats: std::collections::HashMap<&str, (Option<i64>, u64)> = previous
+            .entries
+            .iter()
+            .map(|e| (e.id.as_str(), (e.last_used_at, e.use_count)))
+            .collect();
+        for entry in &mut self.entries {
+            let (last_used_at, use_count) =
+                stats.get(entry.id.as_str()).copied().unwrap_or((None, 0));
+            entry.last_used_at = last_used_at;
+            entry.use_count = use_count;
+        }
+    }
+
+    // ==========================================
+    // --- PASSWORD HISTORY ---
+    // ==========================================
+
+    /// Carries the history limit and every entry's history over from the vault on disk,
+    /// recording the old password of each entry whose password changed (see
+    /// `VaultEntry::carry_history_from`). Entries new to the vault start without history.
+    pub fn preserve_history_from(&mut self, previous: &PasswordVault, now: i64) {
+        self.history_limit = previous.history_limit;
+        for entry in &mut self.entries {
+            match previous.entries.iter().find(|e| e.id == entry.id) {
+                Some(stored) => entry.carry_history_from(stored, now, self.history_limit),
+                None => entry.history.clear(),
+            }
+        }
+    }
+
+    /// Carries every entry's attachment list over from the vault on disk: attachments are
+    /// added and removed through their own commands (see entry_attachments.rs).
+    /// Returns the IDs of attachments whose entry is gone, for their files to be deleted.
+    pub fn preserve_attachments_from(&mut self, previous: &PasswordVault) -> Vec<String> {
+        for entry in &mut self.entries {
+            entry.attachments = previous
+                .entries
+                .iter()
+                .find(|e| e.id == entry.id)
+                .map(|e| e.attachments.clone())
+                .unwrap_or_default();
+        }
+        previous
+            .entries
+            .iter()
+            .filter(|e| !self.entries.iter().any(|kept| kept.id == e.id))
+            .flat_map(|e| e.attachments.iter().map(|a| a.id.clone()))
+            .collect()
+    }
+
+    /// Changes how many earlier passwords are kept, trimming longer histories now.
+    pub fn set_history_limit(&mut self, limit: usize) -> Result<(), String> {
+        if limit > Self::MAX_HISTORY_LIMIT {
+            return Err(format!(
+                "Keep at most {} earlier passwords per entry.",
+                Self::MAX_HISTORY_LIMIT
+            ));
+        }
+        self.history_limit = limit;
+        for entry in &mut self.entries {
+            entry.history.truncate(limit);
         }
         Ok(())
     }
 
+    /// Usage summary of every entry, least recently used first.
+    /// With `min_idle_days`, only entries idle for at least that many days are returned.
+    pub fn usage_report(&self, now: i64, min_idle_days: Option<u32>) -> Vec<EntryUsage> {
+        let mut report: Vec<EntryUsage> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let reference = e.last_used_at.unwrap_or(e.created_at);
+                EntryUsage {
+                    id: e.id.clone(),
+                    service: e.service.clone(),
+                    username: e.username.clone(),
+                    url: e.url.clone(),
+                    created_at: e.created_at,
+                    last_used_at: e.last_used_at,
+                    use_count: e.use_count,
+                    idle_days: (now - reference).max(0) / 86_400,
+                }
+            })
+            .filter(|u| min_idle_days.is_none_or(|d| u.idle_days >= i64::from(d)))
+            .collect();
+        report.sort_by_key(|u| std::cmp::Reverse(u.idle_days));
+        report
+    }
+
+    // ==========================================
+    // --- DUPLICATE DETECTION & MERGING ---
+    // ==========================================
+    // Importing from several browsers leaves the same login two or three times, often
+    // with one stale password. Detection is grouped by site so unrelated accounts that
+    // happen to share a username are never suggested for merging.
+
+    /// Groups of entries that look like the same account, exact duplicates first.
+    pub fn find_duplicates(&self) -> Vec<DuplicateGroup> {
+        let mut by_site: BTreeMap<String, Vec<&VaultEntry>> = BTreeMap::new();
+        for entry in &self.entries {
+            if let Some(site) = entry.site_key() {
+                by_site.entry(site).or_default().push(entry);
+            }
+        }
+
+        let mut groups = Vec::new();
+        for (site, entries) in by_site.iter().filter(|(_, e)| e.len() > 1) {
+            let mut by_user: BTreeMap<String, Vec<&VaultEntry>> = BTreeMap::new();
+            for entry in entries {
+                by_user
+                    .entry(entry.username.trim().to_lowercase())
+                    .or_default()
+                    .push(entry);
+            }
+
+            for same_user in by_user.values() {
+                let mut by_password: BTreeMap<&str, Vec<&VaultEntry>> = BTreeMap::new();
+                for entry in same_user {
+                    by_password.entry(&entry.password).or_default().push(entry);
+                }
+                for copies in by_password.values().filter(|c| c.len() > 1) {
+                    groups.push(DuplicateGroup::new(DuplicateKind::Exact, site, copies));
+                }
+                if by_password.len() > 1 {
+                    groups.push(DuplicateGroup::new(
+                        DuplicateKind::PasswordConflict,
+                        site,
+                        same_user,
+                    ));
+                }
+            }
+
+            let usernames: Vec<&String> = by_user.keys().collect();
+            for (i, a) in usernames.iter().enumerate() {
+                for b in &usernames[i + 1..] {
+                    if usernames_similar(a, b) {
+                        let members: Vec<&VaultEntry> =
+                            by_user[*a].iter().chain(&by_user[*b]).copied().collect();
+                        groups.push(DuplicateGroup::new(
+                            DuplicateKind::SimilarUsername,
+                            site,
+                            &members,
+                        ));
+                    }
+                }
+            }
+        }
+        groups.sort_by_key(|g| g.kind as u8);
+        groups
+    }
+
+    /// Folds `merge_ids` into `keep_id` and removes them. The survivor keeps its own
+    /// username and password; notes are concatenated, empty URL/TOTP fields are filled
+    /// from the merged entries, custom fields with new names and all attachments are
+    /// taken over, and usage statistics are combined.
+    /// Returns the number of entries removed.
+    pub fn merge_entries(
+        &mut self,
+        keep_id: &str,
+        merge_ids: &[String],
+        now: i64,
+    ) -> Result<usize, String> {
+        if merge_ids.is_empty() {
+            return Err("Select at least one entry to merge.".to_string());
+        }
+        if merge_ids.iter().any(|id| id == keep_id) {
+            return Err("An entry cannot be merged into itself.".to_string());
+        }
+        let keep_pos = self
+            .entries
+            .iter()
+            .position(|e| e.id == keep_id)
+            .ok_or_else(|| format!("No entry found with ID '{}'.", keep_id))?;
+        if let Some(missing) = merge_ids
+            .iter()
+            .find(|id| !self.entries.iter().any(|e| &e.id == *id))
+        {
+            return Err(format!("No entry found with ID '{}'.", missing));
+        }
+
+        let mut merged = self.entries[keep_pos].clone();
+        for other in self.entries.iter().filter(|e| merge_ids.contains(&e.id)) {
+            let note = other.notes.trim();
+            if !note.is_empty() && !merged.notes.contains(note) {
+                if !merged.notes.is_empty() {
+                    merged.notes.push_str("\n\n");
+                }
+                merged.notes.push_str(note);
+            }
+            if merged.url.is_empty() {
+                merged.url = other.url.clone();
+            }
+            if merged.totp_secret.is_none() {
+                merged.totp_secret = other.totp_secret.clone();
+            }
+            if merged.deletion_status.is_none() {
+                merged.deletion_status = other.deletion_status.clone();
+            }
+            for field in &other.custom_fields {
+                if !merged.custom_fields.iter().any(|f| f.name == field.name) {
+                    merged.custom_fields.push(field.clone());
+                }
+            }
+            merged.attachments.extend(other.attachments.iter().cloned());
+            merged.is_pinned |= other.is_pinned;
+            merged.created_at = merged.created_at.min(other.created_at);
+            merged.last_used_at = merged.last_used_at.max(other.last_used_at);
+            merged.use_count = merged.use_count.saturating_add(other.use_count);
+        }
+        merged.updated_at = now;
+
+        let before = self.entries.len();
+        self.entries.retain(|e| !merge_ids.contains(&e.id));
+        let removed = before - self.entries.len();
+        if let Some(slot) = self.entries.iter_mut().find(|e| e.id == keep_id) {
+            *slot = merged;
+        }
+        Ok(removed)
+    }
+
     // ==========================================
     // --- HELPER MUTATIONS ---
     // ==========================================
     // #[allow(dead_code)] is used because currently, the React frontend manipulates
     // the JSON array directly and sends the whole payload back to be validated and saved.
     // These remain here for future backend-only modifications or CLI tools.
+    // `update_entry` also backs the `update_password_entry` command.
 
     #[allow(dead_code)]
     pub fn add_entry(&mut self, entry: VaultEntry) -> Result<(), String> {
@@ -123,13 +693,20 @@ impl PasswordVault {
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn update_entry(&mut self, updated: VaultEntry) -> Result<(), String> {
+    /// Replaces the entry with the same ID. Usage counters, attachments and the password
+    /// history are the backend's: they are kept from the stored entry, and a changed
+    /// password is added to the history.
+    pub fn update_entry(&mut self, mut updated: VaultEntry, now: i64) -> Result<(), String> {
         let pos = self
             .entries
             .iter()
             .position(|e| e.id == updated.id)
             .ok_or_else(|| format!("No entry found with ID '{}'.", updated.id))?;
+        let stored = &self.entries[pos];
+        updated.last_used_at = stored.last_used_at;
+        updated.use_count = stored.use_count;
+        updated.attachments = stored.attachments.clone();
+        updated.carry_history_from(stored, now, self.history_limit);
         self.entries[pos] = updated;
         Ok(())
     }
@@ -157,6 +734,14 @@ mod tests {
             color: "#000000".to_string(),
             is_pinned: false,
             totp_secret: None,
+            url_match: UrlMatchMode::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
         }
     }
 
@@ -216,6 +801,259 @@ mod tests {
             .contains("newer than this application supports"));
     }
 
+    // --- URL Matching Tests ---
+
+    #[test]
+    fn test_url_host_parsing() {
+        assert_eq!(
+            url_host("https://user:pw@Login.Example.com:8443/path?q=1"),
+            Some("login.example.com".into())
+        );
+        assert_eq!(url_host("example.com/login"), Some("example.com".into()));
+        assert_eq!(url_host("http://[::1]:8080/"), Some("[::1]".into()));
+        assert_eq!(url_host(""), None);
+    }
+
+    #[test]
+    fn test_url_host_is_not_fooled_by_userinfo() {
+        assert_eq!(
+            url_host("https://evil.com\\@github.com/"),
+            Some("evil.com".into())
+        );
+        assert_eq!(
+            url_host("https://github.com@evil.com/"),
+            Some("evil.com".into())
+        );
+        // The host follows the last '@' of the authority.
+        assert_eq!(
+            url_host("https://me@example.org:p@ss@evil.com/login"),
+            Some("evil.com".into())
+        );
+
+        let mut entry = create_valid_entry("id-1");
+        entry.url = "https://github.com/login".to_string();
+        assert!(!entry.matches_url("https://evil.com\\@github.com/"));
+    }
+
+    #[test]
+    fn test_base_domain() {
+        assert_eq!(base_domain("a.b.example.com"), "example.com");
+        assert_eq!(base_domain("shop.example.co.uk"), "example.co.uk");
+        assert_eq!(base_domain("192.168.1.1"), "192.168.1.1");
+    }
+
+    #[test]
+    fn test_url_match_modes() {
+        let mut entry = create_valid_entry("id-1");
+        entry.url = "https://github.com/login".to_string();
+
+        assert_eq!(entry.url_match, UrlMatchMode::ExactHost);
+        assert!(entry.matches_url("https://github.com/settings"));
+        assert!(!entry.matches_url("https://gist.github.com/"));
+        assert!(!entry.matches_url("https://github.com.evil.io/"));
+
+        entry.url_match = UrlMatchMode::BaseDomain;
+        assert!(entry.matches_url("https://gist.github.com/"));
+        assert!(!entry.matches_url("https://github.com.evil.io/"));
+
+        entry.url_match = UrlMatchMode::Regex;
+        entry.url_match_pattern = Some(r"^https://(www\.)?github\.com/enterprise".to_string());
+        assert!(entry.matches_url("https://github.com/enterprise/login"));
+        assert!(!entry.matches_url("https://github.com/login"));
+
+        entry.url_match = UrlMatchMode::Never;
+        assert!(!entry.matches_url("https://github.com/login"));
+    }
+
+    #[test]
+    fn test_find_for_url_orders_specific_first() {
+        let mut vault = PasswordVault::new();
+        let mut broad = create_valid_entry("broad");
+        broad.url = "https://example.com".into();
+        broad.url_match = UrlMatchMode::BaseDomain;
+        let mut exact = create_valid_entry("exact");
+        exact.url = "https://login.example.com".into();
+        exact.url_match = UrlMatchMode::ExactHost;
+        let mut other = create_valid_entry("other");
+        other.url = "https://other.org".into();
+        vault.entries = vec![broad, exact, other];
+
+        let ids: Vec<&str> = vault
+            .find_for_url("https://login.example.com/auth")
+            .iter()
+            .map(|e| e.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["exact", "broad"]);
+    }
+
+    #[test]
+    fn test_default_match_keeps_shared_hosting_tenants_apart() {
+        let mut entry = create_valid_entry("id-1");
+        entry.url = "https://alice.github.io/login".to_string();
+
+        assert!(entry.matches_url("https://alice.github.io/"));
+        assert!(!entry.matches_url("https://attacker.github.io/login"));
+
+        let mut vault = PasswordVault::new();
+        vault.entries.push(entry);
+        assert!(vault
+            .find_for_url("https://attacker.github.io/login")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_url_pattern() {
+        let mut vault = PasswordVault::new();
+        let mut entry = create_valid_entry("id-1");
+        entry.url_match = UrlMatchMode::Regex;
+        entry.url_match_pattern = Some("(unclosed".into());
+        vault.entries.push(entry);
+        assert!(vault
+            .validate()
+            .unwrap_err()
+            .contains("invalid URL pattern"));
+
+        vault.entries[0].url_match_pattern = None;
+        assert!(vault.validate().unwrap_err().contains("no pattern"));
+    }
+
+    // --- Usage Tracking Tests ---
+
+    #[test]
+    fn test_record_use_updates_counters() {
+        let mut vault = PasswordVault::new();
+        vault.entries.push(create_valid_entry("id-1"));
+
+        vault.record_use("id-1", 1_700_000_500).unwrap();
+        let entry = vault.record_use("id-1", 1_700_000_900).unwrap();
+        assert_eq!(entry.use_count, 2);
+        assert_eq!(entry.last_used_at, Some(1_700_000_900));
+
+        assert!(vault.record_use("missing", 0).is_err());
+    }
+
+    #[test]
+    fn test_preserve_usage_ignores_frontend_values() {
+        let mut on_disk = PasswordVault::new();
+        on_disk.entries.push(create_valid_entry("id-1"));
+        on_disk.record_use("id-1", 1_700_000_500).unwrap();
+
+        // Frontend sends an edited vault with forged stats and a brand-new entry.
+        let mut incoming = PasswordVault::new();
+        let mut edited = create_valid_entry("id-1");
+        edited.use_count = 999;
+        edited.last_used_at = None;
+        let mut fresh = create_valid_entry("id-2");
+        fresh.use_count = 5;
+        incoming.entries = vec![edited, fresh];
+
+        incoming.preserve_usage_from(&on_disk);
+        assert_eq!(incoming.entries[0].use_count, 1);
+        assert_eq!(incoming.entries[0].last_used_at, Some(1_700_000_500));
+        assert_eq!(incoming.entries[1].use_count, 0);
+    }
+
+    #[test]
+    fn test_usage_report_filters_idle_entries() {
+        let mut vault = PasswordVault::new();
+        vault.entries.push(create_valid_entry("never-used")); // created 1_700_000_000
+        vault.entries.push(create_valid_entry("recent"));
+        let now = 1_700_000_000 + 400 * 86_400;
+        vault.record_use("recent", now - 86_400).unwrap();
+
+        let all = vault.usage_report(now, None);
+        assert_eq!(all[0].id, "never-used", "Most idle entry comes first");
+        assert_eq!(all[0].idle_days, 400);
+
+        let stale = vault.usage_report(now, Some(365));
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].id, "never-used");
+    }
+
+    // --- Duplicate Detection Tests ---
+
+    fn entry_for(id: &str, url: &str, username: &str, password: &str) -> VaultEntry {
+        let mut entry = create_valid_entry(id);
+        entry.url = url.to_string();
+        entry.username = username.to_string();
+        entry.password = password.to_string();
+        entry
+    }
+
+    #[test]
+    fn test_find_duplicates_by_kind() {
+        let mut vault = PasswordVault::new();
+        vault.entries = vec![
+            entry_for("a", "https://github.com/login", "alice", "pw1"),
+            entry_for("b", "https://www.github.com", "Alice", "pw1"),
+            entry_for("c", "https://gist.github.com", "alice", "pw2"),
+            entry_for("d", "https://example.com", "john.doe@mail.com", "x"),
+            entry_for("e", "https://example.com", "johndoe@mail.com", "y"),
+            // Same username on another site is a different account.
+            entry_for("f", "https://gitlab.com", "alice", "pw1"),
+        ];
+        vault.entries[2].updated_at = 1_800_000_000;
+
+        let groups = vault.find_duplicates();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].kind, DuplicateKind::Exact);
+        assert_eq!(groups[0].entry_ids, vec!["a", "b"]);
+        assert_eq!(groups[1].kind, DuplicateKind::PasswordConflict);
+        assert_eq!(groups[1].entry_ids.len(), 3);
+        assert_eq!(
+            groups[1].keep_id, "c",
+            "Most recently updated entry is kept"
+        );
+        assert_eq!(groups[2].kind, DuplicateKind::SimilarUsername);
+        assert_eq!(groups[2].site, "example.com");
+    }
+
+    #[test]
+    fn test_usernames_similar() {
+        assert!(usernames_similar("john.doe@mail.com", "johndoe@mail.com"));
+        assert!(usernames_similar("alice_w", "alice_v"));
+        assert!(!usernames_similar("bob", "rob"));
+        assert!(!usernames_similar("alice_w", "alice_wxy"));
+    }
+
+    #[test]
+    fn test_merge_entries_combines_fields() {
+        let mut vault = PasswordVault::new();
+        let mut keep = entry_for("keep", "", "alice", "current");
+        keep.notes = String::new();
+        keep.use_count = 2;
+        let mut old = entry_for("old", "https://github.com", "alice", "stale");
+        old.notes = "recovery codes".into();
+        old.totp_secret = Some("JBSWY3DPEHPK3PXP".into());
+        old.use_count = 3;
+        old.created_at = 1_600_000_000;
+        vault.entries = vec![keep, old];
+
+        assert_eq!(
+            vault
+                .merge_entries("keep", &["old".to_string()], 1_900_000_000)
+                .unwrap(),
+            1
+        );
+        assert_eq!(vault.entries.len(), 1);
+        let merged = &vault.entries[0];
+        assert_eq!(merged.password, "current");
+        assert_eq!(merged.url, "https://github.com");
+        assert_eq!(merged.notes, "recovery codes");
+        assert!(merged.totp_secret.is_some());
+        assert_eq!(merged.use_count, 5);
+        assert_eq!(merged.created_at, 1_600_000_000);
+        assert_eq!(merged.updated_at, 1_900_000_000);
+
+        assert!(vault
+            .merge_entries("keep", &["keep".to_string()], 0)
+            .is_err());
+        assert!(vault
+            .merge_entries("keep", &["gone".to_string()], 0)
+            .is_err());
+        assert!(vault.merge_entries("keep", &[], 0).is_err());
+    }
+
     // --- Mutation Logic Tests ---
 
     #[test]
@@ -239,13 +1077,157 @@ mod tests {
         // Update existing entry
         let mut updated = create_valid_entry("id-1");
         updated.password = "NEW_PASSWORD".to_string();
-        assert!(vault.update_entry(updated).is_ok());
+        assert!(vault.update_entry(updated, 100).is_ok());
 
         assert_eq!(vault.entries[0].password, "NEW_PASSWORD");
 
         // Try to update non-existent entry
         let missing = create_valid_entry("id-999");
-        assert!(vault.update_entry(missing).is_err());
+        assert!(vault.update_entry(missing, 100).is_err());
+    }
+
+    // --- Password History Tests ---
+
+    #[test]
+    fn test_update_entry_records_password_history() {
+        let mut vault = PasswordVault::new();
+        vault.add_entry(create_valid_entry("id-1")).unwrap();
+        vault.entries[0].use_count = 4;
+        vault.set_history_limit(2).unwrap();
+
+        for (i, password) in ["second", "second", "third", "fourth"].iter().enumerate() {
+            let mut updated = create_valid_entry("id-1");
+            updated.password = password.to_string();
+            updated.use_count = 0;
+            // Forged history from the frontend is discarded.
+            updated.history = vec![PasswordRevision {
+                password: "forged".to_string(),
+                replaced_at: 1,
+            }];
+            vault.update_entry(updated, 100 + i as i64).unwrap();
+        }
+
+        let entry = &vault.entries[0];
+        assert_eq!(entry.password, "fourth");
+        assert_eq!(entry.use_count, 4);
+        // Re-saving the same password adds nothing; the limit drops the oldest.
+        assert_eq!(
+            entry.history,
+            vec![
+                PasswordRevision {
+                    password: "third".to_string(),
+                    replaced_at: 103
+                },
+                PasswordRevision {
+                    password: "second".to_string(),
+                    replaced_at: 102
+                },
+            ]
+        );
+        assert!(vault
+            .set_history_limit(PasswordVault::MAX_HISTORY_LIMIT + 1)
+            .is_err());
+        vault.set_history_limit(1).unwrap();
+        assert_eq!(vault.entries[0].history.len(), 1);
+    }
+
+    // --- Custom Field & Attachment Tests ---
+
+    fn attachment(name: &str) -> EntryAttachment {
+        entry_attachments::prepare(name, b"data", 1).unwrap()
+    }
+
+    #[test]
+    fn test_attachments_survive_saves_and_merges() {
+        let mut stored = PasswordVault::new();
+        stored.add_entry(create_valid_entry("id-1")).unwrap();
+        stored.add_entry(create_valid_entry("id-2")).unwrap();
+        stored.entries[0].attachments = vec![attachment("codes.pdf")];
+        stored.entries[1].attachments = vec![attachment("licence.txt")];
+        let orphan = stored.entries[1].attachments[0].id.clone();
+
+        // A stale frontend copy: no attachments on id-1, id-2 deleted.
+        let mut saved = PasswordVault::new();
+        saved.add_entry(create_valid_entry("id-1")).unwrap();
+        assert_eq!(saved.preserve_attachments_from(&stored), vec![orphan]);
+        assert_eq!(saved.entries[0].attachments, stored.entries[0].attachments);
+
+        let mut with_field = create_valid_entry("id-1");
+        with_field.custom_fields.push(CustomField {
+            name: "PIN".to_string(),
+            value: "1234".to_string(),
+            hidden: true,
+        });
+        with_field.attachments.clear();
+        stored.update_entry(with_field, 5).unwrap();
+        assert_eq!(stored.entries[0].attachments.len(), 1);
+        assert_eq!(stored.entries[0].custom_fields.len(), 1);
+
+        stored.entries[1].custom_fields = stored.entries[0].custom_fields.clone();
+        stored
+            .merge_entries("id-1", &["id-2".to_string()], 6)
+            .unwrap();
+        let merged = &stored.entries[0];
+        assert_eq!(merged.attachments.len(), 2);
+        assert_eq!(merged.custom_fields.len(), 1);
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_custom_fields_and_attachment_ids() {
+        let mut vault = PasswordVault::new();
+        vault.add_entry(create_valid_entry("id-1")).unwrap();
+        let field = |name: &str| CustomField {
+            name: name.to_string(),
+            value: String::new(),
+            hidden: false,
+        };
+
+        vault.entries[0].custom_fields = vec![field("PIN"), field("Licence")];
+        assert!(vault.validate().is_ok());
+        vault.entries[0].custom_fields = vec![field("PIN"), field(" PIN ")];
+        assert!(vault.validate().is_err());
+        vault.entries[0].custom_fields = vec![field("  ")];
+        assert!(vault.validate().is_err());
+
+        vault.entries[0].custom_fields.clear();
+        let mut bad = attachment("a.txt");
+        bad.id = "../passwords".to_string();
+        vault.entries[0].attachments = vec![bad];
+        assert!(vault.validate().is_err());
+    }
+
+    #[test]
+    fn test_preserve_history_on_whole_vault_save() {
+        let mut stored = PasswordVault::new();
+        stored.history_limit = 3;
+        stored.add_entry(create_valid_entry("id-1")).unwrap();
+        stored.add_entry(create_valid_entry("id-2")).unwrap();
+
+        // The frontend sends the vault back without the limit and with edited entries.
+        let mut saved: PasswordVault = serde_json::from_str(
+            &serde_json::to_string(&stored)
+                .unwrap()
+                .replace(",\"history_limit\":3", ""),
+        )
+        .unwrap();
+        assert_eq!(saved.history_limit, PasswordVault::DEFAULT_HISTORY_LIMIT);
+        saved.entries[0].password = "rotated".to_string();
+        let mut added = create_valid_entry("id-3");
+        added.history = vec![PasswordRevision {
+            password: "made up".to_string(),
+            replaced_at: 1,
+        }];
+        saved.entries.push(added);
+
+        saved.preserve_history_from(&stored, 500);
+        assert_eq!(saved.history_limit, 3);
+        assert_eq!(saved.entries[0].history.len(), 1);
+        assert_eq!(
+            saved.entries[0].history[0].password,
+            "super_secret_password_123!"
+        );
+        assert!(saved.entries[1].history.is_empty());
+        assert!(saved.entries[2].history.is_empty());
     }
 }
 // --- END OF FILE vault.rs ---
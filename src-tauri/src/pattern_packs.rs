@@ -0,0 +1,722 @@
+// --- START OF FILE pattern_packs.rs ---
+
+// ==========================================
+// --- SENSITIVE DATA PATTERN PACKS ---
+// ==========================================
+// `clipboard_store::analyze_content` recognises formats that look the same everywhere
+// (cards, IBANs, phone numbers, keys). National identifiers differ per country, so they
+// come in packs: a pack is a list of regexes, each with the category it reports and an
+// optional checksum the match must pass. Most ID numbers carry a check digit, which is
+// what keeps an ordinary 9- or 11-digit number from being flagged.
+//
+// A few country packs are built in. More can be dropped into `pattern_packs/` in the app
+// data folder as JSON files (same shape as `PatternPack`) and are picked up on the next
+// load. Every pack is active unless the user turned it off; the ids of those are kept in
+// plaintext in `pattern_packs.json`.
+//
+// SECURITY: pack regexes come from files anyone can write, and run on every copy. The
+// `regex` crate never backtracks, so matching stays linear in the input; the compiled
+// size and nesting are capped as well (regexes.rs), and so are the number and length of
+// patterns.
+
+use crate::regexes;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+pub const CONFIG_FILE_NAME: &str = "pattern_packs.json";
+pub const PACKS_DIR_NAME: &str = "pattern_packs";
+
+const MAX_PACK_FILE_BYTES: u64 = 256 * 1024;
+const MAX_PATTERNS_PER_PACK: usize = 64;
+const MAX_REGEX_LEN: usize = 512;
+const MAX_CATEGORY_LEN: usize = 32;
+/// Matches of one pattern checked against its checksum before giving up on it.
+const MAX_CANDIDATES: usize = 32;
+
+/// Check-digit schemes a pattern can require. The match's digits (and, where the scheme
+/// uses them, letters) are what gets checked; separators are ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Checksum {
+    Luhn,
+    /// ISO 13616 mod-97.
+    Iban,
+    /// Greek tax number (ΑΦΜ).
+    GreekAfm,
+    /// Spanish DNI / NIE control letter.
+    SpanishDni,
+    /// German tax ID (Steuer-IdNr), ISO 7064 MOD 11,10.
+    GermanTaxId,
+    /// French social security number (NIR) key.
+    FrenchNir,
+    /// US SSN: the number ranges the SSA never assigns.
+    UsSsn,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackPattern {
+    /// Shown as the clipboard entry's category, e.g. "National ID".
+    pub category: String,
+    pub regex: String,
+    #[serde(default)]
+    pub checksum: Option<Checksum>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PatternPack {
+    /// Lowercase letters, digits, `-` and `_`; also the file name of a user pack.
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub patterns: Vec<PackPattern>,
+}
+
+/// A pack as listed in the settings.
+#[derive(Serialize, Debug, Clone)]
+pub struct PackInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub builtin: bool,
+    pub enabled: bool,
+    pub pattern_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct PackConfig {
+    disabled: Vec<String>,
+}
+
+struct CompiledPattern {
+    category: String,
+    regex: Regex,
+    checksum: Option<Checksum>,
+}
+
+struct CompiledPack {
+    pack: PatternPack,
+    builtin: bool,
+    patterns: Vec<CompiledPattern>,
+}
+
+// ==========================================
+// --- BUILT-IN PACKS ---
+// ==========================================
+
+fn pattern(category: &str, regex: &str, checksum: Option<Checksum>) -> PackPattern {
+    PackPattern {
+        category: category.to_string(),
+        regex: regex.to_string(),
+        checksum,
+    }
+}
+
+fn builtin_packs() -> Vec<PatternPack> {
+    let pack = |id: &str, name: &str, patterns: Vec<PackPattern>| PatternPack {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: String::new(),
+        patterns,
+    };
+    vec![
+        pack(
+            "gr",
+            "Greece",
+            vec![
+                pattern("Tax ID", r"\b[0-9]{9}\b", Some(Checksum::GreekAfm)),
+                // AMKA: birth date (DDMMYY) plus five digits, Luhn-checked.
+                pattern(
+                    "National ID",
+                    r"\b(?:0[1-9]|[12][0-9]|3[01])(?:0[1-9]|1[0-2])[0-9]{7}\b",
+                    Some(Checksum::Luhn),
+                ),
+            ],
+        ),
+        pack(
+            "us",
+            "United States",
+            vec![pattern(
+                "National ID",
+                r"\b[0-9]{3}-[0-9]{2}-[0-9]{4}\b",
+                Some(Checksum::UsSsn),
+            )],
+        ),
+        pack(
+            "uk",
+            "United Kingdom",
+            vec![pattern(
+                "National ID",
+                r"(?i)\b[A-CEGHJ-PR-TW-Z][A-CEGHJ-NPR-TW-Z] ?[0-9]{2} ?[0-9]{2} ?[0-9]{2} ?[A-D]\b",
+                None,
+            )],
+        ),
+        pack(
+            "de",
+            "Germany",
+            vec![pattern(
+                "Tax ID",
+                r"\b[1-9][0-9] ?[0-9]{3} ?[0-9]{3} ?[0-9]{3}\b",
+                Some(Checksum::GermanTaxId),
+            )],
+        ),
+        pack(
+            "es",
+            "Spain",
+            vec![pattern(
+                "National ID",
+                r"(?i)\b[XYZ0-9][0-9]{7}-?[A-Z]\b",
+                Some(Checksum::SpanishDni),
+            )],
+        ),
+        pack(
+            "fr",
+            "France",
+            vec![pattern(
+                "National ID",
+                r"(?i)\b[12] ?[0-9]{2} ?[0-9]{2} ?(?:[0-9]{2}|2A|2B) ?[0-9]{3} ?[0-9]{3} ?[0-9]{2}\b",
+                Some(Checksum::FrenchNir),
+            )],
+        ),
+        pack(
+            "it",
+            "Italy",
+            vec![pattern(
+                "Tax ID",
+                r"(?i)\b[A-Z]{6}[0-9LMNPQRSTUV]{2}[ABCDEHLMPRST][0-9LMNPQRSTUV]{2}[A-Z][0-9LMNPQRSTUV]{3}[A-Z]\b",
+                None,
+            )],
+        ),
+    ]
+}
+
+// ==========================================
+// --- CHECKSUMS ---
+// ==========================================
+
+fn digits(text: &str) -> Vec<u32> {
+    text.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+pub fn luhn_valid(text: &str) -> bool {
+    let digits = digits(text);
+    if digits.len() < 2 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2 == 1, d * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// ISO 13616: country code and check digits moved to the end, letters as 10..35, the
+/// whole number mod 97 must be 1. Spaces and case are ignored.
+pub fn iban_valid(text: &str) -> bool {
+    let iban: Vec<char> = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if !(15..=34).contains(&iban.len())
+        || !iban[..2].iter().all(|c| c.is_ascii_uppercase())
+        || !iban[2..4].iter().all(|c| c.is_ascii_digit())
+        || !iban.iter().all(|c| c.is_ascii_alphanumeric())
+    {
+        return false;
+    }
+    let mut rem = 0u32;
+    for c in iban[4..].iter().chain(&iban[..4]) {
+        let value = c.to_digit(36).unwrap_or(0);
+        rem = if value >= 10 {
+            (rem * 100 + value) % 97
+        } else {
+            (rem * 10 + value) % 97
+        };
+    }
+    rem == 1
+}
+
+fn greek_afm_valid(text: &str) -> bool {
+    let d = digits(text);
+    if d.len() != 9 || d.iter().all(|&x| x == 0) {
+        return false;
+    }
+    let sum: u32 = d[..8].iter().enumerate().map(|(i, &x)| x << (8 - i)).sum();
+    sum % 11 % 10 == d[8]
+}
+
+fn spanish_dni_valid(text: &str) -> bool {
+    const LETTERS: &[u8; 23] = b"TRWAGMYFPDXBNJZSQVHLCKE";
+    let chars: Vec<char> = text
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if chars.len() != 9 {
+        return false;
+    }
+    // NIE: the leading X / Y / Z stands for 0 / 1 / 2.
+    let lead = match chars[0] {
+        'X' => 0,
+        'Y' => 1,
+        'Z' => 2,
+        c => match c.to_digit(10) {
+            Some(d) => d,
+            None => return false,
+        },
+    };
+    let mut number = lead;
+    for c in &chars[1..8] {
+        match c.to_digit(10) {
+            Some(d) => number = number * 10 + d,
+            None => return false,
+        }
+    }
+    chars[8] == LETTERS[(number % 23) as usize] as char
+}
+
+fn german_tax_id_valid(text: &str) -> bool {
+    let d = digits(text);
+    if d.len() != 11 || d[0] == 0 {
+        return false;
+    }
+    let mut product = 10;
+    for &x in &d[..10] {
+        let mut sum = (x + product) % 10;
+        if sum == 0 {
+            sum = 10;
+        }
+        product = sum * 2 % 11;
+    }
+    let check = (11 - product) % 10;
+    check == d[10]
+}
+
+fn french_nir_valid(text: &str) -> bool {
+    let chars: Vec<char> = text
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if chars.len() != 15 {
+        return false;
+    }
+    // Corsica's department codes 2A / 2B count as 19 / 18 for the key.
+    let mut body: String = chars[..13].iter().collect();
+    if body[5..7] == *"2A" {
+        body.replace_range(5..7, "19");
+    } else if body[5..7] == *"2B" {
+        body.replace_range(5..7, "18");
+    }
+    let (Ok(number), Ok(key)) = (
+        body.parse::<u64>(),
+        chars[13..].iter().collect::<String>().parse::<u64>(),
+    ) else {
+        return false;
+    };
+    97 - number % 97 == key
+}
+
+fn us_ssn_valid(text: &str) -> bool {
+    let d = digits(text);
+    if d.len() != 9 {
+        return false;
+    }
+    let area = d[0] * 100 + d[1] * 10 + d[2];
+    let group = d[3] * 10 + d[4];
+    let serial = d[5..].iter().fold(0, |acc, &x| acc * 10 + x);
+    area != 0 && area != 666 && area < 900 && group != 0 && serial != 0
+}
+
+impl Checksum {
+    pub fn verify(self, text: &str) -> bool {
+        match self {
+            Checksum::Luhn => luhn_valid(text),
+            Checksum::Iban => iban_valid(text),
+            Checksum::GreekAfm => greek_afm_valid(text),
+            Checksum::SpanishDni => spanish_dni_valid(text),
+            Checksum::GermanTaxId => german_tax_id_valid(text),
+            Checksum::FrenchNir => french_nir_valid(text),
+            Checksum::UsSsn => us_ssn_valid(text),
+        }
+    }
+}
+
+// ==========================================
+// --- LOADING ---
+// ==========================================
+
+fn compile(pack: PatternPack, builtin: bool) -> Result<CompiledPack> {
+    let id_ok = (1..=32).contains(&pack.id.len())
+        && pack
+            .id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !id_ok {
+        return Err(anyhow!(
+            "Pack id must be 1-32 lowercase letters, digits, '-' or '_'"
+        ));
+    }
+    if pack.name.trim().is_empty() || pack.name.len() > 64 {
+        return Err(anyhow!("Pack name must be 1-64 characters"));
+    }
+    if pack.patterns.is_empty() || pack.patterns.len() > MAX_PATTERNS_PER_PACK {
+        return Err(anyhow!("A pack needs 1-{} patterns", MAX_PATTERNS_PER_PACK));
+    }
+
+    let mut patterns = Vec::with_capacity(pack.patterns.len());
+    for p in &pack.patterns {
+        if p.category.trim().is_empty() || p.category.len() > MAX_CATEGORY_LEN {
+            return Err(anyhow!(
+                "Category must be 1-{} characters",
+                MAX_CATEGORY_LEN
+            ));
+        }
+        if p.regex.len() > MAX_REGEX_LEN {
+            return Err(anyhow!(
+                "Pattern for '{}' is longer than {} characters",
+                p.category,
+                MAX_REGEX_LEN
+            ));
+        }
+        let regex = regexes::compile_untrusted(&p.regex)
+            .map_err(|e| anyhow!("Pattern for '{}' is invalid: {}", p.category, e))?;
+        patterns.push(CompiledPattern {
+            category: p.category.trim().to_string(),
+            regex,
+            checksum: p.checksum,
+        });
+    }
+    Ok(CompiledPack {
+        pack,
+        builtin,
+        patterns,
+    })
+}
+
+fn read_pack_file(path: &Path) -> Result<PatternPack> {
+    if fs::metadata(path)?.len() > MAX_PACK_FILE_BYTES {
+        return Err(anyhow!(
+            "File is larger than {} KiB",
+            MAX_PACK_FILE_BYTES / 1024
+        ));
+    }
+    serde_json::from_slice(&fs::read(path)?).map_err(|e| anyhow!("Not a pattern pack: {}", e))
+}
+
+fn load_config(dir: &Path) -> Result<PackConfig> {
+    let path = dir.join(CONFIG_FILE_NAME);
+    if !path.exists() {
+        return Ok(PackConfig::default());
+    }
+    let data = fs::read(&path)?;
+    serde_json::from_slice(&data).map_err(|_| anyhow!("Pattern pack settings are corrupted"))
+}
+
+fn save_config(dir: &Path, config: &PackConfig) -> Result<()> {
+    let path = dir.join(CONFIG_FILE_NAME);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(config)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// The packs in effect: built-ins, then the user's packs in file name order.
+pub struct Registry {
+    packs: Vec<CompiledPack>,
+    disabled: HashSet<String>,
+}
+
+impl Registry {
+    pub fn builtin() -> Self {
+        Self {
+            packs: builtin_packs()
+                .into_iter()
+                .map(|p| compile(p, true).expect("built-in pattern pack"))
+                .collect(),
+            disabled: HashSet::new(),
+        }
+    }
+
+    /// Built-ins plus every readable pack in `<dir>/pattern_packs`. Packs that fail to
+    /// load are skipped and reported as "file name: reason".
+    pub fn load(dir: &Path) -> (Self, Vec<String>) {
+        let mut registry = Self::builtin();
+        let mut problems = Vec::new();
+        match load_config(dir) {
+            Ok(config) => registry.disabled = config.disabled.into_iter().collect(),
+            Err(e) => problems.push(format!("{}: {}", CONFIG_FILE_NAME, e)),
+        }
+
+        let mut files: Vec<_> = fs::read_dir(dir.join(PACKS_DIR_NAME))
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        for path in files {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let compiled = read_pack_file(&path).and_then(|pack| compile(pack, false));
+            match compiled {
+                Ok(pack) if registry.find(&pack.pack.id).is_some() => problems.push(format!(
+                    "{}: a pack with id '{}' is already loaded",
+                    name, pack.pack.id
+                )),
+                Ok(pack) => registry.packs.push(pack),
+                Err(e) => problems.push(format!("{}: {}", name, e)),
+            }
+        }
+        (registry, problems)
+    }
+
+    fn find(&self, id: &str) -> Option<&CompiledPack> {
+        self.packs.iter().find(|p| p.pack.id == id)
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &CompiledPack> {
+        self.packs
+            .iter()
+            .filter(|p| !self.disabled.contains(&p.pack.id))
+    }
+
+    /// The category of the first enabled pattern with a match that passes its checksum.
+    pub fn detect(&self, text: &str) -> Option<String> {
+        for pattern in self.enabled().flat_map(|p| &p.patterns) {
+            let hit = pattern
+                .regex
+                .find_iter(text)
+                .take(MAX_CANDIDATES)
+                .any(|m| pattern.checksum.is_none_or(|c| c.verify(m.as_str())));
+            if hit {
+                return Some(pattern.category.clone());
+            }
+        }
+        None
+    }
+
+    /// Whether `category` comes from an enabled pack (their previews are masked).
+    pub fn is_pack_category(&self, category: &str) -> bool {
+        self.enabled()
+            .flat_map(|p| &p.patterns)
+            .any(|p| p.category == category)
+    }
+
+    pub fn list(&self) -> Vec<PackInfo> {
+        self.packs
+            .iter()
+            .map(|p| PackInfo {
+                id: p.pack.id.clone(),
+                name: p.pack.name.clone(),
+                description: p.pack.description.clone(),
+                builtin: p.builtin,
+                enabled: !self.disabled.contains(&p.pack.id),
+                pattern_count: p.patterns.len(),
+            })
+            .collect()
+    }
+}
+
+// ==========================================
+// --- ACTIVE REGISTRY ---
+// ==========================================
+// One registry for the whole app; the clipboard analyzer reads it on every copy. It
+// holds only the built-ins until `reload` runs at startup.
+
+fn active() -> &'static RwLock<Registry> {
+    static ACTIVE: OnceLock<RwLock<Registry>> = OnceLock::new();
+    ACTIVE.get_or_init(|| RwLock::new(Registry::builtin()))
+}
+
+pub fn detect(text: &str) -> Option<String> {
+    active()
+        .read()
+        .unwrap_or_else(|p| p.into_inner())
+        .detect(text)
+}
+
+pub fn is_pack_category(category: &str) -> bool {
+    active()
+        .read()
+        .unwrap_or_else(|p| p.into_inner())
+        .is_pack_category(category)
+}
+
+pub fn list() -> Vec<PackInfo> {
+    active().read().unwrap_or_else(|p| p.into_inner()).list()
+}
+
+/// Re-reads the settings and user packs from `dir` and makes them active. Returns the
+/// packs that could not be loaded.
+pub fn reload(dir: &Path) -> Vec<String> {
+    let (registry, problems) = Registry::load(dir);
+    *active().write().unwrap_or_else(|p| p.into_inner()) = registry;
+    problems
+}
+
+/// Turns packs on or off. Ids not in `enabled` are disabled, including packs that are
+/// not installed right now.
+pub fn set_enabled(dir: &Path, enabled: &[String]) -> Result<Vec<PackInfo>> {
+    let mut registry = active().write().unwrap_or_else(|p| p.into_inner());
+    let disabled: HashSet<String> = registry
+        .packs
+        .iter()
+        .map(|p| p.pack.id.clone())
+        .filter(|id| !enabled.contains(id))
+        .collect();
+    let mut stored: Vec<String> = disabled.iter().cloned().collect();
+    stored.sort();
+    save_config(dir, &PackConfig { disabled: stored })?;
+    registry.disabled = disabled;
+    Ok(registry.list())
+}
+
+/// Validates the pack at `source`, copies it into the packs folder as `<id>.json`
+/// (replacing an earlier version of the same user pack) and reloads.
+pub fn import(dir: &Path, source: &Path) -> Result<PackInfo> {
+    let compiled = compile(read_pack_file(source)?, false)?;
+    let id = compiled.pack.id.clone();
+    if builtin_packs().iter().any(|p| p.id == id) {
+        return Err(anyhow!("'{}' is the id of a built-in pack", id));
+    }
+    let packs_dir = dir.join(PACKS_DIR_NAME);
+    fs::create_dir_all(&packs_dir)?;
+    let target = packs_dir.join(format!("{}.json", id));
+    let tmp = target.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(&compiled.pack)?)?;
+    fs::rename(&tmp, &target)?;
+
+    reload(dir);
+    list()
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| anyhow!("The pack was saved but could not be loaded"))
+}
+
+/// Deletes a user pack's file and reloads. Built-in packs can only be disabled.
+pub fn remove(dir: &Path, id: &str) -> Result<()> {
+    if builtin_packs().iter().any(|p| p.id == id) {
+        return Err(anyhow!("Built-in packs can be disabled but not removed"));
+    }
+    // Packs dropped in by hand need not be named after their id, so the file is found
+    // by reading the folder rather than built from `id`.
+    let target = fs::read_dir(dir.join(PACKS_DIR_NAME))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .find(|p| read_pack_file(p).is_ok_and(|pack| pack.id == id))
+        .ok_or_else(|| anyhow!("No pattern pack with id '{}'", id))?;
+    fs::remove_file(&target)?;
+    reload(dir);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert!(iban_valid("DE89 3704 0044 0532 0130 00"));
+        assert!(iban_valid("gr16 0110 1250 0000 0001 2300 695"));
+        assert!(!iban_valid("DE89 3704 0044 0532 0130 01"));
+        assert!(greek_afm_valid("094014201"));
+        assert!(!greek_afm_valid("094014202"));
+        assert!(spanish_dni_valid("12345678Z"));
+        assert!(spanish_dni_valid("x1234567-l"));
+        assert!(!spanish_dni_valid("12345678A"));
+        assert!(german_tax_id_valid("86 095 742 719"));
+        assert!(!german_tax_id_valid("86095742718"));
+        assert!(french_nir_valid("1 84 12 76 451 089 46"));
+        assert!(!french_nir_valid("1 84 12 76 451 089 47"));
+        assert!(us_ssn_valid("536-22-1234"));
+        assert!(!us_ssn_valid("666-22-1234"));
+        assert!(luhn_valid("01018001238"));
+    }
+
+    #[test]
+    fn test_builtin_packs_need_a_valid_check_digit() {
+        let registry = Registry::builtin();
+        assert_eq!(registry.detect("ΑΦΜ: 094014201").as_deref(), Some("Tax ID"));
+        assert_eq!(registry.detect("094014202"), None);
+        assert_eq!(
+            registry.detect("DNI 12345678Z").as_deref(),
+            Some("National ID")
+        );
+        assert_eq!(
+            registry.detect("SSN 536-22-1234").as_deref(),
+            Some("National ID")
+        );
+        assert_eq!(registry.detect("Order 2024-10-16 shipped"), None);
+        assert!(registry.is_pack_category("Tax ID"));
+    }
+
+    #[test]
+    fn test_user_packs_and_disabled_packs() {
+        let dir = std::env::temp_dir().join(format!("qre_packs_{}", uuid::Uuid::new_v4()));
+        let packs = dir.join(PACKS_DIR_NAME);
+        fs::create_dir_all(&packs).unwrap();
+        let pack = r#"{"id":"acme","name":"ACME badges","patterns":[
+            {"category":"Badge","regex":"\\bACME-[0-9]{6}\\b"}]}"#;
+        fs::write(packs.join("acme.json"), pack).unwrap();
+        fs::write(
+            packs.join("broken.json"),
+            r#"{"id":"broken","name":"Broken","patterns":[{"category":"X","regex":"("}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            packs.join("clash.json"),
+            r#"{"id":"gr","name":"Clash","patterns":[{"category":"X","regex":"x"}]}"#,
+        )
+        .unwrap();
+        save_config(
+            &dir,
+            &PackConfig {
+                disabled: vec!["us".to_string()],
+            },
+        )
+        .unwrap();
+
+        let (registry, problems) = Registry::load(&dir);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            registry.detect("badge ACME-123456").as_deref(),
+            Some("Badge")
+        );
+        assert_eq!(registry.detect("536-22-1234"), None);
+        let listed = registry.list();
+        assert!(listed.iter().any(|p| p.id == "acme" && !p.builtin));
+        assert!(listed.iter().any(|p| p.id == "us" && !p.enabled));
+
+        // Oversized patterns are refused before compiling.
+        let long = PatternPack {
+            id: "long".to_string(),
+            name: "Long".to_string(),
+            description: String::new(),
+            patterns: vec![pattern("X", &"a".repeat(MAX_REGEX_LEN + 1), None)],
+        };
+        assert!(compile(long, false).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE pattern_packs.rs ---
@@ -0,0 +1,313 @@
+// --- START OF FILE photo_locations.rs ---
+
+// ==========================================
+// --- PHOTO LOCATION AUDIT ---
+// ==========================================
+// A single geotagged photo reveals one place; a library of them reveals where someone
+// lives, works and spends weekends. This scan reads the GPS position of every photo
+// under the Pictures folder (`cleaner::gps_coordinates`), groups nearby positions into
+// clusters and ranks them, so the user sees "your photos reveal these frequent
+// locations" and can strip the positions of a whole cluster at once.
+//
+// Clustering is a single greedy pass: a photo joins the nearest cluster whose centre is
+// within `CLUSTER_RADIUS_M`, otherwise it starts a new one. That is order-dependent at
+// the edges but stable enough for "home" / "office" style hotspots, and linear in the
+// number of photos times clusters.
+
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Photos read per scan; larger libraries are sampled up to this count.
+pub const MAX_PHOTOS: usize = 20_000;
+/// Extensions the metadata cleaner can also strip.
+const PHOTO_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "tiff"];
+pub const CLUSTER_RADIUS_M: f64 = 250.0;
+/// Clusters with at least this many photos are reported as frequent locations.
+pub const FREQUENT_MIN_PHOTOS: usize = 3;
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LocationCluster {
+    /// "loc-1" is the cluster with the most photos.
+    pub id: String,
+    /// Centre of the cluster, in decimal degrees.
+    pub latitude: f64,
+    pub longitude: f64,
+    pub photo_count: usize,
+    pub frequent: bool,
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct PhotoLocationReport {
+    pub roots: Vec<String>,
+    pub photos_scanned: usize,
+    pub photos_with_gps: usize,
+    /// Stopped at `MAX_PHOTOS`.
+    pub truncated: bool,
+    /// Largest first.
+    pub clusters: Vec<LocationCluster>,
+}
+
+impl PhotoLocationReport {
+    /// Paths of the photos in the given clusters, for batch cleaning.
+    pub fn paths_for(&self, cluster_ids: &[String]) -> Vec<String> {
+        self.clusters
+            .iter()
+            .filter(|c| cluster_ids.contains(&c.id))
+            .flat_map(|c| c.paths.iter().cloned())
+            .collect()
+    }
+}
+
+// ==========================================
+// --- CLUSTERING ---
+// ==========================================
+
+/// Great-circle distance in metres.
+fn haversine_m(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Groups `(path, (lat, lon))` points within `radius_m` of a cluster centre.
+pub fn cluster_points(points: Vec<(String, (f64, f64))>, radius_m: f64) -> Vec<LocationCluster> {
+    // Running sums keep each centre the mean of its members.
+    struct Acc {
+        sum: (f64, f64),
+        paths: Vec<String>,
+    }
+    impl Acc {
+        fn centre(&self) -> (f64, f64) {
+            let n = self.paths.len() as f64;
+            (self.sum.0 / n, self.sum.1 / n)
+        }
+    }
+
+    let mut clusters: Vec<Acc> = Vec::new();
+    for (path, point) in points {
+        let nearest = clusters
+            .iter_mut()
+            .map(|c| (haversine_m(c.centre(), point), c))
+            .filter(|(d, _)| *d <= radius_m)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match nearest {
+            Some((_, c)) => {
+                c.sum = (c.sum.0 + point.0, c.sum.1 + point.1);
+                c.paths.push(path);
+            }
+            None => clusters.push(Acc {
+                sum: point,
+                paths: vec![path],
+            }),
+        }
+    }
+
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.paths.len()));
+    clusters
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let (latitude, longitude) = c.centre();
+            LocationCluster {
+                id: format!("loc-{}", i + 1),
+                latitude,
+                longitude,
+                photo_count: c.paths.len(),
+                frequent: c.paths.len() >= FREQUENT_MIN_PHOTOS,
+                paths: c.paths,
+            }
+        })
+        .collect()
+}
+
+// ==========================================
+// --- SCANNING ---
+// ==========================================
+
+/// The user's photo folders on this platform.
+pub fn photo_dirs() -> Vec<PathBuf> {
+    #[cfg(not(target_os = "android"))]
+    let dirs: Vec<PathBuf> = directories::UserDirs::new()
+        .and_then(|d| d.picture_dir().map(Path::to_path_buf))
+        .into_iter()
+        .collect();
+    #[cfg(target_os = "android")]
+    let dirs: Vec<PathBuf> = ["/storage/emulated/0/DCIM", "/storage/emulated/0/Pictures"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    dirs.into_iter().filter(|d| d.is_dir()).collect()
+}
+
+fn is_photo(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| PHOTO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Reads the GPS position of every photo under `roots` and clusters them.
+pub fn scan(roots: &[PathBuf]) -> PhotoLocationReport {
+    let mut photos: Vec<PathBuf> = roots
+        .iter()
+        .flat_map(|root| {
+            WalkDir::new(root)
+                .follow_links(false)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file() && is_photo(e.path()))
+                .map(|e| e.into_path())
+                .take(MAX_PHOTOS + 1)
+        })
+        .collect();
+    let truncated = photos.len() > MAX_PHOTOS;
+    photos.truncate(MAX_PHOTOS);
+
+    let points: Vec<(String, (f64, f64))> = photos
+        .par_iter()
+        .filter_map(|p| {
+            crate::cleaner::gps_coordinates(p).map(|pos| (p.to_string_lossy().to_string(), pos))
+        })
+        .collect();
+
+    PhotoLocationReport {
+        roots: roots
+            .iter()
+            .map(|r| r.to_string_lossy().to_string())
+            .collect(),
+        photos_scanned: photos.len(),
+        photos_with_gps: points.len(),
+        truncated,
+        clusters: cluster_points(points, CLUSTER_RADIUS_M),
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{experimental::Writer, Field, In, Rational, Tag, Value};
+
+    /// Writes a TIFF holding only a GPS block (degrees/minutes/seconds + hemisphere).
+    fn write_geotagged(path: &Path, lat: (u32, u32, u32, &str), lon: (u32, u32, u32, &str)) {
+        let dms = |d, m, s| {
+            Value::Rational(vec![
+                Rational::from((d, 1)),
+                Rational::from((m, 1)),
+                Rational::from((s, 1)),
+            ])
+        };
+        let fields = [
+            Field {
+                tag: Tag::GPSLatitudeRef,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![lat.3.as_bytes().to_vec()]),
+            },
+            Field {
+                tag: Tag::GPSLatitude,
+                ifd_num: In::PRIMARY,
+                value: dms(lat.0, lat.1, lat.2),
+            },
+            Field {
+                tag: Tag::GPSLongitudeRef,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![lon.3.as_bytes().to_vec()]),
+            },
+            Field {
+                tag: Tag::GPSLongitude,
+                ifd_num: In::PRIMARY,
+                value: dms(lon.0, lon.1, lon.2),
+            },
+        ];
+        let mut writer = Writer::new();
+        for f in &fields {
+            writer.push_field(f);
+        }
+        let mut buf = std::io::Cursor::new(Vec::new());
+        writer.write(&mut buf, true).unwrap();
+        std::fs::write(path, buf.into_inner()).unwrap();
+    }
+
+    #[test]
+    fn test_gps_coordinates_from_exif() {
+        let dir = std::env::temp_dir().join("qre_photo_locations_tests_exif");
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join("south_west.tiff");
+        // 33°52'8"S 151°12'36"E (Sydney) and 40°26'46"N 79°58'56"W (Pittsburgh).
+        write_geotagged(&path, (33, 52, 8, "S"), (151, 12, 36, "E"));
+        let (lat, lon) = crate::cleaner::gps_coordinates(&path).unwrap();
+        assert!((lat + 33.8689).abs() < 1e-3 && (lon - 151.21).abs() < 1e-3);
+
+        write_geotagged(&path, (40, 26, 46, "N"), (79, 58, 56, "W"));
+        let (lat, lon) = crate::cleaner::gps_coordinates(&path).unwrap();
+        assert!((lat - 40.4461).abs() < 1e-3 && (lon + 79.9822).abs() < 1e-3);
+
+        write_geotagged(&path, (0, 0, 0, "N"), (0, 0, 0, "E"));
+        assert!(crate::cleaner::gps_coordinates(&path).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_clustering() {
+        let home = (51.5007, -0.1246);
+        let points = vec![
+            ("a.jpg".to_string(), home),
+            ("b.jpg".to_string(), (51.5010, -0.1250)),
+            ("c.jpg".to_string(), (48.8584, 2.2945)),
+            ("d.jpg".to_string(), (51.5004, -0.1242)),
+        ];
+        let clusters = cluster_points(points, CLUSTER_RADIUS_M);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].id, "loc-1");
+        assert_eq!(clusters[0].photo_count, 3);
+        assert!(clusters[0].frequent && !clusters[1].frequent);
+        assert!(haversine_m((clusters[0].latitude, clusters[0].longitude), home) < 100.0);
+        assert_eq!(clusters[1].paths, vec!["c.jpg"]);
+
+        let report = PhotoLocationReport {
+            clusters,
+            ..Default::default()
+        };
+        assert_eq!(report.paths_for(&["loc-2".to_string()]), vec!["c.jpg"]);
+    }
+
+    #[test]
+    fn test_haversine() {
+        // London to Paris is about 344 km.
+        let d = haversine_m((51.5074, -0.1278), (48.8566, 2.3522));
+        assert!((d - 343_500.0).abs() < 2_000.0, "{}", d);
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let dir = std::env::temp_dir().join("qre_photo_locations_tests_scan");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("2024")).unwrap();
+        write_geotagged(&dir.join("a.tiff"), (40, 26, 46, "N"), (79, 58, 56, "W"));
+        write_geotagged(
+            &dir.join("2024/b.tiff"),
+            (40, 26, 47, "N"),
+            (79, 58, 55, "W"),
+        );
+        std::fs::write(dir.join("notes.txt"), b"not a photo").unwrap();
+        std::fs::write(dir.join("plain.jpg"), b"not really a jpeg").unwrap();
+
+        let report = scan(std::slice::from_ref(&dir));
+        assert_eq!(report.photos_scanned, 3);
+        assert_eq!(report.photos_with_gps, 2);
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].photo_count, 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE photo_locations.rs ---
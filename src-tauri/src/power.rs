@@ -0,0 +1,411 @@
+// --- START OF FILE power.rs ---
+
+// ==========================================
+// --- POWER STATE ---
+// ==========================================
+// Long shred/encrypt/wipe jobs die when the laptop goes to sleep halfway, and a
+// 35-pass Gutmann run can drain a battery. Two mechanisms:
+//
+// SLEEP INHIBITION
+// While any `PowerHold` is alive the system is kept awake (the display may still turn
+// off). Holds are reference-counted, so overlapping jobs share one platform inhibitor:
+//   Windows  SetThreadExecutionState(ES_SYSTEM_REQUIRED) on a small holder thread
+//   macOS    `caffeinate -i`, tied to our PID so it cannot outlive the app
+//   Linux    `systemd-inhibit --what=sleep:idle` (best effort: absent without systemd)
+//
+// LOW-BATTERY PAUSE
+// Batch loops call `wait_for_power` between items. Below the policy threshold and not
+// on AC power, the job pauses until the charger is connected, the level recovers, the
+// policy is relaxed, or the job is cancelled. Work already done is never rolled back.
+//
+// The policy lives in memory; the frontend stores it with the other settings and
+// pushes it with `set_power_policy` on start-up.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often a paused job re-reads the battery.
+const PAUSE_POLL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct PowerPolicy {
+    pub inhibit_sleep: bool,
+    /// Pause jobs below this battery percentage when not charging; 0 disables.
+    pub pause_below_percent: u8,
+}
+
+impl PowerPolicy {
+    pub const fn new() -> Self {
+        Self {
+            inhibit_sleep: true,
+            pause_below_percent: 15,
+        }
+    }
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatteryStatus {
+    /// `None` on desktops without a battery (or when it cannot be read).
+    pub percent: Option<u8>,
+    pub on_ac: Option<bool>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PowerStatus {
+    pub battery: BatteryStatus,
+    pub policy: PowerPolicy,
+    /// A job currently holds the system awake.
+    pub inhibiting_sleep: bool,
+    /// Number of jobs waiting for power right now.
+    pub paused_jobs: usize,
+}
+
+static POLICY: Mutex<PowerPolicy> = Mutex::new(PowerPolicy::new());
+static PAUSED_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn policy() -> PowerPolicy {
+    POLICY.lock().map(|p| *p).unwrap_or_default()
+}
+
+pub fn set_policy(policy: PowerPolicy) -> Result<(), String> {
+    if policy.pause_below_percent > 100 {
+        return Err("The battery threshold must be between 0 and 100%.".to_string());
+    }
+    *POLICY.lock().map_err(|e| e.to_string())? = policy;
+    Ok(())
+}
+
+pub fn status() -> PowerStatus {
+    PowerStatus {
+        battery: read_battery(),
+        policy: policy(),
+        inhibiting_sleep: HOLDS.lock().map(|h| h.inhibitor.is_some()).unwrap_or(false),
+        paused_jobs: PAUSED_JOBS.load(Ordering::Relaxed),
+    }
+}
+
+// ==========================================
+// --- LOW-BATTERY PAUSE ---
+// ==========================================
+
+/// True when jobs should wait: below the threshold and not known to be charging.
+pub fn should_pause(battery: &BatteryStatus, policy: &PowerPolicy) -> bool {
+    match battery.percent {
+        Some(percent) => {
+            policy.pause_below_percent > 0
+                && percent < policy.pause_below_percent
+                && battery.on_ac != Some(true)
+        }
+        None => false,
+    }
+}
+
+/// Blocks while `should_pause` holds. Returns false if `cancel` was raised meanwhile.
+pub fn wait_for_power(cancel: Option<&AtomicBool>) -> bool {
+    let cancelled = || cancel.is_some_and(|c| c.load(Ordering::Relaxed));
+    if !should_pause(&read_battery(), &policy()) {
+        return !cancelled();
+    }
+    PAUSED_JOBS.fetch_add(1, Ordering::Relaxed);
+    while !cancelled() && should_pause(&read_battery(), &policy()) {
+        std::thread::sleep(PAUSE_POLL);
+    }
+    PAUSED_JOBS.fetch_sub(1, Ordering::Relaxed);
+    !cancelled()
+}
+
+// ==========================================
+// --- BATTERY READING ---
+// ==========================================
+
+/// Parses `pmset -g batt` ("Now drawing from 'AC Power' ... 85%; charging; ...").
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_pmset(output: &str) -> BatteryStatus {
+    let percent = output
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .find_map(|word| word.strip_suffix('%')?.parse::<u8>().ok());
+    let on_ac = output
+        .contains("drawing from")
+        .then(|| output.contains("'AC Power'"));
+    BatteryStatus { percent, on_ac }
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_battery() -> BatteryStatus {
+    let mut status = BatteryStatus::default();
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return status;
+    };
+    for supply in supplies.flatten() {
+        let read = |name: &str| {
+            std::fs::read_to_string(supply.path().join(name))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Battery" if status.percent.is_none() => status.percent = read("capacity").parse().ok(),
+            "Mains" | "USB" => {
+                let online = read("online") == "1";
+                status.on_ac = Some(status.on_ac.unwrap_or(false) || online);
+            }
+            _ => {}
+        }
+    }
+    status
+}
+
+#[cfg(target_os = "macos")]
+pub fn read_battery() -> BatteryStatus {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .map(|out| parse_pmset(&String::from_utf8_lossy(&out.stdout)))
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "windows")]
+pub fn read_battery() -> BatteryStatus {
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)] // Full SYSTEM_POWER_STATUS layout; only some fields are read.
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    let mut raw = SystemPowerStatus::default();
+    // SAFETY: `raw` is a correctly laid out SYSTEM_POWER_STATUS owned by this frame.
+    if unsafe { GetSystemPowerStatus(&mut raw) } == 0 {
+        return BatteryStatus::default();
+    }
+    const NO_SYSTEM_BATTERY: u8 = 128;
+    BatteryStatus {
+        percent: (raw.battery_flag & NO_SYSTEM_BATTERY == 0 && raw.battery_life_percent <= 100)
+            .then_some(raw.battery_life_percent),
+        on_ac: match raw.ac_line_status {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn read_battery() -> BatteryStatus {
+    BatteryStatus::default()
+}
+
+// ==========================================
+// --- SLEEP INHIBITION ---
+// ==========================================
+
+struct Holds {
+    count: usize,
+    inhibitor: Option<Inhibitor>,
+}
+
+static HOLDS: Mutex<Holds> = Mutex::new(Holds {
+    count: 0,
+    inhibitor: None,
+});
+
+/// Keeps the system awake until dropped (when the policy allows inhibition).
+#[must_use = "sleep is allowed again as soon as the hold is dropped"]
+pub struct PowerHold(());
+
+impl PowerHold {
+    pub fn acquire(reason: &str) -> Self {
+        if let Ok(mut holds) = HOLDS.lock() {
+            holds.count += 1;
+            if holds.inhibitor.is_none() && policy().inhibit_sleep {
+                holds.inhibitor = Inhibitor::start(reason);
+            }
+        }
+        PowerHold(())
+    }
+}
+
+impl Drop for PowerHold {
+    fn drop(&mut self) {
+        if let Ok(mut holds) = HOLDS.lock() {
+            holds.count = holds.count.saturating_sub(1);
+            if holds.count == 0 {
+                // Dropping the inhibitor releases it.
+                holds.inhibitor = None;
+            }
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+struct Inhibitor(std::process::Child);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Inhibitor {
+    fn start(reason: &str) -> Option<Self> {
+        use std::process::{Command, Stdio};
+        #[cfg(target_os = "linux")]
+        let mut cmd = {
+            let mut cmd = Command::new("systemd-inhibit");
+            cmd.args([
+                "--what=sleep:idle",
+                "--who=QRE Privacy Toolkit",
+                "--mode=block",
+            ])
+            .arg(format!("--why={}", reason))
+            .args(["sleep", "infinity"]);
+            cmd
+        };
+        #[cfg(target_os = "macos")]
+        let mut cmd = {
+            let _ = reason;
+            let mut cmd = Command::new("caffeinate");
+            cmd.args(["-i", "-w", &std::process::id().to_string()]);
+            cmd
+        };
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()
+            .map(Inhibitor)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// The execution state is per thread, so a dedicated thread holds it for the job's lifetime.
+#[cfg(target_os = "windows")]
+struct Inhibitor(Option<std::sync::mpsc::Sender<()>>);
+
+#[cfg(target_os = "windows")]
+impl Inhibitor {
+    fn start(_reason: &str) -> Option<Self> {
+        const ES_CONTINUOUS: u32 = 0x8000_0000;
+        const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn SetThreadExecutionState(flags: u32) -> u32;
+        }
+
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        std::thread::spawn(move || {
+            // SAFETY: plain Win32 call with constant flags.
+            unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) };
+            // Returns when the sender is dropped.
+            let _ = released.recv();
+            unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+        });
+        Some(Inhibitor(Some(release)))
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        self.0.take();
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+struct Inhibitor;
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+impl Inhibitor {
+    fn start(_reason: &str) -> Option<Self> {
+        None
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_pause() {
+        let policy = PowerPolicy::new();
+        let low = BatteryStatus {
+            percent: Some(10),
+            on_ac: Some(false),
+        };
+        assert!(should_pause(&low, &policy));
+        assert!(!should_pause(
+            &BatteryStatus {
+                on_ac: Some(true),
+                ..low
+            },
+            &policy
+        ));
+        assert!(!should_pause(
+            &BatteryStatus {
+                percent: Some(50),
+                ..low
+            },
+            &policy
+        ));
+        // Desktop without a battery, or the pause disabled.
+        assert!(!should_pause(&BatteryStatus::default(), &policy));
+        let disabled = PowerPolicy {
+            pause_below_percent: 0,
+            ..policy
+        };
+        assert!(!should_pause(&low, &disabled));
+    }
+
+    #[test]
+    fn test_parse_pmset() {
+        let ac = "Now drawing from 'AC Power'\n -InternalBattery-0 (id=1234)\t85%; charging; 0:40 remaining present: true";
+        assert_eq!(
+            parse_pmset(ac),
+            BatteryStatus {
+                percent: Some(85),
+                on_ac: Some(true)
+            }
+        );
+        let battery =
+            "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1234)\t9%; discharging;";
+        assert_eq!(
+            parse_pmset(battery),
+            BatteryStatus {
+                percent: Some(9),
+                on_ac: Some(false)
+            }
+        );
+        assert_eq!(parse_pmset(""), BatteryStatus::default());
+    }
+
+    #[test]
+    fn test_cancelled_wait_returns_false() {
+        let cancel = AtomicBool::new(true);
+        assert!(!wait_for_power(Some(&cancel)));
+    }
+}
+
+// --- END OF FILE power.rs ---
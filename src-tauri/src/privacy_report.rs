@@ -0,0 +1,911 @@
+// --- START OF FILE privacy_report.rs ---
+
+// ==========================================
+// --- PRIVACY POSTURE REPORT ---
+// ==========================================
+// One scored overview built from the other modules' checks:
+//   - junk:      leftover caches, logs and histories (`system_cleaner::scan_targets`),
+//   - metadata:  GPS positions and author names in files under the user folders,
+//   - breaches:  open breach-monitor alerts (needs an unlocked vault),
+//   - passwords: weak and reused passwords (needs an unlocked vault),
+//   - telemetry: the operating system's diagnostics and advertising settings,
+//   - network:   VPN, secure DNS and MAC randomization (`network_privacy`).
+//
+// Every section gets a 0-100 score; the overall score is their weighted average. A
+// section that could not be checked (vault locked, offline) is left out of the average
+// rather than counted as perfect or as failed.
+//
+// The report holds counts, names and paths only. No password, key or breach-monitor
+// email address ever ends up in it, so it can be exported and shared as-is.
+
+use crate::breach_monitor::BreachAlert;
+use crate::network_privacy::{MacStatus, NetworkPrivacyReport};
+use crate::passwords::PasswordVault;
+use crate::system_cleaner::JunkItem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Files inspected for metadata; the scan is a sample, not an inventory.
+pub const MAX_METADATA_FILES: usize = 300;
+const METADATA_MAX_DEPTH: usize = 4;
+const METADATA_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "tiff", "pdf", "docx", "xlsx", "pptx",
+];
+/// Paths listed per finding; the counts stay exact.
+const MAX_LISTED_PATHS: usize = 10;
+/// Passwords shorter than this count as weak regardless of their characters.
+const MIN_STRONG_LENGTH: usize = 12;
+
+const JUNK_WARN_BYTES: u64 = 500 * 1024 * 1024;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    Json,
+    Pdf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReportSection {
+    /// "junk", "metadata", "breaches", "passwords", "telemetry" or "network".
+    pub id: String,
+    pub title: String,
+    /// 0-100; `None` when the section could not be checked.
+    pub score: Option<u8>,
+    pub summary: String,
+    pub findings: Vec<String>,
+    pub recommendations: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PrivacyReport {
+    /// Unix seconds.
+    pub generated_at: i64,
+    pub score: u8,
+    /// "A" (80+) to "F" (below 40).
+    pub grade: String,
+    pub sections: Vec<ReportSection>,
+    /// Every section's recommendations, lowest-scoring section first.
+    pub recommendations: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MetadataScan {
+    pub files_checked: usize,
+    /// Stopped at `MAX_METADATA_FILES`.
+    pub truncated: bool,
+    pub with_gps: Vec<String>,
+    pub with_author: Vec<String>,
+}
+
+/// Counts only; computed from an unlocked vault.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PasswordSummary {
+    pub total: usize,
+    pub weak: usize,
+    /// Entries whose password is also used by another entry.
+    pub reused: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TelemetrySetting {
+    pub name: String,
+    /// `None` when the setting could not be read.
+    pub enabled: Option<bool>,
+    pub guidance: String,
+}
+
+/// Everything the report is built from; `None` marks a check that was skipped.
+#[derive(Debug, Default)]
+pub struct ReportInputs {
+    pub junk: Option<Vec<JunkItem>>,
+    pub metadata: Option<MetadataScan>,
+    pub breach_alerts: Option<Vec<BreachAlert>>,
+    pub passwords: Option<PasswordSummary>,
+    pub telemetry: Option<Vec<TelemetrySetting>>,
+    pub network: Option<NetworkPrivacyReport>,
+}
+
+// ==========================================
+// --- DATA COLLECTION ---
+// ==========================================
+
+/// Samples documents and photos under the user folders for GPS and author metadata.
+pub fn scan_metadata(roots: &[String]) -> MetadataScan {
+    let mut scan = MetadataScan::default();
+    let files = roots.iter().flat_map(|root| {
+        WalkDir::new(root)
+            .follow_links(false)
+            .max_depth(METADATA_MAX_DEPTH)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                e.path()
+                    .extension()
+                    .and_then(|x| x.to_str())
+                    .is_some_and(|x| METADATA_EXTENSIONS.contains(&x.to_lowercase().as_str()))
+            })
+    });
+    for entry in files {
+        if scan.files_checked >= MAX_METADATA_FILES {
+            scan.truncated = true;
+            break;
+        }
+        scan.files_checked += 1;
+        let path = entry.path().to_string_lossy().to_string();
+        if let Ok(report) = crate::cleaner::analyze_file(&path) {
+            if report.has_gps {
+                scan.with_gps.push(path.clone());
+            }
+            if report.has_author {
+                scan.with_author.push(path);
+            }
+        }
+    }
+    scan
+}
+
+/// The folders `scan_metadata` samples: the analyzer's user folders plus Pictures.
+pub fn metadata_roots() -> Vec<String> {
+    let mut roots = crate::analyzer::get_user_dirs();
+    #[cfg(not(target_os = "android"))]
+    if let Some(pictures) =
+        directories::UserDirs::new().and_then(|d| d.picture_dir().map(Path::to_path_buf))
+    {
+        roots.push(pictures.to_string_lossy().to_string());
+    }
+    roots.retain(|r| Path::new(r).is_dir());
+    roots
+}
+
+fn is_weak(password: &str) -> bool {
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .iter()
+    .filter(|&&present| present)
+    .count();
+    password.chars().count() < MIN_STRONG_LENGTH || classes < 3
+}
+
+pub fn summarize_passwords(vault: &PasswordVault) -> PasswordSummary {
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for entry in vault.entries.iter().filter(|e| !e.password.is_empty()) {
+        *uses.entry(entry.password.as_str()).or_default() += 1;
+    }
+    let with_password = vault.entries.iter().filter(|e| !e.password.is_empty());
+    PasswordSummary {
+        total: vault.entries.len(),
+        weak: with_password
+            .clone()
+            .filter(|e| is_weak(&e.password))
+            .count(),
+        reused: with_password
+            .filter(|e| uses[e.password.as_str()] > 1)
+            .count(),
+    }
+}
+
+// ==========================================
+// --- TELEMETRY SETTINGS ---
+// ==========================================
+
+#[cfg(target_os = "windows")]
+pub fn telemetry_settings() -> Vec<TelemetrySetting> {
+    use winreg::{enums::*, RegKey};
+    let dword = |hive, path: &str, name: &str| -> Option<u32> {
+        RegKey::predef(hive)
+            .open_subkey(path)
+            .ok()?
+            .get_value::<u32, _>(name)
+            .ok()
+    };
+    // Policy first (set by Group Policy or the Settings app), then the user choice.
+    let diagnostics = dword(
+        HKEY_LOCAL_MACHINE,
+        r"SOFTWARE\Policies\Microsoft\Windows\DataCollection",
+        "AllowTelemetry",
+    )
+    .or_else(|| {
+        dword(
+            HKEY_LOCAL_MACHINE,
+            r"SOFTWARE\Microsoft\Windows\CurrentVersion\Policies\DataCollection",
+            "AllowTelemetry",
+        )
+    });
+    vec![
+        TelemetrySetting {
+            name: "Optional diagnostic data".into(),
+            // 0 = security only, 1 = required only; 2 and 3 include optional data.
+            enabled: diagnostics.map(|v| v > 1),
+            guidance: "Settings › Privacy & security › Diagnostics & feedback: turn off \"Send optional diagnostic data\".".into(),
+        },
+        TelemetrySetting {
+            name: "Advertising ID".into(),
+            enabled: dword(
+                HKEY_CURRENT_USER,
+                r"Software\Microsoft\Windows\CurrentVersion\AdvertisingInfo",
+                "Enabled",
+            )
+            .map(|v| v != 0),
+            guidance: "Settings › Privacy & security › General: turn off personalised ads.".into(),
+        },
+        TelemetrySetting {
+            name: "Tailored experiences".into(),
+            enabled: dword(
+                HKEY_CURRENT_USER,
+                r"Software\Microsoft\Windows\CurrentVersion\Privacy",
+                "TailoredExperiencesWithDiagnosticDataEnabled",
+            )
+            .map(|v| v != 0),
+            guidance: "Settings › Privacy & security › Diagnostics & feedback: turn off \"Tailored experiences\".".into(),
+        },
+        TelemetrySetting {
+            name: "Activity history".into(),
+            enabled: dword(
+                HKEY_LOCAL_MACHINE,
+                r"SOFTWARE\Policies\Microsoft\Windows\System",
+                "PublishUserActivities",
+            )
+            .map(|v| v != 0),
+            guidance: "Settings › Privacy & security › Activity history: turn off \"Store my activity history\".".into(),
+        },
+    ]
+}
+
+#[cfg(target_os = "macos")]
+pub fn telemetry_settings() -> Vec<TelemetrySetting> {
+    let read = |domain: &str, key: &str| -> Option<bool> {
+        let output = std::process::Command::new("defaults")
+            .args(["read", domain, key])
+            .output()
+            .ok()?;
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => None,
+        }
+    };
+    vec![
+        TelemetrySetting {
+            name: "Share Mac analytics".into(),
+            enabled: read(
+                "/Library/Application Support/CrashReporter/DiagnosticMessagesHistory",
+                "AutoSubmit",
+            ),
+            guidance: "System Settings › Privacy & Security › Analytics & Improvements: turn off \"Share Mac Analytics\".".into(),
+        },
+        TelemetrySetting {
+            name: "Personalised Apple ads".into(),
+            enabled: read("com.apple.AdLib", "allowApplePersonalizedAdvertising"),
+            guidance: "System Settings › Privacy & Security › Apple Advertising: turn off \"Personalised Ads\".".into(),
+        },
+    ]
+}
+
+#[cfg(target_os = "linux")]
+pub fn telemetry_settings() -> Vec<TelemetrySetting> {
+    let file_flag = |path: &str, key: &str, on: &str| -> Option<bool> {
+        let text = std::fs::read_to_string(path).ok()?;
+        conf_value(&text, key).map(|v| v.eq_ignore_ascii_case(on))
+    };
+    let problem_reports = std::process::Command::new("gsettings")
+        .args([
+            "get",
+            "org.gnome.desktop.privacy",
+            "report-technical-problems",
+        ])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "true");
+    vec![
+        TelemetrySetting {
+            name: "Popularity contest".into(),
+            enabled: file_flag("/etc/popularity-contest.conf", "PARTICIPATE", "yes"),
+            guidance: "Run `sudo dpkg-reconfigure popularity-contest` and answer No.".into(),
+        },
+        TelemetrySetting {
+            name: "Crash reports (Apport)".into(),
+            enabled: file_flag("/etc/default/apport", "enabled", "1"),
+            guidance: "Set `enabled=0` in /etc/default/apport.".into(),
+        },
+        TelemetrySetting {
+            name: "GNOME problem reporting".into(),
+            enabled: problem_reports,
+            guidance: "Settings › Privacy › Diagnostics: set \"Send error reports\" to Never."
+                .into(),
+        },
+    ]
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn telemetry_settings() -> Vec<TelemetrySetting> {
+    Vec::new()
+}
+
+/// Value of `KEY=value` / `KEY="value"` in a shell-style config file.
+#[cfg(any(target_os = "linux", test))]
+fn conf_value(text: &str, key: &str) -> Option<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, v)| v.trim().trim_matches('"').to_string())
+}
+
+// ==========================================
+// --- SCORING ---
+// ==========================================
+
+/// (section id, weight in the overall score).
+const WEIGHTS: &[(&str, u32)] = &[
+    ("breaches", 25),
+    ("passwords", 20),
+    ("network", 15),
+    ("metadata", 15),
+    ("telemetry", 15),
+    ("junk", 10),
+];
+
+fn section(id: &str, title: &str) -> ReportSection {
+    ReportSection {
+        id: id.to_string(),
+        title: title.to_string(),
+        score: None,
+        summary: String::new(),
+        findings: Vec::new(),
+        recommendations: Vec::new(),
+    }
+}
+
+fn skipped(id: &str, title: &str, why: &str) -> ReportSection {
+    ReportSection {
+        summary: why.to_string(),
+        ..section(id, title)
+    }
+}
+
+fn penalty_score(penalty: u32) -> Option<u8> {
+    Some(100u32.saturating_sub(penalty) as u8)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b => format!("{} KB", b / 1024),
+    }
+}
+
+fn listed(paths: &[String]) -> impl Iterator<Item = &String> {
+    paths.iter().take(MAX_LISTED_PATHS)
+}
+
+fn junk_section(items: Option<&[JunkItem]>) -> ReportSection {
+    let (id, title) = ("junk", "Leftover data");
+    let Some(items) = items else {
+        return skipped(id, title, "Not checked.");
+    };
+    let total: u64 = items.iter().map(|i| i.size).sum();
+    let mut s = section(id, title);
+    s.summary = format!("{} in {} locations.", format_size(total), items.len());
+    let mut by_size: Vec<&JunkItem> = items.iter().filter(|i| i.size > 0).collect();
+    by_size.sort_by_key(|i| std::cmp::Reverse(i.size));
+    s.findings = by_size
+        .iter()
+        .take(MAX_LISTED_PATHS)
+        .map(|i| format!("{}: {}", i.name, format_size(i.size)))
+        .collect();
+    // Histories and caches record activity; size is a proxy for how much.
+    let penalty = (total / (JUNK_WARN_BYTES / 10)).min(60) as u32;
+    s.score = penalty_score(penalty);
+    if total > 0 {
+        s.recommendations
+            .push("Run the System Cleaner to remove caches, logs and histories.".into());
+    }
+    s
+}
+
+fn metadata_section(scan: Option<&MetadataScan>) -> ReportSection {
+    let (id, title) = ("metadata", "File metadata");
+    let Some(scan) = scan else {
+        return skipped(id, title, "Not checked.");
+    };
+    let mut s = section(id, title);
+    s.summary = format!(
+        "{} of {}{} files contain a location, {} an author name.",
+        scan.with_gps.len(),
+        scan.files_checked,
+        if scan.truncated { " sampled" } else { "" },
+        scan.with_author.len()
+    );
+    s.findings
+        .extend(listed(&scan.with_gps).map(|p| format!("GPS: {}", p)));
+    s.findings
+        .extend(listed(&scan.with_author).map(|p| format!("Author: {}", p)));
+    s.score = penalty_score(scan.with_gps.len() as u32 * 10 + scan.with_author.len() as u32 * 3);
+    if !scan.with_gps.is_empty() {
+        s.recommendations.push(
+            "Strip location data from photos with the Metadata Cleaner before sharing them.".into(),
+        );
+    }
+    if !scan.with_author.is_empty() {
+        s.recommendations.push(
+            "Remove author names from documents with the Metadata Cleaner before sending them."
+                .into(),
+        );
+    }
+    s
+}
+
+fn breach_section(alerts: Option<&[BreachAlert]>) -> ReportSection {
+    let (id, title) = ("breaches", "Data breaches");
+    let Some(alerts) = alerts else {
+        return skipped(id, title, "Unlock a vault to include breach alerts.");
+    };
+    let open: Vec<&BreachAlert> = alerts.iter().filter(|a| !a.acknowledged).collect();
+    let mut s = section(id, title);
+    s.summary = format!("{} unresolved breach alert(s).", open.len());
+    // Subjects of email alerts are addresses: listed by breach name only.
+    s.findings = open
+        .iter()
+        .map(|a| match (&a.kind, &a.breach_name) {
+            (crate::breach_monitor::AlertKind::Password, _) => {
+                format!("Password for {} found in breaches", a.subject)
+            }
+            (_, Some(name)) => format!("Email address found in the {} breach", name),
+            (_, None) => "Email address found in a breach".to_string(),
+        })
+        .collect();
+    s.score = penalty_score(open.len() as u32 * 25);
+    if !open.is_empty() {
+        s.recommendations.push(
+            "Change the passwords named in breach alerts, then acknowledge the alerts.".into(),
+        );
+    }
+    s
+}
+
+fn password_section(summary: Option<&PasswordSummary>) -> ReportSection {
+    let (id, title) = ("passwords", "Passwords");
+    let Some(p) = summary else {
+        return skipped(id, title, "Unlock a vault to include password health.");
+    };
+    let mut s = section(id, title);
+    s.summary = format!(
+        "{} passwords: {} weak, {} reused.",
+        p.total, p.weak, p.reused
+    );
+    if p.total == 0 {
+        s.score = Some(100);
+        return s;
+    }
+    let bad = (p.weak + p.reused).min(p.total) as u32;
+    s.score = penalty_score(bad * 100 / p.total as u32);
+    if p.weak > 0 {
+        s.recommendations.push(format!(
+            "Replace {} weak password(s) with generated ones of at least {} characters.",
+            p.weak, MIN_STRONG_LENGTH
+        ));
+    }
+    if p.reused > 0 {
+        s.recommendations.push(format!(
+            "Give each of the {} accounts sharing a password its own.",
+            p.reused
+        ));
+    }
+    s
+}
+
+fn telemetry_section(settings: Option<&[TelemetrySetting]>) -> ReportSection {
+    let (id, title) = ("telemetry", "Operating system telemetry");
+    let Some(settings) = settings.filter(|s| !s.is_empty()) else {
+        return skipped(id, title, "Not available on this platform.");
+    };
+    let known: Vec<&TelemetrySetting> = settings.iter().filter(|t| t.enabled.is_some()).collect();
+    let on: Vec<&TelemetrySetting> = known
+        .iter()
+        .copied()
+        .filter(|t| t.enabled == Some(true))
+        .collect();
+    let mut s = section(id, title);
+    s.summary = format!(
+        "{} of {} readable settings send data.",
+        on.len(),
+        known.len()
+    );
+    s.findings = settings
+        .iter()
+        .map(|t| {
+            let state = match t.enabled {
+                Some(true) => "on",
+                Some(false) => "off",
+                None => "unknown",
+            };
+            format!("{}: {}", t.name, state)
+        })
+        .collect();
+    s.recommendations = on.iter().map(|t| t.guidance.clone()).collect();
+    if !known.is_empty() {
+        s.score = penalty_score((on.len() * 100 / known.len()) as u32);
+    }
+    s
+}
+
+fn network_section(network: Option<&NetworkPrivacyReport>) -> ReportSection {
+    let (id, title) = ("network", "Network");
+    let Some(n) = network else {
+        return skipped(id, title, "Not checked.");
+    };
+    let mut s = section(id, title);
+    let mut penalty = 0;
+    if n.vpn.detected {
+        s.findings.push("VPN detected.".into());
+    } else {
+        s.findings.push("No VPN detected.".into());
+        penalty += 40;
+    }
+    match n.dns.enabled {
+        Some(true) => s.findings.push("DNS queries are encrypted.".into()),
+        Some(false) => {
+            s.findings.push("DNS queries are unencrypted.".into());
+            penalty += 30;
+        }
+        None => s
+            .findings
+            .push("DNS encryption could not be checked.".into()),
+    }
+    let hardware_macs = n
+        .mac
+        .iter()
+        .filter(|m| m.status == MacStatus::Hardware)
+        .count();
+    if hardware_macs > 0 {
+        s.findings.push(format!(
+            "{} Wi-Fi interface(s) use the hardware MAC address.",
+            hardware_macs
+        ));
+        penalty += 30;
+    }
+    s.summary = format!("{} issue(s) found.", n.recommendations.len());
+    s.recommendations = n.recommendations.clone();
+    s.score = penalty_score(penalty);
+    s
+}
+
+fn grade(score: u8) -> &'static str {
+    match score {
+        80.. => "A",
+        65..=79 => "B",
+        50..=64 => "C",
+        40..=49 => "D",
+        _ => "F",
+    }
+}
+
+/// Scores every section and the whole.
+pub fn build_report(inputs: &ReportInputs) -> PrivacyReport {
+    let sections = vec![
+        breach_section(inputs.breach_alerts.as_deref()),
+        password_section(inputs.passwords.as_ref()),
+        network_section(inputs.network.as_ref()),
+        metadata_section(inputs.metadata.as_ref()),
+        telemetry_section(inputs.telemetry.as_deref()),
+        junk_section(inputs.junk.as_deref()),
+    ];
+
+    let (mut total, mut weights) = (0u32, 0u32);
+    for s in &sections {
+        if let Some(score) = s.score {
+            let weight = WEIGHTS
+                .iter()
+                .find(|(id, _)| *id == s.id)
+                .map_or(0, |(_, w)| *w);
+            total += score as u32 * weight;
+            weights += weight;
+        }
+    }
+    let score = total.checked_div(weights).unwrap_or(0) as u8;
+
+    let mut ranked: Vec<&ReportSection> = sections.iter().filter(|s| s.score.is_some()).collect();
+    ranked.sort_by_key(|s| s.score);
+    let recommendations = ranked
+        .iter()
+        .flat_map(|s| s.recommendations.iter().cloned())
+        .collect();
+
+    PrivacyReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        score,
+        grade: grade(score).to_string(),
+        sections,
+        recommendations,
+    }
+}
+
+// ==========================================
+// --- EXPORT ---
+// ==========================================
+
+const PDF_LINE_CHARS: usize = 95;
+const PDF_LINES_PER_PAGE: usize = 58;
+
+/// Plain-text rendering, shared by the PDF export.
+fn text_lines(report: &PrivacyReport) -> Vec<String> {
+    let date = chrono::DateTime::from_timestamp(report.generated_at, 0)
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    let mut lines = vec![
+        "QRE Privacy Report".to_string(),
+        format!("Generated {}", date),
+        format!(
+            "Overall score: {}/100 (grade {})",
+            report.score, report.grade
+        ),
+        String::new(),
+    ];
+    for s in &report.sections {
+        let score = s
+            .score
+            .map_or("not checked".to_string(), |v| format!("{}/100", v));
+        lines.push(format!("{} - {}", s.title, score));
+        lines.push(format!("  {}", s.summary));
+        lines.extend(s.findings.iter().map(|f| format!("  * {}", f)));
+        lines.push(String::new());
+    }
+    if !report.recommendations.is_empty() {
+        lines.push("Recommendations".to_string());
+        lines.extend(
+            report
+                .recommendations
+                .iter()
+                .enumerate()
+                .map(|(i, r)| format!("  {}. {}", i + 1, r)),
+        );
+    }
+
+    // Wrap long lines (paths) at a fixed width.
+    lines
+        .into_iter()
+        .flat_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            if chars.is_empty() {
+                return vec![String::new()];
+            }
+            chars
+                .chunks(PDF_LINE_CHARS)
+                .map(|c| c.iter().collect::<String>())
+                .collect()
+        })
+        .collect()
+}
+
+/// Type 1 fonts use WinAnsi (Latin-1 here); anything else becomes '?'.
+fn pdf_bytes(line: &str) -> Vec<u8> {
+    line.chars()
+        .map(|c| match c {
+            '›' => b'>',
+            c if (c as u32) < 0x100 => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn render_pdf(report: &PrivacyReport) -> Result<Vec<u8>, String> {
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+
+    let lines = text_lines(report);
+    let mut kids: Vec<Object> = Vec::new();
+    for page in lines.chunks(PDF_LINES_PER_PAGE) {
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 10.into()]),
+            Operation::new("TL", vec![13.into()]),
+            Operation::new("Td", vec![50.into(), 790.into()]),
+        ];
+        for line in page {
+            operations.push(Operation::new(
+                "Tj",
+                vec![Object::string_literal(pdf_bytes(line))],
+            ));
+            operations.push(Operation::new("T*", vec![]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+        let content = Content { operations }.encode().map_err(|e| e.to_string())?;
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        kids.push(page_id.into());
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Serialises the report as pretty JSON or as an A4 PDF.
+pub fn export(report: &PrivacyReport, format: ReportFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ReportFormat::Json => serde_json::to_vec_pretty(report).map_err(|e| e.to_string()),
+        ReportFormat::Pdf => render_pdf(report),
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passwords::VaultEntry;
+
+    fn entry(id: &str, password: &str) -> VaultEntry {
+        VaultEntry {
+            id: id.to_string(),
+            service: id.to_string(),
+            username: String::new(),
+            password: password.to_string(),
+            notes: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            url: String::new(),
+            color: String::new(),
+            is_pinned: false,
+            totp_secret: None,
+            url_match: Default::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_password_summary() {
+        let mut vault = PasswordVault::new();
+        vault.entries = vec![
+            entry("a", "hunter2"),
+            entry("b", "Correct-Horse-Battery-9"),
+            entry("c", "Correct-Horse-Battery-9"),
+            entry("d", "Zq8!vT3#pL0@wX"),
+            entry("e", ""),
+        ];
+        assert_eq!(
+            summarize_passwords(&vault),
+            PasswordSummary {
+                total: 5,
+                weak: 1,
+                reused: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_skipped_sections_do_not_count() {
+        let inputs = ReportInputs {
+            passwords: Some(PasswordSummary {
+                total: 4,
+                weak: 0,
+                reused: 0,
+            }),
+            ..Default::default()
+        };
+        let report = build_report(&inputs);
+        assert_eq!(report.score, 100);
+        assert_eq!(report.grade, "A");
+        assert_eq!(
+            report.sections.iter().filter(|s| s.score.is_some()).count(),
+            1
+        );
+
+        let empty = build_report(&ReportInputs::default());
+        assert_eq!(empty.score, 0);
+        assert!(empty.sections.iter().all(|s| s.score.is_none()));
+    }
+
+    #[test]
+    fn test_scores_and_recommendation_order() {
+        let inputs = ReportInputs {
+            passwords: Some(PasswordSummary {
+                total: 4,
+                weak: 2,
+                reused: 0,
+            }),
+            metadata: Some(MetadataScan {
+                files_checked: 20,
+                truncated: false,
+                with_gps: vec!["/home/u/Pictures/a.jpg".into()],
+                with_author: Vec::new(),
+            }),
+            telemetry: Some(vec![TelemetrySetting {
+                name: "Diagnostics".into(),
+                enabled: Some(true),
+                guidance: "Turn it off.".into(),
+            }]),
+            ..Default::default()
+        };
+        let report = build_report(&inputs);
+        let score = |id: &str| report.sections.iter().find(|s| s.id == id).unwrap().score;
+        assert_eq!(score("passwords"), Some(50));
+        assert_eq!(score("metadata"), Some(90));
+        assert_eq!(score("telemetry"), Some(0));
+        // (50 * 20 + 90 * 15 + 0 * 15) / 50
+        assert_eq!(report.score, 47);
+        assert_eq!(report.grade, "D");
+        assert_eq!(report.recommendations[0], "Turn it off.");
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let report = build_report(&ReportInputs {
+            junk: Some(Vec::new()),
+            ..Default::default()
+        });
+        let json = export(&report, ReportFormat::Json).unwrap();
+        let parsed: PrivacyReport = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed, report);
+
+        let pdf = export(&report, ReportFormat::Pdf).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.5"));
+        let doc = lopdf::Document::load_mem(&pdf).unwrap();
+        assert_eq!(doc.get_pages().len(), 1);
+    }
+
+    #[test]
+    fn test_conf_value() {
+        let conf = "# comment\nPARTICIPATE=\"yes\"\nUSEHTTP=\"yes\"\n";
+        assert_eq!(conf_value(conf, "PARTICIPATE").as_deref(), Some("yes"));
+        assert!(conf_value(conf, "MY_HOSTID").is_none());
+    }
+}
+
+// --- END OF FILE privacy_report.rs ---
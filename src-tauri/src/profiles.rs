@@ -0,0 +1,261 @@
+// --- START OF FILE profiles.rs ---
+
+// ==========================================
+// --- KEYCHAIN PROFILES ---
+// ==========================================
+// Several people sharing one computer each get a separate local vault. A profile is a
+// directory holding everything that lives next to a keychain (passwords, notes, audit
+// log, clipboard history, panel PIN...):
+//   - the default profile is the app data directory itself, so vaults created before
+//     profiles existed are the default profile unchanged;
+//   - a named profile is `profiles/<name>/` under it.
+// The "local" vault id always means the active profile (`resolve_keychain_path` in
+// commands/vault.rs), so every command follows a switch without taking a profile
+// argument. Only one profile is unlocked at a time: switching locks the session.
+//
+// App-wide settings (auto-lock, pattern packs) stay in the app data directory. The
+// active profile name is kept in plaintext in `profiles.json`; it is not a secret, and
+// each profile's data is protected by its own keychain.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE_NAME: &str = "profiles.json";
+pub const PROFILES_DIR_NAME: &str = "profiles";
+pub const KEYCHAIN_FILE_NAME: &str = "keychain.json";
+pub const DEFAULT_PROFILE: &str = "default";
+
+const MAX_NAME_LEN: usize = 32;
+/// Names Windows will not create a directory under.
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct ProfileConfig {
+    /// `None` is the default profile.
+    active: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub active: bool,
+    /// A vault has been set up in it.
+    pub initialized: bool,
+}
+
+/// Letters, digits, `-` and `_`, up to 32 characters. Names are compared ignoring case,
+/// as they would be on Windows and macOS file systems.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(anyhow!(
+            "Profile name must be 1-{} characters",
+            MAX_NAME_LEN
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow!(
+            "Profile name may only contain letters, digits, '-' and '_'"
+        ));
+    }
+    let lower = name.to_ascii_lowercase();
+    if lower == DEFAULT_PROFILE || RESERVED_NAMES.contains(&lower.as_str()) {
+        return Err(anyhow!("'{}' cannot be used as a profile name", name));
+    }
+    Ok(())
+}
+
+fn is_default(name: &str) -> bool {
+    name.eq_ignore_ascii_case(DEFAULT_PROFILE)
+}
+
+/// Where `name` keeps its files under the app data directory `root`.
+pub fn profile_dir(root: &Path, name: &str) -> PathBuf {
+    if is_default(name) {
+        root.to_path_buf()
+    } else {
+        root.join(PROFILES_DIR_NAME).join(name)
+    }
+}
+
+fn load_config(root: &Path) -> Result<ProfileConfig> {
+    let path = root.join(CONFIG_FILE_NAME);
+    if !path.exists() {
+        return Ok(ProfileConfig::default());
+    }
+    let data = fs::read(&path)?;
+    serde_json::from_slice(&data).map_err(|_| anyhow!("Profile settings are corrupted"))
+}
+
+fn save_config(root: &Path, config: &ProfileConfig) -> Result<()> {
+    let path = root.join(CONFIG_FILE_NAME);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(config)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// The named profiles, in name order (the default profile is not included).
+fn named_profiles(root: &Path) -> Result<Vec<String>> {
+    let dir = root.join(PROFILES_DIR_NAME);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .filter(|name| validate_name(name).is_ok())
+        .collect();
+    names.sort_by_key(|name| name.to_ascii_lowercase());
+    Ok(names)
+}
+
+/// The profile with this name, as spelled on disk.
+pub fn find(root: &Path, name: &str) -> Result<Option<String>> {
+    if is_default(name) {
+        return Ok(Some(DEFAULT_PROFILE.to_string()));
+    }
+    Ok(named_profiles(root)?
+        .into_iter()
+        .find(|p| p.eq_ignore_ascii_case(name)))
+}
+
+/// The active profile. A missing or unreadable setting, or a profile whose directory is
+/// gone, means the default profile.
+pub fn active(root: &Path) -> String {
+    load_config(root)
+        .ok()
+        .and_then(|config| config.active)
+        .filter(|name| validate_name(name).is_ok() && profile_dir(root, name).is_dir())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+pub fn list(root: &Path) -> Result<Vec<ProfileInfo>> {
+    let active = active(root);
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+    names.extend(named_profiles(root)?);
+    Ok(names
+        .into_iter()
+        .map(|name| ProfileInfo {
+            active: name == active,
+            initialized: profile_dir(root, &name).join(KEYCHAIN_FILE_NAME).exists(),
+            name,
+        })
+        .collect())
+}
+
+/// Creates an empty profile; its vault is set up on first use like a fresh install.
+pub fn create(root: &Path, name: &str) -> Result<()> {
+    validate_name(name)?;
+    if find(root, name)?.is_some() {
+        return Err(anyhow!("A profile named '{}' already exists", name));
+    }
+    fs::create_dir_all(profile_dir(root, name))?;
+    Ok(())
+}
+
+/// Makes `name` the active profile. Returns its name as spelled on disk.
+pub fn switch(root: &Path, name: &str) -> Result<String> {
+    let name = find(root, name)?.ok_or_else(|| anyhow!("No profile named '{}'", name))?;
+    let config = ProfileConfig {
+        active: (!is_default(&name)).then(|| name.clone()),
+    };
+    save_config(root, &config)?;
+    Ok(name)
+}
+
+/// Deletes a named profile and all of its files. The default profile and the active one
+/// cannot be deleted. The keychain goes first: without it the rest cannot be decrypted,
+/// even if removing the other files fails part way.
+pub fn delete(root: &Path, name: &str) -> Result<()> {
+    let name = find(root, name)?.ok_or_else(|| anyhow!("No profile named '{}'", name))?;
+    if is_default(&name) {
+        return Err(anyhow!("The default profile cannot be deleted"));
+    }
+    if name == active(root) {
+        return Err(anyhow!(
+            "Switch to another profile before deleting this one"
+        ));
+    }
+    let dir = profile_dir(root, &name);
+    let keychain = dir.join(KEYCHAIN_FILE_NAME);
+    if keychain.exists() {
+        fs::remove_file(&keychain)?;
+    }
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qre_profiles_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_profile_names() {
+        assert!(validate_name("Mom").is_ok());
+        assert!(validate_name("kid_2").is_ok());
+        for bad in [
+            "",
+            "Default",
+            "../x",
+            "a b",
+            "nul",
+            "Ελένη",
+            &"x".repeat(33),
+        ] {
+            assert!(validate_name(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_create_switch_and_delete() {
+        let root = temp_root();
+        assert_eq!(active(&root), DEFAULT_PROFILE);
+        assert_eq!(profile_dir(&root, DEFAULT_PROFILE), root);
+        fs::write(root.join(KEYCHAIN_FILE_NAME), b"{}").unwrap();
+
+        create(&root, "Mom").unwrap();
+        create(&root, "alex").unwrap();
+        assert!(create(&root, "mom").is_err(), "names ignore case");
+
+        assert_eq!(switch(&root, "MOM").unwrap(), "Mom");
+        assert_eq!(active(&root), "Mom");
+        let listed = list(&root).unwrap();
+        let names: Vec<&str> = listed.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["default", "alex", "Mom"]);
+        assert!(listed[0].initialized && !listed[0].active);
+        assert!(!listed[2].initialized && listed[2].active);
+
+        assert!(delete(&root, "Mom").is_err(), "the active profile stays");
+        assert!(delete(&root, DEFAULT_PROFILE).is_err());
+        switch(&root, "alex").unwrap();
+        fs::write(profile_dir(&root, "Mom").join(KEYCHAIN_FILE_NAME), b"{}").unwrap();
+        delete(&root, "Mom").unwrap();
+        assert!(!profile_dir(&root, "Mom").exists());
+        assert!(switch(&root, "Mom").is_err());
+
+        // A profile removed behind the app's back falls back to the default.
+        fs::remove_dir_all(profile_dir(&root, "alex")).unwrap();
+        assert_eq!(active(&root), DEFAULT_PROFILE);
+        assert!(root.join(KEYCHAIN_FILE_NAME).exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
+
+// --- END OF FILE profiles.rs ---
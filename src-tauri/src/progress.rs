@@ -0,0 +1,201 @@
+// --- START OF FILE progress.rs ---
+
+// ==========================================
+// --- PROGRESS EVENT COALESCING ---
+// ==========================================
+// Batch operations report progress per chunk and per file. On a thousand-file batch
+// that is tens of thousands of IPC messages, which floods the Tauri bridge and makes
+// the React progress bars stutter instead of helping.
+//
+// Every progress emitter goes through this module, which limits each job to at most
+// `DEFAULT_MAX_EVENTS_PER_SEC` events per second:
+//   - Updates that arrive too soon are not sent; they replace the job's pending payload
+//     (the latest state always wins, older intermediate states are simply dropped).
+//   - The final update is never throttled: `finish()` always emits, and dropping an
+//     emitter flushes whatever is still pending, so the UI always ends on the true state.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Runtime};
+
+/// Upper bound on progress events per job per second (one every 100 ms).
+pub const DEFAULT_MAX_EVENTS_PER_SEC: u32 = 10;
+
+// ==========================================
+// --- THROTTLE (pure, UI-independent) ---
+// ==========================================
+
+/// Rate decision for a single job, kept free of Tauri types so it can be unit tested.
+#[derive(Debug, Clone)]
+pub struct ProgressThrottle {
+    min_interval: Duration,
+    last_emit: Option<Instant>,
+}
+
+impl ProgressThrottle {
+    pub fn new(max_events_per_sec: u32) -> Self {
+        Self {
+            min_interval: Duration::from_secs(1) / max_events_per_sec.max(1),
+            last_emit: None,
+        }
+    }
+
+    /// Returns true if an update at `now` may be sent. The first update of a job and
+    /// any final update always pass; a passing update restarts the interval.
+    pub fn should_emit(&mut self, now: Instant, is_final: bool) -> bool {
+        let due = match self.last_emit {
+            None => true,
+            Some(last) => now.saturating_duration_since(last) >= self.min_interval,
+        };
+        if due || is_final {
+            self.last_emit = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for ProgressThrottle {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_EVENTS_PER_SEC)
+    }
+}
+
+// ==========================================
+// --- EMITTER ---
+// ==========================================
+
+struct EmitterState<P> {
+    throttle: ProgressThrottle,
+    pending: Option<P>,
+}
+
+/// Coalescing emitter for one job's progress events. `Sync`, so rayon workers can share it.
+pub struct ProgressEmitter<R: Runtime, P: Serialize + Clone> {
+    app: AppHandle<R>,
+    event: &'static str,
+    state: Mutex<EmitterState<P>>,
+}
+
+impl<R: Runtime, P: Serialize + Clone> ProgressEmitter<R, P> {
+    pub fn new(app: &AppHandle<R>, event: &'static str) -> Self {
+        Self::with_rate(app, event, DEFAULT_MAX_EVENTS_PER_SEC)
+    }
+
+    pub fn with_rate(app: &AppHandle<R>, event: &'static str, max_events_per_sec: u32) -> Self {
+        Self {
+            app: app.clone(),
+            event,
+            state: Mutex::new(EmitterState {
+                throttle: ProgressThrottle::new(max_events_per_sec),
+                pending: None,
+            }),
+        }
+    }
+
+    /// Sends `payload` if the job's rate allows it, otherwise keeps it as the pending update.
+    pub fn emit(&self, payload: P) {
+        self.send(payload, false);
+    }
+
+    /// Sends the final update unconditionally and clears anything pending.
+    pub fn finish(&self, payload: P) {
+        self.send(payload, true);
+    }
+
+    /// Sends the pending update, if any (used when a job ends without an explicit final payload).
+    pub fn flush(&self) {
+        let pending = match self.state.lock() {
+            Ok(mut s) => s.pending.take(),
+            Err(_) => return,
+        };
+        if let Some(payload) = pending {
+            let _ = self.app.emit(self.event, payload);
+        }
+    }
+
+    fn send(&self, payload: P, is_final: bool) {
+        // A poisoned lock only means a worker panicked mid-update; progress is cosmetic,
+        // so keep reporting with the inner state rather than going silent.
+        let mut state = self.state.lock().unwrap_or_else(|p| p.into_inner());
+        if state.throttle.should_emit(Instant::now(), is_final) {
+            state.pending = None;
+            drop(state);
+            let _ = self.app.emit(self.event, payload);
+        } else {
+            state.pending = Some(payload);
+        }
+    }
+}
+
+impl<R: Runtime, P: Serialize + Clone> Drop for ProgressEmitter<R, P> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+// ==========================================
+// --- GLOBAL "qre:progress" CHANNEL ---
+// ==========================================
+
+/// `utils::emit_progress` has no job handle (its callers are plain closures), so the
+/// shared "qre:progress" bar gets one process-wide throttle instead.
+static GLOBAL_THROTTLE: Mutex<Option<ProgressThrottle>> = Mutex::new(None);
+
+/// Returns true if a "qre:progress" update may be sent now. 100% always passes.
+pub fn allow_global(percentage: u8) -> bool {
+    let mut guard = GLOBAL_THROTTLE.lock().unwrap_or_else(|p| p.into_inner());
+    guard
+        .get_or_insert_with(ProgressThrottle::default)
+        .should_emit(Instant::now(), percentage >= 100)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_limits_rate_but_passes_first_and_final() {
+        let mut t = ProgressThrottle::new(10);
+        let start = Instant::now();
+        assert!(t.should_emit(start, false), "first update always passes");
+
+        // 1000 updates within the same 100 ms window: none pass.
+        let passed = (1..1000)
+            .filter(|i| t.should_emit(start + Duration::from_micros(*i * 90), false))
+            .count();
+        assert_eq!(passed, 0);
+
+        // ...but the final one does, even inside the window.
+        assert!(t.should_emit(start + Duration::from_millis(95), true));
+    }
+
+    #[test]
+    fn test_throttle_caps_events_per_second() {
+        let mut t = ProgressThrottle::new(10);
+        let start = Instant::now();
+        // One update per millisecond for two seconds.
+        let passed = (0..2000)
+            .filter(|ms| t.should_emit(start + Duration::from_millis(*ms), false))
+            .count();
+        assert!(passed <= 21, "expected ~20 events, got {}", passed);
+        assert!(passed >= 19, "expected ~20 events, got {}", passed);
+    }
+
+    #[test]
+    fn test_zero_rate_does_not_panic() {
+        let mut t = ProgressThrottle::new(0);
+        let now = Instant::now();
+        assert!(t.should_emit(now, false));
+        assert!(!t.should_emit(now + Duration::from_millis(500), false));
+        assert!(t.should_emit(now + Duration::from_secs(1), false));
+    }
+}
+
+// --- END OF FILE progress.rs ---
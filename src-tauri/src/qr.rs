@@ -1,9 +1,8 @@
 // --- START OF FILE qr.rs ---
 
+use crate::regexes::{self, Pattern};
 use anyhow::{anyhow, Result};
 use qrcodegen::{QrCode, QrCodeEcc};
-use regex::Regex;
-use std::sync::OnceLock;
 
 // ═══════════════════════════════════════════════════════════════════════════
 // CONSTANTS & CONFIGURATION
@@ -111,10 +110,8 @@ pub struct QrValidation {
 
 /// Enforces strict `#RRGGBB` format for colors.
 fn validate_color(color: &str) -> Result<String> {
-    // OnceLock compiles the regex exactly once during the application's lifecycle,
-    // making subsequent calls incredibly fast.
-    static HEX_REGEX: OnceLock<Regex> = OnceLock::new();
-    let regex = HEX_REGEX.get_or_init(|| Regex::new(r"^#[0-9A-Fa-f]{6}$").unwrap());
+    // The registry compiles the regex once for the application's lifetime.
+    let regex = regexes::get(Pattern::HexColor);
 
     if !regex.is_match(color) {
         return Err(anyhow!("Invalid color format. Use #RRGGBB hex format"));
@@ -0,0 +1,156 @@
+// --- START OF FILE random_names.rs ---
+
+// ==========================================
+// --- RANDOM NAMES ---
+// ==========================================
+// Names for files and labels that must say nothing about what they hold: the random
+// rename preset, the name a file takes just before the shredder unlinks it, and the
+// fake values in honeyfiles. Every draw comes from the OS CSPRNG, and indexes are drawn
+// without modulo bias.
+//
+// Kinds:
+//   - uuid:  a random (v4) UUID, "3f2b8c1e-…". Collisions are not a concern.
+//   - words: adjective-noun and four digits, "quiet-harbor-4821" (about 25 bits).
+//   - nato:  four spelling-alphabet words and two digits, "kilo-echo-tango-lima-07"
+//            (about 25 bits), easy to read out over the phone.
+// The readable kinds can repeat; callers that write files resolve collisions as they
+// already do for user-chosen names.
+
+use anyhow::{anyhow, Result};
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameKind {
+    #[default]
+    Uuid,
+    Words,
+    Nato,
+}
+
+const ADJECTIVES: &[&str] = &[
+    "amber", "ancient", "bold", "brave", "bright", "calm", "clever", "cosmic", "crisp", "curious",
+    "dusty", "eager", "early", "fancy", "fierce", "gentle", "giant", "golden", "grand", "happy",
+    "hidden", "humble", "icy", "jolly", "keen", "little", "lively", "lucky", "misty", "modest",
+    "noble", "odd", "pale", "plain", "polite", "proud", "quick", "quiet", "rapid", "rare", "rusty",
+    "shiny", "silent", "silver", "simple", "sleepy", "smooth", "snowy", "solid", "spare", "steady",
+    "sunny", "swift", "tall", "tidy", "tiny", "vivid", "warm", "wild", "windy", "wise", "young",
+    "zany", "zesty",
+];
+
+const NOUNS: &[&str] = &[
+    "acorn", "anchor", "badger", "basket", "beacon", "birch", "bridge", "canyon", "castle",
+    "cedar", "comet", "coral", "delta", "desert", "ember", "falcon", "feather", "forest", "garden",
+    "glacier", "harbor", "hazel", "island", "jigsaw", "kettle", "lagoon", "lantern", "maple",
+    "meadow", "meteor", "mirror", "otter", "orchid", "pebble", "pepper", "pillow", "planet",
+    "prairie", "quartz", "rabbit", "raven", "ribbon", "river", "rocket", "saddle", "sparrow",
+    "spruce", "summit", "teapot", "thistle", "thunder", "tulip", "tunnel", "valley", "velvet",
+    "violin", "walnut", "willow", "window", "winter", "wizard", "yarrow", "zebra", "zephyr",
+];
+
+const NATO: &[&str] = &[
+    "alfa", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliett",
+    "kilo", "lima", "mike", "november", "oscar", "papa", "quebec", "romeo", "sierra", "tango",
+    "uniform", "victor", "whiskey", "xray", "yankee", "zulu",
+];
+
+const NATO_WORDS: usize = 4;
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    Ok(bytes)
+}
+
+/// A uniformly random number in `0..n`; `n` must not be 0.
+pub fn random_below(n: u32) -> Result<u32> {
+    if n == 0 {
+        return Err(anyhow!("Empty range"));
+    }
+    // Values at or above the largest multiple of `n` would make the low ones likelier.
+    let limit = u32::MAX - u32::MAX % n;
+    loop {
+        let value = OsRng
+            .try_next_u32()
+            .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+        if value < limit {
+            return Ok(value % n);
+        }
+    }
+}
+
+/// A uniformly chosen element of `items`.
+pub fn pick<'a>(items: &[&'a str]) -> Result<&'a str> {
+    Ok(items[random_below(items.len() as u32)? as usize])
+}
+
+pub fn generate(kind: NameKind) -> Result<String> {
+    match kind {
+        NameKind::Uuid => Ok(uuid::Builder::from_random_bytes(random_bytes()?)
+            .into_uuid()
+            .to_string()),
+        NameKind::Words => Ok(format!(
+            "{}-{}-{:04}",
+            pick(ADJECTIVES)?,
+            pick(NOUNS)?,
+            random_below(10_000)?
+        )),
+        NameKind::Nato => {
+            let mut parts = (0..NATO_WORDS)
+                .map(|_| pick(NATO).map(str::to_string))
+                .collect::<Result<Vec<_>>>()?;
+            parts.push(format!("{:02}", random_below(100)?));
+            Ok(parts.join("-"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_names_have_the_expected_shape() {
+        let id = generate(NameKind::Uuid).unwrap();
+        let parsed = uuid::Uuid::parse_str(&id).unwrap();
+        assert_eq!(parsed.get_version_num(), 4);
+
+        let words = generate(NameKind::Words).unwrap();
+        let parts: Vec<&str> = words.split('-').collect();
+        assert_eq!(parts.len(), 3, "{}", words);
+        assert!(ADJECTIVES.contains(&parts[0]) && NOUNS.contains(&parts[1]));
+        assert!(parts[2].len() == 4 && parts[2].chars().all(|c| c.is_ascii_digit()));
+
+        let nato = generate(NameKind::Nato).unwrap();
+        let parts: Vec<&str> = nato.split('-').collect();
+        assert_eq!(parts.len(), NATO_WORDS + 1, "{}", nato);
+        assert!(parts[..NATO_WORDS].iter().all(|w| NATO.contains(w)));
+
+        // Safe as file names everywhere.
+        for name in [id, words, nato] {
+            assert!(name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        }
+    }
+
+    #[test]
+    fn test_word_lists_and_random_below() {
+        for list in [ADJECTIVES, NOUNS, NATO] {
+            let unique: HashSet<&&str> = list.iter().collect();
+            assert_eq!(unique.len(), list.len());
+        }
+        assert_eq!(ADJECTIVES.len(), 64);
+        assert_eq!(NOUNS.len(), 64);
+        assert!(random_below(0).is_err());
+        assert_eq!(random_below(1).unwrap(), 0);
+        let seen: HashSet<u32> = (0..200).map(|_| random_below(3).unwrap()).collect();
+        assert_eq!(seen.len(), 3);
+    }
+}
+
+// --- END OF FILE random_names.rs ---
@@ -0,0 +1,796 @@
+// --- START OF FILE recipient.rs ---
+
+// ==========================================
+// --- PUBLIC-KEY CONTAINERS (V10) ---
+// ==========================================
+// Encrypts a file for another vault without a shared password. Every vault can hold an
+// identity keypair (X25519 + ML-KEM-1024, stored sealed in its keychain). Its public half
+// is exported as a short text ("qre-identity:1:...") that a colleague imports as a
+// contact; files locked for that contact can then only be opened by the vault holding
+// the secret half.
+//
+// The file key combines three secrets, so it stays safe as long as either X25519 or
+// ML-KEM holds up (classical and post-quantum):
+//   ss_eph    = X25519(ephemeral, recipient)      fresh per file
+//   ss_static = X25519(sender, recipient)         proves which vault sent it
+//   ss_kem    = ML-KEM encapsulation to the recipient (parameter set named by `kem`)
+//   key       = SHA-256("QRE_RECIPIENT_V1" | ss_eph | ss_static | ss_kem | eph_pub
+//                        | SHA-256(kem_ct) | recipient_fpr | sender_fpr)
+//
+// KEM PARAMETER SET: ML-KEM-1024 (FIPS 203, NIST category 5, the Kyber1024 level) —
+// 1568-byte encapsulation key, 3168-byte decapsulation key, 1568-byte ciphertext, 32-byte
+// shared secret. The header records it as `kem` (KEM_ML_KEM_1024 = 1); readers refuse
+// ids they do not know, so a later parameter set gets a new id (and a new identity text
+// version) instead of a new container version.
+//
+// LAYOUT:
+//   [version u32 = 10][header_len u32][bincode RecipientHeader]
+//   [chunk: len u32 | AES-GCM(zstd(data))]...
+// Chunk AAD = SHA-256(header bytes) | index u64 | final flag. The last chunk carries the
+// flag, so a truncated file fails instead of decrypting to a shorter one. The original
+// file name is encrypted inside the header with the chunk nonce for index u64::MAX.
+//
+// VAULT-BOUND ENCAPSULATION: hybrid stream containers (V12, see crypto_stream.rs) reuse
+// the identity without a sender. `encapsulate` makes an ephemeral X25519 exchange plus an
+// ML-KEM encapsulation to the vault's own identity, and the secret
+//   SHA-256("QRE_VAULT_ENCAP_V1" | ss_eph | ss_kem | eph_pub | SHA-256(kem_ct) | fpr)
+// is mixed into the key that wraps the file key.
+//
+// LIMITS: there is no forward secrecy against the recipient's key (whoever later gets
+// the recipient vault can open old files), and nothing revokes a leaked identity except
+// creating a new vault.
+
+use crate::av_guard::with_retry;
+use crate::crypto_stream::{
+    self, AES_NONCE_LEN, CHUNK_SIZE, GCM_TAG_LEN, SHA256_LEN, VERSION_RECIPIENT,
+};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Context, Result};
+use aws_lc_rs::kem::{Ciphertext, DecapsulationKey, EncapsulationKey, ML_KEM_1024};
+use bincode::Options;
+use data_encoding::BASE64;
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+const X25519_LEN: usize = 32;
+/// `RecipientHeader::kem` for ML-KEM-1024, the only parameter set written so far.
+pub const KEM_ML_KEM_1024: u16 = 1;
+const KEM_PUBLIC_LEN: usize = 1568;
+const KEM_SECRET_LEN: usize = 3168;
+const KEM_CIPHERTEXT_LEN: usize = 1568;
+/// FIPS 203 decapsulation keys embed the encapsulation key after the 1536-byte
+/// K-PKE secret (dk = dk_pke | ek | H(ek) | z).
+const KEM_PUBLIC_OFFSET: usize = 1536;
+const PUBLIC_LEN: usize = X25519_LEN + KEM_PUBLIC_LEN;
+const SECRET_LEN: usize = X25519_LEN + KEM_SECRET_LEN;
+
+const IDENTITY_PREFIX: &str = "qre-identity:1:";
+const MAX_LABEL_LEN: usize = 64;
+const MAX_HEADER_BYTES: u32 = 8 * 1024;
+const COMPRESSION_LEVEL: i32 = 3;
+/// Chunks compress, so a stored chunk can be slightly larger than CHUNK_SIZE at worst.
+const MAX_CHUNK_BYTES: usize = CHUNK_SIZE + 4096;
+/// Secrets-store names under which a vault keeps its imported contacts.
+pub const CONTACT_PREFIX: &str = "contact.";
+
+// ==========================================
+// --- IDENTITIES ---
+// ==========================================
+
+/// The public half of a vault identity, as imported by other vaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicIdentity {
+    x25519: [u8; X25519_LEN],
+    kem: Vec<u8>,
+}
+
+/// A vault's own keypair. Only exists in memory while the vault is unlocked.
+pub struct Identity {
+    x25519: StaticSecret,
+    kem: DecapsulationKey,
+    public: PublicIdentity,
+}
+
+impl PublicIdentity {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(PUBLIC_LEN);
+        out.extend_from_slice(&self.x25519);
+        out.extend_from_slice(&self.kem);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != PUBLIC_LEN {
+            return Err(anyhow!("Not a QRE public identity"));
+        }
+        let mut x25519 = [0u8; X25519_LEN];
+        x25519.copy_from_slice(&bytes[..X25519_LEN]);
+        Ok(Self {
+            x25519,
+            kem: bytes[X25519_LEN..].to_vec(),
+        })
+    }
+
+    /// SHA-256 of the public key bytes. Identifies the recipient inside containers.
+    pub fn fingerprint(&self) -> [u8; SHA256_LEN] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"QRE_IDENTITY_FPR");
+        hasher.update(self.to_bytes());
+        hasher.finalize().into()
+    }
+
+    /// The first 16 fingerprint bytes in groups of four hex digits, for comparing an
+    /// imported identity with its owner over the phone.
+    pub fn fingerprint_text(&self) -> String {
+        self.fingerprint()[..16]
+            .chunks(2)
+            .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `qre-identity:1:<base64 key>:<label>`. The label is a display hint only.
+    pub fn encode(&self, label: &str) -> String {
+        format!(
+            "{}{}:{}",
+            IDENTITY_PREFIX,
+            BASE64.encode(&self.to_bytes()),
+            clean_label(label)
+        )
+    }
+
+    /// Reverses `encode`, returning the identity and its label.
+    pub fn decode(text: &str) -> Result<(Self, String)> {
+        let rest = text
+            .trim()
+            .strip_prefix(IDENTITY_PREFIX)
+            .ok_or_else(|| anyhow!("Not a QRE public identity"))?;
+        let (key, label) = rest.split_once(':').unwrap_or((rest, ""));
+        let bytes = BASE64
+            .decode(key.as_bytes())
+            .map_err(|_| anyhow!("Not a QRE public identity"))?;
+        Ok((Self::from_bytes(&bytes)?, clean_label(label)))
+    }
+
+    fn kem_key(&self) -> Result<EncapsulationKey> {
+        EncapsulationKey::new(&ML_KEM_1024, &self.kem)
+            .map_err(|_| anyhow!("Invalid ML-KEM public key"))
+    }
+}
+
+fn clean_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_LABEL_LEN)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+impl Identity {
+    pub fn generate() -> Result<Self> {
+        let mut x25519 = Zeroizing::new([0u8; X25519_LEN]);
+        OsRng
+            .try_fill_bytes(x25519.as_mut())
+            .map_err(|e| anyhow!("RNG failure: {}", e))?;
+        let kem = DecapsulationKey::generate(&ML_KEM_1024)
+            .map_err(|_| anyhow!("ML-KEM key generation failed"))?;
+        let kem_public = kem
+            .encapsulation_key()
+            .and_then(|key| key.key_bytes())
+            .map_err(|_| anyhow!("ML-KEM key generation failed"))?
+            .as_ref()
+            .to_vec();
+        Ok(Self::assemble(StaticSecret::from(*x25519), kem, kem_public))
+    }
+
+    /// Rebuilds the identity from what `secret_bytes` returned (kept in the keychain).
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != SECRET_LEN {
+            return Err(anyhow!("Invalid identity key"));
+        }
+        let mut x25519 = Zeroizing::new([0u8; X25519_LEN]);
+        x25519.copy_from_slice(&bytes[..X25519_LEN]);
+        let kem_secret = &bytes[X25519_LEN..];
+        let kem = DecapsulationKey::new(&ML_KEM_1024, kem_secret)
+            .map_err(|_| anyhow!("Invalid identity key"))?;
+        let kem_public = kem_secret[KEM_PUBLIC_OFFSET..KEM_PUBLIC_OFFSET + KEM_PUBLIC_LEN].to_vec();
+        Ok(Self::assemble(StaticSecret::from(*x25519), kem, kem_public))
+    }
+
+    fn assemble(x25519: StaticSecret, kem: DecapsulationKey, kem_public: Vec<u8>) -> Self {
+        let public = PublicIdentity {
+            x25519: PublicKey::from(&x25519).to_bytes(),
+            kem: kem_public,
+        };
+        Self {
+            x25519,
+            kem,
+            public,
+        }
+    }
+
+    pub fn secret_bytes(&self) -> Result<Zeroizing<Vec<u8>>> {
+        let kem = self
+            .kem
+            .key_bytes()
+            .map_err(|_| anyhow!("Failed to export identity key"))?;
+        let mut out = Zeroizing::new(Vec::with_capacity(SECRET_LEN));
+        out.extend_from_slice(self.x25519.as_bytes());
+        out.extend_from_slice(kem.as_ref());
+        Ok(out)
+    }
+
+    pub fn public(&self) -> &PublicIdentity {
+        &self.public
+    }
+}
+
+// ==========================================
+// --- CONTAINER FORMAT ---
+// ==========================================
+
+/// Everything needed to derive the file key, in the clear except the file name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecipientHeader {
+    /// Which KEM parameter set `kem_ciphertext` belongs to (see KEM_ML_KEM_1024).
+    pub kem: u16,
+    pub recipient_fingerprint: [u8; SHA256_LEN],
+    /// The sender's full public identity, checked by the static X25519 exchange.
+    pub sender: Vec<u8>,
+    ephemeral: [u8; X25519_LEN],
+    kem_ciphertext: Vec<u8>,
+    base_nonce: [u8; AES_NONCE_LEN],
+    encrypted_filename: Vec<u8>,
+}
+
+fn header_options() -> impl bincode::Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .with_limit(MAX_HEADER_BYTES as u64)
+}
+
+/// All-zero X25519 outputs mean a low-order point was supplied; refuse them.
+fn dh(secret: &StaticSecret, public: &[u8; X25519_LEN]) -> Result<Zeroizing<[u8; 32]>> {
+    let shared = secret.diffie_hellman(&PublicKey::from(*public));
+    if !shared.was_contributory() {
+        return Err(anyhow!("Invalid X25519 public key"));
+    }
+    Ok(Zeroizing::new(shared.to_bytes()))
+}
+
+fn derive_file_key(
+    ss_eph: &[u8],
+    ss_static: &[u8],
+    ss_kem: &[u8],
+    ephemeral: &[u8],
+    kem_ciphertext: &[u8],
+    recipient: &PublicIdentity,
+    sender: &PublicIdentity,
+) -> Result<Aes256Gcm> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_RECIPIENT_V1");
+    hasher.update(ss_eph);
+    hasher.update(ss_static);
+    hasher.update(ss_kem);
+    hasher.update(ephemeral);
+    hasher.update(Sha256::digest(kem_ciphertext));
+    hasher.update(recipient.fingerprint());
+    hasher.update(sender.fingerprint());
+    let key = Zeroizing::new(<[u8; 32]>::from(hasher.finalize()));
+    Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| anyhow!(e))
+}
+
+fn filename_aad(recipient_fingerprint: &[u8], ephemeral: &[u8]) -> Vec<u8> {
+    let mut aad = b"QRE_RECIPIENT_NAME".to_vec();
+    aad.extend_from_slice(recipient_fingerprint);
+    aad.extend_from_slice(ephemeral);
+    aad
+}
+
+fn chunk_aad(header_digest: &[u8], index: u64, is_final: bool) -> Vec<u8> {
+    let mut aad = header_digest.to_vec();
+    aad.extend_from_slice(&index.to_le_bytes());
+    aad.push(is_final as u8);
+    aad
+}
+
+/// Reads up to CHUNK_SIZE bytes, stopping early only at end of input.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// ==========================================
+// --- ENCRYPT / DECRYPT ---
+// ==========================================
+
+/// Encrypts `input_path` for `recipient`, signed (via the static exchange) by `sender`.
+pub fn encrypt_for_recipient(
+    input_path: &str,
+    output_path: &str,
+    sender: &Identity,
+    recipient: &PublicIdentity,
+    callback: impl Fn(u64, u64),
+) -> Result<()> {
+    let filename = Path::new(input_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("Invalid input file name"))?;
+    let file_size = fs::metadata(input_path)?.len();
+
+    let mut eph_bytes = Zeroizing::new([0u8; X25519_LEN]);
+    let mut base_nonce = [0u8; AES_NONCE_LEN];
+    OsRng
+        .try_fill_bytes(eph_bytes.as_mut())
+        .and_then(|_| OsRng.try_fill_bytes(&mut base_nonce))
+        .map_err(|e| anyhow!("RNG failure: {}", e))?;
+    let ephemeral_secret = StaticSecret::from(*eph_bytes);
+    let ephemeral = PublicKey::from(&ephemeral_secret).to_bytes();
+
+    let ss_eph = dh(&ephemeral_secret, &recipient.x25519)?;
+    let ss_static = dh(&sender.x25519, &recipient.x25519)?;
+    let (kem_ciphertext, ss_kem) = recipient
+        .kem_key()?
+        .encapsulate()
+        .map_err(|_| anyhow!("ML-KEM encapsulation failed"))?;
+    let kem_ciphertext = kem_ciphertext.as_ref().to_vec();
+
+    let cipher = derive_file_key(
+        ss_eph.as_ref(),
+        ss_static.as_ref(),
+        ss_kem.as_ref(),
+        &ephemeral,
+        &kem_ciphertext,
+        recipient,
+        sender.public(),
+    )?;
+
+    let recipient_fingerprint = recipient.fingerprint();
+    let encrypted_filename = cipher
+        .encrypt(
+            Nonce::from_slice(&crypto_stream::chunk_nonce(&base_nonce, u64::MAX)),
+            Payload {
+                msg: filename.as_bytes(),
+                aad: &filename_aad(&recipient_fingerprint, &ephemeral),
+            },
+        )
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let header = RecipientHeader {
+        kem: KEM_ML_KEM_1024,
+        recipient_fingerprint,
+        sender: sender.public().to_bytes(),
+        ephemeral,
+        kem_ciphertext,
+        base_nonce,
+        encrypted_filename,
+    };
+    let header_bytes = header_options().serialize(&header)?;
+    let header_digest = Sha256::digest(&header_bytes);
+
+    let mut input = BufReader::new(with_retry("open", Path::new(input_path), || {
+        File::open(input_path)
+    })?);
+    let mut output = BufWriter::new(with_retry("create", Path::new(output_path), || {
+        File::create(output_path)
+    })?);
+
+    let result = (|| -> Result<()> {
+        output.write_all(&VERSION_RECIPIENT.to_le_bytes())?;
+        output.write_all(&(header_bytes.len() as u32).to_le_bytes())?;
+        output.write_all(&header_bytes)?;
+
+        // Read one chunk ahead so the last one can be flagged as final.
+        let mut current = Zeroizing::new(vec![0u8; CHUNK_SIZE]);
+        let mut next = Zeroizing::new(vec![0u8; CHUNK_SIZE]);
+        let mut current_len = read_full(&mut input, &mut current)?;
+        let mut index: u64 = 0;
+        let mut processed: u64 = 0;
+        loop {
+            let next_len = if current_len == CHUNK_SIZE {
+                read_full(&mut input, &mut next)?
+            } else {
+                0
+            };
+            let is_final = next_len == 0;
+
+            let compressed =
+                crypto_stream::compress_chunk(&current[..current_len], COMPRESSION_LEVEL)?;
+            let ciphertext = cipher
+                .encrypt(
+                    Nonce::from_slice(&crypto_stream::chunk_nonce(&base_nonce, index)),
+                    Payload {
+                        msg: &compressed,
+                        aad: &chunk_aad(&header_digest, index, is_final),
+                    },
+                )
+                .map_err(|_| anyhow!("Encryption failed at chunk {}", index))?;
+            output.write_all(&(ciphertext.len() as u32).to_le_bytes())?;
+            output.write_all(&ciphertext)?;
+
+            processed += current_len as u64;
+            callback(processed, file_size);
+            if is_final {
+                break;
+            }
+            std::mem::swap(&mut current, &mut next);
+            current_len = next_len;
+            index += 1;
+        }
+        output.flush()?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        drop(output);
+        let _ = fs::remove_file(output_path);
+    }
+    result
+}
+
+/// Reads the header of a V10 container, e.g. to find out which vault it is for.
+pub fn read_header(path: &str) -> Result<RecipientHeader> {
+    let mut file = BufReader::new(File::open(path)?);
+    read_header_from(&mut file).map(|(header, _)| header)
+}
+
+fn read_header_from<R: Read>(reader: &mut R) -> Result<(RecipientHeader, [u8; SHA256_LEN])> {
+    let mut word = [0u8; 4];
+    reader.read_exact(&mut word)?;
+    if u32::from_le_bytes(word) != VERSION_RECIPIENT {
+        return Err(anyhow!("Not a public-key container"));
+    }
+    reader.read_exact(&mut word)?;
+    let header_len = u32::from_le_bytes(word);
+    if header_len > MAX_HEADER_BYTES {
+        return Err(anyhow!("Header too large — file may be corrupt"));
+    }
+    let mut header_bytes = vec![0u8; header_len as usize];
+    reader.read_exact(&mut header_bytes)?;
+    let header: RecipientHeader = header_options()
+        .deserialize(&header_bytes)
+        .context("Failed to parse public-key container header")?;
+    if header.kem != KEM_ML_KEM_1024 {
+        return Err(anyhow!(
+            "Unsupported key encapsulation (id {}) — this file needs a newer version of QRE.",
+            header.kem
+        ));
+    }
+    Ok((header, Sha256::digest(&header_bytes).into()))
+}
+
+/// Decrypts a V10 container with the recipient's identity into `output_dir`. Returns the
+/// written file name and the sender's public identity, for the caller to match against
+/// its contacts.
+pub fn decrypt_from_sender(
+    input_path: &str,
+    output_dir: &str,
+    identity: &Identity,
+    callback: impl Fn(u64, u64),
+) -> Result<(String, PublicIdentity)> {
+    let file_size = fs::metadata(input_path)?.len();
+    let mut input = BufReader::new(with_retry("open", Path::new(input_path), || {
+        File::open(input_path)
+    })?);
+    let (header, header_digest) = read_header_from(&mut input)?;
+
+    let recipient = identity.public();
+    if header.recipient_fingerprint != recipient.fingerprint() {
+        return Err(anyhow!("This file was encrypted for a different vault."));
+    }
+    let sender = PublicIdentity::from_bytes(&header.sender)?;
+
+    let ss_eph = dh(&identity.x25519, &header.ephemeral)?;
+    let ss_static = dh(&identity.x25519, &sender.x25519)?;
+    if header.kem_ciphertext.len() != KEM_CIPHERTEXT_LEN {
+        return Err(anyhow!("Corrupt key encapsulation"));
+    }
+    let ss_kem = identity
+        .kem
+        .decapsulate(Ciphertext::from(header.kem_ciphertext.as_slice()))
+        .map_err(|_| anyhow!("ML-KEM decapsulation failed"))?;
+
+    let cipher = derive_file_key(
+        ss_eph.as_ref(),
+        ss_static.as_ref(),
+        ss_kem.as_ref(),
+        &header.ephemeral,
+        &header.kem_ciphertext,
+        recipient,
+        &sender,
+    )?;
+
+    // Wrong keys (or a forged sender) surface here, before anything is written.
+    let filename = cipher
+        .decrypt(
+            Nonce::from_slice(&crypto_stream::chunk_nonce(&header.base_nonce, u64::MAX)),
+            Payload {
+                msg: &header.encrypted_filename,
+                aad: &filename_aad(&header.recipient_fingerprint, &header.ephemeral),
+            },
+        )
+        .map_err(|_| {
+            anyhow!("Decryption failed: the file is corrupt or was not sent by the vault it names.")
+        })?;
+    let filename = String::from_utf8(filename).map_err(|_| anyhow!("Invalid file name"))?;
+    crypto_stream::validate_original_filename(&filename)?;
+
+    let final_out = crate::utils::get_unique_path(&Path::new(output_dir).join(&filename));
+    let final_filename = final_out
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut output = BufWriter::new(with_retry("create", &final_out, || {
+        File::create(&final_out)
+    })?);
+
+    let result = (|| -> Result<()> {
+        let mut index: u64 = 0;
+        let mut processed: u64 = 0;
+        let mut len_buf = [0u8; 4];
+        loop {
+            input.read_exact(&mut len_buf)?;
+            let chunk_len = u32::from_le_bytes(len_buf) as usize;
+            if !(GCM_TAG_LEN..=MAX_CHUNK_BYTES).contains(&chunk_len) {
+                return Err(anyhow!(
+                    "Chunk {} size anomaly ({} bytes) — file may be corrupt.",
+                    index,
+                    chunk_len
+                ));
+            }
+            let mut ciphertext = vec![0u8; chunk_len];
+            input.read_exact(&mut ciphertext)?;
+
+            // Only the chunk at the very end of the file may carry the final flag, so
+            // both a cut-off file and data appended after the end fail authentication.
+            let is_final = input.fill_buf()?.is_empty();
+            let compressed = cipher
+                .decrypt(
+                    Nonce::from_slice(&crypto_stream::chunk_nonce(&header.base_nonce, index)),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: &chunk_aad(&header_digest, index, is_final),
+                    },
+                )
+                .map_err(|_| anyhow!("Chunk {} integrity check failed", index))?;
+            let plaintext = Zeroizing::new(crypto_stream::decompress_chunk(&compressed)?);
+            output.write_all(&plaintext)?;
+
+            processed += chunk_len as u64;
+            callback(processed, file_size);
+            if is_final {
+                break;
+            }
+            index += 1;
+        }
+        output.flush()?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        drop(output);
+        let _ = fs::remove_file(&final_out);
+        return Err(e);
+    }
+    Ok((final_filename, sender))
+}
+
+// ==========================================
+// --- VAULT-BOUND ENCAPSULATION ---
+// ==========================================
+
+/// A one-off hybrid encapsulation to an identity, as stored in a V12 stream header.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Encapsulation {
+    /// Which KEM parameter set `kem_ciphertext` belongs to (see KEM_ML_KEM_1024).
+    pub kem: u16,
+    pub recipient_fingerprint: [u8; SHA256_LEN],
+    ephemeral: [u8; X25519_LEN],
+    kem_ciphertext: Vec<u8>,
+}
+
+impl Encapsulation {
+    /// Bounds the ciphertext of a known parameter set; unknown ids are refused only by
+    /// `decapsulate`, so the header still parses and can be reported.
+    pub fn validate(&self) -> Result<()> {
+        if self.kem == KEM_ML_KEM_1024 && self.kem_ciphertext.len() != KEM_CIPHERTEXT_LEN {
+            return Err(anyhow!("Corrupt key encapsulation"));
+        }
+        if self.kem_ciphertext.len() > MAX_HEADER_BYTES as usize {
+            return Err(anyhow!("Key encapsulation too large"));
+        }
+        Ok(())
+    }
+}
+
+fn encapsulation_secret(
+    ss_eph: &[u8],
+    ss_kem: &[u8],
+    encapsulation: &Encapsulation,
+) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_VAULT_ENCAP_V1");
+    hasher.update(ss_eph);
+    hasher.update(ss_kem);
+    hasher.update(encapsulation.ephemeral);
+    hasher.update(Sha256::digest(&encapsulation.kem_ciphertext));
+    hasher.update(encapsulation.recipient_fingerprint);
+    Zeroizing::new(hasher.finalize().into())
+}
+
+/// Encapsulates a fresh 32-byte secret to `recipient`.
+pub fn encapsulate(recipient: &PublicIdentity) -> Result<(Encapsulation, Zeroizing<[u8; 32]>)> {
+    let mut eph_bytes = Zeroizing::new([0u8; X25519_LEN]);
+    OsRng
+        .try_fill_bytes(eph_bytes.as_mut())
+        .map_err(|e| anyhow!("RNG failure: {}", e))?;
+    let ephemeral_secret = StaticSecret::from(*eph_bytes);
+    let ss_eph = dh(&ephemeral_secret, &recipient.x25519)?;
+    let (kem_ciphertext, ss_kem) = recipient
+        .kem_key()?
+        .encapsulate()
+        .map_err(|_| anyhow!("ML-KEM encapsulation failed"))?;
+
+    let encapsulation = Encapsulation {
+        kem: KEM_ML_KEM_1024,
+        recipient_fingerprint: recipient.fingerprint(),
+        ephemeral: PublicKey::from(&ephemeral_secret).to_bytes(),
+        kem_ciphertext: kem_ciphertext.as_ref().to_vec(),
+    };
+    let secret = encapsulation_secret(ss_eph.as_ref(), ss_kem.as_ref(), &encapsulation);
+    Ok((encapsulation, secret))
+}
+
+/// Recovers the secret of `encapsulation` with the identity it was made for.
+pub fn decapsulate(
+    identity: &Identity,
+    encapsulation: &Encapsulation,
+) -> Result<Zeroizing<[u8; 32]>> {
+    if encapsulation.kem != KEM_ML_KEM_1024 {
+        return Err(anyhow!(
+            "Unsupported key encapsulation (id {}) — this file needs a newer version of QRE.",
+            encapsulation.kem
+        ));
+    }
+    encapsulation.validate()?;
+    if encapsulation.recipient_fingerprint != identity.public().fingerprint() {
+        return Err(anyhow!("This file is bound to a different vault identity."));
+    }
+    let ss_eph = dh(&identity.x25519, &encapsulation.ephemeral)?;
+    let ss_kem = identity
+        .kem
+        .decapsulate(Ciphertext::from(encapsulation.kem_ciphertext.as_slice()))
+        .map_err(|_| anyhow!("ML-KEM decapsulation failed"))?;
+    Ok(encapsulation_secret(
+        ss_eph.as_ref(),
+        ss_kem.as_ref(),
+        encapsulation,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join("qre_recipient_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_identity_encoding_roundtrip() {
+        let identity = Identity::generate().unwrap();
+        let restored = Identity::from_secret_bytes(&identity.secret_bytes().unwrap()).unwrap();
+        assert_eq!(restored.public(), identity.public());
+
+        let text = identity.public().encode("Alice\n(laptop)");
+        let (decoded, label) = PublicIdentity::decode(&text).unwrap();
+        assert_eq!(&decoded, identity.public());
+        assert_eq!(label, "Alice(laptop)");
+        assert!(PublicIdentity::decode("qre-identity:1:AAAA:x").is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_and_wrong_recipient() {
+        let dir = temp_dir("roundtrip");
+        let input = dir.join("plans.txt");
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 17).map(|i| (i % 251) as u8).collect();
+        fs::write(&input, &data).unwrap();
+        let container = dir.join("plans.txt.qre");
+
+        let alice = Identity::generate().unwrap();
+        let bob = Identity::generate().unwrap();
+        encrypt_for_recipient(
+            input.to_str().unwrap(),
+            container.to_str().unwrap(),
+            &alice,
+            bob.public(),
+            |_, _| {},
+        )
+        .unwrap();
+
+        let header = read_header(container.to_str().unwrap()).unwrap();
+        assert_eq!(header.recipient_fingerprint, bob.public().fingerprint());
+
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        assert!(decrypt_from_sender(
+            container.to_str().unwrap(),
+            out.to_str().unwrap(),
+            &alice,
+            |_, _| {}
+        )
+        .is_err());
+
+        let (name, sender) = decrypt_from_sender(
+            container.to_str().unwrap(),
+            out.to_str().unwrap(),
+            &bob,
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(name, "plans.txt");
+        assert_eq!(&sender, alice.public());
+        assert_eq!(fs::read(out.join(name)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_truncation_detected() {
+        let dir = temp_dir("truncation");
+        let input = dir.join("big.bin");
+        fs::write(&input, vec![7u8; CHUNK_SIZE + 10]).unwrap();
+        let container = dir.join("big.bin.qre");
+
+        let alice = Identity::generate().unwrap();
+        let bob = Identity::generate().unwrap();
+        encrypt_for_recipient(
+            input.to_str().unwrap(),
+            container.to_str().unwrap(),
+            &alice,
+            bob.public(),
+            |_, _| {},
+        )
+        .unwrap();
+
+        // Drop the final chunk: the first one is not flagged final, so this must fail.
+        let bytes = fs::read(&container).unwrap();
+        let header_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        let first = 8 + header_len;
+        let first_len = u32::from_le_bytes(bytes[first..first + 4].try_into().unwrap()) as usize;
+        fs::write(&container, &bytes[..first + 4 + first_len]).unwrap();
+
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        assert!(decrypt_from_sender(
+            container.to_str().unwrap(),
+            out.to_str().unwrap(),
+            &bob,
+            |_, _| {}
+        )
+        .is_err());
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 0);
+    }
+}
+
+// --- END OF FILE recipient.rs ---
@@ -0,0 +1,427 @@
+// --- START OF FILE recovery_risk.rs ---
+
+// ==========================================
+// --- RECOVERY RISK OF A DELETED FILE ---
+// ==========================================
+// Informational only: after a file was deleted the normal way (not shredded), how
+// likely is it that undelete tools still find it? The answer depends mostly on the
+// medium. A hard drive keeps the old sectors until something happens to overwrite them;
+// an SSD that is told about the deletion (TRIM) erases the blocks in the background,
+// usually within minutes. Copy-on-write file systems and network shares add copies that
+// no local action can reach. The report names the likely outcome and the one action
+// that helps: a free-space wipe on hard drives and flash media without TRIM, a TRIM
+// pass on SSDs.
+//
+// Nothing here reads the disk's contents; it looks only at the volume's type, file
+// system and TRIM setting, plus how long ago the user says the file was deleted.
+
+use serde::Serialize;
+use std::path::Path;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Media {
+    Ssd,
+    Hdd,
+    Unknown,
+}
+
+/// How the volume passes deletions on to the SSD.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrimMode {
+    /// TRIM is sent as files are deleted (Windows and macOS defaults, `discard` mounts).
+    Continuous,
+    /// A scheduled job trims free space (e.g. Linux `fstrim.timer`, weekly).
+    Periodic,
+    Off,
+    Unknown,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+    /// Cannot be judged from this machine (network shares).
+    Unknown,
+}
+
+/// What is known about the volume the file was deleted from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VolumeFacts {
+    pub mount_point: String,
+    pub file_system: Option<String>,
+    pub media: Media,
+    pub removable: bool,
+    pub trim: TrimMode,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecoveryRisk {
+    pub mount_point: Option<String>,
+    pub file_system: Option<String>,
+    pub media: Media,
+    pub removable: bool,
+    pub trim: TrimMode,
+    /// Seconds since the deletion, when the user gave its time.
+    pub elapsed_secs: Option<u64>,
+    pub risk: RiskLevel,
+    /// One sentence per factor that went into `risk`.
+    pub reasons: Vec<String>,
+    pub recommendation: String,
+    pub recommend_free_space_wipe: bool,
+    pub recommend_trim: bool,
+}
+
+/// Estimates how recoverable a file deleted from `former_location` (the file's old
+/// path or its folder) is. `deleted_at` is Unix seconds, if the user knows it.
+pub fn estimate(former_location: &Path, deleted_at: Option<u64>) -> RecoveryRisk {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let elapsed = deleted_at.map(|at| now.saturating_sub(at));
+    match volume_facts(former_location) {
+        Some(facts) => assess(&facts, elapsed),
+        None => RecoveryRisk {
+            mount_point: None,
+            file_system: None,
+            media: Media::Unknown,
+            removable: false,
+            trim: TrimMode::Unknown,
+            elapsed_secs: elapsed,
+            risk: RiskLevel::Unknown,
+            reasons: vec!["The drive that held this location could not be identified.".to_string()],
+            recommendation: "Reconnect the drive the file was deleted from and check again."
+                .to_string(),
+            recommend_free_space_wipe: false,
+            recommend_trim: false,
+        },
+    }
+}
+
+/// The estimate itself, separate from the system queries.
+pub(crate) fn assess(facts: &VolumeFacts, elapsed: Option<u64>) -> RecoveryRisk {
+    let fs = facts
+        .file_system
+        .as_deref()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let mut reasons = Vec::new();
+    let mut recommend_free_space_wipe = false;
+    let mut recommend_trim = false;
+
+    let network = [
+        "nfs",
+        "nfs4",
+        "cifs",
+        "smb3",
+        "smbfs",
+        "afpfs",
+        "9p",
+        "fuse.sshfs",
+        "davfs",
+    ]
+    .contains(&fs.as_str());
+    let mut risk = if network {
+        reasons.push("The file was on a network share: the server decides what happens to deleted data, and it may keep snapshots or a recycle bin of its own.".to_string());
+        RiskLevel::Unknown
+    } else {
+        match (facts.media, facts.trim) {
+            (Media::Ssd, TrimMode::Continuous) => {
+                reasons.push("SSD with TRIM on delete: the drive is told the blocks are free and normally erases them within minutes.".to_string());
+                if elapsed.is_some_and(|e| e >= HOUR) {
+                    RiskLevel::Low
+                } else {
+                    reasons.push(
+                        "The deletion is recent, so the drive may not have erased the blocks yet."
+                            .to_string(),
+                    );
+                    recommend_trim = true;
+                    RiskLevel::Medium
+                }
+            }
+            (Media::Ssd, TrimMode::Periodic) => {
+                reasons.push("SSD trimmed on a schedule: freed blocks stay readable until the next TRIM run, usually weekly.".to_string());
+                recommend_trim = true;
+                if elapsed.is_some_and(|e| e >= 7 * DAY) {
+                    RiskLevel::Low
+                } else {
+                    RiskLevel::Medium
+                }
+            }
+            (Media::Ssd, TrimMode::Off) => {
+                reasons.push("SSD without TRIM: freed blocks keep their contents until the drive reuses them.".to_string());
+                reasons.push(
+                    "Overwriting free space on an SSD is unreliable because of wear levelling."
+                        .to_string(),
+                );
+                recommend_trim = true;
+                RiskLevel::High
+            }
+            (Media::Ssd, TrimMode::Unknown) => {
+                reasons.push(
+                    "SSD with unknown TRIM setting: the blocks may or may not have been erased."
+                        .to_string(),
+                );
+                recommend_trim = true;
+                RiskLevel::Medium
+            }
+            (Media::Hdd, _) => {
+                reasons.push("Hard drive: deleting only marks the space as free; the data stays on the platters until it is overwritten.".to_string());
+                recommend_free_space_wipe = true;
+                if elapsed.is_some_and(|e| e >= 30 * DAY) {
+                    reasons.push("A month or more has passed, so some of it may have been overwritten by new data, but nothing guarantees it.".to_string());
+                    RiskLevel::Medium
+                } else {
+                    RiskLevel::High
+                }
+            }
+            (Media::Unknown, _) if facts.removable => {
+                reasons.push("Removable flash media (USB sticks, SD cards) rarely support TRIM, so deleted data usually stays readable.".to_string());
+                recommend_free_space_wipe = true;
+                RiskLevel::High
+            }
+            (Media::Unknown, _) => {
+                reasons.push("The drive type could not be determined.".to_string());
+                recommend_free_space_wipe = true;
+                RiskLevel::Medium
+            }
+        }
+    };
+
+    if ["btrfs", "zfs", "apfs", "refs", "bcachefs"].contains(&fs.as_str()) {
+        reasons.push(format!("{} is a copy-on-write file system: snapshots taken before the deletion still hold the file. Delete those snapshots too.", facts.file_system.as_deref().unwrap_or_default()));
+        if risk == RiskLevel::Low {
+            risk = RiskLevel::Medium;
+        }
+    }
+    if ["vfat", "fat32", "fat", "exfat", "msdos"].contains(&fs.as_str()) {
+        reasons.push("FAT file systems keep most of a deleted file's directory entry, which makes undeleting easy.".to_string());
+    }
+    if elapsed.is_none() && risk != RiskLevel::Unknown {
+        reasons
+            .push("Without the time of deletion the estimate assumes it was recent.".to_string());
+    }
+
+    let recommendation = match (risk, recommend_free_space_wipe, recommend_trim) {
+        (RiskLevel::Unknown, _, _) => "Ask the share's administrator about snapshots and backups; nothing done on this computer reaches the server's disks.".to_string(),
+        (RiskLevel::Low, _, _) => "No action needed. Shred sensitive files next time so there is nothing to recover.".to_string(),
+        (_, true, _) => format!("Run Wipe Free Space on {} to overwrite the space the file used.", facts.mount_point),
+        (_, _, true) => format!("Run TRIM on {} so the SSD erases the freed blocks now.", facts.mount_point),
+        _ => "Shred sensitive files next time so there is nothing to recover.".to_string(),
+    };
+
+    RecoveryRisk {
+        mount_point: Some(facts.mount_point.clone()),
+        file_system: facts.file_system.clone(),
+        media: facts.media,
+        removable: facts.removable,
+        trim: facts.trim,
+        elapsed_secs: elapsed,
+        risk,
+        reasons,
+        recommendation,
+        recommend_free_space_wipe,
+        recommend_trim,
+    }
+}
+
+// ==========================================
+// --- SYSTEM QUERIES ---
+// ==========================================
+
+/// Type, file system and TRIM mode of the volume holding `location`. The file itself is
+/// gone, so the nearest existing ancestor decides the volume.
+fn volume_facts(location: &Path) -> Option<VolumeFacts> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = location;
+        None
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        let existing = location.ancestors().find(|p| p.exists())?;
+        let existing = std::fs::canonicalize(existing).unwrap_or_else(|_| existing.to_path_buf());
+        let disks = sysinfo::Disks::new_with_refreshed_list();
+        let disk = disks
+            .list()
+            .iter()
+            .filter(|d| existing.starts_with(d.mount_point()))
+            .max_by_key(|d| d.mount_point().components().count())?;
+        let media = match disk.kind() {
+            sysinfo::DiskKind::SSD => Media::Ssd,
+            sysinfo::DiskKind::HDD => Media::Hdd,
+            sysinfo::DiskKind::Unknown(_) => Media::Unknown,
+        };
+        let file_system =
+            Some(disk.file_system().to_string_lossy().to_string()).filter(|f| !f.is_empty());
+        let trim = if media == Media::Ssd {
+            trim_mode(disk.mount_point(), file_system.as_deref())
+        } else {
+            TrimMode::Unknown
+        };
+        Some(VolumeFacts {
+            mount_point: disk.mount_point().to_string_lossy().to_string(),
+            file_system,
+            media,
+            removable: disk.is_removable(),
+            trim,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn trim_mode(mount_point: &Path, _file_system: Option<&str>) -> TrimMode {
+    // /proc/mounts: "<device> <mount point> <fs> <options> ..."; spaces in the mount
+    // point are escaped as \040.
+    let mount = mount_point.to_string_lossy().replace(' ', "\\040");
+    let options = std::fs::read_to_string("/proc/mounts")
+        .ok()
+        .and_then(|mounts| {
+            mounts
+                .lines()
+                .rev() // the last mount on a mount point is the visible one
+                .map(|l| l.split_whitespace().collect::<Vec<_>>())
+                .find(|f| f.len() >= 4 && f[1] == mount)
+                .map(|f| f[3].to_string())
+        });
+    match options {
+        Some(o)
+            if o.split(',')
+                .any(|opt| opt == "discard" || opt.starts_with("discard=")) =>
+        {
+            TrimMode::Continuous
+        }
+        _ if Path::new("/etc/systemd/system/timers.target.wants/fstrim.timer").exists() => {
+            TrimMode::Periodic
+        }
+        Some(_) => TrimMode::Off,
+        None => TrimMode::Unknown,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn trim_mode(_mount_point: &Path, _file_system: Option<&str>) -> TrimMode {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    // "NTFS DisableDeleteNotify = 0 (Allows TRIM operations to be sent to the storage device)"
+    let Ok(output) = std::process::Command::new("fsutil")
+        .args(["behavior", "query", "DisableDeleteNotify"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    else {
+        return TrimMode::Unknown;
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    match text
+        .lines()
+        .find(|l| l.contains("NTFS"))
+        .and_then(|l| l.split('=').nth(1))
+    {
+        Some(v) if v.trim_start().starts_with('0') => TrimMode::Continuous,
+        Some(v) if v.trim_start().starts_with('1') => TrimMode::Off,
+        _ => TrimMode::Unknown,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn trim_mode(_mount_point: &Path, file_system: Option<&str>) -> TrimMode {
+    // Apple SSDs are trimmed by APFS as space is freed; third-party SSDs only after
+    // `trimforce enable`, which cannot be read back without root.
+    if file_system.is_some_and(|f| f.eq_ignore_ascii_case("apfs")) {
+        TrimMode::Continuous
+    } else {
+        TrimMode::Unknown
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "android"
+)))]
+fn trim_mode(_mount_point: &Path, _file_system: Option<&str>) -> TrimMode {
+    TrimMode::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(media: Media, trim: TrimMode, fs: &str, removable: bool) -> VolumeFacts {
+        VolumeFacts {
+            mount_point: "/data".to_string(),
+            file_system: Some(fs.to_string()),
+            media,
+            removable,
+            trim,
+        }
+    }
+
+    #[test]
+    fn test_hdd_and_flash_recommend_free_space_wipe() {
+        let hdd = assess(
+            &volume(Media::Hdd, TrimMode::Unknown, "ntfs", false),
+            Some(2 * DAY),
+        );
+        assert_eq!(hdd.risk, RiskLevel::High);
+        assert!(hdd.recommend_free_space_wipe && !hdd.recommend_trim);
+        assert!(hdd.recommendation.contains("Wipe Free Space on /data"));
+
+        let old = assess(
+            &volume(Media::Hdd, TrimMode::Unknown, "ext4", false),
+            Some(60 * DAY),
+        );
+        assert_eq!(old.risk, RiskLevel::Medium);
+
+        let usb = assess(
+            &volume(Media::Unknown, TrimMode::Unknown, "vfat", true),
+            None,
+        );
+        assert_eq!(usb.risk, RiskLevel::High);
+        assert!(usb.reasons.iter().any(|r| r.contains("FAT")));
+    }
+
+    #[test]
+    fn test_ssd_depends_on_trim_and_time() {
+        let fresh = assess(
+            &volume(Media::Ssd, TrimMode::Continuous, "ntfs", false),
+            Some(60),
+        );
+        assert_eq!(fresh.risk, RiskLevel::Medium);
+        assert!(fresh.recommend_trim && !fresh.recommend_free_space_wipe);
+
+        let settled = assess(
+            &volume(Media::Ssd, TrimMode::Continuous, "ext4", false),
+            Some(DAY),
+        );
+        assert_eq!(settled.risk, RiskLevel::Low);
+        assert!(!settled.recommend_trim);
+
+        // Snapshots can still hold the file on copy-on-write file systems.
+        let cow = assess(
+            &volume(Media::Ssd, TrimMode::Continuous, "btrfs", false),
+            Some(DAY),
+        );
+        assert_eq!(cow.risk, RiskLevel::Medium);
+
+        let no_trim = assess(&volume(Media::Ssd, TrimMode::Off, "ext4", false), Some(DAY));
+        assert_eq!(no_trim.risk, RiskLevel::High);
+        assert!(no_trim.recommendation.contains("TRIM"));
+
+        let share = assess(
+            &volume(Media::Unknown, TrimMode::Unknown, "cifs", false),
+            None,
+        );
+        assert_eq!(share.risk, RiskLevel::Unknown);
+    }
+}
+
+// --- END OF FILE recovery_risk.rs ---
@@ -0,0 +1,297 @@
+// --- START OF FILE regexes.rs ---
+
+// ==========================================
+// --- REGEX REGISTRY ---
+// ==========================================
+// Every fixed pattern the app matches with lives in the table below and is compiled at
+// most once per process, on first use (`get`), instead of on every call. The clipboard
+// watcher classifies each capture with several of them, so compiling per call was most
+// of its cost when text is copied in quick succession.
+//
+// The `regex` crate matches in time linear in the input, so no pattern can backtrack
+// catastrophically (ReDoS). What is left to bound is the size of the input and of the
+// compiled program:
+//   - callers check input length against the limits below before matching;
+//   - every pattern is built with `SIZE_LIMIT`, user patterns also with a nesting limit.
+//
+// The table is checked when the crate is built: a const assertion rejects a pattern with
+// unbalanced groups or classes. The rest of the syntax is only known to the `regex`
+// crate, so `test_every_pattern_compiles` builds each pattern.
+//
+// Patterns that come from users (pattern packs, URL match rules) are not in the table:
+// `compile_untrusted` builds them with the same limits and `cached_untrusted` keeps the
+// recent ones so a lookup does not recompile them.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Text longer than this is not classified (`clipboard_store::analyze_content`).
+pub const MAX_CLASSIFY_LEN: usize = 100_000;
+/// Longest text the clipboard transforms rewrite with a pattern (HTML to text, links).
+pub const MAX_REWRITE_LEN: usize = 4 * 1024 * 1024;
+/// Upper bound on a compiled program, ours or a user's.
+const SIZE_LIMIT: usize = 1 << 20;
+/// Deepest group/repetition nesting accepted in a user pattern.
+const NEST_LIMIT: u32 = 32;
+/// User patterns kept compiled; the cache starts over when it is full.
+const UNTRUSTED_CACHE_SIZE: usize = 256;
+
+macro_rules! patterns {
+    ($($(#[$doc:meta])* $name:ident = $source:expr;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Pattern {
+            $($(#[$doc])* $name,)*
+        }
+
+        impl Pattern {
+            pub const ALL: &'static [Pattern] = &[$(Pattern::$name,)*];
+
+            pub const fn source(self) -> &'static str {
+                match self {
+                    $(Pattern::$name => $source,)*
+                }
+            }
+        }
+    };
+}
+
+patterns! {
+    // --- Clipboard classification (clipboard_store.rs) ---
+    /// 13-19 digit card numbers, grouped by four or run together.
+    CardNumber = r"\b\d{4}[ -]?\d{4}[ -]?\d{4}[ -]?\d{1,7}\b";
+    /// IBAN-shaped text; the mod-97 check decides.
+    IbanCandidate = r"(?i)\b[A-Z]{2}[0-9]{2}(?: ?[A-Z0-9]){11,30}\b";
+    /// An international number written with `+` or `00`.
+    PhoneInternational = r"(?:^|[\s(:;,])(\+|00)[1-9][0-9 ().-]{5,22}[0-9]";
+    /// A national number on its own, with the usual separators.
+    PhoneNational = r"^\(?[0-9]{1,5}\)?(?:[ .-]{1,2}\(?[0-9]{1,8}\)?){1,5}$";
+    /// Dates, IPv4 addresses and amounts with thousands separators.
+    NotPhone = r"^(?:[0-9]{1,4}[./-][0-9]{1,2}[./-][0-9]{1,4}|[0-9]{1,3}(?:\.[0-9]{1,3}){3}|[0-9]{1,3}(?: [0-9]{3})+)$";
+    /// Chinese, Japanese and Korean block and house number markers.
+    CjkAddress = r"[0-9０-９一二三四五六七八九十]+丁目|[0-9０-９]+番地|[路街道巷][0-9０-９]+[号號]|[가-힣]+구\s.*[가-힣]+(?:로|길)\s*[0-9]+";
+
+    // --- Clipboard transforms (clipboard_store.rs, url_cleaner.rs) ---
+    /// Markup whose content is not text: scripts, styles, the head, comments.
+    HtmlInvisible = r"(?is)<(script|style|head)\b[^>]*>.*?</(script|style|head)\s*>|<!--.*?-->";
+    /// Tags that end a line.
+    HtmlBreaks = r"(?i)<br\s*/?>|</(p|div|li|tr|h[1-6]|blockquote|pre)\s*>";
+    HtmlTag = r"<[^>]*>";
+    HtmlEntity = r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z]{2,8});";
+    BlankLines = r"\n[ \t]*(\n[ \t]*)+";
+    HttpLink = r#"(?i)\bhttps?://[^\s<>"']+"#;
+
+    // --- Secret scanner (commands/tools.rs) ---
+    /// A credential name and its value; group 2 is the value, which is entropy-checked.
+    /// Whitespace around the separator is capped at five characters.
+    SecretCredential = r#"(?i)(password|secret|api_key|token|access_key)[\s]{0,5}[:=][\s]{0,5}['"]?([a-zA-Z0-9\-_]{16,})['"]?"#;
+    /// Key formats specific enough to need no entropy check.
+    SecretApiKey = r"(sk_live_[0-9a-zA-Z]{24,}|sk_test_[0-9a-zA-Z]{24,}|ghp_[0-9a-zA-Z]{36}|gho_[0-9a-zA-Z]{36}|AKIA[0-9A-Z]{16}|eyJ[a-zA-Z0-9_-]{20,}[.][a-zA-Z0-9_-]{20,})";
+    /// Exactly 12 lowercase words on a line.
+    SeedPhrase = r"^(?:[a-z]{3,}\s){11}[a-z]{3,}$";
+
+    // --- Log redaction (logging.rs) ---
+    /// A path in free text, at the start or after a space, quote, bracket or `=`. Group 2
+    /// runs to the end of the line; `LogPathEnd` finds where the path most likely stops.
+    LogPath = r#"(^|[\s"'(\[=])((?:[A-Za-z]:[\\/]|\\\\|~[\\/]|/)[^"'<>|\r\n\t]*)"#;
+    /// What ends a path in a message: a `: `, `, ` or `; ` separator, or the next path.
+    LogPathEnd = r"[:,;] |\s(?:[A-Za-z]:[\\/]|\\\\|~[\\/]|/)";
+
+    // --- Other inputs ---
+    /// A leading date in a file name, optionally after a camera/screenshot prefix and
+    /// followed by a time (renamer.rs).
+    DatePrefix = r"(?i)^(?:(?:img|vid|pxl|dsc|screenshot|screen shot)[ _-]?)?(\d{4})[-_.]?(\d{2})[-_.]?(\d{2})(?:(?:[ T_-]|[ _-]at[ _-])\d{2}[-_.:]?\d{2}(?:[-_.:]?\d{2})?)?[ _.-]*";
+    /// `#RRGGBB` colors (qr.rs).
+    HexColor = r"^#[0-9A-Fa-f]{6}$";
+}
+
+const _: () = {
+    let mut i = 0;
+    while i < Pattern::ALL.len() {
+        assert!(
+            well_formed(Pattern::ALL[i].source()),
+            "regex pattern with unbalanced groups or classes"
+        );
+        i += 1;
+    }
+};
+
+/// Non-empty, with every group closed and every class closed. Escapes are skipped; a `]`
+/// right after `[` or `[^` is a literal, as in the `regex` crate.
+const fn well_formed(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    let mut depth = 0usize;
+    let mut class_start: Option<usize> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'\\' {
+            i += 2;
+            continue;
+        }
+        match class_start {
+            Some(start) => {
+                let first = i == start || (i == start + 1 && bytes[start] == b'^');
+                if b == b']' && !first {
+                    class_start = None;
+                }
+            }
+            None => match b {
+                b'[' => class_start = Some(i + 1),
+                b'(' => depth += 1,
+                b')' => {
+                    if depth == 0 {
+                        return false;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    !bytes.is_empty() && i == bytes.len() && depth == 0 && class_start.is_none()
+}
+
+/// The compiled `pattern`; the first call for each pattern compiles it.
+pub fn get(pattern: Pattern) -> &'static Regex {
+    static COMPILED: [OnceLock<Regex>; Pattern::ALL.len()] =
+        [const { OnceLock::new() }; Pattern::ALL.len()];
+    COMPILED[pattern as usize].get_or_init(|| {
+        RegexBuilder::new(pattern.source())
+            .size_limit(SIZE_LIMIT)
+            .build()
+            .unwrap_or_else(|e| panic!("built-in pattern {:?} is invalid: {}", pattern, e))
+    })
+}
+
+/// Compiles every pattern, so the first clipboard capture does not pay for it.
+pub fn precompile() {
+    for &pattern in Pattern::ALL {
+        get(pattern);
+    }
+}
+
+/// Builds a pattern that came from a user, within the registry's limits.
+pub fn compile_untrusted(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .size_limit(SIZE_LIMIT)
+        .nest_limit(NEST_LIMIT)
+        .build()
+}
+
+/// `compile_untrusted`, remembered: a rule checked against every page visit is compiled
+/// once. `None` if the pattern is invalid.
+pub fn cached_untrusted(pattern: &str) -> Option<Regex> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|p| p.into_inner());
+    if let Some(regex) = cache.get(pattern) {
+        return regex.clone();
+    }
+    if cache.len() >= UNTRUSTED_CACHE_SIZE {
+        cache.clear();
+    }
+    let regex = compile_untrusted(pattern).ok();
+    cache.insert(pattern.to_string(), regex.clone());
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_every_pattern_compiles() {
+        for &pattern in Pattern::ALL {
+            assert!(
+                RegexBuilder::new(pattern.source())
+                    .size_limit(SIZE_LIMIT)
+                    .build()
+                    .is_ok(),
+                "{:?}",
+                pattern
+            );
+        }
+        precompile();
+        assert!(std::ptr::eq(get(Pattern::HexColor), get(Pattern::HexColor)));
+    }
+
+    #[test]
+    fn test_well_formed_and_untrusted_limits() {
+        assert!(well_formed(r"(a|b)[)(]\(x"));
+        assert!(well_formed(r"[]a][^]b]"));
+        assert!(!well_formed(""));
+        assert!(!well_formed("(a"));
+        assert!(!well_formed("a)("));
+        assert!(!well_formed("[a"));
+        assert!(!well_formed(r"a\"));
+
+        assert!(compile_untrusted(&format!("{}a{}", "(".repeat(64), ")".repeat(64))).is_err());
+        assert!(compile_untrusted(r"\w{1000}{1000}").is_err());
+        assert!(cached_untrusted("(").is_none());
+        let rule = cached_untrusted(r"^https://example\.com/").unwrap();
+        assert!(rule.is_match("https://example.com/login"));
+    }
+
+    // Benchmarks, run on demand:
+    //   cargo test --release regexes::tests::bench -- --ignored --nocapture
+
+    fn per_call(runs: u32, mut f: impl FnMut()) -> f64 {
+        let start = Instant::now();
+        for _ in 0..runs {
+            f();
+        }
+        start.elapsed().as_secs_f64() * 1e6 / f64::from(runs)
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_registry_against_compiling_per_call() {
+        const RUNS: u32 = 2_000;
+        let text = "Call +44 20 7946 0958 about 4111 2222 3333 4444";
+        precompile();
+        for pattern in [
+            Pattern::CardNumber,
+            Pattern::PhoneInternational,
+            Pattern::CjkAddress,
+        ] {
+            let compiled = per_call(RUNS, || {
+                std::hint::black_box(Regex::new(pattern.source()).unwrap().is_match(text));
+            });
+            let cached = per_call(RUNS, || {
+                std::hint::black_box(get(pattern).is_match(text));
+            });
+            println!(
+                "{:?}: {:.1} µs compiling per call, {:.2} µs from the registry",
+                pattern, compiled, cached
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_clipboard_classification() {
+        use crate::clipboard_store::analyze_content;
+        const RUNS: u32 = 200;
+        let samples = [
+            "4111 2222 3333 4444".to_string(),
+            "GR16 0110 1250 0000 0001 2300 695".to_string(),
+            "Call me on +30 210 123 4567 tomorrow".to_string(),
+            "221B Baker Street, London".to_string(),
+            "lorem ipsum dolor sit amet ".repeat(3_700),
+        ];
+        precompile();
+        for text in &samples {
+            let micros = per_call(RUNS, || {
+                std::hint::black_box(analyze_content(text));
+            });
+            println!("{} bytes: {:.1} µs per capture", text.len(), micros);
+        }
+    }
+}
+
+// --- END OF FILE regexes.rs ---
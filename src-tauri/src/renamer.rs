@@ -0,0 +1,400 @@
+// --- START OF FILE renamer.rs ---
+
+// ==========================================
+// --- BATCH RENAME ---
+// ==========================================
+// Filenames are metadata too: "IMG_20240612_083012.jpg" or "2024-06-12 Lab results.pdf"
+// tell anyone who sees the file when it was made. These presets rename many files in
+// one go:
+//   strip_date_prefix  drop a leading date/time (and camera prefix such as IMG_)
+//   random             replace the name with a random UUID
+//   sequential         <prefix><number>, numbered in the order the paths were given
+//   exif_date          the photo's EXIF capture time (useful before the EXIF is cleaned)
+// Extensions are always kept.
+//
+// Renaming is planned first (`plan_renames`) so the UI can show a dry-run preview, then
+// applied (`apply_plan`). Collisions with existing files and between files of the same
+// batch are resolved during planning; nothing is ever overwritten.
+
+use crate::random_names::{self, NameKind};
+use crate::regexes::{self, Pattern};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RenamePattern {
+    StripDatePrefix,
+    Random {
+        /// UUID unless the preset asks for readable words (see random_names.rs).
+        #[serde(default)]
+        style: NameKind,
+    },
+    Sequential {
+        #[serde(default)]
+        prefix: String,
+        #[serde(default = "default_start")]
+        start: u32,
+        /// Zero-padding width; 0 pads to the width of the largest number.
+        #[serde(default)]
+        padding: usize,
+    },
+    ExifDate,
+}
+
+fn default_start() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Append " (1)", " (2)", ... like `utils::get_unique_path`.
+    #[default]
+    Suffix,
+    Skip,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameStatus {
+    /// Dry run: would be renamed.
+    Planned,
+    Renamed,
+    /// The pattern produces the current name.
+    Unchanged,
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RenameItem {
+    pub path: String,
+    pub new_name: Option<String>,
+    pub status: RenameStatus,
+    pub message: Option<String>,
+}
+
+// ==========================================
+// --- NAME GENERATION ---
+// ==========================================
+
+/// `stem` without its leading date, or `None` if it has no (plausible) date prefix.
+pub fn strip_date_prefix(stem: &str) -> Option<String> {
+    let caps = regexes::get(Pattern::DatePrefix).captures(stem)?;
+    let year: u32 = caps[1].parse().ok()?;
+    let month: u32 = caps[2].parse().ok()?;
+    let day: u32 = caps[3].parse().ok()?;
+    // "12345678_notes" is not a date.
+    if !(1900..=2100).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(stem[caps[0].len()..].to_string())
+}
+
+/// EXIF capture time as `YYYY-MM-DD_HHMMSS` (DateTimeOriginal, else DateTime).
+pub fn exif_date_name(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::BufReader::new(file))
+        .ok()?;
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .iter()
+        .find_map(
+            |tag| match &exif.get_field(*tag, exif::In::PRIMARY)?.value {
+                exif::Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok(),
+                _ => None,
+            },
+        )
+        .map(|dt| {
+            format!(
+                "{:04}-{:02}-{:02}_{:02}{:02}{:02}",
+                dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+            )
+        })
+}
+
+/// (stem, ".ext") — the extension keeps its original case; names without one get "".
+fn split_name(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    }
+}
+
+/// New name for the `index`-th path of a batch of `total`, or an error message.
+fn generate_name(
+    path: &Path,
+    index: usize,
+    total: usize,
+    pattern: &RenamePattern,
+) -> Result<String, String> {
+    let name = path
+        .file_name()
+        .ok_or("Path has no file name")?
+        .to_string_lossy();
+    let (stem, ext) = split_name(&name);
+    match pattern {
+        RenamePattern::StripDatePrefix => match strip_date_prefix(stem) {
+            Some(rest) if rest.trim().is_empty() => {
+                Err("The name is only a date; use another preset.".to_string())
+            }
+            Some(rest) => Ok(format!("{}{}", rest, ext)),
+            None => Ok(name.to_string()),
+        },
+        RenamePattern::Random { style } => random_names::generate(*style)
+            .map(|name| format!("{}{}", name, ext))
+            .map_err(|e| e.to_string()),
+        RenamePattern::Sequential {
+            prefix,
+            start,
+            padding,
+        } => {
+            if prefix.contains(['/', '\\']) {
+                return Err("The prefix cannot contain path separators.".to_string());
+            }
+            let number = *start as u64 + index as u64;
+            let width = if *padding == 0 {
+                (*start as u64 + total.saturating_sub(1) as u64)
+                    .to_string()
+                    .len()
+            } else {
+                *padding
+            };
+            Ok(format!(
+                "{}{:0width$}{}",
+                prefix,
+                number,
+                ext,
+                width = width
+            ))
+        }
+        RenamePattern::ExifDate => exif_date_name(path)
+            .map(|date| format!("{}{}", date, ext))
+            .ok_or_else(|| "No EXIF capture date.".to_string()),
+    }
+}
+
+// ==========================================
+// --- PLANNING & APPLYING ---
+// ==========================================
+
+/// True if `target` is the source file itself, e.g. a case-only rename on a
+/// case-insensitive filesystem, where the target already "exists".
+fn is_same_entry(source: &Path, target: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::symlink_metadata(source), fs::symlink_metadata(target)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        source.parent() == target.parent()
+            && source
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                == target
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+    }
+}
+
+/// First free "<stem> (n)<ext>" in `dir`, not on disk and not claimed by the batch.
+fn with_suffix(dir: &Path, name: &str, claimed: &HashSet<PathBuf>) -> PathBuf {
+    let (stem, ext) = split_name(name);
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists() && !claimed.contains(p))
+        .unwrap()
+}
+
+/// Works out the new name of every path without touching the disk. Items that would
+/// be renamed are `Planned`.
+pub fn plan_renames(
+    paths: &[PathBuf],
+    pattern: &RenamePattern,
+    on_collision: CollisionPolicy,
+) -> Vec<RenameItem> {
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+    let mut plan = Vec::with_capacity(paths.len());
+
+    for (index, path) in paths.iter().enumerate() {
+        let mut item = RenameItem {
+            path: path.to_string_lossy().to_string(),
+            new_name: None,
+            status: RenameStatus::Planned,
+            message: None,
+        };
+        let new_name = match generate_name(path, index, paths.len(), pattern) {
+            Ok(name) => name,
+            Err(msg) => {
+                item.status = RenameStatus::Skipped;
+                item.message = Some(msg);
+                plan.push(item);
+                continue;
+            }
+        };
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut target = dir.join(&new_name);
+
+        if target == *path {
+            item.status = RenameStatus::Unchanged;
+        } else if (target.exists() && !is_same_entry(path, &target)) || claimed.contains(&target) {
+            match on_collision {
+                CollisionPolicy::Skip => {
+                    item.status = RenameStatus::Skipped;
+                    item.message = Some(format!("'{}' already exists.", new_name));
+                }
+                CollisionPolicy::Suffix => target = with_suffix(dir, &new_name, &claimed),
+            }
+        }
+        if item.status == RenameStatus::Planned {
+            item.new_name = Some(target.file_name().unwrap().to_string_lossy().to_string());
+            claimed.insert(target);
+        }
+        plan.push(item);
+    }
+    plan
+}
+
+/// Performs the `Planned` renames. The target is re-checked right before each rename
+/// because `fs::rename` silently replaces existing files on Unix.
+pub fn apply_plan(plan: &mut [RenameItem]) {
+    for item in plan.iter_mut() {
+        if item.status != RenameStatus::Planned {
+            continue;
+        }
+        let source = PathBuf::from(&item.path);
+        let target = source
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(item.new_name.as_deref().unwrap_or_default());
+        if target.exists() && !is_same_entry(&source, &target) {
+            item.status = RenameStatus::Failed;
+            item.message = Some("Target appeared since the preview.".to_string());
+            continue;
+        }
+        match fs::rename(&source, &target) {
+            Ok(()) => item.status = RenameStatus::Renamed,
+            Err(e) => {
+                item.status = RenameStatus::Failed;
+                item.message = Some(e.to_string());
+            }
+        }
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("qre_renamer_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_strip_date_prefix() {
+        assert_eq!(
+            strip_date_prefix("2024-06-12 Lab results").as_deref(),
+            Some("Lab results")
+        );
+        assert_eq!(
+            strip_date_prefix("20240612_083012_beach").as_deref(),
+            Some("beach")
+        );
+        assert_eq!(
+            strip_date_prefix("IMG_20240612_083012").as_deref(),
+            Some("")
+        );
+        assert_eq!(
+            strip_date_prefix("Screenshot 2024-06-12 at 08.30.12 invoice").as_deref(),
+            Some("invoice")
+        );
+        assert_eq!(strip_date_prefix("12345678_notes"), None, "Not a date");
+        assert_eq!(strip_date_prefix("notes 2024-06-12"), None, "Only prefixes");
+    }
+
+    #[test]
+    fn test_sequential_plan_pads_and_keeps_extensions() {
+        let dir = temp_dir("sequential");
+        let paths: Vec<PathBuf> = (0..10)
+            .map(|i| {
+                let p = dir.join(format!("photo{}.JPG", i));
+                fs::write(&p, b"x").unwrap();
+                p
+            })
+            .collect();
+        let pattern = RenamePattern::Sequential {
+            prefix: "trip_".into(),
+            start: 1,
+            padding: 0,
+        };
+        let plan = plan_renames(&paths, &pattern, CollisionPolicy::Suffix);
+        assert_eq!(plan[0].new_name.as_deref(), Some("trip_01.JPG"));
+        assert_eq!(plan[9].new_name.as_deref(), Some("trip_10.JPG"));
+        assert!(plan.iter().all(|i| i.status == RenameStatus::Planned));
+        // Dry run: nothing moved.
+        assert!(paths.iter().all(|p| p.exists()));
+    }
+
+    #[test]
+    fn test_collisions_never_overwrite() {
+        let dir = temp_dir("collisions");
+        let a = dir.join("2024-01-01 report.txt");
+        let b = dir.join("2024-02-01 report.txt");
+        let existing = dir.join("report.txt");
+        for p in [&a, &b, &existing] {
+            fs::write(p, p.to_string_lossy().as_bytes()).unwrap();
+        }
+        let paths = vec![a.clone(), b.clone()];
+
+        let skipped = plan_renames(
+            &paths,
+            &RenamePattern::StripDatePrefix,
+            CollisionPolicy::Skip,
+        );
+        assert!(skipped.iter().all(|i| i.status == RenameStatus::Skipped));
+
+        let mut plan = plan_renames(
+            &paths,
+            &RenamePattern::StripDatePrefix,
+            CollisionPolicy::Suffix,
+        );
+        assert_eq!(plan[0].new_name.as_deref(), Some("report (1).txt"));
+        assert_eq!(plan[1].new_name.as_deref(), Some("report (2).txt"));
+        apply_plan(&mut plan);
+        assert!(plan.iter().all(|i| i.status == RenameStatus::Renamed));
+        assert_eq!(
+            fs::read(&existing).unwrap(),
+            existing.to_string_lossy().as_bytes()
+        );
+        assert_eq!(
+            fs::read(dir.join("report (2).txt")).unwrap(),
+            b.to_string_lossy().as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_exif_date_skips_files_without_exif() {
+        let dir = temp_dir("exif");
+        let p = dir.join("scan.jpg");
+        fs::write(&p, [0xFFu8, 0xD8, 0xFF, 0xD9]).unwrap();
+        let plan = plan_renames(&[p], &RenamePattern::ExifDate, CollisionPolicy::Suffix);
+        assert_eq!(plan[0].status, RenameStatus::Skipped);
+        assert!(plan[0].new_name.is_none());
+    }
+}
+
+// --- END OF FILE renamer.rs ---
@@ -0,0 +1,188 @@
+// --- START OF FILE resources.rs ---
+
+// ==========================================
+// --- RESOURCE GOVERNOR ---
+// ==========================================
+// On a 4 GB Android phone, 1 MB chunks compressed at zstd level 19 can get the app
+// killed: a level-19 compressor alone needs tens of megabytes on top of the chunk
+// buffers, and the OS reclaims memory from the foreground app first. Operations ask
+// this module for their limits when they start instead of hard-coding them:
+//
+//   crypto_stream    chunk size and highest compression level (a smaller chunk size is
+//                    recorded in the header, so the file reads back anywhere)
+//   hasher           read buffer size
+//   system_cleaner   worker threads for the folder scans
+//
+// LOW RESOURCE MODE
+// `Auto` (the default) switches to the low limits whenever available memory is below
+// `LOW_MEMORY_THRESHOLD` as an operation starts; `On` and `Off` force the choice. The
+// setting lives in memory; the frontend stores it with the other settings and pushes
+// it with `set_resource_mode` on start-up, like the power policy (see power.rs).
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Below this much available memory, `Auto` uses the low limits.
+pub const LOW_MEMORY_THRESHOLD: u64 = 1536 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceMode {
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// What an operation may use.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub low_resource: bool,
+    /// Plaintext bytes per container chunk.
+    pub chunk_size: usize,
+    /// zstd levels above this are lowered to it.
+    pub max_compression_level: i32,
+    /// Worker threads for parallel scans.
+    pub threads: usize,
+    pub read_buffer_bytes: usize,
+}
+
+impl Limits {
+    pub fn for_mode(low_resource: bool) -> Self {
+        if low_resource {
+            Self {
+                low_resource,
+                chunk_size: 256 * 1024,
+                // Level 3 is the "auto" default; higher levels grow the compressor's
+                // window and match tables several times over.
+                max_compression_level: 3,
+                threads: 2,
+                read_buffer_bytes: 64 * 1024,
+            }
+        } else {
+            Self {
+                low_resource,
+                chunk_size: crate::crypto_stream::CHUNK_SIZE,
+                max_compression_level: 22,
+                threads: std::thread::available_parallelism().map_or(4, |n| n.get()),
+                read_buffer_bytes: 1024 * 1024,
+            }
+        }
+    }
+
+    pub fn compression_level(&self, requested: i32) -> i32 {
+        requested.min(self.max_compression_level)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ResourceStatus {
+    pub mode: ResourceMode,
+    /// `None` where the platform does not report it.
+    pub available_memory_bytes: Option<u64>,
+    pub total_memory_bytes: Option<u64>,
+    /// The limits an operation starting now would get.
+    pub limits: Limits,
+}
+
+static MODE: Mutex<ResourceMode> = Mutex::new(ResourceMode::Auto);
+
+pub fn mode() -> ResourceMode {
+    MODE.lock().map(|m| *m).unwrap_or_default()
+}
+
+pub fn set_mode(mode: ResourceMode) -> Result<(), String> {
+    *MODE.lock().map_err(|e| e.to_string())? = mode;
+    Ok(())
+}
+
+/// Whether the low limits apply. Unknown memory counts as plenty.
+pub fn is_low(mode: ResourceMode, available_memory: Option<u64>) -> bool {
+    match mode {
+        ResourceMode::On => true,
+        ResourceMode::Off => false,
+        ResourceMode::Auto => available_memory.is_some_and(|m| m < LOW_MEMORY_THRESHOLD),
+    }
+}
+
+/// Limits for an operation starting now.
+pub fn limits() -> Limits {
+    let mode = mode();
+    let available = match mode {
+        ResourceMode::Auto => read_memory().0,
+        _ => None,
+    };
+    Limits::for_mode(is_low(mode, available))
+}
+
+pub fn status() -> ResourceStatus {
+    let mode = mode();
+    let (available, total) = read_memory();
+    ResourceStatus {
+        mode,
+        available_memory_bytes: available,
+        total_memory_bytes: total,
+        limits: Limits::for_mode(is_low(mode, available)),
+    }
+}
+
+/// Runs `job` on a thread pool no bigger than the limits allow. Outside low resource
+/// mode it uses the global pool.
+pub fn run_parallel<T: Send>(job: impl FnOnce() -> T + Send) -> T {
+    let limits = limits();
+    if !limits.low_resource {
+        return job();
+    }
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(limits.threads)
+        .build()
+    {
+        Ok(pool) => pool.install(job),
+        Err(_) => job(),
+    }
+}
+
+/// (available, total) memory in bytes. sysinfo reports 0 where it cannot tell.
+fn read_memory() -> (Option<u64>, Option<u64>) {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_memory();
+    let known = |bytes: u64| (bytes > 0).then_some(bytes);
+    (known(sys.available_memory()), known(sys.total_memory()))
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_decides_low_limits() {
+        let plenty = Some(8 * 1024 * 1024 * 1024);
+        let scarce = Some(900 * 1024 * 1024);
+        assert!(!is_low(ResourceMode::Auto, plenty));
+        assert!(is_low(ResourceMode::Auto, scarce));
+        assert!(!is_low(ResourceMode::Auto, None));
+        assert!(is_low(ResourceMode::On, plenty));
+        assert!(!is_low(ResourceMode::Off, scarce));
+    }
+
+    #[test]
+    fn test_low_limits_are_smaller() {
+        let (low, normal) = (Limits::for_mode(true), Limits::for_mode(false));
+        assert!(low.chunk_size < normal.chunk_size);
+        assert!(low.read_buffer_bytes < normal.read_buffer_bytes);
+        assert!(low.threads <= 2 && normal.threads >= 1);
+        assert_eq!(low.compression_level(19), 3);
+        assert_eq!(low.compression_level(1), 1);
+        assert_eq!(normal.compression_level(19), 19);
+        assert_eq!(
+            serde_json::from_str::<ResourceMode>("\"on\"").unwrap(),
+            ResourceMode::On
+        );
+    }
+}
+
+// --- END OF FILE resources.rs ---
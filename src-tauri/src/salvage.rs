@@ -0,0 +1,616 @@
+// --- START OF FILE salvage.rs ---
+
+// ==========================================
+// --- PARTIAL RECOVERY ---
+// ==========================================
+// A normal unlock stops at the first chunk that fails authentication and deletes what it
+// wrote, so one flipped bit costs the whole file. `salvage` is the opt-in alternative for
+// V5–V8 files: it unlocks the file exactly like an unlock does (time-lock, expiry and the
+// failed-attempt limit all apply, see `crypto_stream::unlock_stream_cipher`), then writes
+// every chunk that still authenticates and keeps going past the ones that do not.
+//
+// Damaged chunks keep their place: the writer fills every chunk but the last, so a damaged
+// chunk is replaced by the header's chunk size in zero bytes and everything after it stays
+// at its original offset (what most video and archive tools need to read past the hole). A
+// damaged last chunk is zero-filled to the size the V8 trailer records, or left out when
+// that is unknown. The chunk lengths cannot be resynchronised: after an impossible length
+// prefix or the end of a truncated file nothing more can be located, and the report says so.
+//
+// Containers with parity are read through it first (see parity.rs), so only the blocks
+// the parity could not rebuild are lost.
+//
+// The output is named "<name> (partial).<ext>" with a "<...>.gaps.txt" report beside it.
+// Only when nothing was damaged or lost and the whole-file hash matches does it get the
+// original name, like a normal unlock.
+
+use crate::crypto_stream::{
+    self, PaddingRecord, StreamHeader, Trailer, CHUNK_SIZE, GCM_TAG_LEN, PADDING_MARKER,
+    TRAILER_MARKER, TRAILER_RECORD_LEN, VERSION_V8,
+};
+use crate::diagnosis::read_full;
+use crate::keychain::MasterKey;
+use crate::parity::BodyReader;
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GapKind {
+    /// The chunks failed authentication; their bytes are zeros in the output.
+    ZeroFilled,
+    /// The last chunk failed and its size is unknown, so nothing was written for it.
+    Omitted,
+    /// Nothing from here on could be located: the file is truncated or its framing broke.
+    Lost,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    pub kind: GapKind,
+    /// First chunk affected.
+    pub first_chunk: u64,
+    /// Chunks affected; 0 for `Lost`, where the number is unknown.
+    pub chunks: u64,
+    /// Offset in the recovered file where the gap starts.
+    pub offset: u64,
+    /// Zero bytes written for the gap; 0 unless `ZeroFilled`.
+    pub length: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SalvageReport {
+    pub output_path: String,
+    /// The gap report written beside a partial output.
+    pub report_path: Option<String>,
+    /// Every chunk was recovered and the data matches the whole-file hash.
+    pub complete: bool,
+    /// Chunks located in the file.
+    pub chunks: u64,
+    pub chunks_recovered: u64,
+    /// Size of the output, zero fill included.
+    pub bytes_written: u64,
+    /// Size of the original file, when the V8 trailer could be read.
+    pub original_size: Option<u64>,
+    /// The output matches the whole-file hash. `None` when it could not match (gaps).
+    pub hash_ok: Option<bool>,
+    pub gaps: Vec<Gap>,
+    /// One or two sentences for the user.
+    pub summary: String,
+}
+
+/// What the chunk walk found, before the output is named.
+#[derive(Default)]
+struct Walk {
+    chunks: u64,
+    recovered: u64,
+    written: u64,
+    gaps: Vec<Gap>,
+    /// The authenticated V8 trailer, when it opened.
+    trailer: Option<Trailer>,
+    /// Why the walk stopped early, if it did.
+    lost: Option<String>,
+}
+
+impl Walk {
+    fn zero_fill<W: Write>(&mut self, out: &mut W, chunk: u64, length: u64) -> Result<()> {
+        std::io::copy(&mut std::io::repeat(0).take(length), out)?;
+        match self.gaps.last_mut() {
+            Some(gap)
+                if gap.kind == GapKind::ZeroFilled && gap.first_chunk + gap.chunks == chunk =>
+            {
+                gap.chunks += 1;
+                gap.length += length;
+            }
+            _ => self.gaps.push(Gap {
+                kind: GapKind::ZeroFilled,
+                first_chunk: chunk,
+                chunks: 1,
+                offset: self.written,
+                length,
+            }),
+        }
+        self.written += length;
+        Ok(())
+    }
+
+    fn gap(&mut self, kind: GapKind, chunk: u64, chunks: u64) {
+        self.gaps.push(Gap {
+            kind,
+            first_chunk: chunk,
+            chunks,
+            offset: self.written,
+            length: 0,
+        });
+    }
+}
+
+/// Recovers what can be read from the V5–V8 file at `input_path` into `output_dir`.
+/// Fails like an unlock when the file cannot be opened with the key, and when not a
+/// single chunk could be recovered; otherwise the report lists what is missing.
+pub fn salvage(
+    input_path: &str,
+    output_dir: &str,
+    master_key: &MasterKey,
+    keyfile_bytes: Option<&[u8]>,
+    callback: impl Fn(u64, u64),
+) -> Result<SalvageReport> {
+    let file_size = fs::metadata(input_path)?.len();
+    let mut input = BufReader::new(File::open(input_path).context("Failed to open file")?);
+    let mut ver_buf = [0u8; 4];
+    input
+        .read_exact(&mut ver_buf)
+        .context("The file is too short to be a QRE container")?;
+    let version = u32::from_le_bytes(ver_buf);
+    if !(5..=VERSION_V8).contains(&version) {
+        return Err(anyhow!(
+            "Partial recovery works on single-file containers (format 5 to {}), not format {}.",
+            VERSION_V8,
+            version
+        ));
+    }
+    let header = crypto_stream::parse_stream_header(version, &mut input)
+        .context("The header is damaged, so no chunk can be located")?;
+    let (cipher, input) = crypto_stream::unlock_stream_cipher(
+        input_path,
+        version,
+        &header,
+        master_key,
+        keyfile_bytes,
+        None,
+        input,
+    )?;
+    let mut input = BodyReader::new(input, &header, false)?;
+
+    let name = Path::new(&header.original_filename);
+    let partial_name = match name.extension() {
+        Some(ext) => format!(
+            "{} (partial).{}",
+            name.file_stem().unwrap_or_default().to_string_lossy(),
+            ext.to_string_lossy()
+        ),
+        None => format!("{} (partial)", header.original_filename),
+    };
+    let partial_path = crate::utils::get_unique_path(&Path::new(output_dir).join(partial_name));
+    let mut output = BufWriter::new(File::create(&partial_path)?);
+    let mut hasher = Sha256::new();
+
+    let walked = walk(
+        version,
+        &header,
+        &cipher,
+        &mut input,
+        &mut output,
+        &mut hasher,
+        |processed| callback(processed, file_size),
+    )
+    .and_then(|walk| {
+        output.flush()?;
+        output.get_ref().sync_all()?;
+        Ok(walk)
+    });
+    drop(output);
+    let walk = match walked {
+        Ok(walk) if walk.recovered > 0 => walk,
+        Ok(_) => {
+            let _ = fs::remove_file(&partial_path);
+            return Err(anyhow!(
+                "Nothing could be recovered: not a single chunk of this file is readable."
+            ));
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial_path);
+            return Err(e);
+        }
+    };
+
+    // V8 keeps the authenticated whole-file hash in its trailer; the header's copy is
+    // only trusted for V5–V7.
+    let expected = if version >= VERSION_V8 {
+        walk.trailer.map(|trailer| trailer.hash.to_vec())
+    } else {
+        header.original_hash.clone()
+    };
+    let hash_ok = match (&expected, walk.gaps.is_empty()) {
+        (Some(expected), true) => Some(crypto_stream::constant_time_eq(
+            &hasher.finalize(),
+            expected,
+        )),
+        _ => None,
+    };
+    let complete = walk.gaps.is_empty() && hash_ok != Some(false);
+    let summary = summarize(&walk, hash_ok);
+
+    let (output_path, report_path) = if complete {
+        let final_path =
+            crate::utils::get_unique_path(&Path::new(output_dir).join(&header.original_filename));
+        fs::rename(&partial_path, &final_path)?;
+        (final_path, None)
+    } else {
+        let report_path = write_gap_report(&partial_path, &header, &walk, &summary)?;
+        (
+            partial_path,
+            Some(report_path.to_string_lossy().to_string()),
+        )
+    };
+
+    Ok(SalvageReport {
+        output_path: output_path.to_string_lossy().to_string(),
+        report_path,
+        complete,
+        chunks: walk.chunks,
+        chunks_recovered: walk.recovered,
+        bytes_written: walk.written,
+        original_size: walk.trailer.map(|trailer| trailer.plaintext_len),
+        hash_ok,
+        gaps: walk.gaps,
+        summary,
+    })
+}
+
+/// Writes every chunk that opens, zero fill for the ones that do not, and records the gaps.
+/// Errors only on I/O failures; damage in the file ends up in the returned `Walk`.
+fn walk<R: Read, W: Write>(
+    version: u32,
+    header: &StreamHeader,
+    cipher: &Aes256Gcm,
+    input: &mut R,
+    output: &mut W,
+    hasher: &mut Sha256,
+    progress: impl Fn(u64),
+) -> Result<Walk> {
+    let requires_trailer = version >= VERSION_V8;
+    let mut w = Walk::default();
+    let mut processed = 0u64;
+    let mut padding: Option<PaddingRecord> = None;
+    // A damaged chunk is only known to be full size once another chunk follows it.
+    let mut pending_damaged: Option<u64> = None;
+    let mut trailer_seen = false;
+
+    loop {
+        let index = w.chunks;
+        let mut marker_buf = [0u8; 4];
+        match read_full(input, &mut marker_buf)? {
+            0 => break,
+            4 => {}
+            _ => {
+                w.lost = Some(format!(
+                    "The file ends inside the length of chunk {}.",
+                    index
+                ));
+                break;
+            }
+        }
+        let marker = u32::from_le_bytes(marker_buf);
+
+        if requires_trailer && marker == PADDING_MARKER && header.padding.is_some() {
+            match crypto_stream::read_padding(input) {
+                Ok(record) => padding = Some(record),
+                Err(_) => {
+                    w.lost = Some("The file ends inside its size padding.".into());
+                    break;
+                }
+            }
+            continue;
+        }
+        if requires_trailer && marker == TRAILER_MARKER {
+            trailer_seen = true;
+            let mut record = [0u8; TRAILER_RECORD_LEN];
+            if read_full(input, &mut record)? == TRAILER_RECORD_LEN {
+                // A damaged trailer only costs the size of a damaged last chunk.
+                w.trailer = crypto_stream::open_trailer(
+                    &record,
+                    cipher,
+                    header,
+                    w.chunks,
+                    padding.as_ref(),
+                )
+                .ok();
+            }
+            break;
+        }
+
+        let len = marker as usize;
+        if !(GCM_TAG_LEN..=CHUNK_SIZE + 4096).contains(&len) {
+            w.lost = Some(format!(
+                "Chunk {} has an impossible length ({} bytes); nothing after it can be located.",
+                index, len
+            ));
+            break;
+        }
+        let mut ciphertext = vec![0u8; len];
+        if read_full(input, &mut ciphertext)? != len {
+            w.lost = Some(format!("The file ends inside chunk {}.", index));
+            break;
+        }
+        if let Some(damaged) = pending_damaged.take() {
+            w.zero_fill(output, damaged, header.chunk_size() as u64)?;
+        }
+        w.chunks += 1;
+
+        let aad = format!("{}:{}", header.original_filename, index);
+        let opened = cipher
+            .decrypt(
+                Nonce::from_slice(&crypto_stream::chunk_nonce(&header.base_nonce, index)),
+                Payload {
+                    msg: &ciphertext,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .ok()
+            .and_then(|compressed| crypto_stream::decompress_chunk(&compressed).ok());
+        match opened {
+            Some(plaintext) => {
+                output.write_all(&plaintext)?;
+                hasher.update(&plaintext);
+                w.written += plaintext.len() as u64;
+                w.recovered += 1;
+            }
+            None => pending_damaged = Some(index),
+        }
+
+        processed += 4 + len as u64;
+        if w.chunks.is_multiple_of(5) {
+            progress(processed);
+        }
+    }
+
+    if let Some(damaged) = pending_damaged {
+        // The last chunk located: the trailer, when it opened, says how big it was.
+        match w.trailer {
+            Some(Trailer { plaintext_len, .. })
+                if (w.written..=w.written + header.chunk_size() as u64)
+                    .contains(&plaintext_len) =>
+            {
+                w.zero_fill(output, damaged, plaintext_len - w.written)?;
+            }
+            _ => w.gap(GapKind::Omitted, damaged, 1),
+        }
+    }
+    if w.lost.is_none() && requires_trailer && !trailer_seen {
+        w.lost = Some(format!(
+            "The file ends after chunk {} without its trailer.",
+            w.chunks
+        ));
+    }
+    if w.lost.is_some() {
+        let next = w.chunks;
+        w.gap(GapKind::Lost, next, 0);
+    }
+    Ok(w)
+}
+
+fn summarize(w: &Walk, hash_ok: Option<bool>) -> String {
+    if w.gaps.is_empty() {
+        return match hash_ok {
+            Some(false) => format!(
+                "All {} chunks were recovered, but the data does not match the stored hash: \
+                 chunks are probably missing from the end.",
+                w.chunks
+            ),
+            _ => "Every chunk was recovered and the file is complete.".to_string(),
+        };
+    }
+    let total = w.trailer.map_or(w.chunks, |trailer| trailer.chunks);
+    let mut summary = format!(
+        "Recovered {} of {} chunks; the output is incomplete.",
+        w.recovered, total
+    );
+    let zeroed: u64 = w
+        .gaps
+        .iter()
+        .filter(|g| g.kind == GapKind::ZeroFilled)
+        .map(|g| g.chunks)
+        .sum();
+    if zeroed > 0 {
+        summary.push_str(&format!(
+            " {} damaged chunk(s) were replaced with zeros.",
+            zeroed
+        ));
+    }
+    if w.gaps.iter().any(|g| g.kind == GapKind::Omitted) {
+        summary.push_str(" The damaged last chunk was left out.");
+    }
+    if let Some(lost) = &w.lost {
+        summary.push(' ');
+        summary.push_str(lost);
+    }
+    summary
+}
+
+/// Writes "<output>.gaps.txt" with one line per gap, so the report stays with the file.
+fn write_gap_report(
+    output: &Path,
+    header: &StreamHeader,
+    w: &Walk,
+    summary: &str,
+) -> Result<PathBuf> {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".gaps.txt");
+    let path = crate::utils::get_unique_path(&output.with_file_name(name));
+
+    let mut text = format!(
+        "PARTIAL RECOVERY of {}\n\n{}\n\nChunks are {} bytes; offsets are in the recovered file.\n\n",
+        header.original_filename,
+        summary,
+        header.chunk_size()
+    );
+    for gap in &w.gaps {
+        let line = match gap.kind {
+            GapKind::ZeroFilled => format!(
+                "Chunks {}-{}: damaged, {} zero bytes at offset {}\n",
+                gap.first_chunk,
+                gap.first_chunk + gap.chunks - 1,
+                gap.length,
+                gap.offset
+            ),
+            GapKind::Omitted => format!(
+                "Chunk {}: damaged last chunk of unknown size, left out after offset {}\n",
+                gap.first_chunk, gap.offset
+            ),
+            GapKind::Lost => format!(
+                "Chunk {} onwards: could not be located, the output ends at offset {}\n",
+                gap.first_chunk, gap.offset
+            ),
+        };
+        text.push_str(&line);
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> MasterKey {
+        MasterKey([byte; 32])
+    }
+
+    fn sample_data() -> Vec<u8> {
+        (0..CHUNK_SIZE * 5 / 2)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect()
+    }
+
+    /// Encrypts 2.5 chunks of data and returns (dir, container path).
+    fn sample_container() -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("qre_salvage_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("out")).unwrap();
+        let input = dir.join("movie.mp4");
+        fs::write(&input, sample_data()).unwrap();
+        let output = dir.join("movie.mp4.qre");
+        crypto_stream::encrypt_file_stream(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &key(1),
+            "local",
+            None,
+            None,
+            None,
+            1,
+            |_, _| {},
+        )
+        .unwrap();
+        (dir, output)
+    }
+
+    /// Offset of the length prefix of chunk `index`.
+    fn chunk_offset(bytes: &[u8], index: usize) -> usize {
+        let mut at = 4 + crypto_stream::HEADER_RESERVED_BYTES;
+        for _ in 0..index {
+            at += 4 + u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        }
+        at
+    }
+
+    fn run(dir: &Path, path: &Path) -> Result<SalvageReport> {
+        salvage(
+            path.to_str().unwrap(),
+            dir.join("out").to_str().unwrap(),
+            &key(1),
+            None,
+            |_, _| {},
+        )
+    }
+
+    #[test]
+    fn test_intact_file_is_recovered_under_its_name() {
+        let (dir, path) = sample_container();
+        let report = run(&dir, &path).unwrap();
+        assert!(report.complete, "{:?}", report);
+        assert_eq!(report.hash_ok, Some(true));
+        assert!(report.output_path.ends_with("movie.mp4"));
+        assert_eq!(fs::read(&report.output_path).unwrap(), sample_data());
+
+        assert!(salvage(
+            path.to_str().unwrap(),
+            dir.join("out").to_str().unwrap(),
+            &key(2),
+            None,
+            |_, _| {},
+        )
+        .is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_damaged_chunks_are_zero_filled_in_place() {
+        let (dir, path) = sample_container();
+        let mut bytes = fs::read(&path).unwrap();
+        for index in [1, 2] {
+            let at = chunk_offset(&bytes, index) + 100;
+            bytes[at] ^= 0x01;
+        }
+        fs::write(&path, &bytes).unwrap();
+
+        let report = run(&dir, &path).unwrap();
+        assert!(!report.complete);
+        assert_eq!(report.chunks_recovered, 1);
+        assert_eq!(report.original_size, Some(sample_data().len() as u64));
+        // Chunk 1 is full size; the last chunk takes its size from the trailer.
+        assert_eq!(
+            report.gaps,
+            [Gap {
+                kind: GapKind::ZeroFilled,
+                first_chunk: 1,
+                chunks: 2,
+                offset: CHUNK_SIZE as u64,
+                length: (CHUNK_SIZE * 3 / 2) as u64,
+            }]
+        );
+        assert!(report.output_path.ends_with("movie (partial).mp4"));
+        let out = fs::read(&report.output_path).unwrap();
+        assert_eq!(out.len(), sample_data().len());
+        assert_eq!(out[..CHUNK_SIZE], sample_data()[..CHUNK_SIZE]);
+        assert!(out[CHUNK_SIZE..].iter().all(|&b| b == 0));
+        assert!(Path::new(report.report_path.as_ref().unwrap()).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_truncation_and_broken_framing_keep_the_start() {
+        let (dir, path) = sample_container();
+        let bytes = fs::read(&path).unwrap();
+
+        fs::write(&path, &bytes[..chunk_offset(&bytes, 2) + 10]).unwrap();
+        let report = run(&dir, &path).unwrap();
+        assert_eq!(
+            (report.chunks_recovered, report.bytes_written),
+            (2, 2 * CHUNK_SIZE as u64)
+        );
+        assert_eq!(report.gaps.len(), 1);
+        assert_eq!(report.gaps[0].kind, GapKind::Lost);
+        assert_eq!(report.gaps[0].first_chunk, 2);
+
+        let mut broken = bytes.clone();
+        let at = chunk_offset(&broken, 1);
+        broken[at..at + 4].copy_from_slice(&5u32.to_le_bytes());
+        fs::write(&path, &broken).unwrap();
+        let report = run(&dir, &path).unwrap();
+        assert_eq!(report.chunks_recovered, 1);
+        assert_eq!(report.gaps[0].kind, GapKind::Lost);
+        assert!(
+            report.summary.contains("impossible length"),
+            "{}",
+            report.summary
+        );
+
+        let mut hopeless = bytes.clone();
+        let at = chunk_offset(&hopeless, 0) + 100;
+        hopeless[at] ^= 0x01;
+        hopeless.truncate(chunk_offset(&hopeless, 1));
+        fs::write(&path, &hopeless).unwrap();
+        assert!(run(&dir, &path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE salvage.rs ---
@@ -0,0 +1,330 @@
+// --- START OF FILE search.rs ---
+
+// ==========================================
+// --- VAULT SEARCH ---
+// ==========================================
+// One search for every vault panel, instead of each UI filtering its own list. The
+// `search_vaults` command decrypts the stores and hands their searchable fields to
+// `search` as `SearchDoc`s; nothing is indexed or written to disk.
+//
+// MATCHING
+// The query is lower-cased and split on whitespace. A document matches when EVERY term
+// matches at least one of its fields. Each term scores its best match:
+//
+//   whole word 100, word prefix 80, substring 60,
+//   fuzzy 30 (a word within 1 edit for terms of 4+ characters, 2 edits from 8)
+//
+// multiplied by the field's weight (title 3, labels such as tags/URL/category 2, bodies 1).
+// Hits are sorted by total score, then title.
+//
+// SECRETS
+// Passwords, TOTP secrets and hidden custom fields are never handed to this module, so
+// they cannot match or leak into a snippet. Clipboard hits show the entry's redacted
+// preview rather than an excerpt of the content.
+
+use serde::Serialize;
+
+pub const MAX_RESULTS: usize = 200;
+/// Longer queries are cut, which keeps the fuzzy comparison cheap.
+const MAX_QUERY_CHARS: usize = 200;
+const SNIPPET_CHARS: usize = 80;
+
+pub const WEIGHT_TITLE: u32 = 3;
+pub const WEIGHT_LABEL: u32 = 2;
+pub const WEIGHT_BODY: u32 = 1;
+
+const SCORE_WORD: u32 = 100;
+const SCORE_PREFIX: u32 = 80;
+const SCORE_SUBSTRING: u32 = 60;
+const SCORE_FUZZY: u32 = 30;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Note,
+    Password,
+    Bookmark,
+    Clipboard,
+}
+
+pub struct SearchField<'a> {
+    /// Reported back as `SearchHit::field` ("title", "url", "tags"...).
+    pub name: &'static str,
+    pub text: &'a str,
+    pub weight: u32,
+}
+
+pub struct SearchDoc<'a> {
+    pub kind: SearchKind,
+    pub id: &'a str,
+    pub title: &'a str,
+    pub fields: Vec<SearchField<'a>>,
+    /// Shown instead of an excerpt of the matched field when set.
+    pub preview: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub id: String,
+    pub title: String,
+    /// The field that contributed most to the score.
+    pub field: &'static str,
+    pub snippet: String,
+    pub score: u32,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Panels left out because they are PIN-protected and no fresh token was given.
+    pub locked_panels: Vec<&'static str>,
+    /// More documents matched than `MAX_RESULTS`.
+    pub truncated: bool,
+}
+
+/// Lower-cased query terms. Empty for a blank query.
+pub fn parse_query(query: &str) -> Vec<String> {
+    let query: String = query.chars().take(MAX_QUERY_CHARS).collect();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Ranks `docs` against `query`. A blank query matches nothing.
+pub fn search(query: &str, docs: &[SearchDoc]) -> SearchResults {
+    let terms = parse_query(query);
+    if terms.is_empty() {
+        return SearchResults::default();
+    }
+
+    let mut hits: Vec<SearchHit> = docs
+        .iter()
+        .filter_map(|doc| score_doc(&terms, doc))
+        .collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+    let truncated = hits.len() > MAX_RESULTS;
+    hits.truncate(MAX_RESULTS);
+    SearchResults {
+        hits,
+        locked_panels: Vec::new(),
+        truncated,
+    }
+}
+
+fn score_doc(terms: &[String], doc: &SearchDoc) -> Option<SearchHit> {
+    let lowered: Vec<String> = doc.fields.iter().map(|f| f.text.to_lowercase()).collect();
+    let mut total = 0;
+    let mut per_field = vec![0u32; doc.fields.len()];
+
+    for term in terms {
+        let best = doc
+            .fields
+            .iter()
+            .zip(&lowered)
+            .enumerate()
+            .map(|(i, (field, text))| (i, term_score(term, text) * field.weight))
+            .max_by_key(|&(_, score)| score)?;
+        if best.1 == 0 {
+            return None;
+        }
+        total += best.1;
+        per_field[best.0] += best.1;
+    }
+
+    let (best_field, _) = per_field.iter().enumerate().max_by_key(|&(_, s)| *s)?;
+    let field = &doc.fields[best_field];
+    let snippet = match doc.preview {
+        Some(preview) => preview.to_string(),
+        None => excerpt(field.text, &lowered[best_field], terms),
+    };
+    Some(SearchHit {
+        kind: doc.kind,
+        id: doc.id.to_string(),
+        title: doc.title.to_string(),
+        field: field.name,
+        snippet,
+        score: total,
+    })
+}
+
+/// Best match of one lower-cased term in one lower-cased field.
+fn term_score(term: &str, text: &str) -> u32 {
+    let mut best = 0;
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let score = if word == term {
+            SCORE_WORD
+        } else if word.starts_with(term) {
+            SCORE_PREFIX
+        } else if word.contains(term) {
+            SCORE_SUBSTRING
+        } else if is_fuzzy_match(term, word) {
+            SCORE_FUZZY
+        } else {
+            0
+        };
+        best = best.max(score);
+        if best == SCORE_WORD {
+            return best;
+        }
+    }
+    // Terms with punctuation ("example.com", "key-2") span several words.
+    if best < SCORE_SUBSTRING && text.contains(term) {
+        best = SCORE_SUBSTRING;
+    }
+    best
+}
+
+fn is_fuzzy_match(term: &str, word: &str) -> bool {
+    let term: Vec<char> = term.chars().collect();
+    let max_edits = match term.len() {
+        0..=3 => return false,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let word: Vec<char> = word.chars().collect();
+    if word.len().abs_diff(term.len()) > max_edits {
+        return false;
+    }
+    edit_distance(&term, &word) <= max_edits
+}
+
+/// Levenshtein distance, two rows at a time.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Up to `SNIPPET_CHARS` of `text` around the first term found in it (the start of the
+/// text for fuzzy-only matches), on one line.
+fn excerpt(text: &str, lowered: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let found = terms.iter().filter_map(|t| lowered.find(t.as_str())).min();
+    // Lower-casing can change the character count of a few scripts; clamp rather than
+    // trust the offset exactly.
+    let at = found.map_or(0, |byte| lowered[..byte].chars().count().min(chars.len()));
+    let start = at.saturating_sub(SNIPPET_CHARS / 4);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+
+    let mut snippet: String = chars[start..end]
+        .iter()
+        .map(|&c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc<'a>(kind: SearchKind, id: &'a str, title: &'a str, body: &'a str) -> SearchDoc<'a> {
+        SearchDoc {
+            kind,
+            id,
+            title,
+            fields: vec![
+                SearchField {
+                    name: "title",
+                    text: title,
+                    weight: WEIGHT_TITLE,
+                },
+                SearchField {
+                    name: "content",
+                    text: body,
+                    weight: WEIGHT_BODY,
+                },
+            ],
+            preview: None,
+        }
+    }
+
+    #[test]
+    fn test_matching_is_case_insensitive_and_ranked() {
+        let docs = [
+            doc(SearchKind::Note, "1", "Shopping", "buy BANKING stickers"),
+            doc(SearchKind::Note, "2", "Bank details", "IBAN and sort code"),
+            doc(SearchKind::Password, "3", "Online Banking", "login notes"),
+            doc(SearchKind::Bookmark, "4", "Recipes", "see example.com"),
+        ];
+        let ids = |q: &str| {
+            search(q, &docs)
+                .hits
+                .into_iter()
+                .map(|h| h.id)
+                .collect::<Vec<_>>()
+        };
+
+        // Title prefix beats body prefix; "bank" is a whole word only in doc 2.
+        assert_eq!(ids("BANK"), ["2", "3", "1"]);
+        // Every term has to match somewhere.
+        assert_eq!(ids("bank iban"), ["2"]);
+        // Substring inside a word, and a term spanning punctuation.
+        assert_eq!(ids("anki"), ["3", "1"]);
+        assert_eq!(ids("example.com"), ["4"]);
+        // One typo is tolerated for longer terms, none for short ones.
+        assert_eq!(ids("shoping"), ["1"]);
+        assert!(ids("bnk").is_empty());
+        assert!(ids("   ").is_empty());
+
+        let hit = &search("iban", &docs).hits[0];
+        assert_eq!((hit.kind, hit.field), (SearchKind::Note, "content"));
+        assert_eq!(hit.snippet, "IBAN and sort code");
+    }
+
+    #[test]
+    fn test_snippets_and_previews() {
+        let body = format!(
+            "{} the wifi password is on the fridge {}",
+            "x".repeat(100),
+            "y".repeat(100)
+        );
+        let docs = [doc(SearchKind::Note, "1", "House", &body)];
+        let snippet = &search("fridge", &docs).hits[0].snippet;
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("on the fridge"));
+
+        let mut clip = doc(SearchKind::Clipboard, "2", "API Key", "sk_live_51abcdef");
+        clip.preview = Some("sk_l••••••");
+        let hit = &search("sk_live", &[clip]).hits[0];
+        assert_eq!(hit.snippet, "sk_l••••••");
+
+        assert_eq!(
+            edit_distance(
+                &['k', 'i', 't', 't', 'e', 'n'],
+                &['s', 'i', 't', 't', 'i', 'n', 'g']
+            ),
+            3
+        );
+        assert_eq!(excerpt("a\nb", "a\nb", &["b".to_string()]), "a b");
+    }
+}
+
+// --- END OF FILE search.rs ---
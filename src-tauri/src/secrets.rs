@@ -0,0 +1,170 @@
+// --- START OF FILE secrets.rs ---
+
+// ==========================================
+// --- ENCRYPTED CREDENTIALS ---
+// ==========================================
+// API keys and tokens for the online features (sync, the HIBP email API, share links)
+// must not sit in plaintext settings next to the vault they are meant to protect.
+//
+// They live in their own encrypted store (`secrets.qre`, same V4 container as the other
+// vaults, wrapped by the session's master key). The frontend can list names without
+// values; reading, writing and deleting a value are separate commands, each recorded
+// in the audit log by name only.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+pub const SECRETS_FILE_NAME: &str = "secrets.qre";
+
+const MAX_NAME_LEN: usize = 64;
+/// Generous for any API key or OAuth token; keeps the store from becoming a file vault.
+const MAX_VALUE_LEN: usize = 16 * 1024;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SecretEntry {
+    pub value: String,
+    pub updated_at: i64,
+}
+
+impl Drop for SecretEntry {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// Root of `secrets.qre`, keyed by secret name.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SecretsStore {
+    secrets: BTreeMap<String, SecretEntry>,
+}
+
+/// What `list_secrets` returns: names and timestamps, never values.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SecretInfo {
+    pub name: String,
+    pub updated_at: i64,
+}
+
+// ==========================================
+// --- STORE LOGIC ---
+// ==========================================
+
+/// Names are identifiers like "hibp_api_key" or "sync.token": lowercase ASCII letters,
+/// digits, '_', '-' and '.'. They end up in the audit log, so free text is refused.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "Secret names must be 1 to {} characters long.",
+            MAX_NAME_LEN
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(format!(
+            "'{}' is not a valid secret name (use a-z, 0-9, '_', '-', '.').",
+            name
+        ));
+    }
+    Ok(())
+}
+
+impl SecretsStore {
+    pub fn list(&self) -> Vec<SecretInfo> {
+        self.secrets
+            .iter()
+            .map(|(name, entry)| SecretInfo {
+                name: name.clone(),
+                updated_at: entry.updated_at,
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.secrets.get(name).map(|e| e.value.as_str())
+    }
+
+    /// Inserts or replaces a secret. Returns true if the name was new.
+    pub fn set(&mut self, name: &str, value: &str, now: i64) -> Result<bool, String> {
+        validate_name(name)?;
+        if value.is_empty() {
+            return Err("The secret value is empty.".to_string());
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(format!(
+                "The secret value exceeds {} KB.",
+                MAX_VALUE_LEN / 1024
+            ));
+        }
+        let previous = self.secrets.insert(
+            name.to_string(),
+            SecretEntry {
+                value: value.to_string(),
+                updated_at: now,
+            },
+        );
+        Ok(previous.is_none())
+    }
+
+    /// Removes a secret. Returns true if it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.secrets.remove(name).is_some()
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get_remove() {
+        let mut store = SecretsStore::default();
+        assert!(store.set("hibp_api_key", "abc123", 10).unwrap());
+        assert!(!store.set("hibp_api_key", "def456", 20).unwrap());
+        assert_eq!(store.get("hibp_api_key"), Some("def456"));
+        assert_eq!(
+            store.list(),
+            vec![SecretInfo {
+                name: "hibp_api_key".into(),
+                updated_at: 20
+            }]
+        );
+        assert!(store.remove("hibp_api_key"));
+        assert!(!store.remove("hibp_api_key"));
+        assert!(store.get("hibp_api_key").is_none());
+    }
+
+    #[test]
+    fn test_names_and_values_are_validated() {
+        let mut store = SecretsStore::default();
+        assert!(store.set("", "v", 0).is_err());
+        assert!(store.set("Has Spaces", "v", 0).is_err());
+        assert!(store.set(&"a".repeat(MAX_NAME_LEN + 1), "v", 0).is_err());
+        assert!(store.set("sync.token", "", 0).is_err());
+        assert!(store
+            .set("sync.token", &"x".repeat(MAX_VALUE_LEN + 1), 0)
+            .is_err());
+        assert!(store.set("sync.token", "ok", 0).is_ok());
+    }
+
+    #[test]
+    fn test_listing_never_serializes_values() {
+        let mut store = SecretsStore::default();
+        store.set("share-link", "super-secret-token", 1).unwrap();
+        let listed = serde_json::to_string(&store.list()).unwrap();
+        assert!(!listed.contains("super-secret-token"));
+    }
+}
+
+// --- END OF FILE secrets.rs ---
@@ -0,0 +1,840 @@
+// --- START OF FILE secure_dns.rs ---
+
+// ==========================================
+// --- SECURE DNS (DoH / DoT) ---
+// ==========================================
+// Reports whether DNS queries leave the machine encrypted, and switches the system to a
+// chosen encrypted resolver with a way back:
+//   - Windows 11: DNS-over-HTTPS templates (`Add-DnsClientDohServerAddress`) plus the
+//     resolver's addresses on every connected adapter. Needs administrator rights.
+//   - macOS: encrypted DNS can only be set by a configuration profile. One is written and
+//     opened; the user approves it in System Settings › Privacy & Security › Profiles.
+//   - Linux: NetworkManager with systemd-resolved, which speaks DNS-over-TLS (it has no
+//     DoH client). The active connections get the resolver and `dns-over-tls=yes`.
+//
+// Before the first change the current settings are saved to `secure_dns_backup.json` in
+// the app data directory. Switching resolvers again keeps that first copy, so "revert"
+// always returns to the settings the user had before QRE touched them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const BACKUP_FILE_NAME: &str = "secure_dns_backup.json";
+
+/// A public resolver offering encrypted DNS.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolver {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// IPv4 addresses, primary first.
+    pub addresses: &'static [&'static str],
+    pub doh_template: &'static str,
+    /// Host name presented over TLS (DoT) and checked against the certificate.
+    pub tls_name: &'static str,
+}
+
+pub const RESOLVERS: &[Resolver] = &[
+    Resolver {
+        id: "cloudflare",
+        name: "Cloudflare",
+        addresses: &["1.1.1.1", "1.0.0.1"],
+        doh_template: "https://cloudflare-dns.com/dns-query",
+        tls_name: "cloudflare-dns.com",
+    },
+    Resolver {
+        id: "quad9",
+        name: "Quad9",
+        addresses: &["9.9.9.9", "149.112.112.112"],
+        doh_template: "https://dns.quad9.net/dns-query",
+        tls_name: "dns.quad9.net",
+    },
+    Resolver {
+        id: "mullvad",
+        name: "Mullvad",
+        addresses: &["194.242.2.2"],
+        doh_template: "https://dns.mullvad.net/dns-query",
+        tls_name: "dns.mullvad.net",
+    },
+    Resolver {
+        id: "google",
+        name: "Google Public DNS",
+        addresses: &["8.8.8.8", "8.8.4.4"],
+        doh_template: "https://dns.google/dns-query",
+        tls_name: "dns.google",
+    },
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    /// Windows and macOS.
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    Doh,
+    /// Linux (systemd-resolved).
+    Dot,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SecureDnsStatus {
+    /// False on platforms where QRE cannot read or change the setting.
+    pub supported: bool,
+    pub protocol: Option<Protocol>,
+    /// `None` when the platform does not expose the state (macOS profiles).
+    pub enabled: Option<bool>,
+    /// DNS servers currently in use.
+    pub servers: Vec<String>,
+    /// Name of the known resolver the servers belong to.
+    pub resolver: Option<String>,
+    /// A backup of the previous settings exists.
+    pub can_revert: bool,
+    pub details: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SecureDnsChange {
+    /// Resolver now configured; `None` after a revert.
+    pub resolver: Option<String>,
+    /// Adapters, connections or profiles that were changed.
+    pub targets: Vec<String>,
+    /// Set when the user has to finish the change in the system settings.
+    pub action_required: Option<String>,
+}
+
+/// The DNS settings of one adapter / connection before QRE changed them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct SavedSetting {
+    target: String,
+    /// Statically configured servers; empty means "automatic" (DHCP).
+    servers: Vec<String>,
+    /// Other platform properties to restore verbatim (NetworkManager).
+    #[serde(default)]
+    properties: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Backup {
+    created_at: i64,
+    settings: Vec<SavedSetting>,
+}
+
+// ==========================================
+// --- HELPERS ---
+// ==========================================
+
+pub fn find_resolver(id: &str) -> Result<&'static Resolver, String> {
+    RESOLVERS
+        .iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("Unknown resolver '{}'.", id))
+}
+
+/// The known resolver `servers` point at. Server entries may carry a port or a TLS
+/// name (`1.1.1.1#cloudflare-dns.com`, `1.1.1.1:853`).
+fn resolver_for(servers: &[String]) -> Option<&'static Resolver> {
+    servers.iter().find_map(|s| {
+        let ip = s.split(['#', '%']).next().unwrap_or_default();
+        let ip = ip.strip_suffix(":853").unwrap_or(ip);
+        RESOLVERS.iter().find(|r| r.addresses.contains(&ip))
+    })
+}
+
+/// Runs a system tool and returns its standard output, or its error output on failure.
+#[cfg_attr(
+    not(any(target_os = "windows", target_os = "macos", target_os = "linux")),
+    allow(dead_code)
+)]
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if stderr.is_empty() {
+            format!("{} exited with {}", program, output.status)
+        } else {
+            stderr
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn backup_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(BACKUP_FILE_NAME)
+}
+
+fn load_backup(data_dir: &Path) -> Option<Backup> {
+    let json = std::fs::read_to_string(backup_path(data_dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn save_backup(data_dir: &Path, backup: &Backup) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(backup).map_err(|e| e.to_string())?;
+    std::fs::write(backup_path(data_dir), json).map_err(|e| e.to_string())
+}
+
+// ==========================================
+// --- PUBLIC API ---
+// ==========================================
+
+/// Reads the current secure-DNS state. Never fails; problems end up in `details`.
+pub fn status(data_dir: &Path) -> SecureDnsStatus {
+    let mut status = platform::status();
+    status.resolver = resolver_for(&status.servers).map(|r| r.name.to_string());
+    status.can_revert = backup_path(data_dir).exists();
+    status
+}
+
+/// Points the system at `resolver_id` with encryption on. The settings found before the
+/// first change are saved for `revert`.
+pub fn configure(resolver_id: &str, data_dir: &Path) -> Result<SecureDnsChange, String> {
+    let resolver = find_resolver(resolver_id)?;
+    let backup = match load_backup(data_dir) {
+        Some(existing) => existing,
+        None => {
+            let backup = Backup {
+                created_at: chrono::Utc::now().timestamp(),
+                settings: platform::current_settings()?,
+            };
+            save_backup(data_dir, &backup)?;
+            backup
+        }
+    };
+    let targets: Vec<String> = backup.settings.iter().map(|s| s.target.clone()).collect();
+    let action_required = platform::apply(resolver, &targets, data_dir)?;
+    Ok(SecureDnsChange {
+        resolver: Some(resolver.name.to_string()),
+        targets,
+        action_required,
+    })
+}
+
+/// Restores the settings saved by the first `configure` and forgets the backup.
+pub fn revert(data_dir: &Path) -> Result<SecureDnsChange, String> {
+    let backup = load_backup(data_dir)
+        .ok_or_else(|| "There are no saved DNS settings to restore.".to_string())?;
+    let action_required = platform::restore(&backup.settings, data_dir)?;
+    std::fs::remove_file(backup_path(data_dir)).map_err(|e| e.to_string())?;
+    Ok(SecureDnsChange {
+        resolver: None,
+        targets: backup.settings.into_iter().map(|s| s.target).collect(),
+        action_required,
+    })
+}
+
+// ==========================================
+// --- WINDOWS ---
+// ==========================================
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{run, Protocol, Resolver, SavedSetting, SecureDnsStatus};
+    use std::path::Path;
+
+    fn powershell(script: &str) -> Result<String, String> {
+        run(
+            "powershell",
+            &["-NoProfile", "-NonInteractive", "-Command", script],
+        )
+    }
+
+    /// Single-quoted PowerShell literal.
+    fn quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    /// Parses `name|a,b` lines.
+    fn parse_pairs(output: &str) -> Vec<(String, Vec<String>)> {
+        output
+            .lines()
+            .filter_map(|l| l.trim().split_once('|'))
+            .map(|(name, list)| {
+                let values = list
+                    .split([',', ' '])
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                (name.to_string(), values)
+            })
+            .collect()
+    }
+
+    pub fn status() -> SecureDnsStatus {
+        let mut status = SecureDnsStatus {
+            supported: true,
+            protocol: Some(Protocol::Doh),
+            ..Default::default()
+        };
+        let servers = powershell(
+            "Get-DnsClientServerAddress -AddressFamily IPv4 | Where-Object { $_.ServerAddresses } | \
+             ForEach-Object { \"$($_.InterfaceAlias)|$($_.ServerAddresses -join ',')\" }",
+        );
+        let doh = powershell(
+            "Get-DnsClientDohServerAddress | Where-Object { $_.AutoUpgrade } | \
+             ForEach-Object { \"$($_.ServerAddress)|$($_.DohTemplate)\" }",
+        );
+        match (servers, doh) {
+            (Ok(servers), Ok(doh)) => {
+                for (_, list) in parse_pairs(&servers) {
+                    for server in list {
+                        if !status.servers.contains(&server) {
+                            status.servers.push(server);
+                        }
+                    }
+                }
+                let encrypted: Vec<String> =
+                    parse_pairs(&doh).into_iter().map(|(ip, _)| ip).collect();
+                let plain: Vec<&String> = status
+                    .servers
+                    .iter()
+                    .filter(|s| !encrypted.contains(s))
+                    .collect();
+                status.enabled = Some(!status.servers.is_empty() && plain.is_empty());
+                status.details = if plain.is_empty() {
+                    "All DNS servers in use have an automatic DNS-over-HTTPS template.".into()
+                } else {
+                    format!(
+                        "Queries to {} are sent unencrypted.",
+                        plain
+                            .iter()
+                            .map(|s| s.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                };
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                status.details = format!(
+                    "Could not read the DNS client settings (Windows 11 is required): {}",
+                    e
+                );
+            }
+        }
+        status
+    }
+
+    /// Connected adapters and their static servers (read from the registry, so servers
+    /// handed out by DHCP are not mistaken for a manual setting).
+    pub fn current_settings() -> Result<Vec<SavedSetting>, String> {
+        let output = powershell(
+            "Get-NetAdapter | Where-Object { $_.Status -eq 'Up' } | ForEach-Object { \
+             $key = \"HKLM:\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\$($_.InterfaceGuid)\"; \
+             \"$($_.Name)|$((Get-ItemProperty -Path $key -ErrorAction SilentlyContinue).NameServer)\" }",
+        )?;
+        let settings: Vec<SavedSetting> = parse_pairs(&output)
+            .into_iter()
+            .map(|(target, servers)| SavedSetting {
+                target,
+                servers,
+                ..Default::default()
+            })
+            .collect();
+        if settings.is_empty() {
+            return Err("No connected network adapter found.".to_string());
+        }
+        Ok(settings)
+    }
+
+    pub fn apply(
+        resolver: &Resolver,
+        targets: &[String],
+        _data_dir: &Path,
+    ) -> Result<Option<String>, String> {
+        let mut script = String::new();
+        for ip in resolver.addresses {
+            let args = format!(
+                "-ServerAddress {} -DohTemplate {} -AllowFallbackToUdp $False -AutoUpgrade $True",
+                quote(ip),
+                quote(resolver.doh_template)
+            );
+            script.push_str(&format!(
+                "if (Get-DnsClientDohServerAddress -ServerAddress {ip} -ErrorAction SilentlyContinue) \
+                 {{ Set-DnsClientDohServerAddress {args} }} else {{ Add-DnsClientDohServerAddress {args} }}; ",
+                ip = quote(ip),
+                args = args
+            ));
+        }
+        let addresses: Vec<String> = resolver.addresses.iter().map(|a| quote(a)).collect();
+        for target in targets {
+            script.push_str(&format!(
+                "Set-DnsClientServerAddress -InterfaceAlias {} -ServerAddresses ({}); ",
+                quote(target),
+                addresses.join(",")
+            ));
+        }
+        script.push_str("Clear-DnsClientCache");
+        powershell(&script)
+            .map_err(|e| format!("Changing DNS settings requires administrator rights: {}", e))?;
+        Ok(None)
+    }
+
+    /// DoH templates are left registered: Windows ships them for these resolvers anyway
+    /// and they only apply while the resolver is in use.
+    pub fn restore(settings: &[SavedSetting], _data_dir: &Path) -> Result<Option<String>, String> {
+        let mut script = String::new();
+        for setting in settings {
+            if setting.servers.is_empty() {
+                script.push_str(&format!(
+                    "Set-DnsClientServerAddress -InterfaceAlias {} -ResetServerAddresses; ",
+                    quote(&setting.target)
+                ));
+            } else {
+                let servers: Vec<String> = setting.servers.iter().map(|s| quote(s)).collect();
+                script.push_str(&format!(
+                    "Set-DnsClientServerAddress -InterfaceAlias {} -ServerAddresses ({}); ",
+                    quote(&setting.target),
+                    servers.join(",")
+                ));
+            }
+        }
+        script.push_str("Clear-DnsClientCache");
+        powershell(&script)
+            .map_err(|e| format!("Changing DNS settings requires administrator rights: {}", e))?;
+        Ok(None)
+    }
+}
+
+// ==========================================
+// --- MACOS ---
+// ==========================================
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{run, Protocol, Resolver, SavedSetting, SecureDnsStatus};
+    use std::path::Path;
+
+    const PROFILE_FILE_NAME: &str = "QRE Secure DNS.mobileconfig";
+    const PROFILE_IDENTIFIER: &str = "com.qre.privacy-toolkit.secure-dns";
+    const PROFILES_PANE: &str =
+        "x-apple.systempreferences:com.apple.preferences.configurationprofiles";
+
+    /// `nameserver[0] : 1.1.1.1` lines of `scutil --dns`, de-duplicated.
+    fn parse_scutil(output: &str) -> Vec<String> {
+        let mut servers: Vec<String> = Vec::new();
+        for line in output.lines() {
+            let line = line.trim();
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim().to_string();
+                if key.trim().starts_with("nameserver[") && !servers.contains(&value) {
+                    servers.push(value);
+                }
+            }
+        }
+        servers
+    }
+
+    fn profile(resolver: &Resolver) -> String {
+        let addresses: String = resolver
+            .addresses
+            .iter()
+            .map(|a| format!("<string>{}</string>", a))
+            .collect();
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>PayloadContent</key>
+  <array>
+    <dict>
+      <key>DNSSettings</key>
+      <dict>
+        <key>DNSProtocol</key><string>HTTPS</string>
+        <key>ServerURL</key><string>{url}</string>
+        <key>ServerAddresses</key><array>{addresses}</array>
+      </dict>
+      <key>PayloadDisplayName</key><string>{name} (DNS over HTTPS)</string>
+      <key>PayloadIdentifier</key><string>{id}.dns</string>
+      <key>PayloadType</key><string>com.apple.dnsSettings.managed</string>
+      <key>PayloadUUID</key><string>{uuid}</string>
+      <key>PayloadVersion</key><integer>1</integer>
+    </dict>
+  </array>
+  <key>PayloadDisplayName</key><string>QRE Secure DNS</string>
+  <key>PayloadIdentifier</key><string>{id}</string>
+  <key>PayloadRemovalDisallowed</key><false/>
+  <key>PayloadType</key><string>Configuration</string>
+  <key>PayloadUUID</key><string>{profile_uuid}</string>
+  <key>PayloadVersion</key><integer>1</integer>
+</dict>
+</plist>
+"#,
+            url = resolver.doh_template,
+            addresses = addresses,
+            name = resolver.name,
+            id = PROFILE_IDENTIFIER,
+            uuid = uuid::Uuid::new_v4().to_string().to_uppercase(),
+            profile_uuid = uuid::Uuid::new_v4().to_string().to_uppercase(),
+        )
+    }
+
+    pub fn status() -> SecureDnsStatus {
+        let mut status = SecureDnsStatus {
+            supported: true,
+            protocol: Some(Protocol::Doh),
+            ..Default::default()
+        };
+        match run("scutil", &["--dns"]) {
+            Ok(output) => status.servers = parse_scutil(&output),
+            Err(e) => status.details = format!("Could not read the DNS configuration: {}", e),
+        }
+        if status.details.is_empty() {
+            status.details = "macOS does not report encrypted-DNS profiles to other apps; \
+                              check System Settings › Privacy & Security › Profiles."
+                .into();
+        }
+        status
+    }
+
+    /// Nothing to read back: the profile is an addition, removing it restores the rest.
+    pub fn current_settings() -> Result<Vec<SavedSetting>, String> {
+        Ok(vec![SavedSetting {
+            target: "QRE Secure DNS profile".to_string(),
+            ..Default::default()
+        }])
+    }
+
+    pub fn apply(
+        resolver: &Resolver,
+        _targets: &[String],
+        data_dir: &Path,
+    ) -> Result<Option<String>, String> {
+        let path = data_dir.join(PROFILE_FILE_NAME);
+        std::fs::write(&path, profile(resolver)).map_err(|e| e.to_string())?;
+        run("open", &[&path.to_string_lossy()])?;
+        Ok(Some(
+            "Open System Settings › Privacy & Security › Profiles and install \"QRE Secure DNS\"."
+                .to_string(),
+        ))
+    }
+
+    pub fn restore(_settings: &[SavedSetting], data_dir: &Path) -> Result<Option<String>, String> {
+        let _ = std::fs::remove_file(data_dir.join(PROFILE_FILE_NAME));
+        run("open", &[PROFILES_PANE])?;
+        Ok(Some(
+            "Remove the \"QRE Secure DNS\" profile in System Settings › Privacy & Security › Profiles."
+                .to_string(),
+        ))
+    }
+}
+
+// ==========================================
+// --- LINUX (NetworkManager + systemd-resolved) ---
+// ==========================================
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{
+        parse_resolvectl, run, split_terse, Protocol, Resolver, SavedSetting, SecureDnsStatus,
+    };
+    use std::path::Path;
+
+    /// Properties saved and restored besides `ipv4.dns`.
+    const PROPERTIES: &[&str] = &[
+        "ipv4.ignore-auto-dns",
+        "ipv6.ignore-auto-dns",
+        "connection.dns-over-tls",
+    ];
+
+    pub fn status() -> SecureDnsStatus {
+        let mut status = SecureDnsStatus {
+            supported: true,
+            protocol: Some(Protocol::Dot),
+            ..Default::default()
+        };
+        match run("resolvectl", &["status"]) {
+            Ok(output) => {
+                let (enabled, servers) = parse_resolvectl(&output);
+                status.enabled = Some(enabled);
+                status.servers = servers;
+                status.details = if enabled {
+                    "systemd-resolved sends queries over DNS-over-TLS.".into()
+                } else {
+                    "systemd-resolved sends queries unencrypted.".into()
+                };
+            }
+            Err(_) => {
+                status.servers = std::fs::read_to_string("/etc/resolv.conf")
+                    .unwrap_or_default()
+                    .lines()
+                    .filter_map(|l| l.trim().strip_prefix("nameserver"))
+                    .map(|s| s.trim().to_string())
+                    .collect();
+                status.details =
+                    "systemd-resolved is not running; encrypted DNS cannot be checked or set."
+                        .into();
+                status.supported = false;
+            }
+        }
+        status
+    }
+
+    pub fn current_settings() -> Result<Vec<SavedSetting>, String> {
+        let active = run(
+            "nmcli",
+            &["-t", "-f", "NAME,TYPE", "connection", "show", "--active"],
+        )
+        .map_err(|e| format!("NetworkManager is required: {}", e))?;
+        let mut settings = Vec::new();
+        for line in active.lines() {
+            let fields = split_terse(line);
+            let (Some(name), Some(kind)) = (fields.first(), fields.get(1)) else {
+                continue;
+            };
+            if kind == "loopback" || kind == "bridge" || kind.ends_with("tun") {
+                continue;
+            }
+            let fields = format!("ipv4.dns,{}", PROPERTIES.join(","));
+            let values = run("nmcli", &["-t", "-g", &fields, "connection", "show", name])?;
+            let mut values = values.lines().map(|v| v.trim().to_string());
+            let servers = values
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect();
+            let properties = PROPERTIES
+                .iter()
+                .map(|p| (p.to_string(), values.next().unwrap_or_default()))
+                .collect();
+            settings.push(SavedSetting {
+                target: name.clone(),
+                servers,
+                properties,
+            });
+        }
+        if settings.is_empty() {
+            return Err("No active network connection found.".to_string());
+        }
+        Ok(settings)
+    }
+
+    fn modify_and_reconnect(name: &str, changes: &[(&str, String)]) -> Result<(), String> {
+        let mut args = vec!["connection", "modify", name];
+        for (key, value) in changes {
+            args.push(key);
+            args.push(value);
+        }
+        run("nmcli", &args)?;
+        run("nmcli", &["connection", "up", name]).map(|_| ())
+    }
+
+    pub fn apply(
+        resolver: &Resolver,
+        targets: &[String],
+        _data_dir: &Path,
+    ) -> Result<Option<String>, String> {
+        // `ip#name` lets systemd-resolved verify the resolver's certificate.
+        let servers: Vec<String> = resolver
+            .addresses
+            .iter()
+            .map(|a| format!("{}#{}", a, resolver.tls_name))
+            .collect();
+        for target in targets {
+            modify_and_reconnect(
+                target,
+                &[
+                    ("ipv4.dns", servers.join(",")),
+                    ("ipv4.ignore-auto-dns", "yes".into()),
+                    ("ipv6.ignore-auto-dns", "yes".into()),
+                    ("connection.dns-over-tls", "yes".into()),
+                ],
+            )?;
+        }
+        Ok(None)
+    }
+
+    pub fn restore(settings: &[SavedSetting], _data_dir: &Path) -> Result<Option<String>, String> {
+        for setting in settings {
+            let mut changes = vec![("ipv4.dns", setting.servers.join(","))];
+            for (key, value) in &setting.properties {
+                // Unset values read back empty; "default" is accepted by every property.
+                let value = if value.is_empty() { "default" } else { value };
+                changes.push((key.as_str(), value.to_string()));
+            }
+            modify_and_reconnect(&setting.target, &changes)?;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+mod platform {
+    use super::{Resolver, SavedSetting, SecureDnsStatus};
+    use std::path::Path;
+
+    const UNSUPPORTED: &str = "Secure DNS settings are not supported on this platform.";
+
+    pub fn status() -> SecureDnsStatus {
+        SecureDnsStatus {
+            details: UNSUPPORTED.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn current_settings() -> Result<Vec<SavedSetting>, String> {
+        Err(UNSUPPORTED.into())
+    }
+
+    pub fn apply(_: &Resolver, _: &[String], _: &Path) -> Result<Option<String>, String> {
+        Err(UNSUPPORTED.into())
+    }
+
+    pub fn restore(_: &[SavedSetting], _: &Path) -> Result<Option<String>, String> {
+        Err(UNSUPPORTED.into())
+    }
+}
+
+// ==========================================
+// --- PARSERS (Linux) ---
+// ==========================================
+
+/// Splits a line of `nmcli -t` output on unescaped `:`.
+#[cfg(any(target_os = "linux", test))]
+fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    fields.last_mut().unwrap().push(next);
+                }
+            }
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Whether `resolvectl status` shows DNS-over-TLS in strict mode on any scope, and the
+/// servers it lists. Handles both the `+DNSOverTLS` protocol flags (systemd 247+) and the
+/// older `DNSOverTLS setting: yes` line. Opportunistic mode falls back to plain text
+/// silently, so it does not count.
+#[cfg(any(target_os = "linux", test))]
+fn parse_resolvectl(output: &str) -> (bool, Vec<String>) {
+    let mut enabled = false;
+    let mut servers: Vec<String> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        match key.trim() {
+            "Protocols" => {
+                enabled |= value.split_whitespace().any(|f| f == "+DNSOverTLS");
+            }
+            "DNSOverTLS setting" => enabled |= value.trim() == "yes",
+            "DNS Servers" | "Current DNS Server" => {
+                for server in value.split_whitespace() {
+                    if !servers.iter().any(|s| s == server) {
+                        servers.push(server.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    (enabled, servers)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolvers_are_well_formed() {
+        for r in RESOLVERS {
+            assert!(!r.addresses.is_empty(), "{}", r.id);
+            assert!(r.doh_template.starts_with("https://"), "{}", r.id);
+            assert!(r
+                .addresses
+                .iter()
+                .all(|a| a.parse::<std::net::Ipv4Addr>().is_ok()));
+            assert_eq!(find_resolver(r.id).unwrap(), r);
+        }
+        assert!(find_resolver("nope").is_err());
+    }
+
+    #[test]
+    fn test_resolver_matching() {
+        let servers = vec![
+            "192.168.1.1".to_string(),
+            "9.9.9.9#dns.quad9.net".to_string(),
+        ];
+        assert_eq!(resolver_for(&servers).unwrap().id, "quad9");
+        assert_eq!(
+            resolver_for(&["1.0.0.1:853".to_string()]).unwrap().id,
+            "cloudflare"
+        );
+        assert!(resolver_for(&["192.168.1.1".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_parse_resolvectl() {
+        let modern = "Global\n         Protocols: +LLMNR +mDNS -DNSOverTLS DNSSEC=no/unsupported\n\
+                      Link 2 (wlp3s0)\n         Protocols: +DefaultRoute -LLMNR +DNSOverTLS\n\
+                      Current DNS Server: 1.1.1.1#cloudflare-dns.com\n\
+                      \x20      DNS Servers: 1.1.1.1#cloudflare-dns.com 1.0.0.1#cloudflare-dns.com\n";
+        let (enabled, servers) = parse_resolvectl(modern);
+        assert!(enabled);
+        assert_eq!(
+            servers,
+            vec!["1.1.1.1#cloudflare-dns.com", "1.0.0.1#cloudflare-dns.com"]
+        );
+
+        let plain = "Global\n       LLMNR setting: yes\nDNSOverTLS setting: opportunistic\n\
+                     Link 2 (eth0)\n      DNS Servers: 192.168.1.1\n";
+        assert_eq!(
+            parse_resolvectl(plain),
+            (false, vec!["192.168.1.1".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_split_terse() {
+        assert_eq!(
+            split_terse("Home\\: Wi-Fi:802-11-wireless"),
+            vec!["Home: Wi-Fi", "802-11-wireless"]
+        );
+        assert_eq!(split_terse("lo:loopback"), vec!["lo", "loopback"]);
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let dir = std::env::temp_dir().join("qre_secure_dns_tests");
+        let _ = std::fs::create_dir_all(&dir);
+        let _ = std::fs::remove_file(backup_path(&dir));
+        assert!(load_backup(&dir).is_none());
+        assert!(revert(&dir).is_err());
+
+        let backup = Backup {
+            created_at: 1_700_000_000,
+            settings: vec![SavedSetting {
+                target: "Wired connection 1".to_string(),
+                servers: Vec::new(),
+                properties: [("connection.dns-over-tls".to_string(), "-1".to_string())]
+                    .into_iter()
+                    .collect(),
+            }],
+        };
+        save_backup(&dir, &backup).unwrap();
+        assert_eq!(load_backup(&dir).unwrap(), backup);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+// --- END OF FILE secure_dns.rs ---
@@ -0,0 +1,233 @@
+// --- START OF FILE self_decrypt.rs ---
+
+// ==========================================
+// --- SELF-DECRYPTING EXPORT (.html) ---
+// ==========================================
+// Sends one decrypted container to someone who does not have QRE. The file is re-sealed
+// under a passphrase (and optionally a keyfile) agreed out-of-band and wrapped in a single
+// HTML page that decrypts itself in any modern browser using WebCrypto. No network
+// access, no install, and unlike a bundled executable it runs on every OS and is not
+// stripped by mail filters.
+//
+// WebCrypto has no Argon2, so the passphrase goes through PBKDF2-HMAC-SHA256 with a high
+// iteration count. That is weaker per guess than the vault's Argon2id: the passphrase
+// length check below is the real protection.
+//
+// PAYLOAD (JSON in the page, binary fields base64):
+//   { v, iter, keyfile, salt, iv, ct }
+//   key        = PBKDF2(utf8(passphrase) || SHA-256(keyfile)?, salt, iter)
+//   AAD        = "QRE-SFX:{v}:{iter}:{keyfile}"
+//   plaintext  = name_len (u32 LE) | file name (UTF-8) | content
+// The file name travels inside the ciphertext so the page does not leak it.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Result};
+use data_encoding::BASE64;
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+const SFX_VERSION: u32 = 1;
+/// OWASP's 2023 recommendation for PBKDF2-HMAC-SHA256.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+pub const MIN_PASSPHRASE_LEN: usize = 12;
+/// The whole file is base64-encoded into the page and decrypted in browser memory.
+pub const MAX_SFX_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_FILENAME_LEN: usize = 255;
+
+const PAYLOAD_MARKER: &str = "__QRE_PAYLOAD__";
+const TEMPLATE: &str = include_str!("self_decrypt.html");
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SfxPayload {
+    v: u32,
+    iter: u32,
+    keyfile: bool,
+    salt: String,
+    iv: String,
+    ct: String,
+}
+
+impl SfxPayload {
+    fn aad(&self) -> Vec<u8> {
+        format!("QRE-SFX:{}:{}:{}", self.v, self.iter, self.keyfile).into_bytes()
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    keyfile: Option<&[u8]>,
+    salt: &[u8],
+    iterations: u32,
+) -> Zeroizing<[u8; 32]> {
+    let mut material = Zeroizing::new(passphrase.as_bytes().to_vec());
+    if let Some(keyfile) = keyfile {
+        material.extend_from_slice(&Sha256::digest(keyfile));
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(&material, salt, iterations, key.as_mut());
+    key
+}
+
+/// Seals `content` and returns the complete decryptor page.
+pub fn build_page(
+    filename: &str,
+    content: &[u8],
+    passphrase: &str,
+    keyfile: Option<&[u8]>,
+) -> Result<String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    if content.len() as u64 > MAX_SFX_BYTES {
+        return Err(anyhow!(
+            "File is too large for a self-decrypting export (limit {} MB)",
+            MAX_SFX_BYTES / (1024 * 1024)
+        ));
+    }
+    if filename.is_empty() || filename.len() > MAX_FILENAME_LEN {
+        return Err(anyhow!("Invalid file name"));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng
+        .try_fill_bytes(&mut salt)
+        .map_err(|e| anyhow!("RNG failure: {}", e))?;
+    OsRng
+        .try_fill_bytes(&mut nonce)
+        .map_err(|e| anyhow!("RNG failure: {}", e))?;
+
+    let mut payload = SfxPayload {
+        v: SFX_VERSION,
+        iter: PBKDF2_ITERATIONS,
+        keyfile: keyfile.is_some(),
+        salt: BASE64.encode(&salt),
+        iv: BASE64.encode(&nonce),
+        ct: String::new(),
+    };
+
+    let mut plaintext = Zeroizing::new(Vec::with_capacity(4 + filename.len() + content.len()));
+    plaintext.extend_from_slice(&(filename.len() as u32).to_le_bytes());
+    plaintext.extend_from_slice(filename.as_bytes());
+    plaintext.extend_from_slice(content);
+
+    let key = derive_key(passphrase, keyfile, &salt, payload.iter);
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|_| anyhow!("Invalid key"))?;
+    let aad = payload.aad();
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    payload.ct = BASE64.encode(&ciphertext);
+
+    // Only base64 and numbers: nothing in the JSON can close the <script> element.
+    let json = serde_json::to_string(&payload)?;
+    Ok(TEMPLATE.replacen(PAYLOAD_MARKER, &json, 1))
+}
+
+/// Reverses `build_page` (used to verify an export before handing it over).
+pub fn open_page(
+    page: &str,
+    passphrase: &str,
+    keyfile: Option<&[u8]>,
+) -> Result<(String, Zeroizing<Vec<u8>>)> {
+    let start = page
+        .find("<script id=\"qre-payload\" type=\"application/json\">")
+        .map(|i| i + "<script id=\"qre-payload\" type=\"application/json\">".len())
+        .ok_or_else(|| anyhow!("Not a QRE self-decrypting file"))?;
+    let end = page[start..]
+        .find("</script>")
+        .map(|i| start + i)
+        .ok_or_else(|| anyhow!("Not a QRE self-decrypting file"))?;
+    let payload: SfxPayload = serde_json::from_str(&page[start..end])?;
+    if payload.v != SFX_VERSION {
+        return Err(anyhow!("Unsupported export version {}", payload.v));
+    }
+    if payload.keyfile != keyfile.is_some() {
+        return Err(anyhow!(if payload.keyfile {
+            "This file also requires a keyfile"
+        } else {
+            "This file does not use a keyfile"
+        }));
+    }
+
+    let salt = BASE64.decode(payload.salt.as_bytes())?;
+    let nonce = BASE64.decode(payload.iv.as_bytes())?;
+    let ciphertext = BASE64.decode(payload.ct.as_bytes())?;
+    if nonce.len() != NONCE_LEN {
+        return Err(anyhow!("Corrupt export"));
+    }
+
+    let key = derive_key(passphrase, keyfile, &salt, payload.iter);
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|_| anyhow!("Invalid key"))?;
+    let aad = payload.aad();
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("Wrong passphrase or keyfile"))?,
+    );
+
+    let name_len = plaintext
+        .get(..4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| anyhow!("Corrupt export"))?;
+    let name = plaintext
+        .get(4..4 + name_len)
+        .ok_or_else(|| anyhow!("Corrupt export"))?;
+    let name = String::from_utf8(name.to_vec())?;
+    Ok((name, Zeroizing::new(plaintext[4 + name_len..].to_vec())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_roundtrip_with_keyfile() {
+        let page = build_page(
+            "report.pdf",
+            b"%PDF secret",
+            "correct horse battery",
+            Some(b"kf"),
+        )
+        .unwrap();
+        assert!(!page.contains(PAYLOAD_MARKER));
+        assert!(!page.contains("report.pdf"));
+
+        let (name, content) = open_page(&page, "correct horse battery", Some(b"kf")).unwrap();
+        assert_eq!(name, "report.pdf");
+        assert_eq!(content.as_slice(), b"%PDF secret");
+
+        assert!(open_page(&page, "correct horse battery", Some(b"other")).is_err());
+        assert!(open_page(&page, "correct horse battery", None).is_err());
+        assert!(open_page(&page, "wrong horse battery!", Some(b"kf")).is_err());
+    }
+
+    #[test]
+    fn test_short_passphrase_rejected() {
+        assert!(build_page("a.txt", b"x", "short", None).is_err());
+    }
+}
+
+// --- END OF FILE self_decrypt.rs ---
@@ -0,0 +1,315 @@
+// --- START OF FILE settings_profile.rs ---
+
+// ==========================================
+// --- SETTINGS PROFILE (EXPORT / IMPORT) ---
+// ==========================================
+// A plain JSON file holding the app configuration, so a set-up can be copied to another
+// machine or restored after a reinstall: cleaner defaults and per-type rules, exclusions,
+// schedules, shredder defaults, hotkeys and compression presets.
+//
+// Most of these are kept by the frontend; it passes them to `export_settings` and stores
+// what `import_settings` returns. Settings the backend holds itself (power policy, low
+// resource mode, network monitor) are read from and applied to the live state here.
+//
+// NO SECRETS: the format simply has no place for them. Passwords, keys, API keys (e.g.
+// the HIBP key in the breach monitor settings) and vault contents stay in the encrypted
+// vault. Unknown fields in an imported file are ignored, so a hand-edited file cannot
+// smuggle anything else in either.
+
+use crate::cleaner::CleaningOptions;
+use crate::commands::files::COMPRESSION_PRESETS;
+use crate::network_monitor::NetworkMonitorConfig;
+use crate::power::PowerPolicy;
+use crate::resources::ResourceMode;
+use crate::shredder::ShredMethod;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const SETTINGS_FORMAT: &str = "qre-settings";
+/// Bumped when a field changes meaning; newer files are refused rather than misread.
+pub const SETTINGS_VERSION: u32 = 1;
+/// Settings files are small; anything bigger is not one.
+pub const MAX_SETTINGS_BYTES: u64 = 1024 * 1024;
+
+const MAX_LIST_ITEMS: usize = 500;
+const MAX_HOTKEYS: usize = 100;
+const MAX_TEXT_LEN: usize = 260;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SettingsProfile {
+    pub format: String,
+    pub version: u32,
+    /// Unix seconds, set on export.
+    pub exported_at: Option<i64>,
+    pub cleaner: CleanerSettings,
+    /// Paths and glob patterns excluded from junk scans and batch cleaning.
+    pub exclusions: Vec<String>,
+    pub schedules: Vec<Schedule>,
+    pub shredder: ShredderDefaults,
+    /// Action name -> accelerator (e.g. "lock_all" -> "CmdOrCtrl+Shift+L").
+    pub hotkeys: BTreeMap<String, String>,
+    pub compression: CompressionSettings,
+    pub power: PowerPolicy,
+    pub resources: ResourceMode,
+    pub network_monitor: NetworkMonitorConfig,
+}
+
+impl Default for SettingsProfile {
+    fn default() -> Self {
+        Self {
+            format: SETTINGS_FORMAT.to_string(),
+            version: SETTINGS_VERSION,
+            exported_at: None,
+            cleaner: CleanerSettings::default(),
+            exclusions: Vec::new(),
+            schedules: Vec::new(),
+            shredder: ShredderDefaults::default(),
+            hotkeys: BTreeMap::new(),
+            compression: CompressionSettings::default(),
+            power: PowerPolicy::default(),
+            resources: ResourceMode::default(),
+            network_monitor: NetworkMonitorConfig::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct CleanerSettings {
+    /// What the metadata cleaner strips unless a rule says otherwise.
+    pub defaults: CleaningOptions,
+    /// Checked in order; the first rule whose extension matches wins.
+    pub rules: Vec<CleanerRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CleanerRule {
+    /// Lower-case extension without the dot ("jpg", "docx").
+    pub extension: String,
+    pub options: CleaningOptions,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// Task identifier understood by the frontend ("system_clean", "free_space_wipe", ...).
+    pub task: String,
+    pub every_hours: u32,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct ShredderDefaults {
+    pub method: ShredMethod,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CompressionSettings {
+    pub default_preset: String,
+    /// Extension (lower-case, no dot) -> preset, overriding the default.
+    pub by_extension: BTreeMap<String, String>,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            default_preset: "auto".to_string(),
+            by_extension: BTreeMap::new(),
+        }
+    }
+}
+
+// ==========================================
+// --- VALIDATION ---
+// ==========================================
+
+fn check_text(what: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{} must not be empty.", what));
+    }
+    if value.len() > MAX_TEXT_LEN || value.chars().any(char::is_control) {
+        return Err(format!("{} '{}' is not valid.", what, value));
+    }
+    Ok(())
+}
+
+fn check_extension(ext: &str) -> Result<(), String> {
+    check_text("Extension", ext)?;
+    if ext.starts_with('.')
+        || ext
+            .chars()
+            .any(|c| c.is_uppercase() || c == '/' || c == '\\')
+    {
+        return Err(format!(
+            "Extension '{}' must be lower-case, without the dot.",
+            ext
+        ));
+    }
+    Ok(())
+}
+
+fn check_preset(preset: &str) -> Result<(), String> {
+    if !COMPRESSION_PRESETS.contains(&preset) {
+        return Err(format!(
+            "Unknown compression preset '{}' (expected one of: {}).",
+            preset,
+            COMPRESSION_PRESETS.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+impl SettingsProfile {
+    /// Checks a profile before it is written or applied.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.format != SETTINGS_FORMAT {
+            return Err("This file is not a QRE settings export.".to_string());
+        }
+        if self.version > SETTINGS_VERSION {
+            return Err(format!(
+                "These settings were exported by a newer version of QRE (format {}). Please update.",
+                self.version
+            ));
+        }
+        if self.exclusions.len() > MAX_LIST_ITEMS
+            || self.cleaner.rules.len() > MAX_LIST_ITEMS
+            || self.schedules.len() > MAX_LIST_ITEMS
+            || self.compression.by_extension.len() > MAX_LIST_ITEMS
+        {
+            return Err("The settings file contains too many entries.".to_string());
+        }
+
+        for rule in &self.cleaner.rules {
+            check_extension(&rule.extension)?;
+        }
+        for exclusion in &self.exclusions {
+            check_text("Exclusion", exclusion)?;
+        }
+        for schedule in &self.schedules {
+            check_text("Scheduled task", &schedule.task)?;
+            if schedule.every_hours == 0 {
+                return Err(format!(
+                    "Schedule '{}' must run at most once per hour.",
+                    schedule.task
+                ));
+            }
+        }
+
+        if self.hotkeys.len() > MAX_HOTKEYS {
+            return Err("The settings file contains too many hotkeys.".to_string());
+        }
+        let mut seen: BTreeMap<String, &str> = BTreeMap::new();
+        for (action, accelerator) in &self.hotkeys {
+            check_text("Hotkey action", action)?;
+            check_text("Hotkey", accelerator)?;
+            if let Some(other) = seen.insert(accelerator.to_lowercase(), action) {
+                return Err(format!(
+                    "Hotkey '{}' is assigned to both '{}' and '{}'.",
+                    accelerator, other, action
+                ));
+            }
+        }
+
+        check_preset(&self.compression.default_preset)?;
+        for (ext, preset) in &self.compression.by_extension {
+            check_extension(ext)?;
+            check_preset(preset)?;
+        }
+
+        if self.power.pause_below_percent > 100 {
+            return Err("The battery threshold must be between 0 and 100%.".to_string());
+        }
+        self.network_monitor.validate()
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        self.validate()?;
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let profile: Self = serde_json::from_str(json)
+            .map_err(|e| format!("The settings file could not be read: {}", e))?;
+        profile.validate()?;
+        Ok(profile)
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SettingsProfile {
+        let mut profile = SettingsProfile {
+            exclusions: vec!["~/Projects/**".to_string()],
+            ..Default::default()
+        };
+        profile.cleaner.rules.push(CleanerRule {
+            extension: "jpg".to_string(),
+            options: CleaningOptions {
+                gps: true,
+                author: false,
+                date: false,
+            },
+        });
+        profile.schedules.push(Schedule {
+            task: "system_clean".to_string(),
+            every_hours: 24,
+            enabled: true,
+        });
+        profile.shredder.method = ShredMethod::Gutmann;
+        profile
+            .hotkeys
+            .insert("lock_all".to_string(), "CmdOrCtrl+Shift+L".to_string());
+        profile
+            .compression
+            .by_extension
+            .insert("log".to_string(), "extreme".to_string());
+        profile
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let profile = sample();
+        let json = profile.to_json().unwrap();
+        assert_eq!(SettingsProfile::from_json(&json).unwrap(), profile);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_dropped() {
+        let json = r#"{"format":"qre-settings","version":1,"hibp_api_key":"secret",
+            "shredder":{"method":"simple","password":"hunter2"}}"#;
+        let profile = SettingsProfile::from_json(json).unwrap();
+        assert_eq!(profile.shredder.method, ShredMethod::Simple);
+        let exported = profile.to_json().unwrap();
+        assert!(!exported.contains("secret") && !exported.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_profiles() {
+        assert!(SettingsProfile::from_json(r#"{"format":"other"}"#).is_err());
+        assert!(SettingsProfile::from_json(r#"{"format":"qre-settings","version":99}"#).is_err());
+
+        let mut duplicate_hotkey = sample();
+        duplicate_hotkey
+            .hotkeys
+            .insert("shred".to_string(), "cmdorctrl+shift+l".to_string());
+        assert!(duplicate_hotkey.validate().is_err());
+
+        let mut bad_preset = sample();
+        bad_preset.compression.default_preset = "ultra".to_string();
+        assert!(bad_preset.validate().is_err());
+
+        let mut bad_schedule = sample();
+        bad_schedule.schedules[0].every_hours = 0;
+        assert!(bad_schedule.validate().is_err());
+    }
+}
+
+// --- END OF FILE settings_profile.rs ---
@@ -0,0 +1,353 @@
+// --- START OF FILE shamir.rs ---
+
+// ==========================================
+// --- SPLIT-KEY CONTAINERS (SHAMIR M-OF-N) ---
+// ==========================================
+// A split-key container is an ordinary V8 stream whose "master key" is a random key
+// made for that one file and never stored. The key is cut into N shares with Shamir's
+// scheme over GF(256): any M shares rebuild it, fewer reveal nothing about it. Shares
+// are short text lines (also rendered as QR codes) that team members keep separately.
+//
+// Share text: `qre-share:1:<set>:<m>:<n>:<index>:<data b64>:<check>`
+//   set   — 8 hex bytes of SHA-256("QRE_SHAMIR_SET" | key): which container the share
+//           belongs to, and a check that the rebuilt key is the right one
+//   check — 4 hex bytes of SHA-256 over everything before it, to catch typos
+//
+// The header's vault id is `shamir:<set>`, so unlocking knows to ask for shares and
+// can pick this file's shares out of a batch.
+
+use crate::keychain::MasterKey;
+use anyhow::{anyhow, Result};
+use data_encoding::{BASE64, HEXLOWER, HEXLOWER_PERMISSIVE};
+use rand::{rngs::OsRng, TryRngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+const VAULT_ID_PREFIX: &str = "shamir:";
+const SHARE_PREFIX: &str = "qre-share:1";
+const SET_ID_LEN: usize = 8;
+const CHECK_LEN: usize = 4;
+
+/// `lock_file` option: lock with a fresh key split into `shares` shares instead of the
+/// vault key.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SplitKeyOptions {
+    /// Shares needed to unlock (M).
+    pub threshold: u8,
+    /// Shares made (N).
+    pub shares: u8,
+    /// Where the share files go (default: next to the container).
+    pub output_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub set_id: [u8; SET_ID_LEN],
+    pub threshold: u8,
+    pub count: u8,
+    pub index: u8,
+    pub data: Zeroizing<Vec<u8>>,
+}
+
+impl Share {
+    pub fn encode(&self) -> String {
+        let body = format!(
+            "{}:{}:{}:{}:{}:{}",
+            SHARE_PREFIX,
+            HEXLOWER.encode(&self.set_id),
+            self.threshold,
+            self.count,
+            self.index,
+            BASE64.encode(&self.data)
+        );
+        format!("{}:{}", body, checksum(&body))
+    }
+
+    /// Accepts the bare share line or a whole share file around it.
+    pub fn decode(text: &str) -> Result<Self> {
+        let text = text
+            .lines()
+            .map(str::trim)
+            .find(|l| l.starts_with(SHARE_PREFIX))
+            .unwrap_or(text.trim());
+        let (body, check) = text
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("Not a QRE key share"))?;
+        let parts: Vec<&str> = body.split(':').collect();
+        if parts.len() != 7 || format!("{}:{}", parts[0], parts[1]) != SHARE_PREFIX {
+            return Err(anyhow!("Not a QRE key share"));
+        }
+        if checksum(body) != check.to_ascii_lowercase() {
+            return Err(anyhow!("This key share is damaged or mistyped"));
+        }
+        let set_id: [u8; SET_ID_LEN] = HEXLOWER_PERMISSIVE
+            .decode(parts[2].as_bytes())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow!("Invalid share set id"))?;
+        let number = |s: &str| s.parse::<u8>().map_err(|_| anyhow!("Invalid share header"));
+        let share = Self {
+            set_id,
+            threshold: number(parts[3])?,
+            count: number(parts[4])?,
+            index: number(parts[5])?,
+            data: Zeroizing::new(
+                BASE64
+                    .decode(parts[6].as_bytes())
+                    .map_err(|_| anyhow!("Invalid share data"))?,
+            ),
+        };
+        if share.index == 0
+            || share.index > share.count
+            || share.threshold < 2
+            || share.threshold > share.count
+        {
+            return Err(anyhow!("Invalid share header"));
+        }
+        Ok(share)
+    }
+}
+
+fn checksum(body: &str) -> String {
+    HEXLOWER.encode(&Sha256::digest(body.as_bytes())[..CHECK_LEN])
+}
+
+/// Which share set a key belongs to (see the module comment).
+pub fn set_id(secret: &[u8]) -> [u8; SET_ID_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_SHAMIR_SET");
+    hasher.update(secret);
+    let mut out = [0u8; SET_ID_LEN];
+    out.copy_from_slice(&hasher.finalize()[..SET_ID_LEN]);
+    out
+}
+
+/// A random key for one split-key container.
+pub fn new_file_key() -> Result<MasterKey> {
+    let mut key = [0u8; 32];
+    OsRng
+        .try_fill_bytes(&mut key)
+        .map_err(|e| anyhow!("OS RNG failed generating file key: {}", e))?;
+    Ok(MasterKey(key))
+}
+
+/// Header vault id of a container whose key is `key`.
+pub fn vault_id(key: &MasterKey) -> String {
+    format!("{}{}", VAULT_ID_PREFIX, HEXLOWER.encode(&set_id(&key.0)))
+}
+
+pub fn is_split_key_vault(vault_id: &str) -> bool {
+    vault_id.starts_with(VAULT_ID_PREFIX)
+}
+
+/// Rebuilds the key of the container with header vault id `vault_id` from whichever of
+/// `shares` belong to it; shares of other containers and unreadable ones are skipped.
+pub fn key_for_vault(vault_id: &str, shares: &[impl AsRef<str>]) -> Result<MasterKey> {
+    let wanted = vault_id
+        .strip_prefix(VAULT_ID_PREFIX)
+        .ok_or_else(|| anyhow!("Not a split-key container"))?;
+    let matching: Vec<Share> = shares
+        .iter()
+        .filter_map(|text| Share::decode(text.as_ref()).ok())
+        .filter(|share| HEXLOWER.encode(&share.set_id) == wanted)
+        .collect();
+    if matching.is_empty() {
+        return Err(anyhow!(
+            "This file is locked with split key shares. Provide enough of its shares to open it."
+        ));
+    }
+    let secret = combine(&matching)?;
+    let key: [u8; 32] = secret
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("The key shares do not hold a file key"))?;
+    Ok(MasterKey(key))
+}
+
+/// Splits `secret` into `count` shares, any `threshold` of which rebuild it. Shares are
+/// the polynomial's values at x = 1..=count; x = 0 is the secret itself.
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > count {
+        return Err(anyhow!(
+            "Choose at least 2 required shares and no more than the number of shares"
+        ));
+    }
+    let set = set_id(secret);
+    let mut shares: Vec<Share> = (1..=count)
+        .map(|index| Share {
+            set_id: set,
+            threshold,
+            count,
+            index,
+            data: Zeroizing::new(Vec::with_capacity(secret.len())),
+        })
+        .collect();
+
+    // One random polynomial of degree threshold - 1 per secret byte, constant term = byte.
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for &byte in secret {
+        coefficients[0] = byte;
+        OsRng
+            .try_fill_bytes(&mut coefficients[1..])
+            .map_err(|e| anyhow!("RNG failure: {}", e))?;
+        for share in shares.iter_mut() {
+            // Horner's rule, highest coefficient first.
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, &c| gf_mul(acc, share.index) ^ c);
+            share.data.push(y);
+        }
+    }
+    Ok(shares)
+}
+
+/// Rebuilds the secret from at least `threshold` shares of one set.
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>> {
+    let first = shares
+        .first()
+        .ok_or_else(|| anyhow!("No key shares given"))?;
+    let mut used: Vec<&Share> = Vec::new();
+    for share in shares {
+        if share.set_id != first.set_id {
+            return Err(anyhow!("These key shares belong to different files"));
+        }
+        if share.threshold != first.threshold || share.data.len() != first.data.len() {
+            return Err(anyhow!("Inconsistent key shares"));
+        }
+        if !used.iter().any(|u| u.index == share.index) {
+            used.push(share);
+        }
+    }
+    let threshold = first.threshold as usize;
+    if used.len() < threshold {
+        return Err(anyhow!(
+            "{} of {} key shares given; {} are needed",
+            used.len(),
+            first.count,
+            threshold
+        ));
+    }
+    used.truncate(threshold);
+
+    // Lagrange interpolation at x = 0.
+    let mut secret = Zeroizing::new(vec![0u8; first.data.len()]);
+    for (i, share) in used.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, other) in used.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+            }
+        }
+        for (out, &y) in secret.iter_mut().zip(share.data.iter()) {
+            *out ^= gf_mul(basis, y);
+        }
+    }
+    if set_id(&secret) != first.set_id {
+        return Err(anyhow!("The key shares do not fit together"));
+    }
+    Ok(secret)
+}
+
+// ==========================================
+// --- GF(256) ARITHMETIC ---
+// ==========================================
+// AES field (x^8 + x^4 + x^3 + x + 1). Multiplication runs a fixed 8 rounds with masks
+// instead of branches, so timing does not depend on the secret bytes.
+
+pub(crate) fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// a^254 = a^-1 for a != 0.
+pub(crate) fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    gf_mul(a, gf_inv(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_threshold_subset_rebuilds_the_key() {
+        let secret = [0x42u8; 32];
+        let shares = split(&secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1]] {
+            let picked: Vec<Share> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(combine(&picked).unwrap().as_slice(), &secret);
+        }
+        // Two shares are not enough, and a repeated share does not count twice.
+        let err = combine(&shares[..2]).unwrap_err().to_string();
+        assert!(err.contains("3 are needed"), "unexpected error: {err}");
+        assert!(combine(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_share_text_roundtrip_and_checks() {
+        let shares = split(b"file key", 2, 3).unwrap();
+        let text = shares[1].encode();
+        assert!(text.starts_with("qre-share:1:"));
+        assert_eq!(Share::decode(&format!("  {}\n", text)).unwrap(), shares[1]);
+
+        // A typo is caught by the check digits.
+        let mut typo = text.clone().into_bytes();
+        let at = typo.len() - 12;
+        typo[at] = if typo[at] == b'A' { b'B' } else { b'A' };
+        assert!(Share::decode(&String::from_utf8(typo).unwrap()).is_err());
+
+        // Shares of another key are refused rather than producing a wrong key.
+        let other = split(b"file key", 2, 3).unwrap();
+        assert!(combine(&[shares[0].clone(), other[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_key_for_vault_picks_matching_shares() {
+        let key = new_file_key().unwrap();
+        let other = new_file_key().unwrap();
+        let id = vault_id(&key);
+        assert!(is_split_key_vault(&id));
+
+        let mut given: Vec<String> = split(&other.0, 2, 2)
+            .unwrap()
+            .iter()
+            .map(Share::encode)
+            .collect();
+        let own = split(&key.0, 2, 3).unwrap();
+        given.push(format!("Key share 1 of 3\n\n{}\n", own[0].encode()));
+        assert!(key_for_vault(&id, &given).is_err());
+
+        given.push(own[2].encode());
+        assert_eq!(key_for_vault(&id, &given).unwrap().0, key.0);
+    }
+
+    #[test]
+    fn test_gf_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+}
+
+// --- END OF FILE shamir.rs ---
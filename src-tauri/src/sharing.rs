@@ -0,0 +1,891 @@
+// --- START OF FILE sharing.rs ---
+
+// ==========================================
+// --- ENTRY SHARING BUNDLES (.qreshare) ---
+// ==========================================
+// A lightweight way to hand a few passwords/notes to someone else: the selected entries
+// are serialized into a bundle and encrypted with a passphrase agreed out-of-band.
+// The recipient's vault key is never involved, so the bundle can be opened by any QRE
+// install that knows the passphrase.
+//
+// FILE LAYOUT (bincode):
+//   v1: SharePackage { magic, version, argon2 params, salt, nonce, ciphertext }
+//   v2: KdfPackage { magic, version, kdf, salt, nonce, ciphertext }
+// The header fields (everything except the ciphertext) are passed to AES-GCM as
+// associated data, so lowering the KDF cost or swapping the salt breaks decryption.
+//
+// Argon2id is the default and is still written as v1, which every QRE version opens.
+// v2 names its KDF and is written when the sender picks scrypt, so the bundle can be
+// opened by tools that have no Argon2. Its layout, for such tools (integers are
+// little-endian, `bytes` is a u64 length followed by the data):
+//   "QRESHARE" | u32 version = 2 | u32 kdf id | kdf params | bytes salt
+//   | bytes nonce (12) | bytes ciphertext
+//   kdf id 1, scrypt: u8 log_n | u32 r | u32 p
+//     key = scrypt(UTF-8 passphrase, salt, N = 2^log_n, r, p, 32 bytes)
+//   kdf id 0, Argon2id: u32 memory (KiB) | u32 iterations | u32 lanes, over the raw salt
+// The plaintext is the JSON bundle, sealed with AES-256-GCM; the associated data is
+// every byte of the file before the ciphertext's length.
+//
+// Personal metadata (usage counters, deletion tracking) is stripped before sealing:
+// it describes the sender's habits, not the credential.
+
+use crate::notes::{NoteEntry, NotesVault};
+use crate::passwords::{PasswordVault, VaultEntry};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Context, Result};
+use argon2::password_hash::{rand_core::OsRng as Argon2OsRng, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use bincode::Options;
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zeroize::Zeroizing;
+
+const SHARE_MAGIC: [u8; 8] = *b"QRESHARE";
+const SHARE_VERSION: u32 = 1;
+/// Bundles whose header names the KDF (see `KdfParams`).
+const SHARE_VERSION_KDF: u32 = 2;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// Same cost as a freshly created keychain (64 MB / 3 iterations / 4 lanes).
+const SHARE_KDF: (u32, u32, u32) = (65536, 3, 4);
+/// N = 2^17, r = 8, p = 1: 128 MB, the usual interactive-use recommendation.
+const SHARE_SCRYPT: KdfParams = KdfParams::Scrypt {
+    log_n: 17,
+    r: 8,
+    p: 1,
+};
+/// Refuse to even run a KDF on parameters beyond this many KiB (a crafted file could
+/// ask for 4 GB). Applies to scrypt's 128 * r * N bytes as well.
+const MAX_KDF_MEMORY: u32 = 1_048_576;
+/// scrypt's r and p multiply its running time; keep crafted files from asking for hours.
+const MAX_SCRYPT_R: u32 = 32;
+const MAX_SCRYPT_P: u32 = 16;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+/// Bundles are meant to be small; anything larger is not a share file.
+const MAX_BUNDLE_BYTES: usize = 16 * 1024 * 1024;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SharePackage {
+    magic: [u8; 8],
+    version: u32,
+    kdf_memory: u32,
+    kdf_iterations: u32,
+    kdf_parallelism: u32,
+    salt: String,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl SharePackage {
+    /// Bytes authenticated alongside the ciphertext.
+    fn header_aad(&self) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(64);
+        aad.extend_from_slice(&self.magic);
+        aad.extend_from_slice(&self.version.to_le_bytes());
+        aad.extend_from_slice(&self.kdf_memory.to_le_bytes());
+        aad.extend_from_slice(&self.kdf_iterations.to_le_bytes());
+        aad.extend_from_slice(&self.kdf_parallelism.to_le_bytes());
+        aad.extend_from_slice(self.salt.as_bytes());
+        aad.extend_from_slice(&self.nonce);
+        aad
+    }
+}
+
+/// The KDF a v2 bundle was sealed with. The variant order is the kdf id on disk.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum KdfParams {
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KdfPackage {
+    magic: [u8; 8],
+    version: u32,
+    kdf: KdfParams,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl KdfPackage {
+    /// The file bytes before the ciphertext's length, authenticated alongside it.
+    fn header_aad(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(
+            &self.magic,
+            self.version,
+            &self.kdf,
+            &self.salt,
+            &self.nonce,
+        ))?)
+    }
+}
+
+/// Which KDF to seal a bundle with. Argon2id unless the recipient needs scrypt.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareKdf {
+    #[default]
+    Argon2id,
+    Scrypt,
+}
+
+/// The decrypted content of a share file.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ShareBundle {
+    pub created_at: i64,
+    #[serde(default)]
+    pub passwords: Vec<VaultEntry>,
+    #[serde(default)]
+    pub notes: Vec<NoteEntry>,
+}
+
+/// What to do with an incoming entry that collides with an existing one.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Leave the existing entry alone and drop the incoming one.
+    Skip,
+    /// Replace the existing entry's content (its ID and usage history are kept).
+    Overwrite,
+    /// Import the incoming entry as a new one alongside the existing entry.
+    KeepBoth,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// An entry with the same ID exists (usually: this bundle was imported before).
+    SameId,
+    /// A password entry for the same service and username exists.
+    DuplicateLogin,
+    /// A note with the same title exists.
+    DuplicateTitle,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SharedKind {
+    Password,
+    Note,
+}
+
+/// One row of the import preview shown before anything is written.
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportPreviewItem {
+    pub id: String,
+    pub kind: SharedKind,
+    pub title: String,
+    pub conflict: Option<ConflictKind>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
+// ==========================================
+// --- BUILDING & SEALING ---
+// ==========================================
+
+/// Collects the requested entries (IDs may refer to passwords or notes) into a bundle.
+pub fn build_bundle(
+    passwords: &PasswordVault,
+    notes: &NotesVault,
+    ids: &[String],
+    now: i64,
+) -> Result<ShareBundle, String> {
+    if ids.is_empty() {
+        return Err("Select at least one entry to share.".to_string());
+    }
+
+    let mut bundle = ShareBundle {
+        created_at: now,
+        ..Default::default()
+    };
+    for id in ids {
+        if let Some(entry) = passwords.entries.iter().find(|e| &e.id == id) {
+            let mut shared = entry.clone();
+            shared.last_used_at = None;
+            shared.use_count = 0;
+            shared.deletion_status = None;
+            shared.history.clear();
+            // Attachment files stay in the sender's vault, like note images.
+            shared.attachments.clear();
+            bundle.passwords.push(shared);
+        } else if let Some(note) = notes.entries.iter().find(|n| &n.id == id) {
+            // Embedded images and attachments stay in the sender's vault; the IDs would
+            // dangle on import. Earlier versions are the sender's too.
+            let mut shared = note.clone();
+            shared.image_ids.clear();
+            shared.asset_ids.clear();
+            shared.history.clear();
+            bundle.notes.push(shared);
+        } else {
+            return Err(format!("No entry found with ID '{}'.", id));
+        }
+    }
+    Ok(bundle)
+}
+
+fn derive_share_key(
+    passphrase: &str,
+    salt: &str,
+    mem: u32,
+    iter: u32,
+    par: u32,
+) -> Result<Zeroizing<[u8; 32]>> {
+    if mem > MAX_KDF_MEMORY {
+        return Err(anyhow!("Share file requests unreasonable KDF parameters."));
+    }
+    let params =
+        Params::new(mem, iter, par, Some(32)).map_err(|e| anyhow!("KDF param error: {}", e))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let salt = SaltString::from_b64(salt).map_err(|_| anyhow!("Invalid salt"))?;
+    let hash = argon2
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map_err(|_| anyhow!("Hashing failed"))?;
+    let hash_bytes = hash.hash.ok_or_else(|| anyhow!("No KDF output"))?;
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(hash_bytes.as_bytes());
+    Ok(Zeroizing::new(key))
+}
+
+/// Key derivation for v2 bundles, over the raw salt.
+fn derive_kdf_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    match kdf {
+        KdfParams::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => {
+            if memory > MAX_KDF_MEMORY {
+                return Err(anyhow!("Share file requests unreasonable KDF parameters."));
+            }
+            let params = Params::new(memory, iterations, parallelism, Some(32))
+                .map_err(|e| anyhow!("KDF param error: {}", e))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+                .map_err(|_| anyhow!("Hashing failed"))?;
+        }
+        KdfParams::Scrypt { log_n, r, p } => {
+            // scrypt uses 128 * r * 2^log_n bytes, i.e. r * 2^log_n / 8 KiB.
+            let too_costly = log_n >= 32 || (u64::from(r) << log_n) / 8 > u64::from(MAX_KDF_MEMORY);
+            if r == 0 || r > MAX_SCRYPT_R || p == 0 || p > MAX_SCRYPT_P || too_costly {
+                return Err(anyhow!("Share file requests unreasonable KDF parameters."));
+            }
+            let params = scrypt::Params::new(log_n, r, p, 32)
+                .map_err(|e| anyhow!("KDF param error: {}", e))?;
+            scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut *key)
+                .map_err(|_| anyhow!("Hashing failed"))?;
+        }
+    }
+    Ok(key)
+}
+
+/// Encrypts a bundle with the passphrase and returns the file bytes.
+pub fn seal_bundle(bundle: &ShareBundle, passphrase: &str, kdf: ShareKdf) -> Result<Vec<u8>> {
+    let (memory, iterations, parallelism) = SHARE_KDF;
+    let params = match kdf {
+        ShareKdf::Argon2id => KdfParams::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        },
+        ShareKdf::Scrypt => SHARE_SCRYPT,
+    };
+    seal_bundle_with(bundle, passphrase, params)
+}
+
+fn seal_bundle_with(bundle: &ShareBundle, passphrase: &str, kdf: KdfParams) -> Result<Vec<u8>> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!(
+            "The share passphrase must be at least {} characters.",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    let plaintext = Zeroizing::new(serde_json::to_vec(bundle)?);
+    match kdf {
+        KdfParams::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => seal_v1(&plaintext, passphrase, (memory, iterations, parallelism)),
+        KdfParams::Scrypt { .. } => seal_v2(&plaintext, passphrase, kdf),
+    }
+}
+
+fn random_nonce() -> Result<[u8; NONCE_LEN]> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng
+        .try_fill_bytes(&mut nonce)
+        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    Ok(nonce)
+}
+
+fn seal_v1(
+    plaintext: &[u8],
+    passphrase: &str,
+    (mem, iter, par): (u32, u32, u32),
+) -> Result<Vec<u8>> {
+    let salt = SaltString::generate(&mut Argon2OsRng).as_str().to_string();
+    let nonce = random_nonce()?;
+
+    let mut package = SharePackage {
+        magic: SHARE_MAGIC,
+        version: SHARE_VERSION,
+        kdf_memory: mem,
+        kdf_iterations: iter,
+        kdf_parallelism: par,
+        salt,
+        nonce: nonce.to_vec(),
+        ciphertext: Vec::new(),
+    };
+
+    let key = derive_share_key(passphrase, &package.salt, mem, iter, par)?;
+    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let aad = package.header_aad();
+    package.ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt share bundle"))?;
+
+    Ok(bincode::serialize(&package)?)
+}
+
+fn seal_v2(plaintext: &[u8], passphrase: &str, kdf: KdfParams) -> Result<Vec<u8>> {
+    let mut salt = vec![0u8; SALT_LEN];
+    OsRng
+        .try_fill_bytes(&mut salt)
+        .map_err(|e| anyhow!("OS RNG failed: {}", e))?;
+    let nonce = random_nonce()?;
+
+    let mut package = KdfPackage {
+        magic: SHARE_MAGIC,
+        version: SHARE_VERSION_KDF,
+        kdf,
+        salt,
+        nonce: nonce.to_vec(),
+        ciphertext: Vec::new(),
+    };
+
+    let key = derive_kdf_key(passphrase, &package.salt, kdf)?;
+    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let aad = package.header_aad()?;
+    package.ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt share bundle"))?;
+
+    Ok(bincode::serialize(&package)?)
+}
+
+/// Decrypts share file bytes. A wrong passphrase and a tampered file are
+/// indistinguishable by design and produce the same error.
+pub fn open_bundle(bytes: &[u8], passphrase: &str) -> Result<ShareBundle> {
+    if bytes.len() > MAX_BUNDLE_BYTES {
+        return Err(anyhow!("File is too large to be a share bundle."));
+    }
+    if bytes.len() < 12 || bytes[..8] != SHARE_MAGIC {
+        return Err(anyhow!("Not a QRE share file"));
+    }
+    let version = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+    let plaintext = match version {
+        SHARE_VERSION => open_v1(bytes, passphrase)?,
+        SHARE_VERSION_KDF => open_v2(bytes, passphrase)?,
+        _ => {
+            return Err(anyhow!("Unsupported share file version: {}.", version));
+        }
+    };
+    serde_json::from_slice(&plaintext).context("Share bundle content is malformed")
+}
+
+fn package_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_BUNDLE_BYTES as u64)
+}
+
+fn open_v1(bytes: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let package: SharePackage = package_options()
+        .deserialize(bytes)
+        .context("Not a QRE share file")?;
+    if package.nonce.len() != NONCE_LEN {
+        return Err(anyhow!("Share file is corrupted."));
+    }
+
+    let key = derive_share_key(
+        passphrase,
+        &package.salt,
+        package.kdf_memory,
+        package.kdf_iterations,
+        package.kdf_parallelism,
+    )?;
+    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let aad = package.header_aad();
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&package.nonce),
+            Payload {
+                msg: &package.ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Wrong passphrase or corrupted share file."))?;
+    Ok(Zeroizing::new(plaintext))
+}
+
+fn open_v2(bytes: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let package: KdfPackage = package_options()
+        .deserialize(bytes)
+        .context("Not a QRE share file")?;
+    if package.nonce.len() != NONCE_LEN || package.salt.len() < SALT_LEN {
+        return Err(anyhow!("Share file is corrupted."));
+    }
+
+    let key = derive_kdf_key(passphrase, &package.salt, package.kdf)?;
+    let cipher = Aes256Gcm::new_from_slice(&*key).map_err(|e| anyhow!("Cipher init: {}", e))?;
+    let aad = package.header_aad()?;
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&package.nonce),
+            Payload {
+                msg: &package.ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Wrong passphrase or corrupted share file."))?;
+    Ok(Zeroizing::new(plaintext))
+}
+
+// ==========================================
+// --- IMPORT & CONFLICTS ---
+// ==========================================
+
+pub(crate) fn password_conflict(
+    existing: &PasswordVault,
+    incoming: &VaultEntry,
+) -> Option<(usize, ConflictKind)> {
+    if let Some(idx) = existing.entries.iter().position(|e| e.id == incoming.id) {
+        return Some((idx, ConflictKind::SameId));
+    }
+    existing
+        .entries
+        .iter()
+        .position(|e| {
+            e.service.eq_ignore_ascii_case(&incoming.service)
+                && e.username.eq_ignore_ascii_case(&incoming.username)
+        })
+        .map(|idx| (idx, ConflictKind::DuplicateLogin))
+}
+
+fn note_conflict(existing: &NotesVault, incoming: &NoteEntry) -> Option<(usize, ConflictKind)> {
+    if let Some(idx) = existing.entries.iter().position(|n| n.id == incoming.id) {
+        return Some((idx, ConflictKind::SameId));
+    }
+    existing
+        .entries
+        .iter()
+        .position(|n| n.title.trim() == incoming.title.trim())
+        .map(|idx| (idx, ConflictKind::DuplicateTitle))
+}
+
+/// Lists every incoming entry and whether it collides with something already in the vaults.
+pub fn preview_import(
+    bundle: &ShareBundle,
+    passwords: &PasswordVault,
+    notes: &NotesVault,
+) -> Vec<ImportPreviewItem> {
+    let pw_items = bundle.passwords.iter().map(|e| ImportPreviewItem {
+        id: e.id.clone(),
+        kind: SharedKind::Password,
+        title: e.service.clone(),
+        conflict: password_conflict(passwords, e).map(|(_, kind)| kind),
+    });
+    let note_items = bundle.notes.iter().map(|n| ImportPreviewItem {
+        id: n.id.clone(),
+        kind: SharedKind::Note,
+        title: n.title.clone(),
+        conflict: note_conflict(notes, n).map(|(_, kind)| kind),
+    });
+    pw_items.chain(note_items).collect()
+}
+
+/// Merges the bundle into the vaults. `resolutions` is keyed by incoming entry ID;
+/// conflicting entries without an explicit choice use `default_resolution`.
+/// Entries without a conflict are always added.
+pub fn apply_import(
+    bundle: &ShareBundle,
+    passwords: &mut PasswordVault,
+    notes: &mut NotesVault,
+    resolutions: &HashMap<String, ConflictResolution>,
+    default_resolution: ConflictResolution,
+    now: i64,
+) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    let choice = |id: &str| *resolutions.get(id).unwrap_or(&default_resolution);
+    import_password_entries(&bundle.passwords, passwords, &choice, now, &mut summary);
+
+    for incoming in &bundle.notes {
+        let mut note = incoming.clone();
+        note.updated_at = now;
+        note.image_ids.clear();
+        note.asset_ids.clear();
+        note.history.clear();
+        let history_limit = notes.history_limit;
+        match note_conflict(notes, incoming) {
+            None => {
+                notes.entries.push(note);
+                summary.added += 1;
+            }
+            Some((idx, _)) => match choice(&incoming.id) {
+                ConflictResolution::Skip => summary.skipped += 1,
+                ConflictResolution::Overwrite => {
+                    let existing = &mut notes.entries[idx];
+                    note.id = existing.id.clone();
+                    note.created_at = existing.created_at;
+                    note.image_ids = std::mem::take(&mut existing.image_ids);
+                    note.asset_ids = std::mem::take(&mut existing.asset_ids);
+                    note.carry_history_from(existing, now, history_limit);
+                    *existing = note;
+                    summary.overwritten += 1;
+                }
+                ConflictResolution::KeepBoth => {
+                    note.id = uuid::Uuid::new_v4().to_string();
+                    note.created_at = now;
+                    notes.entries.push(note);
+                    summary.added += 1;
+                }
+            },
+        }
+    }
+
+    summary
+}
+
+/// The password half of `apply_import`, shared with the password manager import
+/// (vault_import.rs). Incoming entries are checked one by one, so a later entry also
+/// collides with an earlier one it duplicates.
+pub(crate) fn import_password_entries(
+    incoming_entries: &[VaultEntry],
+    passwords: &mut PasswordVault,
+    choice: &dyn Fn(&str) -> ConflictResolution,
+    now: i64,
+    summary: &mut ImportSummary,
+) {
+    for incoming in incoming_entries {
+        let mut entry = incoming.clone();
+        entry.updated_at = now;
+        entry.history.clear();
+        entry.attachments.clear();
+        let history_limit = passwords.history_limit;
+        match password_conflict(passwords, incoming) {
+            None => {
+                passwords.entries.push(entry);
+                summary.added += 1;
+            }
+            Some((idx, _)) => match choice(&incoming.id) {
+                ConflictResolution::Skip => summary.skipped += 1,
+                ConflictResolution::Overwrite => {
+                    let existing = &mut passwords.entries[idx];
+                    entry.id = existing.id.clone();
+                    entry.created_at = existing.created_at;
+                    entry.last_used_at = existing.last_used_at;
+                    entry.use_count = existing.use_count;
+                    entry.deletion_status = existing.deletion_status.clone();
+                    entry.attachments = existing.attachments.clone();
+                    entry.carry_history_from(existing, now, history_limit);
+                    *existing = entry;
+                    summary.overwritten += 1;
+                }
+                ConflictResolution::KeepBoth => {
+                    entry.id = uuid::Uuid::new_v4().to_string();
+                    entry.created_at = now;
+                    passwords.entries.push(entry);
+                    summary.added += 1;
+                }
+            },
+        }
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap KDFs so the suite stays fast; the format is identical.
+    const TEST_KDF: KdfParams = KdfParams::Argon2id {
+        memory: 8_192,
+        iterations: 1,
+        parallelism: 1,
+    };
+    const TEST_SCRYPT: KdfParams = KdfParams::Scrypt {
+        log_n: 10,
+        r: 8,
+        p: 1,
+    };
+
+    fn pw(id: &str, service: &str, user: &str, password: &str) -> VaultEntry {
+        VaultEntry {
+            id: id.to_string(),
+            service: service.to_string(),
+            username: user.to_string(),
+            password: password.to_string(),
+            notes: String::new(),
+            created_at: 1,
+            updated_at: 1,
+            url: String::new(),
+            color: String::new(),
+            is_pinned: false,
+            totp_secret: None,
+            url_match: Default::default(),
+            url_match_pattern: None,
+            last_used_at: Some(50),
+            use_count: 3,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    fn note(id: &str, title: &str) -> NoteEntry {
+        NoteEntry {
+            id: id.to_string(),
+            title: title.to_string(),
+            content: "body".to_string(),
+            created_at: 1,
+            updated_at: 1,
+            is_pinned: false,
+            tags: vec![],
+            image_ids: vec![],
+            asset_ids: vec![],
+            history: vec![],
+        }
+    }
+
+    fn sender_vaults() -> (PasswordVault, NotesVault) {
+        let mut passwords = PasswordVault::new();
+        passwords.entries = vec![
+            pw("p1", "Wifi", "home", "s3cret"),
+            pw("p2", "Bank", "me", "private"),
+        ];
+        let mut notes = NotesVault::new();
+        notes.entries = vec![note("n1", "Door code")];
+        (passwords, notes)
+    }
+
+    #[test]
+    fn test_bundle_roundtrip_strips_usage() {
+        let (passwords, notes) = sender_vaults();
+        let ids = vec!["p1".to_string(), "n1".to_string()];
+        let bundle = build_bundle(&passwords, &notes, &ids, 100).unwrap();
+
+        let bytes = seal_bundle_with(&bundle, "correct horse", TEST_KDF).unwrap();
+        let opened = open_bundle(&bytes, "correct horse").unwrap();
+
+        assert_eq!(opened.passwords.len(), 1);
+        assert_eq!(opened.passwords[0].password, "s3cret");
+        assert_eq!(opened.passwords[0].use_count, 0);
+        assert!(opened.passwords[0].last_used_at.is_none());
+        assert_eq!(opened.notes[0].title, "Door code");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering_rejected() {
+        let (passwords, notes) = sender_vaults();
+        let bundle = build_bundle(&passwords, &notes, &["p1".to_string()], 100).unwrap();
+        let mut bytes = seal_bundle_with(&bundle, "correct horse", TEST_KDF).unwrap();
+
+        assert!(open_bundle(&bytes, "wrong horse!").is_err());
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        assert!(open_bundle(&bytes, "correct horse").is_err());
+        assert!(open_bundle(b"garbage", "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_scrypt_bundle_follows_documented_layout() {
+        let (passwords, notes) = sender_vaults();
+        let bundle = build_bundle(&passwords, &notes, &["p2".to_string()], 100).unwrap();
+        let bytes = seal_bundle_with(&bundle, "correct horse", TEST_SCRYPT).unwrap();
+        assert_eq!(
+            open_bundle(&bytes, "correct horse").unwrap().passwords[0].password,
+            "private"
+        );
+        assert!(open_bundle(&bytes, "wrong horse!").is_err());
+
+        // Decrypt by hand, as another tool would from the header comment.
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[..8], b"QRESHARE");
+        assert_eq!(u32_at(8), 2);
+        assert_eq!(u32_at(12), 1, "kdf id 1 is scrypt");
+        let (log_n, r, p) = (bytes[16], u32_at(17), u32_at(21));
+        let salt_len = u64_at(25);
+        let salt = &bytes[33..33 + salt_len];
+        let nonce_at = 33 + salt_len;
+        let nonce = &bytes[nonce_at + 8..nonce_at + 20];
+        let ct_at = nonce_at + 20;
+        let ciphertext = &bytes[ct_at + 8..ct_at + 8 + u64_at(ct_at)];
+
+        let mut key = [0u8; 32];
+        let params = scrypt::Params::new(log_n, r, p, 32).unwrap();
+        scrypt::scrypt(b"correct horse", salt, &params, &mut key).unwrap();
+        let plaintext = Aes256Gcm::new_from_slice(&key)
+            .unwrap()
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &bytes[..ct_at],
+                },
+            )
+            .unwrap();
+        let opened: ShareBundle = serde_json::from_slice(&plaintext).unwrap();
+        assert_eq!(opened.passwords[0].service, "Bank");
+
+        // The parameters are authenticated, and absurd ones are refused before running.
+        let mut weaker = bytes.clone();
+        weaker[16] = 9;
+        assert!(open_bundle(&weaker, "correct horse").is_err());
+        let mut huge = bytes.clone();
+        huge[16] = 40;
+        let err = open_bundle(&huge, "correct horse").unwrap_err().to_string();
+        assert!(err.contains("unreasonable"), "{}", err);
+    }
+
+    #[test]
+    fn test_default_kdf_still_writes_v1() {
+        let bundle = ShareBundle::default();
+        let bytes = seal_bundle_with(&bundle, "correct horse", TEST_KDF).unwrap();
+        assert_eq!(&bytes[8..12], &SHARE_VERSION.to_le_bytes());
+        assert!(open_bundle(&bytes, "correct horse").is_ok());
+
+        let mut future = bytes.clone();
+        future[8] = 9;
+        let err = open_bundle(&future, "correct horse")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unsupported"), "{}", err);
+    }
+
+    #[test]
+    fn test_build_rejects_unknown_ids_and_short_passphrase() {
+        let (passwords, notes) = sender_vaults();
+        assert!(build_bundle(&passwords, &notes, &[], 0).is_err());
+        assert!(build_bundle(&passwords, &notes, &["nope".to_string()], 0).is_err());
+
+        let bundle = build_bundle(&passwords, &notes, &["p1".to_string()], 0).unwrap();
+        assert!(seal_bundle_with(&bundle, "short", TEST_KDF).is_err());
+    }
+
+    #[test]
+    fn test_import_conflict_handling() {
+        let (sender_pw, sender_notes) = sender_vaults();
+        let ids = vec!["p1".to_string(), "p2".to_string(), "n1".to_string()];
+        let bundle = build_bundle(&sender_pw, &sender_notes, &ids, 100).unwrap();
+
+        // Recipient already has a Wifi login (different ID) and the same note (same ID).
+        let mut passwords = PasswordVault::new();
+        passwords.entries = vec![pw("mine", "wifi", "HOME", "old")];
+        let mut notes = NotesVault::new();
+        notes.entries = vec![note("n1", "Door code")];
+
+        let preview = preview_import(&bundle, &passwords, &notes);
+        let conflicts: Vec<Option<ConflictKind>> = preview.iter().map(|p| p.conflict).collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                Some(ConflictKind::DuplicateLogin),
+                None,
+                Some(ConflictKind::SameId)
+            ]
+        );
+
+        let mut resolutions = HashMap::new();
+        resolutions.insert("p1".to_string(), ConflictResolution::Overwrite);
+        let summary = apply_import(
+            &bundle,
+            &mut passwords,
+            &mut notes,
+            &resolutions,
+            ConflictResolution::Skip,
+            200,
+        );
+
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 1,
+                overwritten: 1,
+                skipped: 1
+            }
+        );
+        let wifi = passwords.entries.iter().find(|e| e.id == "mine").unwrap();
+        assert_eq!(wifi.password, "s3cret", "overwrite replaces content");
+        assert_eq!(wifi.use_count, 3, "but keeps the recipient's own usage");
+        assert_eq!(notes.entries.len(), 1);
+        assert!(passwords.validate().is_ok());
+    }
+
+    #[test]
+    fn test_keep_both_assigns_fresh_ids() {
+        let (sender_pw, sender_notes) = sender_vaults();
+        let bundle = build_bundle(&sender_pw, &sender_notes, &["n1".to_string()], 100).unwrap();
+        let mut passwords = PasswordVault::new();
+        let mut notes = NotesVault::new();
+        notes.entries = vec![note("n1", "Door code")];
+
+        let summary = apply_import(
+            &bundle,
+            &mut passwords,
+            &mut notes,
+            &HashMap::new(),
+            ConflictResolution::KeepBoth,
+            200,
+        );
+        assert_eq!(summary.added, 1);
+        assert_eq!(notes.entries.len(), 2);
+        assert!(notes.validate().is_ok(), "IDs must stay unique");
+    }
+}
+
+// --- END OF FILE sharing.rs ---
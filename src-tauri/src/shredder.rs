@@ -1,5 +1,9 @@
 // --- START OF FILE shredder.rs ---
 
+use crate::av_guard;
+use crate::power;
+use crate::progress::ProgressEmitter;
+use crate::random_names::{self, NameKind};
 use anyhow::{anyhow, Result};
 use rand::Rng;
 use std::fs::{self, OpenOptions};
@@ -79,13 +83,14 @@ pub struct DryRunResult {
 }
 
 /// The specific data destruction algorithm the user selected.
-#[derive(serde::Deserialize, Clone, Copy)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ShredMethod {
-    Simple,   // 1 pass: overwrite with 0x00
+    Simple, // 1 pass: overwrite with 0x00
+    #[default]
     DoD3Pass, // 3 passes: US DoD 5220.22-M standard
     DoD7Pass, // 7 passes: DoD 5220.22-M Extended
-    Gutmann,  // 35 passes: Peter Gutmann method
+    Gutmann, // 35 passes: Peter Gutmann method
 }
 
 // ─── Free-space wipe structs ────────────────────────────────────────────────
@@ -322,7 +327,7 @@ pub fn dry_run(paths: Vec<String>) -> Result<DryRunResult> {
 /// physically commit each overwrite to disk before the next pass begins.
 /// Without this, the OS page cache can coalesce writes, making some passes no-ops.
 ///
-/// FIX #5: The file is renamed to a random hex name before deletion so that the
+/// FIX #5: The file is renamed to a random UUID before deletion so that the
 /// original filename cannot be recovered from directory entry forensics.
 ///
 /// FIX #10: `bytes_before` (sum of all bytes from completed files × their passes)
@@ -330,7 +335,7 @@ pub fn dry_run(paths: Vec<String>) -> Result<DryRunResult> {
 fn shred_file<R: tauri::Runtime>(
     path: &Path,
     method: ShredMethod,
-    app_handle: &tauri::AppHandle<R>,
+    progress_events: &ProgressEmitter<R, ShredProgress>,
     file_index: usize,
     total_files: usize,
     bytes_before: u64,
@@ -362,7 +367,10 @@ fn shred_file<R: tauri::Runtime>(
 
     let total_passes = passes.len() as u8;
 
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    // Open, rename and delete are retried while an on-access scanner holds the file.
+    let mut file = av_guard::with_retry("open", path, || {
+        OpenOptions::new().read(true).write(true).open(path)
+    })?;
 
     for (pass_num, pass_type) in passes.iter().enumerate() {
         if cancel_flag.load(Ordering::Relaxed) {
@@ -394,21 +402,18 @@ fn shred_file<R: tauri::Runtime>(
             total_bytes: total_bytes_all,
         };
 
-        let _ = app_handle.emit("shred-progress", progress);
+        progress_events.emit(progress);
     }
 
     // Final sync before closing.
     file.sync_all()?;
     drop(file);
 
-    // FIX #5: Rename the file to a random hex name so the original filename
+    // FIX #5: Rename the file to a random UUID so the original filename
     // cannot be recovered from forensic directory analysis.
-    let mut rng = rand::rng();
-    let random_name: String = (0..16)
-        .map(|_| format!("{:02x}", rng.random::<u8>()))
-        .collect();
+    let random_name = random_names::generate(NameKind::Uuid)?;
     let renamed_path = path.with_file_name(random_name);
-    fs::rename(path, &renamed_path)?;
+    av_guard::with_retry("rename", path, || fs::rename(path, &renamed_path))?;
 
     // FIX #5 cont.: Sync directory entry so the rename reaches disk before unlink.
     // We do this by opening and syncing the parent directory (Unix only).
@@ -419,7 +424,7 @@ fn shred_file<R: tauri::Runtime>(
         }
     }
 
-    fs::remove_file(&renamed_path)?;
+    av_guard::with_retry("delete", &renamed_path, || fs::remove_file(&renamed_path))?;
 
     if renamed_path.exists() {
         return Err(anyhow!("File still exists after deletion attempt"));
@@ -588,9 +593,13 @@ pub fn batch_shred<R: tauri::Runtime>(
         .sum();
 
     // Phase 2: Shred the valid files sequentially, tracking cumulative bytes.
+    // Per-pass events are coalesced; the last one is flushed when the emitter drops.
+    let progress_events = ProgressEmitter::new(app_handle, "shred-progress");
     let mut bytes_before: u64 = 0;
 
     for (idx, (original_path, canonical_path)) in validated.into_iter().enumerate() {
+        // Low battery: wait between files (a cancel while paused ends the batch below).
+        power::wait_for_power(Some(&cancel_flag));
         if cancel_flag.load(Ordering::Relaxed) {
             failed.push(FailedFile {
                 path: "Remaining files".to_string(),
@@ -604,7 +613,7 @@ pub fn batch_shred<R: tauri::Runtime>(
         match shred_file(
             &canonical_path,
             method,
-            app_handle,
+            &progress_events,
             idx,
             total_files,
             bytes_before,
@@ -845,6 +854,16 @@ pub fn wipe_free_space<R: tauri::Runtime>(
                             phase: "Writing".to_string(),
                         },
                     );
+                    if power::should_pause(&power::read_battery(), &power::policy()) {
+                        let _ = app_handle.emit(
+                            "wipe-progress",
+                            WipeProgress {
+                                bytes_written,
+                                phase: "Paused (low battery)".to_string(),
+                            },
+                        );
+                        power::wait_for_power(Some(&cancel_flag));
+                    }
                 }
             }
             // ENOSPC on Unix (28) / ERROR_DISK_FULL on Windows (112): drive is full — done.
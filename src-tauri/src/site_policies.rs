@@ -0,0 +1,336 @@
+// --- START OF FILE site_policies.rs ---
+
+use crate::passwords::{base_domain, url_host};
+use rand::{rngs::OsRng, TryRngCore};
+use serde::Serialize;
+
+// ==========================================
+// --- DATA STRUCTURES ---
+// ==========================================
+
+/// Password rules a site enforces. Generated passwords use lower- and upper-case letters
+/// and digits, plus the listed symbols.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SitePolicy {
+    pub name: &'static str,
+    /// Registrable domains the rules apply to (matched against the page URL).
+    pub domains: &'static [&'static str],
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Symbols the site accepts; empty when it accepts letters and digits only.
+    pub symbols: &'static str,
+    pub require_symbol: bool,
+    /// Longest run of one repeated character the site accepts (0 = no limit).
+    pub max_consecutive: usize,
+}
+
+/// What `generate_password_for` returns: the password and the rules it follows.
+#[derive(Serialize, Debug, Clone)]
+pub struct GeneratedPassword {
+    pub password: String,
+    /// `None` when the site is not in the dataset and the default policy was used.
+    pub policy: Option<SitePolicy>,
+}
+
+/// Used for sites without known restrictions.
+pub const DEFAULT_POLICY: SitePolicy = SitePolicy {
+    name: "Default",
+    domains: &[],
+    min_length: 12,
+    max_length: 128,
+    symbols: "!@#$%^&*()-_=+[]{};:,.?/~",
+    require_symbol: true,
+    max_consecutive: 0,
+};
+
+/// Length used when the caller does not ask for one (clamped to the site's range).
+pub const DEFAULT_LENGTH: usize = 20;
+
+// ==========================================
+// --- CURATED DATASET ---
+// ==========================================
+// Only sites that reject common generated passwords (length caps, restricted symbols,
+// repeated characters). Where a site's documented rules are ambiguous the stricter
+// reading is used: a password that satisfies a stricter rule is still accepted.
+
+pub const SITE_POLICIES: &[SitePolicy] = &[
+    SitePolicy {
+        name: "American Express",
+        domains: &["americanexpress.com"],
+        min_length: 8,
+        max_length: 20,
+        symbols: "%&_?#=",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Bank of America",
+        domains: &["bankofamerica.com"],
+        min_length: 8,
+        max_length: 20,
+        symbols: "@#$%^&*()_+=",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Chase",
+        domains: &["chase.com"],
+        min_length: 8,
+        max_length: 32,
+        symbols: "!#$%+/=@~",
+        require_symbol: true,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Citi",
+        domains: &["citi.com", "citibank.com"],
+        min_length: 8,
+        max_length: 32,
+        symbols: "!#$%&*+-./:;<=>?@^_~",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Wells Fargo",
+        domains: &["wellsfargo.com"],
+        min_length: 8,
+        max_length: 32,
+        symbols: "!#$%&*+-.=?@^_",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Capital One",
+        domains: &["capitalone.com"],
+        min_length: 8,
+        max_length: 32,
+        symbols: "!#$%&*?@",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "PayPal",
+        domains: &["paypal.com"],
+        min_length: 8,
+        max_length: 20,
+        symbols: "!@#$%^&*()",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Southwest Airlines",
+        domains: &["southwest.com"],
+        min_length: 8,
+        max_length: 16,
+        symbols: "!@#$%^*(),.;:/",
+        require_symbol: false,
+        max_consecutive: 0,
+    },
+    SitePolicy {
+        name: "USPS",
+        domains: &["usps.com"],
+        min_length: 8,
+        max_length: 20,
+        symbols: "-!#&()+,./?@",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Apple",
+        domains: &["apple.com", "icloud.com"],
+        min_length: 8,
+        max_length: 32,
+        symbols: "-!#$%&()*+,./:;=?@[]^_{}~",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Microsoft",
+        domains: &["microsoft.com", "live.com", "outlook.com"],
+        min_length: 8,
+        max_length: 64,
+        symbols: "-!#$%&()*+,./:;=?@[]^_{}~",
+        require_symbol: false,
+        max_consecutive: 0,
+    },
+    SitePolicy {
+        name: "eBay",
+        domains: &["ebay.com", "ebay.co.uk", "ebay.de"],
+        min_length: 8,
+        max_length: 64,
+        symbols: "!@#$%^&*",
+        require_symbol: false,
+        max_consecutive: 0,
+    },
+    SitePolicy {
+        name: "HSBC",
+        domains: &["hsbc.com", "hsbc.co.uk"],
+        min_length: 8,
+        max_length: 30,
+        symbols: "",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+    SitePolicy {
+        name: "Vanguard",
+        domains: &["vanguard.com"],
+        min_length: 8,
+        max_length: 20,
+        symbols: "!@#$%^&*",
+        require_symbol: false,
+        max_consecutive: 2,
+    },
+];
+
+// ==========================================
+// --- LOOKUP & GENERATION ---
+// ==========================================
+
+/// Finds the rules for a URL by comparing registrable domains.
+pub fn lookup_policy(url: &str) -> Option<&'static SitePolicy> {
+    let domain = base_domain(&url_host(url)?);
+    SITE_POLICIES
+        .iter()
+        .find(|p| p.domains.contains(&domain.as_str()))
+}
+
+/// Uniform index in `0..n` (rejection sampling, so no modulo bias).
+fn random_index(n: usize) -> Result<usize, String> {
+    let n = n as u64;
+    let zone = u64::MAX - (u64::MAX % n);
+    loop {
+        let r = OsRng.try_next_u64().map_err(|e| e.to_string())?;
+        if r < zone {
+            return Ok((r % n) as usize);
+        }
+    }
+}
+
+fn longest_run(chars: &[char]) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for (i, c) in chars.iter().enumerate() {
+        run = if i > 0 && chars[i - 1] == *c {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+    }
+    longest
+}
+
+/// True if `password` satisfies every rule of `policy`.
+pub fn satisfies(policy: &SitePolicy, password: &str) -> bool {
+    let chars: Vec<char> = password.chars().collect();
+    (policy.min_length..=policy.max_length).contains(&chars.len())
+        && chars.iter().any(|c| c.is_ascii_lowercase())
+        && chars.iter().any(|c| c.is_ascii_uppercase())
+        && chars.iter().any(|c| c.is_ascii_digit())
+        && (!policy.require_symbol || chars.iter().any(|c| policy.symbols.contains(*c)))
+        && chars
+            .iter()
+            .all(|c| c.is_ascii_alphanumeric() || policy.symbols.contains(*c))
+        && (policy.max_consecutive == 0 || longest_run(&chars) <= policy.max_consecutive)
+}
+
+/// Generates a random password that `policy` accepts. `length` defaults to
+/// `DEFAULT_LENGTH` and is clamped to the policy's range.
+pub fn generate(policy: &SitePolicy, length: Option<usize>) -> Result<String, String> {
+    let length = length
+        .unwrap_or(DEFAULT_LENGTH)
+        .clamp(policy.min_length, policy.max_length);
+    let alphabet: Vec<char> = ('a'..='z')
+        .chain('A'..='Z')
+        .chain('0'..='9')
+        .chain(policy.symbols.chars())
+        .collect();
+
+    // Drawing whole candidates until one passes keeps every accepted password equally
+    // likely; with length >= 8 a candidate fails the class checks only rarely.
+    for _ in 0..1000 {
+        let candidate: String = (0..length)
+            .map(|_| random_index(alphabet.len()).map(|i| alphabet[i]))
+            .collect::<Result<_, _>>()?;
+        if satisfies(policy, &candidate) {
+            return Ok(candidate);
+        }
+    }
+    Err("Could not generate a password matching the site's rules.".to_string())
+}
+
+/// Picks the policy for `url` (or the default) and generates a password for it.
+pub fn generate_for_url(url: &str, length: Option<usize>) -> Result<GeneratedPassword, String> {
+    let policy = lookup_policy(url).copied();
+    let password = generate(policy.as_ref().unwrap_or(&DEFAULT_POLICY), length)?;
+    Ok(GeneratedPassword { password, policy })
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dataset_is_well_formed() {
+        for policy in SITE_POLICIES {
+            assert!(!policy.domains.is_empty(), "{}", policy.name);
+            assert!(
+                policy.min_length >= 8 && policy.min_length <= policy.max_length,
+                "{}",
+                policy.name
+            );
+            assert!(
+                policy.symbols.chars().all(|c| c.is_ascii_punctuation()),
+                "{}",
+                policy.name
+            );
+            assert!(!policy.require_symbol || !policy.symbols.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_generated_passwords_follow_site_rules() {
+        let generated = generate_for_url("https://secure.chase.com/web/auth", None).unwrap();
+        let policy = generated.policy.unwrap();
+        assert_eq!(policy.name, "Chase");
+        assert_eq!(generated.password.len(), DEFAULT_LENGTH);
+        for _ in 0..50 {
+            let password = generate(&policy, None).unwrap();
+            assert!(satisfies(&policy, &password), "{}", password);
+        }
+
+        let southwest = lookup_policy("https://www.southwest.com").unwrap();
+        assert_eq!(generate(southwest, Some(40)).unwrap().len(), 16);
+
+        let hsbc = lookup_policy("https://www.hsbc.co.uk/login").unwrap();
+        assert!(generate(hsbc, None)
+            .unwrap()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_unknown_sites_use_default_policy() {
+        let generated = generate_for_url("https://forum.example.org", Some(4)).unwrap();
+        assert!(generated.policy.is_none());
+        assert_eq!(generated.password.len(), DEFAULT_POLICY.min_length);
+        assert!(satisfies(&DEFAULT_POLICY, &generated.password));
+        assert!(lookup_policy("https://chase.com.evil.io").is_none());
+    }
+
+    #[test]
+    fn test_satisfies_rejects_violations() {
+        let chase = lookup_policy("https://chase.com").unwrap();
+        assert!(satisfies(chase, "Abcdef12!x"));
+        assert!(!satisfies(chase, "Abcdef12xx"), "symbol required");
+        assert!(!satisfies(chase, "Abcdef12^x"), "symbol not allowed");
+        assert!(!satisfies(chase, "Abcdef12!!!"), "run of three");
+        assert!(!satisfies(chase, "Ab1!"), "too short");
+    }
+}
+
+// --- END OF FILE site_policies.rs ---
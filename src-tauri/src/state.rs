@@ -1,6 +1,11 @@
-use crate::keychain::MasterKey;
-use std::collections::HashMap;
+use crate::auto_lock::{AutoLock, AutoLockSettings};
+use crate::i18n::{AppError, ErrorCode};
+use crate::keychain::{MasterKey, OWNER_SLOT_NAME};
+use crate::panel_lock::PanelSessions;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 pub type VaultId = String; // "local" or a portable vault UUID
 
@@ -17,6 +22,28 @@ pub struct SessionState {
     ///   - Ghost-file detection: reject encrypt if source is on a portable drive.
     ///   - Vault routing: use the correct key when decrypting a file on a USB drive.
     pub portable_mounts: Arc<Mutex<HashMap<String, VaultId>>>,
+
+    /// Vaults unlocked with the guest (read-only) credential. Tracked per vault so an
+    /// owner login to one vault does not make a guest session on another writable.
+    /// Every command that mutates, exports or shreds calls `ensure_writable()` first with
+    /// the vault it changes ("local" for file and system tools, which belong to the
+    /// desktop session); the frontend hiding those buttons is only cosmetic.
+    pub read_only: Arc<Mutex<HashSet<VaultId>>>,
+
+    /// Set when the local vault was unlocked with the duress password: "local" then
+    /// resolves to the decoy vault's files (see duress.rs). Cleared by the callers of
+    /// `lock_all`, after the logout is recorded in the decoy vault's audit log.
+    pub decoy: Arc<AtomicBool>,
+
+    /// Maps VaultId → name of the key slot that unlocked it ("owner", "guest" or a
+    /// team member's slot name). Used for audit attribution and owner-only commands.
+    pub users: Arc<Mutex<HashMap<VaultId, String>>>,
+
+    /// PIN tokens for protected panels (clipboard history, notes); see panel_lock.rs.
+    pub panel_sessions: Arc<Mutex<PanelSessions>>,
+
+    /// Idle clock and settings for auto-lock; see auto_lock.rs.
+    pub auto_lock: Arc<Mutex<AutoLock>>,
 }
 
 impl SessionState {
@@ -24,6 +51,98 @@ impl SessionState {
         Self {
             vaults: Arc::new(Mutex::new(HashMap::new())),
             portable_mounts: Arc::new(Mutex::new(HashMap::new())),
+            read_only: Arc::new(Mutex::new(HashSet::new())),
+            decoy: Arc::new(AtomicBool::new(false)),
+            users: Arc::new(Mutex::new(HashMap::new())),
+            panel_sessions: Arc::new(Mutex::new(PanelSessions::default())),
+            auto_lock: Arc::new(Mutex::new(AutoLock::new(AutoLockSettings::default()))),
+        }
+    }
+
+    pub fn is_read_only(&self, vault_id: &str) -> bool {
+        self.read_only
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .contains(vault_id)
+    }
+
+    pub fn set_read_only(&self, vault_id: &str, read_only: bool) {
+        let mut guests = self.read_only.lock().unwrap_or_else(|p| p.into_inner());
+        if read_only {
+            guests.insert(vault_id.to_string());
+        } else {
+            guests.remove(vault_id);
+        }
+    }
+
+    pub fn is_decoy(&self) -> bool {
+        self.decoy.load(Ordering::SeqCst)
+    }
+
+    pub fn set_decoy(&self, decoy: bool) {
+        self.decoy.store(decoy, Ordering::SeqCst);
+    }
+
+    pub fn set_user(&self, vault_id: &str, user: &str) {
+        if let Ok(mut users) = self.users.lock() {
+            users.insert(vault_id.to_string(), user.to_string());
+        }
+    }
+
+    /// Slot name the vault was unlocked with. Vaults unlocked before slots were
+    /// tracked (or with the main password) are attributed to the owner.
+    pub fn user_for(&self, vault_id: &str) -> String {
+        self.users
+            .lock()
+            .ok()
+            .and_then(|users| users.get(vault_id).cloned())
+            .unwrap_or_else(|| OWNER_SLOT_NAME.to_string())
+    }
+
+    pub fn clear_users(&self) {
+        match self.users.lock() {
+            Ok(mut users) => users.clear(),
+            Err(poisoned) => poisoned.into_inner().clear(),
+        }
+    }
+
+    /// Records user activity for the auto-lock idle timer.
+    pub fn touch(&self) {
+        self.auto_lock
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .touch(Instant::now());
+    }
+
+    /// Locks every vault: drops (and so zeroizes) all master keys and forgets slot
+    /// users, the guest flag and panel tokens. Returns the vaults that were unlocked,
+    /// with the slot that unlocked each, for the audit log.
+    pub fn lock_all(&self) -> Vec<(VaultId, String)> {
+        let mut vaults = self.vaults.lock().unwrap_or_else(|p| p.into_inner());
+        let locked = vaults
+            .keys()
+            .map(|vault_id| (vault_id.clone(), self.user_for(vault_id)))
+            .collect();
+        vaults.clear();
+        drop(vaults);
+
+        self.clear_users();
+        self.read_only
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clear();
+        self.panel_sessions
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clear();
+        locked
+    }
+
+    /// Guard for state-changing commands: refuses if `vault_id` was unlocked as a guest.
+    pub fn ensure_writable(&self, vault_id: &str) -> Result<(), String> {
+        if self.is_read_only(vault_id) {
+            return Err(AppError::new(ErrorCode::ReadOnlySession).into());
         }
+        Ok(())
     }
 }
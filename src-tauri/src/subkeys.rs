@@ -0,0 +1,127 @@
+// --- START OF FILE subkeys.rs ---
+
+// ==========================================
+// --- PER-PURPOSE SUBKEYS (HKDF) ---
+// ==========================================
+// The master key is never used directly to wrap file keys. Each use gets its own
+// subkey, derived with HKDF-SHA256 and a purpose label, so a key exposed in one place
+// (a sync token, a wrapping key left in a crash dump) opens nothing else:
+//
+//   passwords vault  -> `passwords.qre` and the password entries' attachments
+//   notes vault      -> `notes.qre`, note images and `notes_assets.qre`
+//   vault data       -> every other vault container (bookmarks, clipboard, secrets...)
+//   file wrapping    -> files encrypted by the user (V4 and streamed .qre, archives)
+//   sync auth        -> reserved for authenticating to a sync server
+//   scan cache       -> HMAC keys of the file analyzer's cache entries (never wraps data)
+//
+// Migration: data written before subkeys wrapped its keys with the master key itself.
+// Readers try the purpose subkey first and fall back to the master key
+// (`wrapping_roots`); writers always use the subkey. Vault containers move over on
+// their next save or at once with `compact_data_dir`; user files when re-encrypted.
+//
+// Keys that already had a label of their own (clipboard journal, identity, container
+// metadata) keep their SHA-256 derivation: they were never shared with another use.
+
+use crate::keychain::MasterKey;
+use hkdf::Hkdf;
+use serde::Serialize;
+use sha2::Sha256;
+
+/// HKDF salt: fixed, as the master key is already uniformly random. Bumping the
+/// version derives a fresh set of subkeys.
+const SUBKEY_SALT: &[u8] = b"QRE_SUBKEYS_V1";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPurpose {
+    PasswordsVault,
+    NotesVault,
+    VaultData,
+    FileWrapping,
+    /// No sync client yet; reserved so its label is settled before one exists.
+    SyncAuth,
+    ScanCache,
+}
+
+impl KeyPurpose {
+    pub const ALL: [KeyPurpose; 6] = [
+        KeyPurpose::PasswordsVault,
+        KeyPurpose::NotesVault,
+        KeyPurpose::VaultData,
+        KeyPurpose::FileWrapping,
+        KeyPurpose::SyncAuth,
+        KeyPurpose::ScanCache,
+    ];
+
+    /// HKDF info string. Part of the on-disk format: never change one.
+    fn label(self) -> &'static [u8] {
+        match self {
+            KeyPurpose::PasswordsVault => b"qre/passwords-vault",
+            KeyPurpose::NotesVault => b"qre/notes-vault",
+            KeyPurpose::VaultData => b"qre/vault-data",
+            KeyPurpose::FileWrapping => b"qre/file-wrapping",
+            KeyPurpose::SyncAuth => b"qre/sync-auth",
+            KeyPurpose::ScanCache => b"qre/scan-cache",
+        }
+    }
+}
+
+/// The subkey for `purpose`. Same type as the master key so it can be handed to the
+/// existing wrapping code, and scrubbed on drop like it.
+pub fn derive(master_key: &MasterKey, purpose: KeyPurpose) -> MasterKey {
+    let mut subkey = MasterKey([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(SUBKEY_SALT), &master_key.0)
+        .expand(purpose.label(), &mut subkey.0)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    subkey
+}
+
+/// The keys a reader tries, in order: the purpose subkey, then the master key that
+/// data written before subkeys was wrapped with. The flag marks the latter.
+pub fn wrapping_roots(master_key: &MasterKey, purpose: KeyPurpose) -> [(MasterKey, bool); 2] {
+    [
+        (derive(master_key, purpose), false),
+        (master_key.clone(), true),
+    ]
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_subkeys_are_distinct_and_stable() {
+        let master = MasterKey([7u8; 32]);
+        let keys: HashSet<[u8; 32]> = KeyPurpose::ALL
+            .iter()
+            .map(|&p| derive(&master, p).0)
+            .chain([master.0])
+            .collect();
+        assert_eq!(keys.len(), KeyPurpose::ALL.len() + 1);
+        assert_eq!(
+            derive(&master, KeyPurpose::NotesVault).0,
+            derive(&master, KeyPurpose::NotesVault).0
+        );
+        assert_ne!(
+            derive(&MasterKey([8u8; 32]), KeyPurpose::NotesVault).0,
+            derive(&master, KeyPurpose::NotesVault).0
+        );
+    }
+
+    #[test]
+    fn test_known_answer() {
+        // Pins the labels and salt: changing either would lock users out of their data.
+        let master = MasterKey(core::array::from_fn(|i| i as u8));
+        assert_eq!(
+            data_encoding::HEXLOWER.encode(&derive(&master, KeyPurpose::FileWrapping).0),
+            "16ef62ea60816835ea477edbe35bf8798523c4ccef3f2d480bbfedaf5c9e7056"
+        );
+    }
+}
+
+// --- END OF FILE subkeys.rs ---
@@ -1,14 +1,16 @@
 // --- START OF FILE system_cleaner.rs ---
 
+use crate::progress::ProgressEmitter;
+use crate::resources;
 use anyhow::Result;
 use directories::BaseDirs;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tauri::Emitter;
 use walkdir::WalkDir;
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -18,8 +20,17 @@ use walkdir::WalkDir;
 const MAX_TOTAL_SIZE: u64 = 50 * 1024 * 1024 * 1024; // 50 GB hard safety limit
 const MAX_DEPTH: usize = 10;
 const LARGE_OPERATION_THRESHOLD: u64 = 10 * 1024 * 1024 * 1024; // Warn at 10 GB
+const MAX_LISTED_LEFTOVERS: usize = 500;
+
+/// Name of the junk size cache inside the app data directory.
+pub const SIZE_CACHE_FILE_NAME: &str = "junk_size_cache.json";
+/// A cached folder is listed again after this long even if its mtime did not change,
+/// so files that grew in place (logs) are picked up eventually.
+const SIZE_CACHE_MAX_AGE_SECS: u64 = 24 * 60 * 60;
 
 static CANCEL_FLAG: AtomicBool = AtomicBool::new(false);
+/// Set while a background size refresh runs, so repeated estimate scans start only one.
+static REFRESH_RUNNING: AtomicBool = AtomicBool::new(false);
 
 // ═══════════════════════════════════════════════════════════════════════════
 // DATA STRUCTURES
@@ -51,6 +62,31 @@ pub struct CleanResult {
     pub bytes_freed: u64,
     pub files_deleted: u64,
     pub errors: Vec<String>,
+    /// Files still present after the verification pass, with the reason for each.
+    pub leftovers: Vec<Leftover>,
+    /// Leftovers beyond `MAX_LISTED_LEFTOVERS`, counted but not listed.
+    pub leftovers_not_listed: u64,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LeftoverReason {
+    /// Open in another program (Windows sharing or lock violation).
+    InUse,
+    /// The account lacks the rights to delete it.
+    PermissionDenied,
+    Other,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Leftover {
+    pub path: String,
+    pub size: u64,
+    pub reason: LeftoverReason,
+    /// The OS error, for the details view.
+    pub detail: String,
+    /// Windows: queued for deletion at the next restart.
+    pub scheduled_for_reboot: bool,
 }
 
 #[derive(Serialize)]
@@ -569,18 +605,218 @@ fn add_target(
 pub fn scan_targets() -> Vec<JunkItem> {
     let mut items = get_system_targets();
 
-    items.par_iter_mut().for_each(|item| {
-        item.size = if item.path.starts_with("::") {
-            0
-        } else {
-            calculate_dir_size(Path::new(&item.path))
-        };
+    // Thread count follows low resource mode (resources.rs).
+    resources::run_parallel(|| {
+        items.par_iter_mut().for_each(|item| {
+            item.size = if item.path.starts_with("::") {
+                0
+            } else {
+                calculate_dir_size(Path::new(&item.path))
+            };
+        })
     });
 
     items.retain(|i| i.size > 0 || i.path.starts_with("::"));
     items
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// SIZE CACHE
+// ═══════════════════════════════════════════════════════════════════════════
+// Sizing a large cache folder means a stat() per file, which takes minutes on big
+// browser or package caches. The cache remembers, per folder, its mtime, the total size
+// of the files directly in it and its subfolders. A folder's mtime changes whenever an
+// entry is added, removed or renamed in it, so an unchanged mtime means the cached
+// listing is still right and only the subfolders need a stat() each. Files rewritten
+// in place do not touch the folder's mtime; `SIZE_CACHE_MAX_AGE_SECS` bounds how long
+// such a change can go unnoticed.
+//
+// The per-target totals from the last scan back the instant "estimate" mode.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedDir {
+    mtime_ms: u64,
+    /// Unix seconds when the folder was last listed.
+    listed_at: u64,
+    files_size: u64,
+    subdirs: Vec<String>,
+}
+
+/// Persistent folder size cache (see above).
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SizeCache {
+    dirs: HashMap<String, CachedDir>,
+    /// Target path -> total size at the last scan.
+    totals: HashMap<String, u64>,
+}
+
+impl SizeCache {
+    /// Loads the cache from disk. A missing or unreadable cache only means the next
+    /// scan lists every folder again.
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the cache atomically (temp file + rename).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        let file = fs::File::create(&tmp_path)?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Like `scan_targets`, but folders unchanged since the last scan are not listed
+/// again. The cache is replaced by what this scan saw, so deleted folders drop out.
+pub fn scan_targets_cached(cache: &mut SizeCache) -> Vec<JunkItem> {
+    let mut items = get_system_targets();
+    let now = unix_now();
+    let previous: &SizeCache = cache;
+
+    let seen: Vec<HashMap<String, CachedDir>> = resources::run_parallel(|| {
+        items
+            .par_iter_mut()
+            .map(|item| {
+                let mut seen = HashMap::new();
+                item.size = if item.path.starts_with("::") {
+                    0
+                } else {
+                    cached_dir_size(Path::new(&item.path), 0, previous, &mut seen, now)
+                };
+                seen
+            })
+            .collect()
+    });
+
+    let mut fresh = SizeCache::default();
+    for dirs in seen {
+        fresh.dirs.extend(dirs);
+    }
+    for item in items.iter().filter(|i| !i.path.starts_with("::")) {
+        fresh.totals.insert(item.path.clone(), item.size);
+    }
+    *cache = fresh;
+
+    items.retain(|i| i.size > 0 || i.path.starts_with("::"));
+    items
+}
+
+/// Instant results from the last scan's totals, without touching the folders. Targets
+/// that were never scanned are listed with size 0 until a real scan fills them in.
+pub fn estimate_targets(cache: &SizeCache) -> Vec<JunkItem> {
+    let mut items = get_system_targets();
+    for item in items.iter_mut() {
+        item.size = cache.totals.get(&item.path).copied().unwrap_or(0);
+    }
+    items.retain(|i| i.size > 0 || i.path.starts_with("::") || !cache.totals.contains_key(&i.path));
+    items
+}
+
+/// Claims the single background refresh slot; `finish_refresh` releases it.
+pub fn try_start_refresh() -> bool {
+    !REFRESH_RUNNING.swap(true, Ordering::AcqRel)
+}
+
+pub fn finish_refresh() {
+    REFRESH_RUNNING.store(false, Ordering::Release);
+}
+
+/// Size of the files under `dir` down to `MAX_DEPTH` (as `calculate_dir_size`), reusing
+/// cached listings. Every folder visited is recorded in `seen`.
+fn cached_dir_size(
+    dir: &Path,
+    depth: usize,
+    cache: &SizeCache,
+    seen: &mut HashMap<String, CachedDir>,
+    now: u64,
+) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(dir) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return 0;
+    }
+    let key = dir.to_string_lossy().to_string();
+    let mtime_ms = mtime_ms(&meta);
+
+    let entry = match cache.dirs.get(&key) {
+        Some(hit)
+            if hit.mtime_ms == mtime_ms
+                && now.saturating_sub(hit.listed_at) < SIZE_CACHE_MAX_AGE_SECS =>
+        {
+            hit.clone()
+        }
+        _ => list_dir(dir, depth, mtime_ms, now),
+    };
+
+    let mut total = entry.files_size;
+    if depth + 1 < MAX_DEPTH {
+        for name in &entry.subdirs {
+            total += cached_dir_size(&dir.join(name), depth + 1, cache, seen, now);
+        }
+    }
+    seen.insert(key, entry);
+    total
+}
+
+fn list_dir(dir: &Path, depth: usize, mtime_ms: u64, now: u64) -> CachedDir {
+    let mut files_size = 0;
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        // DirEntry::file_type does not follow symlinks, matching `calculate_dir_size`.
+        let Ok(kind) = entry.file_type() else {
+            continue;
+        };
+        if kind.is_dir() {
+            match entry.file_name().into_string() {
+                Ok(name) => subdirs.push(name),
+                // A name the JSON cache cannot hold: its subtree is sized now and
+                // counted with this folder's files.
+                Err(_) if depth + 1 < MAX_DEPTH => {
+                    files_size += WalkDir::new(entry.path())
+                        .follow_links(false)
+                        .min_depth(1)
+                        .max_depth(MAX_DEPTH - depth - 1)
+                        .into_iter()
+                        .filter_map(|e| e.ok())
+                        .filter(|e| e.file_type().is_file())
+                        .filter_map(|e| e.metadata().ok())
+                        .map(|m| m.len())
+                        .sum::<u64>();
+                }
+                Err(_) => {}
+            }
+        } else if kind.is_file() {
+            files_size += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+    }
+    CachedDir {
+        mtime_ms,
+        listed_at: now,
+        files_size,
+        subdirs,
+    }
+}
+
+fn mtime_ms(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 fn calculate_dir_size(path: &Path) -> u64 {
     WalkDir::new(path)
         .follow_links(false)
@@ -670,8 +906,12 @@ pub fn dry_run(paths: Vec<String>) -> Result<DryRunResult> {
 // CLEANING (With Progress & Security)
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// Deletes the selected targets, then runs `verify_cleaned` over them. With
+/// `retry_on_reboot` (Windows only), files left because they are in use are queued for
+/// deletion at the next restart.
 pub fn clean_paths<R: tauri::Runtime>(
     paths: Vec<String>,
+    retry_on_reboot: bool,
     app_handle: &tauri::AppHandle<R>,
 ) -> Result<CleanResult> {
     CANCEL_FLAG.store(false, Ordering::Relaxed);
@@ -705,6 +945,8 @@ pub fn clean_paths<R: tauri::Runtime>(
     let total_files = Arc::new(AtomicU64::new(0));
     let files_processed = Arc::new(AtomicU64::new(0));
     let bytes_freed = Arc::new(AtomicU64::new(0));
+    // Shared by the rayon workers below; per-file updates are coalesced.
+    let events = ProgressEmitter::new(app_handle, "clean-progress");
 
     for p in &validated_paths {
         if !p.starts_with("::") {
@@ -712,6 +954,12 @@ pub fn clean_paths<R: tauri::Runtime>(
         }
     }
 
+    let cleaned_targets: Vec<String> = validated_paths
+        .iter()
+        .filter(|p| !p.starts_with("::"))
+        .cloned()
+        .collect();
+
     let results: Vec<_> = validated_paths
         .into_iter()
         .map(|path_str| {
@@ -723,7 +971,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                 "::DNS_CACHE::" => {
                     return virtual_result(
                         flush_dns(),
-                        app_handle,
+                        &events,
                         &files_processed,
                         &total_files,
                         &bytes_freed,
@@ -733,7 +981,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                 "::CLIPBOARD::" => {
                     return virtual_result(
                         clear_clipboard(),
-                        app_handle,
+                        &events,
                         &files_processed,
                         &total_files,
                         &bytes_freed,
@@ -743,7 +991,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                 "::CLEAR_BASH_HISTORY::" => {
                     return virtual_result(
                         clear_shell_history("bash"),
-                        app_handle,
+                        &events,
                         &files_processed,
                         &total_files,
                         &bytes_freed,
@@ -753,7 +1001,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                 "::CLEAR_ZSH_HISTORY::" => {
                     return virtual_result(
                         clear_shell_history("zsh"),
-                        app_handle,
+                        &events,
                         &files_processed,
                         &total_files,
                         &bytes_freed,
@@ -763,7 +1011,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                 "::RECYCLE_BIN::" => {
                     return virtual_result(
                         empty_recycle_bin(),
-                        app_handle,
+                        &events,
                         &files_processed,
                         &total_files,
                         &bytes_freed,
@@ -773,7 +1021,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                 "::TRASH::" => {
                     return virtual_result(
                         empty_trash(),
-                        app_handle,
+                        &events,
                         &files_processed,
                         &total_files,
                         &bytes_freed,
@@ -785,7 +1033,7 @@ pub fn clean_paths<R: tauri::Runtime>(
                         Ok(freed) => {
                             bytes_freed.fetch_add(freed, Ordering::Relaxed);
                             emit_progress(
-                                app_handle,
+                                &events,
                                 files_processed.load(Ordering::Relaxed),
                                 total_files.load(Ordering::Relaxed),
                                 bytes_freed.load(Ordering::Relaxed),
@@ -799,13 +1047,14 @@ pub fn clean_paths<R: tauri::Runtime>(
                 _ => {}
             }
 
-            clean_single_path(
+            let (freed, files) = clean_single_path(
                 &path_str,
-                app_handle,
+                &events,
                 &files_processed,
                 &total_files,
                 &bytes_freed,
-            )
+            );
+            (freed, files, vec![])
         })
         .collect();
 
@@ -817,24 +1066,43 @@ pub fn clean_paths<R: tauri::Runtime>(
         errors.extend(errs);
     }
 
-    emit_progress(
-        app_handle,
+    let mut verification = Verification::default();
+    if !CANCEL_FLAG.load(Ordering::Relaxed) {
+        emit_progress(
+            &events,
+            files_processed.load(Ordering::Relaxed),
+            total_files.load(Ordering::Relaxed),
+            total_bytes_freed,
+            "Verifying cleanup".to_string(),
+        );
+        let targets: Vec<&Path> = cleaned_targets.iter().map(Path::new).collect();
+        verification = verify_cleaned(&targets);
+        total_bytes_freed += verification.bytes_freed;
+        total_files_deleted += verification.files_deleted;
+        if retry_on_reboot {
+            schedule_leftovers_for_reboot(&mut verification.leftovers);
+        }
+    }
+
+    events.finish(clean_progress(
         files_processed.load(Ordering::Relaxed),
         total_files.load(Ordering::Relaxed),
         total_bytes_freed,
         "Cleanup complete".to_string(),
-    );
+    ));
 
     Ok(CleanResult {
         bytes_freed: total_bytes_freed,
         files_deleted: total_files_deleted,
         errors,
+        leftovers: verification.leftovers,
+        leftovers_not_listed: verification.not_listed,
     })
 }
 
 fn virtual_result<R: tauri::Runtime>(
     result: Result<(), String>,
-    app_handle: &tauri::AppHandle<R>,
+    events: &ProgressEmitter<R, CleanProgress>,
     files_processed: &Arc<AtomicU64>,
     total_files: &Arc<AtomicU64>,
     bytes_freed: &Arc<AtomicU64>,
@@ -843,7 +1111,7 @@ fn virtual_result<R: tauri::Runtime>(
     match result {
         Ok(_) => {
             emit_progress(
-                app_handle,
+                events,
                 files_processed.load(Ordering::Relaxed),
                 total_files.load(Ordering::Relaxed),
                 bytes_freed.load(Ordering::Relaxed),
@@ -874,14 +1142,13 @@ fn count_files(path: &Path) -> u64 {
 
 fn clean_single_path<R: tauri::Runtime>(
     path_str: &str,
-    app_handle: &tauri::AppHandle<R>,
+    events: &ProgressEmitter<R, CleanProgress>,
     files_processed: &Arc<AtomicU64>,
     total_files: &Arc<AtomicU64>,
     bytes_freed: &Arc<AtomicU64>,
-) -> (u64, u64, Vec<String>) {
+) -> (u64, u64) {
     let mut local_freed = 0u64;
     let mut local_files = 0u64;
-    let mut local_errors = Vec::new();
     let path = Path::new(path_str);
 
     if path.is_dir() {
@@ -896,41 +1163,29 @@ fn clean_single_path<R: tauri::Runtime>(
                         continue;
                     }
                     emit_progress(
-                        app_handle,
+                        events,
                         files_processed.load(Ordering::Relaxed),
                         total_files.load(Ordering::Relaxed),
                         bytes_freed.load(Ordering::Relaxed),
                         p.display().to_string(),
                     );
+                    // Failures are not reported here: `verify_cleaned` revisits whatever
+                    // is left and reports each file with its reason.
                     if p.is_dir() {
                         let size = calculate_dir_size(&p);
-                        match fs::remove_dir_all(&p) {
-                            Ok(_) => {
-                                local_freed += size;
-                                local_files += 1;
-                                files_processed.fetch_add(1, Ordering::Relaxed);
-                                bytes_freed.fetch_add(size, Ordering::Relaxed);
-                            }
-                            Err(e) => local_errors.push(format!(
-                                "Failed to delete {}: {}",
-                                p.display(),
-                                e
-                            )),
+                        if fs::remove_dir_all(&p).is_ok() {
+                            local_freed += size;
+                            local_files += 1;
+                            files_processed.fetch_add(1, Ordering::Relaxed);
+                            bytes_freed.fetch_add(size, Ordering::Relaxed);
                         }
                     } else if m.is_file() {
                         let size = m.len();
-                        match fs::remove_file(&p) {
-                            Ok(_) => {
-                                local_freed += size;
-                                local_files += 1;
-                                files_processed.fetch_add(1, Ordering::Relaxed);
-                                bytes_freed.fetch_add(size, Ordering::Relaxed);
-                            }
-                            Err(e) => local_errors.push(format!(
-                                "Failed to delete {}: {}",
-                                p.display(),
-                                e
-                            )),
+                        if fs::remove_file(&p).is_ok() {
+                            local_freed += size;
+                            local_files += 1;
+                            files_processed.fetch_add(1, Ordering::Relaxed);
+                            bytes_freed.fetch_add(size, Ordering::Relaxed);
                         }
                     }
                 }
@@ -940,47 +1195,157 @@ fn clean_single_path<R: tauri::Runtime>(
         if let Ok(m) = fs::symlink_metadata(path) {
             if !m.file_type().is_symlink() {
                 let size = m.len();
-                match fs::remove_file(path) {
-                    Ok(_) => {
-                        local_freed += size;
-                        local_files += 1;
-                        files_processed.fetch_add(1, Ordering::Relaxed);
-                        bytes_freed.fetch_add(size, Ordering::Relaxed);
-                    }
-                    Err(e) => {
-                        local_errors.push(format!("Failed to delete {}: {}", path.display(), e))
-                    }
+                if fs::remove_file(path).is_ok() {
+                    local_freed += size;
+                    local_files += 1;
+                    files_processed.fetch_add(1, Ordering::Relaxed);
+                    bytes_freed.fetch_add(size, Ordering::Relaxed);
                 }
             }
         }
     }
-    (local_freed, local_files, local_errors)
+    (local_freed, local_files)
 }
 
 fn emit_progress<R: tauri::Runtime>(
-    app_handle: &tauri::AppHandle<R>,
+    events: &ProgressEmitter<R, CleanProgress>,
     files_processed: u64,
     total_files: u64,
     bytes_freed: u64,
     current_file: String,
 ) {
+    events.emit(clean_progress(
+        files_processed,
+        total_files,
+        bytes_freed,
+        current_file,
+    ));
+}
+
+fn clean_progress(
+    files_processed: u64,
+    total_files: u64,
+    bytes_freed: u64,
+    current_file: String,
+) -> CleanProgress {
     let percentage = if total_files > 0 {
         ((files_processed as f64 / total_files as f64) * 100.0).min(100.0) as u8
     } else {
         100
     };
-    let _ = app_handle.emit(
-        "clean-progress",
-        CleanProgress {
-            files_processed,
-            total_files,
-            bytes_freed,
-            current_file,
-            percentage,
-        },
-    );
+    CleanProgress {
+        files_processed,
+        total_files,
+        bytes_freed,
+        current_file,
+        percentage,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// VERIFICATION PASS
+// ═══════════════════════════════════════════════════════════════════════════
+// A directory that `remove_dir_all` could not finish is left half-deleted with one
+// error for the whole tree. This pass walks what is left of each target, retries every
+// file once (a program may have let go of it in the meantime) and reports each file
+// that still stays, with the reason the OS gave.
+
+#[derive(Default)]
+struct Verification {
+    bytes_freed: u64,
+    files_deleted: u64,
+    leftovers: Vec<Leftover>,
+    not_listed: u64,
+}
+
+fn verify_cleaned(targets: &[&Path]) -> Verification {
+    let mut verification = Verification::default();
+    for target in targets {
+        // Contents first, so directories emptied by the retries can go too. A target
+        // directory itself is kept: cleaning empties it, the OS or app owns it.
+        for entry in WalkDir::new(target)
+            .follow_links(false)
+            .max_depth(MAX_DEPTH)
+            .contents_first(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            let Ok(meta) = fs::symlink_metadata(path) else {
+                continue;
+            };
+            if meta.is_dir() {
+                if entry.depth() > 0 {
+                    let _ = fs::remove_dir(path);
+                }
+                continue;
+            }
+            if meta.file_type().is_symlink() {
+                continue;
+            }
+            match fs::remove_file(path) {
+                Ok(()) => {
+                    verification.bytes_freed += meta.len();
+                    verification.files_deleted += 1;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) if verification.leftovers.len() < MAX_LISTED_LEFTOVERS => {
+                    verification.leftovers.push(Leftover {
+                        path: path.display().to_string(),
+                        size: meta.len(),
+                        reason: leftover_reason(&e),
+                        detail: e.to_string(),
+                        scheduled_for_reboot: false,
+                    });
+                }
+                Err(_) => verification.not_listed += 1,
+            }
+        }
+    }
+    verification
+}
+
+fn leftover_reason(error: &std::io::Error) -> LeftoverReason {
+    // ERROR_SHARING_VIOLATION / ERROR_LOCK_VIOLATION: another process has it open.
+    #[cfg(windows)]
+    if matches!(error.raw_os_error(), Some(32) | Some(33)) {
+        return LeftoverReason::InUse;
+    }
+    match error.kind() {
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem => {
+            LeftoverReason::PermissionDenied
+        }
+        _ => LeftoverReason::Other,
+    }
+}
+
+/// Windows: asks the OS to delete in-use leftovers when it next starts, before any
+/// program can open them. This needs administrator rights (the list lives in HKLM);
+/// without them the leftovers simply stay unscheduled.
+#[cfg(windows)]
+fn schedule_leftovers_for_reboot(leftovers: &mut [Leftover]) {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{MoveFileExW, MOVEFILE_DELAY_UNTIL_REBOOT};
+
+    for leftover in leftovers
+        .iter_mut()
+        .filter(|l| l.reason == LeftoverReason::InUse)
+    {
+        let wide: Vec<u16> = std::ffi::OsStr::new(&leftover.path)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        // SAFETY: `wide` is a NUL-terminated UTF-16 path that outlives the call; a null
+        // destination means "delete".
+        let ok =
+            unsafe { MoveFileExW(wide.as_ptr(), std::ptr::null(), MOVEFILE_DELAY_UNTIL_REBOOT) };
+        leftover.scheduled_for_reboot = ok != 0;
+    }
 }
 
+#[cfg(not(windows))]
+fn schedule_leftovers_for_reboot(_leftovers: &mut [Leftover]) {}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // CANCELLATION
 // ═══════════════════════════════════════════════════════════════════════════
@@ -1900,6 +2265,84 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_cleaned_retries_what_is_left() {
+        let dir = test_dir("verify_cleaned");
+        make_file(&dir, "a.tmp", b"12345");
+        fs::create_dir_all(dir.join("sub/deeper")).unwrap();
+        make_file(&dir.join("sub/deeper"), "b.tmp", b"123");
+
+        let verification = verify_cleaned(&[dir.as_path()]);
+        assert_eq!(verification.files_deleted, 2);
+        assert_eq!(verification.bytes_freed, 8);
+        assert!(verification.leftovers.is_empty());
+        assert!(dir.exists(), "The target folder itself is kept");
+        assert!(!dir.join("sub").exists(), "Emptied subfolders are removed");
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_leftover_reason_from_os_error() {
+        use std::io::{Error, ErrorKind};
+        assert_eq!(
+            leftover_reason(&Error::from(ErrorKind::PermissionDenied)),
+            LeftoverReason::PermissionDenied
+        );
+        assert_eq!(
+            leftover_reason(&Error::other("device error")),
+            LeftoverReason::Other
+        );
+        #[cfg(windows)]
+        assert_eq!(
+            leftover_reason(&Error::from_raw_os_error(32)),
+            LeftoverReason::InUse
+        );
+    }
+
+    #[test]
+    fn test_cached_dir_size_matches_and_reuses_listing() {
+        let dir = test_dir("size_cache");
+        make_file(&dir, "a.bin", &[0u8; 100]);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        make_file(&dir.join("sub"), "b.bin", &[0u8; 50]);
+
+        let now = unix_now();
+        let mut cache = SizeCache::default();
+        let mut seen = HashMap::new();
+        let size = cached_dir_size(&dir, 0, &cache, &mut seen, now);
+        assert_eq!(size, calculate_dir_size(&dir));
+        assert_eq!(size, 150);
+        assert_eq!(seen.len(), 2);
+        cache.dirs = seen;
+        // An unchanged folder is not listed again, so a doctored entry shows through.
+        let key = dir.to_string_lossy().to_string();
+        cache.dirs.get_mut(&key).unwrap().files_size = 1000;
+        assert_eq!(
+            cached_dir_size(&dir, 0, &cache, &mut HashMap::new(), now),
+            1050
+        );
+        // ...until it is older than the maximum age.
+        let later = now + SIZE_CACHE_MAX_AGE_SECS;
+        assert_eq!(
+            cached_dir_size(&dir, 0, &cache, &mut HashMap::new(), later),
+            150
+        );
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_estimate_targets_uses_last_totals() {
+        let targets = get_system_targets();
+        let Some(target) = targets.iter().find(|t| !t.path.starts_with("::")) else {
+            return;
+        };
+        let mut cache = SizeCache::default();
+        cache.totals.insert(target.path.clone(), 4096);
+        let estimate = estimate_targets(&cache);
+        let item = estimate.iter().find(|i| i.path == target.path).unwrap();
+        assert_eq!(item.size, 4096);
+    }
+
     #[cfg(any(target_os = "macos", target_os = "linux"))]
     #[test]
     fn test_unix_targets_include_trash_not_recycle_bin() {
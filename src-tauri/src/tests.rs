@@ -3,6 +3,7 @@ mod tests {
     use crate::crypto;
     use crate::crypto_stream;
     use crate::keychain::MasterKey;
+    use crate::subkeys::KeyPurpose;
     use std::fs;
     use std::io::Write;
 
@@ -237,15 +238,23 @@ mod tests {
         let filename = "passwords.json";
         let mk = MasterKey([123u8; 32]);
 
-        let container =
-            crypto::encrypt_file_with_master_key(&mk, None, filename, original_data, None, 3)
-                .expect("V4 Encryption failed");
+        let container = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            filename,
+            original_data,
+            None,
+            3,
+        )
+        .expect("V4 Encryption failed");
 
         assert_eq!(container.version, 4);
         assert!(!container.ciphertext.is_empty());
 
-        let decrypted_payload = crypto::decrypt_file_with_master_key(&mk, None, &container)
-            .expect("V4 Decryption failed");
+        let decrypted_payload =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container)
+                .expect("V4 Decryption failed");
 
         assert_eq!(decrypted_payload.filename, filename);
         assert_eq!(decrypted_payload.content, original_data);
@@ -260,6 +269,7 @@ mod tests {
 
         let container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             Some(correct_keyfile),
             "test.json",
             original_data,
@@ -268,7 +278,12 @@ mod tests {
         )
         .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, Some(wrong_keyfile), &container);
+        let result = crypto::decrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            Some(wrong_keyfile),
+            &container,
+        );
 
         assert!(
             result.is_err(),
@@ -288,12 +303,20 @@ mod tests {
         let payload = vault_json_payload();
         let mk = mk(10);
 
-        let container =
-            crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &payload, None, 3)
-                .expect("Vault encryption failed");
+        let container = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "vault.json",
+            &payload,
+            None,
+            3,
+        )
+        .expect("Vault encryption failed");
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container)
-            .expect("Vault decryption failed");
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container)
+                .expect("Vault decryption failed");
 
         assert_eq!(
             result.content, payload,
@@ -323,6 +346,7 @@ mod tests {
         let mk = mk(11);
         let container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             None,
             "vault.json",
             &vault_json_payload(),
@@ -331,7 +355,9 @@ mod tests {
         )
         .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container).unwrap();
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container)
+                .unwrap();
 
         assert_eq!(
             result.filename, "vault.json",
@@ -348,6 +374,7 @@ mod tests {
 
         let container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             Some(keyfile),
             "vault.json",
             &vault_json_payload(),
@@ -356,7 +383,8 @@ mod tests {
         )
         .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container);
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container);
         assert!(
             result.is_err(),
             "Vault must not open without its required keyfile"
@@ -370,10 +398,26 @@ mod tests {
         let mk = mk(13);
         let payload = vault_json_payload();
 
-        let c1 = crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &payload, None, 3)
-            .unwrap();
-        let c2 = crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &payload, None, 3)
-            .unwrap();
+        let c1 = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "vault.json",
+            &payload,
+            None,
+            3,
+        )
+        .unwrap();
+        let c2 = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "vault.json",
+            &payload,
+            None,
+            3,
+        )
+        .unwrap();
 
         assert_ne!(
             c1.ciphertext, c2.ciphertext,
@@ -388,6 +432,7 @@ mod tests {
         let mk = mk(14);
         let mut container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             None,
             "vault.json",
             &vault_json_payload(),
@@ -399,7 +444,8 @@ mod tests {
         let mid = container.ciphertext.len() / 2;
         container.ciphertext[mid] ^= 0xFF;
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container);
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container);
         assert!(
             result.is_err(),
             "Tampered vault ciphertext must be rejected"
@@ -413,6 +459,7 @@ mod tests {
         let mk = mk(15);
         let mut container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             None,
             "vault.json",
             &vault_json_payload(),
@@ -425,7 +472,8 @@ mod tests {
             h[0] ^= 0xFF;
         }
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container);
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container);
         assert!(result.is_err(), "Corrupted integrity hash must be caught");
         let msg = result.unwrap_err().to_string();
         assert!(
@@ -444,6 +492,7 @@ mod tests {
 
         let container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             None,
             "vault.json",
             &payload,
@@ -452,8 +501,9 @@ mod tests {
         )
         .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container)
-            .expect("Paranoid vault decryption must succeed");
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container)
+                .expect("Paranoid vault decryption must succeed");
 
         assert_eq!(result.content, payload);
     }
@@ -468,6 +518,7 @@ mod tests {
 
         let c1 = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             None,
             "vault.json",
             &payload,
@@ -477,6 +528,7 @@ mod tests {
         .unwrap();
         let c2 = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             None,
             "vault.json",
             &payload,
@@ -499,12 +551,20 @@ mod tests {
             .into_bytes();
         let mk = mk(18);
 
-        let container =
-            crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &payload, None, 3)
-                .unwrap();
+        let container = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "vault.json",
+            &payload,
+            None,
+            3,
+        )
+        .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container)
-            .expect("Empty vault must decrypt cleanly");
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container)
+                .expect("Empty vault must decrypt cleanly");
 
         let parsed: serde_json::Value = serde_json::from_slice(&result.content).unwrap();
         assert_eq!(
@@ -539,12 +599,20 @@ mod tests {
             .into_bytes();
         let mk = mk(19);
 
-        let container =
-            crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &payload, None, 3)
-                .unwrap();
+        let container = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "vault.json",
+            &payload,
+            None,
+            3,
+        )
+        .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container)
-            .expect("Large vault must decrypt successfully");
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container)
+                .expect("Large vault must decrypt successfully");
 
         let parsed: serde_json::Value = serde_json::from_slice(&result.content).unwrap();
         assert_eq!(
@@ -587,12 +655,24 @@ mod tests {
             .into_bytes();
 
             let mk = mk(20);
-            let container =
-                crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &payload, None, 3)
-                    .unwrap();
+            let container = crypto::encrypt_file_with_master_key(
+                &mk,
+                KeyPurpose::FileWrapping,
+                None,
+                "vault.json",
+                &payload,
+                None,
+                3,
+            )
+            .unwrap();
 
-            let result = crypto::decrypt_file_with_master_key(&mk, None, &container)
-                .expect("Special character password vault must decrypt");
+            let result = crypto::decrypt_file_with_master_key(
+                &mk,
+                KeyPurpose::FileWrapping,
+                None,
+                &container,
+            )
+            .expect("Special character password vault must decrypt");
 
             let parsed: serde_json::Value = serde_json::from_slice(&result.content).unwrap();
             assert_eq!(
@@ -612,12 +692,25 @@ mod tests {
         let correct = mk(30);
         let wrong = mk(31);
 
-        let container =
-            crypto::encrypt_file_with_master_key(&correct, None, "a.txt", b"secret data", None, 3)
-                .unwrap();
+        let container = crypto::encrypt_file_with_master_key(
+            &correct,
+            KeyPurpose::FileWrapping,
+            None,
+            "a.txt",
+            b"secret data",
+            None,
+            3,
+        )
+        .unwrap();
 
         assert!(
-            crypto::decrypt_file_with_master_key(&wrong, None, &container).is_err(),
+            crypto::decrypt_file_with_master_key(
+                &wrong,
+                KeyPurpose::FileWrapping,
+                None,
+                &container
+            )
+            .is_err(),
             "Wrong master key must be rejected"
         );
     }
@@ -631,6 +724,7 @@ mod tests {
 
         let container = crypto::encrypt_file_with_master_key(
             &mk,
+            KeyPurpose::FileWrapping,
             Some(kf),
             "vault.json",
             &vault_json_payload(),
@@ -639,7 +733,8 @@ mod tests {
         )
         .unwrap();
 
-        let result = crypto::decrypt_file_with_master_key(&mk, None, &container);
+        let result =
+            crypto::decrypt_file_with_master_key(&mk, KeyPurpose::FileWrapping, None, &container);
         assert!(result.is_err());
         let msg = result.unwrap_err().to_string();
         assert!(
@@ -654,8 +749,26 @@ mod tests {
         let mk = mk(33);
         let data = b"repeated vault content";
 
-        let c1 = crypto::encrypt_file_with_master_key(&mk, None, "v.json", data, None, 3).unwrap();
-        let c2 = crypto::encrypt_file_with_master_key(&mk, None, "v.json", data, None, 3).unwrap();
+        let c1 = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "v.json",
+            data,
+            None,
+            3,
+        )
+        .unwrap();
+        let c2 = crypto::encrypt_file_with_master_key(
+            &mk,
+            KeyPurpose::FileWrapping,
+            None,
+            "v.json",
+            data,
+            None,
+            3,
+        )
+        .unwrap();
 
         assert_ne!(
             c1.ciphertext, c2.ciphertext,
@@ -670,12 +783,24 @@ mod tests {
         let data = vault_json_payload();
 
         for level in [0i32, 1, 3, 9, 19] {
-            let container =
-                crypto::encrypt_file_with_master_key(&mk, None, "vault.json", &data, None, level)
-                    .unwrap_or_else(|e| panic!("Encryption at level {level} failed: {e}"));
-
-            let result = crypto::decrypt_file_with_master_key(&mk, None, &container)
-                .unwrap_or_else(|e| panic!("Decryption at level {level} failed: {e}"));
+            let container = crypto::encrypt_file_with_master_key(
+                &mk,
+                KeyPurpose::FileWrapping,
+                None,
+                "vault.json",
+                &data,
+                None,
+                level,
+            )
+            .unwrap_or_else(|e| panic!("Encryption at level {level} failed: {e}"));
+
+            let result = crypto::decrypt_file_with_master_key(
+                &mk,
+                KeyPurpose::FileWrapping,
+                None,
+                &container,
+            )
+            .unwrap_or_else(|e| panic!("Decryption at level {level} failed: {e}"));
 
             assert_eq!(
                 result.content, data,
@@ -946,101 +1071,894 @@ mod tests {
 
         let _ = fs::remove_dir_all(dir);
     }
-    // ── Path Security tests call pub(crate) helpers in commands/files.rs ────────
 
-    use crate::commands::files::{
-        is_already_compressed, is_system_critical, reject_critical_path, reject_path_traversal,
-    };
-    use std::path::Path;
+    // ── V8 authenticated chunk framing ───────────────────────────────────────
+
+    /// Splits a V8 file into (prefix = version + 4 KB header, chunk records, trailer record).
+    /// For padded files the last part starts with the padding record.
+    fn split_v8(bytes: &[u8]) -> (Vec<u8>, Vec<Vec<u8>>, Vec<u8>) {
+        let prefix_len = 4 + 4096;
+        let mut pos = prefix_len;
+        let mut chunks = Vec::new();
+        loop {
+            let len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+            if len >= u32::MAX - 1 {
+                return (bytes[..prefix_len].to_vec(), chunks, bytes[pos..].to_vec());
+            }
+            let end = pos + 4 + len as usize;
+            chunks.push(bytes[pos..end].to_vec());
+            pos = end;
+        }
+    }
 
-    // ── Path Traversal ────────────────────────────────────────────────────────
+    /// Encrypts 3 MB (three chunks) and returns (dir, encrypted path, output dir).
+    fn encrypt_three_chunks(name: &str) -> (std::path::PathBuf, String, std::path::PathBuf) {
+        let dir = make_test_dir(name);
+        let input = write_file(&dir, "three.bin", &vec![0x5Au8; 3 * 1024 * 1024]);
+        let encrypted = dir.join("three.bin.qre").to_str().unwrap().to_owned();
+        crypto_stream::encrypt_file_stream(
+            &input,
+            &encrypted,
+            &mk(50),
+            "local",
+            None,
+            None,
+            None,
+            3,
+            |_, _| {},
+        )
+        .unwrap();
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+        (dir, encrypted, out_dir)
+    }
 
-    #[test]
-    fn test_path_traversal_dotdot_rejected() {
-        assert!(
-            reject_path_traversal(Path::new("/home/user/../etc/passwd")).is_err(),
-            "Single '..' must be rejected"
-        );
+    fn decrypt_to(encrypted: &str, out_dir: &std::path::Path) -> anyhow::Result<String> {
+        crypto_stream::decrypt_file_stream(
+            encrypted,
+            out_dir.to_str().unwrap(),
+            &mk(50),
+            None,
+            |_, _| {},
+        )
     }
 
     #[test]
-    fn test_path_traversal_nested_rejected() {
-        assert!(
-            reject_path_traversal(Path::new("docs/../../secret")).is_err(),
-            "Nested '..' must be rejected"
-        );
+    fn test_v8_roundtrip_writes_trailer() {
+        let (dir, encrypted, out_dir) = encrypt_three_chunks("qre_v8_roundtrip");
+        let bytes = fs::read(&encrypted).unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[..4].try_into().unwrap()), 8);
+        let (_, chunks, trailer) = split_v8(&bytes);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(trailer.len(), 4 + 64);
+
+        assert_eq!(decrypt_to(&encrypted, &out_dir).unwrap(), "three.bin");
+        let _ = fs::remove_dir_all(dir);
     }
 
+    /// The post-write check re-reads the framing from disk: a copy cut short (as a
+    /// pulled USB drive leaves it) fails, the intact file passes.
     #[test]
-    fn test_path_traversal_normal_path_allowed() {
+    fn test_v8_written_file_verification() {
+        let (dir, encrypted, _) = encrypt_three_chunks("qre_v8_verify_written");
+        let (_, header) = crypto_stream::read_stream_header(&encrypted).unwrap();
+        let cipher = crypto_stream::unwrap_file_cipher(&header, &mk(50), None, None).unwrap();
+        let plaintext_len = 3 * 1024 * 1024;
+        crypto_stream::verify_written(&encrypted, &cipher, &header, 3, plaintext_len).unwrap();
+
+        let bytes = fs::read(&encrypted).unwrap();
+        let cut = dir.join("cut.qre").to_str().unwrap().to_owned();
+        fs::write(&cut, &bytes[..bytes.len() - 20]).unwrap();
+        let err = crypto_stream::verify_written(&cut, &cipher, &header, 3, plaintext_len)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("verification"), "unexpected error: {err}");
         assert!(
-            reject_path_traversal(Path::new("/home/alice/documents/vault.json")).is_ok(),
-            "Normal absolute path must pass"
+            crypto_stream::verify_written(&encrypted, &cipher, &header, 2, plaintext_len).is_err()
         );
+        let _ = fs::remove_dir_all(dir);
     }
 
+    /// Dropping the last chunk while keeping the trailer must fail the trailer check
+    /// itself, independent of the whole-file hash.
     #[test]
-    fn test_path_traversal_relative_path_allowed() {
+    fn test_v8_dropped_trailing_chunk_detected() {
+        let (dir, encrypted, out_dir) = encrypt_three_chunks("qre_v8_drop");
+        let (prefix, chunks, trailer) = split_v8(&fs::read(&encrypted).unwrap());
+        fs::write(
+            &encrypted,
+            [prefix, chunks[0].clone(), chunks[1].clone(), trailer].concat(),
+        )
+        .unwrap();
+
+        let err = decrypt_to(&encrypted, &out_dir).unwrap_err().to_string();
+        assert!(err.contains("Trailer"), "unexpected error: {err}");
         assert!(
-            reject_path_traversal(Path::new("exports/backup.csv")).is_ok(),
-            "Relative path without '..' must pass"
+            !out_dir.join("three.bin").exists(),
+            "partial output must be removed"
         );
+        let _ = fs::remove_dir_all(dir);
     }
 
-    // ── System Critical Path Guard ────────────────────────────────────────────
-
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_etc_blocked() {
-        assert!(is_system_critical(Path::new("/etc/passwd")));
+    fn test_v8_stripped_trailer_detected() {
+        let (dir, encrypted, out_dir) = encrypt_three_chunks("qre_v8_strip");
+        let (prefix, chunks, _) = split_v8(&fs::read(&encrypted).unwrap());
+        fs::write(&encrypted, [vec![prefix], chunks].concat().concat()).unwrap();
+
+        let err = decrypt_to(&encrypted, &out_dir).unwrap_err().to_string();
+        assert!(err.contains("truncated"), "unexpected error: {err}");
+        assert!(!out_dir.join("three.bin").exists());
+        let _ = fs::remove_dir_all(dir);
     }
 
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_root_blocked() {
-        assert!(is_system_critical(Path::new("/")));
+    fn test_v8_reordered_or_duplicated_chunks_detected() {
+        let (dir, encrypted, out_dir) = encrypt_three_chunks("qre_v8_reorder");
+        let (prefix, c, trailer) = split_v8(&fs::read(&encrypted).unwrap());
+
+        let swapped = [
+            prefix.clone(),
+            c[1].clone(),
+            c[0].clone(),
+            c[2].clone(),
+            trailer.clone(),
+        ];
+        fs::write(&encrypted, swapped.concat()).unwrap();
+        assert!(decrypt_to(&encrypted, &out_dir).is_err());
+
+        let duplicated = [
+            prefix,
+            c[0].clone(),
+            c[0].clone(),
+            c[1].clone(),
+            c[2].clone(),
+            trailer,
+        ];
+        fs::write(&encrypted, duplicated.concat()).unwrap();
+        assert!(decrypt_to(&encrypted, &out_dir).is_err());
+        let _ = fs::remove_dir_all(dir);
     }
 
+    /// A crash after a checkpoint leaves the partial output and its sidecar; resuming
+    /// finishes the same container, which then decrypts to the original.
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_usr_bin_blocked() {
-        assert!(is_system_critical(Path::new("/usr/bin/sh")));
+    fn test_interrupted_encryption_resumes() {
+        let dir = make_test_dir("qre_resume");
+        // 66 chunks: one checkpoint (after chunk 64), then two more chunks.
+        let content: Vec<u8> = (0..66 * 1024 * 1024u32).map(|i| (i / 4096) as u8).collect();
+        let input = write_file(&dir, "disk.img", &content);
+        let encrypted = dir.join("disk.img.qre").to_str().unwrap().to_owned();
+        let keyfile = [7u8; 32];
+
+        let crashed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crypto_stream::encrypt_file_stream(
+                &input,
+                &encrypted,
+                &mk(50),
+                "local",
+                Some(&keyfile),
+                None,
+                None,
+                1,
+                |processed, _| assert!(processed < 65 * 1024 * 1024, "simulated crash"),
+            )
+        }));
+        assert!(crashed.is_err());
+        assert!(crypto_stream::checkpoint_path(&encrypted).exists());
+        assert!(crypto_stream::can_resume(&input, &encrypted));
+        assert!(!crypto_stream::can_resume(&encrypted, &encrypted));
+
+        // The wrong keyfile is refused and leaves the partial output in place.
+        assert!(crypto_stream::resume_file_stream(
+            &input,
+            &encrypted,
+            &mk(50),
+            None,
+            None,
+            |_, _| {}
+        )
+        .is_err());
+        assert!(crypto_stream::can_resume(&input, &encrypted));
+
+        let seen = std::cell::Cell::new(0u64);
+        crypto_stream::resume_file_stream(
+            &input,
+            &encrypted,
+            &mk(50),
+            Some(&keyfile),
+            None,
+            |p, _| seen.set(p),
+        )
+        .unwrap();
+        assert_eq!(seen.get(), content.len() as u64);
+        assert!(!crypto_stream::checkpoint_path(&encrypted).exists());
+
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+        crypto_stream::decrypt_file_stream(
+            &encrypted,
+            out_dir.to_str().unwrap(),
+            &mk(50),
+            Some(&keyfile),
+            |_, _| {},
+        )
+        .unwrap();
+        assert!(fs::read(out_dir.join("disk.img")).unwrap() == content);
+
+        // An unreadable sidecar cannot be resumed; discarding it takes the output along.
+        fs::write(dir.join("disk.img.qre.resume"), b"{}").unwrap();
+        assert!(!crypto_stream::can_resume(&input, &encrypted));
+        crypto_stream::discard_checkpoint(&encrypted);
+        assert!(!std::path::Path::new(&encrypted).exists());
+        let _ = fs::remove_dir_all(dir);
     }
 
+    /// V8 checks the whole-file hash from the authenticated trailer. The header's copy
+    /// can be cleared or rewritten without the key, so neither may matter; a tampered
+    /// trailer must fail.
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_boot_blocked() {
-        assert!(is_system_critical(Path::new("/boot/grub/grub.cfg")));
+    fn test_v8_hash_is_checked_against_the_trailer() {
+        let (dir, encrypted, out_dir) = encrypt_three_chunks("qre_v8_trailer_hash");
+        let original = fs::read(&encrypted).unwrap();
+        let (_, header) = crypto_stream::read_stream_header(&encrypted).unwrap();
+        assert_eq!(header.original_hash.as_ref().map(Vec::len), Some(32));
+
+        for forged in [None, Some(vec![0u8; 32])] {
+            let mut header = header.clone();
+            header.original_hash = forged;
+            let mut bytes = original.clone();
+            let serialized = bincode::serialize(&header).unwrap();
+            bytes[4..4 + 4096].fill(0);
+            bytes[4..4 + serialized.len()].copy_from_slice(&serialized);
+            fs::write(&encrypted, bytes).unwrap();
+
+            assert_eq!(decrypt_to(&encrypted, &out_dir).unwrap(), "three.bin");
+            let plain = fs::read(out_dir.join("three.bin")).unwrap();
+            assert_eq!(plain, vec![0x5Au8; 3 * 1024 * 1024]);
+            fs::remove_file(out_dir.join("three.bin")).unwrap();
+        }
+
+        // The last 16 bytes are the trailer's tag; the hash sits right before them.
+        let mut tampered = original;
+        let hash_byte = tampered.len() - 17;
+        tampered[hash_byte] ^= 0x01;
+        fs::write(&encrypted, tampered).unwrap();
+        let err = decrypt_to(&encrypted, &out_dir).unwrap_err().to_string();
+        assert!(err.contains("Trailer"), "unexpected error: {err}");
+        assert!(!out_dir.join("three.bin").exists());
+        let _ = fs::remove_dir_all(dir);
     }
 
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_proc_blocked() {
-        assert!(is_system_critical(Path::new("/proc/1/mem")));
+    fn test_v8_data_after_trailer_rejected() {
+        let (dir, encrypted, out_dir) = encrypt_three_chunks("qre_v8_append");
+        let mut bytes = fs::read(&encrypted).unwrap();
+        bytes.extend_from_slice(b"extra");
+        fs::write(&encrypted, bytes).unwrap();
+
+        let err = decrypt_to(&encrypted, &out_dir).unwrap_err().to_string();
+        assert!(err.contains("after the trailer"), "unexpected error: {err}");
+        let _ = fs::remove_dir_all(dir);
     }
 
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_home_allowed() {
-        assert!(!is_system_critical(Path::new("/home/alice/vault.json")));
+    fn test_v8_empty_file_roundtrip() {
+        let dir = make_test_dir("qre_v8_empty");
+        let input = write_file(&dir, "empty.txt", b"");
+        let encrypted = dir.join("empty.txt.qre").to_str().unwrap().to_owned();
+        crypto_stream::encrypt_file_stream(
+            &input,
+            &encrypted,
+            &mk(50),
+            "local",
+            None,
+            None,
+            None,
+            3,
+            |_, _| {},
+        )
+        .unwrap();
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+
+        assert_eq!(decrypt_to(&encrypted, &out_dir).unwrap(), "empty.txt");
+        assert!(fs::read(out_dir.join("empty.txt")).unwrap().is_empty());
+        let _ = fs::remove_dir_all(dir);
     }
 
-    #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_critical_path_tmp_allowed() {
-        assert!(!is_system_critical(Path::new("/tmp/export.csv")));
+    // ── V12 hybrid key wrapping ──────────────────────────────────────────────
+
+    /// Encrypts two chunks as V12, bound to `identity`. Returns (dir, encrypted, output dir).
+    fn encrypt_hybrid(
+        name: &str,
+        identity: &crate::recipient::PublicIdentity,
+    ) -> (std::path::PathBuf, String, std::path::PathBuf) {
+        let dir = make_test_dir(name);
+        let input = write_file(&dir, "two.bin", &vec![0x3Cu8; 2 * 1024 * 1024]);
+        let encrypted = dir.join("two.bin.qre").to_str().unwrap().to_owned();
+        crypto_stream::encrypt_file_stream_with_metadata(
+            &input,
+            &encrypted,
+            &mk(50),
+            "local",
+            None,
+            None,
+            None,
+            3,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(identity),
+            |_, _| {},
+        )
+        .unwrap();
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+        (dir, encrypted, out_dir)
+    }
+
+    fn decrypt_hybrid(
+        encrypted: &str,
+        out_dir: &std::path::Path,
+        identity: Option<&crate::recipient::Identity>,
+    ) -> anyhow::Result<String> {
+        crypto_stream::decrypt_file_stream_with_identity(
+            encrypted,
+            out_dir.to_str().unwrap(),
+            &mk(50),
+            None,
+            identity,
+            |_, _| {},
+        )
     }
 
     #[test]
-    #[cfg(target_os = "windows")]
-    fn test_critical_path_windows_system32_blocked() {
-        assert!(is_system_critical(Path::new("C:\\Windows\\System32")));
+    fn test_v12_hybrid_roundtrip() {
+        let vault = crate::recipient::Identity::generate().unwrap();
+        let other = crate::recipient::Identity::generate().unwrap();
+        let (dir, encrypted, out_dir) = encrypt_hybrid("qre_v12_roundtrip", vault.public());
+
+        let bytes = fs::read(&encrypted).unwrap();
+        assert_eq!(
+            u32::from_le_bytes(bytes[..4].try_into().unwrap()),
+            crypto_stream::VERSION_HYBRID
+        );
+        assert!(bytes[4..].starts_with(b"QRECAPS\0"));
+        let report = crate::formats::inspect_bytes(&bytes[..4 + 4096]).unwrap();
+        assert!(report.supported && report.features.contains(&"hybrid_kem".to_string()));
+
+        // The password alone still passes the validation tag, but not the file key.
+        let (_, header) = crypto_stream::read_stream_header(&encrypted).unwrap();
+        assert!(crypto_stream::master_key_opens(&header, &mk(50)));
+        assert!(decrypt_hybrid(&encrypted, &out_dir, None).is_err());
+        assert!(decrypt_to(&encrypted, &out_dir).is_err());
+        let err = decrypt_hybrid(&encrypted, &out_dir, Some(&other))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("different vault identity"),
+            "unexpected error: {err}"
+        );
+        assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 0);
+
+        assert_eq!(
+            decrypt_hybrid(&encrypted, &out_dir, Some(&vault)).unwrap(),
+            "two.bin"
+        );
+        assert!(fs::read(out_dir.join("two.bin")).unwrap() == vec![0x3Cu8; 2 * 1024 * 1024]);
+        let _ = fs::remove_dir_all(dir);
     }
 
+    /// The encapsulation sits in the unauthenticated header. Changing it, or relabelling
+    /// the file as V8 without it, must leave the file key unrecoverable.
     #[test]
-    #[cfg(target_os = "windows")]
-    fn test_critical_path_windows_root_blocked() {
-        assert!(is_system_critical(Path::new("C:\\")));
+    fn test_v12_tampered_encapsulation_rejected() {
+        let vault = crate::recipient::Identity::generate().unwrap();
+        let (dir, encrypted, out_dir) = encrypt_hybrid("qre_v12_tamper", vault.public());
+        let original = fs::read(&encrypted).unwrap();
+        let (_, header) = crypto_stream::read_stream_header(&encrypted).unwrap();
+
+        // The ML-KEM ciphertext is the last thing in the header.
+        let used = crate::formats::notice_len(&original[4..]).unwrap()
+            + bincode::serialized_size(&header).unwrap() as usize;
+        let mut bytes = original.clone();
+        bytes[4 + used - 10] ^= 0x01;
+        fs::write(&encrypted, &bytes).unwrap();
+        let err = decrypt_hybrid(&encrypted, &out_dir, Some(&vault))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unwrap file key"), "unexpected error: {err}");
+
+        // Relabelled as V8 with the notice still in place: the header no longer parses.
+        let mut bytes = original.clone();
+        bytes[..4].copy_from_slice(&8u32.to_le_bytes());
+        fs::write(&encrypted, &bytes).unwrap();
+        assert!(crypto_stream::read_stream_header(&encrypted).is_err());
+
+        // Rewritten as a plain V8 header without the encapsulation.
+        let mut stripped = header.clone();
+        stripped.kem_wrap = None;
+        let serialized = bincode::serialize(&stripped).unwrap();
+        let mut bytes = original;
+        bytes[..4].copy_from_slice(&8u32.to_le_bytes());
+        bytes[4..4 + 4096].fill(0);
+        bytes[4..4 + serialized.len()].copy_from_slice(&serialized);
+        fs::write(&encrypted, &bytes).unwrap();
+        let err = decrypt_to(&encrypted, &out_dir).unwrap_err().to_string();
+        assert!(err.contains("unwrap file key"), "unexpected error: {err}");
+        assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn encrypt_padded(
+        dir: &std::path::Path,
+        content: &[u8],
+        padding: crypto_stream::Padding,
+    ) -> String {
+        let input = write_file(dir, "padded.bin", content);
+        let encrypted = dir.join("padded.bin.qre").to_str().unwrap().to_owned();
+        crypto_stream::encrypt_file_stream_with_metadata(
+            &input,
+            &encrypted,
+            &mk(50),
+            "local",
+            None,
+            None,
+            None,
+            3,
+            None,
+            Some(padding),
+            None,
+            None,
+            None,
+            None,
+            |_, _| {},
+        )
+        .unwrap();
+        encrypted
+    }
+
+    #[test]
+    fn test_v8_padding_hides_size_and_roundtrips() {
+        use crypto_stream::Padding;
+        let dir = make_test_dir("qre_v8_padding");
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+
+        // Random bytes do not compress, so 5000 of them push the file past 8 KB.
+        let noise: Vec<u8> = (0..5000).map(|_| rand::random::<u8>()).collect();
+        for (content, padding, expected) in [
+            (vec![1u8; 10], Padding::PowerOfTwo, 8 * 1024),
+            (noise, Padding::PowerOfTwo, 16 * 1024),
+            (vec![3u8; 10], Padding::Bucket(64 * 1024), 64 * 1024),
+        ] {
+            let encrypted = encrypt_padded(&dir, &content, padding);
+            assert_eq!(fs::metadata(&encrypted).unwrap().len(), expected);
+            let (_, header) = crypto_stream::read_stream_header(&encrypted).unwrap();
+            assert_eq!(header.padding, Some(padding));
+
+            let name = decrypt_to(&encrypted, &out_dir).unwrap();
+            assert_eq!(fs::read(out_dir.join(&name)).unwrap(), content);
+            let _ = fs::remove_file(out_dir.join(name));
+        }
+
+        assert!(Padding::Bucket(0).validate().is_err());
+        assert!(Padding::Bucket(u64::MAX).validate().is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    /// The padding length and bytes are bound to the trailer: resizing or rewriting
+    /// them, or dropping the record, must fail.
+    #[test]
+    fn test_v8_padding_tampering_detected() {
+        let dir = make_test_dir("qre_v8_padding_tamper");
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+        let encrypted = encrypt_padded(&dir, b"size matters", crypto_stream::Padding::PowerOfTwo);
+        let original = fs::read(&encrypted).unwrap();
+
+        // [prefix][chunk][PADDING_MARKER][len u64][padding][TRAILER_MARKER][record]
+        let (prefix, chunks, rest) = split_v8(&original);
+        let trailer = rest[rest.len() - 68..].to_vec();
+        let pad_len = u64::from_le_bytes(rest[4..12].try_into().unwrap()) as usize;
+        assert_eq!(
+            u32::from_le_bytes(rest[..4].try_into().unwrap()),
+            u32::MAX - 1
+        );
+        assert_eq!(rest.len(), 12 + pad_len + 68);
+
+        let mut flipped = original.clone();
+        let pad_start = original.len() - 68 - pad_len;
+        flipped[pad_start + pad_len / 2] ^= 0x01;
+        let mut shortened = [prefix.clone(), chunks.concat(), rest[..4].to_vec()].concat();
+        shortened.extend_from_slice(&(pad_len as u64 - 1).to_le_bytes());
+        shortened.extend_from_slice(&rest[12..12 + pad_len - 1]);
+        shortened.extend_from_slice(&trailer);
+        let unpadded = [prefix, chunks.concat(), trailer].concat();
+
+        for (bytes, expected) in [
+            (flipped, "Trailer"),
+            (shortened, "Trailer"),
+            (unpadded, "padding is missing"),
+        ] {
+            fs::write(&encrypted, bytes).unwrap();
+            let err = decrypt_to(&encrypted, &out_dir).unwrap_err().to_string();
+            assert!(err.contains(expected), "unexpected error: {err}");
+            assert!(!out_dir.join("padded.bin").exists());
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    /// Encrypts a short "expiring.bin" under mk(50) with `expiry`.
+    fn encrypt_expiring(dir: &std::path::Path, expiry: crypto_stream::Expiry) -> String {
+        let input = write_file(dir, "expiring.bin", b"for your eyes only");
+        let encrypted = format!("{}.qre", input);
+        crypto_stream::encrypt_file_stream_with_metadata(
+            &input,
+            &encrypted,
+            &mk(50),
+            "local",
+            None,
+            None,
+            None,
+            3,
+            None,
+            None,
+            Some(expiry),
+            None,
+            None,
+            None,
+            |_, _| {},
+        )
+        .unwrap();
+        encrypted
+    }
+
+    #[test]
+    fn test_v8_expiry_counts_failures_and_self_destructs() {
+        let dir = make_test_dir("qre_v8_expiry_attempts");
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+        let encrypted = encrypt_expiring(
+            &dir,
+            crypto_stream::Expiry {
+                expires_at: None,
+                max_failed_attempts: Some(3),
+            },
+        );
+        let wrong = |path: &str| {
+            crypto_stream::decrypt_file_stream(
+                path,
+                out_dir.to_str().unwrap(),
+                &mk(51),
+                None,
+                |_, _| {},
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert!(wrong(&encrypted).contains("2 attempts left"));
+        assert!(wrong(&encrypted).contains("1 attempt left"));
+        // A successful unlock resets the count.
+        assert_eq!(decrypt_to(&encrypted, &out_dir).unwrap(), "expiring.bin");
+        let (_, header) = crypto_stream::read_stream_header(&encrypted).unwrap();
+        assert_eq!(header.expiry.unwrap().failed_attempts, 0);
+
+        wrong(&encrypted);
+        wrong(&encrypted);
+        assert!(wrong(&encrypted).starts_with("DESTROYED:"));
+        assert!(!std::path::Path::new(&encrypted).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_v8_expiry_date_enforced_and_policy_bound_to_key() {
+        let dir = make_test_dir("qre_v8_expiry_date");
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+        let expired = encrypt_expiring(
+            &dir,
+            crypto_stream::Expiry {
+                expires_at: Some(1_000),
+                max_failed_attempts: None,
+            },
+        );
+        let err = decrypt_to(&expired, &out_dir).unwrap_err().to_string();
+        assert!(err.starts_with("EXPIRED:1000:"), "unexpected error: {err}");
+
+        // Stripping the policy from the header breaks the file key unwrap.
+        let mut bytes = fs::read(&expired).unwrap();
+        let (_, mut header) = crypto_stream::read_stream_header(&expired).unwrap();
+        header.expiry = None;
+        let serialized = bincode::serialize(&header).unwrap();
+        bytes[4..4 + 4096].fill(0);
+        bytes[4..4 + serialized.len()].copy_from_slice(&serialized);
+        fs::write(&expired, bytes).unwrap();
+        let err = decrypt_to(&expired, &out_dir).unwrap_err().to_string();
+        assert!(err.contains("unwrap file key"), "unexpected error: {err}");
+        assert!(!out_dir.join("expiring.bin").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    // =========================================================================
+    // SECTION 5B — KNOWN-ANSWER VECTORS & MALFORMED CONTAINER PARSING
+    // =========================================================================
+    // The vectors below were produced once with MasterKey([0x11; 32]) and are
+    // checked in verbatim. If a format change breaks them, existing user files
+    // would break the same way — update them only together with a version bump.
+
+    /// V6 stream of "kat.txt" containing `KAT_PLAINTEXT`.
+    const KAT_V6_HEX: &[&str] = &[
+        "060000000105000000000000006c6f63616c0c00000000000000ad89fa4a397dc21dc3ffec7c1900",
+        "000000000000b9b8fa52e6c5fb2c442930736b318ad2d60697f479fcce8cad0c00000000000000f9",
+        "15cbb7283af5d1230d0e063000000000000000f4e9e0f0ba6b01356bbbfe7c921bfe64fea44f70f6",
+        "68473601d7a492c4afd676d3b86555d85879038efdceade8dcd3660c0000000000000065d5561a8e",
+        "fd14682e6b06a407000000000000006b61742e747874012000000000000000ebfba8bd6a9b357c8c",
+        "c67e8792459f450a078a0a8954b7003a09f32dc6c6c655003000000004fa47f6c2dffa798d8f2727",
+        "864e8dd41b3ddf01fbef0c62d3e3dab0d44d0d101c3cf692a04c4b6dd5c928d7e3ecedf7",
+    ];
+
+    /// V4 container of "kat.json" containing `{"kat":true}`.
+    const KAT_V4_HEX: &[&str] = &[
+        "040000000c00000000000000c99d7a87788b1b0adcd6ee0619000000000000000e24354662d1107b",
+        "3f9440ffac313ee393dd2c805022ee1cd30c0000000000000057157be0d7026d6cbeb1c2fb300000",
+        "0000000000da82590f8c0afad17e34eb4a42b066ea2f5c791d84cf77f243c8d8f7d3e4eb42da7e0f",
+        "f476b273a8a225e009a239fd700c000000000000008b9c8d97848009c2767acc6400012000000000",
+        "000000d1078d3f7140e424683d5de5da675d5312430572f81f5ccc8cd6e0b60cbdd91a3d00000000",
+        "0000003c235f152c6b4450a0ad981f3063d4d8a12ba3284d3b7d2267e5352dcff7d2767c13bd0a8f",
+        "c6789dd9f72424d957db38d9be4eed1822007b9c4c75e1e6",
+    ];
+
+    const KAT_PLAINTEXT: &[u8] = b"QRE known-answer vector";
+
+    fn kat_bytes(hex: &[&str]) -> Vec<u8> {
+        data_encoding::HEXLOWER
+            .decode(hex.concat().as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_kat_v6_stream_vector_decrypts() {
+        let dir = make_test_dir("qre_kat_v6");
+        let encrypted = dir.join("kat.txt.qre");
+        fs::write(&encrypted, kat_bytes(KAT_V6_HEX)).unwrap();
+        let out_dir = dir.join("output");
+        fs::create_dir_all(&out_dir).unwrap();
+
+        let name = crypto_stream::decrypt_file_stream(
+            encrypted.to_str().unwrap(),
+            out_dir.to_str().unwrap(),
+            &mk(0x11),
+            None,
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(name, "kat.txt");
+        assert_eq!(fs::read(out_dir.join("kat.txt")).unwrap(), KAT_PLAINTEXT);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_kat_v4_container_vector_decrypts() {
+        let container = crypto::EncryptedFileContainer::from_bytes(&kat_bytes(KAT_V4_HEX)).unwrap();
+        let payload = crypto::decrypt_file_with_master_key(
+            &mk(0x11),
+            KeyPurpose::FileWrapping,
+            None,
+            &container,
+        )
+        .unwrap();
+        assert_eq!(payload.filename, "kat.json");
+        assert_eq!(payload.content, br#"{"kat":true}"#);
+    }
+
+    /// Every truncation of a valid file must be rejected cleanly — no panic, no hang.
+    #[test]
+    fn test_truncated_containers_never_panic() {
+        let v6 = kat_bytes(KAT_V6_HEX);
+        for len in 0..v6.len() {
+            let _ = crypto_stream::parse_stream_header_bytes(&v6[..len]);
+        }
+        let v4 = kat_bytes(KAT_V4_HEX);
+        for len in 0..v4.len() {
+            assert!(crypto::EncryptedFileContainer::from_bytes(&v4[..len]).is_err());
+        }
+    }
+
+    /// A length prefix claiming an exabyte-sized string must fail on the limit,
+    /// not attempt the allocation.
+    #[test]
+    fn test_stream_header_huge_length_prefix_rejected() {
+        let mut data = 6u32.to_le_bytes().to_vec();
+        data.push(1); // vault_id: Some(..)
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&[0u8; 64]);
+        assert!(crypto_stream::parse_stream_header_bytes(&data).is_err());
+
+        let mut v4 = 4u32.to_le_bytes().to_vec();
+        v4.extend_from_slice(&(1u64 << 40).to_le_bytes());
+        assert!(crypto::EncryptedFileContainer::from_bytes(&v4).is_err());
+    }
+
+    #[test]
+    fn test_unknown_versions_rejected() {
+        for version in [0u32, 2, 3, 8, u32::MAX] {
+            let mut data = version.to_le_bytes().to_vec();
+            data.extend_from_slice(&[0u8; 64]);
+            assert!(
+                crypto_stream::parse_stream_header_bytes(&data).is_err(),
+                "v{version}"
+            );
+        }
+    }
+
+    /// The stored filename is joined onto the output directory; traversal must be refused.
+    #[test]
+    fn test_stream_header_unsafe_filename_rejected() {
+        let v6 = kat_bytes(KAT_V6_HEX);
+        let (_, header) = crypto_stream::parse_stream_header_bytes(&v6).unwrap();
+
+        for bad in [
+            "../evil.txt",
+            "/etc/passwd",
+            "a/b.txt",
+            "..\\evil.txt",
+            "",
+            "..",
+        ] {
+            let mut tampered = header.clone();
+            tampered.original_filename = bad.to_string();
+            let mut data = 6u32.to_le_bytes().to_vec();
+            data.extend_from_slice(&bincode::serialize(&tampered).unwrap());
+            assert!(
+                crypto_stream::parse_stream_header_bytes(&data).is_err(),
+                "{bad:?} must be rejected"
+            );
+        }
+    }
+
+    /// Low resource mode records a smaller chunk size; anything outside the reader's
+    /// bounds is refused.
+    #[test]
+    fn test_stream_header_chunk_size_bounds() {
+        let v6 = kat_bytes(KAT_V6_HEX);
+        let (_, mut header) = crypto_stream::parse_stream_header_bytes(&v6).unwrap();
+        assert_eq!(header.chunk_size(), crypto_stream::CHUNK_SIZE);
+        header.chunk_size = Some(256 * 1024);
+        assert!(header.validate().is_ok());
+        for bad in [0, 1, crypto_stream::CHUNK_SIZE as u32 + 1] {
+            header.chunk_size = Some(bad);
+            assert!(header.validate().is_err(), "{bad}");
+        }
+    }
+
+    /// Wrong nonce lengths used to panic inside `Nonce::from_slice`.
+    #[test]
+    fn test_bad_nonce_lengths_rejected_without_panic() {
+        let v6 = kat_bytes(KAT_V6_HEX);
+        let (_, mut header) = crypto_stream::parse_stream_header_bytes(&v6).unwrap();
+        header.base_nonce = vec![0u8; 3];
+        let mut data = 6u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&bincode::serialize(&header).unwrap());
+        assert!(crypto_stream::parse_stream_header_bytes(&data).is_err());
+
+        let mut container =
+            crypto::EncryptedFileContainer::from_bytes(&kat_bytes(KAT_V4_HEX)).unwrap();
+        container.header.body_nonce = vec![0u8; 40];
+        assert!(crypto::EncryptedFileContainer::from_bytes(
+            &bincode::serialize(&container).unwrap()
+        )
+        .is_err());
+        assert!(crypto::decrypt_file_with_master_key(
+            &mk(0x11),
+            KeyPurpose::FileWrapping,
+            None,
+            &container
+        )
+        .is_err());
+    }
+    // ── Path Security tests call pub(crate) helpers in commands/files.rs ────────
+
+    use crate::commands::files::{
+        compression_level, is_already_compressed, is_system_critical, recommend_compression,
+        reject_critical_path, reject_path_traversal, run_compression_benchmark,
+        CompressionBenchmark, COMPRESSION_PRESETS,
+    };
+    use std::path::Path;
+
+    // ── Path Traversal ────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_path_traversal_dotdot_rejected() {
+        assert!(
+            reject_path_traversal(Path::new("/home/user/../etc/passwd")).is_err(),
+            "Single '..' must be rejected"
+        );
+    }
+
+    #[test]
+    fn test_path_traversal_nested_rejected() {
+        assert!(
+            reject_path_traversal(Path::new("docs/../../secret")).is_err(),
+            "Nested '..' must be rejected"
+        );
+    }
+
+    #[test]
+    fn test_path_traversal_normal_path_allowed() {
+        assert!(
+            reject_path_traversal(Path::new("/home/alice/documents/vault.json")).is_ok(),
+            "Normal absolute path must pass"
+        );
+    }
+
+    #[test]
+    fn test_path_traversal_relative_path_allowed() {
+        assert!(
+            reject_path_traversal(Path::new("exports/backup.csv")).is_ok(),
+            "Relative path without '..' must pass"
+        );
+    }
+
+    // ── System Critical Path Guard ────────────────────────────────────────────
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_etc_blocked() {
+        assert!(is_system_critical(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_root_blocked() {
+        assert!(is_system_critical(Path::new("/")));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_usr_bin_blocked() {
+        assert!(is_system_critical(Path::new("/usr/bin/sh")));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_boot_blocked() {
+        assert!(is_system_critical(Path::new("/boot/grub/grub.cfg")));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_proc_blocked() {
+        assert!(is_system_critical(Path::new("/proc/1/mem")));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_home_allowed() {
+        assert!(!is_system_critical(Path::new("/home/alice/vault.json")));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_critical_path_tmp_allowed() {
+        assert!(!is_system_critical(Path::new("/tmp/export.csv")));
+    }
+
+    #[test]
+    #[cfg(target_os = "windows")]
+    fn test_critical_path_windows_system32_blocked() {
+        assert!(is_system_critical(Path::new("C:\\Windows\\System32")));
+    }
+
+    #[test]
+    #[cfg(target_os = "windows")]
+    fn test_critical_path_windows_root_blocked() {
+        assert!(is_system_critical(Path::new("C:\\")));
     }
 
     #[test]
@@ -1123,38 +2041,675 @@ mod tests {
         assert!(!is_already_compressed(""));
     }
 
+    // ── Compression Benchmark ─────────────────────────────────────────────────
+
+    fn bench(mode: &str, output_bytes: u64, throughput_mb_s: f64) -> CompressionBenchmark {
+        CompressionBenchmark {
+            mode: mode.to_string(),
+            level: compression_level(mode, "sample.bin"),
+            duration_ms: 100,
+            output_bytes,
+            ratio: output_bytes as f64 / 1000.0,
+            throughput_mb_s,
+        }
+    }
+
+    #[test]
+    fn test_compression_levels_per_preset() {
+        assert_eq!(compression_level("store", "notes.txt"), 0);
+        assert_eq!(compression_level("extreme", "photo.jpg"), 19);
+        assert_eq!(compression_level("auto", "notes.txt"), 3);
+        assert_eq!(compression_level("auto", "photo.jpg"), 1);
+    }
+
+    #[test]
+    fn test_recommend_store_for_incompressible_data() {
+        let results = vec![
+            bench("store", 1000, 500.0),
+            bench("auto", 990, 400.0),
+            bench("extreme", 985, 20.0),
+        ];
+        assert_eq!(recommend_compression(&results), "store");
+    }
+
+    #[test]
+    fn test_recommend_extreme_only_when_much_smaller_and_fast_enough() {
+        let fast = vec![
+            bench("store", 1000, 500.0),
+            bench("auto", 500, 300.0),
+            bench("extreme", 400, 25.0),
+        ];
+        assert_eq!(recommend_compression(&fast), "extreme");
+
+        let slow = vec![
+            bench("store", 1000, 500.0),
+            bench("auto", 500, 300.0),
+            bench("extreme", 400, 2.0),
+        ];
+        assert_eq!(recommend_compression(&slow), "auto");
+
+        let marginal = vec![
+            bench("store", 1000, 500.0),
+            bench("auto", 500, 300.0),
+            bench("extreme", 480, 25.0),
+        ];
+        assert_eq!(recommend_compression(&marginal), "auto");
+    }
+
+    #[test]
+    fn test_run_compression_benchmark_on_text_sample() {
+        let dir = std::env::temp_dir().join("qre_benchmark_tests");
+        fs::create_dir_all(&dir).unwrap();
+        let sample = dir.join("sample.txt");
+        fs::write(
+            &sample,
+            "the quick brown fox jumps over the lazy dog\n".repeat(4096),
+        )
+        .unwrap();
+        let work = dir.join("work");
+
+        let report = run_compression_benchmark(&sample, &work).unwrap();
+        assert_eq!(report.results.len(), COMPRESSION_PRESETS.len());
+        assert!(!report.truncated);
+
+        let auto = report.results.iter().find(|r| r.mode == "auto").unwrap();
+        assert!(
+            auto.ratio < 0.5,
+            "repetitive text must compress, got {}",
+            auto.ratio
+        );
+        assert_ne!(report.recommended, "store");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // ── Container Requirements ────────────────────────────────────────────────
+
+    #[test]
+    fn test_container_requirements_keyfile_and_vault_state() {
+        use crate::commands::files::inspect_container;
+
+        let dir = make_test_dir("qre_requirements_tests");
+        let input = write_file(&dir, "doc.txt", b"requirements probe");
+        let plain = dir.join("plain.qre");
+        let keyed = dir.join("keyed.qre");
+        for (out, keyfile) in [(&plain, None), (&keyed, Some(&b"keyfile-bytes"[..]))] {
+            crypto_stream::encrypt_file_stream(
+                &input,
+                out.to_str().unwrap(),
+                &mk(0x42),
+                "local",
+                keyfile,
+                None,
+                None,
+                3,
+                |_, _| {},
+            )
+            .unwrap();
+        }
+
+        let unlocked = |id: &str| (id == "local").then(|| mk(0x42));
+        let locked = |_: &str| None;
+
+        let r = inspect_container(&plain, unlocked).unwrap();
+        assert_eq!(r.version, 8);
+        assert_eq!(r.vault_id, "local");
+        assert_eq!(r.original_filename.as_deref(), Some("doc.txt"));
+        assert_eq!(r.uses_keyfile, Some(false));
+        assert!(r.vault_unlocked && r.can_open);
+
+        let r = inspect_container(&keyed, unlocked).unwrap();
+        assert_eq!(r.uses_keyfile, Some(true));
+        assert!(r.vault_unlocked && !r.can_open);
+
+        // Vault locked: the keyfile question cannot be answered from the header.
+        let r = inspect_container(&keyed, locked).unwrap();
+        assert_eq!(r.uses_keyfile, None);
+        assert!(!r.vault_unlocked && !r.can_open);
+
+        let junk = write_file(&dir, "junk.qre", b"\xff\xff\xff\x7f garbage");
+        assert!(inspect_container(Path::new(&junk), unlocked).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // ── Keyfile Location ──────────────────────────────────────────────────────
+
+    #[test]
+    fn test_keyfile_location_prefers_longest_mount() {
+        use crate::commands::files::{locate_keyfile, MountedVolume};
+        use std::path::PathBuf;
+
+        let volumes = vec![
+            MountedVolume {
+                mount_point: PathBuf::from("/"),
+                name: "root".into(),
+                removable: false,
+            },
+            MountedVolume {
+                mount_point: PathBuf::from("/data"),
+                name: "data".into(),
+                removable: false,
+            },
+            MountedVolume {
+                mount_point: PathBuf::from("/media/usb"),
+                name: "KEYS".into(),
+                removable: true,
+            },
+        ];
+        let root = Path::new("/");
+
+        let usb = locate_keyfile(Path::new("/media/usb/vault.key"), &volumes, root);
+        assert!(usb.removable && !usb.on_system_drive && usb.warning.is_none());
+        assert_eq!(usb.drive_name.as_deref(), Some("KEYS"));
+
+        let system = locate_keyfile(Path::new("/home/alice/vault.key"), &volumes, root);
+        assert!(!system.removable && system.on_system_drive);
+        assert!(system.warning.unwrap().contains("system drive"));
+
+        let fixed = locate_keyfile(Path::new("/data/vault.key"), &volumes, root);
+        assert!(!fixed.removable && !fixed.on_system_drive && fixed.warning.is_some());
+    }
+
+    #[test]
+    fn test_removed_drive_only_reports_vanished_removable_volumes() {
+        use crate::commands::files::{removed_drive, MountedVolume};
+        use std::path::PathBuf;
+
+        let root = std::env::temp_dir();
+        let usb_mount = make_test_dir("qre_removed_drive_usb");
+        let volume = |mount_point: PathBuf, removable| MountedVolume {
+            mount_point,
+            name: "vol".into(),
+            removable,
+        };
+        let before = vec![
+            volume(PathBuf::from("/"), false),
+            volume(root.clone(), false),
+            volume(usb_mount.clone(), true),
+        ];
+        let file = usb_mount.join("report.pdf");
+
+        // Still mounted: an ordinary failure.
+        assert!(removed_drive(&[&file], &before, &before).is_none());
+        // Unmounted: reported, whether the destination or the source was on it.
+        let now = before[..2].to_vec();
+        let drive = removed_drive(&[Path::new("/elsewhere"), &file], &before, &now).unwrap();
+        assert_eq!(drive.mount_point, usb_mount);
+        // A fixed volume that vanishes is not a pulled drive.
+        assert!(removed_drive(&[&root.join("a.txt")], &before, &before[..1]).is_none());
+        let _ = fs::remove_dir_all(usb_mount);
+    }
+
+    // ── Batch Unlock Destination ──────────────────────────────────────────────
+
+    #[test]
+    fn test_unlock_destination_structure_and_collisions() {
+        use crate::commands::files::{
+            common_source_root, place_unlocked_file, structured_target_dir, CollisionPolicy,
+        };
+
+        let sources = [
+            Path::new("/home/alice/work/a/one.qre"),
+            Path::new("/home/alice/work/b/c/two.qre"),
+        ];
+        let root = common_source_root(sources.iter().copied()).unwrap();
+        assert_eq!(root, Path::new("/home/alice/work"));
+        let dest = Path::new("/out");
+        assert_eq!(
+            structured_target_dir(dest, &root, sources[1]),
+            Path::new("/out/b/c")
+        );
+        assert_eq!(
+            structured_target_dir(dest, &root, Path::new("/elsewhere/x.qre")),
+            Path::new("/out")
+        );
+
+        let dir = make_test_dir("qre_unlock_destination_tests");
+        let target = std::path::PathBuf::from(write_file(&dir, "report.txt", b"existing"));
+        let staged =
+            |content: &[u8]| std::path::PathBuf::from(write_file(&dir, "staged.txt", content));
+
+        assert!(
+            place_unlocked_file(&staged(b"new"), &target, CollisionPolicy::Skip)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(fs::read(&target).unwrap(), b"existing");
+
+        let renamed = place_unlocked_file(&staged(b"new"), &target, CollisionPolicy::Rename)
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed, dir.join("report (1).txt"));
+
+        place_unlocked_file(&staged(b"newer"), &target, CollisionPolicy::Overwrite).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"newer");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // ── Locked File Search ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_search_locked_files_uses_sealed_index() {
+        use crate::commands::files::search_containers;
+        use crate::container_meta;
+
+        let dir = make_test_dir("qre_locked_search_tests");
+        let report = write_file(&dir, "report.txt", b"Quarterly revenue for Acme");
+        let other = write_file(&dir, "other.txt", b"Holiday itinerary Crete");
+        for (input, indexed) in [(&report, true), (&other, false)] {
+            let meta = container_meta::build_for_file(Path::new(input), indexed, &[])
+                .map(|m| m.to_bytes().unwrap());
+            crypto_stream::encrypt_file_stream_with_metadata(
+                input,
+                &format!("{}.qre", input),
+                &mk(0x51),
+                "local",
+                None,
+                None,
+                None,
+                3,
+                meta.as_deref(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                |_, _| {},
+            )
+            .unwrap();
+        }
+
+        let unlocked = |id: &str| (id == "local").then(|| mk(0x51));
+        let r = search_containers(&dir, "revenue ACME", false, unlocked).unwrap();
+        assert_eq!(r.scanned, 2);
+        assert_eq!(r.not_indexed, 1);
+        assert_eq!(r.matches.len(), 1);
+        assert_eq!(r.matches[0].original_filename, "report.txt");
+
+        assert!(search_containers(&dir, "crete", false, unlocked)
+            .unwrap()
+            .matches
+            .is_empty());
+
+        // Wrong or missing session key: the index stays sealed.
+        let wrong = search_containers(&dir, "revenue", false, |_| Some(mk(0x52))).unwrap();
+        assert!(wrong.matches.is_empty());
+        assert_eq!(wrong.inaccessible, 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // ── Container Listing ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_list_containers_decrypts_labels_for_unlocked_vault() {
+        use crate::commands::files::list_container_entries;
+        use crate::container_meta;
+
+        let dir = make_test_dir("qre_container_listing_tests");
+        let input = write_file(&dir, "scan.pdf", b"not really a pdf");
+        let labels = container_meta::normalize_labels(&["Tax 2024".to_string()]).unwrap();
+        let meta = container_meta::build_for_file(Path::new(&input), false, &labels)
+            .map(|m| m.to_bytes().unwrap());
+        let output = dir.join("3f2b9c1e.qre");
+        crypto_stream::encrypt_file_stream_with_metadata(
+            &input,
+            &output.to_string_lossy(),
+            &mk(0x53),
+            "local",
+            None,
+            None,
+            None,
+            3,
+            meta.as_deref(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            |_, _| {},
+        )
+        .unwrap();
+        // Not a container: must be skipped, not reported.
+        write_file(&dir, "junk.qre", b"garbage");
+
+        let listing = list_container_entries(&dir, false, |id| (id == "local").then(|| mk(0x53)));
+        assert_eq!(listing.len(), 1);
+        assert_eq!(listing[0].file_name, "3f2b9c1e.qre");
+        assert_eq!(listing[0].original_filename, "scan.pdf");
+        assert_eq!(
+            listing[0].labels.as_deref(),
+            Some(&["Tax 2024".to_string()][..])
+        );
+        assert!(!listing[0].has_search_index);
+
+        // Vault locked: the file is still listed, its labels are not.
+        let locked = list_container_entries(&dir, false, |_| None);
+        assert_eq!(locked.len(), 1);
+        assert!(locked[0].labels.is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // ── Second Instance Handoff ───────────────────────────────────────────────
+
+    #[test]
+    fn test_paths_from_args_resolves_relative_files() {
+        use crate::commands::files::paths_from_args;
+
+        let dir = make_test_dir("qre_handoff_tests");
+        let file = write_file(&dir, "secret.qre", b"x");
+        let argv: Vec<String> = vec![
+            "qre".into(),
+            "--flag".into(),
+            "secret.qre".into(),
+            "missing.qre".into(),
+            file.clone(),
+        ];
+        let paths = paths_from_args(&argv, &dir);
+        assert_eq!(
+            paths.len(),
+            2,
+            "Relative and absolute forms of the same file"
+        );
+        assert!(paths.iter().all(|p| Path::new(p).ends_with("secret.qre")));
+        assert!(
+            paths_from_args(&argv[..1], &dir).is_empty(),
+            "Program name is skipped"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // ── Keyfile Generation ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_generated_keyfile_is_random_and_backup_roundtrips() {
+        use crate::commands::files::{
+            keyfile_backup_text, parse_keyfile_backup, random_keyfile_bytes,
+        };
+
+        let a = random_keyfile_bytes(64, None).unwrap();
+        let b = random_keyfile_bytes(64, Some(&[0u8; 32])).unwrap();
+        assert_eq!(a.len(), 64);
+        assert_ne!(
+            a, b,
+            "Each keyfile must be fresh, whatever the entropy seed"
+        );
+
+        let text = keyfile_backup_text(&a);
+        assert!(text.starts_with("qre-keyfile:1:"));
+        assert_eq!(
+            parse_keyfile_backup(&format!("{}\n", text.as_str())).unwrap(),
+            a
+        );
+        assert!(parse_keyfile_backup("qre-keyfile:1:not base64!").is_err());
+        assert!(parse_keyfile_backup("some other QR code").is_err());
+    }
+
     // ── rename_item Input Validation ──────────────────────────────────────────
 
     #[test]
     fn test_rename_rejects_empty_name() {
-        let r = crate::commands::files::rename_item("/tmp/file.txt".into(), "".into());
+        let r = crate::commands::files::rename_path("/tmp/file.txt".into(), "".into());
         assert!(r.is_err(), "Empty rename must be rejected");
     }
 
     #[test]
     fn test_rename_rejects_dot() {
-        let r = crate::commands::files::rename_item("/tmp/file.txt".into(), ".".into());
+        let r = crate::commands::files::rename_path("/tmp/file.txt".into(), ".".into());
         assert!(r.is_err(), "'.' as new name must be rejected");
     }
 
     #[test]
     fn test_rename_rejects_dotdot() {
-        let r = crate::commands::files::rename_item("/tmp/file.txt".into(), "..".into());
+        let r = crate::commands::files::rename_path("/tmp/file.txt".into(), "..".into());
         assert!(r.is_err(), "'..' as new name must be rejected");
     }
 
     #[test]
     fn test_rename_rejects_slash_in_name() {
-        let r = crate::commands::files::rename_item("/tmp/file.txt".into(), "sub/dir.txt".into());
+        let r = crate::commands::files::rename_path("/tmp/file.txt".into(), "sub/dir.txt".into());
         assert!(r.is_err(), "Name containing '/' must be rejected");
     }
 
     #[test]
     fn test_rename_rejects_backslash_in_name() {
-        let r = crate::commands::files::rename_item("/tmp/file.txt".into(), "sub\\dir.txt".into());
+        let r = crate::commands::files::rename_path("/tmp/file.txt".into(), "sub\\dir.txt".into());
         assert!(r.is_err(), "Name containing '\\' must be rejected");
     }
 
+    // ── SafePath (shared command path policy) ─────────────────────────────────
+
+    fn safe_path_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join("qre_safe_path_tests").join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_safe_path_rejects_traversal_system_and_empty() {
+        use crate::commands::safe_path::{PathPolicy, SafePath};
+        assert!(SafePath::new("", PathPolicy::read_file()).is_err());
+        assert!(SafePath::new("/tmp/../etc/passwd", PathPolicy::read_file()).is_err());
+        assert!(SafePath::new("/etc/passwd", PathPolicy::read_file()).is_err());
+        assert!(SafePath::new("/etc/qre-export.txt", PathPolicy::write_file()).is_err());
+        assert!(SafePath::new("bad\0name", PathPolicy::write_file()).is_err());
+    }
+
+    #[test]
+    fn test_safe_path_existence_kind_and_size() {
+        use crate::commands::safe_path::{PathPolicy, SafePath};
+        let dir = safe_path_dir("kinds");
+        let file = dir.join("a.txt");
+        std::fs::write(&file, b"0123456789").unwrap();
+        let file_str = file.to_str().unwrap();
+
+        let safe = SafePath::new(file_str, PathPolicy::read_file()).unwrap();
+        assert!(safe.is_absolute());
+        assert!(SafePath::new(file_str, PathPolicy::read_file().max_bytes(5)).is_err());
+        assert!(SafePath::new(file_str, PathPolicy::directory()).is_err());
+        assert!(SafePath::new(dir.to_str().unwrap(), PathPolicy::read_file()).is_err());
+        assert!(SafePath::new(
+            dir.join("missing.txt").to_str().unwrap(),
+            PathPolicy::read_file()
+        )
+        .is_err());
+
+        // Outputs may not exist yet, but their folder must.
+        assert!(SafePath::new(
+            dir.join("new.txt").to_str().unwrap(),
+            PathPolicy::write_file()
+        )
+        .is_ok());
+        assert!(SafePath::new(
+            dir.join("nope").join("new.txt").to_str().unwrap(),
+            PathPolicy::write_file()
+        )
+        .is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_safe_path_symlink_policies() {
+        use crate::commands::safe_path::{PathPolicy, SafePath};
+        let dir = safe_path_dir("links");
+        let target = dir.join("target.txt");
+        std::fs::write(&target, b"secret").unwrap();
+        let link = dir.join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let link_str = link.to_str().unwrap();
+
+        // Reading follows the link to its target.
+        let read = SafePath::new(link_str, PathPolicy::read_file()).unwrap();
+        assert_eq!(read.file_name().unwrap(), "target.txt");
+        // Writing through a planted link is refused.
+        assert!(SafePath::new(link_str, PathPolicy::write_file()).is_err());
+        // In-place operations act on the link itself, never the target.
+        let entry = SafePath::new(link_str, PathPolicy::existing_entry()).unwrap();
+        assert_eq!(entry.file_name().unwrap(), "link.txt");
+
+        // A link into a protected location is caught after resolution.
+        let sneaky = dir.join("sneaky");
+        std::os::unix::fs::symlink("/etc/hostname", &sneaky).unwrap();
+        assert!(SafePath::new(sneaky.to_str().unwrap(), PathPolicy::read_file()).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // ── Command rate limits & job guards ──────────────────────────────────────
+
+    #[test]
+    fn test_rate_limiter_sliding_window() {
+        use crate::commands::guard::{RateLimit, RateLimiter};
+        use std::time::{Duration, Instant};
+        let limit = RateLimit {
+            max_calls: 3,
+            window: Duration::from_secs(10),
+        };
+        let mut limiter = RateLimiter::default();
+        let t0 = Instant::now();
+
+        for i in 0..3 {
+            assert!(limiter
+                .check_at("login", limit, t0 + Duration::from_secs(i))
+                .is_ok());
+        }
+        let wait = limiter
+            .check_at("login", limit, t0 + Duration::from_secs(3))
+            .unwrap_err();
+        assert_eq!(
+            wait,
+            Duration::from_secs(7),
+            "retry once the oldest call leaves the window"
+        );
+
+        // Buckets are per command.
+        assert!(limiter
+            .check_at("delete_items", limit, t0 + Duration::from_secs(3))
+            .is_ok());
+        // The oldest call expires at t0+10s, freeing exactly one slot.
+        assert!(limiter
+            .check_at("login", limit, t0 + Duration::from_secs(10))
+            .is_ok());
+        assert!(limiter
+            .check_at("login", limit, t0 + Duration::from_secs(10))
+            .is_err());
+    }
+
+    #[test]
+    fn test_job_guard_is_exclusive_and_released_on_drop() {
+        use crate::commands::guard::{Job, JobGuard};
+        let first = JobGuard::acquire(Job::SystemClean).unwrap();
+        let err = JobGuard::acquire(Job::SystemClean).err().unwrap();
+        assert!(err.starts_with("BUSY:system_clean:"), "got {}", err);
+        drop(first);
+        assert!(JobGuard::acquire(Job::SystemClean).is_ok());
+    }
+
+    #[test]
+    fn test_read_only_session_blocks_writes() {
+        let state = crate::state::SessionState::new();
+        assert!(state.ensure_writable("local").is_ok());
+
+        state.set_read_only("local", true);
+        let err = state.ensure_writable("local").unwrap_err();
+        assert!(err.contains("read-only"));
+
+        state.set_read_only("local", false);
+        assert!(state.ensure_writable("local").is_ok());
+    }
+
+    #[test]
+    fn test_read_only_is_tracked_per_vault() {
+        let state = crate::state::SessionState::new();
+        let usb = "0b7e1c2a-portable";
+
+        // Guest on the local vault, then an owner login to a portable one.
+        state.set_read_only("local", true);
+        state.set_read_only(usb, false);
+        assert!(state.ensure_writable(usb).is_ok());
+        assert!(state.ensure_writable("local").is_err());
+
+        state.set_read_only(usb, true);
+        state.set_read_only("local", false);
+        assert!(state.ensure_writable("local").is_ok());
+        assert!(state.ensure_writable(usb).is_err());
+
+        state.lock_all();
+        assert!(state.ensure_writable(usb).is_ok());
+    }
+
+    #[test]
+    fn test_guest_portable_vault_cannot_unlock_its_files() {
+        use crate::commands::files::ensure_container_writable;
+        let dir = make_test_dir("qre_guest_portable_unlock");
+        let input = write_file(&dir, "plan.txt", b"portable secret");
+        let usb = "0b7e1c2a-portable";
+        let usb_file = dir.join("usb.qre");
+        let local_file = dir.join("local.qre");
+        for (vault_id, out) in [(usb, &usb_file), ("local", &local_file)] {
+            crypto_stream::encrypt_file_stream(
+                &input,
+                out.to_str().unwrap(),
+                &mk(9),
+                vault_id,
+                None,
+                None,
+                None,
+                1,
+                |_, _| {},
+            )
+            .unwrap();
+        }
+
+        // Owner on the local vault, guest on the portable one.
+        let state = crate::state::SessionState::new();
+        state.vaults.lock().unwrap().insert("local".into(), mk(1));
+        state.vaults.lock().unwrap().insert(usb.into(), mk(9));
+        state.set_user("local", crate::keychain::OWNER_SLOT_NAME);
+        state.set_read_only(usb, true);
+
+        assert!(state.ensure_writable("local").is_ok());
+        let err = ensure_container_writable(&state, &usb_file).unwrap_err();
+        assert!(err.contains("read-only"), "got {}", err);
+        assert_eq!(
+            ensure_container_writable(&state, &local_file).unwrap(),
+            "local"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_guest_session_cannot_create_profiles() {
+        use crate::commands::vault::create_profile_in;
+        use crate::profiles;
+        let root = make_test_dir("qre_profile_guest");
+        let state = crate::state::SessionState::new();
+
+        // Nothing unlocked: `user_for` would still name the owner.
+        assert!(create_profile_in(&state, &root, "work").is_err());
+        assert!(profiles::find(&root, "work").unwrap().is_none());
+
+        state.vaults.lock().unwrap().insert("local".into(), mk(1));
+        state.set_read_only("local", true);
+        let err = create_profile_in(&state, &root, "work").unwrap_err();
+        assert!(err.contains("read-only"), "got {}", err);
+
+        state.set_read_only("local", false);
+        state.set_user("local", "alice");
+        assert!(create_profile_in(&state, &root, "work").is_err());
+        assert!(profiles::find(&root, "work").unwrap().is_none());
+
+        state.set_user("local", crate::keychain::OWNER_SLOT_NAME);
+        create_profile_in(&state, &root, "work").unwrap();
+        assert!(profiles::find(&root, "work").unwrap().is_some());
+        let _ = fs::remove_dir_all(&root);
+    }
+
     // =========================================================================
     // SECTION 6 — VAULTS (Passwords, Notes, Bookmarks)
     // =========================================================================
@@ -1178,6 +2733,14 @@ mod tests {
             color: "".to_string(),
             is_pinned: false,
             totp_secret: None, // ← add this
+            url_match: Default::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
         };
         vault.entries.push(bad_entry.clone());
         assert!(vault.validate().is_err(), "Empty ID must fail");
@@ -1203,6 +2766,9 @@ mod tests {
             updated_at: 0,
             is_pinned: false,
             tags: vec![],
+            image_ids: vec![],
+            asset_ids: vec![],
+            history: vec![],
         };
 
         // Exceeding 10 tags must fail
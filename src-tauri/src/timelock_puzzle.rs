@@ -0,0 +1,385 @@
+// --- START OF FILE timelock_puzzle.rs ---
+
+// ==========================================
+// --- TIME-LOCK PUZZLE ---
+// ==========================================
+// A sequential time-lock puzzle (Rivest–Shamir–Wagner) that can gate a time-locked
+// container on top of the clock check in crypto_stream.rs. The clock check trusts NTP
+// and the file's ratchet; the puzzle trusts nothing but arithmetic. Its solution,
+// mixed into the file's key wrapping, is `x^(2^T) mod N`: whoever made the puzzle
+// knows the factors of N and gets there in one exponentiation, everyone else has to
+// square T times in a row, and squarings cannot be spread across machines.
+//
+// T is sized from how many squarings per second the locking computer manages, times
+// the seconds until the chosen date. THE DATE IS THEREFORE APPROXIMATE: a faster
+// computer finishes sooner, a slower one later, and no progress is made while no
+// computer is working on it. Progress is stored in the header so solving can be
+// paused and resumed; nothing here needs the master key, and nothing leaves the device.
+
+use anyhow::{anyhow, Result};
+use num_bigint::BigUint;
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// Bits of the modulus N = p·q. Factoring it must be far harder than the squarings.
+pub const MODULUS_BITS: u64 = 2048;
+const MIN_MODULUS_BYTES: usize = 32;
+const MAX_MODULUS_BYTES: usize = 512;
+const CHECK_LEN: usize = 8;
+/// Miller–Rabin rounds per prime candidate (error below 2^-80).
+const MILLER_RABIN_ROUNDS: usize = 40;
+/// How long `measure_squarings_per_sec` squares for.
+const CALIBRATION_TIME: Duration = Duration::from_millis(500);
+
+const SMALL_PRIMES: [u32; 53] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251,
+];
+
+/// The puzzle as stored in the StreamHeader. Everything here is public: knowing it
+/// only tells an attacker how much work is left.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleMeta {
+    /// N, big-endian.
+    pub modulus: Vec<u8>,
+    /// T, the number of squarings the puzzle takes.
+    pub squarings: u64,
+    /// Speed of the computer that made the puzzle, for estimates.
+    pub squarings_per_sec: u64,
+    /// Squarings done so far.
+    pub done: u64,
+    /// `x^(2^done) mod N`, big-endian. Starts at the random base x.
+    pub value: Vec<u8>,
+    /// Truncated hash of the solution, so a corrupted checkpoint is caught at the end
+    /// instead of producing a wrong key.
+    pub check: Vec<u8>,
+}
+
+impl PuzzleMeta {
+    /// Rejects fields that would make the solver divide by zero or run off the end.
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_MODULUS_BYTES..=MAX_MODULUS_BYTES).contains(&self.modulus.len())
+            || self.modulus[0] == 0
+            || self.modulus.last().is_some_and(|b| b % 2 == 0)
+        {
+            return Err(anyhow!("invalid modulus"));
+        }
+        if self.value.len() > self.modulus.len() || self.check.len() != CHECK_LEN {
+            return Err(anyhow!("invalid puzzle value"));
+        }
+        if self.squarings == 0 || self.squarings_per_sec == 0 || self.done > self.squarings {
+            return Err(anyhow!("invalid puzzle progress"));
+        }
+        Ok(())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.done >= self.squarings
+    }
+
+    /// Seconds of work left at `squarings_per_sec`.
+    pub fn remaining_secs(&self, squarings_per_sec: u64) -> u64 {
+        (self.squarings - self.done.min(self.squarings)) / squarings_per_sec.max(1)
+    }
+
+    pub fn percent(&self) -> u8 {
+        ((self.done as u128 * 100) / self.squarings.max(1) as u128).min(100) as u8
+    }
+}
+
+/// Shown with every puzzle status, so the UI never presents the date as exact.
+pub const APPROXIMATE_NOTE: &str = "The puzzle was sized for the computer that locked this \
+    file, so the date is approximate: a faster computer opens it sooner, a slower one later, \
+    and no progress is made while no computer is solving it. The file also stays locked until \
+    the date has passed.";
+
+/// Puzzle progress for the frontend. Contains no secrets.
+#[derive(Serialize, Debug)]
+pub struct PuzzleStatus {
+    pub done: u64,
+    pub total: u64,
+    pub percent: u8,
+    pub solved: bool,
+    /// Solving time left at the given speed, e.g. "3 days, 2 hours".
+    pub remaining_display: String,
+    pub note: &'static str,
+}
+
+pub fn status(meta: &PuzzleMeta, squarings_per_sec: u64) -> PuzzleStatus {
+    PuzzleStatus {
+        done: meta.done,
+        total: meta.squarings,
+        percent: meta.percent(),
+        solved: meta.is_solved(),
+        remaining_display: if meta.is_solved() {
+            String::new()
+        } else {
+            crate::timelock::format_duration(meta.remaining_secs(squarings_per_sec))
+        },
+        note: APPROXIMATE_NOTE,
+    }
+}
+
+// ==========================================
+// --- CREATE ---
+// ==========================================
+
+/// Makes a puzzle that takes about `seconds` on a computer doing `squarings_per_sec`,
+/// and returns it with its key. The key is derived through the shortcut only the
+/// creator has; the factors are dropped before returning.
+pub fn create(seconds: u64, squarings_per_sec: u64) -> Result<(PuzzleMeta, Zeroizing<[u8; 32]>)> {
+    create_with_bits(seconds, squarings_per_sec, MODULUS_BITS)
+}
+
+fn create_with_bits(
+    seconds: u64,
+    squarings_per_sec: u64,
+    bits: u64,
+) -> Result<(PuzzleMeta, Zeroizing<[u8; 32]>)> {
+    if squarings_per_sec == 0 {
+        return Err(anyhow!("The puzzle speed must be positive."));
+    }
+    let squarings = seconds.saturating_mul(squarings_per_sec).max(1);
+    let one = BigUint::from(1u8);
+
+    let p = random_prime(bits / 2)?;
+    let q = loop {
+        let q = random_prime(bits / 2)?;
+        if q != p {
+            break q;
+        }
+    };
+    let modulus = &p * &q;
+    let phi = (&p - &one) * (&q - &one);
+
+    let base = random_below(&(&modulus - 3u8))? + 2u8;
+    // x^(2^T) mod N = x^(2^T mod φ(N)) mod N, since x is coprime to N (a random x
+    // sharing a factor with N would mean having found the factor by chance).
+    let exponent = BigUint::from(2u8).modpow(&BigUint::from(squarings), &phi);
+    let solution = base.modpow(&exponent, &modulus);
+
+    let meta = PuzzleMeta {
+        modulus: modulus.to_bytes_be(),
+        squarings,
+        squarings_per_sec,
+        done: 0,
+        value: base.to_bytes_be(),
+        check: check_of(&solution, &modulus),
+    };
+    Ok((meta, key_of(&solution, &modulus)))
+}
+
+// ==========================================
+// --- SOLVE ---
+// ==========================================
+
+/// Does up to `max_squarings` more squarings and records the progress in `meta`.
+/// Returns true once the puzzle is solved.
+pub fn advance(meta: &mut PuzzleMeta, max_squarings: u64) -> Result<bool> {
+    meta.validate()?;
+    let modulus = BigUint::from_bytes_be(&meta.modulus);
+    let mut value = BigUint::from_bytes_be(&meta.value);
+    let steps = max_squarings.min(meta.squarings - meta.done);
+    for _ in 0..steps {
+        value = &value * &value % &modulus;
+    }
+    meta.done += steps;
+    meta.value = value.to_bytes_be();
+    Ok(meta.is_solved())
+}
+
+/// The key of a solved puzzle.
+pub fn solved_key(meta: &PuzzleMeta) -> Result<Zeroizing<[u8; 32]>> {
+    meta.validate()?;
+    if !meta.is_solved() {
+        return Err(anyhow!(
+            "The time-lock puzzle is {}% solved.",
+            meta.percent()
+        ));
+    }
+    let modulus = BigUint::from_bytes_be(&meta.modulus);
+    let solution = BigUint::from_bytes_be(&meta.value);
+    if check_of(&solution, &modulus) != meta.check {
+        return Err(anyhow!(
+            "The time-lock puzzle's saved progress is corrupted and it has to be solved again."
+        ));
+    }
+    Ok(key_of(&solution, &modulus))
+}
+
+/// Derives the keyfile input that wraps the file key from the puzzle key and the
+/// time-lock's own one, so the file needs both.
+pub fn mix(puzzle_key: &[u8; 32], keyfile: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_TIMELOCK_PUZZLE_MIX");
+    hasher.update(puzzle_key);
+    hasher.update(keyfile);
+    hasher.finalize().to_vec()
+}
+
+/// Squarings per second this computer manages on a modulus of the real size. Any odd
+/// number of that size costs the same to square in, so no primes are needed.
+pub fn measure_squarings_per_sec() -> Result<u64> {
+    let mut modulus = random_bits(MODULUS_BITS)?;
+    modulus.set_bit(0, true);
+    let mut value = random_below(&modulus)?;
+    let start = Instant::now();
+    let mut count = 0u64;
+    while start.elapsed() < CALIBRATION_TIME {
+        for _ in 0..100 {
+            value = &value * &value % &modulus;
+        }
+        count += 100;
+    }
+    Ok((count as f64 / start.elapsed().as_secs_f64()).max(1.0) as u64)
+}
+
+// ==========================================
+// --- INTERNALS ---
+// ==========================================
+
+/// The solution written at the full width of the modulus, so leading zero bytes
+/// cannot change the key.
+fn padded(solution: &BigUint, modulus: &BigUint) -> Vec<u8> {
+    let width = modulus.to_bytes_be().len();
+    let bytes = solution.to_bytes_be();
+    let mut out = vec![0u8; width.saturating_sub(bytes.len())];
+    out.extend_from_slice(&bytes);
+    out
+}
+
+fn key_of(solution: &BigUint, modulus: &BigUint) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_PUZZLE_KEY");
+    hasher.update(padded(solution, modulus));
+    Zeroizing::new(hasher.finalize().into())
+}
+
+fn check_of(solution: &BigUint, modulus: &BigUint) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"QRE_PUZZLE_CHECK");
+    hasher.update(padded(solution, modulus));
+    hasher.finalize()[..CHECK_LEN].to_vec()
+}
+
+fn random_bits(bits: u64) -> Result<BigUint> {
+    let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| anyhow!("RNG failure: {}", e))?;
+    let mut n = BigUint::from_bytes_be(&bytes);
+    for bit in bits..bytes.len() as u64 * 8 {
+        n.set_bit(bit, false);
+    }
+    Ok(n)
+}
+
+/// Uniform in [0, bound) by rejection.
+fn random_below(bound: &BigUint) -> Result<BigUint> {
+    loop {
+        let n = random_bits(bound.bits())?;
+        if &n < bound {
+            return Ok(n);
+        }
+    }
+}
+
+/// A random prime of exactly `bits` bits with the top two set, so p·q has the full
+/// modulus size.
+fn random_prime(bits: u64) -> Result<BigUint> {
+    loop {
+        let mut candidate = random_bits(bits)?;
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(bits - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate)? {
+            return Ok(candidate);
+        }
+    }
+}
+
+fn is_probable_prime(n: &BigUint) -> Result<bool> {
+    for p in SMALL_PRIMES {
+        if n % p == BigUint::ZERO {
+            return Ok(*n == BigUint::from(p));
+        }
+    }
+    let one = BigUint::from(1u8);
+    let n_minus_one = n - &one;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+    'witness: for _ in 0..MILLER_RABIN_ROUNDS {
+        let a = random_below(&(n - 3u8))? + 2u8;
+        let mut x = a.modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solving_reaches_the_creators_key() {
+        let (meta, key) = create_with_bits(3, 100, 256).unwrap();
+        assert_eq!(meta.squarings, 300);
+        meta.validate().unwrap();
+
+        let mut solving = meta.clone();
+        assert!(solved_key(&solving).is_err());
+        assert!(!advance(&mut solving, 120).unwrap());
+        assert_eq!((solving.done, solving.percent()), (120, 40));
+        assert_eq!(solving.remaining_secs(100), 1);
+        // Progress survives a round trip through the header, as a checkpoint would.
+        let mut resumed: PuzzleMeta =
+            bincode::deserialize(&bincode::serialize(&solving).unwrap()).unwrap();
+        assert!(advance(&mut resumed, u64::MAX).unwrap());
+        assert_eq!(resumed.done, 300);
+        assert_eq!(*solved_key(&resumed).unwrap(), *key);
+
+        resumed.value[0] ^= 1;
+        assert!(solved_key(&resumed).is_err());
+        assert_ne!(mix(&key, b"a"), mix(&key, b"b"));
+    }
+
+    #[test]
+    fn test_primality_and_validation() {
+        assert!(is_probable_prime(&BigUint::from(251u32)).unwrap());
+        assert!(is_probable_prime(&BigUint::from(2_147_483_647u32)).unwrap());
+        // Carmichael number: passes Fermat, fails Miller–Rabin.
+        assert!(!is_probable_prime(&BigUint::from(3_215_031_751u64)).unwrap());
+        assert_eq!(random_prime(64).unwrap().bits(), 64);
+
+        let (meta, _) = create_with_bits(1, 10, 256).unwrap();
+        let mut even = meta.clone();
+        *even.modulus.last_mut().unwrap() &= !1;
+        assert!(even.validate().is_err());
+        let mut zero = meta.clone();
+        zero.modulus = vec![0; 32];
+        assert!(zero.validate().is_err());
+        let mut overrun = meta;
+        overrun.done = overrun.squarings + 1;
+        assert!(overrun.validate().is_err());
+        assert!(advance(&mut overrun, 1).is_err());
+    }
+}
+
+// --- END OF FILE timelock_puzzle.rs ---
@@ -0,0 +1,290 @@
+// --- START OF FILE timestamps.rs ---
+
+// ==========================================
+// --- FILE TIMESTAMP SCRUBBER ---
+// ==========================================
+// Filesystem timestamps survive metadata cleaning and leak activity patterns (when a
+// document was written, when a folder of photos was last opened). This sets the
+// modification, access and — where the OS allows it — creation time of files and
+// folders to one of:
+//   fixed   a chosen instant (default 1980-01-01, the earliest FAT/ZIP time)
+//   random  an independent random instant within a range, per entry
+//   now     the current time
+//
+// Creation ("birth") time can be set on Windows and macOS only; Linux offers no API
+// for it, which the report states so the UI does not overpromise.
+//
+// Folders are walked contents-first; symlinks are skipped so their targets (possibly
+// outside the selection) are never touched.
+
+use rand::{rngs::OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, FileTimes};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 1980-01-01T00:00:00Z: old enough to say nothing, valid on every filesystem.
+pub const DEFAULT_FIXED_TIME: u64 = 315_532_800;
+/// Upper bound on entries touched by one call (folders can be huge).
+const MAX_SCRUB_ENTRIES: usize = 100_000;
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimestampMode {
+    Fixed {
+        /// Unix seconds.
+        #[serde(default = "default_fixed_time")]
+        time: u64,
+    },
+    /// Unix seconds, both inclusive.
+    Random {
+        from: u64,
+        to: u64,
+    },
+    Now,
+}
+
+fn default_fixed_time() -> u64 {
+    DEFAULT_FIXED_TIME
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TimestampChange {
+    pub path: String,
+    pub is_dir: bool,
+    /// Unix seconds before scrubbing (`None` if the platform does not report it).
+    pub modified_before: Option<u64>,
+    pub accessed_before: Option<u64>,
+    pub created_before: Option<u64>,
+    /// The time applied (or that would be applied in a dry run).
+    pub new_time: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScrubReport {
+    pub dry_run: bool,
+    pub entries: Vec<TimestampChange>,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// False on platforms where creation time cannot be changed.
+    pub creation_time_supported: bool,
+    /// The selection held more than `MAX_SCRUB_ENTRIES` entries; the rest were not touched.
+    pub truncated: bool,
+}
+
+// ==========================================
+// --- TIME SELECTION ---
+// ==========================================
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+impl TimestampMode {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Random { from, to } if from > to => {
+                Err("The start of the range must not be after its end.".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The time for the next entry (`Random` draws a new value every call).
+    fn pick(&self) -> Result<u64, String> {
+        match self {
+            Self::Fixed { time } => Ok(*time),
+            Self::Now => unix_secs(SystemTime::now()).ok_or("System clock is before 1970".into()),
+            Self::Random { from, to } => {
+                let span = to - from;
+                if span == u64::MAX {
+                    return OsRng.try_next_u64().map_err(|e| e.to_string());
+                }
+                let r = OsRng.try_next_u64().map_err(|e| e.to_string())?;
+                Ok(from + r % (span + 1))
+            }
+        }
+    }
+}
+
+// ==========================================
+// --- APPLYING ---
+// ==========================================
+
+/// Opens `path` with just enough access to change its times.
+fn open_for_times(path: &Path) -> std::io::Result<File> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+        // Required to open a directory handle.
+        const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+        fs::OpenOptions::new()
+            .access_mode(FILE_WRITE_ATTRIBUTES)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)
+    }
+    #[cfg(not(windows))]
+    {
+        // futimens() only needs ownership, not write access, so read-only files work.
+        File::open(path)
+    }
+}
+
+fn set_times(path: &Path, secs: u64) -> std::io::Result<()> {
+    let time = UNIX_EPOCH + Duration::from_secs(secs);
+    #[allow(unused_mut)]
+    let mut times = FileTimes::new().set_accessed(time).set_modified(time);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileTimesExt;
+        times = times.set_created(time);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::FileTimesExt;
+        times = times.set_created(time);
+    }
+    open_for_times(path)?.set_times(times)
+}
+
+/// Every entry under `roots` (contents before their folder), symlinks excluded.
+fn collect_entries(roots: &[PathBuf], recursive: bool) -> (Vec<(PathBuf, bool)>, bool) {
+    let mut entries = Vec::new();
+    for root in roots {
+        let walker = walkdir::WalkDir::new(root)
+            .max_depth(if recursive { usize::MAX } else { 0 })
+            .contents_first(true);
+        for entry in walker.into_iter().filter_map(|e| e.ok()) {
+            if entry.path_is_symlink() {
+                continue;
+            }
+            if entries.len() == MAX_SCRUB_ENTRIES {
+                return (entries, true);
+            }
+            entries.push((entry.into_path(), false));
+        }
+    }
+    for (path, is_dir) in entries.iter_mut() {
+        *is_dir = path.is_dir();
+    }
+    (entries, false)
+}
+
+/// Scrubs (or, with `dry_run`, previews) the timestamps of `paths`, descending into
+/// folders when `recursive`. Per-entry failures are reported, not fatal.
+pub fn scrub_timestamps(
+    paths: &[PathBuf],
+    mode: &TimestampMode,
+    recursive: bool,
+    dry_run: bool,
+) -> Result<ScrubReport, String> {
+    mode.validate()?;
+    let (entries, truncated) = collect_entries(paths, recursive);
+    let mut report = ScrubReport {
+        dry_run,
+        entries: Vec::with_capacity(entries.len()),
+        succeeded: 0,
+        failed: 0,
+        creation_time_supported: cfg!(any(windows, target_os = "macos")),
+        truncated,
+    };
+
+    for (path, is_dir) in entries {
+        let meta = fs::symlink_metadata(&path).ok();
+        let time_of = |f: fn(&fs::Metadata) -> std::io::Result<SystemTime>| {
+            meta.as_ref().and_then(|m| f(m).ok()).and_then(unix_secs)
+        };
+        let mut change = TimestampChange {
+            path: path.to_string_lossy().to_string(),
+            is_dir,
+            modified_before: time_of(fs::Metadata::modified),
+            accessed_before: time_of(fs::Metadata::accessed),
+            created_before: time_of(fs::Metadata::created),
+            new_time: mode.pick()?,
+            error: None,
+        };
+        if !dry_run {
+            if let Err(e) = set_times(&path, change.new_time) {
+                change.error = Some(e.to_string());
+            }
+        }
+        if change.error.is_some() {
+            report.failed += 1;
+        } else {
+            report.succeeded += 1;
+        }
+        report.entries.push(change);
+    }
+    Ok(report)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_tree(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("qre_timestamps_tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), b"a").unwrap();
+        fs::write(dir.join("sub").join("b.txt"), b"b").unwrap();
+        dir
+    }
+
+    fn mtime(path: &Path) -> u64 {
+        unix_secs(fs::metadata(path).unwrap().modified().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fixed_time_applies_recursively() {
+        let dir = temp_tree("fixed");
+        let mode = TimestampMode::Fixed {
+            time: DEFAULT_FIXED_TIME,
+        };
+        let report = scrub_timestamps(std::slice::from_ref(&dir), &mode, true, false).unwrap();
+        assert_eq!(report.entries.len(), 4, "root, sub, and two files");
+        assert_eq!(report.failed, 0);
+        for p in [&dir, &dir.join("sub"), &dir.join("sub").join("b.txt")] {
+            assert_eq!(mtime(p), DEFAULT_FIXED_TIME);
+        }
+        // Contents first: the folder comes after its files.
+        assert!(report.entries.last().unwrap().path.ends_with("fixed"));
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() {
+        let dir = temp_tree("dry_run");
+        let file = dir.join("a.txt");
+        let before = mtime(&file);
+        let mode = TimestampMode::Fixed { time: 1_000_000 };
+        let report = scrub_timestamps(std::slice::from_ref(&file), &mode, true, true).unwrap();
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].modified_before, Some(before));
+        assert_eq!(report.entries[0].new_time, 1_000_000);
+        assert_eq!(mtime(&file), before);
+    }
+
+    #[test]
+    fn test_random_times_stay_in_range() {
+        let dir = temp_tree("random");
+        let mode = TimestampMode::Random {
+            from: 1_500_000_000,
+            to: 1_500_086_400,
+        };
+        let report = scrub_timestamps(std::slice::from_ref(&dir), &mode, true, false).unwrap();
+        for entry in &report.entries {
+            assert!((1_500_000_000..=1_500_086_400).contains(&entry.new_time));
+            assert_eq!(mtime(Path::new(&entry.path)), entry.new_time);
+        }
+        let inverted = TimestampMode::Random { from: 10, to: 5 };
+        assert!(scrub_timestamps(&[dir], &inverted, true, true).is_err());
+    }
+}
+
+// --- END OF FILE timestamps.rs ---
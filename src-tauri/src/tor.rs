@@ -0,0 +1,208 @@
+// --- START OF FILE tor.rs ---
+
+// ==========================================
+// --- TOR CONNECTIVITY CHECK ---
+// ==========================================
+// Lets users confirm their anonymity path works before relying on it:
+//   1. Detect a local Tor SOCKS5 proxy (tor daemon on 9050, Tor Browser on 9150) by
+//      performing the SOCKS5 greeting, so an unrelated service on the port is not
+//      mistaken for Tor.
+//   2. Ask check.torproject.org whether the request arrived from a Tor exit — strictly
+//      THROUGH the proxy. The client has no direct route: if the proxy fails, the check
+//      fails; it never silently falls back to the clear-net connection.
+//
+// The proxy URL uses `socks5h://`, so DNS is resolved by Tor as well and the lookup of
+// check.torproject.org does not leak to the local resolver.
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Proxy};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// tor daemon default, then Tor Browser's bundled instance.
+pub const DEFAULT_SOCKS_PORTS: [u16; 2] = [9050, 9150];
+const CHECK_URL: &str = "https://check.torproject.org/api/ip";
+const PROBE_TIMEOUT: Duration = Duration::from_millis(800);
+/// Circuits can take a while to build; the check is a one-off user action.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TorStatus {
+    /// A SOCKS5 server answered on one of the probed ports.
+    pub proxy_detected: bool,
+    pub proxy_address: Option<String>,
+    /// check.torproject.org confirmed the request came from a Tor exit node.
+    pub is_tor: bool,
+    pub exit_ip: Option<String>,
+    pub error: Option<String>,
+}
+
+/// check.torproject.org/api/ip response body.
+#[derive(Deserialize, Debug)]
+struct TorCheckResponse {
+    #[serde(rename = "IsTor")]
+    is_tor: bool,
+    #[serde(rename = "IP")]
+    ip: String,
+}
+
+// ==========================================
+// --- PROXY DETECTION ---
+// ==========================================
+
+/// True if a SOCKS5 server that accepts unauthenticated clients listens on `addr`.
+/// Sends the greeting (version 5, one method: "no authentication") and expects the
+/// server to select it. Blocking, bounded by `PROBE_TIMEOUT` per step.
+pub fn is_socks5_proxy(addr: SocketAddr) -> bool {
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, PROBE_TIMEOUT) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(PROBE_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(PROBE_TIMEOUT));
+    if stream.write_all(&[0x05, 0x01, 0x00]).is_err() {
+        return false;
+    }
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).is_ok() && reply == [0x05, 0x00]
+}
+
+/// First local port in `ports` with a SOCKS5 proxy behind it.
+pub fn detect_socks_proxy(ports: &[u16]) -> Option<SocketAddr> {
+    ports
+        .iter()
+        .map(|port| SocketAddr::from(([127, 0, 0, 1], *port)))
+        .find(|addr| is_socks5_proxy(*addr))
+}
+
+// ==========================================
+// --- CONNECTIVITY CHECK ---
+// ==========================================
+
+/// Parses the check.torproject.org answer into (is_tor, exit_ip).
+fn parse_check_response(body: &str) -> Result<(bool, String)> {
+    let parsed: TorCheckResponse = serde_json::from_str(body)
+        .map_err(|_| anyhow!("Unexpected response from the Tor check service"))?;
+    Ok((parsed.is_tor, parsed.ip))
+}
+
+/// Runs the check.torproject.org request through the SOCKS proxy at `proxy`.
+pub async fn check_via_proxy(proxy: SocketAddr) -> Result<(bool, String)> {
+    let client = Client::builder()
+        .proxy(Proxy::all(format!("socks5h://{}", proxy))?)
+        .timeout(CHECK_TIMEOUT)
+        .build()?;
+    let body = client
+        .get(CHECK_URL)
+        .header("User-Agent", "QRE-Privacy-Toolkit/1.0")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_check_response(&body)
+}
+
+/// Full status: detection, then the check through whatever proxy was found.
+/// Network failures are reported in `error` rather than as an `Err`, so the UI can
+/// still show that a proxy was detected.
+pub async fn check_tor_status(ports: Vec<u16>) -> TorStatus {
+    let detected = tauri::async_runtime::spawn_blocking(move || detect_socks_proxy(&ports))
+        .await
+        .ok()
+        .flatten();
+
+    let Some(proxy) = detected else {
+        return TorStatus {
+            proxy_detected: false,
+            proxy_address: None,
+            is_tor: false,
+            exit_ip: None,
+            error: Some(
+                "No Tor SOCKS proxy found. Start Tor or Tor Browser and try again.".to_string(),
+            ),
+        };
+    };
+
+    let (is_tor, exit_ip, error) = match check_via_proxy(proxy).await {
+        Ok((true, ip)) => (true, Some(ip), None),
+        Ok((false, ip)) => (
+            false,
+            Some(ip),
+            Some(
+                "The proxy works, but traffic does not leave through the Tor network.".to_string(),
+            ),
+        ),
+        Err(e) => (
+            false,
+            None,
+            Some(format!("Tor check failed through the proxy: {}", e)),
+        ),
+    };
+    TorStatus {
+        proxy_detected: true,
+        proxy_address: Some(proxy.to_string()),
+        is_tor,
+        exit_ip,
+        error,
+    }
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// One-shot local server that reads the greeting and answers with `reply`.
+    fn fake_server(reply: &'static [u8]) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            if let Ok((mut conn, _)) = listener.accept() {
+                let mut greeting = [0u8; 3];
+                let _ = conn.read_exact(&mut greeting);
+                let _ = conn.write_all(reply);
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_socks5_greeting_detects_proxy() {
+        assert!(is_socks5_proxy(fake_server(&[0x05, 0x00])));
+    }
+
+    #[test]
+    fn test_non_socks_service_is_not_a_proxy() {
+        assert!(!is_socks5_proxy(fake_server(
+            b"HTTP/1.1 400 Bad Request\r\n"
+        )));
+        // SOCKS5, but authentication required: unusable for the check.
+        assert!(!is_socks5_proxy(fake_server(&[0x05, 0xFF])));
+    }
+
+    #[test]
+    fn test_closed_port_is_not_a_proxy() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        // The listener is dropped, so nothing accepts on this port any more.
+        assert!(!is_socks5_proxy(addr));
+    }
+
+    #[test]
+    fn test_parse_check_response() {
+        let (is_tor, ip) = parse_check_response(r#"{"IsTor":true,"IP":"185.220.101.1"}"#).unwrap();
+        assert!(is_tor);
+        assert_eq!(ip, "185.220.101.1");
+        assert!(parse_check_response("<html>").is_err());
+    }
+}
+
+// --- END OF FILE tor.rs ---
@@ -0,0 +1,270 @@
+// --- START OF FILE totp.rs ---
+
+// ==========================================
+// --- TOTP (2FA) CODES ---
+// ==========================================
+// Time-based one-time passwords (RFC 6238) for the `totp_secret` of password entries.
+// The field holds what the website handed out, in one of two forms:
+//   - a bare Base32 secret ("JBSWY3DP EHPK3PXP"): SHA-1, 6 digits, 30 s, as almost every
+//     site uses;
+//   - the content of the setup QR code, an `otpauth://totp/Issuer:account?secret=...`
+//     URI, which may also choose SHA-256/SHA-512, 8 digits or another period.
+// The URI is stored as pasted, so the parameters stay with the secret. Counter-based
+// `otpauth://hotp/` codes are not supported: generating one must advance a stored counter.
+
+use crate::url_cleaner::percent_decode;
+use anyhow::{anyhow, Result};
+use data_encoding::BASE32_NOPAD;
+use serde::Serialize;
+use totp_rs::{Algorithm, TOTP};
+use zeroize::Zeroizing;
+
+const URI_SCHEME: &str = "otpauth://";
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+/// RFC 4226 asks for at least 128 bits, but 80-bit secrets are common in the wild.
+const MIN_SECRET_BYTES: usize = 10;
+const MAX_SECRET_BYTES: usize = 128;
+const MAX_PERIOD: u64 = 3600;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// A parsed `totp_secret`.
+pub struct TotpConfig {
+    secret: Zeroizing<Vec<u8>>,
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+/// What the UI shows after a QR code is pasted. The secret itself is left out.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TotpInfo {
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+impl TotpConfig {
+    pub fn info(&self) -> TotpInfo {
+        TotpInfo {
+            algorithm: self.algorithm,
+            digits: self.digits,
+            period: self.period,
+            issuer: self.issuer.clone(),
+            account: self.account.clone(),
+        }
+    }
+
+    /// The code for `unix_time` and the seconds it stays valid.
+    pub fn code_at(&self, unix_time: u64) -> (String, u64) {
+        let algorithm = match self.algorithm {
+            TotpAlgorithm::Sha1 => Algorithm::SHA1,
+            TotpAlgorithm::Sha256 => Algorithm::SHA256,
+            TotpAlgorithm::Sha512 => Algorithm::SHA512,
+        };
+        let totp = TOTP::new_unchecked(
+            algorithm,
+            self.digits as usize,
+            1,
+            self.period,
+            self.secret.to_vec(),
+        );
+        let remaining = self.period - unix_time % self.period;
+        (totp.generate(unix_time), remaining)
+    }
+}
+
+/// Accepts a Base32 secret or an `otpauth://totp/` URI.
+pub fn parse(input: &str) -> Result<TotpConfig> {
+    let input = input.trim();
+    if input
+        .get(..URI_SCHEME.len())
+        .is_some_and(|s| s.eq_ignore_ascii_case(URI_SCHEME))
+    {
+        return parse_uri(input);
+    }
+    Ok(TotpConfig {
+        secret: decode_secret(input)?,
+        algorithm: TotpAlgorithm::Sha1,
+        digits: DEFAULT_DIGITS,
+        period: DEFAULT_PERIOD,
+        issuer: None,
+        account: None,
+    })
+}
+
+/// Base32 as sites print it: any case, grouped with spaces or dashes, padded or not.
+fn decode_secret(secret: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let clean: Zeroizing<String> = Zeroizing::new(
+        secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect(),
+    );
+    let bytes = BASE32_NOPAD
+        .decode(clean.trim_end_matches('=').as_bytes())
+        .map_err(|_| anyhow!("Invalid 2FA Secret Key (Must be valid Base32)"))?;
+    let bytes = Zeroizing::new(bytes);
+    if bytes.len() < MIN_SECRET_BYTES || bytes.len() > MAX_SECRET_BYTES {
+        return Err(anyhow!(
+            "2FA Secret Key must be {}-{} bytes long",
+            MIN_SECRET_BYTES,
+            MAX_SECRET_BYTES
+        ));
+    }
+    Ok(bytes)
+}
+
+fn parse_uri(uri: &str) -> Result<TotpConfig> {
+    let rest = &uri[URI_SCHEME.len()..];
+    let (kind, rest) = rest
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Invalid otpauth:// link"))?;
+    match kind.to_ascii_lowercase().as_str() {
+        "totp" => {}
+        "hotp" => return Err(anyhow!("Counter-based (HOTP) codes are not supported")),
+        _ => return Err(anyhow!("Invalid otpauth:// link")),
+    }
+    let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let label = percent_decode(label).ok_or_else(|| anyhow!("Invalid otpauth:// label"))?;
+    let (label_issuer, account) = match label.split_once(':') {
+        Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim().to_string()),
+        None => (None, label.trim().to_string()),
+    };
+
+    let mut secret = None;
+    let mut issuer = None;
+    let mut algorithm = TotpAlgorithm::Sha1;
+    let mut digits = DEFAULT_DIGITS;
+    let mut period = DEFAULT_PERIOD;
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = Zeroizing::new(
+            percent_decode(value).ok_or_else(|| anyhow!("Invalid otpauth:// parameter"))?,
+        );
+        match name.to_ascii_lowercase().as_str() {
+            "secret" => secret = Some(decode_secret(&value)?),
+            "issuer" => issuer = Some(value.trim().to_string()),
+            "algorithm" => {
+                algorithm = match value.to_ascii_uppercase().as_str() {
+                    "SHA1" => TotpAlgorithm::Sha1,
+                    "SHA256" => TotpAlgorithm::Sha256,
+                    "SHA512" => TotpAlgorithm::Sha512,
+                    other => return Err(anyhow!("Unsupported 2FA algorithm: {}", other)),
+                }
+            }
+            "digits" => {
+                digits = match value.parse() {
+                    Ok(d @ (6 | 8)) => d,
+                    _ => return Err(anyhow!("2FA codes must have 6 or 8 digits")),
+                }
+            }
+            "period" => {
+                period = match value.parse() {
+                    Ok(p @ 1..=MAX_PERIOD) => p,
+                    _ => return Err(anyhow!("Invalid 2FA period: {}", value.as_str())),
+                }
+            }
+            // image, color... from other authenticator apps.
+            _ => {}
+        }
+    }
+
+    Ok(TotpConfig {
+        secret: secret.ok_or_else(|| anyhow!("The otpauth:// link has no secret"))?,
+        algorithm,
+        digits,
+        period,
+        // The issuer parameter wins: the label prefix is often shortened or missing.
+        issuer: issuer.or(label_issuer).filter(|s| !s.is_empty()),
+        account: Some(account).filter(|s| !s.is_empty()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B: the ASCII seeds, in Base32.
+    const RFC_SHA1: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    const RFC_SHA256: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let uri = |secret: &str, algorithm: &str| {
+            format!(
+                "otpauth://totp/Test?secret={}&algorithm={}&digits=8",
+                secret, algorithm
+            )
+        };
+        let sha1 = parse(&uri(RFC_SHA1, "SHA1")).unwrap();
+        let sha256 = parse(&uri(RFC_SHA256, "SHA256")).unwrap();
+        for (time, expect_sha1, expect_sha256) in [
+            (59, "94287082", "46119246"),
+            (1111111109, "07081804", "68084774"),
+            (2000000000, "69279037", "90698825"),
+        ] {
+            assert_eq!(sha1.code_at(time).0, expect_sha1);
+            assert_eq!(sha256.code_at(time).0, expect_sha256);
+        }
+        assert_eq!(sha1.code_at(59).1, 1);
+        assert_eq!(sha1.code_at(60).1, 30);
+    }
+
+    #[test]
+    fn test_bare_secret_uses_the_defaults() {
+        let config = parse(" jbsw-y3dp ehpk3pxp== ").unwrap();
+        assert_eq!(config.algorithm, TotpAlgorithm::Sha1);
+        assert_eq!((config.digits, config.period), (6, 30));
+        assert_eq!(config.code_at(0).0.len(), 6);
+        assert!(parse("not base32!").is_err());
+        assert!(parse("JBSWY3DP").is_err(), "too short to be a real secret");
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let config = parse(
+            "otpauth://totp/ACME%20Co:alice%40example.com?secret=JBSWY3DPEHPK3PXP\
+             &issuer=ACME%20Co&algorithm=sha256&digits=8&period=60&image=x",
+        )
+        .unwrap();
+        assert_eq!(
+            config.info(),
+            TotpInfo {
+                algorithm: TotpAlgorithm::Sha256,
+                digits: 8,
+                period: 60,
+                issuer: Some("ACME Co".into()),
+                account: Some("alice@example.com".into()),
+            }
+        );
+        assert_eq!(config.code_at(0).1, 60);
+
+        let config = parse("OTPAUTH://totp/GitHub:bob?secret=JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!(config.issuer.as_deref(), Some("GitHub"));
+
+        for bad in [
+            "otpauth://hotp/x?secret=JBSWY3DPEHPK3PXP&counter=1",
+            "otpauth://totp/x?issuer=NoSecret",
+            "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&digits=7",
+            "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&period=0",
+            "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&algorithm=MD5",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
+
+// --- END OF FILE totp.rs ---
@@ -0,0 +1,387 @@
+// --- START OF FILE url_cleaner.rs ---
+
+// ==========================================
+// --- URL TRACKING CLEANER ---
+// ==========================================
+// Removes click and campaign identifiers from links and unwraps redirector links
+// (`google.com/url?q=...`, `l.facebook.com/l.php?u=...`), so a copied or imported link
+// points straight at its target and carries nothing that ties it to the person who
+// shared it.
+//
+// The ruleset below is maintained by hand, in the spirit of the ClearURLs lists:
+//   - global parameters, removed from every URL,
+//   - prefixes (`utm_*`), removed from every URL,
+//   - per-site parameters that are only tracking on that site (`si` on YouTube is a
+//     share id, elsewhere it may be meaningful),
+//   - redirectors, with the parameter holding the real target.
+//
+// Other parameters are kept byte-for-byte; the URL is never re-encoded.
+
+use crate::passwords::{base_domain, url_host};
+use crate::regexes::{self, Pattern};
+use serde::Serialize;
+
+/// Removed from every URL.
+const GLOBAL_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "igshid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "li_fat_id",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "__hssc",
+    "__hstc",
+    "__hsfp",
+    "hsctatracking",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "rb_clickid",
+    "s_cid",
+    "vero_conv",
+    "vero_id",
+    "wickedid",
+    "_openstat",
+    "ncid",
+    "srsltid",
+    "_ga",
+    "_gl",
+];
+
+/// Parameter-name prefixes removed from every URL.
+const GLOBAL_PREFIXES: &[&str] = &["utm_", "pk_", "mtm_", "hsa_", "itm_", "stm_"];
+
+/// Parameters that are tracking only on the listed registrable domains.
+const SITE_PARAMS: &[(&[&str], &[&str])] = &[
+    (
+        &[
+            "amazon.com",
+            "amazon.co.uk",
+            "amazon.de",
+            "amazon.fr",
+            "amazon.ca",
+            "amazon.in",
+        ],
+        &[
+            "ref", "ref_", "pf_rd_p", "pf_rd_r", "pf_rd_s", "pf_rd_t", "pf_rd_i", "pf_rd_m",
+            "pd_rd_r", "pd_rd_w", "pd_rd_wg", "crid", "sprefix", "qid", "tag", "linkcode",
+        ],
+    ),
+    (&["youtube.com", "youtu.be"], &["si", "feature", "pp"]),
+    (&["twitter.com", "x.com"], &["s", "t", "ref_src", "ref_url"]),
+    (&["instagram.com"], &["igsh", "img_index"]),
+    (&["spotify.com"], &["si"]),
+    (
+        &["linkedin.com"],
+        &["trk", "trkinfo", "trackingid", "lipi", "refid"],
+    ),
+    (&["reddit.com"], &["share_id", "ref", "ref_source", "rdt"]),
+    (
+        &["tiktok.com"],
+        &["_r", "_t", "is_from_webapp", "sender_device", "is_copy_url"],
+    ),
+    (
+        &["facebook.com"],
+        &["mibextid", "ref", "sfnsn", "__tn__", "__cft__"],
+    ),
+    (
+        &["ebay.com", "ebay.co.uk", "ebay.de"],
+        &["_trkparms", "_trksid", "amdata"],
+    ),
+    (
+        &["aliexpress.com"],
+        &[
+            "spm",
+            "scm",
+            "pvid",
+            "algo_pvid",
+            "algo_exp_id",
+            "btsid",
+            "ws_ab_test",
+        ],
+    ),
+];
+
+/// Link-wrapping services: (host, path prefix, query parameter carrying the percent-encoded
+/// target). A host starting with '.' matches any subdomain.
+const REDIRECTORS: &[(&str, &str, &str)] = &[
+    ("www.google.com", "/url", "q"),
+    ("www.google.com", "/url", "url"),
+    ("google.com", "/url", "q"),
+    ("l.facebook.com", "/l.php", "u"),
+    ("lm.facebook.com", "/l.php", "u"),
+    ("l.instagram.com", "/", "u"),
+    ("l.messenger.com", "/l.php", "u"),
+    ("www.youtube.com", "/redirect", "q"),
+    ("out.reddit.com", "/", "url"),
+    ("t.umblr.com", "/redirect", "z"),
+    ("steamcommunity.com", "/linkfilter/", "url"),
+    ("slack-redir.net", "/link", "url"),
+    ("www.linkedin.com", "/redir/redirect", "url"),
+    ("away.vk.com", "/away.php", "to"),
+    ("vk.com", "/away.php", "to"),
+    ("exit.sc", "/", "url"),
+    // Microsoft Defender Safe Links, e.g. eur01.safelinks.protection.outlook.com
+    (".safelinks.protection.outlook.com", "/", "url"),
+];
+
+/// Redirectors nested deeper than this are left alone.
+const MAX_UNWRAP_DEPTH: usize = 5;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CleanedUrl {
+    pub original: String,
+    pub cleaned: String,
+    /// Names of the removed parameters, in order of appearance.
+    pub removed_params: Vec<String>,
+    /// Redirector hosts that were unwrapped, outermost first.
+    pub unwrapped: Vec<String>,
+    pub changed: bool,
+}
+
+// ==========================================
+// --- HELPERS ---
+// ==========================================
+
+/// Splits a URL into (everything before `?`, query, fragment).
+fn split_url(url: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (rest, fragment) = match url.split_once('#') {
+        Some((r, f)) => (r, Some(f)),
+        None => (url, None),
+    };
+    match rest.split_once('?') {
+        Some((base, query)) => (base, Some(query), fragment),
+        None => (rest, None, fragment),
+    }
+}
+
+fn param_name(pair: &str) -> String {
+    pair.split('=')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Decodes `%XX` escapes (and `+` as a space). `None` if the result is not UTF-8.
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let decoded = std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match decoded {
+                    Some(b) => {
+                        out.push(b);
+                        i += 3;
+                        continue;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
+fn is_tracking_param(name: &str, site_params: &[&str]) -> bool {
+    GLOBAL_PARAMS.contains(&name)
+        || GLOBAL_PREFIXES.iter().any(|p| name.starts_with(p))
+        || site_params.contains(&name)
+}
+
+fn site_params_for(host: &str) -> &'static [&'static str] {
+    let domain = base_domain(host);
+    SITE_PARAMS
+        .iter()
+        .find(|(domains, _)| domains.contains(&domain.as_str()))
+        .map(|(_, params)| *params)
+        .unwrap_or(&[])
+}
+
+/// The target of a redirector link, if `url` is one.
+fn unwrap_redirect(url: &str) -> Option<(String, String)> {
+    let host = url_host(url)?;
+    let (base, query, _) = split_url(url);
+    let path = base
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .and_then(|rest| rest.find('/').map(|i| &rest[i..]))
+        .unwrap_or("/");
+
+    for (redirector, prefix, param) in REDIRECTORS {
+        let host_matches = if redirector.starts_with('.') {
+            host.ends_with(redirector)
+        } else {
+            host == *redirector
+        };
+        if !host_matches || !path.starts_with(prefix) {
+            continue;
+        }
+        let Some(target) = query
+            .unwrap_or_default()
+            .split('&')
+            .find(|pair| param_name(pair) == *param)
+            .and_then(|pair| pair.split_once('='))
+            .and_then(|(_, value)| percent_decode(value))
+        else {
+            continue;
+        };
+        let lower = target.to_ascii_lowercase();
+        if lower.starts_with("https://") || lower.starts_with("http://") {
+            return Some((host, target));
+        }
+    }
+    None
+}
+
+// ==========================================
+// --- CLEANING ---
+// ==========================================
+
+/// Unwraps redirectors and removes tracking parameters from one URL.
+pub fn clean_url(url: &str) -> CleanedUrl {
+    let original = url.trim().to_string();
+    let mut current = original.clone();
+    let mut unwrapped = Vec::new();
+    while unwrapped.len() < MAX_UNWRAP_DEPTH {
+        match unwrap_redirect(&current) {
+            Some((host, target)) => {
+                unwrapped.push(host);
+                current = target;
+            }
+            None => break,
+        }
+    }
+
+    let mut removed_params = Vec::new();
+    let (base, query, fragment) = split_url(&current);
+    let mut cleaned = base.to_string();
+    if let Some(query) = query {
+        let site_params = url_host(&current)
+            .map(|h| site_params_for(&h))
+            .unwrap_or(&[]);
+        let kept: Vec<&str> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .filter(|pair| {
+                let name = param_name(pair);
+                let tracking = is_tracking_param(&name, site_params);
+                if tracking {
+                    removed_params.push(name);
+                }
+                !tracking
+            })
+            .collect();
+        if !kept.is_empty() {
+            cleaned.push('?');
+            cleaned.push_str(&kept.join("&"));
+        }
+    }
+    if let Some(fragment) = fragment {
+        cleaned.push('#');
+        cleaned.push_str(fragment);
+    }
+
+    CleanedUrl {
+        changed: cleaned != original,
+        original,
+        cleaned,
+        removed_params,
+        unwrapped,
+    }
+}
+
+/// True if `text` is one http(s) URL and nothing else (surrounding whitespace aside).
+pub fn is_single_link(text: &str) -> bool {
+    let text = text.trim();
+    let lower = text.get(..8).unwrap_or(text).to_ascii_lowercase();
+    (lower.starts_with("https://") || lower.starts_with("http://"))
+        && !text.contains(char::is_whitespace)
+}
+
+/// Cleans every http(s) link inside free text, leaving the rest untouched.
+pub fn clean_links_in_text(text: &str) -> String {
+    regexes::get(Pattern::HttpLink)
+        .replace_all(text, |caps: &regex::Captures| clean_url(&caps[0]).cleaned)
+        .into_owned()
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removes_global_and_site_params() {
+        let result = clean_url("https://www.youtube.com/watch?v=abc&si=XyZ&utm_source=share#t=10");
+        assert_eq!(result.cleaned, "https://www.youtube.com/watch?v=abc#t=10");
+        assert_eq!(result.removed_params, vec!["si", "utm_source"]);
+        assert!(result.changed);
+
+        // `si` is only tracking on the listed sites.
+        let other = clean_url("https://example.com/search?si=1&Fbclid=2");
+        assert_eq!(other.cleaned, "https://example.com/search?si=1");
+
+        let untouched = clean_url("https://example.com/a?b=%20c&d");
+        assert!(!untouched.changed);
+    }
+
+    #[test]
+    fn test_unwraps_nested_redirectors() {
+        let inner = "https%3A%2F%2Fshop.example%2Fitem%3Fid%3D7%26gclid%3Dzz";
+        let wrapped = format!(
+            "https://l.facebook.com/l.php?u=https%3A%2F%2Fwww.google.com%2Furl%3Fq%3D{}&h=AT0",
+            inner.replace('%', "%25")
+        );
+        let result = clean_url(&wrapped);
+        assert_eq!(result.cleaned, "https://shop.example/item?id=7");
+        assert_eq!(result.unwrapped, vec!["l.facebook.com", "www.google.com"]);
+
+        let safelink = clean_url(
+            "https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.org%2F&data=x",
+        );
+        assert_eq!(safelink.cleaned, "https://example.org/");
+    }
+
+    #[test]
+    fn test_redirect_to_non_http_target_is_kept() {
+        let url = "https://www.google.com/url?q=javascript%3Aalert(1)";
+        assert_eq!(clean_url(url).cleaned, url);
+        assert!(clean_url(url).unwrapped.is_empty());
+    }
+
+    #[test]
+    fn test_cleans_links_inside_text() {
+        assert_eq!(
+            clean_links_in_text(
+                "read https://news.example/a?utm_medium=x and https://b.example/?gclid=1"
+            ),
+            "read https://news.example/a and https://b.example/"
+        );
+        assert!(is_single_link("  HTTPS://example.com/?a=1\n"));
+        assert!(!is_single_link("see https://example.com"));
+        assert!(!is_single_link("ftp://example.com"));
+    }
+}
+
+// --- END OF FILE url_cleaner.rs ---
@@ -1,12 +1,12 @@
 // --- START OF FILE utils.rs ---
 
+use crate::random_names::{self, NameKind};
 use rand::RngCore;
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Emitter};
-use uuid::Uuid;
 use walkdir::WalkDir;
 use zip::write::SimpleFileOptions;
 
@@ -17,7 +17,13 @@ use zip::write::SimpleFileOptions;
 /// Emits a progress update event over the Tauri IPC bridge to the React frontend.
 /// This allows the UI to display live progress bars during long I/O bound operations
 /// (like encryption, decryption, or shredding) so the app doesn't appear "frozen".
+///
+/// Per-chunk callers fire far faster than the UI can repaint, so updates are coalesced
+/// to `progress::DEFAULT_MAX_EVENTS_PER_SEC`; a 100% update is always delivered.
 pub fn emit_progress(app: &AppHandle, label: &str, percentage: u8) {
+    if !crate::progress::allow_global(percentage) {
+        return;
+    }
     let _ = app.emit(
         "qre:progress",
         serde_json::json!({
@@ -274,7 +280,7 @@ fn shred_file_internal(app: &AppHandle, path: &Path) -> std::io::Result<()> {
     // doesn't delete the filename. We rename the file to a random UUID before deleting it
     // so forensic recovery tools just see a deleted UUID instead of "Tax_Returns_2023.pdf".
     let parent = path.parent().unwrap_or(Path::new("/"));
-    let new_name = Uuid::new_v4().to_string();
+    let new_name = random_names::generate(NameKind::Uuid).map_err(std::io::Error::other)?;
     let new_path = parent.join(new_name);
 
     if fs::rename(path, &new_path).is_ok() {
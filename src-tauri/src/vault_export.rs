@@ -0,0 +1,276 @@
+// --- START OF FILE vault_export.rs ---
+
+// ==========================================
+// --- EXPORT TO KEEPASS ---
+// ==========================================
+// Writes the password vault as a KeePass database (KDBX 4, see kdbx.rs) locked with a
+// passphrase of the user's choosing, so the passwords can leave QRE for KeePass,
+// KeePassXC, KeePassDX, Strongbox... Every entry becomes a KeePass entry in one group:
+//   service, username, password, url, notes -> Title, UserName, Password, URL, Notes;
+//   totp_secret -> "otp", an otpauth:// link (KeePassXC's field, read by most others);
+//   custom fields -> strings of their own, hidden ones protected like the password;
+//   created_at, updated_at, last_used_at, use_count -> Times.
+// Entry IDs are kept as KeePass UUIDs, so importing the export again (vault_import.rs)
+// recognizes every entry. Colors, pins, URL matching rules and account-deletion tracking
+// have no KeePass equivalent and are left out, as are password history and attachments.
+
+use crate::kdbx::{self, InnerStream};
+use crate::passwords::{PasswordVault, VaultEntry};
+use crate::vault_import::KDBX_EPOCH_OFFSET;
+use anyhow::{anyhow, Result};
+use data_encoding::BASE64;
+use quick_xml::escape::escape;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+/// The export can be attacked offline for as long as it exists.
+pub const MIN_PASSPHRASE_LEN: usize = 12;
+const GENERATOR: &str = "QRE Privacy Toolkit";
+/// Keys the export writes itself; custom fields are renamed around them.
+const STANDARD_KEYS: [&str; 6] = ["Title", "UserName", "Password", "URL", "Notes", "otp"];
+
+/// The vault as a KDBX 4 file protected by `passphrase`.
+pub fn export_kdbx(vault: &PasswordVault, passphrase: &str) -> Result<Vec<u8>> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!(
+            "The export passphrase must be at least {} characters.",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    kdbx::seal(passphrase, |stream| Ok(keepass_xml(&vault.entries, stream)))
+}
+
+/// The KeePass XML document; passwords and TOTP links are protected values.
+fn keepass_xml(entries: &[VaultEntry], stream: &mut InnerStream) -> Zeroizing<String> {
+    let mut xml = Zeroizing::new(String::new());
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n<KeePassFile>");
+    xml.push_str(&format!(
+        "<Meta><Generator>{}</Generator><DatabaseName>QRE passwords</DatabaseName>\
+         <MemoryProtection><ProtectPassword>True</ProtectPassword></MemoryProtection></Meta>",
+        GENERATOR
+    ));
+    xml.push_str(&format!(
+        "<Root><Group><UUID>{}</UUID><Name>QRE</Name>",
+        keepass_uuid("QRE export root group")
+    ));
+    for entry in entries {
+        xml.push_str(&format!(
+            "<Entry><UUID>{}</UUID><Times><CreationTime>{}</CreationTime>\
+             <LastModificationTime>{}</LastModificationTime>\
+             <LastAccessTime>{}</LastAccessTime><UsageCount>{}</UsageCount></Times>",
+            keepass_uuid(&entry.id),
+            keepass_time(entry.created_at),
+            keepass_time(entry.updated_at.max(entry.created_at)),
+            keepass_time(entry.last_used_at.unwrap_or(entry.created_at)),
+            entry.use_count
+        ));
+        string_field(&mut xml, "Title", &entry.service);
+        string_field(&mut xml, "UserName", &entry.username);
+        protected_field(&mut xml, stream, "Password", &entry.password);
+        string_field(&mut xml, "URL", &entry.url);
+        string_field(&mut xml, "Notes", &entry.notes);
+        if let Some(secret) = entry
+            .totp_secret
+            .as_deref()
+            .filter(|s| !s.trim().is_empty())
+        {
+            let link = Zeroizing::new(otpauth_link(entry, secret));
+            protected_field(&mut xml, stream, "otp", &link);
+        }
+        for field in &entry.custom_fields {
+            // A custom "Password" must not shadow the real one.
+            let key = if STANDARD_KEYS.contains(&field.name.as_str()) {
+                format!("{} (custom)", field.name)
+            } else {
+                field.name.clone()
+            };
+            if field.hidden {
+                protected_field(&mut xml, stream, &key, &field.value);
+            } else {
+                string_field(&mut xml, &key, &field.value);
+            }
+        }
+        xml.push_str("</Entry>");
+    }
+    xml.push_str("</Group></Root></KeePassFile>");
+    xml
+}
+
+fn string_field(xml: &mut String, key: &str, value: &str) {
+    xml.push_str(&format!(
+        "<String><Key>{}</Key><Value>{}</Value></String>",
+        escape(key),
+        escape(value)
+    ));
+}
+
+fn protected_field(xml: &mut String, stream: &mut InnerStream, key: &str, value: &str) {
+    xml.push_str(&format!(
+        "<String><Key>{}</Key><Value Protected=\"True\">{}</Value></String>",
+        escape(key),
+        stream.protect(value)
+    ));
+}
+
+/// QRE IDs are UUIDs; anything else gets a stable one derived from it.
+fn keepass_uuid(id: &str) -> String {
+    let uuid = uuid::Uuid::parse_str(id).unwrap_or_else(|_| {
+        let hash = Sha256::digest(id.as_bytes());
+        uuid::Builder::from_random_bytes(hash[..16].try_into().unwrap()).into_uuid()
+    });
+    BASE64.encode(uuid.as_bytes())
+}
+
+/// KDBX 4 times: Base64 of the seconds since 0001-01-01 (i64 LE).
+fn keepass_time(unix: i64) -> String {
+    BASE64.encode(&(unix + KDBX_EPOCH_OFFSET).to_le_bytes())
+}
+
+/// Stored links go out as they are; a bare Base32 secret becomes a link with the
+/// default settings (SHA-1, 6 digits, 30 seconds), labelled like the entry.
+fn otpauth_link(entry: &VaultEntry, secret: &str) -> String {
+    let secret = secret.trim();
+    if secret.starts_with("otpauth://") {
+        return secret.to_string();
+    }
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let label = if entry.username.is_empty() {
+        percent_encode(&entry.service)
+    } else {
+        format!(
+            "{}:{}",
+            percent_encode(&entry.service),
+            percent_encode(&entry.username)
+        )
+    };
+    format!(
+        "otpauth://totp/{}?secret={}&issuer={}",
+        label,
+        secret,
+        percent_encode(&entry.service)
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passwords::CustomField;
+    use crate::vault_import::{self, ImportFormat};
+
+    fn entry(id: &str, service: &str, password: &str) -> VaultEntry {
+        VaultEntry {
+            id: id.to_string(),
+            service: service.to_string(),
+            username: "alice@example.com".to_string(),
+            password: password.to_string(),
+            notes: "line 1\nline <2> & more".to_string(),
+            created_at: 1_600_000_000,
+            updated_at: 1_650_000_000,
+            url: "https://example.com/login?a=1&b=2".to_string(),
+            color: String::new(),
+            is_pinned: false,
+            totp_secret: None,
+            url_match: Default::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_export_reimports_the_same_entries() {
+        let mut vault = PasswordVault::new();
+        let mut with_totp = entry(
+            "6f1b0a34-2f0e-4b43-9d51-0a1d7c9f7e11",
+            "ACME & Co",
+            "pä$$ <w>",
+        );
+        with_totp.totp_secret = Some("jbsw y3dp ehpk 3pxp".to_string());
+        with_totp.custom_fields = vec![
+            CustomField {
+                name: "PIN".to_string(),
+                value: "1234".to_string(),
+                hidden: true,
+            },
+            CustomField {
+                name: "Password".to_string(),
+                value: "old one".to_string(),
+                hidden: false,
+            },
+        ];
+        vault.entries.push(with_totp);
+        vault.entries.push(entry("legacy-id", "Mail", ""));
+
+        let bytes = export_kdbx(&vault, "a long export passphrase").unwrap();
+        let parsed = vault_import::parse(
+            &bytes,
+            None,
+            Some("a long export passphrase"),
+            None,
+            1_700_000_000,
+        )
+        .unwrap();
+        assert_eq!(parsed.format, ImportFormat::Kdbx);
+        assert!(parsed.ignored.is_empty(), "{:?}", parsed.ignored);
+        assert_eq!(parsed.entries.len(), 2);
+
+        let first = &parsed.entries[0];
+        assert_eq!(first.id, "6f1b0a34-2f0e-4b43-9d51-0a1d7c9f7e11");
+        assert_eq!(
+            (first.service.as_str(), first.password.as_str()),
+            ("ACME & Co", "pä$$ <w>")
+        );
+        assert_eq!(first.url, "https://example.com/login?a=1&b=2");
+        assert_eq!(
+            first.notes,
+            "line 1\nline <2> & more\nPIN: 1234\nPassword (custom): old one"
+        );
+        assert_eq!(first.created_at, 1_600_000_000);
+        assert_eq!(
+            first.totp_secret.as_deref(),
+            Some(
+                "otpauth://totp/ACME%20%26%20Co:alice%40example.com?secret=JBSWY3DPEHPK3PXP\
+                 &issuer=ACME%20%26%20Co"
+            )
+        );
+        crate::totp::parse(first.totp_secret.as_deref().unwrap()).unwrap();
+
+        // Non-UUID IDs get a stable UUID, so a second export matches the first.
+        assert_eq!(
+            parsed.entries[1].id,
+            uuid_string(&keepass_uuid("legacy-id"))
+        );
+        assert_eq!(parsed.entries[1].password, "");
+
+        assert!(vault_import::parse(&bytes, None, Some("wrong passphrase"), None, 0).is_err());
+        assert!(export_kdbx(&vault, "short").is_err());
+    }
+
+    fn uuid_string(base64: &str) -> String {
+        uuid::Uuid::from_slice(&BASE64.decode(base64.as_bytes()).unwrap())
+            .unwrap()
+            .to_string()
+    }
+}
+
+// --- END OF FILE vault_export.rs ---
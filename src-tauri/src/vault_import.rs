@@ -0,0 +1,1069 @@
+// --- START OF FILE vault_import.rs ---
+
+// ==========================================
+// --- IMPORT FROM OTHER PASSWORD MANAGERS ---
+// ==========================================
+// Turns the export of another password manager into `VaultEntry`s:
+//   - CSV from Chrome/Edge/Brave, Firefox, Bitwarden, KeePassXC or QRE itself. Columns
+//     are found by header name, so their order does not matter;
+//   - Bitwarden JSON (unencrypted export; password-protected exports must be
+//     re-exported without a password);
+//   - KeePass XML (KeePass 2.x "XML" export) and KeePass databases (.kdbx, see kdbx.rs).
+//
+// Only logins are imported. Secure notes, cards and identities, entries in the KeePass
+// recycle bin, KeePass history and rows repeated within the file are reported as
+// ignored. Custom fields and extra URLs are kept at the end of the entry's notes.
+//
+// Imported entries go through the same conflict handling as share bundles
+// (sharing.rs): an entry conflicts when the vault (or an earlier row of the file)
+// already has its ID or its service and username. IDs are derived from the file
+// (KeePass and Bitwarden UUIDs, else a hash of the row), so a preview and the import
+// that follows agree on them, and importing the same file twice is recognized.
+
+use crate::kdbx::{self, InnerStream};
+use crate::passwords::{url_host, PasswordVault, UrlMatchMode, VaultEntry};
+use crate::sharing::{self, ConflictKind, ConflictResolution, ImportSummary};
+use anyhow::{anyhow, Result};
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Seconds between 0001-01-01 (KDBX 4 timestamps) and the UNIX epoch.
+pub(crate) const KDBX_EPOCH_OFFSET: i64 = 62_135_596_800;
+const FALLBACK_SERVICE: &str = "Imported login";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Csv,
+    BitwardenJson,
+    KeepassXml,
+    Kdbx,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IgnoreReason {
+    /// A secure note, card, identity... rather than a login.
+    NotALogin,
+    /// No username, password or URL.
+    Empty,
+    InRecycleBin,
+    /// Same service, username, password and URL as an earlier record of the file.
+    DuplicateInFile,
+}
+
+/// A record of the file that will not be imported.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredRecord {
+    /// 1-based: the CSV row after the header, the Bitwarden item or the KeePass entry.
+    pub position: usize,
+    pub title: String,
+    pub reason: IgnoreReason,
+}
+
+/// The logins found in an export, ready to merge.
+pub struct ParsedImport {
+    pub format: ImportFormat,
+    pub entries: Vec<VaultEntry>,
+    pub ignored: Vec<IgnoredRecord>,
+}
+
+/// One login of the dry run. The password is left out.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PasswordImportItem {
+    pub id: String,
+    pub service: String,
+    pub username: String,
+    pub url: String,
+    pub has_totp: bool,
+    pub conflict: Option<ConflictKind>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PasswordImportPreview {
+    pub format: ImportFormat,
+    pub items: Vec<PasswordImportItem>,
+    pub ignored: Vec<IgnoredRecord>,
+}
+
+/// Guesses the format from the content: KDBX signature, JSON object, XML, else CSV.
+pub fn detect_format(bytes: &[u8]) -> ImportFormat {
+    if kdbx::is_kdbx(bytes) {
+        return ImportFormat::Kdbx;
+    }
+    let start = bytes
+        .strip_prefix(b"\xEF\xBB\xBF".as_slice())
+        .unwrap_or(bytes);
+    match start.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') => ImportFormat::BitwardenJson,
+        Some(b'<') => ImportFormat::KeepassXml,
+        _ => ImportFormat::Csv,
+    }
+}
+
+/// Reads an export. `password` and `keyfile` are only used for KeePass databases.
+pub fn parse(
+    bytes: &[u8],
+    format: Option<ImportFormat>,
+    password: Option<&str>,
+    keyfile: Option<&[u8]>,
+    now: i64,
+) -> Result<ParsedImport> {
+    let format = format.unwrap_or_else(|| detect_format(bytes));
+    let (records, mut ignored) = match format {
+        ImportFormat::Kdbx => {
+            let mut database = kdbx::open(bytes, password, keyfile)?;
+            parse_keepass_xml(&database.xml, Some(&mut database.protected), now)?
+        }
+        ImportFormat::Csv => parse_csv(text(bytes)?, now)?,
+        ImportFormat::BitwardenJson => parse_bitwarden(text(bytes)?, now)?,
+        ImportFormat::KeepassXml => parse_keepass_xml(text(bytes)?, None, now)?,
+    };
+
+    // Rows repeated in the file (common in browser exports) are dropped here; logins
+    // that differ only by password are kept and surface as conflicts.
+    let mut seen = HashSet::new();
+    let mut ids = HashSet::new();
+    let mut entries = Vec::new();
+    for (position, mut entry) in records {
+        let fingerprint = Zeroizing::new(record_fingerprint(&entry));
+        if !seen.insert(Sha256::digest(fingerprint.as_bytes()).to_vec()) {
+            ignored.push(IgnoredRecord {
+                position,
+                title: entry.service.clone(),
+                reason: IgnoreReason::DuplicateInFile,
+            });
+            continue;
+        }
+        if entry.id.is_empty() {
+            entry.id = derived_id(format, &entry);
+        }
+        if !ids.insert(entry.id.clone()) {
+            entry.id = uuid::Uuid::new_v4().to_string();
+        }
+        entries.push(entry);
+    }
+    ignored.sort_by_key(|r| r.position);
+    Ok(ParsedImport {
+        format,
+        entries,
+        ignored,
+    })
+}
+
+/// The dry run: what `apply` would add, and what collides with the vault.
+pub fn preview(parsed: &ParsedImport, vault: &PasswordVault) -> PasswordImportPreview {
+    // Earlier entries of the file count like vault entries, as they will once imported.
+    let mut earlier = PasswordVault::new();
+    let items = parsed
+        .entries
+        .iter()
+        .map(|entry| {
+            let conflict = sharing::password_conflict(vault, entry)
+                .or_else(|| sharing::password_conflict(&earlier, entry))
+                .map(|(_, kind)| kind);
+            earlier.entries.push(entry.clone());
+            PasswordImportItem {
+                id: entry.id.clone(),
+                service: entry.service.clone(),
+                username: entry.username.clone(),
+                url: entry.url.clone(),
+                has_totp: entry.totp_secret.is_some(),
+                conflict,
+            }
+        })
+        .collect();
+    PasswordImportPreview {
+        format: parsed.format,
+        items,
+        ignored: parsed.ignored.clone(),
+    }
+}
+
+/// Merges the parsed entries into `vault`. `resolutions` is keyed by the IDs of the
+/// preview; other conflicts use `default_resolution`.
+pub fn apply(
+    parsed: &ParsedImport,
+    vault: &mut PasswordVault,
+    resolutions: &HashMap<String, ConflictResolution>,
+    default_resolution: ConflictResolution,
+    now: i64,
+) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    let choice = |id: &str| *resolutions.get(id).unwrap_or(&default_resolution);
+    sharing::import_password_entries(&parsed.entries, vault, &choice, now, &mut summary);
+    summary
+}
+
+// ==========================================
+// --- ENTRIES ---
+// ==========================================
+
+/// The fields every format fills in; the rest of `VaultEntry` starts out empty.
+#[derive(Default)]
+struct Login {
+    id: String,
+    service: String,
+    username: String,
+    password: String,
+    url: String,
+    notes: String,
+    totp: Option<String>,
+    created_at: Option<i64>,
+    pinned: bool,
+    /// (name, value) pairs with no field of their own, appended to the notes.
+    extras: Vec<(String, String)>,
+}
+
+impl Drop for Login {
+    fn drop(&mut self) {
+        self.password.zeroize();
+        self.notes.zeroize();
+        self.totp.zeroize();
+        for (_, value) in &mut self.extras {
+            value.zeroize();
+        }
+    }
+}
+
+impl Login {
+    fn is_empty(&self) -> bool {
+        self.username.trim().is_empty() && self.password.is_empty() && self.url.trim().is_empty()
+    }
+
+    fn title(&self) -> String {
+        let service = self.service.trim();
+        if !service.is_empty() {
+            return service.to_string();
+        }
+        url_host(&self.url).unwrap_or_else(|| FALLBACK_SERVICE.to_string())
+    }
+
+    fn into_entry(mut self, now: i64) -> VaultEntry {
+        let mut notes = std::mem::take(&mut self.notes);
+        for (name, value) in &self.extras {
+            if value.trim().is_empty() {
+                continue;
+            }
+            if !notes.is_empty() {
+                notes.push('\n');
+            }
+            notes.push_str(&format!("{}: {}", name.trim(), value));
+        }
+        VaultEntry {
+            id: std::mem::take(&mut self.id),
+            service: self.title(),
+            username: self.username.trim().to_string(),
+            password: std::mem::take(&mut self.password),
+            notes,
+            created_at: self.created_at.unwrap_or(now),
+            updated_at: now,
+            url: self.url.trim().to_string(),
+            color: String::new(),
+            is_pinned: self.pinned,
+            totp_secret: self.totp.take().filter(|t| !t.trim().is_empty()),
+            url_match: UrlMatchMode::default(),
+            url_match_pattern: None,
+            last_used_at: None,
+            use_count: 0,
+            deletion_status: None,
+            history: Vec::new(),
+            custom_fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+}
+
+/// Identifies a record for the in-file duplicate check.
+fn record_fingerprint(entry: &VaultEntry) -> String {
+    format!(
+        "{}\0{}\0{}\0{}",
+        entry.service.to_lowercase(),
+        entry.username.to_lowercase(),
+        entry.url,
+        entry.password
+    )
+}
+
+/// A stable ID for records without one (CSV rows, odd Bitwarden IDs).
+fn derived_id(format: ImportFormat, entry: &VaultEntry) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("qre-import:{:?}\0", format));
+    hasher.update(record_fingerprint(entry));
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hasher.finalize()[..16]);
+    uuid::Builder::from_random_bytes(bytes)
+        .into_uuid()
+        .to_string()
+}
+
+fn text(bytes: &[u8]) -> Result<&str> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| anyhow!("The export is not UTF-8 text; export it again as UTF-8"))?;
+    Ok(text.trim_start_matches('\u{feff}'))
+}
+
+fn parse_timestamp(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+type Records = (Vec<(usize, VaultEntry)>, Vec<IgnoredRecord>);
+
+// ==========================================
+// --- CSV ---
+// ==========================================
+
+/// Header names per field, lowercase. The first column found wins.
+const CSV_SERVICE: &[&str] = &["name", "title"];
+const CSV_URL: &[&str] = &["url", "login_uri", "website", "web site"];
+const CSV_USERNAME: &[&str] = &["username", "login_username", "user name", "login"];
+const CSV_PASSWORD: &[&str] = &["password", "login_password"];
+const CSV_NOTES: &[&str] = &["note", "notes", "extra", "comments"];
+const CSV_TOTP: &[&str] = &["totp_secret", "totp", "login_totp", "otpauth"];
+/// Bitwarden: "login", "note", "card"...
+const CSV_KIND: &[&str] = &["type"];
+const CSV_FAVORITE: &[&str] = &["favorite"];
+/// Firefox: milliseconds since the epoch.
+const CSV_CREATED_MS: &[&str] = &["timecreated"];
+
+fn parse_csv(text: &str, now: i64) -> Result<Records> {
+    let mut rows = read_csv(text)?;
+    let result = map_csv_rows(&rows, now);
+    rows.zeroize();
+    result
+}
+
+fn map_csv_rows(rows: &[Vec<String>], now: i64) -> Result<Records> {
+    let (header, rows) = rows
+        .split_first()
+        .ok_or_else(|| anyhow!("The CSV file is empty"))?;
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|h| h == name))
+    };
+    let (username_col, password_col) = (column(CSV_USERNAME), column(CSV_PASSWORD));
+    if username_col.is_none() && password_col.is_none() {
+        return Err(anyhow!(
+            "The CSV file has no username or password column; is it a password export?"
+        ));
+    }
+    let service_col = column(CSV_SERVICE);
+    let url_col = column(CSV_URL);
+    let notes_col = column(CSV_NOTES);
+    let totp_col = column(CSV_TOTP);
+    let kind_col = column(CSV_KIND);
+    let favorite_col = column(CSV_FAVORITE);
+    let created_col = column(CSV_CREATED_MS);
+
+    let mut records = Vec::new();
+    let mut ignored = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let position = index + 1;
+        let cell = |col: Option<usize>| {
+            col.and_then(|c| row.get(c))
+                .map(String::as_str)
+                .unwrap_or("")
+        };
+        if row.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+        let login = Login {
+            service: cell(service_col).to_string(),
+            username: cell(username_col).to_string(),
+            password: cell(password_col).to_string(),
+            url: cell(url_col).to_string(),
+            notes: cell(notes_col).to_string(),
+            totp: Some(cell(totp_col).to_string()),
+            created_at: cell(created_col)
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .map(|ms| ms / 1000),
+            pinned: cell(favorite_col).trim() == "1",
+            id: String::new(),
+            extras: Vec::new(),
+        };
+        let kind = cell(kind_col).trim();
+        let reason = if !kind.is_empty() && !kind.eq_ignore_ascii_case("login") {
+            Some(IgnoreReason::NotALogin)
+        } else if login.is_empty() {
+            Some(IgnoreReason::Empty)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => ignored.push(IgnoredRecord {
+                position,
+                title: login.title(),
+                reason,
+            }),
+            None => records.push((position, login.into_entry(now))),
+        }
+    }
+    Ok((records, ignored))
+}
+
+/// RFC 4180: comma-separated, `"` quotes fields (doubled inside them), quoted fields
+/// may span lines. Lines end in LF or CRLF.
+fn read_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        field.zeroize();
+        rows.zeroize();
+        return Err(anyhow!("The CSV file ends inside a quoted field"));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+// ==========================================
+// --- BITWARDEN JSON ---
+// ==========================================
+
+const BITWARDEN_LOGIN: u8 = 1;
+
+#[derive(Deserialize)]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct BitwardenItem {
+    id: Option<String>,
+    #[serde(rename = "type")]
+    kind: u8,
+    name: Option<String>,
+    notes: Option<String>,
+    favorite: bool,
+    login: Option<BitwardenLogin>,
+    fields: Option<Vec<BitwardenField>>,
+    creation_date: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct BitwardenLogin {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct BitwardenField {
+    name: Option<String>,
+    value: Option<String>,
+}
+
+fn parse_bitwarden(text: &str, now: i64) -> Result<Records> {
+    let export: BitwardenExport =
+        serde_json::from_str(text).map_err(|e| anyhow!("Not a Bitwarden JSON export: {}", e))?;
+    if export.encrypted {
+        return Err(anyhow!(
+            "This Bitwarden export is encrypted; export the vault again as unencrypted JSON"
+        ));
+    }
+
+    let mut records = Vec::new();
+    let mut ignored = Vec::new();
+    for (index, item) in export.items.into_iter().enumerate() {
+        let position = index + 1;
+        let service = item.name.unwrap_or_default();
+        let Some(login) = item.login.filter(|_| item.kind == BITWARDEN_LOGIN) else {
+            ignored.push(IgnoredRecord {
+                position,
+                title: service,
+                reason: IgnoreReason::NotALogin,
+            });
+            continue;
+        };
+        let mut uris = login
+            .uris
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|u| u.uri)
+            .filter(|u| !u.trim().is_empty());
+        let url = uris.next().unwrap_or_default();
+        let mut extras: Vec<(String, String)> = uris.map(|u| ("URL".to_string(), u)).collect();
+        extras.extend(
+            item.fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(|f| (f.name.unwrap_or_default(), f.value.unwrap_or_default())),
+        );
+
+        let login = Login {
+            // Bitwarden item IDs are UUIDs; anything else gets a derived ID.
+            id: item
+                .id
+                .filter(|id| uuid::Uuid::parse_str(id).is_ok())
+                .unwrap_or_default(),
+            service,
+            username: login.username.unwrap_or_default(),
+            password: login.password.unwrap_or_default(),
+            url,
+            notes: item.notes.unwrap_or_default(),
+            totp: login.totp,
+            created_at: item.creation_date.as_deref().and_then(parse_timestamp),
+            pinned: item.favorite,
+            extras,
+        };
+        if login.is_empty() {
+            ignored.push(IgnoredRecord {
+                position,
+                title: login.title(),
+                reason: IgnoreReason::Empty,
+            });
+        } else {
+            records.push((position, login.into_entry(now)));
+        }
+    }
+    Ok((records, ignored))
+}
+
+// ==========================================
+// --- KEEPASS XML ---
+// ==========================================
+
+/// KeePass string fields with a place in `VaultEntry` or that are import noise.
+const KEEPASS_SKIPPED_FIELDS: &[&str] = &[
+    "KPRPC JSON",
+    "TimeOtp-Length",
+    "TimeOtp-Period",
+    "TimeOtp-Algorithm",
+    "TOTP Settings",
+];
+
+/// Walks `<KeePassFile>`. `protected` is the inner stream of a .kdbx; in XML exports
+/// every value is plain text.
+fn parse_keepass_xml(
+    xml: &str,
+    mut protected: Option<&mut InnerStream>,
+    now: i64,
+) -> Result<Records> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut is_keepass = false;
+    let mut recycle_bin = String::new();
+    // One flag per open <Group>: it is the recycle bin or inside it.
+    let mut groups: Vec<bool> = Vec::new();
+    // Entries inside <History> are old versions of the entry around them.
+    let mut history_depth = 0usize;
+    let mut entry: Option<KeePassEntry> = None;
+    let mut text = Zeroizing::new(String::new());
+    let mut value_protected = false;
+    let mut records = Vec::new();
+    let mut ignored = Vec::new();
+    let mut position = 0usize;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| anyhow!("Invalid KeePass XML: {}", e))?;
+        match event {
+            Event::Start(e) => {
+                let name = e.name().as_ref().to_vec();
+                if path.is_empty() {
+                    is_keepass = name == b"KeePassFile";
+                }
+                match name.as_slice() {
+                    b"Group" => groups.push(groups.last().copied().unwrap_or(false)),
+                    b"History" => history_depth += 1,
+                    b"Entry" if history_depth == 0 && !groups.is_empty() => {
+                        entry = Some(KeePassEntry::default())
+                    }
+                    b"Value" => value_protected = is_protected(&e),
+                    _ => {}
+                }
+                text.clear();
+                path.push(name);
+            }
+            // <Value Protected="True"/> still takes its (empty) turn in the stream.
+            Event::Empty(e) if e.name().as_ref() == b"Value" => {
+                text.clear();
+                let store = history_depth == 0 && path.last().is_some_and(|p| p == b"String");
+                end_value(
+                    &mut entry,
+                    &mut text,
+                    is_protected(&e),
+                    store,
+                    &mut protected,
+                )?;
+            }
+            Event::Text(e) => {
+                let value = e
+                    .unescape()
+                    .map_err(|e| anyhow!("Invalid KeePass XML: {}", e))?;
+                text.push_str(&value);
+            }
+            Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+            Event::End(e) => {
+                let name = e.name().as_ref().to_vec();
+                let parent = path.len().checked_sub(2).and_then(|i| path.get(i));
+                match (parent.map(Vec::as_slice), name.as_slice()) {
+                    (Some(b"Meta"), b"RecycleBinUUID") => recycle_bin = text.trim().to_string(),
+                    (Some(b"Group"), b"UUID")
+                        if !recycle_bin.is_empty() && text.trim() == recycle_bin =>
+                    {
+                        if let Some(flag) = groups.last_mut() {
+                            *flag = true;
+                        }
+                    }
+                    (_, b"Group") => {
+                        groups.pop();
+                    }
+                    (_, b"History") => history_depth = history_depth.saturating_sub(1),
+                    (parent, b"Value") => {
+                        let store = history_depth == 0 && parent == Some(b"String");
+                        end_value(
+                            &mut entry,
+                            &mut text,
+                            value_protected,
+                            store,
+                            &mut protected,
+                        )?
+                    }
+                    (Some(b"String"), b"Key") => {
+                        if let Some(entry) = entry.as_mut().filter(|_| history_depth == 0) {
+                            entry.key = text.to_string();
+                        }
+                    }
+                    (Some(b"Entry"), b"UUID") if history_depth == 0 => {
+                        if let Some(entry) = entry.as_mut() {
+                            entry.login.id = keepass_uuid(text.trim()).unwrap_or_default();
+                        }
+                    }
+                    (Some(b"Times"), b"CreationTime") if history_depth == 0 => {
+                        if let Some(entry) = entry.as_mut() {
+                            entry.login.created_at = keepass_time(text.trim());
+                        }
+                    }
+                    (_, b"Entry") if history_depth == 0 => {
+                        if let Some(done) = entry.take() {
+                            position += 1;
+                            let in_recycle_bin = groups.last().copied().unwrap_or(false);
+                            let login = done.finish();
+                            let reason = if in_recycle_bin {
+                                Some(IgnoreReason::InRecycleBin)
+                            } else if login.is_empty() {
+                                Some(IgnoreReason::Empty)
+                            } else {
+                                None
+                            };
+                            match reason {
+                                Some(reason) => ignored.push(IgnoredRecord {
+                                    position,
+                                    title: login.title(),
+                                    reason,
+                                }),
+                                None => records.push((position, login.into_entry(now))),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                text.clear();
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !is_keepass {
+        return Err(anyhow!("Not a KeePass XML export"));
+    }
+    Ok((records, ignored))
+}
+
+#[derive(Default)]
+struct KeePassEntry {
+    login: Login,
+    /// The <Key> of the <String> being read.
+    key: String,
+    /// KeePass' own TOTP fields, turned into an otpauth:// link at the end.
+    otp_base32: Option<String>,
+    otp_params: Vec<(String, String)>,
+}
+
+impl KeePassEntry {
+    fn set(&mut self, key: &str, value: String) {
+        match key {
+            "Title" => self.login.service = value,
+            "UserName" => self.login.username = value,
+            "Password" => self.login.password = value,
+            "URL" => self.login.url = value,
+            "Notes" => self.login.notes = value,
+            // KeePassXC: an otpauth:// link.
+            "otp" => self.login.totp = Some(value),
+            // KeePass 2.47+, and the older KeePassXC/KeeTrayTOTP seed field.
+            "TimeOtp-Secret-Base32" | "TOTP Seed" => self.otp_base32 = Some(value),
+            "TimeOtp-Length" => self.otp_params.push(("digits".into(), value)),
+            "TimeOtp-Period" => self.otp_params.push(("period".into(), value)),
+            "TimeOtp-Algorithm" => self.otp_params.push((
+                "algorithm".into(),
+                value.replace("HMAC-", "").replace('-', ""),
+            )),
+            _ if KEEPASS_SKIPPED_FIELDS.contains(&key) => {}
+            _ => self.login.extras.push((key.to_string(), value)),
+        }
+    }
+
+    fn finish(mut self) -> Login {
+        if self
+            .login
+            .totp
+            .as_deref()
+            .is_none_or(|t| t.trim().is_empty())
+        {
+            if let Some(secret) = self.otp_base32.take() {
+                let secret: String = secret.chars().filter(|c| !c.is_whitespace()).collect();
+                self.login.totp = Some(if self.otp_params.is_empty() {
+                    secret
+                } else {
+                    let mut uri = format!("otpauth://totp/Imported?secret={}", secret);
+                    for (name, value) in &self.otp_params {
+                        uri.push_str(&format!("&{}={}", name, value.trim()));
+                    }
+                    uri
+                });
+            }
+        }
+        std::mem::take(&mut self.login)
+    }
+}
+
+impl Drop for KeePassEntry {
+    fn drop(&mut self) {
+        self.otp_base32.zeroize();
+    }
+}
+
+fn is_protected(element: &quick_xml::events::BytesStart) -> bool {
+    element
+        .try_get_attribute("Protected")
+        .ok()
+        .flatten()
+        .is_some_and(|a| a.value.as_ref().eq_ignore_ascii_case(b"True"))
+}
+
+/// Finishes a `<Value>`: decodes it if protected and, with `store`, puts it in the
+/// current entry under the key read before it.
+fn end_value(
+    entry: &mut Option<KeePassEntry>,
+    text: &mut Zeroizing<String>,
+    value_protected: bool,
+    store: bool,
+    protected: &mut Option<&mut InnerStream>,
+) -> Result<()> {
+    // Every protected value moves the stream on, wherever it is in the document.
+    let value = match protected.as_deref_mut() {
+        Some(stream) if value_protected => stream.unprotect(text)?,
+        _ => Zeroizing::new(text.to_string()),
+    };
+    text.clear();
+    if let Some(entry) = entry.as_mut().filter(|_| store) {
+        let key = std::mem::take(&mut entry.key);
+        entry.set(&key, value.to_string());
+    }
+    Ok(())
+}
+
+/// KeePass writes UUIDs as Base64 of the 16 bytes.
+fn keepass_uuid(value: &str) -> Option<String> {
+    let bytes = BASE64.decode(value.as_bytes()).ok()?;
+    let uuid = uuid::Uuid::from_slice(&bytes).ok()?;
+    (!uuid.is_nil()).then(|| uuid.to_string())
+}
+
+/// KDBX 4: Base64 of the seconds since 0001-01-01 (i64 LE). Older files and XML
+/// exports: ISO 8601.
+fn keepass_time(value: &str) -> Option<i64> {
+    if let Some(time) = parse_timestamp(value) {
+        return Some(time);
+    }
+    let bytes: [u8; 8] = BASE64.decode(value.as_bytes()).ok()?.try_into().ok()?;
+    Some(i64::from_le_bytes(bytes) - KDBX_EPOCH_OFFSET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn parse_text(text: &str) -> ParsedImport {
+        parse(text.as_bytes(), None, None, None, NOW).unwrap()
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(b"name,url\n"), ImportFormat::Csv);
+        assert_eq!(
+            detect_format(b"\xEF\xBB\xBF  {\"items\":[]}"),
+            ImportFormat::BitwardenJson
+        );
+        assert_eq!(detect_format(b"<?xml ?>"), ImportFormat::KeepassXml);
+        assert_eq!(
+            detect_format(&[0x03, 0xD9, 0xA2, 0x9A, 0x67, 0xFB, 0x4B, 0xB5]),
+            ImportFormat::Kdbx
+        );
+    }
+
+    #[test]
+    fn test_browser_csv() {
+        // Chrome: name,url,username,password,note. The quoted note spans two lines.
+        let chrome = parse_text(
+            "name,url,username,password,note\r\n\
+             GitHub,https://github.com/login,alice,\"pa,ss\"\"word\",\"line 1\nline 2\"\r\n\
+             ,https://mail.example.com/,bob,hunter2,\r\n\
+             GitHub,https://github.com/login,alice,\"pa,ss\"\"word\",\"line 1\nline 2\"\r\n\
+             ,,,,\r\n\
+             Empty,,,,\r\n",
+        );
+        assert_eq!(chrome.format, ImportFormat::Csv);
+        assert_eq!(chrome.entries.len(), 2);
+        let github = &chrome.entries[0];
+        assert_eq!(
+            (github.service.as_str(), github.username.as_str()),
+            ("GitHub", "alice")
+        );
+        assert_eq!(github.password, "pa,ss\"word");
+        assert_eq!(github.notes, "line 1\nline 2");
+        assert_eq!(github.created_at, NOW);
+        // No name: the host stands in.
+        assert_eq!(chrome.entries[1].service, "mail.example.com");
+        let reasons: Vec<(usize, IgnoreReason)> = chrome
+            .ignored
+            .iter()
+            .map(|r| (r.position, r.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            [(3, IgnoreReason::DuplicateInFile), (5, IgnoreReason::Empty)]
+        );
+
+        // Firefox: different columns, creation time in milliseconds, no name at all.
+        let firefox = parse_text(
+            "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\"\n\
+             \"https://www.example.org\",\"carol\",\"s3cret\",,\"\",\"{abc}\",\"1600000000000\"\n",
+        );
+        let entry = &firefox.entries[0];
+        assert_eq!(entry.service, "www.example.org");
+        assert_eq!(entry.created_at, 1_600_000_000);
+
+        // A re-parse gives the same IDs, so a preview matches the import after it.
+        assert_eq!(
+            parse_text("url,username,password\nhttps://a.com,u,p\n").entries[0].id,
+            parse_text("url,username,password\nhttps://a.com,u,p\n").entries[0].id
+        );
+
+        for bad in ["", "title,url\nx,y\n", "username,password\n\"open"] {
+            assert!(
+                parse(bad.as_bytes(), None, None, None, NOW).is_err(),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitwarden_json() {
+        let parsed = parse_text(
+            r#"{
+              "encrypted": false,
+              "folders": [],
+              "items": [
+                {
+                  "id": "6f1b0a34-2f0e-4b43-9d51-0a1d7c9f7e11",
+                  "type": 1,
+                  "name": "Example",
+                  "notes": null,
+                  "favorite": true,
+                  "fields": [{ "name": "PIN", "value": "1234", "type": 1 }],
+                  "login": {
+                    "username": "dave",
+                    "password": "pw",
+                    "totp": "JBSWY3DPEHPK3PXP",
+                    "uris": [{ "match": null, "uri": "https://example.com" },
+                             { "match": null, "uri": "https://app.example.com" }]
+                  },
+                  "creationDate": "2021-03-04T05:06:07.000Z"
+                },
+                { "id": "x", "type": 2, "name": "Wifi", "notes": "code", "secureNote": { "type": 0 } },
+                { "id": "y", "type": 1, "name": "Blank", "login": { "uris": null } }
+              ]
+            }"#,
+        );
+        assert_eq!(parsed.format, ImportFormat::BitwardenJson);
+        let entry = &parsed.entries[0];
+        assert_eq!(entry.id, "6f1b0a34-2f0e-4b43-9d51-0a1d7c9f7e11");
+        assert_eq!(entry.url, "https://example.com");
+        assert_eq!(entry.notes, "URL: https://app.example.com\nPIN: 1234");
+        assert_eq!(entry.totp_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert!(entry.is_pinned);
+        assert_eq!(entry.created_at, 1_614_834_367);
+        let reasons: Vec<IgnoreReason> = parsed.ignored.iter().map(|r| r.reason).collect();
+        assert_eq!(reasons, [IgnoreReason::NotALogin, IgnoreReason::Empty]);
+
+        let encrypted = r#"{"encrypted": true, "passwordProtected": true, "data": "..."}"#;
+        assert!(parse(encrypted.as_bytes(), None, None, None, NOW).is_err());
+    }
+
+    #[test]
+    fn test_keepass_xml() {
+        let parsed = parse_text(
+            r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+            <KeePassFile>
+              <Meta><RecycleBinUUID>AAAAAAAAAAAAAAAAAAAAAQ==</RecycleBinUUID></Meta>
+              <Root><Group>
+                <UUID>AAAAAAAAAAAAAAAAAAAAAA==</UUID><Name>Root</Name>
+                <Entry>
+                  <UUID>ESIzRFVmd4iZqrvM3e7/AA==</UUID>
+                  <Times><CreationTime>2020-01-02T03:04:05Z</CreationTime></Times>
+                  <String><Key>Title</Key><Value>Bank &amp; Co</Value></String>
+                  <String><Key>UserName</Key><Value>erin</Value></String>
+                  <String><Key>Password</Key><Value ProtectInMemory="True"> spaced </Value></String>
+                  <String><Key>URL</Key><Value>https://bank.example</Value></String>
+                  <String><Key>Notes</Key><Value/></String>
+                  <String><Key>Customer no.</Key><Value>42</Value></String>
+                  <String><Key>TimeOtp-Secret-Base32</Key><Value>JBSWY3DPEHPK3PXP</Value></String>
+                  <String><Key>TimeOtp-Algorithm</Key><Value>HMAC-SHA-256</Value></String>
+                  <History><Entry>
+                    <UUID>ESIzRFVmd4iZqrvM3e7/AA==</UUID>
+                    <String><Key>Password</Key><Value>old</Value></String>
+                  </Entry></History>
+                </Entry>
+                <Group>
+                  <UUID>AAAAAAAAAAAAAAAAAAAAAQ==</UUID><Name>Recycle Bin</Name>
+                  <Entry><String><Key>Title</Key><Value>Deleted</Value></String>
+                    <String><Key>Password</Key><Value>x</Value></String></Entry>
+                </Group>
+                <Entry><String><Key>Title</Key><Value>Nothing here</Value></String></Entry>
+              </Group></Root>
+            </KeePassFile>"#,
+        );
+        assert_eq!(parsed.format, ImportFormat::KeepassXml);
+        assert_eq!(parsed.entries.len(), 1);
+        let entry = &parsed.entries[0];
+        assert_eq!(entry.id, "11223344-5566-7788-99aa-bbccddeeff00");
+        assert_eq!(entry.service, "Bank & Co");
+        assert_eq!(entry.password, " spaced ");
+        assert_eq!(entry.notes, "Customer no.: 42");
+        assert_eq!(entry.created_at, 1_577_934_245);
+        assert_eq!(
+            entry.totp_secret.as_deref(),
+            Some("otpauth://totp/Imported?secret=JBSWY3DPEHPK3PXP&algorithm=SHA256")
+        );
+        let reasons: Vec<(usize, IgnoreReason)> = parsed
+            .ignored
+            .iter()
+            .map(|r| (r.position, r.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            [(2, IgnoreReason::InRecycleBin), (3, IgnoreReason::Empty)]
+        );
+
+        assert!(parse(b"<html></html>", None, None, None, NOW).is_err());
+    }
+
+    #[test]
+    fn test_preview_and_apply() {
+        let mut vault = PasswordVault::new();
+        vault.entries = parse_text("name,username,password\nGitHub,alice,old\n").entries;
+        let existing_id = vault.entries[0].id.clone();
+
+        let parsed = parse_text(
+            "name,username,password\n\
+             github,ALICE,new\n\
+             Mail,bob,one\n\
+             Mail,bob,two\n",
+        );
+        let preview = preview(&parsed, &vault);
+        let conflicts: Vec<Option<ConflictKind>> =
+            preview.items.iter().map(|i| i.conflict).collect();
+        assert_eq!(
+            conflicts,
+            [
+                Some(ConflictKind::DuplicateLogin),
+                None,
+                Some(ConflictKind::DuplicateLogin)
+            ]
+        );
+        assert_eq!(vault.entries.len(), 1, "the preview writes nothing");
+
+        let mut resolutions = HashMap::new();
+        resolutions.insert(preview.items[0].id.clone(), ConflictResolution::Overwrite);
+        let summary = apply(
+            &parsed,
+            &mut vault,
+            &resolutions,
+            ConflictResolution::Skip,
+            NOW,
+        );
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 1,
+                overwritten: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(vault.entries.len(), 2);
+        assert_eq!(vault.entries[0].id, existing_id);
+        assert_eq!(vault.entries[0].password, "new");
+        assert!(vault.validate().is_ok());
+    }
+}
+
+// --- END OF FILE vault_import.rs ---
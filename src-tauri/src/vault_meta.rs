@@ -0,0 +1,209 @@
+// --- START OF FILE vault_meta.rs ---
+
+// ==========================================
+// --- LAST-WRITER METADATA ---
+// ==========================================
+// When a vault folder is synced or copied between machines, the containers alone do not
+// tell which device wrote them last. Each save therefore stamps the container it wrote
+// with the device, the app version and the time, in `vault_meta.qre` next to it.
+// `get_vault_stats` puts the stamps beside the size and modification time of each file,
+// so a container replaced behind the app's back (a sync conflict, a restored copy)
+// stands out.
+//
+// The stamps name the machine, so they are encrypted with the vault's master key like
+// the containers they describe. The device ID is a random UUID kept in the app data
+// directory: it tells apart two machines with the same name, and is not derived from
+// any hardware identifier.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const META_FILE_NAME: &str = "vault_meta.qre";
+/// In the app data directory, shared by every profile on this machine.
+const DEVICE_ID_FILE_NAME: &str = "device_id";
+/// Filesystems and sync tools round modification times; a file touched within this
+/// many seconds of its stamp counts as the stamped write.
+const MTIME_SLACK_SECS: i64 = 5;
+
+/// Who wrote a container, and when.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WriteStamp {
+    pub device_id: String,
+    pub device_name: String,
+    pub app_version: String,
+    pub written_at: i64,
+}
+
+/// Root of `vault_meta.qre`: the latest stamp of each container, by file name.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VaultMeta {
+    writes: BTreeMap<String, WriteStamp>,
+}
+
+/// One container as `get_vault_stats` reports it.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ContainerStats {
+    pub name: String,
+    pub exists: bool,
+    pub size_bytes: u64,
+    pub modified_at: Option<i64>,
+    pub last_write: Option<WriteStamp>,
+    /// The file changed after its last stamp: it was replaced or edited by something
+    /// other than this app (a sync tool, a backup restore), or by a version without
+    /// stamps.
+    pub changed_since_write: bool,
+}
+
+/// What `get_vault_stats` returns.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct VaultStats {
+    pub containers: Vec<ContainerStats>,
+    /// The most recent stamp of any container, and which container it is for.
+    pub last_write: Option<WriteStamp>,
+    pub last_written_container: Option<String>,
+    /// This machine, so the UI can point out writes made elsewhere.
+    pub this_device_id: String,
+    pub this_device_name: String,
+}
+
+impl VaultMeta {
+    pub fn record(&mut self, container: &str, stamp: WriteStamp) {
+        self.writes.insert(container.to_string(), stamp);
+    }
+
+    pub fn get(&self, container: &str) -> Option<&WriteStamp> {
+        self.writes.get(container)
+    }
+
+    /// The most recent stamp and its container.
+    pub fn last_write(&self) -> Option<(&str, &WriteStamp)> {
+        self.writes
+            .iter()
+            .max_by_key(|(_, stamp)| stamp.written_at)
+            .map(|(name, stamp)| (name.as_str(), stamp))
+    }
+
+    /// Stats for `containers`, each with its size and modification time if the file
+    /// exists.
+    pub fn stats(
+        &self,
+        containers: &[(&str, Option<(u64, i64)>)],
+        device: &WriteStamp,
+    ) -> VaultStats {
+        let containers = containers
+            .iter()
+            .map(|&(name, file)| {
+                let last_write = self.get(name).cloned();
+                let changed_since_write = match (&last_write, file) {
+                    (Some(stamp), Some((_, modified))) => {
+                        modified > stamp.written_at + MTIME_SLACK_SECS
+                    }
+                    // Stamped but gone: deleted outside the app.
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                };
+                ContainerStats {
+                    name: name.to_string(),
+                    exists: file.is_some(),
+                    size_bytes: file.map_or(0, |(size, _)| size),
+                    modified_at: file.map(|(_, modified)| modified),
+                    last_write,
+                    changed_since_write,
+                }
+            })
+            .collect();
+        let last = self.last_write();
+        VaultStats {
+            containers,
+            last_write: last.map(|(_, stamp)| stamp.clone()),
+            last_written_container: last.map(|(name, _)| name.to_string()),
+            this_device_id: device.device_id.clone(),
+            this_device_name: device.device_name.clone(),
+        }
+    }
+}
+
+/// This machine's device ID, created on first use.
+pub fn device_id(data_root: &Path) -> Result<String> {
+    let path = data_root.join(DEVICE_ID_FILE_NAME);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if let Ok(id) = uuid::Uuid::parse_str(existing.trim()) {
+            return Ok(id.to_string());
+        }
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    fs::write(&path, &id).context("Failed to save the device ID")?;
+    Ok(id)
+}
+
+// ==========================================
+// --- TESTS ---
+// ==========================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(device: &str, at: i64) -> WriteStamp {
+        WriteStamp {
+            device_id: device.to_string(),
+            device_name: format!("{}-host", device),
+            app_version: "2.7.5".to_string(),
+            written_at: at,
+        }
+    }
+
+    #[test]
+    fn test_stats_flag_changes_made_elsewhere() {
+        let mut meta = VaultMeta::default();
+        meta.record("passwords.qre", stamp("laptop", 100));
+        meta.record("notes.qre", stamp("laptop", 200));
+        meta.record("notes.qre", stamp("desktop", 300));
+        meta.record("bookmarks.qre", stamp("laptop", 150));
+
+        let restored: VaultMeta =
+            serde_json::from_slice(&serde_json::to_vec(&meta).unwrap()).unwrap();
+        let stats = restored.stats(
+            &[
+                ("passwords.qre", Some((10, 100 + MTIME_SLACK_SECS))),
+                ("notes.qre", Some((20, 900))),
+                ("bookmarks.qre", None),
+                ("secrets.qre", Some((30, 50))),
+            ],
+            &stamp("laptop", 0),
+        );
+
+        assert_eq!(stats.last_write, Some(stamp("desktop", 300)));
+        assert_eq!(stats.last_written_container.as_deref(), Some("notes.qre"));
+        assert_eq!(stats.this_device_name, "laptop-host");
+        let flags: Vec<(bool, bool)> = stats
+            .containers
+            .iter()
+            .map(|c| (c.exists, c.changed_since_write))
+            .collect();
+        assert_eq!(
+            flags,
+            vec![(true, false), (true, true), (false, true), (true, false)]
+        );
+        assert_eq!(stats.containers[1].size_bytes, 20);
+        assert!(stats.containers[3].last_write.is_none());
+        assert!(VaultMeta::default().last_write().is_none());
+    }
+
+    #[test]
+    fn test_device_id_is_created_once() {
+        let dir = std::env::temp_dir().join(format!("qre_device_id_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let id = device_id(&dir).unwrap();
+        assert_eq!(device_id(&dir).unwrap(), id);
+        fs::write(dir.join(DEVICE_ID_FILE_NAME), "not a uuid").unwrap();
+        assert_ne!(device_id(&dir).unwrap(), id);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// --- END OF FILE vault_meta.rs ---
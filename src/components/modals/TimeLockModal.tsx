@@ -23,6 +23,12 @@ interface BatchItemResult {
 const MIN_DURATION_SECS = 60;
 const MAX_DURATION_SECS = 50 * 365 * 24 * 3600;
 
+// Mirrors APPROXIMATE_NOTE in timelock_puzzle.rs.
+const PUZZLE_NOTE =
+  "The puzzle is sized for this computer, so the date is approximate: a faster " +
+  "computer opens the file sooner, a slower one later, and no progress is made " +
+  "while no computer is solving it. The file also stays locked until the date has passed.";
+
 const PRESETS: { label: string; secs: number }[] = [
   { label: "1 Hour", secs: 3600 },
   { label: "1 Day", secs: 86400 },
@@ -95,6 +101,7 @@ export function TimeLockModal({
   const [selectedPreset, setSelectedPreset] = useState<number | null>(null);
   const [customDatetime, setCustomDatetime] = useState<string>("");
   const [resolvedUnixTs, setResolvedUnixTs] = useState<number | null>(null);
+  const [usePuzzle, setUsePuzzle] = useState(false);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
 
@@ -161,6 +168,7 @@ export function TimeLockModal({
         filePath,
         unlockAt: resolvedUnixTs,
         compressionMode: "auto",
+        puzzle: usePuzzle,
       });
 
       if (result.success) {
@@ -464,6 +472,39 @@ export function TimeLockModal({
               </div>
             </div>
 
+            {/* Time-lock puzzle */}
+            <div>
+              <label
+                style={{
+                  display: "flex",
+                  alignItems: "center",
+                  gap: "8px",
+                  fontSize: "0.875rem",
+                  color: "var(--text-main, #ccc)",
+                  cursor: loading ? "default" : "pointer",
+                }}
+              >
+                <input
+                  type="checkbox"
+                  checked={usePuzzle}
+                  onChange={(e) => setUsePuzzle(e.target.checked)}
+                  disabled={loading}
+                />
+                Also require a time-lock puzzle (no clock or server trusted)
+              </label>
+              {usePuzzle && (
+                <p
+                  style={{
+                    margin: "6px 0 0 24px",
+                    fontSize: "0.78rem",
+                    color: "var(--text-dim, #999)",
+                  }}
+                >
+                  {PUZZLE_NOTE}
+                </p>
+              )}
+            </div>
+
             {/* Resolved summary */}
             {resolvedUnixTs !== null && (
               <div style={summaryBoxStyle}>
@@ -481,7 +522,8 @@ export function TimeLockModal({
                       color: "var(--text-main, #eee)",
                     }}
                   >
-                    Unlocks in {formatRemainingFromNow(resolvedUnixTs)}
+                    Unlocks in {usePuzzle ? "about " : ""}
+                    {formatRemainingFromNow(resolvedUnixTs)}
                   </p>
                   <p
                     style={{